@@ -6,7 +6,7 @@ use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use clap::Parser;
 use model::{
-    order::{OrderKind, OrderStatus, OrderUid, BUY_ETH_ADDRESS},
+    order::{OrderClass, OrderKind, OrderStatus, OrderUid, BUY_ETH_ADDRESS},
     u256_decimal,
 };
 use primitive_types::{H160, U256};
@@ -29,7 +29,8 @@ struct Order {
     status: OrderStatus,
     creation_date: DateTime<Utc>,
     partially_fillable: bool,
-    is_liquidity_order: bool,
+    #[serde(default)]
+    class: OrderClass,
 }
 
 struct OrderBookApi {
@@ -186,7 +187,7 @@ impl Alerter {
             .await
             .context("solvable_orders")?
             .into_iter()
-            .filter(|order| !order.is_liquidity_order && !order.partially_fillable)
+            .filter(|order| order.class == OrderClass::Market && !order.partially_fillable)
             .map(|order| {
                 let existing_time = self
                     .open_orders
@@ -0,0 +1,222 @@
+//! Keeps the accounting totals of solvers up to date and reconciles the indexed trades with the
+//! trade events on chain, so that missed or duplicated events don't silently skew the totals.
+
+use crate::database::Postgres;
+use anyhow::{Context, Result};
+use bigdecimal::BigDecimal;
+use contracts::GPv2Settlement;
+use database::accounting::BlockTradeTotals;
+use number_conversions::u256_to_big_decimal;
+use prometheus::{IntCounter, IntGauge};
+use shared::{
+    event_handling::{EventStoring, MAX_REORG_BLOCK_COUNT},
+    maintenance::Maintaining,
+};
+use std::{
+    collections::BTreeMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use web3::types::BlockNumber;
+
+/// The totals cover all trades ever settled, so they get recomputed much less often than
+/// maintenance runs.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(600);
+
+// Limits the node requests per run.
+const BLOCKS_PER_RUN: u64 = 100;
+
+#[derive(prometheus_metric_storage::MetricStorage)]
+#[metric(subsystem = "accounting")]
+struct Metrics {
+    /// Blocks whose indexed trades don't match the trade events on chain.
+    mismatched_blocks: IntCounter,
+    /// The last block whose trades were reconciled.
+    reconciled_block: IntGauge,
+}
+
+impl Metrics {
+    fn get() -> &'static Self {
+        Metrics::instance(global_metrics::get_metric_storage_registry()).unwrap()
+    }
+}
+
+/// The sums of the trades of a block.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Totals {
+    trades: i64,
+    sell_amount: BigDecimal,
+    buy_amount: BigDecimal,
+    fee_amount: BigDecimal,
+}
+
+impl From<BlockTradeTotals> for Totals {
+    fn from(totals: BlockTradeTotals) -> Self {
+        Self {
+            trades: totals.trades,
+            sell_amount: totals.sell_amount,
+            buy_amount: totals.buy_amount,
+            fee_amount: totals.fee_amount,
+        }
+    }
+}
+
+/// Returns the blocks whose totals differ, including blocks that only have trades on one side.
+fn mismatched_blocks(indexed: &BTreeMap<u64, Totals>, onchain: &BTreeMap<u64, Totals>) -> Vec<u64> {
+    let mut blocks: Vec<u64> = indexed.keys().chain(onchain.keys()).copied().collect();
+    blocks.sort_unstable();
+    blocks.dedup();
+    blocks.retain(|block| indexed.get(block) != onchain.get(block));
+    blocks
+}
+
+pub struct AccountingUpdater {
+    db: Postgres,
+    settlement: GPv2Settlement,
+    last_refresh: Mutex<Option<Instant>>,
+    /// The next block to reconcile. Reconciliation starts at the latest reconcilable block when
+    /// the service starts.
+    next_block: Mutex<Option<u64>>,
+}
+
+impl AccountingUpdater {
+    pub fn new(db: Postgres, settlement: GPv2Settlement) -> Self {
+        Self {
+            db,
+            settlement,
+            last_refresh: Default::default(),
+            next_block: Default::default(),
+        }
+    }
+
+    async fn onchain_totals(
+        &self,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<BTreeMap<u64, Totals>> {
+        let events = self
+            .settlement
+            .events()
+            .trade()
+            .from_block(BlockNumber::Number(from_block.into()))
+            .to_block(BlockNumber::Number(to_block.into()))
+            .query()
+            .await
+            .context("failed to fetch trade events")?;
+        let mut totals = BTreeMap::<u64, Totals>::new();
+        for event in events {
+            let block = event
+                .meta
+                .context("trade event without metadata")?
+                .block_number;
+            let block_totals = totals.entry(block).or_default();
+            block_totals.trades += 1;
+            block_totals.sell_amount += u256_to_big_decimal(&event.data.sell_amount);
+            block_totals.buy_amount += u256_to_big_decimal(&event.data.buy_amount);
+            block_totals.fee_amount += u256_to_big_decimal(&event.data.fee_amount);
+        }
+        Ok(totals)
+    }
+
+    async fn reconcile(&self) -> Result<()> {
+        // Only blocks that the event updater has indexed and can't reorg anymore are compared.
+        let current_block = self
+            .settlement
+            .raw_instance()
+            .web3()
+            .eth()
+            .block_number()
+            .await?
+            .as_u64();
+        let last_block = current_block
+            .saturating_sub(MAX_REORG_BLOCK_COUNT)
+            .min(self.db.last_event_block().await?);
+        let from_block = self.next_block.lock().unwrap().unwrap_or(last_block);
+        if from_block > last_block {
+            return Ok(());
+        }
+        let to_block = last_block.min(from_block + BLOCKS_PER_RUN - 1);
+
+        let indexed = self
+            .db
+            .block_trade_totals(from_block as i64, to_block as i64)
+            .await?
+            .into_iter()
+            .map(|totals| (totals.block_number as u64, Totals::from(totals)))
+            .collect();
+        let onchain = self.onchain_totals(from_block, to_block).await?;
+        let mismatched = mismatched_blocks(&indexed, &onchain);
+        for block in &mismatched {
+            tracing::error!(
+                block,
+                indexed = ?indexed.get(block),
+                onchain = ?onchain.get(block),
+                "indexed trades don't match the chain"
+            );
+        }
+
+        let metrics = Metrics::get();
+        metrics.mismatched_blocks.inc_by(mismatched.len() as u64);
+        metrics.reconciled_block.set(to_block as i64);
+        *self.next_block.lock().unwrap() = Some(to_block + 1);
+        Ok(())
+    }
+}
+
+fn is_due(last_refresh: Option<Instant>, now: Instant) -> bool {
+    last_refresh.map_or(true, |last| now.duration_since(last) >= REFRESH_INTERVAL)
+}
+
+#[async_trait::async_trait]
+impl Maintaining for AccountingUpdater {
+    async fn run_maintenance(&self) -> Result<()> {
+        self.reconcile().await?;
+        let now = Instant::now();
+        if !is_due(*self.last_refresh.lock().unwrap(), now) {
+            return Ok(());
+        }
+        self.db.refresh_solver_accounting().await?;
+        *self.last_refresh.lock().unwrap() = Some(now);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::btreemap;
+
+    #[test]
+    fn finds_mismatched_blocks() {
+        let totals = |trades: i64, fee_amount: u32| Totals {
+            trades,
+            sell_amount: 100.into(),
+            buy_amount: 100.into(),
+            fee_amount: fee_amount.into(),
+        };
+        let indexed = btreemap! {
+            1 => totals(1, 5),
+            2 => totals(2, 5),
+            3 => totals(1, 5),
+        };
+        let onchain = btreemap! {
+            1 => totals(1, 5),
+            // An event was indexed twice.
+            2 => totals(1, 5),
+            // The fee was indexed wrongly.
+            3 => totals(1, 6),
+            // An event was missed.
+            4 => totals(1, 5),
+        };
+        assert_eq!(mismatched_blocks(&indexed, &onchain), [2, 3, 4]);
+        assert!(mismatched_blocks(&indexed, &indexed).is_empty());
+    }
+
+    #[test]
+    fn refreshes_after_interval() {
+        let now = Instant::now();
+        assert!(is_due(None, now));
+        assert!(!is_due(Some(now), now + REFRESH_INTERVAL / 2));
+        assert!(is_due(Some(now), now + REFRESH_INTERVAL));
+    }
+}
@@ -0,0 +1,118 @@
+//! The API autopilot serves to drivers and for monitoring.
+
+use crate::{event_consistency::EventConsistencyChecker, solvable_orders::SolvableOrdersCache};
+use futures::{Stream, StreamExt};
+use model::auction::SolvableOrdersChanges;
+use std::{net::SocketAddr, sync::Arc};
+use tokio::{
+    sync::broadcast::{error::RecvError, Receiver},
+    task::{self, JoinHandle},
+};
+use warp::{sse::Event, Filter, Rejection, Reply};
+
+pub fn serve_api(
+    cache: Arc<SolvableOrdersCache>,
+    event_consistency: Arc<EventConsistencyChecker>,
+    address: SocketAddr,
+) -> JoinHandle<()> {
+    let filter = warp::path!("api" / "v1" / ..)
+        .and(stream_solvable_orders(cache).or(get_event_consistency(event_consistency)));
+    tracing::info!(%address, "serving api");
+    task::spawn(warp::serve(filter).bind(address))
+}
+
+fn stream_solvable_orders_request() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::path!("solvable_orders" / "stream").and(warp::get())
+}
+
+/// Server-sent events with the changes to the solvable orders between auctions. The first event
+/// contains all current solvable orders.
+fn stream_solvable_orders(
+    cache: Arc<SolvableOrdersCache>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    stream_solvable_orders_request().map(move || {
+        let (snapshot, receiver) = cache.subscribe_changes();
+        let events = futures::stream::once(async move { Arc::new(snapshot) })
+            .chain(changes(receiver))
+            .map(|changes| {
+                Event::default()
+                    .event("solvable_orders_changes")
+                    .json_data(&*changes)
+            });
+        warp::sse::reply(warp::sse::keep_alive().stream(events))
+    })
+}
+
+fn get_event_consistency_request() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::path!("event_consistency").and(warp::get())
+}
+
+/// The report of the most recent event consistency check.
+fn get_event_consistency(
+    checker: Arc<EventConsistencyChecker>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    get_event_consistency_request().map(move || warp::reply::json(&checker.report()))
+}
+
+/// Ends the stream when a subscriber lags behind because it would otherwise miss changes. The
+/// subscriber has to reconnect to receive all solvable orders again.
+fn changes(
+    receiver: Receiver<Arc<SolvableOrdersChanges>>,
+) -> impl Stream<Item = Arc<SolvableOrdersChanges>> {
+    futures::stream::unfold(receiver, |mut receiver| async move {
+        match receiver.recv().await {
+            Ok(changes) => Some((changes, receiver)),
+            Err(RecvError::Lagged(skipped)) => {
+                tracing::debug!(skipped, "solvable orders subscriber lagged behind");
+                None
+            }
+            Err(RecvError::Closed) => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::broadcast;
+
+    #[tokio::test]
+    async fn ends_stream_of_lagging_subscribers() {
+        let (sender, receiver) = broadcast::channel(2);
+        for block in 0..3 {
+            sender
+                .send(Arc::new(SolvableOrdersChanges {
+                    block,
+                    ..Default::default()
+                }))
+                .unwrap();
+        }
+
+        let changes = changes(receiver).collect::<Vec<_>>().await;
+        assert!(changes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn stream_solvable_orders_request_ok() {
+        let filter = stream_solvable_orders_request();
+        assert!(
+            warp::test::request()
+                .path("/solvable_orders/stream")
+                .method("GET")
+                .matches(&filter)
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn get_event_consistency_request_ok() {
+        let filter = get_event_consistency_request();
+        assert!(
+            warp::test::request()
+                .path("/event_consistency")
+                .method("GET")
+                .matches(&filter)
+                .await
+        );
+    }
+}
@@ -1,6 +1,10 @@
 use primitive_types::{H160, U256};
-use shared::{arguments::display_option, bad_token::token_owner_finder};
-use std::{net::SocketAddr, time::Duration};
+use shared::{
+    arguments::{display_option, display_secret_option},
+    bad_token::token_owner_finder,
+    trace_many::TracingBackend,
+};
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
 use url::Url;
 
 #[derive(clap::Parser)]
@@ -11,18 +15,84 @@ pub struct Arguments {
     #[clap(flatten)]
     pub token_owner_finder: token_owner_finder::Arguments,
 
+    #[clap(flatten)]
+    pub scoring: crate::scoring::Arguments,
+
+    #[clap(flatten)]
+    pub solver_rewards: crate::solver_rewards::Arguments,
+
+    #[clap(flatten)]
+    pub solve_deadline: crate::solve_deadline::Arguments,
+
+    #[clap(flatten)]
+    pub auction_size: crate::auction_size::Arguments,
+
+    #[clap(flatten)]
+    pub mev_rebates: crate::mev_rebates::Arguments,
+
+    #[clap(flatten)]
+    pub leader_election: crate::leader_election::Arguments,
+
+    #[clap(flatten)]
+    pub sealed_orders: crate::sealed_orders::Arguments,
+
+    #[clap(flatten)]
+    pub price_sanity: crate::price_sanity::Arguments,
+
+    #[clap(flatten)]
+    pub stuck_orders: crate::stuck_orders::Arguments,
+
+    #[clap(flatten)]
+    pub event_consistency: crate::event_consistency::Arguments,
+
     /// A tracing Ethereum node URL to connect to, allowing a separate node URL
     /// to be used exclusively for tracing calls.
     #[clap(long, env)]
     pub tracing_node_url: Option<Url>,
 
+    /// How the bad token detection simulates token transfers. `Node` traces them with
+    /// `trace_callMany` and requires the tracing node. `Revm` simulates them locally with state
+    /// fetched from the tracing node or, if there is none, the regular node.
+    #[clap(long, env, arg_enum, ignore_case = true, default_value = "Node")]
+    pub token_detection_backend: TracingBackend,
+
     #[clap(long, env, default_value = "0.0.0.0:9589")]
     pub metrics_address: SocketAddr,
 
+    /// Address to serve the stream of solvable orders changes for drivers and the event
+    /// consistency report on. The API is only served by the leader and not at all if this is
+    /// unset.
+    #[clap(long, env)]
+    pub api_address: Option<SocketAddr>,
+
     /// Url of the Postgres database. By default connects to locally running postgres.
     #[clap(long, env, default_value = "postgresql://")]
     pub db_url: Url,
 
+    /// Apply pending database migrations at startup. Without this flag services refuse to start
+    /// if the database schema doesn't match the migrations they were built with.
+    #[clap(long, env)]
+    pub apply_database_migrations: bool,
+
+    /// Database statements that take longer than this many seconds get logged as warnings.
+    #[clap(
+        long,
+        env,
+        default_value = "1",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub db_slow_query_threshold: Duration,
+
+    /// How long in seconds expired quotes are kept before they get deleted. Has to cover the fee
+    /// guarantee window of the orderbook so that guaranteed quotes can still be honored.
+    #[clap(
+        long,
+        env,
+        default_value = "0",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub quote_retention: Duration,
+
     /// Skip syncing past events (useful for local deployments)
     #[clap(long)]
     pub skip_event_sync: bool,
@@ -99,6 +169,16 @@ pub struct Arguments {
     )]
     pub native_price_cache_max_age_secs: Duration,
 
+    /// List of token addresses whose native prices get continuously precomputed in the background
+    /// so that they are always served from the cache. Should be the most frequently traded tokens.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub native_price_cache_hot_tokens: Vec<H160>,
+
+    /// The maximum number of tokens whose native prices get estimated concurrently when creating
+    /// an auction.
+    #[clap(long, env, default_value = "20")]
+    pub native_price_estimation_parallelism: usize,
+
     /// The minimum amount of time in seconds an order has to be valid for.
     #[clap(
         long,
@@ -111,15 +191,98 @@ pub struct Arguments {
     /// List of account addresses to be denied from order creation
     #[clap(long, env, use_value_delimiter = true)]
     pub banned_users: Vec<H160>,
+
+    /// JSON file with overrides for runtime tunable settings that gets read every time the
+    /// process receives SIGHUP. Supported keys are `unsupportedTokens` and `bannedUsers`, missing
+    /// keys keep the value of the corresponding argument.
+    #[clap(long, env)]
+    pub reload_config_file: Option<PathBuf>,
+
+    /// Auctions with more orders than this get split into independent clusters of orders that
+    /// share neither tokens nor liquidity. Drivers solve the clusters as sub-auctions in parallel
+    /// and merge their best solutions into a single settlement. Clusters that are still larger get
+    /// reduced by dropping orders, preferring to keep user orders.
+    #[clap(long, env)]
+    pub auction_cluster_size: Option<usize>,
+
+    /// Drivers that receive every auction as part of a shadow competition. Their solutions get
+    /// scored and recorded but never executed. Format: name|url where url is the base of the
+    /// driver's api for the solver, for example http://driver/api/name.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub shadow_drivers: Vec<crate::shadow_competition::ShadowDriverArg>,
+
+    /// How long shadow drivers have to respond to an auction. Should exceed the maximum solve
+    /// deadline.
+    #[clap(
+        long,
+        env,
+        default_value = "30",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub shadow_driver_timeout: Duration,
+
+    /// The Tenderly project api url, https://api.tenderly.co/api/v1/account/<USER>/project/<PROJECT>.
+    /// When set, autopilot creates a fork of the node's network on startup and runs against the
+    /// fork instead of the node. Meant for end to end rehearsals of production auctions before
+    /// risky releases. The fork's rpc url gets logged so that the orderbook and drivers can be
+    /// pointed at it. The fork gets deleted when autopilot shuts down.
+    #[clap(long, env)]
+    pub tenderly_url: Option<Url>,
+
+    /// The Tenderly api key. Required when `tenderly_url` is set.
+    #[clap(long, env)]
+    pub tenderly_api_key: Option<String>,
+
+    /// The block to fork at. Defaults to the latest block.
+    #[clap(long, env)]
+    pub tenderly_fork_block: Option<u64>,
+
+    /// Solver accounts that get funded with ether on the fork so that they can pay for gas. Drivers
+    /// configured with the address of an account instead of its private key impersonate it on the
+    /// fork.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub tenderly_fork_solvers: Vec<H160>,
+
+    /// User accounts that get funded with WETH approved for trading on the fork so that test orders
+    /// can be placed from them.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub tenderly_fork_users: Vec<H160>,
 }
 
 impl std::fmt::Display for Arguments {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.shared)?;
         write!(f, "{}", self.token_owner_finder)?;
+        write!(f, "{}", self.scoring)?;
+        write!(f, "{}", self.solver_rewards)?;
+        write!(f, "{}", self.solve_deadline)?;
+        write!(f, "{}", self.auction_size)?;
+        write!(f, "{}", self.mev_rebates)?;
+        write!(f, "{}", self.leader_election)?;
+        write!(f, "{}", self.sealed_orders)?;
+        write!(f, "{}", self.price_sanity)?;
+        write!(f, "{}", self.stuck_orders)?;
+        write!(f, "{}", self.event_consistency)?;
         display_option(f, "tracing_node_url", &self.tracing_node_url)?;
+        writeln!(
+            f,
+            "token_detection_backend: {:?}",
+            self.token_detection_backend
+        )?;
         writeln!(f, "metrics_address: {}", self.metrics_address)?;
+        display_option(f, "api_address", &self.api_address)?;
         writeln!(f, "db_url: SECRET")?;
+        writeln!(
+            f,
+            "apply_database_migrations: {}",
+            self.apply_database_migrations
+        )?;
+        writeln!(
+            f,
+            "db_slow_query_threshold: {:?}",
+            self.db_slow_query_threshold
+        )?;
+        writeln!(f, "quote_retention: {:?}", self.quote_retention)?;
         writeln!(f, "skip_event_sync: {}", self.skip_event_sync)?;
         writeln!(f, "allowed_tokens: {:?}", self.allowed_tokens)?;
         writeln!(f, "unsupported_tokens: {:?}", self.unsupported_tokens)?;
@@ -152,12 +315,35 @@ impl std::fmt::Display for Arguments {
             "native_price_cache_max_age_secs: {:?}",
             self.native_price_cache_max_age_secs
         )?;
+        writeln!(
+            f,
+            "native_price_cache_hot_tokens: {:?}",
+            self.native_price_cache_hot_tokens
+        )?;
+        writeln!(
+            f,
+            "native_price_estimation_parallelism: {}",
+            self.native_price_estimation_parallelism
+        )?;
         writeln!(
             f,
             "min_order_validity_period: {:?}",
             self.min_order_validity_period
         )?;
         writeln!(f, "banned_users: {:?}", self.banned_users)?;
+        display_option(
+            f,
+            "reload_config_file",
+            &self.reload_config_file.as_ref().map(|path| path.display()),
+        )?;
+        display_option(f, "auction_cluster_size", &self.auction_cluster_size)?;
+        writeln!(f, "shadow_drivers: {:?}", self.shadow_drivers)?;
+        writeln!(f, "shadow_driver_timeout: {:?}", self.shadow_driver_timeout)?;
+        display_option(f, "tenderly_url", &self.tenderly_url)?;
+        display_secret_option(f, "tenderly_api_key", &self.tenderly_api_key)?;
+        display_option(f, "tenderly_fork_block", &self.tenderly_fork_block)?;
+        writeln!(f, "tenderly_fork_solvers: {:?}", self.tenderly_fork_solvers)?;
+        writeln!(f, "tenderly_fork_users: {:?}", self.tenderly_fork_users)?;
         Ok(())
     }
 }
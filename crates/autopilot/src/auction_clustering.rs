@@ -0,0 +1,356 @@
+//! Splits huge auctions into clusters of orders that drivers solve as independent sub-auctions.
+//!
+//! Orders that trade a common token need uniform clearing prices and therefore have to be solved
+//! together. So do orders whose solutions could trade against the same pool because the merged
+//! solutions would use the pool's state twice. The connected components of the graph of tokens
+//! that orders trade and pools hold get packed into clusters of at most the configured size.
+//! Pools of a base token connect nearly all orders through it, so base tokens don't join
+//! components. Drivers simulate the merged settlement again to catch conflicts over them.
+//!
+//! Components larger than the configured size cannot be split so orders get dropped from them.
+//! Which orders get dropped is pseudo-random but determined by a seed that gets recorded with the
+//! auction so that the selection can be reproduced.
+
+use anyhow::Result;
+use model::{
+    order::{Order, OrderClass, OrderUid},
+    TokenPair,
+};
+use primitive_types::H160;
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use shared::{
+    baseline_solver::BaseTokens, recent_block_cache::Block,
+    sources::balancer_v2::BalancerPoolFetching,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+pub struct AuctionClustering {
+    max_orders: usize,
+    base_tokens: Arc<BaseTokens>,
+    balancer_pools: Option<Arc<dyn BalancerPoolFetching>>,
+}
+
+/// The orders of an auction split into clusters.
+#[derive(Debug, Default)]
+pub struct Clusters {
+    /// The orders of all clusters.
+    pub orders: Vec<Order>,
+    /// Empty if the auction doesn't need to be split.
+    pub clusters: Vec<Vec<OrderUid>>,
+    /// The seed of the selection of orders if clusters had to drop orders.
+    pub order_selection_seed: Option<u64>,
+}
+
+impl AuctionClustering {
+    pub fn new(
+        max_orders: usize,
+        base_tokens: Arc<BaseTokens>,
+        balancer_pools: Option<Arc<dyn BalancerPoolFetching>>,
+    ) -> Self {
+        Self {
+            max_orders,
+            base_tokens,
+            balancer_pools,
+        }
+    }
+
+    /// Splits the orders into clusters if there are more than the maximum auction size.
+    pub async fn cluster(&self, orders: Vec<Order>, block: u64) -> Clusters {
+        if orders.len() <= self.max_orders {
+            return Clusters {
+                orders,
+                ..Default::default()
+            };
+        }
+        let pools = match self.pools(&orders, block).await {
+            Ok(pools) => pools,
+            Err(err) => {
+                tracing::warn!(?err, "failed to fetch pools for auction clustering");
+                Vec::new()
+            }
+        };
+        let seed = rand::random();
+        let mut dropped = 0;
+        let clusters = cluster_orders(orders, &pools, self.base_tokens.tokens(), self.max_orders)
+            .into_iter()
+            .map(|cluster| {
+                let count = cluster.len();
+                let cluster = select_orders(cluster, self.max_orders, seed);
+                dropped += count - cluster.len();
+                cluster
+            })
+            .collect::<Vec<_>>();
+        tracing::info!(
+            clusters = clusters.len(),
+            dropped,
+            seed,
+            "split auction into independent clusters"
+        );
+        Clusters {
+            clusters: clusters
+                .iter()
+                .map(|cluster| cluster.iter().map(|order| order.metadata.uid).collect())
+                .collect(),
+            orders: clusters.into_iter().flatten().collect(),
+            order_selection_seed: (dropped > 0).then(|| seed),
+        }
+    }
+
+    /// The tokens of the pools that trade more than two tokens and that solvers could use for the
+    /// orders. Other pools only connect tokens that are already connected by an order or through
+    /// a base token.
+    async fn pools(&self, orders: &[Order], block: u64) -> Result<Vec<Vec<H160>>> {
+        let balancer_pools = match &self.balancer_pools {
+            Some(balancer_pools) => balancer_pools,
+            None => return Ok(Vec::new()),
+        };
+        let pairs = self.base_tokens.relevant_pairs(
+            orders
+                .iter()
+                .filter_map(|order| TokenPair::new(order.data.sell_token, order.data.buy_token)),
+        );
+        let pools = balancer_pools.fetch(pairs, Block::Number(block)).await?;
+        Ok(pools
+            .stable_pools
+            .into_iter()
+            .map(|pool| pool.reserves.into_keys().collect())
+            .chain(
+                pools
+                    .weighted_pools
+                    .into_iter()
+                    .map(|pool| pool.reserves.into_keys().collect()),
+            )
+            .collect())
+    }
+}
+
+/// Selects at most `max_orders` orders. User orders are preferred over liquidity orders and
+/// orders of the same kind get selected pseudo-randomly. The selection only depends on the set of
+/// orders and the seed, not on the order they are passed in.
+pub fn select_orders(mut orders: Vec<Order>, max_orders: usize, seed: u64) -> Vec<Order> {
+    if orders.len() <= max_orders {
+        return orders;
+    }
+    orders.sort_by_key(|order| order.metadata.uid.0);
+    // ChaCha is used because unlike `StdRng` its output is guaranteed to stay the same across
+    // versions of the `rand` crates.
+    orders.shuffle(&mut ChaCha8Rng::seed_from_u64(seed));
+    // The sort is stable so orders of the same kind stay shuffled.
+    orders.sort_by_key(|order| order.metadata.class == OrderClass::Liquidity);
+    orders.truncate(max_orders);
+    orders
+}
+
+/// Partitions orders into groups of at most `max_orders` (unless a single connected component is
+/// larger) such that no two groups trade a common token or tokens of a common pool other than
+/// base tokens.
+pub fn cluster_orders(
+    orders: Vec<Order>,
+    pools: &[Vec<H160>],
+    base_tokens: &HashSet<H160>,
+    max_orders: usize,
+) -> Vec<Vec<Order>> {
+    let mut tokens = TokenUnionFind::default();
+    for order in &orders {
+        tokens.union(order.data.sell_token, order.data.buy_token);
+    }
+    for pool in pools {
+        let mut pool_tokens = pool.iter().filter(|token| !base_tokens.contains(token));
+        if let Some(first) = pool_tokens.next() {
+            for token in pool_tokens {
+                tokens.union(*first, *token);
+            }
+        }
+    }
+
+    let mut components = HashMap::<usize, Vec<Order>>::new();
+    for order in orders {
+        let root = tokens.find(order.data.sell_token);
+        components.entry(root).or_default().push(order);
+    }
+
+    // First fit decreasing bin packing keeps the number of clusters low.
+    let mut components = components.into_values().collect::<Vec<_>>();
+    components.sort_by_key(|component| std::cmp::Reverse(component.len()));
+    let mut clusters = Vec::<Vec<Order>>::new();
+    for component in components {
+        match clusters
+            .iter_mut()
+            .find(|cluster| cluster.len() + component.len() <= max_orders)
+        {
+            Some(cluster) => cluster.extend(component),
+            None => clusters.push(component),
+        }
+    }
+    clusters
+}
+
+#[derive(Default)]
+struct TokenUnionFind {
+    indices: HashMap<H160, usize>,
+    parents: Vec<usize>,
+}
+
+impl TokenUnionFind {
+    fn index(&mut self, token: H160) -> usize {
+        let next = self.parents.len();
+        let index = *self.indices.entry(token).or_insert(next);
+        if index == next {
+            self.parents.push(next);
+        }
+        index
+    }
+
+    fn root(&mut self, mut index: usize) -> usize {
+        while self.parents[index] != index {
+            self.parents[index] = self.parents[self.parents[index]];
+            index = self.parents[index];
+        }
+        index
+    }
+
+    fn find(&mut self, token: H160) -> usize {
+        let index = self.index(token);
+        self.root(index)
+    }
+
+    fn union(&mut self, a: H160, b: H160) {
+        let a = self.find(a);
+        let b = self.find(b);
+        self.parents[a] = b;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::order::{OrderData, OrderMetadata};
+
+    fn order(id: u8, sell_token: u8, buy_token: u8) -> Order {
+        Order {
+            data: OrderData {
+                sell_token: H160([sell_token; 20]),
+                buy_token: H160([buy_token; 20]),
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid([id; 56]),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn ids(clusters: &[Vec<Order>]) -> HashSet<Vec<u8>> {
+        clusters
+            .iter()
+            .map(|cluster| {
+                let mut ids = cluster
+                    .iter()
+                    .map(|order| order.metadata.uid.0[0])
+                    .collect::<Vec<_>>();
+                ids.sort();
+                ids
+            })
+            .collect()
+    }
+
+    fn orders() -> Vec<Order> {
+        vec![
+            order(1, 1, 2),
+            order(2, 2, 3),
+            order(3, 4, 5),
+            order(4, 6, 7),
+        ]
+    }
+
+    #[test]
+    fn splits_disconnected_token_graphs() {
+        let clusters = cluster_orders(orders(), &[], &HashSet::new(), 1);
+        assert_eq!(
+            ids(&clusters),
+            HashSet::from([vec![1, 2], vec![3], vec![4]])
+        );
+    }
+
+    #[test]
+    fn packs_small_components_together() {
+        let clusters = cluster_orders(orders(), &[], &HashSet::new(), 3);
+        let mut sizes = clusters.iter().map(Vec::len).collect::<Vec<_>>();
+        sizes.sort();
+        assert_eq!(sizes, [1, 3]);
+        // The connected orders are never separated.
+        assert!(ids(&clusters).iter().any(|ids| ids.starts_with(&[1, 2])));
+    }
+
+    #[test]
+    fn connected_orders_stay_together() {
+        let orders = vec![order(1, 1, 2), order(2, 3, 1), order(3, 2, 3)];
+        let clusters = cluster_orders(orders, &[], &HashSet::new(), 1);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].len(), 3);
+    }
+
+    #[test]
+    fn orders_sharing_pools_stay_together() {
+        let base_token = H160([8; 20]);
+        let pools = [
+            // Connects the components of orders 1 and 3.
+            vec![H160([3; 20]), H160([4; 20]), base_token],
+            // Base tokens don't connect components.
+            vec![H160([6; 20]), base_token],
+            vec![H160([1; 20]), base_token],
+        ];
+        let clusters = cluster_orders(orders(), &pools, &HashSet::from([base_token]), 1);
+        assert_eq!(ids(&clusters), HashSet::from([vec![1, 2, 3], vec![4]]));
+    }
+
+    #[test]
+    fn selects_orders_deterministically() {
+        let orders = (0..10)
+            .map(|i| {
+                let mut order = order(i, 1, 2);
+                if i < 5 {
+                    order.metadata.class = OrderClass::Liquidity;
+                }
+                order
+            })
+            .collect::<Vec<_>>();
+        let selected_ids = |orders: Vec<Order>, seed| {
+            select_orders(orders, 3, seed)
+                .into_iter()
+                .map(|order| order.metadata.uid.0[0])
+                .collect::<Vec<_>>()
+        };
+
+        let selected = selected_ids(orders.clone(), 42);
+        assert_eq!(selected.len(), 3);
+        // User orders get selected before liquidity orders.
+        assert!(selected.iter().all(|id| *id >= 5));
+        // The order of the input does not matter.
+        let reversed = orders.iter().rev().cloned().collect();
+        assert_eq!(selected_ids(reversed, 42), selected);
+        // Different seeds select different orders.
+        assert!((0..10).any(|seed| selected_ids(orders.clone(), seed) != selected));
+    }
+
+    #[tokio::test]
+    async fn small_auctions_are_not_split() {
+        let clustering =
+            AuctionClustering::new(4, Arc::new(BaseTokens::new(H160::zero(), &[])), None);
+        let clusters = clustering.cluster(orders(), 0).await;
+        assert_eq!(clusters.orders.len(), 4);
+        assert!(clusters.clusters.is_empty());
+
+        let clustering =
+            AuctionClustering::new(2, Arc::new(BaseTokens::new(H160::zero(), &[])), None);
+        let clusters = clustering.cluster(orders(), 0).await;
+        assert_eq!(clusters.orders.len(), 4);
+        assert_eq!(clusters.clusters.len(), 2);
+        assert_eq!(clusters.order_selection_seed, None);
+    }
+}
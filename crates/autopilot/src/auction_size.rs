@@ -0,0 +1,320 @@
+//! Adapts the maximum number of orders per auction to how well the drivers keep up with it.
+//!
+//! Drivers report how many of their solver runs timed out and how long simulating the solutions
+//! took as part of the solver competitions they store. When either exceeds its budget the auction
+//! size shrinks multiplicatively, otherwise it grows linearly back towards the maximum. Every
+//! competition gets counted exactly once, so the size only changes when drivers reported new ones.
+
+use crate::database::Postgres;
+use database::auction::AuctionId;
+use model::{
+    order::{Order, OrderClass},
+    solver_competition::{CompetitionPerformance, SolverCompetition},
+};
+use prometheus::IntGauge;
+use shared::arguments::duration_from_seconds;
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::Mutex,
+    time::Duration,
+};
+
+/// Arguments related to the adaptive auction size.
+#[derive(clap::Parser)]
+pub struct Arguments {
+    /// The maximum number of orders of the first auction. Enables adapting the auction size to the
+    /// solver timeout rate and the simulation latency of recent auctions. Auctions are unlimited
+    /// if not set.
+    #[clap(long, env)]
+    pub auction_size_initial: Option<usize>,
+
+    /// The auction size never shrinks below this number of orders.
+    #[clap(long, env, default_value = "50")]
+    pub auction_size_min: usize,
+
+    /// The auction size never grows above this number of orders.
+    #[clap(long, env, default_value = "1000")]
+    pub auction_size_max: usize,
+
+    /// The number of orders by which the auction size grows when recent auctions were solved
+    /// within budget.
+    #[clap(long, env, default_value = "25")]
+    pub auction_size_step: usize,
+
+    /// The share of solver runs that may time out before the auction size shrinks.
+    #[clap(long, env, default_value = "0.1")]
+    pub auction_size_max_timeout_rate: f64,
+
+    /// The average time in seconds simulating solutions may take before the auction size shrinks.
+    #[clap(
+        long,
+        env,
+        default_value = "5",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub auction_size_max_simulation_time: Duration,
+
+    /// The maximum number of new solver competitions the timeout rate and simulation time get
+    /// averaged over when adapting the auction size.
+    #[clap(long, env, default_value = "10")]
+    pub auction_size_window: i64,
+}
+
+impl Display for Arguments {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "auction_size_initial: {:?}", self.auction_size_initial)?;
+        writeln!(f, "auction_size_min: {}", self.auction_size_min)?;
+        writeln!(f, "auction_size_max: {}", self.auction_size_max)?;
+        writeln!(f, "auction_size_step: {}", self.auction_size_step)?;
+        writeln!(
+            f,
+            "auction_size_max_timeout_rate: {}",
+            self.auction_size_max_timeout_rate
+        )?;
+        writeln!(
+            f,
+            "auction_size_max_simulation_time: {:?}",
+            self.auction_size_max_simulation_time
+        )?;
+        writeln!(f, "auction_size_window: {}", self.auction_size_window)?;
+        Ok(())
+    }
+}
+
+/// The factor by which the auction size shrinks when recent auctions were over budget.
+const SHRINK_FACTOR: f64 = 0.8;
+
+#[derive(prometheus_metric_storage::MetricStorage)]
+#[metric(subsystem = "auction_size")]
+struct Metrics {
+    /// The current maximum number of orders per auction.
+    limit: IntGauge,
+}
+
+impl Metrics {
+    fn get() -> &'static Self {
+        Metrics::instance(global_metrics::get_metric_storage_registry()).unwrap()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Limits {
+    pub min: usize,
+    pub max: usize,
+    pub step: usize,
+    pub max_timeout_rate: f64,
+    pub max_simulation_time: Duration,
+}
+
+pub struct AuctionSizeController {
+    database: Postgres,
+    limits: Limits,
+    window: i64,
+    state: Mutex<State>,
+}
+
+struct State {
+    size: usize,
+    /// The newest solver competition the size was adapted to. Older competitions were already
+    /// counted.
+    latest_competition: Option<i64>,
+}
+
+impl AuctionSizeController {
+    /// Returns `None` if the auction size isn't adaptive.
+    pub fn new(database: Postgres, args: &Arguments) -> Option<Self> {
+        let limits = Limits {
+            min: args.auction_size_min,
+            max: args.auction_size_max,
+            step: args.auction_size_step,
+            max_timeout_rate: args.auction_size_max_timeout_rate,
+            max_simulation_time: args.auction_size_max_simulation_time,
+        };
+        let size = args
+            .auction_size_initial?
+            .clamp(limits.min, limits.max.max(limits.min));
+        Metrics::get().limit.set(size as i64);
+        Some(Self {
+            database,
+            limits,
+            window: args.auction_size_window,
+            state: Mutex::new(State {
+                size,
+                latest_competition: None,
+            }),
+        })
+    }
+
+    /// The maximum number of orders of the next auction. The size only adapts to the solver
+    /// competitions drivers reported since the last adaptation so that an auction doesn't get
+    /// counted several times.
+    pub async fn max_orders(&self) -> usize {
+        let competitions = match self.database.latest_solver_competitions(self.window).await {
+            Ok(competitions) => competitions,
+            Err(err) => {
+                tracing::warn!(
+                    ?err,
+                    "failed to load solver competitions for auction sizing"
+                );
+                return self.state.lock().unwrap().size;
+            }
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let new_competitions = new_competitions(&competitions, state.latest_competition);
+        let newest = match new_competitions.first() {
+            Some((id, _)) => *id,
+            None => return state.size,
+        };
+        state.latest_competition = Some(newest);
+
+        let performances = new_competitions
+            .iter()
+            .filter_map(|(_, competition)| competition.performance.as_ref())
+            .collect::<Vec<_>>();
+        let size = next_size(&self.limits, state.size, &performances);
+        if size != state.size {
+            tracing::info!(
+                previous = state.size,
+                size,
+                "adapted maximum number of orders per auction"
+            );
+        }
+        state.size = size;
+        Metrics::get().limit.set(size as i64);
+        size
+    }
+}
+
+/// The competitions newer than the latest one the size was adapted to. Competitions are ordered
+/// newest first.
+fn new_competitions(
+    competitions: &[(AuctionId, SolverCompetition)],
+    latest: Option<AuctionId>,
+) -> &[(AuctionId, SolverCompetition)] {
+    let count = match latest {
+        Some(latest) => competitions
+            .iter()
+            .take_while(|(id, _)| *id > latest)
+            .count(),
+        None => competitions.len(),
+    };
+    &competitions[..count]
+}
+
+/// Shrinks the auction size if the solver timeout rate or the average simulation time of the
+/// recent auctions exceeds its budget and grows it otherwise.
+fn next_size(limits: &Limits, size: usize, performances: &[&CompetitionPerformance]) -> usize {
+    if performances.is_empty() {
+        return size;
+    }
+    let runs: u64 = performances.iter().map(|p| p.solver_runs).sum();
+    let timed_out: u64 = performances.iter().map(|p| p.timed_out_runs).sum();
+    let timeout_rate = if runs == 0 {
+        0.
+    } else {
+        timed_out as f64 / runs as f64
+    };
+    let simulation_time = performances
+        .iter()
+        .map(|p| p.simulation_seconds)
+        .sum::<f64>()
+        / performances.len() as f64;
+
+    let over_budget = timeout_rate > limits.max_timeout_rate
+        || simulation_time > limits.max_simulation_time.as_secs_f64();
+    let size = if over_budget {
+        (size as f64 * SHRINK_FACTOR) as usize
+    } else {
+        size.saturating_add(limits.step)
+    };
+    size.clamp(limits.min, limits.max.max(limits.min))
+}
+
+/// Limits the orders to the maximum auction size. User orders are preferred over liquidity orders
+/// and older orders over newer ones so that orders don't get starved by a stream of new orders.
+pub fn truncate_orders(mut orders: Vec<Order>, max_orders: usize) -> Vec<Order> {
+    if orders.len() <= max_orders {
+        return orders;
+    }
+    orders.sort_by_key(|order| {
+        (
+            order.metadata.class == OrderClass::Liquidity,
+            order.metadata.creation_date,
+        )
+    });
+    orders.truncate(max_orders);
+    orders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use model::order::OrderMetadata;
+
+    #[test]
+    fn adapts_size_to_performance() {
+        let limits = Limits {
+            min: 50,
+            max: 1000,
+            step: 25,
+            max_timeout_rate: 0.1,
+            max_simulation_time: Duration::from_secs(5),
+        };
+        let performance =
+            |solver_runs, timed_out_runs, simulation_seconds| CompetitionPerformance {
+                solver_runs,
+                timed_out_runs,
+                simulation_seconds,
+            };
+        let healthy = performance(10, 0, 1.);
+        let timeouts = performance(10, 5, 1.);
+        let slow = performance(10, 0, 20.);
+
+        assert_eq!(next_size(&limits, 500, &[]), 500);
+        assert_eq!(next_size(&limits, 500, &[&healthy]), 525);
+        assert_eq!(next_size(&limits, 990, &[&healthy]), 1000);
+        assert_eq!(next_size(&limits, 500, &[&healthy, &timeouts]), 400);
+        assert_eq!(next_size(&limits, 500, &[&healthy, &slow]), 400);
+        assert_eq!(next_size(&limits, 55, &[&timeouts]), 50);
+    }
+
+    #[test]
+    fn counts_competitions_once() {
+        let competitions = [3, 2, 1]
+            .map(|id| (id, SolverCompetition::default()))
+            .to_vec();
+        let ids = |latest| {
+            new_competitions(&competitions, latest)
+                .iter()
+                .map(|(id, _)| *id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(None), [3, 2, 1]);
+        assert_eq!(ids(Some(1)), [3, 2]);
+        assert_eq!(ids(Some(3)), Vec::<AuctionId>::new());
+    }
+
+    #[test]
+    fn truncates_newest_liquidity_orders_first() {
+        let order = |class, timestamp| Order {
+            metadata: OrderMetadata {
+                class,
+                creation_date: Utc.timestamp(timestamp, 0),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let orders = vec![
+            order(OrderClass::Liquidity, 0),
+            order(OrderClass::Market, 2),
+            order(OrderClass::Limit, 1),
+        ];
+        assert_eq!(truncate_orders(orders.clone(), 5), orders);
+        assert_eq!(
+            truncate_orders(orders.clone(), 2),
+            vec![orders[2].clone(), orders[1].clone()]
+        );
+    }
+}
@@ -1,9 +1,24 @@
+mod accounting;
 mod auction;
+mod event_consistency;
 mod events;
+mod order_book_stats;
+mod order_diagnoses;
+mod quote_requests;
 mod quotes;
+mod sealed_orders;
+mod settlement_rebates;
+mod shadow_competition;
+mod solver_competitions;
+mod solver_rewards;
+mod trade_routes;
 
-use sqlx::{PgConnection, PgPool};
-use std::time::Duration;
+pub use quotes::ExpiredQuoteRemover;
+use sqlx::{postgres::PgConnectOptions, ConnectOptions, PgConnection, PgPool};
+use std::time::{Duration, Instant};
+
+const POOL_METRICS_UPDATE_INTERVAL: Duration = Duration::from_secs(5);
+const TABLE_ROWS_UPDATE_INTERVAL: Duration = Duration::from_secs(60);
 
 #[derive(Clone)]
 pub struct Postgres(pub PgPool);
@@ -13,6 +28,47 @@ impl Postgres {
         Ok(Self(PgPool::connect(url).await?))
     }
 
+    /// Like `new` but logs statements that take longer than the threshold as warnings.
+    pub async fn with_slow_query_threshold(url: &str, threshold: Duration) -> sqlx::Result<Self> {
+        let mut options: PgConnectOptions = url.parse()?;
+        options.log_slow_statements(log::LevelFilter::Warn, threshold);
+        Ok(Self(PgPool::connect_with(options).await?))
+    }
+
+    /// The tables that don't exist because not all migrations have been applied.
+    pub async fn missing_tables(&self) -> sqlx::Result<Vec<&'static str>> {
+        let mut ex = self.0.acquire().await?;
+        database::missing_tables(&mut ex).await
+    }
+
+    /// Verifies that the schema matches the migrations of this binary and applies pending
+    /// migrations if allowed. Returns the versions of the applied migrations.
+    pub async fn ensure_schema(
+        &self,
+        apply_migrations: bool,
+    ) -> Result<Vec<u32>, database::migrations::Error> {
+        let mut ex = self.0.acquire().await?;
+        database::migrations::ensure_schema(&mut ex, apply_migrations).await
+    }
+
+    pub fn update_pool_metrics(&self) {
+        let metrics = Metrics::get();
+        let connections = self.0.size();
+        let idle = self.0.num_idle() as u32;
+        let max_connections = self.0.options().get_max_connections();
+        metrics
+            .database_connections
+            .with_label_values(&["idle"])
+            .set(idle.into());
+        metrics
+            .database_connections
+            .with_label_values(&["active"])
+            .set(connections.saturating_sub(idle).into());
+        metrics
+            .database_pool_saturation
+            .set(connections.saturating_sub(idle) as f64 / max_connections as f64);
+    }
+
     pub async fn update_table_rows_metric(&self) -> sqlx::Result<()> {
         let metrics = Metrics::get();
         for &table in database::ALL_TABLES {
@@ -38,6 +94,19 @@ struct Metrics {
     /// Timing of db queries.
     #[metric(name = "autopilot_database_queries", labels("type"))]
     database_queries: prometheus::HistogramVec,
+
+    /// Timing of db transactions from begin to commit, excluding the time spent waiting for a
+    /// connection.
+    #[metric(name = "autopilot_database_transactions", labels("type"))]
+    database_transactions: prometheus::HistogramVec,
+
+    /// Number of connections in the pool by state.
+    #[metric(name = "autopilot_database_connections", labels("state"))]
+    database_connections: prometheus::IntGaugeVec,
+
+    /// Ratio of active connections to the maximum size of the pool.
+    #[metric(name = "autopilot_database_pool_saturation")]
+    database_pool_saturation: prometheus::Gauge,
 }
 
 impl Metrics {
@@ -47,11 +116,18 @@ impl Metrics {
 }
 
 pub async fn database_metrics(db: Postgres) -> ! {
+    let mut table_rows_updated: Option<Instant> = None;
     loop {
-        if let Err(err) = db.update_table_rows_metric().await {
-            tracing::error!(?err, "failed to update table rows metric");
+        db.update_pool_metrics();
+        if table_rows_updated.map_or(true, |updated| {
+            updated.elapsed() >= TABLE_ROWS_UPDATE_INTERVAL
+        }) {
+            if let Err(err) = db.update_table_rows_metric().await {
+                tracing::error!(?err, "failed to update table rows metric");
+            }
+            table_rows_updated = Some(Instant::now());
         }
-        tokio::time::sleep(Duration::from_secs(60)).await;
+        tokio::time::sleep(POOL_METRICS_UPDATE_INTERVAL).await;
     }
 }
 
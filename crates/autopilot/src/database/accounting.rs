@@ -0,0 +1,33 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+use database::accounting::BlockTradeTotals;
+
+impl Postgres {
+    pub async fn refresh_solver_accounting(&self) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["refresh_solver_accounting"])
+            .start_timer();
+
+        let mut ex = self.0.acquire().await?;
+        database::accounting::refresh(&mut ex)
+            .await
+            .context("refresh_solver_accounting")
+    }
+
+    pub async fn block_trade_totals(
+        &self,
+        from_block: i64,
+        to_block: i64,
+    ) -> Result<Vec<BlockTradeTotals>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["block_trade_totals"])
+            .start_timer();
+
+        let mut ex = self.0.acquire().await?;
+        database::accounting::block_trade_totals(&mut ex, from_block, to_block)
+            .await
+            .context("block_trade_totals")
+    }
+}
@@ -2,23 +2,27 @@ use super::Postgres;
 use anyhow::{anyhow, Context, Result};
 use database::{
     auction::AuctionId,
+    byte_array::ByteArray,
     orders::{
         BuyTokenDestination as DbBuyTokenDestination, OrderKind as DbOrderKind,
-        SellTokenSource as DbSellTokenSource, SigningScheme as DbSigningScheme,
+        SellTokenSource as DbSellTokenSource,
     },
 };
 use futures::{StreamExt, TryStreamExt};
 use model::{
     app_id::AppId,
-    auction::Auction,
+    auction::{Auction, AuctionWithId},
     order::{
         BuyTokenDestination, Order, OrderData, OrderKind, OrderMetadata, OrderStatus, OrderUid,
         SellTokenSource,
     },
-    signature::{Signature, SigningScheme},
+    signature::Signature,
 };
 use number_conversions::{big_decimal_to_big_uint, big_decimal_to_u256};
 use primitive_types::H160;
+use shared::db_order_conversions::{
+    order_class_from, order_condition_from, quote_firmness_from, signing_scheme_from,
+};
 
 pub struct SolvableOrders {
     pub orders: Vec<Order>,
@@ -33,6 +37,10 @@ impl Postgres {
             .start_timer();
 
         let mut ex = self.0.begin().await?;
+        let _transaction_timer = super::Metrics::get()
+            .database_transactions
+            .with_label_values(&["solvable_orders"])
+            .start_timer();
         let orders = database::orders::solvable_orders(&mut ex, min_valid_to as i64)
             .map(|result| match result {
                 Ok(order) => full_order_into_model_order(order),
@@ -48,6 +56,38 @@ impl Postgres {
         })
     }
 
+    /// Presign orders that are otherwise solvable but whose presignature events haven't been
+    /// indexed yet.
+    pub async fn presignature_pending_orders(&self, min_valid_to: u32) -> Result<Vec<Order>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["presignature_pending_orders"])
+            .start_timer();
+
+        let mut ex = self.0.acquire().await?;
+        database::orders::presignature_pending_orders(&mut ex, min_valid_to as i64)
+            .map(|result| match result {
+                Ok(order) => full_order_into_model_order(order),
+                Err(err) => Err(anyhow::Error::from(err)),
+            })
+            .try_collect()
+            .await
+    }
+
+    /// Marks the presign orders as pre-signed as of the block so that their status stops being
+    /// pending before their presignature events get indexed.
+    pub async fn record_onchain_presignatures(&self, uids: &[OrderUid], block: u64) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["record_onchain_presignatures"])
+            .start_timer();
+
+        let uids = uids.iter().map(|uid| ByteArray(uid.0)).collect::<Vec<_>>();
+        let mut ex = self.0.acquire().await?;
+        database::orders::record_onchain_presignatures(&mut ex, &uids, block as i64).await?;
+        Ok(())
+    }
+
     pub async fn replace_current_auction(&self, auction: &Auction) -> Result<AuctionId> {
         let _timer = super::Metrics::get()
             .database_queries
@@ -56,11 +96,30 @@ impl Postgres {
 
         let data = serde_json::to_value(&auction)?;
         let mut ex = self.0.begin().await?;
+        let _transaction_timer = super::Metrics::get()
+            .database_transactions
+            .with_label_values(&["save_auction"])
+            .start_timer();
         database::auction::delete_all_auctions(&mut ex).await?;
         let id = database::auction::save(&mut ex, &data).await?;
         ex.commit().await?;
         Ok(id)
     }
+
+    pub async fn most_recent_auction(&self) -> Result<Option<AuctionWithId>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["load_most_recent_auction"])
+            .start_timer();
+
+        let mut ex = self.0.acquire().await?;
+        let (id, json) = match database::auction::load_most_recent(&mut ex).await? {
+            Some(inner) => inner,
+            None => return Ok(None),
+        };
+        let auction: Auction = serde_json::from_value(json)?;
+        Ok(Some(AuctionWithId { id, auction }))
+    }
 }
 
 fn full_order_into_model_order(order: database::orders::FullOrder) -> Result<Order> {
@@ -88,7 +147,20 @@ fn full_order_into_model_order(order: database::orders::FullOrder) -> Result<Ord
         settlement_contract: H160(order.settlement_contract.0),
         full_fee_amount: big_decimal_to_u256(&order.full_fee_amount)
             .ok_or_else(|| anyhow!("full_fee_amount is not U256"))?,
-        is_liquidity_order: order.is_liquidity_order,
+        protocol_fee_amount: big_decimal_to_u256(&order.protocol_fee_amount)
+            .ok_or_else(|| anyhow!("protocol_fee_amount is not U256"))?,
+        class: order_class_from(order.class),
+        quote_firmness: quote_firmness_from(order.quote_expiry_block, order.quote_last_look),
+        min_partial_fill_amount: order
+            .min_partial_fill_amount
+            .as_ref()
+            .map(|amount| {
+                big_decimal_to_u256(amount)
+                    .ok_or_else(|| anyhow!("min_partial_fill_amount is not U256"))
+            })
+            .transpose()?,
+        private_submission: order.private_submission,
+        condition: order_condition_from(order.condition)?,
     };
     let data = OrderData {
         sell_token: H160(order.sell_token.0),
@@ -137,12 +209,3 @@ fn buy_token_destination_from(destination: DbBuyTokenDestination) -> BuyTokenDes
         DbBuyTokenDestination::Internal => BuyTokenDestination::Internal,
     }
 }
-
-fn signing_scheme_from(scheme: DbSigningScheme) -> SigningScheme {
-    match scheme {
-        DbSigningScheme::Eip712 => SigningScheme::Eip712,
-        DbSigningScheme::EthSign => SigningScheme::EthSign,
-        DbSigningScheme::Eip1271 => SigningScheme::Eip1271,
-        DbSigningScheme::PreSign => SigningScheme::PreSign,
-    }
-}
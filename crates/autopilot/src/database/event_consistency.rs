@@ -0,0 +1,45 @@
+use super::Postgres;
+use crate::event_consistency::{Discrepancies, TradeEvent};
+use anyhow::{Context, Result};
+use model::order::OrderUid;
+
+impl Postgres {
+    /// Reads all discrepancies from the same snapshot so that events indexed in the meantime
+    /// don't show up as inconsistencies.
+    pub async fn event_discrepancies(&self, from_block: u64) -> Result<Discrepancies> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["event_discrepancies"])
+            .start_timer();
+
+        let from_block = i64::try_from(from_block).context("block out of range")?;
+        let mut ex = self.0.acquire().await?;
+        let mut ex = database::begin_snapshot(&mut ex).await?;
+        let uids = |uids: Vec<database::OrderUid>| {
+            uids.into_iter()
+                .map(|uid| OrderUid(uid.0))
+                .collect::<Vec<_>>()
+        };
+        let overfilled_orders = database::event_consistency::overfilled_orders(&mut ex, from_block)
+            .await
+            .context("overfilled_orders")?;
+        let excess_fee_orders = database::event_consistency::excess_fee_orders(&mut ex, from_block)
+            .await
+            .context("excess_fee_orders")?;
+        let trades_without_settlement =
+            database::event_consistency::trades_without_settlement(&mut ex, from_block)
+                .await
+                .context("trades_without_settlement")?;
+        Ok(Discrepancies {
+            overfilled_orders: uids(overfilled_orders),
+            excess_fee_orders: uids(excess_fee_orders),
+            trades_without_settlement: trades_without_settlement
+                .into_iter()
+                .map(|index| TradeEvent {
+                    block_number: index.block_number as u64,
+                    log_index: index.log_index as u64,
+                })
+                .collect(),
+        })
+    }
+}
@@ -62,6 +62,10 @@ impl EventStoring<ContractEvent> for Postgres {
 
         let events = contract_to_db_events(events)?;
         let mut transaction = self.0.begin().await?;
+        let _transaction_timer = super::Metrics::get()
+            .database_transactions
+            .with_label_values(&["append_events"])
+            .start_timer();
         database::events::append(&mut transaction, &events)
             .await
             .context("append_events")?;
@@ -81,6 +85,10 @@ impl EventStoring<ContractEvent> for Postgres {
 
         let events = contract_to_db_events(events)?;
         let mut transaction = self.0.begin().await?;
+        let _transaction_timer = super::Metrics::get()
+            .database_transactions
+            .with_label_values(&["replace_events"])
+            .start_timer();
         database::events::delete(&mut transaction, range.start().to_u64() as i64)
             .await
             .context("delete_events failed")?;
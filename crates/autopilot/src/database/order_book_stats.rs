@@ -0,0 +1,45 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+
+impl Postgres {
+    pub async fn blocks_without_timestamp(&self, limit: i64) -> Result<Vec<i64>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["blocks_without_timestamp"])
+            .start_timer();
+
+        let mut ex = self.0.acquire().await?;
+        database::order_book_stats::blocks_without_timestamp(&mut ex, limit)
+            .await
+            .context("blocks_without_timestamp")
+    }
+
+    pub async fn set_block_timestamp(
+        &self,
+        block_number: i64,
+        timestamp: DateTime<Utc>,
+    ) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["set_block_timestamp"])
+            .start_timer();
+
+        let mut ex = self.0.acquire().await?;
+        database::order_book_stats::set_block_timestamp(&mut ex, block_number, timestamp)
+            .await
+            .context("set_block_timestamp")
+    }
+
+    pub async fn refresh_order_book_stats(&self) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["refresh_order_book_stats"])
+            .start_timer();
+
+        let mut ex = self.0.acquire().await?;
+        database::order_book_stats::refresh(&mut ex)
+            .await
+            .context("refresh_order_book_stats")
+    }
+}
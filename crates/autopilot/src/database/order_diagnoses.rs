@@ -0,0 +1,45 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use database::{byte_array::ByteArray, order_diagnoses::OrderDiagnosis};
+use model::{order::OrderUid, stuck_orders::StuckOrderReason};
+
+impl Postgres {
+    pub async fn replace_order_diagnoses(
+        &self,
+        diagnoses: &[(OrderUid, StuckOrderReason)],
+        diagnosed_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["replace_order_diagnoses"])
+            .start_timer();
+
+        let diagnoses = diagnoses
+            .iter()
+            .map(|(uid, reason)| OrderDiagnosis {
+                order_uid: ByteArray(uid.0),
+                reason: reason_into(*reason),
+                diagnosed_at,
+            })
+            .collect::<Vec<_>>();
+        let mut transaction = self.0.begin().await?;
+        database::order_diagnoses::replace(&mut transaction, &diagnoses)
+            .await
+            .context("replace_order_diagnoses")?;
+        transaction.commit().await.context("commit")
+    }
+}
+
+fn reason_into(reason: StuckOrderReason) -> database::order_diagnoses::StuckOrderReason {
+    use database::order_diagnoses::StuckOrderReason as Db;
+    match reason {
+        StuckOrderReason::TokenDenied => Db::TokenDenied,
+        StuckOrderReason::MissingAllowance => Db::MissingAllowance,
+        StuckOrderReason::InsufficientBalance => Db::InsufficientBalance,
+        StuckOrderReason::NoLiquidityPath => Db::NoLiquidityPath,
+        StuckOrderReason::TooSmall => Db::TooSmall,
+        StuckOrderReason::PriceOutOfRange => Db::PriceOutOfRange,
+        StuckOrderReason::Unknown => Db::Unknown,
+    }
+}
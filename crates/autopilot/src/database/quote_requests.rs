@@ -0,0 +1,16 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+
+impl Postgres {
+    pub async fn refresh_integrator_quote_funnel(&self) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["refresh_integrator_quote_funnel"])
+            .start_timer();
+
+        let mut ex = self.0.acquire().await?;
+        database::quote_requests::refresh_funnel(&mut ex)
+            .await
+            .context("refresh_integrator_quote_funnel")
+    }
+}
@@ -2,6 +2,7 @@ use super::Postgres;
 use anyhow::{Context, Result};
 use shared::maintenance::Maintaining;
 use sqlx::types::chrono::{DateTime, Utc};
+use std::time::Duration;
 
 impl Postgres {
     pub async fn remove_expired_quotes(&self, max_expiry: DateTime<Utc>) -> Result<()> {
@@ -16,10 +17,27 @@ impl Postgres {
     }
 }
 
+/// Deletes quotes once they expired longer than the retention ago.
+pub struct ExpiredQuoteRemover {
+    db: Postgres,
+    retention: chrono::Duration,
+}
+
+impl ExpiredQuoteRemover {
+    pub fn new(db: Postgres, retention: Duration) -> Self {
+        Self {
+            db,
+            retention: chrono::Duration::from_std(retention)
+                .unwrap_or_else(|_| chrono::Duration::zero()),
+        }
+    }
+}
+
 #[async_trait::async_trait]
-impl Maintaining for Postgres {
+impl Maintaining for ExpiredQuoteRemover {
     async fn run_maintenance(&self) -> Result<()> {
-        self.remove_expired_quotes(Utc::now())
+        self.db
+            .remove_expired_quotes(Utc::now() - self.retention)
             .await
             .context("fee measurement maintenance error")
     }
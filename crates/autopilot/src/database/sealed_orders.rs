@@ -0,0 +1,49 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+use database::{
+    byte_array::ByteArray,
+    sealed_orders::{SealedOrder, SealedOrderId},
+};
+use model::order::OrderUid;
+
+impl Postgres {
+    pub async fn pending_sealed_orders(&self) -> Result<Vec<SealedOrder>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["pending_sealed_orders"])
+            .start_timer();
+
+        let mut ex = self.0.acquire().await?;
+        database::sealed_orders::pending(&mut ex)
+            .await
+            .context("pending_sealed_orders")
+    }
+
+    pub async fn mark_sealed_order_revealed(
+        &self,
+        id: &SealedOrderId,
+        order_uid: &OrderUid,
+    ) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["mark_sealed_order_revealed"])
+            .start_timer();
+
+        let mut ex = self.0.acquire().await?;
+        database::sealed_orders::mark_revealed(&mut ex, id, &ByteArray(order_uid.0))
+            .await
+            .context("mark_sealed_order_revealed")
+    }
+
+    pub async fn mark_sealed_order_rejected(&self, id: &SealedOrderId, reason: &str) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["mark_sealed_order_rejected"])
+            .start_timer();
+
+        let mut ex = self.0.acquire().await?;
+        database::sealed_orders::mark_rejected(&mut ex, id, reason)
+            .await
+            .context("mark_sealed_order_rejected")
+    }
+}
@@ -0,0 +1,67 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use database::{
+    events::EventIndex,
+    settlement_rebates::{OrderRebate, UnprocessedSettlement},
+    Address, OrderUid, TransactionHash,
+};
+
+impl Postgres {
+    pub async fn unprocessed_rebate_settlements(
+        &self,
+        start_block: i64,
+        before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<UnprocessedSettlement>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["unprocessed_rebate_settlements"])
+            .start_timer();
+
+        let mut ex = self.0.acquire().await?;
+        database::settlement_rebates::unprocessed_settlements(&mut ex, start_block, before, limit)
+            .await
+            .context("unprocessed_rebate_settlements")
+    }
+
+    pub async fn settled_user_order_uids(&self, settlement: &EventIndex) -> Result<Vec<OrderUid>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["settled_user_order_uids"])
+            .start_timer();
+
+        let mut ex = self.0.acquire().await?;
+        database::settlement_rebates::settled_user_orders(&mut ex, settlement)
+            .await
+            .context("settled_user_order_uids")
+    }
+
+    pub async fn insert_settlement_rebate(
+        &self,
+        settlement: &EventIndex,
+        tx_hash: &TransactionHash,
+        recipient: &Address,
+        rebate: &BigDecimal,
+        orders: &[OrderRebate],
+    ) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["insert_settlement_rebate"])
+            .start_timer();
+
+        let mut transaction = self.0.begin().await?;
+        database::settlement_rebates::insert(
+            &mut transaction,
+            settlement,
+            tx_hash,
+            recipient,
+            rebate,
+            orders,
+        )
+        .await
+        .context("insert_settlement_rebate")?;
+        transaction.commit().await.context("commit")
+    }
+}
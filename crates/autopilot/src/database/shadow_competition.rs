@@ -0,0 +1,17 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+use database::shadow_competition::ShadowResult;
+
+impl Postgres {
+    pub async fn save_shadow_result(&self, result: &ShadowResult) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["save_shadow_result"])
+            .start_timer();
+
+        let mut ex = self.0.acquire().await?;
+        database::shadow_competition::save(&mut ex, result)
+            .await
+            .context("save_shadow_result")
+    }
+}
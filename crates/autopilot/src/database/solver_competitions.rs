@@ -0,0 +1,29 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+use database::auction::AuctionId;
+use model::solver_competition::SolverCompetition;
+
+impl Postgres {
+    /// The most recent solver competitions, newest first.
+    pub async fn latest_solver_competitions(
+        &self,
+        limit: i64,
+    ) -> Result<Vec<(AuctionId, SolverCompetition)>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["latest_solver_competitions"])
+            .start_timer();
+
+        let mut ex = self.0.acquire().await?;
+        database::solver_competition::load_latest(&mut ex, limit)
+            .await
+            .context("latest_solver_competitions")?
+            .into_iter()
+            .map(|(id, json)| {
+                let competition = serde_json::from_value(json)
+                    .with_context(|| format!("deserialize solver competition {}", id))?;
+                Ok((id, competition))
+            })
+            .collect()
+    }
+}
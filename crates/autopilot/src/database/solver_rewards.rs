@@ -0,0 +1,72 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use database::{
+    events::EventIndex,
+    solver_rewards::{SettlementReward, UnrewardedSettlement},
+    TransactionHash,
+};
+use model::solver_competition::SolverCompetition;
+
+impl Postgres {
+    pub async fn unrewarded_settlements(
+        &self,
+        before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<UnrewardedSettlement>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["unrewarded_settlements"])
+            .start_timer();
+
+        let mut ex = self.0.acquire().await?;
+        database::solver_rewards::unrewarded_settlements(&mut ex, before, limit)
+            .await
+            .context("unrewarded_settlements")
+    }
+
+    pub async fn settled_user_orders(&self, settlement: &EventIndex) -> Result<i64> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["settled_user_orders"])
+            .start_timer();
+
+        let mut ex = self.0.acquire().await?;
+        database::solver_rewards::settled_user_orders(&mut ex, settlement)
+            .await
+            .context("settled_user_orders")
+    }
+
+    pub async fn solver_competition_by_tx_hash(
+        &self,
+        tx_hash: &TransactionHash,
+    ) -> Result<Option<SolverCompetition>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["solver_competition_by_tx_hash"])
+            .start_timer();
+
+        let mut ex = self.0.acquire().await?;
+        // Rewards get computed long before competitions get archived so archived competitions are
+        // treated as missing.
+        database::solver_competition::load_by_tx_hash(&mut ex, tx_hash)
+            .await
+            .context("solver_competition_by_tx_hash")?
+            .and_then(|competition| competition.json)
+            .map(serde_json::from_value)
+            .transpose()
+            .context("deserialize solver competition")
+    }
+
+    pub async fn insert_settlement_reward(&self, reward: &SettlementReward) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["insert_settlement_reward"])
+            .start_timer();
+
+        let mut ex = self.0.acquire().await?;
+        database::solver_rewards::insert(&mut ex, reward)
+            .await
+            .context("insert_settlement_reward")
+    }
+}
@@ -0,0 +1,49 @@
+use super::Postgres;
+use anyhow::{Context, Result};
+use database::{
+    events::EventIndex,
+    trade_routes::{SettlementTrade, UnindexedSettlement, Venue},
+};
+
+impl Postgres {
+    pub async fn unindexed_settlements(&self, limit: i64) -> Result<Vec<UnindexedSettlement>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["unindexed_settlements"])
+            .start_timer();
+
+        let mut ex = self.0.acquire().await?;
+        database::trade_routes::unindexed_settlements(&mut ex, limit)
+            .await
+            .context("unindexed_settlements")
+    }
+
+    pub async fn settlement_trades(&self, settlement: &EventIndex) -> Result<Vec<SettlementTrade>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["settlement_trades"])
+            .start_timer();
+
+        let mut ex = self.0.acquire().await?;
+        database::trade_routes::settlement_trades(&mut ex, settlement)
+            .await
+            .context("settlement_trades")
+    }
+
+    pub async fn insert_trade_routes(
+        &self,
+        settlement: &EventIndex,
+        trades: &[(EventIndex, Vec<Venue>)],
+    ) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["insert_trade_routes"])
+            .start_timer();
+
+        let mut transaction = self.0.begin().await?;
+        database::trade_routes::insert(&mut transaction, settlement, trades)
+            .await
+            .context("insert_trade_routes")?;
+        transaction.commit().await.context("commit")
+    }
+}
@@ -0,0 +1,212 @@
+//! Audits the indexed settlement contract events.
+//!
+//! Bugs in event indexing don't fail loudly: a missed or duplicated event only shows up in wrong
+//! executed amounts. The checker periodically looks for events since a recent block that violate
+//! invariants of the settlement contract, like orders that got executed for more than they signed,
+//! and reports them as metrics and through the API.
+
+use crate::database::Postgres;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use model::order::OrderUid;
+use prometheus::IntGaugeVec;
+use serde::Serialize;
+use shared::{
+    arguments::duration_from_seconds,
+    current_block::{block_number, CurrentBlockStream},
+    maintenance::Maintaining,
+};
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Arguments related to the event consistency checker.
+#[derive(clap::Parser)]
+pub struct Arguments {
+    /// How many of the most recent blocks get checked for inconsistent events.
+    #[clap(long, env, default_value = "7200")]
+    pub event_consistency_blocks: u64,
+
+    /// How often in seconds the events get checked.
+    #[clap(
+        long,
+        env,
+        default_value = "600",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub event_consistency_interval: Duration,
+}
+
+impl Display for Arguments {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "event_consistency_blocks: {}",
+            self.event_consistency_blocks
+        )?;
+        writeln!(
+            f,
+            "event_consistency_interval: {:?}",
+            self.event_consistency_interval
+        )?;
+        Ok(())
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage)]
+#[metric(subsystem = "event_consistency")]
+struct Metrics {
+    /// Inconsistencies found in the most recent check by the violated invariant.
+    #[metric(labels("check"))]
+    discrepancies: IntGaugeVec,
+}
+
+impl Metrics {
+    fn get() -> &'static Self {
+        Metrics::instance(global_metrics::get_metric_storage_registry()).unwrap()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TradeEvent {
+    pub block_number: u64,
+    pub log_index: u64,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Discrepancies {
+    /// Orders executed for more than their limit amount.
+    pub overfilled_orders: Vec<OrderUid>,
+    /// Market orders that paid more fees than they signed.
+    pub excess_fee_orders: Vec<OrderUid>,
+    /// Trade events that weren't followed by the settlement event of their transaction.
+    pub trades_without_settlement: Vec<TradeEvent>,
+}
+
+impl Discrepancies {
+    fn counts(&self) -> [(&'static str, usize); 3] {
+        [
+            ("overfilled_orders", self.overfilled_orders.len()),
+            ("excess_fee_orders", self.excess_fee_orders.len()),
+            (
+                "trades_without_settlement",
+                self.trades_without_settlement.len(),
+            ),
+        ]
+    }
+
+    fn is_empty(&self) -> bool {
+        self.counts().iter().all(|(_, count)| *count == 0)
+    }
+}
+
+/// The result of the most recent check.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    /// `None` until the first check finished.
+    pub checked_at: Option<DateTime<Utc>>,
+    pub from_block: u64,
+    #[serde(flatten)]
+    pub discrepancies: Discrepancies,
+}
+
+pub struct EventConsistencyChecker {
+    db: Postgres,
+    current_block: CurrentBlockStream,
+    blocks: u64,
+    interval: Duration,
+    last_run: Mutex<Option<Instant>>,
+    report: Mutex<Report>,
+}
+
+impl EventConsistencyChecker {
+    pub fn new(db: Postgres, current_block: CurrentBlockStream, args: &Arguments) -> Self {
+        Self {
+            db,
+            current_block,
+            blocks: args.event_consistency_blocks,
+            interval: args.event_consistency_interval,
+            last_run: Default::default(),
+            report: Default::default(),
+        }
+    }
+
+    pub fn report(&self) -> Report {
+        self.report.lock().unwrap().clone()
+    }
+
+    async fn check(&self) -> Result<()> {
+        let current_block = block_number(&self.current_block.borrow())?;
+        let from_block = current_block.saturating_sub(self.blocks);
+        let discrepancies = self.db.event_discrepancies(from_block).await?;
+
+        let metrics = Metrics::get();
+        for (check, count) in discrepancies.counts() {
+            metrics
+                .discrepancies
+                .with_label_values(&[check])
+                .set(count as i64);
+        }
+        if !discrepancies.is_empty() {
+            tracing::warn!(from_block, ?discrepancies, "inconsistent settlement events");
+        }
+        *self.report.lock().unwrap() = Report {
+            checked_at: Some(Utc::now()),
+            from_block,
+            discrepancies,
+        };
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Maintaining for EventConsistencyChecker {
+    async fn run_maintenance(&self) -> Result<()> {
+        let now = Instant::now();
+        if matches!(*self.last_run.lock().unwrap(), Some(last) if now.duration_since(last) < self.interval)
+        {
+            return Ok(());
+        }
+        self.check().await?;
+        *self.last_run.lock().unwrap() = Some(now);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_report() {
+        let report = Report {
+            checked_at: None,
+            from_block: 1,
+            discrepancies: Discrepancies {
+                overfilled_orders: vec![OrderUid([1; 56])],
+                excess_fee_orders: Vec::new(),
+                trades_without_settlement: vec![TradeEvent {
+                    block_number: 2,
+                    log_index: 3,
+                }],
+            },
+        };
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "checkedAt": null,
+                "fromBlock": 1,
+                "overfilledOrders": [OrderUid([1; 56])],
+                "excessFeeOrders": [],
+                "tradesWithoutSettlement": [{"blockNumber": 2, "logIndex": 3}],
+            })
+        );
+        assert!(!report.discrepancies.is_empty());
+        assert!(Discrepancies::default().is_empty());
+    }
+}
@@ -0,0 +1,57 @@
+//! Keeps the quote funnel of integrators served by the orderbook api up to date.
+
+use crate::database::Postgres;
+use anyhow::Result;
+use shared::maintenance::Maintaining;
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// The funnel covers months of quote requests and orders, so it gets recomputed much less often
+/// than maintenance runs.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(600);
+
+pub struct IntegratorFunnelUpdater {
+    db: Postgres,
+    last_refresh: Mutex<Option<Instant>>,
+}
+
+impl IntegratorFunnelUpdater {
+    pub fn new(db: Postgres) -> Self {
+        Self {
+            db,
+            last_refresh: Default::default(),
+        }
+    }
+}
+
+fn is_due(last_refresh: Option<Instant>, now: Instant) -> bool {
+    last_refresh.map_or(true, |last| now.duration_since(last) >= REFRESH_INTERVAL)
+}
+
+#[async_trait::async_trait]
+impl Maintaining for IntegratorFunnelUpdater {
+    async fn run_maintenance(&self) -> Result<()> {
+        let now = Instant::now();
+        if !is_due(*self.last_refresh.lock().unwrap(), now) {
+            return Ok(());
+        }
+        self.db.refresh_integrator_quote_funnel().await?;
+        *self.last_refresh.lock().unwrap() = Some(now);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refreshes_after_interval() {
+        let now = Instant::now();
+        assert!(is_due(None, now));
+        assert!(!is_due(Some(now), now + REFRESH_INTERVAL / 2));
+        assert!(is_due(Some(now), now + REFRESH_INTERVAL));
+    }
+}
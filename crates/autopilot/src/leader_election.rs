@@ -0,0 +1,146 @@
+//! Optional leader election so that several autopilot replicas, for example in different regions,
+//! can run for failover while only one of them cuts auctions.
+//!
+//! Replicas compete for a Postgres advisory lock on a dedicated connection. The replica holding the
+//! lock is the leader. The others wait until the lock becomes free, which happens when the
+//! leader's connection closes, for example because the leader crashed or lost its network. A
+//! leader that loses its connection can't know whether another replica has taken over, so it
+//! stops and is expected to be restarted as a follower.
+
+use anyhow::{Context, Result};
+use shared::arguments::{display_option, duration_from_seconds};
+use sqlx::{Connection, PgConnection};
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+/// Arguments related to leader election.
+#[derive(clap::Parser)]
+pub struct Arguments {
+    /// Enables leader election. All replicas that should fail over to each other must use the same
+    /// key and database.
+    #[clap(long, env)]
+    pub leader_election_lock_key: Option<i64>,
+
+    /// How often in seconds followers try to become the leader and the leader checks that it still
+    /// holds the lock.
+    #[clap(
+        long,
+        env,
+        default_value = "5",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub leader_election_interval: Duration,
+}
+
+impl Display for Arguments {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        display_option(
+            f,
+            "leader_election_lock_key",
+            &self.leader_election_lock_key,
+        )?;
+        writeln!(
+            f,
+            "leader_election_interval: {:?}",
+            self.leader_election_interval
+        )?;
+        Ok(())
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage)]
+#[metric(subsystem = "leader_election")]
+struct Metrics {
+    /// Whether this replica is the leader.
+    is_leader: prometheus::IntGauge,
+
+    /// Leadership changes of this replica by kind of change.
+    #[metric(labels("change"))]
+    leadership_changes: prometheus::IntCounterVec,
+}
+
+impl Metrics {
+    fn get() -> &'static Self {
+        Metrics::instance(global_metrics::get_metric_storage_registry()).unwrap()
+    }
+}
+
+/// Holds the leader lock for as long as it is alive.
+pub struct Leader {
+    connection: PgConnection,
+    interval: Duration,
+}
+
+/// Waits until this replica becomes the leader.
+pub async fn acquire(db_url: &str, key: i64, interval: Duration) -> Leader {
+    Metrics::get().is_leader.set(0);
+    let mut connection = None;
+    loop {
+        match try_acquire(&mut connection, db_url, key).await {
+            Ok(true) => break,
+            Ok(false) => tracing::debug!("another replica is the leader"),
+            Err(err) => {
+                tracing::warn!(?err, "failed to acquire leader lock");
+                connection = None;
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+    tracing::info!("became the leader");
+    let metrics = Metrics::get();
+    metrics.is_leader.set(1);
+    metrics
+        .leadership_changes
+        .with_label_values(&["acquired"])
+        .inc();
+    Leader {
+        connection: connection.unwrap(),
+        interval,
+    }
+}
+
+async fn try_acquire(
+    connection: &mut Option<PgConnection>,
+    db_url: &str,
+    key: i64,
+) -> Result<bool> {
+    if connection.is_none() {
+        *connection = Some(
+            PgConnection::connect(db_url)
+                .await
+                .context("failed to connect")?,
+        );
+    }
+    let connection = connection.as_mut().unwrap();
+    Ok(database::leader_lock::try_acquire(connection, key).await?)
+}
+
+impl Leader {
+    /// Returns when leadership is lost because the connection holding the lock failed or didn't
+    /// respond within the interval. Postgres may have dropped an unresponsive session together
+    /// with the lock, so another replica could already be the leader.
+    pub async fn retain(mut self) {
+        loop {
+            tokio::time::sleep(self.interval).await;
+            match tokio::time::timeout(self.interval, self.connection.ping()).await {
+                Ok(Ok(())) => (),
+                Ok(Err(err)) => {
+                    tracing::error!(?err, "lost leader lock connection");
+                    break;
+                }
+                Err(_) => {
+                    tracing::error!("leader lock connection timed out");
+                    break;
+                }
+            }
+        }
+        let metrics = Metrics::get();
+        metrics.is_leader.set(0);
+        metrics
+            .leadership_changes
+            .with_label_values(&["lost"])
+            .inc();
+    }
+}
@@ -1,11 +1,47 @@
+pub mod accounting;
+pub mod api;
 pub mod arguments;
+pub mod auction_clustering;
+pub mod auction_size;
 pub mod database;
+pub mod event_consistency;
 pub mod event_updater;
+pub mod integrator_analytics;
+pub mod leader_election;
+pub mod mev_rebates;
+pub mod order_book_stats;
+pub mod price_sanity;
+pub mod scoring;
+pub mod sealed_orders;
+pub mod shadow_competition;
 pub mod solvable_orders;
+pub mod solve_deadline;
+pub mod solver_rewards;
+pub mod stuck_orders;
+pub mod tenderly_fork;
+pub mod trade_routes;
 
-use crate::{database::Postgres, solvable_orders::SolvableOrdersCache};
-use contracts::{BalancerV2Vault, IUniswapV3Factory, WETH9};
-use ethcontract::errors::DeployError;
+use crate::{
+    accounting::AccountingUpdater,
+    auction_clustering::AuctionClustering,
+    auction_size::AuctionSizeController,
+    database::{ExpiredQuoteRemover, Postgres},
+    event_consistency::EventConsistencyChecker,
+    integrator_analytics::IntegratorFunnelUpdater,
+    mev_rebates::MevRebateIndexer,
+    order_book_stats::OrderBookStatsUpdater,
+    price_sanity::PriceSanityGuard,
+    scoring::ScoringRules,
+    sealed_orders::SealedOrderRevealer,
+    shadow_competition::ShadowCompetition,
+    solvable_orders::SolvableOrdersCache,
+    solve_deadline::SolveDeadline,
+    solver_rewards::{RewardScheme, SolverRewardsUpdater},
+    stuck_orders::StuckOrderDetector,
+    trade_routes::{RouteClassifier, TradeRouteIndexer, UniswapV3Contracts},
+};
+use contracts::{BalancerV2Vault, IUniswapV3Factory, UniswapV3SwapRouter, WETH9};
+use ethcontract::{errors::DeployError, H160};
 use shared::{
     account_balances::Web3BalanceFetcher,
     bad_token::{
@@ -14,13 +50,18 @@ use shared::{
         list_based::{ListBasedDetector, UnknownTokenStrategy},
         token_owner_finder,
         trace_call::TraceCallDetector,
+        BadTokenDetecting,
     },
     balancer_sor_api::DefaultBalancerSorApi,
     baseline_solver::BaseTokens,
+    hot_reload::{reload_on_sighup, Overrides, Reloadable},
     http_solver::{DefaultHttpSolverApi, SolverConfig},
     metrics::LivenessChecking,
     oneinch_api::OneInchClientImpl,
+    order_conditions::Web3ConditionEvaluator,
     paraswap_api::DefaultParaswapApi,
+    preflight::{self, Preflight},
+    presignature::Web3PresignatureChecker,
     price_estimation::{
         balancer_sor::BalancerSor, baseline::BaselinePriceEstimator,
         competition::CompetitionPriceEstimator, http::HttpPriceEstimator,
@@ -33,15 +74,25 @@ use shared::{
     recent_block_cache::CacheConfig,
     signature_validator::Web3SignatureValidator,
     sources::{
-        balancer_v2::{pool_fetching::BalancerContracts, BalancerFactoryKind, BalancerPoolFetcher},
+        balancer_v2::{
+            pool_fetching::BalancerContracts, BalancerFactoryKind, BalancerPoolFetcher,
+            BalancerPoolFetching,
+        },
         uniswap_v2::pool_cache::PoolCache,
         uniswap_v3::pool_fetching::UniswapV3PoolFetcher,
         BaselineSource, PoolAggregator,
     },
+    tenderly_api::TenderlyApi,
     token_info::{CachedTokenInfoFetcher, TokenInfoFetcher},
+    trace_many::create_tracer,
+    wrapped_native::WrappedNativeConfig,
     zeroex_api::DefaultZeroExApi,
 };
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 
 struct Liveness;
 #[async_trait::async_trait]
@@ -51,15 +102,104 @@ impl LivenessChecking for Liveness {
     }
 }
 
+/// Checks the node, the contracts, the database and the configured external APIs instead of
+/// starting autopilot.
+pub async fn run_preflight(args: &arguments::Arguments) -> ! {
+    let client = shared::http_client(args.shared.http_timeout);
+    let web3 = shared::web3(&client, &args.shared.node_url, "base");
+    let mut checks = Preflight::default();
+    checks.check_chain(&web3).await;
+    checks
+        .check(
+            "database",
+            preflight::database(async {
+                Ok(Postgres::new(args.db_url.as_str())
+                    .await?
+                    .missing_tables()
+                    .await?)
+            }),
+        )
+        .await;
+    let apis = [
+        ("tracing node", &args.tracing_node_url),
+        ("quasimodo solver", &args.quasimodo_solver_url),
+        ("yearn solver", &args.yearn_solver_url),
+        ("balancer sor", &args.balancer_sor_url),
+        ("tenderly", &args.tenderly_url),
+    ];
+    for (name, url) in apis {
+        if let Some(url) = url {
+            checks
+                .check(name, preflight::url_reachable(&client, url))
+                .await;
+        }
+    }
+    checks.exit()
+}
+
 /// Assumes tracing and metrics registry have already been set up.
 pub async fn main(args: arguments::Arguments) {
     let serve_metrics = shared::metrics::serve_metrics(Arc::new(Liveness), args.metrics_address);
 
-    let db = Postgres::new(args.db_url.as_str()).await.unwrap();
+    // Followers wait here without touching the database or cutting auctions until they become
+    // the leader.
+    let leader = match args.leader_election.leader_election_lock_key {
+        Some(key) => Some(
+            leader_election::acquire(
+                args.db_url.as_str(),
+                key,
+                args.leader_election.leader_election_interval,
+            )
+            .await,
+        ),
+        None => None,
+    };
+    let leadership = async move {
+        match leader {
+            Some(leader) => leader.retain().await,
+            None => std::future::pending().await,
+        }
+    };
+
+    let db =
+        Postgres::with_slow_query_threshold(args.db_url.as_str(), args.db_slow_query_threshold)
+            .await
+            .unwrap();
+    let applied = db
+        .ensure_schema(args.apply_database_migrations)
+        .await
+        .expect("database schema does not match this binary");
+    if !applied.is_empty() {
+        tracing::info!(?applied, "applied database migrations");
+    }
     let db_metrics = crate::database::database_metrics(db.clone());
 
     let client = shared::http_client(args.shared.http_timeout);
-    let web3 = shared::web3(&client, &args.shared.node_url, "base");
+    let mut web3 = shared::web3(&client, &args.shared.node_url, "base");
+    let mut tenderly_fork = None;
+    if let Some(tenderly_url) = &args.tenderly_url {
+        let api = TenderlyApi::new(
+            tenderly_url.clone(),
+            client.clone(),
+            args.tenderly_api_key
+                .as_deref()
+                .expect("tenderly fork requires an api key"),
+        )
+        .unwrap();
+        let (fork, fork_web3) = tenderly_fork::create(
+            &api,
+            &web3,
+            &client,
+            args.tenderly_fork_block,
+            &args.tenderly_fork_solvers,
+            &args.tenderly_fork_users,
+        )
+        .await
+        .expect("failed to set up tenderly fork");
+        tracing::info!(fork = %fork.id, rpc_url = %fork.rpc_url, "running against tenderly fork");
+        web3 = fork_web3;
+        tenderly_fork = Some((api, fork));
+    }
 
     let current_block_stream = shared::current_block::current_block_stream(
         web3.clone(),
@@ -130,13 +270,12 @@ pub async fn main(args: arguments::Arguments) {
             .expect("failed to get default baseline sources")
     });
     tracing::info!(?baseline_sources, "using baseline sources");
-    let (pair_providers, pool_fetchers): (Vec<_>, Vec<_>) =
+    let uniswap_like_sources =
         shared::sources::uniswap_like_liquidity_sources(&web3, &baseline_sources)
             .await
-            .expect("failed to load baseline source pair providers")
-            .values()
-            .cloned()
-            .unzip();
+            .expect("failed to load baseline source pair providers");
+    let (pair_providers, pool_fetchers): (Vec<_>, Vec<_>) =
+        uniswap_like_sources.values().cloned().unzip();
 
     let base_tokens = Arc::new(BaseTokens::new(
         native_token.address(),
@@ -145,7 +284,6 @@ pub async fn main(args: arguments::Arguments) {
     let mut allowed_tokens = args.allowed_tokens.clone();
     allowed_tokens.extend(base_tokens.tokens().iter().copied());
     allowed_tokens.push(model::order::BUY_ETH_ADDRESS);
-    let unsupported_tokens = args.unsupported_tokens.clone();
 
     let finder = token_owner_finder::init(
         &args.token_owner_finder,
@@ -160,26 +298,42 @@ pub async fn main(args: arguments::Arguments) {
     .await
     .expect("failed to initialize token owner finders");
 
-    let trace_call_detector = args.tracing_node_url.as_ref().map(|tracing_node_url| {
-        Box::new(CachingDetector::new(
+    let tracing_node = args
+        .tracing_node_url
+        .as_ref()
+        .map(|tracing_node_url| shared::web3(&client, tracing_node_url, "trace"));
+    let tracer = create_tracer(args.token_detection_backend, tracing_node, &web3);
+    let trace_call_detector = tracer.map(|tracer| {
+        Arc::new(CachingDetector::new(
             Box::new(TraceCallDetector {
-                web3: shared::web3(&client, tracing_node_url, "trace"),
+                web3: web3.clone(),
+                tracer,
                 finder,
                 settlement_contract: settlement_contract.address(),
             }),
             args.token_quality_cache_expiry,
-        ))
+        )) as Arc<dyn BadTokenDetecting>
     });
-    let bad_token_detector = Arc::new(
-        ListBasedDetector::new(
-            allowed_tokens,
+    let list_based_detector = move |unsupported_tokens: Vec<H160>| -> anyhow::Result<_> {
+        anyhow::ensure!(
+            unsupported_tokens
+                .iter()
+                .all(|token| !allowed_tokens.contains(token)),
+            "token is allowed and unsupported"
+        );
+        Ok(ListBasedDetector::new(
+            allowed_tokens.clone(),
             unsupported_tokens,
             trace_call_detector
-                .map(|detector| UnknownTokenStrategy::Forward(detector))
+                .clone()
+                .map(|detector| UnknownTokenStrategy::Forward(Box::new(detector)))
                 .unwrap_or(UnknownTokenStrategy::Allow),
-        )
-        .instrumented(),
+        ))
+    };
+    let token_list = Reloadable::new(
+        list_based_detector(args.unsupported_tokens.clone()).expect("invalid unsupported tokens"),
     );
+    let bad_token_detector = Arc::new(token_list.clone().instrumented());
 
     let pool_aggregator = PoolAggregator { pool_fetchers };
 
@@ -261,9 +415,9 @@ pub async fn main(args: arguments::Arguments) {
     let native_token_price_estimation_amount = args
         .amount_to_estimate_prices_with
         .or_else(|| {
-            shared::price_estimation::native::default_amount_to_estimate_native_prices_with(
-                &network,
-            )
+            WrappedNativeConfig::for_chain(chain_id)
+                .ok()
+                .map(|config| config.price_estimation_amount)
         })
         .expect("No amount to estimate prices with set.");
 
@@ -377,24 +531,57 @@ pub async fn main(args: arguments::Arguments) {
             native_token_price_estimation_amount,
         )),
         args.native_price_cache_max_age_secs,
+        args.native_price_cache_hot_tokens.clone(),
     ));
+    if !args.native_price_cache_hot_tokens.is_empty() {
+        // Only keep the hot tokens warm, other prices get estimated on demand.
+        native_price_estimator.spawn_maintenance_task(Duration::from_secs(1), Some(0));
+    }
 
+    let banned_users = Reloadable::new(args.banned_users.iter().copied().collect::<HashSet<_>>());
     let solvable_orders_cache = SolvableOrdersCache::new(
         args.min_order_validity_period,
         db.clone(),
-        args.banned_users.iter().copied().collect(),
+        banned_users.clone(),
         balance_fetcher.clone(),
         bad_token_detector.clone(),
         current_block_stream.clone(),
         native_price_estimator.clone(),
         signature_validator.clone(),
+        Arc::new(Web3ConditionEvaluator::new(web3.clone())),
+        Arc::new(Web3PresignatureChecker::new(settlement_contract.clone())),
         Duration::from_secs(2),
+        PriceSanityGuard::new(&args.price_sanity),
+        args.native_price_estimation_parallelism,
+        AuctionSizeController::new(db.clone(), &args.auction_size),
+        args.auction_cluster_size.map(|max_orders| {
+            AuctionClustering::new(
+                max_orders,
+                base_tokens.clone(),
+                balancer_pool_fetcher
+                    .clone()
+                    .map(|fetcher| fetcher as Arc<dyn BalancerPoolFetching>),
+            )
+        }),
+        SealedOrderRevealer::new(db.clone(), client.clone(), &args.sealed_orders),
     );
     let block = current_block_stream.borrow().number.unwrap().as_u64();
     solvable_orders_cache
         .update(block)
         .await
         .expect("failed to perform initial solvable orders update");
+    let event_consistency = Arc::new(EventConsistencyChecker::new(
+        db.clone(),
+        current_block_stream.clone(),
+        &args.event_consistency,
+    ));
+    if let Some(address) = args.api_address {
+        api::serve_api(
+            solvable_orders_cache.clone(),
+            event_consistency.clone(),
+            address,
+        );
+    }
 
     let sync_start = if args.skip_event_sync {
         web3.eth()
@@ -411,9 +598,73 @@ pub async fn main(args: arguments::Arguments) {
         sync_start,
     ));
 
+    let mut uniswap_v2_routers = HashMap::new();
+    for (source, (pair_provider, _)) in &uniswap_like_sources {
+        if let Some(router) = trade_routes::uniswap_like_router(&web3, *source)
+            .await
+            .expect("failed to load uniswap like router")
+        {
+            uniswap_v2_routers.insert(router, pair_provider.clone());
+        }
+    }
+    let uniswap_v3 = match (
+        &uniswapv3_factory,
+        UniswapV3SwapRouter::deployed(&web3).await,
+    ) {
+        (Some(factory), Ok(router)) => Some(UniswapV3Contracts {
+            router: router.address(),
+            factory: factory.address(),
+        }),
+        _ => None,
+    };
+    let trade_route_indexer = Arc::new(TradeRouteIndexer::new(
+        db.clone(),
+        web3.clone(),
+        RouteClassifier {
+            uniswap_v2_routers,
+            uniswap_v3,
+            balancer_vault: vault.as_ref().map(|vault| vault.address()),
+            aggregators: trade_routes::known_aggregators(chain_id),
+            native_token: native_token.address(),
+        },
+    ));
+
     let mut service_maintainer = shared::maintenance::ServiceMaintenance {
-        maintainers: vec![pool_fetcher, event_updater, Arc::new(db.clone())],
+        maintainers: vec![
+            pool_fetcher,
+            event_updater,
+            Arc::new(ExpiredQuoteRemover::new(db.clone(), args.quote_retention)),
+            trade_route_indexer,
+            Arc::new(OrderBookStatsUpdater::new(db.clone(), web3.clone())),
+            Arc::new(IntegratorFunnelUpdater::new(db.clone())),
+            Arc::new(AccountingUpdater::new(
+                db.clone(),
+                settlement_contract.clone(),
+            )),
+            Arc::new(SolverRewardsUpdater::new(
+                db.clone(),
+                RewardScheme::from(&args.solver_rewards),
+            )),
+            Arc::new(StuckOrderDetector::new(
+                db.clone(),
+                balance_fetcher.clone(),
+                bad_token_detector.clone(),
+                native_price_estimator.clone(),
+                &args.stuck_orders,
+            )),
+            event_consistency,
+        ],
     };
+    if let Some(start_block) = args.mev_rebates.mev_rebates_start_block {
+        service_maintainer
+            .maintainers
+            .push(Arc::new(MevRebateIndexer::new(
+                db.clone(),
+                web3.clone(),
+                start_block,
+                args.mev_rebates.mev_rebate_recipient,
+            )));
+    }
     if let Some(balancer) = balancer_pool_fetcher {
         service_maintainer.maintainers.push(balancer);
     }
@@ -423,9 +674,83 @@ pub async fn main(args: arguments::Arguments) {
     let maintenance_task =
         tokio::task::spawn(service_maintainer.run_maintenance_on_new_block(current_block_stream));
 
+    if let Some(path) = args.reload_config_file.clone() {
+        let unsupported_tokens = args.unsupported_tokens.clone();
+        let banned_user_list = args.banned_users.clone();
+        let apply = move |overrides: Overrides| {
+            let tokens = overrides
+                .unsupported_tokens
+                .clone()
+                .unwrap_or_else(|| unsupported_tokens.clone());
+            match list_based_detector(tokens) {
+                Ok(detector) => token_list.set(detector),
+                Err(err) => tracing::error!(?err, "not reloading unsupported tokens"),
+            }
+            banned_users.set(
+                overrides
+                    .banned_users
+                    .as_ref()
+                    .unwrap_or(&banned_user_list)
+                    .iter()
+                    .copied()
+                    .collect(),
+            );
+        };
+        tokio::task::spawn(reload_on_sighup(path, apply));
+    }
+
+    if !args.shadow_drivers.is_empty() {
+        let shadow_competition = ShadowCompetition::new(
+            db.clone(),
+            shared::http_client(args.shadow_driver_timeout),
+            args.shadow_drivers.clone(),
+            ScoringRules::from(&args.scoring),
+            SolveDeadline::from(&args.solve_deadline),
+            gas_price_estimator.clone(),
+        );
+        tokio::task::spawn(shadow_competition.run_forever(Duration::from_secs(1)));
+    }
+
     tokio::select! {
         result = serve_metrics => tracing::error!(?result, "serve_metrics exited"),
         _ = db_metrics => unreachable!(),
         _ = maintenance_task => unreachable!(),
+        _ = leadership => tracing::error!("lost leadership, stopping"),
+        _ = shutdown_signal() => tracing::info!("shutting down"),
     };
+
+    // Forks keep running until they get deleted so the rehearsal cleans up after itself.
+    if let Some((api, fork)) = tenderly_fork {
+        match api.delete_fork(&fork).await {
+            Ok(()) => tracing::info!(fork = %fork.id, "deleted tenderly fork"),
+            Err(err) => tracing::error!(fork = %fork.id, ?err, "failed to delete tenderly fork"),
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn shutdown_signal() {
+    // Intercept main signals for graceful shutdown
+    // Kubernetes sends sigterm, whereas locally sigint (ctrl-c) is most common
+    let sigterm = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .unwrap()
+            .recv()
+            .await
+    };
+    let sigint = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt())
+            .unwrap()
+            .recv()
+            .await;
+    };
+    futures::pin_mut!(sigint);
+    futures::pin_mut!(sigterm);
+    futures::future::select(sigterm, sigint).await;
+}
+
+#[cfg(windows)]
+async fn shutdown_signal() {
+    // We don't support signal handling on windows
+    std::future::pending().await
 }
@@ -8,6 +8,9 @@ async fn main() {
         args.shared.log_stderr_threshold,
     );
     tracing::info!("running autopilot with validated arguments:\n{}", args);
+    if args.shared.preflight {
+        autopilot::run_preflight(&args).await;
+    }
     global_metrics::setup_metrics_registry(Some("gp_v2_autopilot".into()), None);
     autopilot::main(args).await;
 }
@@ -0,0 +1,235 @@
+//! Indexes the rebates block builders pay for the order flow of settlements, for example through
+//! MEV-share style private relays, so that surplus accounting can include them.
+//!
+//! Builders pay rebates with transactions from the fee recipient of the block that includes the
+//! settlement to the rebate recipient. A rebate gets attributed to the auction of the settlement
+//! and is split evenly between the user orders the settlement executed.
+
+use crate::{database::Postgres, trade_routes::FailedAttempts};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use database::{
+    byte_array::ByteArray,
+    events::EventIndex,
+    settlement_rebates::{OrderRebate, UnprocessedSettlement},
+    OrderUid,
+};
+use number_conversions::u256_to_big_decimal;
+use primitive_types::{H160, U256};
+use shared::{arguments::display_option, maintenance::Maintaining, Web3};
+use std::fmt::{self, Display, Formatter};
+use web3::types::{Block, BlockId, BlockNumber, Transaction};
+
+// Limits the work per run when catching up on old settlements.
+const SETTLEMENTS_PER_RUN: i64 = 100;
+
+/// Solver competitions get stored after the settlement transaction has been mined so settlements
+/// only get processed once they are this old to be able to attribute them to their auction.
+fn processing_delay() -> chrono::Duration {
+    chrono::Duration::minutes(10)
+}
+
+/// Arguments related to MEV rebates.
+#[derive(clap::Parser)]
+pub struct Arguments {
+    /// Enables indexing the MEV rebates of settlements starting at this block.
+    #[clap(long, env)]
+    pub mev_rebates_start_block: Option<u64>,
+
+    /// The address builders pay MEV rebates to. Defaults to the solver account that submitted the
+    /// settlement.
+    #[clap(long, env)]
+    pub mev_rebate_recipient: Option<H160>,
+}
+
+impl Display for Arguments {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        display_option(f, "mev_rebates_start_block", &self.mev_rebates_start_block)?;
+        display_option(
+            f,
+            "mev_rebate_recipient",
+            &self.mev_rebate_recipient.map(|a| format!("{a:?}")),
+        )?;
+        Ok(())
+    }
+}
+
+/// Sums up the payments from the block's fee recipient, which is the builder of the block, to the
+/// rebate recipient.
+fn builder_payments(block: &Block<Transaction>, recipient: H160) -> U256 {
+    block
+        .transactions
+        .iter()
+        .filter(|tx| tx.from == Some(block.author) && tx.to == Some(recipient))
+        .fold(U256::zero(), |sum, tx| sum.saturating_add(tx.value))
+}
+
+/// Splits the rebate evenly between the orders. The remainder of the division goes to the first
+/// orders so that the shares add up to the rebate.
+fn attribute(rebate: U256, orders: &[OrderUid]) -> Vec<(OrderUid, U256)> {
+    if orders.is_empty() {
+        return Vec::new();
+    }
+    let (share, remainder) = rebate.div_mod(orders.len().into());
+    orders
+        .iter()
+        .enumerate()
+        .map(|(i, order)| {
+            let extra = if U256::from(i) < remainder { 1 } else { 0 };
+            (*order, share + extra)
+        })
+        .collect()
+}
+
+pub struct MevRebateIndexer {
+    db: Postgres,
+    web3: Web3,
+    start_block: u64,
+    recipient: Option<H160>,
+    failures: FailedAttempts,
+}
+
+impl MevRebateIndexer {
+    pub fn new(db: Postgres, web3: Web3, start_block: u64, recipient: Option<H160>) -> Self {
+        Self {
+            db,
+            web3,
+            start_block,
+            recipient,
+            failures: Default::default(),
+        }
+    }
+
+    fn recipient(&self, settlement: &UnprocessedSettlement) -> H160 {
+        self.recipient.unwrap_or(H160(settlement.solver.0))
+    }
+
+    async fn index_settlement(
+        &self,
+        index: &EventIndex,
+        settlement: &UnprocessedSettlement,
+    ) -> Result<()> {
+        let block = self
+            .web3
+            .eth()
+            .block_with_txs(BlockId::Number(BlockNumber::Number(
+                (settlement.block_number as u64).into(),
+            )))
+            .await?
+            .context("settlement block not found")?;
+        let recipient = self.recipient(settlement);
+        let rebate = builder_payments(&block, recipient);
+        let orders = if rebate.is_zero() {
+            Vec::new()
+        } else {
+            let orders = self.db.settled_user_order_uids(index).await?;
+            attribute(rebate, &orders)
+                .into_iter()
+                .map(|(order_uid, rebate)| OrderRebate {
+                    order_uid,
+                    rebate: u256_to_big_decimal(&rebate),
+                })
+                .collect()
+        };
+        tracing::debug!(?settlement, ?rebate, "indexing mev rebate");
+        self.db
+            .insert_settlement_rebate(
+                index,
+                &settlement.tx_hash,
+                &ByteArray(recipient.0),
+                &u256_to_big_decimal(&rebate),
+                &orders,
+            )
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl Maintaining for MevRebateIndexer {
+    async fn run_maintenance(&self) -> Result<()> {
+        let settlements = self
+            .db
+            .unprocessed_rebate_settlements(
+                self.start_block as i64,
+                Utc::now() - processing_delay(),
+                SETTLEMENTS_PER_RUN,
+            )
+            .await?;
+        for settlement in settlements {
+            let index = EventIndex {
+                block_number: settlement.block_number,
+                log_index: settlement.log_index,
+            };
+            let err = match self.index_settlement(&index, &settlement).await {
+                Ok(()) => {
+                    self.failures.succeeded(&index);
+                    continue;
+                }
+                Err(err) => err,
+            };
+            if !self.failures.failed(&index) {
+                tracing::warn!(
+                    ?settlement,
+                    ?err,
+                    "failed to index mev rebate, retrying later"
+                );
+                continue;
+            }
+            tracing::error!(
+                ?settlement,
+                ?err,
+                "failed to index mev rebate repeatedly, storing settlement without rebate"
+            );
+            self.db
+                .insert_settlement_rebate(
+                    &index,
+                    &settlement.tx_hash,
+                    &ByteArray(self.recipient(&settlement).0),
+                    &0.into(),
+                    &[],
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sums_builder_payments_to_recipient() {
+        let builder = H160([1; 20]);
+        let recipient = H160([2; 20]);
+        let tx = |from, to, value: u64| Transaction {
+            from: Some(H160([from; 20])),
+            to: Some(H160([to; 20])),
+            value: value.into(),
+            ..Default::default()
+        };
+        let block = Block {
+            author: builder,
+            transactions: vec![tx(1, 2, 10), tx(3, 2, 20), tx(1, 3, 40), tx(1, 2, 5)],
+            ..Default::default()
+        };
+
+        assert_eq!(builder_payments(&block, recipient), 15.into());
+        assert_eq!(builder_payments(&block, H160([4; 20])), 0.into());
+    }
+
+    #[test]
+    fn attributes_rebate_evenly() {
+        let orders = [ByteArray([1; 56]), ByteArray([2; 56]), ByteArray([3; 56])];
+        assert_eq!(
+            attribute(11.into(), &orders),
+            [
+                (orders[0], 4.into()),
+                (orders[1], 4.into()),
+                (orders[2], 3.into()),
+            ]
+        );
+        assert_eq!(attribute(3.into(), &orders[..1]), [(orders[0], 3.into())]);
+        assert!(attribute(3.into(), &[]).is_empty());
+    }
+}
@@ -0,0 +1,62 @@
+//! Keeps the rolling order book statistics served by the orderbook api up to date.
+
+use crate::database::Postgres;
+use anyhow::{Context, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use shared::{maintenance::Maintaining, Web3};
+use web3::types::BlockId;
+
+// Limits the node requests per run when catching up on old settlements.
+const BLOCKS_PER_RUN: i64 = 100;
+
+pub struct OrderBookStatsUpdater {
+    db: Postgres,
+    web3: Web3,
+}
+
+impl OrderBookStatsUpdater {
+    pub fn new(db: Postgres, web3: Web3) -> Self {
+        Self { db, web3 }
+    }
+
+    async fn block_timestamp(&self, block_number: i64) -> Result<DateTime<Utc>> {
+        let block = self
+            .web3
+            .eth()
+            .block(BlockId::Number((block_number as u64).into()))
+            .await?
+            .context("block not found")?;
+        Ok(timestamp(block.timestamp.as_u64()))
+    }
+}
+
+fn timestamp(seconds: u64) -> DateTime<Utc> {
+    Utc.timestamp(seconds as i64, 0)
+}
+
+#[async_trait::async_trait]
+impl Maintaining for OrderBookStatsUpdater {
+    async fn run_maintenance(&self) -> Result<()> {
+        for block_number in self.db.blocks_without_timestamp(BLOCKS_PER_RUN).await? {
+            let timestamp = self
+                .block_timestamp(block_number)
+                .await
+                .with_context(|| format!("failed to fetch timestamp of block {block_number}"))?;
+            self.db.set_block_timestamp(block_number, timestamp).await?;
+        }
+        self.db.refresh_order_book_stats().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_block_timestamp() {
+        assert_eq!(
+            timestamp(1_660_000_000).to_rfc3339(),
+            "2022-08-08T23:06:40+00:00"
+        );
+    }
+}
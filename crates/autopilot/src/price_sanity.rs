@@ -0,0 +1,296 @@
+//! Cross-checks the native prices of an auction against the prices of the previous auctions before
+//! the auction gets published.
+//!
+//! Broken price estimates, for example from an oracle that got the decimals of a token wrong, are
+//! usually off by orders of magnitude and lead to nonsensical objective values. Prices that deviate
+//! from the median of a token's recent prices by more than a configurable factor get clamped, have
+//! their token dropped from the auction or block the auction altogether.
+//!
+//! The history records the prices as estimated so that a token whose price legitimately moves by a
+//! lot is accepted again once the new price makes up the majority of the history.
+
+use anyhow::{anyhow, Result};
+use model::order::Order;
+use primitive_types::{H160, U256};
+use prometheus::IntCounterVec;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fmt::{self, Display, Formatter},
+    sync::Mutex,
+};
+
+/// Arguments related to the price sanity guard.
+#[derive(clap::Parser)]
+pub struct Arguments {
+    /// The number of previous auctions whose prices of a token the prices of new auctions get
+    /// compared against. 0 disables the guard.
+    #[clap(long, env, default_value = "10")]
+    pub price_sanity_history: usize,
+
+    /// The factor by which a price may deviate from the median of the token's previous prices in
+    /// either direction.
+    #[clap(long, env, default_value = "100")]
+    pub price_sanity_max_deviation: f64,
+
+    /// What happens to prices that deviate too much.
+    #[clap(long, env, default_value = "drop", arg_enum)]
+    pub price_sanity_action: PriceSanityAction,
+}
+
+impl Display for Arguments {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "price_sanity_history: {}", self.price_sanity_history)?;
+        writeln!(
+            f,
+            "price_sanity_max_deviation: {}",
+            self.price_sanity_max_deviation
+        )?;
+        writeln!(f, "price_sanity_action: {:?}", self.price_sanity_action)?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ArgEnum)]
+pub enum PriceSanityAction {
+    /// Limit the price to the maximum deviation.
+    Clamp,
+    /// Remove the token and all orders trading it from the auction.
+    Drop,
+    /// Don't publish the auction.
+    Block,
+}
+
+#[derive(prometheus_metric_storage::MetricStorage)]
+#[metric(subsystem = "price_sanity")]
+struct Metrics {
+    /// Prices that deviated too much from the token's previous prices by action taken.
+    #[metric(labels("action"))]
+    deviating_prices: IntCounterVec,
+}
+
+impl Metrics {
+    fn get() -> &'static Self {
+        Metrics::instance(global_metrics::get_metric_storage_registry()).unwrap()
+    }
+}
+
+pub struct PriceSanityGuard {
+    history_len: usize,
+    max_deviation: f64,
+    action: PriceSanityAction,
+    history: Mutex<HashMap<H160, VecDeque<U256>>>,
+}
+
+impl Default for PriceSanityGuard {
+    /// A disabled guard.
+    fn default() -> Self {
+        Self {
+            history_len: 0,
+            max_deviation: f64::INFINITY,
+            action: PriceSanityAction::Drop,
+            history: Default::default(),
+        }
+    }
+}
+
+impl PriceSanityGuard {
+    pub fn new(args: &Arguments) -> Self {
+        Self {
+            history_len: args.price_sanity_history,
+            max_deviation: args.price_sanity_max_deviation,
+            action: args.price_sanity_action,
+            history: Default::default(),
+        }
+    }
+
+    /// Checks the prices of an auction and records them in the history. Returns the orders and
+    /// prices to publish or an error if the auction should be blocked.
+    pub fn check(
+        &self,
+        mut orders: Vec<Order>,
+        mut prices: BTreeMap<H160, U256>,
+    ) -> Result<(Vec<Order>, BTreeMap<H160, U256>)> {
+        if self.history_len == 0 {
+            return Ok((orders, prices));
+        }
+
+        let mut history = self.history.lock().unwrap();
+        let mut dropped_tokens = HashSet::new();
+        let mut blocking_tokens = Vec::new();
+        for (token, price) in prices.iter_mut() {
+            let previous = history.entry(*token).or_default();
+            let reference = median(previous);
+            if previous.len() == self.history_len {
+                previous.pop_front();
+            }
+            previous.push_back(*price);
+
+            let (min, max) = match reference {
+                Some(reference) => self.bounds(reference),
+                None => continue,
+            };
+            if min <= *price && *price <= max {
+                continue;
+            }
+
+            tracing::warn!(
+                ?token,
+                %price,
+                ?reference,
+                action = ?self.action,
+                "native price deviates too much from previous auctions"
+            );
+            Metrics::get()
+                .deviating_prices
+                .with_label_values(&[self.action_label()])
+                .inc();
+            match self.action {
+                PriceSanityAction::Clamp => *price = (*price).clamp(min, max),
+                PriceSanityAction::Drop => {
+                    dropped_tokens.insert(*token);
+                }
+                PriceSanityAction::Block => blocking_tokens.push(*token),
+            }
+        }
+        drop(history);
+
+        if !blocking_tokens.is_empty() {
+            return Err(anyhow!(
+                "blocked auction because of deviating native prices of {:?}",
+                blocking_tokens
+            ));
+        }
+        if !dropped_tokens.is_empty() {
+            orders.retain(|order| {
+                !dropped_tokens.contains(&order.data.sell_token)
+                    && !dropped_tokens.contains(&order.data.buy_token)
+            });
+            // Orders that traded a dropped token may have been the only ones using some of the
+            // other prices.
+            let used_tokens = orders
+                .iter()
+                .flat_map(|order| [order.data.sell_token, order.data.buy_token])
+                .collect::<HashSet<_>>();
+            prices.retain(|token, _| used_tokens.contains(token));
+        }
+        Ok((orders, prices))
+    }
+
+    /// The range of prices that is accepted for a token with the specified reference price.
+    fn bounds(&self, reference: U256) -> (U256, U256) {
+        let reference = reference.to_f64_lossy();
+        (
+            U256::from_f64_lossy(reference / self.max_deviation),
+            U256::from_f64_lossy(reference * self.max_deviation),
+        )
+    }
+
+    fn action_label(&self) -> &'static str {
+        match self.action {
+            PriceSanityAction::Clamp => "clamp",
+            PriceSanityAction::Drop => "drop",
+            PriceSanityAction::Block => "block",
+        }
+    }
+}
+
+fn median(prices: &VecDeque<U256>) -> Option<U256> {
+    let mut prices = prices.iter().copied().collect::<Vec<_>>();
+    prices.sort_unstable();
+    prices.get(prices.len() / 2).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::btreemap;
+    use model::order::OrderData;
+
+    fn guard(action: PriceSanityAction) -> PriceSanityGuard {
+        PriceSanityGuard::new(&Arguments {
+            price_sanity_history: 3,
+            price_sanity_max_deviation: 100.,
+            price_sanity_action: action,
+        })
+    }
+
+    fn order(sell_token: u8, buy_token: u8) -> Order {
+        Order {
+            data: OrderData {
+                sell_token: H160([sell_token; 20]),
+                buy_token: H160([buy_token; 20]),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn orders() -> Vec<Order> {
+        vec![order(1, 2), order(2, 3), order(1, 3)]
+    }
+
+    fn prices(price: u64) -> BTreeMap<H160, U256> {
+        btreemap! {
+            H160([1; 20]) => U256::exp10(18),
+            H160([2; 20]) => price.into(),
+            H160([3; 20]) => U256::exp10(18),
+        }
+    }
+
+    #[test]
+    fn drops_tokens_with_deviating_prices() {
+        let guard = guard(PriceSanityAction::Drop);
+        for price in [1_000, 1_100, 900] {
+            let (orders, _) = guard.check(orders(), prices(price)).unwrap();
+            assert_eq!(orders.len(), 3);
+        }
+
+        let (orders, prices) = guard.check(orders(), prices(1_000_000)).unwrap();
+        assert_eq!(orders, [order(1, 3)]);
+        assert_eq!(
+            prices.keys().copied().collect::<Vec<_>>(),
+            [H160([1; 20]), H160([3; 20])]
+        );
+
+        // Prices within the deviation are accepted.
+        let (orders, _) = guard.check(orders(), prices(99_000)).unwrap();
+        assert_eq!(orders.len(), 3);
+    }
+
+    #[test]
+    fn clamps_deviating_prices() {
+        let guard = guard(PriceSanityAction::Clamp);
+        guard.check(orders(), prices(1_000)).unwrap();
+
+        let (_, prices) = guard.check(orders(), prices(1)).unwrap();
+        assert_eq!(prices[&H160([2; 20])], 10.into());
+    }
+
+    #[test]
+    fn blocks_auctions_with_deviating_prices() {
+        let guard = guard(PriceSanityAction::Block);
+        guard.check(orders(), prices(1_000)).unwrap();
+        assert!(guard.check(orders(), prices(1_000_000)).is_err());
+    }
+
+    #[test]
+    fn accepts_lasting_price_changes() {
+        let guard = guard(PriceSanityAction::Drop);
+        for price in [1_000, 1_000, 1_000] {
+            guard.check(orders(), prices(price)).unwrap();
+        }
+        // Once the new price makes up the majority of the history it is accepted again.
+        for expected_orders in [1, 1, 3] {
+            let (orders, _) = guard.check(orders(), prices(1_000_000)).unwrap();
+            assert_eq!(orders.len(), expected_orders);
+        }
+    }
+
+    #[test]
+    fn disabled_guard_accepts_everything() {
+        let guard = PriceSanityGuard::default();
+        guard.check(orders(), prices(1)).unwrap();
+        let (orders, _) = guard.check(orders(), prices(u64::MAX)).unwrap();
+        assert_eq!(orders.len(), 3);
+    }
+}
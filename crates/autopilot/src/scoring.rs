@@ -0,0 +1,268 @@
+//! Configurable rules for turning a driver's solution into the score it gets ranked by.
+//!
+//! The raw objective, surplus minus gas costs, can be adjusted without code changes: costs and
+//! scores can be capped, settling orders that have been waiting for a long time can be rewarded
+//! and individual solvers' surplus can be normalized. The rules that were applied get recorded
+//! with every competition result so that scores stay comparable when the rules change.
+
+use crate::shadow_competition::Solution;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use model::{auction::Auction, order::OrderUid};
+use serde::Serialize;
+use shared::arguments::{display_option, duration_from_seconds};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+    time::Duration,
+};
+
+/// Arguments related to scoring solutions.
+#[derive(clap::Parser)]
+pub struct Arguments {
+    /// The factor the surplus of solutions gets multiplied with.
+    #[clap(long, env, default_value = "1")]
+    pub scoring_surplus_factor: f64,
+
+    /// The maximum gas costs in native token that get deducted from the surplus.
+    #[clap(long, env)]
+    pub scoring_max_cost: Option<f64>,
+
+    /// The maximum score a solution can get.
+    #[clap(long, env)]
+    pub scoring_max_score: Option<f64>,
+
+    /// The bonus in native token a solution gets for every order it settles that is older than
+    /// `scoring_waiting_order_age`.
+    #[clap(long, env, default_value = "0")]
+    pub scoring_waiting_order_bonus: f64,
+
+    /// The age in seconds from which on settling an order earns the waiting order bonus.
+    #[clap(
+        long,
+        env,
+        default_value = "300",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub scoring_waiting_order_age: Duration,
+
+    /// Factors the surplus of individual solvers gets multiplied with in addition to the general
+    /// surplus factor, for example to normalize solvers that are known to over report their
+    /// surplus. Format: name|factor.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub scoring_solver_factors: Vec<SolverFactorArg>,
+}
+
+impl Display for Arguments {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "scoring_surplus_factor: {}", self.scoring_surplus_factor)?;
+        display_option(f, "scoring_max_cost", &self.scoring_max_cost)?;
+        display_option(f, "scoring_max_score", &self.scoring_max_score)?;
+        writeln!(
+            f,
+            "scoring_waiting_order_bonus: {}",
+            self.scoring_waiting_order_bonus
+        )?;
+        writeln!(
+            f,
+            "scoring_waiting_order_age: {:?}",
+            self.scoring_waiting_order_age
+        )?;
+        writeln!(
+            f,
+            "scoring_solver_factors: {:?}",
+            self.scoring_solver_factors
+        )?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SolverFactorArg {
+    pub name: String,
+    pub factor: f64,
+}
+
+impl FromStr for SolverFactorArg {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, factor) = s.split_once('|').ok_or_else(|| anyhow!("missing factor"))?;
+        Ok(Self {
+            name: name.to_string(),
+            factor: factor.parse().context("parse factor")?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoringRules {
+    pub surplus_factor: f64,
+    pub max_cost: Option<f64>,
+    pub max_score: Option<f64>,
+    pub waiting_order_bonus: f64,
+    pub waiting_order_age_secs: u64,
+    pub solver_factors: HashMap<String, f64>,
+}
+
+impl Default for ScoringRules {
+    /// The raw objective.
+    fn default() -> Self {
+        Self {
+            surplus_factor: 1.,
+            max_cost: None,
+            max_score: None,
+            waiting_order_bonus: 0.,
+            waiting_order_age_secs: 0,
+            solver_factors: Default::default(),
+        }
+    }
+}
+
+impl From<&Arguments> for ScoringRules {
+    fn from(args: &Arguments) -> Self {
+        Self {
+            surplus_factor: args.scoring_surplus_factor,
+            max_cost: args.scoring_max_cost,
+            max_score: args.scoring_max_score,
+            waiting_order_bonus: args.scoring_waiting_order_bonus,
+            waiting_order_age_secs: args.scoring_waiting_order_age.as_secs(),
+            solver_factors: args
+                .scoring_solver_factors
+                .iter()
+                .map(|arg| (arg.name.clone(), arg.factor))
+                .collect(),
+        }
+    }
+}
+
+impl ScoringRules {
+    /// Scores a solution of the solver that is known to only settle orders of the auction.
+    pub fn score(
+        &self,
+        solver: &str,
+        auction: &Auction,
+        solution: &Solution,
+        now: DateTime<Utc>,
+    ) -> f64 {
+        let surplus_factor =
+            self.surplus_factor * self.solver_factors.get(solver).copied().unwrap_or(1.);
+        let cost = solution.gas_reimbursement.to_f64_lossy();
+        let cost = self.max_cost.map_or(cost, |max_cost| cost.min(max_cost));
+        let bonus = self.waiting_order_bonus * self.waiting_orders(auction, solution, now) as f64;
+        let score = solution.surplus * surplus_factor - cost + bonus;
+        self.max_score
+            .map_or(score, |max_score| score.min(max_score))
+    }
+
+    fn waiting_orders(&self, auction: &Auction, solution: &Solution, now: DateTime<Utc>) -> usize {
+        if self.waiting_order_bonus == 0. {
+            return 0;
+        }
+        let settled = solution
+            .settled_orders
+            .iter()
+            .collect::<HashSet<&OrderUid>>();
+        let min_age = chrono::Duration::seconds(self.waiting_order_age_secs as i64);
+        auction
+            .orders
+            .iter()
+            .filter(|order| settled.contains(&order.metadata.uid))
+            .filter(|order| now - order.metadata.creation_date >= min_age)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+    use model::order::{Order, OrderMetadata};
+
+    fn solution(surplus: f64, gas_reimbursement: u64, settled_orders: Vec<OrderUid>) -> Solution {
+        Solution {
+            surplus,
+            gas_reimbursement: gas_reimbursement.into(),
+            settled_orders,
+            auction_id: 0,
+            deadline: None,
+        }
+    }
+
+    #[test]
+    fn parse_solver_factor_arg() {
+        assert_eq!(
+            "solver|0.5".parse::<SolverFactorArg>().unwrap(),
+            SolverFactorArg {
+                name: "solver".to_string(),
+                factor: 0.5,
+            }
+        );
+        assert!("solver".parse::<SolverFactorArg>().is_err());
+        assert!("solver|half".parse::<SolverFactorArg>().is_err());
+    }
+
+    #[test]
+    fn default_rules_are_raw_objective() {
+        let rules = ScoringRules::default();
+        let score = rules.score(
+            "solver",
+            &Default::default(),
+            &solution(3000., 1000, vec![]),
+            Utc::now(),
+        );
+        assert_eq!(score, 2000.);
+    }
+
+    #[test]
+    fn applies_caps_and_solver_factors() {
+        let rules = ScoringRules {
+            surplus_factor: 2.,
+            max_cost: Some(500.),
+            max_score: Some(10000.),
+            solver_factors: HashMap::from([("normalized".to_string(), 0.5)]),
+            ..Default::default()
+        };
+        let auction = Default::default();
+        let now = Utc::now();
+        assert_eq!(
+            rules.score("solver", &auction, &solution(3000., 1000, vec![]), now),
+            5500.
+        );
+        assert_eq!(
+            rules.score("normalized", &auction, &solution(3000., 1000, vec![]), now),
+            2500.
+        );
+        assert_eq!(
+            rules.score("solver", &auction, &solution(6000., 1000, vec![]), now),
+            10000.
+        );
+    }
+
+    #[test]
+    fn rewards_waiting_orders() {
+        let rules = ScoringRules {
+            waiting_order_bonus: 100.,
+            waiting_order_age_secs: 60,
+            ..Default::default()
+        };
+        let order = |uid: u8, created: i64| Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([uid; 56]),
+                creation_date: DateTime::from_utc(NaiveDateTime::from_timestamp(created, 0), Utc),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let auction = Auction {
+            orders: vec![order(1, 0), order(2, 100), order(3, 0)],
+            ..Default::default()
+        };
+        let now = DateTime::from_utc(NaiveDateTime::from_timestamp(120, 0), Utc);
+        let solution = solution(1000., 0, vec![OrderUid([1; 56]), OrderUid([2; 56])]);
+        // Only the first order is old enough, the third is not settled.
+        assert_eq!(rules.score("solver", &auction, &solution, now), 1100.);
+    }
+}
@@ -0,0 +1,200 @@
+//! Reveals sealed orders when an auction gets cut.
+//!
+//! Users can submit orders encrypted to the key of the autopilot so that nobody learns what they
+//! trade before the order can be settled. The orderbook only stores the sealed orders. Right
+//! before the solvable orders of a new auction are loaded, all pending sealed orders get decrypted
+//! and placed through the orderbook's regular order endpoint, which validates them like any other
+//! order. Orders that can't be decrypted or fail validation get rejected with the reason so that
+//! their submitters can find out.
+
+use crate::database::Postgres;
+use anyhow::{anyhow, Context, Result};
+use database::sealed_orders::SealedOrder;
+use model::{
+    order::{OrderCreation, OrderUid},
+    sealed_order,
+};
+use prometheus::IntCounterVec;
+use reqwest::StatusCode;
+use secp256k1::SecretKey;
+use shared::arguments::display_secret_option;
+use std::fmt::{self, Display, Formatter};
+use url::Url;
+
+/// Arguments related to sealed orders.
+#[derive(clap::Parser)]
+pub struct Arguments {
+    /// The secret key in hex that users seal orders to. Its public key has to be configured in the
+    /// orderbook. Sealed orders are not revealed if not set.
+    #[clap(long, env)]
+    pub sealed_order_secret_key: Option<SecretKey>,
+
+    /// The orderbook revealed sealed orders get placed with.
+    #[clap(long, env, default_value = "http://localhost:8080")]
+    pub sealed_order_orderbook_url: Url,
+}
+
+impl Display for Arguments {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        display_secret_option(f, "sealed_order_secret_key", &self.sealed_order_secret_key)?;
+        writeln!(
+            f,
+            "sealed_order_orderbook_url: {}",
+            self.sealed_order_orderbook_url
+        )?;
+        Ok(())
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage)]
+#[metric(subsystem = "sealed_orders")]
+struct Metrics {
+    /// Sealed orders by what happened when revealing them.
+    #[metric(labels("result"))]
+    reveals: IntCounterVec,
+}
+
+impl Metrics {
+    fn get() -> &'static Self {
+        Metrics::instance(global_metrics::get_metric_storage_registry()).unwrap()
+    }
+}
+
+/// What placing a revealed order resulted in.
+#[derive(Debug, Eq, PartialEq)]
+enum Outcome {
+    Placed(OrderUid),
+    /// The order is invalid and won't be retried.
+    Rejected(String),
+}
+
+pub struct SealedOrderRevealer {
+    db: Postgres,
+    client: reqwest::Client,
+    key: SecretKey,
+    orders_url: Url,
+}
+
+impl SealedOrderRevealer {
+    /// Returns `None` if sealed orders are disabled.
+    pub fn new(db: Postgres, client: reqwest::Client, args: &Arguments) -> Option<Self> {
+        Some(Self {
+            db,
+            client,
+            key: args.sealed_order_secret_key?,
+            orders_url: args
+                .sealed_order_orderbook_url
+                .join("api/v1/orders")
+                .expect("invalid orderbook url"),
+        })
+    }
+
+    /// Reveals and places all pending sealed orders. Orders whose placement failed for reasons
+    /// unrelated to the order, like the orderbook being unreachable, stay pending and get retried
+    /// with the next auction.
+    pub async fn reveal(&self) -> Result<()> {
+        let pending = self.db.pending_sealed_orders().await?;
+        if pending.is_empty() {
+            return Ok(());
+        }
+        let results =
+            futures::future::join_all(pending.iter().map(|order| self.reveal_order(order))).await;
+        let metrics = Metrics::get();
+        for (order, result) in pending.iter().zip(results) {
+            let id = order.id;
+            let label = match result {
+                Ok(Outcome::Placed(uid)) => {
+                    tracing::debug!(?id, %uid, "revealed sealed order");
+                    self.db.mark_sealed_order_revealed(&id, &uid).await?;
+                    "placed"
+                }
+                Ok(Outcome::Rejected(reason)) => {
+                    tracing::debug!(?id, %reason, "rejected sealed order");
+                    self.db.mark_sealed_order_rejected(&id, &reason).await?;
+                    "rejected"
+                }
+                Err(err) => {
+                    tracing::warn!(?id, ?err, "failed to place revealed order");
+                    "failed"
+                }
+            };
+            metrics.reveals.with_label_values(&[label]).inc();
+        }
+        Ok(())
+    }
+
+    async fn reveal_order(&self, order: &SealedOrder) -> Result<Outcome> {
+        let creation = match decrypt(&self.key, &order.sealed) {
+            Ok(creation) => creation,
+            Err(err) => return Ok(Outcome::Rejected(format!("{:#}", err))),
+        };
+        let response = self
+            .client
+            .post(self.orders_url.clone())
+            .json(&creation)
+            .send()
+            .await?;
+        let status = response.status();
+        let body = response.text().await?;
+        outcome(status, &body)
+    }
+}
+
+fn decrypt(key: &SecretKey, sealed: &[u8]) -> Result<OrderCreation> {
+    let plaintext = sealed_order::unseal(key, sealed).context("decryption failed")?;
+    serde_json::from_slice(&plaintext).context("invalid order")
+}
+
+/// Client errors mean that the order is invalid, everything else including being rate limited can
+/// succeed when retried.
+fn outcome(status: StatusCode, body: &str) -> Result<Outcome> {
+    if status.is_success() {
+        let uid = serde_json::from_str(body).with_context(|| format!("bad order uid {body:?}"))?;
+        Ok(Outcome::Placed(uid))
+    } else if status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS {
+        Ok(Outcome::Rejected(body.to_string()))
+    } else {
+        Err(anyhow!("orderbook responded with {}: {}", status, body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::{PublicKey, Secp256k1};
+
+    #[test]
+    fn decrypts_sealed_orders() {
+        let key = SecretKey::from_slice(&[1; 32]).unwrap();
+        let public_key = PublicKey::from_secret_key(&Secp256k1::signing_only(), &key);
+        let ephemeral = SecretKey::from_slice(&[2; 32]).unwrap();
+        let creation = OrderCreation::default();
+
+        let sealed = sealed_order::seal(
+            &public_key,
+            &ephemeral,
+            &serde_json::to_vec(&creation).unwrap(),
+        );
+        assert_eq!(decrypt(&key, &sealed).unwrap(), creation);
+
+        let other = SecretKey::from_slice(&[3; 32]).unwrap();
+        assert!(decrypt(&other, &sealed).is_err());
+        let not_an_order = sealed_order::seal(&public_key, &ephemeral, b"{}");
+        assert!(decrypt(&key, &not_an_order).is_err());
+    }
+
+    #[test]
+    fn classifies_orderbook_responses() {
+        let uid = OrderUid([1; 56]);
+        assert_eq!(
+            outcome(StatusCode::CREATED, &serde_json::to_string(&uid).unwrap()).unwrap(),
+            Outcome::Placed(uid)
+        );
+        assert_eq!(
+            outcome(StatusCode::BAD_REQUEST, "InsufficientFee").unwrap(),
+            Outcome::Rejected("InsufficientFee".to_string())
+        );
+        assert!(outcome(StatusCode::TOO_MANY_REQUESTS, "").is_err());
+        assert!(outcome(StatusCode::SERVICE_UNAVAILABLE, "").is_err());
+    }
+}
@@ -0,0 +1,288 @@
+//! Shadow competitions send every auction to an additional set of drivers. Their solutions get
+//! simulated by the drivers and scored like regular solutions but are never executed. The results
+//! are recorded so that new solvers can be evaluated on production auctions before they are allowed
+//! to settle.
+
+use crate::{database::Postgres, scoring::ScoringRules, solve_deadline::SolveDeadline};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use chrono::{DateTime, Utc};
+use database::shadow_competition::ShadowResult;
+use gas_estimation::GasPriceEstimating;
+use model::{
+    auction::{AuctionId, AuctionWithId, SolveRequest},
+    order::OrderUid,
+    u256_decimal,
+};
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{collections::HashSet, str::FromStr, sync::Arc, time::Duration};
+use url::Url;
+
+#[derive(Clone, Debug)]
+pub struct ShadowDriverArg {
+    pub name: String,
+    /// The base url of the driver's api for the solver, the auction gets posted to `{url}/solve`.
+    pub url: Url,
+}
+
+impl FromStr for ShadowDriverArg {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, url) = s.split_once('|').ok_or_else(|| anyhow!("missing url"))?;
+        Ok(Self {
+            name: name.to_string(),
+            url: url.parse().context("parse url")?,
+        })
+    }
+}
+
+/// The solution summary a driver responds with. Mirrors the driver's `SettlementSummary`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Solution {
+    /// Surplus in native token based on the auction's prices.
+    pub surplus: f64,
+    /// The gas costs in native token the solver wants to get reimbursed.
+    #[serde(with = "u256_decimal")]
+    pub gas_reimbursement: U256,
+    pub settled_orders: Vec<OrderUid>,
+    pub auction_id: AuctionId,
+    /// The deadline the driver was able to solve until.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<DateTime<Utc>>,
+}
+
+pub struct ShadowCompetition {
+    db: Postgres,
+    client: reqwest::Client,
+    drivers: Vec<ShadowDriverArg>,
+    rules: ScoringRules,
+    deadline: SolveDeadline,
+    gas_price_estimator: Arc<dyn GasPriceEstimating>,
+}
+
+impl ShadowCompetition {
+    /// The client's timeout bounds how long a driver can take to solve an auction.
+    pub fn new(
+        db: Postgres,
+        client: reqwest::Client,
+        drivers: Vec<ShadowDriverArg>,
+        rules: ScoringRules,
+        deadline: SolveDeadline,
+        gas_price_estimator: Arc<dyn GasPriceEstimating>,
+    ) -> Self {
+        Self {
+            db,
+            client,
+            drivers,
+            rules,
+            deadline,
+            gas_price_estimator,
+        }
+    }
+
+    /// Runs a shadow competition for every new auction. Auctions that get created while a
+    /// competition is still running are skipped.
+    pub async fn run_forever(self, poll_interval: Duration) -> ! {
+        let mut last_auction = None;
+        loop {
+            match self.db.most_recent_auction().await {
+                Ok(Some(auction)) if Some(auction.id) != last_auction => {
+                    last_auction = Some(auction.id);
+                    self.run(&auction).await;
+                }
+                Ok(_) => (),
+                Err(err) => tracing::error!(?err, "failed to load auction for shadow competition"),
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    async fn run(&self, auction: &AuctionWithId) {
+        let gas_price = match self.gas_price_estimator.estimate().await {
+            Ok(gas_price) => gas_price.effective_gas_price(),
+            Err(err) => {
+                tracing::warn!(?err, "failed to estimate gas price for solve deadline");
+                0.
+            }
+        };
+        let request = SolveRequest {
+            auction: auction.clone(),
+            deadline: Some(
+                self.deadline
+                    .deadline(&auction.auction, gas_price, Utc::now()),
+            ),
+        };
+        let solutions = futures::future::join_all(
+            self.drivers
+                .iter()
+                .map(|driver| self.solve(driver, &request)),
+        )
+        .await;
+        let now = Utc::now();
+        for (driver, solution) in self.drivers.iter().zip(solutions) {
+            let scored = solution.and_then(|solution| {
+                if solution.deadline > request.deadline {
+                    tracing::warn!(
+                        driver = %driver.name,
+                        requested = ?request.deadline,
+                        achievable = ?solution.deadline,
+                        "driver can't meet the deadline"
+                    );
+                }
+                let score = score(&self.rules, &driver.name, auction, &solution, now)?;
+                Ok((score, solution))
+            });
+            let (score, json) = match scored {
+                Ok((score, solution)) => (
+                    Some(score),
+                    json!({
+                        "solution": solution,
+                        "scoringRules": self.rules,
+                        "deadline": request.deadline,
+                    }),
+                ),
+                Err(err) => {
+                    tracing::debug!(driver = %driver.name, ?err, "shadow driver failed");
+                    (None, json!({ "error": format!("{:?}", err) }))
+                }
+            };
+            tracing::debug!(driver = %driver.name, auction_id = auction.id, ?score, "shadow solution");
+            let result = ShadowResult {
+                auction_id: auction.id,
+                driver: driver.name.clone(),
+                creation_timestamp: Utc::now(),
+                score,
+                json,
+            };
+            if let Err(err) = self.db.save_shadow_result(&result).await {
+                tracing::error!(?err, "failed to save shadow competition result");
+            }
+        }
+    }
+
+    async fn solve(&self, driver: &ShadowDriverArg, request: &SolveRequest) -> Result<Solution> {
+        let url = format!("{}/solve", driver.url.as_str().trim_end_matches('/'));
+        let response = self.client.post(url).json(request).send().await?;
+        let status = response.status();
+        let body = response.text().await?;
+        ensure!(
+            status.is_success(),
+            "driver responded with {}: {}",
+            status,
+            body
+        );
+        serde_json::from_str(&body).with_context(|| format!("bad solution {:?}", body))
+    }
+}
+
+/// Verifies that the solution belongs to the auction and scores it according to the rules.
+fn score(
+    rules: &ScoringRules,
+    driver: &str,
+    auction: &AuctionWithId,
+    solution: &Solution,
+    now: DateTime<Utc>,
+) -> Result<f64> {
+    ensure!(
+        solution.auction_id == auction.id,
+        "solution is for auction {}",
+        solution.auction_id
+    );
+    let orders = auction
+        .auction
+        .orders
+        .iter()
+        .map(|order| order.metadata.uid)
+        .collect::<HashSet<_>>();
+    if let Some(uid) = solution
+        .settled_orders
+        .iter()
+        .find(|uid| !orders.contains(uid))
+    {
+        bail!("settled order {} is not part of the auction", uid);
+    }
+    Ok(rules.score(driver, &auction.auction, solution, now))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use model::{
+        auction::Auction,
+        order::{Order, OrderMetadata},
+    };
+
+    #[test]
+    fn parse_shadow_driver_arg() {
+        let arg = "name|http://driver.com/api/name"
+            .parse::<ShadowDriverArg>()
+            .unwrap();
+        assert_eq!(arg.name, "name");
+        assert_eq!(arg.url.as_str(), "http://driver.com/api/name");
+        assert!("http://driver.com".parse::<ShadowDriverArg>().is_err());
+    }
+
+    #[test]
+    fn deserializes_driver_settlement_summary() {
+        let solution: Solution = serde_json::from_value(json!({
+            "surplus": 1.5,
+            "gas_reimbursement": "1000",
+            "settled_orders": [OrderUid([1; 56])],
+            "auction_id": 7,
+            "deadline": "2020-09-13T12:26:40Z",
+        }))
+        .unwrap();
+        assert_eq!(
+            solution,
+            Solution {
+                surplus: 1.5,
+                gas_reimbursement: 1000.into(),
+                settled_orders: vec![OrderUid([1; 56])],
+                auction_id: 7,
+                deadline: Some(Utc.timestamp(1_600_000_000, 0)),
+            }
+        );
+    }
+
+    #[test]
+    fn scores_solutions() {
+        let auction = AuctionWithId {
+            id: 1,
+            auction: Auction {
+                orders: vec![Order {
+                    metadata: OrderMetadata {
+                        uid: OrderUid([1; 56]),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        };
+        let solution = Solution {
+            surplus: 3000.,
+            gas_reimbursement: 1000.into(),
+            settled_orders: vec![OrderUid([1; 56])],
+            auction_id: 1,
+            deadline: None,
+        };
+        let rules = ScoringRules::default();
+        let score_solution = |solution| score(&rules, "driver", &auction, solution, Utc::now());
+        assert_eq!(score_solution(&solution).unwrap(), 2000.);
+
+        let wrong_auction = Solution {
+            auction_id: 2,
+            ..solution.clone()
+        };
+        assert!(score_solution(&wrong_auction).is_err());
+
+        let unknown_order = Solution {
+            settled_orders: vec![OrderUid([2; 56])],
+            ..solution
+        };
+        assert!(score_solution(&unknown_order).is_err());
+    }
+}
@@ -1,29 +1,48 @@
-use crate::database::Postgres;
+use crate::{
+    auction_clustering::{AuctionClustering, Clusters},
+    auction_size::{self, AuctionSizeController},
+    database::Postgres,
+    price_sanity::PriceSanityGuard,
+    sealed_orders::SealedOrderRevealer,
+};
 use anyhow::{Context as _, Result};
 use futures::StreamExt;
-use model::{auction::Auction, order::Order, signature::Signature, time::now_in_epoch_seconds};
+use model::{
+    auction::{Auction, SolvableOrdersChanges},
+    order::Order,
+    time::now_in_epoch_seconds,
+};
 use primitive_types::{H160, H256, U256};
-use prometheus::{IntCounter, IntGauge};
+use prometheus::{Histogram, IntCounter, IntGauge};
 use shared::{
     account_balances::{BalanceFetching, Query},
     bad_token::BadTokenDetecting,
     current_block::CurrentBlockStream,
-    price_estimation::native::NativePriceEstimating,
+    hot_reload::Reloadable,
+    metrics::exemplars::observe_with_exemplar,
+    order_conditions::ConditionEvaluating,
+    order_validation::{AuctionInclusion, ClassPolicy},
+    presignature::PresignatureChecking,
+    price_estimation::native::{native_single_estimate, NativePriceEstimating},
     signature_validator::{SignatureCheck, SignatureValidating},
 };
 use std::{
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     iter::FromIterator,
     sync::{Arc, Mutex, Weak},
     time::Duration,
 };
-use tokio::time::Instant;
+use tokio::{sync::broadcast, time::Instant};
 
 // When creating the auction after solvable orders change we need to fetch native prices for a
 // potentially large amount of tokens. This is the maximum amount of time we allot for this
 // operation.
 const MAX_AUCTION_CREATION_TIME: Duration = Duration::from_secs(10);
 
+/// Subscribers to solvable orders changes that fall further behind than this many updates miss
+/// changes.
+const CHANGES_CAPACITY: usize = 10;
+
 #[derive(prometheus_metric_storage::MetricStorage)]
 pub struct Metrics {
     /// auction creations
@@ -40,6 +59,12 @@ pub struct Metrics {
 
     /// auction price estimate timeouts
     auction_price_estimate_timeouts: IntCounter,
+
+    /// auction prices that fell back to the price of the previous auction
+    auction_fallback_prices: IntCounter,
+
+    /// time it took to create an auction
+    auction_creation_seconds: Histogram,
 }
 
 /// Keeps track and updates the set of currently solvable orders.
@@ -51,12 +76,20 @@ pub struct Metrics {
 pub struct SolvableOrdersCache {
     min_order_validity_period: Duration,
     database: Postgres,
-    banned_users: HashSet<H160>,
+    banned_users: Reloadable<HashSet<H160>>,
     balance_fetcher: Arc<dyn BalanceFetching>,
     bad_token_detector: Arc<dyn BadTokenDetecting>,
     cache: Mutex<Inner>,
     native_price_estimator: Arc<dyn NativePriceEstimating>,
     signature_validator: Arc<dyn SignatureValidating>,
+    condition_evaluator: Arc<dyn ConditionEvaluating>,
+    presignature_checker: Arc<dyn PresignatureChecking>,
+    price_sanity: PriceSanityGuard,
+    native_price_estimation_parallelism: usize,
+    auction_size: Option<AuctionSizeController>,
+    auction_clustering: Option<AuctionClustering>,
+    sealed_orders: Option<SealedOrderRevealer>,
+    changes: broadcast::Sender<Arc<SolvableOrdersChanges>>,
     metrics: &'static Metrics,
 }
 
@@ -65,6 +98,9 @@ type Balances = HashMap<Query, U256>;
 struct Inner {
     orders: SolvableOrders,
     balances: Balances,
+    /// The native prices of the most recent auction. Used in place of prices that fail to be
+    /// fetched for the next auction.
+    prices: BTreeMap<H160, U256>,
 }
 
 #[derive(Clone, Debug)]
@@ -80,18 +116,25 @@ impl SolvableOrdersCache {
     pub fn new(
         min_order_validity_period: Duration,
         database: Postgres,
-        banned_users: HashSet<H160>,
+        banned_users: impl Into<Reloadable<HashSet<H160>>>,
         balance_fetcher: Arc<dyn BalanceFetching>,
         bad_token_detector: Arc<dyn BadTokenDetecting>,
         current_block: CurrentBlockStream,
         native_price_estimator: Arc<dyn NativePriceEstimating>,
         signature_validator: Arc<dyn SignatureValidating>,
+        condition_evaluator: Arc<dyn ConditionEvaluating>,
+        presignature_checker: Arc<dyn PresignatureChecking>,
         update_interval: Duration,
+        price_sanity: PriceSanityGuard,
+        native_price_estimation_parallelism: usize,
+        auction_size: Option<AuctionSizeController>,
+        auction_clustering: Option<AuctionClustering>,
+        sealed_orders: Option<SealedOrderRevealer>,
     ) -> Arc<Self> {
         let self_ = Arc::new(Self {
             min_order_validity_period,
             database,
-            banned_users,
+            banned_users: banned_users.into(),
             balance_fetcher,
             bad_token_detector,
             cache: Mutex::new(Inner {
@@ -102,9 +145,18 @@ impl SolvableOrdersCache {
                     block: 0,
                 },
                 balances: Default::default(),
+                prices: Default::default(),
             }),
             native_price_estimator,
             signature_validator,
+            condition_evaluator,
+            presignature_checker,
+            price_sanity,
+            native_price_estimation_parallelism,
+            auction_size,
+            auction_clustering,
+            sealed_orders,
+            changes: broadcast::channel(CHANGES_CAPACITY).0,
             metrics: Metrics::instance(global_metrics::get_metric_storage_registry()).unwrap(),
         });
         tokio::task::spawn(update_task(
@@ -120,12 +172,46 @@ impl SolvableOrdersCache {
     /// Usually this method is called from update_task. If it isn't, which is the case in unit tests,
     /// then concurrent calls might overwrite eachother's results.
     pub async fn update(&self, block: u64) -> Result<()> {
+        let start = Instant::now();
+        // Revealed orders get placed in the database so they are part of this auction.
+        if let Some(sealed_orders) = &self.sealed_orders {
+            if let Err(err) = sealed_orders.reveal().await {
+                tracing::error!(?err, "failed to reveal sealed orders");
+            }
+        }
         let min_valid_to = now_in_epoch_seconds() + self.min_order_validity_period.as_secs() as u32;
-        let db_solvable_orders = self.database.solvable_orders(min_valid_to).await?;
-        let orders = filter_banned_user_orders(db_solvable_orders.orders, &self.banned_users);
+        let (db_solvable_orders, presignature_pending_orders) = futures::try_join!(
+            self.database.solvable_orders(min_valid_to),
+            self.database.presignature_pending_orders(min_valid_to),
+        )?;
+        let presigned = presigned_orders(
+            presignature_pending_orders,
+            self.presignature_checker.as_ref(),
+        )
+        .await;
+        if !presigned.is_empty() {
+            let uids = presigned
+                .iter()
+                .map(|order| order.metadata.uid)
+                .collect::<Vec<_>>();
+            if let Err(err) = self
+                .database
+                .record_onchain_presignatures(&uids, block)
+                .await
+            {
+                tracing::warn!(?err, "failed to record onchain presignatures");
+            }
+        }
+        let mut orders = db_solvable_orders.orders;
+        orders.extend(presigned);
+        let orders = filter_banned_user_orders(orders, &self.banned_users.get());
+        let orders = filter_expired_quotes(orders, block);
         let orders = filter_unsupported_tokens(orders, self.bad_token_detector.as_ref()).await?;
         let orders =
             filter_invalid_signature_orders(orders, self.signature_validator.as_ref()).await;
+        let orders =
+            filter_unmet_condition_orders(orders, self.condition_evaluator.as_ref(), block).await;
+        let orders = filter_later_sequential_orders(orders);
 
         // If we update due to an explicit notification we can reuse existing balances as they
         // cannot have changed.
@@ -157,27 +243,57 @@ impl SolvableOrdersCache {
         }
 
         let mut orders = solvable_orders(orders, &new_balances);
+        if let Some(auction_size) = &self.auction_size {
+            orders = auction_size::truncate_orders(orders, auction_size.max_orders().await);
+        }
         for order in &mut orders {
             let query = Query::from_order(order);
             order.metadata.available_balance = new_balances.get(&query).copied();
         }
 
         // create auction
-        let (orders, prices) = get_orders_with_native_prices(
+        let previous_prices = self.cache.lock().unwrap().prices.clone();
+        let (orders, prices, mut estimated_prices) = get_orders_with_native_prices(
             orders.clone(),
             &*self.native_price_estimator,
+            self.native_price_estimation_parallelism,
+            &previous_prices,
             Instant::now() + MAX_AUCTION_CREATION_TIME,
             self.metrics,
         )
         .await;
+        let (orders, prices) = self.price_sanity.check(orders, prices)?;
+        estimated_prices.retain(|token| prices.contains_key(token));
+        let clusters = match &self.auction_clustering {
+            Some(auction_clustering) => auction_clustering.cluster(orders, block).await,
+            None => Clusters {
+                orders,
+                ..Default::default()
+            },
+        };
+        let orders = clusters.orders;
         let auction = Auction {
             block,
             latest_settlement_block: db_solvable_orders.latest_settlement_block,
             orders: orders.clone(),
             prices,
+            estimated_prices,
+            clusters: clusters.clusters,
+            order_selection_seed: clusters.order_selection_seed,
         };
-        let _id = self.database.replace_current_auction(&auction).await?;
-        *self.cache.lock().unwrap() = Inner {
+        let id = self.database.replace_current_auction(&auction).await?;
+        observe_with_exemplar(
+            &self.metrics.auction_creation_seconds,
+            start.elapsed().as_secs_f64(),
+            &id.to_string(),
+        );
+        let mut inner = self.cache.lock().unwrap();
+        let changes = changes(&inner.orders.orders, &orders, block);
+        if !changes.is_empty() {
+            // Sending only fails if there are no subscribers.
+            let _ = self.changes.send(Arc::new(changes));
+        }
+        *inner = Inner {
             orders: SolvableOrders {
                 orders,
                 update_time: Instant::now(),
@@ -185,7 +301,9 @@ impl SolvableOrdersCache {
                 block,
             },
             balances: new_balances,
+            prices: auction.prices.clone(),
         };
+        drop(inner);
 
         tracing::debug!(
             "updated auction with {} solvable orders",
@@ -194,6 +312,75 @@ impl SolvableOrdersCache {
 
         Ok(())
     }
+
+    /// Subscribes to the changes of future updates. Returns the current solvable orders as
+    /// changes to an empty set of orders so that subscribers start out in sync.
+    pub fn subscribe_changes(
+        &self,
+    ) -> (
+        SolvableOrdersChanges,
+        broadcast::Receiver<Arc<SolvableOrdersChanges>>,
+    ) {
+        // Subscribing while holding the lock ensures that no update happens in between.
+        let inner = self.cache.lock().unwrap();
+        let snapshot = changes(&[], &inner.orders.orders, inner.orders.block);
+        (snapshot, self.changes.subscribe())
+    }
+}
+
+/// The changes from the old to the new solvable orders. Orders that are in both sets are updated
+/// if anything about them changed.
+fn changes(old: &[Order], new: &[Order], block: u64) -> SolvableOrdersChanges {
+    let old_orders = old
+        .iter()
+        .map(|order| (order.metadata.uid, order))
+        .collect::<HashMap<_, _>>();
+    let new_uids = new
+        .iter()
+        .map(|order| order.metadata.uid)
+        .collect::<HashSet<_>>();
+    SolvableOrdersChanges {
+        block,
+        updated: new
+            .iter()
+            .filter(|order| old_orders.get(&order.metadata.uid) != Some(order))
+            .cloned()
+            .collect(),
+        removed: old
+            .iter()
+            .map(|order| order.metadata.uid)
+            .filter(|uid| !new_uids.contains(uid))
+            .collect(),
+    }
+}
+
+/// Returns the presign orders that are already pre-signed in the settlement contract. This makes
+/// them solvable right away instead of after their `PreSignature` events got indexed.
+async fn presigned_orders(
+    orders: Vec<Order>,
+    presignature_checker: &dyn PresignatureChecking,
+) -> Vec<Order> {
+    if orders.is_empty() {
+        return orders;
+    }
+
+    let uids = orders.iter().map(|order| order.metadata.uid).collect();
+    let presigned = presignature_checker.are_presigned(uids).await;
+    orders
+        .into_iter()
+        .zip(presigned)
+        .filter_map(|(order, presigned)| match presigned {
+            Ok(true) => Some(order),
+            Ok(false) => None,
+            Err(err) => {
+                tracing::warn!(
+                    order_uid =% order.metadata.uid, ?err,
+                    "failed to check presignature"
+                );
+                None
+            }
+        })
+        .collect()
 }
 
 /// Filters all orders whose owners are in the set of "banned" users.
@@ -202,24 +389,64 @@ fn filter_banned_user_orders(mut orders: Vec<Order>, banned_users: &HashSet<H160
     orders
 }
 
-/// Filters EIP-1271 orders whose signatures are no longer validating.
+/// Filters orders whose quotes can't be filled anymore if their class is only included in auctions
+/// while their quote is firm. Settlements for the auction can be included in the next block at the
+/// earliest.
+fn filter_expired_quotes(mut orders: Vec<Order>, block: u64) -> Vec<Order> {
+    orders.retain(
+        |order| match ClassPolicy::of(order.metadata.class).auction_inclusion {
+            AuctionInclusion::Always | AuctionInclusion::Sequentially => true,
+            AuctionInclusion::WhileQuoteIsFirm => match &order.metadata.quote_firmness {
+                Some(firmness) => !firmness.is_expired(block + 1),
+                None => true,
+            },
+        },
+    );
+    orders
+}
+
+/// Filters orders of classes that get included in auctions one after the other unless they are
+/// the order of their owner and token pair that expires first.
+fn filter_later_sequential_orders(orders: Vec<Order>) -> Vec<Order> {
+    let is_sequential = |order: &Order| {
+        ClassPolicy::of(order.metadata.class).auction_inclusion == AuctionInclusion::Sequentially
+    };
+    let key = |order: &Order| {
+        (
+            order.metadata.owner,
+            order.data.sell_token,
+            order.data.buy_token,
+        )
+    };
+    let mut next = HashMap::new();
+    for order in orders.iter().filter(|order| is_sequential(order)) {
+        // The uid breaks ties so that the selection doesn't depend on the order of the orders.
+        let candidate = (order.data.valid_to, order.metadata.uid.0);
+        let current = next.entry(key(order)).or_insert(candidate);
+        *current = (*current).min(candidate);
+    }
+    orders
+        .into_iter()
+        .filter(|order| {
+            !is_sequential(order)
+                || next.get(&key(order)).map(|(_, uid)| uid) == Some(&order.metadata.uid.0)
+        })
+        .collect()
+}
+
+/// Filters orders verified by the owner contract (EIP-1271) whose signatures are no longer
+/// validating.
 async fn filter_invalid_signature_orders(
     orders: Vec<Order>,
     signature_validator: &dyn SignatureValidating,
 ) -> Vec<Order> {
+    let signature_check = |order: &Order| {
+        let (H256(hash), signer, _) = order.metadata.uid.parts();
+        SignatureCheck::for_signature(&order.signature, signer, hash)
+    };
     let checks = orders
         .iter()
-        .filter_map(|order| match &order.signature {
-            Signature::Eip1271(signature) => {
-                let (H256(hash), signer, _) = order.metadata.uid.parts();
-                Some(SignatureCheck {
-                    signer,
-                    hash,
-                    signature: signature.clone(),
-                })
-            }
-            _ => None,
-        })
+        .filter_map(signature_check)
         .collect::<Vec<_>>();
 
     if checks.is_empty() {
@@ -233,7 +460,7 @@ async fn filter_invalid_signature_orders(
     orders
         .into_iter()
         .filter(|order| {
-            if let Signature::Eip1271(_) = &order.signature {
+            if order.signature.contract_signature().is_some() {
                 if let Err(err) = validations.next().unwrap() {
                     tracing::warn!(
                         order_uid =% order.metadata.uid, ?err,
@@ -249,6 +476,36 @@ async fn filter_invalid_signature_orders(
 }
 
 /// Returns existing balances and Vec of queries that need to be peformed.
+/// Filters conditional orders whose condition doesn't hold at the block. All conditional orders
+/// get filtered if the conditions fail to be evaluated.
+async fn filter_unmet_condition_orders(
+    orders: Vec<Order>,
+    condition_evaluator: &dyn ConditionEvaluating,
+    block: u64,
+) -> Vec<Order> {
+    let conditions = orders
+        .iter()
+        .filter_map(|order| order.metadata.condition.clone())
+        .collect::<Vec<_>>();
+
+    if conditions.is_empty() {
+        return orders;
+    }
+
+    let holds = match condition_evaluator.evaluate(conditions, block).await {
+        Ok(holds) => holds,
+        Err(err) => {
+            tracing::warn!(?err, "failed to evaluate order conditions");
+            Vec::new()
+        }
+    };
+    let mut holds = holds.into_iter();
+    orders
+        .into_iter()
+        .filter(|order| order.metadata.condition.is_none() || holds.next().unwrap_or(false))
+        .collect()
+}
+
 fn new_balances(old_balances: &Balances, orders: &[Order]) -> (HashMap<Query, U256>, Vec<Query>) {
     let mut new_balances = HashMap::new();
     let mut missing_queries = HashSet::new();
@@ -328,7 +585,7 @@ fn solvable_orders(mut orders: Vec<Order>, balances: &Balances) -> Vec<Order> {
 /// a half-filled order would be `(sell_amount + fee_amount) / 2`).
 ///
 /// Returns `Err` on overflow.
-fn max_transfer_out_amount(order: &Order) -> Result<U256> {
+pub(crate) fn max_transfer_out_amount(order: &Order) -> Result<U256> {
     let remaining = shared::remaining_amounts::Remaining::from_order(order)?;
     let sell = remaining.remaining(order.data.sell_amount)?;
     let fee = remaining.remaining(order.data.fee_amount)?;
@@ -378,12 +635,17 @@ async fn update_task(
     }
 }
 
+/// Fetches the native prices of all traded tokens with at most `parallelism` concurrent estimates
+/// and filters out orders without prices. Tokens whose price can't be fetched in time use their
+/// price from `fallback_prices` instead, which get returned as the set of estimated prices.
 async fn get_orders_with_native_prices(
     mut orders: Vec<Order>,
     native_price_estimator: &dyn NativePriceEstimating,
+    parallelism: usize,
+    fallback_prices: &BTreeMap<H160, U256>,
     deadline: Instant,
     metrics: &Metrics,
-) -> (Vec<Order>, BTreeMap<H160, U256>) {
+) -> (Vec<Order>, BTreeMap<H160, U256>, BTreeSet<H160>) {
     let traded_tokens = orders
         .iter()
         .flat_map(|order| [order.data.sell_token, order.data.buy_token])
@@ -391,11 +653,15 @@ async fn get_orders_with_native_prices(
         .into_iter()
         .collect::<Vec<_>>();
     let mut prices = HashMap::new();
-    let mut price_stream = native_price_estimator.estimate_native_prices(&traded_tokens);
+    let mut price_stream = futures::stream::iter(&traded_tokens)
+        .map(|token| async move {
+            let result = native_single_estimate(native_price_estimator, token).await;
+            (token, result)
+        })
+        .buffer_unordered(parallelism.max(1));
     let mut errored_estimates: u64 = 0;
     let collect_prices = async {
-        while let Some((index, result)) = price_stream.next().await {
-            let token = &traded_tokens[index];
+        while let Some((token, result)) = price_stream.next().await {
             let price = match result {
                 Ok(price) => price,
                 Err(err) => {
@@ -423,6 +689,18 @@ async fn get_orders_with_native_prices(
         }
     };
 
+    let mut estimated_prices = BTreeSet::new();
+    for token in &traded_tokens {
+        if prices.contains_key(token) {
+            continue;
+        }
+        if let Some(price) = fallback_prices.get(token) {
+            tracing::debug!(?token, "using native price of the previous auction");
+            prices.insert(*token, *price);
+            estimated_prices.insert(*token);
+        }
+    }
+
     let original_order_count = orders.len() as u64;
     // Filter both orders and prices so that we only return orders that have prices and prices that
     // have orders.
@@ -456,8 +734,12 @@ async fn get_orders_with_native_prices(
     metrics
         .auction_errored_price_estimates
         .inc_by(errored_estimates);
+    estimated_prices.retain(|token| used_prices.contains_key(token));
+    metrics
+        .auction_fallback_prices
+        .inc_by(estimated_prices.len() as u64);
 
-    (orders, used_prices)
+    (orders, used_prices, estimated_prices)
 }
 
 fn to_normalized_price(price: f64) -> Option<U256> {
@@ -478,12 +760,14 @@ async fn filter_unsupported_tokens(
     // Can't use normal `retain` or `filter` because the bad token detection is async. So either
     // this manual iteration or conversion to stream.
     let mut index = 0;
-    'outer: while index < orders.len() {
-        for token in orders[index].data.token_pair().unwrap() {
-            if !bad_token.detect(token).await?.is_good() {
-                orders.swap_remove(index);
-                continue 'outer;
-            }
+    while index < orders.len() {
+        let order = &orders[index].data;
+        // Fee on transfer tokens can be bought because the receiver pays the fee.
+        if !bad_token.detect(order.sell_token).await?.is_good()
+            || !bad_token.detect(order.buy_token).await?.is_buyable()
+        {
+            orders.swap_remove(index);
+            continue;
         }
         index += 1;
     }
@@ -495,12 +779,20 @@ mod tests {
     use super::*;
     use chrono::{DateTime, NaiveDateTime, Utc};
     use futures::{FutureExt, StreamExt};
-    use maplit::{btreemap, hashmap, hashset};
+    use maplit::{btreemap, btreeset, hashmap, hashset};
     use mockall::predicate::eq;
-    use model::order::{OrderBuilder, OrderData, OrderKind, OrderMetadata, OrderUid};
+    use model::{
+        order::{
+            OrderBuilder, OrderClass, OrderCondition, OrderData, OrderKind, OrderMetadata,
+            OrderUid, QuoteFirmness,
+        },
+        signature::Signature,
+    };
     use primitive_types::H160;
     use shared::{
         bad_token::list_based::ListBasedDetector,
+        order_conditions::MockConditionEvaluating,
+        presignature::MockPresignatureChecking,
         price_estimation::{native::MockNativePriceEstimating, PriceEstimationError},
         signature_validator::{MockSignatureValidating, SignatureValidationError},
     };
@@ -607,34 +899,13 @@ mod tests {
             token4 => 0., // invalid price!
         };
 
-        let mut native_price_estimator = MockNativePriceEstimating::new();
-        native_price_estimator
-            .expect_estimate_native_prices()
-            // deal with undeterministic ordering of `HashSet`.
-            .withf(move |tokens| {
-                tokens.iter().cloned().collect::<HashSet<_>>()
-                    == hashset!(token1, token2, token3, token4)
-            })
-            .returning({
-                let prices = prices.clone();
-                move |tokens| {
-                    let results = tokens
-                        .iter()
-                        .map(|token| {
-                            prices
-                                .get(token)
-                                .copied()
-                                .ok_or(PriceEstimationError::NoLiquidity)
-                        })
-                        .enumerate()
-                        .collect::<Vec<_>>();
-                    futures::stream::iter(results).boxed()
-                }
-            });
+        let native_price_estimator = native_price_estimator(prices);
 
-        let (filtered_orders, prices) = get_orders_with_native_prices(
+        let (filtered_orders, prices, estimated_prices) = get_orders_with_native_prices(
             orders.clone(),
             &native_price_estimator,
+            2,
+            &Default::default(),
             Instant::now() + MAX_AUCTION_CREATION_TIME,
             Metrics::instance(global_metrics::get_metric_storage_registry()).unwrap(),
         )
@@ -648,6 +919,67 @@ mod tests {
                 token3 => U256::from(250_000_000_000_000_000_u128),
             }
         );
+        assert!(estimated_prices.is_empty());
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_previous_prices() {
+        let token1 = H160([1; 20]);
+        let token2 = H160([2; 20]);
+        let token3 = H160([3; 20]);
+
+        let orders = vec![
+            OrderBuilder::default()
+                .with_sell_token(token1)
+                .with_buy_token(token2)
+                .build(),
+            OrderBuilder::default()
+                .with_sell_token(token1)
+                .with_buy_token(token3)
+                .build(),
+        ];
+        let native_price_estimator = native_price_estimator(btreemap! { token1 => 2. });
+        let previous_prices = btreemap! {
+            token1 => U256::from(1),
+            token2 => U256::from(3),
+        };
+
+        let (filtered_orders, prices, estimated_prices) = get_orders_with_native_prices(
+            orders.clone(),
+            &native_price_estimator,
+            2,
+            &previous_prices,
+            Instant::now() + MAX_AUCTION_CREATION_TIME,
+            Metrics::instance(global_metrics::get_metric_storage_registry()).unwrap(),
+        )
+        .await;
+
+        assert_eq!(filtered_orders, [orders[0].clone()]);
+        assert_eq!(
+            prices,
+            btreemap! {
+                token1 => U256::from(2_000_000_000_000_000_000_u128),
+                token2 => U256::from(3),
+            }
+        );
+        assert_eq!(estimated_prices, btreeset! { token2 });
+    }
+
+    /// A native price estimator that gets queried for a single token at a time and returns the
+    /// specified prices.
+    fn native_price_estimator(prices: BTreeMap<H160, f64>) -> MockNativePriceEstimating {
+        let mut native_price_estimator = MockNativePriceEstimating::new();
+        native_price_estimator
+            .expect_estimate_native_prices()
+            .withf(|tokens| tokens.len() == 1)
+            .returning(move |tokens| {
+                let result = prices
+                    .get(&tokens[0])
+                    .copied()
+                    .ok_or(PriceEstimationError::NoLiquidity);
+                futures::stream::iter(vec![(0, result)]).boxed()
+            });
+        native_price_estimator
     }
 
     #[test]
@@ -771,6 +1103,31 @@ mod tests {
         assert!(prices.contains_key(&orders_[0].data.buy_token));
     }
 
+    #[test]
+    fn computes_solvable_orders_changes() {
+        let order = |uid: u8, executed: u32| Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([uid; 56]),
+                executed_sell_amount: executed.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let old = [order(1, 0), order(2, 0), order(3, 0)];
+        let new = [order(1, 0), order(3, 1), order(4, 0)];
+
+        assert_eq!(
+            changes(&old, &new, 5),
+            SolvableOrdersChanges {
+                block: 5,
+                updated: vec![order(3, 1), order(4, 0)],
+                removed: vec![OrderUid([2; 56])],
+            }
+        );
+        assert!(changes(&new, &new, 6).is_empty());
+        assert_eq!(changes(&[], &new, 6).updated, new);
+    }
+
     #[test]
     fn filters_banned_users() {
         let banned_users = hashset!(H160([0xba; 20]), H160([0xbb; 20]));
@@ -809,6 +1166,67 @@ mod tests {
         );
     }
 
+    #[test]
+    fn filters_expired_quotes() {
+        let order = |expiry_block| Order {
+            metadata: OrderMetadata {
+                class: OrderClass::Liquidity,
+                quote_firmness: expiry_block.map(|expiry_block| QuoteFirmness {
+                    expiry_block: Some(expiry_block),
+                    last_look: false,
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let orders = vec![
+            order(None),
+            order(Some(10)),
+            order(Some(11)),
+            order(Some(12)),
+        ];
+
+        let filtered = filter_expired_quotes(orders, 10);
+        let expiry_blocks = filtered
+            .iter()
+            .map(|order| order.metadata.quote_firmness.and_then(|f| f.expiry_block))
+            .collect::<Vec<_>>();
+        assert_eq!(expiry_blocks, [None, Some(11), Some(12)]);
+    }
+
+    #[test]
+    fn filters_later_twap_parts() {
+        let order = |id: u8, owner: u8, buy_token: u8, valid_to, class| Order {
+            data: OrderData {
+                buy_token: H160([buy_token; 20]),
+                valid_to,
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid([id; 56]),
+                owner: H160([owner; 20]),
+                class,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let orders = vec![
+            order(1, 1, 1, 20, OrderClass::Twap),
+            order(2, 1, 1, 10, OrderClass::Twap),
+            order(3, 1, 2, 30, OrderClass::Twap),
+            order(4, 2, 1, 30, OrderClass::Twap),
+            order(5, 1, 1, 30, OrderClass::Limit),
+            order(6, 1, 1, 40, OrderClass::Limit),
+        ];
+
+        let filtered = filter_later_sequential_orders(orders);
+        let ids = filtered
+            .iter()
+            .map(|order| order.metadata.uid.0[0])
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [2, 3, 4, 5, 6]);
+    }
+
     #[test]
     fn filters_zero_amount_orders() {
         let orders = vec![
@@ -940,6 +1358,84 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn filters_orders_with_unmet_conditions() {
+        let condition = |not_before| OrderCondition::TimeWindow {
+            not_before: Some(not_before),
+            not_after: None,
+        };
+        let orders = vec![
+            OrderBuilder::default().with_valid_to(1).build(),
+            OrderBuilder::default()
+                .with_valid_to(2)
+                .with_condition(condition(2))
+                .build(),
+            OrderBuilder::default()
+                .with_valid_to(3)
+                .with_condition(condition(3))
+                .build(),
+        ];
+
+        let mut condition_evaluator = MockConditionEvaluating::new();
+        condition_evaluator
+            .expect_evaluate()
+            .with(eq(vec![condition(2), condition(3)]), eq(7))
+            .returning(|_, _| Ok(vec![false, true]));
+        let filtered = filter_unmet_condition_orders(orders.clone(), &condition_evaluator, 7).await;
+        assert_eq!(
+            filtered
+                .iter()
+                .map(|order| order.data.valid_to)
+                .collect::<Vec<_>>(),
+            [1, 3]
+        );
+
+        let mut condition_evaluator = MockConditionEvaluating::new();
+        condition_evaluator
+            .expect_evaluate()
+            .returning(|_, _| Err(anyhow::anyhow!("node error")));
+        let filtered = filter_unmet_condition_orders(orders, &condition_evaluator, 7).await;
+        assert_eq!(filtered.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn keeps_presigned_orders() {
+        let orders = (1..=3)
+            .map(|i| Order {
+                metadata: OrderMetadata {
+                    uid: OrderUid([i; 56]),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        let mut presignature_checker = MockPresignatureChecking::new();
+        presignature_checker
+            .expect_are_presigned()
+            .times(1)
+            .with(eq(vec![
+                OrderUid([1; 56]),
+                OrderUid([2; 56]),
+                OrderUid([3; 56]),
+            ]))
+            .returning(|_| vec![Ok(true), Ok(false), Err(anyhow::anyhow!("node error"))]);
+
+        let presigned = presigned_orders(orders, &presignature_checker).await;
+        assert_eq!(
+            presigned
+                .iter()
+                .map(|order| order.metadata.uid)
+                .collect::<Vec<_>>(),
+            vec![OrderUid([1; 56])]
+        );
+
+        // The checker isn't called when there are no pending orders.
+        assert!(presigned_orders(Vec::new(), &presignature_checker)
+            .await
+            .is_empty());
+    }
+
     #[test]
     fn filter_unsupported_tokens_() {
         let token0 = H160::from_low_u64_le(0);
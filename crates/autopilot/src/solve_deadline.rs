@@ -0,0 +1,158 @@
+//! Deadlines by which drivers have to respond to an auction.
+//!
+//! Large auctions get more time to be solved. When the network is congested settlements take longer
+//! to get mined so solving ends earlier to leave more time for the reveal phase and submission.
+
+use chrono::{DateTime, Utc};
+use model::auction::Auction;
+use shared::arguments::{duration_from_seconds, wei_from_gwei};
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+/// Arguments related to the solving deadlines sent to drivers.
+#[derive(clap::Parser)]
+pub struct Arguments {
+    /// The time in seconds drivers get to solve an auction without orders.
+    #[clap(
+        long,
+        env,
+        default_value = "10",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub solve_deadline_base: Duration,
+
+    /// The additional time in seconds drivers get for every order in the auction.
+    #[clap(
+        long,
+        env,
+        default_value = "0.01",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub solve_deadline_per_order: Duration,
+
+    /// The maximum time in seconds drivers get to solve an auction.
+    #[clap(
+        long,
+        env,
+        default_value = "25",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub solve_deadline_max: Duration,
+
+    /// The gas price in Gwei from which on the network is considered congested.
+    #[clap(
+        long,
+        env,
+        default_value = "200",
+        parse(try_from_str = wei_from_gwei),
+    )]
+    pub solve_deadline_congestion_gas_price: f64,
+
+    /// The time in seconds solving ends earlier when the network is congested.
+    #[clap(
+        long,
+        env,
+        default_value = "5",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub solve_deadline_congestion_reduction: Duration,
+}
+
+impl Display for Arguments {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "solve_deadline_base: {:?}", self.solve_deadline_base)?;
+        writeln!(
+            f,
+            "solve_deadline_per_order: {:?}",
+            self.solve_deadline_per_order
+        )?;
+        writeln!(f, "solve_deadline_max: {:?}", self.solve_deadline_max)?;
+        writeln!(
+            f,
+            "solve_deadline_congestion_gas_price: {}",
+            self.solve_deadline_congestion_gas_price
+        )?;
+        writeln!(
+            f,
+            "solve_deadline_congestion_reduction: {:?}",
+            self.solve_deadline_congestion_reduction
+        )?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SolveDeadline {
+    pub base: Duration,
+    pub per_order: Duration,
+    pub max: Duration,
+    pub congestion_gas_price: f64,
+    pub congestion_reduction: Duration,
+}
+
+impl From<&Arguments> for SolveDeadline {
+    fn from(args: &Arguments) -> Self {
+        Self {
+            base: args.solve_deadline_base,
+            per_order: args.solve_deadline_per_order,
+            max: args.solve_deadline_max,
+            congestion_gas_price: args.solve_deadline_congestion_gas_price,
+            congestion_reduction: args.solve_deadline_congestion_reduction,
+        }
+    }
+}
+
+impl SolveDeadline {
+    /// The time drivers get to solve the auction at the given gas price.
+    pub fn solve_time(&self, auction: &Auction, gas_price: f64) -> Duration {
+        let orders = u32::try_from(auction.orders.len()).unwrap_or(u32::MAX);
+        let solve_time = self
+            .per_order
+            .checked_mul(orders)
+            .and_then(|time| time.checked_add(self.base))
+            .unwrap_or(self.max)
+            .min(self.max);
+        if gas_price >= self.congestion_gas_price {
+            solve_time.saturating_sub(self.congestion_reduction)
+        } else {
+            solve_time
+        }
+    }
+
+    /// The deadline by which drivers have to respond to the auction.
+    pub fn deadline(&self, auction: &Auction, gas_price: f64, now: DateTime<Utc>) -> DateTime<Utc> {
+        now + chrono::Duration::from_std(self.solve_time(auction, gas_price))
+            .expect("solve time is bounded by max")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::order::Order;
+
+    #[test]
+    fn solve_time_depends_on_auction_size_and_congestion() {
+        let rules = SolveDeadline {
+            base: Duration::from_secs(10),
+            per_order: Duration::from_secs(1),
+            max: Duration::from_secs(25),
+            congestion_gas_price: 100e9,
+            congestion_reduction: Duration::from_secs(5),
+        };
+        let auction = |orders| Auction {
+            orders: vec![Order::default(); orders],
+            ..Default::default()
+        };
+        let solve_time =
+            |orders, gas_price| rules.solve_time(&auction(orders), gas_price).as_secs();
+
+        assert_eq!(solve_time(0, 10e9), 10);
+        assert_eq!(solve_time(5, 10e9), 15);
+        assert_eq!(solve_time(100, 10e9), 25);
+        assert_eq!(solve_time(5, 100e9), 10);
+        assert_eq!(solve_time(100, 200e9), 20);
+    }
+}
@@ -0,0 +1,201 @@
+//! Computes the rewards solvers earn for their settlements and stores them so that the orderbook
+//! api can serve them per accounting period.
+//!
+//! A settlement's reward consists of a fixed reward for every user order it settles and a reward
+//! proportional to the quality of the winning solution of the solver competition, which is its
+//! objective value. The total reward of a settlement can be capped. Rewards are computed with
+//! decimals so that they add up exactly.
+
+use crate::database::Postgres;
+use anyhow::Result;
+use bigdecimal::{BigDecimal, FromPrimitive, Zero};
+use chrono::Utc;
+use database::{
+    events::EventIndex,
+    solver_rewards::{SettlementReward, UnrewardedSettlement},
+};
+use model::solver_competition::SolverCompetition;
+use shared::{arguments::display_option, maintenance::Maintaining};
+use std::fmt::{self, Display, Formatter};
+
+// Limits the database requests per run when catching up on old settlements.
+const SETTLEMENTS_PER_RUN: i64 = 100;
+
+/// Solver competitions get stored after the settlement transaction has been mined so settlements
+/// only get rewarded once they are this old.
+fn reward_delay() -> chrono::Duration {
+    chrono::Duration::minutes(10)
+}
+
+/// Arguments related to solver rewards.
+#[derive(clap::Parser)]
+pub struct Arguments {
+    /// The reward in the reward token for every settled order that is not a liquidity order.
+    #[clap(long, env, default_value = "0")]
+    pub reward_per_order: BigDecimal,
+
+    /// The factor the objective value of the winning solution gets multiplied with to compute the
+    /// quality reward of a settlement.
+    #[clap(long, env, default_value = "0")]
+    pub reward_quality_factor: BigDecimal,
+
+    /// The maximum reward of a single settlement.
+    #[clap(long, env)]
+    pub reward_max_per_settlement: Option<BigDecimal>,
+}
+
+impl Display for Arguments {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "reward_per_order: {}", self.reward_per_order)?;
+        writeln!(f, "reward_quality_factor: {}", self.reward_quality_factor)?;
+        display_option(
+            f,
+            "reward_max_per_settlement",
+            &self.reward_max_per_settlement,
+        )?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RewardScheme {
+    pub per_order: BigDecimal,
+    pub quality_factor: BigDecimal,
+    pub max_per_settlement: Option<BigDecimal>,
+}
+
+impl From<&Arguments> for RewardScheme {
+    fn from(args: &Arguments) -> Self {
+        Self {
+            per_order: args.reward_per_order.clone(),
+            quality_factor: args.reward_quality_factor.clone(),
+            max_per_settlement: args.reward_max_per_settlement.clone(),
+        }
+    }
+}
+
+impl RewardScheme {
+    /// Solutions with a negative objective value don't reduce the reward.
+    pub fn reward(&self, user_orders: i64, quality_score: Option<&BigDecimal>) -> BigDecimal {
+        let order_reward = &self.per_order * BigDecimal::from(user_orders);
+        let quality_reward = quality_score.map_or_else(BigDecimal::zero, |score| {
+            (&self.quality_factor * score).max(BigDecimal::zero())
+        });
+        let reward = order_reward + quality_reward;
+        match &self.max_per_settlement {
+            Some(max_reward) => reward.min(max_reward.clone()),
+            None => reward,
+        }
+    }
+}
+
+/// The objective value of the competition's winning solution. Solutions are sorted by ascending
+/// objective value. `None` if the objective value isn't finite.
+fn quality_score(competition: &SolverCompetition) -> Option<BigDecimal> {
+    competition
+        .solutions
+        .last()
+        .and_then(|solution| BigDecimal::from_f64(solution.objective.total))
+}
+
+pub struct SolverRewardsUpdater {
+    db: Postgres,
+    scheme: RewardScheme,
+}
+
+impl SolverRewardsUpdater {
+    pub fn new(db: Postgres, scheme: RewardScheme) -> Self {
+        Self { db, scheme }
+    }
+
+    async fn reward(&self, settlement: &UnrewardedSettlement) -> Result<SettlementReward> {
+        let user_orders = self
+            .db
+            .settled_user_orders(&EventIndex {
+                block_number: settlement.block_number,
+                log_index: settlement.log_index,
+            })
+            .await?;
+        let competition = self
+            .db
+            .solver_competition_by_tx_hash(&settlement.tx_hash)
+            .await?;
+        let quality_score = competition.as_ref().and_then(quality_score);
+        Ok(SettlementReward {
+            block_number: settlement.block_number,
+            log_index: settlement.log_index,
+            solver: settlement.solver,
+            tx_hash: settlement.tx_hash,
+            reward: self.scheme.reward(user_orders, quality_score.as_ref()),
+            user_orders,
+            quality_score,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Maintaining for SolverRewardsUpdater {
+    async fn run_maintenance(&self) -> Result<()> {
+        let settlements = self
+            .db
+            .unrewarded_settlements(Utc::now() - reward_delay(), SETTLEMENTS_PER_RUN)
+            .await?;
+        for settlement in settlements {
+            let reward = self.reward(&settlement).await?;
+            tracing::debug!(?reward, "rewarding settlement");
+            self.db.insert_settlement_reward(&reward).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::solver_competition::{Objective, SolverSettlement};
+
+    #[test]
+    fn computes_rewards() {
+        let decimal = |value: &str| value.parse::<BigDecimal>().unwrap();
+        let scheme = RewardScheme {
+            per_order: decimal("10"),
+            quality_factor: decimal("2"),
+            max_per_settlement: Some(decimal("100")),
+        };
+        assert_eq!(scheme.reward(0, None), decimal("0"));
+        assert_eq!(scheme.reward(2, None), decimal("20"));
+        assert_eq!(scheme.reward(2, Some(&decimal("5"))), decimal("30"));
+        assert_eq!(scheme.reward(2, Some(&decimal("-5"))), decimal("20"));
+        assert_eq!(scheme.reward(20, Some(&decimal("5"))), decimal("100"));
+
+        // Decimal rewards don't accumulate rounding errors.
+        let scheme = RewardScheme {
+            per_order: decimal("0.1"),
+            quality_factor: decimal("0.2"),
+            max_per_settlement: None,
+        };
+        assert_eq!(scheme.reward(3, Some(&decimal("0.1"))), decimal("0.32"));
+    }
+
+    #[test]
+    fn quality_score_is_winning_objective() {
+        let solution = |total| SolverSettlement {
+            objective: Objective {
+                total,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let competition = SolverCompetition {
+            solutions: vec![solution(1.), solution(3.)],
+            ..Default::default()
+        };
+        assert_eq!(quality_score(&competition), Some(BigDecimal::from(3)));
+        assert_eq!(quality_score(&Default::default()), None);
+        let competition = SolverCompetition {
+            solutions: vec![solution(f64::NAN)],
+            ..Default::default()
+        };
+        assert_eq!(quality_score(&competition), None);
+    }
+}
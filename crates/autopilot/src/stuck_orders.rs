@@ -0,0 +1,383 @@
+//! Diagnoses why open orders don't get matched.
+//!
+//! Orders that have been solvable for a while without getting settled are classified by the most
+//! likely reason, checking the reasons that can be determined with certainty first. The latest
+//! diagnoses replace the previous ones so that the orderbook can show them on the order status and
+//! report the stuck orders of integrators.
+
+use crate::{database::Postgres, solvable_orders::max_transfer_out_amount};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use model::{
+    order::{Order, OrderClass},
+    stuck_orders::StuckOrderReason,
+    time::now_in_epoch_seconds,
+};
+use primitive_types::H160;
+use prometheus::IntGaugeVec;
+use shared::{
+    account_balances::{BalanceFetching, TransferSimulationError},
+    arguments::duration_from_seconds,
+    bad_token::BadTokenDetecting,
+    maintenance::Maintaining,
+    price_estimation::{
+        native::{native_single_estimate, NativePriceEstimating},
+        PriceEstimationError,
+    },
+};
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Arguments related to the stuck order detector.
+#[derive(clap::Parser)]
+pub struct Arguments {
+    /// How long in seconds an order has to be open before it gets diagnosed.
+    #[clap(
+        long,
+        env,
+        default_value = "1800",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub stuck_order_age: Duration,
+
+    /// The value in native token below which the remaining sell amount of an order is considered
+    /// too small to be worth settling.
+    #[clap(long, env, default_value = "0.001")]
+    pub stuck_order_min_value: f64,
+
+    /// How often in seconds open orders get diagnosed.
+    #[clap(
+        long,
+        env,
+        default_value = "300",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub stuck_order_diagnosis_interval: Duration,
+}
+
+impl Display for Arguments {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "stuck_order_age: {:?}", self.stuck_order_age)?;
+        writeln!(f, "stuck_order_min_value: {}", self.stuck_order_min_value)?;
+        writeln!(
+            f,
+            "stuck_order_diagnosis_interval: {:?}",
+            self.stuck_order_diagnosis_interval
+        )?;
+        Ok(())
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage)]
+#[metric(subsystem = "stuck_orders")]
+struct Metrics {
+    /// Open orders that didn't get matched by their diagnosed reason.
+    #[metric(labels("reason"))]
+    stuck_orders: IntGaugeVec,
+}
+
+impl Metrics {
+    fn get() -> &'static Self {
+        Metrics::instance(global_metrics::get_metric_storage_registry()).unwrap()
+    }
+}
+
+pub struct StuckOrderDetector {
+    db: Postgres,
+    balance_fetcher: Arc<dyn BalanceFetching>,
+    bad_token_detector: Arc<dyn BadTokenDetecting>,
+    native_price_estimator: Arc<dyn NativePriceEstimating>,
+    min_age: Duration,
+    /// The minimum value in wei of the native token.
+    min_value: f64,
+    interval: Duration,
+    last_run: Mutex<Option<Instant>>,
+}
+
+impl StuckOrderDetector {
+    pub fn new(
+        db: Postgres,
+        balance_fetcher: Arc<dyn BalanceFetching>,
+        bad_token_detector: Arc<dyn BadTokenDetecting>,
+        native_price_estimator: Arc<dyn NativePriceEstimating>,
+        args: &Arguments,
+    ) -> Self {
+        Self {
+            db,
+            balance_fetcher,
+            bad_token_detector,
+            native_price_estimator,
+            min_age: args.stuck_order_age,
+            min_value: args.stuck_order_min_value * 1e18,
+            interval: args.stuck_order_diagnosis_interval,
+            last_run: Default::default(),
+        }
+    }
+
+    async fn diagnose_orders(&self) -> Result<()> {
+        let now = Utc::now();
+        let orders = self
+            .db
+            .solvable_orders(now_in_epoch_seconds())
+            .await?
+            .orders;
+        let mut diagnoses = Vec::new();
+        for order in orders
+            .iter()
+            .filter(|order| is_stuck(order, self.min_age, now))
+        {
+            match self.diagnose(order).await {
+                Ok(reason) => diagnoses.push((order.metadata.uid, reason)),
+                Err(err) => {
+                    tracing::debug!(uid = %order.metadata.uid, ?err, "failed to diagnose order")
+                }
+            }
+        }
+
+        let metrics = Metrics::get();
+        metrics.stuck_orders.reset();
+        for (_, reason) in &diagnoses {
+            metrics
+                .stuck_orders
+                .with_label_values(&[label(*reason)])
+                .inc();
+        }
+        self.db.replace_order_diagnoses(&diagnoses, now).await
+    }
+
+    /// Errors if the reason can't be determined right now, for example because a node request
+    /// failed.
+    async fn diagnose(&self, order: &Order) -> Result<StuckOrderReason> {
+        // Fee on transfer tokens can be bought because the receiver pays the fee.
+        if !self
+            .bad_token_detector
+            .detect(order.data.sell_token)
+            .await?
+            .is_good()
+            || !self
+                .bad_token_detector
+                .detect(order.data.buy_token)
+                .await?
+                .is_buyable()
+        {
+            return Ok(StuckOrderReason::TokenDenied);
+        }
+
+        let sell_amount = max_transfer_out_amount(order)?;
+        let transfer = self
+            .balance_fetcher
+            .can_transfer(
+                order.data.sell_token,
+                order.metadata.owner,
+                sell_amount,
+                order.data.sell_token_balance,
+            )
+            .await;
+        if let Some(reason) = transfer_reason(transfer)? {
+            return Ok(reason);
+        }
+
+        let sell_price = self.native_price(order.data.sell_token).await?;
+        let buy_price = self.native_price(order.data.buy_token).await?;
+        Ok(price_reason(
+            order,
+            sell_price,
+            buy_price,
+            sell_amount.to_f64_lossy(),
+            self.min_value,
+        ))
+    }
+
+    /// `None` if the token can't be priced because there is no liquidity for it.
+    async fn native_price(&self, token: H160) -> Result<Option<f64>> {
+        match native_single_estimate(self.native_price_estimator.as_ref(), &token).await {
+            Ok(price) => Ok(Some(price)),
+            Err(PriceEstimationError::NoLiquidity | PriceEstimationError::UnsupportedToken(_)) => {
+                Ok(None)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Market orders that are open for long enough that they should have been matched already. Limit
+/// and liquidity orders only get matched when the market allows it and TWAP parts wait for their
+/// turn, so they are never stuck.
+fn is_stuck(order: &Order, min_age: Duration, now: DateTime<Utc>) -> bool {
+    order.metadata.class == OrderClass::Market
+        && now
+            .signed_duration_since(order.metadata.creation_date)
+            .to_std()
+            .map_or(false, |age| age >= min_age)
+}
+
+fn transfer_reason(
+    transfer: Result<(), TransferSimulationError>,
+) -> Result<Option<StuckOrderReason>> {
+    match transfer {
+        Ok(()) => Ok(None),
+        Err(TransferSimulationError::InsufficientAllowance { .. }) => {
+            Ok(Some(StuckOrderReason::MissingAllowance))
+        }
+        Err(TransferSimulationError::InsufficientBalance { .. }) => {
+            Ok(Some(StuckOrderReason::InsufficientBalance))
+        }
+        // The owner holds and approved enough but the token itself reverts the transfer.
+        Err(TransferSimulationError::TransferFailed) => Ok(Some(StuckOrderReason::TokenDenied)),
+        Err(TransferSimulationError::Other(err)) => Err(err),
+    }
+}
+
+/// Compares the order's amounts in native token. The limit price only depends on the ratio of the
+/// amounts, so the full amounts are used for it while the value uses what is left to sell.
+fn price_reason(
+    order: &Order,
+    sell_price: Option<f64>,
+    buy_price: Option<f64>,
+    remaining_sell_amount: f64,
+    min_value: f64,
+) -> StuckOrderReason {
+    let (sell_price, buy_price) = match (sell_price, buy_price) {
+        (Some(sell_price), Some(buy_price)) => (sell_price, buy_price),
+        _ => return StuckOrderReason::NoLiquidityPath,
+    };
+    if remaining_sell_amount * sell_price < min_value {
+        return StuckOrderReason::TooSmall;
+    }
+    let sell_value = order.data.sell_amount.to_f64_lossy() * sell_price;
+    let buy_value = order.data.buy_amount.to_f64_lossy() * buy_price;
+    if buy_value > sell_value {
+        return StuckOrderReason::PriceOutOfRange;
+    }
+    StuckOrderReason::Unknown
+}
+
+fn label(reason: StuckOrderReason) -> &'static str {
+    match reason {
+        StuckOrderReason::TokenDenied => "token_denied",
+        StuckOrderReason::MissingAllowance => "missing_allowance",
+        StuckOrderReason::InsufficientBalance => "insufficient_balance",
+        StuckOrderReason::NoLiquidityPath => "no_liquidity_path",
+        StuckOrderReason::TooSmall => "too_small",
+        StuckOrderReason::PriceOutOfRange => "price_out_of_range",
+        StuckOrderReason::Unknown => "unknown",
+    }
+}
+
+fn is_due(last_run: Option<Instant>, interval: Duration, now: Instant) -> bool {
+    last_run.map_or(true, |last| now.duration_since(last) >= interval)
+}
+
+#[async_trait::async_trait]
+impl Maintaining for StuckOrderDetector {
+    async fn run_maintenance(&self) -> Result<()> {
+        let now = Instant::now();
+        if !is_due(*self.last_run.lock().unwrap(), self.interval, now) {
+            return Ok(());
+        }
+        self.diagnose_orders().await?;
+        *self.last_run.lock().unwrap() = Some(now);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::order::{OrderData, OrderMetadata};
+    use primitive_types::U256;
+
+    fn order(sell_amount: u64, buy_amount: u64) -> Order {
+        Order {
+            data: OrderData {
+                sell_amount: sell_amount.into(),
+                buy_amount: buy_amount.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn stuck_orders_are_old_user_orders() {
+        let now = Utc::now();
+        let min_age = Duration::from_secs(60);
+        let created = |seconds_ago| Order {
+            metadata: OrderMetadata {
+                creation_date: now - chrono::Duration::seconds(seconds_ago),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(is_stuck(&created(60), min_age, now));
+        assert!(!is_stuck(&created(59), min_age, now));
+
+        for class in [OrderClass::Limit, OrderClass::Liquidity, OrderClass::Twap] {
+            let mut order = created(120);
+            order.metadata.class = class;
+            assert!(!is_stuck(&order, min_age, now));
+        }
+    }
+
+    #[test]
+    fn classifies_transfers() {
+        assert_eq!(transfer_reason(Ok(())).unwrap(), None);
+        assert_eq!(
+            transfer_reason(Err(TransferSimulationError::InsufficientAllowance {
+                allowance: U256::zero(),
+                spender: H160::zero(),
+            }))
+            .unwrap(),
+            Some(StuckOrderReason::MissingAllowance)
+        );
+        assert_eq!(
+            transfer_reason(Err(TransferSimulationError::InsufficientBalance {
+                balance: U256::zero(),
+            }))
+            .unwrap(),
+            Some(StuckOrderReason::InsufficientBalance)
+        );
+        assert_eq!(
+            transfer_reason(Err(TransferSimulationError::TransferFailed)).unwrap(),
+            Some(StuckOrderReason::TokenDenied)
+        );
+        assert!(transfer_reason(Err(TransferSimulationError::Other(anyhow::anyhow!("")))).is_err());
+    }
+
+    #[test]
+    fn classifies_prices() {
+        let order = order(100, 100);
+        assert_eq!(
+            price_reason(&order, None, Some(1.), 100., 0.),
+            StuckOrderReason::NoLiquidityPath
+        );
+        assert_eq!(
+            price_reason(&order, Some(1.), None, 100., 0.),
+            StuckOrderReason::NoLiquidityPath
+        );
+        assert_eq!(
+            price_reason(&order, Some(1.), Some(1.), 10., 20.),
+            StuckOrderReason::TooSmall
+        );
+        assert_eq!(
+            price_reason(&order, Some(1.), Some(1.1), 100., 20.),
+            StuckOrderReason::PriceOutOfRange
+        );
+        assert_eq!(
+            price_reason(&order, Some(1.), Some(1.), 100., 20.),
+            StuckOrderReason::Unknown
+        );
+    }
+
+    #[test]
+    fn runs_after_interval() {
+        let now = Instant::now();
+        let interval = Duration::from_secs(300);
+        assert!(is_due(None, interval, now));
+        assert!(!is_due(Some(now), interval, now + interval / 2));
+        assert!(is_due(Some(now), interval, now + interval));
+    }
+}
@@ -0,0 +1,62 @@
+//! Sets up a Tenderly fork for end to end rehearsals of production auctions.
+
+use anyhow::{Context, Result};
+use contracts::{GPv2Settlement, WETH9};
+use ethcontract::Account;
+use primitive_types::{H160, U256};
+use shared::{
+    tenderly_api::{self, Fork, TenderlyApi},
+    Web3,
+};
+
+/// The ether balance solvers and users get on the fork.
+fn funding() -> U256 {
+    U256::exp10(21)
+}
+
+/// Creates a fork of the node's network and funds the accounts on it.
+///
+/// Solvers get ether to pay for gas. Users additionally get impersonated to wrap half of their
+/// ether and approve the vault relayer for it so that they can place WETH sell orders.
+pub async fn create(
+    api: &TenderlyApi,
+    node: &Web3,
+    client: &reqwest::Client,
+    block_number: Option<u64>,
+    solvers: &[H160],
+    users: &[H160],
+) -> Result<(Fork, Web3)> {
+    let network_id = node.net().version().await?;
+    let fork = api
+        .create_fork(&network_id, block_number)
+        .await
+        .context("failed to create fork")?;
+    let web3 = shared::web3(client, &fork.rpc_url, "tenderly_fork");
+
+    let accounts = solvers.iter().chain(users).copied().collect::<Vec<_>>();
+    tenderly_api::set_balance(&web3, &accounts, funding()).await?;
+
+    let weth = WETH9::deployed(&web3).await?;
+    let vault_relayer = GPv2Settlement::deployed(&web3)
+        .await?
+        .vault_relayer()
+        .call()
+        .await?;
+    for &user in users {
+        // Forks accept transactions from any account without a signature.
+        let account = Account::Local(user, None);
+        weth.deposit()
+            .from(account.clone())
+            .value(funding() / 2)
+            .send()
+            .await
+            .with_context(|| format!("failed to wrap ether of {user:?}"))?;
+        weth.approve(vault_relayer, U256::MAX)
+            .from(account)
+            .send()
+            .await
+            .with_context(|| format!("failed to approve WETH of {user:?}"))?;
+    }
+
+    Ok((fork, web3))
+}
@@ -0,0 +1,627 @@
+//! Indexes the routes of settled trades, i.e. the liquidity venues a settlement interacted with to
+//! fill each of its trades.
+//!
+//! Venues are identified by decoding the interactions of the settlement transaction's `settle`
+//! call. A swap is attributed to the trades that sell or buy one of the tokens it swaps. Calls to
+//! aggregators can't be decoded generically so they are attributed to all trades of the
+//! settlement.
+
+use crate::database::Postgres;
+use anyhow::{Context, Result};
+use contracts::{BalancerV2Vault, GPv2Settlement, IUniswapLikeRouter, UniswapV3SwapRouter};
+use database::{
+    byte_array::ByteArray,
+    events::EventIndex,
+    trade_routes::{SettlementTrade, UnindexedSettlement, Venue, VenueKind},
+};
+use ethcontract::common::abi::{Contract, Token};
+use hex_literal::hex;
+use model::{order::BUY_ETH_ADDRESS, TokenPair};
+use primitive_types::{H160, H256};
+use shared::{
+    addr,
+    maintenance::Maintaining,
+    sources::{
+        uniswap_v2::pair_provider::{create2_target_address, PairProvider},
+        BaselineSource,
+    },
+    Web3,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+use web3::{signing::keccak256, types::TransactionId};
+
+/// Number of settlements indexed per maintenance run which bounds the work while backfilling.
+const SETTLEMENTS_PER_RUN: i64 = 100;
+
+/// How often indexing a settlement may fail before it gets marked as indexed without routes so
+/// that it doesn't hold up the settlements after it. Other indexers of settlements give up after
+/// the same number of attempts.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// The aggregators that solvers commonly interact with.
+pub fn known_aggregators(chain_id: u64) -> HashMap<H160, VenueKind> {
+    let mut aggregators = HashMap::from([
+        (
+            addr!("1111111254fb6c44bac0bed2854e76f90643097d"),
+            VenueKind::OneInch,
+        ),
+        (
+            addr!("def171fe48cf0115b1d80b88dc8eab59176fee57"),
+            VenueKind::Paraswap,
+        ),
+    ]);
+    if chain_id == 1 {
+        aggregators.insert(
+            addr!("def1c0ded9bec7f1a1670819833240f027b25eff"),
+            VenueKind::ZeroEx,
+        );
+    }
+    aggregators
+}
+
+/// Returns the router of a UniswapV2-like baseline source.
+pub async fn uniswap_like_router(web3: &Web3, source: BaselineSource) -> Result<Option<H160>> {
+    Ok(Some(match source {
+        BaselineSource::UniswapV2 => contracts::UniswapV2Router02::deployed(web3)
+            .await?
+            .address(),
+        BaselineSource::SushiSwap => contracts::SushiSwapRouter::deployed(web3).await?.address(),
+        BaselineSource::Honeyswap => contracts::HoneyswapRouter::deployed(web3).await?.address(),
+        BaselineSource::Baoswap => contracts::BaoswapRouter::deployed(web3).await?.address(),
+        BaselineSource::Swapr => contracts::SwaprRouter::deployed(web3).await?.address(),
+        BaselineSource::BalancerV2 | BaselineSource::ZeroEx | BaselineSource::UniswapV3 => {
+            return Ok(None)
+        }
+    }))
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct UniswapV3Contracts {
+    pub router: H160,
+    pub factory: H160,
+}
+
+/// The contracts needed to identify the venues of settlement interactions.
+#[derive(Clone, Debug, Default)]
+pub struct RouteClassifier {
+    /// UniswapV2-like routers and the pair providers of their factories.
+    pub uniswap_v2_routers: HashMap<H160, PairProvider>,
+    pub uniswap_v3: Option<UniswapV3Contracts>,
+    pub balancer_vault: Option<H160>,
+    pub aggregators: HashMap<H160, VenueKind>,
+    /// Settlements trade the native token wrapper for orders buying ETH.
+    pub native_token: H160,
+}
+
+/// A decoded swap interaction.
+#[derive(Debug, Eq, PartialEq)]
+struct Swap {
+    venues: Vec<Venue>,
+    /// The traded tokens or `None` if they are not known.
+    tokens: Option<HashSet<H160>>,
+}
+
+impl RouteClassifier {
+    /// Returns the venues of every trade of a settlement from the settlement transaction's input.
+    ///
+    /// Transactions that are not a direct call to `settle` (for example because a solver used a
+    /// wrapper contract) result in empty routes.
+    pub fn trade_routes(&self, input: &[u8], trades: &[SettlementTrade]) -> Vec<Vec<Venue>> {
+        let swaps = match self.decode_swaps(input) {
+            Some(swaps) => swaps,
+            None => {
+                tracing::debug!("settlement transaction is not a settle call");
+                Vec::new()
+            }
+        };
+
+        trades
+            .iter()
+            .map(|trade| {
+                let tokens = [trade.sell_token, trade.buy_token].map(|token| {
+                    let token = H160(token.0);
+                    if token == BUY_ETH_ADDRESS {
+                        self.native_token
+                    } else {
+                        token
+                    }
+                });
+                let mut route = Vec::<Venue>::new();
+                for swap in &swaps {
+                    let relevant = match &swap.tokens {
+                        Some(swapped) => tokens.iter().any(|token| swapped.contains(token)),
+                        None => true,
+                    };
+                    if !relevant {
+                        continue;
+                    }
+                    for venue in &swap.venues {
+                        if !route.contains(venue) {
+                            route.push(*venue);
+                        }
+                    }
+                }
+                route
+            })
+            .collect()
+    }
+
+    fn decode_swaps(&self, input: &[u8]) -> Option<Vec<Swap>> {
+        let (_, params) = decode_call(&GPv2Settlement::raw_contract().abi, input, &["settle"])?;
+        let interactions = params.into_iter().nth(3)?.into_fixed_array()?;
+        let swaps = interactions
+            .into_iter()
+            .filter_map(Token::into_array)
+            .flatten()
+            .filter_map(|interaction| {
+                let mut interaction = interaction.into_tuple()?.into_iter();
+                let target = interaction.next()?.into_address()?;
+                let calldata = interaction.nth(1)?.into_bytes()?;
+                self.classify(target, &calldata)
+            })
+            .collect();
+        Some(swaps)
+    }
+
+    fn classify(&self, target: H160, calldata: &[u8]) -> Option<Swap> {
+        if let Some(kind) = self.aggregators.get(&target) {
+            return Some(Swap {
+                venues: vec![venue(*kind, target)],
+                tokens: None,
+            });
+        }
+        if let Some(pair_provider) = self.uniswap_v2_routers.get(&target) {
+            return uniswap_v2_swap(pair_provider, calldata);
+        }
+        match self.uniswap_v3 {
+            Some(uniswap_v3) if uniswap_v3.router == target => {
+                return uniswap_v3_swap(uniswap_v3.factory, calldata)
+            }
+            _ => (),
+        }
+        if self.balancer_vault == Some(target) {
+            return balancer_v2_swap(calldata);
+        }
+        None
+    }
+}
+
+fn uniswap_v2_swap(pair_provider: &PairProvider, calldata: &[u8]) -> Option<Swap> {
+    let (_, params) = decode_call(
+        &IUniswapLikeRouter::raw_contract().abi,
+        calldata,
+        &["swapExactTokensForTokens", "swapTokensForExactTokens"],
+    )?;
+    let path = params
+        .into_iter()
+        .nth(2)?
+        .into_array()?
+        .into_iter()
+        .map(Token::into_address)
+        .collect::<Option<Vec<_>>>()?;
+    let venues = path
+        .windows(2)
+        .filter_map(|tokens| TokenPair::new(tokens[0], tokens[1]))
+        .map(|pair| venue(VenueKind::UniswapV2, pair_provider.pair_address(&pair)))
+        .collect();
+    Some(Swap {
+        venues,
+        tokens: Some(path.into_iter().collect()),
+    })
+}
+
+fn uniswap_v3_swap(factory: H160, calldata: &[u8]) -> Option<Swap> {
+    let (name, params) = decode_call(
+        &UniswapV3SwapRouter::raw_contract().abi,
+        calldata,
+        &[
+            "exactInputSingle",
+            "exactOutputSingle",
+            "exactInput",
+            "exactOutput",
+        ],
+    )?;
+    let mut params = params.into_iter().next()?.into_tuple()?.into_iter();
+    let hops = match name {
+        "exactInputSingle" | "exactOutputSingle" => {
+            let token_in = params.next()?.into_address()?;
+            let token_out = params.next()?.into_address()?;
+            let fee = params.next()?.into_uint()?.try_into().ok()?;
+            vec![(token_in, token_out, fee)]
+        }
+        _ => uniswap_v3_path(&params.next()?.into_bytes()?)?,
+    };
+    Some(Swap {
+        venues: hops
+            .iter()
+            .map(|(a, b, fee)| venue(VenueKind::UniswapV3, uniswap_v3_pool(factory, *a, *b, *fee)))
+            .collect(),
+        tokens: Some(hops.iter().flat_map(|(a, b, _)| [*a, *b]).collect()),
+    })
+}
+
+/// Parses a packed path of `token (fee token)*` into its hops.
+fn uniswap_v3_path(path: &[u8]) -> Option<Vec<(H160, H160, u32)>> {
+    if path.len() < 20 || (path.len() - 20) % 23 != 0 {
+        return None;
+    }
+    let mut hops = Vec::new();
+    let mut token_in = H160::from_slice(&path[..20]);
+    for hop in path[20..].chunks(23) {
+        let fee = u32::from_be_bytes([0, hop[0], hop[1], hop[2]]);
+        let token_out = H160::from_slice(&hop[3..]);
+        hops.push((token_in, token_out, fee));
+        token_in = token_out;
+    }
+    Some(hops)
+}
+
+fn uniswap_v3_pool(factory: H160, token_a: H160, token_b: H160, fee: u32) -> H160 {
+    // https://github.com/Uniswap/v3-periphery/blob/main/contracts/libraries/PoolAddress.sol
+    const POOL_INIT_CODE_HASH: [u8; 32] =
+        hex!("e34f199b19b2b4f47f68442619d555527d244f78a3297ea89325f843f87b8b54");
+    let (token0, token1) = if token_a < token_b {
+        (token_a, token_b)
+    } else {
+        (token_b, token_a)
+    };
+    let mut key = [0u8; 96];
+    key[12..32].copy_from_slice(token0.as_bytes());
+    key[44..64].copy_from_slice(token1.as_bytes());
+    key[92..96].copy_from_slice(&fee.to_be_bytes());
+    create2_target_address(factory, &keccak256(&key), &POOL_INIT_CODE_HASH)
+}
+
+fn balancer_v2_swap(calldata: &[u8]) -> Option<Swap> {
+    let (name, params) = decode_call(
+        &BalancerV2Vault::raw_contract().abi,
+        calldata,
+        &["swap", "batchSwap"],
+    )?;
+    let mut params = params.into_iter();
+    let (pool_ids, tokens) = match name {
+        "swap" => {
+            let mut single_swap = params.next()?.into_tuple()?.into_iter();
+            let pool_id = single_swap.next()?.into_fixed_bytes()?;
+            let asset_in = single_swap.nth(1)?.into_address()?;
+            let asset_out = single_swap.next()?.into_address()?;
+            (vec![pool_id], vec![asset_in, asset_out])
+        }
+        _ => {
+            let steps = params.nth(1)?.into_array()?;
+            let assets = params
+                .next()?
+                .into_array()?
+                .into_iter()
+                .map(Token::into_address)
+                .collect::<Option<Vec<_>>>()?;
+            let pool_ids = steps
+                .into_iter()
+                .map(|step| step.into_tuple()?.into_iter().next()?.into_fixed_bytes())
+                .collect::<Option<Vec<_>>>()?;
+            (pool_ids, assets)
+        }
+    };
+
+    let mut venues = Vec::new();
+    for pool_id in pool_ids {
+        // The first 20 bytes of a pool id are the pool address.
+        let pool = venue(VenueKind::BalancerV2, H160::from_slice(pool_id.get(..20)?));
+        if !venues.contains(&pool) {
+            venues.push(pool);
+        }
+    }
+    Some(Swap {
+        venues,
+        tokens: Some(tokens.into_iter().collect()),
+    })
+}
+
+/// Decodes the parameters of a call to one of the specified functions.
+fn decode_call(
+    abi: &Contract,
+    calldata: &[u8],
+    functions: &[&'static str],
+) -> Option<(&'static str, Vec<Token>)> {
+    let selector = calldata.get(..4)?;
+    functions.iter().find_map(|name| {
+        let function = abi.function(name).ok()?;
+        if function.short_signature() != selector {
+            return None;
+        }
+        Some((*name, function.decode_input(&calldata[4..]).ok()?))
+    })
+}
+
+fn venue(kind: VenueKind, address: H160) -> Venue {
+    Venue {
+        kind,
+        address: ByteArray(address.0),
+    }
+}
+
+pub struct TradeRouteIndexer {
+    db: Postgres,
+    web3: Web3,
+    classifier: RouteClassifier,
+    failures: FailedAttempts,
+}
+
+impl TradeRouteIndexer {
+    pub fn new(db: Postgres, web3: Web3, classifier: RouteClassifier) -> Self {
+        Self {
+            db,
+            web3,
+            classifier,
+            failures: Default::default(),
+        }
+    }
+
+    async fn index_settlement(
+        &self,
+        index: &EventIndex,
+        settlement: &UnindexedSettlement,
+    ) -> Result<()> {
+        let transaction = self
+            .web3
+            .eth()
+            .transaction(TransactionId::Hash(H256(settlement.tx_hash.0)))
+            .await?
+            .context("settlement transaction not found")?;
+        let trades = self.db.settlement_trades(index).await?;
+        let routes = self.classifier.trade_routes(&transaction.input.0, &trades);
+        let trades = trades
+            .iter()
+            .zip(routes)
+            .map(|(trade, route)| {
+                let trade = EventIndex {
+                    block_number: index.block_number,
+                    log_index: trade.log_index,
+                };
+                (trade, route)
+            })
+            .collect::<Vec<_>>();
+        self.db.insert_trade_routes(index, &trades).await
+    }
+}
+
+/// Counts how often indexing each settlement failed.
+#[derive(Default)]
+pub(crate) struct FailedAttempts(Mutex<HashMap<EventIndex, u32>>);
+
+impl FailedAttempts {
+    /// Records a failed attempt and returns whether the settlement should be given up on.
+    pub(crate) fn failed(&self, index: &EventIndex) -> bool {
+        let mut failures = self.0.lock().unwrap();
+        let attempts = failures.entry(*index).or_default();
+        *attempts += 1;
+        if *attempts < MAX_ATTEMPTS {
+            return false;
+        }
+        failures.remove(index);
+        true
+    }
+
+    pub(crate) fn succeeded(&self, index: &EventIndex) {
+        self.0.lock().unwrap().remove(index);
+    }
+}
+
+#[async_trait::async_trait]
+impl Maintaining for TradeRouteIndexer {
+    async fn run_maintenance(&self) -> Result<()> {
+        let settlements = self.db.unindexed_settlements(SETTLEMENTS_PER_RUN).await?;
+        for settlement in settlements {
+            let index = EventIndex {
+                block_number: settlement.block_number,
+                log_index: settlement.log_index,
+            };
+            let err = match self.index_settlement(&index, &settlement).await {
+                Ok(()) => {
+                    self.failures.succeeded(&index);
+                    continue;
+                }
+                Err(err) => err,
+            };
+            if !self.failures.failed(&index) {
+                tracing::warn!(
+                    ?settlement,
+                    ?err,
+                    "failed to index trade routes, retrying later"
+                );
+                continue;
+            }
+            tracing::error!(
+                ?settlement,
+                ?err,
+                "failed to index trade routes repeatedly, marking settlement as indexed without routes"
+            );
+            self.db.insert_trade_routes(&index, &[]).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethcontract::{Bytes, U256};
+    use shared::dummy_contract;
+
+    fn settle_calldata(interactions: Vec<(H160, Vec<u8>)>) -> Vec<u8> {
+        let settlement = dummy_contract!(GPv2Settlement, H160::zero());
+        let interactions = interactions
+            .into_iter()
+            .map(|(target, calldata)| (target, U256::zero(), Bytes(calldata)))
+            .collect();
+        settlement
+            .settle(vec![], vec![], vec![], [vec![], interactions, vec![]])
+            .tx
+            .data
+            .unwrap()
+            .0
+    }
+
+    fn trade(log_index: i64, sell_token: H160, buy_token: H160) -> SettlementTrade {
+        SettlementTrade {
+            log_index,
+            sell_token: ByteArray(sell_token.0),
+            buy_token: ByteArray(buy_token.0),
+        }
+    }
+
+    #[test]
+    fn attributes_swaps_to_trades_by_token() {
+        let (a, b, c, d) = (H160([1; 20]), H160([2; 20]), H160([3; 20]), H160([4; 20]));
+        let uniswap_router = H160([0x10; 20]);
+        let vault = H160([0x11; 20]);
+        let pair_provider = PairProvider {
+            factory: H160([0x12; 20]),
+            init_code_digest: [0x13; 32],
+        };
+        let classifier = RouteClassifier {
+            uniswap_v2_routers: HashMap::from([(uniswap_router, pair_provider.clone())]),
+            balancer_vault: Some(vault),
+            ..Default::default()
+        };
+
+        let uniswap_swap = dummy_contract!(IUniswapLikeRouter, uniswap_router)
+            .swap_tokens_for_exact_tokens(1.into(), 2.into(), vec![a, b, c], H160::zero(), 3.into())
+            .tx
+            .data
+            .unwrap()
+            .0;
+        let pool_id = [[0x14; 20].as_slice(), &[0; 12]].concat();
+        let balancer_swap = dummy_contract!(BalancerV2Vault, vault)
+            .swap(
+                (
+                    Bytes(pool_id.try_into().unwrap()),
+                    1,
+                    d,
+                    H160([5; 20]),
+                    1.into(),
+                    Bytes(vec![]),
+                ),
+                (H160::zero(), false, H160::zero(), false),
+                1.into(),
+                1.into(),
+            )
+            .tx
+            .data
+            .unwrap()
+            .0;
+        let input = settle_calldata(vec![
+            (uniswap_router, uniswap_swap),
+            (vault, balancer_swap),
+            // Unknown interactions like approvals are ignored.
+            (H160([0x15; 20]), vec![1, 2, 3, 4]),
+        ]);
+
+        let routes = classifier.trade_routes(
+            &input,
+            &[
+                trade(0, a, c),
+                trade(1, d, BUY_ETH_ADDRESS),
+                trade(2, H160([6; 20]), a),
+            ],
+        );
+        let pair = |x, y| {
+            venue(
+                VenueKind::UniswapV2,
+                pair_provider.pair_address(&TokenPair::new(x, y).unwrap()),
+            )
+        };
+        assert_eq!(
+            routes,
+            vec![
+                vec![pair(a, b), pair(b, c)],
+                vec![venue(VenueKind::BalancerV2, H160([0x14; 20]))],
+                vec![pair(a, b), pair(b, c)],
+            ]
+        );
+    }
+
+    #[test]
+    fn aggregators_are_attributed_to_all_trades() {
+        let aggregator = H160([0x10; 20]);
+        let classifier = RouteClassifier {
+            aggregators: HashMap::from([(aggregator, VenueKind::ZeroEx)]),
+            ..Default::default()
+        };
+        let input = settle_calldata(vec![(aggregator, vec![0xff; 100])]);
+        let routes = classifier.trade_routes(
+            &input,
+            &[
+                trade(0, H160([1; 20]), H160([2; 20])),
+                trade(1, H160([3; 20]), H160([4; 20])),
+            ],
+        );
+        assert_eq!(routes, vec![vec![venue(VenueKind::ZeroEx, aggregator)]; 2]);
+    }
+
+    #[test]
+    fn non_settle_calls_have_empty_routes() {
+        let classifier = RouteClassifier::default();
+        let routes = classifier.trade_routes(&[1, 2, 3, 4, 5], &[SettlementTrade::default()]);
+        assert_eq!(routes, vec![Vec::<Venue>::new()]);
+    }
+
+    #[test]
+    fn decodes_uniswap_v3_paths() {
+        let (a, b, c) = (H160([1; 20]), H160([2; 20]), H160([3; 20]));
+        let path = [
+            a.as_bytes(),
+            &[0x00, 0x0b, 0xb8],
+            b.as_bytes(),
+            &[0x00, 0x01, 0xf4],
+            c.as_bytes(),
+        ]
+        .concat();
+        assert_eq!(
+            uniswap_v3_path(&path).unwrap(),
+            vec![(a, b, 3000), (b, c, 500)]
+        );
+        assert!(uniswap_v3_path(&path[..30]).is_none());
+    }
+
+    #[test]
+    fn computes_uniswap_v3_pool_address() {
+        // https://etherscan.io/address/0x88e6a0c2ddd26feeb64f039a2c41296fcb3f5640 (USDC/WETH 0.05%)
+        assert_eq!(
+            uniswap_v3_pool(
+                addr!("1F98431c8aD98523631AE4a59f267346ea31F984"),
+                addr!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+                addr!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+                500,
+            ),
+            addr!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"),
+        );
+    }
+
+    #[test]
+    fn gives_up_on_settlements_after_repeated_failures() {
+        let failures = FailedAttempts::default();
+        let index = EventIndex {
+            block_number: 1,
+            log_index: 2,
+        };
+        let other = EventIndex {
+            block_number: 1,
+            log_index: 3,
+        };
+        for _ in 1..MAX_ATTEMPTS {
+            assert!(!failures.failed(&index));
+        }
+        assert!(!failures.failed(&other));
+        assert!(failures.failed(&index));
+
+        // Successes reset the count.
+        failures.succeeded(&other);
+        for _ in 1..MAX_ATTEMPTS {
+            assert!(!failures.failed(&other));
+        }
+    }
+}
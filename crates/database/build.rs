@@ -0,0 +1,34 @@
+//! Embeds the SQL migrations of the repository's `database/sql` directory into the crate so that
+//! binaries know which schema they were built for.
+
+use std::{env, fmt::Write, fs, path::Path};
+
+fn main() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../database/sql")
+        .canonicalize()
+        .expect("missing migrations directory");
+    println!("cargo:rerun-if-changed={}", dir.display());
+
+    let mut scripts = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.ends_with(".sql"))
+        .collect::<Vec<_>>();
+    scripts.sort();
+
+    let mut code = String::from("&[\n");
+    for script in scripts {
+        let path = dir.join(&script);
+        writeln!(
+            code,
+            "    ({:?}, include_str!({:?})),",
+            script,
+            path.display().to_string()
+        )
+        .unwrap();
+    }
+    code.push(']');
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("migrations.rs");
+    fs::write(out, code).unwrap();
+}
@@ -0,0 +1,198 @@
+use crate::Address;
+use bigdecimal::BigDecimal;
+use sqlx::PgConnection;
+
+/// Recomputes the accounting totals without blocking concurrent reads.
+pub async fn refresh(ex: &mut PgConnection) -> Result<(), sqlx::Error> {
+    const QUERY: &str = "REFRESH MATERIALIZED VIEW CONCURRENTLY solver_accounting;";
+    sqlx::query(QUERY).execute(ex).await?;
+    Ok(())
+}
+
+/// The totals of the trades a solver settled in one token as of the last refresh. The amounts
+/// are integers but can exceed 256 bits and the surplus can be negative.
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct SolverTokenTotals {
+    pub solver: Address,
+    pub token: Address,
+    /// The trades that paid their fee in the token.
+    pub trades: i64,
+    pub fees: BigDecimal,
+    pub protocol_fees: BigDecimal,
+    pub surplus: BigDecimal,
+}
+
+/// Returns the totals of every solver and token, ordered by solver and token.
+pub async fn load_solver_totals(
+    ex: &mut PgConnection,
+) -> Result<Vec<SolverTokenTotals>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT solver, token, trades, fees, protocol_fees, surplus
+FROM solver_accounting
+ORDER BY solver, token
+    "#;
+    sqlx::query_as(QUERY).fetch_all(ex).await
+}
+
+/// The sums of the indexed trades of one block.
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct BlockTradeTotals {
+    pub block_number: i64,
+    pub trades: i64,
+    pub sell_amount: BigDecimal,
+    pub buy_amount: BigDecimal,
+    pub fee_amount: BigDecimal,
+}
+
+/// Returns the sums of the indexed trades of every block in `[from_block, to_block]` that has
+/// trades, ordered by block.
+pub async fn block_trade_totals(
+    ex: &mut PgConnection,
+    from_block: i64,
+    to_block: i64,
+) -> Result<Vec<BlockTradeTotals>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT
+    block_number,
+    COUNT(*) AS trades,
+    SUM(sell_amount) AS sell_amount,
+    SUM(buy_amount) AS buy_amount,
+    SUM(fee_amount) AS fee_amount
+FROM trades
+WHERE block_number >= $1 AND block_number <= $2
+GROUP BY block_number
+ORDER BY block_number
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(from_block)
+        .bind(to_block)
+        .fetch_all(ex)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        byte_array::ByteArray,
+        events::{Event, EventIndex, Settlement, Trade},
+        orders::{insert_order, Order, OrderKind},
+    };
+    use sqlx::Connection;
+    use std::str::FromStr;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_solver_accounting() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let solver = ByteArray([1; 20]);
+        let (sell_token, buy_token) = (ByteArray([2; 20]), ByteArray([3; 20]));
+        // Amounts close to the maximum of 256 bits so that the sums exceed it.
+        let max = BigDecimal::from_str(
+            "115792089237316195423570985008687907853269984665640564039457584007913129639935",
+        )
+        .unwrap();
+        let orders = [
+            Order {
+                uid: ByteArray([1; 56]),
+                kind: OrderKind::Sell,
+                sell_token,
+                buy_token,
+                sell_amount: 100.into(),
+                buy_amount: 100.into(),
+                fee_amount: max.clone(),
+                protocol_fee_amount: max.clone(),
+                ..Default::default()
+            },
+            Order {
+                uid: ByteArray([2; 56]),
+                kind: OrderKind::Buy,
+                sell_token,
+                buy_token,
+                sell_amount: 100.into(),
+                buy_amount: 100.into(),
+                fee_amount: max.clone(),
+                ..Default::default()
+            },
+        ];
+        for order in &orders {
+            insert_order(&mut db, order).await.unwrap();
+        }
+        let index = |log_index| EventIndex {
+            block_number: 1,
+            log_index,
+        };
+        crate::events::append(
+            &mut db,
+            &[
+                // Got 10 more than the limit price.
+                (
+                    index(0),
+                    Event::Trade(Trade {
+                        order_uid: ByteArray([1; 56]),
+                        sell_amount_including_fee: &max + BigDecimal::from(100),
+                        buy_amount: 110.into(),
+                        fee_amount: max.clone(),
+                    }),
+                ),
+                // Paid 20 more than the limit price.
+                (
+                    index(1),
+                    Event::Trade(Trade {
+                        order_uid: ByteArray([2; 56]),
+                        sell_amount_including_fee: &max + BigDecimal::from(120),
+                        buy_amount: 100.into(),
+                        fee_amount: max.clone(),
+                    }),
+                ),
+                (
+                    index(2),
+                    Event::Settlement(Settlement {
+                        solver,
+                        ..Default::default()
+                    }),
+                ),
+            ],
+        )
+        .await
+        .unwrap();
+
+        refresh(&mut db).await.unwrap();
+        assert_eq!(
+            load_solver_totals(&mut db).await.unwrap(),
+            [
+                SolverTokenTotals {
+                    solver,
+                    token: sell_token,
+                    trades: 2,
+                    fees: &max + &max,
+                    protocol_fees: max.clone(),
+                    surplus: (-20).into(),
+                },
+                SolverTokenTotals {
+                    solver,
+                    token: buy_token,
+                    trades: 0,
+                    fees: 0.into(),
+                    protocol_fees: 0.into(),
+                    surplus: 10.into(),
+                },
+            ]
+        );
+
+        assert_eq!(
+            block_trade_totals(&mut db, 0, 1).await.unwrap(),
+            [BlockTradeTotals {
+                block_number: 1,
+                trades: 2,
+                sell_amount: &max + &max + BigDecimal::from(220),
+                buy_amount: 210.into(),
+                fee_amount: &max + &max,
+            }]
+        );
+        assert!(block_trade_totals(&mut db, 2, 3).await.unwrap().is_empty());
+    }
+}
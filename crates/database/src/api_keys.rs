@@ -0,0 +1,203 @@
+use crate::{Address, PgTransaction};
+use chrono::{DateTime, Utc};
+use sqlx::PgConnection;
+
+pub type KeyHash = crate::byte_array::ByteArray<32>;
+
+/// One row in the `api_keys` table.
+#[derive(Clone, Debug, Eq, PartialEq, sqlx::FromRow)]
+pub struct ApiKey {
+    pub key_hash: KeyHash,
+    pub owner: Address,
+    pub valid_to: i64,
+    pub creation_timestamp: DateTime<Utc>,
+    pub revocation_timestamp: Option<DateTime<Utc>>,
+}
+
+pub async fn insert(ex: &mut PgConnection, key: &ApiKey) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO api_keys (key_hash, owner, valid_to, creation_timestamp, revocation_timestamp)
+VALUES ($1, $2, $3, $4, $5)
+    ;"#;
+    sqlx::query(QUERY)
+        .bind(key.key_hash)
+        .bind(key.owner)
+        .bind(key.valid_to)
+        .bind(key.creation_timestamp)
+        .bind(key.revocation_timestamp)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+pub async fn fetch(
+    ex: &mut PgConnection,
+    key_hash: &KeyHash,
+) -> Result<Option<ApiKey>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT * FROM api_keys
+WHERE key_hash = $1
+    ;"#;
+    sqlx::query_as(QUERY)
+        .bind(key_hash)
+        .fetch_optional(ex)
+        .await
+}
+
+/// Returns the owner of the key if it exists, has not been revoked and has not expired at `now`.
+pub async fn active_key_owner(
+    ex: &mut PgConnection,
+    key_hash: &KeyHash,
+    now: DateTime<Utc>,
+) -> Result<Option<Address>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT owner FROM api_keys
+WHERE key_hash = $1 AND revocation_timestamp IS NULL AND valid_to >= $2
+    ;"#;
+    let owner: Option<(Address,)> = sqlx::query_as(QUERY)
+        .bind(key_hash)
+        .bind(now.timestamp())
+        .fetch_optional(ex)
+        .await?;
+    Ok(owner.map(|inner| inner.0))
+}
+
+/// Revokes the key if it belongs to `owner`.
+///
+/// Returns whether a key was revoked.
+pub async fn revoke(
+    ex: &mut PgConnection,
+    key_hash: &KeyHash,
+    owner: &Address,
+    now: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    const QUERY: &str = r#"
+UPDATE api_keys
+SET revocation_timestamp = $3
+WHERE key_hash = $1 AND owner = $2 AND revocation_timestamp IS NULL
+    ;"#;
+    let result = sqlx::query(QUERY)
+        .bind(key_hash)
+        .bind(owner)
+        .bind(now)
+        .execute(ex)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Inserts a new key and revokes the key it replaces in a single transaction.
+///
+/// Returns whether the replaced key was revoked. If it was not (because it does not exist, is
+/// already revoked or belongs to a different owner) the caller should not commit the transaction.
+pub async fn rotate(
+    ex: &mut PgTransaction<'_>,
+    new_key: &ApiKey,
+    replaced_key_hash: &KeyHash,
+) -> Result<bool, sqlx::Error> {
+    insert(ex, new_key).await?;
+    revoke(
+        ex,
+        replaced_key_hash,
+        &new_key.owner,
+        new_key.creation_timestamp,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_array::ByteArray;
+    use chrono::NaiveDateTime;
+    use sqlx::Connection;
+
+    fn key(key_hash: u8, owner: u8) -> ApiKey {
+        ApiKey {
+            key_hash: ByteArray([key_hash; 32]),
+            owner: ByteArray([owner; 20]),
+            valid_to: 100,
+            creation_timestamp: DateTime::from_utc(NaiveDateTime::from_timestamp(0, 0), Utc),
+            revocation_timestamp: None,
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_api_key_roundtrip() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let key = key(1, 2);
+        insert(&mut db, &key).await.unwrap();
+        assert_eq!(fetch(&mut db, &key.key_hash).await.unwrap().unwrap(), key);
+
+        let before_expiry = DateTime::from_utc(NaiveDateTime::from_timestamp(100, 0), Utc);
+        let after_expiry = DateTime::from_utc(NaiveDateTime::from_timestamp(101, 0), Utc);
+        assert_eq!(
+            active_key_owner(&mut db, &key.key_hash, before_expiry)
+                .await
+                .unwrap(),
+            Some(key.owner)
+        );
+        assert_eq!(
+            active_key_owner(&mut db, &key.key_hash, after_expiry)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_revoke_api_key() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let key = key(1, 2);
+        let now = key.creation_timestamp;
+        insert(&mut db, &key).await.unwrap();
+
+        // Only the owner can revoke a key.
+        assert!(!revoke(&mut db, &key.key_hash, &ByteArray([3; 20]), now)
+            .await
+            .unwrap());
+        assert!(revoke(&mut db, &key.key_hash, &key.owner, now)
+            .await
+            .unwrap());
+        assert!(!revoke(&mut db, &key.key_hash, &key.owner, now)
+            .await
+            .unwrap());
+        assert_eq!(
+            active_key_owner(&mut db, &key.key_hash, now).await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_rotate_api_key() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let old_key = key(1, 2);
+        let new_key = key(2, 2);
+        let now = new_key.creation_timestamp;
+        insert(&mut db, &old_key).await.unwrap();
+        assert!(rotate(&mut db, &new_key, &old_key.key_hash).await.unwrap());
+        assert_eq!(
+            active_key_owner(&mut db, &old_key.key_hash, now)
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            active_key_owner(&mut db, &new_key.key_hash, now)
+                .await
+                .unwrap(),
+            Some(new_key.owner)
+        );
+    }
+}
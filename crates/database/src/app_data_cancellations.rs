@@ -0,0 +1,148 @@
+use crate::{Address, AppId, OrderUid, PgTransaction};
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    PgConnection,
+};
+
+/// Cancels all open orders with the specified app data as of `now` and records the cancellation.
+/// Orders that were placed on-chain are skipped because they have to be cancelled on-chain.
+///
+/// Returns the uids of the cancelled orders.
+pub async fn cancel_orders(
+    ex: &mut PgTransaction<'_>,
+    app_data: &AppId,
+    owner: &Address,
+    now: DateTime<Utc>,
+) -> Result<Vec<OrderUid>, sqlx::Error> {
+    const CANCEL: &str = r#"
+UPDATE orders o
+SET cancellation_timestamp = $2
+WHERE o.app_data = $1
+AND o.valid_to >= $3
+AND o.cancellation_timestamp IS NULL
+AND o.class NOT IN ('limit', 'liquidity')
+AND NOT EXISTS (SELECT 1 FROM onchain_placed_orders op WHERE op.uid = o.uid)
+AND CASE o.kind
+    WHEN 'sell' THEN (SELECT COALESCE(SUM(t.sell_amount), 0) FROM trades t WHERE t.order_uid = o.uid) < o.sell_amount
+    WHEN 'buy' THEN (SELECT COALESCE(SUM(t.buy_amount), 0) FROM trades t WHERE t.order_uid = o.uid) < o.buy_amount
+END
+RETURNING o.uid
+    "#;
+    let uids: Vec<OrderUid> = sqlx::query_scalar(CANCEL)
+        .bind(app_data)
+        .bind(now)
+        .bind(now.timestamp())
+        .fetch_all(&mut *ex)
+        .await?;
+
+    const RECORD: &str = r#"
+INSERT INTO app_data_cancellations (app_data, owner, timestamp, orders)
+VALUES ($1, $2, $3, $4)
+    "#;
+    sqlx::query(RECORD)
+        .bind(app_data)
+        .bind(owner)
+        .bind(now)
+        .bind(uids.len() as i64)
+        .execute(&mut *ex)
+        .await?;
+    Ok(uids)
+}
+
+/// One row in the `app_data_cancellations` table.
+#[derive(Clone, Debug, Eq, PartialEq, sqlx::FromRow)]
+pub struct AppDataCancellation {
+    pub app_data: AppId,
+    pub owner: Address,
+    pub timestamp: DateTime<Utc>,
+    pub orders: i64,
+}
+
+pub async fn load(
+    ex: &mut PgConnection,
+    app_data: &AppId,
+) -> Result<Vec<AppDataCancellation>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT * FROM app_data_cancellations
+WHERE app_data = $1
+ORDER BY timestamp
+    "#;
+    sqlx::query_as(QUERY).bind(app_data).fetch_all(ex).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        byte_array::ByteArray,
+        events::{Event, EventIndex, Trade},
+        orders::{insert_order, Order, OrderKind},
+    };
+    use sqlx::Connection;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_cancel_orders_by_app_data() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let now = Utc::now();
+        let app_data = ByteArray([1; 32]);
+        let order = |uid, app_data, valid_to| Order {
+            uid: ByteArray([uid; 56]),
+            app_data,
+            valid_to,
+            kind: OrderKind::Sell,
+            sell_amount: 10.into(),
+            ..Default::default()
+        };
+        let valid_to = now.timestamp() + 60;
+        for order in [
+            order(1, app_data, valid_to),
+            // Other app data.
+            order(2, ByteArray([2; 32]), valid_to),
+            // Expired.
+            order(3, app_data, now.timestamp() - 60),
+            // Fully executed.
+            order(4, app_data, valid_to),
+            // Already cancelled.
+            order(5, app_data, valid_to),
+        ] {
+            insert_order(&mut db, &order).await.unwrap();
+        }
+        crate::events::append(
+            &mut db,
+            &[(
+                EventIndex::default(),
+                Event::Trade(Trade {
+                    order_uid: ByteArray([4; 56]),
+                    sell_amount_including_fee: 10.into(),
+                    ..Default::default()
+                }),
+            )],
+        )
+        .await
+        .unwrap();
+        crate::orders::cancel_order(&mut db, &ByteArray([5; 56]), now)
+            .await
+            .unwrap();
+
+        let owner = ByteArray([3; 20]);
+        let uids = cancel_orders(&mut db, &app_data, &owner, now)
+            .await
+            .unwrap();
+        assert_eq!(uids, [ByteArray([1; 56])]);
+        let uids = cancel_orders(&mut db, &app_data, &owner, now)
+            .await
+            .unwrap();
+        assert!(uids.is_empty());
+
+        let cancellations = load(&mut db, &app_data).await.unwrap();
+        assert_eq!(
+            cancellations.iter().map(|c| c.orders).collect::<Vec<_>>(),
+            [1, 0]
+        );
+        assert!(cancellations.iter().all(|c| c.owner == owner));
+    }
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use sqlx::{types::JsonValue, PgConnection};
 
 pub type AuctionId = i64;
@@ -24,6 +25,19 @@ LIMIT 1
     sqlx::query_as(QUERY).fetch_optional(ex).await
 }
 
+/// The block and creation time of the most recent auction without loading the whole auction.
+pub async fn load_most_recent_creation(
+    ex: &mut PgConnection,
+) -> Result<Option<(i64, DateTime<Utc>)>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT (json->>'block')::bigint, created
+FROM auctions
+ORDER BY id DESC
+LIMIT 1
+    ;"#;
+    sqlx::query_as(QUERY).fetch_optional(ex).await
+}
+
 pub async fn delete_all_auctions(ex: &mut PgConnection) -> Result<(), sqlx::Error> {
     const QUERY: &str = "TRUNCATE auctions;";
     sqlx::query(QUERY).execute(ex).await.map(|_| ())
@@ -57,6 +71,8 @@ mod tests {
         delete_all_auctions(&mut db).await.unwrap();
         let result = load_most_recent(&mut db).await.unwrap();
         assert!(result.is_none());
+        let result = load_most_recent_creation(&mut db).await.unwrap();
+        assert!(result.is_none());
 
         // id still increases after deletion
         let value = JsonValue::Number(3.into());
@@ -66,4 +82,18 @@ mod tests {
         assert_eq!(value, value_);
         assert_eq!(id_, id);
     }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_most_recent_creation() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let auction: JsonValue = [("block", JsonValue::from(5))].into_iter().collect();
+        save(&mut db, &auction).await.unwrap();
+        let (block, created) = load_most_recent_creation(&mut db).await.unwrap().unwrap();
+        assert_eq!(block, 5);
+        assert!(created <= Utc::now());
+    }
 }
@@ -0,0 +1,99 @@
+use crate::{Address, TransactionHash};
+use bigdecimal::BigDecimal;
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    PgConnection,
+};
+
+/// Records how much of each token's buffer the settlement with the transaction hash used. Recording
+/// the same settlement again replaces the earlier amounts.
+pub async fn record(
+    ex: &mut PgConnection,
+    tx_hash: &TransactionHash,
+    time: DateTime<Utc>,
+    usage: &[(Address, BigDecimal)],
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO buffer_usage (tx_hash, token, amount, time)
+VALUES ($1, $2, $3, $4)
+ON CONFLICT (tx_hash, token) DO UPDATE
+SET amount = EXCLUDED.amount, time = EXCLUDED.time
+    "#;
+    for (token, amount) in usage {
+        sqlx::query(QUERY)
+            .bind(tx_hash)
+            .bind(token)
+            .bind(amount)
+            .bind(time)
+            .execute(&mut *ex)
+            .await?;
+    }
+    Ok(())
+}
+
+/// The buffer usage per token of all settlements recorded in the time range `[start, end)`.
+pub async fn total_usage(
+    ex: &mut PgConnection,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<(Address, BigDecimal)>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT token, SUM(amount)
+FROM buffer_usage
+WHERE time >= $1 AND time < $2
+GROUP BY token
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(start)
+        .bind(end)
+        .fetch_all(ex)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_array::ByteArray;
+    use sqlx::Connection;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_buffer_usage() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let start = Utc::now();
+        let end = start + chrono::Duration::days(1);
+        assert!(total_usage(&mut db, start, end).await.unwrap().is_empty());
+
+        let token = Address::default();
+        record(
+            &mut db,
+            &ByteArray([1; 32]),
+            start,
+            &[(token, 2.into()), (ByteArray([1; 20]), 5.into())],
+        )
+        .await
+        .unwrap();
+        record(&mut db, &ByteArray([2; 32]), start, &[(token, 3.into())])
+            .await
+            .unwrap();
+        // Outside of the range.
+        record(&mut db, &ByteArray([3; 32]), end, &[(token, 7.into())])
+            .await
+            .unwrap();
+
+        let usage = total_usage(&mut db, start, end).await.unwrap();
+        assert_eq!(usage.len(), 2);
+        assert!(usage.contains(&(token, 5.into())));
+        assert!(usage.contains(&(ByteArray([1; 20]), 5.into())));
+
+        // Recording a settlement again replaces its usage.
+        record(&mut db, &ByteArray([2; 32]), start, &[(token, 1.into())])
+            .await
+            .unwrap();
+        let usage = total_usage(&mut db, start, end).await.unwrap();
+        assert!(usage.contains(&(token, 3.into())));
+    }
+}
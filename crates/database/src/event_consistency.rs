@@ -0,0 +1,160 @@
+//! Checks that the indexed settlement contract events are consistent with each other and with the
+//! orders they execute.
+
+use crate::{events::EventIndex, OrderUid};
+use sqlx::PgConnection;
+
+/// Orders that traded since the block and got executed for more than their limit amount: the sell
+/// amount without fees for sell orders and the buy amount for buy orders.
+pub async fn overfilled_orders(
+    ex: &mut PgConnection,
+    from_block: i64,
+) -> Result<Vec<OrderUid>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT o.uid
+FROM orders o
+JOIN (
+    SELECT order_uid, SUM(sell_amount - fee_amount) AS sum_sell, SUM(buy_amount) AS sum_buy
+    FROM trades
+    WHERE order_uid IN (SELECT order_uid FROM trades WHERE block_number >= $1)
+    GROUP BY order_uid
+) t ON t.order_uid = o.uid
+WHERE CASE o.kind
+    WHEN 'sell' THEN t.sum_sell > o.sell_amount
+    WHEN 'buy' THEN t.sum_buy > o.buy_amount
+END
+ORDER BY o.uid
+    ;"#;
+    sqlx::query_scalar(QUERY)
+        .bind(from_block)
+        .fetch_all(ex)
+        .await
+}
+
+/// Market orders that traded since the block and paid more fees than they signed. Other classes
+/// of orders pay fees that they didn't sign.
+pub async fn excess_fee_orders(
+    ex: &mut PgConnection,
+    from_block: i64,
+) -> Result<Vec<OrderUid>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT o.uid
+FROM orders o
+JOIN (
+    SELECT order_uid, SUM(fee_amount) AS sum_fee
+    FROM trades
+    WHERE order_uid IN (SELECT order_uid FROM trades WHERE block_number >= $1)
+    GROUP BY order_uid
+) t ON t.order_uid = o.uid
+WHERE o.class = 'market' AND t.sum_fee > o.fee_amount
+ORDER BY o.uid
+    ;"#;
+    sqlx::query_scalar(QUERY)
+        .bind(from_block)
+        .fetch_all(ex)
+        .await
+}
+
+/// Trade events since the block without a settlement event after them in the same block. Every
+/// trade is emitted by a settlement which emits its settlement event last.
+pub async fn trades_without_settlement(
+    ex: &mut PgConnection,
+    from_block: i64,
+) -> Result<Vec<EventIndex>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT t.block_number, t.log_index
+FROM trades t
+WHERE t.block_number >= $1
+AND NOT EXISTS (
+    SELECT 1 FROM settlements s
+    WHERE s.block_number = t.block_number AND s.log_index > t.log_index
+)
+ORDER BY t.block_number, t.log_index
+    ;"#;
+    sqlx::query_as(QUERY).bind(from_block).fetch_all(ex).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        byte_array::ByteArray,
+        events::{Event, Settlement, Trade},
+        orders::{insert_order, Order, OrderClass, OrderKind},
+    };
+    use sqlx::Connection;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_event_consistency() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        // A sell order that is filled exactly, an overfilled buy order and a market order that
+        // paid too much fee.
+        let orders = [
+            (1, OrderKind::Sell, OrderClass::Market),
+            (2, OrderKind::Buy, OrderClass::Limit),
+            (3, OrderKind::Sell, OrderClass::Market),
+        ];
+        for (uid, kind, class) in orders {
+            let order = Order {
+                uid: ByteArray([uid; 56]),
+                kind,
+                class,
+                sell_amount: 10.into(),
+                buy_amount: 10.into(),
+                fee_amount: 1.into(),
+                ..Default::default()
+            };
+            insert_order(&mut db, &order).await.unwrap();
+        }
+        let trade = |uid, sell: u32, buy: u32, fee: u32| {
+            Event::Trade(Trade {
+                order_uid: ByteArray([uid; 56]),
+                sell_amount_including_fee: sell.into(),
+                buy_amount: buy.into(),
+                fee_amount: fee.into(),
+            })
+        };
+        let index = |block_number, log_index| EventIndex {
+            block_number,
+            log_index,
+        };
+        crate::events::append(
+            &mut db,
+            &[
+                (index(1, 0), trade(1, 11, 10, 1)),
+                (index(1, 1), trade(2, 10, 11, 1)),
+                (index(1, 2), Event::Settlement(Settlement::default())),
+                (index(2, 0), trade(3, 8, 10, 2)),
+                (index(2, 1), Event::Settlement(Settlement::default())),
+                // Indexed without the settlement that emitted it.
+                (index(3, 0), trade(3, 1, 1, 0)),
+            ],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            overfilled_orders(&mut db, 0).await.unwrap(),
+            [ByteArray([2; 56])]
+        );
+        assert_eq!(
+            excess_fee_orders(&mut db, 0).await.unwrap(),
+            [ByteArray([3; 56])]
+        );
+        assert_eq!(
+            trades_without_settlement(&mut db, 0).await.unwrap(),
+            [index(3, 0)]
+        );
+
+        // Only orders that traded since the block get checked.
+        assert!(overfilled_orders(&mut db, 2).await.unwrap().is_empty());
+        assert!(trades_without_settlement(&mut db, 4)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -35,7 +35,7 @@ pub struct PreSignature {
     pub signed: bool,
 }
 
-#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash, sqlx::FromRow)]
 pub struct EventIndex {
     pub block_number: i64,
     pub log_index: i64,
@@ -63,6 +63,28 @@ pub async fn delete(
     ex.execute(sqlx::query(QUERY_TRADE).bind(delete_from_block_number))
         .await?;
 
+    const QUERY_PROTOCOL_FEES: &str = "DELETE FROM protocol_fee_accruals WHERE block_number >= $1;";
+    ex.execute(sqlx::query(QUERY_PROTOCOL_FEES).bind(delete_from_block_number))
+        .await?;
+
+    const QUERY_SETTLEMENT_REWARDS: &str =
+        "DELETE FROM settlement_rewards WHERE block_number >= $1;";
+    ex.execute(sqlx::query(QUERY_SETTLEMENT_REWARDS).bind(delete_from_block_number))
+        .await?;
+
+    const QUERY_ORDER_REBATES: &str = "DELETE FROM order_rebates WHERE block_number >= $1;";
+    ex.execute(sqlx::query(QUERY_ORDER_REBATES).bind(delete_from_block_number))
+        .await?;
+
+    const QUERY_SETTLEMENT_REBATES: &str =
+        "DELETE FROM settlement_rebates WHERE block_number >= $1;";
+    ex.execute(sqlx::query(QUERY_SETTLEMENT_REBATES).bind(delete_from_block_number))
+        .await?;
+
+    const QUERY_TRADE_VENUES: &str = "DELETE FROM trade_venues WHERE block_number >= $1;";
+    ex.execute(sqlx::query(QUERY_TRADE_VENUES).bind(delete_from_block_number))
+        .await?;
+
     const QUERY_SETTLEMENTS: &str = "DELETE FROM settlements WHERE block_number >= $1;";
     ex.execute(sqlx::query(QUERY_SETTLEMENTS).bind(delete_from_block_number))
         .await?;
@@ -71,6 +93,11 @@ pub async fn delete(
     ex.execute(sqlx::query(QUERY_PRESIGNATURES).bind(delete_from_block_number))
         .await?;
 
+    const QUERY_ONCHAIN_PRESIGNATURES: &str =
+        "DELETE FROM onchain_presignatures WHERE block_number >= $1;";
+    ex.execute(sqlx::query(QUERY_ONCHAIN_PRESIGNATURES).bind(delete_from_block_number))
+        .await?;
+
     Ok(())
 }
 
@@ -127,6 +154,21 @@ async fn insert_trade(
         .bind(&event.sell_amount_including_fee)
         .bind(&event.buy_amount)
         .bind(&event.fee_amount)
+        .execute(&mut *ex)
+        .await?;
+
+    // The protocol's share of the executed fee is proportional to its share of the signed fee.
+    const QUERY_PROTOCOL_FEE: &str = "\
+        INSERT INTO protocol_fee_accruals (block_number, log_index, order_uid, token, amount) \
+        SELECT $1, $2, o.uid, o.sell_token, TRUNC($4 * o.protocol_fee_amount / o.fee_amount) \
+        FROM orders o \
+        WHERE o.uid = $3 AND o.protocol_fee_amount > 0 AND o.fee_amount > 0 \
+        ON CONFLICT DO NOTHING;";
+    sqlx::query(QUERY_PROTOCOL_FEE)
+        .bind(index.block_number)
+        .bind(index.log_index)
+        .bind(event.order_uid)
+        .bind(&event.fee_amount)
         .execute(ex)
         .await?;
     Ok(())
@@ -0,0 +1,96 @@
+use crate::{quotes::QuoteId, PgTransaction};
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    Executor,
+};
+
+/// Records the cost of honoring the fee guarantee of a quote if it fits in the budget together
+/// with the costs recorded since `since`, replacing an earlier record for the same quote. Returns
+/// whether the cost got recorded.
+///
+/// The table gets locked for the rest of the transaction so that concurrent placements can't all
+/// fit in the budget on their own and overspend it together.
+pub async fn record_cost_within_budget(
+    ex: &mut PgTransaction<'_>,
+    quote_id: QuoteId,
+    cost: f64,
+    since: DateTime<Utc>,
+    budget: f64,
+    time: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    ex.execute("LOCK TABLE fee_guarantee_costs IN SHARE ROW EXCLUSIVE MODE;")
+        .await?;
+    const QUERY: &str = r#"
+INSERT INTO fee_guarantee_costs (quote_id, cost, time)
+SELECT $1, $2, $5
+WHERE (
+    SELECT COALESCE(SUM(cost), 0)
+    FROM fee_guarantee_costs
+    WHERE time >= $3 AND quote_id <> $1
+) + $2 <= $4
+ON CONFLICT (quote_id) DO UPDATE
+SET cost = EXCLUDED.cost, time = EXCLUDED.time
+    "#;
+    let result = sqlx::query(QUERY)
+        .bind(quote_id)
+        .bind(cost)
+        .bind(since)
+        .bind(budget)
+        .bind(time)
+        .execute(ex)
+        .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::{Connection, PgConnection};
+
+    async fn total_cost(ex: &mut PgConnection) -> f64 {
+        sqlx::query_scalar("SELECT COALESCE(SUM(cost), 0) FROM fee_guarantee_costs")
+            .fetch_one(ex)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_fee_guarantee_costs() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let now = Utc::now();
+        let day_ago = now - chrono::Duration::days(1);
+        for (quote_id, cost, time) in [
+            (1, 2., now),
+            (2, 3., now),
+            (3, 5., day_ago - chrono::Duration::seconds(1)),
+        ] {
+            assert!(
+                record_cost_within_budget(&mut db, quote_id, cost, day_ago, 10., time)
+                    .await
+                    .unwrap()
+            );
+        }
+        assert_eq!(total_cost(&mut db).await, 10.);
+
+        // Costs recorded before `since` don't count against the budget.
+        assert!(record_cost_within_budget(&mut db, 4, 5., day_ago, 10., now)
+            .await
+            .unwrap());
+        assert!(
+            !record_cost_within_budget(&mut db, 5, 1., day_ago, 10., now)
+                .await
+                .unwrap()
+        );
+        assert_eq!(total_cost(&mut db).await, 15.);
+
+        // A retried placement with the same quote replaces its cost.
+        assert!(record_cost_within_budget(&mut db, 1, 2., day_ago, 10., now)
+            .await
+            .unwrap());
+        assert_eq!(total_cost(&mut db).await, 15.);
+    }
+}
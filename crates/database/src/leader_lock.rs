@@ -0,0 +1,35 @@
+//! Session level advisory locks that elect a leader among several replicas of a service.
+//!
+//! The lock is held by the connection that acquired it until it is released or the connection is
+//! closed. So the leader has to keep its connection open and replicas that lose their connection
+//! lose leadership.
+
+use sqlx::PgConnection;
+
+/// Tries to acquire the lock with the specified key without waiting. Returns whether the
+/// connection holds the lock. Acquiring a lock that the connection already holds succeeds.
+pub async fn try_acquire(ex: &mut PgConnection, key: i64) -> Result<bool, sqlx::Error> {
+    const QUERY: &str = "SELECT pg_try_advisory_lock($1);";
+    sqlx::query_scalar(QUERY).bind(key).fetch_one(ex).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::Connection;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_leader_lock() {
+        let mut leader = PgConnection::connect("postgresql://").await.unwrap();
+        let mut follower = PgConnection::connect("postgresql://").await.unwrap();
+
+        assert!(try_acquire(&mut leader, 42).await.unwrap());
+        assert!(try_acquire(&mut leader, 42).await.unwrap());
+        assert!(!try_acquire(&mut follower, 42).await.unwrap());
+        assert!(try_acquire(&mut follower, 43).await.unwrap());
+
+        leader.close().await.unwrap();
+        assert!(try_acquire(&mut follower, 42).await.unwrap());
+    }
+}
@@ -1,15 +1,33 @@
+pub mod accounting;
+pub mod api_keys;
+pub mod app_data_cancellations;
 pub mod auction;
+pub mod buffer_usage;
 pub mod byte_array;
 pub mod ethflow_orders;
+pub mod event_consistency;
 pub mod events;
+pub mod fee_guarantees;
+pub mod leader_lock;
+pub mod migrations;
 pub mod onchain_broadcasted_orders;
+pub mod order_book_stats;
+pub mod order_diagnoses;
 pub mod orders;
+pub mod protocol_fees;
+pub mod quote_requests;
 pub mod quotes;
+pub mod sealed_orders;
+pub mod settlement_rebates;
+pub mod shadow_competition;
 pub mod solver_competition;
+pub mod solver_rewards;
+pub mod solver_teams;
+pub mod trade_routes;
 pub mod trades;
 
 use byte_array::ByteArray;
-use sqlx::{Executor, PgPool};
+use sqlx::{Connection, Executor, PgConnection, PgPool};
 
 // Design:
 //
@@ -29,6 +47,22 @@ use sqlx::{Executor, PgPool};
 
 pub type PgTransaction<'a> = sqlx::Transaction<'a, sqlx::Postgres>;
 
+/// Begins a read only transaction in which all queries see the same snapshot of the database.
+///
+/// Every statement of a regular transaction sees the data committed before the statement started,
+/// so reads that combine several queries can contradict each other while other transactions
+/// write. Use this for reads that have to be consistent with each other, like an order with its
+/// quote or a solver competition with its rebates. Reads with a single statement, like the trades
+/// and user orders queries, are consistent without it. Dropping the transaction without
+/// committing it is fine because it doesn't write anything.
+pub async fn begin_snapshot(ex: &mut PgConnection) -> sqlx::Result<PgTransaction<'_>> {
+    let mut transaction = ex.begin().await?;
+    transaction
+        .execute("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY;")
+        .await?;
+    Ok(transaction)
+}
+
 /// The names of all tables we use in the db.
 pub const ALL_TABLES: &[&str] = &[
     "orders",
@@ -40,8 +74,40 @@ pub const ALL_TABLES: &[&str] = &[
     "order_quotes",
     "solver_competitions",
     "auctions",
+    "api_keys",
+    "trade_venues",
+    "shadow_competitions",
+    "settlement_rewards",
+    "app_data_cancellations",
+    "settlement_rebates",
+    "order_rebates",
+    "protocol_fee_accruals",
+    "solver_teams",
+    "quote_requests",
+    "fee_guarantee_costs",
+    "sealed_orders",
+    "order_diagnoses",
+    "buffer_usage",
+    "onchain_presignatures",
 ];
 
+/// Returns the tables that don't exist in the database, which means that not all migrations have
+/// been applied.
+pub async fn missing_tables(ex: &mut PgConnection) -> sqlx::Result<Vec<&'static str>> {
+    const QUERY: &str = "SELECT to_regclass($1) IS NOT NULL;";
+    let mut missing = Vec::new();
+    for table in ALL_TABLES {
+        let exists: bool = sqlx::query_scalar(QUERY)
+            .bind(table)
+            .fetch_one(&mut *ex)
+            .await?;
+        if !exists {
+            missing.push(*table);
+        }
+    }
+    Ok(missing)
+}
+
 /// Delete all data in the database. Only used by tests.
 #[allow(non_snake_case)]
 pub async fn clear_DANGER_(ex: &mut PgTransaction<'_>) -> sqlx::Result<()> {
@@ -67,7 +133,6 @@ pub type OrderUid = ByteArray<56>;
 #[cfg(test)]
 mod tests {
     use super::*;
-    use sqlx::{Connection, PgConnection};
 
     #[tokio::test]
     #[ignore]
@@ -76,4 +141,28 @@ mod tests {
         let mut con = con.begin().await.unwrap();
         clear_DANGER_(&mut con).await.unwrap();
     }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_snapshot_is_repeatable_read() {
+        let mut con = PgConnection::connect("postgresql://").await.unwrap();
+        let mut snapshot = begin_snapshot(&mut con).await.unwrap();
+        let isolation: String = sqlx::query_scalar("SHOW transaction_isolation;")
+            .fetch_one(&mut snapshot)
+            .await
+            .unwrap();
+        assert_eq!(isolation, "repeatable read");
+        let read_only: String = sqlx::query_scalar("SHOW transaction_read_only;")
+            .fetch_one(&mut snapshot)
+            .await
+            .unwrap();
+        assert_eq!(read_only, "on");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_no_missing_tables() {
+        let mut con = PgConnection::connect("postgresql://").await.unwrap();
+        assert_eq!(missing_tables(&mut con).await.unwrap(), Vec::<&str>::new());
+    }
 }
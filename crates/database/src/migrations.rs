@@ -0,0 +1,404 @@
+//! The SQL migrations of the database embedded into the binaries. Services verify at startup that
+//! the schema of the database is the one they were built for instead of failing later with opaque
+//! query errors, and can optionally apply pending migrations themselves.
+//!
+//! Applied migrations are tracked in the schema history table of flyway, which applies the
+//! migrations in deployments, so that both ways of migrating can be used interchangeably.
+
+use crate::PgTransaction;
+use sqlx::{Connection, Executor, PgConnection};
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Instant,
+};
+
+/// The file names and contents of the migrations sorted by file name. Generated by the build
+/// script.
+const SCRIPTS: &[(&str, &str)] = include!(concat!(env!("OUT_DIR"), "/migrations.rs"));
+
+/// Key of the advisory lock that keeps several services from applying migrations at once.
+const LOCK_KEY: i64 = 0x6d6967726174696f;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Migration {
+    pub version: u32,
+    /// The version as it appears in the file name, which is how flyway records it.
+    pub version_text: &'static str,
+    pub description: String,
+    pub script: &'static str,
+    pub sql: &'static str,
+}
+
+impl Migration {
+    /// Parses flyway's file name format `V<version>__<description>.sql`.
+    fn parse(script: &'static str, sql: &'static str) -> Option<Self> {
+        let name = script.strip_prefix('V')?.strip_suffix(".sql")?;
+        let (version_text, description) = name.split_once("__")?;
+        Some(Self {
+            version: version_text.parse().ok()?,
+            version_text,
+            description: description.replace('_', " "),
+            script,
+            sql,
+        })
+    }
+
+    /// The checksum flyway records for the script.
+    pub fn checksum(&self) -> i32 {
+        checksum(self.sql)
+    }
+}
+
+/// All migrations of this binary ordered by version.
+pub fn migrations() -> Vec<Migration> {
+    SCRIPTS
+        .iter()
+        .map(|(script, sql)| {
+            Migration::parse(script, sql)
+                .unwrap_or_else(|| panic!("invalid migration file name {script}"))
+        })
+        .collect()
+}
+
+/// A migration recorded in the schema history table.
+#[derive(Clone, Debug, Eq, PartialEq, sqlx::FromRow)]
+pub struct AppliedMigration {
+    pub version: String,
+    /// `NULL` for the baseline entry flyway creates for databases that predate it.
+    pub checksum: Option<i32>,
+    pub success: bool,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// Migrations are pending and applying them wasn't allowed.
+    Outdated {
+        current: Option<u32>,
+        expected: u32,
+    },
+    /// The database has migrations that this binary doesn't know about, for example because a
+    /// deployment got rolled back after its migrations had been applied.
+    Newer {
+        current: u32,
+        expected: u32,
+    },
+    /// The script of an applied migration differs from the one this binary was built with.
+    ChecksumMismatch {
+        version: u32,
+    },
+    /// A migration failed to apply and the database has to be repaired manually.
+    FailedMigration {
+        version: String,
+    },
+    Database(sqlx::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Outdated { current, expected } => write!(
+                f,
+                "database schema version {current:?} is older than the expected version \
+                 {expected}, apply the pending migrations"
+            ),
+            Self::Newer { current, expected } => write!(
+                f,
+                "database schema version {current} is newer than the expected version {expected}, \
+                 this binary is outdated"
+            ),
+            Self::ChecksumMismatch { version } => write!(
+                f,
+                "applied migration {version} differs from the migration of this binary"
+            ),
+            Self::FailedMigration { version } => {
+                write!(f, "migration {version} failed and needs to be repaired")
+            }
+            Self::Database(err) => write!(f, "database error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<sqlx::Error> for Error {
+    fn from(err: sqlx::Error) -> Self {
+        Self::Database(err)
+    }
+}
+
+/// Verifies that the schema of the database matches the migrations of this binary. With `apply`
+/// pending migrations get applied instead of being an error. Returns the versions of the applied
+/// migrations.
+pub async fn ensure_schema(ex: &mut PgConnection, apply: bool) -> Result<Vec<u32>, Error> {
+    let migrations = migrations();
+    if !apply {
+        let history = history(ex).await?;
+        if !pending(&history, &migrations)?.is_empty() {
+            return Err(Error::Outdated {
+                current: current_version(&history),
+                expected: expected_version(&migrations),
+            });
+        }
+        return Ok(Vec::new());
+    }
+
+    let mut transaction = ex.begin().await?;
+    let applied = apply_pending(&mut transaction, &migrations).await?;
+    transaction.commit().await?;
+    Ok(applied)
+}
+
+/// Applies the pending migrations. The whole transaction fails if any of them fails.
+async fn apply_pending(
+    ex: &mut PgTransaction<'_>,
+    migrations: &[Migration],
+) -> Result<Vec<u32>, Error> {
+    sqlx::query("SELECT pg_advisory_xact_lock($1);")
+        .bind(LOCK_KEY)
+        .execute(&mut *ex)
+        .await?;
+    ex.execute(CREATE_HISTORY_TABLE).await?;
+    // Read the history while holding the lock so that migrations that another service applied in
+    // the meantime don't get applied twice.
+    let history = history(ex).await?;
+    let pending = pending(&history, migrations)?;
+    for migration in &pending {
+        let start = Instant::now();
+        ex.execute(migration.sql).await?;
+        record(ex, migration, start.elapsed().as_millis() as i32).await?;
+    }
+    Ok(pending.iter().map(|migration| migration.version).collect())
+}
+
+/// The schema history table as flyway creates it.
+const CREATE_HISTORY_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS flyway_schema_history (
+    installed_rank integer NOT NULL PRIMARY KEY,
+    version varchar(50),
+    description varchar(200) NOT NULL,
+    type varchar(20) NOT NULL,
+    script varchar(1000) NOT NULL,
+    checksum integer,
+    installed_by varchar(100) NOT NULL,
+    installed_on timestamp NOT NULL DEFAULT now(),
+    execution_time integer NOT NULL,
+    success boolean NOT NULL
+);
+CREATE INDEX IF NOT EXISTS flyway_schema_history_s_idx ON flyway_schema_history (success);
+"#;
+
+/// Returns the versioned migrations in the schema history table or nothing if the table doesn't
+/// exist because no migrations have been applied yet.
+pub async fn history(ex: &mut PgConnection) -> sqlx::Result<Vec<AppliedMigration>> {
+    const EXISTS: &str = "SELECT to_regclass('flyway_schema_history') IS NOT NULL;";
+    let exists: bool = sqlx::query_scalar(EXISTS).fetch_one(&mut *ex).await?;
+    if !exists {
+        return Ok(Vec::new());
+    }
+    const QUERY: &str = r#"
+SELECT version, checksum, success
+FROM flyway_schema_history
+WHERE version IS NOT NULL
+ORDER BY installed_rank
+    ;"#;
+    sqlx::query_as(QUERY).fetch_all(ex).await
+}
+
+async fn record(
+    ex: &mut PgConnection,
+    migration: &Migration,
+    execution_time: i32,
+) -> sqlx::Result<()> {
+    const QUERY: &str = r#"
+INSERT INTO flyway_schema_history (
+    installed_rank, version, description, type, script, checksum, installed_by, execution_time,
+    success
+)
+SELECT COALESCE(MAX(installed_rank), 0) + 1, $1, $2, 'SQL', $3, $4, current_user, $5, true
+FROM flyway_schema_history
+    ;"#;
+    sqlx::query(QUERY)
+        .bind(migration.version_text)
+        .bind(&migration.description)
+        .bind(migration.script)
+        .bind(migration.checksum())
+        .bind(execution_time)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Compares the applied migrations to the migrations of this binary and returns the ones that
+/// still have to be applied.
+pub fn pending<'a>(
+    history: &[AppliedMigration],
+    migrations: &'a [Migration],
+) -> Result<Vec<&'a Migration>, Error> {
+    if let Some(failed) = history.iter().find(|applied| !applied.success) {
+        return Err(Error::FailedMigration {
+            version: failed.version.clone(),
+        });
+    }
+    for applied in history {
+        let checksum = match applied.checksum {
+            Some(checksum) => checksum,
+            None => continue,
+        };
+        let migration = migrations
+            .iter()
+            .find(|migration| Some(migration.version) == applied.version.parse().ok());
+        if let Some(migration) = migration {
+            if migration.checksum() != checksum {
+                return Err(Error::ChecksumMismatch {
+                    version: migration.version,
+                });
+            }
+        }
+    }
+
+    let current = current_version(history);
+    let expected = expected_version(migrations);
+    if let Some(current) = current {
+        if current > expected {
+            return Err(Error::Newer { current, expected });
+        }
+    }
+    Ok(migrations
+        .iter()
+        .filter(|migration| Some(migration.version) > current)
+        .collect())
+}
+
+fn current_version(history: &[AppliedMigration]) -> Option<u32> {
+    history
+        .iter()
+        .filter_map(|applied| applied.version.parse().ok())
+        .max()
+}
+
+fn expected_version(migrations: &[Migration]) -> u32 {
+    migrations
+        .iter()
+        .map(|migration| migration.version)
+        .max()
+        .unwrap_or_default()
+}
+
+/// Flyway's checksum of a script: the CRC-32 of its lines without line breaks, interpreted as a
+/// signed integer.
+fn checksum(sql: &str) -> i32 {
+    let sql = sql.strip_prefix('\u{feff}').unwrap_or(sql);
+    sql.lines().fold(0, |crc, line| crc32(crc, line.as_bytes())) as i32
+}
+
+/// Continues the CRC-32 (as in zlib and `java.util.zip.CRC32`) of previous data with more data.
+fn crc32(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embeds_all_migrations() {
+        let migrations = migrations();
+        assert_eq!(migrations[0].version, 1);
+        assert_eq!(migrations[0].description, "create orders");
+        assert!(migrations
+            .windows(2)
+            .all(|pair| pair[0].version < pair[1].version));
+    }
+
+    #[test]
+    fn checksum_ignores_line_breaks() {
+        assert_eq!(crc32(0, b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(crc32(0, b"1234"), b"56789"), 0xcbf4_3926);
+        assert_eq!(checksum("1234\n56789\n"), 0xcbf4_3926_u32 as i32);
+        assert_eq!(checksum("\u{feff}1234\r\n56789"), checksum("1234\n56789"));
+    }
+
+    #[test]
+    fn finds_pending_migrations() {
+        let migrations = [1, 2, 3]
+            .into_iter()
+            .map(|version| Migration {
+                version,
+                version_text: "",
+                description: Default::default(),
+                script: "",
+                sql: "",
+            })
+            .collect::<Vec<_>>();
+        let applied = |version: &str| AppliedMigration {
+            version: version.to_string(),
+            checksum: Some(checksum("")),
+            success: true,
+        };
+        let versions = |pending: Vec<&Migration>| {
+            pending
+                .into_iter()
+                .map(|migration| migration.version)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(versions(pending(&[], &migrations).unwrap()), [1, 2, 3]);
+        assert_eq!(
+            versions(pending(&[applied("1"), applied("002")], &migrations).unwrap()),
+            [3]
+        );
+        assert_eq!(
+            versions(pending(&[applied("3")], &migrations).unwrap()),
+            Vec::<u32>::new()
+        );
+        // The baseline of databases that predate flyway has no checksum.
+        let baseline = AppliedMigration {
+            checksum: None,
+            ..applied("2")
+        };
+        assert_eq!(versions(pending(&[baseline], &migrations).unwrap()), [3]);
+
+        assert!(matches!(
+            pending(&[applied("4")], &migrations),
+            Err(Error::Newer {
+                current: 4,
+                expected: 3
+            })
+        ));
+        let modified = AppliedMigration {
+            checksum: Some(1),
+            ..applied("1")
+        };
+        assert!(matches!(
+            pending(&[modified], &migrations),
+            Err(Error::ChecksumMismatch { version: 1 })
+        ));
+        let failed = AppliedMigration {
+            success: false,
+            ..applied("2")
+        };
+        assert!(matches!(
+            pending(&[applied("1"), failed], &migrations),
+            Err(Error::FailedMigration { .. })
+        ));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_schema_is_up_to_date() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        assert_eq!(ensure_schema(&mut db, false).await.unwrap(), []);
+        assert_eq!(ensure_schema(&mut db, true).await.unwrap(), []);
+    }
+}
@@ -0,0 +1,142 @@
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    PgConnection,
+};
+
+/// Rolling order book statistics over the last 24 hours.
+#[derive(Clone, Debug, PartialEq, sqlx::FromRow)]
+pub struct OrderBookStats {
+    pub settlement_count: i64,
+    pub trade_count: i64,
+    pub unique_traders: i64,
+    /// Traded volume in the native token.
+    pub volume: f64,
+    pub refreshed_at: DateTime<Utc>,
+}
+
+/// Returns the block numbers of the oldest settlements whose block timestamp is not known yet.
+pub async fn blocks_without_timestamp(
+    ex: &mut PgConnection,
+    limit: i64,
+) -> Result<Vec<i64>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT DISTINCT block_number
+FROM settlements
+WHERE block_timestamp IS NULL
+ORDER BY block_number
+LIMIT $1
+    "#;
+    sqlx::query_scalar(QUERY).bind(limit).fetch_all(ex).await
+}
+
+pub async fn set_block_timestamp(
+    ex: &mut PgConnection,
+    block_number: i64,
+    timestamp: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+UPDATE settlements
+SET block_timestamp = $2
+WHERE block_number = $1
+    "#;
+    sqlx::query(QUERY)
+        .bind(block_number)
+        .bind(timestamp)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Recomputes the statistics.
+pub async fn refresh(ex: &mut PgConnection) -> Result<(), sqlx::Error> {
+    const QUERY: &str = "REFRESH MATERIALIZED VIEW order_book_stats;";
+    sqlx::query(QUERY).execute(ex).await?;
+    Ok(())
+}
+
+/// Returns the statistics as of the last refresh.
+pub async fn load(ex: &mut PgConnection) -> Result<OrderBookStats, sqlx::Error> {
+    const QUERY: &str = "SELECT * FROM order_book_stats;";
+    sqlx::query_as(QUERY).fetch_one(ex).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        byte_array::ByteArray,
+        events::{Event, EventIndex, Settlement, Trade},
+        orders::{insert_order, Order},
+    };
+    use bigdecimal::BigDecimal;
+    use sqlx::Connection;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_order_book_stats() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        refresh(&mut db).await.unwrap();
+        let stats = load(&mut db).await.unwrap();
+        assert_eq!(
+            stats,
+            OrderBookStats {
+                settlement_count: 0,
+                trade_count: 0,
+                unique_traders: 0,
+                volume: 0.,
+                refreshed_at: stats.refreshed_at,
+            }
+        );
+
+        for (uid, owner) in [(1, 1), (2, 1), (3, 2)] {
+            let order = Order {
+                uid: ByteArray([uid; 56]),
+                owner: ByteArray([owner; 20]),
+                ..Default::default()
+            };
+            insert_order(&mut db, &order).await.unwrap();
+        }
+        let index = |block_number, log_index| EventIndex {
+            block_number,
+            log_index,
+        };
+        let trade = |uid| {
+            Event::Trade(Trade {
+                order_uid: ByteArray([uid; 56]),
+                sell_amount_including_fee: BigDecimal::from(10),
+                ..Default::default()
+            })
+        };
+        crate::events::append(
+            &mut db,
+            &[
+                (index(1, 0), trade(1)),
+                (index(1, 1), trade(2)),
+                (index(1, 2), Event::Settlement(Settlement::default())),
+                (index(2, 0), trade(3)),
+                (index(2, 1), Event::Settlement(Settlement::default())),
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(blocks_without_timestamp(&mut db, 10).await.unwrap(), [1, 2]);
+
+        set_block_timestamp(&mut db, 1, Utc::now()).await.unwrap();
+        set_block_timestamp(&mut db, 2, Utc::now() - chrono::Duration::days(2))
+            .await
+            .unwrap();
+        assert!(blocks_without_timestamp(&mut db, 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        refresh(&mut db).await.unwrap();
+        let stats = load(&mut db).await.unwrap();
+        assert_eq!(stats.settlement_count, 1);
+        assert_eq!(stats.trade_count, 2);
+        assert_eq!(stats.unique_traders, 1);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{AppId, OrderUid, PgTransaction};
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    PgConnection,
+};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, sqlx::Type)]
+#[sqlx(type_name = "StuckOrderReason")]
+#[sqlx(rename_all = "snake_case")]
+pub enum StuckOrderReason {
+    TokenDenied,
+    MissingAllowance,
+    InsufficientBalance,
+    NoLiquidityPath,
+    TooSmall,
+    PriceOutOfRange,
+    Unknown,
+}
+
+/// One row in the `order_diagnoses` table.
+#[derive(Clone, Debug, Eq, PartialEq, sqlx::FromRow)]
+pub struct OrderDiagnosis {
+    pub order_uid: OrderUid,
+    pub reason: StuckOrderReason,
+    pub diagnosed_at: DateTime<Utc>,
+}
+
+/// Replaces all stored diagnoses so that orders which got matched, expired or were cancelled since
+/// the last analysis lose theirs.
+pub async fn replace(
+    ex: &mut PgTransaction<'_>,
+    diagnoses: &[OrderDiagnosis],
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM order_diagnoses;")
+        .execute(&mut *ex)
+        .await?;
+
+    const INSERT: &str = r#"
+INSERT INTO order_diagnoses (order_uid, reason, diagnosed_at)
+VALUES ($1, $2, $3)
+    ;"#;
+    for diagnosis in diagnoses {
+        sqlx::query(INSERT)
+            .bind(diagnosis.order_uid)
+            .bind(diagnosis.reason)
+            .bind(diagnosis.diagnosed_at)
+            .execute(&mut *ex)
+            .await?;
+    }
+    Ok(())
+}
+
+pub async fn fetch(
+    ex: &mut PgConnection,
+    order_uid: &OrderUid,
+) -> Result<Option<OrderDiagnosis>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT * FROM order_diagnoses
+WHERE order_uid = $1
+    ;"#;
+    sqlx::query_as(QUERY)
+        .bind(order_uid)
+        .fetch_optional(ex)
+        .await
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, sqlx::FromRow)]
+pub struct StuckOrders {
+    pub app_data: AppId,
+    pub reason: StuckOrderReason,
+    pub orders: i64,
+}
+
+/// Counts the diagnosed orders by app data and reason.
+pub async fn count_by_app_data(ex: &mut PgConnection) -> Result<Vec<StuckOrders>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT o.app_data, d.reason, COUNT(*) AS orders
+FROM order_diagnoses d
+JOIN orders o ON o.uid = d.order_uid
+GROUP BY o.app_data, d.reason
+ORDER BY o.app_data, d.reason
+    ;"#;
+    sqlx::query_as(QUERY).fetch_all(ex).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        byte_array::ByteArray,
+        orders::{insert_order, Order},
+    };
+    use chrono::NaiveDateTime;
+    use sqlx::Connection;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_order_diagnoses() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        for (uid, app_data) in [(1, 1), (2, 1), (3, 2)] {
+            let order = Order {
+                uid: ByteArray([uid; 56]),
+                app_data: ByteArray([app_data; 32]),
+                ..Default::default()
+            };
+            insert_order(&mut db, &order).await.unwrap();
+        }
+        let diagnosis = |uid, reason| OrderDiagnosis {
+            order_uid: ByteArray([uid; 56]),
+            reason,
+            diagnosed_at: DateTime::from_utc(NaiveDateTime::from_timestamp(1, 0), Utc),
+        };
+
+        replace(
+            &mut db,
+            &[
+                diagnosis(1, StuckOrderReason::TooSmall),
+                diagnosis(2, StuckOrderReason::Unknown),
+            ],
+        )
+        .await
+        .unwrap();
+        replace(
+            &mut db,
+            &[
+                diagnosis(1, StuckOrderReason::MissingAllowance),
+                diagnosis(2, StuckOrderReason::MissingAllowance),
+                diagnosis(3, StuckOrderReason::TokenDenied),
+            ],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            fetch(&mut db, &ByteArray([1; 56])).await.unwrap(),
+            Some(diagnosis(1, StuckOrderReason::MissingAllowance))
+        );
+        assert_eq!(fetch(&mut db, &ByteArray([4; 56])).await.unwrap(), None);
+        assert_eq!(
+            count_by_app_data(&mut db).await.unwrap(),
+            vec![
+                StuckOrders {
+                    app_data: ByteArray([1; 32]),
+                    reason: StuckOrderReason::MissingAllowance,
+                    orders: 2,
+                },
+                StuckOrders {
+                    app_data: ByteArray([2; 32]),
+                    reason: StuckOrderReason::TokenDenied,
+                    orders: 1,
+                },
+            ]
+        );
+
+        replace(&mut db, &[]).await.unwrap();
+        assert_eq!(fetch(&mut db, &ByteArray([1; 56])).await.unwrap(), None);
+    }
+}
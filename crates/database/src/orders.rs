@@ -3,7 +3,7 @@ use futures::stream::BoxStream;
 use sqlx::{
     types::{
         chrono::{DateTime, NaiveDateTime, Utc},
-        BigDecimal,
+        BigDecimal, JsonValue,
     },
     PgConnection,
 };
@@ -28,6 +28,24 @@ pub enum SigningScheme {
     PreSign,
 }
 
+impl SigningScheme {
+    pub const ALL: [Self; 4] = [Self::Eip712, Self::EthSign, Self::Eip1271, Self::PreSign];
+
+    /// The label of the variant in the Postgres enum.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Eip712 => "eip712",
+            Self::EthSign => "ethsign",
+            Self::Eip1271 => "eip1271",
+            Self::PreSign => "presign",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scheme| scheme.label() == label)
+    }
+}
+
 /// Source from which the sellAmount should be drawn upon order fulfilment
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq, sqlx::Type)]
 #[sqlx(type_name = "SellTokenSource")]
@@ -54,6 +72,17 @@ pub enum BuyTokenDestination {
     Internal,
 }
 
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, sqlx::Type)]
+#[sqlx(type_name = "OrderClass")]
+#[sqlx(rename_all = "lowercase")]
+pub enum OrderClass {
+    #[default]
+    Market,
+    Limit,
+    Liquidity,
+    Twap,
+}
+
 /// One row in the `orders` table.
 #[derive(Clone, Debug, Eq, PartialEq, sqlx::FromRow)]
 pub struct Order {
@@ -76,8 +105,14 @@ pub struct Order {
     pub sell_token_balance: SellTokenSource,
     pub buy_token_balance: BuyTokenDestination,
     pub full_fee_amount: BigDecimal,
-    pub is_liquidity_order: bool,
     pub cancellation_timestamp: Option<DateTime<Utc>>,
+    pub quote_expiry_block: Option<i64>,
+    pub quote_last_look: bool,
+    pub class: OrderClass,
+    pub protocol_fee_amount: BigDecimal,
+    pub min_partial_fill_amount: Option<BigDecimal>,
+    pub private_submission: bool,
+    pub condition: Option<JsonValue>,
 }
 
 impl Default for Order {
@@ -102,8 +137,14 @@ impl Default for Order {
             sell_token_balance: Default::default(),
             buy_token_balance: Default::default(),
             full_fee_amount: Default::default(),
-            is_liquidity_order: Default::default(),
             cancellation_timestamp: Default::default(),
+            quote_expiry_block: Default::default(),
+            quote_last_look: Default::default(),
+            class: Default::default(),
+            protocol_fee_amount: Default::default(),
+            min_partial_fill_amount: Default::default(),
+            private_submission: Default::default(),
+            condition: Default::default(),
         }
     }
 }
@@ -130,10 +171,16 @@ INSERT INTO orders (
     sell_token_balance,
     buy_token_balance,
     full_fee_amount,
-    is_liquidity_order,
-    cancellation_timestamp
+    cancellation_timestamp,
+    quote_expiry_block,
+    quote_last_look,
+    class,
+    protocol_fee_amount,
+    min_partial_fill_amount,
+    private_submission,
+    condition
 )
-VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27)
     "#;
     sqlx::query(QUERY)
         .bind(&order.uid)
@@ -155,8 +202,14 @@ VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $
         .bind(order.sell_token_balance)
         .bind(order.buy_token_balance)
         .bind(&order.full_fee_amount)
-        .bind(order.is_liquidity_order)
         .bind(order.cancellation_timestamp)
+        .bind(order.quote_expiry_block)
+        .bind(order.quote_last_look)
+        .bind(order.class)
+        .bind(&order.protocol_fee_amount)
+        .bind(&order.min_partial_fill_amount)
+        .bind(order.private_submission)
+        .bind(&order.condition)
         .execute(ex)
         .await?;
     Ok(())
@@ -191,6 +244,9 @@ pub struct Quote {
     pub sell_token_price: f64,
     pub sell_amount: BigDecimal,
     pub buy_amount: BigDecimal,
+    pub subsidy_discount: Option<f64>,
+    pub subsidy_min_discounted: Option<f64>,
+    pub subsidy_factor: Option<f64>,
 }
 
 pub async fn insert_quote(ex: &mut PgConnection, quote: &Quote) -> Result<(), sqlx::Error> {
@@ -201,9 +257,12 @@ INSERT INTO order_quotes (
     gas_price,
     sell_token_price,
     sell_amount,
-    buy_amount
+    buy_amount,
+    subsidy_discount,
+    subsidy_min_discounted,
+    subsidy_factor
 )
-VALUES ($1, $2, $3, $4, $5, $6)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
     "#;
     sqlx::query(QUERY)
         .bind(&quote.order_uid)
@@ -212,6 +271,9 @@ VALUES ($1, $2, $3, $4, $5, $6)
         .bind(quote.sell_token_price)
         .bind(&quote.sell_amount)
         .bind(&quote.buy_amount)
+        .bind(quote.subsidy_discount)
+        .bind(quote.subsidy_min_discounted)
+        .bind(quote.subsidy_factor)
         .execute(ex)
         .await?;
     Ok(())
@@ -250,6 +312,34 @@ AND cancellation_timestamp IS NULL
         .map(|_| ())
 }
 
+/// Cancels the order unless it got cancelled, invalidated or completely filled already. Returns
+/// whether the order got cancelled.
+pub async fn cancel_open_order(
+    ex: &mut PgConnection,
+    order_uid: &OrderUid,
+    timestamp: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    const QUERY: &str = r#"
+UPDATE orders o
+SET cancellation_timestamp = $1
+WHERE o.uid = $2
+AND o.cancellation_timestamp IS NULL
+AND NOT EXISTS (SELECT 1 FROM invalidations i WHERE i.order_uid = o.uid)
+AND CASE o.kind
+    WHEN 'sell' THEN
+        (SELECT COALESCE(SUM(t.sell_amount), 0) FROM trades t WHERE t.order_uid = o.uid) < o.sell_amount
+    WHEN 'buy' THEN
+        (SELECT COALESCE(SUM(t.buy_amount), 0) FROM trades t WHERE t.order_uid = o.uid) < o.buy_amount
+END
+    "#;
+    let result = sqlx::query(QUERY)
+        .bind(timestamp)
+        .bind(order_uid.0.as_ref())
+        .execute(ex)
+        .await?;
+    Ok(result.rows_affected() == 1)
+}
+
 /// Order with extra information from other tables. Has all the information needed to construct a model::Order.
 #[derive(sqlx::FromRow)]
 pub struct FullOrder {
@@ -277,7 +367,13 @@ pub struct FullOrder {
     pub sell_token_balance: SellTokenSource,
     pub buy_token_balance: BuyTokenDestination,
     pub presignature_pending: bool,
-    pub is_liquidity_order: bool,
+    pub quote_expiry_block: Option<i64>,
+    pub quote_last_look: bool,
+    pub class: OrderClass,
+    pub protocol_fee_amount: BigDecimal,
+    pub min_partial_fill_amount: Option<BigDecimal>,
+    pub private_submission: bool,
+    pub condition: Option<JsonValue>,
 }
 
 // When querying orders we have several specialized use cases working with their own filtering,
@@ -304,7 +400,8 @@ const ORDERS_SELECT: &str = r#"
 o.uid, o.owner, o.creation_timestamp, o.sell_token, o.buy_token, o.sell_amount, o.buy_amount,
 o.valid_to, o.app_data, o.fee_amount, o.full_fee_amount, o.kind, o.partially_fillable, o.signature,
 o.receiver, o.signing_scheme, o.settlement_contract, o.sell_token_balance, o.buy_token_balance,
-o.is_liquidity_order,
+o.quote_expiry_block, o.quote_last_look, o.class,
+o.protocol_fee_amount, o.min_partial_fill_amount, o.private_submission, o.condition,
 (SELECT COALESCE(SUM(t.buy_amount), 0) FROM trades t WHERE t.order_uid = o.uid) AS sum_buy,
 (SELECT COALESCE(SUM(t.sell_amount), 0) FROM trades t WHERE t.order_uid = o.uid) AS sum_sell,
 (SELECT COALESCE(SUM(t.fee_amount), 0) FROM trades t WHERE t.order_uid = o.uid) AS sum_fee,
@@ -317,7 +414,13 @@ o.is_liquidity_order,
     WHERE o.uid = p.order_uid
     ORDER BY p.block_number DESC, p.log_index DESC
     LIMIT 1
-), true)) AS presignature_pending
+), true) AND NOT EXISTS (
+    SELECT 1
+    FROM onchain_presignatures c
+    WHERE c.order_uid = o.uid AND c.block_number >= COALESCE((
+        SELECT MAX(p.block_number) FROM presignature_events p WHERE p.order_uid = o.uid
+    ), 0)
+)) AS presignature_pending
 "#;
 
 const ORDERS_FROM: &str = "orders o";
@@ -424,6 +527,53 @@ WHERE
     sqlx::query_as(QUERY).bind(min_valid_to).fetch(ex)
 }
 
+/// Orders that would be solvable if their presignature events were indexed.
+pub fn presignature_pending_orders(
+    ex: &mut PgConnection,
+    min_valid_to: i64,
+) -> BoxStream<'_, Result<FullOrder, sqlx::Error>> {
+    #[rustfmt::skip]
+    const QUERY: &str = const_format::concatcp!(
+"SELECT * FROM ( ",
+    "SELECT ", ORDERS_SELECT,
+    " FROM ", ORDERS_FROM,
+    " WHERE o.valid_to >= $1 AND o.signing_scheme = 'presign' ",
+r#") AS unfiltered
+WHERE
+    CASE kind
+        WHEN 'sell' THEN sum_sell < sell_amount
+        WHEN 'buy' THEN sum_buy < buy_amount
+    END AND
+    (NOT invalidated) AND
+    presignature_pending;
+"#
+    );
+    sqlx::query_as(QUERY).bind(min_valid_to).fetch(ex)
+}
+
+/// Records that the orders were found pre-signed in the settlement contract at the block so that
+/// they stop being pending before their presignature events get indexed.
+pub async fn record_onchain_presignatures(
+    ex: &mut PgConnection,
+    order_uids: &[OrderUid],
+    block_number: i64,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO onchain_presignatures (order_uid, block_number)
+VALUES ($1, $2)
+ON CONFLICT (order_uid) DO UPDATE
+SET block_number = GREATEST(onchain_presignatures.block_number, EXCLUDED.block_number)
+    "#;
+    for order_uid in order_uids {
+        sqlx::query(QUERY)
+            .bind(order_uid)
+            .bind(block_number)
+            .execute(&mut *ex)
+            .await?;
+    }
+    Ok(())
+}
+
 pub async fn latest_settlement_block(ex: &mut PgConnection) -> Result<i64, sqlx::Error> {
     const QUERY: &str = r#"
 SELECT COALESCE(MAX(block_number), 0)
@@ -444,6 +594,20 @@ mod tests {
     use futures::{StreamExt, TryStreamExt};
     use sqlx::Connection;
 
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_signing_scheme_labels() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        for scheme in SigningScheme::ALL {
+            let label: String = sqlx::query_scalar("SELECT $1::text")
+                .bind(scheme)
+                .fetch_one(&mut db)
+                .await
+                .unwrap();
+            assert_eq!(label, scheme.label());
+        }
+    }
+
     #[tokio::test]
     #[ignore]
     async fn postgres_order_roundtrip() {
@@ -457,6 +621,33 @@ mod tests {
         assert_eq!(order, order_);
     }
 
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_order_class_roundtrip() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        for (i, class) in [
+            OrderClass::Market,
+            OrderClass::Limit,
+            OrderClass::Liquidity,
+            OrderClass::Twap,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let order = Order {
+                uid: ByteArray([i as u8; 56]),
+                class,
+                ..Default::default()
+            };
+            insert_order(&mut db, &order).await.unwrap();
+            let order_ = read_order(&mut db, &order.uid).await.unwrap().unwrap();
+            assert_eq!(order_.class, class);
+        }
+    }
+
     #[tokio::test]
     #[ignore]
     async fn postgres_insert_same_order_twice_fails() {
@@ -484,6 +675,9 @@ mod tests {
             sell_token_price: 3.,
             sell_amount: 4.into(),
             buy_amount: 5.into(),
+            subsidy_discount: Some(6.),
+            subsidy_min_discounted: Some(7.),
+            subsidy_factor: Some(0.5),
         };
         insert_quote(&mut db, &quote).await.unwrap();
         let quote_ = read_quote(&mut db, &quote.order_uid)
@@ -521,6 +715,51 @@ mod tests {
         assert_eq!(time, order.cancellation_timestamp.unwrap());
     }
 
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_cancel_open_order() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let time = DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(1234567890, 0), Utc);
+        let order = |uid| Order {
+            uid: ByteArray([uid; 56]),
+            kind: OrderKind::Sell,
+            sell_amount: 10.into(),
+            ..Default::default()
+        };
+
+        let open = order(1);
+        insert_order(&mut db, &open).await.unwrap();
+        assert!(cancel_open_order(&mut db, &open.uid, time).await.unwrap());
+        // Cancelled orders are not open anymore.
+        assert!(!cancel_open_order(&mut db, &open.uid, time).await.unwrap());
+
+        let filled = order(2);
+        insert_order(&mut db, &filled).await.unwrap();
+        crate::events::append(
+            &mut db,
+            &[(
+                EventIndex::default(),
+                Event::Trade(Trade {
+                    order_uid: filled.uid,
+                    sell_amount_including_fee: 10.into(),
+                    ..Default::default()
+                }),
+            )],
+        )
+        .await
+        .unwrap();
+        assert!(!cancel_open_order(&mut db, &filled.uid, time).await.unwrap());
+        let filled = read_order(&mut db, &filled.uid).await.unwrap().unwrap();
+        assert!(filled.cancellation_timestamp.is_none());
+
+        assert!(!cancel_open_order(&mut db, &ByteArray([3; 56]), time)
+            .await
+            .unwrap());
+    }
+
     // In the schema we set the type of executed amounts in individual events to a 78 decimal digit
     // number. Summing over multiple events could overflow this because the smart contract only
     // guarantees that the filled amount (which amount that is depends on order type) does not
@@ -610,6 +849,13 @@ mod tests {
         async fn get_order(ex: &mut PgConnection) -> Option<FullOrder> {
             solvable_orders(ex, 0).next().await.transpose().unwrap()
         }
+        async fn get_pending_order(ex: &mut PgConnection) -> Option<FullOrder> {
+            presignature_pending_orders(ex, 0)
+                .next()
+                .await
+                .transpose()
+                .unwrap()
+        }
 
         async fn pre_signature_event(
             ex: &mut PgTransaction<'_>,
@@ -634,18 +880,37 @@ mod tests {
 
         // not solvable because there is no presignature event.
         assert!(get_order(&mut db).await.is_none());
+        assert!(get_pending_order(&mut db).await.is_some());
 
         // solvable because once presignature event is observed.
         pre_signature_event(&mut db, 0, order.owner, order.uid, true).await;
         assert!(get_order(&mut db).await.is_some());
+        assert!(get_pending_order(&mut db).await.is_none());
 
         // not solvable because "unsigned" presignature event.
         pre_signature_event(&mut db, 1, order.owner, order.uid, false).await;
         assert!(get_order(&mut db).await.is_none());
+        assert!(get_pending_order(&mut db).await.is_some());
 
         // solvable once again because of new presignature event.
         pre_signature_event(&mut db, 2, order.owner, order.uid, true).await;
         assert!(get_order(&mut db).await.is_some());
+
+        // not solvable because of a newer "unsigned" presignature event.
+        pre_signature_event(&mut db, 3, order.owner, order.uid, false).await;
+        assert!(get_order(&mut db).await.is_none());
+
+        // solvable because the presignature was found onchain before its event got indexed.
+        record_onchain_presignatures(&mut db, &[order.uid], 4)
+            .await
+            .unwrap();
+        assert!(get_order(&mut db).await.is_some());
+        assert!(get_pending_order(&mut db).await.is_none());
+
+        // newer events take precedence over the onchain presignature.
+        pre_signature_event(&mut db, 5, order.owner, order.uid, false).await;
+        assert!(get_order(&mut db).await.is_none());
+        assert!(get_pending_order(&mut db).await.is_some());
     }
 
     #[tokio::test]
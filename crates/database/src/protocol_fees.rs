@@ -0,0 +1,95 @@
+use crate::Address;
+use bigdecimal::BigDecimal;
+use sqlx::PgConnection;
+
+/// The protocol fees accrued in a token.
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct TokenAccrual {
+    pub token: Address,
+    pub amount: BigDecimal,
+    pub trades: i64,
+}
+
+/// Returns the protocol fees accrued by all trades per token.
+pub async fn accrued_per_token(ex: &mut PgConnection) -> Result<Vec<TokenAccrual>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT token, SUM(amount) AS amount, COUNT(*) AS trades
+FROM protocol_fee_accruals
+GROUP BY token
+ORDER BY token
+    "#;
+    sqlx::query_as(QUERY).fetch_all(ex).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        byte_array::ByteArray,
+        events::{Event, EventIndex, Trade},
+        orders::{insert_order, Order},
+    };
+    use sqlx::Connection;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_protocol_fees_accrue_with_trades() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let token = ByteArray([9; 20]);
+        for (uid, protocol_fee_amount) in [(1, 10), (2, 0), (3, 4)] {
+            let order = Order {
+                uid: ByteArray([uid; 56]),
+                sell_token: token,
+                fee_amount: 20.into(),
+                protocol_fee_amount: protocol_fee_amount.into(),
+                ..Default::default()
+            };
+            insert_order(&mut db, &order).await.unwrap();
+        }
+        let trade = |uid, fee_amount: u32| {
+            Event::Trade(Trade {
+                order_uid: ByteArray([uid; 56]),
+                fee_amount: fee_amount.into(),
+                ..Default::default()
+            })
+        };
+        let index = |block_number, log_index| EventIndex {
+            block_number,
+            log_index,
+        };
+        crate::events::append(
+            &mut db,
+            &[
+                // Order 1 gets filled in two halves.
+                (index(1, 0), trade(1, 10)),
+                (index(1, 1), trade(2, 20)),
+                (index(2, 0), trade(1, 10)),
+                (index(2, 1), trade(3, 20)),
+            ],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            accrued_per_token(&mut db).await.unwrap(),
+            [TokenAccrual {
+                token,
+                amount: 14.into(),
+                trades: 3,
+            }]
+        );
+
+        crate::events::delete(&mut db, 2).await.unwrap();
+        assert_eq!(
+            accrued_per_token(&mut db).await.unwrap(),
+            [TokenAccrual {
+                token,
+                amount: 5.into(),
+                trades: 1,
+            }]
+        );
+    }
+}
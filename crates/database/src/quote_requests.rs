@@ -0,0 +1,152 @@
+use crate::{quotes::QuoteId, AppId};
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    PgConnection,
+};
+
+/// One row in the `quote_requests` table.
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct QuoteRequest {
+    pub time: DateTime<Utc>,
+    pub app_data: AppId,
+    pub user_agent: Option<String>,
+    pub quote_id: Option<QuoteId>,
+}
+
+pub async fn insert(ex: &mut PgConnection, request: &QuoteRequest) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO quote_requests (time, app_data, user_agent, quote_id)
+VALUES ($1, $2, $3, $4)
+    "#;
+    sqlx::query(QUERY)
+        .bind(request.time)
+        .bind(&request.app_data)
+        .bind(&request.user_agent)
+        .bind(request.quote_id)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// The quote funnel of one app data summed over a period.
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct IntegratorFunnel {
+    pub app_data: AppId,
+    pub quote_requests: i64,
+    pub quotes: i64,
+    pub orders: i64,
+    pub filled_orders: i64,
+}
+
+/// Recomputes the funnel without blocking concurrent reads.
+pub async fn refresh_funnel(ex: &mut PgConnection) -> Result<(), sqlx::Error> {
+    const QUERY: &str = "REFRESH MATERIALIZED VIEW CONCURRENTLY integrator_quote_funnel;";
+    sqlx::query(QUERY).execute(ex).await?;
+    Ok(())
+}
+
+/// Returns the funnel of every app data with activity in the days from `start` (inclusive) to
+/// `end` (exclusive) as of the last refresh, most quote requests first.
+pub async fn load_funnel(
+    ex: &mut PgConnection,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<IntegratorFunnel>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT
+    app_data,
+    SUM(quote_requests)::bigint AS quote_requests,
+    SUM(quotes)::bigint AS quotes,
+    SUM(orders)::bigint AS orders,
+    SUM(filled_orders)::bigint AS filled_orders
+FROM integrator_quote_funnel
+WHERE day >= $1 AND day < $2
+GROUP BY app_data
+ORDER BY quote_requests DESC, orders DESC
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(start)
+        .bind(end)
+        .fetch_all(ex)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        byte_array::ByteArray,
+        events::{Event, EventIndex, Trade},
+        orders::{insert_order, Order},
+    };
+    use sqlx::Connection;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_integrator_funnel() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let now = Utc::now();
+        let integrator = ByteArray([1; 32]);
+        let other = ByteArray([2; 32]);
+        for (app_data, quote_id) in [(integrator, Some(1)), (integrator, None), (other, Some(2))] {
+            let request = QuoteRequest {
+                time: now,
+                app_data,
+                user_agent: Some("sdk".to_string()),
+                quote_id,
+            };
+            insert(&mut db, &request).await.unwrap();
+        }
+        for uid in [1, 2] {
+            let order = Order {
+                uid: ByteArray([uid; 56]),
+                app_data: integrator,
+                creation_timestamp: now,
+                ..Default::default()
+            };
+            insert_order(&mut db, &order).await.unwrap();
+        }
+        crate::events::append(
+            &mut db,
+            &[(
+                EventIndex::default(),
+                Event::Trade(Trade {
+                    order_uid: ByteArray([1; 56]),
+                    ..Default::default()
+                }),
+            )],
+        )
+        .await
+        .unwrap();
+
+        let start = now - chrono::Duration::days(1);
+        let end = now + chrono::Duration::days(1);
+        refresh_funnel(&mut db).await.unwrap();
+        assert_eq!(
+            load_funnel(&mut db, start, end).await.unwrap(),
+            vec![
+                IntegratorFunnel {
+                    app_data: integrator,
+                    quote_requests: 2,
+                    quotes: 1,
+                    orders: 2,
+                    filled_orders: 1,
+                },
+                IntegratorFunnel {
+                    app_data: other,
+                    quote_requests: 1,
+                    quotes: 1,
+                    orders: 0,
+                    filled_orders: 0,
+                },
+            ]
+        );
+        assert!(load_funnel(&mut db, end, end + chrono::Duration::days(1))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
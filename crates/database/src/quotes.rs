@@ -123,6 +123,25 @@ LIMIT 1
         .await
 }
 
+/// Updates the expiration of a quote and returns whether the quote exists.
+pub async fn update_expiration(
+    ex: &mut PgConnection,
+    id: QuoteId,
+    expiration: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    const QUERY: &str = r#"
+UPDATE quotes
+SET expiration_timestamp = $2
+WHERE id = $1
+    "#;
+    let result = sqlx::query(QUERY)
+        .bind(id)
+        .bind(expiration)
+        .execute(ex)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
 pub async fn remove_expired_quotes(
     ex: &mut PgConnection,
     max_expiry: DateTime<Utc>,
@@ -183,6 +202,40 @@ mod tests {
         assert_eq!(get(&mut db, id).await.unwrap(), None);
     }
 
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_update_quote_expiration() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let now = low_precision_now();
+        let mut quote = Quote {
+            id: Default::default(),
+            sell_token: ByteArray([1; 20]),
+            buy_token: ByteArray([2; 20]),
+            sell_amount: 3.into(),
+            buy_amount: 4.into(),
+            gas_amount: 5.,
+            gas_price: 6.,
+            sell_token_price: 7.,
+            order_kind: OrderKind::Sell,
+            expiration_timestamp: now,
+            quote_kind: QuoteKind::Standard,
+        };
+        let id = save(&mut db, &quote).await.unwrap();
+        quote.id = id;
+
+        let expiration = now + Duration::seconds(60);
+        assert!(update_expiration(&mut db, id, expiration).await.unwrap());
+        quote.expiration_timestamp = expiration;
+        assert_eq!(get(&mut db, id).await.unwrap().unwrap(), quote);
+
+        assert!(!update_expiration(&mut db, id + 1, expiration)
+            .await
+            .unwrap());
+    }
+
     #[tokio::test]
     #[ignore]
     async fn postgres_save_and_find_quote() {
@@ -0,0 +1,159 @@
+use crate::OrderUid;
+use chrono::{DateTime, Utc};
+use sqlx::PgConnection;
+
+pub type SealedOrderId = crate::byte_array::ByteArray<32>;
+
+/// One row in the `sealed_orders` table.
+#[derive(Clone, Debug, Eq, PartialEq, sqlx::FromRow)]
+pub struct SealedOrder {
+    pub id: SealedOrderId,
+    pub sealed: Vec<u8>,
+    pub creation_timestamp: DateTime<Utc>,
+    pub order_uid: Option<OrderUid>,
+    pub rejection_reason: Option<String>,
+}
+
+/// Inserts a sealed order. Submitting the same sealed order again is a no-op.
+pub async fn insert(ex: &mut PgConnection, order: &SealedOrder) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO sealed_orders (id, sealed, creation_timestamp, order_uid, rejection_reason)
+VALUES ($1, $2, $3, $4, $5)
+ON CONFLICT (id) DO NOTHING
+    ;"#;
+    sqlx::query(QUERY)
+        .bind(order.id)
+        .bind(&order.sealed)
+        .bind(order.creation_timestamp)
+        .bind(order.order_uid)
+        .bind(&order.rejection_reason)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+pub async fn fetch(
+    ex: &mut PgConnection,
+    id: &SealedOrderId,
+) -> Result<Option<SealedOrder>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT * FROM sealed_orders
+WHERE id = $1
+    ;"#;
+    sqlx::query_as(QUERY).bind(id).fetch_optional(ex).await
+}
+
+/// The sealed orders that have been neither revealed nor rejected, oldest first.
+pub async fn pending(ex: &mut PgConnection) -> Result<Vec<SealedOrder>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT * FROM sealed_orders
+WHERE order_uid IS NULL AND rejection_reason IS NULL
+ORDER BY creation_timestamp
+    ;"#;
+    sqlx::query_as(QUERY).fetch_all(ex).await
+}
+
+pub async fn mark_revealed(
+    ex: &mut PgConnection,
+    id: &SealedOrderId,
+    order_uid: &OrderUid,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+UPDATE sealed_orders
+SET order_uid = $2
+WHERE id = $1
+    ;"#;
+    sqlx::query(QUERY)
+        .bind(id)
+        .bind(order_uid)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+pub async fn mark_rejected(
+    ex: &mut PgConnection,
+    id: &SealedOrderId,
+    reason: &str,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+UPDATE sealed_orders
+SET rejection_reason = $2
+WHERE id = $1
+    ;"#;
+    sqlx::query(QUERY).bind(id).bind(reason).execute(ex).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_array::ByteArray;
+    use chrono::NaiveDateTime;
+    use sqlx::Connection;
+
+    fn sealed_order(id: u8, creation_timestamp: i64) -> SealedOrder {
+        SealedOrder {
+            id: ByteArray([id; 32]),
+            sealed: vec![id],
+            creation_timestamp: DateTime::from_utc(
+                NaiveDateTime::from_timestamp(creation_timestamp, 0),
+                Utc,
+            ),
+            order_uid: None,
+            rejection_reason: None,
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_sealed_orders() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let older = sealed_order(1, 1);
+        let newer = sealed_order(2, 2);
+        let rejected = sealed_order(3, 2);
+        for order in [&newer, &older, &rejected] {
+            insert(&mut db, order).await.unwrap();
+        }
+        // Submitting again changes nothing.
+        insert(
+            &mut db,
+            &SealedOrder {
+                sealed: vec![0],
+                ..newer.clone()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(fetch(&mut db, &newer.id).await.unwrap().unwrap(), newer);
+        assert_eq!(fetch(&mut db, &ByteArray([4; 32])).await.unwrap(), None);
+
+        mark_rejected(&mut db, &rejected.id, "invalid tag")
+            .await
+            .unwrap();
+        assert_eq!(
+            pending(&mut db).await.unwrap(),
+            vec![older.clone(), newer.clone()]
+        );
+
+        let uid = ByteArray([5; 56]);
+        mark_revealed(&mut db, &older.id, &uid).await.unwrap();
+        assert_eq!(pending(&mut db).await.unwrap(), vec![newer]);
+        assert_eq!(
+            fetch(&mut db, &older.id).await.unwrap().unwrap().order_uid,
+            Some(uid)
+        );
+        assert_eq!(
+            fetch(&mut db, &rejected.id)
+                .await
+                .unwrap()
+                .unwrap()
+                .rejection_reason
+                .as_deref(),
+            Some("invalid tag")
+        );
+    }
+}
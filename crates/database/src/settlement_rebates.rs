@@ -0,0 +1,300 @@
+use crate::{
+    auction::AuctionId, events::EventIndex, Address, OrderUid, PgTransaction, TransactionHash,
+};
+use bigdecimal::BigDecimal;
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    PgConnection,
+};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, sqlx::FromRow)]
+pub struct UnprocessedSettlement {
+    pub block_number: i64,
+    pub log_index: i64,
+    pub solver: Address,
+    pub tx_hash: TransactionHash,
+}
+
+/// Returns the oldest settlements starting at the specified block whose rebates have not been
+/// indexed yet and that happened before the specified time. Settlements whose block timestamp is
+/// not known yet are skipped.
+pub async fn unprocessed_settlements(
+    ex: &mut PgConnection,
+    start_block: i64,
+    before: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<UnprocessedSettlement>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT s.block_number, s.log_index, s.solver, s.tx_hash
+FROM settlements s
+WHERE s.block_number >= $1
+AND s.block_timestamp < $2
+AND NOT EXISTS (
+    SELECT 1 FROM settlement_rebates r
+    WHERE r.block_number = s.block_number AND r.log_index = s.log_index
+)
+ORDER BY s.block_number, s.log_index
+LIMIT $3
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(start_block)
+        .bind(before)
+        .bind(limit)
+        .fetch_all(ex)
+        .await
+}
+
+/// Returns the orders that are not liquidity orders the settlement with the specified event index
+/// executed in the order of their trade events.
+pub async fn settled_user_orders(
+    ex: &mut PgConnection,
+    settlement: &EventIndex,
+) -> Result<Vec<OrderUid>, sqlx::Error> {
+    // Trade events are emitted before the settlement event of the same transaction.
+    const QUERY: &str = r#"
+SELECT t.order_uid
+FROM trades t
+JOIN orders o ON o.uid = t.order_uid
+WHERE t.block_number = $1
+AND t.log_index < $2
+AND t.log_index > COALESCE((
+    SELECT MAX(s.log_index) FROM settlements s
+    WHERE s.block_number = $1 AND s.log_index < $2
+), -1)
+AND o.class NOT IN ('limit', 'liquidity')
+ORDER BY t.log_index
+    "#;
+    sqlx::query_scalar(QUERY)
+        .bind(settlement.block_number)
+        .bind(settlement.log_index)
+        .fetch_all(ex)
+        .await
+}
+
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct SettlementRebate {
+    pub block_number: i64,
+    pub log_index: i64,
+    pub tx_hash: TransactionHash,
+    pub auction_id: Option<AuctionId>,
+    pub recipient: Address,
+    pub rebate: BigDecimal,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct OrderRebate {
+    pub order_uid: OrderUid,
+    pub rebate: BigDecimal,
+}
+
+/// Stores the rebate of a settlement and its attribution to the settled orders. The settlement
+/// gets attributed to the auction whose solver competition has the same transaction hash.
+pub async fn insert(
+    ex: &mut PgTransaction<'_>,
+    settlement: &EventIndex,
+    tx_hash: &TransactionHash,
+    recipient: &Address,
+    rebate: &BigDecimal,
+    orders: &[OrderRebate],
+) -> Result<(), sqlx::Error> {
+    const INSERT_SETTLEMENT: &str = r#"
+INSERT INTO settlement_rebates (block_number, log_index, tx_hash, auction_id, recipient, rebate)
+VALUES ($1, $2, $3, (SELECT id FROM solver_competitions WHERE tx_hash = $3), $4, $5)
+    "#;
+    sqlx::query(INSERT_SETTLEMENT)
+        .bind(settlement.block_number)
+        .bind(settlement.log_index)
+        .bind(tx_hash)
+        .bind(recipient)
+        .bind(rebate)
+        .execute(&mut *ex)
+        .await?;
+
+    const INSERT_ORDER: &str = r#"
+INSERT INTO order_rebates (block_number, log_index, order_uid, rebate)
+VALUES ($1, $2, $3, $4)
+    "#;
+    for order in orders {
+        sqlx::query(INSERT_ORDER)
+            .bind(settlement.block_number)
+            .bind(settlement.log_index)
+            .bind(order.order_uid)
+            .bind(&order.rebate)
+            .execute(&mut *ex)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Returns the rebate of the settlement of the specified auction.
+pub async fn load_by_auction(
+    ex: &mut PgConnection,
+    auction_id: AuctionId,
+) -> Result<Option<SettlementRebate>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT block_number, log_index, tx_hash, auction_id, recipient, rebate
+FROM settlement_rebates
+WHERE auction_id = $1
+ORDER BY block_number DESC, log_index DESC
+LIMIT 1
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(auction_id)
+        .fetch_optional(ex)
+        .await
+}
+
+/// Returns the shares of the rebate of the specified settlement that are attributed to its orders.
+pub async fn load_order_rebates(
+    ex: &mut PgConnection,
+    settlement: &EventIndex,
+) -> Result<Vec<OrderRebate>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT order_uid, rebate
+FROM order_rebates
+WHERE block_number = $1 AND log_index = $2
+ORDER BY order_uid
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(settlement.block_number)
+        .bind(settlement.log_index)
+        .fetch_all(ex)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        byte_array::ByteArray,
+        events::{Event, Settlement, Trade},
+        orders::{insert_order, Order, OrderClass},
+    };
+    use sqlx::Connection;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_settlement_rebates_roundtrip() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        for (uid, class) in [
+            (1, OrderClass::Market),
+            (2, OrderClass::Liquidity),
+            (3, OrderClass::Twap),
+        ] {
+            let order = Order {
+                uid: ByteArray([uid; 56]),
+                class,
+                ..Default::default()
+            };
+            insert_order(&mut db, &order).await.unwrap();
+        }
+        let index = |block_number, log_index| EventIndex {
+            block_number,
+            log_index,
+        };
+        let trade = |uid| {
+            Event::Trade(Trade {
+                order_uid: ByteArray([uid; 56]),
+                ..Default::default()
+            })
+        };
+        let tx_hash = ByteArray([4; 32]);
+        crate::events::append(
+            &mut db,
+            &[
+                (index(1, 0), trade(1)),
+                (index(1, 1), trade(2)),
+                (index(1, 2), trade(3)),
+                (
+                    index(1, 3),
+                    Event::Settlement(Settlement {
+                        solver: ByteArray([5; 20]),
+                        transaction_hash: tx_hash,
+                    }),
+                ),
+            ],
+        )
+        .await
+        .unwrap();
+        crate::solver_competition::save(&mut db, 6, &Default::default(), Some(&tx_hash))
+            .await
+            .unwrap();
+
+        let now = Utc::now();
+        // Without block timestamps no settlement can be processed.
+        assert!(unprocessed_settlements(&mut db, 0, now, 10)
+            .await
+            .unwrap()
+            .is_empty());
+        crate::order_book_stats::set_block_timestamp(&mut db, 1, now - chrono::Duration::hours(1))
+            .await
+            .unwrap();
+        assert!(unprocessed_settlements(&mut db, 2, now, 10)
+            .await
+            .unwrap()
+            .is_empty());
+        let settlements = unprocessed_settlements(&mut db, 0, now, 10).await.unwrap();
+        assert_eq!(
+            settlements,
+            [UnprocessedSettlement {
+                block_number: 1,
+                log_index: 3,
+                solver: ByteArray([5; 20]),
+                tx_hash,
+            }]
+        );
+
+        let orders = settled_user_orders(&mut db, &index(1, 3)).await.unwrap();
+        assert_eq!(orders, [ByteArray([1; 56]), ByteArray([3; 56])]);
+
+        let order_rebates = orders
+            .into_iter()
+            .map(|order_uid| OrderRebate {
+                order_uid,
+                rebate: 5.into(),
+            })
+            .collect::<Vec<_>>();
+        insert(
+            &mut db,
+            &index(1, 3),
+            &tx_hash,
+            &ByteArray([5; 20]),
+            &10.into(),
+            &order_rebates,
+        )
+        .await
+        .unwrap();
+        assert!(unprocessed_settlements(&mut db, 0, now, 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(
+            load_by_auction(&mut db, 6).await.unwrap(),
+            Some(SettlementRebate {
+                block_number: 1,
+                log_index: 3,
+                tx_hash,
+                auction_id: Some(6),
+                recipient: ByteArray([5; 20]),
+                rebate: 10.into(),
+            })
+        );
+        assert_eq!(load_by_auction(&mut db, 7).await.unwrap(), None);
+        assert_eq!(
+            load_order_rebates(&mut db, &index(1, 3)).await.unwrap(),
+            order_rebates
+        );
+
+        // Rebates of reorged settlements get deleted with them.
+        crate::events::delete(&mut db, 1).await.unwrap();
+        assert_eq!(load_by_auction(&mut db, 6).await.unwrap(), None);
+        assert!(load_order_rebates(&mut db, &index(1, 3))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+use crate::auction::AuctionId;
+use sqlx::{
+    types::{
+        chrono::{DateTime, Utc},
+        JsonValue,
+    },
+    PgConnection,
+};
+
+/// The result of a single shadow driver for an auction.
+#[derive(Clone, Debug, PartialEq, sqlx::FromRow)]
+pub struct ShadowResult {
+    pub auction_id: AuctionId,
+    pub driver: String,
+    pub creation_timestamp: DateTime<Utc>,
+    pub score: Option<f64>,
+    pub json: JsonValue,
+}
+
+pub async fn save(ex: &mut PgConnection, result: &ShadowResult) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO shadow_competitions (auction_id, driver, creation_timestamp, score, json)
+VALUES ($1, $2, $3, $4, $5)
+ON CONFLICT DO NOTHING
+    ;"#;
+    sqlx::query(QUERY)
+        .bind(result.auction_id)
+        .bind(&result.driver)
+        .bind(result.creation_timestamp)
+        .bind(result.score)
+        .bind(&result.json)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Returns the results of all shadow drivers for an auction ordered by descending score.
+pub async fn load_by_auction_id(
+    ex: &mut PgConnection,
+    auction_id: AuctionId,
+) -> Result<Vec<ShadowResult>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT *
+FROM shadow_competitions
+WHERE auction_id = $1
+ORDER BY score DESC NULLS LAST, driver
+    ;"#;
+    sqlx::query_as(QUERY).bind(auction_id).fetch_all(ex).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+    use sqlx::Connection;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_roundtrip() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let now = DateTime::from_utc(NaiveDateTime::from_timestamp(1234567890, 0), Utc);
+        let result = |driver: &str, score| ShadowResult {
+            auction_id: 1,
+            driver: driver.to_string(),
+            creation_timestamp: now,
+            score,
+            json: JsonValue::Bool(true),
+        };
+        let failed = result("a", None);
+        let worse = result("b", Some(1.));
+        let better = result("c", Some(2.));
+        for result in [&failed, &worse, &better] {
+            save(&mut db, result).await.unwrap();
+        }
+        // Duplicates are ignored.
+        save(&mut db, &result("c", Some(3.))).await.unwrap();
+
+        assert_eq!(
+            load_by_auction_id(&mut db, 1).await.unwrap(),
+            vec![better, worse, failed]
+        );
+        assert!(load_by_auction_id(&mut db, 2).await.unwrap().is_empty());
+    }
+}
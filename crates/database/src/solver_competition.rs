@@ -1,6 +1,13 @@
-use crate::{auction::AuctionId, TransactionHash};
+use crate::{auction::AuctionId, Address, TransactionHash};
+use chrono::{DateTime, Utc};
 use sqlx::{types::JsonValue, PgConnection};
 
+/// The channel on which the ids of newly saved solver competitions get announced with
+/// `NOTIFY`.
+pub const NOTIFICATION_CHANNEL: &str = "solver_competitions";
+
+/// Saves the solver competition and notifies listeners of `NOTIFICATION_CHANNEL` with its id once
+/// the surrounding transaction commits.
 pub async fn save(
     ex: &mut PgConnection,
     id: AuctionId,
@@ -8,46 +15,181 @@ pub async fn save(
     tx_hash: Option<&TransactionHash>,
 ) -> Result<(), sqlx::Error> {
     const QUERY: &str = r#"
-INSERT INTO solver_competitions (id, json, tx_hash)
-VALUES ($1, $2, $3)
+WITH inserted AS (
+    INSERT INTO solver_competitions (id, json, tx_hash)
+    VALUES ($1, $2, $3)
+    RETURNING id
+)
+SELECT pg_notify($4, id::text) FROM inserted
     ;"#;
     sqlx::query(QUERY)
         .bind(id)
         .bind(data)
         .bind(tx_hash)
+        .bind(NOTIFICATION_CHANNEL)
         .execute(ex)
         .await?;
     Ok(())
 }
 
+#[derive(Clone, Debug, Eq, PartialEq, sqlx::FromRow)]
+pub struct StoredCompetition {
+    pub id: AuctionId,
+    /// `None` if the competition got archived.
+    pub json: Option<JsonValue>,
+}
+
 pub async fn load_by_id(
     ex: &mut PgConnection,
     id: AuctionId,
-) -> Result<Option<JsonValue>, sqlx::Error> {
+) -> Result<Option<StoredCompetition>, sqlx::Error> {
     const QUERY: &str = r#"
-SELECT json
+SELECT id, json
 FROM solver_competitions
 WHERE id = $1
     ;"#;
-    let solver_competition: Option<(JsonValue,)> =
-        sqlx::query_as(QUERY).bind(id).fetch_optional(ex).await?;
-    Ok(solver_competition.map(|inner| inner.0))
+    sqlx::query_as(QUERY).bind(id).fetch_optional(ex).await
 }
 
 pub async fn load_by_tx_hash(
     ex: &mut PgConnection,
     tx_hash: &TransactionHash,
-) -> Result<Option<JsonValue>, sqlx::Error> {
+) -> Result<Option<StoredCompetition>, sqlx::Error> {
     const QUERY: &str = r#"
-SELECT json
+SELECT id, json
 FROM solver_competitions
 WHERE tx_hash = $1
     ;"#;
-    let solver_competition: Option<(JsonValue,)> = sqlx::query_as(QUERY)
-        .bind(tx_hash)
-        .fetch_optional(ex)
-        .await?;
-    Ok(solver_competition.map(|inner| inner.0))
+    sqlx::query_as(QUERY).bind(tx_hash).fetch_optional(ex).await
+}
+
+/// Loads the oldest competitions created before the specified time that haven't been archived
+/// yet.
+pub async fn load_archivable(
+    ex: &mut PgConnection,
+    created_before: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<(AuctionId, JsonValue)>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT id, json
+FROM solver_competitions
+WHERE json IS NOT NULL AND created < $1
+ORDER BY created, id
+LIMIT $2
+    ;"#;
+    sqlx::query_as(QUERY)
+        .bind(created_before)
+        .bind(limit)
+        .fetch_all(ex)
+        .await
+}
+
+/// Drops the json of a competition once it is stored in the archive.
+pub async fn mark_archived(ex: &mut PgConnection, id: AuctionId) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+UPDATE solver_competitions
+SET json = NULL
+WHERE id = $1
+    ;"#;
+    sqlx::query(QUERY).bind(id).execute(ex).await?;
+    Ok(())
+}
+
+/// Loads the most recent solver competitions, newest first.
+pub async fn load_latest(
+    ex: &mut PgConnection,
+    limit: i64,
+) -> Result<Vec<(AuctionId, JsonValue)>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT id, json
+FROM solver_competitions
+WHERE json IS NOT NULL
+ORDER BY id DESC
+LIMIT $1
+    ;"#;
+    sqlx::query_as(QUERY).bind(limit).fetch_all(ex).await
+}
+
+/// Loads the most recent solver competitions in which the solver proposed a solution or had a
+/// solution that failed to simulate, newest first.
+pub async fn load_latest_by_solver(
+    ex: &mut PgConnection,
+    solver: &str,
+    limit: i64,
+) -> Result<Vec<JsonValue>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT json
+FROM solver_competitions
+WHERE json->'solutions' @> jsonb_build_array(jsonb_build_object('solver', $1::text))
+OR json->'simulationFailures' @> jsonb_build_array(jsonb_build_object('solver', $1::text))
+ORDER BY id DESC
+LIMIT $2
+    ;"#;
+    sqlx::query_scalar(QUERY)
+        .bind(solver)
+        .bind(limit)
+        .fetch_all(ex)
+        .await
+}
+
+/// Filters solver competitions. Unset fields don't filter.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CompetitionFilter<'a> {
+    /// Only competitions whose auction includes the token.
+    pub token: Option<&'a Address>,
+    /// Only competitions in which the solver proposed a solution or had a solution that failed to
+    /// simulate.
+    pub solver: Option<&'a str>,
+    /// Inclusive lower bound of the time the competition was stored.
+    pub from: Option<DateTime<Utc>>,
+    /// Exclusive upper bound of the time the competition was stored.
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, sqlx::FromRow)]
+pub struct FoundCompetition {
+    pub id: AuctionId,
+    pub created: DateTime<Utc>,
+    pub json: JsonValue,
+}
+
+/// Loads the solver competitions matching the filter, newest first. Archived competitions can't be
+/// searched and are never returned.
+pub async fn search(
+    ex: &mut PgConnection,
+    filter: &CompetitionFilter<'_>,
+    offset: i64,
+    limit: i64,
+) -> Result<Vec<FoundCompetition>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT id, created, json
+FROM solver_competitions
+WHERE
+    json IS NOT NULL
+AND
+    ($1::bytea IS NULL OR json->'auction'->'prices' ? ('0x' || encode($1, 'hex')))
+AND (
+    $2::text IS NULL
+    OR json->'solutions' @> jsonb_build_array(jsonb_build_object('solver', $2))
+    OR json->'simulationFailures' @> jsonb_build_array(jsonb_build_object('solver', $2))
+)
+AND
+    ($3::timestamptz IS NULL OR created >= $3)
+AND
+    ($4::timestamptz IS NULL OR created < $4)
+ORDER BY id DESC
+LIMIT $5
+OFFSET $6
+    ;"#;
+    sqlx::query_as(QUERY)
+        .bind(filter.token)
+        .bind(filter.solver)
+        .bind(filter.from)
+        .bind(filter.to)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(ex)
+        .await
 }
 
 #[cfg(test)]
@@ -66,7 +208,7 @@ mod tests {
         let value = JsonValue::Bool(true);
         save(&mut db, 0, &value, None).await.unwrap();
         let value_ = load_by_id(&mut db, 0).await.unwrap().unwrap();
-        assert_eq!(value, value_);
+        assert_eq!(value_.json, Some(value));
         assert!(load_by_id(&mut db, 1).await.unwrap().is_none());
     }
 
@@ -83,12 +225,175 @@ mod tests {
 
         let value_by_id = load_by_id(&mut db, 0).await.unwrap().unwrap();
         let value_by_hash = load_by_tx_hash(&mut db, &hash).await.unwrap().unwrap();
-        assert_eq!(value, value_by_id);
-        assert_eq!(value, value_by_hash);
+        assert_eq!(value_by_id.json, Some(value.clone()));
+        assert_eq!(value_by_hash.json, Some(value));
 
         let not_found = load_by_tx_hash(&mut db, &ByteArray([2u8; 32]))
             .await
             .unwrap();
         assert!(not_found.is_none());
     }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_notifies_listeners() {
+        let db = sqlx::PgPool::connect("postgresql://").await.unwrap();
+        crate::clear_DANGER(&db).await.unwrap();
+        let mut listener = sqlx::postgres::PgListener::connect_with(&db).await.unwrap();
+        listener.listen(NOTIFICATION_CHANNEL).await.unwrap();
+
+        let mut ex = db.acquire().await.unwrap();
+        save(&mut ex, 42, &JsonValue::Bool(true), None)
+            .await
+            .unwrap();
+        let notification = listener.recv().await.unwrap();
+        assert_eq!(notification.payload(), "42");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_load_latest() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        for id in 0..3 {
+            save(&mut db, id, &JsonValue::from(id), None).await.unwrap();
+        }
+        let latest = load_latest(&mut db, 2).await.unwrap();
+        assert_eq!(
+            latest,
+            vec![(2, JsonValue::from(2)), (1, JsonValue::from(1))]
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_load_latest_by_solver() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let solved: JsonValue = r#"{"solutions": [{"solver": "a"}, {"solver": "b"}]}"#
+            .parse()
+            .unwrap();
+        let failed: JsonValue = r#"{"solutions": [], "simulationFailures": [{"solver": "a"}]}"#
+            .parse()
+            .unwrap();
+        save(&mut db, 0, &solved, None).await.unwrap();
+        save(&mut db, 1, &failed, None).await.unwrap();
+
+        assert_eq!(
+            load_latest_by_solver(&mut db, "a", 10).await.unwrap(),
+            vec![failed.clone(), solved.clone()]
+        );
+        assert_eq!(
+            load_latest_by_solver(&mut db, "a", 1).await.unwrap(),
+            vec![failed]
+        );
+        assert_eq!(
+            load_latest_by_solver(&mut db, "b", 10).await.unwrap(),
+            vec![solved]
+        );
+        assert!(load_latest_by_solver(&mut db, "c", 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_search() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let token = ByteArray([1; 20]);
+        let competition = |token: &str, solver: &str| -> JsonValue {
+            format!(
+                r#"{{"auction": {{"prices": {{"{token}": "1"}}}}, "solutions": [{{"solver": "{solver}"}}]}}"#
+            )
+            .parse()
+            .unwrap()
+        };
+        let a = competition("0x0101010101010101010101010101010101010101", "a");
+        let b = competition("0x0202020202020202020202020202020202020202", "a");
+        let c = competition("0x0101010101010101010101010101010101010101", "b");
+        for (id, json) in [&a, &b, &c].into_iter().enumerate() {
+            save(&mut db, id as AuctionId, json, None).await.unwrap();
+        }
+        mark_archived(&mut db, 2).await.unwrap();
+
+        let ids = |found: Vec<FoundCompetition>| {
+            found
+                .into_iter()
+                .map(|competition| competition.id)
+                .collect::<Vec<_>>()
+        };
+        let filter = CompetitionFilter::default();
+        assert_eq!(ids(search(&mut db, &filter, 0, 10).await.unwrap()), [1, 0]);
+        assert_eq!(ids(search(&mut db, &filter, 1, 10).await.unwrap()), [0]);
+        assert_eq!(ids(search(&mut db, &filter, 0, 1).await.unwrap()), [1]);
+
+        let filter = CompetitionFilter {
+            token: Some(&token),
+            ..Default::default()
+        };
+        assert_eq!(ids(search(&mut db, &filter, 0, 10).await.unwrap()), [0]);
+
+        let filter = CompetitionFilter {
+            solver: Some("b"),
+            ..Default::default()
+        };
+        assert!(search(&mut db, &filter, 0, 10).await.unwrap().is_empty());
+
+        let now = Utc::now();
+        let filter = CompetitionFilter {
+            solver: Some("a"),
+            from: Some(now - chrono::Duration::days(1)),
+            to: Some(now + chrono::Duration::days(1)),
+            ..Default::default()
+        };
+        assert_eq!(ids(search(&mut db, &filter, 0, 10).await.unwrap()), [1, 0]);
+        let filter = CompetitionFilter {
+            to: Some(now - chrono::Duration::days(1)),
+            ..Default::default()
+        };
+        assert!(search(&mut db, &filter, 0, 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_archive() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        for id in 0..3 {
+            save(&mut db, id, &JsonValue::from(id), None).await.unwrap();
+        }
+        let now = Utc::now() + chrono::Duration::seconds(1);
+        assert!(
+            load_archivable(&mut db, Utc::now() - chrono::Duration::days(1), 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(load_archivable(&mut db, now, 10).await.unwrap().len(), 3);
+
+        mark_archived(&mut db, 1).await.unwrap();
+        let archivable = load_archivable(&mut db, now, 10).await.unwrap();
+        assert_eq!(
+            archivable.into_iter().map(|(id, _)| id).collect::<Vec<_>>(),
+            [0, 2]
+        );
+        assert_eq!(
+            load_by_id(&mut db, 1).await.unwrap(),
+            Some(StoredCompetition { id: 1, json: None })
+        );
+        assert_eq!(
+            load_latest(&mut db, 10).await.unwrap(),
+            vec![(2, JsonValue::from(2)), (0, JsonValue::from(0))]
+        );
+    }
 }
@@ -0,0 +1,259 @@
+use crate::{events::EventIndex, Address, TransactionHash};
+use bigdecimal::BigDecimal;
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    PgConnection,
+};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, sqlx::FromRow)]
+pub struct UnrewardedSettlement {
+    pub block_number: i64,
+    pub log_index: i64,
+    pub solver: Address,
+    pub tx_hash: TransactionHash,
+}
+
+/// Returns the oldest settlements that have not been rewarded yet and happened before the
+/// specified time. Settlements whose block timestamp is not known yet are skipped.
+pub async fn unrewarded_settlements(
+    ex: &mut PgConnection,
+    before: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<UnrewardedSettlement>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT s.block_number, s.log_index, s.solver, s.tx_hash
+FROM settlements s
+WHERE s.block_timestamp < $1
+AND NOT EXISTS (
+    SELECT 1 FROM settlement_rewards r
+    WHERE r.block_number = s.block_number AND r.log_index = s.log_index
+)
+ORDER BY s.block_number, s.log_index
+LIMIT $2
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(before)
+        .bind(limit)
+        .fetch_all(ex)
+        .await
+}
+
+/// Returns how many orders that are not liquidity orders the settlement with the specified event
+/// index executed.
+pub async fn settled_user_orders(
+    ex: &mut PgConnection,
+    settlement: &EventIndex,
+) -> Result<i64, sqlx::Error> {
+    // Trade events are emitted before the settlement event of the same transaction.
+    const QUERY: &str = r#"
+SELECT COUNT(*)
+FROM trades t
+JOIN orders o ON o.uid = t.order_uid
+WHERE t.block_number = $1
+AND t.log_index < $2
+AND t.log_index > COALESCE((
+    SELECT MAX(s.log_index) FROM settlements s
+    WHERE s.block_number = $1 AND s.log_index < $2
+), -1)
+AND o.class NOT IN ('limit', 'liquidity')
+    "#;
+    sqlx::query_scalar(QUERY)
+        .bind(settlement.block_number)
+        .bind(settlement.log_index)
+        .fetch_one(ex)
+        .await
+}
+
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct SettlementReward {
+    pub block_number: i64,
+    pub log_index: i64,
+    pub solver: Address,
+    pub tx_hash: TransactionHash,
+    pub user_orders: i64,
+    pub quality_score: Option<BigDecimal>,
+    pub reward: BigDecimal,
+}
+
+pub async fn insert(ex: &mut PgConnection, reward: &SettlementReward) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO settlement_rewards (block_number, log_index, solver, tx_hash, user_orders, quality_score, reward)
+VALUES ($1, $2, $3, $4, $5, $6, $7)
+    "#;
+    sqlx::query(QUERY)
+        .bind(reward.block_number)
+        .bind(reward.log_index)
+        .bind(reward.solver)
+        .bind(reward.tx_hash)
+        .bind(reward.user_orders)
+        .bind(&reward.quality_score)
+        .bind(&reward.reward)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// The rewards of a solver summed up over a period.
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct SolverRewards {
+    pub solver: Address,
+    pub settlements: i64,
+    pub user_orders: i64,
+    pub reward: BigDecimal,
+}
+
+/// Returns the rewards of the settlements that happened in `[start, end)` per solver.
+pub async fn load_by_period(
+    ex: &mut PgConnection,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<SolverRewards>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT r.solver, COUNT(*) AS settlements, SUM(r.user_orders)::bigint AS user_orders, SUM(r.reward) AS reward
+FROM settlement_rewards r
+JOIN settlements s ON s.block_number = r.block_number AND s.log_index = r.log_index
+WHERE s.block_timestamp >= $1 AND s.block_timestamp < $2
+GROUP BY r.solver
+ORDER BY r.solver
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(start)
+        .bind(end)
+        .fetch_all(ex)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        byte_array::ByteArray,
+        events::{Event, Settlement, Trade},
+        orders::{insert_order, Order, OrderClass},
+    };
+    use sqlx::Connection;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_solver_rewards() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        for (uid, class) in [
+            (1, OrderClass::Market),
+            (2, OrderClass::Liquidity),
+            (3, OrderClass::Twap),
+        ] {
+            let order = Order {
+                uid: ByteArray([uid; 56]),
+                class,
+                ..Default::default()
+            };
+            insert_order(&mut db, &order).await.unwrap();
+        }
+        let index = |block_number, log_index| EventIndex {
+            block_number,
+            log_index,
+        };
+        let trade = |uid| {
+            Event::Trade(Trade {
+                order_uid: ByteArray([uid; 56]),
+                ..Default::default()
+            })
+        };
+        let settlement = |solver| {
+            Event::Settlement(Settlement {
+                solver: ByteArray([solver; 20]),
+                ..Default::default()
+            })
+        };
+        crate::events::append(
+            &mut db,
+            &[
+                (index(1, 0), trade(1)),
+                (index(1, 1), trade(2)),
+                (index(1, 2), settlement(1)),
+                (index(1, 3), trade(3)),
+                (index(1, 4), settlement(2)),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let now = Utc::now();
+        // Without block timestamps no settlement can be rewarded.
+        assert!(unrewarded_settlements(&mut db, now, 10)
+            .await
+            .unwrap()
+            .is_empty());
+        crate::order_book_stats::set_block_timestamp(&mut db, 1, now - chrono::Duration::hours(1))
+            .await
+            .unwrap();
+        let settlements = unrewarded_settlements(&mut db, now, 10).await.unwrap();
+        assert_eq!(settlements.len(), 2);
+        assert_eq!(settlements[0].solver, ByteArray([1; 20]));
+
+        assert_eq!(settled_user_orders(&mut db, &index(1, 2)).await.unwrap(), 1);
+        assert_eq!(settled_user_orders(&mut db, &index(1, 4)).await.unwrap(), 1);
+
+        for (settlement, reward) in settlements.iter().zip([1, 2]) {
+            insert(
+                &mut db,
+                &SettlementReward {
+                    block_number: settlement.block_number,
+                    log_index: settlement.log_index,
+                    solver: settlement.solver,
+                    tx_hash: settlement.tx_hash,
+                    user_orders: 1,
+                    quality_score: None,
+                    reward: reward.into(),
+                },
+            )
+            .await
+            .unwrap();
+        }
+        assert!(unrewarded_settlements(&mut db, now, 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let rewards = load_by_period(&mut db, now - chrono::Duration::days(1), now)
+            .await
+            .unwrap();
+        assert_eq!(
+            rewards,
+            [
+                SolverRewards {
+                    solver: ByteArray([1; 20]),
+                    settlements: 1,
+                    user_orders: 1,
+                    reward: 1.into(),
+                },
+                SolverRewards {
+                    solver: ByteArray([2; 20]),
+                    settlements: 1,
+                    user_orders: 1,
+                    reward: 2.into(),
+                },
+            ]
+        );
+        assert!(load_by_period(
+            &mut db,
+            now - chrono::Duration::days(2),
+            now - chrono::Duration::days(1)
+        )
+        .await
+        .unwrap()
+        .is_empty());
+
+        // Rewards of reorged settlements get deleted with them so that they get rewarded again.
+        crate::events::delete(&mut db, 1).await.unwrap();
+        assert!(
+            load_by_period(&mut db, now - chrono::Duration::days(1), now)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use crate::Address;
+use sqlx::PgConnection;
+
+/// One row in the `solver_teams` table.
+#[derive(Clone, Debug, Default, Eq, PartialEq, sqlx::FromRow)]
+pub struct SolverTeam {
+    pub owner: Address,
+    pub name: String,
+    pub solver: Address,
+    pub callback_url: Option<String>,
+}
+
+pub async fn insert(ex: &mut PgConnection, team: &SolverTeam) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+INSERT INTO solver_teams (owner, name, solver, callback_url)
+VALUES ($1, $2, $3, $4)
+    ;"#;
+    sqlx::query(QUERY)
+        .bind(team.owner)
+        .bind(&team.name)
+        .bind(team.solver)
+        .bind(&team.callback_url)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+pub async fn fetch_by_owner(
+    ex: &mut PgConnection,
+    owner: &Address,
+) -> Result<Option<SolverTeam>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT * FROM solver_teams
+WHERE owner = $1
+    ;"#;
+    sqlx::query_as(QUERY).bind(owner).fetch_optional(ex).await
+}
+
+/// Returns the callback urls the teams configured by the account their solver submits settlements
+/// with.
+pub async fn callback_urls(ex: &mut PgConnection) -> Result<Vec<(Address, String)>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT solver, callback_url FROM solver_teams
+WHERE callback_url IS NOT NULL
+    ;"#;
+    sqlx::query_as(QUERY).fetch_all(ex).await
+}
+
+/// Replaces the configuration of the team of `owner`.
+///
+/// Returns whether the team exists.
+pub async fn update_config(
+    ex: &mut PgConnection,
+    owner: &Address,
+    callback_url: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    const QUERY: &str = r#"
+UPDATE solver_teams
+SET callback_url = $2
+WHERE owner = $1
+    ;"#;
+    let result = sqlx::query(QUERY)
+        .bind(owner)
+        .bind(callback_url)
+        .execute(ex)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_array::ByteArray;
+    use sqlx::Connection;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_solver_team_roundtrip() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let team = SolverTeam {
+            owner: ByteArray([1; 20]),
+            name: "solver".to_string(),
+            solver: ByteArray([2; 20]),
+            callback_url: None,
+        };
+        insert(&mut db, &team).await.unwrap();
+        assert_eq!(
+            fetch_by_owner(&mut db, &team.owner).await.unwrap(),
+            Some(team.clone())
+        );
+        assert_eq!(
+            fetch_by_owner(&mut db, &ByteArray([2; 20])).await.unwrap(),
+            None
+        );
+
+        assert!(callback_urls(&mut db).await.unwrap().is_empty());
+
+        assert!(
+            update_config(&mut db, &team.owner, Some("http://solver.test"))
+                .await
+                .unwrap()
+        );
+        assert!(!update_config(&mut db, &ByteArray([2; 20]), None)
+            .await
+            .unwrap());
+        assert_eq!(
+            fetch_by_owner(&mut db, &team.owner).await.unwrap(),
+            Some(SolverTeam {
+                callback_url: Some("http://solver.test".to_string()),
+                ..team
+            })
+        );
+        assert_eq!(
+            callback_urls(&mut db).await.unwrap(),
+            vec![(team.solver, "http://solver.test".to_string())]
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{events::EventIndex, Address, PgTransaction, TransactionHash};
+use sqlx::{
+    postgres::{PgHasArrayType, PgTypeInfo},
+    PgConnection,
+};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, sqlx::Type)]
+#[sqlx(type_name = "VenueKind")]
+#[sqlx(rename_all = "lowercase")]
+pub enum VenueKind {
+    UniswapV2,
+    UniswapV3,
+    BalancerV2,
+    ZeroEx,
+    OneInch,
+    Paraswap,
+}
+
+impl PgHasArrayType for VenueKind {
+    fn array_type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("_VenueKind")
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Venue {
+    pub kind: VenueKind,
+    pub address: Address,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, sqlx::FromRow)]
+pub struct UnindexedSettlement {
+    pub block_number: i64,
+    pub log_index: i64,
+    pub tx_hash: TransactionHash,
+}
+
+/// Returns the oldest settlements whose trade routes have not been indexed yet.
+pub async fn unindexed_settlements(
+    ex: &mut PgConnection,
+    limit: i64,
+) -> Result<Vec<UnindexedSettlement>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT block_number, log_index, tx_hash
+FROM settlements
+WHERE NOT routes_indexed
+ORDER BY block_number, log_index
+LIMIT $1
+    "#;
+    sqlx::query_as(QUERY).bind(limit).fetch_all(ex).await
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, sqlx::FromRow)]
+pub struct SettlementTrade {
+    pub log_index: i64,
+    pub sell_token: Address,
+    pub buy_token: Address,
+}
+
+/// Returns the trades that were executed by the settlement with the specified event index.
+///
+/// Trade events are emitted before the settlement event of the same transaction so these are the
+/// trades in the same block between the previous settlement and this one.
+pub async fn settlement_trades(
+    ex: &mut PgConnection,
+    settlement: &EventIndex,
+) -> Result<Vec<SettlementTrade>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT t.log_index, o.sell_token, o.buy_token
+FROM trades t
+JOIN orders o ON o.uid = t.order_uid
+WHERE t.block_number = $1
+AND t.log_index < $2
+AND t.log_index > COALESCE((
+    SELECT MAX(s.log_index) FROM settlements s
+    WHERE s.block_number = $1 AND s.log_index < $2
+), -1)
+ORDER BY t.log_index
+    "#;
+    sqlx::query_as(QUERY)
+        .bind(settlement.block_number)
+        .bind(settlement.log_index)
+        .fetch_all(ex)
+        .await
+}
+
+/// Stores the venues of the trades of a settlement and marks the settlement as indexed.
+pub async fn insert(
+    ex: &mut PgTransaction<'_>,
+    settlement: &EventIndex,
+    trades: &[(EventIndex, Vec<Venue>)],
+) -> Result<(), sqlx::Error> {
+    const INSERT_VENUE: &str = r#"
+INSERT INTO trade_venues (block_number, log_index, venue_index, kind, address)
+VALUES ($1, $2, $3, $4, $5)
+ON CONFLICT DO NOTHING
+    "#;
+    for (trade, venues) in trades {
+        for (venue_index, venue) in venues.iter().enumerate() {
+            sqlx::query(INSERT_VENUE)
+                .bind(trade.block_number)
+                .bind(trade.log_index)
+                .bind(venue_index as i64)
+                .bind(venue.kind)
+                .bind(venue.address)
+                .execute(&mut *ex)
+                .await?;
+        }
+    }
+
+    const MARK_INDEXED: &str = r#"
+UPDATE settlements
+SET routes_indexed = true
+WHERE block_number = $1 AND log_index = $2
+    "#;
+    sqlx::query(MARK_INDEXED)
+        .bind(settlement.block_number)
+        .bind(settlement.log_index)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        byte_array::ByteArray,
+        events::{Event, Settlement, Trade},
+        orders::Order,
+    };
+    use futures::TryStreamExt;
+    use sqlx::Connection;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_trade_routes_roundtrip() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let order = Order {
+            uid: ByteArray([1; 56]),
+            sell_token: ByteArray([2; 20]),
+            buy_token: ByteArray([3; 20]),
+            ..Default::default()
+        };
+        crate::orders::insert_order(&mut db, &order).await.unwrap();
+        let index = |log_index| EventIndex {
+            block_number: 1,
+            log_index,
+        };
+        crate::events::append(
+            &mut db,
+            &[
+                (
+                    index(0),
+                    Event::Trade(Trade {
+                        order_uid: order.uid,
+                        ..Default::default()
+                    }),
+                ),
+                (index(1), Event::Settlement(Settlement::default())),
+                (
+                    index(2),
+                    Event::Trade(Trade {
+                        order_uid: order.uid,
+                        ..Default::default()
+                    }),
+                ),
+                (index(3), Event::Settlement(Settlement::default())),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let unindexed = unindexed_settlements(&mut db, 10).await.unwrap();
+        assert_eq!(unindexed.len(), 2);
+        assert_eq!(unindexed[0].log_index, 1);
+
+        let trades = settlement_trades(&mut db, &index(3)).await.unwrap();
+        assert_eq!(
+            trades,
+            [SettlementTrade {
+                log_index: 2,
+                sell_token: order.sell_token,
+                buy_token: order.buy_token,
+            }]
+        );
+
+        let venues = vec![
+            Venue {
+                kind: VenueKind::UniswapV2,
+                address: ByteArray([4; 20]),
+            },
+            Venue {
+                kind: VenueKind::BalancerV2,
+                address: ByteArray([5; 20]),
+            },
+        ];
+        insert(&mut db, &index(3), &[(index(2), venues)])
+            .await
+            .unwrap();
+        let trades: Vec<_> = crate::trades::trades(&mut db, &Default::default())
+            .try_collect()
+            .await
+            .unwrap();
+        let route = |log_index| {
+            let trade = trades
+                .iter()
+                .find(|trade| trade.log_index == log_index)
+                .unwrap();
+            (trade.venue_kinds.clone(), trade.venue_addresses.clone())
+        };
+        assert_eq!(route(0), (vec![], vec![]));
+        assert_eq!(
+            route(2),
+            (
+                vec![VenueKind::UniswapV2, VenueKind::BalancerV2],
+                vec![ByteArray([4; 20]), ByteArray([5; 20])]
+            )
+        );
+
+        let unindexed = unindexed_settlements(&mut db, 10).await.unwrap();
+        assert_eq!(unindexed.len(), 1);
+        assert_eq!(unindexed[0].log_index, 1);
+    }
+}
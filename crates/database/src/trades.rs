@@ -1,7 +1,27 @@
-use crate::{Address, OrderUid, TransactionHash};
+use crate::{trade_routes::VenueKind, Address, OrderUid, TransactionHash};
 use bigdecimal::BigDecimal;
 use futures::stream::BoxStream;
-use sqlx::PgConnection;
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    PgConnection,
+};
+
+/// Filters trades. Unset fields don't filter.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TradesFilter<'a> {
+    pub owner: Option<&'a Address>,
+    pub order_uid: Option<&'a OrderUid>,
+    pub sell_token: Option<&'a Address>,
+    pub buy_token: Option<&'a Address>,
+    /// Inclusive lower bound of the settlement's block timestamp. Trades whose settlement has no
+    /// known timestamp yet are excluded if either bound is set.
+    pub from: Option<DateTime<Utc>>,
+    /// Exclusive upper bound of the settlement's block timestamp.
+    pub to: Option<DateTime<Utc>>,
+    /// Only returns rows whose event comes after this `(block_number, log_index)`.
+    pub after: Option<(i64, i64)>,
+    pub limit: Option<i64>,
+}
 
 #[derive(Clone, Debug, Default, Eq, PartialEq, sqlx::FromRow)]
 pub struct TradesQueryRow {
@@ -15,12 +35,14 @@ pub struct TradesQueryRow {
     pub buy_token: Address,
     pub sell_token: Address,
     pub tx_hash: Option<TransactionHash>,
+    pub venue_kinds: Vec<VenueKind>,
+    pub venue_addresses: Vec<Address>,
 }
 
+/// Streams the trades matching the filter ordered by their event index.
 pub fn trades<'a>(
     ex: &'a mut PgConnection,
-    owner_filter: Option<&'a Address>,
-    order_uid_filter: Option<&'a OrderUid>,
+    filter: &TradesFilter<'a>,
 ) -> BoxStream<'a, Result<TradesQueryRow, sqlx::Error>> {
     const QUERY: &str = r#"
 SELECT
@@ -33,15 +55,25 @@ SELECT
     o.owner,
     o.buy_token,
     o.sell_token,
-    settlement.tx_hash
+    settlement.tx_hash,
+    COALESCE(route.venue_kinds, '{}') AS venue_kinds,
+    COALESCE(route.venue_addresses, '{}') AS venue_addresses
 FROM trades t
 LEFT OUTER JOIN LATERAL (
-    SELECT tx_hash FROM settlements s
+    SELECT tx_hash, block_timestamp FROM settlements s
     WHERE s.block_number = t.block_number
     AND   s.log_index > t.log_index
     ORDER BY s.log_index ASC
     LIMIT 1
 ) AS settlement ON true
+LEFT OUTER JOIN LATERAL (
+    SELECT
+        array_agg(v.kind ORDER BY v.venue_index) AS venue_kinds,
+        array_agg(v.address ORDER BY v.venue_index) AS venue_addresses
+    FROM trade_venues v
+    WHERE v.block_number = t.block_number
+    AND   v.log_index = t.log_index
+) AS route ON true
 JOIN orders o
 ON o.uid = t.order_uid
 WHERE
@@ -50,21 +82,231 @@ AND
     ($1 IS NULL OR o.owner = $1)
 AND
     ($2 IS NULL OR o.uid = $2)
+AND
+    ($3 IS NULL OR o.sell_token = $3)
+AND
+    ($4 IS NULL OR o.buy_token = $4)
+AND
+    ($5 IS NULL OR settlement.block_timestamp >= $5)
+AND
+    ($6 IS NULL OR settlement.block_timestamp < $6)
+AND
+    ($7 IS NULL OR (t.block_number, t.log_index) > ($7, $8))
+ORDER BY t.block_number, t.log_index
+LIMIT $9
     "#;
 
     sqlx::query_as(QUERY)
-        .bind(owner_filter)
-        .bind(order_uid_filter)
+        .bind(filter.owner)
+        .bind(filter.order_uid)
+        .bind(filter.sell_token)
+        .bind(filter.buy_token)
+        .bind(filter.from)
+        .bind(filter.to)
+        .bind(filter.after.map(|(block_number, _)| block_number))
+        .bind(filter.after.map(|(_, log_index)| log_index))
+        .bind(filter.limit)
         .fetch(ex)
 }
 
+/// A settlement with aggregates over its trades that match the filter.
+#[derive(Clone, Debug, Default, Eq, PartialEq, sqlx::FromRow)]
+pub struct SettlementTradesQueryRow {
+    pub block_number: i64,
+    pub log_index: i64,
+    pub tx_hash: TransactionHash,
+    pub solver: Address,
+    pub block_timestamp: Option<DateTime<Utc>>,
+    pub trade_count: i64,
+    pub owner_count: i64,
+    pub order_uids: Vec<OrderUid>,
+}
+
+/// Groups the trades matching the filter by their settlement ordered by the settlement's event
+/// index. Trades whose settlement hasn't been indexed yet are excluded. The cursor and limit
+/// apply to settlements.
+pub async fn settlement_trades(
+    ex: &mut PgConnection,
+    filter: &TradesFilter<'_>,
+) -> Result<Vec<SettlementTradesQueryRow>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT
+    s.block_number,
+    s.log_index,
+    s.tx_hash,
+    s.solver,
+    s.block_timestamp,
+    COUNT(*) AS trade_count,
+    COUNT(DISTINCT o.owner) AS owner_count,
+    array_agg(o.uid ORDER BY t.log_index) AS order_uids
+FROM trades t
+JOIN LATERAL (
+    SELECT * FROM settlements s
+    WHERE s.block_number = t.block_number
+    AND   s.log_index > t.log_index
+    ORDER BY s.log_index ASC
+    LIMIT 1
+) AS s ON true
+JOIN orders o
+ON o.uid = t.order_uid
+WHERE
+    ($1 IS NULL OR o.owner = $1)
+AND
+    ($2 IS NULL OR o.uid = $2)
+AND
+    ($3 IS NULL OR o.sell_token = $3)
+AND
+    ($4 IS NULL OR o.buy_token = $4)
+AND
+    ($5 IS NULL OR s.block_timestamp >= $5)
+AND
+    ($6 IS NULL OR s.block_timestamp < $6)
+AND
+    ($7 IS NULL OR (s.block_number, s.log_index) > ($7, $8))
+GROUP BY s.block_number, s.log_index, s.tx_hash, s.solver, s.block_timestamp
+ORDER BY s.block_number, s.log_index
+LIMIT $9
+    "#;
+
+    sqlx::query_as(QUERY)
+        .bind(filter.owner)
+        .bind(filter.order_uid)
+        .bind(filter.sell_token)
+        .bind(filter.buy_token)
+        .bind(filter.from)
+        .bind(filter.to)
+        .bind(filter.after.map(|(block_number, _)| block_number))
+        .bind(filter.after.map(|(_, log_index)| log_index))
+        .bind(filter.limit)
+        .fetch_all(ex)
+        .await
+}
+
+/// What one trade of a token pair cost in basis points.
+#[derive(Clone, Debug, Default, PartialEq, sqlx::FromRow)]
+pub struct PairTradeCost {
+    /// The fee relative to the sell amount including the fee.
+    pub fee_bps: f64,
+    /// How much worse the executed price was than the price the order was quoted with. Negative
+    /// if the trade got a better price. `None` if the order has no quote.
+    pub slippage_bps: Option<f64>,
+}
+
+/// Returns the costs of the most recent trades selling `sell_token` for `buy_token` whose
+/// settlement happened at or after `since` and whose sell amount including the fee lies in
+/// `[min_sell_amount, max_sell_amount]`, newest first.
+pub async fn pair_trade_costs(
+    ex: &mut PgConnection,
+    sell_token: &Address,
+    buy_token: &Address,
+    since: DateTime<Utc>,
+    min_sell_amount: &BigDecimal,
+    max_sell_amount: &BigDecimal,
+    limit: i64,
+) -> Result<Vec<PairTradeCost>, sqlx::Error> {
+    const QUERY: &str = r#"
+SELECT
+    t.fee_amount::double precision / t.sell_amount::double precision * 10000 AS fee_bps,
+    CASE WHEN oq.sell_amount > 0 AND oq.buy_amount > 0 AND t.sell_amount > t.fee_amount THEN
+        (1 - (t.buy_amount::double precision / (t.sell_amount - t.fee_amount)::double precision)
+            / (oq.buy_amount::double precision / oq.sell_amount::double precision)) * 10000
+    END AS slippage_bps
+FROM trades t
+JOIN LATERAL (
+    SELECT block_timestamp FROM settlements s
+    WHERE s.block_number = t.block_number
+    AND   s.log_index > t.log_index
+    ORDER BY s.log_index ASC
+    LIMIT 1
+) AS s ON true
+JOIN orders o
+ON o.uid = t.order_uid
+LEFT OUTER JOIN order_quotes oq
+ON oq.order_uid = t.order_uid
+WHERE
+    o.sell_token = $1
+AND
+    o.buy_token = $2
+AND
+    s.block_timestamp >= $3
+AND
+    t.sell_amount > 0
+AND
+    t.sell_amount BETWEEN $4 AND $5
+ORDER BY t.block_number DESC, t.log_index DESC
+LIMIT $6
+    "#;
+
+    sqlx::query_as(QUERY)
+        .bind(sell_token)
+        .bind(buy_token)
+        .bind(since)
+        .bind(min_sell_amount)
+        .bind(max_sell_amount)
+        .bind(limit)
+        .fetch_all(ex)
+        .await
+}
+
+/// Returns the average number of user orders in the settlements that happened at or after `since`
+/// and traded the token pair in either direction. `None` if there were no such settlements.
+pub async fn average_pair_batch_size(
+    ex: &mut PgConnection,
+    token_a: &Address,
+    token_b: &Address,
+    since: DateTime<Utc>,
+) -> Result<Option<f64>, sqlx::Error> {
+    const QUERY: &str = r#"
+WITH pair_settlements AS (
+    SELECT DISTINCT s.block_number, s.log_index
+    FROM trades t
+    JOIN LATERAL (
+        SELECT block_number, log_index, block_timestamp FROM settlements s
+        WHERE s.block_number = t.block_number
+        AND   s.log_index > t.log_index
+        ORDER BY s.log_index ASC
+        LIMIT 1
+    ) AS s ON true
+    JOIN orders o
+    ON o.uid = t.order_uid
+    WHERE
+        ((o.sell_token = $1 AND o.buy_token = $2) OR (o.sell_token = $2 AND o.buy_token = $1))
+    AND
+        o.class NOT IN ('limit', 'liquidity')
+    AND
+        s.block_timestamp >= $3
+)
+SELECT AVG((
+    SELECT COUNT(*)
+    FROM trades t
+    JOIN orders o
+    ON o.uid = t.order_uid
+    WHERE t.block_number = ps.block_number
+    AND   t.log_index < ps.log_index
+    AND   t.log_index > COALESCE((
+        SELECT MAX(s.log_index) FROM settlements s
+        WHERE s.block_number = ps.block_number AND s.log_index < ps.log_index
+    ), -1)
+    AND   o.class NOT IN ('limit', 'liquidity')
+))::double precision
+FROM pair_settlements ps
+    "#;
+
+    sqlx::query_scalar(QUERY)
+        .bind(token_a)
+        .bind(token_b)
+        .bind(since)
+        .fetch_one(ex)
+        .await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
         byte_array::ByteArray,
         events::{Event, EventIndex, Settlement, Trade},
-        orders::Order,
+        orders::{Order, OrderClass},
         PgTransaction,
     };
     use futures::TryStreamExt;
@@ -130,10 +372,12 @@ mod tests {
         order_uid_filter: Option<&OrderUid>,
         expected: &[TradesQueryRow],
     ) {
-        let filtered = trades(db, owner_filter, order_uid_filter)
-            .try_collect::<Vec<_>>()
-            .await
-            .unwrap();
+        let filter = TradesFilter {
+            owner: owner_filter,
+            order_uid: order_uid_filter,
+            ..Default::default()
+        };
+        let filtered = trades(db, &filter).try_collect::<Vec<_>>().await.unwrap();
         assert_eq!(filtered, expected);
     }
 
@@ -417,4 +661,297 @@ mod tests {
         .await;
         assert_trades(&mut db, None, None, &[trade_a, trade_b]).await;
     }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_trades_paginated_and_grouped_by_settlement() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let (owners, order_ids) = generate_owners_and_order_ids(2, 3).await;
+        let index = |block_number, log_index| EventIndex {
+            block_number,
+            log_index,
+        };
+        let settlement_a =
+            add_settlement(&mut db, index(0, 2), owners[0], ByteArray([1; 32])).await;
+        let settlement_b =
+            add_settlement(&mut db, index(1, 1), owners[0], ByteArray([2; 32])).await;
+        let trade_0 = add_order_and_trade(
+            &mut db,
+            owners[0],
+            order_ids[0],
+            index(0, 0),
+            Some(settlement_a.transaction_hash),
+        )
+        .await;
+        let trade_1 = add_order_and_trade(
+            &mut db,
+            owners[1],
+            order_ids[1],
+            index(0, 1),
+            Some(settlement_a.transaction_hash),
+        )
+        .await;
+        let trade_2 = add_order_and_trade(
+            &mut db,
+            owners[1],
+            order_ids[2],
+            index(1, 0),
+            Some(settlement_b.transaction_hash),
+        )
+        .await;
+
+        let page = |after, limit| TradesFilter {
+            after,
+            limit: Some(limit),
+            ..Default::default()
+        };
+        let first = trades(&mut db, &page(None, 2))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(first, [trade_0, trade_1]);
+        let second = trades(&mut db, &page(Some((0, 1)), 2))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(second, [trade_2]);
+
+        let grouped = settlement_trades(&mut db, &TradesFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            grouped,
+            [
+                SettlementTradesQueryRow {
+                    block_number: 0,
+                    log_index: 2,
+                    tx_hash: settlement_a.transaction_hash,
+                    solver: owners[0],
+                    block_timestamp: None,
+                    trade_count: 2,
+                    owner_count: 2,
+                    order_uids: vec![order_ids[0], order_ids[1]],
+                },
+                SettlementTradesQueryRow {
+                    block_number: 1,
+                    log_index: 1,
+                    tx_hash: settlement_b.transaction_hash,
+                    solver: owners[0],
+                    block_timestamp: None,
+                    trade_count: 1,
+                    owner_count: 1,
+                    order_uids: vec![order_ids[2]],
+                },
+            ]
+        );
+
+        let owner_filter = TradesFilter {
+            owner: Some(&owners[1]),
+            after: Some((0, 2)),
+            ..Default::default()
+        };
+        let grouped = settlement_trades(&mut db, &owner_filter).await.unwrap();
+        assert_eq!(grouped.len(), 1);
+        assert_eq!(grouped[0].order_uids, [order_ids[2]]);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_pair_trade_costs() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let (sell_token, buy_token) = (ByteArray([1; 20]), ByteArray([2; 20]));
+        let now = Utc::now();
+        let add_order = |uid: u8, sell_amount: u32, buy_amount: u32, fee_amount: u32| {
+            (
+                Order {
+                    uid: ByteArray([uid; 56]),
+                    sell_token,
+                    buy_token,
+                    ..Default::default()
+                },
+                Trade {
+                    order_uid: ByteArray([uid; 56]),
+                    sell_amount_including_fee: sell_amount.into(),
+                    buy_amount: buy_amount.into(),
+                    fee_amount: fee_amount.into(),
+                },
+            )
+        };
+        // A quoted trade that got 1% less than quoted, an unquoted one and one that is too large.
+        let trades = [
+            add_order(0, 1010, 990, 10),
+            add_order(1, 1000, 1000, 50),
+            add_order(2, 100_000, 100_000, 0),
+        ];
+        for (i, (order, trade)) in trades.into_iter().enumerate() {
+            crate::orders::insert_order(&mut db, &order).await.unwrap();
+            crate::events::append(
+                &mut db,
+                &[
+                    (
+                        EventIndex {
+                            block_number: i as i64,
+                            log_index: 0,
+                        },
+                        Event::Trade(trade),
+                    ),
+                    (
+                        EventIndex {
+                            block_number: i as i64,
+                            log_index: 1,
+                        },
+                        Event::Settlement(Default::default()),
+                    ),
+                ],
+            )
+            .await
+            .unwrap();
+            crate::order_book_stats::set_block_timestamp(&mut db, i as i64, now)
+                .await
+                .unwrap();
+        }
+        crate::orders::insert_quote(
+            &mut db,
+            &crate::orders::Quote {
+                order_uid: ByteArray([0; 56]),
+                sell_amount: 1000.into(),
+                buy_amount: 1000.into(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let costs = pair_trade_costs(
+            &mut db,
+            &sell_token,
+            &buy_token,
+            now - chrono::Duration::hours(1),
+            &500.into(),
+            &2000.into(),
+            10,
+        )
+        .await
+        .unwrap();
+        assert_eq!(costs.len(), 2);
+        assert_eq!(costs[0].fee_bps, 500.);
+        assert_eq!(costs[0].slippage_bps, None);
+        assert!((costs[1].fee_bps - 99.0099).abs() < 1e-3);
+        assert!((costs[1].slippage_bps.unwrap() - 100.).abs() < 1e-9);
+
+        // Trades before `since` don't count.
+        let costs = pair_trade_costs(
+            &mut db,
+            &sell_token,
+            &buy_token,
+            now + chrono::Duration::hours(1),
+            &500.into(),
+            &2000.into(),
+            10,
+        )
+        .await
+        .unwrap();
+        assert!(costs.is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_average_pair_batch_size() {
+        let mut db = PgConnection::connect("postgresql://").await.unwrap();
+        let mut db = db.begin().await.unwrap();
+        crate::clear_DANGER_(&mut db).await.unwrap();
+
+        let (token_a, token_b, token_c) =
+            (ByteArray([1; 20]), ByteArray([2; 20]), ByteArray([3; 20]));
+        let now = Utc::now();
+        let since = now - chrono::Duration::hours(1);
+        assert_eq!(
+            average_pair_batch_size(&mut db, &token_a, &token_b, since)
+                .await
+                .unwrap(),
+            None
+        );
+
+        let order = |uid: u8, sell_token, buy_token, class| Order {
+            uid: ByteArray([uid; 56]),
+            sell_token,
+            buy_token,
+            class,
+            ..Default::default()
+        };
+        let orders = [
+            order(0, token_a, token_b, OrderClass::Market),
+            order(1, token_b, token_c, OrderClass::Market),
+            order(2, token_c, token_a, OrderClass::Liquidity),
+            order(3, token_b, token_a, OrderClass::Market),
+            order(4, token_b, token_c, OrderClass::Market),
+        ];
+        for order in &orders {
+            crate::orders::insert_order(&mut db, order).await.unwrap();
+        }
+        let index = |block_number, log_index| EventIndex {
+            block_number,
+            log_index,
+        };
+        let trade = |uid: u8| {
+            Event::Trade(Trade {
+                order_uid: ByteArray([uid; 56]),
+                ..Default::default()
+            })
+        };
+        // The first settlement of block 0 batches two user orders and a liquidity order, the
+        // second one only trades the pair in the opposite direction. The settlement in block 1
+        // doesn't trade the pair.
+        crate::events::append(
+            &mut db,
+            &[
+                (index(0, 0), trade(0)),
+                (index(0, 1), trade(1)),
+                (index(0, 2), trade(2)),
+                (index(0, 3), Event::Settlement(Default::default())),
+                (index(0, 4), trade(3)),
+                (index(0, 5), Event::Settlement(Default::default())),
+                (index(1, 0), trade(4)),
+                (index(1, 1), Event::Settlement(Default::default())),
+            ],
+        )
+        .await
+        .unwrap();
+        for block in [0, 1] {
+            crate::order_book_stats::set_block_timestamp(&mut db, block, now)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(
+            average_pair_batch_size(&mut db, &token_a, &token_b, since)
+                .await
+                .unwrap(),
+            Some(1.5)
+        );
+        assert_eq!(
+            average_pair_batch_size(&mut db, &token_b, &token_c, since)
+                .await
+                .unwrap(),
+            Some(1.5)
+        );
+        // Settlements before `since` don't count.
+        assert_eq!(
+            average_pair_batch_size(
+                &mut db,
+                &token_a,
+                &token_b,
+                now + chrono::Duration::hours(1)
+            )
+            .await
+            .unwrap(),
+            None
+        );
+    }
 }
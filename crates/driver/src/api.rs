@@ -1,27 +1,61 @@
+pub mod auction_graph;
 pub mod execute;
 pub mod solve;
+pub mod stats;
 
 use crate::driver::Driver;
 use futures::Future;
-use shared::api::finalize_router;
+use shared::{
+    api::{finalize_router, ApiReply},
+    metrics::handle_metrics,
+};
 use std::{net::SocketAddr, sync::Arc};
 use tokio::{task, task::JoinHandle};
-use warp::{Filter, Rejection, Reply};
+use warp::{filters::BoxedFilter, Filter, Rejection, Reply};
+
+/// The drivers of all solvers on one network.
+pub struct NetworkDrivers {
+    /// Routes of named networks are prefixed with the name so that one process can serve
+    /// several networks.
+    pub network: Option<String>,
+    pub drivers: Vec<(Arc<Driver>, String)>,
+}
 
 pub fn serve_api(
     address: SocketAddr,
     shutdown_receiver: impl Future<Output = ()> + Send + 'static,
-    drivers: Vec<(Arc<Driver>, String)>,
+    networks: Vec<NetworkDrivers>,
 ) -> JoinHandle<()> {
-    let filter = handle_all_routes(drivers).boxed();
+    let filter = handle_all_routes(networks).boxed();
     tracing::info!(%address, "serving driver");
     let (_, server) = warp::serve(filter).bind_with_graceful_shutdown(address, shutdown_receiver);
     task::spawn(server)
 }
 
 fn handle_all_routes(
-    drivers: Vec<(Arc<Driver>, String)>,
+    networks: Vec<NetworkDrivers>,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let routes = networks
+        .into_iter()
+        .map(|network| {
+            let routes = network_routes(network.drivers);
+            match network.network {
+                Some(name) => {
+                    let name: &'static str = Box::leak(name.into_boxed_str());
+                    warp::path(name).and(routes).boxed()
+                }
+                None => routes,
+            }
+        })
+        .reduce(|routes, route| routes.or(route).unify().boxed())
+        .expect("there should be at least 1 network configured");
+
+    let routes = warp::path!("api" / ..).and(routes).untuple_one().boxed();
+    // Solver teams running their own driver don't necessarily run a separate metrics server.
+    handle_metrics().or(finalize_router(routes, "driver::api::request_summary"))
+}
+
+fn network_routes(drivers: Vec<(Arc<Driver>, String)>) -> BoxedFilter<((ApiReply, &'static str),)> {
     // Routes for api v1.
 
     // Note that we add a string with endpoint's name to all responses.
@@ -38,17 +72,24 @@ fn handle_all_routes(
             .boxed();
         base_routes.push(solve);
 
-        let execute = execute::post_execute(name, driver)
+        let execute = execute::post_execute(name, driver.clone())
             .map(|result| (result, "execute"))
             .boxed();
         base_routes.push(execute);
+
+        let auction_graph = auction_graph::get_auction_graph(name, driver.clone())
+            .map(|result| (result, "auction_graph"))
+            .boxed();
+        base_routes.push(auction_graph);
+
+        let stats = stats::get_stats(name, driver)
+            .map(|result| (result, "stats"))
+            .boxed();
+        base_routes.push(stats);
     }
 
-    let routes = base_routes
+    base_routes
         .into_iter()
         .reduce(|routes, route| routes.or(route).unify().boxed())
-        .expect("there should be at least 1 solver configured");
-
-    let routes = warp::path!("api" / ..).and(routes).untuple_one().boxed();
-    finalize_router(routes, "driver::api::request_summary")
+        .expect("there should be at least 1 solver configured")
 }
@@ -0,0 +1,35 @@
+use crate::driver::Driver;
+use anyhow::Result;
+use shared::api::{error, ApiReply};
+use std::{convert::Infallible, sync::Arc};
+use warp::{hyper::StatusCode, reply::with_status, Filter, Rejection};
+
+fn get_auction_graph_request(
+    prefix: &'static str,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::path(prefix)
+        .and(warp::path("auction_graph"))
+        .and(warp::path::end())
+        .and(warp::get())
+}
+
+/// Serves the graph of orders and liquidity of the auction the driver most recently prepared for
+/// its solver.
+pub fn get_auction_graph(
+    prefix: &'static str,
+    driver: Arc<Driver>,
+) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
+    get_auction_graph_request(prefix).and_then(move || {
+        let driver = driver.clone();
+        async move {
+            let reply = match driver.auction_converter.latest_auction_graph() {
+                Some(graph) => with_status(warp::reply::json(&graph), StatusCode::OK),
+                None => with_status(
+                    error("NoAuction", "no auction has been prepared yet"),
+                    StatusCode::NOT_FOUND,
+                ),
+            };
+            Result::<_, Infallible>::Ok(reply)
+        }
+    })
+}
@@ -1,6 +1,6 @@
 use crate::driver::Driver;
 use anyhow::Result;
-use model::auction::AuctionWithId;
+use model::auction::SolveRequest;
 use shared::api::{
     convert_json_response, error, extract_payload_with_max_size, ApiReply, IntoWarpReply,
 };
@@ -10,7 +10,7 @@ use warp::{hyper::StatusCode, reply::with_status, Filter, Rejection};
 
 fn post_solve_request(
     prefix: &'static str,
-) -> impl Filter<Extract = (AuctionWithId,), Error = Rejection> + Clone {
+) -> impl Filter<Extract = (SolveRequest,), Error = Rejection> + Clone {
     warp::path(prefix)
         .and(warp::path("solve"))
         .and(warp::post())
@@ -21,11 +21,11 @@ pub fn post_solve(
     prefix: &'static str,
     driver: Arc<Driver>,
 ) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
-    post_solve_request(prefix).and_then(move |auction: AuctionWithId| {
+    post_solve_request(prefix).and_then(move |request: SolveRequest| {
         let driver = driver.clone();
-        let auction_id = auction.id;
+        let auction_id = request.auction.id;
         async move {
-            let result = driver.on_auction_started(auction.clone()).await;
+            let result = driver.on_auction_started(request).await;
             if let Err(err) = &result {
                 tracing::warn!(?err, "post_solve error");
             }
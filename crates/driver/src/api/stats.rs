@@ -0,0 +1,25 @@
+use crate::driver::Driver;
+use shared::api::ApiReply;
+use std::{convert::Infallible, sync::Arc};
+use warp::{hyper::StatusCode, reply::with_status, Filter, Rejection};
+
+fn get_stats_request(prefix: &'static str) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::path(prefix)
+        .and(warp::path("stats"))
+        .and(warp::path::end())
+        .and(warp::get())
+}
+
+/// Serves the phase timings and outcomes of the solver's most recent auctions.
+pub fn get_stats(
+    prefix: &'static str,
+    driver: Arc<Driver>,
+) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
+    get_stats_request(prefix).and_then(move || {
+        let driver = driver.clone();
+        async move {
+            let summary = driver.phase_stats.summary();
+            Result::<_, Infallible>::Ok(with_status(warp::reply::json(&summary), StatusCode::OK))
+        }
+    })
+}
@@ -1,3 +1,4 @@
+use anyhow::{anyhow, Context};
 use primitive_types::{H160, H256};
 use reqwest::Url;
 use shared::{
@@ -6,12 +7,16 @@ use shared::{
     sources::{balancer_v2::BalancerFactoryKind, BaselineSource},
 };
 use solver::{
-    arguments::TransactionStrategyArg, settlement_access_list::AccessListEstimatorType,
-    solver::ExternalSolverArg,
+    arguments::TransactionStrategyArg,
+    encoding::SettlementContractVersion,
+    settlement_access_list::AccessListEstimatorType,
+    settlement_simulation::SimulationBackend,
+    solver::{http_solver::flash_loans::FlashLoanLender, ExternalSolverArg},
 };
 use std::{
     net::SocketAddr,
     num::{NonZeroU64, NonZeroU8},
+    str::FromStr,
     time::Duration,
 };
 use tracing::level_filters::LevelFilter;
@@ -35,10 +40,32 @@ pub struct Arguments {
     #[clap(long, env, use_value_delimiter = true)]
     pub solvers: Vec<ExternalSolverArg>,
 
+    /// Runs all solvers concurrently behind a single driver with this name instead of one driver
+    /// per solver. The driver commits only the best of their solutions, ranked by surplus minus
+    /// gas reimbursement, and the solver that computed it executes the settlement.
+    #[clap(long, env)]
+    pub co_located_solvers: Option<String>,
+
+    /// Contracts that tokens can be flash borrowed from in the form of `protocol|address|fee`
+    /// where protocol is `BalancerV2` or `AaveV2` and fee is the fraction of the borrowed amount
+    /// charged, for example `AaveV2|0x7d2768dE32b0b80b7a3454c06BdAc94A69DDc7A9|0.0009`. HTTP
+    /// solvers get told which flash loans are available for the tokens of an auction.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub flash_loan_lenders: Vec<FlashLoanLender>,
+
     /// The Ethereum node URL to connect to.
     #[clap(long, env, default_value = "http://localhost:8545")]
     pub node_url: Url,
 
+    /// Runs an independent driver for every network in the form of `name|node_url` instead of
+    /// only one for the `node_url`, for example
+    /// `mainnet|http://mainnet-node,gnosis|http://gnosis-node`. The routes of every network are
+    /// prefixed with its name, like `/api/mainnet/{solver}/solve`. All other arguments apply to
+    /// every network and transaction submission nodes are used for the network whose node has
+    /// the same network id.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub networks: Vec<NetworkArg>,
+
     /// Timeout in seconds for all http requests.
     #[clap(
         long,
@@ -47,6 +74,51 @@ pub struct Arguments {
     )]
     pub http_timeout: Duration,
 
+    /// The maximum time in seconds solvers get to solve an auction. Deadlines requested by
+    /// autopilot get capped to this and auctions without a deadline get solved this long.
+    #[clap(
+        long,
+        env,
+        default_value = "25",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub max_solve_time: Duration,
+
+    /// The time in seconds reserved before autopilot's deadline for finishing the current solver
+    /// run and sending back the response.
+    #[clap(
+        long,
+        env,
+        default_value = "1",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub solve_response_margin: Duration,
+
+    /// The time in seconds reserved at the end of the solving time for ranking and simulating
+    /// the solutions. Solvers have to return their solutions this long before the deadline.
+    #[clap(
+        long,
+        env,
+        default_value = "3",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub solution_ranking_margin: Duration,
+
+    /// Additionally reserves this quantile of the recent times from revealing a settlement until
+    /// its inclusion before autopilot's deadline. Only the response margin gets reserved if not
+    /// set.
+    #[clap(long, env)]
+    pub submission_latency_quantile: Option<f64>,
+
+    /// The maximum time in seconds reserved for the submission latency.
+    #[clap(
+        long,
+        env,
+        default_value = "10",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub max_submission_latency_padding: Duration,
+
     /// If solvers should use internal buffers to improve solution quality.
     #[clap(long, env)]
     pub use_internal_buffers: bool,
@@ -138,6 +210,17 @@ pub struct Arguments {
     #[clap(long, env)]
     pub tenderly_api_key: Option<String>,
 
+    /// Which backend to use for simulating transactions. `Tenderly` requires the Tenderly URL and
+    /// API key to be set and only simulates historic transactions while settlements get simulated
+    /// on the node. `Revm` simulates both locally with state fetched from the node.
+    #[clap(long, env, arg_enum, ignore_case = true, default_value = "Tenderly")]
+    pub simulation_backend: SimulationBackend,
+
+    /// The revision of the settlement contract deployed on the chain, which determines how
+    /// settlements get encoded into `settle` calls.
+    #[clap(long, env, arg_enum, ignore_case = true, default_value = "V1")]
+    pub settlement_contract_version: SettlementContractVersion,
+
     /// Gas limit for simulations. This parameter is important to set correctly, such that
     /// there are no simulation errors due to: err: insufficient funds for gas * price + value,
     /// but at the same time we don't restrict solutions sizes too much
@@ -265,6 +348,45 @@ pub struct Arguments {
     /// ZeroEx API key.
     #[clap(long, env)]
     pub zeroex_api_key: Option<String>,
+
+    /// Instead of starting, check the node, the contracts and the solver APIs the driver is
+    /// configured with, print a report and exit with a non-zero exit code if any check failed.
+    #[clap(long, env)]
+    pub preflight: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct NetworkArg {
+    pub name: String,
+    pub node_url: Url,
+}
+
+impl FromStr for NetworkArg {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, node_url) = s
+            .split_once('|')
+            .ok_or_else(|| anyhow!("expected network in the form name|node_url"))?;
+        Ok(Self {
+            name: name.to_string(),
+            node_url: node_url.parse().context("parse node url")?,
+        })
+    }
+}
+
+impl Arguments {
+    /// The node of every network to run a driver for. The network name is `None` if the driver
+    /// only runs for the `node_url`.
+    pub fn network_nodes(&self) -> Vec<(Option<String>, Url)> {
+        if self.networks.is_empty() {
+            return vec![(None, self.node_url.clone())];
+        }
+        self.networks
+            .iter()
+            .map(|network| (Some(network.name.clone()), network.node_url.clone()))
+            .collect()
+    }
 }
 
 impl std::fmt::Display for Arguments {
@@ -273,8 +395,28 @@ impl std::fmt::Display for Arguments {
         writeln!(f, "log_filter: {}", self.log_filter)?;
         writeln!(f, "log_stderr_threshold: {}", self.log_stderr_threshold)?;
         writeln!(f, "solvers: {:?}", self.solvers)?;
+        display_option(f, "co_located_solvers", &self.co_located_solvers)?;
+        writeln!(f, "flash_loan_lenders: {:?}", self.flash_loan_lenders)?;
         writeln!(f, "node_url: {}", self.node_url)?;
+        writeln!(f, "networks: {:?}", self.networks)?;
         writeln!(f, "http_timeout: {:?}", self.http_timeout)?;
+        writeln!(f, "max_solve_time: {:?}", self.max_solve_time)?;
+        writeln!(f, "solve_response_margin: {:?}", self.solve_response_margin)?;
+        writeln!(
+            f,
+            "solution_ranking_margin: {:?}",
+            self.solution_ranking_margin
+        )?;
+        display_option(
+            f,
+            "submission_latency_quantile",
+            &self.submission_latency_quantile,
+        )?;
+        writeln!(
+            f,
+            "max_submission_latency_padding: {:?}",
+            self.max_submission_latency_padding
+        )?;
         writeln!(f, "use_internal_buffers: {}", self.use_internal_buffers)?;
         display_list(
             f,
@@ -316,6 +458,12 @@ impl std::fmt::Display for Arguments {
         )?;
         display_option(f, "tenderly_url", &self.tenderly_url)?;
         display_secret_option(f, "tenderly_api_key", &self.tenderly_api_key)?;
+        writeln!(f, "simulation_backend: {:?}", self.simulation_backend)?;
+        writeln!(
+            f,
+            "settlement_contract_version: {:?}",
+            self.settlement_contract_version
+        )?;
         writeln!(f, "simulation_gas_limit: {}", self.simulation_gas_limit)?;
         writeln!(f, "max_gas_price_bumps: {}", self.max_gas_price_bumps)?;
         writeln!(f, "target_confirm_time: {:?}", self.target_confirm_time)?;
@@ -368,6 +516,21 @@ impl std::fmt::Display for Arguments {
         )?;
         display_option(f, "zeroex_url", &self.zeroex_url)?;
         display_secret_option(f, "zeroex_api_key", &self.zeroex_api_key)?;
+        writeln!(f, "preflight: {}", self.preflight)?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_network_arg() {
+        let network = NetworkArg::from_str("gnosis|http://gnosis-node:8545/").unwrap();
+        assert_eq!(network.name, "gnosis");
+        assert_eq!(network.node_url.as_str(), "http://gnosis-node:8545/");
+        assert!(NetworkArg::from_str("gnosis").is_err());
+        assert!(NetworkArg::from_str("gnosis|not a url").is_err());
+    }
+}
@@ -2,27 +2,35 @@ use anyhow::{Context, Result};
 use gas_estimation::GasPriceEstimating;
 use model::auction::AuctionWithId as AuctionModel;
 use primitive_types::H160;
-use shared::recent_block_cache::Block;
+use shared::{http_solver::graph::AuctionGraph, recent_block_cache::Block};
 use solver::{
-    liquidity::order_converter::OrderConverter, liquidity_collector::LiquidityCollecting,
-    settlement::external_prices::ExternalPrices, solver::Auction,
+    liquidity::order_converter::OrderConverter,
+    liquidity_collector::LiquidityCollecting,
+    settlement::external_prices::ExternalPrices,
+    solver::{http_solver, Auction},
 };
 use std::{
     sync::{
         atomic::{AtomicU64, Ordering},
-        Arc,
+        Arc, Mutex,
     },
-    time::{Duration, Instant},
+    time::Instant,
 };
 
-// TODO eventually this has to be part of the auction coming from the autopilot.
-/// Determines how much time a solver has to compute solutions for an incoming `Auction`.
-const RUN_DURATION: Duration = Duration::from_secs(15);
-
 #[async_trait::async_trait]
 #[cfg_attr(test, mockall::automock)]
 pub trait AuctionConverting: Send + Sync {
-    async fn convert_auction(&self, model: AuctionModel, block: u64) -> Result<Auction>;
+    /// Converts the auction with the liquidity of the `block`. Solvers have until the `deadline`
+    /// to compute solutions for it.
+    async fn convert_auction(
+        &self,
+        model: AuctionModel,
+        block: u64,
+        deadline: Instant,
+    ) -> Result<Auction>;
+
+    /// The graph of orders and liquidity of the most recently converted auction.
+    fn latest_auction_graph(&self) -> Option<AuctionGraph>;
 }
 
 pub struct AuctionConverter {
@@ -31,6 +39,7 @@ pub struct AuctionConverter {
     pub native_token: H160,
     pub run: AtomicU64,
     pub liquidity_collector: Box<dyn LiquidityCollecting>,
+    pub latest_auction_graph: Mutex<Option<AuctionGraph>>,
 }
 
 impl AuctionConverter {
@@ -45,13 +54,19 @@ impl AuctionConverter {
             run: AtomicU64::default(),
             liquidity_collector,
             order_converter,
+            latest_auction_graph: Default::default(),
         }
     }
 }
 
 #[async_trait::async_trait]
 impl AuctionConverting for AuctionConverter {
-    async fn convert_auction(&self, auction: AuctionModel, block: u64) -> Result<Auction> {
+    async fn convert_auction(
+        &self,
+        auction: AuctionModel,
+        block: u64,
+        deadline: Instant,
+    ) -> Result<Auction> {
         let auction_id = auction.id;
         let auction = auction.auction;
         let run = self.run.fetch_add(1, Ordering::SeqCst);
@@ -103,16 +118,23 @@ impl AuctionConverting for AuctionConverter {
             .context("failed to estimate gas price")?;
         tracing::debug!("solving with gas price of {:?}", gas_price);
 
-        Ok(Auction {
+        let auction = Auction {
             id: auction_id,
             run,
             orders,
             liquidity,
             liquidity_fetch_block: block,
             gas_price: gas_price.effective_gas_price(),
-            deadline: Instant::now() + RUN_DURATION,
+            deadline,
             external_prices,
-        })
+        };
+        *self.latest_auction_graph.lock().unwrap() =
+            Some(http_solver::auction_graph(&auction, self.native_token));
+        Ok(auction)
+    }
+
+    fn latest_auction_graph(&self) -> Option<AuctionGraph> {
+        self.latest_auction_graph.lock().unwrap().clone()
     }
 }
 
@@ -123,7 +145,7 @@ mod tests {
     use gas_estimation::GasPrice1559;
     use maplit::btreemap;
     use model::{
-        order::{Order, OrderData, OrderMetadata, BUY_ETH_ADDRESS},
+        order::{Order, OrderClass, OrderData, OrderMetadata, BUY_ETH_ADDRESS},
         TokenPair,
     };
     use num::rational::{BigRational, Ratio};
@@ -136,6 +158,7 @@ mod tests {
         liquidity_collector::MockLiquidityCollecting,
         settlement::SettlementEncoder,
     };
+    use std::time::Duration;
 
     struct DummySettlementHandler;
     impl SettlementHandling<ConstantProductOrder> for DummySettlementHandler {
@@ -210,19 +233,18 @@ mod tests {
                 latest_settlement_block: 2,
                 orders: vec![order(1, 2, false), order(2, 3, false), order(1, 3, true)],
                 prices: btreemap! { token(2) => U256::exp10(18), token(3) => U256::exp10(18) },
+                estimated_prices: Default::default(),
+                ..Default::default()
             },
         };
 
-        let auction = converter.convert_auction(model.clone(), 3).await.unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let auction = converter
+            .convert_auction(model.clone(), 3, deadline)
+            .await
+            .unwrap();
         assert_eq!(auction.id, 3);
-        assert_eq!(
-            auction
-                .deadline
-                .duration_since(Instant::now())
-                .as_secs_f64()
-                .ceil(),
-            RUN_DURATION.as_secs_f64()
-        );
+        assert_eq!(auction.deadline, deadline);
         assert_eq!(auction.run, 0);
         // only orders which don't have a logical error
         assert_eq!(auction.orders.len(), 2);
@@ -235,6 +257,9 @@ mod tests {
         assert_eq!(auction.orders[0].scaled_unsubsidized_fee, 180.into());
         assert_eq!(auction.orders[1].scaled_unsubsidized_fee, 180.into());
         assert_eq!(auction.liquidity.len(), 1);
+        let graph = converter.latest_auction_graph().unwrap();
+        assert_eq!(graph.nodes.len(), 3);
+        assert_eq!(graph.edges.len(), 3);
         for t in &[native_token.address(), BUY_ETH_ADDRESS, token(2), token(3)] {
             assert_eq!(
                 auction.external_prices.price(t).unwrap(),
@@ -242,12 +267,15 @@ mod tests {
             );
         }
 
-        let auction = converter.convert_auction(model.clone(), 3).await.unwrap();
+        let auction = converter
+            .convert_auction(model.clone(), 3, deadline)
+            .await
+            .unwrap();
         assert_eq!(auction.run, 1);
 
         // auction has to include at least 1 user order
         model.auction.orders = vec![order(1, 2, false)];
-        model.auction.orders[0].metadata.is_liquidity_order = true;
-        assert!(converter.convert_auction(model, 3).await.is_err());
+        model.auction.orders[0].metadata.class = OrderClass::Liquidity;
+        assert!(converter.convert_auction(model, 3, deadline).await.is_err());
     }
 }
@@ -43,11 +43,25 @@ pub trait CommitRevealSolving: Send + Sync {
     /// `Ok(None)`.
     async fn reveal(&self, summary: &SettlementSummary) -> Result<Option<Settlement>>;
 
+    /// The solver that computed the solution of the summary if this solver delegates to others.
+    /// Its account executes the revealed settlement.
+    fn delegate(&self, _summary: &SettlementSummary) -> Option<Arc<dyn CommitRevealSolving>> {
+        None
+    }
+
     fn account(&self) -> &Account;
 
     fn name(&self) -> &str;
 }
 
+impl SettlementSummary {
+    /// The objective solutions get ranked by: surplus minus the gas reimbursement, both in native
+    /// token.
+    pub fn objective(&self) -> f64 {
+        self.surplus - self.gas_reimbursement.to_f64_lossy()
+    }
+}
+
 // Wraps a legacy `Solver` implementation and makes it compatible with the commit reveal protocol.
 // Because RFQ support can not be solved generically the wrapped `Solver` will not be able to opt into
 // RFQ orders, yet. A solver would have to support RFQ themselves.
@@ -94,11 +108,16 @@ impl CommitRevealSolver {
         let gas_price = self.gas_estimator.estimate().await?;
         let (mut rated_settlements, errors) = self
             .settlement_ranker
-            .rank_legal_settlements(vec![(self.solver.clone(), solutions)], &prices, gas_price)
+            .rank_legal_settlements(
+                auction_id,
+                vec![(self.solver.clone(), solutions)],
+                &prices,
+                gas_price,
+            )
             .await?;
 
         self.logger
-            .report_simulation_errors(errors, liquidity_fetch_block, gas_price);
+            .report_simulation_errors(auction_id, errors, liquidity_fetch_block, gas_price);
 
         let (_, winning_settlement, _) = rated_settlements
             .pop()
@@ -110,7 +129,8 @@ impl CommitRevealSolver {
                 .to_f64()
                 .context("couldn't convert surplus to f64")?,
             gas_reimbursement: big_rational_to_u256(
-                &(winning_settlement.gas_estimate.to_big_rational() * winning_settlement.gas_price),
+                &(winning_settlement.effective_gas().to_big_rational()
+                    * winning_settlement.gas_price),
             )?,
             settled_orders: winning_settlement
                 .settlement
@@ -168,6 +188,84 @@ impl CommitRevealSolving for CommitRevealSolver {
     }
 }
 
+/// Runs several co-located solvers concurrently on every auction and commits only the solution with
+/// the best objective so that one driver can serve all of them.
+pub struct MultiCommitRevealSolver {
+    name: String,
+    solvers: Vec<Arc<dyn CommitRevealSolving>>,
+    /// The committed summary and the index of the solver that computed it.
+    winner: Mutex<Option<(SettlementSummary, usize)>>,
+}
+
+impl MultiCommitRevealSolver {
+    pub fn new(name: String, solvers: Vec<Arc<dyn CommitRevealSolving>>) -> Self {
+        assert!(!solvers.is_empty(), "at least one solver is needed");
+        Self {
+            name,
+            solvers,
+            winner: Default::default(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl CommitRevealSolving for MultiCommitRevealSolver {
+    async fn commit(&self, auction: Auction) -> Result<SettlementSummary> {
+        let results = futures::future::join_all(
+            self.solvers
+                .iter()
+                .map(|solver| solver.commit(auction.clone())),
+        )
+        .await;
+        let best = results
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, result)| match result {
+                Ok(summary) => {
+                    tracing::debug!(solver = self.solvers[i].name(), ?summary, "committed");
+                    Some((summary, i))
+                }
+                Err(err) => {
+                    tracing::debug!(solver = self.solvers[i].name(), ?err, "failed to commit");
+                    None
+                }
+            })
+            .max_by(|(a, _), (b, _)| a.objective().total_cmp(&b.objective()));
+
+        let mut winner = self.winner.lock().unwrap();
+        *winner = best.clone();
+        let (summary, i) = best.context("no solver computed a valid solution")?;
+        tracing::info!(solver = self.solvers[i].name(), "best solution");
+        Ok(summary)
+    }
+
+    async fn reveal(&self, summary: &SettlementSummary) -> Result<Option<Settlement>> {
+        self.delegate(summary)
+            .context("could not find solution for requested summary")?
+            .reveal(summary)
+            .await
+    }
+
+    fn delegate(&self, summary: &SettlementSummary) -> Option<Arc<dyn CommitRevealSolving>> {
+        match &*self.winner.lock().unwrap() {
+            Some((winning_summary, i)) if winning_summary == summary => {
+                Some(self.solvers[*i].clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// The account of the first solver. Settlements get executed by the account of the solver
+    /// that computed them.
+    fn account(&self) -> &Account {
+        self.solvers[0].account()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
 /// This is just a wrapper type to make a `dyn CommitRevealSolving` usable where `dyn Solver` is
 /// expected for logging purposes. This type is only supposed to give information about the
 /// name and account of the underlying solver and will panic if `solve()` gets called.
@@ -201,3 +299,69 @@ impl Solver for CommitRevealSolverAdapter {
         self.solver.name()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+
+    fn solver(name: &'static str, surplus: Option<f64>) -> Arc<dyn CommitRevealSolving> {
+        let mut solver = MockCommitRevealSolving::new();
+        solver.expect_name().return_const(name.to_string());
+        solver.expect_commit().returning(move |_| {
+            async move {
+                surplus
+                    .map(|surplus| SettlementSummary {
+                        surplus,
+                        gas_reimbursement: 10.into(),
+                        ..Default::default()
+                    })
+                    .context("no solution")
+            }
+            .boxed()
+        });
+        solver
+            .expect_reveal()
+            .returning(|_| async { Ok(Some(Settlement::default())) }.boxed());
+        Arc::new(solver)
+    }
+
+    #[tokio::test]
+    async fn commits_best_solution() {
+        let multi = MultiCommitRevealSolver::new(
+            "multi".to_string(),
+            vec![
+                solver("a", Some(100.)),
+                solver("b", None),
+                solver("c", Some(200.)),
+            ],
+        );
+        let summary = multi.commit(Default::default()).await.unwrap();
+        assert_eq!(summary.surplus, 200.);
+        assert_eq!(multi.delegate(&summary).unwrap().name(), "c");
+        assert!(multi.reveal(&summary).await.unwrap().is_some());
+
+        let other = SettlementSummary {
+            surplus: 100.,
+            ..summary
+        };
+        assert!(multi.delegate(&other).is_none());
+        assert!(multi.reveal(&other).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn fails_without_solutions() {
+        let multi = MultiCommitRevealSolver::new("multi".to_string(), vec![solver("a", None)]);
+        assert!(multi.commit(Default::default()).await.is_err());
+    }
+
+    #[test]
+    fn objective_subtracts_gas_reimbursement() {
+        let summary = SettlementSummary {
+            surplus: 100.,
+            gas_reimbursement: 30.into(),
+            ..Default::default()
+        };
+        assert_eq!(summary.objective(), 70.);
+    }
+}
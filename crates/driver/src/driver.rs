@@ -2,12 +2,16 @@ use crate::{
     api::{execute::ExecuteError, solve::SolveError},
     auction_converter::AuctionConverting,
     commit_reveal::{CommitRevealSolverAdapter, CommitRevealSolving, SettlementSummary},
+    phase_stats::{PhaseStats, SubmissionOutcome},
+    submission_latency::SubmissionLatency,
 };
 use anyhow::{Context, Error, Result};
+use chrono::{DateTime, Utc};
 use futures::StreamExt;
 use gas_estimation::GasPriceEstimating;
-use model::auction::AuctionWithId;
+use model::auction::{AuctionWithId, SolveRequest};
 use primitive_types::H256;
+use serde::{Deserialize, Serialize};
 use shared::current_block::{block_number, into_stream, Block, CurrentBlockStream};
 use solver::{
     driver::submit_settlement,
@@ -29,6 +33,21 @@ pub struct Driver {
     pub settlement_rater: Arc<dyn SettlementRating>,
     pub logger: Arc<DriverLogger>,
     pub gas_price_estimator: Arc<dyn GasPriceEstimating>,
+    pub max_solve_time: Duration,
+    pub solve_response_margin: Duration,
+    pub solution_ranking_margin: Duration,
+    pub submission_latency: Arc<SubmissionLatency>,
+    pub phase_stats: Arc<PhaseStats>,
+}
+
+/// The response to a solve request.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct SolveResponse {
+    #[serde(flatten)]
+    pub summary: SettlementSummary,
+    /// The deadline the driver was able to solve until. Autopilot uses it to schedule the reveal
+    /// phase.
+    pub deadline: DateTime<Utc>,
 }
 
 impl Driver {
@@ -36,19 +55,35 @@ impl Driver {
     /// for the solver.
     pub async fn on_auction_started(
         &self,
-        auction: AuctionWithId,
-    ) -> Result<SettlementSummary, SolveError> {
-        // TODO get deadline from autopilot auction
-        let deadline = Instant::now() + Duration::from_secs(25);
-        Self::solve_until_deadline(
-            auction,
+        request: SolveRequest,
+    ) -> Result<SolveResponse, SolveError> {
+        let start = Instant::now();
+        let auction_id = request.auction.id;
+        let now = Utc::now();
+        let submission_padding = self.submission_latency.padding();
+        let deadline = achievable_deadline(
+            request.deadline,
+            now,
+            self.max_solve_time,
+            self.solve_response_margin + submission_padding,
+        );
+        tracing::debug!(
+            requested = ?request.deadline,
+            achievable = ?deadline,
+            ?submission_padding,
+            "solving auction"
+        );
+        let summary = Self::solve_until_deadline(
+            request.auction,
             self.solver.clone(),
             self.auction_converter.clone(),
             self.block_stream.clone(),
-            deadline,
+            Instant::now() + (deadline - now).to_std().unwrap_or_default(),
+            self.solution_ranking_margin,
         )
-        .await
-        .map_err(SolveError::from)
+        .await?;
+        self.phase_stats.record_commit(auction_id, start.elapsed());
+        Ok(SolveResponse { summary, deadline })
     }
 
     /// Computes a solution with the liquidity collected from a given block.
@@ -57,14 +92,16 @@ impl Driver {
         block: Block,
         converter: Arc<dyn AuctionConverting>,
         solver: Arc<dyn CommitRevealSolving>,
+        deadline: Instant,
     ) -> Result<SettlementSummary> {
         let block = block_number(&block)?;
-        let auction = converter.convert_auction(auction, block).await?;
+        let auction = converter.convert_auction(auction, block, deadline).await?;
         solver.commit(auction).await
     }
 
     /// Keeps solving the auction in a loop with the latest known liquidity until the `deadline`
-    /// has been reached or the `block_stream` terminates.
+    /// has been reached or the `block_stream` terminates. Solvers have to finish `ranking_margin`
+    /// before the `deadline` so that their solutions can still be ranked.
     /// This function uses a `WatchStream` to get notified about new blocks which will start with
     /// yielding the current block immediately and will skip intermediate blocks if it observed
     /// multiple blocks while computing a result.
@@ -74,13 +111,16 @@ impl Driver {
         converter: Arc<dyn AuctionConverting>,
         block_stream: CurrentBlockStream,
         deadline: Instant,
+        ranking_margin: Duration,
     ) -> Result<SettlementSummary> {
+        let solver_deadline = deadline.checked_sub(ranking_margin).unwrap_or(deadline);
         let compute_solutions = into_stream(block_stream.clone()).then(|block| {
             Self::compute_solution_for_block(
                 auction.clone(),
                 block,
                 converter.clone(),
                 solver.clone(),
+                solver_deadline,
             )
         });
         let timeout = tokio::time::sleep_until(deadline.into());
@@ -104,9 +144,13 @@ impl Driver {
     }
 
     /// Validates that the `Settlement` satisfies expected fairness and correctness properties.
-    async fn validate_settlement(&self, settlement: Settlement) -> Result<SimulationDetails> {
+    async fn validate_settlement(
+        &self,
+        solver: Arc<dyn CommitRevealSolving>,
+        settlement: Settlement,
+    ) -> Result<SimulationDetails> {
         let gas_price = self.gas_price_estimator.estimate().await?;
-        let fake_solver = Arc::new(CommitRevealSolverAdapter::from(self.solver.clone()));
+        let fake_solver = Arc::new(CommitRevealSolverAdapter::from(solver));
         let simulation_details = self
             .settlement_rater
             .simulate_settlements(vec![(fake_solver, settlement)], gas_price)
@@ -124,7 +168,16 @@ impl Driver {
     /// still wants to execute and submit that `Settlement`.
     pub async fn on_auction_won(&self, summary: SettlementSummary) -> Result<H256, ExecuteError> {
         tracing::info!("solver won the auction");
-        let settlement = match self.solver.reveal(&summary).await? {
+        let start = Instant::now();
+        let auction_id = summary.auction_id;
+        // Co-located solvers reveal and execute the settlements they computed themselves.
+        let solver = self
+            .solver
+            .delegate(&summary)
+            .unwrap_or_else(|| self.solver.clone());
+        let revealed = solver.reveal(&summary).await;
+        self.phase_stats.record_reveal(auction_id, start.elapsed());
+        let settlement = match revealed? {
             None => {
                 tracing::info!("solver decided against executing the settlement");
                 return Err(ExecuteError::ExecutionRejected);
@@ -132,11 +185,20 @@ impl Driver {
             Some(solution) => solution,
         };
         tracing::info!(?settlement, "received settlement from solver");
-        let simulation_details = self.validate_settlement(settlement).await?;
-        self.submit_settlement(simulation_details)
-            .await
-            // TODO correctly propagate specific errors to the end
-            .map_err(|e| ExecuteError::from(e.into_anyhow()))
+        let simulation_details = self.validate_settlement(solver, settlement).await;
+        self.phase_stats
+            .record_simulation(auction_id, simulation_details.is_ok());
+        let submission = self.submit_settlement(simulation_details?).await;
+        let outcome = match &submission {
+            Ok(_) => SubmissionOutcome::Success,
+            Err(SubmissionError::Revert(_)) => SubmissionOutcome::Revert,
+            Err(_) => SubmissionOutcome::Failure,
+        };
+        self.phase_stats.record_submission(auction_id, outcome);
+        // TODO correctly propagate specific errors to the end
+        let tx_hash = submission.map_err(|e| ExecuteError::from(e.into_anyhow()))?;
+        self.submission_latency.record(start.elapsed());
+        Ok(tx_hash)
     }
 
     /// Tries to submit the `Settlement` on chain. Returns a transaction hash if it was successful.
@@ -161,6 +223,25 @@ impl Driver {
     }
 }
 
+/// Autopilot's deadline brought forward by the margin needed to send back the response and capped
+/// by the maximum solving time. The deadline is never in the past.
+fn achievable_deadline(
+    requested: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    max_solve_time: Duration,
+    response_margin: Duration,
+) -> DateTime<Utc> {
+    let to_chrono = |duration: Duration| {
+        chrono::Duration::from_std(duration).unwrap_or_else(|_| chrono::Duration::max_value())
+    };
+    let max = now + to_chrono(max_solve_time);
+    let deadline = match requested {
+        Some(requested) => (requested - to_chrono(response_margin)).min(max),
+        None => max,
+    };
+    deadline.max(now)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -195,6 +276,7 @@ mod tests {
             Arc::new(converter),
             rx.clone(),
             deadline(10),
+            Duration::ZERO,
         )
         .await;
 
@@ -207,7 +289,7 @@ mod tests {
         let mut converter = MockAuctionConverting::new();
         converter
             .expect_convert_auction()
-            .returning(|_, _| async { anyhow::bail!("failed to convert auction") }.boxed());
+            .returning(|_, _, _| async { anyhow::bail!("failed to convert auction") }.boxed());
         let solver = MockCommitRevealSolving::new();
         let result = Driver::solve_until_deadline(
             Default::default(),
@@ -215,6 +297,7 @@ mod tests {
             Arc::new(converter),
             rx.clone(),
             deadline(10),
+            Duration::ZERO,
         )
         .await;
 
@@ -227,7 +310,7 @@ mod tests {
         let mut converter = MockAuctionConverting::new();
         converter
             .expect_convert_auction()
-            .returning(|_, _| async { Ok(Default::default()) }.boxed());
+            .returning(|_, _, _| async { Ok(Default::default()) }.boxed());
         let mut solver = MockCommitRevealSolving::new();
         solver
             .expect_commit()
@@ -238,6 +321,7 @@ mod tests {
             Arc::new(converter),
             rx.clone(),
             deadline(10),
+            Duration::ZERO,
         )
         .await;
 
@@ -250,7 +334,7 @@ mod tests {
         let mut converter = MockAuctionConverting::new();
         converter
             .expect_convert_auction()
-            .returning(|_, block| {
+            .returning(|_, block, _| {
                 async move {
                     Ok(solver::solver::Auction {
                         liquidity_fetch_block: block,
@@ -289,6 +373,7 @@ mod tests {
             Arc::new(converter),
             rx.clone(),
             deadline(100),
+            Duration::ZERO,
         )
         .await
         .unwrap();
@@ -302,7 +387,7 @@ mod tests {
         let mut converter = MockAuctionConverting::new();
         converter
             .expect_convert_auction()
-            .returning(|_, block| {
+            .returning(|_, block, _| {
                 async move {
                     Ok(solver::solver::Auction {
                         liquidity_fetch_block: block,
@@ -328,6 +413,7 @@ mod tests {
             Arc::new(converter),
             rx.clone(),
             deadline(10),
+            Duration::ZERO,
         )
         .await
         .unwrap();
@@ -341,7 +427,7 @@ mod tests {
         let mut converter = MockAuctionConverting::new();
         converter
             .expect_convert_auction()
-            .returning(|_, block| {
+            .returning(|_, block, _| {
                 async move {
                     Ok(solver::solver::Auction {
                         liquidity_fetch_block: block,
@@ -369,10 +455,73 @@ mod tests {
             Arc::new(converter),
             rx.clone(),
             deadline(1_000),
+            Duration::ZERO,
         )
         .await
         .unwrap();
         assert_eq!(result, SettlementSummary::default());
         assert!(start.elapsed().as_millis() < 100);
     }
+
+    #[tokio::test]
+    async fn solutions_using_the_whole_solving_time_still_get_ranked() {
+        let (_tx, rx) = channel(block(Some(1)));
+        let mut converter = MockAuctionConverting::new();
+        converter
+            .expect_convert_auction()
+            .returning(|_, _, deadline| {
+                async move {
+                    Ok(solver::solver::Auction {
+                        deadline,
+                        ..Default::default()
+                    })
+                }
+                .boxed()
+            });
+
+        let mut solver = MockCommitRevealSolving::new();
+        solver.expect_commit().returning(|auction| {
+            async move {
+                // The solver returns its solution right at its deadline and ranking it takes
+                // some more time.
+                tokio::time::sleep_until(auction.deadline.into()).await;
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                Ok(SettlementSummary {
+                    surplus: 1.,
+                    ..Default::default()
+                })
+            }
+            .boxed()
+        });
+
+        let result = Driver::solve_until_deadline(
+            Default::default(),
+            Arc::new(solver),
+            Arc::new(converter),
+            rx.clone(),
+            deadline(100),
+            Duration::from_millis(50),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.surplus, 1.);
+    }
+
+    #[test]
+    fn negotiates_deadline() {
+        let now = Utc::now();
+        let seconds = chrono::Duration::seconds;
+        let deadline = |requested| {
+            achievable_deadline(
+                requested,
+                now,
+                Duration::from_secs(25),
+                Duration::from_secs(1),
+            )
+        };
+        assert_eq!(deadline(None), now + seconds(25));
+        assert_eq!(deadline(Some(now + seconds(10))), now + seconds(9));
+        assert_eq!(deadline(Some(now + seconds(60))), now + seconds(25));
+        assert_eq!(deadline(Some(now - seconds(10))), now);
+    }
 }
@@ -3,4 +3,6 @@ pub mod arguments;
 pub mod auction_converter;
 pub mod commit_reveal;
 pub mod driver;
+pub mod phase_stats;
 pub mod settlement_proposal;
+pub mod submission_latency;
@@ -1,17 +1,24 @@
 use anyhow::{Context, Result};
 use clap::Parser;
-use contracts::{IUniswapLikeRouter, UniswapV3SwapRouter, WETH9};
+use contracts::{BalancerV2Vault, IUniswapLikeRouter, UniswapV3SwapRouter, WETH9};
 use driver::{
-    api::serve_api, arguments::Arguments, auction_converter::AuctionConverter,
-    commit_reveal::CommitRevealSolver, driver::Driver,
+    api::{serve_api, NetworkDrivers},
+    arguments::Arguments,
+    auction_converter::AuctionConverter,
+    commit_reveal::{CommitRevealSolver, CommitRevealSolving, MultiCommitRevealSolver},
+    driver::Driver,
+    phase_stats::PhaseStats,
+    submission_latency::SubmissionLatency,
 };
 use gas_estimation::GasPriceEstimating;
-use reqwest::Client;
+use reqwest::{Client, Url};
 use shared::{
     baseline_solver::BaseTokens,
+    buffers::BufferRetriever,
     current_block::{current_block_stream, CurrentBlockStream},
     http_solver::{DefaultHttpSolverApi, SolverConfig},
     maintenance::{Maintaining, ServiceMaintenance},
+    preflight::{self, Preflight},
     recent_block_cache::CacheConfig,
     sources::{
         self,
@@ -36,7 +43,7 @@ use solver::{
     settlement_access_list::AccessListEstimating,
     settlement_ranker::SettlementRanker,
     settlement_rater::SettlementRater,
-    settlement_simulation::TenderlyApi,
+    settlement_simulation::{create_revm_simulator, create_simulator},
     settlement_submission::{
         submitter::{
             custom_nodes_api::CustomNodesApi, eden_api::EdenApi, flashbots_api::FlashbotsApi,
@@ -45,12 +52,21 @@ use solver::{
         GlobalTxPool, SolutionSubmitter, StrategyArgs, TransactionStrategy,
     },
     solver::{
-        http_solver::{buffers::BufferRetriever, HttpSolver, InstanceCache},
+        http_solver::{
+            flash_loans::FlashLoanRetriever, HttpSolver, InstanceCache, DEFAULT_INSTANCE_CACHE_SIZE,
+        },
         Solver,
     },
 };
 use std::{collections::HashMap, sync::Arc, time::Duration};
 
+/// The number of recent submissions whose latency decides how much time gets reserved for
+/// submitting settlements.
+const SUBMISSION_LATENCY_SAMPLES: usize = 50;
+
+/// The number of recent auctions per solver whose phase stats the driver API serves.
+const PHASE_STATS_AUCTIONS: usize = 100;
+
 struct CommonComponents {
     client: Client,
     web3: shared::Web3,
@@ -65,9 +81,12 @@ struct CommonComponents {
     current_block_stream: CurrentBlockStream,
 }
 
-async fn init_common_components(args: &Arguments) -> CommonComponents {
-    let client = shared::http_client(args.http_timeout);
-    let web3 = shared::web3(&client, &args.node_url, "base");
+async fn init_common_components(
+    args: &Arguments,
+    client: Client,
+    node_url: &Url,
+) -> CommonComponents {
+    let web3 = shared::web3(&client, node_url, "base");
     let network_id = web3
         .net()
         .version()
@@ -140,11 +159,16 @@ async fn build_solvers(common: &CommonComponents, args: &Arguments) -> Vec<Arc<d
         common.web3.clone(),
         common.settlement_contract.address(),
     ));
+    let flash_loan_retriever = Arc::new(FlashLoanRetriever::new(
+        common.web3.clone(),
+        args.flash_loan_lenders.clone(),
+    ));
     let allowance_mananger = Arc::new(AllowanceManager::new(
         common.web3.clone(),
         common.settlement_contract.address(),
     ));
-    let http_solver_cache = InstanceCache::default();
+    let http_solver_cache =
+        InstanceCache::new(common.network_id.clone(), DEFAULT_INSTANCE_CACHE_SIZE);
 
     args.solvers
         .iter()
@@ -158,6 +182,7 @@ async fn build_solvers(common: &CommonComponents, args: &Arguments) -> Vec<Arc<d
                     client: common.client.clone(),
                     config: SolverConfig {
                         use_internal_buffers: Some(args.use_internal_buffers),
+                        notify_rejections: true,
                         ..Default::default()
                     },
                 },
@@ -165,6 +190,7 @@ async fn build_solvers(common: &CommonComponents, args: &Arguments) -> Vec<Arc<d
                 common.native_token_contract.address(),
                 common.token_info_fetcher.clone(),
                 buffer_retriever.clone(),
+                flash_loan_retriever.clone(),
                 allowance_mananger.clone(),
                 common.order_converter.clone(),
                 http_solver_cache.clone(),
@@ -184,7 +210,8 @@ async fn build_submitter(common: &CommonComponents, args: &Arguments) -> Arc<Sol
         .enumerate()
         .map(|(index, url)| (shared::web3(client, url, index), url))
         .collect::<Vec<_>>();
-    for (node, url) in &submission_nodes_with_url {
+    let mut submission_nodes = Vec::new();
+    for (node, url) in submission_nodes_with_url {
         let node_network_id = node
             .net()
             .version()
@@ -195,15 +222,16 @@ async fn build_submitter(common: &CommonComponents, args: &Arguments) -> Arc<Sol
                 )
             })
             .unwrap();
+        // With several networks every submission node belongs to one of them.
+        if !args.networks.is_empty() && node_network_id != common.network_id {
+            continue;
+        }
         assert_eq!(
             node_network_id, common.network_id,
             "network id of custom node doesn't match main node"
         );
+        submission_nodes.push(node);
     }
-    let submission_nodes = submission_nodes_with_url
-        .into_iter()
-        .map(|(node, _)| node)
-        .collect::<Vec<_>>();
     let submitted_transactions = GlobalTxPool::default();
     let mut transaction_strategies = vec![];
     for strategy in &args.transaction_strategy {
@@ -270,6 +298,7 @@ async fn build_submitter(common: &CommonComponents, args: &Arguments) -> Arc<Sol
     Arc::new(SolutionSubmitter {
         web3: web3.clone(),
         contract: common.settlement_contract.clone(),
+        contract_version: args.settlement_contract_version,
         gas_price_estimator: common.gas_price_estimator.clone(),
         target_confirm_time: args.target_confirm_time,
         max_confirm_time: args.max_submission_seconds,
@@ -475,68 +504,139 @@ async fn build_amm_artifacts(
     res
 }
 
-async fn build_drivers(common: &CommonComponents, args: &Arguments) -> Vec<(Arc<Driver>, String)> {
+async fn build_drivers(
+    common: &CommonComponents,
+    args: &Arguments,
+    metrics: Arc<Metrics>,
+) -> Vec<(Arc<Driver>, String)> {
     let solvers = build_solvers(common, args).await;
     let submitter = build_submitter(common, args).await;
     let settlement_rater = Arc::new(SettlementRater {
         access_list_estimator: common.access_list_estimator.clone(),
         settlement_contract: common.settlement_contract.clone(),
+        settlement_contract_version: args.settlement_contract_version,
         web3: common.web3.clone(),
+        revm_simulator: create_revm_simulator(args.simulation_backend, &common.web3),
     });
     let auction_converter = build_auction_converter(common, args).await.unwrap();
-    let tenderly = args
-        .tenderly_url
-        .clone()
-        .zip(args.tenderly_api_key.clone())
-        .and_then(|(url, api_key)| TenderlyApi::new(url, common.client.clone(), &api_key).ok());
-    let metrics = Arc::new(Metrics::new().unwrap());
-
+    let simulator = create_simulator(
+        args.simulation_backend,
+        &common.web3,
+        &common.client,
+        args.tenderly_url.clone(),
+        args.tenderly_api_key.as_deref(),
+    );
     let settlement_ranker = Arc::new(SettlementRanker {
         metrics: metrics.clone(),
         settlement_rater: settlement_rater.clone(),
         min_order_age: std::time::Duration::from_secs(30),
         max_settlement_price_deviation: None,
         token_list_restriction_for_price_checks: solver::settlement::PriceCheckTokens::All,
+        buffer_usage_limits: Default::default(),
+        internal_balance_vault: BalancerV2Vault::deployed(&common.web3)
+            .await
+            .ok()
+            .map(|vault| vault.address()),
     });
     let logger = Arc::new(DriverLogger {
         web3: common.web3.clone(),
         network_id: common.network_id.clone(),
         metrics,
         settlement_contract: common.settlement_contract.clone(),
+        settlement_contract_version: args.settlement_contract_version,
         simulation_gas_limit: args.simulation_gas_limit,
-        tenderly,
+        simulator,
     });
 
+    let submission_latency = Arc::new(SubmissionLatency::new(
+        args.submission_latency_quantile,
+        args.max_submission_latency_padding,
+        SUBMISSION_LATENCY_SAMPLES,
+    ));
+
+    let solvers = solvers
+        .into_iter()
+        .map(|solver| {
+            Arc::new(CommitRevealSolver::new(
+                solver,
+                common.gas_price_estimator.clone(),
+                settlement_ranker.clone(),
+                logger.clone(),
+            )) as Arc<dyn CommitRevealSolving>
+        })
+        .collect::<Vec<_>>();
+    let solvers = match &args.co_located_solvers {
+        Some(name) => vec![
+            Arc::new(MultiCommitRevealSolver::new(name.clone(), solvers))
+                as Arc<dyn CommitRevealSolving>,
+        ],
+        None => solvers,
+    };
+
     solvers
         .into_iter()
         .map(|solver| {
             let name = solver.name().to_string();
             let driver = Arc::new(Driver {
-                solver: Arc::new(CommitRevealSolver::new(
-                    solver,
-                    common.gas_price_estimator.clone(),
-                    settlement_ranker.clone(),
-                    logger.clone(),
-                )),
+                solver,
                 submitter: submitter.clone(),
                 auction_converter: auction_converter.clone(),
                 block_stream: common.current_block_stream.clone(),
                 logger: logger.clone(),
                 settlement_rater: settlement_rater.clone(),
                 gas_price_estimator: common.gas_price_estimator.clone(),
+                max_solve_time: args.max_solve_time,
+                solve_response_margin: args.solve_response_margin,
+                solution_ranking_margin: args.solution_ranking_margin,
+                submission_latency: submission_latency.clone(),
+                phase_stats: Arc::new(PhaseStats::new(name.clone(), PHASE_STATS_AUCTIONS)),
             });
             (driver, name)
         })
         .collect()
 }
 
+/// Checks the nodes, the contracts and the solver APIs instead of starting the driver.
+async fn run_preflight(args: &Arguments) -> ! {
+    let client = shared::http_client(args.http_timeout);
+    let mut checks = Preflight::default();
+    for (_, node_url) in args.network_nodes() {
+        checks
+            .check_chain(&shared::web3(&client, &node_url, "base"))
+            .await;
+    }
+    for solver in &args.solvers {
+        checks
+            .check(
+                &format!("solver {}", solver.name),
+                preflight::url_reachable(&client, &solver.url),
+            )
+            .await;
+    }
+    checks.exit()
+}
+
 #[tokio::main]
 async fn main() {
     let args = driver::arguments::Arguments::parse();
     shared::tracing::initialize(args.log_filter.as_str(), args.log_stderr_threshold);
     tracing::info!("running driver with validated arguments:\n{}", args);
+    if args.preflight {
+        run_preflight(&args).await;
+    }
     global_metrics::setup_metrics_registry(Some("gp_v2_driver".into()), None);
-    let common = init_common_components(&args).await;
+    // The networks share the HTTP client and the metrics.
+    let client = shared::http_client(args.http_timeout);
+    let metrics = Arc::new(Metrics::new().unwrap());
+    let mut networks = Vec::new();
+    for (network, node_url) in args.network_nodes() {
+        let common = init_common_components(&args, client.clone(), &node_url).await;
+        tracing::info!(?network, chain_id = common.chain_id, "initialized network");
+        networks.push(NetworkDrivers {
+            network,
+            drivers: build_drivers(&common, &args, metrics.clone()).await,
+        });
+    }
 
     let (shutdown_sender, shutdown_receiver) = tokio::sync::oneshot::channel();
     let serve_api = serve_api(
@@ -544,7 +644,7 @@ async fn main() {
         async {
             let _ = shutdown_receiver.await;
         },
-        build_drivers(&common, &args).await,
+        networks,
     );
 
     futures::pin_mut!(serve_api);
@@ -0,0 +1,244 @@
+//! Timings and outcomes of the phases the driver goes through for every auction.
+//!
+//! Solver teams running their own driver don't have access to the monitoring of the central
+//! stack. The stats get exported as prometheus metrics and the most recent auctions are kept in
+//! memory so that the driver API can serve them as JSON.
+
+use serde::Serialize;
+use shared::metrics::exemplars::observe_with_exemplar;
+use std::{collections::VecDeque, sync::Mutex, time::Duration};
+
+/// How a settlement submission ended.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SubmissionOutcome {
+    Success,
+    Revert,
+    /// The settlement didn't get mined, for example because it timed out.
+    Failure,
+}
+
+impl SubmissionOutcome {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Revert => "revert",
+            Self::Failure => "failure",
+        }
+    }
+}
+
+/// The phases of a single auction. Phases the driver didn't get to are `None`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuctionStats {
+    pub auction_id: i64,
+    /// Time from receiving the auction until responding with the solution summary.
+    pub commit_seconds: Option<f64>,
+    /// Time the solver took to reveal the settlement after winning.
+    pub reveal_seconds: Option<f64>,
+    pub simulation_succeeded: Option<bool>,
+    pub submission: Option<SubmissionOutcome>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmissionCounts {
+    pub success: u64,
+    pub revert: u64,
+    pub failure: u64,
+}
+
+/// Aggregates over the most recent auctions.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsSummary {
+    pub mean_commit_seconds: Option<f64>,
+    pub mean_reveal_seconds: Option<f64>,
+    /// The share of simulated settlements that simulated successfully.
+    pub simulation_success_rate: Option<f64>,
+    pub submissions: SubmissionCounts,
+    /// Oldest first.
+    pub auctions: Vec<AuctionStats>,
+}
+
+pub struct PhaseStats {
+    solver: String,
+    max_auctions: usize,
+    auctions: Mutex<VecDeque<AuctionStats>>,
+    metrics: &'static Metrics,
+}
+
+impl PhaseStats {
+    /// Keeps the stats of the most recent `max_auctions` auctions of the solver.
+    pub fn new(solver: String, max_auctions: usize) -> Self {
+        Self {
+            solver,
+            max_auctions,
+            auctions: Default::default(),
+            metrics: Metrics::instance(global_metrics::get_metric_storage_registry()).unwrap(),
+        }
+    }
+
+    pub fn record_commit(&self, auction_id: i64, duration: Duration) {
+        observe_with_exemplar(
+            &self
+                .metrics
+                .commit_seconds
+                .with_label_values(&[&self.solver]),
+            duration.as_secs_f64(),
+            &auction_id.to_string(),
+        );
+        self.update(auction_id, |stats| {
+            stats.commit_seconds = Some(duration.as_secs_f64())
+        });
+    }
+
+    pub fn record_reveal(&self, auction_id: i64, duration: Duration) {
+        observe_with_exemplar(
+            &self
+                .metrics
+                .reveal_seconds
+                .with_label_values(&[&self.solver]),
+            duration.as_secs_f64(),
+            &auction_id.to_string(),
+        );
+        self.update(auction_id, |stats| {
+            stats.reveal_seconds = Some(duration.as_secs_f64())
+        });
+    }
+
+    pub fn record_simulation(&self, auction_id: i64, succeeded: bool) {
+        let result = if succeeded { "success" } else { "failure" };
+        self.metrics
+            .simulations
+            .with_label_values(&[&self.solver, result])
+            .inc();
+        self.update(auction_id, |stats| {
+            stats.simulation_succeeded = Some(succeeded)
+        });
+    }
+
+    pub fn record_submission(&self, auction_id: i64, outcome: SubmissionOutcome) {
+        self.metrics
+            .submissions
+            .with_label_values(&[&self.solver, outcome.label()])
+            .inc();
+        self.update(auction_id, |stats| stats.submission = Some(outcome));
+    }
+
+    fn update(&self, auction_id: i64, update: impl FnOnce(&mut AuctionStats)) {
+        let mut auctions = self.auctions.lock().unwrap();
+        let index = match auctions
+            .iter()
+            .rposition(|stats| stats.auction_id == auction_id)
+        {
+            Some(index) => index,
+            None => {
+                auctions.push_back(AuctionStats {
+                    auction_id,
+                    ..Default::default()
+                });
+                while auctions.len() > self.max_auctions {
+                    auctions.pop_front();
+                }
+                auctions.len() - 1
+            }
+        };
+        update(&mut auctions[index]);
+    }
+
+    pub fn summary(&self) -> StatsSummary {
+        let auctions = self
+            .auctions
+            .lock()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        let mean = |values: Vec<f64>| {
+            (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+        };
+        let simulations = auctions
+            .iter()
+            .filter_map(|stats| stats.simulation_succeeded)
+            .collect::<Vec<_>>();
+        let mut submissions = SubmissionCounts::default();
+        for outcome in auctions.iter().filter_map(|stats| stats.submission) {
+            match outcome {
+                SubmissionOutcome::Success => submissions.success += 1,
+                SubmissionOutcome::Revert => submissions.revert += 1,
+                SubmissionOutcome::Failure => submissions.failure += 1,
+            }
+        }
+        StatsSummary {
+            mean_commit_seconds: mean(auctions.iter().filter_map(|s| s.commit_seconds).collect()),
+            mean_reveal_seconds: mean(auctions.iter().filter_map(|s| s.reveal_seconds).collect()),
+            simulation_success_rate: (!simulations.is_empty()).then(|| {
+                simulations.iter().filter(|succeeded| **succeeded).count() as f64
+                    / simulations.len() as f64
+            }),
+            submissions,
+            auctions,
+        }
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage)]
+#[metric(subsystem = "driver_phases")]
+struct Metrics {
+    /// Time from receiving an auction until responding with the solution summary.
+    #[metric(labels("solver"))]
+    commit_seconds: prometheus::HistogramVec,
+
+    /// Time the solver took to reveal a settlement after winning an auction.
+    #[metric(labels("solver"))]
+    reveal_seconds: prometheus::HistogramVec,
+
+    /// Number of simulated settlements by result.
+    #[metric(labels("solver", "result"))]
+    simulations: prometheus::IntCounterVec,
+
+    /// Number of submitted settlements by outcome.
+    #[metric(labels("solver", "outcome"))]
+    submissions: prometheus::IntCounterVec,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_recent_auctions() {
+        let stats = PhaseStats::new("solver".to_string(), 2);
+        stats.record_commit(1, Duration::from_secs(1));
+        stats.record_commit(2, Duration::from_secs(3));
+        stats.record_reveal(2, Duration::from_secs(2));
+        stats.record_simulation(2, true);
+        stats.record_submission(2, SubmissionOutcome::Revert);
+        stats.record_commit(3, Duration::from_secs(5));
+        stats.record_simulation(3, false);
+
+        let summary = stats.summary();
+        // The first auction was dropped.
+        assert_eq!(
+            summary
+                .auctions
+                .iter()
+                .map(|stats| stats.auction_id)
+                .collect::<Vec<_>>(),
+            [2, 3]
+        );
+        assert_eq!(summary.mean_commit_seconds, Some(4.));
+        assert_eq!(summary.mean_reveal_seconds, Some(2.));
+        assert_eq!(summary.simulation_success_rate, Some(0.5));
+        assert_eq!(
+            summary.submissions,
+            SubmissionCounts {
+                success: 0,
+                revert: 1,
+                failure: 0,
+            }
+        );
+    }
+}
@@ -46,7 +46,7 @@ impl TradedOrder {
     /// User orders are allowed to get surplus and therefore return the clearing price of the
     /// buy_token whereas liquidity orders must not get surplus so they return their limit price.
     fn buy_token_price(&self, clearing_prices: &HashMap<H160, U256>) -> Option<U256> {
-        match self.order.metadata.is_liquidity_order {
+        match self.order.metadata.class.is_liquidity_order() {
             // liquidity orders have to be settled at their limit price
             true => clearing_prices
                 .get(&self.order.data.sell_token)?
@@ -156,7 +156,7 @@ impl SettlementProposal {
             let remaining = shared::remaining_amounts::Remaining::from_order(&trade.order)?;
             let remaining_fee = remaining.remaining(trade.order.data.fee_amount)?;
 
-            if trade.order.metadata.is_liquidity_order {
+            if trade.order.metadata.class.is_liquidity_order() {
                 encoder.add_liquidity_order_trade(
                     trade.order,
                     trade.executed_amount,
@@ -0,0 +1,92 @@
+//! Tracks how long it takes from revealing a settlement until it is included in a block.
+//!
+//! The deadline autopilot sends with an auction is the same for every network although blocks and
+//! mempools behave differently on each of them. Reserving a quantile of the recently observed
+//! latencies from the deadline gives the settlement enough time to get mined on slow networks
+//! without wasting solving time on fast ones.
+
+use std::{collections::VecDeque, sync::Mutex, time::Duration};
+
+/// The number of latencies that need to be observed before they affect the deadline.
+const MIN_SAMPLES: usize = 5;
+
+pub struct SubmissionLatency {
+    /// The quantile of the recent latencies that gets reserved. Nothing gets reserved if not set.
+    quantile: Option<f64>,
+    max_padding: Duration,
+    max_samples: usize,
+    samples: Mutex<VecDeque<Duration>>,
+}
+
+impl SubmissionLatency {
+    /// Keeps the most recent `max_samples` latencies and never reserves more than `max_padding`.
+    pub fn new(quantile: Option<f64>, max_padding: Duration, max_samples: usize) -> Self {
+        Self {
+            quantile,
+            max_padding,
+            max_samples,
+            samples: Default::default(),
+        }
+    }
+
+    /// Records the time from revealing a settlement until its inclusion.
+    pub fn record(&self, latency: Duration) {
+        let mut samples = self.samples.lock().unwrap();
+        samples.push_back(latency);
+        while samples.len() > self.max_samples {
+            samples.pop_front();
+        }
+    }
+
+    /// The time to reserve before the auction deadline for getting a settlement included.
+    pub fn padding(&self) -> Duration {
+        let quantile = match self.quantile {
+            Some(quantile) => quantile.clamp(0., 1.),
+            None => return Duration::ZERO,
+        };
+        let samples = self.samples.lock().unwrap();
+        if samples.len() < MIN_SAMPLES {
+            return Duration::ZERO;
+        }
+        let mut sorted = samples.iter().copied().collect::<Vec<_>>();
+        sorted.sort_unstable();
+        let index = ((sorted.len() - 1) as f64 * quantile).round() as usize;
+        sorted[index].min(self.max_padding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(seconds: u64) -> Duration {
+        Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn reserves_quantile_of_recent_latencies() {
+        let latency = SubmissionLatency::new(Some(0.5), seconds(10), 5);
+        for sample in [1, 2, 3, 4] {
+            latency.record(seconds(sample));
+        }
+        assert_eq!(latency.padding(), Duration::ZERO);
+
+        latency.record(seconds(5));
+        assert_eq!(latency.padding(), seconds(3));
+
+        // Old samples get replaced and the padding is capped.
+        for _ in 0..3 {
+            latency.record(seconds(30));
+        }
+        assert_eq!(latency.padding(), seconds(10));
+    }
+
+    #[test]
+    fn reserves_nothing_without_quantile() {
+        let latency = SubmissionLatency::new(None, seconds(10), 5);
+        for _ in 0..5 {
+            latency.record(seconds(5));
+        }
+        assert_eq!(latency.padding(), Duration::ZERO);
+    }
+}
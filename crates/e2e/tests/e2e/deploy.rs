@@ -4,7 +4,7 @@ use contracts::{
     UniswapV2Factory, UniswapV2Router02, WETH9,
 };
 use ethcontract::{Address, U256};
-use model::DomainSeparator;
+use model::{DomainParameters, DomainSeparator};
 use shared::Web3;
 
 pub struct Contracts {
@@ -15,6 +15,7 @@ pub struct Contracts {
     pub weth: WETH9,
     pub allowance: Address,
     pub domain_separator: DomainSeparator,
+    pub domain: DomainParameters,
 }
 
 pub async fn deploy(web3: &Web3) -> Result<Contracts> {
@@ -94,6 +95,15 @@ pub async fn deploy(web3: &Web3) -> Result<Contracts> {
             .expect("Couldn't query domain separator")
             .0,
     );
+    let domain = DomainParameters {
+        chain_id: web3
+            .eth()
+            .chain_id()
+            .await
+            .expect("get chain ID failed")
+            .as_u64(),
+        verifying_contract: gp_settlement.address(),
+    };
 
     Ok(Contracts {
         balancer_vault,
@@ -103,5 +113,6 @@ pub async fn deploy(web3: &Web3) -> Result<Contracts> {
         weth,
         allowance,
         domain_separator,
+        domain,
     })
 }
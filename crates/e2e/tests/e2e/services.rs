@@ -1,19 +1,24 @@
 use crate::deploy::Contracts;
 use anyhow::{anyhow, Result};
-use autopilot::solvable_orders::SolvableOrdersCache;
+use autopilot::{database::ExpiredQuoteRemover, solvable_orders::SolvableOrdersCache};
 use contracts::{ERC20Mintable, GnosisSafe, GnosisSafeCompatibilityFallbackHandler, WETH9};
 use ethcontract::{Bytes, H160, H256, U256};
-use orderbook::{database::Postgres, orderbook::Orderbook};
+use orderbook::{
+    buffers::BufferCache, database::Postgres, orderbook::Orderbook, trade_proofs::TradeProofs,
+};
 use reqwest::{Client, StatusCode};
 use shared::{
     account_balances::Web3BalanceFetcher,
     bad_token::list_based::ListBasedDetector,
     baseline_solver::BaseTokens,
+    buffers::BufferRetriever,
     current_block::{current_block_stream, CurrentBlockStream},
     fee_subsidy::Subsidy,
     maintenance::ServiceMaintenance,
+    order_conditions::Web3ConditionEvaluator,
     order_quoting::{OrderQuoter, QuoteHandler},
     order_validation::{OrderValidator, SignatureConfiguration},
+    presignature::Web3PresignatureChecker,
     price_estimation::baseline::BaselinePriceEstimator,
     price_estimation::native::NativePriceEstimator,
     price_estimation::sanitized::SanitizedPriceEstimator,
@@ -246,7 +251,16 @@ impl OrderbookServices {
             current_block_stream.clone(),
             native_price_estimator,
             signature_validator.clone(),
+            Arc::new(Web3ConditionEvaluator::new(web3.clone())),
+            Arc::new(Web3PresignatureChecker::new(
+                contracts.gp_settlement.clone(),
+            )),
             Duration::from_secs(1),
+            Default::default(),
+            10,
+            None,
+            None,
+            None,
         );
         let order_validator = Arc::new(OrderValidator::new(
             Box::new(web3.clone()),
@@ -262,17 +276,32 @@ impl OrderbookServices {
             signature_validator,
         ));
         let orderbook = Arc::new(Orderbook::new(
-            contracts.domain_separator,
+            contracts.domain,
             contracts.gp_settlement.address(),
-            api_db.as_ref().clone(),
+            api_db.clone(),
             order_validator.clone(),
             100,
             current_block_stream.clone(),
+            Default::default(),
         ));
         let maintenance = ServiceMaintenance {
-            maintainers: vec![Arc::new(autopilot_db.clone()), event_updater],
+            maintainers: vec![
+                Arc::new(ExpiredQuoteRemover::new(
+                    autopilot_db.clone(),
+                    Duration::ZERO,
+                )),
+                event_updater,
+            ],
         };
         let quotes = Arc::new(QuoteHandler::new(order_validator, quoter));
+        let buffers = Arc::new(BufferCache::new(
+            orderbook.clone(),
+            Arc::new(BufferRetriever::new(
+                web3.clone(),
+                contracts.gp_settlement.address(),
+            )),
+        ));
+        let trade_proofs = Arc::new(TradeProofs::new(web3.clone(), api_db.clone()));
         orderbook::serve_api(
             api_db.clone(),
             orderbook,
@@ -281,6 +310,11 @@ impl OrderbookServices {
             pending(),
             api_db.clone(),
             None,
+            None,
+            Default::default(),
+            buffers,
+            trade_proofs,
+            None,
         );
 
         Self {
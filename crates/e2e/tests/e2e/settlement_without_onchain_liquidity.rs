@@ -181,12 +181,12 @@ async fn onchain_settlement_without_liquidity(web3: Web3) {
         Arc::new(PoolFetcher::uniswap(uniswap_pair_provider, web3.clone())),
     );
     let solver = solver::solver::naive_solver(solver_account);
-    let liquidity_collector = LiquidityCollector {
+    let liquidity_collector = Arc::new(LiquidityCollector {
         uniswap_like_liquidity: vec![uniswap_liquidity],
         balancer_v2_liquidity: None,
         zeroex_liquidity: None,
         uniswap_v3_liquidity: None,
-    };
+    });
     let network_id = web3.net().version().await.unwrap();
     let market_makable_token_list = TokenList::new(maplit::hashmap! {
         token_a.address() => Token {
@@ -209,11 +209,12 @@ async fn onchain_settlement_without_liquidity(web3: Web3) {
         web3.clone(),
         network_id.clone(),
         Duration::from_secs(10),
-        Some(market_makable_token_list),
+        Some(Arc::new(market_makable_token_list.into())),
         block_stream,
         SolutionSubmitter {
             web3: web3.clone(),
             contract: contracts.gp_settlement.clone(),
+            contract_version: solver::encoding::SettlementContractVersion::V1,
             gas_price_estimator: Arc::new(web3.clone()),
             target_confirm_time: Duration::from_secs(1),
             gas_price_cap: f64::MAX,
@@ -249,6 +250,9 @@ async fn onchain_settlement_without_liquidity(web3: Web3) {
         None,
         None.into(),
         None,
+        None,
+        Default::default(),
+        None,
     );
     driver.single_run().await.unwrap();
 
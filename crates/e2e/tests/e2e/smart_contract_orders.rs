@@ -226,12 +226,12 @@ async fn smart_contract_orders(web3: Web3) {
         Arc::new(PoolFetcher::uniswap(uniswap_pair_provider, web3.clone())),
     );
     let solver = solver::solver::naive_solver(solver_account);
-    let liquidity_collector = LiquidityCollector {
+    let liquidity_collector = Arc::new(LiquidityCollector {
         uniswap_like_liquidity: vec![uniswap_liquidity],
         balancer_v2_liquidity: None,
         zeroex_liquidity: None,
         uniswap_v3_liquidity: None,
-    };
+    });
     let network_id = web3.net().version().await.unwrap();
     let submitted_transactions = GlobalTxPool::default();
     let mut driver = solver::driver::Driver::new(
@@ -251,6 +251,7 @@ async fn smart_contract_orders(web3: Web3) {
         SolutionSubmitter {
             web3: web3.clone(),
             contract: contracts.gp_settlement.clone(),
+            contract_version: solver::encoding::SettlementContractVersion::V1,
             gas_price_estimator: Arc::new(web3.clone()),
             target_confirm_time: Duration::from_secs(1),
             gas_price_cap: f64::MAX,
@@ -286,6 +287,9 @@ async fn smart_contract_orders(web3: Web3) {
         None,
         None.into(),
         None,
+        None,
+        Default::default(),
+        None,
     );
     driver.single_run().await.unwrap();
 
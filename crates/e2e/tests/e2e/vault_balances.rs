@@ -139,12 +139,12 @@ async fn vault_balances(web3: Web3) {
         Arc::new(PoolFetcher::uniswap(uniswap_pair_provider, web3.clone())),
     );
     let solver = solver::solver::naive_solver(solver_account);
-    let liquidity_collector = LiquidityCollector {
+    let liquidity_collector = Arc::new(LiquidityCollector {
         uniswap_like_liquidity: vec![uniswap_liquidity],
         balancer_v2_liquidity: None,
         zeroex_liquidity: None,
         uniswap_v3_liquidity: None,
-    };
+    });
     let network_id = web3.net().version().await.unwrap();
     let submitted_transactions = GlobalTxPool::default();
     let mut driver = solver::driver::Driver::new(
@@ -164,6 +164,7 @@ async fn vault_balances(web3: Web3) {
         SolutionSubmitter {
             web3: web3.clone(),
             contract: contracts.gp_settlement.clone(),
+            contract_version: solver::encoding::SettlementContractVersion::V1,
             gas_price_estimator: Arc::new(web3.clone()),
             target_confirm_time: Duration::from_secs(1),
             gas_price_cap: f64::MAX,
@@ -199,6 +200,9 @@ async fn vault_balances(web3: Web3) {
         None,
         None.into(),
         None,
+        None,
+        Default::default(),
+        None,
     );
     driver.single_run().await.unwrap();
 
@@ -4,6 +4,9 @@ use std::collections::HashMap;
 /// Global metrics registry used by all components.
 static REGISTRY: OnceCell<prometheus_metric_storage::StorageRegistry> = OnceCell::new();
 
+/// Prefix of the metric names in the global registry.
+static PREFIX: OnceCell<String> = OnceCell::new();
+
 /// Configure global metrics registry.
 ///
 /// This function allows specifying common prefix that will be added
@@ -19,9 +22,17 @@ static REGISTRY: OnceCell<prometheus_metric_storage::StorageRegistry> = OnceCell
 /// any call to [`get_registry`]. This function also panics if registry
 /// configuration is invalid.
 pub fn setup_metrics_registry(prefix: Option<String>, labels: Option<HashMap<String, String>>) {
-    let registry = prometheus::Registry::new_custom(prefix, labels).unwrap();
+    let registry = prometheus::Registry::new_custom(prefix.clone(), labels).unwrap();
     let storage_registry = prometheus_metric_storage::StorageRegistry::new(registry);
     REGISTRY.set(storage_registry).unwrap();
+    if let Some(prefix) = prefix {
+        PREFIX.set(prefix).unwrap();
+    }
+}
+
+/// The prefix the global registry adds to metric names when they are gathered.
+pub fn get_metrics_prefix() -> Option<&'static str> {
+    PREFIX.get().map(String::as_str)
 }
 
 /// Get the global instance of the metrics registry.
@@ -0,0 +1,176 @@
+//! API keys allow an owner to delegate order placement to a service without signing every order
+//! with their wallet.
+//!
+//! The key itself is a secret chosen by the owner. Only its hash is shared with the orderbook
+//! together with an EIP-712 signature of the owner authorizing it.
+
+use crate::{
+    signature::{EcdsaSignature, EcdsaSigningScheme},
+    DomainSeparator,
+};
+use anyhow::Result;
+use hex_literal::hex;
+use primitive_types::{H160, H256};
+use serde::{Deserialize, Serialize};
+use web3::signing;
+
+/// Hashes an API key as it is stored by the orderbook.
+pub fn hash_api_key(key: &str) -> H256 {
+    H256(signing::keccak256(key.as_bytes()))
+}
+
+/// An owner's authorization of an API key.
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyAuthorization {
+    pub key_hash: H256,
+    /// Unix timestamp after which the key can no longer be used.
+    pub valid_to: u32,
+    /// The hash of a key of the same owner that gets revoked when this key is created. It is part
+    /// of the signed data so that an authorization can't be replayed to revoke another key.
+    #[serde(default)]
+    pub replaces: Option<H256>,
+    pub signature: EcdsaSignature,
+    pub signing_scheme: EcdsaSigningScheme,
+}
+
+// EIP-712
+impl ApiKeyAuthorization {
+    // keccak256("ApiKeyAuthorization(bytes32 keyHash,uint32 validTo,bytes32 replaces)")
+    const TYPE_HASH: [u8; 32] =
+        hex!("75e229fda10499b09f27d37a0626744cb1ed6b04b386e2a1913fedd4e186f839");
+
+    /// Authorizations that don't replace a key sign the zero hash for `replaces`.
+    pub fn hash_struct(&self) -> [u8; 32] {
+        let mut hash_data = [0u8; 128];
+        hash_data[0..32].copy_from_slice(&Self::TYPE_HASH);
+        hash_data[32..64].copy_from_slice(self.key_hash.as_bytes());
+        hash_data[92..96].copy_from_slice(&self.valid_to.to_be_bytes());
+        hash_data[96..128].copy_from_slice(self.replaces.unwrap_or_default().as_bytes());
+        signing::keccak256(&hash_data)
+    }
+
+    /// Returns the owner that authorized the key.
+    pub fn validate(&self, domain_separator: &DomainSeparator) -> Result<H160> {
+        self.signature
+            .recover(self.signing_scheme, domain_separator, &self.hash_struct())
+    }
+}
+
+/// An owner's revocation of an API key.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub struct ApiKeyRevocation {
+    pub key_hash: H256,
+    pub signature: EcdsaSignature,
+    pub signing_scheme: EcdsaSigningScheme,
+}
+
+// EIP-712
+impl ApiKeyRevocation {
+    // keccak256("ApiKeyRevocation(bytes32 keyHash)")
+    const TYPE_HASH: [u8; 32] =
+        hex!("b470897f93c204ce61d52526e67b65772ffeaab4fa74c350c6abfbeb276b3ed9");
+
+    pub fn hash_struct(&self) -> [u8; 32] {
+        let mut hash_data = [0u8; 64];
+        hash_data[0..32].copy_from_slice(&Self::TYPE_HASH);
+        hash_data[32..64].copy_from_slice(self.key_hash.as_bytes());
+        signing::keccak256(&hash_data)
+    }
+
+    /// Returns the owner that revoked the key.
+    pub fn validate(&self, domain_separator: &DomainSeparator) -> Result<H160> {
+        self.signature
+            .recover(self.signing_scheme, domain_separator, &self.hash_struct())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::ONE_KEY;
+    use web3::signing::{Key, SecretKeyRef};
+
+    #[test]
+    fn authorization_recovers_owner() {
+        let domain_separator = DomainSeparator([1; 32]);
+        let key = SecretKeyRef::new(&ONE_KEY);
+        let mut authorization = ApiKeyAuthorization {
+            key_hash: hash_api_key("secret"),
+            valid_to: 1337,
+            replaces: None,
+            signature: Default::default(),
+            signing_scheme: EcdsaSigningScheme::Eip712,
+        };
+        authorization.signature = EcdsaSignature::sign(
+            authorization.signing_scheme,
+            &domain_separator,
+            &authorization.hash_struct(),
+            key,
+        );
+        assert_eq!(
+            authorization.validate(&domain_separator).unwrap(),
+            key.address()
+        );
+
+        // Changing the expiry invalidates the authorization.
+        authorization.valid_to += 1;
+        assert_ne!(
+            authorization.validate(&domain_separator).unwrap(),
+            key.address()
+        );
+    }
+
+    #[test]
+    fn authorization_signs_replaced_key() {
+        let domain_separator = DomainSeparator([1; 32]);
+        let key = SecretKeyRef::new(&ONE_KEY);
+        let mut authorization = ApiKeyAuthorization {
+            key_hash: hash_api_key("secret"),
+            valid_to: 1337,
+            replaces: Some(hash_api_key("old secret")),
+            signature: Default::default(),
+            signing_scheme: EcdsaSigningScheme::Eip712,
+        };
+        authorization.signature = EcdsaSignature::sign(
+            authorization.signing_scheme,
+            &domain_separator,
+            &authorization.hash_struct(),
+            key,
+        );
+        assert_eq!(
+            authorization.validate(&domain_separator).unwrap(),
+            key.address()
+        );
+
+        // Replaying the authorization to revoke another key of the owner fails.
+        for replaces in [Some(hash_api_key("other secret")), None] {
+            let tampered = ApiKeyAuthorization {
+                replaces,
+                ..authorization
+            };
+            assert_ne!(tampered.validate(&domain_separator).unwrap(), key.address());
+        }
+    }
+
+    #[test]
+    fn revocation_recovers_owner() {
+        let domain_separator = DomainSeparator([1; 32]);
+        let key = SecretKeyRef::new(&ONE_KEY);
+        let mut revocation = ApiKeyRevocation {
+            key_hash: hash_api_key("secret"),
+            signature: Default::default(),
+            signing_scheme: EcdsaSigningScheme::EthSign,
+        };
+        revocation.signature = EcdsaSignature::sign(
+            revocation.signing_scheme,
+            &domain_separator,
+            &revocation.hash_struct(),
+            key,
+        );
+        assert_eq!(
+            revocation.validate(&domain_separator).unwrap(),
+            key.address()
+        );
+    }
+}
@@ -1,10 +1,14 @@
 //! Module defining a batch auction.
 
-use crate::{order::Order, u256_decimal::DecimalU256};
+use crate::{
+    order::{Order, OrderUid},
+    u256_decimal::DecimalU256,
+};
+use chrono::{DateTime, Utc};
 use primitive_types::{H160, U256};
 use serde::{Deserialize, Serialize};
-use serde_with::serde_as;
-use std::collections::BTreeMap;
+use serde_with::{serde_as, DisplayFromStr};
+use std::collections::{BTreeMap, BTreeSet};
 
 pub type AuctionId = i64;
 
@@ -21,6 +25,19 @@ pub struct AuctionWithId {
     pub auction: Auction,
 }
 
+/// The request autopilot sends to drivers to solve an auction.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SolveRequest {
+    #[serde(flatten)]
+    pub auction: AuctionWithId,
+    /// The time by which the driver has to respond with its solution. Autopilot picks it per
+    /// auction based on the auction's size and the network congestion. Drivers fall back to their
+    /// maximum solving time if it is missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<DateTime<Utc>>,
+}
+
 /// A batch auction.
 #[serde_as]
 #[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
@@ -43,13 +60,52 @@ pub struct Auction {
     /// The reference prices for all traded tokens in the auction.
     #[serde_as(as = "BTreeMap<_, DecimalU256>")]
     pub prices: BTreeMap<H160, U256>,
+
+    /// Tokens whose price could not be fetched for this auction and is the price of a previous
+    /// auction instead.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub estimated_prices: BTreeSet<H160>,
+
+    /// Groups of orders that get solved as independent sub-auctions because the auction exceeds
+    /// the maximum auction size. Orders of different clusters neither trade a common token nor
+    /// share liquidity. Empty if the auction gets solved as a whole.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub clusters: Vec<Vec<OrderUid>>,
+
+    /// The seed of the pseudo-random selection of orders if clusters exceeded the maximum auction
+    /// size and orders had to be dropped. Allows reproducing the selection.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub order_selection_seed: Option<u64>,
+}
+
+/// Changes to the solvable orders between two updates of the auction. Drivers can follow them to
+/// prepare for orders before the auction containing them arrives.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SolvableOrdersChanges {
+    /// The block the solvable orders were updated on.
+    pub block: u64,
+    /// Orders that became solvable or changed, for example because they got partially filled or
+    /// the available balance of their owner changed.
+    pub updated: Vec<Order>,
+    /// Orders that are no longer solvable, for example because they got filled, cancelled or
+    /// their owner's balance became too low.
+    pub removed: Vec<OrderUid>,
+}
+
+impl SolvableOrdersChanges {
+    pub fn is_empty(&self) -> bool {
+        self.updated.is_empty() && self.removed.is_empty()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::order::{OrderMetadata, OrderUid};
-    use maplit::btreemap;
+    use chrono::TimeZone;
+    use maplit::{btreemap, btreeset};
     use serde_json::json;
 
     #[test]
@@ -69,6 +125,9 @@ mod tests {
                 H160([2; 20]) => U256::from(2),
                 H160([1; 20]) => U256::from(1),
             },
+            estimated_prices: btreeset! { H160([2; 20]) },
+            clusters: vec![vec![OrderUid([1; 56])], vec![OrderUid([2; 56])]],
+            order_selection_seed: Some(u64::MAX),
         };
         let auction = AuctionWithId { id: 0, auction };
 
@@ -86,6 +145,12 @@ mod tests {
                     "0x0101010101010101010101010101010101010101": "1",
                     "0x0202020202020202020202020202020202020202": "2",
                 },
+                "estimatedPrices": ["0x0202020202020202020202020202020202020202"],
+                "clusters": [
+                    [OrderUid([1; 56])],
+                    [OrderUid([2; 56])],
+                ],
+                "orderSelectionSeed": "18446744073709551615",
             }),
         );
         assert_eq!(
@@ -94,4 +159,33 @@ mod tests {
             auction,
         );
     }
+
+    #[test]
+    fn solve_request_deadline_is_optional() {
+        let auction = AuctionWithId {
+            id: 1,
+            ..Default::default()
+        };
+        let request = SolveRequest {
+            auction: auction.clone(),
+            deadline: Some(Utc.timestamp(1_600_000_000, 0)),
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["id"], 1);
+        assert_eq!(json["deadline"], "2020-09-13T12:26:40Z");
+        assert_eq!(
+            serde_json::from_value::<SolveRequest>(json).unwrap(),
+            request
+        );
+
+        let request: SolveRequest =
+            serde_json::from_value(serde_json::to_value(&auction).unwrap()).unwrap();
+        assert_eq!(
+            request,
+            SolveRequest {
+                auction,
+                deadline: None,
+            }
+        );
+    }
 }
@@ -0,0 +1,58 @@
+//! Contains the settlement contract buffers served by the `buffers` endpoint.
+
+use crate::{auction::AuctionId, u256_decimal};
+use primitive_types::{H160, U256};
+use serde::{Deserialize, Serialize};
+
+/// The token balances of the settlement contract. Solvers can use them instead of external
+/// liquidity which is why they are called buffers.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Buffers {
+    /// The auction whose tokens the buffers were fetched for.
+    pub auction_id: AuctionId,
+    /// The block of the auction.
+    pub block: u64,
+    pub tokens: Vec<TokenBuffer>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenBuffer {
+    pub token: H160,
+    #[serde(with = "u256_decimal")]
+    pub balance: U256,
+    /// The value of the balance in atoms of the native token according to the auction's price.
+    #[serde(with = "u256_decimal")]
+    pub native_value: U256,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serialization() {
+        let buffers = Buffers {
+            auction_id: 1,
+            block: 2,
+            tokens: vec![TokenBuffer {
+                token: H160([3; 20]),
+                balance: 4.into(),
+                native_value: 5.into(),
+            }],
+        };
+        let json = json!({
+            "auctionId": 1,
+            "block": 2,
+            "tokens": [{
+                "token": "0x0303030303030303030303030303030303030303",
+                "balance": "4",
+                "nativeValue": "5",
+            }],
+        });
+        assert_eq!(serde_json::to_value(&buffers).unwrap(), json);
+        assert_eq!(serde_json::from_value::<Buffers>(json).unwrap(), buffers);
+    }
+}
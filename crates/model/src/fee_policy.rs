@@ -0,0 +1,99 @@
+//! Contains the explanation of an order's fee served by the `fee_policy` endpoint.
+
+use crate::u256_decimal::{self, DecimalU256};
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+/// How the minimum fee of an order was computed from the quote it was created with.
+#[serde_as]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderFeePolicy {
+    /// The estimated gas units required to execute the quoted trade.
+    pub gas_amount: f64,
+    /// The estimated gas price at the time of quoting.
+    pub gas_price: f64,
+    /// The price of the sell token in native token at the time of quoting.
+    pub sell_token_price: f64,
+    /// The fee in sell token before any subsidy.
+    #[serde(with = "u256_decimal")]
+    pub raw_fee_amount: U256,
+    /// The minimum fee in sell token after the subsidy. `None` for orders created before subsidies
+    /// were recorded.
+    #[serde_as(as = "Option<DecimalU256>")]
+    pub subsidized_fee_amount: Option<U256>,
+    /// The fee the order was created with, which is at least the subsidized fee.
+    #[serde(with = "u256_decimal")]
+    pub fee_amount: U256,
+    pub subsidy: Option<FeeSubsidy>,
+    /// The rules of the subsidy that changed the fee.
+    pub applied_rules: Vec<FeeSubsidyRule>,
+}
+
+/// The combined fee subsidy of all subsidy strategies that applied to an order.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeSubsidy {
+    /// A flat discount in native token, applied before the factor.
+    pub discount: f64,
+    /// The lower bound of the fee in native token after the flat discount.
+    pub min_discounted: f64,
+    /// The factor the discounted fee gets multiplied with.
+    pub factor: f64,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FeeSubsidyRule {
+    /// The flat discount lowered the fee.
+    FlatDiscount,
+    /// The fee after the flat discount was raised to the minimum discounted fee.
+    MinimumDiscountedFee,
+    /// The fee got multiplied with a factor, for example for partners, COW holders or liquidity
+    /// orders.
+    FeeFactor,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serialization() {
+        let policy = OrderFeePolicy {
+            gas_amount: 100_000.,
+            gas_price: 2e10,
+            sell_token_price: 0.5,
+            raw_fee_amount: 4_000_000_000_000_000u64.into(),
+            subsidized_fee_amount: Some(2_000_000_000_000_000u64.into()),
+            fee_amount: 2_500_000_000_000_000u64.into(),
+            subsidy: Some(FeeSubsidy {
+                discount: 0.,
+                min_discounted: 0.,
+                factor: 0.5,
+            }),
+            applied_rules: vec![FeeSubsidyRule::FeeFactor],
+        };
+        let json = json!({
+            "gasAmount": 100_000.,
+            "gasPrice": 2e10,
+            "sellTokenPrice": 0.5,
+            "rawFeeAmount": "4000000000000000",
+            "subsidizedFeeAmount": "2000000000000000",
+            "feeAmount": "2500000000000000",
+            "subsidy": {
+                "discount": 0.,
+                "minDiscounted": 0.,
+                "factor": 0.5,
+            },
+            "appliedRules": ["feeFactor"],
+        });
+        assert_eq!(serde_json::to_value(&policy).unwrap(), json);
+        assert_eq!(
+            serde_json::from_value::<OrderFeePolicy>(json).unwrap(),
+            policy
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! Contains the expected costs of a trade served by the `fee_recommendation` endpoint.
+
+use serde::{Deserialize, Serialize};
+
+/// The fee and slippage recent trades of a token pair of similar size had, so that integrators
+/// can show users what to expect before they trade.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeRecommendation {
+    /// The number of trades the recommendation is based on.
+    pub sample_size: u64,
+    /// `None` if there were no trades.
+    pub fee_bps: Option<BpsRange>,
+    /// Positive values mean a worse price than quoted. `None` if there were no quoted trades.
+    pub slippage_bps: Option<BpsRange>,
+}
+
+/// The 10th, 50th and 90th percentile of a cost in basis points.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct BpsRange {
+    pub low: f64,
+    pub median: f64,
+    pub high: f64,
+}
+
+impl BpsRange {
+    /// Returns `None` if there are no samples.
+    pub fn from_samples(mut samples: Vec<f64>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_by(f64::total_cmp);
+        // Linearly interpolates between the closest ranks like postgres' `percentile_cont`.
+        let percentile = |p: f64| {
+            let rank = p * (samples.len() - 1) as f64;
+            let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+            samples[lower] + (samples[upper] - samples[lower]) * (rank - lower as f64)
+        };
+        Some(Self {
+            low: percentile(0.1),
+            median: percentile(0.5),
+            high: percentile(0.9),
+        })
+    }
+}
+
+impl FeeRecommendation {
+    /// Summarizes the fee and, if the order was quoted, the slippage of every trade.
+    pub fn from_trades(trades: impl IntoIterator<Item = (f64, Option<f64>)>) -> Self {
+        let (fees, slippages): (Vec<_>, Vec<_>) = trades.into_iter().unzip();
+        Self {
+            sample_size: fees.len() as u64,
+            fee_bps: BpsRange::from_samples(fees),
+            slippage_bps: BpsRange::from_samples(slippages.into_iter().flatten().collect()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn percentiles() {
+        assert_eq!(BpsRange::from_samples(Vec::new()), None);
+        assert_eq!(
+            BpsRange::from_samples(vec![5.]),
+            Some(BpsRange {
+                low: 5.,
+                median: 5.,
+                high: 5.
+            })
+        );
+        let samples = (0..=100).rev().map(f64::from).collect();
+        assert_eq!(
+            BpsRange::from_samples(samples),
+            Some(BpsRange {
+                low: 10.,
+                median: 50.,
+                high: 90.
+            })
+        );
+        assert_eq!(
+            BpsRange::from_samples(vec![0., 10.]),
+            Some(BpsRange {
+                low: 1.,
+                median: 5.,
+                high: 9.
+            })
+        );
+    }
+
+    #[test]
+    fn serialization() {
+        let recommendation =
+            FeeRecommendation::from_trades([(10., Some(-10.)), (20., None), (30., Some(10.))]);
+        let json = json!({
+            "sampleSize": 3,
+            "feeBps": { "low": 12., "median": 20., "high": 28. },
+            "slippageBps": { "low": -8., "median": 0., "high": 8. },
+        });
+        assert_eq!(serde_json::to_value(&recommendation).unwrap(), json);
+    }
+}
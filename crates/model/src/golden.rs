@@ -0,0 +1,309 @@
+//! Golden file tests for the JSON serialization of the models that cross crate and service
+//! boundaries.
+//!
+//! Every model is compared against a canonical JSON fixture in the `golden` directory of this
+//! crate so that any change to the serialization format, intended or not, shows up as a diff of
+//! the fixtures. Run the tests with the `regenerate-golden-files` feature to rewrite the fixtures
+//! from the current serialization:
+//!
+//! ```text
+//! cargo test -p model --features regenerate-golden-files golden
+//! ```
+
+use crate::{
+    app_id::AppId,
+    auction::{Auction, AuctionWithId},
+    order::{
+        BuyTokenDestination, Order, OrderClass, OrderCreation, OrderData, OrderKind, OrderMetadata,
+        OrderStatus, OrderUid, QuoteFirmness, SellTokenSource,
+    },
+    quote::{
+        CostOfImmediacy, OrderQuote, OrderQuoteRequest, OrderQuoteResponse, OrderQuoteSide,
+        PriceQuality, QuoteSigningScheme, SellAmount, ThreatWarning, Validity,
+    },
+    signature::{EcdsaSignature, EcdsaSigningScheme, Signature},
+    solver_competition::{
+        self, CompetitionAuction, CompetitionResult, Objective, SolverCompetition, SolverSettlement,
+    },
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use maplit::btreemap;
+use num::{BigInt, BigUint};
+use primitive_types::{H160, H256, U256};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{fmt::Debug, fs, path::PathBuf};
+
+fn path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("golden")
+        .join(format!("{name}.json"))
+}
+
+/// Asserts that the value serializes to the golden file and returns the file's content.
+fn assert_serializes<T: Serialize>(name: &str, value: &T) -> Value {
+    let path = path(name);
+    let serialized = serde_json::to_value(value).unwrap();
+    if cfg!(feature = "regenerate-golden-files") {
+        let mut json = serde_json::to_string_pretty(&serialized).unwrap();
+        json.push('\n');
+        fs::write(&path, json).unwrap();
+    }
+
+    let golden = fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("failed to read {}: {err}", path.display()));
+    let golden: Value = serde_json::from_str(&golden).unwrap();
+    assert_eq!(
+        serialized,
+        golden,
+        "serialization doesn't match {}",
+        path.display()
+    );
+    golden
+}
+
+/// Like `assert_serializes` but also asserts that the golden file deserializes to the value.
+fn assert_roundtrips<T>(name: &str, value: &T)
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let golden = assert_serializes(name, value);
+    assert_eq!(&serde_json::from_value::<T>(golden).unwrap(), value);
+}
+
+fn timestamp(seconds: i64) -> DateTime<Utc> {
+    DateTime::from_utc(NaiveDateTime::from_timestamp(seconds, 0), Utc)
+}
+
+fn order_data() -> OrderData {
+    OrderData {
+        sell_token: H160([0x11; 20]),
+        buy_token: H160([0x22; 20]),
+        receiver: Some(H160([0x33; 20])),
+        sell_amount: U256::MAX,
+        buy_amount: 1.into(),
+        valid_to: u32::MAX,
+        app_data: AppId([0x44; 32]),
+        fee_amount: 0.into(),
+        kind: OrderKind::Sell,
+        partially_fillable: true,
+        sell_token_balance: SellTokenSource::External,
+        buy_token_balance: BuyTokenDestination::Internal,
+    }
+}
+
+fn order() -> Order {
+    Order {
+        metadata: OrderMetadata {
+            creation_date: timestamp(1_660_000_000),
+            owner: H160([0x55; 20]),
+            uid: OrderUid([0x66; 56]),
+            available_balance: Some(U256::MAX),
+            // Executed amounts aren't bounded by U256 because they are sums of trades.
+            executed_buy_amount: BigUint::from(2u8).pow(256),
+            executed_sell_amount: 0u8.into(),
+            executed_sell_amount_before_fees: U256::MAX,
+            executed_fee_amount: 1.into(),
+            invalidated: false,
+            status: OrderStatus::PresignaturePending,
+            settlement_contract: H160([0x77; 20]),
+            full_fee_amount: 2.into(),
+            protocol_fee_amount: 1.into(),
+            class: OrderClass::Liquidity,
+            quote_firmness: Some(QuoteFirmness {
+                expiry_block: Some(u64::MAX),
+                last_look: true,
+            }),
+            min_partial_fill_amount: None,
+            private_submission: false,
+            condition: None,
+        },
+        data: order_data(),
+        signature: EcdsaSignature {
+            r: H256([0x88; 32]),
+            s: H256([0x99; 32]),
+            v: 27,
+        }
+        .to_signature(EcdsaSigningScheme::EthSign),
+    }
+}
+
+#[test]
+fn order_golden() {
+    assert_roundtrips("order", &order());
+}
+
+#[test]
+fn order_creation_golden() {
+    assert_roundtrips(
+        "order_creation",
+        &OrderCreation {
+            data: OrderData {
+                receiver: None,
+                ..order_data()
+            },
+            from: Some(H160([0x55; 20])),
+            signature: Signature::Eip1271(vec![0xde, 0xad, 0xbe, 0xef]),
+            quote_id: None,
+            quote_firmness: None,
+            min_partial_fill_amount: None,
+            class: Some(OrderClass::Twap),
+            private_submission: false,
+            condition: None,
+        },
+    );
+}
+
+#[test]
+fn order_quote_request_golden() {
+    assert_roundtrips(
+        "order_quote_request",
+        &OrderQuoteRequest {
+            from: H160([0x55; 20]),
+            sell_token: H160([0x11; 20]),
+            buy_token: H160([0x22; 20]),
+            receiver: None,
+            side: OrderQuoteSide::Sell {
+                sell_amount: SellAmount::BeforeFee { value: U256::MAX },
+            },
+            validity: Validity::To(u32::MAX),
+            app_data: AppId([0x44; 32]),
+            partially_fillable: false,
+            sell_token_balance: SellTokenSource::Erc20,
+            buy_token_balance: BuyTokenDestination::Erc20,
+            signing_scheme: QuoteSigningScheme::PreSign {
+                onchain_order: true,
+            },
+            price_quality: PriceQuality::Fast,
+        },
+    );
+}
+
+#[test]
+fn order_quote_response_golden() {
+    // Quote responses only get serialized.
+    assert_serializes(
+        "order_quote_response",
+        &OrderQuoteResponse {
+            quote: OrderQuote {
+                sell_token: H160([0x11; 20]),
+                buy_token: H160([0x22; 20]),
+                receiver: None,
+                sell_amount: U256::MAX,
+                buy_amount: 1.into(),
+                valid_to: 0,
+                app_data: AppId([0x44; 32]),
+                fee_amount: 3.into(),
+                kind: OrderKind::Buy,
+                partially_fillable: false,
+                sell_token_balance: SellTokenSource::Internal,
+                buy_token_balance: BuyTokenDestination::Erc20,
+            },
+            from: H160([0x55; 20]),
+            expiration: timestamp(1_660_000_000),
+            guaranteed_until: Some(timestamp(1_660_000_300)),
+            id: Some(i64::MAX),
+            threat_warnings: vec![
+                ThreatWarning::DenyListedToken(H160([0x11; 20])),
+                ThreatWarning::MaliciousReceiver(H160([0x33; 20])),
+            ],
+            cost_of_immediacy: Some(CostOfImmediacy {
+                limit_amount: U256::MAX,
+                improvement_bps: 25,
+                expected_fill_time: 3600,
+            }),
+        },
+    );
+}
+
+#[test]
+fn auction_golden() {
+    assert_roundtrips(
+        "auction",
+        &AuctionWithId {
+            id: 42,
+            auction: Auction {
+                block: 15_000_000,
+                latest_settlement_block: 14_999_999,
+                orders: vec![order()],
+                prices: btreemap! {
+                    H160([0x11; 20]) => U256::MAX,
+                    H160([0x22; 20]) => 0.into(),
+                },
+                estimated_prices: Default::default(),
+                clusters: vec![],
+                order_selection_seed: None,
+            },
+        },
+    );
+}
+
+fn competition() -> SolverCompetition {
+    SolverCompetition {
+        auction_id: 42,
+        gas_price: 1.5,
+        auction_start_block: 15_000_000,
+        liquidity_collected_block: 15_000_001,
+        competition_simulation_block: 15_000_002,
+        transaction_hash: None,
+        auction: CompetitionAuction {
+            orders: vec![OrderUid([0x66; 56])],
+            prices: btreemap! {
+                H160([0x11; 20]) => U256::MAX,
+            },
+            amendments: vec![],
+        },
+        order_selection_seed: Some(u64::MAX),
+        solutions: vec![SolverSettlement {
+            solver: "Sölver 🐮 ソルバー".to_string(),
+            objective: Objective {
+                total: 0.25,
+                surplus: 1234.5,
+                fees: -0.5,
+                cost: 0.0,
+                gas: u64::MAX,
+            },
+            clearing_prices: btreemap! {
+                H160([0x11; 20]) => U256::MAX,
+                H160([0x22; 20]) => 1.into(),
+            },
+            orders: vec![solver_competition::Order {
+                id: OrderUid([0x66; 56]),
+                executed_amount: U256::MAX,
+            }],
+            call_data: vec![0x13, 0x37],
+            auction_version: 0,
+            balance_diffs: btreemap! {
+                H160([0x11; 20]) => BigInt::from(-1),
+                H160([0x22; 20]) => BigInt::from(u128::MAX),
+            },
+            sub_auctions: vec![],
+        }],
+        mev_rebate: None,
+        excluded_solvers: vec![],
+        performance: None,
+        simulation_failures: vec![],
+        revert: None,
+    }
+}
+
+#[test]
+fn solver_competition_golden() {
+    assert_roundtrips("solver_competition", &competition());
+}
+
+#[test]
+fn competition_result_golden() {
+    assert_roundtrips(
+        "competition_result",
+        &CompetitionResult::from(&competition()),
+    );
+}
+
+#[test]
+fn rejects_unicode_app_data() {
+    // App data is the hash of the app data document so it can't contain arbitrary text.
+    let mut golden = assert_serializes("order", &order());
+    golden["appData"] = "🐮".into();
+    assert!(serde_json::from_value::<Order>(golden).is_err());
+}
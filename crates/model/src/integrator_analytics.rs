@@ -0,0 +1,74 @@
+//! Contains the quote funnel of integrators served by the `integrator_analytics` endpoint.
+
+use crate::app_id::AppId;
+use serde::{Deserialize, Serialize};
+
+/// How the quotes requested with one app data converted into orders and fills. Integrators are
+/// identified by the app data they set on their quotes and orders.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegratorFunnel {
+    pub app_data: AppId,
+    pub quote_requests: u64,
+    /// The quote requests that got a quote.
+    pub quotes: u64,
+    pub orders: u64,
+    /// The orders that were at least partially filled.
+    pub filled_orders: u64,
+    /// Orders per quote. `None` if there were no quotes.
+    pub order_rate: Option<f64>,
+    /// Filled orders per order. `None` if there were no orders.
+    pub fill_rate: Option<f64>,
+}
+
+impl IntegratorFunnel {
+    pub fn new(
+        app_data: AppId,
+        quote_requests: u64,
+        quotes: u64,
+        orders: u64,
+        filled_orders: u64,
+    ) -> Self {
+        let rate = |numerator: u64, denominator: u64| {
+            (denominator > 0).then(|| numerator as f64 / denominator as f64)
+        };
+        Self {
+            app_data,
+            quote_requests,
+            quotes,
+            orders,
+            filled_orders,
+            order_rate: rate(orders, quotes),
+            fill_rate: rate(filled_orders, orders),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serialization() {
+        let funnel = IntegratorFunnel::new(AppId([1; 32]), 10, 8, 2, 1);
+        let json = json!({
+            "appData": "0x0101010101010101010101010101010101010101010101010101010101010101",
+            "quoteRequests": 10,
+            "quotes": 8,
+            "orders": 2,
+            "filledOrders": 1,
+            "orderRate": 0.25,
+            "fillRate": 0.5,
+        });
+        assert_eq!(serde_json::to_value(&funnel).unwrap(), json);
+        assert_eq!(
+            serde_json::from_value::<IntegratorFunnel>(json).unwrap(),
+            funnel
+        );
+
+        let funnel = IntegratorFunnel::new(AppId([1; 32]), 1, 0, 0, 0);
+        assert_eq!(funnel.order_rate, None);
+        assert_eq!(funnel.fill_rate, None);
+    }
+}
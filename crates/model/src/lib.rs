@@ -1,17 +1,32 @@
 //! Contains models that are shared between the orderbook and the solver.
 
+pub mod api_key;
 pub mod app_id;
 pub mod auction;
+pub mod buffers;
 pub mod bytes_hex;
+pub mod fee_policy;
+pub mod fee_recommendation;
+pub mod integrator_analytics;
 pub mod order;
+pub mod order_book_stats;
+pub mod protocol_fees;
 pub mod quote;
+pub mod rate_limit;
 pub mod ratio_as_decimal;
+pub mod sealed_order;
 pub mod signature;
 pub mod solver_competition;
+pub mod solver_rewards;
+pub mod solver_team;
+pub mod stuck_orders;
 pub mod time;
 pub mod trade;
 pub mod u256_decimal;
 
+#[cfg(test)]
+mod golden;
+
 use ethabi::{encode, Token};
 use hex::{FromHex, FromHexError};
 use lazy_static::lazy_static;
@@ -110,14 +125,20 @@ impl std::fmt::Debug for DomainSeparator {
     }
 }
 
+/// The EIP-712 domain name of the settlement contract.
+pub const DOMAIN_NAME: &str = "Gnosis Protocol";
+
+/// The EIP-712 domain version of the settlement contract.
+pub const DOMAIN_VERSION: &str = "v2";
+
 impl DomainSeparator {
     pub fn new(chain_id: u64, contract_address: H160) -> Self {
         lazy_static! {
-            /// The EIP-712 domain name used for computing the domain separator.
-            static ref DOMAIN_NAME: [u8; 32] = signing::keccak256(b"Gnosis Protocol");
+            /// The hashed EIP-712 domain name used for computing the domain separator.
+            static ref DOMAIN_NAME_HASH: [u8; 32] = signing::keccak256(DOMAIN_NAME.as_bytes());
 
-            /// The EIP-712 domain version used for computing the domain separator.
-            static ref DOMAIN_VERSION: [u8; 32] = signing::keccak256(b"v2");
+            /// The hashed EIP-712 domain version used for computing the domain separator.
+            static ref DOMAIN_VERSION_HASH: [u8; 32] = signing::keccak256(DOMAIN_VERSION.as_bytes());
 
             /// The EIP-712 domain type used computing the domain separator.
             static ref DOMAIN_TYPE_HASH: [u8; 32] = signing::keccak256(
@@ -126,8 +147,8 @@ impl DomainSeparator {
         }
         let abi_encode_string = encode(&[
             Token::Uint((*DOMAIN_TYPE_HASH).into()),
-            Token::Uint((*DOMAIN_NAME).into()),
-            Token::Uint((*DOMAIN_VERSION).into()),
+            Token::Uint((*DOMAIN_NAME_HASH).into()),
+            Token::Uint((*DOMAIN_VERSION_HASH).into()),
             Token::Uint(chain_id.into()),
             Token::Address(contract_address),
         ]);
@@ -136,6 +157,21 @@ impl DomainSeparator {
     }
 }
 
+/// The parts of the EIP-712 domain that differ between deployments of the settlement contract.
+/// Orders signed for one deployment can't be placed for another.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DomainParameters {
+    pub chain_id: u64,
+    pub verifying_contract: H160,
+}
+
+impl DomainParameters {
+    pub fn separator(&self) -> DomainSeparator {
+        DomainSeparator::new(self.chain_id, self.verifying_contract)
+    }
+}
+
 #[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SolvableOrders {
@@ -2,8 +2,9 @@
 
 use crate::{
     app_id::AppId,
+    bytes_hex,
     quote::QuoteId,
-    signature::{EcdsaSignature, EcdsaSigningScheme, Signature, VerificationError},
+    signature::{EcdsaSignature, EcdsaSigningScheme, Signature, SigningScheme, VerificationError},
     u256_decimal::{self, DecimalU256},
     DomainSeparator, TokenPair,
 };
@@ -57,7 +58,7 @@ impl Order {
         domain: &DomainSeparator,
         settlement_contract: H160,
         full_fee_amount: U256,
-        is_liquidity_order: bool,
+        class: OrderClass,
     ) -> Result<Self, VerificationError> {
         let owner = order.verify_owner(domain)?;
         Ok(Self {
@@ -67,7 +68,11 @@ impl Order {
                 uid: order.data.uid(domain, &owner),
                 settlement_contract,
                 full_fee_amount,
-                is_liquidity_order,
+                class,
+                quote_firmness: order.quote_firmness,
+                min_partial_fill_amount: order.min_partial_fill_amount,
+                private_submission: order.private_submission,
+                condition: order.condition.clone(),
                 ..Default::default()
             },
             signature: order.signature.clone(),
@@ -137,6 +142,11 @@ impl OrderBuilder {
         self
     }
 
+    pub fn with_protocol_fee_amount(mut self, protocol_fee_amount: U256) -> Self {
+        self.0.metadata.protocol_fee_amount = protocol_fee_amount;
+        self
+    }
+
     pub fn with_kind(mut self, kind: OrderKind) -> Self {
         self.0.data.kind = kind;
         self
@@ -147,6 +157,21 @@ impl OrderBuilder {
         self
     }
 
+    pub fn with_min_partial_fill_amount(mut self, min_partial_fill_amount: U256) -> Self {
+        self.0.metadata.min_partial_fill_amount = Some(min_partial_fill_amount);
+        self
+    }
+
+    pub fn with_private_submission(mut self, private_submission: bool) -> Self {
+        self.0.metadata.private_submission = private_submission;
+        self
+    }
+
+    pub fn with_condition(mut self, condition: OrderCondition) -> Self {
+        self.0.metadata.condition = Some(condition);
+        self
+    }
+
     pub fn with_sell_token_balance(mut self, balance: SellTokenSource) -> Self {
         self.0.data.sell_token_balance = balance;
         self
@@ -290,6 +315,7 @@ impl OrderData {
 }
 
 // An order as provided to the orderbook by the frontend.
+#[serde_as]
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OrderCreation {
@@ -299,6 +325,268 @@ pub struct OrderCreation {
     #[serde(flatten)]
     pub signature: Signature,
     pub quote_id: Option<QuoteId>,
+    /// Only market makers can attach this to their liquidity orders.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quote_firmness: Option<QuoteFirmness>,
+    /// The smallest amount a partially fillable order may get filled by in a single trade, see
+    /// [`OrderMetadata::min_partial_fill_amount`].
+    #[serde_as(as = "Option<DecimalU256>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_partial_fill_amount: Option<U256>,
+    /// The class the order should be placed as. Orders of market makers are always liquidity
+    /// orders and orders without a class are market orders.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<OrderClass>,
+    /// See [`OrderMetadata::private_submission`].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub private_submission: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<OrderCondition>,
+}
+
+/// A prospective order that gets validated without placing it, so it doesn't need a signature.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderValidationRequest {
+    #[serde(flatten)]
+    pub data: OrderData,
+    pub from: H160,
+    /// The scheme the order would be signed with.
+    #[serde(default)]
+    pub signing_scheme: SigningScheme,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<OrderClass>,
+}
+
+/// Whether placing a prospective order would pass validation.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderValidationResult {
+    pub valid: bool,
+    /// The error placing the order would fail with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<OrderValidationError>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderValidationError {
+    pub error_type: String,
+    pub description: String,
+}
+
+/// The response to placing an order that asked for an estimate of its auction inclusion.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderPlacement {
+    pub uid: OrderUid,
+    /// `None` if the inclusion couldn't be estimated. The order got placed regardless.
+    pub auction_inclusion: Option<AuctionInclusionEstimate>,
+}
+
+/// When a newly placed order is expected to enter an auction.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuctionInclusionEstimate {
+    /// When the next auction is expected to be created. `None` if auctions are currently not
+    /// being created.
+    pub next_auction: Option<DateTime<Utc>>,
+    /// Whether the order is expected to be part of the next auction.
+    pub in_next_auction: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay_reason: Option<InclusionDelayReason>,
+}
+
+/// Why an order is not expected to be part of the next auction.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InclusionDelayReason {
+    /// The most recent auction is out of date so no auctions are being created at the moment.
+    NoRecentAuction,
+    /// Pre-sign orders only get included once they have been signed on chain.
+    PresignaturePending,
+}
+
+/// The class of an order decides which rules it gets validated with, which fee it has to pay and
+/// when it gets included in auctions.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderClass {
+    /// A user order that is expected to get filled at the current market price.
+    #[default]
+    Market,
+    /// A user order priced outside of the market that waits for the market to reach its limit
+    /// price.
+    Limit,
+    /// An order of a market maker that only provides liquidity for settling other orders.
+    Liquidity,
+    /// A part of a user order that trades its amount over time at the time-weighted average
+    /// price. The parts of an order get included in auctions one after the other and, unlike
+    /// limit orders, are expected to get filled once they are.
+    Twap,
+}
+
+impl OrderClass {
+    /// Limit and liquidity orders don't have to be settled right away so solvers can use them
+    /// like any other liquidity. Their surplus doesn't count towards the objective. This is the
+    /// only place that decides which orders are liquidity orders.
+    pub fn is_liquidity_order(self) -> bool {
+        matches!(self, Self::Limit | Self::Liquidity)
+    }
+}
+
+/// Metadata of the quote a market maker's liquidity order is based on. Liquidity orders often
+/// encode quotes that are only good for a short time or that the market maker can still reject.
+/// Autopilot drops orders whose quote has expired from auctions and solvers get the metadata to
+/// take the risk of a failed fill into account.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuoteFirmness {
+    /// The last block in which the quote can be filled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiry_block: Option<u64>,
+    /// Whether the market maker can still reject the fill, for example through a hook that
+    /// checks the price at execution time.
+    #[serde(default)]
+    pub last_look: bool,
+}
+
+impl QuoteFirmness {
+    /// Whether the quote can't be filled in the specified block anymore.
+    pub fn is_expired(&self, block: u64) -> bool {
+        matches!(self.expiry_block, Some(expiry_block) if expiry_block < block)
+    }
+}
+
+/// An on-chain condition that has to hold for an order to be included in an auction. This allows
+/// for example stop-loss orders that only become solvable once a price oracle crosses a
+/// threshold. Autopilot evaluates the conditions of all conditional orders when cutting an
+/// auction.
+#[serde_as]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum OrderCondition {
+    /// The first 32 bytes returned by calling `target` with `calldata`, interpreted as a
+    /// `uint256`, compared to the threshold. For example the `latestAnswer()` of a Chainlink
+    /// price feed.
+    #[serde(rename_all = "camelCase")]
+    Oracle {
+        target: H160,
+        #[serde(with = "bytes_hex")]
+        calldata: Vec<u8>,
+        comparison: ThresholdComparison,
+        #[serde_as(as = "DecimalU256")]
+        threshold: U256,
+    },
+    /// The timestamp of the block the auction is cut at lies within the window. Both bounds are
+    /// inclusive.
+    #[serde(rename_all = "camelCase")]
+    TimeWindow {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        not_before: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        not_after: Option<u64>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ThresholdComparison {
+    /// The value has to be greater than or equal to the threshold.
+    AtLeast,
+    /// The value has to be less than or equal to the threshold.
+    AtMost,
+}
+
+impl OrderCondition {
+    /// Whether the condition can hold at all. Time windows need a bound and must not be empty.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Self::Oracle { calldata, .. } => !calldata.is_empty(),
+            Self::TimeWindow {
+                not_before,
+                not_after,
+            } => match (not_before, not_after) {
+                (None, None) => false,
+                (Some(not_before), Some(not_after)) => not_before <= not_after,
+                _ => true,
+            },
+        }
+    }
+
+    /// Whether the condition holds at a block with the specified timestamp. `oracle_output` is
+    /// what calling the oracle of an oracle condition returned at that block, `None` if the call
+    /// failed.
+    pub fn holds(&self, timestamp: u64, oracle_output: Option<&[u8]>) -> bool {
+        match self {
+            Self::Oracle {
+                comparison,
+                threshold,
+                ..
+            } => {
+                let value = match oracle_output {
+                    Some(output) if output.len() >= 32 => U256::from_big_endian(&output[..32]),
+                    _ => return false,
+                };
+                match comparison {
+                    ThresholdComparison::AtLeast => value >= *threshold,
+                    ThresholdComparison::AtMost => value <= *threshold,
+                }
+            }
+            Self::TimeWindow {
+                not_before,
+                not_after,
+            } => {
+                not_before.map_or(true, |not_before| timestamp >= not_before)
+                    && not_after.map_or(true, |not_after| timestamp <= not_after)
+            }
+        }
+    }
+}
+
+/// Describes why the owner can't fund an order that got rejected for an insufficient balance or
+/// allowance and how to fix it.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InsufficientFunds {
+    pub sell_token: H160,
+    /// The sell amount plus fee the owner has to be able to transfer.
+    #[serde(with = "u256_decimal")]
+    pub required: U256,
+    /// The owner's balance or allowance, whichever is insufficient.
+    #[serde(with = "u256_decimal")]
+    pub available: U256,
+    #[serde(with = "u256_decimal")]
+    pub shortfall: U256,
+    /// The contract the owner has to approve to spend the sell token. Only set for insufficient
+    /// allowances.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval_target: Option<H160>,
+    pub suggestion: FundingSuggestion,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum FundingSuggestion {
+    /// Approve the approval target to spend at least the required amount.
+    Approve,
+    /// Place the order with lower amounts at the same limit price that the balance covers.
+    #[serde(rename_all = "camelCase")]
+    LowerAmounts {
+        #[serde(with = "u256_decimal")]
+        sell_amount: U256,
+        #[serde(with = "u256_decimal")]
+        buy_amount: U256,
+    },
+    /// The balance doesn't even cover the fee so the owner has to add funds.
+    AddFunds,
+    /// Wrap this much of the native token, e.g. ETH, by depositing it into the sell token, which
+    /// is the wrapped native token. Settlements can only transfer the wrapped token.
+    #[serde(rename_all = "camelCase")]
+    WrapNativeToken {
+        #[serde(with = "u256_decimal")]
+        amount: U256,
+    },
 }
 
 impl OrderCreation {
@@ -325,6 +613,11 @@ impl Default for OrderCreation {
             from: None,
             signature: Signature::Eip712(EcdsaSignature::non_zero()),
             quote_id: None,
+            quote_firmness: None,
+            min_partial_fill_amount: None,
+            class: None,
+            private_submission: false,
+            condition: None,
         }
     }
 }
@@ -336,17 +629,23 @@ impl From<Order> for OrderCreation {
             from: Some(order.metadata.owner),
             signature: order.signature,
             quote_id: None,
+            quote_firmness: order.metadata.quote_firmness,
+            min_partial_fill_amount: order.metadata.min_partial_fill_amount,
+            // The class of an order can differ from the requested one, for example when a market
+            // order is priced outside of the market.
+            class: None,
+            private_submission: order.metadata.private_submission,
+            condition: order.metadata.condition,
         }
     }
 }
 
-/// An order cancellation as provided to the orderbook by the frontend.
-#[serde_as]
-#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+/// An order cancellation as provided to the orderbook by the frontend. Smart contract wallets
+/// sign cancellations with EIP-1271 signatures.
+#[derive(Eq, PartialEq, Clone, Debug)]
 pub struct OrderCancellation {
     pub order_uid: OrderUid,
-    pub signature: EcdsaSignature,
-    pub signing_scheme: EcdsaSigningScheme,
+    pub signature: Signature,
 }
 
 impl Default for OrderCancellation {
@@ -354,14 +653,14 @@ impl Default for OrderCancellation {
         let mut result = Self {
             order_uid: OrderUid::default(),
             signature: Default::default(),
-            signing_scheme: EcdsaSigningScheme::Eip712,
         };
         result.signature = EcdsaSignature::sign(
-            result.signing_scheme,
+            EcdsaSigningScheme::Eip712,
             &DomainSeparator::default(),
             &result.hash_struct(),
             SecretKeyRef::new(&ONE_KEY),
-        );
+        )
+        .to_signature(EcdsaSigningScheme::Eip712);
         result
     }
 }
@@ -379,9 +678,11 @@ impl OrderCancellation {
         signing::keccak256(&hash_data)
     }
 
-    pub fn validate(&self, domain_separator: &DomainSeparator) -> Result<H160> {
+    /// Recovers the signer of ECDSA signatures. Returns `None` for signatures that have to be
+    /// verified by the owner contract.
+    pub fn validate(&self, domain_separator: &DomainSeparator) -> Result<Option<H160>> {
         self.signature
-            .recover(self.signing_scheme, domain_separator, &self.hash_struct())
+            .recover(domain_separator, &self.hash_struct())
     }
 }
 
@@ -411,7 +712,26 @@ pub struct OrderMetadata {
     pub settlement_contract: H160,
     #[serde(default, with = "u256_decimal")]
     pub full_fee_amount: U256,
-    pub is_liquidity_order: bool,
+    /// The part of the signed fee that is owed to the protocol instead of the solver.
+    #[serde(default, with = "u256_decimal")]
+    pub protocol_fee_amount: U256,
+    #[serde(default)]
+    pub class: OrderClass,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quote_firmness: Option<QuoteFirmness>,
+    /// The smallest amount a single trade of a partially fillable order may fill, in sell token
+    /// for sell orders and in buy token for buy orders. Trades that fill the whole remaining
+    /// amount of the order are allowed to be smaller so that orders can get filled completely.
+    #[serde_as(as = "Option<DecimalU256>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_partial_fill_amount: Option<U256>,
+    /// Settlements containing the order may only be submitted through private relays so that the
+    /// order can't be front-run in the public mempool.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub private_submission: bool,
+    /// The order is only included in auctions while the condition holds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<OrderCondition>,
 }
 
 impl Default for OrderMetadata {
@@ -429,7 +749,12 @@ impl Default for OrderMetadata {
             status: OrderStatus::Open,
             settlement_contract: H160::default(),
             full_fee_amount: U256::default(),
-            is_liquidity_order: false,
+            protocol_fee_amount: U256::default(),
+            class: Default::default(),
+            quote_firmness: None,
+            min_partial_fill_amount: None,
+            private_submission: false,
+            condition: None,
         }
     }
 }
@@ -611,7 +936,7 @@ pub fn debug_biguint_to_string(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::signature::{EcdsaSigningScheme, SigningScheme};
+    use crate::signature::EcdsaSigningScheme;
     use chrono::NaiveDateTime;
     use hex_literal::hex;
     use maplit::hashset;
@@ -642,6 +967,7 @@ mod tests {
             "appData": "0x6000000000000000000000000000000000000000000000000000000000000007",
             "feeAmount": "115792089237316195423570985008687907853269984665640564039457584007913129639935",
             "fullFeeAmount": "115792089237316195423570985008687907853269984665640564039457584007913129639935",
+            "protocolFeeAmount": "6",
             "kind": "buy",
             "partiallyFillable": false,
             "signature": "0x0200000000000000000000000000000000000000000000000000000000000003040000000000000000000000000000000000000000000000000000000000000501",
@@ -650,7 +976,7 @@ mod tests {
             "settlementContract": "0x0000000000000000000000000000000000000002",
             "sellTokenBalance": "external",
             "buyTokenBalance": "internal",
-            "isLiquidityOrder": false,
+            "class": "market",
         });
         let signing_scheme = EcdsaSigningScheme::Eip712;
         let expected = Order {
@@ -667,7 +993,12 @@ mod tests {
                 status: OrderStatus::Open,
                 settlement_contract: H160::from_low_u64_be(2),
                 full_fee_amount: U256::MAX,
-                is_liquidity_order: false,
+                protocol_fee_amount: 6.into(),
+                class: OrderClass::Market,
+                quote_firmness: None,
+                min_partial_fill_amount: None,
+                private_submission: false,
+                condition: None,
             },
             data: OrderData {
                 sell_token: H160::from_low_u64_be(10),
@@ -704,6 +1035,27 @@ mod tests {
         assert_eq!(serialized, value);
     }
 
+    #[test]
+    fn order_validation_request_defaults() {
+        let request: OrderValidationRequest = serde_json::from_value(json!({
+            "sellToken": "0x1111111111111111111111111111111111111111",
+            "buyToken": "0x2222222222222222222222222222222222222222",
+            "sellAmount": "123",
+            "buyAmount": "456",
+            "validTo": 1337,
+            "appData": "0x4444444444444444444444444444444444444444444444444444444444444444",
+            "feeAmount": "789",
+            "kind": "sell",
+            "partiallyFillable": false,
+            "from": "0x3333333333333333333333333333333333333333",
+        }))
+        .unwrap();
+        assert_eq!(request.from, H160([0x33; 20]));
+        assert_eq!(request.data.sell_amount, 123.into());
+        assert_eq!(request.signing_scheme, SigningScheme::Eip712);
+        assert_eq!(request.class, None);
+    }
+
     #[test]
     fn order_creation_serialization() {
         let owner = H160([0xff; 20]);
@@ -744,6 +1096,11 @@ mod tests {
                 from,
                 signature,
                 quote_id: Some(42),
+                quote_firmness: None,
+                min_partial_fill_amount: None,
+                class: None,
+                private_submission: false,
+                condition: None,
             };
             let order_json = json!({
                 "sellToken": "0x1111111111111111111111111111111111111111",
@@ -769,6 +1126,132 @@ mod tests {
         }
     }
 
+    #[test]
+    fn quote_firmness_serialization() {
+        let order: OrderCreation = serde_json::from_value(json!({
+            "sellToken": "0x1111111111111111111111111111111111111111",
+            "buyToken": "0x2222222222222222222222222222222222222222",
+            "sellAmount": "123",
+            "buyAmount": "456",
+            "validTo": 1337,
+            "appData": "0x4444444444444444444444444444444444444444444444444444444444444444",
+            "feeAmount": "0",
+            "kind": "sell",
+            "partiallyFillable": true,
+            "signingScheme": "presign",
+            "signature": "0x",
+            "from": "0xffffffffffffffffffffffffffffffffffffffff",
+            "quoteFirmness": {
+                "expiryBlock": 100,
+            },
+        }))
+        .unwrap();
+        let firmness = order.quote_firmness.unwrap();
+        assert_eq!(
+            firmness,
+            QuoteFirmness {
+                expiry_block: Some(100),
+                last_look: false,
+            }
+        );
+        assert!(!firmness.is_expired(100));
+        assert!(firmness.is_expired(101));
+        assert!(!QuoteFirmness::default().is_expired(u64::MAX));
+        assert_eq!(
+            json!(firmness),
+            json!({ "expiryBlock": 100, "lastLook": false })
+        );
+    }
+
+    #[test]
+    fn min_partial_fill_amount_serialization() {
+        let order = OrderCreation {
+            min_partial_fill_amount: Some(1000.into()),
+            ..Default::default()
+        };
+        let json = json!(order);
+        assert_eq!(json["minPartialFillAmount"], "1000");
+        assert_eq!(order, serde_json::from_value(json).unwrap());
+        assert!(json!(OrderCreation::default())
+            .get("minPartialFillAmount")
+            .is_none());
+    }
+
+    #[test]
+    fn private_submission_serialization() {
+        let order = OrderCreation {
+            private_submission: true,
+            ..Default::default()
+        };
+        let json = json!(order);
+        assert_eq!(json["privateSubmission"], true);
+        assert_eq!(order, serde_json::from_value(json).unwrap());
+        assert!(json!(OrderCreation::default())
+            .get("privateSubmission")
+            .is_none());
+    }
+
+    #[test]
+    fn order_conditions() {
+        let order: OrderCreation = serde_json::from_value(json!({
+            "sellToken": "0x1111111111111111111111111111111111111111",
+            "buyToken": "0x2222222222222222222222222222222222222222",
+            "sellAmount": "123",
+            "buyAmount": "456",
+            "validTo": 1337,
+            "appData": "0x4444444444444444444444444444444444444444444444444444444444444444",
+            "feeAmount": "0",
+            "kind": "sell",
+            "partiallyFillable": false,
+            "signingScheme": "presign",
+            "signature": "0x",
+            "from": "0xffffffffffffffffffffffffffffffffffffffff",
+            "condition": {
+                "kind": "oracle",
+                "target": "0x5555555555555555555555555555555555555555",
+                "calldata": "0x50d25bcd",
+                "comparison": "atMost",
+                "threshold": "1000",
+            },
+        }))
+        .unwrap();
+        let condition = order.condition.unwrap();
+        assert!(condition.is_valid());
+        let output = |value: u64| {
+            let mut output = [0u8; 32];
+            U256::from(value).to_big_endian(&mut output);
+            output
+        };
+        assert!(condition.holds(0, Some(&output(999))));
+        assert!(condition.holds(0, Some(&output(1000))));
+        assert!(!condition.holds(0, Some(&output(1001))));
+        assert!(!condition.holds(0, Some(&[])));
+        assert!(!condition.holds(0, None));
+
+        let window: OrderCondition = serde_json::from_value(json!({
+            "kind": "timeWindow",
+            "notBefore": 10,
+        }))
+        .unwrap();
+        assert!(window.is_valid());
+        assert!(!window.holds(9, None));
+        assert!(window.holds(10, None));
+        assert_eq!(
+            json!(window),
+            json!({ "kind": "timeWindow", "notBefore": 10 })
+        );
+        assert!(!OrderCondition::TimeWindow {
+            not_before: None,
+            not_after: None,
+        }
+        .is_valid());
+        assert!(!OrderCondition::TimeWindow {
+            not_before: Some(2),
+            not_after: Some(1),
+        }
+        .is_valid());
+    }
+
     // from the test `should recover signing address for all supported ECDSA-based schemes` in
     // <https://github.com/cowprotocol/contracts/blob/v1.1.2/test/GPv2Signing.test.ts#L280>.
     #[test]
@@ -874,11 +1357,10 @@ mod tests {
         ] {
             let cancellation = OrderCancellation {
                 order_uid: OrderUid(hex!("2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a")),
-                signature: EcdsaSignature::from_bytes(signature),
-                signing_scheme: *signing_scheme,
+                signature: EcdsaSignature::from_bytes(signature).to_signature(*signing_scheme),
             };
             let owner = cancellation.validate(&domain_separator).unwrap();
-            assert_eq!(owner, expected_owner);
+            assert_eq!(owner, Some(expected_owner));
         }
     }
 
@@ -0,0 +1,50 @@
+//! Contains the rolling order book statistics served by the `stats` endpoint.
+
+use crate::u256_decimal;
+use chrono::{DateTime, Utc};
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+
+/// Aggregates over the settlements of the last 24 hours.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderBookStats {
+    pub settlement_count: u64,
+    pub trade_count: u64,
+    pub unique_traders: u64,
+    /// The traded sell volume in atoms of the native token.
+    #[serde(with = "u256_decimal")]
+    pub volume: U256,
+    /// When the statistics were last recomputed.
+    pub refreshed_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+    use serde_json::json;
+
+    #[test]
+    fn serialization() {
+        let stats = OrderBookStats {
+            settlement_count: 1,
+            trade_count: 2,
+            unique_traders: 3,
+            volume: 4.into(),
+            refreshed_at: DateTime::from_utc(NaiveDateTime::from_timestamp(5, 0), Utc),
+        };
+        let json = json!({
+            "settlementCount": 1,
+            "tradeCount": 2,
+            "uniqueTraders": 3,
+            "volume": "4",
+            "refreshedAt": "1970-01-01T00:00:05Z",
+        });
+        assert_eq!(serde_json::to_value(&stats).unwrap(), json);
+        assert_eq!(
+            serde_json::from_value::<OrderBookStats>(json).unwrap(),
+            stats
+        );
+    }
+}
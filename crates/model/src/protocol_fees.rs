@@ -0,0 +1,42 @@
+//! Contains the accrued protocol fees served by the `protocol_fees` endpoint.
+
+use crate::u256_decimal;
+use primitive_types::{H160, U256};
+use serde::{Deserialize, Serialize};
+
+/// The protocol fees that trades accrued in a token.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtocolFeeAccrual {
+    /// The token the fees were paid in, which is the sell token of the orders.
+    pub token: H160,
+    #[serde(with = "u256_decimal")]
+    pub amount: U256,
+    /// The number of trades that paid a protocol fee in the token.
+    pub trades: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serialization() {
+        let accrual = ProtocolFeeAccrual {
+            token: H160([1; 20]),
+            amount: 1_000.into(),
+            trades: 3,
+        };
+        let json = json!({
+            "token": "0x0101010101010101010101010101010101010101",
+            "amount": "1000",
+            "trades": 3,
+        });
+        assert_eq!(serde_json::to_value(&accrual).unwrap(), json);
+        assert_eq!(
+            serde_json::from_value::<ProtocolFeeAccrual>(json).unwrap(),
+            accrual
+        );
+    }
+}
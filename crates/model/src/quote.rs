@@ -4,7 +4,7 @@ use crate::{
     signature::SigningScheme,
     time, u256_decimal,
 };
-use anyhow::bail;
+use anyhow::anyhow;
 use chrono::{DateTime, Utc};
 use primitive_types::{H160, U256};
 use serde::{de, ser::SerializeStruct as _, Deserialize, Deserializer, Serialize, Serializer};
@@ -50,15 +50,10 @@ impl TryFrom<QuoteSigningDeserializationData> for QuoteSigningScheme {
     type Error = anyhow::Error;
 
     fn try_from(data: QuoteSigningDeserializationData) -> Result<Self, Self::Error> {
-        match (data.signing_scheme, data.onchain_order) {
-            (scheme, true) if scheme.is_ecdsa_scheme() => {
-                bail!("ECDSA-signed orders cannot be on-chain")
-            }
-            (SigningScheme::Eip712, _) => Ok(Self::Eip712),
-            (SigningScheme::EthSign, _) => Ok(Self::EthSign),
-            (SigningScheme::Eip1271, onchain_order) => Ok(Self::Eip1271 { onchain_order }),
-            (SigningScheme::PreSign, onchain_order) => Ok(Self::PreSign { onchain_order }),
-        }
+        data.signing_scheme
+            .implementation()
+            .quote_signing_scheme(data.onchain_order)
+            .ok_or_else(|| anyhow!("ECDSA-signed orders cannot be on-chain"))
     }
 }
 
@@ -219,7 +214,160 @@ pub struct OrderQuoteResponse {
     pub quote: OrderQuote,
     pub from: H160,
     pub expiration: DateTime<Utc>,
+    /// Until when an order with the quoted fee is accepted even if the quote expired and gas
+    /// prices rose. Only set for stored quotes if the orderbook guarantees fees.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guaranteed_until: Option<DateTime<Utc>>,
     pub id: Option<QuoteId>,
+    /// Matches of the order against threat intelligence lists. Wallets can use these to warn
+    /// users before they sign the order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub threat_warnings: Vec<ThreatWarning>,
+    /// What the user could gain by placing a limit order instead of trading at the quoted price
+    /// right away. `None` if it isn't estimated or there is nothing to gain.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_of_immediacy: Option<CostOfImmediacy>,
+}
+
+/// How much better the price of a limit order resting at the mid-price is than the quoted price
+/// and how long the order is expected to rest until it fills.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CostOfImmediacy {
+    /// The buy amount of sell orders or the sell amount of buy orders at the mid-price.
+    #[serde(with = "u256_decimal")]
+    pub limit_amount: U256,
+    /// How much better the mid-price is than the quoted price in basis points.
+    pub improvement_bps: u32,
+    /// The expected time in seconds until the limit order fills.
+    pub expected_fill_time: u64,
+}
+
+/// An address of an order that is on a threat intelligence list.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(tag = "kind", content = "address", rename_all = "camelCase")]
+pub enum ThreatWarning {
+    /// The sell or buy token is on a token deny list.
+    DenyListedToken(H160),
+    /// The receiver is a known malicious address, for example of a phishing campaign.
+    MaliciousReceiver(H160),
+}
+
+/// A basket of orders to quote together, for example to rebalance a portfolio. Every leg trades
+/// its token against the common quote token.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BasketQuoteRequest {
+    pub from: H160,
+    /// The token that sell legs are sold for and that buy legs are bought with.
+    pub quote_token: H160,
+    pub legs: Vec<BasketLeg>,
+    #[serde(flatten)]
+    pub validity: Validity,
+    #[serde(default)]
+    pub app_data: AppId,
+    #[serde(default)]
+    pub sell_token_balance: SellTokenSource,
+    #[serde(default)]
+    pub buy_token_balance: BuyTokenDestination,
+    #[serde(flatten)]
+    pub signing_scheme: QuoteSigningScheme,
+    #[serde(default)]
+    pub price_quality: PriceQuality,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BasketLeg {
+    pub token: H160,
+    /// The amount of `token` to sell (including the fee) or buy.
+    #[serde(with = "u256_decimal")]
+    pub amount: U256,
+    pub kind: OrderKind,
+}
+
+impl BasketQuoteRequest {
+    /// The quote request for a single leg of the basket.
+    pub fn leg_request(&self, leg: &BasketLeg) -> OrderQuoteRequest {
+        let (sell_token, buy_token, side) = match leg.kind {
+            OrderKind::Sell => (
+                leg.token,
+                self.quote_token,
+                OrderQuoteSide::Sell {
+                    sell_amount: SellAmount::BeforeFee { value: leg.amount },
+                },
+            ),
+            OrderKind::Buy => (
+                self.quote_token,
+                leg.token,
+                OrderQuoteSide::Buy {
+                    buy_amount_after_fee: leg.amount,
+                },
+            ),
+        };
+        OrderQuoteRequest {
+            from: self.from,
+            sell_token,
+            buy_token,
+            receiver: None,
+            side,
+            validity: self.validity,
+            app_data: self.app_data,
+            partially_fillable: false,
+            sell_token_balance: self.sell_token_balance,
+            buy_token_balance: self.buy_token_balance,
+            signing_scheme: self.signing_scheme,
+            price_quality: self.price_quality,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BasketQuoteResponse {
+    /// The quotes for the legs in the order of the request.
+    pub legs: Vec<OrderQuoteResponse>,
+    pub internal_cows: Vec<InternalCow>,
+}
+
+/// An amount of the quote token that a sell leg receives and a buy leg spends. It could be
+/// traded directly between the two legs instead of through external liquidity.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InternalCow {
+    pub sell_leg: usize,
+    pub buy_leg: usize,
+    #[serde(with = "u256_decimal")]
+    pub amount: U256,
+}
+
+/// Refreshes a stored quote. The trader and app data aren't stored with the quote but are needed
+/// to compute the fee subsidy.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct QuoteRefreshRequest {
+    pub from: H160,
+    #[serde(default)]
+    pub app_data: AppId,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuoteRefreshResponse {
+    /// The ID of the refreshed quote. This is a new ID if the stored quote was no longer valid.
+    pub id: QuoteId,
+    /// Whether the validity of the stored quote got extended. Otherwise a new quote with new
+    /// amounts was stored.
+    pub extended: bool,
+    /// The sell amount the quote was computed for. The fee is charged on top of it.
+    #[serde(with = "u256_decimal")]
+    pub sell_amount: U256,
+    #[serde(with = "u256_decimal")]
+    pub buy_amount: U256,
+    #[serde(with = "u256_decimal")]
+    pub fee_amount: U256,
+    pub kind: OrderKind,
+    pub expiration: DateTime<Utc>,
 }
 
 impl OrderQuoteRequest {
@@ -392,4 +540,76 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn basket_leg_requests() {
+        let request: BasketQuoteRequest = serde_json::from_value(json!({
+            "from": "0x0101010101010101010101010101010101010101",
+            "quoteToken": "0x0202020202020202020202020202020202020202",
+            "legs": [
+                {
+                    "token": "0x0303030303030303030303030303030303030303",
+                    "amount": "1337",
+                    "kind": "sell",
+                },
+                {
+                    "token": "0x0404040404040404040404040404040404040404",
+                    "amount": "42",
+                    "kind": "buy",
+                },
+            ],
+            "validTo": 0x12345678,
+            "priceQuality": "fast",
+        }))
+        .unwrap();
+        assert_eq!(
+            request.leg_request(&request.legs[0]),
+            OrderQuoteRequest {
+                from: H160([0x01; 20]),
+                sell_token: H160([0x03; 20]),
+                buy_token: H160([0x02; 20]),
+                side: OrderQuoteSide::Sell {
+                    sell_amount: SellAmount::BeforeFee { value: 1337.into() },
+                },
+                validity: Validity::To(0x12345678),
+                price_quality: PriceQuality::Fast,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            request.leg_request(&request.legs[1]),
+            OrderQuoteRequest {
+                from: H160([0x01; 20]),
+                sell_token: H160([0x02; 20]),
+                buy_token: H160([0x04; 20]),
+                side: OrderQuoteSide::Buy {
+                    buy_amount_after_fee: 42.into(),
+                },
+                validity: Validity::To(0x12345678),
+                price_quality: PriceQuality::Fast,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn serialize_threat_warnings() {
+        assert_eq!(
+            serde_json::to_value(&[
+                ThreatWarning::DenyListedToken(H160([0x01; 20])),
+                ThreatWarning::MaliciousReceiver(H160([0x02; 20])),
+            ])
+            .unwrap(),
+            json!([
+                {
+                    "kind": "denyListedToken",
+                    "address": "0x0101010101010101010101010101010101010101",
+                },
+                {
+                    "kind": "maliciousReceiver",
+                    "address": "0x0202020202020202020202020202020202020202",
+                },
+            ])
+        );
+    }
 }
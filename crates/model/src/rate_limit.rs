@@ -0,0 +1,40 @@
+//! Contains the API request quota of a client served by the `limits` endpoint.
+
+use serde::{Deserialize, Serialize};
+
+/// How many more requests a client can make to the API before it gets rate limited.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitQuota {
+    /// The number of requests a client can make per window.
+    pub limit: u64,
+    /// The number of requests the client can still make in the current window.
+    pub remaining: u64,
+    /// The number of seconds until the current window ends and the quota resets.
+    pub reset: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serialization() {
+        let quota = RateLimitQuota {
+            limit: 100,
+            remaining: 42,
+            reset: 17,
+        };
+        let json = json!({
+            "limit": 100,
+            "remaining": 42,
+            "reset": 17,
+        });
+        assert_eq!(serde_json::to_value(quota).unwrap(), json);
+        assert_eq!(
+            serde_json::from_value::<RateLimitQuota>(json).unwrap(),
+            quota
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Sealed orders are orders that get submitted encrypted to the key of the autopilot and are only
+//! revealed when an auction gets cut. Until then nobody but the submitter knows what they trade,
+//! which reduces the information leaked before large orders are traded.
+//!
+//! Orders are sealed with ECIES on secp256k1 with HKDF-SHA256 and ChaCha20-Poly1305:
+//!
+//! - The submitter generates a random ephemeral key pair and computes the ECDH shared secret with
+//!   the sealing key as the SHA-256 hash of the compressed shared point.
+//! - The key is `HKDF-SHA256(secret, info = "cow sealed order" || ephemeral_public_key)` without
+//!   a salt.
+//! - The plaintext gets encrypted with ChaCha20-Poly1305 under that key with the ephemeral public
+//!   key as associated data. The nonce is all zeros, which is fine because every ephemeral key
+//!   derives a fresh key that is only ever used once.
+//!
+//! The sealed order is the compressed ephemeral public key followed by the ciphertext and the
+//! 16 byte Poly1305 tag.
+//! The plaintext is the JSON of the order as it would have been posted to the orders endpoint.
+//!
+//! # Threat model
+//!
+//! Sealing protects the content of an order from everyone who can observe it before the auction
+//! cut: other users, solvers, and anyone reading the orderbook API or its database. It does not
+//! protect it from the operator. Orders are sealed to a single key held by the autopilot so
+//! whoever controls the autopilot can reveal orders early. Revealed orders are placed like any
+//! other order and become public, and the size of the sealed order, the time it was submitted
+//! and the submitter's connection still leak. Tampered or truncated sealed orders get rejected
+//! when they are revealed and can't be turned into a different order.
+//!
+//! Sealing to a committee with threshold decryption, where no single party can reveal orders
+//! early, is not supported. It needs a distributed key generation and decryption shares from the
+//! committee members at every auction cut, which is a protocol of its own.
+
+use crate::{bytes_hex, order::OrderUid};
+use anyhow::{anyhow, ensure, Result};
+use chacha20poly1305::{
+    aead::{Aead, NewAead, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use hkdf::Hkdf;
+use primitive_types::H256;
+use secp256k1::{ecdh::SharedSecret, PublicKey, Secp256k1, SecretKey};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use web3::signing::keccak256;
+
+const PUBLIC_KEY_SIZE: usize = 33;
+const TAG_SIZE: usize = 16;
+const KEY_INFO: &[u8] = b"cow sealed order";
+
+/// Identifies a sealed order. It is the Keccak-256 hash of the sealed order.
+pub type SealedOrderId = H256;
+
+pub fn sealed_order_id(sealed: &[u8]) -> SealedOrderId {
+    H256(keccak256(sealed))
+}
+
+/// Encrypts an order to the sealing key. The ephemeral key has to be random and must not be reused.
+pub fn seal(sealing_key: &PublicKey, ephemeral_key: &SecretKey, plaintext: &[u8]) -> Vec<u8> {
+    let ephemeral_public_key =
+        PublicKey::from_secret_key(&Secp256k1::signing_only(), ephemeral_key).serialize();
+    let ciphertext = cipher(sealing_key, ephemeral_key, &ephemeral_public_key)
+        .encrypt(
+            &nonce(),
+            Payload {
+                msg: plaintext,
+                aad: &ephemeral_public_key,
+            },
+        )
+        .expect("encrypting into a vector can't fail");
+    [&ephemeral_public_key[..], &ciphertext].concat()
+}
+
+/// Decrypts a sealed order with the secret sealing key. Fails if the sealed order was tampered
+/// with or not sealed to this key.
+pub fn unseal(sealing_key: &SecretKey, sealed: &[u8]) -> Result<Vec<u8>> {
+    let ephemeral_public_key = check_format(sealed)?;
+    let (aad, ciphertext) = sealed.split_at(PUBLIC_KEY_SIZE);
+    cipher(&ephemeral_public_key, sealing_key, aad)
+        .decrypt(
+            &nonce(),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| anyhow!("invalid tag"))
+}
+
+/// Checks that a sealed order is well formed without decrypting it and returns its ephemeral
+/// public key.
+pub fn check_format(sealed: &[u8]) -> Result<PublicKey> {
+    ensure!(
+        sealed.len() >= PUBLIC_KEY_SIZE + TAG_SIZE,
+        "sealed order is too short"
+    );
+    Ok(PublicKey::from_slice(&sealed[..PUBLIC_KEY_SIZE])?)
+}
+
+fn cipher(
+    public_key: &PublicKey,
+    secret_key: &SecretKey,
+    ephemeral_public_key: &[u8],
+) -> ChaCha20Poly1305 {
+    let secret = SharedSecret::new(public_key, secret_key);
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(None, secret.as_ref())
+        .expand(&[KEY_INFO, ephemeral_public_key].concat(), &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+}
+
+fn nonce() -> Nonce {
+    *Nonce::from_slice(&[0; 12])
+}
+
+/// A sealed order as it gets submitted.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SealedOrderCreation {
+    #[serde(with = "bytes_hex")]
+    pub sealed: Vec<u8>,
+}
+
+/// What happened to a sealed order.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum SealedOrderStatus {
+    /// The order hasn't been revealed yet.
+    Pending,
+    /// The order was revealed and placed.
+    #[serde(rename_all = "camelCase")]
+    Revealed { order_uid: OrderUid },
+    /// The order couldn't be decrypted or was rejected by the order validation.
+    #[serde(rename_all = "camelCase")]
+    Rejected { reason: String },
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SealedOrder {
+    pub id: SealedOrderId,
+    #[serde(flatten)]
+    pub status: SealedOrderStatus,
+}
+
+/// The key orders get sealed to.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SealingKey {
+    /// The compressed secp256k1 public key.
+    #[serde(with = "bytes_hex")]
+    pub public_key: Vec<u8>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn keys() -> (SecretKey, PublicKey) {
+        let secret = SecretKey::from_slice(&[1; 32]).unwrap();
+        let public = PublicKey::from_secret_key(&Secp256k1::signing_only(), &secret);
+        (secret, public)
+    }
+
+    #[test]
+    fn seal_roundtrip() {
+        let (secret, public) = keys();
+        let ephemeral = SecretKey::from_slice(&[2; 32]).unwrap();
+        let plaintext =
+            b"a plaintext that is longer than a single ChaCha20 block of sixty-four bytes";
+
+        let sealed = seal(&public, &ephemeral, plaintext);
+        assert_eq!(sealed.len(), PUBLIC_KEY_SIZE + plaintext.len() + TAG_SIZE);
+        assert!(!sealed
+            .windows(plaintext.len())
+            .any(|window| window == plaintext));
+        assert_eq!(unseal(&secret, &sealed).unwrap(), plaintext);
+
+        let mut tampered = sealed.clone();
+        tampered[PUBLIC_KEY_SIZE] ^= 1;
+        assert!(unseal(&secret, &tampered).is_err());
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(unseal(&secret, &tampered).is_err());
+        let mut tampered = sealed.clone();
+        tampered[..PUBLIC_KEY_SIZE].copy_from_slice(&public.serialize());
+        assert!(unseal(&secret, &tampered).is_err());
+
+        let other = SecretKey::from_slice(&[3; 32]).unwrap();
+        assert!(unseal(&other, &sealed).is_err());
+        assert!(unseal(&secret, &sealed[..PUBLIC_KEY_SIZE]).is_err());
+    }
+
+    #[test]
+    fn serialize_status() {
+        let order = SealedOrder {
+            id: H256([1; 32]),
+            status: SealedOrderStatus::Revealed {
+                order_uid: OrderUid([2; 56]),
+            },
+        };
+        let serialized = serde_json::to_value(&order).unwrap();
+        assert_eq!(
+            serialized,
+            json!({
+                "id": "0x0101010101010101010101010101010101010101010101010101010101010101",
+                "status": "revealed",
+                "orderUid": format!("0x{}", "02".repeat(56)),
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<SealedOrder>(serialized).unwrap(),
+            order
+        );
+        assert_eq!(
+            serde_json::to_value(&SealedOrderStatus::Pending).unwrap(),
+            json!({ "status": "pending" })
+        );
+    }
+}
@@ -1,5 +1,7 @@
+pub mod schemes;
+
 use crate::{bytes_hex, quote::QuoteSigningScheme, DomainSeparator};
-use anyhow::{ensure, Context as _, Result};
+use anyhow::{anyhow, Context as _, Result};
 use primitive_types::{H160, H256};
 use serde::{de, Deserialize, Serialize};
 use std::{
@@ -61,12 +63,7 @@ impl Debug for Signature {
 
 impl Signature {
     pub fn default_with(scheme: SigningScheme) -> Self {
-        match scheme {
-            SigningScheme::Eip712 => Signature::Eip712(Default::default()),
-            SigningScheme::EthSign => Signature::EthSign(Default::default()),
-            SigningScheme::Eip1271 => Signature::Eip1271(Default::default()),
-            SigningScheme::PreSign => Signature::PreSign,
-        }
+        scheme.implementation().default_signature()
     }
 
     /// Recovers the owner of the specified signature.
@@ -79,15 +76,9 @@ impl Signature {
         domain_separator: &DomainSeparator,
         struct_hash: &[u8; 32],
     ) -> Result<Option<H160>> {
-        match self {
-            Self::Eip712(signature) => signature
-                .recover(EcdsaSigningScheme::Eip712, domain_separator, struct_hash)
-                .map(Some),
-            Self::EthSign(signature) => signature
-                .recover(EcdsaSigningScheme::EthSign, domain_separator, struct_hash)
-                .map(Some),
-            _ => Ok(None),
-        }
+        self.scheme()
+            .implementation()
+            .recover(&self.to_bytes(), domain_separator, struct_hash)
     }
 
     /// Verifies the owner for the specified creation signature.
@@ -118,31 +109,7 @@ impl Signature {
     }
 
     pub fn from_bytes(scheme: SigningScheme, bytes: &[u8]) -> Result<Self> {
-        Ok(match scheme {
-            scheme @ (SigningScheme::Eip712 | SigningScheme::EthSign) => {
-                let bytes: [u8; 65] = bytes
-                    .try_into()
-                    .context("ECDSA signature must be 65 bytes long")?;
-                EcdsaSignature {
-                    r: H256::from_slice(&bytes[..32]),
-                    s: H256::from_slice(&bytes[32..64]),
-                    v: bytes[64],
-                }
-                .to_signature(
-                    scheme
-                        .try_to_ecdsa_scheme()
-                        .expect("scheme is an ecdsa scheme"),
-                )
-            }
-            SigningScheme::Eip1271 => Self::Eip1271(bytes.to_vec()),
-            SigningScheme::PreSign => {
-                ensure!(
-                    bytes.is_empty() || bytes.len() == 20,
-                    "presign signature bytes should be empty or an address (legacy)",
-                );
-                Self::PreSign
-            }
-        })
+        scheme.implementation().signature_from_bytes(bytes)
     }
 
     #[allow(clippy::wrong_self_convention)]
@@ -164,11 +131,17 @@ impl Signature {
     }
 
     pub fn encode_for_settlement(&self, owner: H160) -> Vec<u8> {
-        match self {
-            Self::Eip712(signature) | Self::EthSign(signature) => signature.to_bytes().to_vec(),
-            Self::Eip1271(signature) => [owner.as_bytes(), signature].concat(),
-            Self::PreSign => owner.as_bytes().to_vec(),
-        }
+        self.scheme()
+            .implementation()
+            .encode_for_settlement(&self.to_bytes(), owner)
+    }
+
+    /// The signature the owner contract has to verify, if the signing scheme is verified by the
+    /// owner.
+    pub fn contract_signature(&self) -> Option<Vec<u8>> {
+        self.scheme()
+            .implementation()
+            .contract_signature(&self.to_bytes())
     }
 }
 
@@ -223,16 +196,22 @@ impl From<EcdsaSigningScheme> for SigningScheme {
 }
 
 impl SigningScheme {
+    /// The behaviour of the scheme from the scheme registry.
+    pub fn implementation(self) -> &'static dyn schemes::Scheme {
+        schemes::get(self)
+    }
+
     pub fn is_ecdsa_scheme(&self) -> bool {
         self.try_to_ecdsa_scheme().is_some()
     }
 
     pub fn try_to_ecdsa_scheme(&self) -> Option<EcdsaSigningScheme> {
-        match self {
-            Self::Eip712 => Some(EcdsaSigningScheme::Eip712),
-            Self::EthSign => Some(EcdsaSigningScheme::EthSign),
-            Self::Eip1271 | Self::PreSign => None,
-        }
+        self.implementation().ecdsa_scheme()
+    }
+
+    /// See [`schemes::Scheme::signed_by_transaction`].
+    pub fn is_signed_by_transaction(&self) -> bool {
+        self.implementation().signed_by_transaction()
     }
 }
 
@@ -289,6 +268,9 @@ impl EcdsaSignature {
         bytes
     }
 
+    /// Parses r + s + v. The recovery id `v` is kept as is so that the signature round trips.
+    /// Wallets are not consistent about which convention they use for it, so recovering the
+    /// signer and encoding the signature for the settlement contract normalize it.
     pub fn from_bytes(bytes: &[u8; 65]) -> Self {
         EcdsaSignature {
             r: H256::from_slice(&bytes[..32]),
@@ -297,6 +279,42 @@ impl EcdsaSignature {
         }
     }
 
+    /// r + yParityAndS as specified in EIP-2098
+    pub fn to_compact_bytes(self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(self.r.as_bytes());
+        bytes[32..].copy_from_slice(self.s.as_bytes());
+        if normalize_v(self.v) == 28 {
+            bytes[32] |= 0x80;
+        }
+        bytes
+    }
+
+    /// Parses an EIP-2098 compact signature where the recovery id is stored in the otherwise
+    /// unused highest bit of `s`.
+    pub fn from_compact_bytes(bytes: &[u8; 64]) -> Self {
+        let mut s = H256::from_slice(&bytes[32..]);
+        let y_parity = s.0[0] >> 7;
+        s.0[0] &= 0x7f;
+        EcdsaSignature {
+            r: H256::from_slice(&bytes[..32]),
+            s,
+            v: 27 + y_parity,
+        }
+    }
+
+    /// Parses either a 65 byte or a 64 byte compact signature.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        match bytes.len() {
+            65 => Ok(Self::from_bytes(bytes.try_into().unwrap())),
+            64 => Ok(Self::from_compact_bytes(bytes.try_into().unwrap())),
+            len => Err(anyhow!(
+                "ECDSA signature must be 65 or 64 (compact) bytes long but is {} bytes",
+                len
+            )),
+        }
+    }
+
     pub fn recover(
         &self,
         signing_scheme: EcdsaSigningScheme,
@@ -304,7 +322,7 @@ impl EcdsaSignature {
         struct_hash: &[u8; 32],
     ) -> Result<H160> {
         let message = hashed_signing_message(signing_scheme, domain_separator, struct_hash);
-        let recovery = Recovery::new(message, self.v as u64, self.r, self.s);
+        let recovery = Recovery::new(message, normalize_v(self.v) as u64, self.r, self.s);
         let (signature, recovery_id) = recovery
             .as_signature()
             .context("unexpectedly invalid signature")?;
@@ -338,6 +356,22 @@ impl EcdsaSignature {
     }
 }
 
+/// Maps the different conventions for the recovery id to 27 or 28:
+/// - 0 or 1 as returned by some hardware wallets and signing libraries
+/// - 27 or 28 as expected by `ecrecover`
+/// - EIP-155 `chain_id * 2 + 35` or `+ 36`, which is odd for recovery id 0 and even for 1
+///
+/// EIP-155 values of large chain ids get truncated to a byte. Truncating keeps the parity but can
+/// wrap below 35, so every other value is mapped by its parity too. Only truncated values of 0 and
+/// 1 can't be told apart from the first convention and get treated as such.
+fn normalize_v(v: u8) -> u8 {
+    match v {
+        0 | 1 => v + 27,
+        27 | 28 => v,
+        _ => 28 - v % 2,
+    }
+}
+
 impl Serialize for EcdsaSignature {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -365,7 +399,7 @@ impl<'de> Deserialize<'de> for EcdsaSignature {
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
                 write!(
                     formatter,
-                    "the 65 ecdsa signature bytes as a hex encoded string, ordered as r, s, v, where v is either 27 or 28"
+                    "the 65 ecdsa signature bytes as a hex encoded string, ordered as r, s, v, or the 64 bytes of an EIP-2098 compact signature"
                 )
             }
 
@@ -379,14 +413,17 @@ impl<'de> Deserialize<'de> for EcdsaSignature {
                         s
                     ))
                 })?;
-                let mut bytes = [0u8; 65];
-                hex::decode_to_slice(s, &mut bytes).map_err(|err| {
+                let mut buffer = [0u8; 65];
+                let bytes = buffer.get_mut(..s.len() / 2).ok_or_else(|| {
+                    de::Error::custom(format!("{:?} is too long for an ecdsa signature", s))
+                })?;
+                hex::decode_to_slice(s, bytes).map_err(|err| {
                     de::Error::custom(format!(
                         "failed to decode {:?} as hex ecdsa signature: {}",
                         s, err
                     ))
                 })?;
-                Ok(EcdsaSignature::from_bytes(&bytes))
+                EcdsaSignature::from_slice(bytes).map_err(de::Error::custom)
             }
         }
 
@@ -552,6 +589,148 @@ mod tests {
         }
     }
 
+    #[test]
+    fn normalizes_all_recovery_ids() {
+        for v in 0..=u8::MAX {
+            let normalized = normalize_v(v);
+            match v {
+                0 | 27 => assert_eq!(normalized, 27),
+                1 | 28 => assert_eq!(normalized, 28),
+                // EIP-155: even values are odd y parity
+                v if v % 2 == 1 => assert_eq!(normalized, 27, "{v}"),
+                _ => assert_eq!(normalized, 28, "{v}"),
+            }
+        }
+    }
+
+    #[test]
+    fn normalizes_truncated_eip155_recovery_ids() {
+        // Chain id 111 wraps to 1 and 2, chain id 120 to 19 and 20.
+        for chain_id in [1u64, 5, 100, 111, 120, 1337, 11155111] {
+            for recovery_id in [0, 1] {
+                let v = (chain_id * 2 + 35 + recovery_id) as u8;
+                if v <= 1 {
+                    // Indistinguishable from a plain recovery id.
+                    continue;
+                }
+                assert_eq!(
+                    normalize_v(v),
+                    27 + recovery_id as u8,
+                    "chain {chain_id} recovery id {recovery_id}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn compact_signature_round_trip() {
+        for v in [27, 28] {
+            let signature = EcdsaSignature {
+                r: H256([0xff; 32]),
+                s: H256([0x7f; 32]),
+                v,
+            };
+            let compact = signature.to_compact_bytes();
+            assert_eq!(compact[32] >> 7, v - 27);
+            assert_eq!(EcdsaSignature::from_compact_bytes(&compact), signature);
+            assert_eq!(EcdsaSignature::from_slice(&compact).unwrap(), signature);
+        }
+    }
+
+    #[test]
+    fn from_slice_rejects_other_lengths() {
+        for len in [0, 20, 63, 66, 128] {
+            assert!(EcdsaSignature::from_slice(&vec![0u8; len]).is_err());
+        }
+    }
+
+    #[test]
+    fn fuzz_recovery_of_wallet_signature_formats() {
+        // Deterministic pseudo random keys and messages so failures are reproducible.
+        let mut seed = [0u8; 32];
+        for i in 0u32..256 {
+            seed = signing::keccak256(&seed);
+            let secret = secp256k1::SecretKey::from_slice(&seed).unwrap();
+            let key = SecretKeyRef::new(&secret);
+            let domain_separator = DomainSeparator(signing::keccak256(&i.to_be_bytes()));
+            let struct_hash = signing::keccak256(&seed);
+
+            for scheme in [EcdsaSigningScheme::Eip712, EcdsaSigningScheme::EthSign] {
+                let signature = EcdsaSignature::sign(scheme, &domain_separator, &struct_hash, key);
+                let y_parity = signature.v - 27;
+
+                let mut variants = vec![signature.to_compact_bytes().to_vec()];
+                // Legacy, raw recovery id and EIP-155 for mainnet, Goerli, Gnosis Chain and
+                // chain ids that don't fit into a byte, the second of which wraps below 35.
+                for v in [27, 0, 37, 45, 235, 309u64 as u8, 275u64 as u8] {
+                    let mut bytes = signature.to_bytes();
+                    bytes[64] = v + y_parity;
+                    variants.push(bytes.to_vec());
+                }
+
+                for bytes in variants {
+                    let parsed = Signature::from_bytes(scheme.into(), &bytes).unwrap();
+                    assert_eq!(
+                        parsed
+                            .recover(&domain_separator, &struct_hash)
+                            .unwrap()
+                            .unwrap(),
+                        key.address(),
+                    );
+                    assert_eq!(
+                        parsed.encode_for_settlement(H160::zero()),
+                        signature.to_bytes()
+                    );
+                }
+
+                // Unnormalized signatures constructed directly also recover.
+                let raw = EcdsaSignature {
+                    v: y_parity,
+                    ..signature
+                };
+                assert_eq!(
+                    raw.recover(scheme, &domain_separator, &struct_hash)
+                        .unwrap(),
+                    key.address()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn deserializes_compact_signature() {
+        let signature: Signature = serde_json::from_value(json!({
+            "signingScheme": "eip712",
+            "signature": "0x\
+                0101010101010101010101010101010101010101010101010101010101010101\
+                8202020202020202020202020202020202020202020202020202020202020202",
+        }))
+        .unwrap();
+        assert_eq!(
+            signature,
+            Signature::Eip712(EcdsaSignature {
+                r: H256([1; 32]),
+                s: H256([2; 32]),
+                v: 28,
+            })
+        );
+
+        let signature: EcdsaSignature = serde_json::from_value(json!(
+            "0x\
+            0101010101010101010101010101010101010101010101010101010101010101\
+            0202020202020202020202020202020202020202020202020202020202020202\
+            00"
+        ))
+        .unwrap();
+        assert_eq!(signature.v, 0);
+        assert_eq!(
+            signature
+                .to_signature(EcdsaSigningScheme::Eip712)
+                .encode_for_settlement(H160::zero())[64],
+            27
+        );
+    }
+
     #[test]
     fn legacy_presign_signature_format() {
         assert_eq!(
@@ -0,0 +1,272 @@
+//! The behaviour that differs between signing schemes.
+//!
+//! Every signing scheme implements [`Scheme`] and is registered in [`REGISTRY`]. Code handling
+//! signatures asks the registry instead of matching on [`SigningScheme`], so supporting a new
+//! scheme comes down to a `SigningScheme` and `Signature` variant and an implementation in this
+//! module. The implementation names the scheme's label in the Postgres `SigningScheme` enum, which
+//! a migration has to add along with the variant of `database::orders::SigningScheme`.
+
+use super::{normalize_v, EcdsaSignature, EcdsaSigningScheme, Signature, SigningScheme};
+use crate::{quote::QuoteSigningScheme, DomainSeparator};
+use anyhow::{ensure, Result};
+use primitive_types::H160;
+
+pub trait Scheme: Send + Sync {
+    fn signing_scheme(&self) -> SigningScheme;
+
+    /// The label of the scheme in the Postgres `SigningScheme` enum.
+    fn database_label(&self) -> &'static str;
+
+    /// Parses the signature bytes as they are used in the API and the database.
+    fn signature_from_bytes(&self, bytes: &[u8]) -> Result<Signature>;
+
+    /// A signature of the scheme with placeholder bytes.
+    fn default_signature(&self) -> Signature;
+
+    /// Recovers the signer from the signature bytes. `None` for schemes that can only be verified
+    /// on chain.
+    fn recover(
+        &self,
+        bytes: &[u8],
+        domain_separator: &DomainSeparator,
+        struct_hash: &[u8; 32],
+    ) -> Result<Option<H160>>;
+
+    /// The signature the owner contract verifies with EIP-1271 `isValidSignature`. `None` for
+    /// schemes that aren't verified by the owner.
+    fn contract_signature(&self, _bytes: &[u8]) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// The signature as the settlement contract expects it.
+    fn encode_for_settlement(&self, bytes: &[u8], owner: H160) -> Vec<u8>;
+
+    /// The 2 bit identifier of the scheme in the settlement contract's trade flags.
+    fn settlement_flag(&self) -> u8;
+
+    fn ecdsa_scheme(&self) -> Option<EcdsaSigningScheme> {
+        None
+    }
+
+    /// Orders of schemes that get signed with a transaction to the settlement contract can be
+    /// placed before they are funded and without a maximum validity, because the signing
+    /// transaction can be bundled with the funding.
+    fn signed_by_transaction(&self) -> bool {
+        false
+    }
+
+    /// How quotes for orders of the scheme get signed. `None` if orders of the scheme can't be
+    /// placed on chain but `onchain_order` is set.
+    fn quote_signing_scheme(&self, onchain_order: bool) -> Option<QuoteSigningScheme>;
+}
+
+/// All supported signing schemes.
+pub static REGISTRY: [&dyn Scheme; 4] = [
+    &Ecdsa(EcdsaSigningScheme::Eip712),
+    &Ecdsa(EcdsaSigningScheme::EthSign),
+    &Eip1271,
+    &PreSign,
+];
+
+/// The implementation of the signing scheme.
+pub fn get(scheme: SigningScheme) -> &'static dyn Scheme {
+    *REGISTRY
+        .iter()
+        .find(|implementation| implementation.signing_scheme() == scheme)
+        .expect("every signing scheme is registered")
+}
+
+/// The implementation of the signing scheme stored with the label in the database.
+pub fn from_database_label(label: &str) -> Option<&'static dyn Scheme> {
+    REGISTRY
+        .iter()
+        .copied()
+        .find(|implementation| implementation.database_label() == label)
+}
+
+/// Signatures recovering the signer with ECDSA off chain.
+struct Ecdsa(EcdsaSigningScheme);
+
+impl Scheme for Ecdsa {
+    fn signing_scheme(&self) -> SigningScheme {
+        self.0.into()
+    }
+
+    fn database_label(&self) -> &'static str {
+        match self.0 {
+            EcdsaSigningScheme::Eip712 => "eip712",
+            EcdsaSigningScheme::EthSign => "ethsign",
+        }
+    }
+
+    fn signature_from_bytes(&self, bytes: &[u8]) -> Result<Signature> {
+        Ok(EcdsaSignature::from_slice(bytes)?.to_signature(self.0))
+    }
+
+    fn default_signature(&self) -> Signature {
+        EcdsaSignature::default().to_signature(self.0)
+    }
+
+    fn recover(
+        &self,
+        bytes: &[u8],
+        domain_separator: &DomainSeparator,
+        struct_hash: &[u8; 32],
+    ) -> Result<Option<H160>> {
+        EcdsaSignature::from_slice(bytes)?
+            .recover(self.0, domain_separator, struct_hash)
+            .map(Some)
+    }
+
+    fn encode_for_settlement(&self, bytes: &[u8], _: H160) -> Vec<u8> {
+        // The settlement contract's `ecrecover` only accepts 27 or 28.
+        let mut bytes = bytes.to_vec();
+        if let Some(v) = bytes.get_mut(64) {
+            *v = normalize_v(*v);
+        }
+        bytes
+    }
+
+    fn settlement_flag(&self) -> u8 {
+        match self.0 {
+            EcdsaSigningScheme::Eip712 => 0b00,
+            EcdsaSigningScheme::EthSign => 0b01,
+        }
+    }
+
+    fn ecdsa_scheme(&self) -> Option<EcdsaSigningScheme> {
+        Some(self.0)
+    }
+
+    fn quote_signing_scheme(&self, onchain_order: bool) -> Option<QuoteSigningScheme> {
+        if onchain_order {
+            return None;
+        }
+        Some(match self.0 {
+            EcdsaSigningScheme::Eip712 => QuoteSigningScheme::Eip712,
+            EcdsaSigningScheme::EthSign => QuoteSigningScheme::EthSign,
+        })
+    }
+}
+
+/// Signatures of smart contract owners that get verified by the owner.
+///
+/// <https://eips.ethereum.org/EIPS/eip-1271>
+struct Eip1271;
+
+impl Scheme for Eip1271 {
+    fn signing_scheme(&self) -> SigningScheme {
+        SigningScheme::Eip1271
+    }
+
+    fn database_label(&self) -> &'static str {
+        "eip1271"
+    }
+
+    fn signature_from_bytes(&self, bytes: &[u8]) -> Result<Signature> {
+        Ok(Signature::Eip1271(bytes.to_vec()))
+    }
+
+    fn default_signature(&self) -> Signature {
+        Signature::Eip1271(Default::default())
+    }
+
+    fn recover(&self, _: &[u8], _: &DomainSeparator, _: &[u8; 32]) -> Result<Option<H160>> {
+        Ok(None)
+    }
+
+    fn contract_signature(&self, bytes: &[u8]) -> Option<Vec<u8>> {
+        Some(bytes.to_vec())
+    }
+
+    fn encode_for_settlement(&self, bytes: &[u8], owner: H160) -> Vec<u8> {
+        [owner.as_bytes(), bytes].concat()
+    }
+
+    fn settlement_flag(&self) -> u8 {
+        0b10
+    }
+
+    fn quote_signing_scheme(&self, onchain_order: bool) -> Option<QuoteSigningScheme> {
+        Some(QuoteSigningScheme::Eip1271 { onchain_order })
+    }
+}
+
+/// Orders signed with a `setPreSignature` transaction to the settlement contract.
+struct PreSign;
+
+impl Scheme for PreSign {
+    fn signing_scheme(&self) -> SigningScheme {
+        SigningScheme::PreSign
+    }
+
+    fn database_label(&self) -> &'static str {
+        "presign"
+    }
+
+    fn signature_from_bytes(&self, bytes: &[u8]) -> Result<Signature> {
+        ensure!(
+            bytes.is_empty() || bytes.len() == 20,
+            "presign signature bytes should be empty or an address (legacy)",
+        );
+        Ok(Signature::PreSign)
+    }
+
+    fn default_signature(&self) -> Signature {
+        Signature::PreSign
+    }
+
+    fn recover(&self, _: &[u8], _: &DomainSeparator, _: &[u8; 32]) -> Result<Option<H160>> {
+        Ok(None)
+    }
+
+    fn encode_for_settlement(&self, _: &[u8], owner: H160) -> Vec<u8> {
+        owner.as_bytes().to_vec()
+    }
+
+    fn settlement_flag(&self) -> u8 {
+        0b11
+    }
+
+    fn signed_by_transaction(&self) -> bool {
+        true
+    }
+
+    fn quote_signing_scheme(&self, onchain_order: bool) -> Option<QuoteSigningScheme> {
+        Some(QuoteSigningScheme::PreSign { onchain_order })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn every_scheme_is_registered_once() {
+        let all = [
+            SigningScheme::Eip712,
+            SigningScheme::EthSign,
+            SigningScheme::Eip1271,
+            SigningScheme::PreSign,
+        ];
+        for scheme in all {
+            assert_eq!(get(scheme).signing_scheme(), scheme);
+            assert_eq!(get(scheme).default_signature().scheme(), scheme);
+            assert_eq!(
+                from_database_label(get(scheme).database_label())
+                    .unwrap()
+                    .signing_scheme(),
+                scheme
+            );
+        }
+        assert_eq!(REGISTRY.len(), all.len());
+
+        let flags = REGISTRY
+            .iter()
+            .map(|scheme| scheme.settlement_flag())
+            .collect::<HashSet<_>>();
+        assert_eq!(flags.len(), REGISTRY.len());
+        assert!(flags.iter().all(|flag| *flag <= 0b11));
+    }
+}
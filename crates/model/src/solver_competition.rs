@@ -3,11 +3,14 @@ use crate::{
     order::OrderUid,
     u256_decimal::{self, DecimalU256},
 };
+use chrono::{DateTime, Utc};
+use num::BigInt;
 use primitive_types::{H160, H256, U256};
 use serde::{Deserialize, Serialize};
-use serde_with::serde_as;
+use serde_with::{serde_as, DisplayFromStr};
 use std::collections::BTreeMap;
 
+#[serde_as]
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct SolverCompetition {
@@ -21,7 +24,99 @@ pub struct SolverCompetition {
     pub competition_simulation_block: u64,
     pub transaction_hash: Option<H256>,
     pub auction: CompetitionAuction,
+    /// The seed of the pseudo-random selection of orders if the auction exceeded the maximum
+    /// auction size and orders had to be dropped. Allows reproducing the selection.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub order_selection_seed: Option<u64>,
     pub solutions: Vec<SolverSettlement>,
+    /// The rebate the block builder paid for the order flow of the settlement. `None` until the
+    /// rebate of the mined settlement has been indexed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mev_rebate: Option<MevRebate>,
+    /// Solvers that were not allowed to participate in the auction.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded_solvers: Vec<ExcludedSolver>,
+    /// How well the driver kept up with the auction. `None` for competitions of drivers that
+    /// don't report it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub performance: Option<CompetitionPerformance>,
+    /// Solutions that failed to simulate and therefore didn't get ranked.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub simulation_failures: Vec<SimulationFailure>,
+    /// Why the settlement transaction of the winning solution reverted. `None` if it didn't.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revert: Option<SettlementRevert>,
+}
+
+impl SolverCompetition {
+    /// The winning solution. Solutions are ordered by increasing objective value.
+    pub fn winner(&self) -> Option<&SolverSettlement> {
+        self.solutions.last()
+    }
+}
+
+/// The outcome of a solver competition that gets pushed to subscribers as soon as the competition
+/// is stored.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CompetitionResult {
+    pub auction_id: AuctionId,
+    pub transaction_hash: Option<H256>,
+    /// The name of the winning solver. `None` if no solver found a solution.
+    pub winner: Option<String>,
+    /// The surplus of the winning solution in native token.
+    pub surplus: Option<f64>,
+}
+
+impl From<&SolverCompetition> for CompetitionResult {
+    fn from(competition: &SolverCompetition) -> Self {
+        let winner = competition.winner();
+        Self {
+            auction_id: competition.auction_id,
+            transaction_hash: competition.transaction_hash,
+            winner: winner.map(|solution| solution.solver.clone()),
+            surplus: winner.map(|solution| solution.objective.surplus),
+        }
+    }
+}
+
+/// A stored solver competition as listed when browsing past auctions.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CompetitionSummary {
+    #[serde(flatten)]
+    pub result: CompetitionResult,
+    /// When the competition was stored.
+    pub created: DateTime<Utc>,
+    /// The solvers that proposed a solution, including solutions that failed to simulate.
+    pub solvers: Vec<String>,
+}
+
+impl CompetitionSummary {
+    pub fn new(competition: &SolverCompetition, created: DateTime<Utc>) -> Self {
+        let mut solvers = Vec::<String>::new();
+        for solver in competition
+            .solutions
+            .iter()
+            .map(|solution| &solution.solver)
+            .chain(
+                competition
+                    .simulation_failures
+                    .iter()
+                    .map(|failure| &failure.solver),
+            )
+        {
+            if !solvers.contains(solver) {
+                solvers.push(solver.clone());
+            }
+        }
+        Self {
+            result: CompetitionResult::from(competition),
+            created,
+            solvers,
+        }
+    }
 }
 
 #[serde_as]
@@ -31,6 +126,20 @@ pub struct CompetitionAuction {
     pub orders: Vec<OrderUid>,
     #[serde_as(as = "BTreeMap<_, DecimalU256>")]
     pub prices: BTreeMap<H160, U256>,
+    /// Orders that joined the auction after the driver cut it, in the order the amendments were
+    /// made. `prices` includes the prices of their tokens.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub amendments: Vec<AuctionAmendment>,
+}
+
+/// Orders that were added to an auction while it was already being solved.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuctionAmendment {
+    /// The version of the auction content that includes the orders. The auction as it was cut
+    /// has version 0 and every amendment increments it.
+    pub version: u32,
+    pub orders: Vec<OrderUid>,
 }
 
 #[serde_as]
@@ -44,6 +153,33 @@ pub struct SolverSettlement {
     pub orders: Vec<Order>,
     #[serde(with = "crate::bytes_hex")]
     pub call_data: Vec<u8>,
+    /// The version of the auction content the solution was computed for.
+    #[serde(default, skip_serializing_if = "is_original_version")]
+    pub auction_version: u32,
+    /// How the token balances of the settlement contract change when the solution is executed
+    /// according to a simulation. Negative changes are paid from the contract's buffers. Empty if
+    /// the solution wasn't simulated.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[serde_as(as = "BTreeMap<_, DisplayFromStr>")]
+    pub balance_diffs: BTreeMap<H160, BigInt>,
+    /// The winning solutions of the sub-auctions this solution merges if the auction was split
+    /// into clusters. `solver` is the solver that submits the merged solution. Empty for
+    /// solutions of a single solver.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sub_auctions: Vec<SubAuctionSolution>,
+}
+
+/// The contribution of the winning solution of a sub-auction to a merged solution.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SubAuctionSolution {
+    pub solver: String,
+    pub objective: f64,
+    pub orders: Vec<OrderUid>,
+}
+
+fn is_original_version(version: &u32) -> bool {
+    *version == 0
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
@@ -64,6 +200,178 @@ pub struct Order {
     pub executed_amount: U256,
 }
 
+/// A rebate a block builder paid back for the order flow of a settlement, for example through an
+/// MEV-share style private relay.
+#[serde_as]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MevRebate {
+    pub recipient: H160,
+    #[serde_as(as = "DecimalU256")]
+    pub amount: U256,
+    /// The shares of the rebate attributed to the user orders of the settlement.
+    pub orders: Vec<OrderRebate>,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderRebate {
+    pub id: OrderUid,
+    #[serde_as(as = "DecimalU256")]
+    pub amount: U256,
+}
+
+/// How long solving the auction took the driver. Gets used to size auctions so that they can be
+/// solved in time.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CompetitionPerformance {
+    /// How many times solvers were run. Can be larger than the number of solvers if the auction
+    /// was split into sub-auctions.
+    pub solver_runs: u64,
+    /// How many of the solver runs didn't finish before the deadline.
+    pub timed_out_runs: u64,
+    /// The time in seconds it took to simulate the solutions.
+    pub simulation_seconds: f64,
+}
+
+/// A solution whose simulation failed.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationFailure {
+    pub solver: String,
+    pub error: String,
+}
+
+/// Why a mined settlement transaction reverted, as seen when replaying it.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SettlementRevert {
+    /// The error of the transaction's top level call, e.g. "Reverted" or "Out of gas". `None` if
+    /// the transaction couldn't be replayed.
+    pub error: Option<String>,
+    /// The message of the `Error(string)` the transaction reverted with.
+    pub reason: Option<String>,
+    /// The raw data the transaction reverted with.
+    #[serde(with = "crate::bytes_hex")]
+    pub output: Vec<u8>,
+}
+
+impl SettlementRevert {
+    pub fn new(error: Option<String>, output: Vec<u8>) -> Self {
+        Self {
+            error,
+            reason: revert_reason(&output),
+            output,
+        }
+    }
+}
+
+/// Decodes the message of revert data encoded like a call to `Error(string)`.
+fn revert_reason(output: &[u8]) -> Option<String> {
+    const ERROR_SELECTOR: [u8; 4] = hex_literal::hex!("08c379a0");
+    let data = output.strip_prefix(&ERROR_SELECTOR)?;
+    match ethabi::decode(&[ethabi::ParamType::String], data)
+        .ok()?
+        .pop()?
+    {
+        ethabi::Token::String(reason) => Some(reason),
+        _ => None,
+    }
+}
+
+/// A solver that did not participate in an auction.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExcludedSolver {
+    pub solver: String,
+    pub reason: ExclusionReason,
+}
+
+/// Why a solver did not participate in an auction.
+#[serde_as]
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ExclusionReason {
+    /// The ETH balance of the solver's account can't pay for the gas of a settlement at the gas
+    /// price of the auction. Both amounts are in wei.
+    #[serde(rename_all = "camelCase")]
+    InsufficientBalance {
+        #[serde_as(as = "DecimalU256")]
+        balance: U256,
+        #[serde_as(as = "DecimalU256")]
+        required: U256,
+    },
+    /// The solver kept returning solutions that violate the schema and is demoted until the time.
+    #[serde(rename_all = "camelCase")]
+    SchemaViolations { until: DateTime<Utc> },
+}
+
+/// The differences between two solutions of a solver competition. Only entries that differ between
+/// the solutions are included.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SolutionDiff {
+    pub auction_id: AuctionId,
+    pub a: SolutionSummary,
+    pub b: SolutionSummary,
+    pub clearing_prices: Vec<ClearingPriceDiff>,
+    pub orders: Vec<ExecutedAmountDiff>,
+    /// Interactions of one solution that the other solution doesn't have.
+    pub interactions: InteractionsDiff,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SolutionSummary {
+    pub solver: String,
+    pub objective: Objective,
+}
+
+/// The clearing price of a token in either solution or `None` if the solution doesn't price it.
+#[serde_as]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ClearingPriceDiff {
+    pub token: H160,
+    #[serde_as(as = "Option<DecimalU256>")]
+    pub a: Option<U256>,
+    #[serde_as(as = "Option<DecimalU256>")]
+    pub b: Option<U256>,
+}
+
+/// The executed amount of an order in either solution or `None` if the solution doesn't execute
+/// it.
+#[serde_as]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutedAmountDiff {
+    pub id: OrderUid,
+    #[serde_as(as = "Option<DecimalU256>")]
+    pub a: Option<U256>,
+    #[serde_as(as = "Option<DecimalU256>")]
+    pub b: Option<U256>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InteractionsDiff {
+    pub a: Vec<Interaction>,
+    pub b: Vec<Interaction>,
+}
+
+/// An interaction of a settlement identified by the called contract and function.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, Hash, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Interaction {
+    pub target: H160,
+    /// The first 4 bytes of the interaction's call data. Empty for plain transfers of the native
+    /// token.
+    #[serde(with = "crate::bytes_hex")]
+    pub selector: Vec<u8>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -140,7 +448,9 @@ mod tests {
                     H160([0x22; 20]) => 2000.into(),
                     H160([0x33; 20]) => 3000.into(),
                 },
+                amendments: vec![],
             },
+            order_selection_seed: None,
             solutions: vec![SolverSettlement {
                 solver: "2".to_string(),
                 objective: Objective {
@@ -158,7 +468,15 @@ mod tests {
                     executed_amount: 12.into(),
                 }],
                 call_data: vec![0x13],
+                auction_version: 0,
+                balance_diffs: Default::default(),
+                sub_auctions: vec![],
             }],
+            mev_rebate: None,
+            excluded_solvers: vec![],
+            performance: None,
+            simulation_failures: vec![],
+            revert: None,
         };
 
         let serialized = serde_json::to_value(&orig).unwrap();
@@ -166,4 +484,200 @@ mod tests {
         let deserialized: SolverCompetition = serde_json::from_value(correct).unwrap();
         assert_eq!(orig, deserialized);
     }
+
+    #[test]
+    fn serialize_order_selection_seed() {
+        let competition = SolverCompetition {
+            order_selection_seed: Some(u64::MAX),
+            ..Default::default()
+        };
+        let serialized = serde_json::to_value(&competition).unwrap();
+        assert_eq!(serialized["orderSelectionSeed"], "18446744073709551615");
+        let deserialized: SolverCompetition = serde_json::from_value(serialized).unwrap();
+        assert_eq!(deserialized, competition);
+    }
+
+    #[test]
+    fn serialize_amendments() {
+        let competition = SolverCompetition {
+            auction: CompetitionAuction {
+                amendments: vec![AuctionAmendment {
+                    version: 1,
+                    orders: vec![OrderUid([0x11; 56])],
+                }],
+                ..Default::default()
+            },
+            solutions: vec![
+                SolverSettlement::default(),
+                SolverSettlement {
+                    auction_version: 1,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let serialized = serde_json::to_value(&competition).unwrap();
+        assert_eq!(serialized["auction"]["amendments"][0]["version"], 1);
+        assert!(serialized["solutions"][0].get("auctionVersion").is_none());
+        assert_eq!(serialized["solutions"][1]["auctionVersion"], 1);
+        let deserialized: SolverCompetition = serde_json::from_value(serialized).unwrap();
+        assert_eq!(deserialized, competition);
+    }
+
+    #[test]
+    fn serialize_balance_diffs() {
+        let solution = SolverSettlement {
+            balance_diffs: btreemap! {
+                H160([0x11; 20]) => BigInt::from(-5),
+                H160([0x22; 20]) => BigInt::from(7),
+            },
+            ..Default::default()
+        };
+        let serialized = serde_json::to_value(&solution).unwrap();
+        assert_eq!(
+            serialized["balanceDiffs"],
+            serde_json::json!({
+                "0x1111111111111111111111111111111111111111": "-5",
+                "0x2222222222222222222222222222222222222222": "7",
+            })
+        );
+        let deserialized: SolverSettlement = serde_json::from_value(serialized).unwrap();
+        assert_eq!(deserialized, solution);
+
+        let serialized = serde_json::to_value(&SolverSettlement::default()).unwrap();
+        assert!(serialized.get("balanceDiffs").is_none());
+    }
+
+    #[test]
+    fn serialize_mev_rebate() {
+        let competition = SolverCompetition {
+            mev_rebate: Some(MevRebate {
+                recipient: H160([0x11; 20]),
+                amount: 10.into(),
+                orders: vec![OrderRebate {
+                    id: OrderUid([0x22; 56]),
+                    amount: 10.into(),
+                }],
+            }),
+            ..Default::default()
+        };
+        let serialized = serde_json::to_value(&competition).unwrap();
+        assert_eq!(
+            serialized["mevRebate"],
+            serde_json::json!({
+                "recipient": "0x1111111111111111111111111111111111111111",
+                "amount": "10",
+                "orders": [{
+                    "id": "0x2222222222222222222222222222222222222222222222222222222222222222\
+                             2222222222222222222222222222222222222222\
+                             22222222",
+                    "amount": "10",
+                }],
+            })
+        );
+        let deserialized: SolverCompetition = serde_json::from_value(serialized).unwrap();
+        assert_eq!(deserialized, competition);
+    }
+
+    #[test]
+    fn serialize_excluded_solvers() {
+        let competition = SolverCompetition {
+            excluded_solvers: vec![
+                ExcludedSolver {
+                    solver: "solver".to_string(),
+                    reason: ExclusionReason::InsufficientBalance {
+                        balance: 1.into(),
+                        required: 2.into(),
+                    },
+                },
+                ExcludedSolver {
+                    solver: "other".to_string(),
+                    reason: ExclusionReason::SchemaViolations {
+                        until: DateTime::parse_from_rfc3339("2022-10-01T12:00:00Z")
+                            .unwrap()
+                            .into(),
+                    },
+                },
+            ],
+            ..Default::default()
+        };
+        let serialized = serde_json::to_value(&competition).unwrap();
+        assert_eq!(
+            serialized["excludedSolvers"],
+            serde_json::json!([
+                {
+                    "solver": "solver",
+                    "reason": {
+                        "kind": "insufficientBalance",
+                        "balance": "1",
+                        "required": "2",
+                    },
+                },
+                {
+                    "solver": "other",
+                    "reason": {
+                        "kind": "schemaViolations",
+                        "until": "2022-10-01T12:00:00Z",
+                    },
+                },
+            ])
+        );
+        let deserialized: SolverCompetition = serde_json::from_value(serialized).unwrap();
+        assert_eq!(deserialized, competition);
+    }
+
+    #[test]
+    fn competition_result_of_winner() {
+        let solution = |solver: &str, surplus| SolverSettlement {
+            solver: solver.to_string(),
+            objective: Objective {
+                surplus,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let competition = SolverCompetition {
+            auction_id: 1,
+            transaction_hash: Some(H256([0x11; 32])),
+            solutions: vec![solution("loser", 2.), solution("winner", 1.)],
+            ..Default::default()
+        };
+        assert_eq!(
+            CompetitionResult::from(&competition),
+            CompetitionResult {
+                auction_id: 1,
+                transaction_hash: Some(H256([0x11; 32])),
+                winner: Some("winner".to_string()),
+                surplus: Some(1.),
+            }
+        );
+        assert_eq!(
+            CompetitionResult::from(&SolverCompetition::default()),
+            CompetitionResult::default()
+        );
+    }
+
+    #[test]
+    fn decodes_revert_reason() {
+        // Reverted with `require(false, "GPv2: limit price not respected")`.
+        let output = hex_literal::hex!(
+            "08c379a0"
+            "0000000000000000000000000000000000000000000000000000000000000020"
+            "000000000000000000000000000000000000000000000000000000000000001f"
+            "475076323a206c696d6974207072696365206e6f742072657370656374656400"
+        );
+        let revert = SettlementRevert::new(Some("Reverted".to_string()), output.to_vec());
+        assert_eq!(
+            revert.reason.as_deref(),
+            Some("GPv2: limit price not respected")
+        );
+        assert_eq!(revert.output, output);
+
+        // Custom errors and empty reverts have no reason.
+        assert_eq!(
+            SettlementRevert::new(None, vec![0x12, 0x34, 0x56, 0x78]).reason,
+            None
+        );
+        assert_eq!(SettlementRevert::new(None, vec![]).reason, None);
+    }
 }
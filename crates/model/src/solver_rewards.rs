@@ -0,0 +1,46 @@
+//! Contains the solver rewards served by the `solver_rewards` endpoint.
+
+use bigdecimal::BigDecimal;
+use primitive_types::H160;
+use serde::{Deserialize, Serialize};
+
+/// The rewards a solver earned for its settlements in an accounting period.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SolverRewards {
+    /// The address the solver settles with.
+    pub solver: H160,
+    pub settlements: u64,
+    /// Settled orders that are not liquidity orders.
+    pub user_orders: u64,
+    /// The total reward denominated in the reward token.
+    #[serde(with = "serde_with::rust::display_fromstr")]
+    pub reward: BigDecimal,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serialization() {
+        let rewards = SolverRewards {
+            solver: H160([1; 20]),
+            settlements: 2,
+            user_orders: 3,
+            reward: "4.5".parse().unwrap(),
+        };
+        let json = json!({
+            "solver": "0x0101010101010101010101010101010101010101",
+            "settlements": 2,
+            "userOrders": 3,
+            "reward": "4.5",
+        });
+        assert_eq!(serde_json::to_value(&rewards).unwrap(), json);
+        assert_eq!(
+            serde_json::from_value::<SolverRewards>(json).unwrap(),
+            rewards
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Contains the data solver teams can inspect and configure with their API key.
+
+use crate::{
+    auction::AuctionId,
+    solver_competition::{SettlementRevert, SolverCompetition, SolverSettlement},
+};
+use primitive_types::{H160, H256};
+use serde::{Deserialize, Serialize};
+
+/// A solver team as registered by the operators. The team authenticates with an API key of the
+/// registered owner.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SolverTeam {
+    /// The name of the team's solver in solver competitions.
+    pub name: String,
+    /// The account the solver submits settlements with, which is what rewards are paid to.
+    pub solver: H160,
+    #[serde(flatten)]
+    pub config: SolverTeamConfig,
+}
+
+/// The driver configuration a solver team controls.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SolverTeamConfig {
+    /// The url auctions and rejection notifications get sent to. `None` keeps the url the
+    /// operators configured.
+    pub callback_url: Option<String>,
+}
+
+/// A solver competition as seen by a single solver team.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SolverTeamCompetition {
+    pub auction_id: AuctionId,
+    /// The settlement transaction if the team won.
+    pub transaction_hash: Option<H256>,
+    pub won: bool,
+    /// Why the settlement transaction of the team's winning solution reverted. `None` if it
+    /// didn't.
+    pub revert: Option<SettlementRevert>,
+    /// The team's solutions ordered by increasing objective value.
+    pub solutions: Vec<SolverSettlement>,
+    /// The errors of the team's solutions that failed to simulate.
+    pub simulation_failures: Vec<String>,
+}
+
+impl SolverTeamCompetition {
+    /// Returns `None` if the solver neither proposed a solution nor failed to simulate one in the
+    /// competition.
+    pub fn new(competition: &SolverCompetition, solver: &str) -> Option<Self> {
+        let solutions = competition
+            .solutions
+            .iter()
+            .filter(|solution| solution.solver == solver)
+            .cloned()
+            .collect::<Vec<_>>();
+        let simulation_failures = competition
+            .simulation_failures
+            .iter()
+            .filter(|failure| failure.solver == solver)
+            .map(|failure| failure.error.clone())
+            .collect::<Vec<_>>();
+        if solutions.is_empty() && simulation_failures.is_empty() {
+            return None;
+        }
+        let won = competition
+            .winner()
+            .map(|winner| winner.solver == solver)
+            .unwrap_or(false);
+        Some(Self {
+            auction_id: competition.auction_id,
+            transaction_hash: competition.transaction_hash.filter(|_| won),
+            won,
+            revert: competition.revert.clone().filter(|_| won),
+            solutions,
+            simulation_failures,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver_competition::SimulationFailure;
+
+    #[test]
+    fn competition_of_team() {
+        let solution = |solver: &str| SolverSettlement {
+            solver: solver.to_string(),
+            ..Default::default()
+        };
+        let competition = SolverCompetition {
+            auction_id: 1,
+            transaction_hash: Some(H256([1; 32])),
+            solutions: vec![solution("a"), solution("b")],
+            simulation_failures: vec![SimulationFailure {
+                solver: "a".to_string(),
+                error: "reverted".to_string(),
+            }],
+            revert: Some(SettlementRevert::new(Some("Reverted".to_string()), vec![])),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            SolverTeamCompetition::new(&competition, "a").unwrap(),
+            SolverTeamCompetition {
+                auction_id: 1,
+                transaction_hash: None,
+                won: false,
+                revert: None,
+                solutions: vec![solution("a")],
+                simulation_failures: vec!["reverted".to_string()],
+            }
+        );
+        assert_eq!(
+            SolverTeamCompetition::new(&competition, "b").unwrap(),
+            SolverTeamCompetition {
+                auction_id: 1,
+                transaction_hash: Some(H256([1; 32])),
+                won: true,
+                revert: competition.revert.clone(),
+                solutions: vec![solution("b")],
+                simulation_failures: vec![],
+            }
+        );
+        assert_eq!(SolverTeamCompetition::new(&competition, "c"), None);
+    }
+}
@@ -0,0 +1,79 @@
+//! Diagnoses of open orders that have been waiting to be matched for a while.
+
+use crate::app_id::AppId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Why an open order most likely doesn't get matched.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StuckOrderReason {
+    /// The sell or buy token isn't supported.
+    TokenDenied,
+    /// The owner didn't approve the settlement contract to transfer enough of the sell token.
+    MissingAllowance,
+    /// The owner doesn't hold enough of the sell token.
+    InsufficientBalance,
+    /// One of the tokens can't be priced because there is no liquidity connecting it to the
+    /// native token.
+    NoLiquidityPath,
+    /// The order is worth too little to pay for its settlement.
+    TooSmall,
+    /// The limit price is better for the owner than the market price.
+    PriceOutOfRange,
+    /// None of the other reasons apply.
+    Unknown,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderDiagnosis {
+    pub reason: StuckOrderReason,
+    pub diagnosed_at: DateTime<Utc>,
+}
+
+/// How many of the orders with an app data are stuck for a reason. Integrators are identified by
+/// the app data of their orders.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StuckOrders {
+    pub app_data: AppId,
+    pub reason: StuckOrderReason,
+    pub orders: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+    use serde_json::json;
+
+    #[test]
+    fn serialization() {
+        let diagnosis = OrderDiagnosis {
+            reason: StuckOrderReason::MissingAllowance,
+            diagnosed_at: DateTime::from_utc(NaiveDateTime::from_timestamp(1_660_000_000, 0), Utc),
+        };
+        assert_eq!(
+            serde_json::to_value(&diagnosis).unwrap(),
+            json!({
+                "reason": "missingAllowance",
+                "diagnosedAt": "2022-08-08T23:06:40Z",
+            })
+        );
+
+        let stuck = StuckOrders {
+            app_data: AppId([1; 32]),
+            reason: StuckOrderReason::NoLiquidityPath,
+            orders: 3,
+        };
+        assert_eq!(
+            serde_json::to_value(&stuck).unwrap(),
+            json!({
+                "appData": "0x0101010101010101010101010101010101010101010101010101010101010101",
+                "reason": "noLiquidityPath",
+                "orders": 3,
+            })
+        );
+    }
+}
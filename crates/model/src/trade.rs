@@ -1,9 +1,11 @@
 //! Contains the Trade type as described by the specification with serialization as described by the openapi documentation.
 
 use crate::order::OrderUid;
+use chrono::{DateTime, Utc};
 use num::BigUint;
 use primitive_types::{H160, H256};
 use serde::{Deserialize, Serialize};
+use web3::types::Bytes;
 
 #[derive(Eq, PartialEq, Clone, Debug, Default, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -23,6 +25,71 @@ pub struct Trade {
     pub sell_token: H160,
     // Settlement Data
     pub tx_hash: Option<H256>,
+    /// The venues the settlement interacted with to fill this trade. Empty for trades that were
+    /// matched directly against other orders or whose route has not been indexed yet.
+    #[serde(default)]
+    pub route: Vec<TradeVenue>,
+}
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TradeVenue {
+    pub kind: VenueKind,
+    /// The pool address for AMMs or the contract address for aggregators.
+    pub address: H160,
+}
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VenueKind {
+    UniswapV2,
+    UniswapV3,
+    BalancerV2,
+    ZeroEx,
+    OneInch,
+    Paraswap,
+}
+
+/// A settlement with aggregates over its trades that matched a trade query.
+#[derive(Eq, PartialEq, Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettlementTrades {
+    /// The block number and log index of the settlement event.
+    pub block_number: u64,
+    pub log_index: u64,
+    pub tx_hash: H256,
+    pub solver: H160,
+    /// `None` if the timestamp of the block hasn't been indexed yet.
+    pub block_timestamp: Option<DateTime<Utc>>,
+    pub trade_count: u64,
+    /// The number of distinct owners of the traded orders.
+    pub owner_count: u64,
+    pub order_uids: Vec<OrderUid>,
+}
+
+/// Proof that a trade happened on chain which can be verified against a block header alone, for
+/// example by a light client on another chain.
+///
+/// The trade event is part of the receipt of the settlement transaction. The receipt is proven to
+/// be included in the block by a Merkle Patricia proof against the receipts root of the block
+/// header. The key of the receipt in the trie is the RLP encoded transaction index.
+#[derive(Eq, PartialEq, Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TradeProof {
+    pub order_uid: OrderUid,
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub receipts_root: H256,
+    pub tx_hash: H256,
+    pub transaction_index: u64,
+    /// The index of the trade event among all logs of the block.
+    pub log_index: u64,
+    /// The index of the trade event among the logs of the receipt.
+    pub receipt_log_index: u64,
+    /// The consensus encoding of the receipt.
+    pub receipt: Bytes,
+    /// The RLP encoded trie nodes on the path from the root to the receipt.
+    pub proof: Vec<Bytes>,
 }
 
 #[cfg(test)]
@@ -43,7 +110,13 @@ mod tests {
             "owner": "0x0000000000000000000000000000000000000001",
             "sellToken": "0x000000000000000000000000000000000000000a",
             "buyToken": "0x0000000000000000000000000000000000000009",
-            "txHash": "0x0000000000000000000000000000000000000000000000000000000000000040"
+            "txHash": "0x0000000000000000000000000000000000000000000000000000000000000040",
+            "route": [
+                {
+                    "kind": "uniswapV2",
+                    "address": "0x0000000000000000000000000000000000000002"
+                }
+            ]
         });
         let expected = Trade {
             block_number: 1337u64,
@@ -56,6 +129,10 @@ mod tests {
             buy_token: H160::from_low_u64_be(9),
             sell_token: H160::from_low_u64_be(10),
             tx_hash: Some(H256::from_low_u64_be(64)),
+            route: vec![TradeVenue {
+                kind: VenueKind::UniswapV2,
+                address: H160::from_low_u64_be(2),
+            }],
         };
 
         let deserialized: Trade = serde_json::from_value(value.clone()).unwrap();
@@ -64,6 +141,41 @@ mod tests {
         assert_eq!(serialized, value);
     }
 
+    #[test]
+    fn trade_proof_serialization() {
+        let proof = TradeProof {
+            receipt: Bytes(vec![0x02, 0xf9]),
+            proof: vec![Bytes(vec![0xf8, 0x51])],
+            ..Default::default()
+        };
+        let serialized = serde_json::to_value(&proof).unwrap();
+        assert_eq!(serialized["receipt"], "0x02f9");
+        assert_eq!(serialized["proof"], json!(["0xf851"]));
+        assert_eq!(serialized["receiptLogIndex"], 0);
+        assert_eq!(
+            serde_json::from_value::<TradeProof>(serialized).unwrap(),
+            proof
+        );
+    }
+
+    #[test]
+    fn settlement_trades_serialization() {
+        let settlement = SettlementTrades {
+            block_number: 1,
+            trade_count: 2,
+            order_uids: vec![OrderUid([17u8; 56])],
+            ..Default::default()
+        };
+        let serialized = serde_json::to_value(&settlement).unwrap();
+        assert_eq!(serialized["blockNumber"], 1);
+        assert_eq!(serialized["tradeCount"], 2);
+        assert_eq!(serialized["blockTimestamp"], json!(null));
+        assert_eq!(
+            serde_json::from_value::<SettlementTrades>(serialized).unwrap(),
+            settlement
+        );
+    }
+
     #[test]
     fn debug_trade_data() {
         dbg!(Trade::default());
@@ -1,6 +1,6 @@
 use anyhow::{ensure, Result};
 use bigdecimal::{num_bigint::ToBigInt, BigDecimal};
-use num::{bigint::Sign, BigInt, BigRational, BigUint, Zero};
+use num::{bigint::Sign, BigInt, BigRational, BigUint, ToPrimitive, Zero};
 use primitive_types::U256;
 
 pub fn u256_to_big_uint(input: &U256) -> BigUint {
@@ -53,6 +53,26 @@ pub fn big_decimal_to_u256(big_decimal: &BigDecimal) -> Option<U256> {
     big_int_to_u256(&big_int).ok()
 }
 
+/// Unlike U256 this can represent sums of many U256 amounts and negative amounts like surplus.
+pub fn big_decimal_to_big_int(big_decimal: &BigDecimal) -> Option<BigInt> {
+    if !big_decimal.is_integer() {
+        return None;
+    }
+    big_decimal.to_bigint()
+}
+
+pub fn big_int_to_big_decimal(big_int: &BigInt) -> BigDecimal {
+    BigDecimal::from(big_int.clone())
+}
+
+pub fn big_decimal_to_i128(big_decimal: &BigDecimal) -> Option<i128> {
+    big_decimal_to_big_int(big_decimal)?.to_i128()
+}
+
+pub fn i128_to_big_decimal(input: i128) -> BigDecimal {
+    BigDecimal::from(BigInt::from(input))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -146,4 +166,38 @@ mod tests {
         );
         assert!(big_decimal_to_u256(&(max_u256_as_big_decimal + BigDecimal::one())).is_none());
     }
+
+    #[test]
+    fn big_decimal_to_big_int_() {
+        let max_u256 = u256_to_big_decimal(&U256::MAX);
+        let sum = &max_u256 + &max_u256;
+        assert_eq!(
+            big_decimal_to_big_int(&sum),
+            Some(BigInt::from(2) * u256_to_big_int(&U256::MAX))
+        );
+        assert_eq!(
+            big_int_to_big_decimal(&big_decimal_to_big_int(&sum).unwrap()),
+            sum
+        );
+        assert_eq!(
+            big_decimal_to_big_int(&BigDecimal::from(-1)),
+            Some(BigInt::from(-1))
+        );
+        assert!(big_decimal_to_big_int(&BigDecimal::from_str("0.5").unwrap()).is_none());
+    }
+
+    #[test]
+    fn big_decimal_to_i128_() {
+        for value in [0, -1, i128::MIN, i128::MAX] {
+            assert_eq!(
+                big_decimal_to_i128(&i128_to_big_decimal(value)),
+                Some(value)
+            );
+        }
+        assert!(
+            big_decimal_to_i128(&(i128_to_big_decimal(i128::MAX) + BigDecimal::one())).is_none()
+        );
+        assert!(big_decimal_to_i128(&u256_to_big_decimal(&U256::MAX)).is_none());
+        assert!(big_decimal_to_i128(&BigDecimal::from_str("-0.5").unwrap()).is_none());
+    }
 }
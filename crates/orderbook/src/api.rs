@@ -1,24 +1,50 @@
+mod api_keys;
 mod cancel_order;
 mod create_order;
 mod get_auction;
+mod get_auctions;
+mod get_buffers;
+mod get_domain;
 mod get_fee_and_quote;
 mod get_fee_info;
+mod get_fee_recommendation;
+mod get_integrator_analytics;
+mod get_limits;
 mod get_markets;
 mod get_order_by_uid;
+mod get_order_fee_policy;
 mod get_orders_by_tx;
+mod get_protocol_fees;
 mod get_solvable_orders;
 mod get_solvable_orders_v2;
 mod get_solver_competition;
+mod get_solver_competition_diff;
+mod get_solver_rewards;
+mod get_stats;
+mod get_trade_proofs;
 mod get_trades;
 mod get_user_orders;
+mod post_basket_quote;
 mod post_quote;
 pub mod post_solver_competition;
+mod refresh_quote;
 mod replace_order;
+mod sealed_orders;
+mod solver_team;
+mod stream_solver_competitions;
+mod stuck_orders;
+mod validate_orders;
 mod version;
 
-use crate::solver_competition::SolverCompetitionStoring;
-use crate::{database::trades::TradeRetrieving, orderbook::Orderbook};
-use shared::api::{error, finalize_router, internal_error, ApiReply};
+use crate::solver_competition::{CompetitionResults, SolverCompetitionStoring};
+use crate::{
+    buffers::BufferCache,
+    database::trades::TradeRetrieving,
+    orderbook::Orderbook,
+    rate_limit::{with_rate_limit, InboundRateLimiter},
+    trade_proofs::TradeProofs,
+};
+use shared::api::{cors, error, finalize_router, internal_error, ApiReply};
 use shared::order_quoting::QuoteHandler;
 use std::sync::Arc;
 use warp::{Filter, Rejection, Reply};
@@ -29,6 +55,11 @@ pub fn handle_all_routes(
     quotes: Arc<QuoteHandler>,
     solver_competition: Arc<dyn SolverCompetitionStoring>,
     solver_competition_auth: Option<String>,
+    analytics_auth: Option<String>,
+    competition_results: Arc<CompetitionResults>,
+    buffers: Arc<BufferCache>,
+    trade_proofs: Arc<TradeProofs>,
+    rate_limiter: Option<Arc<InboundRateLimiter>>,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
     // Routes for api v1.
 
@@ -39,18 +70,33 @@ pub fn handle_all_routes(
     let create_order = create_order::create_order(orderbook.clone())
         .map(|result| (result, "v1/create_order"))
         .boxed();
+    let validate_orders = validate_orders::validate_orders(orderbook.clone())
+        .map(|result| (result, "v1/validate_orders"))
+        .boxed();
     let fee_info = get_fee_info::get_fee_info(quotes.clone())
         .map(|result| (result, "v1/fee_info"))
         .boxed();
     let get_order = get_order_by_uid::get_order_by_uid(orderbook.clone())
         .map(|result| (result, "v1/get_order"))
         .boxed();
+    let get_order_fee_policy = get_order_fee_policy::get_order_fee_policy(orderbook.clone())
+        .map(|result| (result, "v1/get_order_fee_policy"))
+        .boxed();
     let get_solvable_orders = get_solvable_orders::get_solvable_orders(orderbook.clone())
         .map(|result| (result, "v1/get_solvable_orders"))
         .boxed();
     let get_trades = get_trades::get_trades(database)
         .map(|result| (result, "v1/get_trades"))
         .boxed();
+    let get_trade_proofs = get_trade_proofs::get_trade_proofs(trade_proofs)
+        .map(|result| (result, "v1/get_trade_proofs"))
+        .boxed();
+    let cancel_order_with_api_key = cancel_order::cancel_order_with_api_key(orderbook.clone())
+        .map(|result| (result, "v1/cancel_order_with_api_key"))
+        .boxed();
+    let cancel_orders_by_app_data = cancel_order::cancel_orders_by_app_data(orderbook.clone())
+        .map(|result| (result, "v1/cancel_orders_by_app_data"))
+        .boxed();
     let cancel_order = cancel_order::cancel_order(orderbook.clone())
         .map(|result| (result, "v1/cancel_order"))
         .boxed();
@@ -72,34 +118,118 @@ pub fn handle_all_routes(
     let get_orders_by_tx = get_orders_by_tx::get_orders_by_tx(orderbook.clone())
         .map(|result| (result, "v1/get_orders_by_tx"))
         .boxed();
-    let post_quote = post_quote::post_quote(quotes)
+    let post_quote = post_quote::post_quote(quotes.clone(), orderbook.clone())
         .map(|result| (result, "v1/post_quote"))
         .boxed();
+    let post_basket_quote = post_basket_quote::post_basket_quote(quotes.clone())
+        .map(|result| (result, "v1/post_basket_quote"))
+        .boxed();
+    let refresh_quote = refresh_quote::refresh_quote(quotes)
+        .map(|result| (result, "v1/refresh_quote"))
+        .boxed();
     let get_auction = get_auction::get_auction(orderbook.clone())
         .map(|result| (result, "v1/auction"))
         .boxed();
+    let get_auctions = get_auctions::get_auctions(orderbook.clone())
+        .map(|result| (result, "v1/auctions"))
+        .boxed();
+    let get_buffers = get_buffers::get_buffers(buffers)
+        .map(|result| (result, "v1/buffers"))
+        .boxed();
     let get_solver_competition = get_solver_competition::get(solver_competition.clone())
         .map(|result| (result, "v1/solver_competition"))
         .boxed();
+    let get_solver_competition_diff = get_solver_competition_diff::get(solver_competition.clone())
+        .map(|result| (result, "v1/solver_competition_diff"))
+        .boxed();
     let post_solver_competition =
         post_solver_competition::post(solver_competition, solver_competition_auth)
             .map(|result| (result, "v1/solver_competition"))
             .boxed();
+    let get_stats = get_stats::get_stats(orderbook.clone())
+        .map(|result| (result, "v1/get_stats"))
+        .boxed();
+    let get_solver_rewards = get_solver_rewards::get_solver_rewards(orderbook.clone())
+        .map(|result| (result, "v1/get_solver_rewards"))
+        .boxed();
+    let get_integrator_analytics = get_integrator_analytics::get_integrator_analytics(
+        orderbook.clone(),
+        analytics_auth.clone(),
+    )
+    .map(|result| (result, "v1/get_integrator_analytics"))
+    .boxed();
+    let get_fee_recommendation = get_fee_recommendation::get_fee_recommendation(orderbook.clone())
+        .map(|result| (result, "v1/get_fee_recommendation"))
+        .boxed();
+    let get_protocol_fees = get_protocol_fees::get_protocol_fees(orderbook.clone())
+        .map(|result| (result, "v1/get_protocol_fees"))
+        .boxed();
+    let create_api_key = api_keys::create(orderbook.clone())
+        .map(|result| (result, "v1/create_api_key"))
+        .boxed();
+    let revoke_api_key = api_keys::revoke(orderbook.clone())
+        .map(|result| (result, "v1/revoke_api_key"))
+        .boxed();
+    let post_sealed_order = sealed_orders::post(orderbook.clone())
+        .map(|result| (result, "v1/post_sealed_order"))
+        .boxed();
+    let get_sealing_key = sealed_orders::get_key(orderbook.clone())
+        .map(|result| (result, "v1/get_sealing_key"))
+        .boxed();
+    let get_sealed_order = sealed_orders::get(orderbook.clone())
+        .map(|result| (result, "v1/get_sealed_order"))
+        .boxed();
+    let get_order_diagnosis = stuck_orders::get_diagnosis(orderbook.clone())
+        .map(|result| (result, "v1/get_order_diagnosis"))
+        .boxed();
+    let get_stuck_orders = stuck_orders::get_report(orderbook.clone(), analytics_auth)
+        .map(|result| (result, "v1/get_stuck_orders"))
+        .boxed();
+    let get_solver_team = solver_team::get(orderbook.clone())
+        .map(|result| (result, "v1/get_solver_team"))
+        .boxed();
+    let update_solver_team_config = solver_team::update_config(orderbook.clone())
+        .map(|result| (result, "v1/update_solver_team_config"))
+        .boxed();
+    let get_solver_team_competitions = solver_team::get_competitions(orderbook.clone())
+        .map(|result| (result, "v1/get_solver_team_competitions"))
+        .boxed();
+    let get_solver_team_rewards = solver_team::get_rewards(orderbook.clone())
+        .map(|result| (result, "v1/get_solver_team_rewards"))
+        .boxed();
+    let get_limits = get_limits::get_limits(rate_limiter.clone())
+        .map(|result| (result, "v1/get_limits"))
+        .boxed();
     let version = version::version()
         .map(|result| (result, "v1/version"))
         .boxed();
+    let get_domain = get_domain::get_domain(orderbook.clone())
+        .map(|result| (result, "v1/get_domain"))
+        .boxed();
 
     let routes_v1 = warp::path!("api" / "v1" / ..)
         .and(
             create_order
+                .or(validate_orders)
+                .unify()
                 .or(fee_info)
                 .unify()
                 .or(get_order)
                 .unify()
+                .or(get_order_fee_policy)
+                .unify()
+                .or(get_order_diagnosis)
+                .unify()
                 .or(get_solvable_orders)
                 .unify()
                 .or(get_trades)
                 .unify()
+                .or(get_trade_proofs)
+                .unify()
+                .or(cancel_order_with_api_key)
+                .unify()
+                .or(cancel_orders_by_app_data)
+                .unify()
                 .or(cancel_order)
                 .unify()
                 .or(replace_order)
@@ -116,13 +246,57 @@ pub fn handle_all_routes(
                 .unify()
                 .or(post_quote)
                 .unify()
+                .or(post_basket_quote)
+                .unify()
+                .or(refresh_quote)
+                .unify()
                 .or(get_auction)
                 .unify()
+                .or(get_auctions)
+                .unify()
+                .or(get_buffers)
+                .unify()
                 .or(get_solver_competition)
                 .unify()
+                .or(get_solver_competition_diff)
+                .unify()
                 .or(post_solver_competition)
                 .unify()
+                .or(get_stats)
+                .unify()
+                .or(get_solver_rewards)
+                .unify()
+                .or(get_integrator_analytics)
+                .unify()
+                .or(get_stuck_orders)
+                .unify()
+                .or(get_fee_recommendation)
+                .unify()
+                .or(get_protocol_fees)
+                .unify()
+                .or(create_api_key)
+                .unify()
+                .or(revoke_api_key)
+                .unify()
+                .or(post_sealed_order)
+                .unify()
+                .or(get_sealing_key)
+                .unify()
+                .or(get_sealed_order)
+                .unify()
+                .or(get_solver_team)
+                .unify()
+                .or(update_solver_team_config)
+                .unify()
+                .or(get_solver_team_competitions)
+                .unify()
+                .or(get_solver_team_rewards)
+                .unify()
+                .or(get_limits)
+                .unify()
                 .or(version)
+                .unify()
+                .or(get_domain)
                 .unify(),
         )
         .untuple_one()
@@ -141,5 +315,16 @@ pub fn handle_all_routes(
     // Routes combined
 
     let routes = routes_v1.or(routes_v2).unify().boxed();
-    finalize_router(routes, "orderbook::api::request_summary")
+
+    // Streaming routes don't reply with a single JSON value so they can't share the metrics of the
+    // other routes.
+
+    let stream_solver_competitions = warp::path!("api" / "v1" / ..)
+        .and(stream_solver_competitions::stream(competition_results))
+        .with(cors());
+
+    with_rate_limit(
+        stream_solver_competitions.or(finalize_router(routes, "orderbook::api::request_summary")),
+        rate_limiter,
+    )
 }
@@ -0,0 +1,192 @@
+use crate::orderbook::{ApiKeyError, Orderbook};
+use anyhow::Result;
+use model::{
+    api_key::{ApiKeyAuthorization, ApiKeyRevocation},
+    signature::{EcdsaSignature, EcdsaSigningScheme},
+};
+use primitive_types::{H160, H256};
+use serde::{Deserialize, Serialize};
+use shared::api::{
+    convert_json_response, convert_json_response_with_status, error, extract_payload,
+    internal_error, IntoWarpReply,
+};
+use std::{convert::Infallible, sync::Arc};
+use warp::{hyper::StatusCode, reply::with_status, Filter, Rejection};
+
+/// The header containing the API key for requests that are authenticated by one.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+pub fn api_key_header() -> impl Filter<Extract = (Option<String>,), Error = Rejection> + Clone {
+    warp::header::optional(API_KEY_HEADER)
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RevocationPayload {
+    signature: EcdsaSignature,
+    signing_scheme: EcdsaSigningScheme,
+}
+
+fn create_request() -> impl Filter<Extract = (ApiKeyAuthorization,), Error = Rejection> + Clone {
+    warp::path!("api_keys")
+        .and(warp::post())
+        .and(extract_payload())
+}
+
+fn revoke_request() -> impl Filter<Extract = (ApiKeyRevocation,), Error = Rejection> + Clone {
+    warp::path!("api_keys" / H256)
+        .and(warp::delete())
+        .and(extract_payload())
+        .map(|key_hash, payload: RevocationPayload| ApiKeyRevocation {
+            key_hash,
+            signature: payload.signature,
+            signing_scheme: payload.signing_scheme,
+        })
+}
+
+impl IntoWarpReply for ApiKeyError {
+    fn into_warp_reply(self) -> super::ApiReply {
+        match self {
+            Self::InvalidSignature => with_status(
+                error("InvalidSignature", "Malformed signature"),
+                StatusCode::BAD_REQUEST,
+            ),
+            Self::InvalidKey => with_status(
+                error("InvalidApiKey", "API key is unknown, expired or revoked"),
+                StatusCode::UNAUTHORIZED,
+            ),
+            Self::DuplicatedKey => with_status(
+                error("DuplicatedApiKey", "API key already exists"),
+                StatusCode::BAD_REQUEST,
+            ),
+            Self::ReplacedKeyNotFound => with_status(
+                error(
+                    "ReplacedApiKeyNotFound",
+                    "Replaced API key does not exist or belongs to a different owner",
+                ),
+                StatusCode::BAD_REQUEST,
+            ),
+            Self::AlreadyExpired => with_status(
+                error("ApiKeyExpired", "API key validTo is in the past"),
+                StatusCode::BAD_REQUEST,
+            ),
+            Self::WrongOwner => with_status(
+                error("WrongOwner", "Order owner doesn't match API key owner"),
+                StatusCode::UNAUTHORIZED,
+            ),
+            Self::UnsupportedSigningScheme => with_status(
+                error(
+                    "UnsupportedSigningScheme",
+                    "Orders placed with an API key must use the presign signing scheme",
+                ),
+                StatusCode::BAD_REQUEST,
+            ),
+            Self::NotAppDataOwner => with_status(
+                error(
+                    "NotAppDataOwner",
+                    "API key owner is not the owner of the app data",
+                ),
+                StatusCode::FORBIDDEN,
+            ),
+            Self::DatabaseDegraded(err) => err.into_warp_reply(),
+            Self::Other(err) => with_status(
+                internal_error(err.context("api_key")),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        }
+    }
+}
+
+fn create_response(result: Result<H160, ApiKeyError>) -> super::ApiReply {
+    convert_json_response_with_status(result, StatusCode::CREATED)
+}
+
+fn revoke_response(result: Result<(), ApiKeyError>) -> super::ApiReply {
+    convert_json_response(result.map(|_| "Revoked"))
+}
+
+pub fn create(
+    orderbook: Arc<Orderbook>,
+) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
+    create_request().and_then(move |authorization| {
+        let orderbook = orderbook.clone();
+        async move {
+            let result = orderbook.register_api_key(authorization).await;
+            if let Ok(owner) = &result {
+                tracing::debug!(?owner, "api key created");
+            }
+            Result::<_, Infallible>::Ok(create_response(result))
+        }
+    })
+}
+
+pub fn revoke(
+    orderbook: Arc<Orderbook>,
+) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
+    revoke_request().and_then(move |revocation| {
+        let orderbook = orderbook.clone();
+        async move {
+            let result = orderbook.revoke_api_key(revocation).await;
+            Result::<_, Infallible>::Ok(revoke_response(result))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use warp::{test::request, Reply};
+
+    #[tokio::test]
+    async fn create_request_ok() {
+        let authorization = ApiKeyAuthorization {
+            key_hash: H256([1; 32]),
+            valid_to: 1337,
+            replaces: Some(H256([2; 32])),
+            signature: Default::default(),
+            signing_scheme: EcdsaSigningScheme::Eip712,
+        };
+        let request = request()
+            .path("/api_keys")
+            .method("POST")
+            .header("content-type", "application/json")
+            .json(&authorization);
+        let result = request.filter(&create_request()).await.unwrap();
+        assert_eq!(result, authorization);
+    }
+
+    #[tokio::test]
+    async fn revoke_request_ok() {
+        let key_hash = H256([1; 32]);
+        let request = request()
+            .path(&format!("/api_keys/{key_hash:?}"))
+            .method("DELETE")
+            .header("content-type", "application/json")
+            .json(&json!({
+                "signature": EcdsaSignature::default(),
+                "signingScheme": "ethsign",
+            }));
+        let result = request.filter(&revoke_request()).await.unwrap();
+        assert_eq!(
+            result,
+            ApiKeyRevocation {
+                key_hash,
+                signature: Default::default(),
+                signing_scheme: EcdsaSigningScheme::EthSign,
+            }
+        );
+    }
+
+    #[test]
+    fn responses() {
+        let response = create_response(Ok(H160([1; 20]))).into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let response = revoke_response(Ok(())).into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = revoke_response(Err(ApiKeyError::InvalidKey)).into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -1,8 +1,10 @@
-use crate::orderbook::{OrderCancellationError, Orderbook};
+use super::api_keys::API_KEY_HEADER;
+use crate::orderbook::{ApiKeyError, OrderCancellationError, Orderbook};
 use anyhow::Result;
 use model::{
+    app_id::AppId,
     order::{OrderCancellation, OrderUid},
-    signature::{EcdsaSignature, EcdsaSigningScheme},
+    signature::Signature,
 };
 use serde::{Deserialize, Serialize};
 use shared::api::{convert_json_response, extract_payload, IntoWarpReply};
@@ -10,10 +12,9 @@ use std::{convert::Infallible, sync::Arc};
 use warp::{hyper::StatusCode, reply::with_status, Filter, Rejection};
 
 #[derive(Debug, Deserialize, PartialEq, Serialize)]
-#[serde(rename_all = "camelCase")]
 struct CancellationPayload {
-    signature: EcdsaSignature,
-    signing_scheme: EcdsaSigningScheme,
+    #[serde(flatten)]
+    signature: Signature,
 }
 
 pub fn cancel_order_request(
@@ -24,7 +25,6 @@ pub fn cancel_order_request(
         .map(|uid, payload: CancellationPayload| OrderCancellation {
             order_uid: uid,
             signature: payload.signature,
-            signing_scheme: payload.signing_scheme,
         })
 }
 
@@ -62,6 +62,7 @@ impl IntoWarpReply for OrderCancellationError {
                 super::error("OnChainOrder", "On-chain orders must be cancelled on-chain"),
                 StatusCode::BAD_REQUEST,
             ),
+            Self::DatabaseDegraded(err) => err.into_warp_reply(),
             Self::Other(err) => with_status(
                 super::internal_error(err.context("cancel_order")),
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -86,12 +87,64 @@ pub fn cancel_order(
     })
 }
 
+fn cancel_order_with_api_key_request(
+) -> impl Filter<Extract = (OrderUid, String), Error = Rejection> + Clone {
+    warp::path!("orders" / OrderUid)
+        .and(warp::delete())
+        .and(warp::header(API_KEY_HEADER))
+}
+
+/// Cancels a pre-signed order of the owner of the API key provided in the request headers.
+pub fn cancel_order_with_api_key(
+    orderbook: Arc<Orderbook>,
+) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
+    cancel_order_with_api_key_request().and_then(move |uid, api_key: String| {
+        let orderbook = orderbook.clone();
+        async move {
+            let result = orderbook.cancel_order_with_api_key(&api_key, &uid).await;
+            Result::<_, Infallible>::Ok(cancel_order_response(result))
+        }
+    })
+}
+
+fn cancel_orders_by_app_data_request(
+) -> impl Filter<Extract = (AppId, String), Error = Rejection> + Clone {
+    warp::path!("app_data" / AppId / "orders")
+        .and(warp::delete())
+        .and(warp::header(API_KEY_HEADER))
+}
+
+fn cancel_orders_by_app_data_response(
+    result: Result<Vec<OrderUid>, ApiKeyError>,
+) -> super::ApiReply {
+    convert_json_response(result)
+}
+
+/// Cancels all open orders with the app data if the owner of the API key provided in the request
+/// headers owns the app data. Responds with the uids of the cancelled orders.
+pub fn cancel_orders_by_app_data(
+    orderbook: Arc<Orderbook>,
+) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
+    cancel_orders_by_app_data_request().and_then(move |app_data, api_key: String| {
+        let orderbook = orderbook.clone();
+        async move {
+            let result = orderbook
+                .cancel_orders_by_app_data(&api_key, &app_data)
+                .await;
+            Result::<_, Infallible>::Ok(cancel_orders_by_app_data_response(result))
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::database::health::{DatabaseDegraded, DatabaseState};
     use ethcontract::H256;
     use hex_literal::hex;
+    use model::signature::EcdsaSignature;
     use serde_json::json;
+    use shared::api::response_body;
     use warp::{test::request, Reply};
 
     #[test]
@@ -106,7 +159,7 @@ mod tests {
             }))
             .unwrap(),
             CancellationPayload {
-                signature: EcdsaSignature {
+                signature: Signature::Eip712(EcdsaSignature {
                     r: H256(hex!(
                         "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
                     )),
@@ -114,8 +167,17 @@ mod tests {
                         "202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f"
                     )),
                     v: 27,
-                },
-                signing_scheme: EcdsaSigningScheme::Eip712,
+                }),
+            },
+        );
+        assert_eq!(
+            CancellationPayload::deserialize(json!({
+                "signature": "0x010203",
+                "signingScheme": "eip1271"
+            }))
+            .unwrap(),
+            CancellationPayload {
+                signature: Signature::Eip1271(vec![1, 2, 3]),
             },
         );
     }
@@ -130,13 +192,64 @@ mod tests {
             .method("DELETE")
             .header("content-type", "application/json")
             .json(&CancellationPayload {
-                signature: cancellation.signature,
-                signing_scheme: cancellation.signing_scheme,
+                signature: cancellation.signature.clone(),
             });
         let result = request.filter(&filter).await.unwrap();
         assert_eq!(result, cancellation);
     }
 
+    #[tokio::test]
+    async fn cancel_order_with_api_key_request_ok() {
+        let uid = OrderUid([1; 56]);
+        let request = request()
+            .path(&format!("/orders/{uid}"))
+            .method("DELETE")
+            .header(API_KEY_HEADER, "secret");
+        let result = request
+            .filter(&cancel_order_with_api_key_request())
+            .await
+            .unwrap();
+        assert_eq!(result, (uid, "secret".to_string()));
+
+        // Requests without an API key are handled by the signature based cancellation.
+        let request = request().path(&format!("/orders/{uid}")).method("DELETE");
+        assert!(request
+            .filter(&cancel_order_with_api_key_request())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn cancel_orders_by_app_data_request_ok() {
+        let app_data = AppId([1; 32]);
+        let request = request()
+            .path(&format!("/app_data/{app_data:?}/orders"))
+            .method("DELETE")
+            .header(API_KEY_HEADER, "secret");
+        let result = request
+            .filter(&cancel_orders_by_app_data_request())
+            .await
+            .unwrap();
+        assert_eq!(result, (app_data, "secret".to_string()));
+    }
+
+    #[test]
+    fn cancel_orders_by_app_data_response_ok() {
+        let response =
+            cancel_orders_by_app_data_response(Ok(vec![OrderUid([1; 56])])).into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response =
+            cancel_orders_by_app_data_response(Err(ApiKeyError::NotAppDataOwner)).into_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = cancel_orders_by_app_data_response(Err(ApiKeyError::DatabaseDegraded(
+            DatabaseDegraded(DatabaseState::ReadOnly),
+        )))
+        .into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
     #[test]
     fn cancel_order_response_ok() {
         let response = cancel_order_response(Ok(())).into_response();
@@ -175,4 +288,16 @@ mod tests {
         .into_response();
         assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
     }
+
+    #[tokio::test]
+    async fn cancel_order_response_database_degraded() {
+        let response = cancel_order_response(Err(OrderCancellationError::DatabaseDegraded(
+            DatabaseDegraded(DatabaseState::ReadOnly),
+        )))
+        .into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = response_body(response).await;
+        let body: serde_json::Value = serde_json::from_slice(body.as_slice()).unwrap();
+        assert_eq!(body["errorType"], "DatabaseDegraded");
+    }
 }
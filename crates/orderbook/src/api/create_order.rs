@@ -1,7 +1,12 @@
-use crate::orderbook::{AddOrderError, Orderbook};
+use super::api_keys::api_key_header;
+use crate::{
+    database::health::{DatabaseDegraded, DatabaseState},
+    orderbook::{AddOrderError, Orderbook},
+};
 use anyhow::Result;
-use model::order::{OrderCreation, OrderUid};
-use shared::api::{error, extract_payload, internal_error, ApiReply, IntoWarpReply};
+use model::order::{AuctionInclusionEstimate, OrderCreation, OrderPlacement, OrderUid};
+use serde::Deserialize;
+use shared::api::{error, extract_payload, internal_error, rich_error, ApiReply, IntoWarpReply};
 use shared::order_validation::{PartialValidationError, ValidationError};
 use std::{convert::Infallible, sync::Arc};
 use warp::reply::with_status;
@@ -14,6 +19,14 @@ pub fn create_order_request() -> impl Filter<Extract = (OrderCreation,), Error =
         .and(extract_payload())
 }
 
+#[derive(Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct CreateOrderQuery {
+    /// Respond with an estimate of when the order enters an auction in addition to its uid.
+    #[serde(default)]
+    estimate_inclusion: bool,
+}
+
 pub struct PartialValidationErrorWrapper(pub PartialValidationError);
 impl IntoWarpReply for PartialValidationErrorWrapper {
     fn into_warp_reply(self) -> ApiReply {
@@ -77,6 +90,20 @@ impl IntoWarpReply for PartialValidationErrorWrapper {
                 error("UnsupportedToken", format!("Token address {token:?}")),
                 StatusCode::BAD_REQUEST,
             ),
+            PartialValidationError::FeeOnTransferSellToken(token) => with_status(
+                error(
+                    "FeeOnTransferSellToken",
+                    format!("Token {token:?} takes a fee on transfer and can't be sold"),
+                ),
+                StatusCode::BAD_REQUEST,
+            ),
+            PartialValidationError::ThreatIntel(warning) => with_status(
+                error(
+                    "ThreatIntelMatch",
+                    format!("Order matches threat intelligence list: {warning:?}"),
+                ),
+                StatusCode::FORBIDDEN,
+            ),
             PartialValidationError::Other(err) => with_status(
                 internal_error(err.context("partial_validation")),
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -85,7 +112,7 @@ impl IntoWarpReply for PartialValidationErrorWrapper {
     }
 }
 
-pub struct ValidationErrorWrapper(ValidationError);
+pub struct ValidationErrorWrapper(pub ValidationError);
 impl IntoWarpReply for ValidationErrorWrapper {
     fn into_warp_reply(self) -> ApiReply {
         match self.0 {
@@ -119,17 +146,38 @@ impl IntoWarpReply for ValidationErrorWrapper {
                 ),
                 StatusCode::BAD_REQUEST,
             ),
-            ValidationError::InsufficientBalance => with_status(
+            ValidationError::WrongDomain(domain) => with_status(
                 error(
+                    "WrongDomain",
+                    format!(
+                        "The order is signed for the settlement contract {:?} on chain {}. The \
+                         domain to sign orders for is served by the domain endpoint.",
+                        domain.verifying_contract, domain.chain_id,
+                    ),
+                ),
+                StatusCode::BAD_REQUEST,
+            ),
+            ValidationError::InsufficientBalance(funds) => with_status(
+                rich_error(
                     "InsufficientBalance",
-                    "order owner must have funds worth at least x in his account",
+                    format!(
+                        "order owner must have a balance of at least {} of the sell token but is \
+                         short of {}",
+                        funds.required, funds.shortfall
+                    ),
+                    funds,
                 ),
                 StatusCode::BAD_REQUEST,
             ),
-            ValidationError::InsufficientAllowance => with_status(
-                error(
+            ValidationError::InsufficientAllowance(funds) => with_status(
+                rich_error(
                     "InsufficientAllowance",
-                    "order owner must give allowance to VaultRelayer",
+                    format!(
+                        "order owner must approve {:?} to spend at least {} of the sell token",
+                        funds.approval_target.unwrap_or_default(),
+                        funds.required
+                    ),
+                    funds,
                 ),
                 StatusCode::BAD_REQUEST,
             ),
@@ -166,6 +214,36 @@ impl IntoWarpReply for ValidationErrorWrapper {
                 ),
                 StatusCode::BAD_REQUEST,
             ),
+            ValidationError::UnsupportedQuoteFirmness => with_status(
+                error(
+                    "UnsupportedQuoteFirmness",
+                    "Only liquidity orders of market makers can specify quote firmness.",
+                ),
+                StatusCode::BAD_REQUEST,
+            ),
+            ValidationError::InvalidMinPartialFillAmount => with_status(
+                error(
+                    "InvalidMinPartialFillAmount",
+                    "The minimum partial fill amount requires a partially fillable order and must \
+                     be positive and at most the order's amount.",
+                ),
+                StatusCode::BAD_REQUEST,
+            ),
+            ValidationError::InvalidCondition => with_status(
+                error(
+                    "InvalidCondition",
+                    "The order's condition can never hold. Time windows need a bound and must not \
+                     be empty and oracle conditions need calldata.",
+                ),
+                StatusCode::BAD_REQUEST,
+            ),
+            ValidationError::UnsupportedOrderClass(class) => with_status(
+                error(
+                    "UnsupportedOrderClass",
+                    format!("The owner can't place orders of class {class:?}."),
+                ),
+                StatusCode::BAD_REQUEST,
+            ),
             ValidationError::Other(err) => with_status(
                 internal_error(err.context("order_validation")),
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -186,10 +264,28 @@ impl IntoWarpReply for AddOrderError {
                 internal_error(anyhow::Error::new(err).context("create_order")),
                 StatusCode::INTERNAL_SERVER_ERROR,
             ),
+            Self::DatabaseDegraded(err) => err.into_warp_reply(),
+            Self::ApiKey(err) => err.into_warp_reply(),
         }
     }
 }
 
+impl IntoWarpReply for DatabaseDegraded {
+    fn into_warp_reply(self) -> ApiReply {
+        let reason = match self.0 {
+            DatabaseState::ReadOnly => "the database is read-only",
+            _ => "the database is unavailable",
+        };
+        with_status(
+            error(
+                "DatabaseDegraded",
+                format!("{reason}, orders can't be placed or cancelled right now"),
+            ),
+            StatusCode::SERVICE_UNAVAILABLE,
+        )
+    }
+}
+
 pub fn create_order_response(result: Result<OrderUid, AddOrderError>) -> ApiReply {
     match result {
         Ok(uid) => with_status(warp::reply::json(&uid), StatusCode::CREATED),
@@ -197,26 +293,63 @@ pub fn create_order_response(result: Result<OrderUid, AddOrderError>) -> ApiRepl
     }
 }
 
+fn order_placement_response(
+    uid: OrderUid,
+    auction_inclusion: Option<AuctionInclusionEstimate>,
+) -> ApiReply {
+    let placement = OrderPlacement {
+        uid,
+        auction_inclusion,
+    };
+    with_status(warp::reply::json(&placement), StatusCode::CREATED)
+}
+
 pub fn create_order(
     orderbook: Arc<Orderbook>,
 ) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
-    create_order_request().and_then(move |order_payload: OrderCreation| {
-        let orderbook = orderbook.clone();
-        async move {
-            let quote_id = order_payload.quote_id;
-            let result = orderbook.add_order(order_payload).await;
-            if let Ok(order_uid) = result {
-                tracing::debug!(%order_uid, ?quote_id, "order created");
-            }
-            Result::<_, Infallible>::Ok(create_order_response(result))
-        }
-    })
+    create_order_request()
+        .and(warp::query::<CreateOrderQuery>())
+        .and(api_key_header())
+        .and_then(
+            move |order_payload: OrderCreation,
+                  query: CreateOrderQuery,
+                  api_key: Option<String>| {
+                let orderbook = orderbook.clone();
+                async move {
+                    let quote_id = order_payload.quote_id;
+                    let signing_scheme = order_payload.signature.scheme();
+                    let result = match api_key {
+                        Some(api_key) => {
+                            orderbook
+                                .add_order_with_api_key(&api_key, order_payload)
+                                .await
+                        }
+                        None => orderbook.add_order(order_payload).await,
+                    };
+                    let order_uid = match result {
+                        Ok(order_uid) => order_uid,
+                        Err(err) => return Result::<_, Infallible>::Ok(err.into_warp_reply()),
+                    };
+                    tracing::debug!(%order_uid, ?quote_id, "order created");
+                    if !query.estimate_inclusion {
+                        return Ok(create_order_response(Ok(order_uid)));
+                    }
+                    let estimate = orderbook
+                        .estimate_auction_inclusion(signing_scheme)
+                        .await
+                        .map_err(|err| tracing::warn!(?err, "failed to estimate auction inclusion"))
+                        .ok();
+                    Ok(order_placement_response(order_uid, estimate))
+                }
+            },
+        )
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use model::order::{OrderCreation, OrderUid};
+    use model::order::{FundingSuggestion, InsufficientFunds, OrderCreation, OrderUid};
+    use primitive_types::H160;
     use serde_json::json;
     use shared::api::response_body;
     use warp::{test::request, Reply};
@@ -247,6 +380,35 @@ mod tests {
         assert_eq!(body, expected);
     }
 
+    #[tokio::test]
+    async fn create_order_query() {
+        let filter = warp::query::<CreateOrderQuery>();
+        for (path, estimate_inclusion) in [
+            ("/orders", false),
+            ("/orders?estimateInclusion=false", false),
+            ("/orders?estimateInclusion=true", true),
+        ] {
+            let query = request().path(path).filter(&filter).await.unwrap();
+            assert_eq!(query, CreateOrderQuery { estimate_inclusion });
+        }
+    }
+
+    #[tokio::test]
+    async fn order_placement_response_created() {
+        let uid = OrderUid([1u8; 56]);
+        let response = order_placement_response(uid, None).into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = response_body(response).await;
+        let body: OrderPlacement = serde_json::from_slice(body.as_slice()).unwrap();
+        assert_eq!(
+            body,
+            OrderPlacement {
+                uid,
+                auction_inclusion: None,
+            }
+        );
+    }
+
     #[tokio::test]
     async fn create_order_response_duplicate() {
         let response = create_order_response(Err(AddOrderError::DuplicatedOrder)).into_response();
@@ -257,4 +419,41 @@ mod tests {
             json!({"errorType": "DuplicatedOrder", "description": "order already exists"});
         assert_eq!(body, expected_error);
     }
+
+    #[tokio::test]
+    async fn create_order_response_insufficient_balance() {
+        let funds = InsufficientFunds {
+            sell_token: H160([1; 20]),
+            required: 100.into(),
+            available: 60.into(),
+            shortfall: 40.into(),
+            approval_target: None,
+            suggestion: FundingSuggestion::LowerAmounts {
+                sell_amount: 50.into(),
+                buy_amount: 25.into(),
+            },
+        };
+        let response = create_order_response(Err(AddOrderError::OrderValidation(
+            ValidationError::InsufficientBalance(funds),
+        )))
+        .into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response_body(response).await;
+        let body: serde_json::Value = serde_json::from_slice(body.as_slice()).unwrap();
+        assert_eq!(body["errorType"], "InsufficientBalance");
+        assert_eq!(
+            body["data"],
+            json!({
+                "sellToken": "0x0101010101010101010101010101010101010101",
+                "required": "100",
+                "available": "60",
+                "shortfall": "40",
+                "suggestion": {
+                    "kind": "lowerAmounts",
+                    "sellAmount": "50",
+                    "buyAmount": "25",
+                },
+            })
+        );
+    }
 }
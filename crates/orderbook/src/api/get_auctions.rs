@@ -0,0 +1,105 @@
+use crate::{database::solver_competition::CompetitionFilter, orderbook::Orderbook};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use primitive_types::H160;
+use serde::Deserialize;
+use shared::api::{convert_json_response, ApiReply};
+use std::{convert::Infallible, sync::Arc};
+use warp::{hyper::StatusCode, reply::with_status, Filter, Rejection};
+
+const DEFAULT_LIMIT: u64 = 10;
+const MIN_LIMIT: u64 = 1;
+const MAX_LIMIT: u64 = 100;
+
+#[derive(Clone, Debug, Default, Deserialize)]
+struct Query {
+    token: Option<H160>,
+    solver: Option<String>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    offset: Option<u64>,
+    limit: Option<u64>,
+}
+
+fn request() -> impl Filter<Extract = (Query,), Error = Rejection> + Clone {
+    warp::path!("auctions")
+        .and(warp::get())
+        .and(warp::query::<Query>())
+}
+
+/// Lists the stored solver competitions of past auctions, newest first.
+pub fn get_auctions(
+    orderbook: Arc<Orderbook>,
+) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
+    request().and_then(move |query: Query| {
+        let orderbook = orderbook.clone();
+        async move {
+            let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+            if !(MIN_LIMIT..=MAX_LIMIT).contains(&limit) {
+                return Ok(with_status(
+                    super::error(
+                        "LIMIT_OUT_OF_BOUNDS",
+                        &format!("The pagination limit is [{},{}].", MIN_LIMIT, MAX_LIMIT),
+                    ),
+                    StatusCode::BAD_REQUEST,
+                ));
+            }
+            if let (Some(from), Some(to)) = (query.from, query.to) {
+                if from >= to {
+                    return Ok(with_status(
+                        super::error("InvalidTimeRange", "The time range is empty."),
+                        StatusCode::BAD_REQUEST,
+                    ));
+                }
+            }
+            let filter = CompetitionFilter {
+                token: query.token,
+                solver: query.solver,
+                from: query.from,
+                to: query.to,
+            };
+            let result = orderbook
+                .search_auctions(&filter, query.offset.unwrap_or_default(), limit)
+                .await;
+            Result::<_, Infallible>::Ok(convert_json_response(result))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use shared::addr;
+
+    #[tokio::test]
+    async fn request_() {
+        let query = warp::test::request()
+            .path("/auctions")
+            .method("GET")
+            .filter(&request())
+            .await
+            .unwrap();
+        assert_eq!(query.token, None);
+        assert_eq!(query.limit, None);
+
+        let query = warp::test::request()
+            .path(
+                "/auctions?token=0x0000000000000000000000000000000000000001&solver=Naive\
+                 &from=2022-10-01T00:00:00Z&to=2022-10-08T00:00:00Z&offset=10&limit=20",
+            )
+            .method("GET")
+            .filter(&request())
+            .await
+            .unwrap();
+        assert_eq!(
+            query.token,
+            Some(addr!("0000000000000000000000000000000000000001"))
+        );
+        assert_eq!(query.solver.as_deref(), Some("Naive"));
+        assert_eq!(query.from, Some(Utc.ymd(2022, 10, 1).and_hms(0, 0, 0)));
+        assert_eq!(query.to, Some(Utc.ymd(2022, 10, 8).and_hms(0, 0, 0)));
+        assert_eq!(query.offset, Some(10));
+        assert_eq!(query.limit, Some(20));
+    }
+}
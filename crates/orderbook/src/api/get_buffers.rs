@@ -0,0 +1,33 @@
+use crate::buffers::BufferCache;
+use anyhow::Result;
+use reqwest::StatusCode;
+use shared::api::{ApiReply, IntoWarpReply};
+use std::{convert::Infallible, sync::Arc};
+use warp::{reply::with_status, Filter, Rejection};
+
+fn get_buffers_request() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::path!("buffers").and(warp::get())
+}
+
+pub fn get_buffers(
+    buffers: Arc<BufferCache>,
+) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
+    get_buffers_request().and_then(move || {
+        let buffers = buffers.clone();
+        async move {
+            let result = buffers.get().await;
+            let reply = match result {
+                Ok(Some(buffers)) => with_status(warp::reply::json(&buffers), StatusCode::OK),
+                Ok(None) => with_status(
+                    super::error("NotFound", "There is no active auction"),
+                    StatusCode::NOT_FOUND,
+                ),
+                Err(err) => {
+                    tracing::error!(?err, "/api/v1/buffers");
+                    err.into_warp_reply()
+                }
+            };
+            Result::<_, Infallible>::Ok(reply)
+        }
+    })
+}
@@ -0,0 +1,28 @@
+use crate::orderbook::Orderbook;
+use model::{DOMAIN_NAME, DOMAIN_VERSION};
+use reqwest::StatusCode;
+use serde_json::json;
+use shared::api::ApiReply;
+use std::{convert::Infallible, sync::Arc};
+use warp::{reply::with_status, Filter, Rejection};
+
+/// The EIP-712 domain orders have to be signed for.
+pub fn get_domain(
+    orderbook: Arc<Orderbook>,
+) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
+    warp::path!("domain").and(warp::get()).and_then(move || {
+        let domain = orderbook.domain();
+        async move {
+            Result::<_, Infallible>::Ok(with_status(
+                warp::reply::json(&json!({
+                    "name": DOMAIN_NAME,
+                    "version": DOMAIN_VERSION,
+                    "chainId": domain.chain_id,
+                    "verifyingContract": domain.verifying_contract,
+                    "domainSeparator": format!("0x{:?}", domain.separator()),
+                })),
+                StatusCode::OK,
+            ))
+        }
+    })
+}
@@ -0,0 +1,78 @@
+use crate::orderbook::Orderbook;
+use anyhow::Result;
+use primitive_types::{H160, U256};
+use serde::Deserialize;
+use shared::api::{convert_json_response, ApiReply};
+use std::{convert::Infallible, sync::Arc};
+use warp::{Filter, Rejection};
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct Query {
+    sell_token: H160,
+    buy_token: H160,
+    /// Including the fee.
+    #[serde(with = "model::u256_decimal")]
+    sell_amount: U256,
+}
+
+fn request() -> impl Filter<Extract = (Query,), Error = Rejection> + Clone {
+    warp::path!("fee_recommendation")
+        .and(warp::get())
+        .and(warp::query::<Query>())
+}
+
+pub fn get_fee_recommendation(
+    orderbook: Arc<Orderbook>,
+) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
+    request().and_then(move |query: Query| {
+        let orderbook = orderbook.clone();
+        async move {
+            let result = orderbook
+                .get_fee_recommendation(query.sell_token, query.buy_token, query.sell_amount)
+                .await;
+            Result::<_, Infallible>::Ok(convert_json_response(result))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::test::request as test_request;
+
+    #[tokio::test]
+    async fn request_ok() {
+        let query = test_request()
+            .path(
+                "/fee_recommendation\
+                 ?sellToken=0x0101010101010101010101010101010101010101\
+                 &buyToken=0x0202020202020202020202020202020202020202\
+                 &sellAmount=1000000000000000000",
+            )
+            .filter(&request())
+            .await
+            .unwrap();
+        assert_eq!(
+            query,
+            Query {
+                sell_token: H160([1; 20]),
+                buy_token: H160([2; 20]),
+                sell_amount: U256::exp10(18),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn request_requires_amount() {
+        let result = test_request()
+            .path(
+                "/fee_recommendation\
+                 ?sellToken=0x0101010101010101010101010101010101010101\
+                 &buyToken=0x0202020202020202020202020202020202020202",
+            )
+            .filter(&request())
+            .await;
+        assert!(result.is_err());
+    }
+}
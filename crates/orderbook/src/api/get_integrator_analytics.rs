@@ -0,0 +1,84 @@
+//! Lets business development see how the quotes of integrators convert into orders and fills.
+//! The endpoint is authenticated with a shared secret and disabled if none is configured.
+
+use super::get_solver_rewards::Period;
+use crate::orderbook::Orderbook;
+use serde::Deserialize;
+use shared::api::{convert_json_response, ApiReply};
+use std::{convert::Infallible, sync::Arc};
+use warp::{hyper::StatusCode, reply::with_status, Filter, Rejection};
+
+#[derive(Clone, Debug, Deserialize)]
+struct Query {
+    period: String,
+}
+
+fn request() -> impl Filter<Extract = (Option<String>, Query), Error = Rejection> + Clone {
+    warp::path!("integrator_analytics")
+        .and(warp::get())
+        .and(warp::header::optional::<String>("Authorization"))
+        .and(warp::query::<Query>())
+}
+
+pub(super) fn is_authorized(expected_auth: Option<&str>, auth: Option<&str>) -> bool {
+    matches!(expected_auth, Some(expected) if auth == Some(expected))
+}
+
+pub fn get_integrator_analytics(
+    orderbook: Arc<Orderbook>,
+    expected_auth: Option<String>,
+) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
+    request().and_then(move |auth: Option<String>, query: Query| {
+        let orderbook = orderbook.clone();
+        let expected_auth = expected_auth.clone();
+        async move {
+            if !is_authorized(expected_auth.as_deref(), auth.as_deref()) {
+                return Result::<_, Infallible>::Ok(with_status(
+                    super::error("Unauthorized", ""),
+                    StatusCode::UNAUTHORIZED,
+                ));
+            }
+            let period = match query.period.parse::<Period>() {
+                Ok(period) => period,
+                Err(err) => {
+                    return Ok(with_status(
+                        super::error("InvalidPeriod", &format!("{err:#}")),
+                        StatusCode::BAD_REQUEST,
+                    ))
+                }
+            };
+            let result = orderbook
+                .get_integrator_analytics(period.start, period.end)
+                .await;
+            Ok(convert_json_response(result))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::test::request as test_request;
+
+    #[tokio::test]
+    async fn request_ok() {
+        let (auth, query) = test_request()
+            .path("/integrator_analytics?period=2022-10-01/2022-10-08")
+            .header("authorization", "secret")
+            .filter(&request())
+            .await
+            .unwrap();
+        assert_eq!(auth.as_deref(), Some("secret"));
+        assert_eq!(query.period, "2022-10-01/2022-10-08");
+    }
+
+    #[test]
+    fn authorization() {
+        assert!(is_authorized(Some("secret"), Some("secret")));
+        assert!(!is_authorized(Some("secret"), Some("wrong")));
+        assert!(!is_authorized(Some("secret"), None));
+        // Without a configured secret the endpoint is disabled.
+        assert!(!is_authorized(None, None));
+        assert!(!is_authorized(None, Some("secret")));
+    }
+}
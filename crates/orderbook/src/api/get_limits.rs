@@ -0,0 +1,81 @@
+//! Lets clients look up how many more requests they can make before they get rate limited.
+
+use crate::rate_limit::{client_id, InboundRateLimiter};
+use shared::api::ApiReply;
+use std::{convert::Infallible, sync::Arc};
+use tokio::time::Instant;
+use warp::{hyper::StatusCode, reply::with_status, Filter, Rejection};
+
+fn request(
+    limiter: Option<Arc<InboundRateLimiter>>,
+) -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::path!("limits")
+        .and(warp::get())
+        .and(client_id(limiter))
+}
+
+pub fn get_limits(
+    limiter: Option<Arc<InboundRateLimiter>>,
+) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
+    request(limiter.clone()).and_then(move |client: String| {
+        let limiter = limiter.clone();
+        async move {
+            Result::<_, Infallible>::Ok(match limiter {
+                Some(limiter) => with_status(
+                    warp::reply::json(&limiter.quota(&client, Instant::now())),
+                    StatusCode::OK,
+                ),
+                None => with_status(
+                    super::error("RateLimitingDisabled", "requests are not rate limited"),
+                    StatusCode::NOT_FOUND,
+                ),
+            })
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::rate_limit::RateLimitQuota;
+    use shared::api::response_body;
+    use std::{net::SocketAddr, time::Duration};
+    use warp::{test::request as test_request, Reply};
+
+    #[tokio::test]
+    async fn reports_quota_of_caller() {
+        let proxy: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+        let limiter = Arc::new(InboundRateLimiter::new(
+            10,
+            Duration::from_secs(60),
+            vec![proxy.ip()],
+        ));
+        limiter.consume("10.0.0.1", Instant::now()).unwrap();
+        let filter = get_limits(Some(limiter));
+
+        let reply = test_request()
+            .path("/limits")
+            .remote_addr(proxy)
+            .header("x-forwarded-for", "10.0.0.1")
+            .filter(&filter)
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(reply.status(), StatusCode::OK);
+        let quota: RateLimitQuota =
+            serde_json::from_slice(response_body(reply).await.as_slice()).unwrap();
+        assert_eq!(quota.limit, 10);
+        assert_eq!(quota.remaining, 9);
+    }
+
+    #[tokio::test]
+    async fn not_found_without_limiter() {
+        let reply = test_request()
+            .path("/limits")
+            .filter(&get_limits(None))
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(reply.status(), StatusCode::NOT_FOUND);
+    }
+}
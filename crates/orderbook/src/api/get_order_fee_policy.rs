@@ -0,0 +1,60 @@
+use crate::orderbook::Orderbook;
+use anyhow::Result;
+use model::{fee_policy::OrderFeePolicy, order::OrderUid};
+use shared::api::IntoWarpReply;
+use std::{convert::Infallible, sync::Arc};
+use warp::{hyper::StatusCode, reply, Filter, Rejection};
+
+fn request() -> impl Filter<Extract = (OrderUid,), Error = Rejection> + Clone {
+    warp::path!("orders" / OrderUid / "fee_policy").and(warp::get())
+}
+
+fn response(result: Result<Option<OrderFeePolicy>>) -> super::ApiReply {
+    match result {
+        Ok(Some(policy)) => reply::with_status(reply::json(&policy), StatusCode::OK),
+        Ok(None) => reply::with_status(
+            super::error(
+                "NotFound",
+                "Order was not found or not created with a quote",
+            ),
+            StatusCode::NOT_FOUND,
+        ),
+        Err(err) => err.into_warp_reply(),
+    }
+}
+
+pub fn get_order_fee_policy(
+    orderbook: Arc<Orderbook>,
+) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
+    request().and_then(move |uid| {
+        let orderbook = orderbook.clone();
+        async move {
+            let result = orderbook.get_order_fee_policy(&uid).await;
+            Result::<_, Infallible>::Ok(response(result))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::{test::request as test_request, Reply};
+
+    #[tokio::test]
+    async fn request_ok() {
+        let uid = OrderUid::default();
+        let result = test_request()
+            .path(&format!("/orders/{:}/fee_policy", uid))
+            .method("GET")
+            .filter(&request())
+            .await
+            .unwrap();
+        assert_eq!(result, uid);
+    }
+
+    #[tokio::test]
+    async fn response_not_found() {
+        let response = response(Ok(None)).into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
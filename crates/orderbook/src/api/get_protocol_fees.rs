@@ -0,0 +1,21 @@
+use crate::orderbook::Orderbook;
+use anyhow::Result;
+use shared::api::{convert_json_response, ApiReply};
+use std::{convert::Infallible, sync::Arc};
+use warp::{Filter, Rejection};
+
+fn request() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::path!("protocol_fees").and(warp::get())
+}
+
+pub fn get_protocol_fees(
+    orderbook: Arc<Orderbook>,
+) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
+    request().and_then(move || {
+        let orderbook = orderbook.clone();
+        async move {
+            let result = orderbook.get_protocol_fees().await;
+            Result::<_, Infallible>::Ok(convert_json_response(result))
+        }
+    })
+}
@@ -0,0 +1,139 @@
+use crate::solver_competition::{
+    diff::diff, Identifier, LoadSolverCompetitionError, SolverCompetitionStoring,
+};
+use anyhow::Result;
+use model::{auction::AuctionId, solver_competition::SolutionDiff};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use shared::api::{convert_json_response, error, ApiReply, IntoWarpReply};
+use std::{convert::Infallible, sync::Arc};
+use warp::{reply::with_status, Filter, Rejection};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Query {
+    solver_a: String,
+    solver_b: String,
+}
+
+fn diff_request() -> impl Filter<Extract = (AuctionId, Query), Error = Rejection> + Clone {
+    warp::path!("solver_competition" / AuctionId / "diff")
+        .and(warp::get())
+        .and(warp::query::<Query>())
+}
+
+#[derive(Debug)]
+enum DiffError {
+    Load(LoadSolverCompetitionError),
+    SolutionNotFound(String),
+}
+
+async fn solution_diff(
+    storage: &dyn SolverCompetitionStoring,
+    auction_id: AuctionId,
+    query: Query,
+) -> Result<SolutionDiff, DiffError> {
+    let competition = storage
+        .load(Identifier::Id(auction_id))
+        .await
+        .map_err(DiffError::Load)?;
+    // Solutions are ordered by increasing objective value so searching from the back finds the
+    // best solution of a solver.
+    let solution = |solver: &str| {
+        competition
+            .solutions
+            .iter()
+            .rev()
+            .find(|solution| solution.solver == solver)
+            .ok_or_else(|| DiffError::SolutionNotFound(solver.to_string()))
+    };
+    Ok(diff(
+        auction_id,
+        solution(&query.solver_a)?,
+        solution(&query.solver_b)?,
+    ))
+}
+
+pub fn get(
+    storage: Arc<dyn SolverCompetitionStoring>,
+) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
+    diff_request().and_then(move |auction_id: AuctionId, query: Query| {
+        let storage = storage.clone();
+        async move {
+            let result = solution_diff(storage.as_ref(), auction_id, query).await;
+            Result::<_, Infallible>::Ok(convert_json_response(result))
+        }
+    })
+}
+
+impl IntoWarpReply for DiffError {
+    fn into_warp_reply(self) -> ApiReply {
+        match self {
+            Self::Load(err) => err.into_warp_reply(),
+            Self::SolutionNotFound(solver) => with_status(
+                error(
+                    "SolutionNotFound",
+                    format!("solver {solver} has no solution in this competition"),
+                ),
+                StatusCode::NOT_FOUND,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver_competition::MockSolverCompetitionStoring;
+    use model::solver_competition::{SolverCompetition, SolverSettlement};
+    use shared::api::response_body;
+    use warp::{test::request, Reply};
+
+    #[tokio::test]
+    async fn diff_endpoint() {
+        let mut storage = MockSolverCompetitionStoring::new();
+        storage
+            .expect_load()
+            .returning(|identifier| match identifier {
+                Identifier::Id(1) => Ok(SolverCompetition {
+                    auction_id: 1,
+                    solutions: ["a", "b"]
+                        .into_iter()
+                        .map(|solver| SolverSettlement {
+                            solver: solver.to_string(),
+                            ..Default::default()
+                        })
+                        .collect(),
+                    ..Default::default()
+                }),
+                _ => Err(LoadSolverCompetitionError::NotFound),
+            });
+        let filter = get(Arc::new(storage));
+
+        let response = request()
+            .path("/solver_competition/1/diff?solverA=a&solverB=b")
+            .method("GET")
+            .filter(&filter)
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response_body(response).await;
+        let diff: SolutionDiff = serde_json::from_slice(&body).unwrap();
+        assert_eq!((diff.a.solver.as_str(), diff.b.solver.as_str()), ("a", "b"));
+
+        for path in [
+            "/solver_competition/1/diff?solverA=a&solverB=c",
+            "/solver_competition/2/diff?solverA=a&solverB=b",
+        ] {
+            let response = request()
+                .path(path)
+                .method("GET")
+                .filter(&filter)
+                .await
+                .unwrap()
+                .into_response();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+    }
+}
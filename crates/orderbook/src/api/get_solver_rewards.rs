@@ -0,0 +1,82 @@
+use crate::orderbook::Orderbook;
+use anyhow::{anyhow, ensure, Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+use shared::api::{convert_json_response, ApiReply};
+use std::{convert::Infallible, sync::Arc};
+use warp::{hyper::StatusCode, reply::with_status, Filter, Rejection};
+
+#[derive(Clone, Debug, Deserialize)]
+struct Query {
+    period: String,
+}
+
+/// An accounting period of whole days in UTC, `start/end` where the end date is exclusive. For
+/// example `2022-10-01/2022-10-08` is the first week of October.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) struct Period {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl std::str::FromStr for Period {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (start, end) = s.split_once('/').ok_or_else(|| anyhow!("missing end"))?;
+        let date = |date: &str| -> Result<DateTime<Utc>> {
+            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .with_context(|| format!("invalid date {date:?}"))?;
+            Ok(DateTime::from_utc(date.and_hms(0, 0, 0), Utc))
+        };
+        let period = Self {
+            start: date(start)?,
+            end: date(end)?,
+        };
+        ensure!(period.start < period.end, "period is empty");
+        Ok(period)
+    }
+}
+
+fn request() -> impl Filter<Extract = (Query,), Error = Rejection> + Clone {
+    warp::path!("solver_rewards")
+        .and(warp::get())
+        .and(warp::query::<Query>())
+}
+
+pub fn get_solver_rewards(
+    orderbook: Arc<Orderbook>,
+) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
+    request().and_then(move |query: Query| {
+        let orderbook = orderbook.clone();
+        async move {
+            let period = match query.period.parse::<Period>() {
+                Ok(period) => period,
+                Err(err) => {
+                    return Ok(with_status(
+                        super::error("InvalidPeriod", &format!("{err:#}")),
+                        StatusCode::BAD_REQUEST,
+                    ))
+                }
+            };
+            let result = orderbook.get_solver_rewards(period.start, period.end).await;
+            Result::<_, Infallible>::Ok(convert_json_response(result))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_period() {
+        let period = "2022-10-01/2022-10-08".parse::<Period>().unwrap();
+        assert_eq!(period.start.to_rfc3339(), "2022-10-01T00:00:00+00:00");
+        assert_eq!(period.end.to_rfc3339(), "2022-10-08T00:00:00+00:00");
+        assert!("2022-10-01".parse::<Period>().is_err());
+        assert!("2022-10-01/2022-13-01".parse::<Period>().is_err());
+        assert!("2022-10-08/2022-10-01".parse::<Period>().is_err());
+        assert!("2022-10-01/2022-10-01".parse::<Period>().is_err());
+    }
+}
@@ -0,0 +1,29 @@
+use crate::orderbook::Orderbook;
+use anyhow::Result;
+use reqwest::StatusCode;
+use shared::api::{ApiReply, IntoWarpReply};
+use std::{convert::Infallible, sync::Arc};
+use warp::{reply::with_status, Filter, Rejection};
+
+fn get_stats_request() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::path!("stats").and(warp::get())
+}
+
+pub fn get_stats(
+    orderbook: Arc<Orderbook>,
+) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
+    get_stats_request().and_then(move || {
+        let orderbook = orderbook.clone();
+        async move {
+            let result = orderbook.get_stats().await;
+            let reply = match result {
+                Ok(stats) => with_status(warp::reply::json(&stats), StatusCode::OK),
+                Err(err) => {
+                    tracing::error!(?err, "/api/v1/stats");
+                    err.into_warp_reply()
+                }
+            };
+            Result::<_, Infallible>::Ok(reply)
+        }
+    })
+}
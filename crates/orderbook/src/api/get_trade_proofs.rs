@@ -0,0 +1,48 @@
+use crate::trade_proofs::TradeProofs;
+use model::order::OrderUid;
+use shared::api::{ApiReply, IntoWarpReply};
+use std::{convert::Infallible, sync::Arc};
+use warp::{hyper::StatusCode, reply, Filter, Rejection};
+
+fn get_trade_proofs_request() -> impl Filter<Extract = (OrderUid,), Error = Rejection> + Clone {
+    warp::path!("trades" / OrderUid / "proof").and(warp::get())
+}
+
+pub fn get_trade_proofs(
+    trade_proofs: Arc<TradeProofs>,
+) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
+    get_trade_proofs_request().and_then(move |uid| {
+        let trade_proofs = trade_proofs.clone();
+        async move {
+            let reply = match trade_proofs.proofs(&uid).await {
+                Ok(proofs) if proofs.is_empty() => reply::with_status(
+                    super::error("NotFound", "The order has no settled trades"),
+                    StatusCode::NOT_FOUND,
+                ),
+                Ok(proofs) => reply::with_status(reply::json(&proofs), StatusCode::OK),
+                Err(err) => {
+                    tracing::error!(?err, "/api/v1/trades/{uid}/proof");
+                    err.into_warp_reply()
+                }
+            };
+            Result::<_, Infallible>::Ok(reply)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::test::request;
+
+    #[tokio::test]
+    async fn get_trade_proofs_request_ok() {
+        let uid = OrderUid::default();
+        let request = request()
+            .path(&format!("/trades/{uid}/proof"))
+            .method("GET");
+        let filter = get_trade_proofs_request();
+        let result = request.filter(&filter).await.unwrap();
+        assert_eq!(result, uid);
+    }
+}
@@ -1,5 +1,6 @@
-use crate::database::trades::{TradeFilter, TradeRetrieving};
+use crate::database::trades::{TradeCursor, TradeFilter, TradeRetrieving};
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use model::order::OrderUid;
 use primitive_types::H160;
 use serde::Deserialize;
@@ -7,11 +8,28 @@ use shared::api::{convert_json_response, error, ApiReply};
 use std::{convert::Infallible, sync::Arc};
 use warp::{hyper::StatusCode, Filter, Rejection};
 
+const MIN_LIMIT: u64 = 1;
+const MAX_LIMIT: u64 = 1000;
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct Query {
     pub order_uid: Option<OrderUid>,
     pub owner: Option<H160>,
+    pub sell_token: Option<H160>,
+    pub buy_token: Option<H160>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub after: Option<String>,
+    pub limit: Option<u64>,
+    #[serde(default)]
+    pub group_by_settlement: bool,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+struct TradesRequest {
+    filter: TradeFilter,
+    group_by_settlement: bool,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -20,25 +38,49 @@ enum TradeFilterError {
 }
 
 impl Query {
-    fn trade_filter(&self) -> TradeFilter {
-        TradeFilter {
-            order_uid: self.order_uid,
-            owner: self.owner,
+    fn validate(&self) -> Result<TradesRequest, TradeFilterError> {
+        let invalid = |msg: &str| Err(TradeFilterError::InvalidFilter(msg.to_owned()));
+        match (self.order_uid, self.owner, self.limit) {
+            (Some(_), Some(_), _) => {
+                return invalid("Must specify at most one of owner and order_uid.")
+            }
+            // Listing all trades is only allowed page by page.
+            (None, None, None) => return invalid("Must specify owner, order_uid or limit."),
+            _ => (),
         }
-    }
-
-    fn validate(&self) -> Result<TradeFilter, TradeFilterError> {
-        match (self.order_uid.as_ref(), self.owner.as_ref()) {
-            (Some(_), None) | (None, Some(_)) => Ok(self.trade_filter()),
-            _ => Err(TradeFilterError::InvalidFilter(
-                "Must specify exactly one of owner and order_uid.".to_owned(),
-            )),
+        if let Some(limit) = self.limit {
+            if !(MIN_LIMIT..=MAX_LIMIT).contains(&limit) {
+                return invalid(&format!("The limit must be in [{MIN_LIMIT},{MAX_LIMIT}]."));
+            }
         }
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from >= to {
+                return invalid("The time range is empty.");
+            }
+        }
+        let after = match self.after.as_deref().map(str::parse::<TradeCursor>) {
+            Some(Ok(cursor)) => Some(cursor),
+            Some(Err(err)) => return invalid(&format!("Invalid cursor: {err:#}.")),
+            None => None,
+        };
+        Ok(TradesRequest {
+            filter: TradeFilter {
+                order_uid: self.order_uid,
+                owner: self.owner,
+                sell_token: self.sell_token,
+                buy_token: self.buy_token,
+                from: self.from,
+                to: self.to,
+                after,
+                limit: self.limit,
+            },
+            group_by_settlement: self.group_by_settlement,
+        })
     }
 }
 
 fn get_trades_request(
-) -> impl Filter<Extract = (Result<TradeFilter, TradeFilterError>,), Error = Rejection> + Clone {
+) -> impl Filter<Extract = (Result<TradesRequest, TradeFilterError>,), Error = Rejection> + Clone {
     warp::path!("trades")
         .and(warp::get())
         .and(warp::query::<Query>())
@@ -52,10 +94,20 @@ pub fn get_trades(
         let database = db.clone();
         async move {
             match request_result {
-                Ok(trade_filter) => {
-                    let result = database.trades(&trade_filter).await.context("get_trades");
+                Ok(TradesRequest {
+                    filter,
+                    group_by_settlement: true,
+                }) => {
+                    let result = database
+                        .settlement_trades(&filter)
+                        .await
+                        .context("get_settlement_trades");
                     Result::<_, Infallible>::Ok(convert_json_response(result))
                 }
+                Ok(TradesRequest { filter, .. }) => {
+                    let result = database.trades(&filter).await.context("get_trades");
+                    Ok(convert_json_response(result))
+                }
                 Err(TradeFilterError::InvalidFilter(msg)) => {
                     let err = error("InvalidTradeFilter", msg);
                     Ok(warp::reply::with_status(err, StatusCode::BAD_REQUEST))
@@ -76,7 +128,11 @@ mod tests {
     async fn get_trades_request_ok() {
         let trade_filter = |request: RequestBuilder| async move {
             let filter = get_trades_request();
-            request.method("GET").filter(&filter).await
+            request
+                .method("GET")
+                .filter(&filter)
+                .await
+                .map(|result| result.map(|request| request.filter))
         };
 
         let owner = H160::from_slice(&hex!("0000000000000000000000000000000000000001"));
@@ -98,6 +154,38 @@ mod tests {
         assert_eq!(result.order_uid, Some(uid));
     }
 
+    #[tokio::test]
+    async fn get_trades_request_paginated() {
+        let filter = get_trades_request();
+        let token = H160::from_low_u64_be(2);
+        let path = format!(
+            "/trades?sellToken=0x{token:x}&from=2022-10-01T00:00:00Z&to=2022-10-02T00:00:00Z\
+             &after=15000000_3&limit=100&groupBySettlement=true"
+        );
+        let result = request()
+            .path(&path)
+            .method("GET")
+            .filter(&filter)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(result.group_by_settlement);
+        assert_eq!(
+            result.filter,
+            TradeFilter {
+                sell_token: Some(token),
+                from: Some("2022-10-01T00:00:00Z".parse().unwrap()),
+                to: Some("2022-10-02T00:00:00Z".parse().unwrap()),
+                after: Some(TradeCursor {
+                    block_number: 15_000_000,
+                    log_index: 3,
+                }),
+                limit: Some(100),
+                ..Default::default()
+            }
+        );
+    }
+
     #[tokio::test]
     async fn get_trades_request_err() {
         let trade_filter = |request: RequestBuilder| async move {
@@ -115,5 +203,15 @@ mod tests {
         let path = "/trades";
         let result = trade_filter(request().path(path)).await.unwrap();
         assert!(result.is_err());
+
+        for path in [
+            "/trades?limit=0",
+            "/trades?limit=1001",
+            "/trades?limit=10&after=1",
+            "/trades?limit=10&from=2022-10-02T00:00:00Z&to=2022-10-01T00:00:00Z",
+        ] {
+            let result = trade_filter(request().path(path)).await.unwrap();
+            assert!(result.is_err(), "{path}");
+        }
     }
 }
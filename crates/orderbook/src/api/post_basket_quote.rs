@@ -0,0 +1,84 @@
+use super::post_quote::OrderQuoteErrorWrapper;
+use anyhow::Result;
+use model::quote::BasketQuoteRequest;
+use reqwest::StatusCode;
+use shared::{
+    api::{self, convert_json_response, error, ApiReply, IntoWarpReply},
+    order_quoting::{BasketQuoteError, QuoteHandler},
+};
+use std::{convert::Infallible, sync::Arc};
+use warp::{Filter, Rejection};
+
+fn post_basket_quote_request(
+) -> impl Filter<Extract = (BasketQuoteRequest,), Error = Rejection> + Clone {
+    warp::path!("quotes" / "basket")
+        .and(warp::post())
+        .and(api::extract_payload())
+}
+
+pub fn post_basket_quote(
+    quotes: Arc<QuoteHandler>,
+) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
+    post_basket_quote_request().and_then(move |request: BasketQuoteRequest| {
+        let quotes = quotes.clone();
+        async move {
+            let result = quotes
+                .calculate_basket_quote(&request)
+                .await
+                .map_err(BasketQuoteErrorWrapper);
+            if let Err(err) = &result {
+                tracing::warn!(?err, ?request, "post_basket_quote error");
+            }
+            Result::<_, Infallible>::Ok(convert_json_response(result))
+        }
+    })
+}
+
+#[derive(Debug)]
+pub struct BasketQuoteErrorWrapper(BasketQuoteError);
+impl IntoWarpReply for BasketQuoteErrorWrapper {
+    fn into_warp_reply(self) -> ApiReply {
+        match self.0 {
+            err @ BasketQuoteError::InvalidLegCount => warp::reply::with_status(
+                error("InvalidLegCount", err.to_string()),
+                StatusCode::BAD_REQUEST,
+            ),
+            BasketQuoteError::Leg { err, .. } => OrderQuoteErrorWrapper(err).into_warp_reply(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::api::response_body;
+    use warp::{test::request, Reply};
+
+    #[tokio::test]
+    async fn post_basket_quote_request_ok() {
+        let filter = post_basket_quote_request();
+        let request_payload = BasketQuoteRequest {
+            legs: vec![Default::default()],
+            ..Default::default()
+        };
+        let request = request()
+            .path("/quotes/basket")
+            .method("POST")
+            .header("content-type", "application/json")
+            .json(&request_payload);
+        let result = request.filter(&filter).await.unwrap();
+        assert_eq!(result, request_payload);
+    }
+
+    #[tokio::test]
+    async fn post_basket_quote_invalid_leg_count() {
+        let response = convert_json_response::<(), BasketQuoteErrorWrapper>(Err(
+            BasketQuoteErrorWrapper(BasketQuoteError::InvalidLegCount),
+        ))
+        .into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response_body(response).await;
+        let body: serde_json::Value = serde_json::from_slice(body.as_slice()).unwrap();
+        assert_eq!(body["errorType"], "InvalidLegCount");
+    }
+}
@@ -1,4 +1,5 @@
 use super::create_order::PartialValidationErrorWrapper;
+use crate::orderbook::Orderbook;
 use anyhow::Result;
 use model::quote::OrderQuoteRequest;
 use reqwest::StatusCode;
@@ -17,20 +18,28 @@ fn post_quote_request() -> impl Filter<Extract = (OrderQuoteRequest,), Error = R
 
 pub fn post_quote(
     quotes: Arc<QuoteHandler>,
+    orderbook: Arc<Orderbook>,
 ) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
-    post_quote_request().and_then(move |request: OrderQuoteRequest| {
-        let quotes = quotes.clone();
-        async move {
-            let result = quotes
-                .calculate_quote(&request)
-                .await
-                .map_err(OrderQuoteErrorWrapper);
-            if let Err(err) = &result {
-                tracing::warn!(?err, ?request, "post_quote error");
+    post_quote_request()
+        .and(warp::header::optional::<String>("user-agent"))
+        .and_then(move |request: OrderQuoteRequest, user_agent| {
+            let quotes = quotes.clone();
+            let orderbook = orderbook.clone();
+            async move {
+                let result = quotes
+                    .calculate_quote(&request)
+                    .await
+                    .map_err(OrderQuoteErrorWrapper);
+                if let Err(err) = &result {
+                    tracing::warn!(?err, ?request, "post_quote error");
+                }
+                let quote_id = result.as_ref().ok().and_then(|response| response.id);
+                orderbook
+                    .record_quote_request(request.app_data, user_agent, quote_id)
+                    .await;
+                Result::<_, Infallible>::Ok(convert_json_response(result))
             }
-            Result::<_, Infallible>::Ok(convert_json_response(result))
-        }
-    })
+        })
 }
 
 #[derive(Debug)]
@@ -262,7 +271,10 @@ mod tests {
             quote,
             from: H160::zero(),
             expiration: DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(0, 0), Utc),
+            guaranteed_until: None,
             id: Some(0),
+            threat_warnings: Default::default(),
+            cost_of_immediacy: None,
         };
         let response = convert_json_response::<OrderQuoteResponse, OrderQuoteErrorWrapper>(Ok(
             order_quote_response.clone(),
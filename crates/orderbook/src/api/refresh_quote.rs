@@ -0,0 +1,81 @@
+use anyhow::Result;
+use model::quote::{QuoteId, QuoteRefreshRequest};
+use reqwest::StatusCode;
+use shared::{
+    api::{self, convert_json_response, error, ApiReply, IntoWarpReply},
+    order_quoting::{QuoteHandler, RefreshQuoteError},
+};
+use std::{convert::Infallible, sync::Arc};
+use warp::{Filter, Rejection};
+
+fn refresh_quote_request(
+) -> impl Filter<Extract = (QuoteId, QuoteRefreshRequest), Error = Rejection> + Clone {
+    warp::path!("quotes" / QuoteId / "refresh")
+        .and(warp::post())
+        .and(api::extract_payload())
+}
+
+pub fn refresh_quote(
+    quotes: Arc<QuoteHandler>,
+) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
+    refresh_quote_request().and_then(move |id: QuoteId, request: QuoteRefreshRequest| {
+        let quotes = quotes.clone();
+        async move {
+            let result = quotes
+                .refresh_quote(id, &request)
+                .await
+                .map_err(RefreshQuoteErrorWrapper);
+            if let Err(err) = &result {
+                tracing::warn!(?err, %id, ?request, "refresh_quote error");
+            }
+            Result::<_, Infallible>::Ok(convert_json_response(result))
+        }
+    })
+}
+
+#[derive(Debug)]
+pub struct RefreshQuoteErrorWrapper(RefreshQuoteError);
+impl IntoWarpReply for RefreshQuoteErrorWrapper {
+    fn into_warp_reply(self) -> ApiReply {
+        match self.0 {
+            RefreshQuoteError::NotFound(_) => warp::reply::with_status(
+                error("NotFound", "Quote was not found"),
+                StatusCode::NOT_FOUND,
+            ),
+            RefreshQuoteError::Price(err) => err.into_warp_reply(),
+            RefreshQuoteError::Other(err) => err.into_warp_reply(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitive_types::H160;
+    use warp::{test::request, Reply};
+
+    #[tokio::test]
+    async fn refresh_quote_request_ok() {
+        let filter = refresh_quote_request();
+        let request_payload = QuoteRefreshRequest {
+            from: H160([1; 20]),
+            ..Default::default()
+        };
+        let request = request()
+            .path("/quotes/42/refresh")
+            .method("POST")
+            .header("content-type", "application/json")
+            .json(&request_payload);
+        let result = request.filter(&filter).await.unwrap();
+        assert_eq!(result, (42, request_payload));
+    }
+
+    #[tokio::test]
+    async fn refresh_quote_not_found() {
+        let response = convert_json_response::<(), RefreshQuoteErrorWrapper>(Err(
+            RefreshQuoteErrorWrapper(RefreshQuoteError::NotFound(42)),
+        ))
+        .into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::orderbook::{Orderbook, SealedOrderError};
+use anyhow::Result;
+use model::sealed_order::{SealedOrder, SealedOrderCreation, SealedOrderId, SealingKey};
+use shared::api::{error, extract_payload, internal_error, ApiReply, IntoWarpReply};
+use std::{convert::Infallible, sync::Arc};
+use warp::{
+    hyper::StatusCode,
+    reply::{self, with_status},
+    Filter, Rejection,
+};
+
+fn post_request() -> impl Filter<Extract = (SealedOrderCreation,), Error = Rejection> + Clone {
+    warp::path!("sealed_orders")
+        .and(warp::post())
+        .and(extract_payload())
+}
+
+fn get_request() -> impl Filter<Extract = (SealedOrderId,), Error = Rejection> + Clone {
+    warp::path!("sealed_orders" / SealedOrderId).and(warp::get())
+}
+
+fn get_key_request() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::path!("sealed_orders" / "key").and(warp::get())
+}
+
+impl IntoWarpReply for SealedOrderError {
+    fn into_warp_reply(self) -> ApiReply {
+        match self {
+            Self::Disabled => with_status(
+                error("SealedOrdersDisabled", "Sealed orders are not accepted"),
+                StatusCode::NOT_FOUND,
+            ),
+            Self::Malformed(reason) => with_status(
+                error("MalformedSealedOrder", reason),
+                StatusCode::BAD_REQUEST,
+            ),
+            Self::DatabaseDegraded(err) => err.into_warp_reply(),
+            Self::Other(err) => with_status(
+                internal_error(err.context("sealed_order")),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        }
+    }
+}
+
+fn post_response(result: Result<SealedOrderId, SealedOrderError>) -> ApiReply {
+    match result {
+        Ok(id) => with_status(reply::json(&id), StatusCode::CREATED),
+        Err(err) => err.into_warp_reply(),
+    }
+}
+
+fn get_response(result: Result<Option<SealedOrder>>) -> ApiReply {
+    match result {
+        Ok(Some(order)) => with_status(reply::json(&order), StatusCode::OK),
+        Ok(None) => with_status(
+            error("NotFound", "Sealed order was not found"),
+            StatusCode::NOT_FOUND,
+        ),
+        Err(err) => err.into_warp_reply(),
+    }
+}
+
+fn get_key_response(key: Option<SealingKey>) -> ApiReply {
+    match key {
+        Some(key) => with_status(reply::json(&key), StatusCode::OK),
+        None => SealedOrderError::Disabled.into_warp_reply(),
+    }
+}
+
+pub fn post(
+    orderbook: Arc<Orderbook>,
+) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
+    post_request().and_then(move |order| {
+        let orderbook = orderbook.clone();
+        async move {
+            let result = orderbook.add_sealed_order(order).await;
+            Result::<_, Infallible>::Ok(post_response(result))
+        }
+    })
+}
+
+pub fn get(
+    orderbook: Arc<Orderbook>,
+) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
+    get_request().and_then(move |id| {
+        let orderbook = orderbook.clone();
+        async move {
+            let result = orderbook.get_sealed_order(&id).await;
+            Result::<_, Infallible>::Ok(get_response(result))
+        }
+    })
+}
+
+pub fn get_key(
+    orderbook: Arc<Orderbook>,
+) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
+    get_key_request().map(move || get_key_response(orderbook.sealing_key()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitive_types::H256;
+    use serde_json::json;
+    use shared::api::response_body;
+    use warp::{test::request, Reply};
+
+    #[tokio::test]
+    async fn post_request_ok() {
+        let request = request()
+            .path("/sealed_orders")
+            .method("POST")
+            .header("content-type", "application/json")
+            .json(&json!({ "sealed": "0x0102" }));
+        let result = request.filter(&post_request()).await.unwrap();
+        assert_eq!(result, SealedOrderCreation { sealed: vec![1, 2] });
+    }
+
+    #[tokio::test]
+    async fn get_requests_ok() {
+        let id = H256([1; 32]);
+        let result = request()
+            .path(&format!("/sealed_orders/{:?}", id))
+            .method("GET")
+            .filter(&get_request())
+            .await
+            .unwrap();
+        assert_eq!(result, id);
+
+        let key = request().path("/sealed_orders/key").method("GET");
+        assert!(key.filter(&get_key_request()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn responses() {
+        let response = post_response(Err(SealedOrderError::Malformed(
+            "sealed order is too short".to_string(),
+        )))
+        .into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = get_key_response(None).into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = get_response(Ok(None)).into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = get_response(Ok(Some(SealedOrder {
+            id: H256([1; 32]),
+            status: model::sealed_order::SealedOrderStatus::Pending,
+        })))
+        .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value =
+            serde_json::from_slice(&response_body(response).await).unwrap();
+        assert_eq!(body["status"], "pending");
+    }
+}
@@ -0,0 +1,232 @@
+//! Endpoints through which registered solver teams inspect their solutions and rewards and
+//! configure their driver. Requests are authenticated with an API key of the team's owner.
+
+use super::{api_keys::API_KEY_HEADER, get_solver_rewards::Period};
+use crate::orderbook::{Orderbook, SolverTeamError};
+use anyhow::Result;
+use model::solver_team::SolverTeamConfig;
+use serde::Deserialize;
+use shared::api::{convert_json_response, error, extract_payload, internal_error, IntoWarpReply};
+use std::{convert::Infallible, sync::Arc};
+use warp::{hyper::StatusCode, reply::with_status, Filter, Rejection};
+
+#[derive(Clone, Debug, Deserialize)]
+struct CompetitionsQuery {
+    limit: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct RewardsQuery {
+    period: String,
+}
+
+fn get_request() -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::path!("solver_team")
+        .and(warp::get())
+        .and(warp::header(API_KEY_HEADER))
+}
+
+fn update_config_request(
+) -> impl Filter<Extract = (String, SolverTeamConfig), Error = Rejection> + Clone {
+    warp::path!("solver_team" / "config")
+        .and(warp::put())
+        .and(warp::header(API_KEY_HEADER))
+        .and(extract_payload())
+}
+
+fn competitions_request(
+) -> impl Filter<Extract = (String, CompetitionsQuery), Error = Rejection> + Clone {
+    warp::path!("solver_team" / "competitions")
+        .and(warp::get())
+        .and(warp::header(API_KEY_HEADER))
+        .and(warp::query::<CompetitionsQuery>())
+}
+
+fn rewards_request() -> impl Filter<Extract = (String, RewardsQuery), Error = Rejection> + Clone {
+    warp::path!("solver_team" / "rewards")
+        .and(warp::get())
+        .and(warp::header(API_KEY_HEADER))
+        .and(warp::query::<RewardsQuery>())
+}
+
+impl IntoWarpReply for SolverTeamError {
+    fn into_warp_reply(self) -> super::ApiReply {
+        match self {
+            Self::ApiKey(err) => err.into_warp_reply(),
+            Self::NotSolverTeam => with_status(
+                error(
+                    "NotSolverTeam",
+                    "API key owner is not a registered solver team",
+                ),
+                StatusCode::FORBIDDEN,
+            ),
+            Self::InvalidCallbackUrl => with_status(
+                error("InvalidCallbackUrl", "Callback URL is not a valid http URL"),
+                StatusCode::BAD_REQUEST,
+            ),
+            Self::Other(err) => with_status(
+                internal_error(err.context("solver_team")),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        }
+    }
+}
+
+/// Responds with the solver team of the owner of the API key.
+pub fn get(
+    orderbook: Arc<Orderbook>,
+) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
+    get_request().and_then(move |api_key: String| {
+        let orderbook = orderbook.clone();
+        async move {
+            let result = orderbook.get_solver_team(&api_key).await;
+            Result::<_, Infallible>::Ok(convert_json_response(result))
+        }
+    })
+}
+
+/// Replaces the driver configuration of the solver team and responds with the updated team.
+pub fn update_config(
+    orderbook: Arc<Orderbook>,
+) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
+    update_config_request().and_then(move |api_key: String, config| {
+        let orderbook = orderbook.clone();
+        async move {
+            let result = orderbook.update_solver_team_config(&api_key, config).await;
+            Result::<_, Infallible>::Ok(convert_json_response(result))
+        }
+    })
+}
+
+/// Responds with the most recent solver competitions the team's solver took part in.
+pub fn get_competitions(
+    orderbook: Arc<Orderbook>,
+) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
+    competitions_request().and_then(move |api_key: String, query: CompetitionsQuery| {
+        let orderbook = orderbook.clone();
+        async move {
+            const DEFAULT_LIMIT: u64 = 10;
+            const MIN_LIMIT: u64 = 1;
+            const MAX_LIMIT: u64 = 100;
+            let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+            if !(MIN_LIMIT..=MAX_LIMIT).contains(&limit) {
+                return Ok(with_status(
+                    super::error(
+                        "LIMIT_OUT_OF_BOUNDS",
+                        &format!("The limit is [{},{}].", MIN_LIMIT, MAX_LIMIT),
+                    ),
+                    StatusCode::BAD_REQUEST,
+                ));
+            }
+            let result = orderbook
+                .get_solver_team_competitions(&api_key, limit)
+                .await;
+            Result::<_, Infallible>::Ok(convert_json_response(result))
+        }
+    })
+}
+
+/// Responds with the rewards the team's solver accrued in the accounting period.
+pub fn get_rewards(
+    orderbook: Arc<Orderbook>,
+) -> impl Filter<Extract = (super::ApiReply,), Error = Rejection> + Clone {
+    rewards_request().and_then(move |api_key: String, query: RewardsQuery| {
+        let orderbook = orderbook.clone();
+        async move {
+            let period = match query.period.parse::<Period>() {
+                Ok(period) => period,
+                Err(err) => {
+                    return Ok(with_status(
+                        super::error("InvalidPeriod", &format!("{err:#}")),
+                        StatusCode::BAD_REQUEST,
+                    ))
+                }
+            };
+            let result = orderbook
+                .get_solver_team_rewards(&api_key, period.start, period.end)
+                .await;
+            Result::<_, Infallible>::Ok(convert_json_response(result))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::ApiKeyError;
+    use serde_json::json;
+    use warp::{test::request, Reply};
+
+    #[tokio::test]
+    async fn requests() {
+        let result = request()
+            .path("/solver_team")
+            .method("GET")
+            .header(API_KEY_HEADER, "key")
+            .filter(&get_request())
+            .await
+            .unwrap();
+        assert_eq!(result, "key");
+
+        let (api_key, config) = request()
+            .path("/solver_team/config")
+            .method("PUT")
+            .header(API_KEY_HEADER, "key")
+            .json(&json!({
+                "callbackUrl": "https://solver.test",
+            }))
+            .filter(&update_config_request())
+            .await
+            .unwrap();
+        assert_eq!(api_key, "key");
+        assert_eq!(
+            config,
+            SolverTeamConfig {
+                callback_url: Some("https://solver.test".to_string()),
+            }
+        );
+
+        let (_, query) = request()
+            .path("/solver_team/competitions?limit=5")
+            .method("GET")
+            .header(API_KEY_HEADER, "key")
+            .filter(&competitions_request())
+            .await
+            .unwrap();
+        assert_eq!(query.limit, Some(5));
+
+        let (_, query) = request()
+            .path("/solver_team/rewards?period=2022-10-01/2022-10-08")
+            .method("GET")
+            .header(API_KEY_HEADER, "key")
+            .filter(&rewards_request())
+            .await
+            .unwrap();
+        assert_eq!(query.period, "2022-10-01/2022-10-08");
+
+        // Requests without an API key don't match.
+        assert!(request()
+            .path("/solver_team")
+            .method("GET")
+            .filter(&get_request())
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn error_responses() {
+        let status = |err: SolverTeamError| err.into_warp_reply().into_response().status();
+        assert_eq!(
+            status(ApiKeyError::InvalidKey.into()),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(SolverTeamError::NotSolverTeam),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(SolverTeamError::InvalidCallbackUrl),
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
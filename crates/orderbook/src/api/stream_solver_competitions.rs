@@ -0,0 +1,62 @@
+use crate::solver_competition::CompetitionResults;
+use futures::Stream;
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use warp::{sse::Event, Filter, Rejection, Reply};
+
+fn request() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::path!("solver_competition" / "stream").and(warp::get())
+}
+
+/// Server-sent events with the results of solver competitions as they get stored.
+pub fn stream(
+    results: Arc<CompetitionResults>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    request().map(move || {
+        let events = events(results.subscribe());
+        warp::sse::reply(warp::sse::keep_alive().stream(events))
+    })
+}
+
+fn events<T>(receiver: Receiver<T>) -> impl Stream<Item = Result<Event, warp::Error>>
+where
+    T: Clone + Serialize,
+{
+    futures::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(result) => {
+                    let event = Event::default()
+                        .event("solver_competition")
+                        .json_data(&result);
+                    return Some((event, receiver));
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::debug!(skipped, "solver competition subscriber lagged behind");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use tokio::sync::broadcast;
+
+    #[tokio::test]
+    async fn skips_results_of_lagging_subscribers() {
+        let (sender, receiver) = broadcast::channel(2);
+        for i in 0..3 {
+            sender.send(i).unwrap();
+        }
+        drop(sender);
+
+        let events = events(receiver).collect::<Vec<_>>().await;
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(Result::is_ok));
+    }
+}
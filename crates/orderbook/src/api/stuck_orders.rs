@@ -0,0 +1,101 @@
+//! Explains why open orders don't get matched. Users see the diagnosis of their order while the
+//! report of stuck orders per app data is meant for partners and authenticated like the
+//! integrator analytics.
+
+use super::get_integrator_analytics::is_authorized;
+use crate::orderbook::Orderbook;
+use anyhow::Result;
+use model::{order::OrderUid, stuck_orders::OrderDiagnosis};
+use shared::api::{convert_json_response, ApiReply, IntoWarpReply};
+use std::{convert::Infallible, sync::Arc};
+use warp::{
+    hyper::StatusCode,
+    reply::{self, with_status},
+    Filter, Rejection,
+};
+
+fn diagnosis_request() -> impl Filter<Extract = (OrderUid,), Error = Rejection> + Clone {
+    warp::path!("orders" / OrderUid / "diagnosis").and(warp::get())
+}
+
+fn report_request() -> impl Filter<Extract = (Option<String>,), Error = Rejection> + Clone {
+    warp::path!("stuck_orders")
+        .and(warp::get())
+        .and(warp::header::optional::<String>("Authorization"))
+}
+
+fn diagnosis_response(result: Result<Option<OrderDiagnosis>>) -> ApiReply {
+    match result {
+        Ok(Some(diagnosis)) => with_status(reply::json(&diagnosis), StatusCode::OK),
+        Ok(None) => with_status(
+            super::error("NotFound", "Order was not found or is not stuck"),
+            StatusCode::NOT_FOUND,
+        ),
+        Err(err) => err.into_warp_reply(),
+    }
+}
+
+pub fn get_diagnosis(
+    orderbook: Arc<Orderbook>,
+) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
+    diagnosis_request().and_then(move |uid| {
+        let orderbook = orderbook.clone();
+        async move {
+            let result = orderbook.get_order_diagnosis(&uid).await;
+            Result::<_, Infallible>::Ok(diagnosis_response(result))
+        }
+    })
+}
+
+pub fn get_report(
+    orderbook: Arc<Orderbook>,
+    expected_auth: Option<String>,
+) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
+    report_request().and_then(move |auth: Option<String>| {
+        let orderbook = orderbook.clone();
+        let expected_auth = expected_auth.clone();
+        async move {
+            if !is_authorized(expected_auth.as_deref(), auth.as_deref()) {
+                return Result::<_, Infallible>::Ok(with_status(
+                    super::error("Unauthorized", ""),
+                    StatusCode::UNAUTHORIZED,
+                ));
+            }
+            let result = orderbook.get_stuck_orders().await;
+            Ok(convert_json_response(result))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::{test::request, Reply};
+
+    #[tokio::test]
+    async fn requests_ok() {
+        let uid = OrderUid::default();
+        let result = request()
+            .path(&format!("/orders/{:}/diagnosis", uid))
+            .method("GET")
+            .filter(&diagnosis_request())
+            .await
+            .unwrap();
+        assert_eq!(result, uid);
+
+        let auth = request()
+            .path("/stuck_orders")
+            .method("GET")
+            .header("authorization", "secret")
+            .filter(&report_request())
+            .await
+            .unwrap();
+        assert_eq!(auth.as_deref(), Some("secret"));
+    }
+
+    #[tokio::test]
+    async fn diagnosis_not_found() {
+        let response = diagnosis_response(Ok(None)).into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,116 @@
+use super::create_order::ValidationErrorWrapper;
+use crate::orderbook::Orderbook;
+use anyhow::Result;
+use model::order::{OrderValidationError, OrderValidationRequest, OrderValidationResult};
+use shared::{
+    api::{error, extract_payload, response_body, ApiReply, IntoWarpReply},
+    order_validation::ValidationError,
+};
+use std::{convert::Infallible, sync::Arc};
+use warp::{hyper::StatusCode, reply::with_status, Filter, Rejection, Reply};
+
+/// The maximum number of orders that can be validated in one request.
+const MAX_ORDERS: usize = 100;
+
+fn request() -> impl Filter<Extract = (Vec<OrderValidationRequest>,), Error = Rejection> + Clone {
+    warp::path!("orders" / "validate_batch")
+        .and(warp::post())
+        .and(extract_payload())
+}
+
+/// The result of an order in the format of the error placing it would respond with.
+async fn validation_result(result: Result<(), ValidationError>) -> OrderValidationResult {
+    let err = match result {
+        Ok(()) => {
+            return OrderValidationResult {
+                valid: true,
+                error: None,
+            }
+        }
+        Err(err) => err,
+    };
+    let body = response_body(
+        ValidationErrorWrapper(err)
+            .into_warp_reply()
+            .into_response(),
+    )
+    .await;
+    let error = serde_json::from_slice::<OrderValidationError>(&body).unwrap_or_else(|err| {
+        tracing::error!(?err, "failed to read validation error");
+        OrderValidationError {
+            error_type: "InternalServerError".to_string(),
+            description: String::new(),
+        }
+    });
+    OrderValidationResult {
+        valid: false,
+        error: Some(error),
+    }
+}
+
+pub fn validate_orders(
+    orderbook: Arc<Orderbook>,
+) -> impl Filter<Extract = (ApiReply,), Error = Rejection> + Clone {
+    request().and_then(move |orders: Vec<OrderValidationRequest>| {
+        let orderbook = orderbook.clone();
+        async move {
+            if orders.is_empty() || orders.len() > MAX_ORDERS {
+                return Result::<_, Infallible>::Ok(with_status(
+                    error(
+                        "InvalidBatchSize",
+                        format!("Must validate between 1 and {MAX_ORDERS} orders."),
+                    ),
+                    StatusCode::BAD_REQUEST,
+                ));
+            }
+            let mut results = Vec::with_capacity(orders.len());
+            for result in orderbook.validate_orders(&orders).await {
+                results.push(validation_result(result).await);
+            }
+            Ok(with_status(warp::reply::json(&results), StatusCode::OK))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitive_types::H160;
+    use shared::order_validation::PartialValidationError;
+    use warp::test::request as test_request;
+
+    #[tokio::test]
+    async fn validate_orders_request_ok() {
+        let filter = request();
+        let orders = vec![OrderValidationRequest {
+            from: H160::from_low_u64_be(1),
+            ..Default::default()
+        }];
+        let result = test_request()
+            .path("/orders/validate_batch")
+            .method("POST")
+            .header("content-type", "application/json")
+            .json(&orders)
+            .filter(&filter)
+            .await
+            .unwrap();
+        assert_eq!(result, orders);
+    }
+
+    #[tokio::test]
+    async fn validation_results_use_api_errors() {
+        assert_eq!(
+            validation_result(Ok(())).await,
+            OrderValidationResult {
+                valid: true,
+                error: None,
+            }
+        );
+        let result = validation_result(Err(ValidationError::Partial(
+            PartialValidationError::SameBuyAndSellToken,
+        )))
+        .await;
+        assert!(!result.valid);
+        assert_eq!(result.error.unwrap().error_type, "SameBuyAndSellToken");
+    }
+}
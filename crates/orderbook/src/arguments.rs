@@ -1,13 +1,26 @@
+use crate::database::StorageBackend;
 use anyhow::{anyhow, Context, Result};
 use model::app_id::AppId;
 use primitive_types::{H160, U256};
 use reqwest::Url;
+use secp256k1::PublicKey;
 use shared::fee_subsidy::cow_token::SubsidyTiers;
 use shared::{
-    arguments::display_option, bad_token::token_owner_finder, price_estimation::PriceEstimatorType,
+    account_balances::storage_proofs::Erc20StorageLayout,
+    arguments::{display_option, display_secret_option},
+    bad_token::token_owner_finder,
+    price_estimation::PriceEstimatorType,
     rate_limiter::RateLimitingStrategy,
+    trace_many::TracingBackend,
+};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    num::NonZeroUsize,
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
 };
-use std::{collections::HashMap, net::SocketAddr, num::NonZeroUsize, time::Duration};
 
 #[derive(clap::Parser)]
 pub struct Arguments {
@@ -17,18 +30,64 @@ pub struct Arguments {
     #[clap(flatten)]
     pub token_owner_finder: token_owner_finder::Arguments,
 
+    #[clap(flatten)]
+    pub threat_intel: shared::threat_intel::Arguments,
+
+    #[clap(flatten)]
+    pub token_list: shared::token_list::Arguments,
+
+    #[clap(flatten)]
+    pub protocol_fee: shared::protocol_fee::Arguments,
+
+    #[clap(flatten)]
+    pub immediacy: shared::immediacy::Arguments,
+
     /// A tracing Ethereum node URL to connect to, allowing a separate node URL
     /// to be used exclusively for tracing calls.
     #[clap(long, env)]
     pub tracing_node_url: Option<Url>,
 
+    /// How the bad token detection simulates token transfers. `Node` traces them with
+    /// `trace_callMany` and requires the tracing node. `Revm` simulates them locally with state
+    /// fetched from the tracing node or, if there is none, the regular node.
+    #[clap(long, env, arg_enum, ignore_case = true, default_value = "Node")]
+    pub token_detection_backend: TracingBackend,
+
     #[clap(long, env, default_value = "0.0.0.0:8080")]
     pub bind_address: SocketAddr,
 
+    /// Where the orderbook stores its data. The in-memory storage is meant for local development.
+    #[clap(long, env, default_value = "postgres", arg_enum)]
+    pub storage_backend: StorageBackend,
+
     /// Url of the Postgres database. By default connects to locally running postgres.
     #[clap(long, env, default_value = "postgresql://")]
     pub db_url: Url,
 
+    /// Apply pending database migrations at startup. Without this flag services refuse to start
+    /// if the database schema doesn't match the migrations they were built with.
+    #[clap(long, env)]
+    pub apply_database_migrations: bool,
+
+    /// Database statements that take longer than this many seconds get logged as warnings.
+    #[clap(
+        long,
+        env,
+        default_value = "1",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub db_slow_query_threshold: Duration,
+
+    /// How often in seconds the database is probed for whether it accepts writes. While it doesn't,
+    /// orders can't be placed or cancelled but quotes and cached order reads are still served.
+    #[clap(
+        long,
+        env,
+        default_value = "5",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub db_health_probe_interval: Duration,
+
     /// The minimum amount of time in seconds an order has to be valid for.
     #[clap(
         long,
@@ -83,6 +142,13 @@ pub struct Arguments {
     #[clap(long, env, use_value_delimiter = true)]
     pub banned_users: Vec<H160>,
 
+    /// JSON file with overrides for runtime tunable settings that gets read every time the
+    /// process receives SIGHUP. Supported keys are `unsupportedTokens`, `bannedUsers`,
+    /// `feeDiscount`, `minDiscountedFee` and `feeFactor`, missing keys keep the value of the
+    /// corresponding argument.
+    #[clap(long, env)]
+    pub reload_config_file: Option<PathBuf>,
+
     /// List of token addresses that should be allowed regardless of whether the bad token detector
     /// thinks they are bad. Base tokens are automatically allowed.
     #[clap(long, env, use_value_delimiter = true)]
@@ -108,6 +174,16 @@ pub struct Arguments {
     #[clap(long, default_value = "24")]
     pub solvable_orders_max_update_age_blocks: u64,
 
+    /// How often in seconds autopilot creates a new auction. Used to estimate when placed orders
+    /// enter an auction.
+    #[clap(
+        long,
+        env,
+        default_value = "2",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub auction_interval: Duration,
+
     /// A flat fee discount denominated in the network's native token (i.e. Ether for Mainnet).
     ///
     /// Note that flat fee discounts are applied BEFORE any multiplicative factors from either
@@ -123,11 +199,64 @@ pub struct Arguments {
     #[clap(long, env, default_value = "0")]
     pub min_discounted_fee: f64,
 
+    /// How long in seconds after their expiration the fees of stored quotes are still honored for
+    /// orders referencing them by id, even if gas prices rose since. 0 disables the guarantee.
+    /// Autopilot has to keep expired quotes at least as long, see its `--quote-retention`.
+    #[clap(
+        long,
+        env,
+        default_value = "0",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub fee_guarantee_window: Duration,
+
+    /// How much honoring fee guarantees may cost over the last 24 hours, denominated in the
+    /// network's native token. Honoring a quote costs the increase of the gas price since it was
+    /// computed times its gas amount.
+    #[clap(long, env, default_value = "0")]
+    pub fee_guarantee_daily_budget: f64,
+
+    /// The most orders the fixed overhead of a settlement gets shared between when quoting fees.
+    /// Orders of a token pair are expected to be batched with as many orders as the recent
+    /// settlements of the pair contained on average. 1 charges every order the full overhead.
+    #[clap(long, env, default_value = "1")]
+    pub fee_overhead_sharing_max_batch_size: f64,
+
+    /// The fraction the average batch size of a token pair gets reduced by before sharing the
+    /// overhead, so that fees still cover it when fewer orders than usual get batched.
+    #[clap(long, env, default_value = "0.5")]
+    pub fee_overhead_sharing_safety_margin: f64,
+
+    /// How far back in seconds settlements count towards the average batch size of a token pair.
+    #[clap(
+        long,
+        env,
+        default_value = "86400",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub fee_overhead_sharing_lookback: Duration,
+
     /// Gas Fee Factor: 1.0 means cost is forwarded to users alteration, 0.9 means there is a 10%
     /// subsidy, 1.1 means users pay 10% in fees than what we estimate we pay for gas.
     #[clap(long, env, default_value = "1", parse(try_from_str = shared::arguments::parse_unbounded_factor))]
     pub fee_factor: f64,
 
+    /// The half life in seconds of the moving average over the gas price that fees get quoted
+    /// with, so that momentary gas price spikes don't result in absurd fees. 0 quotes fees with
+    /// the live gas price.
+    #[clap(
+        long,
+        env,
+        default_value = "0",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub fee_gas_price_half_life: Duration,
+
+    /// The gas price that fees get quoted with stays within this factor of the live gas price.
+    /// For example 2 means between half and double the live gas price.
+    #[clap(long, env, default_value = "2", parse(try_from_str = shared::arguments::parse_unbounded_factor))]
+    pub fee_gas_price_max_deviation: f64,
+
     /// Used to specify additional fee subsidy factor based on app_ids contained in orders.
     /// Should take the form of a json string as shown in the following example:
     ///
@@ -144,6 +273,54 @@ pub struct Arguments {
     )]
     pub partner_additional_fee_factors: HashMap<AppId, f64>,
 
+    /// The owners of partners' app data who can cancel all open orders with their app data at
+    /// once using an API key, for example when their frontend quoted bad prices.
+    /// Format: '$PROJECT_APP_ID:$OWNER,...'
+    #[clap(
+        long,
+        env,
+        default_value = "",
+        parse(try_from_str = parse_app_data_owners),
+    )]
+    pub app_data_owners: HashMap<AppId, H160>,
+
+    /// Tokens whose balances and allowances get read from storage proofs verified against the
+    /// state root instead of calls when validating orders. Needs a node supporting `eth_getProof`.
+    /// Format: '$TOKEN:$BALANCES_SLOT:$ALLOWANCES_SLOT,...' with the storage slots of the token's
+    /// `balanceOf` and `allowance` mappings.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub balance_proof_tokens: Vec<Erc20StorageLayout>,
+
+    /// Value of the authorization header for the integrator analytics and stuck orders apis. The
+    /// apis are disabled if not set.
+    #[clap(long, env)]
+    pub analytics_auth: Option<String>,
+
+    /// The compressed public key in hex that users seal orders to, which the autopilot reveals
+    /// when it cuts an auction. Sealed orders are not accepted if not set.
+    #[clap(long, env)]
+    pub sealed_order_public_key: Option<PublicKey>,
+
+    /// The number of API requests each client can make per rate limit window. Requests are not
+    /// rate limited if not set.
+    #[clap(long, env)]
+    pub api_rate_limit: Option<u64>,
+
+    /// The length of the API rate limit window in seconds.
+    #[clap(
+        long,
+        env,
+        default_value = "60",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub api_rate_limit_window: Duration,
+
+    /// The addresses of the reverse proxies in front of the API. Requests from them are rate
+    /// limited by the client address in their `X-Forwarded-For` header, all other requests by
+    /// their remote address.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub api_trusted_proxies: Vec<IpAddr>,
+
     /// Used to configure how much of the regular fee a user should pay based on their
     /// COW + VCOW balance in base units on the current network.
     ///
@@ -174,6 +351,36 @@ pub struct Arguments {
     #[clap(long, env, default_value = "3")]
     pub native_price_cache_max_update_size: usize,
 
+    /// List of token addresses whose native prices get continuously precomputed in the background
+    /// so that they are always served from the cache. Should be the most frequently traded tokens.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub native_price_cache_hot_tokens: Vec<H160>,
+
+    /// Redis instance through which replicas of the orderbook coalesce identical native price
+    /// estimates so that only one of them computes each price. If not set every replica estimates
+    /// prices on its own.
+    #[clap(long, env)]
+    pub native_price_coalescing_redis_url: Option<Url>,
+
+    /// How long replicas wait for another replica to estimate a native price before estimating it
+    /// themselves.
+    #[clap(
+        long,
+        env,
+        default_value = "5",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub native_price_coalescing_lock_secs: Duration,
+
+    /// How long native prices estimated by one replica can be used by the others.
+    #[clap(
+        long,
+        env,
+        default_value = "10",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub native_price_coalescing_max_age_secs: Duration,
+
     /// Which estimators to use to estimate token prices in terms of the chain's native token.
     #[clap(
         long,
@@ -233,15 +440,66 @@ pub struct Arguments {
     /// The API endpoint for the Balancer SOR API for solving.
     #[clap(long, env)]
     pub balancer_sor_url: Option<Url>,
+
+    /// S3 compatible object storage endpoint to which old solver competitions get moved out of
+    /// the database. Archived competitions stay available through the API. Competitions don't get
+    /// archived if not set.
+    #[clap(long, env)]
+    pub competition_archive_endpoint: Option<Url>,
+
+    /// The bucket of the competition archive.
+    #[clap(long, env, default_value = "solver-competitions")]
+    pub competition_archive_bucket: String,
+
+    /// The region of the competition archive.
+    #[clap(long, env, default_value = "us-east-1")]
+    pub competition_archive_region: String,
+
+    /// The access key id for the competition archive.
+    #[clap(long, env, default_value = "")]
+    pub competition_archive_access_key_id: String,
+
+    /// The secret access key for the competition archive.
+    #[clap(long, env, default_value = "")]
+    pub competition_archive_secret_access_key: String,
+
+    /// The age in days after which solver competitions get archived.
+    #[clap(long, env, default_value = "30")]
+    pub competition_archive_age_days: u32,
 }
 
 impl std::fmt::Display for Arguments {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.shared)?;
         write!(f, "{}", self.token_owner_finder)?;
+        write!(f, "{}", self.threat_intel)?;
+        write!(f, "{}", self.token_list)?;
+        write!(f, "{}", self.protocol_fee)?;
+        write!(f, "{}", self.immediacy)?;
         display_option(f, "tracing_node_url", &self.tracing_node_url)?;
+        writeln!(
+            f,
+            "token_detection_backend: {:?}",
+            self.token_detection_backend
+        )?;
         writeln!(f, "bind_address: {}", self.bind_address)?;
+        writeln!(f, "storage_backend: {:?}", self.storage_backend)?;
         writeln!(f, "db_url: SECRET")?;
+        writeln!(
+            f,
+            "apply_database_migrations: {}",
+            self.apply_database_migrations
+        )?;
+        writeln!(
+            f,
+            "db_slow_query_threshold: {:?}",
+            self.db_slow_query_threshold
+        )?;
+        writeln!(
+            f,
+            "db_health_probe_interval: {:?}",
+            self.db_health_probe_interval
+        )?;
         writeln!(
             f,
             "min_order_validity_period: {:?}",
@@ -269,6 +527,11 @@ impl std::fmt::Display for Arguments {
         )?;
         writeln!(f, "unsupported_tokens: {:?}", self.unsupported_tokens)?;
         writeln!(f, "banned_users: {:?}", self.banned_users)?;
+        display_option(
+            f,
+            "reload_config_file",
+            &self.reload_config_file.as_ref().map(|path| path.display()),
+        )?;
         writeln!(f, "allowed_tokens: {:?}", self.allowed_tokens)?;
         writeln!(f, "pool_cache_lru_size: {}", self.pool_cache_lru_size)?;
         writeln!(f, "enable_eip1271_orders: {}", self.enable_eip1271_orders)?;
@@ -278,14 +541,53 @@ impl std::fmt::Display for Arguments {
             "solvable_orders_max_update_age_blocks: {}",
             self.solvable_orders_max_update_age_blocks,
         )?;
+        writeln!(f, "auction_interval: {:?}", self.auction_interval)?;
         writeln!(f, "fee_discount: {}", self.fee_discount)?;
         writeln!(f, "min_discounted_fee: {}", self.min_discounted_fee)?;
+        writeln!(f, "fee_guarantee_window: {:?}", self.fee_guarantee_window)?;
+        writeln!(
+            f,
+            "fee_guarantee_daily_budget: {}",
+            self.fee_guarantee_daily_budget
+        )?;
+        writeln!(
+            f,
+            "fee_overhead_sharing_max_batch_size: {}",
+            self.fee_overhead_sharing_max_batch_size
+        )?;
+        writeln!(
+            f,
+            "fee_overhead_sharing_safety_margin: {}",
+            self.fee_overhead_sharing_safety_margin
+        )?;
+        writeln!(
+            f,
+            "fee_overhead_sharing_lookback: {:?}",
+            self.fee_overhead_sharing_lookback
+        )?;
         writeln!(f, "fee_factor: {}", self.fee_factor)?;
+        writeln!(
+            f,
+            "fee_gas_price_half_life: {:?}",
+            self.fee_gas_price_half_life
+        )?;
+        writeln!(
+            f,
+            "fee_gas_price_max_deviation: {}",
+            self.fee_gas_price_max_deviation
+        )?;
         writeln!(
             f,
             "partner_additional_fee_factors: {:?}",
             self.partner_additional_fee_factors
         )?;
+        writeln!(f, "app_data_owners: {:?}", self.app_data_owners)?;
+        writeln!(f, "balance_proof_tokens: {:?}", self.balance_proof_tokens)?;
+        display_secret_option(f, "analytics_auth", &self.analytics_auth)?;
+        display_option(f, "sealed_order_public_key", &self.sealed_order_public_key)?;
+        display_option(f, "api_rate_limit", &self.api_rate_limit)?;
+        writeln!(f, "api_rate_limit_window: {:?}", self.api_rate_limit_window)?;
+        writeln!(f, "api_trusted_proxies: {:?}", self.api_trusted_proxies)?;
         writeln!(f, "cow_fee_factors: {:?}", self.cow_fee_factors)?;
         display_option(f, "quasimodo_solver_url", &self.quasimodo_solver_url)?;
         display_option(f, "yearn_solver_url", &self.yearn_solver_url)?;
@@ -299,6 +601,26 @@ impl std::fmt::Display for Arguments {
             "native_price_cache_max_update_size: {}",
             self.native_price_cache_max_update_size
         )?;
+        writeln!(
+            f,
+            "native_price_cache_hot_tokens: {:?}",
+            self.native_price_cache_hot_tokens
+        )?;
+        display_secret_option(
+            f,
+            "native_price_coalescing_redis_url",
+            &self.native_price_coalescing_redis_url,
+        )?;
+        writeln!(
+            f,
+            "native_price_coalescing_lock_secs: {:?}",
+            self.native_price_coalescing_lock_secs
+        )?;
+        writeln!(
+            f,
+            "native_price_coalescing_max_age_secs: {:?}",
+            self.native_price_coalescing_max_age_secs
+        )?;
         writeln!(
             f,
             "native_price_estimators: {:?}",
@@ -326,12 +648,51 @@ impl std::fmt::Display for Arguments {
             self.liquidity_order_owners
         )?;
         display_option(f, "balancer_sor_url", &self.balancer_sor_url)?;
+        display_option(
+            f,
+            "competition_archive_endpoint",
+            &self.competition_archive_endpoint,
+        )?;
+        writeln!(
+            f,
+            "competition_archive_bucket: {}",
+            self.competition_archive_bucket
+        )?;
+        writeln!(
+            f,
+            "competition_archive_region: {}",
+            self.competition_archive_region
+        )?;
+        writeln!(
+            f,
+            "competition_archive_access_key_id: {}",
+            self.competition_archive_access_key_id
+        )?;
+        writeln!(f, "competition_archive_secret_access_key: SECRET")?;
+        writeln!(
+            f,
+            "competition_archive_age_days: {}",
+            self.competition_archive_age_days
+        )?;
         Ok(())
     }
 }
 
 /// Parses a comma separated list of colon separated values representing fee factors for AppIds.
 fn parse_partner_fee_factor(s: &str) -> Result<HashMap<AppId, f64>> {
+    parse_app_id_map(s, "fee factor")
+}
+
+/// Parses a comma separated list of colon separated AppIds and the addresses owning them.
+fn parse_app_data_owners(s: &str) -> Result<HashMap<AppId, H160>> {
+    parse_app_id_map(s, "owner")
+}
+
+fn parse_app_id_map<T>(s: &str, value_name: &str) -> Result<HashMap<AppId, T>>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
     let mut res = HashMap::default();
     if s.is_empty() {
         return Ok(res);
@@ -348,8 +709,8 @@ fn parse_partner_fee_factor(s: &str) -> Result<HashMap<AppId, f64>> {
             .next()
             .ok_or_else(|| anyhow!("missing value"))?
             .trim()
-            .parse::<f64>()
-            .context("failed to parse fee factor")?;
+            .parse::<T>()
+            .with_context(|| format!("failed to parse {value_name}"))?;
         if split.next().is_some() {
             return Err(anyhow!("Invalid pair lengths"));
         }
@@ -384,6 +745,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_app_data_owners_ok() {
+        let app_data = "0x0101010101010101010101010101010101010101010101010101010101010101";
+        let owner = "0x0202020202020202020202020202020202020202";
+        assert_eq!(
+            parse_app_data_owners(&format!("{app_data}:{owner}")).unwrap(),
+            hashmap! { AppId([1u8; 32]) => H160([2u8; 20]) }
+        );
+        assert!(parse_app_data_owners(&format!("{app_data}:0.5")).is_err());
+    }
+
     #[test]
     fn parse_partner_fee_factor_err() {
         assert!(parse_partner_fee_factor("0x1:0.5,0x2:0.7").is_err());
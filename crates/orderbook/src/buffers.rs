@@ -0,0 +1,142 @@
+//! Serves the token balances of the settlement contract, its so called buffers.
+//!
+//! Balances get fetched for all tokens of the current auction and are cached until the next
+//! auction so that polling dashboards don't cause a node request per token on every call.
+
+use crate::orderbook::Orderbook;
+use anyhow::Result;
+use model::{
+    auction::AuctionWithId,
+    buffers::{Buffers, TokenBuffer},
+};
+use primitive_types::U256;
+use shared::buffers::BufferRetrieving;
+use std::sync::{Arc, Mutex};
+
+pub struct BufferCache {
+    orderbook: Arc<Orderbook>,
+    retriever: Arc<dyn BufferRetrieving>,
+    cache: Mutex<Option<Buffers>>,
+}
+
+impl BufferCache {
+    pub fn new(orderbook: Arc<Orderbook>, retriever: Arc<dyn BufferRetrieving>) -> Self {
+        Self {
+            orderbook,
+            retriever,
+            cache: Default::default(),
+        }
+    }
+
+    /// Returns the buffers for the tokens of the current auction or `None` if there is no
+    /// auction.
+    pub async fn get(&self) -> Result<Option<Buffers>> {
+        let auction = match self.orderbook.get_auction().await? {
+            Some(auction) => auction,
+            None => return Ok(None),
+        };
+        if let Some(cached) = self.cache.lock().unwrap().as_ref() {
+            if cached.auction_id == auction.id {
+                return Ok(Some(cached.clone()));
+            }
+        }
+        let buffers = fetch_buffers(self.retriever.as_ref(), &auction).await;
+        *self.cache.lock().unwrap() = Some(buffers.clone());
+        Ok(Some(buffers))
+    }
+}
+
+async fn fetch_buffers(retriever: &dyn BufferRetrieving, auction: &AuctionWithId) -> Buffers {
+    let prices = &auction.auction.prices;
+    let tokens = prices.keys().copied().collect::<Vec<_>>();
+    let mut balances = retriever.get_buffers(&tokens).await;
+    let tokens = tokens
+        .into_iter()
+        .filter_map(|token| match balances.remove(&token)? {
+            Ok(balance) => Some(TokenBuffer {
+                token,
+                balance,
+                native_value: native_value(balance, prices[&token]),
+            }),
+            Err(err) => {
+                tracing::warn!(?token, ?err, "failed to fetch buffer");
+                None
+            }
+        })
+        .collect();
+    Buffers {
+        auction_id: auction.id,
+        block: auction.auction.block,
+        tokens,
+    }
+}
+
+/// Auction prices are denominated so that an amount of a token is worth
+/// `amount * price / 1e18` of the native token.
+fn native_value(balance: U256, price: U256) -> U256 {
+    let value = balance.full_mul(price) / U256::exp10(18);
+    value.try_into().unwrap_or(U256::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::{btreemap, hashmap};
+    use model::auction::Auction;
+    use primitive_types::H160;
+    use shared::buffers::{BufferRetrievalError, MockBufferRetrieving};
+
+    #[tokio::test]
+    async fn fetches_buffers_of_auction_tokens() {
+        let token = |byte| H160([byte; 20]);
+        let auction = AuctionWithId {
+            id: 1,
+            auction: Auction {
+                block: 2,
+                prices: btreemap! {
+                    token(1) => U256::exp10(18),
+                    token(2) => U256::exp10(17),
+                    token(3) => U256::exp10(18),
+                },
+                ..Default::default()
+            },
+        };
+        let mut retriever = MockBufferRetrieving::new();
+        retriever
+            .expect_get_buffers()
+            .withf(move |tokens| tokens == [token(1), token(2), token(3)])
+            .returning(move |_| {
+                hashmap! {
+                    token(1) => Ok(5.into()),
+                    token(2) => Ok(50.into()),
+                    token(3) => Err(BufferRetrievalError::Eth(web3::Error::Internal)),
+                }
+            });
+
+        let buffers = fetch_buffers(&retriever, &auction).await;
+        assert_eq!(
+            buffers,
+            Buffers {
+                auction_id: 1,
+                block: 2,
+                tokens: vec![
+                    TokenBuffer {
+                        token: token(1),
+                        balance: 5.into(),
+                        native_value: 5.into(),
+                    },
+                    TokenBuffer {
+                        token: token(2),
+                        balance: 50.into(),
+                        native_value: 5.into(),
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn native_value_saturates() {
+        assert_eq!(native_value(U256::MAX, U256::exp10(19)), U256::MAX);
+    }
+}
@@ -1,20 +1,190 @@
+pub mod api_keys;
+pub mod app_data_cancellations;
 pub mod auctions;
+pub mod health;
+pub mod in_memory;
+pub mod order_book_stats;
+pub mod order_diagnoses;
 pub mod orders;
+pub mod protocol_fees;
+pub mod quote_requests;
 pub mod quotes;
+pub mod sealed_orders;
 pub mod solver_competition;
+pub mod solver_rewards;
+pub mod solver_teams;
 pub mod trades;
 
+use self::{
+    orders::{InsertionError, OrderStoring},
+    quote_requests::QuoteRequestAttribution,
+    solver_competition::CompetitionFilter,
+    trades::TradeRetrieving,
+};
+use crate::{object_storage::ObjectStorage, solver_competition::SolverCompetitionStoring};
 use anyhow::Result;
-use sqlx::PgPool;
+use chrono::{DateTime, Utc};
+use model::{
+    app_id::AppId,
+    auction::AuctionWithId,
+    fee_policy::OrderFeePolicy,
+    fee_recommendation::FeeRecommendation,
+    integrator_analytics::IntegratorFunnel,
+    order::OrderUid,
+    order_book_stats::OrderBookStats,
+    protocol_fees::ProtocolFeeAccrual,
+    sealed_order::{SealedOrder, SealedOrderId},
+    solver_competition::{CompetitionSummary, SolverCompetition},
+    solver_rewards::SolverRewards,
+    solver_team::{SolverTeam, SolverTeamConfig},
+    stuck_orders::{OrderDiagnosis, StuckOrders},
+};
+use primitive_types::{H160, H256, U256};
+use shared::order_quoting::QuoteStoring;
+use sqlx::{postgres::PgConnectOptions, ConnectOptions, PgPool};
+use std::{ops::RangeInclusive, sync::Arc, time::Duration};
 
 // TODO: There is remaining optimization potential by implementing sqlx encoding and decoding for
 // U256 directly instead of going through BigDecimal. This is not very important as this is fast
 // enough anyway.
 
+/// Where the orderbook stores its data.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ArgEnum)]
+pub enum StorageBackend {
+    Postgres,
+    /// Keeps all data in memory so that the orderbook can run without a database, for example
+    /// for local development. Nothing gets indexed from the chain so orders are never traded and
+    /// pre-signatures never get set, there are no auctions, solver competitions don't get
+    /// streamed and sealed orders are never revealed.
+    InMemory,
+}
+
+/// The storage backend as the trait objects the components of the orderbook use.
+#[derive(Clone)]
+pub struct Storage {
+    pub orderbook: Arc<dyn OrderbookStoring>,
+    pub trades: Arc<dyn TradeRetrieving>,
+    pub quotes: Arc<dyn QuoteStoring>,
+    pub solver_competitions: Arc<dyn SolverCompetitionStoring>,
+}
+
+impl Storage {
+    pub fn new<T>(storage: Arc<T>) -> Self
+    where
+        T: OrderbookStoring + TradeRetrieving + QuoteStoring + SolverCompetitionStoring + 'static,
+    {
+        Self {
+            orderbook: storage.clone(),
+            trades: storage.clone(),
+            quotes: storage.clone(),
+            solver_competitions: storage,
+        }
+    }
+}
+
+/// The storage the orderbook needs besides orders, trades, quotes and solver competitions.
+#[async_trait::async_trait]
+pub trait OrderbookStoring: OrderStoring {
+    /// Stores a new API key of `owner`, revoking the key it replaces if there is one.
+    ///
+    /// Returns `false` without storing the new key if the replaced key does not exist, is already
+    /// revoked or belongs to a different owner.
+    async fn insert_api_key(
+        &self,
+        key_hash: H256,
+        owner: H160,
+        valid_to: u32,
+        replaces: Option<H256>,
+        now: DateTime<Utc>,
+    ) -> Result<bool, InsertionError>;
+    /// Returns the owner of an API key that is neither revoked nor expired.
+    async fn active_api_key_owner(
+        &self,
+        key_hash: H256,
+        now: DateTime<Utc>,
+    ) -> Result<Option<H160>>;
+    /// Revokes an API key of `owner`. Returns whether a key was revoked.
+    async fn revoke_api_key(&self, key_hash: H256, owner: H160, now: DateTime<Utc>)
+        -> Result<bool>;
+    /// Cancels all open orders with the app data on behalf of `owner` and returns their uids.
+    async fn cancel_orders_by_app_data(
+        &self,
+        app_data: &AppId,
+        owner: H160,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<OrderUid>>;
+    async fn most_recent_auction(&self) -> Result<Option<AuctionWithId>>;
+    /// The block and creation time of the most recent auction.
+    async fn most_recent_auction_creation(&self) -> Result<Option<(u64, DateTime<Utc>)>>;
+    async fn order_book_stats(&self) -> Result<OrderBookStats>;
+    async fn solver_rewards(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<SolverRewards>>;
+    async fn protocol_fees(&self) -> Result<Vec<ProtocolFeeAccrual>>;
+    /// Explains the fee of an order from the quote it was created with. `None` if the order
+    /// doesn't exist or wasn't created with a quote.
+    async fn order_fee_policy(&self, uid: &OrderUid) -> Result<Option<OrderFeePolicy>>;
+    /// Returns the solver team registered for `owner`.
+    async fn solver_team(&self, owner: H160) -> Result<Option<SolverTeam>>;
+    /// Replaces the configuration of the solver team of `owner`. Returns whether the team exists.
+    async fn update_solver_team_config(
+        &self,
+        owner: H160,
+        config: &SolverTeamConfig,
+    ) -> Result<bool>;
+    /// Returns the most recent solver competitions the solver took part in, newest first.
+    async fn solver_competitions_of_solver(
+        &self,
+        solver: &str,
+        limit: u64,
+    ) -> Result<Vec<SolverCompetition>>;
+    /// Returns the stored solver competitions matching the filter, newest first.
+    async fn search_solver_competitions(
+        &self,
+        filter: &CompetitionFilter,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<CompetitionSummary>>;
+    /// Logs who requested a quote.
+    async fn insert_quote_request(&self, request: &QuoteRequestAttribution) -> Result<()>;
+    /// Returns the quote funnel of every app data in the days from `start` to `end` as of the
+    /// last refresh.
+    async fn integrator_funnels(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<IntegratorFunnel>>;
+    /// Summarizes the costs of at most `max_samples` of the most recent trades selling
+    /// `sell_token` for `buy_token` that settled since `since` and whose sell amount including
+    /// the fee lies in `sell_amounts`.
+    async fn fee_recommendation(
+        &self,
+        sell_token: H160,
+        buy_token: H160,
+        sell_amounts: RangeInclusive<U256>,
+        since: DateTime<Utc>,
+        max_samples: u64,
+    ) -> Result<FeeRecommendation>;
+    /// Stores a sealed order until the autopilot reveals it and returns its id. Storing the same
+    /// sealed order again is a no-op.
+    async fn insert_sealed_order(&self, sealed: &[u8], now: DateTime<Utc>)
+        -> Result<SealedOrderId>;
+    async fn sealed_order(&self, id: &SealedOrderId) -> Result<Option<SealedOrder>>;
+    /// The latest diagnosis of why the order doesn't get matched. `None` if the order isn't
+    /// considered stuck.
+    async fn order_diagnosis(&self, uid: &OrderUid) -> Result<Option<OrderDiagnosis>>;
+    /// Counts the stuck orders of every app data by reason.
+    async fn stuck_orders(&self) -> Result<Vec<StuckOrders>>;
+}
+
 // The pool uses an Arc internally.
 #[derive(Clone)]
 pub struct Postgres {
     pub pool: PgPool,
+    /// Where archived solver competitions are stored.
+    competition_archive: Option<Arc<dyn ObjectStorage>>,
 }
 
 // The implementation is split up into several modules which contain more public methods.
@@ -23,8 +193,212 @@ impl Postgres {
     pub fn new(uri: &str) -> Result<Self> {
         Ok(Self {
             pool: PgPool::connect_lazy(uri)?,
+            competition_archive: None,
+        })
+    }
+
+    /// Like `new` but logs statements that take longer than the threshold as warnings.
+    pub fn with_slow_query_threshold(uri: &str, threshold: Duration) -> Result<Self> {
+        let mut options: PgConnectOptions = uri.parse()?;
+        options.log_slow_statements(log::LevelFilter::Warn, threshold);
+        Ok(Self {
+            pool: PgPool::connect_lazy_with(options),
+            competition_archive: None,
         })
     }
+
+    /// Loads archived solver competitions from the object storage.
+    pub fn with_competition_archive(mut self, archive: Arc<dyn ObjectStorage>) -> Self {
+        self.competition_archive = Some(archive);
+        self
+    }
+
+    /// The tables that don't exist because not all migrations have been applied.
+    pub async fn missing_tables(&self) -> Result<Vec<&'static str>> {
+        let mut ex = self.pool.acquire().await?;
+        Ok(database::missing_tables(&mut ex).await?)
+    }
+
+    /// Verifies that the schema matches the migrations of this binary and applies pending
+    /// migrations if allowed. Returns the versions of the applied migrations.
+    pub async fn ensure_schema(&self, apply_migrations: bool) -> Result<Vec<u32>> {
+        let mut ex = self.pool.acquire().await?;
+        Ok(database::migrations::ensure_schema(&mut ex, apply_migrations).await?)
+    }
+
+    pub fn update_pool_metrics(&self) {
+        let metrics = Metrics::get();
+        let connections = self.pool.size();
+        let idle = self.pool.num_idle() as u32;
+        let max_connections = self.pool.options().get_max_connections();
+        metrics
+            .database_connections
+            .with_label_values(&["idle"])
+            .set(idle.into());
+        metrics
+            .database_connections
+            .with_label_values(&["active"])
+            .set(connections.saturating_sub(idle).into());
+        metrics
+            .database_pool_saturation
+            .set(connections.saturating_sub(idle) as f64 / max_connections as f64);
+    }
+}
+
+// The Postgres implementations live in the modules of the individual tables.
+#[async_trait::async_trait]
+impl OrderbookStoring for Postgres {
+    async fn insert_api_key(
+        &self,
+        key_hash: H256,
+        owner: H160,
+        valid_to: u32,
+        replaces: Option<H256>,
+        now: DateTime<Utc>,
+    ) -> Result<bool, InsertionError> {
+        Postgres::insert_api_key(self, key_hash, owner, valid_to, replaces, now).await
+    }
+
+    async fn active_api_key_owner(
+        &self,
+        key_hash: H256,
+        now: DateTime<Utc>,
+    ) -> Result<Option<H160>> {
+        Postgres::active_api_key_owner(self, key_hash, now).await
+    }
+
+    async fn revoke_api_key(
+        &self,
+        key_hash: H256,
+        owner: H160,
+        now: DateTime<Utc>,
+    ) -> Result<bool> {
+        Postgres::revoke_api_key(self, key_hash, owner, now).await
+    }
+
+    async fn cancel_orders_by_app_data(
+        &self,
+        app_data: &AppId,
+        owner: H160,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<OrderUid>> {
+        Postgres::cancel_orders_by_app_data(self, app_data, owner, now).await
+    }
+
+    async fn most_recent_auction(&self) -> Result<Option<AuctionWithId>> {
+        Postgres::most_recent_auction(self).await
+    }
+
+    async fn most_recent_auction_creation(&self) -> Result<Option<(u64, DateTime<Utc>)>> {
+        Postgres::most_recent_auction_creation(self).await
+    }
+
+    async fn order_book_stats(&self) -> Result<OrderBookStats> {
+        Postgres::order_book_stats(self).await
+    }
+
+    async fn solver_rewards(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<SolverRewards>> {
+        Postgres::solver_rewards(self, start, end).await
+    }
+
+    async fn protocol_fees(&self) -> Result<Vec<ProtocolFeeAccrual>> {
+        Postgres::protocol_fees(self).await
+    }
+
+    async fn order_fee_policy(&self, uid: &OrderUid) -> Result<Option<OrderFeePolicy>> {
+        Postgres::order_fee_policy(self, uid).await
+    }
+
+    async fn solver_team(&self, owner: H160) -> Result<Option<SolverTeam>> {
+        Postgres::solver_team(self, owner).await
+    }
+
+    async fn update_solver_team_config(
+        &self,
+        owner: H160,
+        config: &SolverTeamConfig,
+    ) -> Result<bool> {
+        Postgres::update_solver_team_config(self, owner, config).await
+    }
+
+    async fn solver_competitions_of_solver(
+        &self,
+        solver: &str,
+        limit: u64,
+    ) -> Result<Vec<SolverCompetition>> {
+        Postgres::solver_competitions_of_solver(self, solver, limit).await
+    }
+
+    async fn search_solver_competitions(
+        &self,
+        filter: &CompetitionFilter,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<CompetitionSummary>> {
+        Postgres::search_solver_competitions(self, filter, offset, limit).await
+    }
+
+    async fn insert_quote_request(&self, request: &QuoteRequestAttribution) -> Result<()> {
+        Postgres::insert_quote_request(self, request).await
+    }
+
+    async fn integrator_funnels(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<IntegratorFunnel>> {
+        Postgres::integrator_funnels(self, start, end).await
+    }
+
+    async fn fee_recommendation(
+        &self,
+        sell_token: H160,
+        buy_token: H160,
+        sell_amounts: RangeInclusive<U256>,
+        since: DateTime<Utc>,
+        max_samples: u64,
+    ) -> Result<FeeRecommendation> {
+        Postgres::fee_recommendation(
+            self,
+            sell_token,
+            buy_token,
+            sell_amounts,
+            since,
+            max_samples,
+        )
+        .await
+    }
+
+    async fn insert_sealed_order(
+        &self,
+        sealed: &[u8],
+        now: DateTime<Utc>,
+    ) -> Result<SealedOrderId> {
+        Postgres::insert_sealed_order(self, sealed, now).await
+    }
+
+    async fn sealed_order(&self, id: &SealedOrderId) -> Result<Option<SealedOrder>> {
+        Postgres::sealed_order(self, id).await
+    }
+
+    async fn order_diagnosis(&self, uid: &OrderUid) -> Result<Option<OrderDiagnosis>> {
+        Postgres::order_diagnosis(self, uid).await
+    }
+
+    async fn stuck_orders(&self) -> Result<Vec<StuckOrders>> {
+        Postgres::stuck_orders(self).await
+    }
+}
+
+pub async fn pool_metrics(db: Postgres) -> ! {
+    loop {
+        db.update_pool_metrics();
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
 }
 
 #[derive(prometheus_metric_storage::MetricStorage)]
@@ -32,6 +406,23 @@ struct Metrics {
     /// Timing of db queries.
     #[metric(name = "orderbook_database_queries", labels("type"))]
     database_queries: prometheus::HistogramVec,
+
+    /// Timing of db transactions from begin to commit, excluding the time spent waiting for a
+    /// connection.
+    #[metric(name = "orderbook_database_transactions", labels("type"))]
+    database_transactions: prometheus::HistogramVec,
+
+    /// Number of connections in the pool by state.
+    #[metric(name = "orderbook_database_connections", labels("state"))]
+    database_connections: prometheus::IntGaugeVec,
+
+    /// Ratio of active connections to the maximum size of the pool.
+    #[metric(name = "orderbook_database_pool_saturation")]
+    database_pool_saturation: prometheus::Gauge,
+
+    /// 1 for the current state of the database, 0 for the others.
+    #[metric(name = "orderbook_database_state", labels("state"))]
+    database_state: prometheus::IntGaugeVec,
 }
 
 impl Metrics {
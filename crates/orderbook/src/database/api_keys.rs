@@ -0,0 +1,147 @@
+use super::orders::InsertionError;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use database::{api_keys::ApiKey, byte_array::ByteArray};
+use primitive_types::{H160, H256};
+use sqlx::Connection;
+
+impl super::Postgres {
+    /// Stores a new API key of `owner`, revoking the key it replaces if there is one.
+    ///
+    /// Returns `false` without storing the new key if the replaced key does not exist, is already
+    /// revoked or belongs to a different owner.
+    pub async fn insert_api_key(
+        &self,
+        key_hash: H256,
+        owner: H160,
+        valid_to: u32,
+        replaces: Option<H256>,
+        now: DateTime<Utc>,
+    ) -> Result<bool, InsertionError> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["insert_api_key"])
+            .start_timer();
+
+        let key = ApiKey {
+            key_hash: ByteArray(key_hash.0),
+            owner: ByteArray(owner.0),
+            valid_to: valid_to as i64,
+            creation_timestamp: now,
+            revocation_timestamp: None,
+        };
+        let mut ex = self.pool.acquire().await?;
+        let mut transaction = ex.begin().await?;
+        let _transaction_timer = super::Metrics::get()
+            .database_transactions
+            .with_label_values(&["insert_api_key"])
+            .start_timer();
+        let inserted = match replaces {
+            Some(replaces) => {
+                database::api_keys::rotate(&mut transaction, &key, &ByteArray(replaces.0)).await
+            }
+            None => database::api_keys::insert(&mut transaction, &key)
+                .await
+                .map(|_| true),
+        }
+        .map_err(|err| {
+            if database::orders::is_duplicate_record_error(&err) {
+                InsertionError::DuplicatedRecord
+            } else {
+                InsertionError::DbError(err)
+            }
+        })?;
+        if inserted {
+            transaction.commit().await?;
+        }
+        Ok(inserted)
+    }
+
+    /// Returns the owner of an API key that is neither revoked nor expired.
+    pub async fn active_api_key_owner(
+        &self,
+        key_hash: H256,
+        now: DateTime<Utc>,
+    ) -> Result<Option<H160>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["active_api_key_owner"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let owner = database::api_keys::active_key_owner(&mut ex, &ByteArray(key_hash.0), now)
+            .await
+            .context("active_api_key_owner")?;
+        Ok(owner.map(|owner| H160(owner.0)))
+    }
+
+    /// Revokes an API key of `owner`. Returns whether a key was revoked.
+    pub async fn revoke_api_key(
+        &self,
+        key_hash: H256,
+        owner: H160,
+        now: DateTime<Utc>,
+    ) -> Result<bool> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["revoke_api_key"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        database::api_keys::revoke(&mut ex, &ByteArray(key_hash.0), &ByteArray(owner.0), now)
+            .await
+            .context("revoke_api_key")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Postgres;
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_api_key_lifecycle() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let owner = H160([1; 20]);
+        let old_key = H256([2; 32]);
+        let new_key = H256([3; 32]);
+        let now = Utc::now();
+        let valid_to = u32::MAX;
+
+        assert!(db
+            .insert_api_key(old_key, owner, valid_to, None, now)
+            .await
+            .unwrap());
+        assert!(matches!(
+            db.insert_api_key(old_key, owner, valid_to, None, now).await,
+            Err(InsertionError::DuplicatedRecord)
+        ));
+        assert_eq!(
+            db.active_api_key_owner(old_key, now).await.unwrap(),
+            Some(owner)
+        );
+
+        // Rotating a key of a different owner fails and does not store the new key.
+        assert!(!db
+            .insert_api_key(new_key, H160([4; 20]), valid_to, Some(old_key), now)
+            .await
+            .unwrap());
+        assert_eq!(db.active_api_key_owner(new_key, now).await.unwrap(), None);
+
+        assert!(db
+            .insert_api_key(new_key, owner, valid_to, Some(old_key), now)
+            .await
+            .unwrap());
+        assert_eq!(db.active_api_key_owner(old_key, now).await.unwrap(), None);
+        assert_eq!(
+            db.active_api_key_owner(new_key, now).await.unwrap(),
+            Some(owner)
+        );
+
+        assert!(db.revoke_api_key(new_key, owner, now).await.unwrap());
+        assert_eq!(db.active_api_key_owner(new_key, now).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,37 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use database::byte_array::ByteArray;
+use model::{app_id::AppId, order::OrderUid};
+use primitive_types::H160;
+
+impl super::Postgres {
+    /// Cancels all open orders with the app data on behalf of `owner` in a single transaction and
+    /// returns their uids.
+    pub async fn cancel_orders_by_app_data(
+        &self,
+        app_data: &AppId,
+        owner: H160,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<OrderUid>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["cancel_orders_by_app_data"])
+            .start_timer();
+
+        let mut ex = self.pool.begin().await?;
+        let _transaction_timer = super::Metrics::get()
+            .database_transactions
+            .with_label_values(&["cancel_orders_by_app_data"])
+            .start_timer();
+        let uids = database::app_data_cancellations::cancel_orders(
+            &mut ex,
+            &ByteArray(app_data.0),
+            &ByteArray(owner.0),
+            now,
+        )
+        .await
+        .context("cancel_orders_by_app_data")?;
+        ex.commit().await?;
+        Ok(uids.into_iter().map(|uid| OrderUid(uid.0)).collect())
+    }
+}
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use model::auction::{Auction, AuctionWithId};
 
 impl super::Postgres {
@@ -17,4 +18,15 @@ impl super::Postgres {
         let auction = AuctionWithId { id, auction };
         Ok(Some(auction))
     }
+
+    pub async fn most_recent_auction_creation(&self) -> Result<Option<(u64, DateTime<Utc>)>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["load_most_recent_auction_creation"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let creation = database::auction::load_most_recent_creation(&mut ex).await?;
+        Ok(creation.map(|(block, created)| (block as u64, created)))
+    }
 }
@@ -0,0 +1,347 @@
+//! Tracks whether the database accepts writes so that the orderbook keeps serving what it can while
+//! the database fails over.
+//!
+//! During a failover the database is briefly unreachable and afterwards can be a read-only replica
+//! until the new primary is promoted. Instead of failing every request the orderbook then stops
+//! accepting order placements and cancellations with a dedicated error, keeps quoting without
+//! storing the quotes and serves order reads from a cache.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use model::quote::QuoteId;
+use shared::order_quoting::{QuoteData, QuoteSearchParameters, QuoteStoring};
+use sqlx::{Connection, PgConnection};
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use thiserror::Error;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum DatabaseState {
+    #[default]
+    Healthy,
+    /// The database can be read but rejects writes, for example because it is a replica.
+    ReadOnly,
+    /// The database can't be reached.
+    Unavailable,
+}
+
+impl DatabaseState {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Healthy => "healthy",
+            Self::ReadOnly => "read_only",
+            Self::Unavailable => "unavailable",
+        }
+    }
+
+    /// The state an error indicates. `None` for errors unrelated to the availability of the
+    /// database, like constraint violations. Pool timeouts are `None` too because they happen
+    /// when all connections are busy under load while the database itself is fine.
+    fn from_error(err: &sqlx::Error) -> Option<Self> {
+        match err {
+            sqlx::Error::Database(err) => match err.code()?.as_ref() {
+                // read_only_sql_transaction
+                "25006" => Some(Self::ReadOnly),
+                // admin_shutdown, crash_shutdown, cannot_connect_now and connection exceptions
+                "57P01" | "57P02" | "57P03" => Some(Self::Unavailable),
+                code if code.starts_with("08") => Some(Self::Unavailable),
+                _ => None,
+            },
+            sqlx::Error::Io(_) | sqlx::Error::Tls(_) | sqlx::Error::PoolClosed => {
+                Some(Self::Unavailable)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for DatabaseState {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Healthy => "healthy",
+            Self::ReadOnly => "read-only",
+            Self::Unavailable => "unavailable",
+        })
+    }
+}
+
+/// Writes are not possible because the database is degraded.
+#[derive(Debug, Error)]
+#[error("database is {0}")]
+pub struct DatabaseDegraded(pub DatabaseState);
+
+/// The last known state of the database.
+///
+/// Query errors degrade the state immediately and only a successful probe restores it, so that
+/// requests don't keep hitting a database that is failing over.
+#[derive(Default)]
+pub struct DatabaseHealth {
+    state: Mutex<DatabaseState>,
+}
+
+impl DatabaseHealth {
+    pub fn state(&self) -> DatabaseState {
+        *self.state.lock().unwrap()
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.state() != DatabaseState::Healthy
+    }
+
+    /// Errors if the database doesn't accept writes.
+    pub fn ensure_writable(&self) -> Result<(), DatabaseDegraded> {
+        match self.state() {
+            DatabaseState::Healthy => Ok(()),
+            state => Err(DatabaseDegraded(state)),
+        }
+    }
+
+    /// Degrades the state if the error of a query indicates that the database is read-only or
+    /// unreachable. Returns whether it did.
+    pub fn observe(&self, err: &sqlx::Error) -> bool {
+        match DatabaseState::from_error(err) {
+            Some(state) => {
+                self.set(state);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Like `observe` for errors that might be caused by a query error.
+    pub fn observe_any(&self, err: &anyhow::Error) -> bool {
+        err.chain()
+            .filter_map(|err| err.downcast_ref::<sqlx::Error>())
+            .any(|err| self.observe(err))
+    }
+
+    fn set(&self, current: DatabaseState) {
+        let previous = std::mem::replace(&mut *self.state.lock().unwrap(), current);
+        if previous == current {
+            return;
+        }
+        tracing::warn!(%previous, %current, "database state changed");
+        let metrics = super::Metrics::get();
+        for state in [
+            DatabaseState::Healthy,
+            DatabaseState::ReadOnly,
+            DatabaseState::Unavailable,
+        ] {
+            metrics
+                .database_state
+                .with_label_values(&[state.as_str()])
+                .set((state == current) as i64);
+        }
+    }
+}
+
+/// Probes the database on a dedicated connection instead of one from the pool, so that a pool
+/// exhausted by requests neither delays the probe nor looks like an outage.
+struct DatabaseProbe {
+    url: String,
+    timeout: Duration,
+    connection: Option<PgConnection>,
+}
+
+impl DatabaseProbe {
+    fn new(url: String, timeout: Duration) -> Self {
+        Self {
+            url,
+            timeout,
+            connection: None,
+        }
+    }
+
+    /// Probes whether the database can be reached and accepts writes. Reconnects after failed
+    /// probes because the connection might point to a database that was failed over.
+    async fn database_state(&mut self) -> DatabaseState {
+        let result = tokio::time::timeout(self.timeout, self.is_read_only()).await;
+        match result {
+            Ok(Ok(false)) => DatabaseState::Healthy,
+            Ok(Ok(true)) => DatabaseState::ReadOnly,
+            Ok(Err(err)) => {
+                tracing::debug!(?err, "database probe failed");
+                self.connection = None;
+                DatabaseState::Unavailable
+            }
+            Err(_) => {
+                tracing::debug!("database probe timed out");
+                self.connection = None;
+                DatabaseState::Unavailable
+            }
+        }
+    }
+
+    async fn is_read_only(&mut self) -> Result<bool, sqlx::Error> {
+        if self.connection.is_none() {
+            self.connection = Some(PgConnection::connect(&self.url).await?);
+        }
+        let connection = self.connection.as_mut().expect("connected");
+        sqlx::query_scalar("SELECT current_setting('transaction_read_only') = 'on';")
+            .fetch_one(connection)
+            .await
+    }
+}
+
+pub async fn probe_forever(url: String, health: Arc<DatabaseHealth>, interval: Duration) -> ! {
+    let mut probe = DatabaseProbe::new(url, interval);
+    loop {
+        health.set(probe.database_state().await);
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Quote storage that keeps quoting while the database is degraded. Quotes are not stored then
+/// and can't be referenced by orders, which can't be placed anyway.
+pub struct DegradableQuoteStorage {
+    inner: Arc<dyn QuoteStoring>,
+    health: Arc<DatabaseHealth>,
+}
+
+impl DegradableQuoteStorage {
+    pub fn new(inner: Arc<dyn QuoteStoring>, health: Arc<DatabaseHealth>) -> Self {
+        Self { inner, health }
+    }
+}
+
+#[async_trait::async_trait]
+impl QuoteStoring for DegradableQuoteStorage {
+    async fn save(&self, data: QuoteData) -> Result<Option<QuoteId>> {
+        if self.health.is_degraded() {
+            return Ok(None);
+        }
+        match self.inner.save(data).await {
+            Err(err) if self.health.observe_any(&err) => {
+                tracing::warn!(?err, "not storing quote because the database is degraded");
+                Ok(None)
+            }
+            result => result,
+        }
+    }
+
+    async fn get(&self, id: QuoteId) -> Result<Option<QuoteData>> {
+        self.inner.get(id).await
+    }
+
+    async fn find(
+        &self,
+        parameters: QuoteSearchParameters,
+        expiration: DateTime<Utc>,
+    ) -> Result<Option<(QuoteId, QuoteData)>> {
+        match self.inner.find(parameters, expiration).await {
+            Err(err) if self.health.observe_any(&err) => Ok(None),
+            result => result,
+        }
+    }
+
+    async fn extend(&self, id: QuoteId, expiration: DateTime<Utc>) -> Result<bool> {
+        self.health.ensure_writable()?;
+        self.inner.extend(id, expiration).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn connection_lost() -> anyhow::Error {
+        sqlx::Error::Io(std::io::ErrorKind::BrokenPipe.into()).into()
+    }
+
+    /// Storage whose database connection is gone.
+    #[derive(Default)]
+    struct Disconnected {
+        saves: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl QuoteStoring for Disconnected {
+        async fn save(&self, _: QuoteData) -> Result<Option<QuoteId>> {
+            self.saves.fetch_add(1, Ordering::SeqCst);
+            Err(connection_lost())
+        }
+
+        async fn get(&self, _: QuoteId) -> Result<Option<QuoteData>> {
+            Err(connection_lost())
+        }
+
+        async fn find(
+            &self,
+            _: QuoteSearchParameters,
+            _: DateTime<Utc>,
+        ) -> Result<Option<(QuoteId, QuoteData)>> {
+            Err(connection_lost())
+        }
+
+        async fn extend(&self, _: QuoteId, _: DateTime<Utc>) -> Result<bool> {
+            Err(connection_lost())
+        }
+    }
+
+    #[test]
+    fn classifies_errors() {
+        assert_eq!(DatabaseState::from_error(&sqlx::Error::PoolTimedOut), None);
+        assert_eq!(
+            DatabaseState::from_error(&sqlx::Error::Io(std::io::ErrorKind::BrokenPipe.into())),
+            Some(DatabaseState::Unavailable)
+        );
+        assert_eq!(DatabaseState::from_error(&sqlx::Error::RowNotFound), None);
+    }
+
+    #[test]
+    fn observing_errors_degrades_state() {
+        let health = DatabaseHealth::default();
+        assert!(health.ensure_writable().is_ok());
+
+        assert!(!health.observe_any(&anyhow::anyhow!("unrelated")));
+        assert!(!health.is_degraded());
+
+        let err = anyhow::Error::new(sqlx::Error::PoolClosed).context("insert_order");
+        assert!(health.observe_any(&err));
+        assert_eq!(
+            health.ensure_writable().unwrap_err().0,
+            DatabaseState::Unavailable
+        );
+
+        health.set(DatabaseState::Healthy);
+        assert!(!health.is_degraded());
+    }
+
+    #[tokio::test]
+    async fn quotes_are_not_stored_while_degraded() {
+        let inner = Arc::new(Disconnected::default());
+        let health = Arc::new(DatabaseHealth::default());
+        let storage = DegradableQuoteStorage::new(inner.clone(), health.clone());
+
+        // The failing save degrades the state so the second one doesn't reach the database.
+        assert_eq!(storage.save(Default::default()).await.unwrap(), None);
+        assert!(health.is_degraded());
+        assert_eq!(storage.save(Default::default()).await.unwrap(), None);
+        assert_eq!(inner.saves.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            storage.find(Default::default(), Utc::now()).await.unwrap(),
+            None
+        );
+        assert!(storage.extend(0, Utc::now()).await.is_err());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_probe_uses_own_connection() {
+        let mut probe = DatabaseProbe::new("postgresql://".to_string(), Duration::from_secs(5));
+        assert_eq!(probe.database_state().await, DatabaseState::Healthy);
+        assert!(probe.connection.is_some());
+        assert_eq!(probe.database_state().await, DatabaseState::Healthy);
+
+        let mut probe = DatabaseProbe::new(
+            "postgresql://localhost:1".to_string(),
+            Duration::from_secs(5),
+        );
+        assert_eq!(probe.database_state().await, DatabaseState::Unavailable);
+        assert!(probe.connection.is_none());
+    }
+}
@@ -0,0 +1,723 @@
+//! Storage that keeps all data in memory so that the orderbook can run without Postgres, for
+//! example in a local development loop. Data is lost on restart.
+//!
+//! Unlike with Postgres nothing gets indexed from the chain: orders never get traded, pre-signed
+//! orders stay pending and there are no auctions, solver rewards, solver teams, integrator
+//! analytics or fee recommendations.
+
+use super::{
+    orders::{InsertionError, OrderStoring},
+    quote_requests::QuoteRequestAttribution,
+    solver_competition::CompetitionFilter,
+    trades::{TradeFilter, TradeRetrieving},
+    OrderbookStoring,
+};
+use crate::solver_competition::{Identifier, LoadSolverCompetitionError, SolverCompetitionStoring};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use model::{
+    app_id::AppId,
+    auction::{AuctionId, AuctionWithId},
+    fee_policy::OrderFeePolicy,
+    fee_recommendation::FeeRecommendation,
+    integrator_analytics::IntegratorFunnel,
+    order::{Order, OrderKind, OrderStatus, OrderUid},
+    order_book_stats::OrderBookStats,
+    protocol_fees::ProtocolFeeAccrual,
+    quote::QuoteId,
+    sealed_order::{self, SealedOrder, SealedOrderId, SealedOrderStatus},
+    signature::Signature,
+    solver_competition::{CompetitionSummary, SolverCompetition},
+    solver_rewards::SolverRewards,
+    solver_team::{SolverTeam, SolverTeamConfig},
+    stuck_orders::{OrderDiagnosis, StuckOrders},
+    trade::{SettlementTrades, Trade},
+};
+use number_conversions::u256_to_big_uint;
+use primitive_types::{H160, H256, U256};
+use shared::{
+    fee_subsidy::order_fee_policy,
+    order_quoting::{Quote, QuoteData, QuoteSearchParameters, QuoteStoring},
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::RangeInclusive,
+    sync::Mutex,
+};
+
+#[derive(Default)]
+pub struct InMemory {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    orders: HashMap<OrderUid, Order>,
+    /// The quotes orders were created with.
+    order_quotes: HashMap<OrderUid, Quote>,
+    quotes: BTreeMap<QuoteId, QuoteData>,
+    last_quote_id: QuoteId,
+    api_keys: HashMap<H256, ApiKey>,
+    solver_competitions: BTreeMap<AuctionId, SolverCompetition>,
+    /// When the solver competitions were stored.
+    solver_competition_times: HashMap<AuctionId, DateTime<Utc>>,
+    /// Nothing reveals sealed orders so they stay pending.
+    sealed_orders: HashMap<SealedOrderId, Vec<u8>>,
+}
+
+struct ApiKey {
+    owner: H160,
+    valid_to: u32,
+    revoked: bool,
+}
+
+impl Inner {
+    fn insert_order(&mut self, order: &Order, quote: Option<Quote>) -> Result<(), InsertionError> {
+        if self.orders.contains_key(&order.metadata.uid) {
+            return Err(InsertionError::DuplicatedRecord);
+        }
+        self.orders.insert(order.metadata.uid, order.clone());
+        if let Some(quote) = quote {
+            self.order_quotes.insert(order.metadata.uid, quote);
+        }
+        Ok(())
+    }
+
+    fn cancel_order(&mut self, uid: &OrderUid) {
+        if let Some(order) = self.orders.get_mut(uid) {
+            order.metadata.invalidated = true;
+        }
+    }
+}
+
+/// Whether the order wasn't cancelled or completely filled yet, like Postgres checks before
+/// replacing an order.
+fn is_open(order: &Order) -> bool {
+    let filled = match order.data.kind {
+        OrderKind::Sell => {
+            order.metadata.executed_sell_amount_before_fees >= order.data.sell_amount
+        }
+        OrderKind::Buy => {
+            order.metadata.executed_buy_amount >= u256_to_big_uint(&order.data.buy_amount)
+        }
+    };
+    !order.metadata.invalidated && !filled
+}
+
+/// Sets the status the way Postgres computes it for an order that was never traded.
+fn with_status(order: &Order, now: DateTime<Utc>) -> Order {
+    let mut order = order.clone();
+    order.metadata.status = if order.metadata.invalidated {
+        OrderStatus::Cancelled
+    } else if (order.data.valid_to as i64) < now.timestamp() {
+        OrderStatus::Expired
+    } else if order.signature == Signature::PreSign {
+        OrderStatus::PresignaturePending
+    } else {
+        OrderStatus::Open
+    };
+    order
+}
+
+/// The value Postgres sorts matching quotes by.
+fn quote_cost(quote: &QuoteData) -> f64 {
+    let fee = &quote.fee_parameters;
+    fee.gas_amount * fee.gas_price * fee.sell_token_price
+}
+
+#[async_trait::async_trait]
+impl OrderStoring for InMemory {
+    async fn insert_order(
+        &self,
+        order: &Order,
+        quote: Option<Quote>,
+    ) -> Result<(), InsertionError> {
+        self.inner.lock().unwrap().insert_order(order, quote)
+    }
+
+    async fn cancel_order(&self, order_uid: &OrderUid, _: DateTime<Utc>) -> Result<()> {
+        self.inner.lock().unwrap().cancel_order(order_uid);
+        Ok(())
+    }
+
+    async fn replace_order(
+        &self,
+        old_order: &OrderUid,
+        new_order: &Order,
+        new_quote: Option<Quote>,
+    ) -> Result<bool, InsertionError> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.orders.contains_key(&new_order.metadata.uid) {
+            return Err(InsertionError::DuplicatedRecord);
+        }
+        match inner.orders.get(old_order) {
+            Some(order) if is_open(order) => (),
+            _ => return Ok(false),
+        }
+        inner.cancel_order(old_order);
+        inner.insert_order(new_order, new_quote)?;
+        Ok(true)
+    }
+
+    async fn orders_for_tx(&self, _: &H256) -> Result<Vec<Order>> {
+        Ok(Vec::new())
+    }
+
+    async fn single_order(&self, uid: &OrderUid) -> Result<Option<Order>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner
+            .orders
+            .get(uid)
+            .map(|order| with_status(order, Utc::now())))
+    }
+
+    async fn user_orders(
+        &self,
+        owner: &H160,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Order>> {
+        let inner = self.inner.lock().unwrap();
+        let now = Utc::now();
+        let mut orders = inner
+            .orders
+            .values()
+            .filter(|order| order.metadata.owner == *owner)
+            .map(|order| with_status(order, now))
+            .collect::<Vec<_>>();
+        orders.sort_by(|a, b| b.metadata.creation_date.cmp(&a.metadata.creation_date));
+        Ok(orders
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit.map(|limit| limit as usize).unwrap_or(usize::MAX))
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl TradeRetrieving for InMemory {
+    async fn trades(&self, _: &TradeFilter) -> Result<Vec<Trade>> {
+        Ok(Vec::new())
+    }
+
+    async fn settlement_trades(&self, _: &TradeFilter) -> Result<Vec<SettlementTrades>> {
+        Ok(Vec::new())
+    }
+}
+
+#[async_trait::async_trait]
+impl QuoteStoring for InMemory {
+    async fn save(&self, data: QuoteData) -> Result<Option<QuoteId>> {
+        let mut inner = self.inner.lock().unwrap();
+        // Postgres gets cleaned up by the autopilot. Here expired quotes get removed on the go so
+        // that they don't accumulate.
+        let now = Utc::now();
+        inner.quotes.retain(|_, quote| quote.expiration >= now);
+        inner.last_quote_id += 1;
+        let id = inner.last_quote_id;
+        inner.quotes.insert(id, data);
+        Ok(Some(id))
+    }
+
+    async fn get(&self, id: QuoteId) -> Result<Option<QuoteData>> {
+        Ok(self.inner.lock().unwrap().quotes.get(&id).cloned())
+    }
+
+    async fn find(
+        &self,
+        params: QuoteSearchParameters,
+        expiration: DateTime<Utc>,
+    ) -> Result<Option<(QuoteId, QuoteData)>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner
+            .quotes
+            .iter()
+            .filter(|(_, quote)| {
+                params.matches(quote)
+                    && quote.quote_kind == params.quote_kind
+                    && quote.expiration >= expiration
+            })
+            .min_by(|(_, a), (_, b)| quote_cost(a).total_cmp(&quote_cost(b)))
+            .map(|(id, quote)| (*id, quote.clone())))
+    }
+
+    async fn extend(&self, id: QuoteId, expiration: DateTime<Utc>) -> Result<bool> {
+        let mut inner = self.inner.lock().unwrap();
+        Ok(match inner.quotes.get_mut(&id) {
+            Some(quote) => {
+                quote.expiration = expiration;
+                true
+            }
+            None => false,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl SolverCompetitionStoring for InMemory {
+    async fn save(&self, model: SolverCompetition) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner
+            .solver_competition_times
+            .insert(model.auction_id, Utc::now());
+        inner.solver_competitions.insert(model.auction_id, model);
+        Ok(())
+    }
+
+    async fn load(
+        &self,
+        identifier: Identifier,
+    ) -> Result<SolverCompetition, LoadSolverCompetitionError> {
+        let inner = self.inner.lock().unwrap();
+        let competition = match identifier {
+            Identifier::Id(id) => inner.solver_competitions.get(&id),
+            Identifier::Transaction(hash) => inner
+                .solver_competitions
+                .values()
+                .find(|competition| competition.transaction_hash == Some(hash)),
+        };
+        competition
+            .cloned()
+            .ok_or(LoadSolverCompetitionError::NotFound)
+    }
+}
+
+#[async_trait::async_trait]
+impl OrderbookStoring for InMemory {
+    async fn insert_api_key(
+        &self,
+        key_hash: H256,
+        owner: H160,
+        valid_to: u32,
+        replaces: Option<H256>,
+        _: DateTime<Utc>,
+    ) -> Result<bool, InsertionError> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.api_keys.contains_key(&key_hash) {
+            return Err(InsertionError::DuplicatedRecord);
+        }
+        if let Some(replaces) = replaces {
+            match inner.api_keys.get_mut(&replaces) {
+                Some(key) if key.owner == owner && !key.revoked => key.revoked = true,
+                _ => return Ok(false),
+            }
+        }
+        inner.api_keys.insert(
+            key_hash,
+            ApiKey {
+                owner,
+                valid_to,
+                revoked: false,
+            },
+        );
+        Ok(true)
+    }
+
+    async fn active_api_key_owner(
+        &self,
+        key_hash: H256,
+        now: DateTime<Utc>,
+    ) -> Result<Option<H160>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner
+            .api_keys
+            .get(&key_hash)
+            .filter(|key| !key.revoked && key.valid_to as i64 >= now.timestamp())
+            .map(|key| key.owner))
+    }
+
+    async fn revoke_api_key(&self, key_hash: H256, owner: H160, _: DateTime<Utc>) -> Result<bool> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.api_keys.get_mut(&key_hash) {
+            Some(key) if key.owner == owner && !key.revoked => {
+                key.revoked = true;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn cancel_orders_by_app_data(
+        &self,
+        app_data: &AppId,
+        _: H160,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<OrderUid>> {
+        let mut inner = self.inner.lock().unwrap();
+        let mut uids = Vec::new();
+        for order in inner.orders.values_mut() {
+            if order.data.app_data == *app_data
+                && !order.metadata.invalidated
+                && !order.metadata.class.is_liquidity_order()
+                && order.data.valid_to as i64 >= now.timestamp()
+            {
+                order.metadata.invalidated = true;
+                uids.push(order.metadata.uid);
+            }
+        }
+        Ok(uids)
+    }
+
+    async fn most_recent_auction(&self) -> Result<Option<AuctionWithId>> {
+        Ok(None)
+    }
+
+    async fn most_recent_auction_creation(&self) -> Result<Option<(u64, DateTime<Utc>)>> {
+        Ok(None)
+    }
+
+    async fn order_book_stats(&self) -> Result<OrderBookStats> {
+        Ok(OrderBookStats {
+            settlement_count: 0,
+            trade_count: 0,
+            unique_traders: 0,
+            volume: U256::zero(),
+            refreshed_at: Utc::now(),
+        })
+    }
+
+    async fn solver_rewards(
+        &self,
+        _: DateTime<Utc>,
+        _: DateTime<Utc>,
+    ) -> Result<Vec<SolverRewards>> {
+        Ok(Vec::new())
+    }
+
+    async fn protocol_fees(&self) -> Result<Vec<ProtocolFeeAccrual>> {
+        Ok(Vec::new())
+    }
+
+    async fn order_fee_policy(&self, uid: &OrderUid) -> Result<Option<OrderFeePolicy>> {
+        let inner = self.inner.lock().unwrap();
+        let (order, quote) = match (inner.orders.get(uid), inner.order_quotes.get(uid)) {
+            (Some(order), Some(quote)) => (order, quote),
+            _ => return Ok(None),
+        };
+        Ok(Some(order_fee_policy(
+            &quote.data.fee_parameters,
+            Some(&quote.subsidy),
+            order.data.fee_amount,
+        )))
+    }
+
+    async fn solver_team(&self, _: H160) -> Result<Option<SolverTeam>> {
+        Ok(None)
+    }
+
+    async fn update_solver_team_config(&self, _: H160, _: &SolverTeamConfig) -> Result<bool> {
+        Ok(false)
+    }
+
+    async fn solver_competitions_of_solver(
+        &self,
+        solver: &str,
+        limit: u64,
+    ) -> Result<Vec<SolverCompetition>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner
+            .solver_competitions
+            .values()
+            .rev()
+            .filter(|competition| {
+                competition
+                    .solutions
+                    .iter()
+                    .map(|solution| &solution.solver)
+                    .chain(
+                        competition
+                            .simulation_failures
+                            .iter()
+                            .map(|failure| &failure.solver),
+                    )
+                    .any(|name| name == solver)
+            })
+            .take(limit.try_into().unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+
+    async fn search_solver_competitions(
+        &self,
+        filter: &CompetitionFilter,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<CompetitionSummary>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner
+            .solver_competitions
+            .values()
+            .rev()
+            .map(|competition| {
+                let created = inner.solver_competition_times[&competition.auction_id];
+                (competition, CompetitionSummary::new(competition, created))
+            })
+            .filter(|(competition, summary)| filter.matches(competition, summary))
+            .map(|(_, summary)| summary)
+            .skip(offset.try_into().unwrap_or(usize::MAX))
+            .take(limit.try_into().unwrap_or(usize::MAX))
+            .collect())
+    }
+
+    async fn insert_quote_request(&self, _: &QuoteRequestAttribution) -> Result<()> {
+        Ok(())
+    }
+
+    async fn integrator_funnels(
+        &self,
+        _: DateTime<Utc>,
+        _: DateTime<Utc>,
+    ) -> Result<Vec<IntegratorFunnel>> {
+        Ok(Vec::new())
+    }
+
+    async fn fee_recommendation(
+        &self,
+        _: H160,
+        _: H160,
+        _: RangeInclusive<U256>,
+        _: DateTime<Utc>,
+        _: u64,
+    ) -> Result<FeeRecommendation> {
+        Ok(FeeRecommendation::default())
+    }
+
+    async fn insert_sealed_order(&self, sealed: &[u8], _: DateTime<Utc>) -> Result<SealedOrderId> {
+        let id = sealed_order::sealed_order_id(sealed);
+        let mut inner = self.inner.lock().unwrap();
+        inner.sealed_orders.insert(id, sealed.to_vec());
+        Ok(id)
+    }
+
+    async fn sealed_order(&self, id: &SealedOrderId) -> Result<Option<SealedOrder>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.sealed_orders.get(id).map(|_| SealedOrder {
+            id: *id,
+            status: SealedOrderStatus::Pending,
+        }))
+    }
+
+    async fn order_diagnosis(&self, _: &OrderUid) -> Result<Option<OrderDiagnosis>> {
+        Ok(None)
+    }
+
+    async fn stuck_orders(&self) -> Result<Vec<StuckOrders>> {
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::order::{OrderData, OrderMetadata};
+
+    fn order(uid: u8, owner: u8, creation_date: i64) -> Order {
+        Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([uid; 56]),
+                owner: H160([owner; 20]),
+                creation_date: DateTime::from_utc(
+                    chrono::NaiveDateTime::from_timestamp(creation_date, 0),
+                    Utc,
+                ),
+                ..Default::default()
+            },
+            data: OrderData {
+                sell_amount: 1.into(),
+                buy_amount: 1.into(),
+                valid_to: u32::MAX,
+                app_data: AppId([uid; 32]),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn stores_orders() {
+        let db = InMemory::default();
+        db.insert_order(&order(1, 1, 1), None).await.unwrap();
+        db.insert_order(&order(2, 1, 2), None).await.unwrap();
+        db.insert_order(&order(3, 2, 3), None).await.unwrap();
+        assert!(matches!(
+            db.insert_order(&order(1, 1, 1), None).await,
+            Err(InsertionError::DuplicatedRecord)
+        ));
+
+        let uids = |orders: Vec<Order>| {
+            orders
+                .into_iter()
+                .map(|order| order.metadata.uid)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            uids(db.user_orders(&H160([1; 20]), 0, None).await.unwrap()),
+            [OrderUid([2; 56]), OrderUid([1; 56])]
+        );
+        assert_eq!(
+            uids(db.user_orders(&H160([1; 20]), 1, Some(1)).await.unwrap()),
+            [OrderUid([1; 56])]
+        );
+
+        assert!(db
+            .replace_order(&OrderUid([1; 56]), &order(4, 1, 4), None)
+            .await
+            .unwrap());
+        // The cancelled order can't be replaced again.
+        assert!(!db
+            .replace_order(&OrderUid([1; 56]), &order(5, 1, 5), None)
+            .await
+            .unwrap());
+        // Neither can a fulfilled order.
+        let mut fulfilled = order(6, 1, 6);
+        fulfilled.metadata.executed_sell_amount_before_fees = 1.into();
+        db.insert_order(&fulfilled, None).await.unwrap();
+        assert!(!db
+            .replace_order(&OrderUid([6; 56]), &order(7, 1, 7), None)
+            .await
+            .unwrap());
+        let db = &db;
+        let status = |uid| async move {
+            db.single_order(&OrderUid([uid; 56]))
+                .await
+                .unwrap()
+                .unwrap()
+                .metadata
+                .status
+        };
+        assert_eq!(status(1).await, OrderStatus::Cancelled);
+        assert_eq!(status(4).await, OrderStatus::Open);
+
+        let cancelled = db
+            .cancel_orders_by_app_data(&AppId([2; 32]), H160([1; 20]), Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(cancelled, [OrderUid([2; 56])]);
+        assert_eq!(status(2).await, OrderStatus::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn rotates_api_keys() {
+        let db = InMemory::default();
+        let now = Utc::now();
+        let key = |byte| H256([byte; 32]);
+        let owner = H160([1; 20]);
+        assert!(db
+            .insert_api_key(key(1), owner, u32::MAX, None, now)
+            .await
+            .unwrap());
+        // Only the owner can replace a key.
+        assert!(!db
+            .insert_api_key(key(2), H160([2; 20]), u32::MAX, Some(key(1)), now)
+            .await
+            .unwrap());
+        assert!(db
+            .insert_api_key(key(2), owner, u32::MAX, Some(key(1)), now)
+            .await
+            .unwrap());
+
+        assert_eq!(db.active_api_key_owner(key(1), now).await.unwrap(), None);
+        assert_eq!(
+            db.active_api_key_owner(key(2), now).await.unwrap(),
+            Some(owner)
+        );
+        assert!(db.revoke_api_key(key(2), owner, now).await.unwrap());
+        assert_eq!(db.active_api_key_owner(key(2), now).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn finds_cheapest_matching_quote() {
+        let db = InMemory::default();
+        let quote = |gas_amount| QuoteData {
+            quoted_sell_amount: 10.into(),
+            fee_parameters: shared::fee_subsidy::FeeParameters {
+                gas_amount,
+                gas_price: 1.,
+                sell_token_price: 1.,
+            },
+            kind: model::order::OrderKind::Sell,
+            expiration: Utc::now() + chrono::Duration::seconds(60),
+            ..Default::default()
+        };
+        QuoteStoring::save(&db, quote(2.)).await.unwrap();
+        let id = QuoteStoring::save(&db, quote(1.)).await.unwrap().unwrap();
+        QuoteStoring::save(
+            &db,
+            QuoteData {
+                quoted_sell_amount: 11.into(),
+                ..quote(0.)
+            },
+        )
+        .await
+        .unwrap();
+
+        let params = QuoteSearchParameters {
+            sell_amount: 10.into(),
+            kind: model::order::OrderKind::Sell,
+            ..Default::default()
+        };
+        let (found, _) = db.find(params.clone(), Utc::now()).await.unwrap().unwrap();
+        assert_eq!(found, id);
+        let in_an_hour = Utc::now() + chrono::Duration::hours(1);
+        assert!(db.find(params, in_an_hour).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn searches_solver_competitions() {
+        let db = InMemory::default();
+        let competition = |auction_id, token: u8, solver: &str| SolverCompetition {
+            auction_id,
+            auction: model::solver_competition::CompetitionAuction {
+                prices: [(H160([token; 20]), 1.into())].into_iter().collect(),
+                ..Default::default()
+            },
+            solutions: vec![model::solver_competition::SolverSettlement {
+                solver: solver.to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        for competition in [
+            competition(0, 1, "a"),
+            competition(1, 2, "a"),
+            competition(2, 1, "b"),
+        ] {
+            SolverCompetitionStoring::save(&db, competition)
+                .await
+                .unwrap();
+        }
+
+        let ids = |summaries: Vec<CompetitionSummary>| {
+            summaries
+                .into_iter()
+                .map(|summary| summary.result.auction_id)
+                .collect::<Vec<_>>()
+        };
+        let search = |filter: CompetitionFilter, offset, limit| {
+            let db = &db;
+            async move {
+                ids(db
+                    .search_solver_competitions(&filter, offset, limit)
+                    .await
+                    .unwrap())
+            }
+        };
+        assert_eq!(search(Default::default(), 0, 10).await, [2, 1, 0]);
+        assert_eq!(search(Default::default(), 1, 1).await, [1]);
+        let by_token = CompetitionFilter {
+            token: Some(H160([1; 20])),
+            ..Default::default()
+        };
+        assert_eq!(search(by_token, 0, 10).await, [2, 0]);
+        let by_solver = CompetitionFilter {
+            solver: Some("a".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(search(by_solver, 0, 10).await, [1, 0]);
+        let in_the_future = CompetitionFilter {
+            from: Some(Utc::now() + chrono::Duration::hours(1)),
+            ..Default::default()
+        };
+        assert!(search(in_the_future, 0, 10).await.is_empty());
+    }
+}
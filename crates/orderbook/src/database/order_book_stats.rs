@@ -0,0 +1,22 @@
+use anyhow::Result;
+use model::order_book_stats::OrderBookStats;
+use primitive_types::U256;
+
+impl super::Postgres {
+    pub async fn order_book_stats(&self) -> Result<OrderBookStats> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["order_book_stats"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let stats = database::order_book_stats::load(&mut ex).await?;
+        Ok(OrderBookStats {
+            settlement_count: stats.settlement_count as u64,
+            trade_count: stats.trade_count as u64,
+            unique_traders: stats.unique_traders as u64,
+            volume: U256::from_f64_lossy(stats.volume),
+            refreshed_at: stats.refreshed_at,
+        })
+    }
+}
@@ -0,0 +1,58 @@
+use anyhow::{Context, Result};
+use database::{byte_array::ByteArray, order_diagnoses::StuckOrderReason as DbStuckOrderReason};
+use model::{
+    app_id::AppId,
+    order::OrderUid,
+    stuck_orders::{OrderDiagnosis, StuckOrderReason, StuckOrders},
+};
+
+impl super::Postgres {
+    pub async fn order_diagnosis(&self, uid: &OrderUid) -> Result<Option<OrderDiagnosis>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["order_diagnosis"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let diagnosis = database::order_diagnoses::fetch(&mut ex, &ByteArray(uid.0))
+            .await
+            .context("order_diagnosis")?;
+        Ok(diagnosis.map(|diagnosis| OrderDiagnosis {
+            reason: reason_from(diagnosis.reason),
+            diagnosed_at: diagnosis.diagnosed_at,
+        }))
+    }
+
+    pub async fn stuck_orders(&self) -> Result<Vec<StuckOrders>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["stuck_orders"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let rows = database::order_diagnoses::count_by_app_data(&mut ex)
+            .await
+            .context("stuck_orders")?;
+        rows.into_iter()
+            .map(|row| {
+                Ok(StuckOrders {
+                    app_data: AppId(row.app_data.0),
+                    reason: reason_from(row.reason),
+                    orders: row.orders.try_into().context("negative order count")?,
+                })
+            })
+            .collect()
+    }
+}
+
+fn reason_from(reason: DbStuckOrderReason) -> StuckOrderReason {
+    match reason {
+        DbStuckOrderReason::TokenDenied => StuckOrderReason::TokenDenied,
+        DbStuckOrderReason::MissingAllowance => StuckOrderReason::MissingAllowance,
+        DbStuckOrderReason::InsufficientBalance => StuckOrderReason::InsufficientBalance,
+        DbStuckOrderReason::NoLiquidityPath => StuckOrderReason::NoLiquidityPath,
+        DbStuckOrderReason::TooSmall => StuckOrderReason::TooSmall,
+        DbStuckOrderReason::PriceOutOfRange => StuckOrderReason::PriceOutOfRange,
+        DbStuckOrderReason::Unknown => StuckOrderReason::Unknown,
+    }
+}
@@ -9,6 +9,7 @@ use ethcontract::H256;
 use futures::{stream::TryStreamExt, FutureExt, StreamExt};
 use model::{
     app_id::AppId,
+    fee_policy::OrderFeePolicy,
     order::{Order, OrderData, OrderMetadata, OrderStatus, OrderUid},
     signature::Signature,
 };
@@ -17,9 +18,12 @@ use number_conversions::{big_decimal_to_big_uint, big_decimal_to_u256, u256_to_b
 use primitive_types::H160;
 use shared::{
     db_order_conversions::{
-        buy_token_destination_from, buy_token_destination_into, order_kind_from, order_kind_into,
-        sell_token_source_from, sell_token_source_into, signing_scheme_from, signing_scheme_into,
+        buy_token_destination_from, buy_token_destination_into, order_class_from, order_class_into,
+        order_condition_from, order_condition_into, order_kind_from, order_kind_into,
+        quote_firmness_from, quote_firmness_into, sell_token_source_from, sell_token_source_into,
+        signing_scheme_from, signing_scheme_into,
     },
+    fee_subsidy::{order_fee_policy, FeeParameters, Subsidy},
     order_quoting::Quote,
 };
 use sqlx::{types::BigDecimal, Connection, PgConnection};
@@ -31,12 +35,14 @@ pub trait OrderStoring: Send + Sync {
     async fn insert_order(&self, order: &Order, quote: Option<Quote>)
         -> Result<(), InsertionError>;
     async fn cancel_order(&self, order_uid: &OrderUid, now: DateTime<Utc>) -> Result<()>;
+    /// Cancels the old order and inserts the new one atomically. Returns `false` without inserting
+    /// the new order if the old one got cancelled or filled completely in the meantime.
     async fn replace_order(
         &self,
         old_order: &OrderUid,
         new_order: &Order,
         new_quote: Option<Quote>,
-    ) -> Result<(), InsertionError>;
+    ) -> Result<bool, InsertionError>;
     async fn orders_for_tx(&self, tx_hash: &H256) -> Result<Vec<Order>>;
     async fn single_order(&self, uid: &OrderUid) -> Result<Option<Order>>;
     /// All orders of a single user ordered by creation date descending (newest orders first).
@@ -66,6 +72,7 @@ impl From<sqlx::Error> for InsertionError {
 }
 
 async fn insert_order(order: &Order, ex: &mut PgConnection) -> Result<(), InsertionError> {
+    let (quote_expiry_block, quote_last_look) = quote_firmness_into(order.metadata.quote_firmness);
     let order = database::orders::Order {
         uid: ByteArray(order.metadata.uid.0),
         owner: ByteArray(order.metadata.owner.0),
@@ -86,8 +93,18 @@ async fn insert_order(order: &Order, ex: &mut PgConnection) -> Result<(), Insert
         sell_token_balance: sell_token_source_into(order.data.sell_token_balance),
         buy_token_balance: buy_token_destination_into(order.data.buy_token_balance),
         full_fee_amount: u256_to_big_decimal(&order.metadata.full_fee_amount),
-        is_liquidity_order: order.metadata.is_liquidity_order,
+        protocol_fee_amount: u256_to_big_decimal(&order.metadata.protocol_fee_amount),
         cancellation_timestamp: None,
+        quote_expiry_block,
+        quote_last_look,
+        class: order_class_into(order.metadata.class),
+        min_partial_fill_amount: order
+            .metadata
+            .min_partial_fill_amount
+            .as_ref()
+            .map(u256_to_big_decimal),
+        private_submission: order.metadata.private_submission,
+        condition: order_condition_into(order.metadata.condition.as_ref()),
     };
     database::orders::insert_order(ex, &order)
         .await
@@ -112,6 +129,9 @@ async fn insert_quote(
         sell_token_price: quote.data.fee_parameters.sell_token_price,
         sell_amount: u256_to_big_decimal(&quote.sell_amount),
         buy_amount: u256_to_big_decimal(&quote.buy_amount),
+        subsidy_discount: Some(quote.subsidy.discount),
+        subsidy_min_discounted: Some(quote.subsidy.min_discounted),
+        subsidy_factor: Some(quote.subsidy.factor),
     };
     database::orders::insert_quote(ex, &quote)
         .await
@@ -119,6 +139,52 @@ async fn insert_quote(
     Ok(())
 }
 
+impl Postgres {
+    pub async fn order_fee_policy(&self, uid: &OrderUid) -> Result<Option<OrderFeePolicy>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["order_fee_policy"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        // Read the order and its quote from the same snapshot so that concurrent writes can't
+        // make them contradict each other.
+        let mut ex = database::begin_snapshot(&mut ex).await?;
+        let uid = ByteArray(uid.0);
+        let (order, quote) = match (
+            database::orders::read_order(&mut ex, &uid).await?,
+            database::orders::read_quote(&mut ex, &uid).await?,
+        ) {
+            (Some(order), Some(quote)) => (order, quote),
+            _ => return Ok(None),
+        };
+        let fee_parameters = FeeParameters {
+            gas_amount: quote.gas_amount,
+            gas_price: quote.gas_price,
+            sell_token_price: quote.sell_token_price,
+        };
+        let subsidy = match (
+            quote.subsidy_discount,
+            quote.subsidy_min_discounted,
+            quote.subsidy_factor,
+        ) {
+            (Some(discount), Some(min_discounted), Some(factor)) => Some(Subsidy {
+                discount,
+                min_discounted,
+                factor,
+            }),
+            _ => None,
+        };
+        let fee_amount = big_decimal_to_u256(&order.fee_amount)
+            .ok_or_else(|| anyhow!("fee_amount is not U256"))?;
+        Ok(Some(order_fee_policy(
+            &fee_parameters,
+            subsidy.as_ref(),
+            fee_amount,
+        )))
+    }
+}
+
 #[async_trait::async_trait]
 impl OrderStoring for Postgres {
     async fn insert_order(
@@ -133,6 +199,10 @@ impl OrderStoring for Postgres {
 
         let order = order.clone();
         let mut connection = self.pool.acquire().await?;
+        let _transaction_timer = super::Metrics::get()
+            .database_transactions
+            .with_label_values(&["insert_order"])
+            .start_timer();
         connection
             .transaction(move |transaction| {
                 async move {
@@ -165,7 +235,7 @@ impl OrderStoring for Postgres {
         old_order: &model::order::OrderUid,
         new_order: &model::order::Order,
         new_quote: Option<Quote>,
-    ) -> anyhow::Result<(), super::orders::InsertionError> {
+    ) -> anyhow::Result<bool, super::orders::InsertionError> {
         let _timer = super::Metrics::get()
             .database_queries
             .with_label_values(&["replace_order"])
@@ -174,20 +244,29 @@ impl OrderStoring for Postgres {
         let old_order = *old_order;
         let new_order = new_order.clone();
         let mut connection = self.pool.acquire().await?;
+        let _transaction_timer = super::Metrics::get()
+            .database_transactions
+            .with_label_values(&["replace_order"])
+            .start_timer();
         connection
             .transaction(move |ex| {
                 async move {
-                    database::orders::cancel_order(
+                    // Cancelling only open orders makes sure that the old order didn't get filled
+                    // since it was checked before replacing it.
+                    if !database::orders::cancel_open_order(
                         ex,
                         &ByteArray(old_order.0),
                         new_order.metadata.creation_date,
                     )
-                    .await?;
+                    .await?
+                    {
+                        return Ok(false);
+                    }
                     insert_order(&new_order, ex).await?;
                     if let Some(quote) = new_quote {
                         insert_quote(&new_order.metadata.uid, &quote, ex).await?;
                     }
-                    Ok(())
+                    Ok(true)
                 }
                 .boxed()
             })
@@ -298,7 +377,20 @@ fn full_order_into_model_order(order: FullOrder) -> Result<Order> {
         settlement_contract: H160(order.settlement_contract.0),
         full_fee_amount: big_decimal_to_u256(&order.full_fee_amount)
             .ok_or_else(|| anyhow!("full_fee_amount is not U256"))?,
-        is_liquidity_order: order.is_liquidity_order,
+        protocol_fee_amount: big_decimal_to_u256(&order.protocol_fee_amount)
+            .ok_or_else(|| anyhow!("protocol_fee_amount is not U256"))?,
+        class: order_class_from(order.class),
+        quote_firmness: quote_firmness_from(order.quote_expiry_block, order.quote_last_look),
+        min_partial_fill_amount: order
+            .min_partial_fill_amount
+            .as_ref()
+            .map(|amount| {
+                big_decimal_to_u256(amount)
+                    .ok_or_else(|| anyhow!("min_partial_fill_amount is not U256"))
+            })
+            .transpose()?,
+        private_submission: order.private_submission,
+        condition: order_condition_from(order.condition)?,
     };
     let data = OrderData {
         sell_token: H160(order.sell_token.0),
@@ -348,13 +440,20 @@ mod tests {
     use chrono::Duration;
     use database::byte_array::ByteArray;
     use database::orders::{
-        BuyTokenDestination as DbBuyTokenDestination, FullOrder, OrderKind as DbOrderKind,
-        SellTokenSource as DbSellTokenSource, SigningScheme as DbSigningScheme,
+        BuyTokenDestination as DbBuyTokenDestination, FullOrder, OrderClass as DbOrderClass,
+        OrderKind as DbOrderKind, SellTokenSource as DbSellTokenSource,
+        SigningScheme as DbSigningScheme,
     };
     use model::{
-        order::{Order, OrderData, OrderMetadata, OrderStatus, OrderUid},
+        fee_policy::FeeSubsidyRule,
+        order::{
+            Order, OrderClass, OrderCondition, OrderData, OrderMetadata, OrderStatus, OrderUid,
+            QuoteFirmness,
+        },
         signature::{Signature, SigningScheme},
     };
+    use primitive_types::U256;
+    use shared::order_quoting::QuoteData;
     use std::sync::atomic::{AtomicI64, Ordering};
 
     #[test]
@@ -386,7 +485,13 @@ mod tests {
             sell_token_balance: DbSellTokenSource::External,
             buy_token_balance: DbBuyTokenDestination::Internal,
             presignature_pending: false,
-            is_liquidity_order: true,
+            quote_expiry_block: None,
+            quote_last_look: false,
+            class: DbOrderClass::Liquidity,
+            protocol_fee_amount: BigDecimal::default(),
+            min_partial_fill_amount: None,
+            private_submission: false,
+            condition: None,
         };
 
         // Open - sell (filled - 0%)
@@ -588,9 +693,10 @@ mod tests {
             },
             ..Default::default()
         };
-        db.replace_order(&old_order.metadata.uid, &new_order, None)
+        assert!(db
+            .replace_order(&old_order.metadata.uid, &new_order, None)
             .await
-            .unwrap();
+            .unwrap());
 
         let order_statuses = db
             .user_orders(&owner, 0, None)
@@ -665,6 +771,47 @@ mod tests {
         assert_eq!(old_order_cancellation, None);
     }
 
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_replace_order_rejects_new_order_if_old_order_not_open() {
+        let owner = H160([0x77; 20]);
+
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let old_order = Order {
+            metadata: OrderMetadata {
+                owner,
+                uid: OrderUid([1; 56]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        db.insert_order(&old_order, None).await.unwrap();
+        db.cancel_order(&old_order.metadata.uid, Utc::now())
+            .await
+            .unwrap();
+
+        let new_order = Order {
+            metadata: OrderMetadata {
+                owner,
+                uid: OrderUid([2; 56]),
+                creation_date: Utc::now(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(!db
+            .replace_order(&old_order.metadata.uid, &new_order, None)
+            .await
+            .unwrap());
+        assert!(db
+            .single_order(&new_order.metadata.uid)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
     #[tokio::test]
     #[ignore]
     async fn postgres_presignature_status() {
@@ -738,4 +885,160 @@ mod tests {
         insert_presignature(true).await;
         assert_eq!(order_status().await, OrderStatus::Open);
     }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_quote_firmness_roundtrip() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+        let order = Order {
+            data: OrderData {
+                valid_to: u32::MAX,
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid([1; 56]),
+                class: OrderClass::Liquidity,
+                quote_firmness: Some(QuoteFirmness {
+                    expiry_block: Some(42),
+                    last_look: true,
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        db.insert_order(&order, None).await.unwrap();
+
+        let order_ = db.single_order(&order.metadata.uid).await.unwrap().unwrap();
+        assert_eq!(order_.metadata.class, OrderClass::Liquidity);
+        assert_eq!(
+            order_.metadata.quote_firmness,
+            order.metadata.quote_firmness
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_min_partial_fill_amount_roundtrip() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+        let order = Order {
+            data: OrderData {
+                valid_to: u32::MAX,
+                partially_fillable: true,
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid([1; 56]),
+                min_partial_fill_amount: Some(U256::MAX),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        db.insert_order(&order, None).await.unwrap();
+
+        let order_ = db.single_order(&order.metadata.uid).await.unwrap().unwrap();
+        assert_eq!(order_.metadata.min_partial_fill_amount, Some(U256::MAX));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_private_submission_roundtrip() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+        let order = Order {
+            data: OrderData {
+                valid_to: u32::MAX,
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid([1; 56]),
+                private_submission: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        db.insert_order(&order, None).await.unwrap();
+
+        let order_ = db.single_order(&order.metadata.uid).await.unwrap().unwrap();
+        assert!(order_.metadata.private_submission);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_order_condition_roundtrip() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+        let condition = OrderCondition::TimeWindow {
+            not_before: Some(1),
+            not_after: None,
+        };
+        let order = Order {
+            data: OrderData {
+                valid_to: u32::MAX,
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid([1; 56]),
+                condition: Some(condition.clone()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        db.insert_order(&order, None).await.unwrap();
+
+        let order_ = db.single_order(&order.metadata.uid).await.unwrap().unwrap();
+        assert_eq!(order_.metadata.condition, Some(condition));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_order_fee_policy() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+        let order = Order {
+            data: OrderData {
+                fee_amount: 3.into(),
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid([1; 56]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let subsidy = Subsidy {
+            factor: 0.5,
+            ..Default::default()
+        };
+        let quote = Quote::new(
+            None,
+            QuoteData {
+                fee_parameters: FeeParameters {
+                    gas_amount: 2.,
+                    gas_price: 3.,
+                    sell_token_price: 1.,
+                },
+                ..Default::default()
+            },
+        )
+        .with_subsidy(&subsidy);
+        db.insert_order(&order, Some(quote)).await.unwrap();
+
+        let policy = db
+            .order_fee_policy(&order.metadata.uid)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(policy.raw_fee_amount, 6.into());
+        assert_eq!(policy.subsidized_fee_amount, Some(3.into()));
+        assert_eq!(policy.fee_amount, 3.into());
+        assert_eq!(policy.applied_rules, vec![FeeSubsidyRule::FeeFactor]);
+
+        assert!(db
+            .order_fee_policy(&OrderUid([2; 56]))
+            .await
+            .unwrap()
+            .is_none());
+    }
 }
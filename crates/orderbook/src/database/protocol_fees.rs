@@ -0,0 +1,27 @@
+use anyhow::{anyhow, Result};
+use model::protocol_fees::ProtocolFeeAccrual;
+use number_conversions::big_decimal_to_u256;
+use primitive_types::H160;
+
+impl super::Postgres {
+    pub async fn protocol_fees(&self) -> Result<Vec<ProtocolFeeAccrual>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["protocol_fees"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let accruals = database::protocol_fees::accrued_per_token(&mut ex).await?;
+        accruals
+            .into_iter()
+            .map(|accrual| {
+                Ok(ProtocolFeeAccrual {
+                    token: H160(accrual.token.0),
+                    amount: big_decimal_to_u256(&accrual.amount)
+                        .ok_or_else(|| anyhow!("protocol fee amount is not U256"))?,
+                    trades: accrual.trades as u64,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,62 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use database::byte_array::ByteArray;
+use model::{app_id::AppId, integrator_analytics::IntegratorFunnel, quote::QuoteId};
+
+/// Who requested a quote, so that the quote funnel can be attributed to integrators.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QuoteRequestAttribution {
+    pub time: DateTime<Utc>,
+    pub app_data: AppId,
+    pub user_agent: Option<String>,
+    /// The stored quote the request got. `None` if quoting failed or the quote wasn't stored.
+    pub quote_id: Option<QuoteId>,
+}
+
+impl super::Postgres {
+    pub async fn insert_quote_request(&self, request: &QuoteRequestAttribution) -> Result<()> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["insert_quote_request"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let row = database::quote_requests::QuoteRequest {
+            time: request.time,
+            app_data: ByteArray(request.app_data.0),
+            user_agent: request.user_agent.clone(),
+            quote_id: request.quote_id,
+        };
+        database::quote_requests::insert(&mut ex, &row)
+            .await
+            .context("insert_quote_request")
+    }
+
+    pub async fn integrator_funnels(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<IntegratorFunnel>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["integrator_funnels"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let funnels = database::quote_requests::load_funnel(&mut ex, start, end)
+            .await
+            .context("integrator_funnels")?;
+        Ok(funnels
+            .into_iter()
+            .map(|funnel| {
+                IntegratorFunnel::new(
+                    AppId(funnel.app_data.0),
+                    funnel.quote_requests as u64,
+                    funnel.quotes as u64,
+                    funnel.orders as u64,
+                    funnel.filled_orders as u64,
+                )
+            })
+            .collect())
+    }
+}
@@ -9,7 +9,7 @@ use model::quote::QuoteId;
 use number_conversions::u256_to_big_decimal;
 use shared::{
     db_order_conversions::order_kind_into,
-    order_quoting::{QuoteData, QuoteSearchParameters, QuoteStoring},
+    order_quoting::{FeeGuaranteeStoring, QuoteData, QuoteSearchParameters, QuoteStoring},
 };
 
 #[async_trait::async_trait]
@@ -77,4 +77,39 @@ impl QuoteStoring for Postgres {
             .map(|quote| Ok((quote.id, quote.try_into()?)))
             .transpose()
     }
+
+    async fn extend(&self, id: QuoteId, expiration: DateTime<Utc>) -> Result<bool> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["extend_quote"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        Ok(database::quotes::update_expiration(&mut ex, id, expiration).await?)
+    }
+}
+
+#[async_trait::async_trait]
+impl FeeGuaranteeStoring for Postgres {
+    async fn record_fee_guarantee_cost(
+        &self,
+        id: QuoteId,
+        cost: f64,
+        since: DateTime<Utc>,
+        budget: f64,
+        time: DateTime<Utc>,
+    ) -> Result<bool> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["record_fee_guarantee_cost"])
+            .start_timer();
+
+        let mut ex = self.pool.begin().await?;
+        let recorded = database::fee_guarantees::record_cost_within_budget(
+            &mut ex, id, cost, since, budget, time,
+        )
+        .await?;
+        ex.commit().await?;
+        Ok(recorded)
+    }
 }
@@ -0,0 +1,58 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use database::byte_array::ByteArray;
+use model::{
+    order::OrderUid,
+    sealed_order::{self, SealedOrder, SealedOrderId, SealedOrderStatus},
+};
+use primitive_types::H256;
+
+impl super::Postgres {
+    /// Stores a sealed order until the autopilot reveals it and returns its id.
+    pub async fn insert_sealed_order(
+        &self,
+        sealed: &[u8],
+        now: DateTime<Utc>,
+    ) -> Result<SealedOrderId> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["insert_sealed_order"])
+            .start_timer();
+
+        let id = sealed_order::sealed_order_id(sealed);
+        let order = database::sealed_orders::SealedOrder {
+            id: ByteArray(id.0),
+            sealed: sealed.to_vec(),
+            creation_timestamp: now,
+            order_uid: None,
+            rejection_reason: None,
+        };
+        let mut ex = self.pool.acquire().await?;
+        database::sealed_orders::insert(&mut ex, &order)
+            .await
+            .context("insert_sealed_order")?;
+        Ok(id)
+    }
+
+    pub async fn sealed_order(&self, id: &SealedOrderId) -> Result<Option<SealedOrder>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["sealed_order"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let order = database::sealed_orders::fetch(&mut ex, &ByteArray(id.0))
+            .await
+            .context("sealed_order")?;
+        Ok(order.map(|order| SealedOrder {
+            id: H256(order.id.0),
+            status: match (order.order_uid, order.rejection_reason) {
+                (Some(uid), _) => SealedOrderStatus::Revealed {
+                    order_uid: OrderUid(uid.0),
+                },
+                (None, Some(reason)) => SealedOrderStatus::Rejected { reason },
+                (None, None) => SealedOrderStatus::Pending,
+            },
+        }))
+    }
+}
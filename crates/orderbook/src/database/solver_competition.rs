@@ -1,8 +1,177 @@
 use super::Postgres;
 use crate::solver_competition::{Identifier, LoadSolverCompetitionError, SolverCompetitionStoring};
 use anyhow::{Context, Result};
-use database::byte_array::ByteArray;
-use model::solver_competition::SolverCompetition;
+use chrono::{DateTime, Utc};
+use database::{
+    auction::AuctionId, byte_array::ByteArray, events::EventIndex,
+    solver_competition::StoredCompetition,
+};
+use futures::{Stream, StreamExt};
+use model::{
+    order::OrderUid,
+    solver_competition::{CompetitionSummary, MevRebate, OrderRebate, SolverCompetition},
+};
+use number_conversions::big_decimal_to_u256;
+use primitive_types::H160;
+use sqlx::{postgres::PgListener, PgConnection};
+
+/// Filters stored solver competitions. Unset fields don't filter.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CompetitionFilter {
+    /// Only competitions whose auction includes the token.
+    pub token: Option<H160>,
+    /// Only competitions in which the solver proposed a solution, including solutions that failed
+    /// to simulate.
+    pub solver: Option<String>,
+    /// Range of the time the competition was stored, the end is exclusive.
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl CompetitionFilter {
+    /// Whether the summary of a stored competition matches the filter.
+    pub fn matches(&self, competition: &SolverCompetition, summary: &CompetitionSummary) -> bool {
+        self.token.map_or(true, |token| {
+            competition.auction.prices.contains_key(&token)
+        }) && self
+            .solver
+            .as_ref()
+            .map_or(true, |solver| summary.solvers.contains(solver))
+            && self.from.map_or(true, |from| summary.created >= from)
+            && self.to.map_or(true, |to| summary.created < to)
+    }
+}
+
+/// The object storage key of an archived solver competition.
+pub fn archive_key(id: AuctionId) -> String {
+    format!("solver_competitions/{id}.json")
+}
+
+impl Postgres {
+    async fn load_archived(&self, id: AuctionId) -> Result<SolverCompetition> {
+        let archive = self
+            .competition_archive
+            .as_ref()
+            .context("solver competition is archived but no archive is configured")?;
+        let data = archive
+            .get(&archive_key(id))
+            .await?
+            .with_context(|| format!("archived solver competition {id} is missing"))?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    /// Moves up to `limit` solver competitions created before the specified time to the archive
+    /// and returns how many were moved. Does nothing if no archive is configured.
+    pub async fn archive_solver_competitions(
+        &self,
+        created_before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<usize> {
+        let archive = match &self.competition_archive {
+            Some(archive) => archive,
+            None => return Ok(0),
+        };
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["archive_solver_competitions"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let competitions =
+            database::solver_competition::load_archivable(&mut ex, created_before, limit).await?;
+        for (id, json) in &competitions {
+            // The json only gets dropped once the archive has it so a failure in between at worst
+            // uploads the competition again.
+            archive
+                .put(&archive_key(*id), serde_json::to_vec(json)?)
+                .await
+                .with_context(|| format!("failed to archive solver competition {id}"))?;
+            database::solver_competition::mark_archived(&mut ex, *id).await?;
+        }
+        Ok(competitions.len())
+    }
+
+    /// Returns the solver competitions matching the filter, newest first. Archived competitions
+    /// can't be searched.
+    pub async fn search_solver_competitions(
+        &self,
+        filter: &CompetitionFilter,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<CompetitionSummary>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["search_solver_competitions"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let token = filter.token.map(|token| ByteArray(token.0));
+        let filter = database::solver_competition::CompetitionFilter {
+            token: token.as_ref(),
+            solver: filter.solver.as_deref(),
+            from: filter.from,
+            to: filter.to,
+        };
+        database::solver_competition::search(
+            &mut ex,
+            &filter,
+            i64::try_from(offset).unwrap_or(i64::MAX),
+            i64::try_from(limit).unwrap_or(i64::MAX),
+        )
+        .await
+        .context("search_solver_competitions")?
+        .into_iter()
+        .map(|found| {
+            let competition: SolverCompetition =
+                serde_json::from_value(found.json).context("invalid solver competition")?;
+            Ok(CompetitionSummary::new(&competition, found.created))
+        })
+        .collect()
+    }
+
+    /// Returns the ids of solver competitions as they get saved by any orderbook replica.
+    pub async fn saved_solver_competitions(&self) -> Result<impl Stream<Item = Result<AuctionId>>> {
+        let mut listener = PgListener::connect_with(&self.pool).await?;
+        listener
+            .listen(database::solver_competition::NOTIFICATION_CHANNEL)
+            .await?;
+        Ok(listener.into_stream().map(|notification| {
+            notification?
+                .payload()
+                .parse()
+                .context("invalid solver competition notification")
+        }))
+    }
+}
+
+async fn load_mev_rebate(
+    ex: &mut PgConnection,
+    auction_id: AuctionId,
+) -> Result<Option<MevRebate>> {
+    let rebate = match database::settlement_rebates::load_by_auction(ex, auction_id).await? {
+        Some(rebate) => rebate,
+        None => return Ok(None),
+    };
+    let settlement = EventIndex {
+        block_number: rebate.block_number,
+        log_index: rebate.log_index,
+    };
+    let orders = database::settlement_rebates::load_order_rebates(ex, &settlement)
+        .await?
+        .into_iter()
+        .map(|order| {
+            Ok(OrderRebate {
+                id: OrderUid(order.order_uid.0),
+                amount: big_decimal_to_u256(&order.rebate).context("order rebate is not a u256")?,
+            })
+        })
+        .collect::<Result<_>>()?;
+    Ok(Some(MevRebate {
+        recipient: H160(rebate.recipient.0),
+        amount: big_decimal_to_u256(&rebate.rebate).context("rebate is not a u256")?,
+        orders,
+    }))
+}
 
 #[async_trait::async_trait]
 impl SolverCompetitionStoring for Postgres {
@@ -32,6 +201,11 @@ impl SolverCompetitionStoring for Postgres {
             .start_timer();
 
         let mut ex = self.pool.acquire().await.map_err(anyhow::Error::from)?;
+        // The rebates of a competition get stored while the API might already serve it, so read
+        // them from the same snapshot as the competition.
+        let mut ex = database::begin_snapshot(&mut ex)
+            .await
+            .map_err(anyhow::Error::from)?;
         let value = match id {
             Identifier::Id(id) => database::solver_competition::load_by_id(&mut ex, id).await,
             Identifier::Transaction(hash) => {
@@ -39,20 +213,27 @@ impl SolverCompetitionStoring for Postgres {
             }
         }
         .context("failed to get solver competition by ID")?;
-        match value {
-            None => Err(LoadSolverCompetitionError::NotFound),
-            Some(value) => serde_json::from_value(value)
-                .map_err(anyhow::Error::from)
-                .map_err(Into::into),
-        }
+        let mut competition: SolverCompetition = match value {
+            None => return Err(LoadSolverCompetitionError::NotFound),
+            Some(StoredCompetition {
+                json: Some(value), ..
+            }) => serde_json::from_value(value).map_err(anyhow::Error::from)?,
+            Some(StoredCompetition { id, json: None }) => self.load_archived(id).await?,
+        };
+        competition.mev_rebate = load_mev_rebate(&mut ex, competition.auction_id)
+            .await
+            .context("failed to load mev rebate")?;
+        Ok(competition)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::object_storage::ObjectStorage;
     use model::solver_competition::{CompetitionAuction, SolverSettlement};
     use primitive_types::H256;
+    use std::sync::Arc;
 
     #[tokio::test]
     #[ignore]
@@ -70,20 +251,72 @@ mod tests {
             auction: CompetitionAuction {
                 orders: vec![Default::default()],
                 prices: [Default::default()].into_iter().collect(),
+                amendments: vec![],
             },
+            order_selection_seed: Some(6),
             solutions: vec![SolverSettlement {
                 solver: "asdf".to_string(),
                 objective: Default::default(),
                 clearing_prices: [Default::default()].into_iter().collect(),
                 orders: vec![Default::default()],
                 call_data: vec![1, 2],
+                auction_version: 0,
+                balance_diffs: Default::default(),
+                sub_auctions: vec![],
             }],
+            mev_rebate: None,
+            excluded_solvers: vec![],
+            performance: None,
+            simulation_failures: vec![],
+            revert: None,
         };
         db.save(expected.clone()).await.unwrap();
         let actual = db.load(Identifier::Id(0)).await.unwrap();
         assert_eq!(expected, actual);
     }
 
+    #[derive(Default)]
+    struct InMemoryStorage(std::sync::Mutex<std::collections::HashMap<String, Vec<u8>>>);
+
+    #[async_trait::async_trait]
+    impl ObjectStorage for InMemoryStorage {
+        async fn put(&self, key: &str, data: Vec<u8>) -> Result<()> {
+            self.0.lock().unwrap().insert(key.to_string(), data);
+            Ok(())
+        }
+
+        async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+            Ok(self.0.lock().unwrap().get(key).cloned())
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_archived_solver_competition_roundtrip() {
+        let archive = Arc::new(InMemoryStorage::default());
+        let db = Postgres::new("postgresql://")
+            .unwrap()
+            .with_competition_archive(archive.clone());
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let expected = SolverCompetition {
+            auction_id: 1,
+            transaction_hash: Some(H256([1; 32])),
+            ..Default::default()
+        };
+        db.save(expected.clone()).await.unwrap();
+        let archived = db
+            .archive_solver_competitions(Utc::now() + chrono::Duration::seconds(1), 10)
+            .await
+            .unwrap();
+        assert_eq!(archived, 1);
+        assert!(archive.0.lock().unwrap().contains_key(&archive_key(1)));
+
+        for identifier in [Identifier::Id(1), Identifier::Transaction(H256([1; 32]))] {
+            assert_eq!(db.load(identifier).await.unwrap(), expected);
+        }
+    }
+
     #[tokio::test]
     #[ignore]
     async fn not_found_error() {
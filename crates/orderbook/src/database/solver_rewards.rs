@@ -0,0 +1,29 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use model::solver_rewards::SolverRewards;
+use primitive_types::H160;
+
+impl super::Postgres {
+    pub async fn solver_rewards(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<SolverRewards>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["solver_rewards"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let rewards = database::solver_rewards::load_by_period(&mut ex, start, end).await?;
+        Ok(rewards
+            .into_iter()
+            .map(|rewards| SolverRewards {
+                solver: H160(rewards.solver.0),
+                settlements: rewards.settlements as u64,
+                user_orders: rewards.user_orders as u64,
+                reward: rewards.reward,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,133 @@
+use anyhow::{Context, Result};
+use database::byte_array::ByteArray;
+use model::{
+    solver_competition::SolverCompetition,
+    solver_team::{SolverTeam, SolverTeamConfig},
+};
+use primitive_types::H160;
+
+impl super::Postgres {
+    /// Returns the solver team registered for `owner`.
+    pub async fn solver_team(&self, owner: H160) -> Result<Option<SolverTeam>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["solver_team"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let team = database::solver_teams::fetch_by_owner(&mut ex, &ByteArray(owner.0))
+            .await
+            .context("solver_team")?;
+        Ok(team.map(|team| SolverTeam {
+            name: team.name,
+            solver: H160(team.solver.0),
+            config: SolverTeamConfig {
+                callback_url: team.callback_url,
+            },
+        }))
+    }
+
+    /// Replaces the configuration of the solver team of `owner`. Returns whether the team exists.
+    pub async fn update_solver_team_config(
+        &self,
+        owner: H160,
+        config: &SolverTeamConfig,
+    ) -> Result<bool> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["update_solver_team_config"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        database::solver_teams::update_config(
+            &mut ex,
+            &ByteArray(owner.0),
+            config.callback_url.as_deref(),
+        )
+        .await
+        .context("update_solver_team_config")
+    }
+
+    /// Returns the most recent solver competitions the solver took part in, newest first.
+    pub async fn solver_competitions_of_solver(
+        &self,
+        solver: &str,
+        limit: u64,
+    ) -> Result<Vec<SolverCompetition>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["solver_competitions_of_solver"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+        database::solver_competition::load_latest_by_solver(&mut ex, solver, limit)
+            .await
+            .context("solver_competitions_of_solver")?
+            .into_iter()
+            .map(|json| serde_json::from_value(json).context("invalid solver competition"))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{database::Postgres, solver_competition::SolverCompetitionStoring};
+    use model::solver_competition::{SimulationFailure, SolverSettlement};
+
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_solver_team() {
+        let db = Postgres::new("postgresql://").unwrap();
+        database::clear_DANGER(&db.pool).await.unwrap();
+
+        let owner = H160([1; 20]);
+        let mut ex = db.pool.acquire().await.unwrap();
+        database::solver_teams::insert(
+            &mut ex,
+            &database::solver_teams::SolverTeam {
+                owner: ByteArray(owner.0),
+                name: "solver".to_string(),
+                solver: ByteArray([2; 20]),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(db.solver_team(H160([3; 20])).await.unwrap(), None);
+
+        let config = SolverTeamConfig {
+            callback_url: Some("http://solver.test".to_string()),
+        };
+        assert!(db.update_solver_team_config(owner, &config).await.unwrap());
+        assert_eq!(
+            db.solver_team(owner).await.unwrap(),
+            Some(SolverTeam {
+                name: "solver".to_string(),
+                solver: H160([2; 20]),
+                config,
+            })
+        );
+
+        let competition = SolverCompetition {
+            auction_id: 4,
+            solutions: vec![SolverSettlement {
+                solver: "other".to_string(),
+                ..Default::default()
+            }],
+            simulation_failures: vec![SimulationFailure {
+                solver: "solver".to_string(),
+                error: "reverted".to_string(),
+            }],
+            ..Default::default()
+        };
+        db.save(competition.clone()).await.unwrap();
+        assert_eq!(
+            db.solver_competitions_of_solver("solver", 10)
+                .await
+                .unwrap(),
+            vec![competition]
+        );
+    }
+}
@@ -1,16 +1,28 @@
 use crate::database::Postgres;
-use anyhow::{anyhow, Context, Result};
-use database::{byte_array::ByteArray, trades::TradesQueryRow};
+use anyhow::{anyhow, ensure, Context, Result};
+use chrono::{DateTime, Utc};
+use database::{
+    byte_array::ByteArray,
+    trade_routes,
+    trades::{SettlementTradesQueryRow, TradesFilter, TradesQueryRow},
+};
 use ethcontract::H160;
 use futures::{stream::TryStreamExt, StreamExt};
-use model::{order::OrderUid, trade::Trade};
-use number_conversions::big_decimal_to_big_uint;
-use primitive_types::H256;
-use std::convert::TryInto;
+use model::{
+    fee_recommendation::FeeRecommendation,
+    order::OrderUid,
+    trade::{SettlementTrades, Trade, TradeVenue, VenueKind},
+};
+use number_conversions::{big_decimal_to_big_uint, u256_to_big_decimal};
+use primitive_types::{H256, U256};
+use shared::order_quoting::BatchSizeStoring;
+use std::{convert::TryInto, fmt, ops::RangeInclusive, str::FromStr};
 
 #[async_trait::async_trait]
 pub trait TradeRetrieving: Send + Sync {
     async fn trades(&self, filter: &TradeFilter) -> Result<Vec<Trade>>;
+    /// The trades matching the filter grouped by their settlement.
+    async fn settlement_trades(&self, filter: &TradeFilter) -> Result<Vec<SettlementTrades>>;
 }
 
 /// Any default value means that this field is unfiltered.
@@ -18,6 +30,46 @@ pub trait TradeRetrieving: Send + Sync {
 pub struct TradeFilter {
     pub owner: Option<H160>,
     pub order_uid: Option<OrderUid>,
+    pub sell_token: Option<H160>,
+    pub buy_token: Option<H160>,
+    /// Range of the settlement block timestamp, the end is exclusive.
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub after: Option<TradeCursor>,
+    pub limit: Option<u64>,
+}
+
+/// Position in the list of trades or settlements to continue from. It is the block number and log
+/// index of the last received trade or settlement, formatted as `<blockNumber>_<logIndex>`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TradeCursor {
+    pub block_number: u64,
+    pub log_index: u64,
+}
+
+impl FromStr for TradeCursor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (block_number, log_index) = s
+            .split_once('_')
+            .ok_or_else(|| anyhow!("cursor is not <blockNumber>_<logIndex>"))?;
+        let cursor = Self {
+            block_number: block_number.parse().context("invalid block number")?,
+            log_index: log_index.parse().context("invalid log index")?,
+        };
+        ensure!(
+            i64::try_from(cursor.block_number).is_ok() && i64::try_from(cursor.log_index).is_ok(),
+            "cursor out of range"
+        );
+        Ok(cursor)
+    }
+}
+
+impl fmt::Display for TradeCursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}_{}", self.block_number, self.log_index)
+    }
 }
 
 #[async_trait::async_trait]
@@ -29,20 +81,148 @@ impl TradeRetrieving for Postgres {
             .start_timer();
 
         let mut ex = self.pool.acquire().await?;
-        database::trades::trades(
+        let bytes = FilterBytes::new(filter);
+        database::trades::trades(&mut ex, &bytes.database_filter(filter))
+            .map(|result| match result {
+                Ok(row) => trade_from(row),
+                Err(err) => Err(anyhow::Error::from(err)),
+            })
+            .try_collect()
+            .await
+    }
+
+    async fn settlement_trades(&self, filter: &TradeFilter) -> Result<Vec<SettlementTrades>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["settlement_trades"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let bytes = FilterBytes::new(filter);
+        database::trades::settlement_trades(&mut ex, &bytes.database_filter(filter))
+            .await?
+            .into_iter()
+            .map(settlement_trades_from)
+            .collect()
+    }
+}
+
+impl Postgres {
+    pub async fn fee_recommendation(
+        &self,
+        sell_token: H160,
+        buy_token: H160,
+        sell_amounts: RangeInclusive<U256>,
+        since: DateTime<Utc>,
+        max_samples: u64,
+    ) -> Result<FeeRecommendation> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["fee_recommendation"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        let costs = database::trades::pair_trade_costs(
             &mut ex,
-            filter.owner.map(|owner| ByteArray(owner.0)).as_ref(),
-            filter.order_uid.map(|uid| ByteArray(uid.0)).as_ref(),
+            &ByteArray(sell_token.0),
+            &ByteArray(buy_token.0),
+            since,
+            &u256_to_big_decimal(sell_amounts.start()),
+            &u256_to_big_decimal(sell_amounts.end()),
+            max_samples.try_into().unwrap_or(i64::MAX),
+        )
+        .await
+        .context("fee_recommendation")?;
+        Ok(FeeRecommendation::from_trades(
+            costs
+                .into_iter()
+                .map(|cost| (cost.fee_bps, cost.slippage_bps)),
+        ))
+    }
+}
+
+#[async_trait::async_trait]
+impl BatchSizeStoring for Postgres {
+    async fn average_batch_size(
+        &self,
+        token_a: H160,
+        token_b: H160,
+        since: DateTime<Utc>,
+    ) -> Result<Option<f64>> {
+        let _timer = super::Metrics::get()
+            .database_queries
+            .with_label_values(&["average_batch_size"])
+            .start_timer();
+
+        let mut ex = self.pool.acquire().await?;
+        database::trades::average_pair_batch_size(
+            &mut ex,
+            &ByteArray(token_a.0),
+            &ByteArray(token_b.0),
+            since,
         )
-        .map(|result| match result {
-            Ok(row) => trade_from(row),
-            Err(err) => Err(anyhow::Error::from(err)),
-        })
-        .try_collect()
         .await
+        .context("average_batch_size")
     }
 }
 
+/// The filter fields the database query borrows.
+struct FilterBytes {
+    owner: Option<database::Address>,
+    order_uid: Option<database::OrderUid>,
+    sell_token: Option<database::Address>,
+    buy_token: Option<database::Address>,
+}
+
+impl FilterBytes {
+    fn new(filter: &TradeFilter) -> Self {
+        Self {
+            owner: filter.owner.map(|owner| ByteArray(owner.0)),
+            order_uid: filter.order_uid.map(|uid| ByteArray(uid.0)),
+            sell_token: filter.sell_token.map(|token| ByteArray(token.0)),
+            buy_token: filter.buy_token.map(|token| ByteArray(token.0)),
+        }
+    }
+
+    fn database_filter(&self, filter: &TradeFilter) -> TradesFilter<'_> {
+        TradesFilter {
+            owner: self.owner.as_ref(),
+            order_uid: self.order_uid.as_ref(),
+            sell_token: self.sell_token.as_ref(),
+            buy_token: self.buy_token.as_ref(),
+            from: filter.from,
+            to: filter.to,
+            // Cursors are range checked when they get parsed.
+            after: filter
+                .after
+                .map(|cursor| (cursor.block_number as i64, cursor.log_index as i64)),
+            limit: filter
+                .limit
+                .map(|limit| limit.try_into().unwrap_or(i64::MAX)),
+        }
+    }
+}
+
+fn settlement_trades_from(row: SettlementTradesQueryRow) -> Result<SettlementTrades> {
+    Ok(SettlementTrades {
+        block_number: row
+            .block_number
+            .try_into()
+            .context("block_number is not u64")?,
+        log_index: row.log_index.try_into().context("log_index is not u64")?,
+        tx_hash: H256(row.tx_hash.0),
+        solver: H160(row.solver.0),
+        block_timestamp: row.block_timestamp,
+        trade_count: row.trade_count.try_into().context("negative trade count")?,
+        owner_count: row.owner_count.try_into().context("negative owner count")?,
+        order_uids: row
+            .order_uids
+            .into_iter()
+            .map(|uid| OrderUid(uid.0))
+            .collect(),
+    })
+}
+
 fn trade_from(row: TradesQueryRow) -> Result<Trade> {
     let block_number = row
         .block_number
@@ -60,6 +240,15 @@ fn trade_from(row: TradesQueryRow) -> Result<Trade> {
     let buy_token = H160(row.buy_token.0);
     let sell_token = H160(row.sell_token.0);
     let tx_hash = row.tx_hash.map(|hash| H256(hash.0));
+    let route = row
+        .venue_kinds
+        .into_iter()
+        .zip(row.venue_addresses)
+        .map(|(kind, address)| TradeVenue {
+            kind: venue_kind_from(kind),
+            address: H160(address.0),
+        })
+        .collect();
     Ok(Trade {
         block_number,
         log_index,
@@ -71,9 +260,21 @@ fn trade_from(row: TradesQueryRow) -> Result<Trade> {
         buy_token,
         sell_token,
         tx_hash,
+        route,
     })
 }
 
+fn venue_kind_from(kind: trade_routes::VenueKind) -> VenueKind {
+    match kind {
+        trade_routes::VenueKind::UniswapV2 => VenueKind::UniswapV2,
+        trade_routes::VenueKind::UniswapV3 => VenueKind::UniswapV3,
+        trade_routes::VenueKind::BalancerV2 => VenueKind::BalancerV2,
+        trade_routes::VenueKind::ZeroEx => VenueKind::ZeroEx,
+        trade_routes::VenueKind::OneInch => VenueKind::OneInch,
+        trade_routes::VenueKind::Paraswap => VenueKind::Paraswap,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -81,5 +282,22 @@ mod tests {
     #[test]
     fn convert_trade() {
         trade_from(TradesQueryRow::default()).unwrap();
+        settlement_trades_from(SettlementTradesQueryRow::default()).unwrap();
+    }
+
+    #[test]
+    fn parse_cursor() {
+        let cursor = "15000000_42".parse::<TradeCursor>().unwrap();
+        assert_eq!(
+            cursor,
+            TradeCursor {
+                block_number: 15_000_000,
+                log_index: 42,
+            }
+        );
+        assert_eq!(cursor.to_string(), "15000000_42");
+        assert!("15000000".parse::<TradeCursor>().is_err());
+        assert!("a_1".parse::<TradeCursor>().is_err());
+        assert!(format!("{}_0", u64::MAX).parse::<TradeCursor>().is_err());
     }
 }
@@ -1,17 +1,21 @@
 pub mod api;
 pub mod arguments;
+pub mod buffers;
 pub mod database;
+pub mod object_storage;
 pub mod orderbook;
+pub mod rate_limit;
 pub mod solver_competition;
+pub mod trade_proofs;
 
+use crate::buffers::BufferCache;
 use crate::database::trades::TradeRetrieving;
 use crate::orderbook::Orderbook;
-use anyhow::{anyhow, Context as _, Result};
-use contracts::GPv2Settlement;
+use crate::rate_limit::InboundRateLimiter;
+use crate::trade_proofs::TradeProofs;
 use futures::Future;
-use model::DomainSeparator;
 use shared::order_quoting::QuoteHandler;
-use solver_competition::SolverCompetitionStoring;
+use solver_competition::{CompetitionResults, SolverCompetitionStoring};
 use std::{net::SocketAddr, sync::Arc};
 use tokio::{task, task::JoinHandle};
 use warp::Filter;
@@ -25,6 +29,11 @@ pub fn serve_api(
     shutdown_receiver: impl Future<Output = ()> + Send + 'static,
     solver_competition: Arc<dyn SolverCompetitionStoring>,
     solver_competition_auth: Option<String>,
+    analytics_auth: Option<String>,
+    competition_results: Arc<CompetitionResults>,
+    buffers: Arc<BufferCache>,
+    trade_proofs: Arc<TradeProofs>,
+    rate_limiter: Option<Arc<InboundRateLimiter>>,
 ) -> JoinHandle<()> {
     let filter = api::handle_all_routes(
         database,
@@ -32,37 +41,14 @@ pub fn serve_api(
         quotes,
         solver_competition,
         solver_competition_auth,
+        analytics_auth,
+        competition_results,
+        buffers,
+        trade_proofs,
+        rate_limiter,
     )
     .boxed();
     tracing::info!(%address, "serving order book");
     let (_, server) = warp::serve(filter).bind_with_graceful_shutdown(address, shutdown_receiver);
     task::spawn(server)
 }
-
-/**
- * Check that important constants such as the EIP 712 Domain Separator and Order Type Hash used in this binary match the ones on the deployed contract instance.
- * Signature inconsistencies due to a mismatch of these constants are hard to debug.
- */
-pub async fn verify_deployed_contract_constants(
-    contract: &GPv2Settlement,
-    chain_id: u64,
-) -> Result<()> {
-    let web3 = contract.raw_instance().web3();
-    let bytecode = hex::encode(
-        web3.eth()
-            .code(contract.address(), None)
-            .await
-            .context("Could not load deployed bytecode")?
-            .0,
-    );
-
-    let domain_separator = DomainSeparator::new(chain_id, contract.address());
-    if !bytecode.contains(&hex::encode(domain_separator.0)) {
-        return Err(anyhow!("Bytecode did not contain domain separator"));
-    }
-
-    if !bytecode.contains(&hex::encode(model::order::OrderData::TYPE_HASH)) {
-        return Err(anyhow!("Bytecode did not contain order type hash"));
-    }
-    Ok(())
-}
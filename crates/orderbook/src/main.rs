@@ -3,51 +3,76 @@ use contracts::{
     BalancerV2Vault, CowProtocolToken, CowProtocolVirtualToken, GPv2Settlement, IUniswapV3Factory,
     WETH9,
 };
-use ethcontract::errors::DeployError;
-use model::{order::BUY_ETH_ADDRESS, DomainSeparator};
+use ethcontract::{errors::DeployError, H160};
+use model::{order::BUY_ETH_ADDRESS, DomainParameters};
 use orderbook::{
-    database::Postgres, orderbook::Orderbook, serve_api, verify_deployed_contract_constants,
+    arguments::Arguments,
+    buffers::BufferCache,
+    database::{
+        health::{self, DatabaseHealth, DegradableQuoteStorage},
+        in_memory::InMemory,
+        Postgres, Storage, StorageBackend,
+    },
+    object_storage::{S3Config, S3Storage},
+    orderbook::Orderbook,
+    rate_limit::InboundRateLimiter,
+    serve_api,
+    solver_competition::{CompetitionArchiver, CompetitionResults},
+    trade_proofs::TradeProofs,
 };
 use shared::{
-    account_balances::Web3BalanceFetcher,
+    account_balances::{
+        storage_proofs::StorageProofBalanceFetcher, BalanceFetching, Web3BalanceFetcher,
+    },
     bad_token::{
         cache::CachingDetector,
         instrumented::InstrumentedBadTokenDetectorExt,
         list_based::{ListBasedDetector, UnknownTokenStrategy},
+        token_list::TokenListDetector,
         token_owner_finder,
         trace_call::TraceCallDetector,
+        BadTokenDetecting,
     },
     balancer_sor_api::DefaultBalancerSorApi,
     baseline_solver::BaseTokens,
+    buffers::BufferRetriever,
     current_block::current_block_stream,
     fee_subsidy::{
         config::FeeSubsidyConfiguration, cow_token::CowSubsidy, FeeSubsidies, FeeSubsidizing,
     },
-    gas_price::InstrumentedGasEstimator,
+    gas_price::{InstrumentedGasEstimator, SmoothedGasEstimator},
+    hot_reload::{reload_on_sighup, Overrides, Reloadable},
     http_solver::{DefaultHttpSolverApi, Objective, SolverConfig},
+    immediacy::ImmediacyEstimator,
     maintenance::ServiceMaintenance,
     metrics::{serve_metrics, DEFAULT_METRICS_PORT},
     network::network_name,
     oneinch_api::OneInchClientImpl,
-    order_quoting::{Forget, OrderQuoter, QuoteHandler, QuoteStoring},
+    order_quoting::{
+        FeeGuarantee, Forget, OrderQuoter, OverheadSharing, QuoteHandler, QuoteStoring,
+    },
     order_validation::{OrderValidator, SignatureConfiguration},
     paraswap_api::DefaultParaswapApi,
+    preflight::{self, verify_deployed_contract_constants, Preflight},
     price_estimation::{
         balancer_sor::BalancerSor,
         baseline::BaselinePriceEstimator,
         competition::{CompetitionPriceEstimator, RacingCompetitionPriceEstimator},
         http::HttpPriceEstimator,
         instrumented::InstrumentedPriceEstimator,
-        native::NativePriceEstimator,
+        native::{NativePriceEstimating, NativePriceEstimator},
         native_price_cache::CachingNativePriceEstimator,
+        native_price_coalescing::CoalescingNativePriceEstimator,
         oneinch::OneInchPriceEstimator,
         paraswap::ParaswapPriceEstimator,
         sanitized::SanitizedPriceEstimator,
         zeroex::ZeroExPriceEstimator,
         PriceEstimating, PriceEstimatorType,
     },
+    protocol_fee::ProtocolFee,
     rate_limiter::RateLimiter,
     recent_block_cache::CacheConfig,
+    request_sharing::CrossProcessSharing,
     signature_validator::Web3SignatureValidator,
     sources::balancer_v2::BalancerFactoryKind,
     sources::{
@@ -57,20 +82,64 @@ use shared::{
         uniswap_v3::pool_fetching::UniswapV3PoolFetcher,
         BaselineSource, PoolAggregator,
     },
-    token_info::{CachedTokenInfoFetcher, TokenInfoFetcher},
+    threat_intel::ThreatIntel,
+    token_info::{CachedTokenInfoFetcher, TokenInfoFetcher, TokenListInfoFetcher},
+    token_list::TokenLists,
+    trace_many::create_tracer,
+    wrapped_native::WrappedNativeConfig,
     zeroex_api::DefaultZeroExApi,
 };
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 use tokio::task;
 
+/// Checks the node, the contracts, the database and the configured external APIs instead of
+/// starting the order book.
+async fn run_preflight(args: &Arguments) -> ! {
+    let client = shared::http_client(args.shared.http_timeout);
+    let web3 = shared::web3(&client, &args.shared.node_url, "base");
+    let mut checks = Preflight::default();
+    checks.check_chain(&web3).await;
+    if let StorageBackend::Postgres = args.storage_backend {
+        checks
+            .check(
+                "database",
+                preflight::database(async {
+                    Postgres::new(args.db_url.as_str())?.missing_tables().await
+                }),
+            )
+            .await;
+    }
+    let apis = [
+        ("tracing node", &args.tracing_node_url),
+        ("quasimodo solver", &args.quasimodo_solver_url),
+        ("yearn solver", &args.yearn_solver_url),
+        ("balancer sor", &args.balancer_sor_url),
+    ];
+    for (name, url) in apis {
+        if let Some(url) = url {
+            checks
+                .check(name, preflight::url_reachable(&client, url))
+                .await;
+        }
+    }
+    checks.exit()
+}
+
 #[tokio::main]
 async fn main() {
-    let args = orderbook::arguments::Arguments::parse();
+    let args = Arguments::parse();
     shared::tracing::initialize(
         args.shared.log_filter.as_str(),
         args.shared.log_stderr_threshold,
     );
     tracing::info!("running order book with validated arguments:\n{}", args);
+    if args.shared.preflight {
+        run_preflight(&args).await;
+    }
 
     global_metrics::setup_metrics_registry(Some("gp_v2_api".into()), None);
 
@@ -106,9 +175,9 @@ async fn main() {
     let native_token_price_estimation_amount = args
         .amount_to_estimate_prices_with
         .or_else(|| {
-            shared::price_estimation::native::default_amount_to_estimate_native_prices_with(
-                &network,
-            )
+            WrappedNativeConfig::for_chain(chain_id)
+                .ok()
+                .map(|config| config.price_estimation_amount)
         })
         .expect("No amount to estimate prices with set.");
 
@@ -124,16 +193,92 @@ async fn main() {
     verify_deployed_contract_constants(&settlement_contract, chain_id)
         .await
         .expect("Deployed contract constants don't match the ones in this binary");
-    let domain_separator = DomainSeparator::new(chain_id, settlement_contract.address());
-    let postgres = Postgres::new(args.db_url.as_str()).expect("failed to create database");
-    let database = Arc::new(postgres.clone());
+    let domain = DomainParameters {
+        chain_id,
+        verifying_contract: settlement_contract.address(),
+    };
+    // Orders signed for other deployments of the settlement contract get a specific error.
+    let known_domains = GPv2Settlement::raw_contract()
+        .networks
+        .iter()
+        .filter_map(|(chain_id, network)| {
+            Some(DomainParameters {
+                chain_id: chain_id.parse().ok()?,
+                verifying_contract: network.address,
+            })
+        })
+        .filter(|known| *known != domain)
+        .collect();
+    let competition_archive = args.competition_archive_endpoint.clone().map(|endpoint| {
+        Arc::new(S3Storage::new(
+            client.clone(),
+            S3Config {
+                endpoint,
+                bucket: args.competition_archive_bucket.clone(),
+                region: args.competition_archive_region.clone(),
+                access_key_id: args.competition_archive_access_key_id.clone(),
+                secret_access_key: args.competition_archive_secret_access_key.clone(),
+            },
+        ))
+    });
+    let postgres = match args.storage_backend {
+        StorageBackend::Postgres => {
+            let postgres = Postgres::with_slow_query_threshold(
+                args.db_url.as_str(),
+                args.db_slow_query_threshold,
+            )
+            .expect("failed to create database");
+            Some(match &competition_archive {
+                Some(archive) => postgres.with_competition_archive(archive.clone()),
+                None => postgres,
+            })
+        }
+        StorageBackend::InMemory => None,
+    };
+    if let Some(postgres) = &postgres {
+        let applied = postgres
+            .ensure_schema(args.apply_database_migrations)
+            .await
+            .expect("database schema does not match this binary");
+        if !applied.is_empty() {
+            tracing::info!(?applied, "applied database migrations");
+        }
+    }
+    let database_health = Arc::new(DatabaseHealth::default());
+    let storage = match &postgres {
+        Some(postgres) => {
+            tokio::task::spawn(orderbook::database::pool_metrics(postgres.clone()));
+            tokio::task::spawn(health::probe_forever(
+                args.db_url.to_string(),
+                database_health.clone(),
+                args.db_health_probe_interval,
+            ));
+            let mut storage = Storage::new(Arc::new(postgres.clone()));
+            storage.quotes = Arc::new(DegradableQuoteStorage::new(
+                storage.quotes,
+                database_health.clone(),
+            ));
+            storage
+        }
+        None => Storage::new(Arc::new(InMemory::default())),
+    };
 
-    let balance_fetcher = Arc::new(Web3BalanceFetcher::new(
+    let balance_fetcher: Arc<dyn BalanceFetching> = Arc::new(Web3BalanceFetcher::new(
         web3.clone(),
         vault.clone(),
         vault_relayer,
         settlement_contract.address(),
     ));
+    let balance_fetcher: Arc<dyn BalanceFetching> = if args.balance_proof_tokens.is_empty() {
+        balance_fetcher
+    } else {
+        Arc::new(StorageProofBalanceFetcher::new(
+            web3.clone(),
+            balance_fetcher,
+            args.balance_proof_tokens.clone(),
+            vault_relayer,
+        ))
+    };
 
     let gas_price_estimator = Arc::new(InstrumentedGasEstimator::new(
         shared::gas_price_estimation::create_priority_estimator(
@@ -165,7 +310,6 @@ async fn main() {
     let mut allowed_tokens = args.allowed_tokens.clone();
     allowed_tokens.extend(base_tokens.tokens().iter().copied());
     allowed_tokens.push(BUY_ETH_ADDRESS);
-    let unsupported_tokens = args.unsupported_tokens.clone();
 
     let uniswapv3_factory = match IUniswapV3Factory::deployed(&web3).await {
         Err(DeployError::NotFound(_)) => None,
@@ -185,26 +329,53 @@ async fn main() {
     .await
     .expect("failed to initialize token owner finders");
 
-    let trace_call_detector = args.tracing_node_url.as_ref().map(|tracing_node_url| {
-        Box::new(CachingDetector::new(
+    let token_lists = Arc::new(TokenLists::from_arguments(
+        client.clone(),
+        chain_id,
+        &args.token_list,
+    ));
+    let tracing_node = args
+        .tracing_node_url
+        .as_ref()
+        .map(|tracing_node_url| shared::web3(&client, tracing_node_url, "trace"));
+    let tracer = create_tracer(args.token_detection_backend, tracing_node, &web3);
+    let trace_call_detector = tracer.map(|tracer| {
+        Arc::new(CachingDetector::new(
             Box::new(TraceCallDetector {
-                web3: shared::web3(&client, tracing_node_url, "trace"),
+                web3: web3.clone(),
+                tracer,
                 finder,
                 settlement_contract: settlement_contract.address(),
             }),
             args.token_quality_cache_expiry,
-        ))
+        )) as Arc<dyn BadTokenDetecting>
     });
-    let bad_token_detector = Arc::new(
-        ListBasedDetector::new(
-            allowed_tokens,
+    let listed_tokens = token_lists.clone();
+    let list_based_detector = move |unsupported_tokens: Vec<H160>| -> anyhow::Result<_> {
+        anyhow::ensure!(
+            unsupported_tokens
+                .iter()
+                .all(|token| !allowed_tokens.contains(token)),
+            "token is allowed and unsupported"
+        );
+        Ok(ListBasedDetector::new(
+            allowed_tokens.clone(),
             unsupported_tokens,
             trace_call_detector
-                .map(|detector| UnknownTokenStrategy::Forward(detector))
+                .clone()
+                .map(|detector| {
+                    UnknownTokenStrategy::Forward(Box::new(TokenListDetector::new(
+                        listed_tokens.clone(),
+                        Box::new(detector),
+                    )))
+                })
                 .unwrap_or(UnknownTokenStrategy::Allow),
-        )
-        .instrumented(),
+        ))
+    };
+    let token_list = Reloadable::new(
+        list_based_detector(args.unsupported_tokens.clone()).expect("invalid unsupported tokens"),
     );
+    let bad_token_detector = Arc::new(token_list.clone().instrumented());
 
     let current_block_stream =
         current_block_stream(web3.clone(), args.shared.block_stream_poll_interval_seconds)
@@ -228,9 +399,12 @@ async fn main() {
         )
         .expect("failed to create pool cache"),
     );
-    let token_info_fetcher = Arc::new(CachedTokenInfoFetcher::new(Box::new(TokenInfoFetcher {
-        web3: web3.clone(),
-    })));
+    let token_info_fetcher = Arc::new(TokenListInfoFetcher::new(
+        token_lists.clone(),
+        Box::new(CachedTokenInfoFetcher::new(Box::new(TokenInfoFetcher {
+            web3: web3.clone(),
+        }))),
+    ));
     let balancer_pool_fetcher = if baseline_sources.contains(&BaselineSource::BalancerV2) {
         let factories = args
             .shared
@@ -411,7 +585,7 @@ async fn main() {
         args.fast_price_estimation_results_required,
     ))));
 
-    let native_price_estimator = Arc::new(CachingNativePriceEstimator::new(
+    let mut native_price_estimator: Box<dyn NativePriceEstimating> =
         Box::new(NativePriceEstimator::new(
             Arc::new(sanitized(Box::new(CompetitionPriceEstimator::new(
                 args.native_price_estimators
@@ -421,8 +595,25 @@ async fn main() {
             )))),
             native_token.address(),
             native_token_price_estimation_amount,
-        )),
+        ));
+    if let Some(redis_url) = &args.native_price_coalescing_redis_url {
+        let sharing = CrossProcessSharing::new(
+            redis_url.as_str(),
+            format!("native_price:{}", chain_id),
+            args.native_price_coalescing_lock_secs,
+            args.native_price_coalescing_max_age_secs,
+        )
+        .await
+        .expect("failed to connect to native price coalescing redis");
+        native_price_estimator = Box::new(CoalescingNativePriceEstimator::new(
+            native_price_estimator,
+            sharing,
+        ));
+    }
+    let native_price_estimator = Arc::new(CachingNativePriceEstimator::new(
+        native_price_estimator,
         args.native_price_cache_max_age_secs,
+        args.native_price_cache_hot_tokens.clone(),
     ));
     native_price_estimator.spawn_maintenance_task(
         Duration::from_secs(1),
@@ -447,13 +638,21 @@ async fn main() {
         CowSubsidy::new(token, vtoken, args.cow_fee_factors.unwrap_or_default())
     });
 
-    let fee_subsidy_config = Arc::new(FeeSubsidyConfiguration {
-        fee_discount: args.fee_discount,
-        min_discounted_fee: args.min_discounted_fee,
-        fee_factor: args.fee_factor,
-        liquidity_order_owners: args.liquidity_order_owners.iter().copied().collect(),
-        partner_additional_fee_factors: args.partner_additional_fee_factors.clone(),
-    }) as Arc<dyn FeeSubsidizing>;
+    let fee_subsidy_configuration = {
+        let (fee_discount, min_discounted_fee, fee_factor) =
+            (args.fee_discount, args.min_discounted_fee, args.fee_factor);
+        let liquidity_order_owners = args.liquidity_order_owners.clone();
+        let partner_additional_fee_factors = args.partner_additional_fee_factors.clone();
+        move |overrides: &Overrides| FeeSubsidyConfiguration {
+            fee_discount: overrides.fee_discount.unwrap_or(fee_discount),
+            min_discounted_fee: overrides.min_discounted_fee.unwrap_or(min_discounted_fee),
+            fee_factor: overrides.fee_factor.unwrap_or(fee_factor),
+            liquidity_order_owners: liquidity_order_owners.iter().copied().collect(),
+            partner_additional_fee_factors: partner_additional_fee_factors.clone(),
+        }
+    };
+    let fee_subsidy_reloadable = Reloadable::new(fee_subsidy_configuration(&Default::default()));
+    let fee_subsidy_config = Arc::new(fee_subsidy_reloadable.clone()) as Arc<dyn FeeSubsidizing>;
 
     let fee_subsidy = match cow_subsidy {
         Some(cow_subsidy) => Arc::new(FeeSubsidies(vec![
@@ -463,25 +662,68 @@ async fn main() {
         None => fee_subsidy_config,
     };
 
+    // Fees get quoted with a smoothed gas price while price estimators keep using the live one.
+    let fee_gas_price_estimator = Arc::new(SmoothedGasEstimator::new(
+        gas_price_estimator.clone(),
+        args.fee_gas_price_half_life,
+        args.fee_gas_price_max_deviation,
+    ));
+    // Batch sizes come from the settlements in the database.
+    let overhead_sharing = match &postgres {
+        Some(postgres) if args.fee_overhead_sharing_max_batch_size > 1. => {
+            Some(Arc::new(OverheadSharing::new(
+                chrono::Duration::from_std(args.fee_overhead_sharing_lookback).unwrap(),
+                args.fee_overhead_sharing_safety_margin,
+                args.fee_overhead_sharing_max_batch_size,
+                Arc::new(postgres.clone()),
+            )))
+        }
+        _ => None,
+    };
     let create_quoter = |price_estimator: Arc<dyn PriceEstimating>,
                          storage: Arc<dyn QuoteStoring>| {
-        Arc::new(OrderQuoter::new(
+        let quoter = OrderQuoter::new(
             price_estimator,
             native_price_estimator.clone(),
-            gas_price_estimator.clone(),
+            fee_gas_price_estimator.clone(),
             fee_subsidy.clone(),
             storage,
             chrono::Duration::from_std(args.eip1271_onchain_quote_validity_seconds).unwrap(),
             chrono::Duration::from_std(args.presign_onchain_quote_validity_seconds).unwrap(),
-        ))
+        );
+        match &overhead_sharing {
+            Some(sharing) => quoter.with_overhead_sharing(sharing.clone()),
+            None => quoter,
+        }
+    };
+    // Honoring guarantees needs to keep track of their cost in the database.
+    let fee_guarantee_window = match &postgres {
+        Some(_) if !args.fee_guarantee_window.is_zero() => {
+            Some(chrono::Duration::from_std(args.fee_guarantee_window).unwrap())
+        }
+        _ => None,
     };
-    let optimal_quoter = create_quoter(price_estimator.clone(), database.clone());
-    let fast_quoter = create_quoter(fast_price_estimator.clone(), Arc::new(Forget));
+    let mut optimal_quoter = create_quoter(price_estimator.clone(), storage.quotes.clone());
+    if let (Some(window), Some(postgres)) = (fee_guarantee_window, &postgres) {
+        optimal_quoter = optimal_quoter.with_fee_guarantee(FeeGuarantee {
+            window,
+            daily_budget: args.fee_guarantee_daily_budget,
+            storage: Arc::new(postgres.clone()),
+        });
+    }
+    let optimal_quoter = Arc::new(optimal_quoter);
+    let fast_quoter = Arc::new(create_quoter(
+        fast_price_estimator.clone(),
+        Arc::new(Forget),
+    ));
 
-    let order_validator = Arc::new(OrderValidator::new(
+    let threat_intel = Arc::new(ThreatIntel::new(client.clone(), &args.threat_intel));
+    let protocol_fee = ProtocolFee::from(&args.protocol_fee);
+    let banned_users = Reloadable::new(args.banned_users.iter().copied().collect::<HashSet<_>>());
+    let mut order_validator = OrderValidator::new(
         Box::new(web3.clone()),
         native_token.clone(),
-        args.banned_users.iter().copied().collect(),
+        banned_users.clone(),
         args.liquidity_order_owners.iter().copied().collect(),
         args.min_order_validity_period,
         args.max_order_validity_period,
@@ -493,15 +735,28 @@ async fn main() {
         optimal_quoter.clone(),
         balance_fetcher,
         signature_validator,
-    ));
-    let orderbook = Arc::new(Orderbook::new(
-        domain_separator,
-        settlement_contract.address(),
-        database.as_ref().clone(),
-        order_validator.clone(),
-        args.solvable_orders_max_update_age_blocks,
-        current_block_stream.clone(),
-    ));
+    )
+    .with_threat_intel(threat_intel.clone())
+    .with_protocol_fee(protocol_fee)
+    .with_known_domains(known_domains);
+    if vault.is_some() {
+        order_validator = order_validator.with_internal_balances();
+    }
+    let order_validator = Arc::new(order_validator);
+    let orderbook = Arc::new(
+        Orderbook::new(
+            domain,
+            settlement_contract.address(),
+            storage.orderbook.clone(),
+            order_validator.clone(),
+            args.solvable_orders_max_update_age_blocks,
+            current_block_stream.clone(),
+            args.app_data_owners.clone(),
+        )
+        .with_auction_interval(args.auction_interval)
+        .with_database_health(database_health)
+        .with_sealing_key(args.sealed_order_public_key),
+    );
     let mut service_maintainer = ServiceMaintenance {
         maintainers: vec![pool_fetcher],
     };
@@ -511,24 +766,106 @@ async fn main() {
     if let Some(uniswap_v3) = uniswap_v3_pool_fetcher {
         service_maintainer.maintainers.push(uniswap_v3);
     }
+    if let (Some(postgres), Some(_)) = (&postgres, &competition_archive) {
+        service_maintainer
+            .maintainers
+            .push(Arc::new(CompetitionArchiver::new(
+                postgres.clone(),
+                chrono::Duration::days(args.competition_archive_age_days.into()),
+            )));
+    }
     check_database_connection(orderbook.as_ref()).await;
-    let quotes =
-        Arc::new(QuoteHandler::new(order_validator, optimal_quoter).with_fast_quoter(fast_quoter));
+    let mut quotes = QuoteHandler::new(order_validator, optimal_quoter)
+        .with_fast_quoter(fast_quoter)
+        .with_threat_intel(threat_intel.clone())
+        .with_bad_token_detector(bad_token_detector.clone())
+        .with_protocol_fee(protocol_fee);
+    if let Some(immediacy_estimator) =
+        ImmediacyEstimator::new(native_price_estimator.clone(), &args.immediacy)
+    {
+        quotes = quotes.with_immediacy_estimator(Arc::new(immediacy_estimator));
+    }
+    if let Some(window) = fee_guarantee_window {
+        quotes = quotes.with_fee_guarantee_window(window);
+    }
+    let quotes = Arc::new(quotes);
+    let competition_results = Arc::new(CompetitionResults::default());
+    if let Some(postgres) = postgres {
+        task::spawn(competition_results.clone().publish_forever(postgres));
+    }
+    let buffers = Arc::new(BufferCache::new(
+        orderbook.clone(),
+        Arc::new(BufferRetriever::new(
+            web3.clone(),
+            settlement_contract.address(),
+        )),
+    ));
+    let trade_proofs = Arc::new(TradeProofs::new(web3.clone(), storage.trades.clone()));
     let (shutdown_sender, shutdown_receiver) = tokio::sync::oneshot::channel();
     let serve_api = serve_api(
-        database.clone(),
+        storage.trades,
         orderbook.clone(),
         quotes,
         args.bind_address,
         async {
             let _ = shutdown_receiver.await;
         },
-        database.clone(),
+        storage.solver_competitions,
         args.shared.solver_competition_auth,
+        args.analytics_auth,
+        competition_results,
+        buffers,
+        trade_proofs,
+        args.api_rate_limit.map(|limit| {
+            Arc::new(InboundRateLimiter::new(
+                limit,
+                args.api_rate_limit_window,
+                args.api_trusted_proxies.clone(),
+            ))
+        }),
     );
     let maintenance_task =
         task::spawn(service_maintainer.run_maintenance_on_new_block(current_block_stream));
 
+    if !(args.threat_intel.threat_intel_token_lists.is_empty()
+        && args.threat_intel.threat_intel_address_lists.is_empty())
+    {
+        task::spawn(threat_intel.update_forever(args.threat_intel.threat_intel_update_interval));
+    }
+    if !token_lists.is_empty() {
+        task::spawn(
+            token_lists
+                .clone()
+                .update_forever(args.token_list.token_list_update_interval),
+        );
+    }
+
+    if let Some(path) = args.reload_config_file.clone() {
+        let unsupported_tokens = args.unsupported_tokens.clone();
+        let banned_user_list = args.banned_users.clone();
+        let apply = move |overrides: Overrides| {
+            let tokens = overrides
+                .unsupported_tokens
+                .clone()
+                .unwrap_or_else(|| unsupported_tokens.clone());
+            match list_based_detector(tokens) {
+                Ok(detector) => token_list.set(detector),
+                Err(err) => tracing::error!(?err, "not reloading unsupported tokens"),
+            }
+            banned_users.set(
+                overrides
+                    .banned_users
+                    .as_ref()
+                    .unwrap_or(&banned_user_list)
+                    .iter()
+                    .copied()
+                    .collect(),
+            );
+            fee_subsidy_reloadable.set(fee_subsidy_configuration(&overrides));
+        };
+        task::spawn(reload_on_sighup(path, apply));
+    }
+
     let mut metrics_address = args.bind_address;
     metrics_address.set_port(DEFAULT_METRICS_PORT);
     tracing::info!(%metrics_address, "serving metrics");
@@ -0,0 +1,219 @@
+//! Storage for large blobs that don't need to be queried, like archived solver competitions.
+//!
+//! The S3 implementation talks to any S3 compatible service (AWS, MinIO, GCS interoperability
+//! mode, ...) with path style requests signed with AWS signature version 4.
+
+use anyhow::{anyhow, ensure, Result};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method, StatusCode, Url};
+use sha2::{Digest, Sha256};
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait::async_trait]
+pub trait ObjectStorage: Send + Sync {
+    /// Stores the object under the key, replacing an existing object with the same key.
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<()>;
+
+    /// Returns `None` if there is no object with the key.
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+}
+
+#[derive(Clone, Debug)]
+pub struct S3Config {
+    /// For example `https://s3.eu-central-1.amazonaws.com`.
+    pub endpoint: Url,
+    pub bucket: String,
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+}
+
+pub struct S3Storage {
+    client: Client,
+    config: S3Config,
+}
+
+impl S3Storage {
+    pub fn new(client: Client, config: S3Config) -> Self {
+        Self { client, config }
+    }
+
+    /// Keys may only contain characters that don't need to be percent encoded in paths.
+    fn object_url(&self, key: &str) -> Result<Url> {
+        ensure!(
+            key.bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"-_.~/".contains(&b)),
+            "unsupported object key {key}"
+        );
+        let url = self
+            .config
+            .endpoint
+            .join(&format!("{}/{}", self.config.bucket, key))?;
+        Ok(url)
+    }
+
+    async fn request(&self, method: Method, key: &str, body: Vec<u8>) -> Result<reqwest::Response> {
+        let url = self.object_url(key)?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(anyhow!("S3 endpoint without host")),
+        };
+        let payload_hash = hex::encode(Sha256::digest(&body));
+        let authorization = authorization(
+            &self.config,
+            Utc::now(),
+            &method,
+            url.path(),
+            &host,
+            &payload_hash,
+        );
+        let response = self
+            .client
+            .request(method, url)
+            .header("host", host)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", authorization.date)
+            .header("authorization", authorization.header)
+            .body(body)
+            .send()
+            .await?;
+        Ok(response)
+    }
+}
+
+#[async_trait::async_trait]
+impl ObjectStorage for S3Storage {
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<()> {
+        let response = self.request(Method::PUT, key, data).await?;
+        let status = response.status();
+        ensure!(
+            status.is_success(),
+            "storing object {key} failed with {status}: {}",
+            response.text().await.unwrap_or_default()
+        );
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let response = self.request(Method::GET, key, Vec::new()).await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(Some(response.bytes().await?.to_vec())),
+            status => Err(anyhow!(
+                "loading object {key} failed with {status}: {}",
+                response.text().await.unwrap_or_default()
+            )),
+        }
+    }
+}
+
+struct Authorization {
+    /// The request time in the format of the `x-amz-date` header.
+    date: String,
+    header: String,
+}
+
+/// Signs a request without query parameters with AWS signature version 4.
+fn authorization(
+    config: &S3Config,
+    time: DateTime<Utc>,
+    method: &Method,
+    path: &str,
+    host: &str,
+    payload_hash: &str,
+) -> Authorization {
+    const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+    let date = time.format("%Y%m%dT%H%M%SZ").to_string();
+    let day = time.format("%Y%m%d").to_string();
+    let scope = format!("{day}/{}/s3/aws4_request", config.region);
+    let canonical_request = format!(
+        "{method}\n{path}\n\nhost:{host}\nx-amz-content-sha256:{payload_hash}\nx-amz-date:{date}\n\n\
+         {SIGNED_HEADERS}\n{payload_hash}"
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{date}\n{scope}\n{}",
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    let key = signing_key(&config.secret_access_key, &day, &config.region, "s3");
+    let signature = hex::encode(hmac(&key, string_to_sign.as_bytes()));
+    Authorization {
+        header: format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={SIGNED_HEADERS}, \
+             Signature={signature}",
+            config.access_key_id
+        ),
+        date,
+    }
+}
+
+fn signing_key(secret: &str, day: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac(format!("AWS4{secret}").as_bytes(), day.as_bytes());
+    let key = hmac(&key, region.as_bytes());
+    let key = hmac(&key, service.as_bytes());
+    hmac(&key, b"aws4_request")
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn config() -> S3Config {
+        S3Config {
+            endpoint: "https://s3.example.com".parse().unwrap(),
+            bucket: "bucket".to_string(),
+            region: "us-east-1".to_string(),
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+        }
+    }
+
+    #[test]
+    fn derives_signing_key() {
+        // Example from the AWS documentation on deriving signing keys.
+        assert_eq!(
+            hex::encode(signing_key(
+                "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+                "20120215",
+                "us-east-1",
+                "iam"
+            )),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[test]
+    fn signs_requests() {
+        let authorization = authorization(
+            &config(),
+            Utc.ymd(2022, 10, 1).and_hms(12, 0, 0),
+            &Method::GET,
+            "/bucket/key.json",
+            "s3.example.com",
+            &hex::encode(Sha256::digest(b"")),
+        );
+        assert_eq!(authorization.date, "20221001T120000Z");
+        assert!(authorization.header.starts_with(
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20221001/us-east-1/s3/aws4_request, \
+             SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature="
+        ));
+    }
+
+    #[test]
+    fn object_urls_are_path_style() {
+        let storage = S3Storage::new(Client::new(), config());
+        assert_eq!(
+            storage.object_url("a/b.json").unwrap().as_str(),
+            "https://s3.example.com/bucket/a/b.json"
+        );
+        assert!(storage.object_url("a b").is_err());
+    }
+}
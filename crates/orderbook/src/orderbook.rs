@@ -1,19 +1,48 @@
-use crate::database::orders::{InsertionError, OrderStoring};
+use crate::database::{
+    health::{DatabaseDegraded, DatabaseHealth},
+    orders::{InsertionError, OrderStoring},
+    quote_requests::QuoteRequestAttribution,
+    solver_competition::CompetitionFilter,
+    OrderbookStoring,
+};
 use anyhow::{anyhow, Context, Result};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use ethcontract::H256;
+use lru::LruCache;
 use model::{
+    api_key::{hash_api_key, ApiKeyAuthorization, ApiKeyRevocation},
+    app_id::AppId,
     auction::AuctionWithId,
-    order::{Order, OrderCancellation, OrderCreation, OrderStatus, OrderUid},
-    DomainSeparator,
+    fee_policy::OrderFeePolicy,
+    fee_recommendation::FeeRecommendation,
+    integrator_analytics::IntegratorFunnel,
+    order::{
+        AuctionInclusionEstimate, InclusionDelayReason, Order, OrderCancellation, OrderCreation,
+        OrderStatus, OrderUid, OrderValidationRequest,
+    },
+    order_book_stats::OrderBookStats,
+    protocol_fees::ProtocolFeeAccrual,
+    quote::QuoteId,
+    sealed_order::{self, SealedOrder, SealedOrderCreation, SealedOrderId, SealingKey},
+    signature::{Signature, SigningScheme},
+    solver_competition::CompetitionSummary,
+    solver_rewards::SolverRewards,
+    solver_team::{SolverTeam, SolverTeamCompetition, SolverTeamConfig},
+    stuck_orders::{OrderDiagnosis, StuckOrders},
+    DomainParameters, DomainSeparator,
 };
-use primitive_types::H160;
+use primitive_types::{H160, U256};
+use secp256k1::PublicKey;
 use shared::{
     current_block::CurrentBlockStream,
     metrics::LivenessChecking,
-    order_validation::{OrderValidating, ValidationError},
+    order_validation::{CancellationValidationError, OrderValidating, ValidationError},
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
 };
-use std::sync::Arc;
 use thiserror::Error;
 
 #[derive(prometheus_metric_storage::MetricStorage, Clone, Debug)]
@@ -34,7 +63,7 @@ impl Metrics {
         let metrics = Self::instance(global_metrics::get_metric_storage_registry())
             .expect("unexpected error getting metrics instance");
 
-        let kind = match order.metadata.is_liquidity_order {
+        let kind = match order.metadata.class.is_liquidity_order() {
             true => "liquidity",
             false => "user",
         };
@@ -44,6 +73,15 @@ impl Metrics {
         };
         metrics.orders.with_label_values(&[kind, op]).inc();
     }
+
+    fn on_orders_cancelled_by_app_data(count: usize) {
+        let metrics = Self::instance(global_metrics::get_metric_storage_registry())
+            .expect("unexpected error getting metrics instance");
+        metrics
+            .orders
+            .with_label_values(&["user", "cancelled"])
+            .inc_by(count as u64);
+    }
 }
 
 #[derive(Debug, Error)]
@@ -54,6 +92,10 @@ pub enum AddOrderError {
     OrderValidation(ValidationError),
     #[error("database error: {0}")]
     Database(#[from] sqlx::Error),
+    #[error(transparent)]
+    DatabaseDegraded(#[from] DatabaseDegraded),
+    #[error(transparent)]
+    ApiKey(#[from] ApiKeyError),
 }
 
 impl From<InsertionError> for AddOrderError {
@@ -91,6 +133,65 @@ pub enum OrderCancellationError {
     #[error("on-chain orders cannot be cancelled with off-chain signature")]
     OnChainOrder,
     #[error(transparent)]
+    DatabaseDegraded(#[from] DatabaseDegraded),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum ApiKeyError {
+    #[error("invalid signature")]
+    InvalidSignature,
+    #[error("api key is unknown, expired or revoked")]
+    InvalidKey,
+    #[error("api key already exists")]
+    DuplicatedKey,
+    #[error("replaced api key does not exist or belongs to a different owner")]
+    ReplacedKeyNotFound,
+    #[error("api key is already expired")]
+    AlreadyExpired,
+    #[error("order owner does not match api key owner")]
+    WrongOwner,
+    #[error("orders placed with an api key must use the pre-sign signing scheme")]
+    UnsupportedSigningScheme,
+    #[error("api key owner is not the owner of the app data")]
+    NotAppDataOwner,
+    #[error(transparent)]
+    DatabaseDegraded(#[from] DatabaseDegraded),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl From<InsertionError> for ApiKeyError {
+    fn from(err: InsertionError) -> Self {
+        match err {
+            InsertionError::DuplicatedRecord => Self::DuplicatedKey,
+            InsertionError::DbError(err) => Self::Other(err.into()),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SolverTeamError {
+    #[error(transparent)]
+    ApiKey(#[from] ApiKeyError),
+    #[error("api key owner is not a registered solver team")]
+    NotSolverTeam,
+    #[error("callback url is not a valid http url")]
+    InvalidCallbackUrl,
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum SealedOrderError {
+    #[error("sealed orders are not enabled")]
+    Disabled,
+    #[error("malformed sealed order: {0}")]
+    Malformed(String),
+    #[error(transparent)]
+    DatabaseDegraded(#[from] DatabaseDegraded),
+    #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
 
@@ -117,46 +218,295 @@ impl From<InsertionError> for ReplaceOrderError {
 }
 
 pub struct Orderbook {
+    domain: DomainParameters,
     domain_separator: DomainSeparator,
     settlement_contract: H160,
-    database: crate::database::Postgres,
+    database: Arc<dyn OrderbookStoring>,
     order_validator: Arc<dyn OrderValidating>,
     solvable_orders_max_update_age_blocks: u64,
     current_block: CurrentBlockStream,
+    app_data_owners: HashMap<AppId, H160>,
+    auction_interval: Duration,
+    database_health: Arc<DatabaseHealth>,
+    /// Recently read orders, served while the database can't be reached.
+    cached_orders: Mutex<LruCache<OrderUid, Order>>,
+    cached_auction: Mutex<Option<AuctionWithId>>,
+    /// The key of the autopilot that sealed orders are encrypted to. Sealed orders are not accepted
+    /// if not set.
+    sealing_key: Option<PublicKey>,
 }
 
+/// How often autopilot creates a new auction unless configured otherwise.
+const DEFAULT_AUCTION_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How many recently read orders are kept to serve while the database can't be reached.
+const CACHED_ORDERS: usize = 10_000;
+
+/// How far back trades count towards fee recommendations.
+const FEE_RECOMMENDATION_PERIOD: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// The most trades a fee recommendation is based on.
+const FEE_RECOMMENDATION_MAX_SAMPLES: u64 = 1000;
+
 impl Orderbook {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
-        domain_separator: DomainSeparator,
+        domain: DomainParameters,
         settlement_contract: H160,
-        database: crate::database::Postgres,
+        database: Arc<dyn OrderbookStoring>,
         order_validator: Arc<dyn OrderValidating>,
         solvable_orders_max_update_age_blocks: u64,
         current_block: CurrentBlockStream,
+        app_data_owners: HashMap<AppId, H160>,
     ) -> Self {
         Self {
-            domain_separator,
+            domain,
+            domain_separator: domain.separator(),
             settlement_contract,
             database,
             order_validator,
             solvable_orders_max_update_age_blocks,
             current_block,
+            app_data_owners,
+            auction_interval: DEFAULT_AUCTION_INTERVAL,
+            database_health: Default::default(),
+            cached_orders: Mutex::new(LruCache::new(CACHED_ORDERS)),
+            cached_auction: Default::default(),
+            sealing_key: None,
         }
     }
 
+    /// How often autopilot creates a new auction. Used to estimate when orders enter auctions.
+    pub fn with_auction_interval(mut self, auction_interval: Duration) -> Self {
+        self.auction_interval = auction_interval;
+        self
+    }
+
+    /// The health of the database which gets updated by a probe. While the database is degraded
+    /// orders can't be placed or cancelled and reads fall back to cached data.
+    pub fn with_database_health(mut self, database_health: Arc<DatabaseHealth>) -> Self {
+        self.database_health = database_health;
+        self
+    }
+
+    /// Accepts sealed orders encrypted to the key which the autopilot reveals when it cuts an
+    /// auction.
+    pub fn with_sealing_key(mut self, sealing_key: Option<PublicKey>) -> Self {
+        self.sealing_key = sealing_key;
+        self
+    }
+
+    /// The EIP-712 domain orders have to be signed for.
+    pub fn domain(&self) -> DomainParameters {
+        self.domain
+    }
+
     pub async fn add_order(&self, payload: OrderCreation) -> Result<OrderUid, AddOrderError> {
+        self.database_health.ensure_writable()?;
         let (order, quote) = self
             .order_validator
             .validate_and_construct_order(payload, &self.domain_separator, self.settlement_contract)
             .await?;
 
-        self.database.insert_order(&order, quote).await?;
+        self.database
+            .insert_order(&order, quote)
+            .await
+            .map_err(|err| self.insertion_error(err))?;
         Metrics::on_order_operation(&order, OrderOperation::Created);
 
         Ok(order.metadata.uid)
     }
 
+    /// The key orders have to be sealed to. `None` if sealed orders are not accepted.
+    pub fn sealing_key(&self) -> Option<SealingKey> {
+        self.sealing_key.map(|key| SealingKey {
+            public_key: key.serialize().to_vec(),
+        })
+    }
+
+    /// Stores a sealed order until the autopilot reveals it when cutting an auction. Whether the
+    /// order it contains is valid is only known then.
+    pub async fn add_sealed_order(
+        &self,
+        order: SealedOrderCreation,
+    ) -> Result<SealedOrderId, SealedOrderError> {
+        if self.sealing_key.is_none() {
+            return Err(SealedOrderError::Disabled);
+        }
+        sealed_order::check_format(&order.sealed)
+            .map_err(|err| SealedOrderError::Malformed(err.to_string()))?;
+        self.database_health.ensure_writable()?;
+        let id = self
+            .database
+            .insert_sealed_order(&order.sealed, Utc::now())
+            .await?;
+        tracing::debug!(?id, "sealed order added");
+        Ok(id)
+    }
+
+    pub async fn get_sealed_order(&self, id: &SealedOrderId) -> Result<Option<SealedOrder>> {
+        self.database.sealed_order(id).await
+    }
+
+    /// Validates prospective orders like placing them would without placing them.
+    pub async fn validate_orders(
+        &self,
+        orders: &[OrderValidationRequest],
+    ) -> Vec<Result<(), ValidationError>> {
+        self.order_validator
+            .validate_prospective_orders(orders)
+            .await
+    }
+
+    /// Registers an API key authorized by the signer of `authorization`.
+    ///
+    /// Returns the owner of the key.
+    pub async fn register_api_key(
+        &self,
+        authorization: ApiKeyAuthorization,
+    ) -> Result<H160, ApiKeyError> {
+        let owner = authorization
+            .validate(&self.domain_separator)
+            .map_err(|_| ApiKeyError::InvalidSignature)?;
+        let now = Utc::now();
+        if (authorization.valid_to as i64) < now.timestamp() {
+            return Err(ApiKeyError::AlreadyExpired);
+        }
+
+        if !self
+            .database
+            .insert_api_key(
+                authorization.key_hash,
+                owner,
+                authorization.valid_to,
+                authorization.replaces,
+                now,
+            )
+            .await?
+        {
+            return Err(ApiKeyError::ReplacedKeyNotFound);
+        }
+
+        Ok(owner)
+    }
+
+    pub async fn revoke_api_key(&self, revocation: ApiKeyRevocation) -> Result<(), ApiKeyError> {
+        let owner = revocation
+            .validate(&self.domain_separator)
+            .map_err(|_| ApiKeyError::InvalidSignature)?;
+        if !self
+            .database
+            .revoke_api_key(revocation.key_hash, owner, Utc::now())
+            .await?
+        {
+            return Err(ApiKeyError::InvalidKey);
+        }
+        Ok(())
+    }
+
+    async fn api_key_owner(&self, api_key: &str) -> Result<H160, ApiKeyError> {
+        self.database
+            .active_api_key_owner(hash_api_key(api_key), Utc::now())
+            .await?
+            .ok_or(ApiKeyError::InvalidKey)
+    }
+
+    /// Adds an order on behalf of the owner of an API key.
+    ///
+    /// The order has to use the `PreSign` signing scheme so that the owner can batch the
+    /// pre-signatures of many orders into a single on-chain transaction. If `from` is not set it
+    /// defaults to the key owner.
+    pub async fn add_order_with_api_key(
+        &self,
+        api_key: &str,
+        mut payload: OrderCreation,
+    ) -> Result<OrderUid, AddOrderError> {
+        let owner = self.api_key_owner(api_key).await?;
+        if payload.signature != Signature::PreSign {
+            return Err(ApiKeyError::UnsupportedSigningScheme.into());
+        }
+        if *payload.from.get_or_insert(owner) != owner {
+            return Err(ApiKeyError::WrongOwner.into());
+        }
+        self.add_order(payload).await
+    }
+
+    /// Cancels a pre-signed order on behalf of the owner of an API key.
+    ///
+    /// Unlike other on-chain orders these can be cancelled off-chain because their placement was
+    /// authenticated by the key.
+    pub async fn cancel_order_with_api_key(
+        &self,
+        api_key: &str,
+        order_uid: &OrderUid,
+    ) -> Result<(), OrderCancellationError> {
+        self.database_health.ensure_writable()?;
+        let owner = self.api_key_owner(api_key).await.map_err(|err| match err {
+            ApiKeyError::Other(err) => OrderCancellationError::Other(err),
+            _ => OrderCancellationError::InvalidSignature,
+        })?;
+        let order = self
+            .database
+            .single_order(order_uid)
+            .await?
+            .ok_or(OrderCancellationError::OrderNotFound)?;
+
+        if order.metadata.owner != owner {
+            return Err(OrderCancellationError::WrongOwner);
+        }
+        if order.signature != Signature::PreSign {
+            return Err(OrderCancellationError::OnChainOrder);
+        }
+        match order.metadata.status {
+            OrderStatus::Fulfilled => return Err(OrderCancellationError::OrderFullyExecuted),
+            OrderStatus::Cancelled => return Err(OrderCancellationError::AlreadyCancelled),
+            OrderStatus::Expired => return Err(OrderCancellationError::OrderExpired),
+            _ => {}
+        }
+
+        self.database
+            .cancel_order(&order.metadata.uid, Utc::now())
+            .await
+            .map_err(|err| self.cancellation_error(err))?;
+        Metrics::on_order_operation(&order, OrderOperation::Cancelled);
+
+        Ok(())
+    }
+
+    /// Cancels all open orders with the app data on behalf of the owner of the API key, who has to
+    /// be the configured owner of the app data. This allows partners to stop trading through their
+    /// frontend at once, for example when it quoted bad prices.
+    ///
+    /// Returns the uids of the cancelled orders.
+    pub async fn cancel_orders_by_app_data(
+        &self,
+        api_key: &str,
+        app_data: &AppId,
+    ) -> Result<Vec<OrderUid>, ApiKeyError> {
+        self.database_health.ensure_writable()?;
+        let owner = self.api_key_owner(api_key).await?;
+        if self.app_data_owners.get(app_data) != Some(&owner) {
+            return Err(ApiKeyError::NotAppDataOwner);
+        }
+
+        let uids = self
+            .database
+            .cancel_orders_by_app_data(app_data, owner, Utc::now())
+            .await
+            .map_err(|err| match self.database_health.observe_any(&err) {
+                true => DatabaseDegraded(self.database_health.state()).into(),
+                false => ApiKeyError::Other(err),
+            })?;
+        Metrics::on_orders_cancelled_by_app_data(uids.len());
+        tracing::info!(
+            ?app_data,
+            ?owner,
+            orders = uids.len(),
+            "cancelled orders by app data"
+        );
+        Ok(uids)
+    }
+
     /// Finds an order for cancellation.
     ///
     /// Returns an error if the order cannot be found or cannot be cancelled.
@@ -172,7 +522,8 @@ impl Orderbook {
 
         match order.metadata.status {
             OrderStatus::PresignaturePending => return Err(OrderCancellationError::OnChainOrder),
-            OrderStatus::Open if !order.signature.scheme().is_ecdsa_scheme() => {
+            // Owners of EIP-1271 orders sign cancellations with their contract too.
+            OrderStatus::Open if order.signature.scheme() == SigningScheme::PreSign => {
                 return Err(OrderCancellationError::OnChainOrder);
             }
             OrderStatus::Fulfilled => return Err(OrderCancellationError::OrderFullyExecuted),
@@ -188,23 +539,29 @@ impl Orderbook {
         &self,
         cancellation: OrderCancellation,
     ) -> Result<(), OrderCancellationError> {
+        self.database_health.ensure_writable()?;
         let order = self
             .find_order_for_cancellation(&cancellation.order_uid)
             .await?;
 
-        // Verify the cancellation signer is the same as the order signer.
-        let signer = cancellation
-            .validate(&self.domain_separator)
-            .map_err(|_| OrderCancellationError::InvalidSignature)?;
-        if signer != order.metadata.owner {
-            return Err(OrderCancellationError::WrongOwner);
-        };
+        // Verify the cancellation was signed by the owner of the order.
+        self.order_validator
+            .validate_cancellation(&cancellation, order.metadata.owner, &self.domain_separator)
+            .await
+            .map_err(|err| match err {
+                CancellationValidationError::InvalidSignature => {
+                    OrderCancellationError::InvalidSignature
+                }
+                CancellationValidationError::WrongOwner => OrderCancellationError::WrongOwner,
+                CancellationValidationError::Other(err) => OrderCancellationError::Other(err),
+            })?;
 
         // order is already known to exist in DB at this point, and signer is
         // known to be correct!
         self.database
             .cancel_order(&order.metadata.uid, Utc::now())
-            .await?;
+            .await
+            .map_err(|err| self.cancellation_error(err))?;
         Metrics::on_order_operation(&order, OrderOperation::Cancelled);
 
         Ok(())
@@ -223,6 +580,9 @@ impl Orderbook {
             .scheme()
             .try_to_ecdsa_scheme()
             .ok_or(ReplaceOrderError::InvalidReplacement)?;
+        self.database_health
+            .ensure_writable()
+            .map_err(AddOrderError::from)?;
 
         let old_order = self.find_order_for_cancellation(&old_order).await?;
         let (new_order, new_quote) = self
@@ -247,43 +607,291 @@ impl Orderbook {
             return Err(ReplaceOrderError::InvalidReplacement);
         }
 
-        self.database
+        let replaced = self
+            .database
             .replace_order(&old_order.metadata.uid, &new_order, new_quote)
-            .await?;
+            .await
+            .map_err(|err| self.insertion_error(err))?;
+        if !replaced {
+            // The old order got cancelled or filled since it was checked.
+            let err = match self
+                .find_order_for_cancellation(&old_order.metadata.uid)
+                .await
+            {
+                Err(err) => err,
+                Ok(_) => OrderCancellationError::AlreadyCancelled,
+            };
+            return Err(err.into());
+        }
         Metrics::on_order_operation(&old_order, OrderOperation::Cancelled);
         Metrics::on_order_operation(&new_order, OrderOperation::Created);
 
         Ok(new_order.metadata.uid)
     }
 
+    /// Classifies a failed order insertion, marking the database as degraded if the error shows
+    /// that it doesn't accept writes.
+    fn insertion_error(&self, err: InsertionError) -> AddOrderError {
+        match err {
+            InsertionError::DbError(err) if self.database_health.observe(&err) => {
+                DatabaseDegraded(self.database_health.state()).into()
+            }
+            err => err.into(),
+        }
+    }
+
+    /// Like `insertion_error` for failed cancellations.
+    fn cancellation_error(&self, err: anyhow::Error) -> OrderCancellationError {
+        match self.database_health.observe_any(&err) {
+            true => DatabaseDegraded(self.database_health.state()).into(),
+            false => err.into(),
+        }
+    }
+
+    /// Returns the order, falling back to the last read of it if the database can't be reached.
     pub async fn get_order(&self, uid: &OrderUid) -> Result<Option<Order>> {
-        self.database.single_order(uid).await
+        match self.database.single_order(uid).await {
+            Ok(order) => {
+                if let Some(order) = &order {
+                    self.cached_orders.lock().unwrap().put(*uid, order.clone());
+                }
+                Ok(order)
+            }
+            Err(err) if self.database_health.observe_any(&err) => {
+                match self.cached_orders.lock().unwrap().get(uid) {
+                    Some(order) => {
+                        tracing::warn!(?err, %uid, "serving cached order");
+                        Ok(Some(order.clone()))
+                    }
+                    None => Err(err),
+                }
+            }
+            Err(err) => Err(err),
+        }
     }
 
     pub async fn get_orders_for_tx(&self, hash: &H256) -> Result<Vec<Order>> {
         self.database.orders_for_tx(hash).await
     }
 
+    /// Returns the current auction, falling back to the last one read if the database can't be
+    /// reached.
     pub async fn get_auction(&self) -> Result<Option<AuctionWithId>> {
-        let auction = match self.database.most_recent_auction().await? {
+        let auction = match self.database.most_recent_auction().await {
+            Ok(auction) => {
+                *self.cached_auction.lock().unwrap() = auction.clone();
+                auction
+            }
+            Err(err) if self.database_health.observe_any(&err) => {
+                tracing::warn!(?err, "serving cached auction");
+                self.cached_auction.lock().unwrap().clone()
+            }
+            Err(err) => return Err(err),
+        };
+        let auction = match auction {
             Some(auction) => auction,
             None => {
                 tracing::warn!("there is no current auction");
                 return Ok(None);
             }
         };
+        if self.auction_is_outdated(auction.auction.block)? {
+            tracing::warn!("current auction is out of date");
+            return Ok(None);
+        }
+        Ok(Some(auction))
+    }
+
+    fn auction_is_outdated(&self, auction_block: u64) -> Result<bool> {
         let current_block = self
             .current_block
             .borrow()
             .number
             .ok_or_else(|| anyhow!("no block number"))?
             .as_u64();
-        let age_in_blocks = current_block.saturating_sub(auction.auction.block);
-        if age_in_blocks > self.solvable_orders_max_update_age_blocks {
-            tracing::warn!("current auction is out of date");
-            return Ok(None);
+        let age_in_blocks = current_block.saturating_sub(auction_block);
+        Ok(age_in_blocks > self.solvable_orders_max_update_age_blocks)
+    }
+
+    /// Estimates when a just placed order with the signing scheme enters an auction.
+    pub async fn estimate_auction_inclusion(
+        &self,
+        signing_scheme: SigningScheme,
+    ) -> Result<AuctionInclusionEstimate> {
+        let last_auction = match self.database.most_recent_auction_creation().await? {
+            Some((block, created)) if !self.auction_is_outdated(block)? => Some(created),
+            _ => None,
+        };
+        Ok(auction_inclusion(
+            signing_scheme,
+            last_auction,
+            self.auction_interval,
+            Utc::now(),
+        ))
+    }
+
+    pub async fn get_stats(&self) -> Result<OrderBookStats> {
+        self.database.order_book_stats().await
+    }
+
+    pub async fn get_solver_rewards(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<SolverRewards>> {
+        self.database.solver_rewards(start, end).await
+    }
+
+    /// Logs who requested a quote. Failures only get logged so that they never affect quoting.
+    pub async fn record_quote_request(
+        &self,
+        app_data: AppId,
+        user_agent: Option<String>,
+        quote_id: Option<QuoteId>,
+    ) {
+        let request = QuoteRequestAttribution {
+            time: Utc::now(),
+            app_data,
+            user_agent,
+            quote_id,
+        };
+        if let Err(err) = self.database.insert_quote_request(&request).await {
+            tracing::warn!(?err, "failed to record quote request");
         }
-        Ok(Some(auction))
+    }
+
+    pub async fn get_integrator_analytics(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<IntegratorFunnel>> {
+        self.database.integrator_funnels(start, end).await
+    }
+
+    /// Recommends the fee and slippage to expect from recent trades of the pair that sold between
+    /// half and twice the amount.
+    pub async fn get_fee_recommendation(
+        &self,
+        sell_token: H160,
+        buy_token: H160,
+        sell_amount: U256,
+    ) -> Result<FeeRecommendation> {
+        let sell_amounts = sell_amount / 2..=sell_amount.saturating_mul(2.into());
+        let since = Utc::now() - chrono::Duration::from_std(FEE_RECOMMENDATION_PERIOD)?;
+        self.database
+            .fee_recommendation(
+                sell_token,
+                buy_token,
+                sell_amounts,
+                since,
+                FEE_RECOMMENDATION_MAX_SAMPLES,
+            )
+            .await
+    }
+
+    /// Returns the stored solver competitions of past auctions matching the filter, newest first.
+    pub async fn search_auctions(
+        &self,
+        filter: &CompetitionFilter,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<CompetitionSummary>> {
+        self.database
+            .search_solver_competitions(filter, offset, limit)
+            .await
+    }
+
+    pub async fn get_protocol_fees(&self) -> Result<Vec<ProtocolFeeAccrual>> {
+        self.database.protocol_fees().await
+    }
+
+    pub async fn get_order_fee_policy(&self, uid: &OrderUid) -> Result<Option<OrderFeePolicy>> {
+        self.database.order_fee_policy(uid).await
+    }
+
+    pub async fn get_order_diagnosis(&self, uid: &OrderUid) -> Result<Option<OrderDiagnosis>> {
+        self.database.order_diagnosis(uid).await
+    }
+
+    pub async fn get_stuck_orders(&self) -> Result<Vec<StuckOrders>> {
+        self.database.stuck_orders().await
+    }
+
+    async fn solver_team(&self, api_key: &str) -> Result<(H160, SolverTeam), SolverTeamError> {
+        let owner = self.api_key_owner(api_key).await?;
+        let team = self
+            .database
+            .solver_team(owner)
+            .await?
+            .ok_or(SolverTeamError::NotSolverTeam)?;
+        Ok((owner, team))
+    }
+
+    /// Returns the solver team of the owner of the API key.
+    pub async fn get_solver_team(&self, api_key: &str) -> Result<SolverTeam, SolverTeamError> {
+        Ok(self.solver_team(api_key).await?.1)
+    }
+
+    /// Replaces the driver configuration of the solver team of the owner of the API key.
+    pub async fn update_solver_team_config(
+        &self,
+        api_key: &str,
+        config: SolverTeamConfig,
+    ) -> Result<SolverTeam, SolverTeamError> {
+        if let Some(url) = &config.callback_url {
+            match reqwest::Url::parse(url) {
+                Ok(url) if matches!(url.scheme(), "http" | "https") => (),
+                _ => return Err(SolverTeamError::InvalidCallbackUrl),
+            }
+        }
+        let (owner, team) = self.solver_team(api_key).await?;
+        if !self
+            .database
+            .update_solver_team_config(owner, &config)
+            .await?
+        {
+            return Err(SolverTeamError::NotSolverTeam);
+        }
+        tracing::info!(?owner, ?config, "updated solver team config");
+        Ok(SolverTeam { config, ..team })
+    }
+
+    /// Returns the most recent solver competitions the solver of the team took part in, including
+    /// its solutions that failed to simulate and whether its winning settlements reverted.
+    pub async fn get_solver_team_competitions(
+        &self,
+        api_key: &str,
+        limit: u64,
+    ) -> Result<Vec<SolverTeamCompetition>, SolverTeamError> {
+        let (_, team) = self.solver_team(api_key).await?;
+        let competitions = self
+            .database
+            .solver_competitions_of_solver(&team.name, limit)
+            .await?;
+        Ok(competitions
+            .iter()
+            .filter_map(|competition| SolverTeamCompetition::new(competition, &team.name))
+            .collect())
+    }
+
+    /// Returns the rewards the solver of the team accrued in `[start, end)`.
+    pub async fn get_solver_team_rewards(
+        &self,
+        api_key: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<SolverRewards, SolverTeamError> {
+        let (_, team) = self.solver_team(api_key).await?;
+        let rewards = self.database.solver_rewards(start, end).await?;
+        Ok(rewards
+            .into_iter()
+            .find(|rewards| rewards.solver == team.solver)
+            .unwrap_or(SolverRewards {
+                solver: team.solver,
+                settlements: 0,
+                user_orders: 0,
+                reward: Default::default(),
+            }))
     }
 
     pub async fn get_user_orders(
@@ -306,10 +914,45 @@ impl LivenessChecking for Orderbook {
     }
 }
 
+/// Estimates the auction inclusion of an order placed at `now` given the creation time of the
+/// most recent auction if it is up to date.
+fn auction_inclusion(
+    signing_scheme: SigningScheme,
+    last_auction: Option<DateTime<Utc>>,
+    auction_interval: Duration,
+    now: DateTime<Utc>,
+) -> AuctionInclusionEstimate {
+    let last_auction = match last_auction {
+        Some(last_auction) => last_auction,
+        None => {
+            return AuctionInclusionEstimate {
+                next_auction: None,
+                in_next_auction: false,
+                delay_reason: Some(InclusionDelayReason::NoRecentAuction),
+            }
+        }
+    };
+    // Autopilot can take longer than the interval to create an auction in which case the next
+    // one is overdue.
+    let next_auction = (last_auction
+        + chrono::Duration::from_std(auction_interval)
+            .unwrap_or_else(|_| chrono::Duration::zero()))
+    .max(now);
+    let delay_reason = signing_scheme
+        .is_signed_by_transaction()
+        .then(|| InclusionDelayReason::PresignaturePending);
+    AuctionInclusionEstimate {
+        next_auction: Some(next_auction),
+        in_next_auction: delay_reason.is_none(),
+        delay_reason,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::database::orders::MockOrderStoring;
+    use chrono::TimeZone;
     use ethcontract::H160;
     use mockall::predicate::eq;
     use model::{
@@ -319,6 +962,48 @@ mod tests {
     };
     use shared::order_validation::MockOrderValidating;
 
+    #[test]
+    fn estimates_auction_inclusion() {
+        let now = Utc.timestamp(1_000, 0);
+        let interval = Duration::from_secs(10);
+
+        let estimate = auction_inclusion(
+            SigningScheme::Eip712,
+            Some(Utc.timestamp(995, 0)),
+            interval,
+            now,
+        );
+        assert_eq!(
+            estimate,
+            AuctionInclusionEstimate {
+                next_auction: Some(Utc.timestamp(1_005, 0)),
+                in_next_auction: true,
+                delay_reason: None,
+            }
+        );
+
+        // Overdue auctions are expected right away.
+        let estimate = auction_inclusion(
+            SigningScheme::PreSign,
+            Some(Utc.timestamp(980, 0)),
+            interval,
+            now,
+        );
+        assert_eq!(estimate.next_auction, Some(now));
+        assert!(!estimate.in_next_auction);
+        assert_eq!(
+            estimate.delay_reason,
+            Some(InclusionDelayReason::PresignaturePending)
+        );
+
+        let estimate = auction_inclusion(SigningScheme::Eip712, None, interval, now);
+        assert_eq!(estimate.next_auction, None);
+        assert_eq!(
+            estimate.delay_reason,
+            Some(InclusionDelayReason::NoRecentAuction)
+        );
+    }
+
     #[tokio::test]
     #[ignore]
     async fn postgres_replace_order_verifies_signer_and_app_data() {
@@ -348,7 +1033,9 @@ mod tests {
                 let old_order = old_order.clone();
                 move |_| Ok(Some(old_order.clone()))
             });
-        database.expect_replace_order().returning(|_, _, _| Ok(()));
+        database
+            .expect_replace_order()
+            .returning(|_, _, _| Ok(true));
 
         let mut order_validator = MockOrderValidating::new();
         order_validator
@@ -372,12 +1059,19 @@ mod tests {
         database::clear_DANGER(&database.pool).await.unwrap();
         database.insert_order(&old_order, None).await.unwrap();
         let orderbook = Orderbook {
-            database,
+            database: Arc::new(database),
             order_validator: Arc::new(order_validator),
+            domain: Default::default(),
             domain_separator: Default::default(),
             settlement_contract: H160([0xba; 20]),
             solvable_orders_max_update_age_blocks: Default::default(),
             current_block: shared::current_block::mock_single_block(Default::default()),
+            app_data_owners: Default::default(),
+            auction_interval: DEFAULT_AUCTION_INTERVAL,
+            database_health: Default::default(),
+            cached_orders: Mutex::new(LruCache::new(CACHED_ORDERS)),
+            cached_auction: Default::default(),
+            sealing_key: None,
         };
 
         // App data does not encode cancellation.
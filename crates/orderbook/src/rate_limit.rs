@@ -0,0 +1,327 @@
+//! Limits how many API requests each client can make per time window.
+//!
+//! Clients are identified by their IP address. The `X-Forwarded-For` header is only used for
+//! requests from configured trusted proxies because any other client could forge it to get a fresh
+//! quota with every request. Every response carries the `RateLimit-Limit`,
+//! `RateLimit-Remaining` and `RateLimit-Reset` headers so that clients can throttle themselves
+//! instead of retrying requests that got rejected with `429 Too Many Requests`.
+
+use model::rate_limit::RateLimitQuota;
+use shared::api::{cors, error};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::Instant;
+use warp::{
+    http::HeaderMap,
+    hyper::StatusCode,
+    reply::{with_status, Response},
+    Filter, Rejection, Reply,
+};
+
+pub struct InboundRateLimiter {
+    limit: u64,
+    window: Duration,
+    trusted_proxies: Vec<IpAddr>,
+    state: Mutex<State>,
+}
+
+struct State {
+    clients: HashMap<String, Window>,
+    /// When clients whose window ended were last forgotten.
+    last_prune: Instant,
+}
+
+struct Window {
+    start: Instant,
+    requests: u64,
+}
+
+impl InboundRateLimiter {
+    /// Allows every client `limit` requests per `window`. Requests from `trusted_proxies` are
+    /// attributed to the client they were forwarded for.
+    pub fn new(limit: u64, window: Duration, trusted_proxies: Vec<IpAddr>) -> Self {
+        Self {
+            limit,
+            window,
+            trusted_proxies,
+            state: Mutex::new(State {
+                clients: Default::default(),
+                last_prune: Instant::now(),
+            }),
+        }
+    }
+
+    /// Counts a request of the client. Returns the quota left after the request if it is within
+    /// the limit and the exhausted quota otherwise.
+    pub fn consume(&self, client: &str, now: Instant) -> Result<RateLimitQuota, RateLimitQuota> {
+        let mut state = self.state.lock().unwrap();
+        // Pruning once per window bounds the map by the clients of about two windows without
+        // scanning it on every request.
+        if now >= state.last_prune + self.window {
+            state
+                .clients
+                .retain(|_, window| now < window.start + self.window);
+            state.last_prune = now;
+        }
+        let window = state.clients.entry(client.to_string()).or_insert(Window {
+            start: now,
+            requests: 0,
+        });
+        if now >= window.start + self.window {
+            *window = Window {
+                start: now,
+                requests: 0,
+            };
+        }
+        if window.requests >= self.limit {
+            return Err(self.quota_of(window, now));
+        }
+        window.requests += 1;
+        Ok(self.quota_of(window, now))
+    }
+
+    /// The quota of the client without counting a request.
+    pub fn quota(&self, client: &str, now: Instant) -> RateLimitQuota {
+        let state = self.state.lock().unwrap();
+        match state.clients.get(client) {
+            Some(window) if now < window.start + self.window => self.quota_of(window, now),
+            _ => RateLimitQuota {
+                limit: self.limit,
+                remaining: self.limit,
+                reset: self.window.as_secs(),
+            },
+        }
+    }
+
+    fn quota_of(&self, window: &Window, now: Instant) -> RateLimitQuota {
+        let reset = (window.start + self.window).saturating_duration_since(now);
+        RateLimitQuota {
+            limit: self.limit,
+            remaining: self.limit.saturating_sub(window.requests),
+            // Round up so that clients waiting for the reset don't retry too early.
+            reset: reset.as_secs() + u64::from(reset.subsec_nanos() > 0),
+        }
+    }
+
+    /// Identifies the client of a request by its address. Each trusted proxy appends the address
+    /// it got the request from to the `X-Forwarded-For` header, so the header gets followed from
+    /// the end for as long as the addresses are trusted proxies.
+    fn client(&self, forwarded_for: Option<&str>, remote: Option<IpAddr>) -> String {
+        let mut client = match remote {
+            Some(remote) => remote,
+            None => return String::new(),
+        };
+        for address in forwarded_for
+            .into_iter()
+            .flat_map(|header| header.rsplit(','))
+        {
+            if !self.trusted_proxies.contains(&client) {
+                break;
+            }
+            match address.trim().parse() {
+                Ok(address) => client = address,
+                Err(_) => break,
+            }
+        }
+        client.to_string()
+    }
+}
+
+/// Identifies the client of a request for the rate limiter.
+pub fn client_id(
+    limiter: Option<Arc<InboundRateLimiter>>,
+) -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("x-forwarded-for")
+        .and(warp::addr::remote())
+        .map(
+            move |forwarded_for: Option<String>, remote: Option<SocketAddr>| match &limiter {
+                Some(limiter) => {
+                    limiter.client(forwarded_for.as_deref(), remote.map(|remote| remote.ip()))
+                }
+                None => String::new(),
+            },
+        )
+}
+
+fn insert_headers(headers: &mut HeaderMap, quota: &RateLimitQuota) {
+    headers.insert("RateLimit-Limit", quota.limit.into());
+    headers.insert("RateLimit-Remaining", quota.remaining.into());
+    headers.insert("RateLimit-Reset", quota.reset.into());
+}
+
+/// Rejects requests of clients that exhausted their quota and adds the quota headers to all other
+/// responses. Requests pass unchanged if there is no rate limiter.
+pub fn with_rate_limit<F, R>(
+    filter: F,
+    limiter: Option<Arc<InboundRateLimiter>>,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone
+where
+    F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+    R: Reply,
+{
+    // Requests get counted here. Admitted requests fall through to the wrapped filter.
+    let limited = client_id(limiter.clone())
+        .and_then({
+            let limiter = limiter.clone();
+            move |client: String| {
+                let exhausted = limiter
+                    .as_ref()
+                    .and_then(|limiter| limiter.consume(&client, Instant::now()).err());
+                async move { exhausted.ok_or_else(warp::reject::not_found) }
+            }
+        })
+        .map(|quota: RateLimitQuota| {
+            let mut response = with_status(
+                error("TooManyRequests", "rate limit exceeded"),
+                StatusCode::TOO_MANY_REQUESTS,
+            )
+            .into_response();
+            insert_headers(response.headers_mut(), &quota);
+            response
+                .headers_mut()
+                .insert("Retry-After", quota.reset.into());
+            response
+        })
+        .with(cors())
+        .map(Reply::into_response);
+
+    let admitted = client_id(limiter.clone())
+        .map(move |client: String| {
+            limiter
+                .as_ref()
+                .map(|limiter| limiter.quota(&client, Instant::now()))
+        })
+        .and(filter)
+        .map(|quota: Option<RateLimitQuota>, reply: R| {
+            let mut response = reply.into_response();
+            if let Some(quota) = quota {
+                insert_headers(response.headers_mut(), &quota);
+            }
+            response
+        });
+
+    limited.or(admitted).unify()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_requests_per_window() {
+        let limiter = InboundRateLimiter::new(2, Duration::from_secs(60), vec![]);
+        let start = Instant::now();
+        let quota = |remaining, reset| RateLimitQuota {
+            limit: 2,
+            remaining,
+            reset,
+        };
+
+        assert_eq!(limiter.quota("a", start), quota(2, 60));
+        assert_eq!(limiter.consume("a", start), Ok(quota(1, 60)));
+        let later = start + Duration::from_millis(10_500);
+        assert_eq!(limiter.consume("a", later), Ok(quota(0, 50)));
+        assert_eq!(limiter.consume("a", later), Err(quota(0, 50)));
+        assert_eq!(limiter.quota("a", later), quota(0, 50));
+        assert_eq!(limiter.consume("b", later), Ok(quota(1, 60)));
+
+        let next_window = start + Duration::from_secs(60);
+        assert_eq!(limiter.quota("a", next_window), quota(2, 60));
+        assert_eq!(limiter.consume("a", next_window), Ok(quota(1, 60)));
+    }
+
+    #[test]
+    fn forgets_clients_once_per_window() {
+        let limiter = InboundRateLimiter::new(2, Duration::from_secs(60), vec![]);
+        let clients = || limiter.state.lock().unwrap().clients.len();
+        let start = Instant::now();
+        let consume = |client, secs| {
+            limiter
+                .consume(client, start + Duration::from_secs(secs))
+                .unwrap();
+        };
+        consume("a", 0);
+        consume("b", 30);
+        assert_eq!(clients(), 2);
+
+        // Forgets "a".
+        consume("c", 61);
+        assert_eq!(clients(), 2);
+        // The window of "b" ended but the next prune is only due a window after the last one.
+        consume("d", 100);
+        assert_eq!(clients(), 3);
+        // Forgets "b" and "c".
+        consume("e", 121);
+        assert_eq!(clients(), 2);
+    }
+
+    #[test]
+    fn only_trusts_forwarded_for_from_trusted_proxies() {
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let limiter = InboundRateLimiter::new(1, Duration::from_secs(60), vec![proxy]);
+
+        assert_eq!(limiter.client(None, Some(proxy)), "10.0.0.1");
+        assert_eq!(
+            limiter.client(Some("1.1.1.1, 2.2.2.2"), Some(proxy)),
+            "2.2.2.2"
+        );
+        // Addresses of trusted proxies in the chain get skipped.
+        assert_eq!(
+            limiter.client(Some("1.1.1.1, 10.0.0.1"), Some(proxy)),
+            "1.1.1.1"
+        );
+        assert_eq!(limiter.client(Some("invalid"), Some(proxy)), "10.0.0.1");
+        // Other clients could forge the header.
+        assert_eq!(
+            limiter.client(Some("1.1.1.1"), Some("3.3.3.3".parse().unwrap())),
+            "3.3.3.3"
+        );
+        assert_eq!(limiter.client(Some("1.1.1.1"), None), "");
+    }
+
+    #[tokio::test]
+    async fn adds_quota_headers_and_rejects_exhausted_clients() {
+        let proxy: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+        let limiter = Arc::new(InboundRateLimiter::new(
+            1,
+            Duration::from_secs(60),
+            vec![proxy.ip()],
+        ));
+        let filter = with_rate_limit(warp::any().map(warp::reply), Some(limiter));
+        let request = || {
+            warp::test::request()
+                .remote_addr(proxy)
+                .header("x-forwarded-for", "10.0.0.1, 192.168.0.1")
+                .reply(&filter)
+        };
+
+        let response = request().await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["RateLimit-Limit"], "1");
+        assert_eq!(response.headers()["RateLimit-Remaining"], "0");
+
+        let response = request().await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["RateLimit-Remaining"], "0");
+        assert!(response.headers().contains_key("Retry-After"));
+
+        let response = warp::test::request()
+            .remote_addr(proxy)
+            .header("x-forwarded-for", "10.0.0.1, 192.168.0.2")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn passes_requests_without_limiter() {
+        let filter = with_rate_limit(warp::any().map(warp::reply), None);
+        let response = warp::test::request().reply(&filter).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key("RateLimit-Limit"));
+    }
+}
@@ -1,10 +1,17 @@
 //! Manage solver competition data received by the driver through a private spi.
 
+pub mod diff;
+
+use crate::database::Postgres;
 use anyhow::Result;
 use database::auction::AuctionId;
-use model::solver_competition::SolverCompetition;
+use futures::StreamExt;
+use model::solver_competition::{CompetitionResult, SolverCompetition};
 use primitive_types::H256;
+use shared::maintenance::Maintaining;
+use std::{sync::Arc, time::Duration};
 use thiserror::Error;
+use tokio::sync::broadcast;
 
 pub enum Identifier {
     Id(AuctionId),
@@ -36,3 +43,79 @@ pub enum LoadSolverCompetitionError {
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
+
+/// Pushes the results of solver competitions to API subscribers as soon as they are stored, for
+/// example to power real-time leaderboards. Stored competitions get announced through Postgres so
+/// that subscribers of every replica receive them no matter which replica stored them.
+pub struct CompetitionResults {
+    sender: broadcast::Sender<CompetitionResult>,
+}
+
+impl Default for CompetitionResults {
+    fn default() -> Self {
+        // Subscribers that fall further behind than this skip results.
+        let (sender, _) = broadcast::channel(100);
+        Self { sender }
+    }
+}
+
+impl CompetitionResults {
+    pub fn subscribe(&self) -> broadcast::Receiver<CompetitionResult> {
+        self.sender.subscribe()
+    }
+
+    pub async fn publish_forever(self: Arc<Self>, db: Postgres) -> ! {
+        loop {
+            if let Err(err) = self.publish(&db).await {
+                tracing::warn!(?err, "failed to listen for solver competitions");
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    async fn publish(&self, db: &Postgres) -> Result<()> {
+        let mut ids = Box::pin(db.saved_solver_competitions().await?);
+        while let Some(id) = ids.next().await {
+            let competition = match db.load(Identifier::Id(id?)).await {
+                Ok(competition) => competition,
+                Err(err) => {
+                    tracing::warn!(?err, "failed to load saved solver competition");
+                    continue;
+                }
+            };
+            // Sending only fails if there are no subscribers.
+            let _ = self.sender.send(CompetitionResult::from(&competition));
+        }
+        Ok(())
+    }
+}
+
+/// The number of solver competitions that get archived per maintenance run at most.
+const ARCHIVE_BATCH_SIZE: i64 = 100;
+
+/// Moves solver competitions older than the maximum age out of the database into the archive.
+pub struct CompetitionArchiver {
+    db: Postgres,
+    max_age: chrono::Duration,
+}
+
+impl CompetitionArchiver {
+    /// The database needs to be configured with an archive.
+    pub fn new(db: Postgres, max_age: chrono::Duration) -> Self {
+        Self { db, max_age }
+    }
+}
+
+#[async_trait::async_trait]
+impl Maintaining for CompetitionArchiver {
+    async fn run_maintenance(&self) -> Result<()> {
+        let archived = self
+            .db
+            .archive_solver_competitions(chrono::Utc::now() - self.max_age, ARCHIVE_BATCH_SIZE)
+            .await?;
+        if archived > 0 {
+            tracing::debug!(%archived, "archived solver competitions");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,242 @@
+//! Compares two solutions of a solver competition so that solver teams can see why their solution
+//! lost without comparing the raw competition data by hand.
+//!
+//! Interactions are compared by the contract and function they call because the exact call data
+//! almost always differs between solutions, for example in the traded amounts.
+
+use contracts::GPv2Settlement;
+use ethcontract::common::abi::Token;
+use model::{
+    auction::AuctionId,
+    solver_competition::{
+        ClearingPriceDiff, ExecutedAmountDiff, Interaction, InteractionsDiff, SolutionDiff,
+        SolutionSummary, SolverSettlement,
+    },
+};
+use std::collections::{BTreeSet, HashMap};
+
+pub fn diff(auction_id: AuctionId, a: &SolverSettlement, b: &SolverSettlement) -> SolutionDiff {
+    let clearing_prices = a
+        .clearing_prices
+        .keys()
+        .chain(b.clearing_prices.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|token| ClearingPriceDiff {
+            token: *token,
+            a: a.clearing_prices.get(token).copied(),
+            b: b.clearing_prices.get(token).copied(),
+        })
+        .filter(|diff| diff.a != diff.b)
+        .collect();
+
+    let executed_amount = |solution: &SolverSettlement, id| {
+        solution
+            .orders
+            .iter()
+            .find(|order| order.id == id)
+            .map(|order| order.executed_amount)
+    };
+    let mut orders = Vec::<ExecutedAmountDiff>::new();
+    for id in a.orders.iter().chain(&b.orders).map(|order| order.id) {
+        if orders.iter().any(|diff| diff.id == id) {
+            continue;
+        }
+        orders.push(ExecutedAmountDiff {
+            id,
+            a: executed_amount(a, id),
+            b: executed_amount(b, id),
+        });
+    }
+    orders.retain(|diff| diff.a != diff.b);
+
+    let interactions_a = interactions(&a.call_data);
+    let interactions_b = interactions(&b.call_data);
+    SolutionDiff {
+        auction_id,
+        a: summary(a),
+        b: summary(b),
+        clearing_prices,
+        orders,
+        interactions: InteractionsDiff {
+            a: only_in(&interactions_a, &interactions_b),
+            b: only_in(&interactions_b, &interactions_a),
+        },
+    }
+}
+
+fn summary(solution: &SolverSettlement) -> SolutionSummary {
+    SolutionSummary {
+        solver: solution.solver.clone(),
+        objective: solution.objective.clone(),
+    }
+}
+
+/// Decodes the interactions of all stages of a `settle` call. Call data that isn't a `settle` call
+/// has no interactions.
+fn interactions(call_data: &[u8]) -> Vec<Interaction> {
+    let settle = GPv2Settlement::raw_contract()
+        .abi
+        .function("settle")
+        .expect("settlement contract has settle function");
+    let params = match call_data.get(..4) {
+        Some(selector) if selector == settle.short_signature() => {
+            settle.decode_input(&call_data[4..]).ok()
+        }
+        _ => None,
+    };
+    params
+        .and_then(|params| params.into_iter().nth(3)?.into_fixed_array())
+        .into_iter()
+        .flatten()
+        .filter_map(Token::into_array)
+        .flatten()
+        .filter_map(|interaction| {
+            let mut interaction = interaction.into_tuple()?.into_iter();
+            let target = interaction.next()?.into_address()?;
+            let call_data = interaction.nth(1)?.into_bytes()?;
+            Some(Interaction {
+                target,
+                selector: call_data.into_iter().take(4).collect(),
+            })
+        })
+        .collect()
+}
+
+/// The interactions that don't have a counterpart in `other`. Interactions that occur multiple
+/// times only have as many counterparts as they occur in `other`.
+fn only_in(interactions: &[Interaction], other: &[Interaction]) -> Vec<Interaction> {
+    let mut counterparts = HashMap::<&Interaction, usize>::new();
+    for interaction in other {
+        *counterparts.entry(interaction).or_default() += 1;
+    }
+    interactions
+        .iter()
+        .filter(|interaction| match counterparts.get_mut(interaction) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethcontract::Bytes;
+    use maplit::btreemap;
+    use model::{order::OrderUid, solver_competition::Order};
+    use primitive_types::{H160, U256};
+
+    fn call_data(interactions: &[(u8, [u8; 4])]) -> Vec<u8> {
+        let contract = GPv2Settlement::at(&shared::transport::dummy::web3(), H160::default());
+        let interactions = interactions
+            .iter()
+            .map(|(target, selector)| {
+                (
+                    H160([*target; 20]),
+                    U256::zero(),
+                    Bytes([&selector[..], &[0xff; 32][..]].concat()),
+                )
+            })
+            .collect();
+        contract
+            .settle(
+                Vec::new(),
+                Vec::new(),
+                Vec::new(),
+                [Vec::new(), interactions, Vec::new()],
+            )
+            .tx
+            .data
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn diffs_solutions() {
+        let order = |byte, executed_amount: u64| Order {
+            id: OrderUid([byte; 56]),
+            executed_amount: executed_amount.into(),
+        };
+        let a = SolverSettlement {
+            solver: "a".to_string(),
+            clearing_prices: btreemap! {
+                H160([1; 20]) => 1.into(),
+                H160([2; 20]) => 2.into(),
+            },
+            orders: vec![order(1, 10), order(2, 20)],
+            call_data: call_data(&[(1, [1; 4]), (1, [1; 4]), (2, [2; 4])]),
+            ..Default::default()
+        };
+        let b = SolverSettlement {
+            solver: "b".to_string(),
+            clearing_prices: btreemap! {
+                H160([1; 20]) => 1.into(),
+                H160([3; 20]) => 3.into(),
+            },
+            orders: vec![order(3, 30), order(1, 11)],
+            call_data: call_data(&[(1, [1; 4]), (3, [3; 4])]),
+            ..Default::default()
+        };
+
+        let diff = diff(42, &a, &b);
+        assert_eq!(diff.auction_id, 42);
+        assert_eq!((diff.a.solver.as_str(), diff.b.solver.as_str()), ("a", "b"));
+        assert_eq!(
+            diff.clearing_prices,
+            [
+                ClearingPriceDiff {
+                    token: H160([2; 20]),
+                    a: Some(2.into()),
+                    b: None,
+                },
+                ClearingPriceDiff {
+                    token: H160([3; 20]),
+                    a: None,
+                    b: Some(3.into()),
+                },
+            ]
+        );
+        assert_eq!(
+            diff.orders,
+            [
+                ExecutedAmountDiff {
+                    id: OrderUid([1; 56]),
+                    a: Some(10.into()),
+                    b: Some(11.into()),
+                },
+                ExecutedAmountDiff {
+                    id: OrderUid([2; 56]),
+                    a: Some(20.into()),
+                    b: None,
+                },
+                ExecutedAmountDiff {
+                    id: OrderUid([3; 56]),
+                    a: None,
+                    b: Some(30.into()),
+                },
+            ]
+        );
+        let interaction = |target, selector| Interaction {
+            target: H160([target; 20]),
+            selector: vec![selector; 4],
+        };
+        assert_eq!(
+            diff.interactions,
+            InteractionsDiff {
+                a: vec![interaction(1, 1), interaction(2, 2)],
+                b: vec![interaction(3, 3)],
+            }
+        );
+    }
+
+    #[test]
+    fn ignores_undecodable_call_data() {
+        assert!(interactions(&[]).is_empty());
+        assert!(interactions(&[0x13, 0x37, 0x13, 0x37]).is_empty());
+    }
+}
@@ -0,0 +1,177 @@
+//! Proofs of trades for consumers that can't trust the API, like bridges and light clients on
+//! other chains. A proof consists of the receipt of the settlement transaction, which contains the
+//! trade event, and the Merkle Patricia proof of the receipt against the receipts root of the
+//! block header.
+
+mod trie;
+
+use crate::database::trades::{TradeFilter, TradeRetrieving};
+use anyhow::{ensure, Context, Result};
+use futures::{stream, StreamExt, TryStreamExt};
+use model::{
+    order::OrderUid,
+    trade::{Trade, TradeProof},
+};
+use primitive_types::H256;
+use rlp::RlpStream;
+use shared::Web3;
+use std::sync::Arc;
+use web3::types::{BlockId, Bytes, TransactionReceipt};
+
+/// How many receipts of a block get requested from the node at once.
+const RECEIPT_REQUEST_PARALLELISM: usize = 20;
+
+pub struct TradeProofs {
+    web3: Web3,
+    database: Arc<dyn TradeRetrieving>,
+}
+
+impl TradeProofs {
+    pub fn new(web3: Web3, database: Arc<dyn TradeRetrieving>) -> Self {
+        Self { web3, database }
+    }
+
+    /// Returns proofs for the trades of the order. Trades whose settlement transaction hasn't been
+    /// indexed yet are skipped.
+    pub async fn proofs(&self, order_uid: &OrderUid) -> Result<Vec<TradeProof>> {
+        let filter = TradeFilter {
+            order_uid: Some(*order_uid),
+            ..Default::default()
+        };
+        let mut proofs = Vec::new();
+        for trade in self.database.trades(&filter).await? {
+            if let Some(tx_hash) = trade.tx_hash {
+                proofs.push(self.proof(&trade, tx_hash).await?);
+            }
+        }
+        Ok(proofs)
+    }
+
+    async fn proof(&self, trade: &Trade, tx_hash: H256) -> Result<TradeProof> {
+        let receipt = self
+            .web3
+            .eth()
+            .transaction_receipt(tx_hash)
+            .await?
+            .context("settlement transaction has no receipt")?;
+        ensure!(
+            receipt.block_number.map(|block| block.as_u64()) == Some(trade.block_number),
+            "settlement transaction is not part of block {} anymore",
+            trade.block_number
+        );
+        let block_hash = receipt
+            .block_hash
+            .context("settlement receipt has no block")?;
+        let block = self
+            .web3
+            .eth()
+            .block(BlockId::Hash(block_hash))
+            .await?
+            .context("settlement block not found")?;
+
+        let receipts: Vec<Option<TransactionReceipt>> = stream::iter(
+            block
+                .transactions
+                .iter()
+                .map(|tx| self.web3.eth().transaction_receipt(*tx)),
+        )
+        .buffered(RECEIPT_REQUEST_PARALLELISM)
+        .try_collect()
+        .await
+        .context("failed to fetch the receipts of the settlement block")?;
+        let receipts = receipts
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .context("transaction of the settlement block has no receipt")?;
+        let entries = receipts
+            .iter()
+            .enumerate()
+            .map(|(index, receipt)| (rlp::encode(&(index as u64)).to_vec(), encode(receipt)))
+            .collect::<Vec<_>>();
+
+        let transaction_index = receipt.transaction_index.as_u64();
+        let (receipts_root, proof) =
+            trie::root_and_proof(&entries, &rlp::encode(&transaction_index));
+        ensure!(
+            receipts_root == block.receipts_root,
+            "computed receipts root {:?} does not match the receipts root {:?} of block {:?}",
+            receipts_root,
+            block.receipts_root,
+            block_hash,
+        );
+        let receipt_log_index = receipt
+            .logs
+            .iter()
+            .position(|log| log.log_index == Some(trade.log_index.into()))
+            .context("trade event is not part of the settlement receipt")?;
+
+        Ok(TradeProof {
+            order_uid: trade.order_uid,
+            block_number: trade.block_number,
+            block_hash,
+            receipts_root,
+            tx_hash,
+            transaction_index,
+            log_index: trade.log_index,
+            receipt_log_index: receipt_log_index as u64,
+            receipt: Bytes(entries[transaction_index as usize].1.clone()),
+            proof: proof.into_iter().map(Bytes).collect(),
+        })
+    }
+}
+
+/// The consensus encoding of a receipt, which is what the receipts trie commits to. Typed
+/// transactions (EIP-2718) prefix the RLP encoding with their type.
+fn encode(receipt: &TransactionReceipt) -> Vec<u8> {
+    let mut stream = RlpStream::new_list(4);
+    match (receipt.status, receipt.root) {
+        (Some(status), _) => stream.append(&status.as_u64()),
+        // Receipts from before the Byzantium fork contain the state root instead of a status.
+        (None, Some(root)) => stream.append(&root.as_bytes().to_vec()),
+        (None, None) => stream.append_empty_data(),
+    };
+    stream.append(&receipt.cumulative_gas_used.low_u64());
+    stream.append(&receipt.logs_bloom.as_bytes().to_vec());
+    stream.begin_list(receipt.logs.len());
+    for log in &receipt.logs {
+        stream.begin_list(3);
+        stream.append(&log.address.as_bytes().to_vec());
+        stream.begin_list(log.topics.len());
+        for topic in &log.topics {
+            stream.append(&topic.as_bytes().to_vec());
+        }
+        stream.append(&log.data.0);
+    }
+    let encoded = stream.out().to_vec();
+    match receipt.transaction_type.map(|type_| type_.as_u64()) {
+        None | Some(0) => encoded,
+        Some(type_) => [vec![type_ as u8], encoded].concat(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_receipts() {
+        let receipt = TransactionReceipt {
+            status: Some(1.into()),
+            cumulative_gas_used: 21_000.into(),
+            ..Default::default()
+        };
+        let expected = [
+            &[0xf9u8, 0x01, 0x08, 0x01, 0x82, 0x52, 0x08, 0xb9, 0x01, 0x00][..],
+            &[0; 256],
+            &[0xc0],
+        ]
+        .concat();
+        assert_eq!(encode(&receipt), expected);
+
+        let typed = TransactionReceipt {
+            transaction_type: Some(2.into()),
+            ..receipt
+        };
+        assert_eq!(encode(&typed), [&[0x02][..], &expected[..]].concat());
+    }
+}
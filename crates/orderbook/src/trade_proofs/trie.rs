@@ -0,0 +1,200 @@
+//! A minimal Merkle Patricia trie like the one Ethereum uses to commit to the receipts of a block.
+//! The trie gets built from all of its entries at once and only supports computing the root and
+//! the proof for a key.
+
+use primitive_types::H256;
+use rlp::RlpStream;
+use web3::signing::keccak256;
+
+/// Computes the root of the trie with the entries and the RLP encoded nodes on the path from the
+/// root to the key.
+///
+/// Like in the proofs returned by `eth_getProof`, nodes that are shorter than a hash are embedded
+/// in their parent and are therefore only part of the proof if they are the root.
+pub fn root_and_proof(entries: &[(Vec<u8>, Vec<u8>)], key: &[u8]) -> (H256, Vec<Vec<u8>>) {
+    let entries = entries
+        .iter()
+        .map(|(key, value)| (nibbles(key), value.as_slice()))
+        .collect::<Vec<_>>();
+    let entries = entries
+        .iter()
+        .map(|(key, value)| (key.as_slice(), *value))
+        .collect::<Vec<_>>();
+    let mut proof = Vec::new();
+    let root = encode_node(&entries, Some(&nibbles(key)), &mut proof);
+    let root_hash = H256(keccak256(&root));
+    if root.len() < 32 {
+        proof.push(root);
+    }
+    proof.reverse();
+    (root_hash, proof)
+}
+
+/// Encodes the node for the entries, whose keys have had the path to the node stripped already.
+/// Nodes on the path to the key get collected into the proof from the bottom up.
+fn encode_node(
+    entries: &[(&[u8], &[u8])],
+    path: Option<&[u8]>,
+    proof: &mut Vec<Vec<u8>>,
+) -> Vec<u8> {
+    let node = match entries {
+        [] => rlp::encode(&Vec::<u8>::new()).to_vec(),
+        [(key, value)] => {
+            let mut stream = RlpStream::new_list(2);
+            stream.append(&hex_prefix(key, true));
+            stream.append(&value.to_vec());
+            stream.out().to_vec()
+        }
+        _ => match common_prefix_len(entries) {
+            0 => {
+                let mut stream = RlpStream::new_list(17);
+                for nibble in 0..16u8 {
+                    let children = entries
+                        .iter()
+                        .filter(|(key, _)| key.first() == Some(&nibble))
+                        .map(|(key, value)| (&key[1..], *value))
+                        .collect::<Vec<_>>();
+                    if children.is_empty() {
+                        stream.append_empty_data();
+                        continue;
+                    }
+                    let path = path.and_then(|path| path.strip_prefix(&[nibble]));
+                    append_reference(&mut stream, &encode_node(&children, path, proof));
+                }
+                match entries.iter().find(|(key, _)| key.is_empty()) {
+                    Some((_, value)) => stream.append(&value.to_vec()),
+                    None => stream.append_empty_data(),
+                };
+                stream.out().to_vec()
+            }
+            len => {
+                let prefix = &entries[0].0[..len];
+                let children = entries
+                    .iter()
+                    .map(|(key, value)| (&key[len..], *value))
+                    .collect::<Vec<_>>();
+                let path = path.and_then(|path| path.strip_prefix(prefix));
+                let mut stream = RlpStream::new_list(2);
+                stream.append(&hex_prefix(prefix, false));
+                append_reference(&mut stream, &encode_node(&children, path, proof));
+                stream.out().to_vec()
+            }
+        },
+    };
+    if path.is_some() && node.len() >= 32 {
+        proof.push(node.clone());
+    }
+    node
+}
+
+/// Children are embedded in their parent if their encoding is shorter than a hash and are
+/// referenced by their hash otherwise.
+fn append_reference(stream: &mut RlpStream, child: &[u8]) {
+    if child.len() < 32 {
+        stream.append_raw(child, 1);
+    } else {
+        stream.append(&keccak256(child).to_vec());
+    }
+}
+
+fn common_prefix_len(entries: &[(&[u8], &[u8])]) -> usize {
+    let first = entries[0].0;
+    entries[1..]
+        .iter()
+        .map(|(key, _)| {
+            first
+                .iter()
+                .zip(key.iter())
+                .take_while(|(a, b)| a == b)
+                .count()
+        })
+        .min()
+        .unwrap_or(first.len())
+}
+
+fn nibbles(bytes: &[u8]) -> Vec<u8> {
+    bytes
+        .iter()
+        .flat_map(|byte| [byte >> 4, byte & 0x0f])
+        .collect()
+}
+
+/// The compact encoding of a path in a leaf or extension node. The first nibble flags the node
+/// type and whether the path has an odd number of nibbles.
+fn hex_prefix(nibbles: &[u8], leaf: bool) -> Vec<u8> {
+    let odd = nibbles.len() % 2 == 1;
+    let flag = 2 * leaf as u8 + odd as u8;
+    let mut bytes = Vec::with_capacity(nibbles.len() / 2 + 1);
+    let nibbles = if odd {
+        bytes.push((flag << 4) | nibbles[0]);
+        &nibbles[1..]
+    } else {
+        bytes.push(flag << 4);
+        nibbles
+    };
+    bytes.extend(nibbles.chunks(2).map(|pair| (pair[0] << 4) | pair[1]));
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    fn entries(entries: &[(&str, &str)]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.as_bytes().to_vec(), value.as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn computes_known_roots() {
+        // Test vectors of the go-ethereum trie implementation.
+        let dogs = entries(&[
+            ("doe", "reindeer"),
+            ("dog", "puppy"),
+            ("dogglesworth", "cat"),
+        ]);
+        assert_eq!(
+            root_and_proof(&dogs, b"dog").0,
+            H256(hex!(
+                "8aad789dff2f538bca5d8ea56e8abe10f4c7ba3a5dea95fea4cd6e7c3a1168d3"
+            ))
+        );
+
+        let long_value = entries(&[(
+            "A",
+            "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+        )]);
+        assert_eq!(
+            root_and_proof(&long_value, b"A").0,
+            H256(hex!(
+                "d23786fb4a010da3ce639d66d5e904a11dbc02746d1ce25029e53290cabf28ab"
+            ))
+        );
+
+        // The root of the empty trie, which is the receipts root of blocks without transactions.
+        assert_eq!(
+            root_and_proof(&[], b"").0,
+            H256(hex!(
+                "56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421"
+            ))
+        );
+    }
+
+    #[test]
+    fn proof_starts_at_root_and_ends_at_value() {
+        let entries = (0..100u64)
+            .map(|i| (rlp::encode(&i).to_vec(), vec![i as u8; 40]))
+            .collect::<Vec<_>>();
+        let (root, proof) = root_and_proof(&entries, &rlp::encode(&42u64));
+        assert_eq!(H256(keccak256(&proof[0])), root);
+        for (parent, child) in proof.iter().zip(&proof[1..]) {
+            let hash = keccak256(child);
+            assert!(parent.windows(32).any(|window| window == hash));
+        }
+        let leaf = proof.last().unwrap();
+        assert!(leaf.windows(40).any(|window| window == [42; 40]));
+    }
+}
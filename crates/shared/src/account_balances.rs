@@ -1,3 +1,5 @@
+pub mod storage_proofs;
+
 use crate::{Web3, Web3Transport};
 use anyhow::{anyhow, Context, Result};
 use contracts::{BalancerV2Vault, ERC20};
@@ -8,6 +10,11 @@ use primitive_types::{H160, U256};
 use std::future::Future;
 use web3::types::{BlockId, BlockNumber, CallRequest};
 
+/// The Vault `UserBalanceOpKind` that moves tokens from an owner's internal balance to a recipient.
+const USER_BALANCE_OP_WITHDRAW_INTERNAL: u8 = 1;
+/// The Vault `UserBalanceOpKind` that transfers tokens the owner approved to the Vault.
+const USER_BALANCE_OP_TRANSFER_EXTERNAL: u8 = 3;
+
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
 pub struct Query {
     pub owner: H160,
@@ -27,8 +34,14 @@ impl Query {
 
 #[derive(Debug)]
 pub enum TransferSimulationError {
-    InsufficientAllowance,
-    InsufficientBalance,
+    /// The `spender` that transfers the owner's tokens isn't approved to transfer enough of them.
+    InsufficientAllowance {
+        allowance: U256,
+        spender: H160,
+    },
+    InsufficientBalance {
+        balance: U256,
+    },
     TransferFailed,
     Other(anyhow::Error),
 }
@@ -57,6 +70,9 @@ pub trait BalanceFetching: Send + Sync {
         amount: U256,
         source: SellTokenSource,
     ) -> Result<(), TransferSimulationError>;
+
+    // Returns the balance of the chain's native token, like ETH, of the owner.
+    async fn native_balance(&self, owner: H160) -> Result<U256>;
 }
 
 pub struct Web3BalanceFetcher {
@@ -101,21 +117,22 @@ impl Web3BalanceFetcher {
             .unwrap_or(false)
     }
 
-    async fn can_manage_user_balance_call(&self, token: H160, from: H160, amount: U256) -> bool {
+    /// Simulates the Vault user balance operation the vault relayer performs to transfer the
+    /// tokens of the owner into the settlement contract.
+    async fn can_manage_user_balance_call(
+        &self,
+        kind: u8,
+        token: H160,
+        from: H160,
+        amount: U256,
+    ) -> bool {
         let vault = match self.vault.as_ref() {
             Some(vault) => vault,
             None => return false,
         };
 
-        const USER_BALANCE_OP_TRANSFER_EXTERNAL: u8 = 3;
         vault
-            .manage_user_balance(vec![(
-                USER_BALANCE_OP_TRANSFER_EXTERNAL,
-                token,
-                amount,
-                from,
-                self.settlement_contract,
-            )])
+            .manage_user_balance(vec![(kind, token, amount, from, self.settlement_contract)])
             .from(Account::Local(from, None))
             .call()
             .await
@@ -197,8 +214,16 @@ impl BalanceFetching for Web3BalanceFetcher {
                     (SellTokenSource::External, None) => {
                         async { Err(anyhow!("external balance but no vault")) }.boxed()
                     }
-                    (SellTokenSource::Internal, _) => {
-                        async { Err(anyhow!("internal balances are not supported")) }.boxed()
+                    (SellTokenSource::Internal, Some(vault)) => vault_internal_balance_query(
+                        &mut batch,
+                        vault.clone(),
+                        query.token,
+                        query.owner,
+                        self.vault_relayer,
+                    )
+                    .boxed(),
+                    (SellTokenSource::Internal, None) => {
+                        async { Err(anyhow!("internal balance but no vault")) }.boxed()
                     }
                 }
             })
@@ -235,15 +260,26 @@ impl BalanceFetching for Web3BalanceFetcher {
                 batch.execute_all(usize::MAX).await;
                 let Balance { balance, allowance } = balance_future.await?;
                 if balance < amount {
-                    return Err(TransferSimulationError::InsufficientBalance);
+                    return Err(TransferSimulationError::InsufficientBalance { balance });
                 }
                 if allowance < amount {
-                    return Err(TransferSimulationError::InsufficientAllowance);
+                    return Err(TransferSimulationError::InsufficientAllowance {
+                        allowance,
+                        spender: self.vault_relayer,
+                    });
                 }
                 return Err(TransferSimulationError::TransferFailed);
             }
             (SellTokenSource::External, Some(vault)) => {
-                if self.can_manage_user_balance_call(token, from, amount).await {
+                if self
+                    .can_manage_user_balance_call(
+                        USER_BALANCE_OP_TRANSFER_EXTERNAL,
+                        token,
+                        from,
+                        amount,
+                    )
+                    .await
+                {
                     return Ok(());
                 }
                 let mut batch = CallBatch::new(self.web3.transport().clone());
@@ -253,10 +289,13 @@ impl BalanceFetching for Web3BalanceFetcher {
                 batch.execute_all(usize::MAX).await;
                 let Balance { balance, allowance } = balance_future.await?;
                 if balance < amount {
-                    return Err(TransferSimulationError::InsufficientBalance);
+                    return Err(TransferSimulationError::InsufficientBalance { balance });
                 }
                 if allowance < amount {
-                    return Err(TransferSimulationError::InsufficientAllowance);
+                    return Err(TransferSimulationError::InsufficientAllowance {
+                        allowance,
+                        spender: vault.address(),
+                    });
                 }
                 return Err(TransferSimulationError::TransferFailed);
             }
@@ -265,13 +304,75 @@ impl BalanceFetching for Web3BalanceFetcher {
                     "External Vault balances require a deployed vault"
                 )))
             }
-            (SellTokenSource::Internal, _) => {
+            (SellTokenSource::Internal, Some(vault)) => {
+                if self
+                    .can_manage_user_balance_call(
+                        USER_BALANCE_OP_WITHDRAW_INTERNAL,
+                        token,
+                        from,
+                        amount,
+                    )
+                    .await
+                {
+                    return Ok(());
+                }
+                let mut batch = CallBatch::new(self.web3.transport().clone());
+                let balance_future = vault
+                    .get_internal_balance(from, vec![token])
+                    .batch_call(&mut batch);
+                // Batch needs to execute before we can await the query result
+                batch.execute_all(usize::MAX).await;
+                let balance = balance_future
+                    .await
+                    .context("internal balance")?
+                    .first()
+                    .copied()
+                    .unwrap_or_default();
+                if balance < amount {
+                    return Err(TransferSimulationError::InsufficientBalance { balance });
+                }
+                return Err(TransferSimulationError::TransferFailed);
+            }
+            (SellTokenSource::Internal, None) => {
                 return Err(TransferSimulationError::Other(anyhow!(
-                    "internal Vault balances not supported"
+                    "internal Vault balances require a deployed vault"
                 )))
             }
         };
     }
+
+    async fn native_balance(&self, owner: H160) -> Result<U256> {
+        Ok(self.web3.eth().balance(owner, None).await?)
+    }
+}
+
+/// The internal Vault balance of the owner is only available to the settlement if the owner
+/// approved the vault relayer, in which case the relayer can withdraw all of it.
+fn vault_internal_balance_query(
+    batch: &mut CallBatch<Web3Transport>,
+    vault: BalancerV2Vault,
+    token: H160,
+    owner: H160,
+    relayer: H160,
+) -> impl Future<Output = Result<Balance>> {
+    let balance = vault
+        .get_internal_balance(owner, vec![token])
+        .batch_call(batch);
+    let approval = vault.has_approved_relayer(owner, relayer).batch_call(batch);
+    async move {
+        Ok(match approval.await.context("allowance")? {
+            true => Balance {
+                balance: balance
+                    .await
+                    .context("balance")?
+                    .first()
+                    .copied()
+                    .unwrap_or_default(),
+                allowance: U256::max_value(),
+            },
+            false => Balance::zero(),
+        })
+    }
 }
 
 fn is_empty_or_truthy(bytes: &[u8]) -> bool {
@@ -462,7 +563,7 @@ mod tests {
                     SellTokenSource::External
                 )
                 .await,
-            Err(TransferSimulationError::InsufficientBalance)
+            Err(TransferSimulationError::InsufficientBalance { .. })
         ));
 
         // Set authorization for allowance target to act as a Vault relayer
@@ -510,7 +611,7 @@ mod tests {
                     SellTokenSource::External
                 )
                 .await,
-            Err(TransferSimulationError::InsufficientAllowance)
+            Err(TransferSimulationError::InsufficientAllowance { .. })
         ));
     }
 
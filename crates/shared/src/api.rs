@@ -148,6 +148,14 @@ pub fn extract_payload_with_max_size<T: DeserializeOwned + Send>(
 }
 
 /// Sets up basic metrics, cors and proper log tracing for all routes.
+/// CORS configuration of all API routes.
+pub fn cors() -> warp::cors::Builder {
+    warp::cors()
+        .allow_any_origin()
+        .allow_methods(vec!["GET", "POST", "DELETE", "OPTIONS", "PUT", "PATCH"])
+        .allow_headers(vec!["Origin", "Content-Type", "X-Auth-Token", "X-AppId"])
+}
+
 pub fn finalize_router(
     routes: BoxedFilter<(ApiReply, &'static str)>,
     log_prefix: &'static str,
@@ -174,11 +182,6 @@ pub fn finalize_router(
 
     // Final setup
 
-    let cors = warp::cors()
-        .allow_any_origin()
-        .allow_methods(vec!["GET", "POST", "DELETE", "OPTIONS", "PUT", "PATCH"])
-        .allow_headers(vec!["Origin", "Content-Type", "X-Auth-Token", "X-AppId"]);
-
     // Give each request a unique tracing span.
     // This allows us to match log statements across concurrent API requests. We
     // first try to read the request ID from our reverse proxy (this way we can
@@ -197,7 +200,7 @@ pub fn finalize_router(
 
     routes_with_metrics
         .recover(handle_rejection)
-        .with(cors)
+        .with(cors())
         .with(warp::log::log(log_prefix))
         .with(tracing_span)
 }
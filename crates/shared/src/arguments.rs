@@ -167,6 +167,12 @@ pub struct Arguments {
         parse(try_from_str = duration_from_seconds),
     )]
     pub liquidity_fetcher_max_age_update: Duration,
+
+    /// Instead of starting, check the node, the contracts, the database and the external APIs
+    /// the service is configured with, print a report and exit with a non-zero exit code if any
+    /// check failed.
+    #[clap(long, env)]
+    pub preflight: bool,
 }
 
 pub fn display_secret_option<T>(
@@ -275,6 +281,7 @@ impl Display for Arguments {
             self.balancer_pool_deny_list
         )?;
         display_secret_option(f, "solver_competition_auth", &self.solver_competition_auth)?;
+        writeln!(f, "preflight: {}", self.preflight)?;
         Ok(())
     }
 }
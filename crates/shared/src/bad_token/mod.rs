@@ -1,17 +1,27 @@
 pub mod cache;
 pub mod instrumented;
 pub mod list_based;
+pub mod token_list;
 pub mod token_owner_finder;
 pub mod trace_call;
 
 use anyhow::Result;
 use primitive_types::H160;
+use std::sync::Arc;
 
 /// How well behaved a token is.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum TokenQuality {
     Good,
-    Bad { reason: String },
+    /// The token takes a fee on transfers out of the settlement contract. Such tokens can be
+    /// bought because the fee is paid by the receiver but they can't be sold.
+    FeeOnTransfer {
+        /// The fee in basis points of the transferred amount, rounded up.
+        fee_bps: u32,
+    },
+    Bad {
+        reason: String,
+    },
 }
 
 impl TokenQuality {
@@ -19,6 +29,20 @@ impl TokenQuality {
         matches!(self, Self::Good { .. })
     }
 
+    /// Whether orders can buy the token.
+    pub fn is_buyable(&self) -> bool {
+        matches!(self, Self::Good | Self::FeeOnTransfer { .. })
+    }
+
+    /// The fee in basis points that receivers of the token pay on transfers out of the
+    /// settlement contract.
+    pub fn transfer_fee_bps(&self) -> u32 {
+        match self {
+            Self::FeeOnTransfer { fee_bps } => *fee_bps,
+            _ => 0,
+        }
+    }
+
     pub fn bad(reason: impl ToString) -> Self {
         Self::Bad {
             reason: reason.to_string(),
@@ -32,3 +56,10 @@ impl TokenQuality {
 pub trait BadTokenDetecting: Send + Sync {
     async fn detect(&self, token: H160) -> Result<TokenQuality>;
 }
+
+#[async_trait::async_trait]
+impl<T: BadTokenDetecting + ?Sized> BadTokenDetecting for Arc<T> {
+    async fn detect(&self, token: H160) -> Result<TokenQuality> {
+        self.as_ref().detect(token).await
+    }
+}
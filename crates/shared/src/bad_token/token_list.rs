@@ -0,0 +1,65 @@
+use super::{BadTokenDetecting, TokenQuality};
+use crate::token_list::TokenLists;
+use anyhow::Result;
+use primitive_types::H160;
+use std::sync::Arc;
+
+/// Treats tokens on trusted token lists as good and forwards all other tokens.
+pub struct TokenListDetector {
+    lists: Arc<TokenLists>,
+    inner: Box<dyn BadTokenDetecting>,
+}
+
+impl TokenListDetector {
+    pub fn new(lists: Arc<TokenLists>, inner: Box<dyn BadTokenDetecting>) -> Self {
+        Self { lists, inner }
+    }
+}
+
+#[async_trait::async_trait]
+impl BadTokenDetecting for TokenListDetector {
+    async fn detect(&self, token: H160) -> Result<TokenQuality> {
+        if self.lists.current().get(&token).is_some() {
+            return Ok(TokenQuality::Good);
+        }
+        self.inner.detect(token).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bad_token::MockBadTokenDetecting,
+        token_list::{Token, TokenList},
+    };
+    use futures::FutureExt;
+
+    #[test]
+    fn listed_tokens_are_good() {
+        let listed = H160::from_low_u64_be(1);
+        let lists = TokenLists::from(TokenList::new(maplit::hashmap! {
+            listed => Token {
+                address: listed,
+                symbol: "A".into(),
+                name: "A".into(),
+                decimals: 18,
+            },
+        }));
+        let mut inner = MockBadTokenDetecting::new();
+        inner
+            .expect_detect()
+            .times(1)
+            .returning(|_| Ok(TokenQuality::bad("unknown")));
+        let detector = TokenListDetector::new(Arc::new(lists), Box::new(inner));
+
+        let quality = detector.detect(listed).now_or_never().unwrap().unwrap();
+        assert!(quality.is_good());
+        let quality = detector
+            .detect(H160::from_low_u64_be(2))
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+        assert!(!quality.is_good());
+    }
+}
@@ -1,9 +1,9 @@
 use super::{token_owner_finder::TokenOwnerFinding, BadTokenDetecting, TokenQuality};
-use crate::{trace_many, Web3};
+use crate::{trace_many::CallTracing, Web3};
 use anyhow::{anyhow, bail, ensure, Context, Result};
 use contracts::ERC20;
 use ethcontract::{dyns::DynTransport, transaction::TransactionBuilder, PrivateKey};
-use primitive_types::{H160, U256};
+use primitive_types::{H160, U256, U512};
 use std::sync::Arc;
 use web3::{
     signing::keccak256,
@@ -17,8 +17,13 @@ use web3::{
 /// - we cannot find an amm pool of the token to one of the base tokens
 /// - transfer into the settlement contract or back out fails
 /// - a transfer loses total balance
+///
+/// Tokens that only take a fee on the transfer out of the settlement contract are reported as fee
+/// on transfer tokens which can still be bought.
 pub struct TraceCallDetector {
     pub web3: Web3,
+    /// Simulates the transfers, either with `trace_callMany` on a node or the embedded EVM.
+    pub tracer: Arc<dyn CallTracing>,
     pub finder: Arc<dyn TokenOwnerFinding>,
     pub settlement_contract: H160,
 }
@@ -55,7 +60,9 @@ impl TraceCallDetector {
         // sending to an address that does not have any balance yet (implicitly 0) causes an
         // allocation.
         let request = self.create_trace_request(token, amount, take_from);
-        let traces = trace_many::trace_many(request, &self.web3)
+        let traces = self
+            .tracer
+            .trace_many(request)
             .await
             .context("failed to trace for bad token detection")?;
         Self::handle_response(&traces, amount)
@@ -171,17 +178,13 @@ impl TraceCallDetector {
                 "balance after out transfer does not match",
             ));
         }
-        let computed_balance_recipient_after = match balance_recipient_before.checked_add(amount) {
-            Some(amount) => amount,
-            None => {
-                return Ok(TokenQuality::bad(
-                    "token total supply does not fit a uint256",
-                ))
-            }
+        // The settlement contract got debited the full amount so any difference in what the
+        // recipient received is a fee on the transfer.
+        let received = match balance_recipient_after.checked_sub(balance_recipient_before) {
+            Some(received) if !received.is_zero() && received <= amount => received,
+            _ => return Ok(TokenQuality::bad("balance of recipient does not match")),
         };
-        if computed_balance_recipient_after != balance_recipient_after {
-            return Ok(TokenQuality::bad("balance of recipient does not match"));
-        }
+        let fee_bps = transfer_fee_bps(amount, amount - received);
 
         if let Err(err) = ensure_transaction_ok_and_get_gas(&traces[7])? {
             return Ok(TokenQuality::bad(format!(
@@ -191,10 +194,21 @@ impl TraceCallDetector {
         }
 
         let _gas_per_transfer = (gas_in + gas_out) / 2;
-        Ok(TokenQuality::Good)
+        if fee_bps == 0 {
+            Ok(TokenQuality::Good)
+        } else {
+            Ok(TokenQuality::FeeOnTransfer { fee_bps })
+        }
     }
 }
 
+/// The fee in basis points of the amount, rounded up so that adjusted amounts are conservative.
+fn transfer_fee_bps(amount: U256, fee: U256) -> u32 {
+    let amount = U512::from(amount);
+    let fee_bps = (fee.full_mul(10_000.into()) + amount - U512::one()) / amount;
+    fee_bps.low_u32()
+}
+
 fn call_request(
     from: Option<H160>,
     to: H160,
@@ -261,9 +275,8 @@ mod tests {
         Bytes(bytes)
     }
 
-    #[test]
-    fn handle_response_ok() {
-        let traces = &[
+    fn traces(amount: U256, received: U256) -> Vec<BlockTrace> {
+        vec![
             BlockTrace {
                 output: encode_u256(0.into()),
                 trace: None,
@@ -296,7 +309,7 @@ mod tests {
                 transaction_hash: None,
             },
             BlockTrace {
-                output: encode_u256(1.into()),
+                output: encode_u256(amount),
                 trace: None,
                 vm_trace: None,
                 state_diff: None,
@@ -341,7 +354,7 @@ mod tests {
                 transaction_hash: None,
             },
             BlockTrace {
-                output: encode_u256(1.into()),
+                output: encode_u256(received),
                 trace: None,
                 vm_trace: None,
                 state_diff: None,
@@ -371,13 +384,29 @@ mod tests {
                 state_diff: None,
                 transaction_hash: None,
             },
-        ];
+        ]
+    }
 
-        let result = TraceCallDetector::handle_response(traces, 1.into()).unwrap();
+    #[test]
+    fn handle_response_ok() {
+        let result =
+            TraceCallDetector::handle_response(&traces(1.into(), 1.into()), 1.into()).unwrap();
         let expected = TokenQuality::Good;
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn handle_response_fee_on_transfer() {
+        let amount = U256::from(100_000);
+        let result =
+            TraceCallDetector::handle_response(&traces(amount, 98_999.into()), amount).unwrap();
+        assert_eq!(result, TokenQuality::FeeOnTransfer { fee_bps: 101 });
+
+        // Tokens that don't arrive at all can't be bought either.
+        let result = TraceCallDetector::handle_response(&traces(amount, 0.into()), amount).unwrap();
+        assert!(!result.is_buyable());
+    }
+
     #[test]
     fn arbitrary_recipient_() {
         println!("{:?}", TraceCallDetector::arbitrary_recipient());
@@ -594,7 +623,8 @@ mod tests {
             ],
         });
         let token_cache = TraceCallDetector {
-            web3,
+            web3: web3.clone(),
+            tracer: Arc::new(web3),
             finder,
             settlement_contract: settlement.address(),
         };
@@ -631,7 +661,8 @@ mod tests {
             proposers: vec![univ3],
         });
         let token_cache = super::TraceCallDetector {
-            web3,
+            web3: web3.clone(),
+            tracer: Arc::new(web3),
             finder,
             settlement_contract: settlement.address(),
         };
@@ -48,6 +48,7 @@ impl BalancerSorApi for DefaultBalancerSorApi {
             .json(&query)
             .send()
             .await?
+            .error_for_status()?
             .text()
             .await?;
         tracing::debug!(%response, "received Balancer SOR quote");
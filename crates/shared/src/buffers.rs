@@ -1,12 +1,10 @@
+use crate::{transport::MAX_BATCH_SIZE, Web3};
 use contracts::ERC20;
 use ethcontract::{batch::CallBatch, errors::MethodError, H160, U256};
 use futures::{future::join_all, join};
 use model::order::BUY_ETH_ADDRESS;
-use shared::Web3;
 use std::collections::HashMap;
 
-const MAX_BATCH_SIZE: usize = 100;
-
 #[derive(Clone)]
 /// Computes the amount of "buffer" ERC20 balance that the http solver can use
 /// to offset possible rounding errors in computing the amounts in a solution.
@@ -30,7 +28,7 @@ pub enum BufferRetrievalError {
     Erc20(MethodError),
 }
 
-#[cfg_attr(test, mockall::automock)]
+#[mockall::automock]
 #[async_trait::async_trait]
 pub trait BufferRetrieving: Send + Sync {
     async fn get_buffers(
@@ -94,9 +92,9 @@ impl BufferRetrieving for BufferRetriever {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::transport::create_test_transport;
     use contracts::GPv2Settlement;
     use hex_literal::hex;
-    use shared::transport::create_test_transport;
 
     #[tokio::test]
     #[ignore]
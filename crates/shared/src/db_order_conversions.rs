@@ -1,11 +1,16 @@
+use anyhow::{Context, Result};
 use database::orders::{
-    BuyTokenDestination as DbBuyTokenDestination, OrderKind as DbOrderKind,
-    SellTokenSource as DbSellTokenSource, SigningScheme as DbSigningScheme,
+    BuyTokenDestination as DbBuyTokenDestination, OrderClass as DbOrderClass,
+    OrderKind as DbOrderKind, SellTokenSource as DbSellTokenSource,
+    SigningScheme as DbSigningScheme,
 };
 use model::{
-    order::{BuyTokenDestination, OrderKind, SellTokenSource},
-    signature::SigningScheme,
+    order::{
+        BuyTokenDestination, OrderClass, OrderCondition, OrderKind, QuoteFirmness, SellTokenSource,
+    },
+    signature::{schemes, SigningScheme},
 };
+use serde_json::Value;
 
 pub fn order_kind_into(kind: OrderKind) -> DbOrderKind {
     match kind {
@@ -21,6 +26,24 @@ pub fn order_kind_from(kind: DbOrderKind) -> OrderKind {
     }
 }
 
+pub fn order_class_into(class: OrderClass) -> DbOrderClass {
+    match class {
+        OrderClass::Market => DbOrderClass::Market,
+        OrderClass::Limit => DbOrderClass::Limit,
+        OrderClass::Liquidity => DbOrderClass::Liquidity,
+        OrderClass::Twap => DbOrderClass::Twap,
+    }
+}
+
+pub fn order_class_from(class: DbOrderClass) -> OrderClass {
+    match class {
+        DbOrderClass::Market => OrderClass::Market,
+        DbOrderClass::Limit => OrderClass::Limit,
+        DbOrderClass::Liquidity => OrderClass::Liquidity,
+        DbOrderClass::Twap => OrderClass::Twap,
+    }
+}
+
 pub fn sell_token_source_into(source: SellTokenSource) -> DbSellTokenSource {
     match source {
         SellTokenSource::Erc20 => DbSellTokenSource::Erc20,
@@ -52,19 +75,68 @@ pub fn buy_token_destination_from(destination: DbBuyTokenDestination) -> BuyToke
 }
 
 pub fn signing_scheme_into(scheme: SigningScheme) -> DbSigningScheme {
-    match scheme {
-        SigningScheme::Eip712 => DbSigningScheme::Eip712,
-        SigningScheme::EthSign => DbSigningScheme::EthSign,
-        SigningScheme::Eip1271 => DbSigningScheme::Eip1271,
-        SigningScheme::PreSign => DbSigningScheme::PreSign,
-    }
+    let label = schemes::get(scheme).database_label();
+    DbSigningScheme::from_label(label)
+        .unwrap_or_else(|| panic!("missing database signing scheme {label}"))
 }
 
 pub fn signing_scheme_from(scheme: DbSigningScheme) -> SigningScheme {
-    match scheme {
-        DbSigningScheme::Eip712 => SigningScheme::Eip712,
-        DbSigningScheme::EthSign => SigningScheme::EthSign,
-        DbSigningScheme::Eip1271 => SigningScheme::Eip1271,
-        DbSigningScheme::PreSign => SigningScheme::PreSign,
+    schemes::from_database_label(scheme.label())
+        .unwrap_or_else(|| {
+            panic!(
+                "missing signing scheme for database label {}",
+                scheme.label()
+            )
+        })
+        .signing_scheme()
+}
+
+/// Returns the `quote_expiry_block` and `quote_last_look` columns.
+pub fn quote_firmness_into(firmness: Option<QuoteFirmness>) -> (Option<i64>, bool) {
+    let firmness = firmness.unwrap_or_default();
+    (
+        firmness
+            .expiry_block
+            .map(|block| block.try_into().unwrap_or(i64::MAX)),
+        firmness.last_look,
+    )
+}
+
+/// Returns the `condition` column.
+pub fn order_condition_into(condition: Option<&OrderCondition>) -> Option<Value> {
+    condition.map(|condition| {
+        serde_json::to_value(condition).expect("order conditions serialize to JSON")
+    })
+}
+
+pub fn order_condition_from(condition: Option<Value>) -> Result<Option<OrderCondition>> {
+    condition
+        .map(|condition| serde_json::from_value(condition).context("invalid order condition"))
+        .transpose()
+}
+
+pub fn quote_firmness_from(expiry_block: Option<i64>, last_look: bool) -> Option<QuoteFirmness> {
+    if expiry_block.is_none() && !last_look {
+        return None;
+    }
+    Some(QuoteFirmness {
+        expiry_block: expiry_block.map(|block| block.try_into().unwrap_or_default()),
+        last_look,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signing_schemes_round_trip() {
+        for implementation in schemes::REGISTRY {
+            let scheme = implementation.signing_scheme();
+            assert_eq!(signing_scheme_from(signing_scheme_into(scheme)), scheme);
+        }
+        for scheme in DbSigningScheme::ALL {
+            assert_eq!(signing_scheme_into(signing_scheme_from(scheme)), scheme);
+        }
     }
 }
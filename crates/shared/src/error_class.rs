@@ -0,0 +1,127 @@
+//! Classification of errors returned by external integrations like the 0x, 1Inch, ParaSwap,
+//! Balancer SOR and Tenderly APIs.
+//!
+//! Callers decide whether and when to retry a failed request based on the class of the error
+//! instead of matching on error messages of the individual APIs.
+
+use crate::{
+    oneinch_api::RestError, paraswap_api::ParaswapResponseError, rate_limiter::RateLimiterError,
+    zeroex_api::ZeroExResponseError,
+};
+use reqwest::StatusCode;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ErrorClass {
+    /// A temporary failure like a timeout or a server error. The same request can be retried
+    /// right away.
+    Transient,
+    /// The API asked us to slow down. The same request can be retried after backing off.
+    RateLimited,
+    /// The request can't succeed, for example because there is no liquidity. Retrying it doesn't
+    /// help.
+    Fatal,
+    /// The API rejected the request or responded with something we don't understand. Retrying it
+    /// doesn't help and the error most likely indicates a bug.
+    Invalid,
+}
+
+impl ErrorClass {
+    /// Returns true if the failed request can be retried.
+    pub fn is_retryable(self) -> bool {
+        matches!(self, Self::Transient | Self::RateLimited)
+    }
+
+    /// Classifies an error by the HTTP status code of the response.
+    pub fn from_status(status: StatusCode) -> Self {
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            Self::RateLimited
+        } else if status.is_server_error() || status == StatusCode::REQUEST_TIMEOUT {
+            Self::Transient
+        } else if status.is_client_error() {
+            Self::Invalid
+        } else {
+            Self::Fatal
+        }
+    }
+}
+
+pub trait ClassifyError {
+    fn class(&self) -> ErrorClass;
+}
+
+impl ClassifyError for reqwest::Error {
+    fn class(&self) -> ErrorClass {
+        if let Some(status) = self.status() {
+            ErrorClass::from_status(status)
+        } else if self.is_timeout() || self.is_connect() || self.is_request() || self.is_body() {
+            ErrorClass::Transient
+        } else if self.is_decode() {
+            ErrorClass::Invalid
+        } else {
+            ErrorClass::Fatal
+        }
+    }
+}
+
+/// Errors that get wrapped in an `anyhow::Error` are classified by the first error in the chain
+/// that has a known class. Everything else is fatal so that unknown errors never cause retries.
+impl ClassifyError for anyhow::Error {
+    fn class(&self) -> ErrorClass {
+        self.chain()
+            .find_map(|err| {
+                if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+                    Some(err.class())
+                } else if let Some(err) = err.downcast_ref::<RateLimiterError>() {
+                    Some(err.class())
+                } else if let Some(err) = err.downcast_ref::<ZeroExResponseError>() {
+                    Some(err.class())
+                } else if let Some(err) = err.downcast_ref::<ParaswapResponseError>() {
+                    Some(err.class())
+                } else if let Some(err) = err.downcast_ref::<RestError>() {
+                    Some(err.class())
+                } else if err.is::<serde_json::Error>() {
+                    Some(ErrorClass::Invalid)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(ErrorClass::Fatal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Context};
+
+    #[test]
+    fn classifies_status_codes() {
+        for (status, class) in [
+            (StatusCode::TOO_MANY_REQUESTS, ErrorClass::RateLimited),
+            (StatusCode::INTERNAL_SERVER_ERROR, ErrorClass::Transient),
+            (StatusCode::BAD_GATEWAY, ErrorClass::Transient),
+            (StatusCode::REQUEST_TIMEOUT, ErrorClass::Transient),
+            (StatusCode::BAD_REQUEST, ErrorClass::Invalid),
+            (StatusCode::NOT_FOUND, ErrorClass::Invalid),
+            (StatusCode::OK, ErrorClass::Fatal),
+        ] {
+            assert_eq!(ErrorClass::from_status(status), class, "{status}");
+        }
+    }
+
+    #[test]
+    fn classifies_wrapped_errors() {
+        let err = anyhow::Error::new(RateLimiterError::RateLimited).context("price estimation");
+        assert_eq!(err.class(), ErrorClass::RateLimited);
+
+        let err = Err::<(), _>(ZeroExResponseError::ServerError("url".to_string()))
+            .context("0x")
+            .unwrap_err();
+        assert_eq!(err.class(), ErrorClass::Transient);
+
+        let err = serde_json::from_str::<u32>("").unwrap_err();
+        assert_eq!(anyhow::Error::new(err).class(), ErrorClass::Invalid);
+
+        assert_eq!(anyhow!("unknown").class(), ErrorClass::Fatal);
+    }
+}
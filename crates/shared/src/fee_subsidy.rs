@@ -31,7 +31,10 @@ pub mod cow_token;
 use anyhow::Result;
 use ethcontract::{H160, U256};
 use futures::future;
-use model::app_id::AppId;
+use model::{
+    app_id::AppId,
+    fee_policy::{FeeSubsidy, FeeSubsidyRule, OrderFeePolicy},
+};
 use std::sync::Arc;
 
 #[derive(Clone, Debug, Default)]
@@ -158,6 +161,47 @@ impl FeeParameters {
 
         dtou(discounted_fee_in_eth * subsidy.factor / self.sell_token_price)
     }
+
+    /// The rules of the subsidy that change the fee, in the order they get applied.
+    pub fn applied_subsidy_rules(&self, subsidy: &Subsidy) -> Vec<FeeSubsidyRule> {
+        let fee_in_eth = self.gas_amount * self.gas_price;
+        let mut rules = Vec::new();
+        if subsidy.discount != 0. {
+            rules.push(FeeSubsidyRule::FlatDiscount);
+        }
+        if fee_in_eth - subsidy.discount < subsidy.min_discounted {
+            rules.push(FeeSubsidyRule::MinimumDiscountedFee);
+        }
+        if subsidy.factor != 1. {
+            rules.push(FeeSubsidyRule::FeeFactor);
+        }
+        rules
+    }
+}
+
+/// Explains the fee of an order that was created with a quote with the fee parameters and the
+/// subsidy. The subsidy is `None` if it wasn't recorded.
+pub fn order_fee_policy(
+    fee_parameters: &FeeParameters,
+    subsidy: Option<&Subsidy>,
+    fee_amount: U256,
+) -> OrderFeePolicy {
+    OrderFeePolicy {
+        gas_amount: fee_parameters.gas_amount,
+        gas_price: fee_parameters.gas_price,
+        sell_token_price: fee_parameters.sell_token_price,
+        raw_fee_amount: fee_parameters.unsubsidized(),
+        subsidized_fee_amount: subsidy.map(|subsidy| fee_parameters.subsidized(subsidy)),
+        fee_amount,
+        subsidy: subsidy.map(|subsidy| FeeSubsidy {
+            discount: subsidy.discount,
+            min_discounted: subsidy.min_discounted,
+            factor: subsidy.factor,
+        }),
+        applied_rules: subsidy
+            .map(|subsidy| fee_parameters.applied_subsidy_rules(subsidy))
+            .unwrap_or_default(),
+    }
 }
 
 /// Converts an `f64` to a `U256`.
@@ -221,6 +265,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn reports_applied_subsidy_rules() {
+        let fee = FeeParameters {
+            gas_amount: 100_000.,
+            gas_price: 1_000_000_000.,
+            sell_token_price: 1.,
+        };
+        assert_eq!(fee.applied_subsidy_rules(&Subsidy::default()), vec![]);
+        assert_eq!(
+            fee.applied_subsidy_rules(&Subsidy {
+                discount: 1_000.,
+                factor: 0.5,
+                ..Default::default()
+            }),
+            vec![FeeSubsidyRule::FlatDiscount, FeeSubsidyRule::FeeFactor],
+        );
+        assert_eq!(
+            fee.applied_subsidy_rules(&Subsidy {
+                discount: 500_000_000_000_000.,
+                min_discounted: 1_000_000.,
+                ..Default::default()
+            }),
+            vec![
+                FeeSubsidyRule::FlatDiscount,
+                FeeSubsidyRule::MinimumDiscountedFee
+            ],
+        );
+    }
+
     #[tokio::test]
     async fn combine_multiple_subsidies() {
         let fee_subsidies = FeeSubsidies(vec![
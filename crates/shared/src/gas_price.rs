@@ -6,7 +6,10 @@
 
 use anyhow::Result;
 use gas_estimation::{GasPrice1559, GasPriceEstimating};
-use std::time::Duration;
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 /// An instrumented gas price estimator that wraps an inner one.
 pub struct InstrumentedGasEstimator<T> {
@@ -54,4 +57,152 @@ where
 struct Metrics {
     /// Last measured gas price in gwei
     gas_price: prometheus::Gauge,
+
+    /// Last smoothed gas price in gwei
+    smoothed_gas_price: prometheus::Gauge,
+}
+
+/// A gas price estimator that smooths the estimates of an inner one with an exponential moving
+/// average so that momentary gas price spikes don't result in absurdly high fees.
+///
+/// Only meant for fee calculations. Transactions need the live gas price to get included, which
+/// is why estimates with limits are passed through unchanged.
+pub struct SmoothedGasEstimator {
+    inner: Arc<dyn GasPriceEstimating>,
+    /// The time after which a live gas price contributes half of the smoothed gas price.
+    half_life: Duration,
+    /// The smoothed gas price is at most this factor away from the live gas price in either
+    /// direction so that it follows lasting gas price changes.
+    max_deviation: f64,
+    average: Mutex<Option<(Instant, GasPrice1559)>>,
+    metrics: &'static Metrics,
+}
+
+impl SmoothedGasEstimator {
+    pub fn new(
+        inner: Arc<dyn GasPriceEstimating>,
+        half_life: Duration,
+        max_deviation: f64,
+    ) -> Self {
+        Self {
+            inner,
+            half_life,
+            max_deviation: max_deviation.max(1.),
+            average: Default::default(),
+            metrics: Metrics::instance(global_metrics::get_metric_storage_registry()).unwrap(),
+        }
+    }
+
+    fn smooth(&self, live: GasPrice1559, now: Instant) -> GasPrice1559 {
+        let mut average = self.average.lock().unwrap();
+        let smoothed = match *average {
+            Some((time, previous)) => {
+                // The weight of the previous average halves every half life.
+                let elapsed = now.saturating_duration_since(time).as_secs_f64();
+                let weight = if self.half_life.is_zero() {
+                    0.
+                } else {
+                    0.5f64.powf(elapsed / self.half_life.as_secs_f64())
+                };
+                let smooth = |previous: f64, live: f64| {
+                    let smoothed = previous * weight + live * (1. - weight);
+                    smoothed.clamp(live / self.max_deviation, live * self.max_deviation)
+                };
+                GasPrice1559 {
+                    base_fee_per_gas: smooth(previous.base_fee_per_gas, live.base_fee_per_gas),
+                    max_fee_per_gas: smooth(previous.max_fee_per_gas, live.max_fee_per_gas),
+                    max_priority_fee_per_gas: smooth(
+                        previous.max_priority_fee_per_gas,
+                        live.max_priority_fee_per_gas,
+                    ),
+                }
+            }
+            None => live,
+        };
+        *average = Some((now, smoothed));
+        smoothed
+    }
+}
+
+#[async_trait::async_trait]
+impl GasPriceEstimating for SmoothedGasEstimator {
+    async fn estimate_with_limits(
+        &self,
+        gas_limit: f64,
+        time_limit: Duration,
+    ) -> Result<GasPrice1559> {
+        self.inner.estimate_with_limits(gas_limit, time_limit).await
+    }
+
+    async fn estimate(&self) -> Result<GasPrice1559> {
+        let live = self.inner.estimate().await?;
+        let smoothed = self.smooth(live, Instant::now());
+        self.metrics
+            .smoothed_gas_price
+            .set(smoothed.effective_gas_price() / 1e9);
+        Ok(smoothed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gas_price_estimation::FakeGasPriceEstimator;
+
+    fn gas_price(price: f64) -> GasPrice1559 {
+        GasPrice1559 {
+            base_fee_per_gas: price,
+            max_fee_per_gas: price * 2.,
+            max_priority_fee_per_gas: 1.,
+        }
+    }
+
+    fn fields(gas_price: GasPrice1559) -> (f64, f64, f64) {
+        (
+            gas_price.base_fee_per_gas,
+            gas_price.max_fee_per_gas,
+            gas_price.max_priority_fee_per_gas,
+        )
+    }
+
+    #[test]
+    fn smooths_gas_price_spikes() {
+        let estimator = SmoothedGasEstimator::new(
+            Arc::new(FakeGasPriceEstimator::default()),
+            Duration::from_secs(60),
+            4.,
+        );
+        let start = Instant::now();
+        assert_eq!(
+            fields(estimator.smooth(gas_price(10.), start)),
+            fields(gas_price(10.))
+        );
+
+        // After one half life the spike counts half.
+        let smoothed = estimator.smooth(gas_price(30.), start + Duration::from_secs(60));
+        assert_eq!(fields(smoothed), fields(gas_price(20.)));
+
+        // Spikes are capped at the maximum deviation from the live price.
+        let smoothed = estimator.smooth(gas_price(100.), start + Duration::from_secs(60));
+        assert_eq!(fields(smoothed), fields(gas_price(25.)));
+
+        // Old averages don't matter anymore.
+        let smoothed = estimator.smooth(gas_price(40.), start + Duration::from_secs(6000));
+        assert!((smoothed.base_fee_per_gas - 40.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn zero_half_life_disables_smoothing() {
+        let estimator = SmoothedGasEstimator::new(
+            Arc::new(FakeGasPriceEstimator::default()),
+            Duration::ZERO,
+            4.,
+        );
+        let start = Instant::now();
+        estimator.smooth(gas_price(10.), start);
+        assert_eq!(
+            fields(estimator.smooth(gas_price(30.), start)),
+            fields(gas_price(30.))
+        );
+    }
 }
@@ -0,0 +1,174 @@
+//! Runtime tunable settings that can be changed without restarting a service.
+//!
+//! Components read reloadable settings through a [`Reloadable`] handle on every use. Sending
+//! `SIGHUP` to the process re-reads a JSON file of overrides and swaps in rebuilt components.
+//! Requests that are already in flight keep using the value they started with, new requests see
+//! the new one.
+
+use crate::{
+    bad_token::{BadTokenDetecting, TokenQuality},
+    fee_subsidy::{FeeSubsidizing, Subsidy, SubsidyParameters},
+};
+use anyhow::{Context, Result};
+use primitive_types::H160;
+use serde::Deserialize;
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+/// A shared value that can be replaced while it is being used.
+pub struct Reloadable<T>(Arc<RwLock<Arc<T>>>);
+
+impl<T> Clone for Reloadable<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Reloadable<T> {
+    pub fn new(value: T) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(value))))
+    }
+
+    /// Returns the current value. Replacing the value does not affect the returned one.
+    pub fn get(&self) -> Arc<T> {
+        self.0.read().unwrap().clone()
+    }
+
+    pub fn set(&self, value: T) {
+        *self.0.write().unwrap() = Arc::new(value);
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for Reloadable<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+impl<T> From<T> for Reloadable<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: FeeSubsidizing + 'static> FeeSubsidizing for Reloadable<T> {
+    async fn subsidy(&self, parameters: SubsidyParameters) -> Result<Subsidy> {
+        self.get().subsidy(parameters).await
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: BadTokenDetecting + 'static> BadTokenDetecting for Reloadable<T> {
+    async fn detect(&self, token: H160) -> Result<TokenQuality> {
+        self.get().detect(token).await
+    }
+}
+
+/// Overrides for the command line arguments of runtime tunable settings. Settings that are not
+/// specified keep the value of the command line argument. Every service applies the settings it
+/// has and ignores the others so that services can share a file.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Overrides {
+    pub unsupported_tokens: Option<Vec<H160>>,
+    pub banned_users: Option<Vec<H160>>,
+    pub fee_discount: Option<f64>,
+    pub min_discounted_fee: Option<f64>,
+    pub fee_factor: Option<f64>,
+    pub paraswap_slippage_bps: Option<u32>,
+    pub zeroex_slippage_bps: Option<u32>,
+    pub oneinch_slippage_bps: Option<u32>,
+}
+
+impl Overrides {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("failed to parse {}", path.display()))
+    }
+}
+
+/// Calls `apply` with the overrides from the file every time the process receives `SIGHUP`.
+///
+/// Invalid files get logged and ignored so that the previous settings stay in effect.
+#[cfg(unix)]
+pub async fn reload_on_sighup(path: PathBuf, apply: impl Fn(Overrides) + Send + 'static) {
+    let mut signal = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+        .expect("failed to install SIGHUP handler");
+    while signal.recv().await.is_some() {
+        match Overrides::load(&path) {
+            Ok(overrides) => {
+                tracing::info!(?overrides, "reloading settings");
+                apply(overrides);
+            }
+            Err(err) => tracing::error!(?err, "failed to reload settings"),
+        }
+    }
+}
+
+#[cfg(windows)]
+pub async fn reload_on_sighup(_: PathBuf, _: impl Fn(Overrides) + Send + 'static) {
+    // We don't support signal handling on windows
+    std::future::pending().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bad_token::list_based::ListBasedDetector;
+    use futures::FutureExt;
+    use serde_json::json;
+
+    #[test]
+    fn reloaded_value_does_not_affect_previous_reads() {
+        let value = Reloadable::new(1);
+        let clone = value.clone();
+        let before = value.get();
+        clone.set(2);
+        assert_eq!(*before, 1);
+        assert_eq!(*value.get(), 2);
+    }
+
+    #[test]
+    fn detects_with_current_detector() {
+        let token = H160::from_low_u64_be(1);
+        let detector = Reloadable::new(ListBasedDetector::deny_list(vec![]));
+        assert!(detector
+            .detect(token)
+            .now_or_never()
+            .unwrap()
+            .unwrap()
+            .is_good());
+        detector.set(ListBasedDetector::deny_list(vec![token]));
+        assert!(!detector
+            .detect(token)
+            .now_or_never()
+            .unwrap()
+            .unwrap()
+            .is_good());
+    }
+
+    #[test]
+    fn deserialize_overrides() {
+        let overrides: Overrides = serde_json::from_value(json!({
+            "bannedUsers": ["0x0101010101010101010101010101010101010101"],
+            "feeFactor": 0.5,
+            "zeroexSlippageBps": 20,
+        }))
+        .unwrap();
+        assert_eq!(
+            overrides,
+            Overrides {
+                banned_users: Some(vec![H160([1; 20])]),
+                fee_factor: Some(0.5),
+                zeroex_slippage_bps: Some(20),
+                ..Default::default()
+            }
+        );
+        assert!(serde_json::from_value::<Overrides>(json!({ "unknown": 1 })).is_err());
+    }
+}
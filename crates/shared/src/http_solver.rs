@@ -1,14 +1,21 @@
 use crate::http_client::response_body_with_size_limit;
 use ::model::auction::AuctionId;
 use anyhow::{anyhow, ensure, Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
 use reqwest::header::{self, HeaderValue};
-use reqwest::{Client, Url};
-use std::time::Duration;
+use reqwest::{Client, RequestBuilder, Url};
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
 pub mod gas_model;
+pub mod graph;
 pub mod model;
+pub mod validation;
 
 const SOLVER_RESPONSE_SIZE_LIMIT: usize = 10_000_000;
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Implements an abstract HTTP solver API, can be mocked, instrumented, etc.
 #[mockall::automock]
@@ -20,6 +27,10 @@ pub trait HttpSolverApi: Send + Sync {
         model: &model::BatchAuctionModel,
         timeout: Duration,
     ) -> Result<model::SettledBatchAuctionModel>;
+
+    /// Notifies the solver that the driver rejected one of its settlements. Notifications are best
+    /// effort and get sent in the background.
+    fn notify_rejection(&self, rejection: &model::RejectionModel);
 }
 
 /// Default implementation for HTTP solver API that uses the reqwest client.
@@ -66,6 +77,12 @@ pub struct SolverConfig {
 
     /// Controls the objective function to optimize for.
     pub objective: Option<Objective>,
+
+    /// Controls if rejected settlements get reported to the solver's `notify` endpoint.
+    pub notify_rejections: bool,
+
+    /// Url that replaces the base url while it is set.
+    pub callback_url: CallbackUrl,
 }
 
 impl Default for SolverConfig {
@@ -76,10 +93,27 @@ impl Default for SolverConfig {
             has_ucp_policy_parameter: false,
             use_internal_buffers: None,
             objective: None,
+            notify_rejections: false,
+            callback_url: Default::default(),
         }
     }
 }
 
+/// A url that solver teams configure at runtime to receive requests instead of the url the
+/// operators configured. Clones share the url.
+#[derive(Clone, Debug, Default)]
+pub struct CallbackUrl(Arc<RwLock<Option<Url>>>);
+
+impl CallbackUrl {
+    pub fn get(&self) -> Option<Url> {
+        self.0.read().unwrap().clone()
+    }
+
+    pub fn set(&self, url: Option<Url>) {
+        *self.0.write().unwrap() = url;
+    }
+}
+
 #[derive(Debug)]
 pub enum Objective {
     CappedSurplusFeesCosts,
@@ -100,7 +134,7 @@ impl HttpSolverApi for DefaultHttpSolverApi {
             .checked_sub(Duration::from_secs(1))
             .ok_or_else(|| anyhow!("no time left to send request"))?;
 
-        let mut url = self.base.join("solve")?;
+        let mut url = self.base().join("solve")?;
 
         let maybe_auction_id = model.metadata.as_ref().and_then(|data| data.auction_id);
         let instance_name = self.generate_instance_name(maybe_auction_id.unwrap_or(0));
@@ -112,6 +146,9 @@ impl HttpSolverApi for DefaultHttpSolverApi {
             // does not support fractional values here. Note that this means that
             // we don't have much granularity with the time limit.
             .append_pair("time_limit", &solver_timeout.as_secs().to_string())
+            // The exact time the solver has to respond by for solvers that can make use of the
+            // fractional seconds `time_limit` drops.
+            .append_pair("deadline", &deadline(Utc::now(), solver_timeout))
             .append_pair(
                 "max_nr_exec_orders",
                 self.config.max_nr_exec_orders.to_string().as_str(),
@@ -176,12 +213,76 @@ impl HttpSolverApi for DefaultHttpSolverApi {
             status,
             context()
         );
-        serde_json::from_str(text)
-            .with_context(|| format!("failed to decode response json, {}", context()))
+        let raw: serde_json::Value = serde_json::from_str(text)
+            .with_context(|| format!("failed to decode response json, {}", context()))?;
+        let violations = validation::negative_amounts(&raw);
+        if !violations.is_empty() {
+            return Err(validation::SchemaViolations(violations))
+                .with_context(|| format!("invalid response json, {}", context()));
+        }
+        let settled = serde_json::from_str(text)
+            .with_context(|| format!("failed to decode response json, {}", context()))?;
+        let violations = validation::validate(model, &raw, &settled);
+        if !violations.is_empty() {
+            return Err(validation::SchemaViolations(violations))
+                .with_context(|| format!("invalid response json, {}", context()));
+        }
+        Ok(settled)
+    }
+
+    fn notify_rejection(&self, rejection: &model::RejectionModel) {
+        if !self.config.notify_rejections {
+            return;
+        }
+        let request = match self.notify_request(rejection) {
+            Ok(request) => request,
+            Err(err) => {
+                tracing::warn!(?err, "failed to build rejection notification");
+                return;
+            }
+        };
+        let name = self.name.clone();
+        tokio::task::spawn(async move {
+            let result = async {
+                let response = request.send().await?;
+                let status = response.status();
+                ensure!(status.is_success(), "solver responded with {}", status);
+                Ok(())
+            };
+            if let Err(err) = result.await {
+                tracing::debug!(solver = %name, ?err, "failed to notify solver of rejection");
+            }
+        });
     }
 }
 
 impl DefaultHttpSolverApi {
+    /// The url requests get sent to, which is the callback url if one is set.
+    fn base(&self) -> Url {
+        self.config
+            .callback_url
+            .get()
+            .unwrap_or_else(|| self.base.clone())
+    }
+
+    fn notify_request(&self, rejection: &model::RejectionModel) -> Result<RequestBuilder> {
+        let url = self.base().join("notify")?;
+        let body = serde_json::to_string(rejection).context("failed to encode body")?;
+        tracing::trace!(%url, %body, "rejection notification");
+        let mut request = self
+            .client
+            .post(url)
+            .timeout(NOTIFY_TIMEOUT)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body);
+        if let Some(api_key) = &self.config.api_key {
+            let mut header = HeaderValue::from_str(api_key.as_str()).unwrap();
+            header.set_sensitive(true);
+            request = request.header("X-API-KEY", header);
+        }
+        Ok(request)
+    }
+
     fn generate_instance_name(&self, auction_id: AuctionId) -> String {
         let now = chrono::Utc::now();
         format!(
@@ -198,9 +299,19 @@ impl DefaultHttpSolverApi {
     }
 }
 
+/// The deadline in RFC 3339 format with millisecond precision.
+fn deadline(now: DateTime<Utc>, timeout: Duration) -> String {
+    chrono::Duration::from_std(timeout)
+        .ok()
+        .and_then(|timeout| now.checked_add_signed(timeout))
+        .unwrap_or(chrono::MAX_DATETIME)
+        .to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
 #[cfg(test)]
 mod tests {
     use super::{model::SettledBatchAuctionModel, *};
+    use chrono::TimeZone;
     use flate2::write::GzEncoder;
     use tokio::{io::AsyncWriteExt, net::TcpListener};
 
@@ -244,4 +355,47 @@ Content-Encoding: gzip\r\n\
             .await
             .unwrap_err();
     }
+
+    #[test]
+    fn formats_deadline() {
+        let now = Utc.timestamp(1_600_000_000, 0);
+        assert_eq!(
+            deadline(now, Duration::from_millis(4_250)),
+            "2020-09-13T12:26:44.250Z"
+        );
+    }
+
+    #[test]
+    fn sends_requests_to_callback_url() {
+        let api = DefaultHttpSolverApi {
+            name: Default::default(),
+            network_name: Default::default(),
+            chain_id: Default::default(),
+            base: "http://operator.test".parse().unwrap(),
+            client: Default::default(),
+            config: Default::default(),
+        };
+        let url = || {
+            api.notify_request(&model::RejectionModel {
+                auction_id: 0,
+                rejection: model::SettlementRejection::BufferUsageExceeded {
+                    internal_buffer_usage: Default::default(),
+                },
+            })
+            .unwrap()
+            .build()
+            .unwrap()
+            .url()
+            .to_string()
+        };
+        assert_eq!(url(), "http://operator.test/notify");
+
+        api.config
+            .callback_url
+            .set(Some("http://solver.test".parse().unwrap()));
+        assert_eq!(url(), "http://solver.test/notify");
+
+        api.config.callback_url.set(None);
+        assert_eq!(url(), "http://operator.test/notify");
+    }
 }
@@ -1,10 +1,18 @@
 use super::model::TokenAmount;
-use crate::price_estimation::gas::*;
+use crate::{
+    price_estimation::gas::*,
+    realized_gas::{InteractionClass, RealizedGas},
+};
 use primitive_types::{H160, U256};
+use std::sync::Arc;
 
+#[derive(Clone)]
 pub struct GasModel {
     pub native_token: H160,
     pub gas_price: f64,
+    /// Gas of mined settlements which replaces the static estimates once enough of it has been
+    /// collected.
+    pub realized_gas: Option<Arc<RealizedGas>>,
 }
 
 impl GasModel {
@@ -15,8 +23,18 @@ impl GasModel {
         }
     }
 
+    fn realized(&self, class: InteractionClass) -> Option<u64> {
+        self.realized_gas.as_ref()?.gas(class)
+    }
+
     pub fn gp_order_cost(&self) -> TokenAmount {
-        self.cost_for_gas(GAS_PER_ORDER.into())
+        // The regression already accounts for the transfers at their lower bound, so only the
+        // difference to the realized transfer gas gets added.
+        let gas = match self.realized(InteractionClass::Erc20Transfer) {
+            Some(transfer) => (GAS_PER_ORDER + 2 * transfer).saturating_sub(2 * ERC20_TRANSFER),
+            None => GAS_PER_ORDER,
+        };
+        self.cost_for_gas(gas.into())
     }
 
     pub fn zeroex_order_cost(&self) -> TokenAmount {
@@ -24,10 +42,24 @@ impl GasModel {
     }
 
     pub fn uniswap_cost(&self) -> TokenAmount {
-        self.cost_for_gas(GAS_PER_UNISWAP.into())
+        let gas = self
+            .realized(InteractionClass::UniswapV2Swap)
+            .unwrap_or(GAS_PER_UNISWAP);
+        self.cost_for_gas(gas.into())
+    }
+
+    pub fn uniswap_v3_cost(&self, mean_gas: U256) -> TokenAmount {
+        let gas = self
+            .realized(InteractionClass::UniswapV3Swap)
+            .map(U256::from)
+            .unwrap_or(mean_gas);
+        self.cost_for_gas(gas)
     }
 
     pub fn balancer_cost(&self) -> TokenAmount {
-        self.cost_for_gas(GAS_PER_BALANCER_SWAP.into())
+        let gas = self
+            .realized(InteractionClass::BalancerSwap)
+            .unwrap_or(GAS_PER_BALANCER_SWAP);
+        self.cost_for_gas(gas.into())
     }
 }
@@ -0,0 +1,259 @@
+//! A renderable graph of a batch auction instance. Tokens are the nodes and orders and AMMs are
+//! the edges between them so that explorers can display how the orders of an auction are
+//! connected through the available liquidity.
+
+use super::model::{AmmParameters, BatchAuctionModel};
+use ethcontract::H160;
+use primitive_types::U256;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct AuctionGraph {
+    pub nodes: Vec<TokenNode>,
+    pub edges: Vec<Edge>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TokenNode {
+    pub token: H160,
+    pub alias: Option<String>,
+    pub external_price: Option<f64>,
+}
+
+/// Orders are directed from their sell to their buy token. AMMs get an undirected edge between
+/// every pair of their tokens.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Edge {
+    pub source: H160,
+    pub target: H160,
+    #[serde(flatten)]
+    pub kind: EdgeKind,
+    /// The value of the edge in the native token based on the external prices: the sell amount
+    /// of orders and the reserves of both tokens of AMMs. `None` if a price or the reserves are
+    /// not known.
+    pub weight: Option<f64>,
+}
+
+/// What an edge represents. The ids are the keys of the order or AMM in the instance.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EdgeKind {
+    Order { id: usize, is_liquidity_order: bool },
+    Amm { id: usize },
+}
+
+impl From<&BatchAuctionModel> for AuctionGraph {
+    fn from(model: &BatchAuctionModel) -> Self {
+        let native_value = |token: &H160, amount: U256| {
+            let price = model.tokens.get(token)?.external_price?;
+            Some(amount.to_f64_lossy() * price)
+        };
+
+        let orders = model.orders.iter().map(|(id, order)| Edge {
+            source: order.sell_token,
+            target: order.buy_token,
+            kind: EdgeKind::Order {
+                id: *id,
+                is_liquidity_order: order.is_liquidity_order,
+            },
+            weight: native_value(&order.sell_token, order.sell_amount),
+        });
+        let amms = model.amms.iter().flat_map(|(id, amm)| {
+            let reserves = amm_reserves(&amm.parameters);
+            let tokens = reserves.keys().copied().collect::<Vec<_>>();
+            let mut edges = Vec::new();
+            for (i, source) in tokens.iter().enumerate() {
+                for target in &tokens[i + 1..] {
+                    let value = |token: &H160| native_value(token, reserves[token]?);
+                    edges.push(Edge {
+                        source: *source,
+                        target: *target,
+                        kind: EdgeKind::Amm { id: *id },
+                        weight: value(source)
+                            .zip(value(target))
+                            .map(|(source, target)| source + target),
+                    });
+                }
+            }
+            edges
+        });
+        let edges = orders.chain(amms).collect::<Vec<_>>();
+
+        let tokens = model
+            .tokens
+            .keys()
+            .copied()
+            .chain(edges.iter().flat_map(|edge| [edge.source, edge.target]))
+            .collect::<BTreeSet<_>>();
+        let nodes = tokens
+            .into_iter()
+            .map(|token| {
+                let info = model.tokens.get(&token);
+                TokenNode {
+                    token,
+                    alias: info.and_then(|info| info.alias.clone()),
+                    external_price: info.and_then(|info| info.external_price),
+                }
+            })
+            .collect();
+
+        Self { nodes, edges }
+    }
+}
+
+/// The tokens of the AMM with their reserves if they are known.
+fn amm_reserves(parameters: &AmmParameters) -> BTreeMap<H160, Option<U256>> {
+    match parameters {
+        AmmParameters::ConstantProduct(parameters) => parameters
+            .reserves
+            .iter()
+            .map(|(token, reserve)| (*token, Some(*reserve)))
+            .collect(),
+        AmmParameters::WeightedProduct(parameters) => parameters
+            .reserves
+            .iter()
+            .map(|(token, data)| (*token, Some(data.balance)))
+            .collect(),
+        AmmParameters::Stable(parameters) => parameters
+            .reserves
+            .iter()
+            .map(|(token, reserve)| (*token, Some(*reserve)))
+            .collect(),
+        // Concentrated liquidity doesn't have reserves that would be comparable to the other AMMs.
+        AmmParameters::Concentrated(parameters) => parameters
+            .pool
+            .tokens
+            .iter()
+            .map(|token| (token.id, None))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_solver::model::{
+        AmmModel, ConstantProductPoolParameters, OrderModel, TokenInfoModel, WeightedPoolTokenData,
+        WeightedProductPoolParameters,
+    };
+    use maplit::btreemap;
+    use num::BigRational;
+    use serde_json::json;
+
+    #[test]
+    fn builds_graph_from_instance() {
+        let token = |i| H160([i; 20]);
+        let amm = |parameters| AmmModel {
+            parameters,
+            fee: BigRational::from_integer(0.into()),
+            cost: Default::default(),
+            mandatory: false,
+        };
+        let weighted = |balance: u64| WeightedPoolTokenData {
+            balance: balance.into(),
+            weight: BigRational::from_integer(1.into()),
+        };
+        let model = BatchAuctionModel {
+            tokens: btreemap! {
+                token(1) => TokenInfoModel {
+                    alias: Some("ONE".to_string()),
+                    external_price: Some(1.),
+                    ..Default::default()
+                },
+                token(2) => TokenInfoModel {
+                    external_price: Some(2.),
+                    ..Default::default()
+                },
+            },
+            orders: btreemap! {
+                0 => OrderModel {
+                    sell_token: token(1),
+                    buy_token: token(2),
+                    sell_amount: 10.into(),
+                    buy_amount: 5.into(),
+                    allow_partial_fill: false,
+                    is_sell_order: true,
+                    fee: Default::default(),
+                    cost: Default::default(),
+                    is_liquidity_order: false,
+                    quote_expiry_block: None,
+                    quote_last_look: false,
+                    min_partial_fill_amount: None,
+                    mandatory: false,
+                    has_atomic_execution: false,
+                },
+            },
+            amms: btreemap! {
+                0 => amm(AmmParameters::ConstantProduct(ConstantProductPoolParameters {
+                    reserves: btreemap! {
+                        token(1) => 100.into(),
+                        token(2) => 50.into(),
+                    },
+                })),
+                1 => amm(AmmParameters::WeightedProduct(WeightedProductPoolParameters {
+                    reserves: btreemap! {
+                        token(1) => weighted(1),
+                        token(2) => weighted(2),
+                        token(3) => weighted(3),
+                    },
+                })),
+            },
+            metadata: None,
+        };
+
+        let graph = AuctionGraph::from(&model);
+        assert_eq!(
+            graph.nodes,
+            [
+                TokenNode {
+                    token: token(1),
+                    alias: Some("ONE".to_string()),
+                    external_price: Some(1.),
+                },
+                TokenNode {
+                    token: token(2),
+                    alias: None,
+                    external_price: Some(2.),
+                },
+                TokenNode {
+                    token: token(3),
+                    alias: None,
+                    external_price: None,
+                },
+            ]
+        );
+        let edge = |source, target, kind, weight| Edge {
+            source: token(source),
+            target: token(target),
+            kind,
+            weight,
+        };
+        let order = EdgeKind::Order {
+            id: 0,
+            is_liquidity_order: false,
+        };
+        assert_eq!(
+            graph.edges,
+            [
+                edge(1, 2, order, Some(10.)),
+                edge(1, 2, EdgeKind::Amm { id: 0 }, Some(200.)),
+                edge(1, 2, EdgeKind::Amm { id: 1 }, Some(5.)),
+                edge(1, 3, EdgeKind::Amm { id: 1 }, None),
+                edge(2, 3, EdgeKind::Amm { id: 1 }, None),
+            ]
+        );
+
+        assert_eq!(
+            serde_json::to_value(&graph.edges[0]).unwrap(),
+            json!({
+                "source": "0x0101010101010101010101010101010101010101",
+                "target": "0x0202020202020202020202020202020202020202",
+                "kind": "order",
+                "id": 0,
+                "is_liquidity_order": false,
+                "weight": 10.,
+            })
+        );
+    }
+}
@@ -9,6 +9,7 @@ use model::{
 };
 use num::BigRational;
 use primitive_types::U256;
+use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use std::collections::{BTreeMap, HashMap};
@@ -23,6 +24,7 @@ pub struct BatchAuctionModel {
     pub metadata: Option<MetadataModel>,
 }
 
+#[serde_as]
 #[derive(Clone, Debug, Serialize)]
 pub struct OrderModel {
     pub sell_token: H160,
@@ -36,6 +38,17 @@ pub struct OrderModel {
     pub fee: TokenAmount,
     pub cost: TokenAmount,
     pub is_liquidity_order: bool,
+    /// The last block in which the market maker quote behind a liquidity order can be filled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quote_expiry_block: Option<u64>,
+    /// Whether the market maker can still reject the fill of a liquidity order.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub quote_last_look: bool,
+    /// The smallest amount a partially fillable order may get executed by unless the whole order
+    /// gets executed. Denominated in sell token for sell orders and in buy token for buy orders.
+    #[serde_as(as = "Option<DecimalU256>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_partial_fill_amount: Option<U256>,
     #[serde(default)]
     pub mandatory: bool,
     /// Signals if the order will be executed as an atomic unit. In that case the order's
@@ -110,6 +123,40 @@ pub struct TokenInfoModel {
     pub normalize_priority: Option<u64>,
     #[serde_as(as = "Option<DecimalU256>")]
     pub internal_buffer: Option<U256>,
+    /// Flash loans of the token that a settlement can take through a wrapper interaction.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub flash_loans: Vec<FlashLoanModel>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlashLoanProtocol {
+    BalancerV2,
+    AaveV2,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FlashLoanModel {
+    pub protocol: FlashLoanProtocol,
+    /// The contract to borrow from, the Balancer vault or the Aave lending pool.
+    pub lender: H160,
+    /// The maximum amount that can be borrowed.
+    #[serde(with = "u256_decimal")]
+    pub available: U256,
+    /// The fee as a fraction of the borrowed amount.
+    pub fee: f64,
+}
+
+/// A flash loan taken by an interaction.
+///
+/// The interaction targets a wrapper contract that borrows the tokens, executes the actual
+/// interaction logic and repays the loan plus fee before returning. Only the net token flows
+/// between the settlement contract and the wrapper are part of the interaction's inputs and
+/// outputs so the loan itself does not require any buffers.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct FlashLoanDataModel {
+    pub lender: H160,
+    pub loans: Vec<TokenAmount>,
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
@@ -148,6 +195,8 @@ pub struct InteractionData {
     /// `AMM -> GPv2Settlement`
     pub outputs: Vec<TokenAmount>,
     pub exec_plan: Option<ExecutionPlan>,
+    #[serde(default)]
+    pub flash_loan: Option<FlashLoanDataModel>,
 }
 
 #[serde_as]
@@ -192,6 +241,25 @@ pub struct MetadataModel {
     pub run_id: Option<u64>,
     pub gas_price: Option<f64>,
     pub native_token: Option<H160>,
+    /// Where the solver can request liquidity missing from the instance while it solves, see
+    /// `LiquidityRequestModel`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub liquidity_url: Option<Url>,
+}
+
+/// Requests the liquidity of token pairs that aren't part of the instance of a run.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct LiquidityRequestModel {
+    pub auction_id: AuctionId,
+    pub run_id: u64,
+    pub token_pairs: Vec<(H160, H160)>,
+}
+
+/// The requested liquidity. Indices follow the AMMs of the instance and solutions can use the
+/// AMMs like the instance's own.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct LiquidityResponseModel {
+    pub amms: BTreeMap<usize, AmmModel>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -312,6 +380,57 @@ pub struct ExecutionPlanCoordinatesModel {
     pub position: u32,
 }
 
+/// Notification sent to a solver when the driver rejects one of its settlements so that the solver
+/// can find out what went wrong.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RejectionModel {
+    pub auction_id: AuctionId,
+    #[serde(flatten)]
+    pub rejection: SettlementRejection,
+}
+
+#[serde_as]
+#[derive(Clone, Derivative, Deserialize, PartialEq, Serialize)]
+#[derivative(Debug)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum SettlementRejection {
+    /// The ratio of the clearing prices of the token pair deviates too much from the ratio of the
+    /// external prices.
+    PriceViolation {
+        sell_token: H160,
+        buy_token: H160,
+        #[serde(with = "u256_decimal")]
+        sell_token_clearing_price: U256,
+        #[serde(with = "u256_decimal")]
+        buy_token_clearing_price: U256,
+        sell_token_external_price: f64,
+        buy_token_external_price: f64,
+    },
+    /// The settlement uses more of the internal buffers than allowed.
+    BufferUsageExceeded {
+        #[serde_as(as = "BTreeMap<_, DecimalU256>")]
+        internal_buffer_usage: BTreeMap<H160, U256>,
+    },
+    /// The objective value of the simulated settlement is not positive, so the surplus and fees it
+    /// generates don't cover its gas cost. Amounts are denominated in wei.
+    ObjectiveMismatch {
+        objective_value: f64,
+        surplus: f64,
+        fees: f64,
+        #[serde(with = "u256_decimal")]
+        gas: U256,
+        gas_price: f64,
+    },
+    /// The settlement reverted in the simulation at the block the liquidity was fetched at.
+    SimulationFailed {
+        block_number: u64,
+        error: String,
+        #[derivative(Debug(format_with = "crate::debug_bytes"))]
+        #[serde(with = "model::bytes_hex")]
+        call_data: Vec<u8>,
+    },
+}
+
 #[cfg(test)]
 mod tests {
     use crate::sources::uniswap_v3::graph_api::Token;
@@ -405,6 +524,9 @@ mod tests {
                 token: native_token,
             },
             is_liquidity_order: false,
+            quote_expiry_block: None,
+            quote_last_look: false,
+            min_partial_fill_amount: None,
             mandatory: false,
             has_atomic_execution: false,
         };
@@ -495,6 +617,7 @@ mod tests {
                     external_price: Some(1.2),
                     normalize_priority: Some(1),
                     internal_buffer: Some(U256::from(1337)),
+                    flash_loans: vec![],
                 },
                 sell_token => TokenInfoModel {
                     decimals: Some(18),
@@ -502,6 +625,12 @@ mod tests {
                     external_price: Some(2345.0),
                     normalize_priority: Some(0),
                     internal_buffer: Some(U256::from(42)),
+                    flash_loans: vec![FlashLoanModel {
+                        protocol: FlashLoanProtocol::BalancerV2,
+                        lender: H160([0xba; 20]),
+                        available: U256::from(1000),
+                        fee: 0.,
+                    }],
                 }
             },
             orders: btreemap! { 0 => order_model },
@@ -534,6 +663,14 @@ mod tests {
               "external_price": 2345.0,
               "normalize_priority": 0,
               "internal_buffer": "42",
+              "flash_loans": [
+                {
+                  "protocol": "balancer_v2",
+                  "lender": "0xbabababababababababababababababababababa",
+                  "available": "1000",
+                  "fee": 0.0,
+                },
+              ],
             },
           },
           "orders": {
@@ -786,6 +923,7 @@ mod tests {
                     }
                 ],
                 exec_plan: Some(ExecutionPlan::Internal),
+                flash_loan: None,
             },
         );
     }
@@ -841,4 +979,43 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    fn encode_rejection() {
+        let rejection = RejectionModel {
+            auction_id: 1,
+            rejection: SettlementRejection::BufferUsageExceeded {
+                internal_buffer_usage: btreemap! { H160([1; 20]) => 1000.into() },
+            },
+        };
+        assert_eq!(
+            serde_json::to_value(&rejection).unwrap(),
+            json!({
+                "auction_id": 1,
+                "reason": "buffer_usage_exceeded",
+                "internal_buffer_usage": {
+                    "0x0101010101010101010101010101010101010101": "1000",
+                },
+            })
+        );
+
+        let rejection = RejectionModel {
+            auction_id: 2,
+            rejection: SettlementRejection::SimulationFailed {
+                block_number: 3,
+                error: "revert".to_string(),
+                call_data: vec![0x13, 0xd7],
+            },
+        };
+        assert_eq!(
+            serde_json::to_value(&rejection).unwrap(),
+            json!({
+                "auction_id": 2,
+                "reason": "simulation_failed",
+                "block_number": 3,
+                "error": "revert",
+                "call_data": "0x13d7",
+            })
+        );
+    }
 }
@@ -0,0 +1,429 @@
+//! Strict validation of the solutions HTTP solvers return.
+//!
+//! Decoding a response only ensures that it has roughly the expected shape. Fields the model
+//! doesn't have are silently dropped, which usually means that the solver and the driver disagree
+//! about the API, negative amounts fail decoding with an unhelpful error and nothing checks that
+//! the solution fits the instance it solves. The violations found here are reported per solver so
+//! that malformed output can be attributed and acted upon.
+
+use super::model::{BatchAuctionModel, SettledBatchAuctionModel};
+use primitive_types::H160;
+use serde_json::{Map, Value};
+use std::fmt::{self, Display, Formatter};
+use thiserror::Error;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SchemaViolation {
+    /// A field the solution model doesn't have.
+    UnknownField { path: String },
+    /// An amount that is negative.
+    NegativeAmount { path: String },
+    /// An executed order that isn't part of the instance.
+    UnknownOrder { index: usize },
+    /// An updated AMM that isn't part of the instance.
+    UnknownAmm { index: usize },
+    /// A token traded by an executed order has no clearing price or a zero one.
+    MissingPrice { token: H160 },
+    /// The executed amounts of an order don't respect the clearing prices, meaning the order
+    /// receives more than the prices allow.
+    InconsistentPrices { order: usize },
+}
+
+impl SchemaViolation {
+    /// The kind of the violation as used in metrics.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::UnknownField { .. } => "unknown_field",
+            Self::NegativeAmount { .. } => "negative_amount",
+            Self::UnknownOrder { .. } => "unknown_order",
+            Self::UnknownAmm { .. } => "unknown_amm",
+            Self::MissingPrice { .. } => "missing_price",
+            Self::InconsistentPrices { .. } => "inconsistent_prices",
+        }
+    }
+}
+
+impl Display for SchemaViolation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownField { path } => write!(f, "unknown field {}", path),
+            Self::NegativeAmount { path } => write!(f, "negative amount {}", path),
+            Self::UnknownOrder { index } => write!(f, "unknown order {}", index),
+            Self::UnknownAmm { index } => write!(f, "unknown amm {}", index),
+            Self::MissingPrice { token } => write!(f, "missing price for token {:?}", token),
+            Self::InconsistentPrices { order } => {
+                write!(f, "executed amounts of order {} violate the prices", order)
+            }
+        }
+    }
+}
+
+/// A solution that violates the schema in at least one way.
+#[derive(Debug, Error)]
+#[error("solution violates the schema: {}", display_list(.0))]
+pub struct SchemaViolations(pub Vec<SchemaViolation>);
+
+fn display_list(violations: &[SchemaViolation]) -> String {
+    violations
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Returns the negative amounts of a solution that hasn't been decoded yet because they make
+/// decoding fail.
+pub fn negative_amounts(raw: &Value) -> Vec<SchemaViolation> {
+    let mut violations = Vec::new();
+    find_negative_amounts(raw, &mut Vec::new(), &mut violations);
+    violations
+}
+
+fn find_negative_amounts(value: &Value, path: &mut Vec<String>, out: &mut Vec<SchemaViolation>) {
+    let negative = match value {
+        Value::Number(number) => number.as_f64().map_or(false, |number| number < 0.),
+        Value::String(string) => string
+            .strip_prefix('-')
+            .map_or(false, |digits| digits.parse::<f64>().is_ok()),
+        Value::Array(values) => {
+            for (i, value) in values.iter().enumerate() {
+                path.push(i.to_string());
+                find_negative_amounts(value, path, out);
+                path.pop();
+            }
+            false
+        }
+        Value::Object(values) => {
+            for (key, value) in values {
+                path.push(key.clone());
+                find_negative_amounts(value, path, out);
+                path.pop();
+            }
+            false
+        }
+        Value::Null | Value::Bool(_) => false,
+    };
+    if negative {
+        out.push(SchemaViolation::NegativeAmount {
+            path: path.join("."),
+        });
+    }
+}
+
+/// Returns all violations of a decoded solution. `raw` is the solution as it was received and is
+/// needed to find the fields that got dropped by decoding.
+pub fn validate(
+    instance: &BatchAuctionModel,
+    raw: &Value,
+    solution: &SettledBatchAuctionModel,
+) -> Vec<SchemaViolation> {
+    let mut violations = Vec::new();
+    // Encoding a model can't fail.
+    let decoded = serde_json::to_value(solution).unwrap();
+    find_unknown_fields(raw, &decoded, &mut Vec::new(), &mut violations);
+    find_inconsistencies(instance, solution, &mut violations);
+    violations
+}
+
+fn find_unknown_fields(
+    raw: &Value,
+    decoded: &Value,
+    path: &mut Vec<String>,
+    out: &mut Vec<SchemaViolation>,
+) {
+    match (raw, decoded) {
+        (Value::Object(raw), Value::Object(decoded)) => {
+            // Solvers include the full state of the AMMs they use which isn't needed.
+            let allows_unknown_fields = matches!(path.as_slice(), [amms, _] if amms == "amms");
+            for (key, value) in raw {
+                path.push(key.clone());
+                match field(decoded, key) {
+                    Some(decoded) => find_unknown_fields(value, decoded, path, out),
+                    None if allows_unknown_fields => (),
+                    None => out.push(SchemaViolation::UnknownField {
+                        path: path.join("."),
+                    }),
+                }
+                path.pop();
+            }
+        }
+        (Value::Array(raw), Value::Array(decoded)) => {
+            for (i, (raw, decoded)) in raw.iter().zip(decoded).enumerate() {
+                path.push(i.to_string());
+                find_unknown_fields(raw, decoded, path, out);
+                path.pop();
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Looks up a field of a decoded object. Map keys get normalized by decoding, for example
+/// checksummed token addresses come back in lower case.
+fn field<'a>(object: &'a Map<String, Value>, key: &str) -> Option<&'a Value> {
+    object.get(key).or_else(|| {
+        object
+            .iter()
+            .find(|(decoded, _)| decoded.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value)
+    })
+}
+
+fn find_inconsistencies(
+    instance: &BatchAuctionModel,
+    solution: &SettledBatchAuctionModel,
+    out: &mut Vec<SchemaViolation>,
+) {
+    let mut orders = solution.orders.iter().collect::<Vec<_>>();
+    orders.sort_by_key(|(index, _)| **index);
+    for (index, executed) in orders {
+        let order = match instance.orders.get(index) {
+            Some(order) => order,
+            None => {
+                out.push(SchemaViolation::UnknownOrder { index: *index });
+                continue;
+            }
+        };
+        let mut price = |token: &H160| {
+            let price = solution.prices.get(token).filter(|price| !price.is_zero());
+            if price.is_none() {
+                let violation = SchemaViolation::MissingPrice { token: *token };
+                if !out.contains(&violation) {
+                    out.push(violation);
+                }
+            }
+            price.copied()
+        };
+        let (sell_price, buy_price) = (price(&order.sell_token), price(&order.buy_token));
+        if let (Some(sell_price), Some(buy_price)) = (sell_price, buy_price) {
+            // The value an order pays at the clearing prices has to cover the value it receives.
+            if executed.exec_sell_amount.full_mul(sell_price)
+                < executed.exec_buy_amount.full_mul(buy_price)
+            {
+                out.push(SchemaViolation::InconsistentPrices { order: *index });
+            }
+        }
+    }
+
+    // AMMs the solver requested while solving aren't part of the instance. The caller has to check
+    // those against what got requested.
+    let requests_liquidity = instance
+        .metadata
+        .as_ref()
+        .map_or(false, |metadata| metadata.liquidity_url.is_some());
+    if requests_liquidity {
+        return;
+    }
+    let mut amms = solution.amms.keys().copied().collect::<Vec<_>>();
+    amms.sort_unstable();
+    out.extend(
+        amms.into_iter()
+            .filter(|index| !instance.amms.contains_key(index))
+            .map(|index| SchemaViolation::UnknownAmm { index }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_solver::model::{MetadataModel, OrderModel};
+    use maplit::btreemap;
+    use serde_json::json;
+
+    fn token(byte: u8) -> H160 {
+        H160([byte; 20])
+    }
+
+    fn instance() -> BatchAuctionModel {
+        BatchAuctionModel {
+            orders: btreemap! {
+                0 => OrderModel {
+                    sell_token: token(1),
+                    buy_token: token(0xaa),
+                    sell_amount: 100.into(),
+                    buy_amount: 100.into(),
+                    allow_partial_fill: false,
+                    is_sell_order: true,
+                    fee: Default::default(),
+                    cost: Default::default(),
+                    is_liquidity_order: false,
+                    quote_expiry_block: None,
+                    quote_last_look: false,
+                    min_partial_fill_amount: None,
+                    mandatory: false,
+                    has_atomic_execution: false,
+                },
+            },
+            ..Default::default()
+        }
+    }
+
+    fn check(raw: Value) -> Vec<SchemaViolation> {
+        let solution = serde_json::from_value(raw.clone()).unwrap();
+        validate(&instance(), &raw, &solution)
+    }
+
+    #[test]
+    fn valid_solution() {
+        let raw = json!({
+            "orders": {
+                "0": {
+                    "exec_sell_amount": "100",
+                    "exec_buy_amount": "200",
+                },
+            },
+            "amms": {},
+            "prices": {
+                // Checksummed addresses are fine.
+                "0x0101010101010101010101010101010101010101": "2",
+                "0xAaAAaaaAaAAAAAaaaaaAaAAAaaaAAAaAAAAaaaaa": "1",
+            },
+            "ref_token": null,
+            "metadata": null,
+        });
+        assert_eq!(check(raw), vec![]);
+    }
+
+    #[test]
+    fn finds_unknown_fields() {
+        let raw = json!({
+            "orders": {
+                "0": {
+                    "exec_sell_amount": "100",
+                    "exec_buy_amount": "100",
+                    "surplus": "1",
+                },
+            },
+            "amms": {
+                "0": {
+                    "kind": "ConstantProduct",
+                    "execution": [{
+                        "sell_token": token(1),
+                        "buy_token": token(0xaa),
+                        "exec_sell_amount": "1",
+                        "exec_buy_amount": "1",
+                        "exec_plan": { "sequence": 0, "position": 0 },
+                        "gas": 10000,
+                    }],
+                },
+            },
+            "prices": {
+                "0x0101010101010101010101010101010101010101": "1",
+                "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa": "1",
+            },
+            "score": 5,
+        });
+        assert_eq!(
+            check(raw),
+            vec![
+                SchemaViolation::UnknownField {
+                    path: "amms.0.execution.0.gas".to_string()
+                },
+                SchemaViolation::UnknownField {
+                    path: "orders.0.surplus".to_string()
+                },
+                SchemaViolation::UnknownField {
+                    path: "score".to_string()
+                },
+                SchemaViolation::UnknownAmm { index: 0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn allows_requested_amms() {
+        let raw = json!({
+            "orders": {},
+            "amms": {
+                "5": {
+                    "execution": [],
+                },
+            },
+            "prices": {},
+        });
+        let solution = serde_json::from_value(raw.clone()).unwrap();
+        let instance = BatchAuctionModel {
+            metadata: Some(MetadataModel {
+                liquidity_url: Some("http://localhost:9590/liquidity".parse().unwrap()),
+                ..Default::default()
+            }),
+            ..instance()
+        };
+        assert_eq!(validate(&instance, &raw, &solution), vec![]);
+    }
+
+    #[test]
+    fn finds_inconsistent_prices() {
+        let raw = json!({
+            "orders": {
+                "0": {
+                    "exec_sell_amount": "100",
+                    "exec_buy_amount": "101",
+                },
+                "1": {
+                    "exec_sell_amount": "1",
+                    "exec_buy_amount": "1",
+                },
+            },
+            "prices": {
+                "0x0101010101010101010101010101010101010101": "1",
+                "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa": "1",
+            },
+        });
+        assert_eq!(
+            check(raw),
+            vec![
+                SchemaViolation::InconsistentPrices { order: 0 },
+                SchemaViolation::UnknownOrder { index: 1 },
+            ]
+        );
+
+        let raw = json!({
+            "orders": {
+                "0": {
+                    "exec_sell_amount": "100",
+                    "exec_buy_amount": "100",
+                },
+            },
+            "prices": {
+                "0x0101010101010101010101010101010101010101": "0",
+            },
+        });
+        assert_eq!(
+            check(raw),
+            vec![
+                SchemaViolation::MissingPrice { token: token(1) },
+                SchemaViolation::MissingPrice { token: token(0xaa) },
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_negative_amounts() {
+        let raw = json!({
+            "orders": {
+                "0": {
+                    "exec_sell_amount": "-100",
+                    "exec_buy_amount": "100",
+                },
+            },
+            "interaction_data": [{
+                "inputs": [{ "token": token(1), "amount": -5 }],
+            }],
+            "prices": {
+                "0x0101010101010101010101010101010101010101": "1",
+            },
+        });
+        assert_eq!(
+            negative_amounts(&raw),
+            vec![
+                SchemaViolation::NegativeAmount {
+                    path: "interaction_data.0.inputs.0.amount".to_string()
+                },
+                SchemaViolation::NegativeAmount {
+                    path: "orders.0.exec_sell_amount".to_string()
+                },
+            ]
+        );
+    }
+}
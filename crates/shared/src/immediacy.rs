@@ -0,0 +1,200 @@
+//! Estimates the cost of immediacy of quotes: how much better the price of a limit order resting
+//! at the mid-price is than the quoted price and how long the order would rest until it fills.
+//! Wallets can use this to let users choose between trading right away and placing a limit order.
+//!
+//! Native prices serve as mid-prices since they are estimated from small trades in both tokens.
+//! A resting order fills once the market moved by the difference between the prices, which for a
+//! random walk with hourly volatility σ takes about (difference / σ)² hours.
+
+use crate::{
+    arguments::{display_option, duration_from_seconds},
+    price_estimation::native::{native_single_estimate, NativePriceEstimating},
+};
+use anyhow::Result;
+use model::{
+    order::OrderKind,
+    quote::{CostOfImmediacy, OrderQuote},
+};
+use primitive_types::U256;
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::Arc,
+    time::Duration,
+};
+
+/// Arguments related to the cost of immediacy of quotes.
+#[derive(clap::Parser)]
+pub struct Arguments {
+    /// Enables estimating the cost of immediacy of quotes, assuming that prices move with this
+    /// hourly volatility in basis points.
+    #[clap(long, env)]
+    pub immediacy_hourly_volatility_bps: Option<f64>,
+
+    /// Limit orders that are expected to rest longer than this many seconds get no estimate.
+    #[clap(
+        long,
+        env,
+        default_value = "604800",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub immediacy_max_fill_time: Duration,
+}
+
+impl Display for Arguments {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        display_option(
+            f,
+            "immediacy_hourly_volatility_bps",
+            &self.immediacy_hourly_volatility_bps,
+        )?;
+        writeln!(
+            f,
+            "immediacy_max_fill_time: {:?}",
+            self.immediacy_max_fill_time
+        )?;
+        Ok(())
+    }
+}
+
+pub struct ImmediacyEstimator {
+    native_price_estimator: Arc<dyn NativePriceEstimating>,
+    hourly_volatility_bps: f64,
+    max_fill_time: Duration,
+}
+
+impl ImmediacyEstimator {
+    /// Returns `None` if estimating the cost of immediacy isn't enabled.
+    pub fn new(
+        native_price_estimator: Arc<dyn NativePriceEstimating>,
+        args: &Arguments,
+    ) -> Option<Self> {
+        let hourly_volatility_bps = args
+            .immediacy_hourly_volatility_bps
+            .filter(|volatility| *volatility > 0.)?;
+        Some(Self {
+            native_price_estimator,
+            hourly_volatility_bps,
+            max_fill_time: args.immediacy_max_fill_time,
+        })
+    }
+
+    pub async fn estimate(&self, quote: &OrderQuote) -> Result<Option<CostOfImmediacy>> {
+        let (sell_token_price, buy_token_price) = futures::try_join!(
+            native_single_estimate(self.native_price_estimator.as_ref(), &quote.sell_token),
+            native_single_estimate(self.native_price_estimator.as_ref(), &quote.buy_token),
+        )?;
+        Ok(cost_of_immediacy(
+            quote,
+            sell_token_price / buy_token_price,
+            self.hourly_volatility_bps,
+            self.max_fill_time,
+        ))
+    }
+}
+
+/// Computes the cost of immediacy of the quote given the mid-price as buy token units per sell
+/// token unit.
+fn cost_of_immediacy(
+    quote: &OrderQuote,
+    mid_price: f64,
+    hourly_volatility_bps: f64,
+    max_fill_time: Duration,
+) -> Option<CostOfImmediacy> {
+    let (sell_amount, buy_amount) = (
+        quote.sell_amount.to_f64_lossy(),
+        quote.buy_amount.to_f64_lossy(),
+    );
+    let (limit_amount, improvement) = match quote.kind {
+        OrderKind::Sell => {
+            let limit_amount = sell_amount * mid_price;
+            (limit_amount, limit_amount / buy_amount - 1.)
+        }
+        OrderKind::Buy => {
+            let limit_amount = buy_amount / mid_price;
+            (limit_amount, 1. - limit_amount / sell_amount)
+        }
+    };
+    let improvement_bps = improvement * 10_000.;
+    // Zero amounts or prices result in NaN or infinite values.
+    if improvement_bps.is_nan() || improvement_bps < 1. {
+        return None;
+    }
+    let fill_time = (improvement_bps / hourly_volatility_bps).powi(2) * 3600.;
+    if fill_time > max_fill_time.as_secs_f64() {
+        return None;
+    }
+    Some(CostOfImmediacy {
+        limit_amount: U256::from_f64_lossy(limit_amount),
+        improvement_bps: improvement_bps as u32,
+        expected_fill_time: fill_time as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(kind: OrderKind, sell_amount: u64, buy_amount: u64) -> OrderQuote {
+        OrderQuote {
+            sell_token: Default::default(),
+            buy_token: Default::default(),
+            receiver: None,
+            sell_amount: sell_amount.into(),
+            buy_amount: buy_amount.into(),
+            valid_to: 0,
+            app_data: Default::default(),
+            fee_amount: 0.into(),
+            kind,
+            partially_fillable: false,
+            sell_token_balance: Default::default(),
+            buy_token_balance: Default::default(),
+        }
+    }
+
+    #[test]
+    fn computes_cost_of_immediacy() {
+        let day = Duration::from_secs(86_400);
+        // The mid-price buys 25% more than the quote.
+        assert_eq!(
+            cost_of_immediacy(&quote(OrderKind::Sell, 1000, 1000), 1.25, 2500., day),
+            Some(CostOfImmediacy {
+                limit_amount: 1250.into(),
+                improvement_bps: 2500,
+                expected_fill_time: 3600,
+            })
+        );
+        // The mid-price sells 25% less than the quote.
+        assert_eq!(
+            cost_of_immediacy(&quote(OrderKind::Buy, 1000, 375), 0.5, 5000., day),
+            Some(CostOfImmediacy {
+                limit_amount: 750.into(),
+                improvement_bps: 2500,
+                expected_fill_time: 900,
+            })
+        );
+    }
+
+    #[test]
+    fn no_cost_of_immediacy() {
+        let day = Duration::from_secs(86_400);
+        // The quote is better than the mid-price.
+        assert_eq!(
+            cost_of_immediacy(&quote(OrderKind::Sell, 1000, 1010), 1., 50., day),
+            None
+        );
+        // Zero amounts.
+        assert_eq!(
+            cost_of_immediacy(&quote(OrderKind::Sell, 1000, 0), 1., 50., day),
+            None
+        );
+        assert_eq!(
+            cost_of_immediacy(&quote(OrderKind::Buy, 0, 1000), 1., 50., day),
+            None
+        );
+        // The limit order would take too long to fill.
+        assert_eq!(
+            cost_of_immediacy(&quote(OrderKind::Sell, 1000, 500), 1., 50., day),
+            None
+        );
+    }
+}
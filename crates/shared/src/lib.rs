@@ -7,32 +7,45 @@ pub mod arguments;
 pub mod bad_token;
 pub mod balancer_sor_api;
 pub mod baseline_solver;
+pub mod buffers;
 pub mod conversions;
 pub mod current_block;
 pub mod db_order_conversions;
+pub mod error_class;
 pub mod ethcontract_error;
 pub mod event_handling;
 pub mod fee_subsidy;
 pub mod gas_price;
 pub mod gas_price_estimation;
+pub mod hot_reload;
+pub mod immediacy;
 pub mod http_client;
 pub mod http_solver;
 pub mod maintenance;
 pub mod metrics;
 pub mod network;
 pub mod oneinch_api;
+pub mod order_conditions;
 pub mod order_quoting;
 pub mod order_validation;
 pub mod paraswap_api;
+pub mod preflight;
+pub mod presignature;
 pub mod price_estimation;
+pub mod protocol_fee;
 pub mod rate_limiter;
+pub mod realized_gas;
 pub mod recent_block_cache;
 pub mod remaining_amounts;
 pub mod request_sharing;
+pub mod revm_simulation;
 pub mod signature_validator;
 pub mod solver_utils;
 pub mod sources;
 pub mod subgraph;
+pub mod tenderly_api;
+pub mod threat_intel;
+pub mod token_amount;
 pub mod token_info;
 pub mod token_list;
 pub mod trace_many;
@@ -40,6 +53,7 @@ pub mod tracing;
 pub mod transport;
 pub mod univ3_router_api;
 pub mod web3_traits;
+pub mod wrapped_native;
 pub mod zeroex_api;
 
 use self::transport::http::HttpTransport;
@@ -1,3 +1,5 @@
+pub mod exemplars;
+
 use prometheus::Encoder;
 use std::{convert::Infallible, net::SocketAddr, sync::Arc};
 use tokio::task::{self, JoinHandle};
@@ -16,23 +18,45 @@ pub fn serve_metrics(liveness: Arc<dyn LivenessChecking>, address: SocketAddr) -
     task::spawn(warp::serve(filter).bind(address))
 }
 
-// `/metrics` route exposing encoded prometheus data to monitoring system
+// `/metrics` route exposing encoded prometheus data to monitoring system. Scrapers accepting the
+// OpenMetrics format also get the exemplars of histograms.
 pub fn handle_metrics() -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
     let registry = global_metrics::get_metrics_registry();
-    warp::path("metrics").map(move || {
-        let encoder = prometheus::TextEncoder::new();
-        let mut buffer = Vec::new();
-        if let Err(e) = encoder.encode(&registry.gather(), &mut buffer) {
-            tracing::error!("could not encode metrics: {}", e);
-        };
-        match String::from_utf8(buffer) {
-            Ok(v) => v,
-            Err(e) => {
-                tracing::error!("metrics could not be from_utf8'd: {}", e);
-                String::default()
+    warp::path("metrics")
+        .and(warp::header::optional::<String>("accept"))
+        .map(move |accept: Option<String>| {
+            if matches!(&accept, Some(accept) if accept.contains("application/openmetrics-text")) {
+                let encoded = exemplars::encode_openmetrics(
+                    &registry.gather(),
+                    global_metrics::get_metrics_prefix(),
+                );
+                return warp::reply::with_header(
+                    encoded,
+                    "content-type",
+                    exemplars::OPENMETRICS_CONTENT_TYPE,
+                );
             }
+            warp::reply::with_header(
+                encode_text(registry),
+                "content-type",
+                prometheus::TEXT_FORMAT,
+            )
+        })
+}
+
+fn encode_text(registry: &prometheus::Registry) -> String {
+    let encoder = prometheus::TextEncoder::new();
+    let mut buffer = Vec::new();
+    if let Err(e) = encoder.encode(&registry.gather(), &mut buffer) {
+        tracing::error!("could not encode metrics: {}", e);
+    };
+    match String::from_utf8(buffer) {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::error!("metrics could not be from_utf8'd: {}", e);
+            String::default()
         }
-    })
+    }
 }
 
 fn handle_liveness(
@@ -0,0 +1,359 @@
+//! Exemplars for histograms so that latency spikes in dashboards link to the auction causing them.
+//!
+//! The prometheus crate can't attach exemplars to observations, so the most recent exemplar of
+//! every histogram bucket is kept here and added to the buckets when the metrics get encoded in
+//! the OpenMetrics text format. Prometheus only scrapes exemplars when it negotiates that format
+//! and the plain text format stays the default for everyone else.
+
+use lazy_static::lazy_static;
+use prometheus::{
+    core::Collector,
+    proto::{LabelPair, Metric, MetricFamily, MetricType},
+    Histogram,
+};
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The exemplar label holding the id of the auction.
+pub const AUCTION_ID_LABEL: &str = "auction_id";
+
+pub const OPENMETRICS_CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+#[derive(Clone, Debug, PartialEq)]
+struct Exemplar {
+    auction_id: String,
+    value: f64,
+    /// Seconds since the unix epoch.
+    timestamp: f64,
+}
+
+#[derive(Debug, Default)]
+struct Exemplars {
+    /// The labels of a histogram and the upper bound of the bucket an exemplar belongs to.
+    buckets: Vec<(Vec<(String, String)>, f64, Exemplar)>,
+}
+
+impl Exemplars {
+    fn insert(&mut self, labels: Vec<(String, String)>, upper_bound: f64, exemplar: Exemplar) {
+        match self
+            .buckets
+            .iter_mut()
+            .find(|(labels_, upper_bound_, _)| *labels_ == labels && *upper_bound_ == upper_bound)
+        {
+            Some((_, _, existing)) => *existing = exemplar,
+            None => self.buckets.push((labels, upper_bound, exemplar)),
+        }
+    }
+
+    /// The exemplar of the bucket of a gathered metric. The gathered labels can contain the
+    /// constant labels of the registry in addition to the histogram's own.
+    fn get(&self, labels: &[LabelPair], upper_bound: f64) -> Option<&Exemplar> {
+        self.buckets
+            .iter()
+            .find(|(labels_, upper_bound_, _)| {
+                *upper_bound_ == upper_bound
+                    && labels_.iter().all(|(name, value)| {
+                        labels
+                            .iter()
+                            .any(|label| label.get_name() == name && label.get_value() == value)
+                    })
+            })
+            .map(|(_, _, exemplar)| exemplar)
+    }
+}
+
+lazy_static! {
+    /// Exemplars by metric name without the registry's prefix.
+    static ref EXEMPLARS: Mutex<HashMap<String, Exemplars>> = Default::default();
+}
+
+/// Observes the value and keeps the auction id as the exemplar of the bucket the value falls into.
+pub fn observe_with_exemplar(histogram: &Histogram, value: f64, auction_id: &str) {
+    histogram.observe(value);
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs_f64())
+        .unwrap_or_default();
+    let exemplar = Exemplar {
+        auction_id: auction_id.to_string(),
+        value,
+        timestamp,
+    };
+    for family in histogram.collect() {
+        for metric in family.get_metric() {
+            let upper_bound = metric
+                .get_histogram()
+                .get_bucket()
+                .iter()
+                .map(|bucket| bucket.get_upper_bound())
+                .find(|upper_bound| value <= *upper_bound)
+                .unwrap_or(f64::INFINITY);
+            EXEMPLARS
+                .lock()
+                .unwrap()
+                .entry(family.get_name().to_string())
+                .or_default()
+                .insert(labels(metric), upper_bound, exemplar.clone());
+        }
+    }
+}
+
+fn labels(metric: &Metric) -> Vec<(String, String)> {
+    let mut labels = metric
+        .get_label()
+        .iter()
+        .map(|label| (label.get_name().to_string(), label.get_value().to_string()))
+        .collect::<Vec<_>>();
+    labels.sort();
+    labels
+}
+
+/// Encodes the metric families in the OpenMetrics text format with the stored exemplars.
+/// `prefix` is the prefix the registry added to the names of the families.
+pub fn encode_openmetrics(families: &[MetricFamily], prefix: Option<&str>) -> String {
+    let exemplars = EXEMPLARS.lock().unwrap();
+    encode(families, |name| {
+        let name = match prefix {
+            Some(prefix) => name.strip_prefix(prefix)?.strip_prefix('_')?,
+            None => name,
+        };
+        exemplars.get(name)
+    })
+}
+
+fn encode<'a>(
+    families: &[MetricFamily],
+    exemplars: impl Fn(&str) -> Option<&'a Exemplars>,
+) -> String {
+    let mut out = String::new();
+    for family in families {
+        let name = family.get_name();
+        let (name, type_) = match family.get_field_type() {
+            MetricType::COUNTER => (name.strip_suffix("_total").unwrap_or(name), "counter"),
+            MetricType::GAUGE => (name, "gauge"),
+            MetricType::SUMMARY => (name, "summary"),
+            MetricType::HISTOGRAM => (name, "histogram"),
+            MetricType::UNTYPED => (name, "unknown"),
+        };
+        writeln!(out, "# TYPE {} {}", name, type_).unwrap();
+        if !family.get_help().is_empty() {
+            writeln!(out, "# HELP {} {}", name, escape(family.get_help())).unwrap();
+        }
+        let exemplars = exemplars(family.get_name());
+        for metric in family.get_metric() {
+            let labels = metric.get_label();
+            match family.get_field_type() {
+                MetricType::COUNTER => sample(
+                    &mut out,
+                    name,
+                    "_total",
+                    labels,
+                    None,
+                    metric.get_counter().get_value(),
+                ),
+                MetricType::GAUGE => sample(
+                    &mut out,
+                    name,
+                    "",
+                    labels,
+                    None,
+                    metric.get_gauge().get_value(),
+                ),
+                MetricType::UNTYPED => sample(
+                    &mut out,
+                    name,
+                    "",
+                    labels,
+                    None,
+                    metric.get_untyped().get_value(),
+                ),
+                MetricType::SUMMARY => {
+                    let summary = metric.get_summary();
+                    for quantile in summary.get_quantile() {
+                        let extra = ("quantile", number(quantile.get_quantile()));
+                        sample(
+                            &mut out,
+                            name,
+                            "",
+                            labels,
+                            Some(extra),
+                            quantile.get_value(),
+                        );
+                    }
+                    sample(
+                        &mut out,
+                        name,
+                        "_sum",
+                        labels,
+                        None,
+                        summary.get_sample_sum(),
+                    );
+                    let count = summary.get_sample_count() as f64;
+                    sample(&mut out, name, "_count", labels, None, count);
+                }
+                MetricType::HISTOGRAM => {
+                    let histogram = metric.get_histogram();
+                    let mut buckets = histogram
+                        .get_bucket()
+                        .iter()
+                        .map(|bucket| (bucket.get_upper_bound(), bucket.get_cumulative_count()))
+                        .collect::<Vec<_>>();
+                    if !matches!(buckets.last(), Some((upper_bound, _)) if upper_bound.is_infinite())
+                    {
+                        buckets.push((f64::INFINITY, histogram.get_sample_count()));
+                    }
+                    for (upper_bound, count) in buckets {
+                        let extra = ("le", number(upper_bound));
+                        sample(&mut out, name, "_bucket", labels, Some(extra), count as f64);
+                        if let Some(exemplar) =
+                            exemplars.and_then(|exemplars| exemplars.get(labels, upper_bound))
+                        {
+                            // Replace the newline of the sample.
+                            out.pop();
+                            writeln!(
+                                out,
+                                " # {{{}=\"{}\"}} {} {}",
+                                AUCTION_ID_LABEL,
+                                escape(&exemplar.auction_id),
+                                number(exemplar.value),
+                                exemplar.timestamp
+                            )
+                            .unwrap();
+                        }
+                    }
+                    sample(
+                        &mut out,
+                        name,
+                        "_sum",
+                        labels,
+                        None,
+                        histogram.get_sample_sum(),
+                    );
+                    let count = histogram.get_sample_count() as f64;
+                    sample(&mut out, name, "_count", labels, None, count);
+                }
+            }
+        }
+    }
+    out.push_str("# EOF\n");
+    out
+}
+
+fn sample(
+    out: &mut String,
+    name: &str,
+    suffix: &str,
+    labels: &[LabelPair],
+    extra: Option<(&str, String)>,
+    value: f64,
+) {
+    out.push_str(name);
+    out.push_str(suffix);
+    let labels = labels
+        .iter()
+        .map(|label| (label.get_name(), escape(label.get_value())))
+        .chain(extra)
+        .map(|(name, value)| format!("{}=\"{}\"", name, value))
+        .collect::<Vec<_>>();
+    if !labels.is_empty() {
+        write!(out, "{{{}}}", labels.join(",")).unwrap();
+    }
+    writeln!(out, " {}", number(value)).unwrap();
+}
+
+fn number(value: f64) -> String {
+    if value == f64::INFINITY {
+        "+Inf".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else if value.is_nan() {
+        "NaN".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('\n', r"\n")
+        .replace('"', r#"\""#)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prometheus::{HistogramOpts, HistogramVec, IntCounter, Registry};
+
+    #[test]
+    fn encodes_histogram_with_exemplars() {
+        let registry = Registry::new_custom(Some("test".to_string()), None).unwrap();
+        let histogram = HistogramVec::new(
+            HistogramOpts::new("exemplar_latency_seconds", "Latency.").buckets(vec![1., 2.]),
+            &["solver"],
+        )
+        .unwrap();
+        registry.register(Box::new(histogram.clone())).unwrap();
+        let counter = IntCounter::new("exemplar_events", "Events.").unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        counter.inc();
+
+        let histogram = histogram.with_label_values(&["naive"]);
+        observe_with_exemplar(&histogram, 1.5, "1");
+        observe_with_exemplar(&histogram, 1.75, "2");
+        observe_with_exemplar(&histogram, 3., "3");
+
+        let encoded = encode_openmetrics(&registry.gather(), Some("test"));
+        let lines = encoded.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "# TYPE test_exemplar_events counter");
+        assert_eq!(lines[1], "# HELP test_exemplar_events Events.");
+        assert_eq!(lines[2], "test_exemplar_events_total 1");
+        assert_eq!(lines[3], "# TYPE test_exemplar_latency_seconds histogram");
+        assert_eq!(
+            lines[5],
+            r#"test_exemplar_latency_seconds_bucket{solver="naive",le="1"} 0"#
+        );
+        assert!(lines[6].starts_with(
+            r#"test_exemplar_latency_seconds_bucket{solver="naive",le="2"} 2 # {auction_id="2"} 1.75 "#
+        ));
+        assert!(lines[7].starts_with(
+            r#"test_exemplar_latency_seconds_bucket{solver="naive",le="+Inf"} 3 # {auction_id="3"} 3 "#
+        ));
+        assert_eq!(
+            lines[8],
+            r#"test_exemplar_latency_seconds_sum{solver="naive"} 6.25"#
+        );
+        assert_eq!(
+            lines[9],
+            r#"test_exemplar_latency_seconds_count{solver="naive"} 3"#
+        );
+        assert_eq!(lines[10], "# EOF");
+    }
+
+    #[test]
+    fn exemplars_match_gathered_labels() {
+        let label = |name: &str, value: &str| {
+            let mut label = LabelPair::new();
+            label.set_name(name.to_string());
+            label.set_value(value.to_string());
+            label
+        };
+        let exemplar = Exemplar {
+            auction_id: "1".to_string(),
+            value: 0.5,
+            timestamp: 0.,
+        };
+        let mut exemplars = Exemplars::default();
+        exemplars.insert(vec![("solver".into(), "a".into())], 1., exemplar.clone());
+
+        let gathered = [label("network", "mainnet"), label("solver", "a")];
+        assert_eq!(exemplars.get(&gathered, 1.), Some(&exemplar));
+        assert_eq!(exemplars.get(&gathered, 2.), None);
+        assert_eq!(exemplars.get(&[label("solver", "b")], 1.), None);
+    }
+}
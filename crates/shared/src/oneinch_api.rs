@@ -4,16 +4,20 @@
 //! <https://docs.1inch.io/docs/aggregation-protocol/api/swagger>
 //! Although there is no documentation about API v4.1, it exists and is identical to v4.0 except it
 //! uses EIP 1559 gas prices.
-use crate::solver_utils::Slippage;
+use crate::{
+    error_class::{ClassifyError, ErrorClass},
+    solver_utils::Slippage,
+};
 use anyhow::{ensure, Context, Result};
 use cached::{Cached, TimedCache};
 use ethcontract::{H160, U256};
 use model::u256_decimal;
-use reqwest::{Client, IntoUrl, Url};
+use reqwest::{Client, IntoUrl, StatusCode, Url};
 use serde::Deserialize;
 use std::fmt::{self, Display, Formatter};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use thiserror::Error;
 
 /// Parts to split a swap.
 ///
@@ -324,13 +328,24 @@ pub enum RestResponse<T> {
     Err(RestError),
 }
 
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Default)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Default, Error)]
 #[serde(rename_all = "camelCase")]
+#[error("{description}")]
 pub struct RestError {
     pub status_code: u32,
     pub description: String,
 }
 
+impl ClassifyError for RestError {
+    fn class(&self) -> ErrorClass {
+        u16::try_from(self.status_code)
+            .ok()
+            .and_then(|status| StatusCode::from_u16(status).ok())
+            .map(ErrorClass::from_status)
+            .unwrap_or(ErrorClass::Fatal)
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Swap {
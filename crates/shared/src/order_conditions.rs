@@ -0,0 +1,86 @@
+use crate::{transport::MAX_BATCH_SIZE, Web3};
+use anyhow::{Context, Result};
+use futures::FutureExt;
+use model::order::OrderCondition;
+use web3::types::{BlockId, Bytes, CallRequest};
+
+#[mockall::automock]
+#[async_trait::async_trait]
+pub trait ConditionEvaluating: Send + Sync {
+    /// Returns for every condition whether it holds at the specified block. Oracle conditions
+    /// whose call fails don't hold.
+    async fn evaluate(&self, conditions: Vec<OrderCondition>, block: u64) -> Result<Vec<bool>>;
+}
+
+/// Evaluates all oracle conditions with batched `eth_call`s at the block.
+pub struct Web3ConditionEvaluator {
+    web3: Web3,
+}
+
+impl Web3ConditionEvaluator {
+    pub fn new(web3: Web3) -> Self {
+        Self { web3 }
+    }
+
+    async fn oracle_outputs(
+        &self,
+        conditions: &[OrderCondition],
+        block: BlockId,
+    ) -> Result<Vec<Option<Vec<u8>>>> {
+        let mut outputs = Vec::with_capacity(conditions.len());
+        for chunk in conditions.chunks(MAX_BATCH_SIZE) {
+            let web3 = web3::Web3::new(web3::transports::Batch::new(self.web3.transport()));
+            let calls = chunk
+                .iter()
+                .map(|condition| match condition {
+                    OrderCondition::Oracle {
+                        target, calldata, ..
+                    } => {
+                        let request = CallRequest {
+                            to: Some(*target),
+                            data: Some(Bytes(calldata.clone())),
+                            ..Default::default()
+                        };
+                        Some(web3.eth().call(request, Some(block)))
+                    }
+                    OrderCondition::TimeWindow { .. } => None,
+                })
+                .collect::<Vec<_>>();
+            if calls.iter().any(Option::is_some) {
+                web3.transport()
+                    .submit_batch()
+                    .await
+                    .context("oracle condition batch failed")?;
+            }
+            outputs.extend(calls.into_iter().map(|call| {
+                let result = call?.now_or_never()?;
+                if let Err(err) = &result {
+                    tracing::debug!(?err, "oracle condition call failed");
+                }
+                result.ok().map(|bytes| bytes.0)
+            }));
+        }
+        Ok(outputs)
+    }
+}
+
+#[async_trait::async_trait]
+impl ConditionEvaluating for Web3ConditionEvaluator {
+    async fn evaluate(&self, conditions: Vec<OrderCondition>, block: u64) -> Result<Vec<bool>> {
+        let block = BlockId::Number(block.into());
+        let timestamp = self
+            .web3
+            .eth()
+            .block(block)
+            .await?
+            .context("unknown block")?
+            .timestamp
+            .as_u64();
+        let outputs = self.oracle_outputs(&conditions, block).await?;
+        Ok(conditions
+            .iter()
+            .zip(outputs)
+            .map(|(condition, output)| condition.holds(timestamp, output.as_deref()))
+            .collect())
+    }
+}
@@ -4,11 +4,17 @@ use super::price_estimation::{
     single_estimate, PriceEstimating, PriceEstimationError,
 };
 use crate::{
+    bad_token::BadTokenDetecting,
     db_order_conversions::order_kind_from,
     fee_subsidy::{FeeParameters, FeeSubsidizing, Subsidy, SubsidyParameters},
+    immediacy::ImmediacyEstimator,
     order_validation::{OrderValidating, PartialValidationError, PreOrderData},
+    price_estimation::gas::SETTLEMENT_FIXED,
+    protocol_fee::ProtocolFee,
+    threat_intel::ThreatIntel,
+    token_amount::TokenAmount,
 };
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use chrono::{DateTime, Duration, TimeZone as _, Utc};
 use database::quotes::{Quote as QuoteRow, QuoteKind};
 use ethcontract::{H160, U256};
@@ -16,14 +22,18 @@ use futures::TryFutureExt as _;
 use gas_estimation::GasPriceEstimating;
 use model::{
     app_id::AppId,
-    order::OrderKind,
+    order::{OrderClass, OrderKind},
     quote::{
-        OrderQuote, OrderQuoteRequest, OrderQuoteResponse, OrderQuoteSide, PriceQuality, QuoteId,
-        QuoteSigningScheme, SellAmount,
+        BasketLeg, BasketQuoteRequest, BasketQuoteResponse, InternalCow, OrderQuote,
+        OrderQuoteRequest, OrderQuoteResponse, OrderQuoteSide, PriceQuality, QuoteId,
+        QuoteRefreshRequest, QuoteRefreshResponse, QuoteSigningScheme, SellAmount,
     },
 };
 use number_conversions::big_decimal_to_u256;
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 use thiserror::Error;
 
 /// A high-level interface for handling API quote requests.
@@ -31,6 +41,11 @@ pub struct QuoteHandler {
     order_validator: Arc<dyn OrderValidating>,
     optimal_quoter: Arc<dyn OrderQuoting>,
     fast_quoter: Arc<dyn OrderQuoting>,
+    threat_intel: Option<Arc<ThreatIntel>>,
+    bad_token_detector: Option<Arc<dyn BadTokenDetecting>>,
+    protocol_fee: ProtocolFee,
+    immediacy_estimator: Option<Arc<ImmediacyEstimator>>,
+    fee_guarantee_window: Option<Duration>,
 }
 
 impl QuoteHandler {
@@ -39,6 +54,11 @@ impl QuoteHandler {
             order_validator,
             optimal_quoter: quoter.clone(),
             fast_quoter: quoter,
+            threat_intel: None,
+            bad_token_detector: None,
+            protocol_fee: Default::default(),
+            immediacy_estimator: None,
+            fee_guarantee_window: None,
         }
     }
 
@@ -46,6 +66,44 @@ impl QuoteHandler {
         self.fast_quoter = fast_quoter;
         self
     }
+
+    /// Flags quotes for orders matching the threat intelligence lists.
+    pub fn with_threat_intel(mut self, threat_intel: Arc<ThreatIntel>) -> Self {
+        self.threat_intel = Some(threat_intel);
+        self
+    }
+
+    /// Adjusts the buy amounts of quotes for buy tokens that take a fee on transfer so that they
+    /// reflect what the receiver actually gets.
+    pub fn with_bad_token_detector(
+        mut self,
+        bad_token_detector: Arc<dyn BadTokenDetecting>,
+    ) -> Self {
+        self.bad_token_detector = Some(bad_token_detector);
+        self
+    }
+
+    /// Includes the protocol fee in the fee amounts of quotes.
+    pub fn with_protocol_fee(mut self, protocol_fee: ProtocolFee) -> Self {
+        self.protocol_fee = protocol_fee;
+        self
+    }
+
+    /// Includes the cost of immediacy in quote responses.
+    pub fn with_immediacy_estimator(
+        mut self,
+        immediacy_estimator: Arc<ImmediacyEstimator>,
+    ) -> Self {
+        self.immediacy_estimator = Some(immediacy_estimator);
+        self
+    }
+
+    /// Tells users until when the fees of stored quotes are guaranteed. Has to match the window
+    /// of the `FeeGuarantee` of the optimal quoter.
+    pub fn with_fee_guarantee_window(mut self, window: Duration) -> Self {
+        self.fee_guarantee_window = Some(window);
+        self
+    }
 }
 
 impl QuoteHandler {
@@ -57,37 +115,297 @@ impl QuoteHandler {
 
         let order = PreOrderData::from(request);
         let valid_to = order.valid_to;
+        let class = order.class;
+        let threat_warnings = self
+            .threat_intel
+            .as_ref()
+            .map(|threat_intel| threat_intel.warnings(&order))
+            .unwrap_or_default();
         self.order_validator.partial_validate(order).await?;
 
         let quoter = match request.price_quality {
             PriceQuality::Optimal => &self.optimal_quoter,
             PriceQuality::Fast => &self.fast_quoter,
         };
-        let quote = quoter.calculate_quote(request.into()).await?;
+        let transfer_fee_bps = self
+            .transfer_fee_bps(request.buy_token)
+            .await
+            .map_err(CalculateQuoteError::Other)?;
+        let mut parameters = QuoteParameters::from(request);
+        if let OrderQuoteSide::Buy {
+            buy_amount_after_fee,
+        } = &mut parameters.side
+        {
+            // Buy enough that the receiver ends up with the requested amount after the fee.
+            *buy_amount_after_fee = amount_before_transfer_fee(
+                *buy_amount_after_fee,
+                transfer_fee_bps,
+            )
+            .ok_or_else(|| {
+                CalculateQuoteError::Other(anyhow!("buy amount plus transfer fee overflows"))
+            })?;
+        }
+        let mut quote = quoter.calculate_quote(parameters).await?;
+        if quote.data.kind == OrderKind::Sell {
+            quote.buy_amount = amount_after_transfer_fee(quote.buy_amount, transfer_fee_bps);
+        }
+        let sell_amount_includes_fee = matches!(
+            request.side,
+            OrderQuoteSide::Sell {
+                sell_amount: SellAmount::BeforeFee { .. }
+            }
+        );
+        let protocol_fee = self.protocol_fee.fee(class, &quote.sell());
+        add_protocol_fee(&mut quote, &protocol_fee, sell_amount_includes_fee)
+            .map_err(CalculateQuoteError::Other)?;
 
+        let order_quote = OrderQuote {
+            sell_token: request.sell_token,
+            buy_token: request.buy_token,
+            receiver: request.receiver,
+            sell_amount: quote.sell_amount,
+            buy_amount: quote.buy_amount,
+            valid_to,
+            app_data: request.app_data,
+            fee_amount: quote.fee_amount,
+            kind: quote.data.kind,
+            partially_fillable: request.partially_fillable,
+            sell_token_balance: request.sell_token_balance,
+            buy_token_balance: request.buy_token_balance,
+        };
+        // The estimate is informational so failing to compute it doesn't fail the quote.
+        let cost_of_immediacy = match &self.immediacy_estimator {
+            Some(estimator) => estimator
+                .estimate(&order_quote)
+                .await
+                .unwrap_or_else(|err| {
+                    tracing::debug!(?err, "failed to estimate cost of immediacy");
+                    None
+                }),
+            None => None,
+        };
         let response = OrderQuoteResponse {
-            quote: OrderQuote {
-                sell_token: request.sell_token,
-                buy_token: request.buy_token,
-                receiver: request.receiver,
-                sell_amount: quote.sell_amount,
-                buy_amount: quote.buy_amount,
-                valid_to,
-                app_data: request.app_data,
-                fee_amount: quote.fee_amount,
-                kind: quote.data.kind,
-                partially_fillable: request.partially_fillable,
-                sell_token_balance: request.sell_token_balance,
-                buy_token_balance: request.buy_token_balance,
-            },
+            quote: order_quote,
             from: request.from,
             expiration: quote.data.expiration,
+            guaranteed_until: self
+                .fee_guarantee_window
+                .filter(|_| quote.id.is_some())
+                .map(|window| quote.data.expiration + window),
             id: quote.id,
+            threat_warnings,
+            cost_of_immediacy,
         };
 
         tracing::debug!(?response, "finished computing quote");
         Ok(response)
     }
+
+    /// Refreshes a stored quote so that slow traders don't have to request a completely new
+    /// quote when theirs expires.
+    pub async fn refresh_quote(
+        &self,
+        id: QuoteId,
+        request: &QuoteRefreshRequest,
+    ) -> Result<QuoteRefreshResponse, RefreshQuoteError> {
+        let subsidy = SubsidyParameters {
+            from: request.from,
+            app_data: request.app_data,
+        };
+        let RefreshedQuote {
+            mut quote,
+            extended,
+        } = self.optimal_quoter.refresh_quote(id, subsidy).await?;
+        // Refreshed quotes are for market orders whose sell amount excludes the fee.
+        let protocol_fee = self.protocol_fee.fee(OrderClass::Market, &quote.sell());
+        add_protocol_fee(&mut quote, &protocol_fee, false)?;
+        let buy_amount = match quote.data.kind {
+            OrderKind::Sell => amount_after_transfer_fee(
+                quote.buy_amount,
+                self.transfer_fee_bps(quote.data.buy_token).await?,
+            ),
+            OrderKind::Buy => quote.buy_amount,
+        };
+
+        let response = QuoteRefreshResponse {
+            id: quote
+                .id
+                .ok_or_else(|| anyhow!("refreshed quote was not stored"))?,
+            extended,
+            sell_amount: quote.sell_amount,
+            buy_amount,
+            fee_amount: quote.fee_amount,
+            kind: quote.data.kind,
+            expiration: quote.data.expiration,
+        };
+        tracing::debug!(?response, "finished refreshing quote");
+        Ok(response)
+    }
+
+    async fn transfer_fee_bps(&self, token: H160) -> Result<u32> {
+        Ok(match &self.bad_token_detector {
+            Some(detector) => detector.detect(token).await?.transfer_fee_bps(),
+            None => 0,
+        })
+    }
+}
+
+/// The amount that needs to be transferred so that the receiver gets `amount` after a transfer fee
+/// of `fee_bps` basis points.
+fn amount_before_transfer_fee(amount: U256, fee_bps: u32) -> Option<U256> {
+    let remaining_bps = 10_000u32.checked_sub(fee_bps).filter(|bps| *bps > 0)?;
+    let amount = amount.checked_mul(10_000.into())?;
+    Some(amount.checked_add((remaining_bps - 1).into())? / remaining_bps)
+}
+
+/// The amount the receiver gets when `amount` is transferred with a transfer fee of `fee_bps`
+/// basis points.
+fn amount_after_transfer_fee(amount: U256, fee_bps: u32) -> U256 {
+    let remaining_bps = 10_000u32.saturating_sub(fee_bps);
+    (amount.full_mul(remaining_bps.into()) / 10_000)
+        .try_into()
+        .expect("amount can only get smaller")
+}
+
+/// Adds the protocol fee to the fee of the quote. If the quoted sell amount already includes the
+/// fee, the protocol fee gets taken from the sell amount and the buy amount shrinks accordingly.
+fn add_protocol_fee(
+    quote: &mut Quote,
+    protocol_fee: &TokenAmount,
+    sell_amount_includes_fee: bool,
+) -> Result<()> {
+    if protocol_fee.is_zero() {
+        return Ok(());
+    }
+    let fee = quote.fee().saturating_add(protocol_fee)?;
+    if sell_amount_includes_fee {
+        let sell_amount = quote.sell_amount.saturating_sub(protocol_fee.amount);
+        quote.buy_amount = if quote.sell_amount.is_zero() {
+            U256::zero()
+        } else {
+            (quote.buy_amount.full_mul(sell_amount) / quote.sell_amount)
+                .try_into()
+                .expect("amount can only get smaller")
+        };
+        quote.sell_amount = sell_amount;
+    }
+    quote.fee_amount = fee.amount;
+    Ok(())
+}
+
+/// The maximum number of legs a basket quote can have.
+pub const MAX_BASKET_LEGS: usize = 20;
+
+impl QuoteHandler {
+    /// Quotes all legs of a basket and reports the amounts of the quote token that could be
+    /// traded internally between the legs.
+    ///
+    /// Identical legs only get quoted once. All other legs get quoted concurrently so that the
+    /// lookups they have in common (gas price, native price of the quote token, ...) are shared
+    /// by the caches and request sharing of the underlying estimators.
+    pub async fn calculate_basket_quote(
+        &self,
+        request: &BasketQuoteRequest,
+    ) -> Result<BasketQuoteResponse, BasketQuoteError> {
+        if request.legs.is_empty() || request.legs.len() > MAX_BASKET_LEGS {
+            return Err(BasketQuoteError::InvalidLegCount);
+        }
+
+        // The distinct leg requests together with the index of the first leg requesting them.
+        let mut unique = Vec::<(usize, OrderQuoteRequest)>::new();
+        let indices = request
+            .legs
+            .iter()
+            .enumerate()
+            .map(|(leg, parameters)| {
+                let leg_request = request.leg_request(parameters);
+                match unique.iter().position(|(_, other)| *other == leg_request) {
+                    Some(index) => index,
+                    None => {
+                        unique.push((leg, leg_request));
+                        unique.len() - 1
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let quotes = futures::future::join_all(
+            unique
+                .iter()
+                .map(|(_, leg_request)| self.calculate_quote(leg_request)),
+        )
+        .await
+        .into_iter()
+        .zip(&unique)
+        .map(|(result, (leg, _))| result.map_err(|err| BasketQuoteError::Leg { leg: *leg, err }))
+        .collect::<Result<Vec<_>, _>>()?;
+
+        let legs = indices
+            .into_iter()
+            .map(|index| quotes[index].clone())
+            .collect::<Vec<_>>();
+        let internal_cows = internal_cows(&request.legs, &legs);
+        Ok(BasketQuoteResponse {
+            legs,
+            internal_cows,
+        })
+    }
+}
+
+/// Matches the quote token that sell legs receive with the quote token that buy legs spend in the
+/// order of the legs.
+fn internal_cows(legs: &[BasketLeg], quotes: &[OrderQuoteResponse]) -> Vec<InternalCow> {
+    let flows = |kind: OrderKind| {
+        legs.iter()
+            .zip(quotes)
+            .enumerate()
+            .filter(move |(_, (leg, _))| leg.kind == kind)
+            .map(move |(index, (_, response))| {
+                let amount = match kind {
+                    OrderKind::Sell => response.quote.buy_amount,
+                    OrderKind::Buy => response
+                        .quote
+                        .sell_amount
+                        .saturating_add(response.quote.fee_amount),
+                };
+                (index, amount)
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut proceeds = flows(OrderKind::Sell);
+    let mut costs = flows(OrderKind::Buy);
+
+    let mut cows = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < proceeds.len() && j < costs.len() {
+        let amount = proceeds[i].1.min(costs[j].1);
+        if !amount.is_zero() {
+            cows.push(InternalCow {
+                sell_leg: proceeds[i].0,
+                buy_leg: costs[j].0,
+                amount,
+            });
+        }
+        proceeds[i].1 -= amount;
+        costs[j].1 -= amount;
+        if proceeds[i].1.is_zero() {
+            i += 1;
+        }
+        if costs[j].1.is_zero() {
+            j += 1;
+        }
+    }
+    cows
+}
+
+/// Result from handling a basket quote request.
+#[derive(Debug, Error)]
+pub enum BasketQuoteError {
+    #[error("basket must have between 1 and {} legs", MAX_BASKET_LEGS)]
+    InvalidLegCount,
+
+    #[error("error quoting leg {leg}: {err}")]
+    Leg { leg: usize, err: OrderQuoteError },
 }
 
 /// Result from handling a quote request.
@@ -160,6 +478,12 @@ pub struct Quote {
     /// The final minimum subsidized fee amount for any order created for this
     /// quote.
     pub fee_amount: U256,
+    /// The subsidy that was applied to compute the fee amount.
+    pub subsidy: Subsidy,
+    /// What honoring the fee guarantee of the expired quote costs, denominated in the network's
+    /// native token. It only counts against the budget once an order for the quote passed
+    /// validation.
+    pub fee_guarantee_cost: Option<f64>,
 }
 
 impl Quote {
@@ -170,13 +494,26 @@ impl Quote {
             sell_amount: data.quoted_sell_amount,
             buy_amount: data.quoted_buy_amount,
             fee_amount: data.fee_parameters.unsubsidized(),
+            subsidy: Default::default(),
+            fee_guarantee_cost: None,
             data,
         }
     }
 
+    /// The final sell amount of the quote.
+    pub fn sell(&self) -> TokenAmount {
+        TokenAmount::new(self.data.sell_token, self.sell_amount)
+    }
+
+    /// The fee of the quote, which is denominated in the sell token.
+    pub fn fee(&self) -> TokenAmount {
+        TokenAmount::new(self.data.sell_token, self.fee_amount)
+    }
+
     /// Applies a subsidy to the quote.
     pub fn with_subsidy(mut self, subsidy: &Subsidy) -> Self {
         self.fee_amount = self.data.fee_parameters.subsidized(subsidy);
+        self.subsidy = subsidy.clone();
         self
     }
 
@@ -285,6 +622,27 @@ pub trait OrderQuoting: Send + Sync {
         id: Option<QuoteId>,
         parameters: QuoteSearchParameters,
     ) -> Result<Quote, FindQuoteError>;
+
+    /// Revalidates a stored quote against the current exchange rate and gas price. Extends the
+    /// validity of the quote if it is still at least as good, and stores a new quote otherwise.
+    async fn refresh_quote(
+        &self,
+        id: QuoteId,
+        subsidy: SubsidyParameters,
+    ) -> Result<RefreshedQuote, RefreshQuoteError>;
+
+    /// Charges the cost of honoring the fee guarantee of a found quote to the daily budget.
+    /// Returns false if the budget can't cover it anymore. Only called once the order for the
+    /// quote passed validation.
+    async fn claim_fee_guarantee(&self, quote: &Quote) -> Result<bool>;
+}
+
+/// A quote that got revalidated.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RefreshedQuote {
+    pub quote: Quote,
+    /// Whether the validity of the stored quote got extended instead of storing a new quote.
+    pub extended: bool,
 }
 
 #[derive(Error, Debug)]
@@ -299,6 +657,18 @@ pub enum CalculateQuoteError {
     Other(#[from] anyhow::Error),
 }
 
+#[derive(Error, Debug)]
+pub enum RefreshQuoteError {
+    #[error("quote not found")]
+    NotFound(QuoteId),
+
+    #[error("failed to estimate price")]
+    Price(#[from] PriceEstimationError),
+
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
 #[derive(Error, Debug)]
 pub enum FindQuoteError {
     #[error("quote not found")]
@@ -331,7 +701,7 @@ pub struct QuoteSearchParameters {
 impl QuoteSearchParameters {
     /// Returns true if the search parameter instance matches the specified
     /// quote data.
-    fn matches(&self, data: &QuoteData) -> bool {
+    pub fn matches(&self, data: &QuoteData) -> bool {
         let amounts_match = match self.kind {
             OrderKind::Buy => self.buy_amount == data.quoted_buy_amount,
             OrderKind::Sell => {
@@ -364,6 +734,9 @@ pub trait QuoteStoring: Send + Sync {
         parameters: QuoteSearchParameters,
         expiration: DateTime<Utc>,
     ) -> Result<Option<(QuoteId, QuoteData)>>;
+
+    /// Updates the expiration of an existing quote. Returns false if the quote doesn't exist.
+    async fn extend(&self, id: QuoteId, expiration: DateTime<Utc>) -> Result<bool>;
 }
 
 /// A quote storing strategy that always forgets quotes.
@@ -389,6 +762,50 @@ impl QuoteStoring for Forget {
     ) -> Result<Option<(QuoteId, QuoteData)>> {
         Ok(None)
     }
+
+    async fn extend(&self, _: QuoteId, _: DateTime<Utc>) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+/// Keeps track of what honoring fee guarantees cost.
+#[cfg_attr(test, mockall::automock)]
+#[async_trait::async_trait]
+pub trait FeeGuaranteeStoring: Send + Sync {
+    /// Records the cost of honoring the guarantee of a quote, denominated in the network's native
+    /// token, if it fits in the budget together with the costs recorded since `since`. Returns
+    /// whether it fit. The check and the record are atomic so that concurrent placements can't
+    /// overspend the budget. Honoring the same quote again replaces the earlier record so that
+    /// retried order placements aren't counted twice.
+    async fn record_fee_guarantee_cost(
+        &self,
+        id: QuoteId,
+        cost: f64,
+        since: DateTime<Utc>,
+        budget: f64,
+        time: DateTime<Utc>,
+    ) -> Result<bool>;
+}
+
+/// Honors the fee of stored quotes for a while after they expired so that users who take a while
+/// to sign and submit their order don't lose the quote.
+///
+/// Honoring an expired quote costs the increase of the gas price since the quote was computed,
+/// which has to fit in a daily budget.
+pub struct FeeGuarantee {
+    /// How long after their expiration quotes are honored.
+    pub window: Duration,
+    /// How much honoring quotes may cost over the last 24 hours, denominated in the network's
+    /// native token.
+    pub daily_budget: f64,
+    pub storage: Arc<dyn FeeGuaranteeStoring>,
+}
+
+impl FeeGuarantee {
+    /// Until when the fee of a quote with the expiration is honored.
+    pub fn guaranteed_until(&self, expiration: DateTime<Utc>) -> DateTime<Utc> {
+        expiration + self.window
+    }
 }
 
 #[cfg_attr(test, mockall::automock)]
@@ -411,6 +828,120 @@ impl Now for DateTime<Utc> {
     }
 }
 
+/// Provides how many orders settlements of a token pair recently batched.
+#[cfg_attr(test, mockall::automock)]
+#[async_trait::async_trait]
+pub trait BatchSizeStoring: Send + Sync {
+    /// The average number of user orders in the settlements since the time that traded the token
+    /// pair in either direction. `None` if there were no such settlements.
+    async fn average_batch_size(
+        &self,
+        token_a: H160,
+        token_b: H160,
+        since: DateTime<Utc>,
+    ) -> Result<Option<f64>>;
+}
+
+/// How long the batch size of a token pair is reused for quotes before it gets fetched again.
+const BATCH_SIZE_CACHE_DURATION_SECONDS: i64 = 60;
+
+/// Shares the fixed overhead of a settlement between the orders that are expected to be batched
+/// together instead of charging every order all of it.
+///
+/// The expected batch size is the average size of the recent settlements of the token pair reduced
+/// by a safety margin, so that fees still cover the overhead when fewer orders than usual get
+/// batched.
+pub struct OverheadSharing {
+    lookback: Duration,
+    safety_margin: f64,
+    max_batch_size: f64,
+    storage: Arc<dyn BatchSizeStoring>,
+    batch_sizes: Mutex<HashMap<(H160, H160), (DateTime<Utc>, Option<f64>)>>,
+}
+
+impl OverheadSharing {
+    /// `lookback` is how far back settlements count, `safety_margin` the fraction the average
+    /// batch size gets reduced by and `max_batch_size` the most orders the overhead gets shared
+    /// between.
+    pub fn new(
+        lookback: Duration,
+        safety_margin: f64,
+        max_batch_size: f64,
+        storage: Arc<dyn BatchSizeStoring>,
+    ) -> Self {
+        Self {
+            lookback,
+            safety_margin,
+            max_batch_size,
+            storage,
+            batch_sizes: Default::default(),
+        }
+    }
+
+    /// The gas amount fees of a trade of the pair that was estimated to use `gas` get computed
+    /// with. Charges the full overhead if the batch size can't be fetched.
+    async fn gas_amount(
+        &self,
+        sell_token: H160,
+        buy_token: H160,
+        gas: u64,
+        now: DateTime<Utc>,
+    ) -> f64 {
+        let average = match self.average_batch_size(sell_token, buy_token, now).await {
+            Ok(average) => average,
+            Err(err) => {
+                tracing::warn!(?err, "failed to fetch batch size");
+                None
+            }
+        };
+        shared_gas_amount(gas, self.batch_size(average))
+    }
+
+    async fn average_batch_size(
+        &self,
+        sell_token: H160,
+        buy_token: H160,
+        now: DateTime<Utc>,
+    ) -> Result<Option<f64>> {
+        let pair = if sell_token < buy_token {
+            (sell_token, buy_token)
+        } else {
+            (buy_token, sell_token)
+        };
+        let is_fresh = |fetched: DateTime<Utc>| {
+            now - fetched < Duration::seconds(BATCH_SIZE_CACHE_DURATION_SECONDS)
+        };
+        if let Some((fetched, average)) = self.batch_sizes.lock().unwrap().get(&pair) {
+            if is_fresh(*fetched) {
+                return Ok(*average);
+            }
+        }
+
+        let average = self
+            .storage
+            .average_batch_size(pair.0, pair.1, now - self.lookback)
+            .await?;
+        let mut batch_sizes = self.batch_sizes.lock().unwrap();
+        batch_sizes.retain(|_, (fetched, _)| is_fresh(*fetched));
+        batch_sizes.insert(pair, (now, average));
+        Ok(average)
+    }
+
+    /// How many orders the overhead gets shared between. Pairs without recent settlements are
+    /// charged the full overhead.
+    fn batch_size(&self, average: Option<f64>) -> f64 {
+        let expected = average.unwrap_or(1.) * (1. - self.safety_margin);
+        expected.max(1.).min(self.max_batch_size.max(1.))
+    }
+}
+
+/// Removes the share of the fixed settlement overhead the other orders of the batch pay from the
+/// estimated gas of a trade.
+fn shared_gas_amount(gas: u64, batch_size: f64) -> f64 {
+    let others_share = SETTLEMENT_FIXED as f64 * (1. - 1. / batch_size);
+    (gas as f64 - others_share).max(0.)
+}
+
 /// Standard validity for a quote: Quotes are stored only as long as they are valid.
 const STANDARD_QUOTE_VALIDITY_SECONDS: i64 = 60;
 
@@ -424,6 +955,8 @@ pub struct OrderQuoter {
     now: Arc<dyn Now>,
     eip1271_onchain_quote_validity_seconds: Duration,
     presign_onchain_quote_validity_seconds: Duration,
+    fee_guarantee: Option<FeeGuarantee>,
+    overhead_sharing: Option<Arc<OverheadSharing>>,
 }
 
 impl OrderQuoter {
@@ -445,23 +978,73 @@ impl OrderQuoter {
             now: Arc::new(Utc::now),
             eip1271_onchain_quote_validity_seconds,
             presign_onchain_quote_validity_seconds,
+            fee_guarantee: None,
+            overhead_sharing: None,
+        }
+    }
+
+    /// Honors the fee of quotes referenced by id for a while after they expired.
+    pub fn with_fee_guarantee(mut self, fee_guarantee: FeeGuarantee) -> Self {
+        self.fee_guarantee = Some(fee_guarantee);
+        self
+    }
+
+    /// Shares the fixed settlement overhead between the orders expected to be batched together.
+    pub fn with_overhead_sharing(mut self, overhead_sharing: Arc<OverheadSharing>) -> Self {
+        self.overhead_sharing = Some(overhead_sharing);
+        self
+    }
+
+    /// The gas amount the fee of a trade of the pair that was estimated to use `gas` gets computed
+    /// with.
+    async fn fee_gas_amount(&self, sell_token: H160, buy_token: H160, gas: u64) -> f64 {
+        match &self.overhead_sharing {
+            Some(sharing) => {
+                sharing
+                    .gas_amount(sell_token, buy_token, gas, self.now.now())
+                    .await
+            }
+            None => gas as f64,
+        }
+    }
+
+    /// What honoring the fee guarantee of the expired quote costs in the network's native token,
+    /// or `None` if its fee isn't guaranteed anymore.
+    async fn fee_guarantee_cost(
+        &self,
+        data: &QuoteData,
+        now: DateTime<Utc>,
+    ) -> Result<Option<f64>> {
+        let guaranteed = matches!(
+            &self.fee_guarantee,
+            Some(guarantee) if now <= guarantee.guaranteed_until(data.expiration)
+        );
+        if !guaranteed {
+            return Ok(None);
         }
+
+        // Gas prices are denominated in wei.
+        let gas_price = self.gas_estimator.estimate().await?.effective_gas_price();
+        let cost = data.fee_parameters.gas_amount
+            * (gas_price - data.fee_parameters.gas_price).max(0.)
+            / 1e18;
+        Ok(Some(cost))
+    }
+
+    /// The expiration of a quote of the specified kind computed now.
+    fn expiration(&self, quote_kind: &QuoteKind) -> DateTime<Utc> {
+        let validity = match quote_kind {
+            QuoteKind::Eip1271OnchainOrder => self.eip1271_onchain_quote_validity_seconds,
+            QuoteKind::PreSignOnchainOrder => self.presign_onchain_quote_validity_seconds,
+            QuoteKind::Standard => chrono::Duration::seconds(STANDARD_QUOTE_VALIDITY_SECONDS),
+        };
+        self.now.now() + validity
     }
 
     async fn compute_quote_data(
         &self,
         parameters: &QuoteParameters,
     ) -> Result<QuoteData, CalculateQuoteError> {
-        let expiration = match parameters.signing_scheme {
-            QuoteSigningScheme::Eip1271 {
-                onchain_order: true,
-            } => self.now.now() + self.eip1271_onchain_quote_validity_seconds,
-            QuoteSigningScheme::PreSign {
-                onchain_order: true,
-            } => self.now.now() + self.presign_onchain_quote_validity_seconds,
-            _ => self.now.now() + chrono::Duration::seconds(STANDARD_QUOTE_VALIDITY_SECONDS),
-        };
-
         let trade_query = parameters.to_price_query();
         let (gas_estimate, trade_estimate, sell_token_price, _) = futures::try_join!(
             self.gas_estimator
@@ -487,7 +1070,13 @@ impl OrderQuoter {
             } => (trade_estimate.out_amount, *buy_amount),
         };
         let fee_parameters = FeeParameters {
-            gas_amount: trade_estimate.gas as _,
+            gas_amount: self
+                .fee_gas_amount(
+                    parameters.sell_token,
+                    parameters.buy_token,
+                    trade_estimate.gas,
+                )
+                .await,
             gas_price: gas_estimate.effective_gas_price(),
             sell_token_price,
         };
@@ -509,7 +1098,7 @@ impl OrderQuoter {
             quoted_buy_amount,
             fee_parameters,
             kind: trade_query.kind,
-            expiration,
+            expiration: self.expiration(&quote_kind),
             quote_kind,
         };
 
@@ -585,7 +1174,7 @@ impl OrderQuoting for OrderQuoter {
 
         let now = self.now.now();
         let quote = async {
-            let (id, data) = match id {
+            let (id, data, fee_guarantee_cost) = match id {
                 Some(id) => {
                     let data = self
                         .storage
@@ -596,19 +1185,33 @@ impl OrderQuoting for OrderQuoter {
                     if !parameters.matches(&data) {
                         return Err(FindQuoteError::ParameterMismatch(data));
                     }
-                    if data.expiration < now {
-                        return Err(FindQuoteError::Expired(data.expiration));
-                    }
-
-                    (id, data)
+                    let fee_guarantee_cost = if data.expiration < now {
+                        match self.fee_guarantee_cost(&data, now).await? {
+                            Some(cost) => {
+                                tracing::debug!(%id, %cost, "honoring fee guarantee of expired quote");
+                                (cost > 0.).then(|| cost)
+                            }
+                            None => return Err(FindQuoteError::Expired(data.expiration)),
+                        }
+                    } else {
+                        None
+                    };
+
+                    (id, data, fee_guarantee_cost)
+                }
+                None => {
+                    let (id, data) = self
+                        .storage
+                        .find(parameters, now)
+                        .await?
+                        .ok_or(FindQuoteError::NotFound(None))?;
+                    (id, data, None)
                 }
-                None => self
-                    .storage
-                    .find(parameters, now)
-                    .await?
-                    .ok_or(FindQuoteError::NotFound(None))?,
             };
-            Ok(Quote::new(Some(id), data))
+            Ok(Quote {
+                fee_guarantee_cost,
+                ..Quote::new(Some(id), data)
+            })
         };
 
         let (quote, subsidy) = futures::try_join!(
@@ -627,6 +1230,113 @@ impl OrderQuoting for OrderQuoter {
         tracing::debug!(?quote, ?subsidy, "found quote");
         Ok(quote)
     }
+
+    async fn refresh_quote(
+        &self,
+        id: QuoteId,
+        subsidy: SubsidyParameters,
+    ) -> Result<RefreshedQuote, RefreshQuoteError> {
+        let mut data = self
+            .storage
+            .get(id)
+            .await?
+            .ok_or(RefreshQuoteError::NotFound(id))?;
+
+        // The native price of the sell token gets estimated again because it prices the fee. The
+        // native price of the buy token was already required to exist when the quote was first
+        // computed.
+        let trade_query = price_estimation::Query {
+            sell_token: data.sell_token,
+            buy_token: data.buy_token,
+            in_amount: match data.kind {
+                OrderKind::Sell => data.quoted_sell_amount,
+                OrderKind::Buy => data.quoted_buy_amount,
+            },
+            kind: data.kind,
+        };
+        let (gas_estimate, trade_estimate, sell_token_price, subsidy) = futures::try_join!(
+            self.gas_estimator
+                .estimate()
+                .map_err(RefreshQuoteError::Other),
+            single_estimate(self.price_estimator.as_ref(), &trade_query)
+                .map_err(RefreshQuoteError::Price),
+            native_single_estimate(self.native_price_estimator.as_ref(), &data.sell_token)
+                .map_err(RefreshQuoteError::Price),
+            self.fee_subsidy
+                .subsidy(subsidy)
+                .map_err(RefreshQuoteError::Other),
+        )?;
+        let fee_parameters = FeeParameters {
+            gas_amount: self
+                .fee_gas_amount(data.sell_token, data.buy_token, trade_estimate.gas)
+                .await,
+            gas_price: gas_estimate.effective_gas_price(),
+            sell_token_price,
+        };
+
+        let price_holds = match data.kind {
+            OrderKind::Sell => trade_estimate.out_amount >= data.quoted_buy_amount,
+            OrderKind::Buy => trade_estimate.out_amount <= data.quoted_sell_amount,
+        };
+        let fee_holds = fee_parameters.unsubsidized() <= data.fee_parameters.unsubsidized();
+        let expiration = self.expiration(&data.quote_kind);
+
+        let refreshed = if price_holds && fee_holds {
+            if !self.storage.extend(id, expiration).await? {
+                // The quote expired and got cleaned up in the meantime.
+                return Err(RefreshQuoteError::NotFound(id));
+            }
+            data.expiration = expiration;
+            RefreshedQuote {
+                quote: Quote::new(Some(id), data).with_subsidy(&subsidy),
+                extended: true,
+            }
+        } else {
+            let (quoted_sell_amount, quoted_buy_amount) = match data.kind {
+                OrderKind::Sell => (data.quoted_sell_amount, trade_estimate.out_amount),
+                OrderKind::Buy => (trade_estimate.out_amount, data.quoted_buy_amount),
+            };
+            let data = QuoteData {
+                quoted_sell_amount,
+                quoted_buy_amount,
+                fee_parameters,
+                expiration,
+                ..data
+            };
+            let id = self.storage.save(data.clone()).await?;
+            RefreshedQuote {
+                quote: Quote::new(id, data).with_subsidy(&subsidy),
+                extended: false,
+            }
+        };
+
+        tracing::debug!(?refreshed, ?subsidy, "refreshed quote");
+        Ok(refreshed)
+    }
+
+    async fn claim_fee_guarantee(&self, quote: &Quote) -> Result<bool> {
+        let (guarantee, id, cost) = match (&self.fee_guarantee, quote.id, quote.fee_guarantee_cost)
+        {
+            (Some(guarantee), Some(id), Some(cost)) => (guarantee, id, cost),
+            _ => return Ok(true),
+        };
+
+        let now = self.now.now();
+        let claimed = guarantee
+            .storage
+            .record_fee_guarantee_cost(
+                id,
+                cost,
+                now - Duration::days(1),
+                guarantee.daily_budget,
+                now,
+            )
+            .await?;
+        if !claimed {
+            tracing::warn!(%id, %cost, "fee guarantee budget exhausted");
+        }
+        Ok(claimed)
+    }
 }
 
 impl From<&OrderQuoteRequest> for PreOrderData {
@@ -642,7 +1352,12 @@ impl From<&OrderQuoteRequest> for PreOrderData {
             buy_token_balance: quote_request.buy_token_balance,
             sell_token_balance: quote_request.sell_token_balance,
             signing_scheme: quote_request.signing_scheme.into(),
-            is_liquidity_order: quote_request.partially_fillable,
+            // Only liquidity orders can be partially fillable.
+            class: if quote_request.partially_fillable {
+                OrderClass::Liquidity
+            } else {
+                OrderClass::Market
+            },
         }
     }
 }
@@ -665,7 +1380,9 @@ mod tests {
     use super::*;
     use crate::fee_subsidy::Subsidy;
     use crate::{
+        bad_token::{MockBadTokenDetecting, TokenQuality},
         gas_price_estimation::FakeGasPriceEstimator,
+        order_validation::MockOrderValidating,
         price_estimation::{native::MockNativePriceEstimating, MockPriceEstimating},
     };
     use chrono::Utc;
@@ -674,7 +1391,6 @@ mod tests {
     use gas_estimation::GasPrice1559;
     use mockall::{predicate::eq, Sequence};
     use model::{quote::Validity, time};
-    use std::sync::Mutex;
 
     #[test]
     fn pre_order_data_from_quote_request() {
@@ -786,6 +1502,8 @@ mod tests {
             now: Arc::new(now),
             eip1271_onchain_quote_validity_seconds: Duration::seconds(60i64),
             presign_onchain_quote_validity_seconds: Duration::seconds(60i64),
+            fee_guarantee: None,
+            overhead_sharing: None,
         };
 
         assert_eq!(
@@ -809,6 +1527,8 @@ mod tests {
                 sell_amount: 70.into(),
                 buy_amount: 29.into(),
                 fee_amount: 30.into(),
+                subsidy: Subsidy::default(),
+                fee_guarantee_cost: None,
             }
         );
     }
@@ -901,6 +1621,8 @@ mod tests {
             now: Arc::new(now),
             eip1271_onchain_quote_validity_seconds: Duration::seconds(60i64),
             presign_onchain_quote_validity_seconds: Duration::seconds(60i64),
+            fee_guarantee: None,
+            overhead_sharing: None,
         };
 
         assert_eq!(
@@ -924,6 +1646,11 @@ mod tests {
                 sell_amount: 100.into(),
                 buy_amount: 42.into(),
                 fee_amount: 15.into(),
+                subsidy: Subsidy {
+                    factor: 0.5,
+                    ..Default::default()
+                },
+                fee_guarantee_cost: None,
             }
         );
     }
@@ -1017,6 +1744,8 @@ mod tests {
             now: Arc::new(now),
             eip1271_onchain_quote_validity_seconds: Duration::seconds(60i64),
             presign_onchain_quote_validity_seconds: Duration::seconds(60i64),
+            fee_guarantee: None,
+            overhead_sharing: None,
         };
 
         assert_eq!(
@@ -1040,6 +1769,12 @@ mod tests {
                 sell_amount: 100.into(),
                 buy_amount: 42.into(),
                 fee_amount: 9.into(),
+                subsidy: Subsidy {
+                    discount: 5.,
+                    min_discounted: 2.,
+                    factor: 0.9,
+                },
+                fee_guarantee_cost: None,
             }
         );
     }
@@ -1099,6 +1834,8 @@ mod tests {
             now: Arc::new(Utc::now),
             eip1271_onchain_quote_validity_seconds: Duration::seconds(60i64),
             presign_onchain_quote_validity_seconds: Duration::seconds(60i64),
+            fee_guarantee: None,
+            overhead_sharing: None,
         };
 
         assert!(matches!(
@@ -1168,6 +1905,8 @@ mod tests {
             now: Arc::new(Utc::now),
             eip1271_onchain_quote_validity_seconds: Duration::seconds(60i64),
             presign_onchain_quote_validity_seconds: Duration::seconds(60i64),
+            fee_guarantee: None,
+            overhead_sharing: None,
         };
 
         assert!(matches!(
@@ -1205,6 +1944,8 @@ mod tests {
             now: Arc::new(now),
             eip1271_onchain_quote_validity_seconds: Duration::seconds(60i64),
             presign_onchain_quote_validity_seconds: Duration::seconds(60i64),
+            fee_guarantee: None,
+            overhead_sharing: None,
         };
 
         let quote = quoter.calculate_quote(Default::default()).await.unwrap();
@@ -1257,6 +1998,8 @@ mod tests {
             now: Arc::new(now),
             eip1271_onchain_quote_validity_seconds: Duration::seconds(60i64),
             presign_onchain_quote_validity_seconds: Duration::seconds(60i64),
+            fee_guarantee: None,
+            overhead_sharing: None,
         };
 
         assert_eq!(
@@ -1287,6 +2030,11 @@ mod tests {
                 // example `from` is specified as a random address) can still
                 // create orders with fees that aren't fully subsidized.
                 fee_amount: 8.into(),
+                subsidy: Subsidy {
+                    factor: 0.25,
+                    ..Default::default()
+                },
+                fee_guarantee_cost: None,
             }
         );
     }
@@ -1334,6 +2082,8 @@ mod tests {
             now: Arc::new(now),
             eip1271_onchain_quote_validity_seconds: Duration::seconds(60i64),
             presign_onchain_quote_validity_seconds: Duration::seconds(60i64),
+            fee_guarantee: None,
+            overhead_sharing: None,
         };
 
         assert_eq!(
@@ -1357,6 +2107,8 @@ mod tests {
                 sell_amount: 100.into(),
                 buy_amount: 42.into(),
                 fee_amount: 30.into(),
+                subsidy: Subsidy::default(),
+                fee_guarantee_cost: None,
             }
         );
     }
@@ -1409,6 +2161,8 @@ mod tests {
             now: Arc::new(now),
             eip1271_onchain_quote_validity_seconds: Duration::seconds(60i64),
             presign_onchain_quote_validity_seconds: Duration::seconds(60i64),
+            fee_guarantee: None,
+            overhead_sharing: None,
         };
 
         assert_eq!(
@@ -1432,6 +2186,8 @@ mod tests {
                 sell_amount: 100.into(),
                 buy_amount: 42.into(),
                 fee_amount: 30.into(),
+                subsidy: Subsidy::default(),
+                fee_guarantee_cost: None,
             }
         );
     }
@@ -1478,6 +2234,8 @@ mod tests {
             now: Arc::new(now),
             eip1271_onchain_quote_validity_seconds: Duration::seconds(60i64),
             presign_onchain_quote_validity_seconds: Duration::seconds(60i64),
+            fee_guarantee: None,
+            overhead_sharing: None,
         };
 
         assert!(matches!(
@@ -1493,6 +2251,106 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn honors_fee_guarantee_of_expired_quotes() {
+        let now = Utc::now();
+        let parameters = QuoteSearchParameters {
+            sell_token: H160([1; 20]),
+            sell_amount: 100.into(),
+            kind: OrderKind::Sell,
+            ..Default::default()
+        };
+        let stored = QuoteData {
+            sell_token: H160([1; 20]),
+            quoted_sell_amount: 100.into(),
+            quoted_buy_amount: 42.into(),
+            kind: OrderKind::Sell,
+            fee_parameters: FeeParameters {
+                gas_amount: 3.,
+                gas_price: 2.,
+                sell_token_price: 1.,
+            },
+            expiration: now - chrono::Duration::seconds(10),
+            ..Default::default()
+        };
+        let quoter = |guarantee_storage: MockFeeGuaranteeStoring, window: i64| {
+            let mut storage = MockQuoteStoring::new();
+            storage.expect_get().returning({
+                let stored = stored.clone();
+                move |_| Ok(Some(stored.clone()))
+            });
+            OrderQuoter {
+                price_estimator: Arc::new(MockPriceEstimating::new()),
+                native_price_estimator: Arc::new(MockNativePriceEstimating::new()),
+                // The gas price rose from 2 to 4 since the quote was computed.
+                gas_estimator: Arc::new(FakeGasPriceEstimator::new(GasPrice1559 {
+                    base_fee_per_gas: 3.5,
+                    max_fee_per_gas: 5.0,
+                    max_priority_fee_per_gas: 0.5,
+                })),
+                fee_subsidy: Arc::new(Subsidy::default()),
+                storage: Arc::new(storage),
+                now: Arc::new(now),
+                eip1271_onchain_quote_validity_seconds: Duration::seconds(60i64),
+                presign_onchain_quote_validity_seconds: Duration::seconds(60i64),
+                fee_guarantee: Some(FeeGuarantee {
+                    window: Duration::seconds(window),
+                    daily_budget: 1.,
+                    storage: Arc::new(guarantee_storage),
+                }),
+                overhead_sharing: None,
+            }
+        };
+
+        // Finding the quote doesn't charge the budget yet.
+        let mut guarantee_storage = MockFeeGuaranteeStoring::new();
+        guarantee_storage.expect_record_fee_guarantee_cost().never();
+        let quote = quoter(guarantee_storage, 30)
+            .find_quote(Some(42), parameters.clone())
+            .await
+            .unwrap();
+        assert_eq!(quote.fee_amount, 6.into());
+        // 3 gas at 2 wei more, converted to the native token.
+        assert_eq!(quote.fee_guarantee_cost, Some(6. / 1e18));
+
+        // Placing the order charges the budget.
+        let mut guarantee_storage = MockFeeGuaranteeStoring::new();
+        guarantee_storage
+            .expect_record_fee_guarantee_cost()
+            .with(
+                eq(42),
+                eq(6. / 1e18),
+                eq(now - Duration::days(1)),
+                eq(1.),
+                eq(now),
+            )
+            .times(1)
+            .returning(|_, _, _, _, _| Ok(true));
+        assert!(quoter(guarantee_storage, 30)
+            .claim_fee_guarantee(&quote)
+            .await
+            .unwrap());
+
+        // The budget can't cover the cost anymore.
+        let mut guarantee_storage = MockFeeGuaranteeStoring::new();
+        guarantee_storage
+            .expect_record_fee_guarantee_cost()
+            .returning(|_, _, _, _, _| Ok(false));
+        assert!(!quoter(guarantee_storage, 30)
+            .claim_fee_guarantee(&quote)
+            .await
+            .unwrap());
+
+        // The quote expired before the window.
+        assert!(matches!(
+            quoter(MockFeeGuaranteeStoring::new(), 5)
+                .find_quote(Some(42), parameters)
+                .await
+                .unwrap_err(),
+            FindQuoteError::Expired(_),
+        ));
+    }
+
     #[tokio::test]
     async fn find_quote_error_when_not_found() {
         let mut storage = MockQuoteStoring::new();
@@ -1508,6 +2366,8 @@ mod tests {
             now: Arc::new(Utc::now),
             eip1271_onchain_quote_validity_seconds: Duration::seconds(60i64),
             presign_onchain_quote_validity_seconds: Duration::seconds(60i64),
+            fee_guarantee: None,
+            overhead_sharing: None,
         };
 
         assert!(matches!(
@@ -1525,4 +2385,471 @@ mod tests {
             FindQuoteError::NotFound(None),
         ));
     }
+
+    #[tokio::test]
+    async fn refreshes_quotes() {
+        let now = Utc::now();
+        let stored = QuoteData {
+            sell_token: H160([1; 20]),
+            buy_token: H160([2; 20]),
+            quoted_sell_amount: 100.into(),
+            quoted_buy_amount: 42.into(),
+            fee_parameters: FeeParameters {
+                gas_amount: 3.,
+                gas_price: 2.,
+                sell_token_price: 0.2,
+            },
+            kind: OrderKind::Sell,
+            expiration: now - chrono::Duration::seconds(10),
+            quote_kind: QuoteKind::Standard,
+        };
+        let expiration = now + Duration::seconds(STANDARD_QUOTE_VALIDITY_SECONDS);
+        let quoter = |out_amount: u64, gas: u64, native_price: f64, storage: MockQuoteStoring| {
+            let mut native_price_estimator = MockNativePriceEstimating::new();
+            native_price_estimator
+                .expect_estimate_native_prices()
+                .withf(|q| q == [H160([1; 20])])
+                .returning(move |_| {
+                    futures::stream::iter([Ok(native_price)])
+                        .enumerate()
+                        .boxed()
+                });
+            let mut price_estimator = MockPriceEstimating::new();
+            price_estimator
+                .expect_estimates()
+                .withf(|q| {
+                    q == [price_estimation::Query {
+                        sell_token: H160([1; 20]),
+                        buy_token: H160([2; 20]),
+                        in_amount: 100.into(),
+                        kind: OrderKind::Sell,
+                    }]
+                })
+                .returning(move |_| {
+                    futures::stream::iter([Ok(price_estimation::Estimate {
+                        out_amount: out_amount.into(),
+                        gas,
+                    })])
+                    .enumerate()
+                    .boxed()
+                });
+            OrderQuoter {
+                price_estimator: Arc::new(price_estimator),
+                native_price_estimator: Arc::new(native_price_estimator),
+                gas_estimator: Arc::new(FakeGasPriceEstimator(Arc::new(Mutex::new(
+                    GasPrice1559 {
+                        base_fee_per_gas: 1.5,
+                        max_fee_per_gas: 3.0,
+                        max_priority_fee_per_gas: 0.5,
+                    },
+                )))),
+                fee_subsidy: Arc::new(Subsidy::default()),
+                storage: Arc::new(storage),
+                now: Arc::new(now),
+                eip1271_onchain_quote_validity_seconds: Duration::seconds(60i64),
+                presign_onchain_quote_validity_seconds: Duration::seconds(60i64),
+                fee_guarantee: None,
+                overhead_sharing: None,
+            }
+        };
+
+        // The price improved and the fee stayed the same so the stored quote gets extended.
+        let mut storage = MockQuoteStoring::new();
+        storage.expect_get().with(eq(42)).returning({
+            let stored = stored.clone();
+            move |_| Ok(Some(stored.clone()))
+        });
+        storage
+            .expect_extend()
+            .with(eq(42), eq(expiration))
+            .returning(|_, _| Ok(true));
+        let refreshed = quoter(43, 3, 0.2, storage)
+            .refresh_quote(42, Default::default())
+            .await
+            .unwrap();
+        assert!(refreshed.extended);
+        assert_eq!(refreshed.quote.id, Some(42));
+        assert_eq!(refreshed.quote.buy_amount, 42.into());
+        assert_eq!(refreshed.quote.data.expiration, expiration);
+
+        // The fee increased so a new quote gets stored.
+        let mut storage = MockQuoteStoring::new();
+        storage.expect_get().with(eq(42)).returning({
+            let stored = stored.clone();
+            move |_| Ok(Some(stored.clone()))
+        });
+        storage
+            .expect_save()
+            .with(eq(QuoteData {
+                quoted_buy_amount: 43.into(),
+                fee_parameters: FeeParameters {
+                    gas_amount: 4.,
+                    ..stored.fee_parameters
+                },
+                expiration,
+                ..stored.clone()
+            }))
+            .returning(|_| Ok(Some(1337)));
+        let refreshed = quoter(43, 4, 0.2, storage)
+            .refresh_quote(42, Default::default())
+            .await
+            .unwrap();
+        assert!(!refreshed.extended);
+        assert_eq!(refreshed.quote.id, Some(1337));
+        assert_eq!(refreshed.quote.buy_amount, 43.into());
+        assert_eq!(refreshed.quote.fee_amount, 40.into());
+
+        // The price got worse so a new quote gets stored.
+        let mut storage = MockQuoteStoring::new();
+        storage.expect_get().with(eq(42)).returning({
+            let stored = stored.clone();
+            move |_| Ok(Some(stored.clone()))
+        });
+        storage
+            .expect_save()
+            .with(eq(QuoteData {
+                quoted_buy_amount: 41.into(),
+                expiration,
+                ..stored.clone()
+            }))
+            .returning(|_| Ok(Some(1337)));
+        let refreshed = quoter(41, 3, 0.2, storage)
+            .refresh_quote(42, Default::default())
+            .await
+            .unwrap();
+        assert!(!refreshed.extended);
+        assert_eq!(refreshed.quote.buy_amount, 41.into());
+
+        // The native price of the sell token dropped so the fee increased and a new quote gets
+        // stored with the current price.
+        let mut storage = MockQuoteStoring::new();
+        storage.expect_get().with(eq(42)).returning({
+            let stored = stored.clone();
+            move |_| Ok(Some(stored.clone()))
+        });
+        storage
+            .expect_save()
+            .with(eq(QuoteData {
+                fee_parameters: FeeParameters {
+                    sell_token_price: 0.1,
+                    ..stored.fee_parameters
+                },
+                expiration,
+                ..stored.clone()
+            }))
+            .returning(|_| Ok(Some(1337)));
+        let refreshed = quoter(42, 3, 0.1, storage)
+            .refresh_quote(42, Default::default())
+            .await
+            .unwrap();
+        assert!(!refreshed.extended);
+        assert_eq!(refreshed.quote.fee_amount, 60.into());
+
+        let mut storage = MockQuoteStoring::new();
+        storage.expect_get().returning(|_| Ok(None));
+        assert!(matches!(
+            quoter(41, 3, 0.2, storage)
+                .refresh_quote(42, Default::default())
+                .await
+                .unwrap_err(),
+            RefreshQuoteError::NotFound(42),
+        ));
+    }
+
+    #[tokio::test]
+    async fn basket_quote_shares_identical_legs_and_reports_internal_cows() {
+        let quote_token = H160([0x02; 20]);
+        let leg = |token: u8, amount: u64, kind| BasketLeg {
+            token: H160([token; 20]),
+            amount: amount.into(),
+            kind,
+        };
+        let request = BasketQuoteRequest {
+            quote_token,
+            legs: vec![
+                leg(3, 100, OrderKind::Sell),
+                leg(4, 10, OrderKind::Buy),
+                leg(3, 100, OrderKind::Sell),
+            ],
+            ..Default::default()
+        };
+
+        let mut order_validator = MockOrderValidating::new();
+        order_validator
+            .expect_partial_validate()
+            .times(2)
+            .returning(|_| Ok(()));
+        let mut quoter = MockOrderQuoting::new();
+        quoter
+            .expect_calculate_quote()
+            .times(2)
+            .returning(move |parameters| {
+                let (quoted_sell_amount, quoted_buy_amount) =
+                    if parameters.sell_token == quote_token {
+                        (150.into(), 10.into())
+                    } else {
+                        (100.into(), 200.into())
+                    };
+                Ok(Quote::new(
+                    Some(0),
+                    QuoteData {
+                        sell_token: parameters.sell_token,
+                        buy_token: parameters.buy_token,
+                        quoted_sell_amount,
+                        quoted_buy_amount,
+                        ..Default::default()
+                    },
+                ))
+            });
+        let handler = QuoteHandler::new(Arc::new(order_validator), Arc::new(quoter));
+
+        let response = handler.calculate_basket_quote(&request).await.unwrap();
+        assert_eq!(response.legs.len(), 3);
+        assert_eq!(response.legs[0], response.legs[2]);
+        assert_eq!(response.legs[1].quote.buy_token, H160([4; 20]));
+        assert_eq!(
+            response.internal_cows,
+            [InternalCow {
+                sell_leg: 0,
+                buy_leg: 1,
+                amount: 150.into(),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn basket_quote_rejects_invalid_leg_count() {
+        let handler = QuoteHandler::new(
+            Arc::new(MockOrderValidating::new()),
+            Arc::new(MockOrderQuoting::new()),
+        );
+        let request = BasketQuoteRequest {
+            legs: vec![Default::default(); MAX_BASKET_LEGS + 1],
+            ..Default::default()
+        };
+        assert!(matches!(
+            handler.calculate_basket_quote(&Default::default()).await,
+            Err(BasketQuoteError::InvalidLegCount)
+        ));
+        assert!(matches!(
+            handler.calculate_basket_quote(&request).await,
+            Err(BasketQuoteError::InvalidLegCount)
+        ));
+    }
+
+    #[test]
+    fn internal_cows_match_legs_in_order() {
+        let response = |sell_amount: u64, buy_amount: u64, fee_amount: u64| OrderQuoteResponse {
+            quote: OrderQuote {
+                sell_token: Default::default(),
+                buy_token: Default::default(),
+                receiver: None,
+                sell_amount: sell_amount.into(),
+                buy_amount: buy_amount.into(),
+                valid_to: 0,
+                app_data: Default::default(),
+                fee_amount: fee_amount.into(),
+                kind: Default::default(),
+                partially_fillable: false,
+                sell_token_balance: Default::default(),
+                buy_token_balance: Default::default(),
+            },
+            from: Default::default(),
+            expiration: Utc.timestamp(0, 0),
+            guaranteed_until: None,
+            id: None,
+            threat_warnings: Default::default(),
+            cost_of_immediacy: None,
+        };
+        let leg = |kind| BasketLeg {
+            kind,
+            ..Default::default()
+        };
+        let legs = [
+            leg(OrderKind::Sell),
+            leg(OrderKind::Buy),
+            leg(OrderKind::Sell),
+            leg(OrderKind::Buy),
+        ];
+        let quotes = [
+            // receives 100 of the quote token
+            response(5, 100, 0),
+            // spends 60 of the quote token
+            response(50, 1, 10),
+            // receives 30 of the quote token
+            response(5, 30, 0),
+            // spends 100 of the quote token
+            response(100, 1, 0),
+        ];
+        let cow = |sell_leg, buy_leg, amount: u64| InternalCow {
+            sell_leg,
+            buy_leg,
+            amount: amount.into(),
+        };
+        assert_eq!(
+            internal_cows(&legs, &quotes),
+            [cow(0, 1, 60), cow(0, 3, 40), cow(2, 3, 30)]
+        );
+        assert!(internal_cows(&legs[..1], &quotes[..1]).is_empty());
+    }
+
+    #[test]
+    fn transfer_fee_amounts() {
+        assert_eq!(amount_before_transfer_fee(99.into(), 100), Some(100.into()));
+        assert_eq!(
+            amount_before_transfer_fee(100.into(), 100),
+            Some(102.into())
+        );
+        assert_eq!(amount_before_transfer_fee(100.into(), 0), Some(100.into()));
+        assert_eq!(amount_before_transfer_fee(100.into(), 10_000), None);
+        assert_eq!(amount_before_transfer_fee(U256::MAX, 1), None);
+
+        assert_eq!(amount_after_transfer_fee(100.into(), 100), 99.into());
+        assert_eq!(amount_after_transfer_fee(U256::MAX, 0), U256::MAX);
+        assert_eq!(amount_after_transfer_fee(100.into(), 10_000), 0.into());
+    }
+
+    #[test]
+    fn protocol_fee_amounts() {
+        let quote = |sell_amount: u64, buy_amount: u64, fee_amount: u64| Quote {
+            sell_amount: sell_amount.into(),
+            buy_amount: buy_amount.into(),
+            fee_amount: fee_amount.into(),
+            ..Default::default()
+        };
+
+        let protocol_fee = |amount: u64| TokenAmount::new(H160::zero(), amount.into());
+
+        let mut after_fee = quote(1_000, 2_000, 10);
+        add_protocol_fee(&mut after_fee, &protocol_fee(5), false).unwrap();
+        assert_eq!(after_fee, quote(1_000, 2_000, 15));
+
+        let mut before_fee = quote(1_000, 2_000, 10);
+        add_protocol_fee(&mut before_fee, &protocol_fee(5), true).unwrap();
+        assert_eq!(before_fee, quote(995, 1_990, 15));
+
+        let mut unchanged = quote(1_000, 2_000, 10);
+        add_protocol_fee(&mut unchanged, &protocol_fee(0), true).unwrap();
+        assert_eq!(unchanged, quote(1_000, 2_000, 10));
+
+        // The protocol fee has to be denominated in the sell token.
+        let mut other_token = quote(1_000, 2_000, 10);
+        assert!(add_protocol_fee(
+            &mut other_token,
+            &TokenAmount::new(H160([1; 20]), 5.into()),
+            false
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn quote_adjusts_buy_amounts_for_transfer_fees() {
+        let fee_token = H160([0x42; 20]);
+        let mut order_validator = MockOrderValidating::new();
+        order_validator
+            .expect_partial_validate()
+            .returning(|_| Ok(()));
+        let mut bad_token_detector = MockBadTokenDetecting::new();
+        bad_token_detector
+            .expect_detect()
+            .with(eq(fee_token))
+            .returning(|_| Ok(TokenQuality::FeeOnTransfer { fee_bps: 100 }));
+        let mut quoter = MockOrderQuoting::new();
+        quoter.expect_calculate_quote().returning(|parameters| {
+            let (kind, quoted_sell_amount, quoted_buy_amount) = match parameters.side {
+                OrderQuoteSide::Sell { .. } => (OrderKind::Sell, 100.into(), 1_000.into()),
+                OrderQuoteSide::Buy {
+                    buy_amount_after_fee,
+                } => (OrderKind::Buy, 100.into(), buy_amount_after_fee),
+            };
+            Ok(Quote::new(
+                Some(0),
+                QuoteData {
+                    quoted_sell_amount,
+                    quoted_buy_amount,
+                    kind,
+                    ..Default::default()
+                },
+            ))
+        });
+        let handler = QuoteHandler::new(Arc::new(order_validator), Arc::new(quoter))
+            .with_bad_token_detector(Arc::new(bad_token_detector));
+
+        let sell = handler
+            .calculate_quote(&OrderQuoteRequest {
+                buy_token: fee_token,
+                side: OrderQuoteSide::Sell {
+                    sell_amount: SellAmount::AfterFee { value: 100.into() },
+                },
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(sell.quote.buy_amount, 990.into());
+
+        let buy = handler
+            .calculate_quote(&OrderQuoteRequest {
+                buy_token: fee_token,
+                side: OrderQuoteSide::Buy {
+                    buy_amount_after_fee: 990.into(),
+                },
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(buy.quote.buy_amount, 1_000.into());
+    }
+
+    #[test]
+    fn shares_overhead_between_expected_batch() {
+        let sharing = OverheadSharing::new(
+            Duration::days(1),
+            0.5,
+            4.,
+            Arc::new(MockBatchSizeStoring::new()),
+        );
+        assert_eq!(sharing.batch_size(None), 1.);
+        assert_eq!(sharing.batch_size(Some(1.5)), 1.);
+        assert_eq!(sharing.batch_size(Some(6.)), 3.);
+        assert_eq!(sharing.batch_size(Some(20.)), 4.);
+
+        let gas = SETTLEMENT_FIXED + 100_000;
+        assert_eq!(shared_gas_amount(gas, 1.), gas as f64);
+        assert_eq!(
+            shared_gas_amount(gas, 2.),
+            SETTLEMENT_FIXED as f64 / 2. + 100_000.
+        );
+        assert_eq!(shared_gas_amount(0, 2.), 0.);
+    }
+
+    #[tokio::test]
+    async fn caches_batch_sizes_per_pair() {
+        let now = Utc::now();
+        let (token_a, token_b) = (H160([1; 20]), H160([2; 20]));
+        let mut storage = MockBatchSizeStoring::new();
+        storage
+            .expect_average_batch_size()
+            .with(eq(token_a), eq(token_b), eq(now - Duration::days(1)))
+            .times(1)
+            .returning(|_, _, _| Ok(Some(4.)));
+        storage
+            .expect_average_batch_size()
+            .with(eq(token_a), eq(token_b), eq(now))
+            .times(1)
+            .returning(|_, _, _| Err(anyhow!("database unavailable")));
+        let sharing = OverheadSharing::new(Duration::days(1), 0., 2., Arc::new(storage));
+
+        let gas = SETTLEMENT_FIXED * 2;
+        let shared = SETTLEMENT_FIXED as f64 * 1.5;
+        // Both directions of the pair share the batch size.
+        assert_eq!(sharing.gas_amount(token_a, token_b, gas, now).await, shared);
+        assert_eq!(sharing.gas_amount(token_b, token_a, gas, now).await, shared);
+        // Once it is outdated it gets fetched again and failing to do so charges the full
+        // overhead.
+        let later = now + Duration::days(1);
+        assert_eq!(
+            sharing.gas_amount(token_a, token_b, gas, later).await,
+            gas as f64
+        );
+    }
 }
@@ -1,28 +1,41 @@
 use crate::{
-    account_balances::{BalanceFetching, TransferSimulationError},
-    bad_token::BadTokenDetecting,
+    account_balances::{BalanceFetching, Query, TransferSimulationError},
+    bad_token::{BadTokenDetecting, TokenQuality},
+    hot_reload::Reloadable,
     order_quoting::{
         CalculateQuoteError, FindQuoteError, OrderQuoting, Quote, QuoteParameters,
         QuoteSearchParameters,
     },
     price_estimation::PriceEstimationError,
+    protocol_fee::ProtocolFee,
     signature_validator::{SignatureCheck, SignatureValidating, SignatureValidationError},
+    threat_intel::{ThreatIntel, ThreatIntelMode},
+    token_amount::TokenAmount,
     web3_traits::CodeFetching,
+    wrapped_native,
 };
 use anyhow::anyhow;
 use contracts::WETH9;
 use database::quotes::QuoteKind;
 use ethcontract::{H160, U256};
+use futures::future::join_all;
+use itertools::Itertools;
 use model::{
     order::{
-        BuyTokenDestination, Order, OrderCreation, OrderData, OrderKind, SellTokenSource,
+        BuyTokenDestination, FundingSuggestion, InsufficientFunds, Order, OrderCancellation,
+        OrderClass, OrderCreation, OrderData, OrderKind, OrderValidationRequest, SellTokenSource,
         BUY_ETH_ADDRESS,
     },
-    quote::{OrderQuoteSide, QuoteSigningScheme, SellAmount},
-    signature::{hashed_eip712_message, Signature, SigningScheme, VerificationError},
-    DomainSeparator,
+    quote::{OrderQuoteSide, QuoteSigningScheme, SellAmount, ThreatWarning},
+    signature::{hashed_eip712_message, SigningScheme, VerificationError},
+    DomainParameters, DomainSeparator,
+};
+use primitive_types::U512;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
 };
-use std::{collections::HashSet, sync::Arc, time::Duration};
 
 #[mockall::automock]
 #[async_trait::async_trait]
@@ -37,6 +50,8 @@ pub trait OrderValidating: Send + Sync {
     ///     - if buy token is native asset, receiver is not a smart contract,
     ///     - the sell token is not the native asset,
     ///     - the sender is not a banned user,
+    ///     - the tokens and receiver are not on threat intelligence lists (if configured to reject),
+    ///     - the order class allows partially fillable orders and the order validity,
     ///     - the order validity is appropriate,
     ///     - buy_token is not the same as sell_token,
     ///     - buy and sell token destination and source are supported.
@@ -47,8 +62,10 @@ pub trait OrderValidating: Send + Sync {
     /// (i.e. once all the required fields on an Order are provided). Specifically, verifying that
     ///     - buy & sell amounts are non-zero,
     ///     - order's signature recovers correctly
-    ///     - fee is sufficient,
-    ///     - user has sufficient (transferable) funds to execute the order.
+    ///     - the owner can place orders of the requested class,
+    ///     - fee is sufficient (if the order class pays fees),
+    ///     - user has sufficient (transferable) funds to execute the order (if the order class
+    ///       requires them at placement).
     ///
     /// Furthermore, full order validation also calls partial_validate to ensure that
     /// other aspects of the order are not malformed.
@@ -58,6 +75,25 @@ pub trait OrderValidating: Send + Sync {
         domain_separator: &DomainSeparator,
         settlement_contract: H160,
     ) -> Result<(Order, Option<Quote>), ValidationError>;
+
+    /// Validates prospective orders like `validate_and_construct_order` would but without
+    /// signatures, so neither the signature nor the owner get verified. Balances are fetched
+    /// once for all orders of the same owner and sell token and are checked together with the
+    /// allowance.
+    async fn validate_prospective_orders(
+        &self,
+        orders: &[OrderValidationRequest],
+    ) -> Vec<Result<(), ValidationError>>;
+
+    /// Verifies that an off-chain cancellation was signed by the owner of the order. ECDSA
+    /// signatures have to recover to the owner while EIP-1271 signatures get verified by the
+    /// owner contract.
+    async fn validate_cancellation(
+        &self,
+        cancellation: &OrderCancellation,
+        owner: H160,
+        domain_separator: &DomainSeparator,
+    ) -> Result<(), CancellationValidationError>;
 }
 
 #[derive(Debug)]
@@ -73,6 +109,9 @@ pub enum PartialValidationError {
     UnsupportedOrderType,
     UnsupportedSignature,
     UnsupportedToken(H160),
+    /// The sell token takes a fee on transfers which would leave the settlement contract short.
+    FeeOnTransferSellToken(H160),
+    ThreatIntel(ThreatWarning),
     Other(anyhow::Error),
 }
 
@@ -87,8 +126,8 @@ pub enum ValidationError {
     /// Unable to compute quote because of a price estimation error.
     PriceForQuote(PriceEstimationError),
     InsufficientFee,
-    InsufficientBalance,
-    InsufficientAllowance,
+    InsufficientBalance(InsufficientFunds),
+    InsufficientAllowance(InsufficientFunds),
     InvalidSignature,
     /// If fee and sell amount overflow u256
     SellAmountOverflow,
@@ -97,8 +136,29 @@ pub enum ValidationError {
     /// order signer.
     MissingFrom,
     WrongOwner(H160),
+    /// The signature is valid for another deployment of the settlement contract, for example on a
+    /// different chain.
+    WrongDomain(DomainParameters),
     ZeroAmount,
     IncompatibleSigningScheme,
+    /// Only liquidity orders of market makers can specify quote firmness.
+    UnsupportedQuoteFirmness,
+    /// A minimum partial fill amount was specified for an order that isn't partially fillable or
+    /// the amount is zero or exceeds the order's amount.
+    InvalidMinPartialFillAmount,
+    /// The order's condition can never hold, for example because its time window is empty.
+    InvalidCondition,
+    /// The owner can't place orders of this class. Liquidity orders are reserved for market
+    /// makers and market makers can only place liquidity orders.
+    UnsupportedOrderClass(OrderClass),
+    Other(anyhow::Error),
+}
+
+#[derive(Debug)]
+pub enum CancellationValidationError {
+    /// The signature can't be verified or uses a scheme that can't sign cancellations.
+    InvalidSignature,
+    WrongOwner,
     Other(anyhow::Error),
 }
 
@@ -162,7 +222,7 @@ pub struct OrderValidator {
     /// when only part of the order data is available
     code_fetcher: Box<dyn CodeFetching>,
     native_token: WETH9,
-    banned_users: HashSet<H160>,
+    banned_users: Reloadable<HashSet<H160>>,
     liquidity_order_owners: HashSet<H160>,
     min_order_validity_period: Duration,
     max_order_validity_period: Duration,
@@ -172,6 +232,10 @@ pub struct OrderValidator {
     quoter: Arc<dyn OrderQuoting>,
     balance_fetcher: Arc<dyn BalanceFetching>,
     signature_validator: Arc<dyn SignatureValidating>,
+    threat_intel: Option<Arc<ThreatIntel>>,
+    protocol_fee: ProtocolFee,
+    internal_balances: bool,
+    known_domains: Vec<DomainParameters>,
 }
 
 #[derive(Debug, Eq, PartialEq, Default)]
@@ -185,7 +249,7 @@ pub struct PreOrderData {
     pub buy_token_balance: BuyTokenDestination,
     pub sell_token_balance: SellTokenSource,
     pub signing_scheme: SigningScheme,
-    pub is_liquidity_order: bool,
+    pub class: OrderClass,
 }
 
 fn actual_receiver(owner: H160, order: &OrderData) -> H160 {
@@ -197,12 +261,18 @@ fn actual_receiver(owner: H160, order: &OrderData) -> H160 {
     }
 }
 
+/// A prospective order that passed the checks that don't share lookups with other orders.
+struct ProspectiveOrder {
+    class: OrderClass,
+    min_balance: U256,
+}
+
 impl PreOrderData {
     pub fn from_order_creation(
         owner: H160,
         order: &OrderData,
         signing_scheme: SigningScheme,
-        is_liquidity_order: bool,
+        class: OrderClass,
     ) -> Self {
         Self {
             owner,
@@ -214,7 +284,85 @@ impl PreOrderData {
             buy_token_balance: order.buy_token_balance,
             sell_token_balance: order.sell_token_balance,
             signing_scheme,
-            is_liquidity_order,
+            class,
+        }
+    }
+}
+
+/// The rules that orders of a class get validated with and that decide when they get included in
+/// auctions.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ClassPolicy {
+    /// Whether orders have to pay at least the fee of their quote.
+    pub requires_fee: bool,
+    /// Whether orders can be partially fillable.
+    pub allows_partial_fills: bool,
+    /// Whether orders can be valid for longer than the maximum order validity period.
+    pub allows_long_validity: bool,
+    pub balance_check: BalanceCheck,
+    pub auction_inclusion: AuctionInclusion,
+}
+
+/// When the owner of an order needs to have enough balance and allowance for the order.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BalanceCheck {
+    /// Already when placing the order.
+    Placement,
+    /// Only when the order gets included in an auction. This allows owners to place orders that
+    /// they fund later.
+    Auction,
+}
+
+/// Which of the solvable orders of a class get included in auctions.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AuctionInclusion {
+    /// All of them.
+    Always,
+    /// Only those whose quote can still be filled.
+    WhileQuoteIsFirm,
+    /// Only the order that expires first out of the orders of an owner on the same token pair,
+    /// so that the orders get filled one after the other.
+    Sequentially,
+}
+
+impl ClassPolicy {
+    pub fn of(class: OrderClass) -> Self {
+        match class {
+            OrderClass::Market => Self {
+                requires_fee: true,
+                allows_partial_fills: false,
+                allows_long_validity: false,
+                balance_check: BalanceCheck::Placement,
+                auction_inclusion: AuctionInclusion::Always,
+            },
+            // Limit orders can wait a long time until they get filled so their owners only need
+            // to fund them by then.
+            OrderClass::Limit => Self {
+                requires_fee: true,
+                allows_partial_fills: false,
+                allows_long_validity: true,
+                balance_check: BalanceCheck::Auction,
+                auction_inclusion: AuctionInclusion::Always,
+            },
+            // All parts of a TWAP order get placed up front, so later parts are valid for a long
+            // time and only need to be funded once it is their turn. Including them one at a time
+            // spreads the trades of the order over time.
+            OrderClass::Twap => Self {
+                requires_fee: true,
+                allows_partial_fills: false,
+                allows_long_validity: true,
+                balance_check: BalanceCheck::Auction,
+                auction_inclusion: AuctionInclusion::Sequentially,
+            },
+            // Market makers don't pay fees for their liquidity orders because the orders are only
+            // used for settling other orders.
+            OrderClass::Liquidity => Self {
+                requires_fee: false,
+                allows_partial_fills: true,
+                allows_long_validity: true,
+                balance_check: BalanceCheck::Placement,
+                auction_inclusion: AuctionInclusion::WhileQuoteIsFirm,
+            },
         }
     }
 }
@@ -224,7 +372,7 @@ impl OrderValidator {
     pub fn new(
         code_fetcher: Box<dyn CodeFetching>,
         native_token: WETH9,
-        banned_users: HashSet<H160>,
+        banned_users: impl Into<Reloadable<HashSet<H160>>>,
         liquidity_order_owners: HashSet<H160>,
         min_order_validity_period: Duration,
         max_order_validity_period: Duration,
@@ -237,7 +385,7 @@ impl OrderValidator {
         Self {
             code_fetcher,
             native_token,
-            banned_users,
+            banned_users: banned_users.into(),
             liquidity_order_owners,
             min_order_validity_period,
             max_order_validity_period,
@@ -246,30 +394,204 @@ impl OrderValidator {
             quoter,
             balance_fetcher,
             signature_validator,
+            threat_intel: None,
+            protocol_fee: Default::default(),
+            internal_balances: false,
+            known_domains: Default::default(),
+        }
+    }
+
+    pub fn with_threat_intel(mut self, threat_intel: Arc<ThreatIntel>) -> Self {
+        self.threat_intel = Some(threat_intel);
+        self
+    }
+
+    /// Requires orders to pay the protocol fee of their class on top of the fee of their quote.
+    pub fn with_protocol_fee(mut self, protocol_fee: ProtocolFee) -> Self {
+        self.protocol_fee = protocol_fee;
+        self
+    }
+
+    /// Accepts orders that sell from or pay out to Balancer Vault internal balances. Only enable
+    /// this on networks with a deployed Vault.
+    pub fn with_internal_balances(mut self) -> Self {
+        self.internal_balances = true;
+        self
+    }
+
+    /// Other deployments of the settlement contract users might sign orders for by mistake.
+    /// Orders whose signature is valid for one of them get rejected with a specific error instead
+    /// of a wrong owner.
+    pub fn with_known_domains(mut self, domains: Vec<DomainParameters>) -> Self {
+        self.known_domains = domains;
+        self
+    }
+
+    /// The deployment the order was signed for if it is one of the known other deployments.
+    fn foreign_domain(&self, order: &OrderCreation) -> Option<DomainParameters> {
+        self.known_domains
+            .iter()
+            .copied()
+            .find(|domain| order.verify_owner(&domain.separator()).is_ok())
+    }
+
+    /// The class an order of the owner gets placed as. Orders of market makers are liquidity
+    /// orders and only they can be.
+    fn order_class(
+        &self,
+        owner: H160,
+        requested: Option<OrderClass>,
+    ) -> Result<OrderClass, ValidationError> {
+        let liquidity_owner = self.liquidity_order_owners.contains(&owner);
+        let class = requested.unwrap_or(if liquidity_owner {
+            OrderClass::Liquidity
+        } else {
+            OrderClass::Market
+        });
+        if liquidity_owner != (class == OrderClass::Liquidity) {
+            return Err(ValidationError::UnsupportedOrderClass(class));
+        }
+        Ok(class)
+    }
+
+    /// Validates everything about a prospective order except its balance.
+    /// Checks that the signed fee covers the quoted fee and the protocol fee and returns the
+    /// protocol fee.
+    fn check_fee(
+        &self,
+        class: OrderClass,
+        order: &OrderData,
+        quote: &Quote,
+    ) -> Result<TokenAmount, ValidationError> {
+        let protocol_fee = self.protocol_fee.fee(
+            class,
+            &TokenAmount::new(order.sell_token, order.sell_amount.min(quote.sell_amount)),
+        );
+        let required_fee = quote
+            .fee()
+            .saturating_add(&protocol_fee)
+            .map_err(ValidationError::Other)?;
+        if order.fee_amount < required_fee.amount {
+            return Err(ValidationError::InsufficientFee);
+        }
+        Ok(protocol_fee)
+    }
+
+    async fn validate_prospective_order(
+        &self,
+        order: &OrderValidationRequest,
+    ) -> Result<ProspectiveOrder, ValidationError> {
+        if order.data.buy_amount.is_zero() || order.data.sell_amount.is_zero() {
+            return Err(ValidationError::ZeroAmount);
+        }
+        let class = self.order_class(order.from, order.class)?;
+        self.partial_validate(PreOrderData::from_order_creation(
+            order.from,
+            &order.data,
+            order.signing_scheme,
+            class,
+        ))
+        .await
+        .map_err(ValidationError::Partial)?;
+        let min_balance =
+            minimum_balance(&order.data).ok_or(ValidationError::SellAmountOverflow)?;
+        if !ClassPolicy::of(class).requires_fee {
+            return Ok(ProspectiveOrder { class, min_balance });
+        }
+
+        let quote_parameters = QuoteSearchParameters {
+            sell_token: order.data.sell_token,
+            buy_token: order.data.buy_token,
+            sell_amount: order.data.sell_amount,
+            buy_amount: order.data.buy_amount,
+            fee_amount: order.data.fee_amount,
+            kind: order.data.kind,
+            from: order.from,
+            app_data: order.data.app_data,
+            quote_kind: convert_signing_scheme_into_quote_kind(order.signing_scheme, true)?,
+        };
+        let quote = get_quote_and_check_fee(
+            &*self.quoter,
+            &quote_parameters,
+            None,
+            order.data.fee_amount,
+            convert_signing_scheme_into_quote_signing_scheme(order.signing_scheme, true)?,
+        )
+        .await?;
+        self.check_fee(class, &order.data, &quote)?;
+        // Like placed orders, market orders outside the market price become limit orders, which
+        // don't need their balance yet.
+        let class = if class == OrderClass::Market
+            && is_order_outside_market_price(
+                &order.data.sell_amount,
+                &order.data.buy_amount,
+                &quote,
+            ) {
+            OrderClass::Limit
+        } else {
+            class
+        };
+        Ok(ProspectiveOrder { class, min_balance })
+    }
+
+    /// Describes the missing balance of an order. If the order sells the wrapped native token and
+    /// the owner's native token balance covers the shortfall, the owner is asked to wrap it.
+    /// Settlements can only transfer the wrapped token so they can't fund the order from the
+    /// native token balance themselves. Wrapping on the owner's behalf would need orders placed
+    /// through the eth-flow contract, which isn't supported yet.
+    async fn insufficient_balance(
+        &self,
+        order: &OrderData,
+        owner: H160,
+        required: U256,
+        balance: U256,
+    ) -> InsufficientFunds {
+        let mut funds = insufficient_balance(order, required, balance);
+        if order.sell_token != self.native_token.address()
+            || order.sell_token_balance != SellTokenSource::Erc20
+        {
+            return funds;
+        }
+        match self.balance_fetcher.native_balance(owner).await {
+            Ok(native_balance) if native_balance >= funds.shortfall => {
+                funds.suggestion = FundingSuggestion::WrapNativeToken {
+                    amount: funds.shortfall,
+                };
+            }
+            Ok(_) => (),
+            Err(err) => tracing::warn!(?err, ?owner, "failed to fetch native balance"),
         }
+        funds
     }
 }
 
 #[async_trait::async_trait]
 impl OrderValidating for OrderValidator {
     async fn partial_validate(&self, order: PreOrderData) -> Result<(), PartialValidationError> {
-        if self.banned_users.contains(&order.owner) {
+        if self.banned_users.get().contains(&order.owner) {
             return Err(PartialValidationError::Forbidden);
         }
 
-        if order.partially_fillable && !order.is_liquidity_order {
+        if let Some(threat_intel) = &self.threat_intel {
+            let warnings = threat_intel.check(&order);
+            if let (ThreatIntelMode::Reject, Some(warning)) =
+                (threat_intel.mode(), warnings.first())
+            {
+                return Err(PartialValidationError::ThreatIntel(*warning));
+            }
+        }
+
+        let policy = ClassPolicy::of(order.class);
+        if order.partially_fillable && !policy.allows_partial_fills {
             return Err(PartialValidationError::UnsupportedOrderType);
         }
 
-        if order.buy_token_balance != BuyTokenDestination::Erc20 {
+        if order.buy_token_balance == BuyTokenDestination::Internal && !self.internal_balances {
             return Err(PartialValidationError::UnsupportedBuyTokenDestination(
                 order.buy_token_balance,
             ));
         }
-        if !matches!(
-            order.sell_token_balance,
-            SellTokenSource::Erc20 | SellTokenSource::External
-        ) {
+        if order.sell_token_balance == SellTokenSource::Internal && !self.internal_balances {
             return Err(PartialValidationError::UnsupportedSellTokenSource(
                 order.sell_token_balance,
             ));
@@ -288,8 +610,8 @@ impl OrderValidating for OrderValidator {
             return Err(PartialValidationError::InsufficientValidTo);
         }
         if order.valid_to > now.saturating_add(self.max_order_validity_period.as_secs() as u32)
-            && !order.is_liquidity_order
-            && order.signing_scheme != SigningScheme::PreSign
+            && !policy.allows_long_validity
+            && !order.signing_scheme.is_signed_by_transaction()
         {
             return Err(PartialValidationError::ExcessiveValidTo);
         }
@@ -311,16 +633,32 @@ impl OrderValidating for OrderValidator {
             }
         }
 
-        for &token in &[order.sell_token, order.buy_token] {
-            if !self
-                .bad_token_detector
-                .detect(token)
-                .await
-                .map_err(PartialValidationError::Other)?
-                .is_good()
-            {
-                return Err(PartialValidationError::UnsupportedToken(token));
+        match self
+            .bad_token_detector
+            .detect(order.sell_token)
+            .await
+            .map_err(PartialValidationError::Other)?
+        {
+            TokenQuality::Good => (),
+            TokenQuality::FeeOnTransfer { .. } => {
+                return Err(PartialValidationError::FeeOnTransferSellToken(
+                    order.sell_token,
+                ))
             }
+            TokenQuality::Bad { .. } => {
+                return Err(PartialValidationError::UnsupportedToken(order.sell_token))
+            }
+        }
+        // The receiver pays transfer fees of the buy token so the settlement still works. Quotes
+        // account for the fee in the buy amount.
+        if !self
+            .bad_token_detector
+            .detect(order.buy_token)
+            .await
+            .map_err(PartialValidationError::Other)?
+            .is_buyable()
+        {
+            return Err(PartialValidationError::UnsupportedToken(order.buy_token));
         }
 
         Ok(())
@@ -332,29 +670,54 @@ impl OrderValidating for OrderValidator {
         domain_separator: &DomainSeparator,
         settlement_contract: H160,
     ) -> Result<(Order, Option<Quote>), ValidationError> {
-        let owner = order.verify_owner(domain_separator)?;
+        let owner = match order.verify_owner(domain_separator) {
+            Ok(owner) => owner,
+            Err(VerificationError::UnexpectedSigner(signer)) => {
+                return Err(match self.foreign_domain(&order) {
+                    Some(domain) => ValidationError::WrongDomain(domain),
+                    None => ValidationError::WrongOwner(signer),
+                })
+            }
+            Err(err) => return Err(err.into()),
+        };
         let signing_scheme = order.signature.scheme();
 
-        if let Signature::Eip1271(signature) = &order.signature {
-            self.signature_validator
-                .validate_signature(SignatureCheck {
-                    signer: owner,
-                    hash: hashed_eip712_message(domain_separator, &order.data.hash_struct()),
-                    signature: signature.to_owned(),
-                })
-                .await?;
+        if let Some(check) = SignatureCheck::for_signature(
+            &order.signature,
+            owner,
+            hashed_eip712_message(domain_separator, &order.data.hash_struct()),
+        ) {
+            self.signature_validator.validate_signature(check).await?;
         }
 
         if order.data.buy_amount.is_zero() || order.data.sell_amount.is_zero() {
             return Err(ValidationError::ZeroAmount);
         }
 
-        let liquidity_owner = self.liquidity_order_owners.contains(&owner);
+        let class = self.order_class(owner, order.class)?;
+        if order.quote_firmness.is_some() && class != OrderClass::Liquidity {
+            return Err(ValidationError::UnsupportedQuoteFirmness);
+        }
+        if let Some(min_partial_fill_amount) = order.min_partial_fill_amount {
+            let order_amount = match order.data.kind {
+                OrderKind::Sell => order.data.sell_amount,
+                OrderKind::Buy => order.data.buy_amount,
+            };
+            if !order.data.partially_fillable
+                || min_partial_fill_amount.is_zero()
+                || min_partial_fill_amount > order_amount
+            {
+                return Err(ValidationError::InvalidMinPartialFillAmount);
+            }
+        }
+        if matches!(&order.condition, Some(condition) if !condition.is_valid()) {
+            return Err(ValidationError::InvalidCondition);
+        }
         self.partial_validate(PreOrderData::from_order_creation(
             owner,
             &order.data,
             signing_scheme,
-            liquidity_owner,
+            class,
         ))
         .await
         .map_err(ValidationError::Partial)?;
@@ -370,7 +733,7 @@ impl OrderValidating for OrderValidator {
             app_data: order.data.app_data,
             quote_kind,
         };
-        let quote = if !liquidity_owner {
+        let quote = if ClassPolicy::of(class).requires_fee {
             Some(
                 get_quote_and_check_fee(
                     &*self.quoter,
@@ -385,8 +748,7 @@ impl OrderValidating for OrderValidator {
                 .await?,
             )
         } else {
-            // We don't try to get quotes for orders created by liqudity order
-            // owners for two reasons:
+            // We don't try to get quotes for liquidity orders for two reasons:
             // 1. They don't pay fees, meaning we don't need to know what the
             //    min fee amount is.
             // 2. We don't really care about the equivalent quote since they
@@ -399,6 +761,35 @@ impl OrderValidating for OrderValidator {
             .map(|quote| quote.data.fee_parameters.unsubsidized())
             .unwrap_or_default();
 
+        // The protocol fee is charged on the sell amount of the requested order class. For buy
+        // orders the signed sell amount includes slippage so the quoted sell amount is used when
+        // it is smaller, like the quote response does.
+        let protocol_fee_amount = match &quote {
+            Some(quote) => self.check_fee(class, &order.data, quote)?.amount,
+            None => U256::zero(),
+        };
+
+        // Market orders that are priced outside the market (i.e. buying
+        // more than the market can pay or selling less than the market wants)
+        // become limit orders. The reasoning is that these orders are not
+        // intended to be filled immediately and so need to be treated
+        // slightly differently by the protocol.
+        let class = match &quote {
+            Some(quote)
+                if class == OrderClass::Market
+                    && is_order_outside_market_price(
+                        &quote_parameters.sell_amount,
+                        &quote_parameters.buy_amount,
+                        quote,
+                    ) =>
+            {
+                let order_uid = order.data.uid(domain_separator, &owner);
+                tracing::debug!(%order_uid, ?owner, "order being flagged as outside market price");
+                OrderClass::Limit
+            }
+            _ => class,
+        };
+
         let min_balance = match minimum_balance(&order.data) {
             Some(amount) => amount,
             None => return Err(ValidationError::SellAmountOverflow),
@@ -418,23 +809,32 @@ impl OrderValidating for OrderValidator {
         {
             Ok(_) => (),
             Err(
-                TransferSimulationError::InsufficientAllowance
-                | TransferSimulationError::InsufficientBalance,
-            ) if signing_scheme == SigningScheme::PreSign => {
+                TransferSimulationError::InsufficientAllowance { .. }
+                | TransferSimulationError::InsufficientBalance { .. },
+            ) if signing_scheme.is_signed_by_transaction()
+                || ClassPolicy::of(class).balance_check == BalanceCheck::Auction =>
+            {
                 // We have an exception for pre-sign orders where they do not
                 // require sufficient balance or allowance. The idea, is that
                 // this allows smart contracts to place orders bundled with
                 // other transactions that either produce the required balance
                 // or set the allowance. This would, for example, allow a Gnosis
                 // Safe to bundle the pre-signature transaction with a WETH wrap
-                // and WETH approval to the vault relayer contract.
+                // and WETH approval to the vault relayer contract. Orders of
+                // classes that only need the balance once they get included in
+                // an auction are funded later as well.
             }
             Err(err) => match err {
-                TransferSimulationError::InsufficientAllowance => {
-                    return Err(ValidationError::InsufficientAllowance);
+                TransferSimulationError::InsufficientAllowance { allowance, spender } => {
+                    return Err(ValidationError::InsufficientAllowance(
+                        insufficient_allowance(&order.data, min_balance, allowance, spender),
+                    ));
                 }
-                TransferSimulationError::InsufficientBalance => {
-                    return Err(ValidationError::InsufficientBalance);
+                TransferSimulationError::InsufficientBalance { balance } => {
+                    return Err(ValidationError::InsufficientBalance(
+                        self.insufficient_balance(&order.data, owner, min_balance, balance)
+                            .await,
+                    ));
                 }
                 TransferSimulationError::TransferFailed => {
                     return Err(ValidationError::TransferSimulationFailed);
@@ -446,36 +846,144 @@ impl OrderValidating for OrderValidator {
             },
         }
 
-        // Orders that are placed and priced outside the market (i.e. buying
-        // more than the market can pay or selling less than the market wants)
-        // get flagged as liquidity orders. The reasoning is that these orders
-        // are not intended to be filled immediately and so need to be treated
-        // slightly differently by the protocol.
-        let is_liquidity_order = match &quote {
-            Some(quote)
-                if is_order_outside_market_price(
-                    &quote_parameters.sell_amount,
-                    &quote_parameters.buy_amount,
-                    quote,
-                ) =>
-            {
-                let order_uid = order.data.uid(domain_separator, &owner);
-                tracing::debug!(%order_uid, ?owner, "order being flagged as outside market price");
-                true
-            }
-            Some(_) => false,
-            None => true,
-        };
-
-        let order = Order::from_order_creation(
+        let mut order = Order::from_order_creation(
             &order,
             domain_separator,
             settlement_contract,
             full_fee_amount,
-            is_liquidity_order,
+            class,
         )?;
+        order.metadata.protocol_fee_amount = protocol_fee_amount;
+
+        // The cost of honoring the fee guarantee of an expired quote only gets charged once the
+        // order is otherwise valid so that rejected placements don't use up the budget.
+        if let Some(quote) = &quote {
+            if quote.fee_guarantee_cost.is_some()
+                && !self
+                    .quoter
+                    .claim_fee_guarantee(quote)
+                    .await
+                    .map_err(ValidationError::Other)?
+            {
+                return Err(ValidationError::InvalidQuote);
+            }
+        }
+
         Ok((order, quote))
     }
+
+    async fn validate_prospective_orders(
+        &self,
+        orders: &[OrderValidationRequest],
+    ) -> Vec<Result<(), ValidationError>> {
+        let prospective = join_all(
+            orders
+                .iter()
+                .map(|order| self.validate_prospective_order(order)),
+        )
+        .await;
+
+        let balance_query = |order: &OrderValidationRequest| Query {
+            owner: order.from,
+            token: order.data.sell_token,
+            source: order.data.sell_token_balance,
+        };
+        let needs_balance = |order: &OrderValidationRequest, prospective: &ProspectiveOrder| {
+            !order.signing_scheme.is_signed_by_transaction()
+                && ClassPolicy::of(prospective.class).balance_check == BalanceCheck::Placement
+        };
+        // Orders of the same owner, like a quote ladder, usually sell the same token.
+        let queries = orders
+            .iter()
+            .zip(&prospective)
+            .filter(|(order, prospective)| {
+                matches!(prospective, Ok(prospective) if needs_balance(order, prospective))
+            })
+            .map(|(order, _)| balance_query(order))
+            .unique()
+            .collect::<Vec<_>>();
+        let balances = queries
+            .iter()
+            .copied()
+            .zip(self.balance_fetcher.get_balances(&queries).await)
+            .collect::<HashMap<_, _>>();
+
+        let (balances, balance_query, needs_balance) = (&balances, &balance_query, &needs_balance);
+        join_all(
+            orders
+                .iter()
+                .zip(prospective)
+                .map(|(order, prospective)| async move {
+                    let prospective = prospective?;
+                    if !needs_balance(order, &prospective) {
+                        return Ok(());
+                    }
+                    let balance = match balances.get(&balance_query(order)) {
+                        Some(Ok(balance)) => *balance,
+                        Some(Err(err)) => {
+                            return Err(ValidationError::Other(anyhow!(
+                                "failed to fetch balance: {err:?}"
+                            )))
+                        }
+                        None => unreachable!("balance of every order that needs it is queried"),
+                    };
+                    if balance >= prospective.min_balance {
+                        return Ok(());
+                    }
+                    Err(ValidationError::InsufficientBalance(
+                        self.insufficient_balance(
+                            &order.data,
+                            order.from,
+                            prospective.min_balance,
+                            balance,
+                        )
+                        .await,
+                    ))
+                }),
+        )
+        .await
+    }
+
+    async fn validate_cancellation(
+        &self,
+        cancellation: &OrderCancellation,
+        owner: H160,
+        domain_separator: &DomainSeparator,
+    ) -> Result<(), CancellationValidationError> {
+        let scheme = cancellation.signature.scheme();
+        if scheme == SigningScheme::PreSign
+            || !self
+                .signature_configuration
+                .is_signing_scheme_supported(scheme)
+        {
+            return Err(CancellationValidationError::InvalidSignature);
+        }
+
+        match cancellation.validate(domain_separator) {
+            Ok(Some(signer)) if signer == owner => Ok(()),
+            Ok(Some(_)) => Err(CancellationValidationError::WrongOwner),
+            Ok(None) => {
+                let check = SignatureCheck::for_signature(
+                    &cancellation.signature,
+                    owner,
+                    hashed_eip712_message(domain_separator, &cancellation.hash_struct()),
+                )
+                .ok_or(CancellationValidationError::InvalidSignature)?;
+                self.signature_validator
+                    .validate_signature(check)
+                    .await
+                    .map_err(|err| match err {
+                        SignatureValidationError::Invalid => {
+                            CancellationValidationError::InvalidSignature
+                        }
+                        SignatureValidationError::Other(err) => {
+                            CancellationValidationError::Other(err.into())
+                        }
+                    })
+            }
+            Err(_) => Err(CancellationValidationError::InvalidSignature),
+        }
+    }
 }
 
 /// Signature configuration that is accepted by the orderbook.
@@ -518,7 +1026,7 @@ impl SignatureConfiguration {
 /// This also checks for orders selling wrapped native token for native token.
 fn has_same_buy_and_sell_token(order: &PreOrderData, native_token: &WETH9) -> bool {
     order.sell_token == order.buy_token
-        || (order.sell_token == native_token.address() && order.buy_token == BUY_ETH_ADDRESS)
+        || wrapped_native::is_unwrap(native_token.address(), order.sell_token, order.buy_token)
 }
 
 /// Min balance user must have in sell token for order to be accepted.
@@ -533,6 +1041,52 @@ fn minimum_balance(order: &OrderData) -> Option<U256> {
     order.sell_amount.checked_add(order.fee_amount)
 }
 
+fn insufficient_allowance(
+    order: &OrderData,
+    required: U256,
+    allowance: U256,
+    spender: H160,
+) -> InsufficientFunds {
+    InsufficientFunds {
+        sell_token: order.sell_token,
+        required,
+        available: allowance,
+        shortfall: required.saturating_sub(allowance),
+        approval_target: Some(spender),
+        suggestion: FundingSuggestion::Approve,
+    }
+}
+
+/// Suggests the amounts of an order with the same limit price and fee that the balance covers.
+fn insufficient_balance(order: &OrderData, required: U256, balance: U256) -> InsufficientFunds {
+    let sell_amount = balance.saturating_sub(order.fee_amount);
+    // Round the buy amount up so that the limit price doesn't get worse for the owner.
+    let buy_amount = (!order.sell_amount.is_zero())
+        .then(|| {
+            let original_sell_amount = U512::from(order.sell_amount);
+            (order.buy_amount.full_mul(sell_amount) + original_sell_amount - 1)
+                / original_sell_amount
+        })
+        .and_then(|buy_amount| U256::try_from(buy_amount).ok());
+    let suggestion = match buy_amount {
+        Some(buy_amount) if !sell_amount.is_zero() && !buy_amount.is_zero() => {
+            FundingSuggestion::LowerAmounts {
+                sell_amount,
+                buy_amount,
+            }
+        }
+        _ => FundingSuggestion::AddFunds,
+    };
+    InsufficientFunds {
+        sell_token: order.sell_token,
+        required,
+        available: balance,
+        shortfall: required.saturating_sub(balance),
+        approval_target: None,
+        suggestion,
+    }
+}
+
 /// Retrieves the quote for an order that is being created and verify that its
 /// fee is sufficient.
 ///
@@ -602,18 +1156,10 @@ fn convert_signing_scheme_into_quote_signing_scheme(
     scheme: SigningScheme,
     order_placement_via_api: bool,
 ) -> Result<QuoteSigningScheme, ValidationError> {
-    match (order_placement_via_api, scheme) {
-        (true, SigningScheme::Eip712) => Ok(QuoteSigningScheme::Eip712),
-        (true, SigningScheme::EthSign) => Ok(QuoteSigningScheme::EthSign),
-        (false, SigningScheme::Eip712) => Err(ValidationError::IncompatibleSigningScheme),
-        (false, SigningScheme::EthSign) => Err(ValidationError::IncompatibleSigningScheme),
-        (order_placement_via_api, SigningScheme::PreSign) => Ok(QuoteSigningScheme::PreSign {
-            onchain_order: !order_placement_via_api,
-        }),
-        (order_placement_via_api, SigningScheme::Eip1271) => Ok(QuoteSigningScheme::Eip1271 {
-            onchain_order: !order_placement_via_api,
-        }),
-    }
+    scheme
+        .implementation()
+        .quote_signing_scheme(!order_placement_via_api)
+        .ok_or(ValidationError::IncompatibleSigningScheme)
 }
 
 pub fn convert_signing_scheme_into_quote_kind(
@@ -644,10 +1190,14 @@ mod tests {
     };
     use anyhow::anyhow;
     use chrono::Utc;
-    use ethcontract::web3::signing::SecretKeyRef;
+    use ethcontract::web3::signing::{Key, SecretKeyRef};
     use maplit::hashset;
     use mockall::predicate::{always, eq};
-    use model::{app_id::AppId, order::OrderBuilder, signature::EcdsaSigningScheme};
+    use model::{
+        app_id::AppId,
+        order::{OrderBuilder, OrderCondition, QuoteFirmness},
+        signature::{EcdsaSigningScheme, Signature},
+    };
     use secp256k1::ONE_KEY;
 
     #[test]
@@ -892,7 +1442,8 @@ mod tests {
             Arc::new(MockOrderQuoting::new()),
             Arc::new(MockBalanceFetching::new()),
             Arc::new(MockSignatureValidating::new()),
-        );
+        )
+        .with_internal_balances();
         let order = || PreOrderData {
             valid_to: model::time::now_in_epoch_seconds()
                 + min_order_validity_period.as_secs() as u32
@@ -903,6 +1454,14 @@ mod tests {
         };
 
         assert!(validator.partial_validate(order()).await.is_ok());
+        assert!(validator
+            .partial_validate(PreOrderData {
+                sell_token_balance: SellTokenSource::Internal,
+                buy_token_balance: BuyTokenDestination::Internal,
+                ..order()
+            })
+            .await
+            .is_ok());
         assert!(validator
             .partial_validate(PreOrderData {
                 valid_to: u32::MAX,
@@ -911,10 +1470,20 @@ mod tests {
             })
             .await
             .is_ok());
+        for class in [OrderClass::Limit, OrderClass::Twap] {
+            assert!(validator
+                .partial_validate(PreOrderData {
+                    valid_to: u32::MAX,
+                    class,
+                    ..order()
+                })
+                .await
+                .is_ok());
+        }
         assert!(validator
             .partial_validate(PreOrderData {
                 partially_fillable: true,
-                is_liquidity_order: true,
+                class: OrderClass::Liquidity,
                 owner: liquidity_order_owner,
                 valid_to: u32::MAX,
                 ..order()
@@ -923,6 +1492,52 @@ mod tests {
             .is_ok());
     }
 
+    #[tokio::test]
+    async fn pre_validate_fee_on_transfer_tokens() {
+        let good_token = H160::from_low_u64_be(1);
+        let fee_token = H160::from_low_u64_be(2);
+        let mut bad_token_detector = MockBadTokenDetecting::new();
+        bad_token_detector
+            .expect_detect()
+            .with(eq(good_token))
+            .returning(|_| Ok(TokenQuality::Good));
+        bad_token_detector
+            .expect_detect()
+            .with(eq(fee_token))
+            .returning(|_| Ok(TokenQuality::FeeOnTransfer { fee_bps: 100 }));
+
+        let validator = OrderValidator::new(
+            Box::new(MockCodeFetching::new()),
+            dummy_contract!(WETH9, [0xef; 20]),
+            hashset!(),
+            hashset!(),
+            Duration::from_secs(1),
+            Duration::from_secs(100),
+            SignatureConfiguration::all(),
+            Arc::new(bad_token_detector),
+            Arc::new(MockOrderQuoting::new()),
+            Arc::new(MockBalanceFetching::new()),
+            Arc::new(MockSignatureValidating::new()),
+        );
+        let order = |sell_token, buy_token| PreOrderData {
+            valid_to: model::time::now_in_epoch_seconds() + 2,
+            sell_token,
+            buy_token,
+            ..Default::default()
+        };
+
+        assert!(validator
+            .partial_validate(order(good_token, fee_token))
+            .await
+            .is_ok());
+        assert!(matches!(
+            validator
+                .partial_validate(order(fee_token, good_token))
+                .await,
+            Err(PartialValidationError::FeeOnTransferSellToken(token)) if token == fee_token
+        ));
+    }
+
     #[tokio::test]
     async fn post_validate_ok() {
         let mut order_quoter = MockOrderQuoting::new();
@@ -999,13 +1614,17 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn post_validate_err_zero_amount() {
+    async fn post_validate_requires_protocol_fee() {
         let mut order_quoter = MockOrderQuoting::new();
         let mut bad_token_detector = MockBadTokenDetecting::new();
         let mut balance_fetcher = MockBalanceFetching::new();
-        order_quoter
-            .expect_find_quote()
-            .returning(|_, _| Ok(Default::default()));
+        order_quoter.expect_find_quote().returning(|_, _| {
+            Ok(Quote {
+                sell_amount: 1_000.into(),
+                fee_amount: 10.into(),
+                ..Default::default()
+            })
+        });
         bad_token_detector
             .expect_detect()
             .returning(|_| Ok(TokenQuality::Good));
@@ -1024,32 +1643,285 @@ mod tests {
             Arc::new(order_quoter),
             Arc::new(balance_fetcher),
             Arc::new(MockSignatureValidating::new()),
-        );
-        let order = OrderCreation {
+        )
+        .with_protocol_fee(ProtocolFee {
+            market_bps: 20,
+            ..Default::default()
+        });
+        let creation = |fee_amount: u64| OrderCreation {
             data: OrderData {
                 valid_to: model::time::now_in_epoch_seconds() + 2,
                 sell_token: H160::from_low_u64_be(1),
                 buy_token: H160::from_low_u64_be(2),
-                buy_amount: U256::from(0),
-                sell_amount: U256::from(0),
+                buy_amount: U256::from(1),
+                sell_amount: U256::from(1_000),
+                fee_amount: fee_amount.into(),
                 ..Default::default()
             },
             ..Default::default()
         };
+
+        let (order, _) = validator
+            .validate_and_construct_order(creation(12), &Default::default(), Default::default())
+            .await
+            .unwrap();
+        assert_eq!(order.metadata.protocol_fee_amount, 2.into());
+
         let result = validator
-            .validate_and_construct_order(order, &Default::default(), Default::default())
+            .validate_and_construct_order(creation(11), &Default::default(), Default::default())
             .await;
-        dbg!(&result);
-        assert!(matches!(result, Err(ValidationError::ZeroAmount)));
+        assert!(matches!(result, Err(ValidationError::InsufficientFee)));
     }
 
     #[tokio::test]
-    async fn post_validate_err_wrong_owner() {
-        let mut order_quoter = MockOrderQuoting::new();
-        let mut bad_token_detector = MockBadTokenDetecting::new();
-        let mut balance_fetcher = MockBalanceFetching::new();
-        order_quoter
-            .expect_find_quote()
+    async fn post_validate_claims_fee_guarantee_of_valid_orders() {
+        let validator = |can_transfer: bool, claimed: Option<bool>| {
+            let mut order_quoter = MockOrderQuoting::new();
+            let mut bad_token_detector = MockBadTokenDetecting::new();
+            let mut balance_fetcher = MockBalanceFetching::new();
+            order_quoter.expect_find_quote().returning(|_, _| {
+                Ok(Quote {
+                    id: Some(42),
+                    sell_amount: 1_000.into(),
+                    fee_amount: 10.into(),
+                    fee_guarantee_cost: Some(1e-3),
+                    ..Default::default()
+                })
+            });
+            match claimed {
+                Some(claimed) => order_quoter
+                    .expect_claim_fee_guarantee()
+                    .times(1)
+                    .returning(move |_| Ok(claimed)),
+                None => order_quoter.expect_claim_fee_guarantee().never(),
+            };
+            bad_token_detector
+                .expect_detect()
+                .returning(|_| Ok(TokenQuality::Good));
+            balance_fetcher
+                .expect_can_transfer()
+                .returning(move |_, _, _, _| {
+                    if can_transfer {
+                        Ok(())
+                    } else {
+                        Err(TransferSimulationError::TransferFailed)
+                    }
+                });
+            OrderValidator::new(
+                Box::new(MockCodeFetching::new()),
+                dummy_contract!(WETH9, [0xef; 20]),
+                hashset!(),
+                hashset!(),
+                Duration::from_secs(1),
+                Duration::from_secs(100),
+                SignatureConfiguration::all(),
+                Arc::new(bad_token_detector),
+                Arc::new(order_quoter),
+                Arc::new(balance_fetcher),
+                Arc::new(MockSignatureValidating::new()),
+            )
+        };
+        let creation = || OrderCreation {
+            data: OrderData {
+                valid_to: model::time::now_in_epoch_seconds() + 2,
+                sell_token: H160::from_low_u64_be(1),
+                buy_token: H160::from_low_u64_be(2),
+                buy_amount: U256::from(1),
+                sell_amount: U256::from(1_000),
+                fee_amount: U256::from(10),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert!(validator(true, Some(true))
+            .validate_and_construct_order(creation(), &Default::default(), Default::default())
+            .await
+            .is_ok());
+
+        // The budget can't cover the guarantee anymore.
+        let result = validator(true, Some(false))
+            .validate_and_construct_order(creation(), &Default::default(), Default::default())
+            .await;
+        assert!(matches!(result, Err(ValidationError::InvalidQuote)));
+
+        // Rejected orders don't charge the budget.
+        let result = validator(false, None)
+            .validate_and_construct_order(creation(), &Default::default(), Default::default())
+            .await;
+        assert!(matches!(
+            result,
+            Err(ValidationError::TransferSimulationFailed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn post_validate_err_zero_amount() {
+        let mut order_quoter = MockOrderQuoting::new();
+        let mut bad_token_detector = MockBadTokenDetecting::new();
+        let mut balance_fetcher = MockBalanceFetching::new();
+        order_quoter
+            .expect_find_quote()
+            .returning(|_, _| Ok(Default::default()));
+        bad_token_detector
+            .expect_detect()
+            .returning(|_| Ok(TokenQuality::Good));
+        balance_fetcher
+            .expect_can_transfer()
+            .returning(|_, _, _, _| Ok(()));
+        let validator = OrderValidator::new(
+            Box::new(MockCodeFetching::new()),
+            dummy_contract!(WETH9, [0xef; 20]),
+            hashset!(),
+            hashset!(),
+            Duration::from_secs(1),
+            Duration::from_secs(100),
+            SignatureConfiguration::all(),
+            Arc::new(bad_token_detector),
+            Arc::new(order_quoter),
+            Arc::new(balance_fetcher),
+            Arc::new(MockSignatureValidating::new()),
+        );
+        let order = OrderCreation {
+            data: OrderData {
+                valid_to: model::time::now_in_epoch_seconds() + 2,
+                sell_token: H160::from_low_u64_be(1),
+                buy_token: H160::from_low_u64_be(2),
+                buy_amount: U256::from(0),
+                sell_amount: U256::from(0),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let result = validator
+            .validate_and_construct_order(order, &Default::default(), Default::default())
+            .await;
+        dbg!(&result);
+        assert!(matches!(result, Err(ValidationError::ZeroAmount)));
+    }
+
+    #[tokio::test]
+    async fn post_validate_err_quote_firmness_of_user_order() {
+        let validator = OrderValidator::new(
+            Box::new(MockCodeFetching::new()),
+            dummy_contract!(WETH9, [0xef; 20]),
+            hashset!(),
+            hashset!(),
+            Duration::from_secs(1),
+            Duration::from_secs(100),
+            SignatureConfiguration::all(),
+            Arc::new(MockBadTokenDetecting::new()),
+            Arc::new(MockOrderQuoting::new()),
+            Arc::new(MockBalanceFetching::new()),
+            Arc::new(MockSignatureValidating::new()),
+        );
+        let order = OrderCreation {
+            data: OrderData {
+                valid_to: model::time::now_in_epoch_seconds() + 2,
+                sell_token: H160::from_low_u64_be(1),
+                buy_token: H160::from_low_u64_be(2),
+                buy_amount: U256::from(1),
+                sell_amount: U256::from(1),
+                ..Default::default()
+            },
+            quote_firmness: Some(QuoteFirmness {
+                expiry_block: Some(1),
+                last_look: false,
+            }),
+            ..Default::default()
+        };
+        let result = validator
+            .validate_and_construct_order(order, &Default::default(), Default::default())
+            .await;
+        assert!(matches!(
+            result,
+            Err(ValidationError::UnsupportedQuoteFirmness)
+        ));
+    }
+
+    #[tokio::test]
+    async fn post_validate_err_invalid_min_partial_fill_amount() {
+        let validator = OrderValidator::new(
+            Box::new(MockCodeFetching::new()),
+            dummy_contract!(WETH9, [0xef; 20]),
+            hashset!(),
+            hashset!(),
+            Duration::from_secs(1),
+            Duration::from_secs(100),
+            SignatureConfiguration::all(),
+            Arc::new(MockBadTokenDetecting::new()),
+            Arc::new(MockOrderQuoting::new()),
+            Arc::new(MockBalanceFetching::new()),
+            Arc::new(MockSignatureValidating::new()),
+        );
+        let order = |partially_fillable, min_partial_fill_amount: u64| OrderCreation {
+            data: OrderData {
+                valid_to: model::time::now_in_epoch_seconds() + 2,
+                sell_token: H160::from_low_u64_be(1),
+                buy_token: H160::from_low_u64_be(2),
+                buy_amount: U256::from(1),
+                sell_amount: U256::from(10),
+                kind: OrderKind::Sell,
+                partially_fillable,
+                ..Default::default()
+            },
+            min_partial_fill_amount: Some(min_partial_fill_amount.into()),
+            ..Default::default()
+        };
+        for order in [order(false, 5), order(true, 0), order(true, 11)] {
+            let result = validator
+                .validate_and_construct_order(order, &Default::default(), Default::default())
+                .await;
+            assert!(matches!(
+                result,
+                Err(ValidationError::InvalidMinPartialFillAmount)
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn post_validate_err_invalid_condition() {
+        let validator = OrderValidator::new(
+            Box::new(MockCodeFetching::new()),
+            dummy_contract!(WETH9, [0xef; 20]),
+            hashset!(),
+            hashset!(),
+            Duration::from_secs(1),
+            Duration::from_secs(100),
+            SignatureConfiguration::all(),
+            Arc::new(MockBadTokenDetecting::new()),
+            Arc::new(MockOrderQuoting::new()),
+            Arc::new(MockBalanceFetching::new()),
+            Arc::new(MockSignatureValidating::new()),
+        );
+        let order = OrderCreation {
+            data: OrderData {
+                valid_to: model::time::now_in_epoch_seconds() + 2,
+                sell_token: H160::from_low_u64_be(1),
+                buy_token: H160::from_low_u64_be(2),
+                buy_amount: U256::from(1),
+                sell_amount: U256::from(10),
+                ..Default::default()
+            },
+            condition: Some(OrderCondition::TimeWindow {
+                not_before: Some(2),
+                not_after: Some(1),
+            }),
+            ..Default::default()
+        };
+        let result = validator
+            .validate_and_construct_order(order, &Default::default(), Default::default())
+            .await;
+        assert!(matches!(result, Err(ValidationError::InvalidCondition)));
+    }
+
+    #[tokio::test]
+    async fn post_validate_err_wrong_owner() {
+        let mut order_quoter = MockOrderQuoting::new();
+        let mut bad_token_detector = MockBadTokenDetecting::new();
+        let mut balance_fetcher = MockBalanceFetching::new();
+        order_quoter
+            .expect_find_quote()
             .returning(|_, _| Ok(Default::default()));
         bad_token_detector
             .expect_detect()
@@ -1088,6 +1960,70 @@ mod tests {
         assert!(matches!(result, Err(ValidationError::WrongOwner(_))));
     }
 
+    #[tokio::test]
+    async fn post_validate_err_wrong_domain() {
+        let domain = DomainParameters {
+            chain_id: 1,
+            verifying_contract: H160([0x90; 20]),
+        };
+        let other_domain = DomainParameters {
+            chain_id: 100,
+            ..domain
+        };
+        let validator = OrderValidator::new(
+            Box::new(MockCodeFetching::new()),
+            dummy_contract!(WETH9, [0xef; 20]),
+            hashset!(),
+            hashset!(),
+            Duration::from_secs(1),
+            Duration::from_secs(100),
+            SignatureConfiguration::all(),
+            Arc::new(MockBadTokenDetecting::new()),
+            Arc::new(MockOrderQuoting::new()),
+            Arc::new(MockBalanceFetching::new()),
+            Arc::new(MockSignatureValidating::new()),
+        )
+        .with_known_domains(vec![other_domain]);
+        let order = |domain: &DomainParameters| -> OrderCreation {
+            OrderBuilder::default()
+                .with_valid_to(u32::MAX)
+                .with_sell_token(H160::from_low_u64_be(1))
+                .with_sell_amount(1.into())
+                .with_buy_token(H160::from_low_u64_be(2))
+                .with_buy_amount(1.into())
+                .sign_with(
+                    EcdsaSigningScheme::Eip712,
+                    &domain.separator(),
+                    SecretKeyRef::new(&ONE_KEY),
+                )
+                .build()
+                .into()
+        };
+
+        let result = validator
+            .validate_and_construct_order(
+                order(&other_domain),
+                &domain.separator(),
+                domain.verifying_contract,
+            )
+            .await;
+        assert!(matches!(result, Err(ValidationError::WrongDomain(d)) if d == other_domain));
+
+        // Signatures for unknown domains can't be told apart from signatures of other owners.
+        let unknown_domain = DomainParameters {
+            chain_id: 5,
+            ..domain
+        };
+        let result = validator
+            .validate_and_construct_order(
+                order(&unknown_domain),
+                &domain.separator(),
+                domain.verifying_contract,
+            )
+            .await;
+        assert!(matches!(result, Err(ValidationError::WrongOwner(_))));
+    }
+
     #[tokio::test]
     async fn post_validate_err_getting_quote() {
         let mut order_quoter = MockOrderQuoting::new();
@@ -1244,7 +2180,9 @@ mod tests {
             .returning(|_| Ok(TokenQuality::Good));
         balance_fetcher
             .expect_can_transfer()
-            .returning(|_, _, _, _| Err(TransferSimulationError::InsufficientBalance));
+            .returning(|_, _, _, _| {
+                Err(TransferSimulationError::InsufficientBalance { balance: 0.into() })
+            });
         let validator = OrderValidator::new(
             Box::new(MockCodeFetching::new()),
             dummy_contract!(WETH9, [0xef; 20]),
@@ -1273,7 +2211,269 @@ mod tests {
             .validate_and_construct_order(order, &Default::default(), Default::default())
             .await;
         dbg!(&result);
-        assert!(matches!(result, Err(ValidationError::InsufficientBalance)));
+        assert!(matches!(
+            result,
+            Err(ValidationError::InsufficientBalance(InsufficientFunds {
+                shortfall,
+                suggestion: FundingSuggestion::AddFunds,
+                ..
+            })) if shortfall == U256::one()
+        ));
+    }
+
+    #[tokio::test]
+    async fn post_validate_suggests_wrapping_native_token() {
+        let mut order_quoter = MockOrderQuoting::new();
+        let mut bad_token_detector = MockBadTokenDetecting::new();
+        let mut balance_fetcher = MockBalanceFetching::new();
+        order_quoter
+            .expect_find_quote()
+            .returning(|_, _| Ok(Default::default()));
+        bad_token_detector
+            .expect_detect()
+            .returning(|_| Ok(TokenQuality::Good));
+        balance_fetcher
+            .expect_can_transfer()
+            .returning(|_, _, _, _| {
+                Err(TransferSimulationError::InsufficientBalance { balance: 4.into() })
+            });
+        // Only orders selling the wrapped native token look at the native balance.
+        balance_fetcher
+            .expect_native_balance()
+            .times(2)
+            .returning(|_| Ok(6.into()));
+        let validator = OrderValidator::new(
+            Box::new(MockCodeFetching::new()),
+            dummy_contract!(WETH9, [0xef; 20]),
+            hashset!(),
+            hashset!(),
+            Duration::from_secs(1),
+            Duration::from_secs(100),
+            SignatureConfiguration::all(),
+            Arc::new(bad_token_detector),
+            Arc::new(order_quoter),
+            Arc::new(balance_fetcher),
+            Arc::new(MockSignatureValidating::new()),
+        );
+        let order = |sell_token: H160, sell_amount: u64| OrderCreation {
+            data: OrderData {
+                valid_to: model::time::now_in_epoch_seconds() + 2,
+                sell_token,
+                buy_token: H160::from_low_u64_be(2),
+                buy_amount: U256::from(1),
+                sell_amount: sell_amount.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let weth = H160([0xef; 20]);
+        let validator = &validator;
+        let suggestion = |order| async move {
+            match validator
+                .validate_and_construct_order(order, &Default::default(), Default::default())
+                .await
+            {
+                Err(ValidationError::InsufficientBalance(funds)) => funds.suggestion,
+                result => panic!("unexpected result {:?}", result),
+            }
+        };
+
+        assert_eq!(
+            suggestion(order(weth, 10)).await,
+            FundingSuggestion::WrapNativeToken { amount: 6.into() }
+        );
+        // The native balance doesn't cover the shortfall.
+        assert_eq!(
+            suggestion(order(weth, 11)).await,
+            FundingSuggestion::LowerAmounts {
+                sell_amount: 4.into(),
+                buy_amount: 1.into(),
+            }
+        );
+        assert_eq!(
+            suggestion(order(H160::from_low_u64_be(3), 10)).await,
+            FundingSuggestion::LowerAmounts {
+                sell_amount: 4.into(),
+                buy_amount: 1.into(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn validates_prospective_orders_with_shared_balance_lookups() {
+        let mut order_quoter = MockOrderQuoting::new();
+        let mut bad_token_detector = MockBadTokenDetecting::new();
+        let mut balance_fetcher = MockBalanceFetching::new();
+        order_quoter
+            .expect_find_quote()
+            .returning(|_, _| Ok(Default::default()));
+        bad_token_detector
+            .expect_detect()
+            .returning(|_| Ok(TokenQuality::Good));
+        balance_fetcher
+            .expect_get_balances()
+            .times(1)
+            .withf(|queries| queries.len() == 1)
+            .returning(|_| vec![Ok(8.into())]);
+        let validator = OrderValidator::new(
+            Box::new(MockCodeFetching::new()),
+            dummy_contract!(WETH9, [0xef; 20]),
+            hashset!(),
+            hashset!(),
+            Duration::from_secs(1),
+            Duration::from_secs(100),
+            SignatureConfiguration::all(),
+            Arc::new(bad_token_detector),
+            Arc::new(order_quoter),
+            Arc::new(balance_fetcher),
+            Arc::new(MockSignatureValidating::new()),
+        );
+        let order = |sell_amount: u64| OrderValidationRequest {
+            data: OrderData {
+                valid_to: model::time::now_in_epoch_seconds() + 2,
+                sell_token: H160::from_low_u64_be(1),
+                buy_token: H160::from_low_u64_be(2),
+                buy_amount: U256::from(1),
+                sell_amount: sell_amount.into(),
+                ..Default::default()
+            },
+            from: H160::from_low_u64_be(3),
+            ..Default::default()
+        };
+
+        let results = validator
+            .validate_prospective_orders(&[order(5), order(10), order(0)])
+            .await;
+        assert!(results[0].is_ok());
+        assert!(matches!(
+            results[1],
+            Err(ValidationError::InsufficientBalance(InsufficientFunds { shortfall, .. }))
+                if shortfall == U256::from(2)
+        ));
+        assert!(matches!(results[2], Err(ValidationError::ZeroAmount)));
+    }
+
+    #[tokio::test]
+    async fn validates_cancellations() {
+        let validator = |signature_validator| {
+            OrderValidator::new(
+                Box::new(MockCodeFetching::new()),
+                dummy_contract!(WETH9, [0xef; 20]),
+                hashset!(),
+                hashset!(),
+                Duration::from_secs(1),
+                Duration::from_secs(100),
+                SignatureConfiguration::all(),
+                Arc::new(MockBadTokenDetecting::new()),
+                Arc::new(MockOrderQuoting::new()),
+                Arc::new(MockBalanceFetching::new()),
+                Arc::new(signature_validator),
+            )
+        };
+        let domain_separator = DomainSeparator::default();
+
+        let cancellation = OrderCancellation::default();
+        let signer = SecretKeyRef::new(&ONE_KEY).address();
+        let ecdsa = validator(MockSignatureValidating::new());
+        assert!(ecdsa
+            .validate_cancellation(&cancellation, signer, &domain_separator)
+            .await
+            .is_ok());
+        assert!(matches!(
+            ecdsa
+                .validate_cancellation(&cancellation, H160([1; 20]), &domain_separator)
+                .await,
+            Err(CancellationValidationError::WrongOwner)
+        ));
+        let presign = OrderCancellation {
+            signature: Signature::PreSign,
+            ..cancellation.clone()
+        };
+        assert!(matches!(
+            ecdsa
+                .validate_cancellation(&presign, signer, &domain_separator)
+                .await,
+            Err(CancellationValidationError::InvalidSignature)
+        ));
+
+        let safe = H160([2; 20]);
+        let cancellation = OrderCancellation {
+            signature: Signature::Eip1271(vec![1, 2, 3]),
+            ..cancellation
+        };
+        let hash = hashed_eip712_message(&domain_separator, &cancellation.hash_struct());
+        let mut signature_validator = MockSignatureValidating::new();
+        signature_validator
+            .expect_validate_signature()
+            .with(eq(SignatureCheck {
+                signer: safe,
+                hash,
+                signature: vec![1, 2, 3],
+            }))
+            .returning(|_| Ok(()));
+        signature_validator
+            .expect_validate_signature()
+            .returning(|_| Err(SignatureValidationError::Invalid));
+        let eip1271 = validator(signature_validator);
+        assert!(eip1271
+            .validate_cancellation(&cancellation, safe, &domain_separator)
+            .await
+            .is_ok());
+        assert!(matches!(
+            eip1271
+                .validate_cancellation(&cancellation, H160([3; 20]), &domain_separator)
+                .await,
+            Err(CancellationValidationError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn suggests_how_to_fund_orders() {
+        let order = OrderData {
+            sell_token: H160::from_low_u64_be(1),
+            sell_amount: 1000.into(),
+            buy_amount: 3001.into(),
+            fee_amount: 10.into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            insufficient_balance(&order, 1010.into(), 510.into()),
+            InsufficientFunds {
+                sell_token: H160::from_low_u64_be(1),
+                required: 1010.into(),
+                available: 510.into(),
+                shortfall: 500.into(),
+                approval_target: None,
+                suggestion: FundingSuggestion::LowerAmounts {
+                    sell_amount: 500.into(),
+                    buy_amount: 1501.into(),
+                },
+            }
+        );
+        // The buy amount gets rounded up to not worsen the limit price.
+        assert_eq!(
+            insufficient_balance(&order, 1010.into(), 11.into()).suggestion,
+            FundingSuggestion::LowerAmounts {
+                sell_amount: 1.into(),
+                buy_amount: 4.into(),
+            }
+        );
+        assert_eq!(
+            insufficient_balance(&order, 1010.into(), 10.into()).suggestion,
+            FundingSuggestion::AddFunds
+        );
+        assert_eq!(
+            insufficient_allowance(&order, 1010.into(), 10.into(), H160::from_low_u64_be(2)),
+            InsufficientFunds {
+                sell_token: H160::from_low_u64_be(1),
+                required: 1010.into(),
+                available: 10.into(),
+                shortfall: 1000.into(),
+                approval_target: Some(H160::from_low_u64_be(2)),
+                suggestion: FundingSuggestion::Approve,
+            }
+        );
     }
 
     #[tokio::test]
@@ -1335,7 +2535,7 @@ mod tests {
     #[tokio::test]
     async fn allows_insufficient_allowance_and_balance_for_presign_orders() {
         macro_rules! assert_allows_failed_transfer {
-            ($err:ident) => {
+            ($err:expr, $expected:pat) => {
                 let mut order_quoter = MockOrderQuoting::new();
                 let mut bad_token_detector = MockBadTokenDetecting::new();
                 let mut balance_fetcher = MockBalanceFetching::new();
@@ -1347,7 +2547,7 @@ mod tests {
                     .returning(|_| Ok(TokenQuality::Good));
                 balance_fetcher
                     .expect_can_transfer()
-                    .returning(|_, _, _, _| Err(TransferSimulationError::$err));
+                    .returning(|_, _, _, _| Err($err));
                 let validator = OrderValidator::new(
                     Box::new(MockCodeFetching::new()),
                     dummy_contract!(WETH9, [0xef; 20]),
@@ -1386,7 +2586,7 @@ mod tests {
                                 Default::default()
                             )
                             .await,
-                        Err(ValidationError::$err)
+                        Err($expected)
                     ));
                 }
 
@@ -1403,8 +2603,168 @@ mod tests {
             };
         }
 
-        assert_allows_failed_transfer!(InsufficientAllowance);
-        assert_allows_failed_transfer!(InsufficientBalance);
+        assert_allows_failed_transfer!(
+            TransferSimulationError::InsufficientAllowance {
+                allowance: 0.into(),
+                spender: H160::from_low_u64_be(3),
+            },
+            ValidationError::InsufficientAllowance(InsufficientFunds {
+                approval_target: Some(_),
+                suggestion: FundingSuggestion::Approve,
+                ..
+            })
+        );
+        assert_allows_failed_transfer!(
+            TransferSimulationError::InsufficientBalance { balance: 0.into() },
+            ValidationError::InsufficientBalance(_)
+        );
+    }
+
+    #[tokio::test]
+    async fn post_validate_err_unsupported_order_class() {
+        let liquidity_order_owner = H160::from_low_u64_be(0x42);
+        let validator = OrderValidator::new(
+            Box::new(MockCodeFetching::new()),
+            dummy_contract!(WETH9, [0xef; 20]),
+            hashset!(),
+            hashset!(liquidity_order_owner),
+            Duration::from_secs(1),
+            Duration::from_secs(100),
+            SignatureConfiguration::all(),
+            Arc::new(MockBadTokenDetecting::new()),
+            Arc::new(MockOrderQuoting::new()),
+            Arc::new(MockBalanceFetching::new()),
+            Arc::new(MockSignatureValidating::new()),
+        );
+        let order = OrderCreation {
+            data: OrderData {
+                valid_to: model::time::now_in_epoch_seconds() + 2,
+                sell_token: H160::from_low_u64_be(1),
+                buy_token: H160::from_low_u64_be(2),
+                buy_amount: U256::from(1),
+                sell_amount: U256::from(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let result = validator
+            .validate_and_construct_order(
+                OrderCreation {
+                    class: Some(OrderClass::Liquidity),
+                    ..order.clone()
+                },
+                &Default::default(),
+                Default::default(),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(ValidationError::UnsupportedOrderClass(
+                OrderClass::Liquidity
+            ))
+        ));
+
+        let result = validator
+            .validate_and_construct_order(
+                OrderCreation {
+                    from: Some(liquidity_order_owner),
+                    signature: Signature::PreSign,
+                    class: Some(OrderClass::Market),
+                    ..order
+                },
+                &Default::default(),
+                Default::default(),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(ValidationError::UnsupportedOrderClass(OrderClass::Market))
+        ));
+    }
+
+    #[tokio::test]
+    async fn post_validate_orders_by_class() {
+        let mut order_quoter = MockOrderQuoting::new();
+        let mut bad_token_detector = MockBadTokenDetecting::new();
+        let mut balance_fetcher = MockBalanceFetching::new();
+        order_quoter.expect_find_quote().returning(|_, _| {
+            Ok(Quote {
+                sell_amount: 1.into(),
+                buy_amount: 1.into(),
+                ..Default::default()
+            })
+        });
+        bad_token_detector
+            .expect_detect()
+            .returning(|_| Ok(TokenQuality::Good));
+        balance_fetcher
+            .expect_can_transfer()
+            .returning(|_, _, _, _| {
+                Err(TransferSimulationError::InsufficientBalance { balance: 0.into() })
+            });
+        let validator = OrderValidator::new(
+            Box::new(MockCodeFetching::new()),
+            dummy_contract!(WETH9, [0xef; 20]),
+            hashset!(),
+            hashset!(),
+            Duration::from_secs(1),
+            Duration::from_secs(100),
+            SignatureConfiguration::all(),
+            Arc::new(bad_token_detector),
+            Arc::new(order_quoter),
+            Arc::new(balance_fetcher),
+            Arc::new(MockSignatureValidating::new()),
+        );
+        let order = |valid_to, buy_amount: u64, class| OrderCreation {
+            data: OrderData {
+                valid_to,
+                sell_token: H160::from_low_u64_be(1),
+                buy_token: H160::from_low_u64_be(2),
+                buy_amount: buy_amount.into(),
+                sell_amount: 1.into(),
+                ..Default::default()
+            },
+            class,
+            ..Default::default()
+        };
+
+        // Limit orders and TWAP parts can be valid for a long time and get funded later.
+        for class in [OrderClass::Limit, OrderClass::Twap] {
+            let (order, _) = validator
+                .validate_and_construct_order(
+                    order(u32::MAX, 1, Some(class)),
+                    &Default::default(),
+                    Default::default(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(order.metadata.class, class);
+        }
+
+        // Market orders priced outside of the market become limit orders.
+        let (order, _) = validator
+            .validate_and_construct_order(
+                order(model::time::now_in_epoch_seconds() + 2, 2, None),
+                &Default::default(),
+                Default::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(order.metadata.class, OrderClass::Limit);
+
+        assert!(matches!(
+            validator
+                .validate_and_construct_order(
+                    order(u32::MAX, 1, None),
+                    &Default::default(),
+                    Default::default()
+                )
+                .await,
+            Err(ValidationError::Partial(
+                PartialValidationError::ExcessiveValidTo
+            ))
+        ));
     }
 
     #[tokio::test]
@@ -1,12 +1,13 @@
 use crate::{
     debug_bytes,
+    error_class::{ClassifyError, ErrorClass},
     rate_limiter::{back_off, RateLimiter, RateLimiterError},
 };
 use anyhow::Result;
 use derivative::Derivative;
 use ethcontract::{H160, U256};
 use model::u256_decimal;
-use reqwest::{Client, RequestBuilder, Url};
+use reqwest::{Client, RequestBuilder, Response, StatusCode, Url};
 use serde::{
     de::{DeserializeOwned, Error},
     Deserialize, Deserializer, Serialize,
@@ -38,12 +39,7 @@ impl ParaswapApi for DefaultParaswapApi {
     async fn price(&self, query: PriceQuery) -> Result<PriceResponse, ParaswapResponseError> {
         let url = query.into_url(&self.partner);
         tracing::debug!("Querying Paraswap price API: {}", url);
-        let request = self.client.get(url).send();
-
-        let response = match &self.rate_limiter {
-            Some(limiter) => limiter.execute(request, back_off::on_http_429).await??,
-            _ => request.await?,
-        };
+        let response = self.send(self.client.get(url)).await?;
         let status = response.status();
         let text = response.text().await?;
         tracing::debug!(%status, %text, "Response from Paraswap price API");
@@ -58,13 +54,25 @@ impl ParaswapApi for DefaultParaswapApi {
             query,
             partner: &self.partner,
         };
-        let request = query.into_request(&self.client).send();
+        let response = self.send(query.into_request(&self.client)).await?;
+        let response_text = response.text().await?;
+        parse_paraswap_response_text(&response_text)
+    }
+}
+
+impl DefaultParaswapApi {
+    async fn send(&self, request: RequestBuilder) -> Result<Response, ParaswapResponseError> {
+        let request = request.send();
         let response = match &self.rate_limiter {
             Some(limiter) => limiter.execute(request, back_off::on_http_429).await??,
             _ => request.await?,
         };
-        let response_text = response.text().await?;
-        parse_paraswap_response_text(&response_text)
+        // Rate limited responses don't contain a regular error message so they get reported as
+        // such before trying to parse them.
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            return Err(RateLimiterError::RateLimited.into());
+        }
+        Ok(response)
     }
 }
 
@@ -99,16 +107,18 @@ pub enum ParaswapResponseError {
     RateLimited(#[from] RateLimiterError),
 }
 
-impl ParaswapResponseError {
-    /// Returns true if the error is considered intermittent and the same
-    /// ParaSwap request can be retried.
-    pub fn is_retryable(&self) -> bool {
-        // We don't retry insufficient liquidity errors because it is unlikely a
-        // more liquidity will appear by the time we would retry.
-        matches!(
-            self,
-            ParaswapResponseError::Request(_) | ParaswapResponseError::Retryable(_),
-        )
+impl ClassifyError for ParaswapResponseError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::Request(err) => err.class(),
+            Self::Json(_) => ErrorClass::Invalid,
+            // We don't retry insufficient liquidity errors because it is unlikely a
+            // more liquidity will appear by the time we would retry.
+            Self::InsufficientLiquidity(_) => ErrorClass::Fatal,
+            Self::Retryable(_) => ErrorClass::Transient,
+            Self::Other(_) => ErrorClass::Fatal,
+            Self::RateLimited(_) => ErrorClass::RateLimited,
+        }
     }
 }
 
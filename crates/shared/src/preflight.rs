@@ -0,0 +1,218 @@
+//! Checks of the environment a binary gets started in. Running them with `--preflight` reports
+//! misconfigurations like a node on the wrong network or an unreachable API right away instead of
+//! them surfacing minutes after start in the form of confusing errors.
+
+use crate::Web3;
+use anyhow::{anyhow, ensure, Context, Result};
+use contracts::{GPv2Settlement, WETH9};
+use ethcontract::H160;
+use futures::Future;
+use model::DomainSeparator;
+use reqwest::Client;
+use std::fmt::{self, Display, Formatter};
+use url::Url;
+
+/// The collected outcomes of the checks of a binary.
+#[derive(Default)]
+pub struct Preflight {
+    checks: Vec<(String, Result<String>)>,
+}
+
+impl Preflight {
+    /// Runs a check and records its outcome. Successful checks return what they found, which gets
+    /// displayed in the report and returned for dependent checks.
+    pub async fn check<T: Display>(
+        &mut self,
+        name: &str,
+        check: impl Future<Output = Result<T>>,
+    ) -> Option<T> {
+        let result = check.await;
+        self.checks.push((
+            name.to_string(),
+            result
+                .as_ref()
+                .map(|found| found.to_string())
+                .map_err(|err| anyhow!("{err:#}")),
+        ));
+        result.ok()
+    }
+
+    /// Checks the node and the contracts every service depends on.
+    pub async fn check_chain(&mut self, web3: &Web3) {
+        if let Some(node) = self.check("node", node(web3)).await {
+            self.check(
+                "settlement contract",
+                settlement_contract(web3, node.chain_id),
+            )
+            .await;
+        }
+        self.check("native token", async {
+            let native_token = WETH9::deployed(web3).await?;
+            contract_code(web3, native_token.address()).await
+        })
+        .await;
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.checks.iter().all(|(_, result)| result.is_ok())
+    }
+
+    /// Prints the report and exits the process, with a non-zero exit code if any check failed.
+    pub fn exit(self) -> ! {
+        println!("{}", self);
+        std::process::exit(if self.is_ok() { 0 } else { 1 })
+    }
+}
+
+impl Display for Preflight {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for (name, result) in &self.checks {
+            match result {
+                Ok(details) => writeln!(f, "[ OK ] {name}: {details}")?,
+                Err(err) => writeln!(f, "[FAIL] {name}: {err:#}")?,
+            }
+        }
+        let failed = self
+            .checks
+            .iter()
+            .filter(|(_, result)| result.is_err())
+            .count();
+        write!(f, "{} checks, {} failed", self.checks.len(), failed)
+    }
+}
+
+pub struct Node {
+    pub chain_id: u64,
+    pub block: u64,
+}
+
+impl Display for Node {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "chain id {} at block {}", self.chain_id, self.block)
+    }
+}
+
+/// Checks that the node is reachable and that its chain id and network id agree.
+pub async fn node(web3: &Web3) -> Result<Node> {
+    let chain_id = web3
+        .eth()
+        .chain_id()
+        .await
+        .context("failed to get chain id")?
+        .as_u64();
+    let network_id = web3
+        .net()
+        .version()
+        .await
+        .context("failed to get network id")?;
+    let block = web3
+        .eth()
+        .block_number()
+        .await
+        .context("failed to get block number")?;
+    ensure!(
+        network_id == chain_id.to_string(),
+        "chain id {chain_id} does not match network id {network_id}"
+    );
+    Ok(Node {
+        chain_id,
+        block: block.as_u64(),
+    })
+}
+
+/// Checks that there is contract code deployed at the address.
+pub async fn contract_code(web3: &Web3, address: H160) -> Result<String> {
+    let code = web3
+        .eth()
+        .code(address, None)
+        .await
+        .context("failed to get code")?;
+    ensure!(!code.0.is_empty(), "no contract deployed at {address:?}");
+    Ok(format!("{} bytes of code at {address:?}", code.0.len()))
+}
+
+/// Checks that the settlement contract is deployed on the node's network and that its constants
+/// match the ones of this binary.
+pub async fn settlement_contract(web3: &Web3, chain_id: u64) -> Result<String> {
+    let contract = GPv2Settlement::deployed(web3)
+        .await
+        .context("no settlement contract deployment for this network")?;
+    verify_deployed_contract_constants(&contract, chain_id).await?;
+    Ok(format!(
+        "constants match the deployment at {:?}",
+        contract.address()
+    ))
+}
+
+/// Checks that an HTTP server responds at the URL. Any response counts because the check is only
+/// about reachability.
+pub async fn url_reachable(client: &Client, url: &Url) -> Result<String> {
+    let response = client
+        .get(url.clone())
+        .send()
+        .await
+        .with_context(|| format!("{url} is not reachable"))?;
+    Ok(format!("{url} responded with {}", response.status()))
+}
+
+/// Checks that none of the tables of the database are missing, which means that all migrations
+/// have been applied.
+pub async fn database(
+    missing_tables: impl Future<Output = Result<Vec<&'static str>>>,
+) -> Result<&'static str> {
+    let missing_tables = missing_tables.await.context("failed to query database")?;
+    ensure!(
+        missing_tables.is_empty(),
+        "tables {missing_tables:?} are missing, not all migrations have been applied"
+    );
+    Ok("all tables exist")
+}
+
+/**
+ * Check that important constants such as the EIP 712 Domain Separator and Order Type Hash used in this binary match the ones on the deployed contract instance.
+ * Signature inconsistencies due to a mismatch of these constants are hard to debug.
+ */
+pub async fn verify_deployed_contract_constants(
+    contract: &GPv2Settlement,
+    chain_id: u64,
+) -> Result<()> {
+    let web3 = contract.raw_instance().web3();
+    let bytecode = hex::encode(
+        web3.eth()
+            .code(contract.address(), None)
+            .await
+            .context("Could not load deployed bytecode")?
+            .0,
+    );
+
+    let domain_separator = DomainSeparator::new(chain_id, contract.address());
+    if !bytecode.contains(&hex::encode(domain_separator.0)) {
+        return Err(anyhow!("Bytecode did not contain domain separator"));
+    }
+
+    if !bytecode.contains(&hex::encode(model::order::OrderData::TYPE_HASH)) {
+        return Err(anyhow!("Bytecode did not contain order type hash"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn report_fails_if_any_check_fails() {
+        let mut preflight = Preflight::default();
+        preflight.check("first", async { Ok("fine") }).await;
+        assert!(preflight.is_ok());
+        let result = preflight
+            .check("second", async { Result::<u64>::Err(anyhow!("broken")) })
+            .await;
+        assert!(result.is_none());
+        assert!(!preflight.is_ok());
+        assert_eq!(
+            preflight.to_string(),
+            "[ OK ] first: fine\n[FAIL] second: broken\n2 checks, 1 failed"
+        );
+    }
+}
@@ -0,0 +1,46 @@
+use crate::transport::MAX_BATCH_SIZE;
+use anyhow::Result;
+use contracts::GPv2Settlement;
+use ethcontract::{batch::CallBatch, Bytes};
+use futures::future;
+use model::order::OrderUid;
+
+#[mockall::automock]
+#[async_trait::async_trait]
+pub trait PresignatureChecking: Send + Sync {
+    /// Returns for every order whether it is currently pre-signed in the settlement contract.
+    async fn are_presigned(&self, uids: Vec<OrderUid>) -> Vec<Result<bool>>;
+}
+
+/// Checks the `preSignature` storage of the settlement contract directly instead of waiting for
+/// the `PreSignature` events to get indexed. All orders get checked in a single batch request.
+pub struct Web3PresignatureChecker {
+    settlement: GPv2Settlement,
+}
+
+impl Web3PresignatureChecker {
+    pub fn new(settlement: GPv2Settlement) -> Self {
+        Self { settlement }
+    }
+}
+
+#[async_trait::async_trait]
+impl PresignatureChecking for Web3PresignatureChecker {
+    async fn are_presigned(&self, uids: Vec<OrderUid>) -> Vec<Result<bool>> {
+        let mut batch = CallBatch::new(self.settlement.raw_instance().web3().transport().clone());
+        let calls = uids
+            .into_iter()
+            .map(|uid| {
+                let call = self
+                    .settlement
+                    .pre_signature(Bytes(uid.0.to_vec()))
+                    .batch_call(&mut batch);
+                // The storage holds a magic value for pre-signed orders and is zero otherwise.
+                async move { Result::<bool>::Ok(!call.await?.is_zero()) }
+            })
+            .collect::<Vec<_>>();
+
+        batch.execute_all(MAX_BATCH_SIZE).await;
+        future::join_all(calls).await
+    }
+}
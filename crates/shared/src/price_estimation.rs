@@ -6,15 +6,16 @@ pub mod http;
 pub mod instrumented;
 pub mod native;
 pub mod native_price_cache;
+pub mod native_price_coalescing;
 pub mod oneinch;
 pub mod paraswap;
 pub mod sanitized;
 pub mod zeroex;
 
 use crate::{
-    bad_token::BadTokenDetecting,
     conversions::U256Ext,
-    rate_limiter::{RateLimiter, RateLimiterError},
+    error_class::{ClassifyError, ErrorClass},
+    rate_limiter::{back_off, RateLimiter, RateLimiterError},
 };
 use anyhow::Result;
 use ethcontract::{H160, U256};
@@ -83,6 +84,17 @@ impl Clone for PriceEstimationError {
     }
 }
 
+impl ClassifyError for PriceEstimationError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::UnsupportedToken(_) | Self::NoLiquidity => ErrorClass::Fatal,
+            Self::ZeroAmount | Self::UnsupportedOrderType => ErrorClass::Invalid,
+            Self::Other(err) => err.class(),
+            Self::RateLimited(err) => err.class(),
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
 pub struct Query {
     pub sell_token: H160,
@@ -188,22 +200,6 @@ where
         .boxed()
 }
 
-pub async fn ensure_token_supported(
-    token: H160,
-    bad_token_detector: &dyn BadTokenDetecting,
-) -> Result<(), PriceEstimationError> {
-    match bad_token_detector.detect(token).await {
-        Ok(quality) => {
-            if quality.is_good() {
-                Ok(())
-            } else {
-                Err(PriceEstimationError::UnsupportedToken(token))
-            }
-        }
-        Err(err) => Err(PriceEstimationError::Other(err)),
-    }
-}
-
 pub fn amounts_to_price(sell_amount: U256, buy_amount: U256) -> Option<BigRational> {
     if buy_amount.is_zero() {
         return None;
@@ -225,9 +221,10 @@ pub async fn rate_limited<T>(
         let result = estimation.await;
         (start.elapsed(), result)
     };
-    let rate_limited_estimation = rate_limiter.execute(timed_estimation, |(estimation_time, _)| {
-        *estimation_time > HEALTHY_PRICE_ESTIMATION_TIME
-    });
+    let rate_limited_estimation =
+        rate_limiter.execute(timed_estimation, |(estimation_time, result)| {
+            *estimation_time > HEALTHY_PRICE_ESTIMATION_TIME || back_off::on_rate_limited(result)
+        });
     match rate_limited_estimation.await {
         Ok((_estimation_time, Ok(result))) => Ok(result),
         // return original PriceEstimationError
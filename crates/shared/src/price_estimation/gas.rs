@@ -33,6 +33,9 @@ pub const GAS_REFUNDS: u64 = 13_000;
 pub const SETTLEMENT_SINGLE_TRADE: u64 =
     INITIALIZATION_COST + SETTLEMENT + TRADE + 2 * ERC20_TRANSFER - GAS_REFUNDS;
 
+/// the part of a settlement that costs the same no matter how many trades it contains
+pub const SETTLEMENT_FIXED: u64 = INITIALIZATION_COST + SETTLEMENT;
+
 /// settlement overhead for one trade
 pub const SETTLEMENT_OVERHEAD: u64 = SETTLEMENT + TRADE + 2 * ERC20_TRANSFER;
 
@@ -109,6 +109,9 @@ impl HttpPriceEstimator {
                     token: self.native_token,
                 },
                 is_liquidity_order: false,
+                quote_expiry_block: None,
+                quote_last_look: false,
+                min_partial_fill_amount: None,
                 mandatory: true,
                 has_atomic_execution: false,
             },
@@ -119,6 +122,7 @@ impl HttpPriceEstimator {
         let gas_model = GasModel {
             native_token: self.native_token,
             gas_price: gas_price.to_f64_lossy(),
+            realized_gas: None,
         };
 
         let (uniswap_pools, balancer_pools, uniswap_v3_pools) = futures::try_join!(
@@ -265,7 +269,7 @@ impl HttpPriceEstimator {
                     BigInt::from(*pool.state.fee.numer()),
                     BigInt::from(*pool.state.fee.denom()),
                 )),
-                cost: gas_model.cost_for_gas(pool.gas_stats.mean_gas),
+                cost: gas_model.uniswap_v3_cost(pool.gas_stats.mean_gas),
                 parameters: AmmParameters::Concentrated(ConcentratedPoolParameters { pool }),
                 mandatory: false,
             })
@@ -27,6 +27,7 @@ struct Inner {
     cache: Mutex<HashMap<H160, CachedPrice>>,
     estimator: Box<dyn NativePriceEstimating>,
     max_age: Duration,
+    hot_tokens: Vec<H160>,
     metrics: &'static Metrics,
 }
 
@@ -85,11 +86,18 @@ pub struct CachingNativePriceEstimator(Arc<Inner>);
 impl CachingNativePriceEstimator {
     /// Creates new CachingNativePriceEstimator using `estimator` to calculate native prices which
     /// get cached a duration of `max_age`.
-    pub fn new(estimator: Box<dyn NativePriceEstimating>, max_age: Duration) -> Self {
+    /// Prices of `hot_tokens` get precomputed and kept up to date by the maintenance task
+    /// regardless of whether they have been requested.
+    pub fn new(
+        estimator: Box<dyn NativePriceEstimating>,
+        max_age: Duration,
+        hot_tokens: Vec<H160>,
+    ) -> Self {
         Self(Arc::new(Inner {
             estimator,
             cache: Mutex::new(Default::default()),
             max_age,
+            hot_tokens,
             metrics: Metrics::instance(global_metrics::get_metric_storage_registry()).unwrap(),
         }))
     }
@@ -98,6 +106,7 @@ impl CachingNativePriceEstimator {
     /// Only soon to be outdated prices get updated and recently used prices have a higher priority.
     /// If `update_size` is `Some(n)` at most `n` prices get updated per interval.
     /// If `update_size` is `None` no limit gets applied.
+    /// Hot tokens are always updated and do not count towards `update_size`.
     pub fn spawn_maintenance_task(&self, update_interval: Duration, update_size: Option<usize>) {
         tokio::spawn(update_recently_used_outdated_prices(
             Arc::downgrade(&self.0),
@@ -155,25 +164,39 @@ async fn update_recently_used_outdated_prices(
 ) {
     while let Some(inner) = inner.upgrade() {
         let now = Instant::now();
-
-        let mut outdated_entries: Vec<_> = inner
-            .cache
-            .lock()
-            .unwrap()
-            .iter()
-            .filter(|(_, cached)| {
-                now.saturating_duration_since(cached.updated_at)
-                    .saturating_add(prefetch_time)
-                    > inner.max_age
-            })
-            .map(|(token, cached)| (*token, cached.requested_at))
-            .collect();
+        let is_outdated = |cached: &CachedPrice| {
+            now.saturating_duration_since(cached.updated_at)
+                .saturating_add(prefetch_time)
+                > inner.max_age
+        };
+
+        let (hot_tokens, mut outdated_entries) = {
+            let cache = inner.cache.lock().unwrap();
+            let hot_tokens: Vec<_> = inner
+                .hot_tokens
+                .iter()
+                .filter(|token| cache.get(*token).map(is_outdated).unwrap_or(true))
+                .copied()
+                .collect();
+            let outdated_entries: Vec<_> = cache
+                .iter()
+                .filter(|(token, cached)| {
+                    !inner.hot_tokens.contains(*token) && is_outdated(*cached)
+                })
+                .map(|(token, cached)| (*token, cached.requested_at))
+                .collect();
+            (hot_tokens, outdated_entries)
+        };
         outdated_entries.sort_by_key(|entry| std::cmp::Reverse(entry.1));
 
-        let tokens_to_update: Vec<_> = outdated_entries
-            .iter()
-            .take(update_size.unwrap_or(outdated_entries.len()))
-            .map(|(token, _)| *token)
+        let tokens_to_update: Vec<_> = hot_tokens
+            .into_iter()
+            .chain(
+                outdated_entries
+                    .iter()
+                    .take(update_size.unwrap_or(outdated_entries.len()))
+                    .map(|(token, _)| *token),
+            )
             .collect();
 
         if !tokens_to_update.is_empty() {
@@ -208,7 +231,7 @@ mod tests {
             });
 
         let estimator =
-            CachingNativePriceEstimator::new(Box::new(inner), Duration::from_millis(30));
+            CachingNativePriceEstimator::new(Box::new(inner), Duration::from_millis(30), vec![]);
 
         for _ in 0..10 {
             let tokens = &[token(0)];
@@ -232,7 +255,7 @@ mod tests {
             });
 
         let estimator =
-            CachingNativePriceEstimator::new(Box::new(inner), Duration::from_millis(30));
+            CachingNativePriceEstimator::new(Box::new(inner), Duration::from_millis(30), vec![]);
 
         for _ in 0..10 {
             let tokens = &[token(0)];
@@ -287,7 +310,7 @@ mod tests {
             });
 
         let estimator =
-            CachingNativePriceEstimator::new(Box::new(inner), Duration::from_millis(30));
+            CachingNativePriceEstimator::new(Box::new(inner), Duration::from_millis(30), vec![]);
         let _join_handle = tokio::spawn(update_recently_used_outdated_prices(
             Arc::downgrade(&estimator.0),
             Duration::from_millis(50),
@@ -345,7 +368,7 @@ mod tests {
             });
 
         let estimator =
-            CachingNativePriceEstimator::new(Box::new(inner), Duration::from_millis(30));
+            CachingNativePriceEstimator::new(Box::new(inner), Duration::from_millis(30), vec![]);
 
         let _join_handle = tokio::spawn(update_recently_used_outdated_prices(
             Arc::downgrade(&estimator.0),
@@ -374,4 +397,42 @@ mod tests {
             assert_eq!(price.as_ref().unwrap().to_i64().unwrap(), 2);
         }
     }
+
+    #[tokio::test]
+    async fn maintenance_precomputes_hot_tokens() {
+        let mut inner = MockNativePriceEstimating::new();
+        // hot tokens get estimated without being requested and regardless of the update size
+        inner
+            .expect_estimate_native_prices()
+            .times(1)
+            .returning(move |tokens| {
+                assert_eq!(tokens, [token(0), token(1)]);
+                futures::stream::iter([(0, Ok(1.0)), (1, Ok(2.0))]).boxed()
+            });
+
+        let estimator = CachingNativePriceEstimator::new(
+            Box::new(inner),
+            Duration::from_secs(10),
+            vec![token(0), token(1)],
+        );
+        let _join_handle = tokio::spawn(update_recently_used_outdated_prices(
+            Arc::downgrade(&estimator.0),
+            Duration::from_millis(50),
+            Some(0),
+            Duration::ZERO,
+        ));
+
+        // wait for maintenance cycle
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // both prices come from the cache without calling the wrapped estimator again
+        let results = estimator
+            .estimate_native_prices(&[token(0), token(1)])
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(results[0].0, 0);
+        assert_eq!(results[0].1.as_ref().unwrap().to_i64().unwrap(), 1);
+        assert_eq!(results[1].0, 1);
+        assert_eq!(results[1].1.as_ref().unwrap().to_i64().unwrap(), 2);
+    }
 }
@@ -0,0 +1,45 @@
+use crate::{
+    price_estimation::native::{
+        native_single_estimate, NativePriceEstimateResult, NativePriceEstimating,
+    },
+    request_sharing::CrossProcessSharing,
+};
+use futures::stream::{BoxStream, FuturesUnordered, StreamExt};
+use primitive_types::H160;
+
+/// Wrapper around a native price estimator that coalesces identical lookups of all processes that
+/// share the Redis instance, so that horizontally scaled API replicas don't all estimate the price
+/// of the same token at the same time.
+pub struct CoalescingNativePriceEstimator {
+    inner: Box<dyn NativePriceEstimating>,
+    sharing: CrossProcessSharing,
+}
+
+impl CoalescingNativePriceEstimator {
+    pub fn new(inner: Box<dyn NativePriceEstimating>, sharing: CrossProcessSharing) -> Self {
+        Self { inner, sharing }
+    }
+}
+
+impl NativePriceEstimating for CoalescingNativePriceEstimator {
+    fn estimate_native_prices<'a>(
+        &'a self,
+        tokens: &'a [H160],
+    ) -> BoxStream<'_, (usize, NativePriceEstimateResult)> {
+        tokens
+            .iter()
+            .enumerate()
+            .map(|(i, token)| async move {
+                let result = self
+                    .sharing
+                    .get_or_compute(
+                        &format!("{:?}", token),
+                        native_single_estimate(self.inner.as_ref(), token),
+                    )
+                    .await;
+                (i, result)
+            })
+            .collect::<FuturesUnordered<_>>()
+            .boxed()
+    }
+}
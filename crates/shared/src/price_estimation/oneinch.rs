@@ -62,7 +62,7 @@ impl OneInchPriceEstimator {
                 if e.description == "insufficient liquidity" {
                     return Err(PriceEstimationError::NoLiquidity);
                 }
-                Err(PriceEstimationError::Other(anyhow::anyhow!(e.description)))
+                Err(PriceEstimationError::Other(e.into()))
             }
         }
     }
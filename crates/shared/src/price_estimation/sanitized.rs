@@ -4,15 +4,13 @@ use crate::{
         gas::{GAS_PER_WETH_UNWRAP, GAS_PER_WETH_WRAP},
         Estimate, PriceEstimateResult, PriceEstimating, PriceEstimationError, Query,
     },
+    wrapped_native,
 };
 use anyhow::anyhow;
 use futures::StreamExt;
 use model::order::BUY_ETH_ADDRESS;
 use primitive_types::H160;
-use std::{
-    collections::{HashMap, HashSet},
-    sync::Arc,
-};
+use std::{collections::HashMap, sync::Arc};
 
 /// Verifies that buy and sell tokens are supported and handles
 /// ETH as buy token appropriately.
@@ -35,32 +33,26 @@ impl SanitizedPriceEstimator {
         }
     }
 
-    async fn get_token_quality_errors(
+    async fn get_token_qualities(
         &self,
         queries: impl Iterator<Item = &Query>,
-    ) -> HashMap<H160, PriceEstimationError> {
-        let mut token_quality_errors: HashMap<H160, PriceEstimationError> = Default::default();
-        let mut checked_tokens = HashSet::<H160>::default();
+    ) -> HashMap<H160, Result<TokenQuality, PriceEstimationError>> {
+        let mut token_qualities = HashMap::new();
 
         // TODO should this be parallelised?
         for token in queries.flat_map(|query| [query.buy_token, query.sell_token]) {
-            if checked_tokens.contains(&token) {
+            if token_qualities.contains_key(&token) {
                 continue;
             }
 
-            match self.bad_token_detector.detect(token).await {
-                Err(err) => {
-                    token_quality_errors.insert(token, PriceEstimationError::Other(err));
-                }
-                Ok(TokenQuality::Bad { .. }) => {
-                    token_quality_errors
-                        .insert(token, PriceEstimationError::UnsupportedToken(token));
-                }
-                _ => (),
-            };
-            checked_tokens.insert(token);
+            let quality = self
+                .bad_token_detector
+                .detect(token)
+                .await
+                .map_err(PriceEstimationError::Other);
+            token_qualities.insert(token, quality);
         }
-        token_quality_errors
+        token_qualities
     }
 
     /// Removes easy queries from the input and returns their estimates.
@@ -68,16 +60,26 @@ impl SanitizedPriceEstimator {
         &self,
         queries: &mut Vec<(usize, Query)>,
     ) -> Vec<(usize, PriceEstimateResult)> {
-        let token_quality_errors = self
-            .get_token_quality_errors(queries.iter().map(|(_, query)| query))
+        let token_qualities = self
+            .get_token_qualities(queries.iter().map(|(_, query)| query))
             .await;
         let mut results = Vec::new();
         queries.retain(|(index, query)| {
-            for token in [&query.buy_token, &query.sell_token] {
-                if let Some(err) = token_quality_errors.get(token) {
-                    results.push((*index, Err(err.clone())));
-                    return false;
-                }
+            // Fee-on-transfer tokens can be bought but not sold, the same as when placing orders.
+            let checks: [(&H160, fn(&TokenQuality) -> bool); 2] = [
+                (&query.buy_token, TokenQuality::is_buyable),
+                (&query.sell_token, TokenQuality::is_good),
+            ];
+            for (token, is_supported) in checks {
+                let err = match token_qualities.get(token) {
+                    Some(Err(err)) => err.clone(),
+                    Some(Ok(quality)) if !is_supported(quality) => {
+                        PriceEstimationError::UnsupportedToken(*token)
+                    }
+                    _ => continue,
+                };
+                results.push((*index, Err(err)));
+                return false;
             }
 
             if query.buy_token == query.sell_token {
@@ -90,7 +92,7 @@ impl SanitizedPriceEstimator {
                 return false;
             }
 
-            if query.sell_token == self.native_token && query.buy_token == BUY_ETH_ADDRESS {
+            if wrapped_native::is_unwrap(self.native_token, query.sell_token, query.buy_token) {
                 let estimation = Estimate {
                     out_amount: query.in_amount,
                     gas: GAS_PER_WETH_UNWRAP,
@@ -100,7 +102,7 @@ impl SanitizedPriceEstimator {
                 return false;
             }
 
-            if query.sell_token == BUY_ETH_ADDRESS && query.buy_token == self.native_token {
+            if wrapped_native::is_wrap(self.native_token, query.sell_token, query.buy_token) {
                 let estimation = Estimate {
                     out_amount: query.in_amount,
                     gas: GAS_PER_WETH_WRAP,
@@ -430,6 +432,62 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn fee_on_transfer_tokens_can_only_be_bought() {
+        let fee_token = H160([0x34; 20]);
+        let mut bad_token_detector = MockBadTokenDetecting::new();
+        bad_token_detector.expect_detect().returning(move |token| {
+            if token == fee_token {
+                Ok(TokenQuality::FeeOnTransfer { fee_bps: 100 })
+            } else {
+                Ok(TokenQuality::Good)
+            }
+        });
+
+        let queries = [
+            Query {
+                sell_token: H160::from_low_u64_le(1),
+                buy_token: fee_token,
+                in_amount: 1.into(),
+                kind: OrderKind::Sell,
+            },
+            Query {
+                sell_token: fee_token,
+                buy_token: H160::from_low_u64_le(1),
+                in_amount: 1.into(),
+                kind: OrderKind::Sell,
+            },
+        ];
+
+        let expected_forwarded_queries = [queries[0]];
+        let mut wrapped_estimator = Box::new(MockPriceEstimating::new());
+        wrapped_estimator
+            .expect_estimates()
+            .times(1)
+            .withf(move |arg: &[Query]| arg.iter().eq(expected_forwarded_queries.iter()))
+            .returning(|_| {
+                futures::stream::iter([Ok(Estimate {
+                    out_amount: 1.into(),
+                    gas: 100,
+                })])
+                .enumerate()
+                .boxed()
+            });
+
+        let sanitized_estimator = SanitizedPriceEstimator {
+            inner: wrapped_estimator,
+            bad_token_detector: Arc::new(bad_token_detector),
+            native_token: H160::from_low_u64_le(42),
+        };
+
+        let result = vec_estimates(&sanitized_estimator, &queries).await;
+        assert!(result[0].is_ok());
+        assert!(matches!(
+            result[1].as_ref().unwrap_err(),
+            PriceEstimationError::UnsupportedToken(token) if *token == fee_token
+        ));
+    }
+
     #[tokio::test]
     async fn easy_queries_come_first() {
         let mut bad_token_detector = MockBadTokenDetecting::new();
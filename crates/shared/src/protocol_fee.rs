@@ -0,0 +1,138 @@
+//! Protocol fees are charged per trade in addition to the fee that covers the settlement costs.
+//! They are a configurable fraction of the sell amount per order class and are charged in the sell
+//! token as part of the signed fee amount. The orderbook remembers the protocol's share of the
+//! signed fee so that solvers don't get credited with it and so that it can be accounted for when
+//! the order gets traded.
+
+use crate::token_amount::TokenAmount;
+use model::order::OrderClass;
+use std::fmt::{self, Display, Formatter};
+
+/// Arguments related to protocol fees.
+#[derive(clap::Parser)]
+pub struct Arguments {
+    /// Protocol fee in basis points of the sell amount for market orders.
+    #[clap(long, env, default_value = "0")]
+    pub protocol_fee_bps_market: u32,
+
+    /// Protocol fee in basis points of the sell amount for limit orders.
+    #[clap(long, env, default_value = "0")]
+    pub protocol_fee_bps_limit: u32,
+
+    /// Protocol fee in basis points of the sell amount for TWAP orders.
+    #[clap(long, env, default_value = "0")]
+    pub protocol_fee_bps_twap: u32,
+}
+
+impl Display for Arguments {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "protocol_fee_bps_market: {}",
+            self.protocol_fee_bps_market
+        )?;
+        writeln!(f, "protocol_fee_bps_limit: {}", self.protocol_fee_bps_limit)?;
+        writeln!(f, "protocol_fee_bps_twap: {}", self.protocol_fee_bps_twap)?;
+        Ok(())
+    }
+}
+
+/// The protocol fee configuration. The default charges no protocol fees.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ProtocolFee {
+    pub market_bps: u32,
+    pub limit_bps: u32,
+    pub twap_bps: u32,
+}
+
+impl From<&Arguments> for ProtocolFee {
+    fn from(args: &Arguments) -> Self {
+        Self {
+            market_bps: args.protocol_fee_bps_market,
+            limit_bps: args.protocol_fee_bps_limit,
+            twap_bps: args.protocol_fee_bps_twap,
+        }
+    }
+}
+
+impl ProtocolFee {
+    /// The protocol fee of the order class in basis points. Liquidity orders never pay protocol
+    /// fees because they are placed by market makers to be matched with user orders.
+    pub fn bps(&self, class: OrderClass) -> u32 {
+        match class {
+            OrderClass::Market => self.market_bps,
+            OrderClass::Limit => self.limit_bps,
+            OrderClass::Twap => self.twap_bps,
+            OrderClass::Liquidity => 0,
+        }
+    }
+
+    /// The protocol fee in sell token of an order of the class that sells the specified amount
+    /// excluding fees.
+    pub fn fee(&self, class: OrderClass, sell_amount: &TokenAmount) -> TokenAmount {
+        sell_amount.bps(self.bps(class))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitive_types::{H160, U256};
+
+    fn sell_amount(amount: U256) -> TokenAmount {
+        TokenAmount::new(H160([1; 20]), amount)
+    }
+
+    #[test]
+    fn fee_depends_on_order_class() {
+        let protocol_fee = ProtocolFee {
+            market_bps: 10,
+            limit_bps: 20,
+            twap_bps: 30,
+        };
+        let sell_amount = sell_amount(1_000_000.into());
+        assert_eq!(
+            protocol_fee.fee(OrderClass::Market, &sell_amount).amount,
+            1_000.into()
+        );
+        assert_eq!(
+            protocol_fee.fee(OrderClass::Limit, &sell_amount).amount,
+            2_000.into()
+        );
+        assert_eq!(
+            protocol_fee.fee(OrderClass::Twap, &sell_amount).amount,
+            3_000.into()
+        );
+        assert_eq!(
+            protocol_fee.fee(OrderClass::Liquidity, &sell_amount).amount,
+            0.into()
+        );
+    }
+
+    #[test]
+    fn fee_rounds_down_and_does_not_overflow() {
+        let protocol_fee = ProtocolFee {
+            market_bps: 10_000,
+            limit_bps: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            protocol_fee
+                .fee(OrderClass::Limit, &sell_amount(9_999.into()))
+                .amount,
+            0.into()
+        );
+        assert_eq!(
+            protocol_fee
+                .fee(OrderClass::Market, &sell_amount(U256::MAX))
+                .amount,
+            U256::MAX
+        );
+        assert_eq!(
+            ProtocolFee::default()
+                .fee(OrderClass::Market, &sell_amount(U256::MAX))
+                .amount,
+            0.into()
+        );
+    }
+}
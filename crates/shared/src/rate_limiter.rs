@@ -1,3 +1,4 @@
+use crate::error_class::{ClassifyError, ErrorClass};
 use anyhow::{ensure, Result};
 use std::{
     fmt::{Display, Formatter},
@@ -172,6 +173,12 @@ pub enum RateLimiterError {
     RateLimited,
 }
 
+impl ClassifyError for RateLimiterError {
+    fn class(&self) -> ErrorClass {
+        ErrorClass::RateLimited
+    }
+}
+
 impl RateLimiter {
     /// If a task produces a result which indicates rate limiting is required future requests
     /// will get dropped for some time. Every successive response like that increases that time exponentially.
@@ -213,12 +220,18 @@ impl RateLimiter {
 
 /// Shared module with common back-off checks.
 pub mod back_off {
+    use crate::error_class::{ClassifyError, ErrorClass};
     use reqwest::Response;
 
     /// Determines if the HTTP response indicates that the API should back off for a while.
     pub fn on_http_429(response: &Result<Response, reqwest::Error>) -> bool {
         matches!(response, Ok(response) if response.status() == 429)
     }
+
+    /// Determines if the result is an error that the API returned because it rate limited us.
+    pub fn on_rate_limited<T, E: ClassifyError>(result: &Result<T, E>) -> bool {
+        matches!(result, Err(err) if err.class() == ErrorClass::RateLimited)
+    }
 }
 
 #[cfg(test)]
@@ -306,4 +319,15 @@ mod tests {
             rate_limiter.strategy().get_current_back_off()
         );
     }
+
+    #[test]
+    fn backs_off_on_rate_limited_errors() {
+        assert!(back_off::on_rate_limited::<(), _>(&Err(
+            RateLimiterError::RateLimited
+        )));
+        assert!(!back_off::on_rate_limited::<_, RateLimiterError>(&Ok(())));
+        assert!(!back_off::on_rate_limited::<(), _>(&Err(anyhow::anyhow!(
+            "error"
+        ))));
+    }
 }
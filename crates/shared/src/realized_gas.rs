@@ -0,0 +1,182 @@
+//! Gas actually used by the interactions of mined settlements.
+//!
+//! Receipts only contain the gas used by a settlement as a whole. The gas left after subtracting
+//! the fixed settlement and trade overhead gets split between the settlement's interactions in
+//! proportion to their current estimates. Over many settlements with different mixes of
+//! interactions the medians converge towards what each class of interaction really costs, which
+//! is a better guess for solvers than the static constants we started with.
+
+use crate::price_estimation::gas::{
+    ERC20_TRANSFER, GAS_PER_BALANCER_SWAP, GAS_PER_UNISWAP, GAS_PER_WETH_UNWRAP,
+    INITIALIZATION_COST, SETTLEMENT, TRADE,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+/// The number of samples a class needs before its median replaces the static estimate.
+const MIN_SAMPLES: usize = 5;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum InteractionClass {
+    UniswapV2Swap,
+    UniswapV3Swap,
+    BalancerSwap,
+    /// The transfers in and out of the settlement contract that every trade does.
+    Erc20Transfer,
+}
+
+impl InteractionClass {
+    /// The static estimate used until enough samples have been collected.
+    fn prior(self) -> u64 {
+        match self {
+            Self::UniswapV2Swap => GAS_PER_UNISWAP,
+            // There is no measured median for v3 swaps, so assume they cost about as much as v2
+            // swaps until samples replace the guess.
+            Self::UniswapV3Swap => GAS_PER_UNISWAP,
+            Self::BalancerSwap => GAS_PER_BALANCER_SWAP,
+            Self::Erc20Transfer => ERC20_TRANSFER,
+        }
+    }
+}
+
+/// The gas of a mined settlement and what it consisted of.
+#[derive(Clone, Debug, Default)]
+pub struct SettlementGas {
+    pub gas_used: u64,
+    pub trades: usize,
+    pub unwraps: usize,
+    pub interactions: Vec<InteractionClass>,
+}
+
+/// Lookup table of the median realized gas per interaction class.
+pub struct RealizedGas {
+    max_samples: usize,
+    samples: Mutex<HashMap<InteractionClass, VecDeque<u64>>>,
+}
+
+impl RealizedGas {
+    /// Keeps the most recent `max_samples` samples per class.
+    pub fn new(max_samples: usize) -> Self {
+        Self {
+            max_samples,
+            samples: Default::default(),
+        }
+    }
+
+    /// The median realized gas of the class or `None` if there aren't enough samples yet.
+    pub fn gas(&self, class: InteractionClass) -> Option<u64> {
+        let samples = self.samples.lock().unwrap();
+        median(samples.get(&class)?)
+    }
+
+    fn estimate(
+        samples: &HashMap<InteractionClass, VecDeque<u64>>,
+        class: InteractionClass,
+    ) -> u64 {
+        samples
+            .get(&class)
+            .and_then(median)
+            .unwrap_or_else(|| class.prior())
+    }
+
+    /// Attributes the gas of a mined settlement to its interactions.
+    pub fn record(&self, settlement: &SettlementGas) {
+        let items = settlement
+            .interactions
+            .iter()
+            .copied()
+            .chain(std::iter::repeat(InteractionClass::Erc20Transfer).take(2 * settlement.trades))
+            .collect::<Vec<_>>();
+        if items.is_empty() {
+            return;
+        }
+        let overhead = INITIALIZATION_COST
+            + SETTLEMENT
+            + settlement.trades as u64 * TRADE
+            + settlement.unwraps as u64 * GAS_PER_WETH_UNWRAP;
+        let interaction_gas = settlement.gas_used.saturating_sub(overhead);
+
+        let mut samples = self.samples.lock().unwrap();
+        let estimates = items
+            .iter()
+            .map(|class| (*class, Self::estimate(&samples, *class)))
+            .collect::<Vec<_>>();
+        let total_estimate = estimates.iter().map(|(_, gas)| gas).sum::<u64>();
+        if total_estimate == 0 {
+            return;
+        }
+        let mut recorded = Vec::new();
+        for (class, estimate) in estimates {
+            // Every item of a class gets the same share so one sample per class is enough.
+            if recorded.contains(&class) {
+                continue;
+            }
+            recorded.push(class);
+            let gas = (interaction_gas as u128 * estimate as u128 / total_estimate as u128) as u64;
+            let class_samples = samples.entry(class).or_default();
+            class_samples.push_back(gas);
+            while class_samples.len() > self.max_samples {
+                class_samples.pop_front();
+            }
+        }
+    }
+}
+
+fn median(samples: &VecDeque<u64>) -> Option<u64> {
+    if samples.len() < MIN_SAMPLES {
+        return None;
+    }
+    let mut sorted = samples.iter().copied().collect::<Vec<_>>();
+    sorted.sort_unstable();
+    Some(sorted[sorted.len() / 2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attributes_gas_to_interactions() {
+        let realized = RealizedGas::new(10);
+        let overhead = INITIALIZATION_COST + SETTLEMENT + TRADE;
+        let settlement = SettlementGas {
+            gas_used: overhead + 2 * ERC20_TRANSFER + 2 * GAS_PER_UNISWAP,
+            trades: 1,
+            unwraps: 0,
+            interactions: vec![InteractionClass::UniswapV2Swap],
+        };
+        for _ in 0..MIN_SAMPLES - 1 {
+            realized.record(&settlement);
+        }
+        assert_eq!(realized.gas(InteractionClass::UniswapV2Swap), None);
+
+        realized.record(&settlement);
+        let uniswap = realized.gas(InteractionClass::UniswapV2Swap).unwrap();
+        let transfer = realized.gas(InteractionClass::Erc20Transfer).unwrap();
+        // The settlement used more gas than expected so every class gets a larger share.
+        assert!(uniswap > GAS_PER_UNISWAP);
+        assert!(transfer > ERC20_TRANSFER);
+        assert!(uniswap + 2 * transfer <= settlement.gas_used - overhead);
+        assert_eq!(realized.gas(InteractionClass::BalancerSwap), None);
+    }
+
+    #[test]
+    fn keeps_most_recent_samples() {
+        let realized = RealizedGas::new(MIN_SAMPLES);
+        let settlement = |gas_used| SettlementGas {
+            gas_used,
+            trades: 0,
+            unwraps: 0,
+            interactions: vec![InteractionClass::BalancerSwap],
+        };
+        for _ in 0..MIN_SAMPLES {
+            realized.record(&settlement(INITIALIZATION_COST + SETTLEMENT + 1_000_000));
+        }
+        for _ in 0..MIN_SAMPLES {
+            realized.record(&settlement(INITIALIZATION_COST + SETTLEMENT + 100_000));
+        }
+        assert_eq!(realized.gas(InteractionClass::BalancerSwap), Some(100_000));
+    }
+}
@@ -2,7 +2,13 @@ use futures::{
     future::{Shared, WeakShared},
     FutureExt,
 };
-use std::{future::Future, sync::Mutex};
+use redis::aio::ConnectionManager;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
 
 // The design of this module is intentionally simple. Every time a shared future is requested we
 // loop through all futures to collect garbage. Because of this there is no advantage from using
@@ -73,6 +79,153 @@ where
     }
 }
 
+/// How often a process that waits for another process to compute a response checks whether it is
+/// available.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Share an expensive to compute response across processes, like horizontally scaled API replicas,
+/// through Redis.
+///
+/// The first process to request a key takes a short lock and computes the response, which it stores
+/// for the others. Processes that find the key locked wait for the response instead of computing it
+/// themselves. If the lock expires or its holder fails to compute a response one of the waiting
+/// processes takes over. Redis being unavailable never fails a request, the response is computed
+/// locally instead.
+pub struct CrossProcessSharing {
+    connection: ConnectionManager,
+    namespace: String,
+    lock_duration: Duration,
+    response_duration: Duration,
+}
+
+enum Lookup<T> {
+    Found(T),
+    Locked,
+    TimedOut,
+}
+
+impl CrossProcessSharing {
+    /// Keys are prefixed with the namespace so that unrelated requests can share a Redis instance.
+    /// The lock duration should be longer than it usually takes to compute a response and bounds
+    /// how long processes wait for each other. Responses are kept for the response duration.
+    pub async fn new(
+        redis_url: &str,
+        namespace: String,
+        lock_duration: Duration,
+        response_duration: Duration,
+    ) -> redis::RedisResult<Self> {
+        let client = redis::Client::open(redis_url)?;
+        Ok(Self {
+            connection: ConnectionManager::new(client).await?,
+            namespace,
+            lock_duration,
+            response_duration,
+        })
+    }
+
+    /// Returns the response another process computed for the key or computes it. Only successful
+    /// responses are shared.
+    pub async fn get_or_compute<T, E>(
+        &self,
+        key: &str,
+        compute: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, E>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let response_key = format!("{}:response:{}", self.namespace, key);
+        let lock_key = format!("{}:lock:{}", self.namespace, key);
+        let mut connection = self.connection.clone();
+        match self
+            .wait_for_response(&mut connection, &response_key, &lock_key)
+            .await
+        {
+            Ok(Lookup::Found(response)) => Ok(response),
+            Ok(Lookup::Locked) => {
+                let result = compute.await;
+                if let Err(err) = self
+                    .store(&mut connection, &response_key, &lock_key, &result)
+                    .await
+                {
+                    tracing::warn!(?err, %key, "failed to store shared response");
+                }
+                result
+            }
+            Ok(Lookup::TimedOut) => {
+                tracing::debug!(%key, "timed out waiting for shared response");
+                compute.await
+            }
+            Err(err) => {
+                tracing::warn!(?err, %key, "failed to look up shared response");
+                compute.await
+            }
+        }
+    }
+
+    async fn wait_for_response<T: DeserializeOwned>(
+        &self,
+        connection: &mut ConnectionManager,
+        response_key: &str,
+        lock_key: &str,
+    ) -> redis::RedisResult<Lookup<T>> {
+        let deadline = Instant::now() + self.lock_duration;
+        loop {
+            let response: Option<String> = redis::cmd("GET")
+                .arg(response_key)
+                .query_async(connection)
+                .await?;
+            if let Some(response) = response {
+                match serde_json::from_str(&response) {
+                    Ok(response) => return Ok(Lookup::Found(response)),
+                    // Can happen while replicas of different versions are running. Treat it like a
+                    // missing response and overwrite it.
+                    Err(err) => tracing::warn!(?err, %response_key, "invalid shared response"),
+                }
+            }
+            // `SET NX` replies with nil instead of OK if the lock is already held.
+            let locked: Option<String> = redis::cmd("SET")
+                .arg(lock_key)
+                .arg(1)
+                .arg("NX")
+                .arg("PX")
+                .arg(self.lock_duration.as_millis() as u64)
+                .query_async(connection)
+                .await?;
+            if locked.is_some() {
+                return Ok(Lookup::Locked);
+            }
+            if Instant::now() >= deadline {
+                return Ok(Lookup::TimedOut);
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    async fn store<T: Serialize, E>(
+        &self,
+        connection: &mut ConnectionManager,
+        response_key: &str,
+        lock_key: &str,
+        result: &Result<T, E>,
+    ) -> anyhow::Result<()> {
+        if let Ok(response) = result {
+            redis::cmd("SET")
+                .arg(response_key)
+                .arg(serde_json::to_string(response)?)
+                .arg("PX")
+                .arg(self.response_duration.as_millis() as u64)
+                .query_async(connection)
+                .await?;
+        }
+        // Waiting processes take over on their own if computing the response failed.
+        redis::cmd("DEL")
+            .arg(lock_key)
+            .query_async(connection)
+            .await?;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,4 +252,44 @@ mod tests {
         // complete second shared
         assert_eq!(shared1.now_or_never().unwrap(), 0);
     }
+
+    #[tokio::test]
+    #[ignore]
+    async fn redis_shares_response_across_processes() {
+        let redis_url = "redis://localhost";
+        let namespace = format!(
+            "test:{}",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        );
+        let sharing = || {
+            CrossProcessSharing::new(
+                redis_url,
+                namespace.clone(),
+                Duration::from_secs(1),
+                Duration::from_secs(10),
+            )
+        };
+        let (first, second) = (sharing().await.unwrap(), sharing().await.unwrap());
+
+        let (response0, response1) = futures::join!(
+            first.get_or_compute("key", async {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                Result::<u64, ()>::Ok(0)
+            }),
+            second.get_or_compute("key", async {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                Ok(1)
+            }),
+        );
+        assert_eq!(response0, response1);
+
+        // Failed responses aren't shared.
+        let failed = first.get_or_compute("failed", async { Result::<u64, ()>::Err(()) });
+        assert_eq!(failed.await, Err(()));
+        let retried = second.get_or_compute("failed", async { Result::<u64, ()>::Ok(2) });
+        assert_eq!(retried.await, Ok(2));
+    }
 }
@@ -0,0 +1,451 @@
+//! Transaction simulation with an embedded EVM using state forked from the node.
+//!
+//! This is an alternative to simulating with Tenderly or tracing with `trace_callMany` that does
+//! not use up external quotas, avoids the round trip to their API and works with nodes that don't
+//! support tracing. Accounts and storage slots are lazily fetched from the node and cached for
+//! the duration of a simulation.
+
+use crate::{trace_many::CallTracing, Web3};
+use anyhow::{anyhow, Context, Result};
+use primitive_types::{H160, U256};
+use revm::{
+    db::CacheDB,
+    primitives::{
+        AccountInfo, Address, Bytecode, ExecutionResult, TransactTo, TxEnv, B256, U256 as EvmU256,
+    },
+    DatabaseRef, Evm,
+};
+use tokio::runtime::Handle;
+use web3::types::{
+    AccessList, Action, ActionType, Block, BlockId, BlockNumber, BlockTrace, Bytes,
+    Call as TraceCall, CallRequest, CallResult, CallType, Res, Transaction, TransactionTrace,
+};
+
+/// A call to simulate.
+#[derive(Clone, Debug, Default)]
+pub struct Call {
+    pub from: H160,
+    pub to: H160,
+    pub value: U256,
+    pub data: Vec<u8>,
+    /// The gas limit of the call. Defaults to the block gas limit.
+    pub gas: Option<u64>,
+    pub access_list: Option<AccessList>,
+}
+
+/// The outcome of a simulated call.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CallOutcome {
+    pub gas_used: u64,
+    /// The data returned by a successful call or the reason why the call failed.
+    pub output: Result<Vec<u8>, String>,
+}
+
+pub struct RevmSimulator {
+    web3: Web3,
+}
+
+impl RevmSimulator {
+    pub fn new(web3: Web3) -> Self {
+        Self { web3 }
+    }
+
+    /// Simulates the calls one after another on top of the latest block. Each call sees the
+    /// state changes of the calls before it.
+    pub async fn simulate_at_latest_block(&self, calls: Vec<Call>) -> Result<Vec<CallOutcome>> {
+        let block = self.block(BlockNumber::Latest).await?;
+        let number = block.number.context("pending block")?.as_u64();
+        self.simulate(number, block, 0, calls).await
+    }
+
+    /// Simulates the call at the transaction index of the block, meaning on top of the state
+    /// after all preceding transactions of the block got executed.
+    pub async fn simulate_in_block(
+        &self,
+        block_number: u64,
+        transaction_index: u64,
+        call: Call,
+    ) -> Result<CallOutcome> {
+        let parent = block_number
+            .checked_sub(1)
+            .context("cannot simulate in genesis block")?;
+        let block = self.block(block_number.into()).await?;
+        let outcomes = self
+            .simulate(parent, block, transaction_index as usize, vec![call])
+            .await?;
+        outcomes.into_iter().next().context("missing call outcome")
+    }
+
+    async fn block(&self, number: BlockNumber) -> Result<Block<Transaction>> {
+        self.web3
+            .eth()
+            .block_with_txs(BlockId::Number(number))
+            .await?
+            .context("block not found")
+    }
+
+    /// Executes the first `preceding` transactions of the block and then the calls on top of
+    /// the state at `state_block`.
+    async fn simulate(
+        &self,
+        state_block: u64,
+        block: Block<Transaction>,
+        preceding: usize,
+        calls: Vec<Call>,
+    ) -> Result<Vec<CallOutcome>> {
+        let database = NodeDatabase {
+            web3: self.web3.clone(),
+            handle: Handle::current(),
+            block: BlockNumber::Number(state_block.into()),
+        };
+        // The EVM accesses the database synchronously so it has to run on a blocking thread.
+        tokio::task::spawn_blocking(move || {
+            let preceding = block.transactions.iter().take(preceding);
+            execute(database, &block, preceding, calls)
+        })
+        .await?
+    }
+}
+
+#[async_trait::async_trait]
+impl CallTracing for RevmSimulator {
+    async fn trace_many(&self, requests: Vec<CallRequest>) -> Result<Vec<BlockTrace>> {
+        let calls = requests
+            .iter()
+            .map(|request| {
+                Ok(Call {
+                    from: request.from.unwrap_or_default(),
+                    to: request.to.context("contract creation is not supported")?,
+                    value: request.value.unwrap_or_default(),
+                    data: request.data.clone().unwrap_or_default().0,
+                    gas: request.gas.map(|gas| gas.as_u64()),
+                    access_list: request.access_list.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let outcomes = self.simulate_at_latest_block(calls.clone()).await?;
+        Ok(calls
+            .into_iter()
+            .zip(outcomes)
+            .map(|(call, outcome)| block_trace(call, outcome))
+            .collect())
+    }
+}
+
+/// Converts the outcome of a call into the trace `trace_callMany` would have returned for it.
+fn block_trace(call: Call, outcome: CallOutcome) -> BlockTrace {
+    let (output, result, error) = match outcome.output {
+        Ok(output) => {
+            let result = Res::Call(CallResult {
+                gas_used: outcome.gas_used.into(),
+                output: Bytes(output.clone()),
+            });
+            (output, Some(result), None)
+        }
+        Err(reason) => (Vec::new(), None, Some(reason)),
+    };
+    BlockTrace {
+        output: Bytes(output),
+        trace: Some(vec![TransactionTrace {
+            trace_address: Vec::new(),
+            subtraces: 0,
+            action: Action::Call(TraceCall {
+                from: call.from,
+                to: call.to,
+                value: call.value,
+                gas: call.gas.unwrap_or_default().into(),
+                input: Bytes(call.data),
+                call_type: CallType::Call,
+            }),
+            action_type: ActionType::Call,
+            result,
+            error,
+        }]),
+        vm_trace: None,
+        state_diff: None,
+        transaction_hash: None,
+    }
+}
+
+fn execute<'a, DB>(
+    database: DB,
+    block: &Block<Transaction>,
+    preceding: impl IntoIterator<Item = &'a Transaction>,
+    calls: Vec<Call>,
+) -> Result<Vec<CallOutcome>>
+where
+    DB: DatabaseRef,
+    DB::Error: std::fmt::Debug,
+{
+    let mut evm = Evm::builder()
+        .with_db(CacheDB::new(database))
+        .modify_cfg_env(|cfg| {
+            // Simulations are usually done with a gas price of 0.
+            cfg.disable_base_fee = true;
+            // Allow sending calls from contracts, like token transfers out of AMM pools.
+            cfg.disable_eip3607 = true;
+        })
+        .modify_block_env(|env| {
+            env.number = EvmU256::from(block.number.unwrap_or_default().as_u64());
+            env.coinbase = address(block.author);
+            env.timestamp = u256(block.timestamp);
+            env.gas_limit = u256(block.gas_limit);
+            env.basefee = block.base_fee_per_gas.map(u256).unwrap_or_default();
+            env.difficulty = u256(block.difficulty);
+            env.prevrandao = Some(B256::from(block.mix_hash.unwrap_or_default().0));
+        })
+        .build();
+
+    for transaction in preceding {
+        *evm.tx_mut() = tx_env(transaction)?;
+        evm.transact_commit().map_err(|err| {
+            anyhow!(
+                "failed to replay transaction {:?}: {:?}",
+                transaction.hash,
+                err
+            )
+        })?;
+    }
+
+    calls
+        .into_iter()
+        .map(|call| {
+            *evm.tx_mut() = TxEnv {
+                caller: address(call.from),
+                gas_limit: call.gas.unwrap_or_else(|| block.gas_limit.as_u64()),
+                transact_to: TransactTo::Call(address(call.to)),
+                value: u256(call.value),
+                data: call.data.into(),
+                access_list: access_list(call.access_list.iter().flatten()),
+                ..Default::default()
+            };
+            let result = evm
+                .transact_commit()
+                .map_err(|err| anyhow!("failed to simulate call: {:?}", err))?;
+            Ok(match result {
+                ExecutionResult::Success {
+                    gas_used, output, ..
+                } => CallOutcome {
+                    gas_used,
+                    output: Ok(output.into_data().to_vec()),
+                },
+                ExecutionResult::Revert { gas_used, output } => CallOutcome {
+                    gas_used,
+                    output: Err(format!("Reverted 0x{}", hex::encode(output))),
+                },
+                ExecutionResult::Halt { reason, gas_used } => CallOutcome {
+                    gas_used,
+                    output: Err(format!("{:?}", reason)),
+                },
+            })
+        })
+        .collect()
+}
+
+fn tx_env(transaction: &Transaction) -> Result<TxEnv> {
+    Ok(TxEnv {
+        caller: address(transaction.from.context("transaction without sender")?),
+        gas_limit: transaction.gas.as_u64(),
+        gas_price: u256(
+            transaction
+                .max_fee_per_gas
+                .or(transaction.gas_price)
+                .unwrap_or_default(),
+        ),
+        gas_priority_fee: transaction.max_priority_fee_per_gas.map(u256),
+        transact_to: match transaction.to {
+            Some(to) => TransactTo::Call(address(to)),
+            None => TransactTo::create(),
+        },
+        value: u256(transaction.value),
+        data: transaction.input.0.clone().into(),
+        access_list: access_list(transaction.access_list.iter().flatten()),
+        ..Default::default()
+    })
+}
+
+fn access_list<'a>(
+    items: impl IntoIterator<Item = &'a web3::types::AccessListItem>,
+) -> Vec<(Address, Vec<EvmU256>)> {
+    items
+        .into_iter()
+        .map(|item| {
+            let keys = item
+                .storage_keys
+                .iter()
+                .map(|key| EvmU256::from_be_bytes(key.0))
+                .collect();
+            (address(item.address), keys)
+        })
+        .collect()
+}
+
+/// Fetches state from the node at a fixed block.
+struct NodeDatabase {
+    web3: Web3,
+    handle: Handle,
+    block: BlockNumber,
+}
+
+impl DatabaseRef for NodeDatabase {
+    type Error = anyhow::Error;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>> {
+        let address = H160(address.0 .0);
+        let eth = self.web3.eth();
+        let (balance, nonce, code) = self.handle.block_on(futures::future::try_join3(
+            eth.balance(address, Some(self.block)),
+            eth.transaction_count(address, Some(self.block)),
+            eth.code(address, Some(self.block)),
+        ))?;
+        let code = Bytecode::new_raw(code.0.into());
+        Ok(Some(AccountInfo::new(
+            u256(balance),
+            nonce.as_u64(),
+            code.hash_slow(),
+            code,
+        )))
+    }
+
+    fn code_by_hash_ref(&self, _: B256) -> Result<Bytecode> {
+        Err(anyhow!("code is always fetched together with the account"))
+    }
+
+    fn storage_ref(&self, address: Address, index: EvmU256) -> Result<EvmU256> {
+        let value = self.handle.block_on(self.web3.eth().storage(
+            H160(address.0 .0),
+            U256::from_big_endian(&index.to_be_bytes::<32>()),
+            Some(self.block),
+        ))?;
+        Ok(EvmU256::from_be_bytes(value.0))
+    }
+
+    fn block_hash_ref(&self, number: EvmU256) -> Result<B256> {
+        let number = u64::try_from(number).context("block number overflow")?;
+        let block = self
+            .handle
+            .block_on(self.web3.eth().block(BlockId::Number(number.into())))?
+            .context("block not found")?;
+        Ok(B256::from(block.hash.context("pending block")?.0))
+    }
+}
+
+fn address(address: H160) -> Address {
+    Address::from(address.0)
+}
+
+fn u256(value: U256) -> EvmU256 {
+    EvmU256::from_limbs(value.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::create_env_test_transport;
+    use primitive_types::H256;
+    use revm::db::EmptyDB;
+
+    #[test]
+    fn converts_u256() {
+        let value = U256::from_dec_str("123456789012345678901234567890123456789").unwrap();
+        assert_eq!(
+            u256(value),
+            EvmU256::from_str_radix("123456789012345678901234567890123456789", 10).unwrap()
+        );
+    }
+
+    #[test]
+    fn executes_calls_one_after_another() {
+        // Increments storage slot 0 and returns its new value.
+        // PUSH1 0 SLOAD PUSH1 1 ADD DUP1 PUSH1 0 SSTORE PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+        let counter = hex_literal::hex!("6000546001018060005560005260206000f3");
+        // PUSH1 0 PUSH1 0 REVERT
+        let reverter = hex_literal::hex!("60006000fd");
+        let mut database = CacheDB::new(EmptyDB::default());
+        for (account, code) in [
+            (H160([1; 20]), &counter[..]),
+            (H160([2; 20]), &reverter[..]),
+        ] {
+            let code = Bytecode::new_raw(code.to_vec().into());
+            database.insert_account_info(
+                address(account),
+                AccountInfo::new(EvmU256::ZERO, 0, code.hash_slow(), code),
+            );
+        }
+        let block = Block {
+            number: Some(1.into()),
+            gas_limit: 30_000_000.into(),
+            ..Default::default()
+        };
+        // Calls can be sent from contracts.
+        let call = |to| Call {
+            from: H160([1; 20]),
+            to,
+            ..Default::default()
+        };
+
+        let outcomes = execute(
+            database,
+            &block,
+            [],
+            vec![
+                call(H160([1; 20])),
+                call(H160([1; 20])),
+                call(H160([2; 20])),
+            ],
+        )
+        .unwrap();
+
+        let word = |value: u8| {
+            let mut word = vec![0; 32];
+            word[31] = value;
+            Ok(word)
+        };
+        assert_eq!(outcomes[0].output, word(1));
+        assert_eq!(outcomes[1].output, word(2));
+        // The second increment writes to an already written slot so it is cheaper.
+        assert!(outcomes[1].gas_used < outcomes[0].gas_used);
+        assert!(outcomes[0].gas_used > 21_000);
+        assert_eq!(
+            outcomes[2],
+            CallOutcome {
+                gas_used: 21_006,
+                output: Err("Reverted 0x".to_string()),
+            }
+        );
+
+        let trace = block_trace(call(H160([2; 20])), outcomes[2].clone());
+        assert!(!crate::trace_many::all_calls_succeeded(&[trace]).unwrap());
+    }
+
+    // cargo test -p shared revm_simulation -- --ignored --nocapture
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore]
+    async fn mainnet_gas_used() {
+        let web3 = Web3::new(create_env_test_transport());
+        // https://etherscan.io/tx/0x6756c294eb84c899247f2ec64d6eee73e7aaf50d6cb49ba9bab636f450240f51
+        let hash: H256 = "0x6756c294eb84c899247f2ec64d6eee73e7aaf50d6cb49ba9bab636f450240f51"
+            .parse()
+            .unwrap();
+        let transaction = web3.eth().transaction(hash.into()).await.unwrap().unwrap();
+        let receipt = web3.eth().transaction_receipt(hash).await.unwrap().unwrap();
+        let call = Call {
+            from: transaction.from.unwrap(),
+            to: transaction.to.unwrap(),
+            value: transaction.value,
+            data: transaction.input.0,
+            gas: Some(transaction.gas.as_u64()),
+            access_list: transaction.access_list,
+        };
+        let outcome = RevmSimulator::new(web3)
+            .simulate_in_block(
+                transaction.block_number.unwrap().as_u64(),
+                transaction.transaction_index.unwrap().as_u64(),
+                call,
+            )
+            .await
+            .unwrap();
+        assert!(outcome.output.is_ok());
+        assert_eq!(outcome.gas_used, receipt.gas_used.unwrap().as_u64());
+    }
+}
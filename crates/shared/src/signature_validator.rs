@@ -3,6 +3,7 @@ use contracts::ERC1271SignatureValidator;
 use ethcontract::{batch::CallBatch, errors::MethodError, Bytes};
 use futures::future;
 use hex_literal::hex;
+use model::signature::Signature;
 use primitive_types::H160;
 use thiserror::Error;
 
@@ -14,6 +15,17 @@ pub struct SignatureCheck {
     pub signature: Vec<u8>,
 }
 
+impl SignatureCheck {
+    /// The check of the owner contract for signatures of schemes that get verified by the owner.
+    pub fn for_signature(signature: &Signature, signer: H160, hash: [u8; 32]) -> Option<Self> {
+        Some(Self {
+            signer,
+            hash,
+            signature: signature.contract_signature()?,
+        })
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum SignatureValidationError {
     /// The signature is invalid.
@@ -23,7 +23,7 @@ impl PairProvider {
     }
 }
 
-fn create2_target_address(creator: H160, salt: &[u8; 32], init_code_digest: &[u8; 32]) -> H160 {
+pub fn create2_target_address(creator: H160, salt: &[u8; 32], init_code_digest: &[u8; 32]) -> H160 {
     let mut preimage = [0xff; 85];
     preimage[1..21].copy_from_slice(creator.as_fixed_bytes());
     preimage[21..53].copy_from_slice(salt);
@@ -0,0 +1,554 @@
+//! Simulates swaps of Uniswap V3 pools so that they can be used as baseline liquidity.
+//!
+//! This is a port of the parts of the pool contract that a swap executes, crossing the initialized
+//! ticks of the pool like the contract does:
+//! https://github.com/Uniswap/v3-core/blob/ed88be38ab2032d82bf10ac6f8d03aa631889d48/contracts/UniswapV3Pool.sol#L596-L724
+//! Amounts get rounded like in the contract so that simulated swaps match executed ones.
+
+use super::pool_fetching::{PoolInfo, PoolState};
+use crate::baseline_solver::BaselineSolvable;
+use ethcontract::{H160, U256};
+use num::ToPrimitive;
+use primitive_types::U512;
+
+const MIN_TICK: i32 = -887272;
+const MAX_TICK: i32 = 887272;
+const FEE_DENOMINATOR: u32 = 1_000_000;
+
+fn q96() -> U256 {
+    U256::one() << 96
+}
+
+fn min_sqrt_ratio() -> U256 {
+    U256::from(4295128739u64)
+}
+
+fn max_sqrt_ratio() -> U256 {
+    U256::from_dec_str("1461446703485210103287273052203988822378723970342").unwrap()
+}
+
+fn mul_div(a: U256, b: U256, denominator: U256) -> Option<U256> {
+    if denominator.is_zero() {
+        return None;
+    }
+    (a.full_mul(b) / denominator).try_into().ok()
+}
+
+fn mul_div_rounding_up(a: U256, b: U256, denominator: U256) -> Option<U256> {
+    if denominator.is_zero() {
+        return None;
+    }
+    let (quotient, remainder) = a.full_mul(b).div_mod(U512::from(denominator));
+    let quotient = if remainder.is_zero() {
+        quotient
+    } else {
+        quotient + 1
+    };
+    quotient.try_into().ok()
+}
+
+fn div_rounding_up(a: U256, b: U256) -> Option<U256> {
+    let (quotient, remainder) = a.div_mod(b);
+    if remainder.is_zero() {
+        Some(quotient)
+    } else {
+        quotient.checked_add(1.into())
+    }
+}
+
+/// https://github.com/Uniswap/v3-core/blob/ed88be38ab2032d82bf10ac6f8d03aa631889d48/contracts/libraries/TickMath.sol#L23-L54
+fn sqrt_ratio_at_tick(tick: i32) -> Option<U256> {
+    const FACTORS: [&str; 19] = [
+        "fff97272373d413259a46990580e213a",
+        "fff2e50f5f656932ef12357cf3c7fdcc",
+        "ffe5caca7e10e4e61c3624eaa0941cd0",
+        "ffcb9843d60f6159c9db58835c926644",
+        "ff973b41fa98c081472e6896dfb254c0",
+        "ff2ea16466c96a3843ec78b326b52861",
+        "fe5dee046a99a2a811c461f1969c3053",
+        "fcbe86c7900a88aedcffc83b479aa3a4",
+        "f987a7253ac413176f2b074cf7815e54",
+        "f3392b0822b70005940c7a398e4b70f3",
+        "e7159475a2c29b7443b29c7fa6e889d9",
+        "d097f3bdfd2022b8845ad8f792aa5825",
+        "a9f746462d870fdf8a65dc1f90e061e5",
+        "70d869a156d2a1b890bb3df62baf32f7",
+        "31be135f97d08fd981231505542fcfa6",
+        "9aa508b5b7a84e1c677de54f3e99bc9",
+        "5d6af8dedb81196699c329225ee604",
+        "2216e584f5fa1ea926041bedfe98",
+        "48a170391f7dc42444e8fa2",
+    ];
+    let abs_tick = tick.unsigned_abs();
+    if abs_tick > MAX_TICK as u32 {
+        return None;
+    }
+
+    let mut ratio = if abs_tick & 1 != 0 {
+        U256::from_str_radix("fffcb933bd6fad37aa2d162d1a594001", 16).unwrap()
+    } else {
+        U256::one() << 128
+    };
+    for (bit, factor) in FACTORS.iter().enumerate() {
+        if abs_tick & (2 << bit) != 0 {
+            let factor = U256::from_str_radix(factor, 16).unwrap();
+            ratio = (ratio * factor) >> 128;
+        }
+    }
+    if tick > 0 {
+        ratio = U256::MAX / ratio;
+    }
+
+    // Round up to go from Q128.128 to Q64.96 so that the ratio is never underestimated.
+    let sqrt_ratio = ratio >> 32;
+    if (ratio & U256::from(u32::MAX)).is_zero() {
+        Some(sqrt_ratio)
+    } else {
+        Some(sqrt_ratio + 1)
+    }
+}
+
+/// https://github.com/Uniswap/v3-core/blob/ed88be38ab2032d82bf10ac6f8d03aa631889d48/contracts/libraries/SqrtPriceMath.sol#L153-L174
+fn amount0_delta(
+    sqrt_ratio_a: U256,
+    sqrt_ratio_b: U256,
+    liquidity: U256,
+    round_up: bool,
+) -> Option<U256> {
+    let (sqrt_ratio_a, sqrt_ratio_b) = if sqrt_ratio_a > sqrt_ratio_b {
+        (sqrt_ratio_b, sqrt_ratio_a)
+    } else {
+        (sqrt_ratio_a, sqrt_ratio_b)
+    };
+    if sqrt_ratio_a.is_zero() {
+        return None;
+    }
+    let numerator1 = liquidity << 96;
+    let numerator2 = sqrt_ratio_b - sqrt_ratio_a;
+    if round_up {
+        div_rounding_up(
+            mul_div_rounding_up(numerator1, numerator2, sqrt_ratio_b)?,
+            sqrt_ratio_a,
+        )
+    } else {
+        Some(mul_div(numerator1, numerator2, sqrt_ratio_b)? / sqrt_ratio_a)
+    }
+}
+
+/// https://github.com/Uniswap/v3-core/blob/ed88be38ab2032d82bf10ac6f8d03aa631889d48/contracts/libraries/SqrtPriceMath.sol#L182-L196
+fn amount1_delta(
+    sqrt_ratio_a: U256,
+    sqrt_ratio_b: U256,
+    liquidity: U256,
+    round_up: bool,
+) -> Option<U256> {
+    let difference = if sqrt_ratio_a > sqrt_ratio_b {
+        sqrt_ratio_a - sqrt_ratio_b
+    } else {
+        sqrt_ratio_b - sqrt_ratio_a
+    };
+    if round_up {
+        mul_div_rounding_up(liquidity, difference, q96())
+    } else {
+        mul_div(liquidity, difference, q96())
+    }
+}
+
+/// https://github.com/Uniswap/v3-core/blob/ed88be38ab2032d82bf10ac6f8d03aa631889d48/contracts/libraries/SqrtPriceMath.sol#L28-L61
+///
+/// Computes the result with full precision instead of falling back to a less precise formula when
+/// the intermediate product overflows 256 bits.
+fn next_sqrt_price_from_amount0_rounding_up(
+    sqrt_price: U256,
+    liquidity: U256,
+    amount: U256,
+    add: bool,
+) -> Option<U256> {
+    if amount.is_zero() {
+        return Some(sqrt_price);
+    }
+    let numerator1 = U512::from(liquidity << 96);
+    let product = amount.full_mul(sqrt_price);
+    let denominator = if add {
+        numerator1 + product
+    } else {
+        numerator1.checked_sub(product).filter(|d| !d.is_zero())?
+    };
+    let (quotient, remainder) = (numerator1 * U512::from(sqrt_price)).div_mod(denominator);
+    let quotient = if remainder.is_zero() {
+        quotient
+    } else {
+        quotient + 1
+    };
+    quotient.try_into().ok()
+}
+
+/// https://github.com/Uniswap/v3-core/blob/ed88be38ab2032d82bf10ac6f8d03aa631889d48/contracts/libraries/SqrtPriceMath.sol#L63-L98
+fn next_sqrt_price_from_amount1_rounding_down(
+    sqrt_price: U256,
+    liquidity: U256,
+    amount: U256,
+    add: bool,
+) -> Option<U256> {
+    if liquidity.is_zero() {
+        return None;
+    }
+    if add {
+        let quotient = mul_div(amount, q96(), liquidity)?;
+        sqrt_price.checked_add(quotient)
+    } else {
+        let quotient = mul_div_rounding_up(amount, q96(), liquidity)?;
+        sqrt_price
+            .checked_sub(quotient)
+            .filter(|price| !price.is_zero())
+    }
+}
+
+fn next_sqrt_price_from_input(
+    sqrt_price: U256,
+    liquidity: U256,
+    amount_in: U256,
+    zero_for_one: bool,
+) -> Option<U256> {
+    if zero_for_one {
+        next_sqrt_price_from_amount0_rounding_up(sqrt_price, liquidity, amount_in, true)
+    } else {
+        next_sqrt_price_from_amount1_rounding_down(sqrt_price, liquidity, amount_in, true)
+    }
+}
+
+fn next_sqrt_price_from_output(
+    sqrt_price: U256,
+    liquidity: U256,
+    amount_out: U256,
+    zero_for_one: bool,
+) -> Option<U256> {
+    if zero_for_one {
+        next_sqrt_price_from_amount1_rounding_down(sqrt_price, liquidity, amount_out, false)
+    } else {
+        next_sqrt_price_from_amount0_rounding_up(sqrt_price, liquidity, amount_out, false)
+    }
+}
+
+/// The result of swapping within a range of constant liquidity.
+#[derive(Debug, Eq, PartialEq)]
+struct SwapStep {
+    sqrt_price_next: U256,
+    amount_in: U256,
+    amount_out: U256,
+    fee_amount: U256,
+}
+
+/// https://github.com/Uniswap/v3-core/blob/ed88be38ab2032d82bf10ac6f8d03aa631889d48/contracts/libraries/SwapMath.sol#L21-L97
+fn compute_swap_step(
+    sqrt_price_current: U256,
+    sqrt_price_target: U256,
+    liquidity: U256,
+    amount_remaining: U256,
+    exact_in: bool,
+    fee_pips: u32,
+) -> Option<SwapStep> {
+    let zero_for_one = sqrt_price_current >= sqrt_price_target;
+    let fee_complement = U256::from(FEE_DENOMINATOR - fee_pips);
+
+    let mut amount_in = U256::zero();
+    let mut amount_out = U256::zero();
+    let sqrt_price_next = if exact_in {
+        let amount_remaining_less_fee =
+            mul_div(amount_remaining, fee_complement, FEE_DENOMINATOR.into())?;
+        amount_in = if zero_for_one {
+            amount0_delta(sqrt_price_target, sqrt_price_current, liquidity, true)?
+        } else {
+            amount1_delta(sqrt_price_current, sqrt_price_target, liquidity, true)?
+        };
+        if amount_remaining_less_fee >= amount_in {
+            sqrt_price_target
+        } else {
+            next_sqrt_price_from_input(
+                sqrt_price_current,
+                liquidity,
+                amount_remaining_less_fee,
+                zero_for_one,
+            )?
+        }
+    } else {
+        amount_out = if zero_for_one {
+            amount1_delta(sqrt_price_target, sqrt_price_current, liquidity, false)?
+        } else {
+            amount0_delta(sqrt_price_current, sqrt_price_target, liquidity, false)?
+        };
+        if amount_remaining >= amount_out {
+            sqrt_price_target
+        } else {
+            next_sqrt_price_from_output(
+                sqrt_price_current,
+                liquidity,
+                amount_remaining,
+                zero_for_one,
+            )?
+        }
+    };
+
+    let max = sqrt_price_target == sqrt_price_next;
+    if zero_for_one {
+        if !(max && exact_in) {
+            amount_in = amount0_delta(sqrt_price_next, sqrt_price_current, liquidity, true)?;
+        }
+        if !(max && !exact_in) {
+            amount_out = amount1_delta(sqrt_price_next, sqrt_price_current, liquidity, false)?;
+        }
+    } else {
+        if !(max && exact_in) {
+            amount_in = amount1_delta(sqrt_price_current, sqrt_price_next, liquidity, true)?;
+        }
+        if !(max && !exact_in) {
+            amount_out = amount0_delta(sqrt_price_current, sqrt_price_next, liquidity, false)?;
+        }
+    }
+
+    // The output can't exceed what is left to buy.
+    if !exact_in && amount_out > amount_remaining {
+        amount_out = amount_remaining;
+    }
+
+    let fee_amount = if exact_in && sqrt_price_next != sqrt_price_target {
+        // Everything that is left over after reaching the price is the fee.
+        amount_remaining.checked_sub(amount_in)?
+    } else {
+        mul_div_rounding_up(amount_in, fee_pips.into(), fee_complement)?
+    };
+
+    Some(SwapStep {
+        sqrt_price_next,
+        amount_in,
+        amount_out,
+        fee_amount,
+    })
+}
+
+/// Swaps `amount` of token 0 for token 1 if `zero_for_one` or the other way around. The amount is
+/// what gets sold if `exact_in` and what gets bought otherwise. Returns the other amount of the
+/// swap, `None` if the pool doesn't have enough liquidity for it.
+fn swap(state: &PoolState, zero_for_one: bool, amount: U256, exact_in: bool) -> Option<U256> {
+    let fee_pips = (*state.fee.numer() as u64 * FEE_DENOMINATOR as u64 / *state.fee.denom() as u64)
+        .to_u32()
+        .filter(|fee| *fee < FEE_DENOMINATOR)?;
+    let mut ticks = state
+        .liquidity_net
+        .iter()
+        .map(|(tick, liquidity_net)| Some((tick.to_i32()?, liquidity_net.to_i128()?)))
+        .collect::<Option<Vec<_>>>()?;
+    ticks.sort_unstable();
+    let sqrt_price_limit = if zero_for_one {
+        min_sqrt_ratio() + 1
+    } else {
+        max_sqrt_ratio() - 1
+    };
+
+    let mut amount_remaining = amount;
+    let mut amount_calculated = U256::zero();
+    let mut sqrt_price = state.sqrt_price;
+    let mut tick = state.tick.to_i32()?;
+    let mut liquidity = state.liquidity;
+    while !amount_remaining.is_zero() && sqrt_price != sqrt_price_limit {
+        // The next initialized tick in the direction of the swap. Like in the contract a swap
+        // from token 0 to token 1 can cross the current tick.
+        let next = if zero_for_one {
+            ticks.iter().rev().find(|(index, _)| *index <= tick)
+        } else {
+            ticks.iter().find(|(index, _)| *index > tick)
+        };
+        let tick_next = match next {
+            Some((index, _)) => *index,
+            None if zero_for_one => MIN_TICK,
+            None => MAX_TICK,
+        };
+        let sqrt_price_next = sqrt_ratio_at_tick(tick_next.clamp(MIN_TICK, MAX_TICK))?;
+        let sqrt_price_target = if zero_for_one {
+            sqrt_price_next.max(sqrt_price_limit)
+        } else {
+            sqrt_price_next.min(sqrt_price_limit)
+        };
+
+        let step = compute_swap_step(
+            sqrt_price,
+            sqrt_price_target,
+            liquidity,
+            amount_remaining,
+            exact_in,
+            fee_pips,
+        )?;
+        sqrt_price = step.sqrt_price_next;
+        if exact_in {
+            amount_remaining =
+                amount_remaining.checked_sub(step.amount_in.checked_add(step.fee_amount)?)?;
+            amount_calculated = amount_calculated.checked_add(step.amount_out)?;
+        } else {
+            amount_remaining = amount_remaining.checked_sub(step.amount_out)?;
+            amount_calculated =
+                amount_calculated.checked_add(step.amount_in.checked_add(step.fee_amount)?)?;
+        }
+
+        if sqrt_price != sqrt_price_next {
+            // The swap ended within the range or at the price limit.
+            continue;
+        }
+        if let Some((_, liquidity_net)) = next {
+            let liquidity_net = if zero_for_one {
+                -liquidity_net
+            } else {
+                *liquidity_net
+            };
+            liquidity = if liquidity_net < 0 {
+                liquidity.checked_sub(liquidity_net.unsigned_abs().into())?
+            } else {
+                liquidity.checked_add(liquidity_net.unsigned_abs().into())?
+            };
+        }
+        tick = if zero_for_one {
+            tick_next - 1
+        } else {
+            tick_next
+        };
+    }
+
+    if amount_remaining.is_zero() {
+        Some(amount_calculated)
+    } else {
+        None
+    }
+}
+
+impl PoolInfo {
+    /// Whether swapping from `in_token` to `out_token` sells token 0 of the pool. `None` if the
+    /// pool doesn't trade the tokens.
+    fn zero_for_one(&self, in_token: H160, out_token: H160) -> Option<bool> {
+        match self.tokens.as_slice() {
+            [token0, token1] if token0.id == in_token && token1.id == out_token => Some(true),
+            [token0, token1] if token1.id == in_token && token0.id == out_token => Some(false),
+            _ => None,
+        }
+    }
+}
+
+impl BaselineSolvable for PoolInfo {
+    fn get_amount_out(&self, out_token: H160, (in_amount, in_token): (U256, H160)) -> Option<U256> {
+        let zero_for_one = self.zero_for_one(in_token, out_token)?;
+        swap(&self.state, zero_for_one, in_amount, true)
+    }
+
+    fn get_amount_in(&self, in_token: H160, (out_amount, out_token): (U256, H160)) -> Option<U256> {
+        let zero_for_one = self.zero_for_one(in_token, out_token)?;
+        swap(&self.state, zero_for_one, out_amount, false)
+    }
+
+    fn gas_cost(&self) -> usize {
+        self.gas_stats.mean_gas.try_into().unwrap_or(usize::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::uniswap_v3::{graph_api::Token, pool_fetching::PoolStats};
+    use num::{rational::Ratio, BigInt};
+
+    #[test]
+    fn sqrt_ratio_at_tick_bounds() {
+        assert_eq!(sqrt_ratio_at_tick(0), Some(q96()));
+        assert_eq!(sqrt_ratio_at_tick(MIN_TICK), Some(min_sqrt_ratio()));
+        assert_eq!(sqrt_ratio_at_tick(MAX_TICK), Some(max_sqrt_ratio()));
+        assert_eq!(sqrt_ratio_at_tick(MAX_TICK + 1), None);
+        assert!(sqrt_ratio_at_tick(1).unwrap() > q96());
+        assert!(sqrt_ratio_at_tick(-1).unwrap() < q96());
+    }
+
+    fn pool(liquidity_net: Vec<(i32, i128)>) -> PoolInfo {
+        PoolInfo {
+            address: H160::from_low_u64_be(1),
+            tokens: vec![
+                Token {
+                    id: H160::from_low_u64_be(2),
+                    symbol: "A".to_string(),
+                    decimals: 18,
+                },
+                Token {
+                    id: H160::from_low_u64_be(3),
+                    symbol: "B".to_string(),
+                    decimals: 18,
+                },
+            ],
+            state: PoolState {
+                // A price of 1 at tick 0.
+                sqrt_price: q96(),
+                liquidity: U256::exp10(21),
+                tick: BigInt::from(0),
+                liquidity_net: liquidity_net
+                    .into_iter()
+                    .map(|(tick, net)| (BigInt::from(tick), BigInt::from(net)))
+                    .collect(),
+                fee: Ratio::new(3, 1000),
+            },
+            gas_stats: PoolStats {
+                mean_gas: 100_000.into(),
+            },
+        }
+    }
+
+    #[test]
+    fn swaps_within_range() {
+        let pool = pool(vec![(-600, 10i128.pow(21)), (600, -(10i128.pow(21)))]);
+        let (token0, token1) = (pool.tokens[0].id, pool.tokens[1].id);
+        let amount = U256::exp10(18);
+
+        // At a price of 1 with plenty of liquidity the output is the input minus the 0.3% fee
+        // and a little bit of price impact.
+        let out = pool.get_amount_out(token1, (amount, token0)).unwrap();
+        assert!(out < U256::exp10(15) * 997);
+        assert!(out > U256::exp10(15) * 996);
+        let out_reverse = pool.get_amount_out(token0, (amount, token1)).unwrap();
+        assert!(out_reverse < U256::exp10(15) * 997);
+        assert!(out_reverse > U256::exp10(15) * 996);
+
+        // Selling what is needed to buy an amount buys at least that amount.
+        let needed = pool.get_amount_in(token0, (out, token1)).unwrap();
+        assert!(needed <= amount);
+        assert!(pool.get_amount_out(token1, (needed, token0)).unwrap() >= out);
+
+        assert_eq!(pool.get_amount_out(token1, (amount, token1)), None);
+        assert_eq!(pool.get_amount_out(H160::zero(), (amount, token0)), None);
+        assert_eq!(pool.gas_cost(), 100_000);
+    }
+
+    #[test]
+    fn crosses_ticks() {
+        // One position in a narrow range around the current price.
+        let narrow = pool(vec![(-60, 10i128.pow(21)), (60, -(10i128.pow(21)))]);
+        let (token0, token1) = (narrow.tokens[0].id, narrow.tokens[1].id);
+        // The same position and another one in a wide range.
+        let mut wide = pool(vec![
+            (-6000, 10i128.pow(21)),
+            (-60, 10i128.pow(21)),
+            (60, -(10i128.pow(21))),
+            (6000, -(10i128.pow(21))),
+        ]);
+        wide.state.liquidity = U256::exp10(21) * 2;
+
+        // Buying token 0 moves the price up past tick 60. The narrow pool runs out of liquidity
+        // there while the wide pool keeps going with the liquidity of the wide position.
+        let amount = U256::exp10(19);
+        assert_eq!(narrow.get_amount_out(token0, (amount, token1)), None);
+        let out = wide.get_amount_out(token0, (amount, token1)).unwrap();
+        assert!(out < amount);
+        let needed = wide.get_amount_in(token1, (out, token0)).unwrap();
+        assert!(needed <= amount);
+
+        // Small swaps stay within the narrow range.
+        assert!(narrow
+            .get_amount_out(token1, (U256::exp10(18), token0))
+            .is_some());
+        assert!(narrow
+            .get_amount_out(token0, (U256::exp10(18), token1))
+            .is_some());
+    }
+}
@@ -0,0 +1,195 @@
+//! Tenderly fork API client.
+//!
+//! Forks are private copies of a network at some block that behave like a regular node. They
+//! accept unsigned transactions from any account so users and solvers can be impersonated, and
+//! they allow setting account balances. This makes them suitable for end to end rehearsals of
+//! production auctions.
+//!
+//! For more information on the HTTP API, consult:
+//! <https://docs.tenderly.co/simulations-and-forks/simulation-api/using-simulation-api>
+
+use crate::Web3;
+use anyhow::{Context, Result};
+use primitive_types::{H160, U256};
+use reqwest::{
+    header::{HeaderMap, HeaderValue},
+    Client, IntoUrl, Url,
+};
+use serde::{Deserialize, Serialize};
+use web3::Transport;
+
+const FORK_RPC_URL: &str = "https://rpc.tenderly.co/fork/";
+
+pub struct TenderlyApi {
+    /// The project's api url, `https://api.tenderly.co/api/v1/account/<USER>/project/<PROJECT>/`.
+    url: Url,
+    client: Client,
+    header: HeaderMap,
+}
+
+#[derive(Debug, Serialize)]
+struct ForkRequest<'a> {
+    network_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    block_number: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ForkResponse {
+    simulation_fork: SimulationFork,
+}
+
+#[derive(Debug, Deserialize)]
+struct SimulationFork {
+    id: String,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Fork {
+    pub id: String,
+    /// The JSON RPC url of the fork that can be used like a regular node url.
+    pub rpc_url: Url,
+}
+
+impl Fork {
+    fn new(id: String) -> Result<Self> {
+        let rpc_url = Url::parse(FORK_RPC_URL)?.join(&id)?;
+        Ok(Self { id, rpc_url })
+    }
+}
+
+impl TenderlyApi {
+    pub fn new(url: impl IntoUrl, client: Client, api_key: &str) -> Result<Self> {
+        let mut url = url.into_url()?;
+        // Make sure joining paths appends to the project url instead of replacing its last segment.
+        if !url.path().ends_with('/') {
+            url.set_path(&format!("{}/", url.path()));
+        }
+        Ok(Self {
+            url,
+            client,
+            header: {
+                let mut header = HeaderMap::new();
+                header.insert("x-access-key", HeaderValue::from_str(api_key)?);
+                header
+            },
+        })
+    }
+
+    /// Creates a fork of the network at the specified block or the latest block if none is
+    /// specified.
+    pub async fn create_fork(&self, network_id: &str, block_number: Option<u64>) -> Result<Fork> {
+        let response: ForkResponse = self
+            .client
+            .post(self.url.join("fork")?)
+            .headers(self.header.clone())
+            .json(&ForkRequest {
+                network_id,
+                block_number,
+            })
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .context("failed to decode fork response")?;
+        Fork::new(response.simulation_fork.id)
+    }
+
+    pub async fn delete_fork(&self, fork: &Fork) -> Result<()> {
+        self.client
+            .delete(self.url.join(&format!("fork/{}", fork.id))?)
+            .headers(self.header.clone())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Sets the ether balance of the accounts on a fork.
+///
+/// Transactions on forks can be sent from any account with `eth_sendTransaction`, for example by
+/// using `ethcontract::Account::Local`. The accounts only need ether to pay for gas.
+pub async fn set_balance(fork: &Web3, accounts: &[H160], balance: U256) -> Result<()> {
+    let params = vec![
+        serde_json::to_value(accounts)?,
+        serde_json::to_value(balance)?,
+    ];
+    fork.transport()
+        .execute("tenderly_setBalance", params)
+        .await
+        .context("tenderly_setBalance failed")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn fork_urls() {
+        let api = TenderlyApi::new(
+            "https://api.tenderly.co/api/v1/account/user/project/project",
+            Client::new(),
+            "",
+        )
+        .unwrap();
+        assert_eq!(
+            api.url.join("fork").unwrap().as_str(),
+            "https://api.tenderly.co/api/v1/account/user/project/project/fork"
+        );
+
+        let fork = Fork::new("abc".to_string()).unwrap();
+        assert_eq!(fork.rpc_url.as_str(), "https://rpc.tenderly.co/fork/abc");
+    }
+
+    #[test]
+    fn serialize_fork_request() {
+        let request = ForkRequest {
+            network_id: "1",
+            block_number: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({ "network_id": "1" })
+        );
+    }
+
+    #[test]
+    fn deserialize_fork_response() {
+        let response: ForkResponse = serde_json::from_value(json!({
+            "simulation_fork": {
+                "id": "e3a4b9b1-2b2c-4b1a-9c1a-2f1d8e3e0f5c",
+                "network_id": "1",
+                "block_number": 15000000,
+            },
+            "root_transaction": {},
+        }))
+        .unwrap();
+        assert_eq!(
+            response.simulation_fork.id,
+            "e3a4b9b1-2b2c-4b1a-9c1a-2f1d8e3e0f5c"
+        );
+    }
+
+    // cargo test -p shared tenderly_api -- --ignored --nocapture
+    #[tokio::test]
+    #[ignore]
+    async fn create_fund_and_delete_fork() {
+        let api = TenderlyApi::new(
+            std::env::var("TENDERLY_URL").unwrap(),
+            Client::new(),
+            &std::env::var("TENDERLY_API_KEY").unwrap(),
+        )
+        .unwrap();
+        let fork = api.create_fork("1", None).await.unwrap();
+        let web3 = crate::web3(&Client::new(), &fork.rpc_url, "fork");
+        let account = H160([0x42; 20]);
+        let balance = U256::exp10(18);
+        set_balance(&web3, &[account], balance).await.unwrap();
+        assert_eq!(web3.eth().balance(account, None).await.unwrap(), balance);
+        api.delete_fork(&fork).await.unwrap();
+    }
+}
@@ -0,0 +1,243 @@
+//! External threat intelligence lists of deny listed tokens and malicious addresses, for example
+//! the receivers of phishing campaigns that trick users into signing orders.
+//!
+//! The lists are fetched periodically and cached. Orders matching them are either rejected or, in
+//! warn mode, accepted with a warning that quote responses include so that wallets can warn
+//! their users before they sign.
+
+use crate::{arguments::duration_from_seconds, order_validation::PreOrderData};
+use anyhow::{Context, Result};
+use model::quote::ThreatWarning;
+use primitive_types::H160;
+use prometheus::{IntCounterVec, IntGaugeVec};
+use reqwest::{Client, Url};
+use std::{
+    collections::HashSet,
+    fmt::{self, Display, Formatter},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+/// Arguments related to threat intelligence lists.
+#[derive(clap::Parser)]
+pub struct Arguments {
+    /// URLs of token deny lists. Every list is a JSON array of token addresses.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub threat_intel_token_lists: Vec<Url>,
+
+    /// URLs of lists of malicious addresses that should not receive the proceeds of orders.
+    /// Every list is a JSON array of addresses.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub threat_intel_address_lists: Vec<Url>,
+
+    /// How often in seconds the lists get fetched again.
+    #[clap(
+        long,
+        env,
+        default_value = "3600",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub threat_intel_update_interval: Duration,
+
+    /// Whether orders matching the lists get rejected or only flagged in quote responses.
+    #[clap(long, env, default_value = "warn", arg_enum)]
+    pub threat_intel_mode: ThreatIntelMode,
+}
+
+impl Display for Arguments {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "threat_intel_token_lists: {:?}",
+            self.threat_intel_token_lists
+        )?;
+        writeln!(
+            f,
+            "threat_intel_address_lists: {:?}",
+            self.threat_intel_address_lists
+        )?;
+        writeln!(
+            f,
+            "threat_intel_update_interval: {:?}",
+            self.threat_intel_update_interval
+        )?;
+        writeln!(f, "threat_intel_mode: {:?}", self.threat_intel_mode)?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ArgEnum)]
+pub enum ThreatIntelMode {
+    Warn,
+    Reject,
+}
+
+#[derive(prometheus_metric_storage::MetricStorage)]
+#[metric(subsystem = "threat_intel")]
+struct Metrics {
+    /// Number of orders and quotes that matched a list by kind of match.
+    #[metric(labels("kind"))]
+    matches: IntCounterVec,
+
+    /// Number of addresses on the lists by kind of list.
+    #[metric(labels("kind"))]
+    entries: IntGaugeVec,
+
+    /// List updates by result.
+    #[metric(labels("result"))]
+    updates: IntCounterVec,
+}
+
+impl Metrics {
+    fn get() -> &'static Self {
+        Metrics::instance(global_metrics::get_metric_storage_registry()).unwrap()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Lists {
+    tokens: HashSet<H160>,
+    addresses: HashSet<H160>,
+}
+
+pub struct ThreatIntel {
+    client: Client,
+    token_lists: Vec<Url>,
+    address_lists: Vec<Url>,
+    mode: ThreatIntelMode,
+    lists: RwLock<Lists>,
+}
+
+impl ThreatIntel {
+    /// The lists are empty until the first update.
+    pub fn new(client: Client, args: &Arguments) -> Self {
+        Self {
+            client,
+            token_lists: args.threat_intel_token_lists.clone(),
+            address_lists: args.threat_intel_address_lists.clone(),
+            mode: args.threat_intel_mode,
+            lists: Default::default(),
+        }
+    }
+
+    pub fn mode(&self) -> ThreatIntelMode {
+        self.mode
+    }
+
+    /// Returns the addresses of the order that are on a list.
+    pub fn warnings(&self, order: &PreOrderData) -> Vec<ThreatWarning> {
+        let lists = self.lists.read().unwrap();
+        let mut warnings = Vec::new();
+        for token in [order.sell_token, order.buy_token] {
+            if lists.tokens.contains(&token) {
+                warnings.push(ThreatWarning::DenyListedToken(token));
+            }
+        }
+        if lists.addresses.contains(&order.receiver) {
+            warnings.push(ThreatWarning::MaliciousReceiver(order.receiver));
+        }
+        warnings
+    }
+
+    /// Like `warnings` but also counts the matches.
+    pub fn check(&self, order: &PreOrderData) -> Vec<ThreatWarning> {
+        let warnings = self.warnings(order);
+        for warning in &warnings {
+            let kind = match warning {
+                ThreatWarning::DenyListedToken(_) => "token",
+                ThreatWarning::MaliciousReceiver(_) => "receiver",
+            };
+            Metrics::get().matches.with_label_values(&[kind]).inc();
+        }
+        warnings
+    }
+
+    /// Fetches all lists. If any list can't be fetched the previous lists stay in effect.
+    pub async fn update(&self) -> Result<()> {
+        let tokens = self.fetch_all(&self.token_lists).await?;
+        let addresses = self.fetch_all(&self.address_lists).await?;
+        let metrics = Metrics::get();
+        metrics
+            .entries
+            .with_label_values(&["token"])
+            .set(tokens.len() as i64);
+        metrics
+            .entries
+            .with_label_values(&["address"])
+            .set(addresses.len() as i64);
+        *self.lists.write().unwrap() = Lists { tokens, addresses };
+        Ok(())
+    }
+
+    pub async fn update_forever(self: Arc<Self>, interval: Duration) -> ! {
+        loop {
+            let result = match self.update().await {
+                Ok(()) => "success",
+                Err(err) => {
+                    tracing::warn!(?err, "failed to update threat intelligence lists");
+                    "error"
+                }
+            };
+            Metrics::get().updates.with_label_values(&[result]).inc();
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    async fn fetch_all(&self, urls: &[Url]) -> Result<HashSet<H160>> {
+        let mut addresses = HashSet::new();
+        for url in urls {
+            let list: Vec<H160> = self
+                .client
+                .get(url.clone())
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .with_context(|| format!("failed to fetch {url}"))?
+                .json()
+                .await
+                .with_context(|| format!("failed to decode {url}"))?;
+            addresses.extend(list);
+        }
+        Ok(addresses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_listed_addresses() {
+        let intel = ThreatIntel {
+            client: Client::new(),
+            token_lists: vec![],
+            address_lists: vec![],
+            mode: ThreatIntelMode::Warn,
+            lists: RwLock::new(Lists {
+                tokens: [H160([1; 20])].into_iter().collect(),
+                addresses: [H160([2; 20])].into_iter().collect(),
+            }),
+        };
+        let order = |sell_token, buy_token, receiver| PreOrderData {
+            sell_token,
+            buy_token,
+            receiver,
+            ..Default::default()
+        };
+
+        assert!(intel
+            .warnings(&order(H160([3; 20]), H160([3; 20]), H160([3; 20])))
+            .is_empty());
+        assert_eq!(
+            intel.warnings(&order(H160([3; 20]), H160([1; 20]), H160([2; 20]))),
+            [
+                ThreatWarning::DenyListedToken(H160([1; 20])),
+                ThreatWarning::MaliciousReceiver(H160([2; 20])),
+            ]
+        );
+        // Only the receiver gets checked against the address lists.
+        assert!(intel
+            .warnings(&order(H160([2; 20]), H160([3; 20]), H160([3; 20])))
+            .is_empty());
+    }
+}
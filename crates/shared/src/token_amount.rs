@@ -0,0 +1,200 @@
+//! An amount of a specific token.
+//!
+//! Amounts of different tokens, or amounts in atoms and in whole token units, are all represented
+//! as bare `U256`s or `BigRational`s throughout the code base which makes it easy to mix them up.
+//! `TokenAmount` keeps the token (and its decimals if known) next to the amount and only allows
+//! arithmetic between amounts of the same token.
+
+use crate::conversions::U256Ext;
+use anyhow::{anyhow, ensure, Context, Result};
+use num::{BigInt, BigRational, Signed};
+use primitive_types::{H160, U256};
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct TokenAmount {
+    pub token: H160,
+    /// The amount in the smallest unit (atoms) of the token.
+    pub amount: U256,
+    /// The ERC20 decimals of the token. Not all tokens implement them so they are optional.
+    pub decimals: Option<u8>,
+}
+
+impl TokenAmount {
+    pub fn new(token: H160, amount: U256) -> Self {
+        Self {
+            token,
+            amount,
+            decimals: None,
+        }
+    }
+
+    pub fn with_decimals(mut self, decimals: u8) -> Self {
+        self.decimals = Some(decimals);
+        self
+    }
+
+    /// A zero amount of the token.
+    pub fn zero(token: H160) -> Self {
+        Self::new(token, U256::zero())
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.amount.is_zero()
+    }
+
+    /// Creates an amount with the same token (and decimals) as this one.
+    fn same_token(&self, amount: U256) -> Self {
+        Self { amount, ..*self }
+    }
+
+    fn ensure_same_token(&self, other: &Self) -> Result<()> {
+        ensure!(
+            self.token == other.token,
+            "cannot combine amounts of token {:?} and token {:?}",
+            self.token,
+            other.token
+        );
+        Ok(())
+    }
+
+    pub fn checked_add(&self, other: &Self) -> Result<Self> {
+        self.ensure_same_token(other)?;
+        let amount = self
+            .amount
+            .checked_add(other.amount)
+            .context("token amount overflow")?;
+        Ok(self.same_token(amount))
+    }
+
+    pub fn checked_sub(&self, other: &Self) -> Result<Self> {
+        self.ensure_same_token(other)?;
+        let amount = self
+            .amount
+            .checked_sub(other.amount)
+            .context("token amount underflow")?;
+        Ok(self.same_token(amount))
+    }
+
+    /// Like `checked_add` but saturates at `U256::MAX` instead of failing on overflow. Adding
+    /// amounts of different tokens is still an error.
+    pub fn saturating_add(&self, other: &Self) -> Result<Self> {
+        self.ensure_same_token(other)?;
+        Ok(self.same_token(self.amount.saturating_add(other.amount)))
+    }
+
+    /// The share of `bps` basis points of the amount, rounded down. Saturates at `U256::MAX`.
+    pub fn bps(&self, bps: u32) -> Self {
+        let amount = self.amount.full_mul(bps.into()) / 10_000;
+        self.same_token(amount.try_into().unwrap_or(U256::MAX))
+    }
+
+    pub fn to_big_rational(&self) -> BigRational {
+        self.amount.to_big_rational()
+    }
+
+    /// Converts a rational amount of atoms rounding down.
+    pub fn from_big_rational(token: H160, amount: &BigRational) -> Result<Self> {
+        let amount = number_conversions::big_rational_to_u256(&amount.floor())?;
+        Ok(Self::new(token, amount))
+    }
+
+    /// The amount in whole token units, i.e. `amount / 10^decimals`. Fails if the decimals of the
+    /// token aren't known.
+    pub fn to_units(&self) -> Result<BigRational> {
+        Ok(self.to_big_rational() / unit(self.decimals_or_err()?))
+    }
+
+    /// Converts an amount in whole token units into atoms rounding down.
+    pub fn from_units(token: H160, decimals: u8, units: &BigRational) -> Result<Self> {
+        ensure!(!units.is_negative(), "negative token amount");
+        let atoms = units * unit(decimals);
+        Ok(Self::from_big_rational(token, &atoms)?.with_decimals(decimals))
+    }
+
+    /// The value of the amount in native token given the token's price, which is the amount of
+    /// native token atoms one atom of the token is worth.
+    pub fn native_value(&self, price: &BigRational) -> BigRational {
+        price * self.to_big_rational()
+    }
+
+    fn decimals_or_err(&self) -> Result<u8> {
+        self.decimals
+            .ok_or_else(|| anyhow!("unknown decimals of token {:?}", self.token))
+    }
+}
+
+fn unit(decimals: u8) -> BigRational {
+    BigRational::from_integer(num::pow(BigInt::from(10), decimals.into()))
+}
+
+impl Display for TokenAmount {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{} of {:?}", self.amount, self.token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic_requires_same_token() {
+        let a = TokenAmount::new(H160([1; 20]), 100.into());
+        let b = TokenAmount::new(H160([2; 20]), 1.into());
+        assert!(a.checked_add(&b).is_err());
+        assert!(a.checked_sub(&b).is_err());
+        assert!(a.saturating_add(&b).is_err());
+
+        let c = TokenAmount::new(H160([1; 20]), 30.into()).with_decimals(6);
+        assert_eq!(a.checked_add(&c).unwrap().amount, 130.into());
+        assert_eq!(a.checked_sub(&c).unwrap().amount, 70.into());
+        assert!(c.checked_sub(&a).is_err());
+
+        let max = TokenAmount::new(H160([1; 20]), U256::MAX);
+        assert!(max.checked_add(&a).is_err());
+        assert_eq!(max.saturating_add(&a).unwrap().amount, U256::MAX);
+    }
+
+    #[test]
+    fn basis_points_round_down() {
+        let amount = TokenAmount::new(H160([1; 20]), 9_999.into());
+        assert_eq!(amount.bps(1).amount, 0.into());
+        assert_eq!(amount.bps(5_000).amount, 4_999.into());
+        assert_eq!(
+            TokenAmount::new(H160([1; 20]), U256::MAX)
+                .bps(10_000)
+                .amount,
+            U256::MAX
+        );
+    }
+
+    #[test]
+    fn converts_units() {
+        let token = H160([1; 20]);
+        let amount =
+            TokenAmount::from_units(token, 6, &BigRational::new(3.into(), 2.into())).unwrap();
+        assert_eq!(amount.amount, 1_500_000.into());
+        assert_eq!(amount.decimals, Some(6));
+        assert_eq!(
+            amount.to_units().unwrap(),
+            BigRational::new(3.into(), 2.into())
+        );
+
+        assert!(TokenAmount::new(token, 1.into()).to_units().is_err());
+        assert!(
+            TokenAmount::from_units(token, 6, &BigRational::from_integer((-1).into())).is_err()
+        );
+        // Fractions of atoms get rounded down.
+        assert_eq!(
+            TokenAmount::from_big_rational(token, &BigRational::new(7.into(), 2.into()))
+                .unwrap()
+                .amount,
+            3.into()
+        );
+        assert_eq!(
+            TokenAmount::new(token, 4.into()).native_value(&BigRational::new(1.into(), 2.into())),
+            BigRational::from_integer(2.into())
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::Web3;
+use crate::{token_list::TokenLists, Web3};
 use async_trait::async_trait;
 use contracts::ERC20;
 use ethcontract::{batch::CallBatch, H160};
@@ -126,9 +126,52 @@ impl TokenInfoFetching for CachedTokenInfoFetcher {
     }
 }
 
+/// Takes token information from trusted token lists and fetches it only for unlisted tokens.
+pub struct TokenListInfoFetcher {
+    lists: Arc<TokenLists>,
+    inner: Box<dyn TokenInfoFetching>,
+}
+
+impl TokenListInfoFetcher {
+    pub fn new(lists: Arc<TokenLists>, inner: Box<dyn TokenInfoFetching>) -> Self {
+        Self { lists, inner }
+    }
+}
+
+#[async_trait]
+impl TokenInfoFetching for TokenListInfoFetcher {
+    async fn get_token_infos(&self, addresses: &[H160]) -> HashMap<H160, TokenInfo> {
+        let list = self.lists.current();
+        let mut infos: HashMap<H160, TokenInfo> = addresses
+            .iter()
+            .filter_map(|address| {
+                let token = list.get(address)?;
+                Some((
+                    *address,
+                    TokenInfo {
+                        decimals: Some(token.decimals),
+                        symbol: Some(token.symbol.clone()),
+                    },
+                ))
+            })
+            .collect();
+
+        let unlisted: Vec<H160> = addresses
+            .iter()
+            .filter(|address| !infos.contains_key(address))
+            .cloned()
+            .collect();
+        if !unlisted.is_empty() {
+            infos.extend(self.inner.get_token_infos(&unlisted).await);
+        }
+        infos
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::token_list::{Token, TokenList};
     use maplit::hashmap;
 
     #[tokio::test]
@@ -170,4 +213,39 @@ mod tests {
         // Should try to refetch the item thus satisfying the times(2) constraint above.
         cached_token_info_fetcher.get_token_infos(&[address1]).await;
     }
+
+    #[tokio::test]
+    async fn token_list_info_fetcher_only_fetches_unlisted_tokens() {
+        let listed = H160::from_low_u64_be(1);
+        let unlisted = H160::from_low_u64_be(2);
+        let lists = TokenLists::from(TokenList::new(hashmap! {
+            listed => Token {
+                address: listed,
+                symbol: "LIST".to_string(),
+                name: "Listed".to_string(),
+                decimals: 6,
+            },
+        }));
+
+        let mut inner = MockTokenInfoFetching::new();
+        inner
+            .expect_get_token_infos()
+            .times(1)
+            .withf(move |addresses| addresses == [unlisted])
+            .returning(move |_| {
+                hashmap! {
+                    unlisted => TokenInfo { decimals: Some(18), symbol: Some("CAT".to_string()) },
+                }
+            });
+        let fetcher = TokenListInfoFetcher::new(Arc::new(lists), Box::new(inner));
+
+        let token_infos = fetcher.get_token_infos(&[listed, unlisted]).await;
+        assert_eq!(
+            token_infos,
+            hashmap! {
+                listed => TokenInfo { decimals: Some(6), symbol: Some("LIST".to_string()) },
+                unlisted => TokenInfo { decimals: Some(18), symbol: Some("CAT".to_string()) },
+            }
+        );
+    }
 }
@@ -1,10 +1,90 @@
-use std::collections::HashMap;
+//! Token lists in the Uniswap token list format (https://tokenlists.org).
+//!
+//! Several lists can be combined into one federated list that gets fetched again periodically so
+//! that it doesn't go stale. Lists can be pinned to a content hash or required to be signed by a
+//! trusted address. The status of every list is tracked individually so that one broken list
+//! doesn't take down the others: a list that fails to update keeps its last accepted version.
 
-use anyhow::Result;
-use ethcontract::H160;
-use reqwest::{Client, IntoUrl};
+use crate::arguments::duration_from_seconds;
+use anyhow::{anyhow, ensure, Context, Result};
+use ethcontract::{H160, H256};
+use model::signature::EcdsaSignature;
+use prometheus::{IntCounterVec, IntGaugeVec};
+use reqwest::{Client, IntoUrl, Url};
 use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+use web3::{signing, types::Recovery};
 
+/// Arguments related to trusted token lists.
+#[derive(clap::Parser)]
+pub struct Arguments {
+    /// URLs of trusted token lists in the Uniswap token list format. Tokens on the lists skip bad
+    /// token detection and their metadata is taken from the lists. The keccak256 hash of a list
+    /// can be pinned in the fragment of its URL, for example `https://example.com/list.json#0x..`.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub token_lists: Vec<TokenListSource>,
+
+    /// How often in seconds the token lists get fetched again.
+    #[clap(
+        long,
+        env,
+        default_value = "3600",
+        parse(try_from_str = duration_from_seconds),
+    )]
+    pub token_list_update_interval: Duration,
+
+    /// If set, token lists are only accepted if one of these addresses signed them. The signature
+    /// is fetched from the URL of the list with `.sig` appended and is the hex encoded `eth_sign`
+    /// signature of the keccak256 hash of the list.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub token_list_signers: Vec<H160>,
+}
+
+impl Display for Arguments {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "token_lists: {:?}", self.token_lists)?;
+        writeln!(
+            f,
+            "token_list_update_interval: {:?}",
+            self.token_list_update_interval
+        )?;
+        writeln!(f, "token_list_signers: {:?}", self.token_list_signers)?;
+        Ok(())
+    }
+}
+
+/// The URL of a token list and optionally the keccak256 hash its content must have.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenListSource {
+    pub url: Url,
+    pub hash: Option<H256>,
+}
+
+impl FromStr for TokenListSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut url = Url::parse(s)?;
+        let hash = url
+            .fragment()
+            .map(|fragment| {
+                fragment
+                    .parse()
+                    .with_context(|| format!("invalid token list hash {fragment:?}"))
+            })
+            .transpose()?;
+        url.set_fragment(None);
+        Ok(Self { url, hash })
+    }
+}
+
+#[derive(Default)]
 pub struct TokenList {
     tokens: HashMap<H160, Token>,
 }
@@ -44,6 +124,240 @@ impl TokenList {
     pub fn all(&self) -> Vec<Token> {
         self.tokens.values().cloned().collect()
     }
+
+    /// Combines the lists. Earlier lists take precedence if a token is on several lists.
+    fn merge<'a>(lists: impl IntoIterator<Item = &'a TokenList>) -> Self {
+        let mut tokens = HashMap::new();
+        for list in lists {
+            for (address, token) in &list.tokens {
+                tokens.entry(*address).or_insert_with(|| token.clone());
+            }
+        }
+        Self { tokens }
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage)]
+#[metric(subsystem = "token_list")]
+struct Metrics {
+    /// Number of tokens on the last accepted version of a list.
+    #[metric(labels("list"))]
+    tokens: IntGaugeVec,
+
+    /// List updates by list and result.
+    #[metric(labels("list", "result"))]
+    updates: IntCounterVec,
+}
+
+impl Metrics {
+    fn get() -> &'static Self {
+        Metrics::instance(global_metrics::get_metric_storage_registry()).unwrap()
+    }
+}
+
+/// The state of a single list of a federated list.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenListStatus {
+    pub url: Url,
+    /// The name of the last accepted version of the list.
+    pub name: Option<String>,
+    /// The number of tokens for our chain on the last accepted version of the list.
+    pub tokens: usize,
+    /// When the list was last accepted.
+    pub last_update: Option<SystemTime>,
+    /// Why the last update failed if it did.
+    pub last_error: Option<String>,
+}
+
+struct State {
+    /// The last accepted version of every list in the order of the sources.
+    lists: Vec<Option<TokenList>>,
+    statuses: Vec<TokenListStatus>,
+    merged: Arc<TokenList>,
+}
+
+/// Several token lists that are fetched periodically and combined into one.
+pub struct TokenLists {
+    client: Client,
+    chain_id: u64,
+    sources: Vec<TokenListSource>,
+    signers: Vec<H160>,
+    state: RwLock<State>,
+}
+
+impl TokenLists {
+    /// The lists are empty until the first update.
+    pub fn new(
+        client: Client,
+        chain_id: u64,
+        sources: Vec<TokenListSource>,
+        signers: Vec<H160>,
+    ) -> Self {
+        let statuses = sources
+            .iter()
+            .map(|source| TokenListStatus {
+                url: source.url.clone(),
+                name: None,
+                tokens: 0,
+                last_update: None,
+                last_error: None,
+            })
+            .collect();
+        Self {
+            client,
+            chain_id,
+            state: RwLock::new(State {
+                lists: sources.iter().map(|_| None).collect(),
+                statuses,
+                merged: Default::default(),
+            }),
+            sources,
+            signers,
+        }
+    }
+
+    pub fn from_arguments(client: Client, chain_id: u64, args: &Arguments) -> Self {
+        Self::new(
+            client,
+            chain_id,
+            args.token_lists.clone(),
+            args.token_list_signers.clone(),
+        )
+    }
+
+    /// The combination of the last accepted version of every list.
+    pub fn current(&self) -> Arc<TokenList> {
+        self.state.read().unwrap().merged.clone()
+    }
+
+    pub fn statuses(&self) -> Vec<TokenListStatus> {
+        self.state.read().unwrap().statuses.clone()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Fetches all lists. Lists that can't be fetched or verified keep their previous version.
+    pub async fn update(&self) {
+        if self.sources.is_empty() {
+            return;
+        }
+        let results =
+            futures::future::join_all(self.sources.iter().map(|source| self.fetch(source))).await;
+
+        let metrics = Metrics::get();
+        let mut state = self.state.write().unwrap();
+        for (i, result) in results.into_iter().enumerate() {
+            let url = self.sources[i].url.as_str();
+            match result {
+                Ok((name, list)) => {
+                    metrics
+                        .tokens
+                        .with_label_values(&[url])
+                        .set(list.tokens.len() as i64);
+                    metrics.updates.with_label_values(&[url, "success"]).inc();
+                    let status = &mut state.statuses[i];
+                    status.name = Some(name);
+                    status.tokens = list.tokens.len();
+                    status.last_update = Some(SystemTime::now());
+                    status.last_error = None;
+                    state.lists[i] = Some(list);
+                }
+                Err(err) => {
+                    tracing::warn!(%url, ?err, "failed to update token list");
+                    metrics.updates.with_label_values(&[url, "error"]).inc();
+                    state.statuses[i].last_error = Some(format!("{err:?}"));
+                }
+            }
+        }
+        let merged = TokenList::merge(state.lists.iter().flatten());
+        state.merged = Arc::new(merged);
+    }
+
+    pub async fn update_forever(self: Arc<Self>, interval: Duration) -> ! {
+        loop {
+            self.update().await;
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    async fn fetch(&self, source: &TokenListSource) -> Result<(String, TokenList)> {
+        let body = self.fetch_bytes(source.url.clone()).await?;
+        if let Some(hash) = source.hash {
+            verify_hash(&body, hash)?;
+        }
+        if !self.signers.is_empty() {
+            let mut url = source.url.clone();
+            url.set_path(&format!("{}.sig", url.path()));
+            let signature = self.fetch_bytes(url).await?;
+            let signature = std::str::from_utf8(&signature).context("invalid signature")?;
+            let signer = recover_signer(&body, signature)?;
+            ensure!(
+                self.signers.contains(&signer),
+                "list signed by untrusted address {signer:?}"
+            );
+        }
+        let model: TokenListModel =
+            serde_json::from_slice(&body).context("failed to decode token list")?;
+        Ok((
+            model.name,
+            TokenList::from_tokens(model.tokens, self.chain_id),
+        ))
+    }
+
+    async fn fetch_bytes(&self, url: Url) -> Result<Vec<u8>> {
+        let bytes = self
+            .client
+            .get(url.clone())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("failed to fetch {url}"))?
+            .bytes()
+            .await
+            .with_context(|| format!("failed to fetch {url}"))?;
+        Ok(bytes.to_vec())
+    }
+}
+
+/// A fixed list that never updates.
+impl From<TokenList> for TokenLists {
+    fn from(list: TokenList) -> Self {
+        let lists = Self::new(Client::new(), 0, Vec::new(), Vec::new());
+        lists.state.write().unwrap().merged = Arc::new(list);
+        lists
+    }
+}
+
+fn verify_hash(body: &[u8], expected: H256) -> Result<()> {
+    let hash = H256(signing::keccak256(body));
+    ensure!(
+        hash == expected,
+        "list hash {hash:?} doesn't match pinned hash {expected:?}"
+    );
+    Ok(())
+}
+
+/// Recovers the signer of the `eth_sign` signature of the keccak256 hash of the list.
+fn recover_signer(body: &[u8], signature: &str) -> Result<H160> {
+    let signature = signature.trim();
+    let bytes = hex::decode(signature.strip_prefix("0x").unwrap_or(signature))
+        .context("signature is not hex")?;
+    let signature = EcdsaSignature::from_slice(&bytes)?;
+    let message = eth_signed_message(&signing::keccak256(body));
+    let recovery = Recovery::new(message, signature.v as u64, signature.r, signature.s);
+    let (signature, recovery_id) = recovery
+        .as_signature()
+        .ok_or_else(|| anyhow!("invalid signature"))?;
+    Ok(signing::recover(&message, &signature, recovery_id)?)
+}
+
+fn eth_signed_message(hash: &[u8; 32]) -> [u8; 32] {
+    let mut message = [0u8; 60];
+    message[..28].copy_from_slice(b"\x19Ethereum Signed Message:\n32");
+    message[28..].copy_from_slice(hash);
+    signing::keccak256(&message)
 }
 
 /// Relevant parts of TokenList schema as defined in https://uniswap.org/tokenlist.schema.json
@@ -65,6 +379,8 @@ struct TokenModel {
 #[cfg(test)]
 pub mod tests {
     use super::*;
+    use secp256k1::ONE_KEY;
+    use web3::signing::{Key, SecretKeyRef};
 
     // https://github.com/Uniswap/token-lists/blob/master/test/schema/example.tokenlist.json
     const EXAMPLE_LIST: &str = r#"
@@ -159,4 +475,85 @@ pub mod tests {
             .get(&addr!("39AA39c021dfbaE8faC545936693aC917d5E7563"))
             .is_none());
     }
+
+    #[test]
+    fn parses_sources_with_pinned_hash() {
+        let source: TokenListSource = "https://example.com/list.json".parse().unwrap();
+        assert_eq!(source.url.as_str(), "https://example.com/list.json");
+        assert_eq!(source.hash, None);
+
+        let source: TokenListSource = format!("https://example.com/list.json#{:?}", H256([1; 32]))
+            .parse()
+            .unwrap();
+        assert_eq!(source.url.as_str(), "https://example.com/list.json");
+        assert_eq!(source.hash, Some(H256([1; 32])));
+
+        assert!("https://example.com/list.json#latest"
+            .parse::<TokenListSource>()
+            .is_err());
+    }
+
+    #[test]
+    fn earlier_lists_take_precedence() {
+        let token = |address: u64, symbol: &str| Token {
+            address: H160::from_low_u64_be(address),
+            symbol: symbol.into(),
+            name: symbol.into(),
+            decimals: 18,
+        };
+        let first = TokenList::new(maplit::hashmap! {
+            H160::from_low_u64_be(1) => token(1, "A"),
+        });
+        let second = TokenList::new(maplit::hashmap! {
+            H160::from_low_u64_be(1) => token(1, "B"),
+            H160::from_low_u64_be(2) => token(2, "C"),
+        });
+        let merged = TokenList::merge([&first, &second]);
+        assert_eq!(merged.get(&H160::from_low_u64_be(1)), Some(&token(1, "A")));
+        assert_eq!(merged.get(&H160::from_low_u64_be(2)), Some(&token(2, "C")));
+    }
+
+    #[test]
+    fn verifies_pinned_hash() {
+        let body = EXAMPLE_LIST.as_bytes();
+        assert!(verify_hash(body, H256(signing::keccak256(body))).is_ok());
+        assert!(verify_hash(body, H256::zero()).is_err());
+    }
+
+    #[test]
+    fn recovers_list_signer() {
+        let body = EXAMPLE_LIST.as_bytes();
+        let key = SecretKeyRef::new(&ONE_KEY);
+        let signature = key
+            .sign(&eth_signed_message(&signing::keccak256(body)), None)
+            .unwrap();
+        let signature = EcdsaSignature {
+            r: signature.r,
+            s: signature.s,
+            v: signature.v as u8,
+        };
+        let hex_signature = format!("0x{}", hex::encode(signature.to_bytes()));
+
+        assert_eq!(recover_signer(body, &hex_signature).unwrap(), key.address());
+        assert_ne!(
+            recover_signer(b"tampered", &hex_signature).unwrap(),
+            key.address()
+        );
+        assert!(recover_signer(body, "0x1234").is_err());
+    }
+
+    #[test]
+    fn fixed_list_does_not_update() {
+        let lists = TokenLists::from(TokenList::new(maplit::hashmap! {
+            testlib::tokens::USDC => Token {
+                address: testlib::tokens::USDC,
+                symbol: "USDC".into(),
+                name: "USD Coin".into(),
+                decimals: 6,
+            },
+        }));
+        assert!(lists.is_empty());
+        assert!(lists.statuses().is_empty());
+        assert!(lists.current().get(&testlib::tokens::USDC).is_some());
+    }
 }
@@ -1,10 +1,48 @@
-use crate::Web3;
+use crate::{revm_simulation::RevmSimulator, Web3};
 use anyhow::{anyhow, Context, Result};
+use std::sync::Arc;
 use web3::{
     types::{BlockNumber, BlockTrace, CallRequest, TraceType},
     Transport,
 };
 
+/// Simulates call requests applied together one after another on top of the latest block and
+/// returns traces in the format of `trace_callMany`.
+#[async_trait::async_trait]
+pub trait CallTracing: Send + Sync {
+    async fn trace_many(&self, requests: Vec<CallRequest>) -> Result<Vec<BlockTrace>>;
+}
+
+/// Traces with the node's `trace_callMany`.
+#[async_trait::async_trait]
+impl CallTracing for Web3 {
+    async fn trace_many(&self, requests: Vec<CallRequest>) -> Result<Vec<BlockTrace>> {
+        trace_many(requests, self).await
+    }
+}
+
+#[derive(Copy, Clone, Debug, clap::ArgEnum)]
+#[clap(rename_all = "verbatim")]
+pub enum TracingBackend {
+    Node,
+    Revm,
+}
+
+/// Creates the configured tracer. `Node` needs a tracing node and returns `None` without one.
+/// `Revm` fetches state from the tracing node if there is one and from `web3` otherwise.
+pub fn create_tracer(
+    backend: TracingBackend,
+    tracing_node: Option<Web3>,
+    web3: &Web3,
+) -> Option<Arc<dyn CallTracing>> {
+    match backend {
+        TracingBackend::Node => tracing_node.map(|node| Arc::new(node) as Arc<dyn CallTracing>),
+        TracingBackend::Revm => Some(Arc::new(RevmSimulator::new(
+            tracing_node.unwrap_or_else(|| web3.clone()),
+        ))),
+    }
+}
+
 // Use the trace_callMany api https://openethereum.github.io/JSONRPC-trace-module#trace_callmany
 // api to simulate these call requests applied together one after another.
 // Err if communication with the node failed.
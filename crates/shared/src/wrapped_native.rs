@@ -0,0 +1,102 @@
+//! The wrapped native token of a chain, for example WETH on Ethereum networks and WXDAI on Gnosis
+//! Chain.
+//!
+//! The token's address comes from the `WETH9` contract artifact. Everything else that differs
+//! between chains is configured here so that other modules don't have to special case chains.
+
+use anyhow::{bail, Result};
+use contracts::WETH9;
+use ethcontract::Bytes;
+use model::order::BUY_ETH_ADDRESS;
+use primitive_types::{H160, U256};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WrappedNativeConfig {
+    pub symbol: &'static str,
+    /// The amount of native token that native prices get estimated with. It is worth roughly the
+    /// same on all chains so that estimates have a comparable price impact.
+    pub price_estimation_amount: U256,
+}
+
+impl WrappedNativeConfig {
+    pub fn for_chain(chain_id: u64) -> Result<Self> {
+        Ok(match chain_id {
+            1 | 4 | 5 => Self {
+                symbol: "WETH",
+                price_estimation_amount: U256::exp10(18),
+            },
+            100 => Self {
+                symbol: "WXDAI",
+                price_estimation_amount: U256::exp10(21),
+            },
+            _ => bail!("unsupported chain {:#x}", chain_id),
+        })
+    }
+}
+
+/// Returns true if trading `sell_token` for `buy_token` only wraps the native token.
+pub fn is_wrap(wrapped_native: H160, sell_token: H160, buy_token: H160) -> bool {
+    sell_token == BUY_ETH_ADDRESS && buy_token == wrapped_native
+}
+
+/// Returns true if trading `sell_token` for `buy_token` only unwraps the native token.
+pub fn is_unwrap(wrapped_native: H160, sell_token: H160, buy_token: H160) -> bool {
+    sell_token == wrapped_native && buy_token == BUY_ETH_ADDRESS
+}
+
+/// The interaction that wraps `amount` of native token.
+pub fn wrap(wrapped_native: &WETH9, amount: U256) -> (H160, U256, Bytes<Vec<u8>>) {
+    let method = wrapped_native.deposit();
+    let calldata = method.tx.data.expect("no calldata").0;
+    (wrapped_native.address(), amount, Bytes(calldata))
+}
+
+/// The interaction that unwraps `amount` of wrapped native token.
+pub fn unwrap(wrapped_native: &WETH9, amount: U256) -> (H160, U256, Bytes<Vec<u8>>) {
+    let method = wrapped_native.withdraw(amount);
+    let calldata = method.tx.data.expect("no calldata").0;
+    (wrapped_native.address(), 0.into(), Bytes(calldata))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    #[test]
+    fn config_for_supported_chains() {
+        assert_eq!(WrappedNativeConfig::for_chain(1).unwrap().symbol, "WETH");
+        let gnosis_chain = WrappedNativeConfig::for_chain(100).unwrap();
+        assert_eq!(gnosis_chain.symbol, "WXDAI");
+        assert_eq!(gnosis_chain.price_estimation_amount, U256::exp10(21));
+        assert!(WrappedNativeConfig::for_chain(1337).is_err());
+    }
+
+    #[test]
+    fn detects_wrapping() {
+        let wrapped_native = H160([1; 20]);
+        let token = H160([2; 20]);
+        assert!(is_wrap(wrapped_native, BUY_ETH_ADDRESS, wrapped_native));
+        assert!(!is_wrap(wrapped_native, BUY_ETH_ADDRESS, token));
+        assert!(is_unwrap(wrapped_native, wrapped_native, BUY_ETH_ADDRESS));
+        assert!(!is_unwrap(wrapped_native, token, BUY_ETH_ADDRESS));
+        assert!(!is_unwrap(wrapped_native, BUY_ETH_ADDRESS, wrapped_native));
+    }
+
+    #[test]
+    fn encodes_wrap_and_unwrap() {
+        let wrapped_native = dummy_contract!(WETH9, [0x42; 20]);
+        let amount = U256::from(1337);
+
+        let (target, value, calldata) = wrap(&wrapped_native, amount);
+        assert_eq!(target, wrapped_native.address());
+        assert_eq!(value, amount);
+        assert_eq!(calldata.0, hex!("d0e30db0"));
+
+        let (target, value, calldata) = unwrap(&wrapped_native, amount);
+        assert_eq!(target, wrapped_native.address());
+        assert_eq!(value, U256::zero());
+        assert_eq!(calldata.0[..4], hex!("2e1a7d4d"));
+        assert_eq!(U256::from_big_endian(&calldata.0[4..]), amount);
+    }
+}
@@ -5,13 +5,14 @@
 //! <https://api.0x.org/>
 
 use crate::debug_bytes;
+use crate::error_class::{ClassifyError, ErrorClass};
 use crate::solver_utils::{deserialize_decimal_f64, Slippage};
 use anyhow::{Context, Result};
 use chrono::{DateTime, NaiveDateTime, Utc};
 use derivative::Derivative;
 use ethcontract::{H160, H256, U256};
 use model::u256_decimal;
-use reqwest::{Client, IntoUrl, Url};
+use reqwest::{Client, IntoUrl, StatusCode, Url};
 use serde::Deserialize;
 use std::collections::HashSet;
 use thiserror::Error;
@@ -367,6 +368,9 @@ pub enum ZeroExResponseError {
     #[error("uncatalogued error message: {0}")]
     UnknownZeroExError(String),
 
+    #[error("rate limited")]
+    RateLimited,
+
     #[error("Error({0}) for response {1}")]
     DeserializeError(serde_json::Error, String),
 
@@ -379,6 +383,19 @@ pub enum ZeroExResponseError {
     Send(reqwest::Error),
 }
 
+impl ClassifyError for ZeroExResponseError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::ServerError(_) => ErrorClass::Transient,
+            Self::RateLimited => ErrorClass::RateLimited,
+            Self::UnknownZeroExError(_) => ErrorClass::Fatal,
+            Self::DeserializeError(..) => ErrorClass::Invalid,
+            Self::TextFetch(_) => ErrorClass::Transient,
+            Self::Send(err) => err.class(),
+        }
+    }
+}
+
 #[async_trait::async_trait]
 impl ZeroExApi for DefaultZeroExApi {
     async fn get_swap(&self, query: SwapQuery) -> Result<SwapResponse, ZeroExResponseError> {
@@ -445,10 +462,11 @@ impl DefaultZeroExApi {
         if let Some(key) = &self.api_key {
             request = request.header("0x-api-key", key);
         }
-        let response_text = request
-            .send()
-            .await
-            .map_err(ZeroExResponseError::Send)?
+        let response = request.send().await.map_err(ZeroExResponseError::Send)?;
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            return Err(ZeroExResponseError::RateLimited);
+        }
+        let response_text = response
             .text()
             .await
             .map_err(ZeroExResponseError::TextFetch)?;
@@ -1,11 +1,19 @@
 use crate::{
+    buffer_usage_limits::BufferUsageLimit,
+    encoding::SettlementContractVersion,
     settlement_access_list::AccessListEstimatorType,
-    solver::{ExternalSolverArg, SolverAccountArg, SolverType},
+    settlement_simulation::SimulationBackend,
+    solver::{
+        http_solver::flash_loans::FlashLoanLender, ExternalSolverArg, SolverAccountArg, SolverType,
+    },
 };
 use primitive_types::H160;
 use reqwest::Url;
-use shared::arguments::{display_list, display_option, display_secret_option};
-use std::{num::NonZeroU8, time::Duration};
+use shared::{
+    arguments::{display_list, display_option, display_secret_option},
+    token_list::TokenListSource,
+};
+use std::{net::SocketAddr, num::NonZeroU8, path::PathBuf, time::Duration};
 
 #[derive(clap::Parser)]
 pub struct Arguments {
@@ -87,6 +95,13 @@ pub struct Arguments {
     #[clap(long, env, use_value_delimiter = true)]
     pub external_solvers: Option<Vec<ExternalSolverArg>>,
 
+    /// Contracts that tokens can be flash borrowed from in the form of `protocol|address|fee`
+    /// where protocol is `BalancerV2` or `AaveV2` and fee is the fraction of the borrowed amount
+    /// charged, for example `AaveV2|0x7d2768dE32b0b80b7a3454c06BdAc94A69DDc7A9|0.0009`. HTTP
+    /// solvers get told which flash loans are available for the tokens of an auction.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub flash_loan_lenders: Vec<FlashLoanLender>,
+
     /// A settlement must contain at least one order older than this duration in seconds for it
     /// to be applied.  Larger values delay individual settlements more but have a higher
     /// coincidence of wants chance.
@@ -115,14 +130,25 @@ pub struct Arguments {
     )]
     pub solver_time_limit: Duration,
 
-    /// The list of tokens our settlement contract is willing to buy when settling trades
-    /// without external liquidity
+    /// The token lists of tokens our settlement contract is willing to buy when settling trades
+    /// without external liquidity. The keccak256 hash of a list can be pinned in the fragment of
+    /// its URL.
     #[clap(
         long,
         env,
-        default_value = "https://tokens.coingecko.com/uniswap/all.json"
+        default_value = "https://tokens.coingecko.com/uniswap/all.json",
+        use_value_delimiter = true
     )]
-    pub market_makable_token_list: String,
+    pub market_makable_token_list: Vec<TokenListSource>,
+
+    /// How often in seconds the market makable token lists get fetched again.
+    #[clap(
+        long,
+        env,
+        default_value = "3600",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub market_makable_token_list_update_interval: Duration,
 
     /// The maximum gas price in Gwei the solver is willing to pay in a settlement.
     #[clap(
@@ -149,6 +175,12 @@ pub struct Arguments {
     #[clap(long, env)]
     pub oneinch_max_slippage_in_eth: Option<f64>,
 
+    /// JSON file with overrides for runtime tunable settings that gets read every time the
+    /// process receives SIGHUP. Supported keys are `paraswapSlippageBps`, `zeroexSlippageBps` and
+    /// `oneinchSlippageBps`, missing keys keep the value of the corresponding argument.
+    #[clap(long, env)]
+    pub reload_config_file: Option<PathBuf>,
+
     /// How to to submit settlement transactions.
     /// Expected to contain either:
     /// 1. One value equal to TransactionStrategyArg::DryRun or
@@ -178,6 +210,17 @@ pub struct Arguments {
     #[clap(long, env)]
     pub tenderly_api_key: Option<String>,
 
+    /// Which backend to use for simulating transactions. `Tenderly` requires the Tenderly URL and
+    /// API key to be set and only simulates historic transactions while settlements get simulated
+    /// on the node. `Revm` simulates both locally with state fetched from the node.
+    #[clap(long, env, arg_enum, ignore_case = true, default_value = "Tenderly")]
+    pub simulation_backend: SimulationBackend,
+
+    /// The revision of the settlement contract deployed on the chain, which determines how
+    /// settlements get encoded into `settle` calls.
+    #[clap(long, env, arg_enum, ignore_case = true, default_value = "V1")]
+    pub settlement_contract_version: SettlementContractVersion,
+
     /// The API endpoint of the Eden network for transaction submission.
     #[clap(long, env, default_value = "https://api.edennetwork.io/v1/rpc")]
     pub eden_api_url: Url,
@@ -246,6 +289,26 @@ pub struct Arguments {
     #[clap(long, env)]
     pub disable_high_risk_public_mempool_transactions: bool,
 
+    /// The API endpoint of the relayer executing settlements for the `Relayer` transaction
+    /// strategy. The relayer gets paid in the native token from the settlement contract's buffer
+    /// so the solver accounts don't need to hold ETH. Its executing account has to be
+    /// allow-listed as a solver.
+    #[clap(long, env)]
+    pub relayer_url: Option<Url>,
+
+    /// The API key sent to the relayer.
+    #[clap(long, env)]
+    pub relayer_api_key: Option<String>,
+
+    /// The address the relayer's fees are paid to.
+    #[clap(long, env)]
+    pub relayer_fee_recipient: Option<H160>,
+
+    /// The factor by which the fee paid to the relayer exceeds the estimated gas cost of the
+    /// settlement.
+    #[clap(long, env, default_value = "1.2", parse(try_from_str = shared::arguments::parse_unbounded_factor))]
+    pub relayer_fee_factor: f64,
+
     /// Fee scaling factor for objective value. This controls the constant
     /// factor by which order fees are multiplied with. Setting this to a value
     /// greater than 1.0 makes settlements with negative objective values less
@@ -288,6 +351,78 @@ pub struct Arguments {
     /// in the settlement are checked for price deviation.
     #[clap(long, env, use_value_delimiter = true)]
     pub token_list_restriction_for_price_checks: Option<Vec<H160>>,
+
+    /// Limits how much of a token's balance in the settlement contract may be used as internal
+    /// buffers, in the form of `token|per_settlement|per_day` with amounts in the token's base
+    /// units. Settlements exceeding a limit are discarded. Tokens without a configured limit are
+    /// unrestricted.
+    #[clap(long, env, use_value_delimiter = true)]
+    pub buffer_usage_limits: Vec<BufferUsageLimit>,
+
+    /// Database in which the buffer usage of executed settlements is recorded so that the daily
+    /// buffer usage limits hold across restarts and solver instances. Without it the usage is only
+    /// tracked in memory. External solvers also pick up the callback urls their teams configured
+    /// in it.
+    #[clap(long, env)]
+    pub db_url: Option<Url>,
+
+    /// The number of mined settlements per interaction class whose median gas replaces the
+    /// static gas estimates HTTP solvers get for the class. Not setting it keeps the static
+    /// estimates.
+    #[clap(long, env)]
+    pub realized_gas_samples: Option<usize>,
+
+    /// Orders that get placed within this many seconds after the driver cut an auction are added
+    /// to an amended version of the auction that solvers solve in addition to the original one.
+    /// Auctions are not amended if not set.
+    #[clap(
+        long,
+        env,
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub auction_amendment_window: Option<Duration>,
+
+    /// The ETH balance below which the account of a solver is reported as running low on funds.
+    #[clap(long, env, default_value = "0.1")]
+    pub solver_balance_alert_threshold: f64,
+
+    /// If set, solvers whose account balance can't pay for a settlement using this much gas at
+    /// the current gas price don't participate in auctions.
+    #[clap(long, env)]
+    pub solver_balance_required_settlement_gas: Option<u64>,
+
+    /// A tracing Ethereum node URL supporting `trace_callMany`. If set, the solutions reported to
+    /// the solver competition include how they change the token balances of the settlement
+    /// contract.
+    #[clap(long, env)]
+    pub tracing_node_url: Option<Url>,
+
+    /// Number of consecutive responses violating the solution schema after which a solver gets
+    /// demoted and doesn't participate in auctions for a while. 0 disables demotions.
+    #[clap(long, env, default_value = "3")]
+    pub solver_demotion_threshold: usize,
+
+    /// How long in seconds a solver stays demoted.
+    #[clap(
+        long,
+        env,
+        default_value = "600",
+        parse(try_from_str = shared::arguments::duration_from_seconds),
+    )]
+    pub solver_demotion_period: Duration,
+
+    /// The base URL HTTP solvers reach the liquidity request endpoint at. If set, solvers can
+    /// request liquidity missing from their instance while they solve.
+    #[clap(long, env)]
+    pub liquidity_request_url: Option<Url>,
+
+    /// Address to serve the liquidity request endpoint on.
+    #[clap(long, env, default_value = "0.0.0.0:9590")]
+    pub liquidity_request_address: SocketAddr,
+
+    /// The maximum number of token pairs solvers can request liquidity for at once.
+    #[clap(long, env, default_value = "20")]
+    pub liquidity_request_max_pairs: usize,
 }
 
 impl std::fmt::Display for Arguments {
@@ -318,19 +453,30 @@ impl std::fmt::Display for Arguments {
                 .flatten()
                 .map(|solver| format!("{}|{}|{:?}", solver.name, solver.url, solver.account)),
         )?;
+        writeln!(f, "flash_loan_lenders: {:?}", self.flash_loan_lenders)?;
         writeln!(f, "min_order_age: {:?}", self.min_order_age)?;
         writeln!(f, "metrics_port: {}", self.metrics_port)?;
         writeln!(f, "max_merged_settlements: {}", self.max_merged_settlements)?;
         writeln!(f, "solver_time_limit: {:?}", self.solver_time_limit)?;
         writeln!(
             f,
-            "market_makable_token_list: {}",
+            "market_makable_token_list: {:?}",
             self.market_makable_token_list
         )?;
+        writeln!(
+            f,
+            "market_makable_token_list_update_interval: {:?}",
+            self.market_makable_token_list_update_interval
+        )?;
         writeln!(f, "gas_price_cap: {}", self.gas_price_cap)?;
         writeln!(f, "paraswap_slippage_bps: {}", self.paraswap_slippage_bps)?;
         writeln!(f, "zeroex_slippage_bps: {}", self.zeroex_slippage_bps)?;
         writeln!(f, "oneinch_slippage_bps: {}", self.oneinch_slippage_bps)?;
+        display_option(
+            f,
+            "reload_config_file",
+            &self.reload_config_file.as_ref().map(|path| path.display()),
+        )?;
         writeln!(f, "transaction_strategy: {:?}", self.transaction_strategy)?;
         writeln!(
             f,
@@ -339,6 +485,12 @@ impl std::fmt::Display for Arguments {
         )?;
         display_option(f, "tenderly_url", &self.tenderly_url)?;
         display_secret_option(f, "tenderly_api_key", &self.tenderly_api_key)?;
+        writeln!(f, "simulation_backend: {:?}", self.simulation_backend)?;
+        writeln!(
+            f,
+            "settlement_contract_version: {:?}",
+            self.settlement_contract_version
+        )?;
         writeln!(f, "eden_api_url: {}", self.eden_api_url)?;
         display_list(f, "flashbots_api_url", &self.flashbots_api_url)?;
         writeln!(
@@ -376,6 +528,16 @@ impl std::fmt::Display for Arguments {
             "disable_high_risk_public_mempool_transactions: {}",
             self.disable_high_risk_public_mempool_transactions,
         )?;
+        display_option(f, "relayer_url", &self.relayer_url)?;
+        display_secret_option(f, "relayer_api_key", &self.relayer_api_key)?;
+        display_option(
+            f,
+            "relayer_fee_recipient",
+            &self
+                .relayer_fee_recipient
+                .map(|address| format!("{:?}", address)),
+        )?;
+        writeln!(f, "relayer_fee_factor: {}", self.relayer_fee_factor)?;
         writeln!(
             f,
             "fee_objective_scaling_factor: {}",
@@ -399,6 +561,54 @@ impl std::fmt::Display for Arguments {
             "token_list_restriction_for_price_checks: {:?}",
             self.token_list_restriction_for_price_checks
         )?;
+        writeln!(f, "buffer_usage_limits: {:?}", self.buffer_usage_limits)?;
+        writeln!(
+            f,
+            "db_url: {}",
+            if self.db_url.is_some() {
+                "SECRET"
+            } else {
+                "None"
+            }
+        )?;
+        display_option(f, "realized_gas_samples", &self.realized_gas_samples)?;
+        writeln!(
+            f,
+            "auction_amendment_window: {:?}",
+            self.auction_amendment_window
+        )?;
+        writeln!(
+            f,
+            "solver_balance_alert_threshold: {}",
+            self.solver_balance_alert_threshold
+        )?;
+        display_option(
+            f,
+            "solver_balance_required_settlement_gas",
+            &self.solver_balance_required_settlement_gas,
+        )?;
+        display_option(f, "tracing_node_url", &self.tracing_node_url)?;
+        writeln!(
+            f,
+            "solver_demotion_threshold: {}",
+            self.solver_demotion_threshold
+        )?;
+        writeln!(
+            f,
+            "solver_demotion_period: {:?}",
+            self.solver_demotion_period
+        )?;
+        display_option(f, "liquidity_request_url", &self.liquidity_request_url)?;
+        writeln!(
+            f,
+            "liquidity_request_address: {}",
+            self.liquidity_request_address
+        )?;
+        writeln!(
+            f,
+            "liquidity_request_max_pairs: {}",
+            self.liquidity_request_max_pairs
+        )?;
         Ok(())
     }
 }
@@ -410,5 +620,6 @@ pub enum TransactionStrategyArg {
     Eden,
     Flashbots,
     CustomNodes,
+    Relayer,
     DryRun,
 }
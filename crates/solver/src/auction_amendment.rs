@@ -0,0 +1,166 @@
+//! Lets orders that were placed shortly after the driver cut an auction join it.
+//!
+//! While the solvers work on an auction the driver keeps polling the order book for a short
+//! window. If a newer auction contains orders the running auction doesn't, they get added to a
+//! copy of the running auction which the solvers solve as well, with the same deadline. Solutions
+//! for both versions of the auction compete with each other, so solvers may but don't have to
+//! incorporate the late orders.
+
+use crate::orderbook::OrderBookApi;
+use model::{
+    auction::{Auction, AuctionId},
+    order::OrderUid,
+};
+use std::{collections::HashSet, time::Duration};
+use tokio::time::Instant;
+
+/// How often the order book gets polled for new orders during the amendment window.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// An auction with the orders that joined it after it was cut.
+#[derive(Debug, PartialEq)]
+pub struct Amendment {
+    pub auction: Auction,
+    pub added_orders: Vec<OrderUid>,
+}
+
+/// Adds the orders of `latest` that `auction` doesn't contain and that aren't in flight. Orders of
+/// tokens without a price in either auction can't be solved and are skipped. Returns `None` if no
+/// order was added.
+pub fn amend(
+    auction: &Auction,
+    latest: &Auction,
+    in_flight: &HashSet<OrderUid>,
+) -> Option<Amendment> {
+    let known = auction
+        .orders
+        .iter()
+        .map(|order| order.metadata.uid)
+        .collect::<HashSet<_>>();
+    let mut amended = auction.clone();
+    let mut added_orders = Vec::new();
+    for order in &latest.orders {
+        if known.contains(&order.metadata.uid) || in_flight.contains(&order.metadata.uid) {
+            continue;
+        }
+        let tokens = [order.data.sell_token, order.data.buy_token];
+        let price = |token| {
+            auction
+                .prices
+                .get(&token)
+                .or_else(|| latest.prices.get(&token))
+                .copied()
+        };
+        let prices = match tokens.map(price) {
+            [Some(sell), Some(buy)] => [sell, buy],
+            _ => continue,
+        };
+        // Prices of tokens the running auction already has must not change because solutions
+        // of both versions get compared with them.
+        for (token, price) in tokens.into_iter().zip(prices) {
+            amended.prices.entry(token).or_insert(price);
+        }
+        amended.orders.push(order.clone());
+        added_orders.push(order.metadata.uid);
+    }
+    if added_orders.is_empty() {
+        return None;
+    }
+    Some(Amendment {
+        auction: amended,
+        added_orders,
+    })
+}
+
+/// Polls the order book until `window` elapsed for orders that joined after the auction with the
+/// id got cut.
+pub async fn wait_for_amendment(
+    api: &OrderBookApi,
+    auction_id: AuctionId,
+    auction: &Auction,
+    in_flight: &HashSet<OrderUid>,
+    window: Duration,
+) -> Option<Amendment> {
+    let end = Instant::now() + window;
+    loop {
+        tokio::time::sleep_until((Instant::now() + POLL_INTERVAL).min(end)).await;
+        match api.get_auction().await {
+            Ok(latest) if latest.id > auction_id => {
+                if let Some(amendment) = amend(auction, &latest.auction, in_flight) {
+                    return Some(amendment);
+                }
+            }
+            Ok(_) => (),
+            Err(err) => tracing::warn!(?err, "failed to poll auction for amendment"),
+        }
+        if Instant::now() >= end {
+            return None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::btreemap;
+    use model::order::{Order, OrderData, OrderMetadata};
+    use primitive_types::H160;
+
+    fn order(uid: u8, sell_token: u8, buy_token: u8) -> Order {
+        Order {
+            metadata: OrderMetadata {
+                uid: OrderUid([uid; 56]),
+                ..Default::default()
+            },
+            data: OrderData {
+                sell_token: H160([sell_token; 20]),
+                buy_token: H160([buy_token; 20]),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn adds_new_priced_orders() {
+        let auction = Auction {
+            orders: vec![order(1, 1, 2)],
+            prices: btreemap! {
+                H160([1; 20]) => 1.into(),
+                H160([2; 20]) => 2.into(),
+            },
+            ..Default::default()
+        };
+        let in_flight = HashSet::from([OrderUid([4; 56])]);
+        assert_eq!(amend(&auction, &auction, &in_flight), None);
+
+        let latest = Auction {
+            orders: vec![
+                order(1, 1, 2),
+                order(2, 2, 3),
+                order(3, 3, 4),
+                order(4, 1, 2),
+            ],
+            prices: btreemap! {
+                H160([1; 20]) => 10.into(),
+                H160([3; 20]) => 3.into(),
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            amend(&auction, &latest, &in_flight),
+            Some(Amendment {
+                auction: Auction {
+                    orders: vec![order(1, 1, 2), order(2, 2, 3)],
+                    prices: btreemap! {
+                        H160([1; 20]) => 1.into(),
+                        H160([2; 20]) => 2.into(),
+                        H160([3; 20]) => 3.into(),
+                    },
+                    ..Default::default()
+                },
+                added_orders: vec![OrderUid([2; 56])],
+            })
+        );
+    }
+}
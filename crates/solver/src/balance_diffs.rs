@@ -0,0 +1,286 @@
+//! Simulates how a settlement changes the token balances of the settlement contract.
+//!
+//! The settlement is traced with `trace_callMany` and the balance changes are derived from the
+//! calls it makes: ERC20 transfers from and to the contract, native token transfers and wrapping or
+//! unwrapping of the wrapped native token. Negative changes are paid from the contract's buffers
+//! which makes them relevant for the contract's inventory.
+
+use crate::{encoding::SettlementContractVersion, settlement::Settlement, settlement_simulation};
+use anyhow::{anyhow, ensure, Context, Result};
+use hex_literal::hex;
+use model::order::BUY_ETH_ADDRESS;
+use num::BigInt;
+use number_conversions::u256_to_big_int;
+use primitive_types::{H160, U256};
+use shared::{trace_many, Web3};
+use std::collections::BTreeMap;
+use web3::types::{Action, Bytes, CallRequest, CallType, TransactionTrace};
+
+const TRANSFER: [u8; 4] = hex!("a9059cbb");
+const TRANSFER_FROM: [u8; 4] = hex!("23b872dd");
+const DEPOSIT: [u8; 4] = hex!("d0e30db0");
+const WITHDRAW: [u8; 4] = hex!("2e1a7d4d");
+
+pub struct BalanceDiffSimulator {
+    /// A node supporting `trace_callMany`.
+    pub web3: Web3,
+    pub settlement_contract: H160,
+    pub contract_version: SettlementContractVersion,
+    pub native_token: H160,
+}
+
+impl BalanceDiffSimulator {
+    /// Returns the simulated balance changes of the settlement contract by token. Changes of the
+    /// native token are reported for `BUY_ETH_ADDRESS`.
+    pub async fn simulate(
+        &self,
+        settlement: Settlement,
+        solver: H160,
+    ) -> Result<BTreeMap<H160, BigInt>> {
+        let request = CallRequest {
+            from: Some(solver),
+            to: Some(self.settlement_contract),
+            data: Some(Bytes(settlement_simulation::call_data(
+                self.contract_version,
+                settlement.into(),
+            ))),
+            ..Default::default()
+        };
+        let traces = trace_many::trace_many(vec![request], &self.web3).await?;
+        let trace = traces
+            .into_iter()
+            .next()
+            .and_then(|trace| trace.trace)
+            .ok_or_else(|| anyhow!("trace not set"))?;
+        balance_diffs(&trace, self.settlement_contract, self.native_token)
+    }
+}
+
+fn balance_diffs(
+    traces: &[TransactionTrace],
+    settlement_contract: H160,
+    native_token: H160,
+) -> Result<BTreeMap<H160, BigInt>> {
+    let first = traces
+        .first()
+        .ok_or_else(|| anyhow!("expected at least one trace"))?;
+    ensure!(first.error.is_none(), "settlement reverted");
+
+    let mut diffs = BTreeMap::<H160, BigInt>::new();
+    let mut add = |token: H160, amount: U256, incoming: bool| {
+        if amount.is_zero() {
+            return;
+        }
+        let amount = u256_to_big_int(&amount);
+        let diff = diffs.entry(token).or_default();
+        if incoming {
+            *diff += amount;
+        } else {
+            *diff -= amount;
+        }
+    };
+
+    // Calls that reverted don't change any balances and neither do the calls they made.
+    let reverted = traces
+        .iter()
+        .filter(|trace| trace.error.is_some())
+        .map(|trace| trace.trace_address.as_slice())
+        .collect::<Vec<_>>();
+    for trace in traces {
+        if reverted
+            .iter()
+            .any(|address| trace.trace_address.starts_with(address))
+        {
+            continue;
+        }
+        let call = match &trace.action {
+            Action::Call(call) if matches!(call.call_type, CallType::Call) => call,
+            _ => continue,
+        };
+
+        if call.from == settlement_contract {
+            add(BUY_ETH_ADDRESS, call.value, false);
+        }
+        if call.to == settlement_contract {
+            add(BUY_ETH_ADDRESS, call.value, true);
+        }
+
+        let input = call.input.0.as_slice();
+        let (selector, params) = match input.len() {
+            0 => (None, input),
+            len if len >= 4 => (Some(&input[..4]), &input[4..]),
+            _ => continue,
+        };
+        let word = |i: usize| {
+            params
+                .get(i * 32..(i + 1) * 32)
+                .context("call data too short")
+        };
+        let address = |i: usize| word(i).map(|word| H160::from_slice(&word[12..]));
+        let amount = |i: usize| word(i).map(U256::from_big_endian);
+
+        match selector {
+            Some(selector) if selector == TRANSFER => {
+                let (to, amount) = (address(0)?, amount(1)?);
+                if call.from == settlement_contract {
+                    add(call.to, amount, false);
+                }
+                if to == settlement_contract {
+                    add(call.to, amount, true);
+                }
+            }
+            Some(selector) if selector == TRANSFER_FROM => {
+                let (from, to, amount) = (address(0)?, address(1)?, amount(2)?);
+                if from == settlement_contract {
+                    add(call.to, amount, false);
+                }
+                if to == settlement_contract {
+                    add(call.to, amount, true);
+                }
+            }
+            // Plain transfers to the wrapped native token also wrap.
+            _ if call.to == native_token
+                && call.from == settlement_contract
+                && selector.map_or(true, |selector| selector == DEPOSIT) =>
+            {
+                add(native_token, call.value, true);
+            }
+            Some(selector)
+                if selector == WITHDRAW
+                    && call.to == native_token
+                    && call.from == settlement_contract =>
+            {
+                add(native_token, amount(0)?, false);
+            }
+            _ => (),
+        }
+    }
+
+    diffs.retain(|_, diff| *diff != BigInt::default());
+    Ok(diffs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::btreemap;
+    use serde_json::json;
+
+    const SETTLEMENT: H160 = H160([0x90; 20]);
+    const WETH: H160 = H160([0xee; 20]);
+    const TOKEN: H160 = H160([0x11; 20]);
+    const USER: H160 = H160([0x22; 20]);
+    const POOL: H160 = H160([0x33; 20]);
+
+    fn word(value: impl Into<U256>) -> [u8; 32] {
+        let mut word = [0; 32];
+        value.into().to_big_endian(&mut word);
+        word
+    }
+
+    fn address_word(address: H160) -> [u8; 32] {
+        let mut word = [0; 32];
+        word[12..].copy_from_slice(address.as_bytes());
+        word
+    }
+
+    fn call(
+        trace_address: &[usize],
+        from: H160,
+        to: H160,
+        value: u64,
+        input: Vec<u8>,
+        error: bool,
+    ) -> TransactionTrace {
+        let mut trace = json!({
+            "traceAddress": trace_address,
+            "subtraces": 0,
+            "action": {
+                "callType": "call",
+                "from": from,
+                "gas": "0x0",
+                "input": Bytes(input),
+                "to": to,
+                "value": U256::from(value),
+            },
+            "type": "call",
+        });
+        if error {
+            trace["error"] = json!("Reverted");
+        }
+        serde_json::from_value(trace).unwrap()
+    }
+
+    fn transfer(to: H160, amount: u64) -> Vec<u8> {
+        [&TRANSFER[..], &address_word(to), &word(amount)].concat()
+    }
+
+    fn transfer_from(from: H160, to: H160, amount: u64) -> Vec<u8> {
+        [
+            &TRANSFER_FROM[..],
+            &address_word(from),
+            &address_word(to),
+            &word(amount),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn computes_settlement_contract_balance_diffs() {
+        let traces = vec![
+            call(&[], USER, SETTLEMENT, 0, vec![], false),
+            // The vault relayer pulls the user's sell tokens into the contract.
+            call(
+                &[0],
+                POOL,
+                TOKEN,
+                0,
+                transfer_from(USER, SETTLEMENT, 100),
+                false,
+            ),
+            // An interaction swaps some of them for WETH.
+            call(&[1], SETTLEMENT, TOKEN, 0, transfer(POOL, 60), false),
+            call(&[2], POOL, WETH, 0, transfer(SETTLEMENT, 30), false),
+            // The contract unwraps WETH and pays the user in ETH.
+            call(
+                &[3],
+                SETTLEMENT,
+                WETH,
+                0,
+                [&WITHDRAW[..], &word(25)].concat(),
+                false,
+            ),
+            call(&[3, 0], WETH, SETTLEMENT, 25, vec![], false),
+            call(&[4], SETTLEMENT, USER, 20, vec![], false),
+            // Wraps the remaining ETH again.
+            call(&[5], SETTLEMENT, WETH, 5, DEPOSIT.to_vec(), false),
+        ];
+        assert_eq!(
+            balance_diffs(&traces, SETTLEMENT, WETH).unwrap(),
+            btreemap! {
+                TOKEN => BigInt::from(40),
+                WETH => BigInt::from(10),
+            }
+        );
+    }
+
+    #[test]
+    fn ignores_reverted_calls() {
+        let traces = vec![
+            call(&[], USER, SETTLEMENT, 0, vec![], false),
+            call(&[0], SETTLEMENT, POOL, 0, vec![], true),
+            call(&[0, 0], SETTLEMENT, TOKEN, 0, transfer(POOL, 60), false),
+            call(&[1], SETTLEMENT, TOKEN, 0, transfer(USER, 10), false),
+        ];
+        assert_eq!(
+            balance_diffs(&traces, SETTLEMENT, WETH).unwrap(),
+            btreemap! { TOKEN => BigInt::from(-10) }
+        );
+    }
+
+    #[test]
+    fn reverted_settlement_is_an_error() {
+        let traces = vec![call(&[], USER, SETTLEMENT, 0, vec![], true)];
+        assert!(balance_diffs(&traces, SETTLEMENT, WETH).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{NaiveDate, TimeZone, Utc};
+use database::byte_array::ByteArray;
+use number_conversions::{big_decimal_to_u256, u256_to_big_decimal};
+use primitive_types::{H160, H256, U256};
+use sqlx::PgPool;
+use std::{collections::HashMap, str::FromStr, sync::Mutex};
+
+/// Limits how much of the settlement contract's balance of a token may be paid out of its
+/// internal buffers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BufferUsageLimit {
+    pub token: H160,
+    /// The maximum amount a single settlement may take from the buffer.
+    pub per_settlement: U256,
+    /// The maximum amount all settlements of a (UTC) day may take from the buffer combined.
+    pub per_day: U256,
+}
+
+impl FromStr for BufferUsageLimit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('|');
+        let token = parts.next().ok_or_else(|| anyhow!("missing token"))?;
+        let per_settlement = parts
+            .next()
+            .ok_or_else(|| anyhow!("missing per settlement limit"))?;
+        let per_day = parts
+            .next()
+            .ok_or_else(|| anyhow!("missing per day limit"))?;
+        anyhow::ensure!(parts.next().is_none(), "extraneous buffer limit parameters");
+        Ok(Self {
+            token: token.parse().context("parse token")?,
+            per_settlement: U256::from_dec_str(per_settlement)
+                .context("parse per settlement limit")?,
+            per_day: U256::from_dec_str(per_day).context("parse per day limit")?,
+        })
+    }
+}
+
+/// Enforces inventory risk controls on internal buffer trading.
+///
+/// Tokens without a configured limit are not restricted.
+///
+/// With a database the usage of executed settlements is persisted and today's usage is the sum
+/// over all recorded settlements, so the daily limits hold across restarts and solver instances.
+/// Without one the usage is only tracked in memory.
+#[derive(Debug, Default)]
+pub struct BufferUsageLimits {
+    limits: HashMap<H160, BufferUsageLimit>,
+    daily_usage: Mutex<DailyUsage>,
+    database: Option<PgPool>,
+}
+
+#[derive(Debug, Default)]
+struct DailyUsage {
+    day: Option<NaiveDate>,
+    usage: HashMap<H160, U256>,
+}
+
+impl DailyUsage {
+    /// Returns the usage of the current day, resetting it if the day changed.
+    fn current(&mut self, today: NaiveDate) -> &mut HashMap<H160, U256> {
+        if self.day != Some(today) {
+            self.day = Some(today);
+            self.usage.clear();
+        }
+        &mut self.usage
+    }
+}
+
+impl BufferUsageLimits {
+    pub fn new(limits: impl IntoIterator<Item = BufferUsageLimit>) -> Self {
+        Self {
+            limits: limits
+                .into_iter()
+                .map(|limit| (limit.token, limit))
+                .collect(),
+            daily_usage: Default::default(),
+            database: None,
+        }
+    }
+
+    pub fn with_database(mut self, database: PgPool) -> Self {
+        self.database = Some(database);
+        self
+    }
+
+    /// Replaces the known usage of today with the usage of all settlements recorded in the
+    /// database today.
+    pub async fn update(&self) -> Result<()> {
+        let database = match &self.database {
+            Some(database) => database,
+            None => return Ok(()),
+        };
+        let today = Utc::now().naive_utc().date();
+        let start = Utc.from_utc_datetime(&today.and_hms_opt(0, 0, 0).unwrap());
+        let end = start + chrono::Duration::days(1);
+        let mut ex = database.acquire().await?;
+        let usage = database::buffer_usage::total_usage(&mut ex, start, end)
+            .await
+            .context("total_usage")?
+            .into_iter()
+            // Sums that don't fit are certainly above any limit.
+            .map(|(token, amount)| {
+                (
+                    H160(token.0),
+                    big_decimal_to_u256(&amount).unwrap_or(U256::MAX),
+                )
+            })
+            .collect();
+
+        let mut daily_usage = self.daily_usage.lock().unwrap();
+        daily_usage.day = Some(today);
+        daily_usage.usage = usage;
+        Ok(())
+    }
+
+    /// Checks that the buffer usage of a settlement is within the per settlement limits and that
+    /// executing it would not exceed today's limits.
+    pub fn is_within_limits(&self, usage: &HashMap<H160, U256>) -> bool {
+        self.is_within_limits_on(Utc::now().naive_utc().date(), usage)
+    }
+
+    /// Records the buffer usage of a settlement that was executed on chain in the transaction.
+    pub async fn record_usage(&self, tx_hash: H256, usage: &HashMap<H160, U256>) {
+        let now = Utc::now();
+        self.record_usage_on(now.naive_utc().date(), usage);
+        let database = match &self.database {
+            Some(database) => database,
+            None => return,
+        };
+        let usage = usage
+            .iter()
+            .map(|(token, amount)| (ByteArray(token.0), u256_to_big_decimal(amount)))
+            .collect::<Vec<_>>();
+        let result = async {
+            let mut ex = database.acquire().await?;
+            database::buffer_usage::record(&mut ex, &ByteArray(tx_hash.0), now, &usage).await
+        };
+        if let Err(err) = result.await {
+            tracing::error!(?err, "failed to record buffer usage");
+        }
+    }
+
+    fn is_within_limits_on(&self, today: NaiveDate, usage: &HashMap<H160, U256>) -> bool {
+        let mut daily_usage = self.daily_usage.lock().unwrap();
+        let daily_usage = daily_usage.current(today);
+        usage.iter().all(|(token, amount)| {
+            let limit = match self.limits.get(token) {
+                Some(limit) => limit,
+                None => return true,
+            };
+            if *amount > limit.per_settlement {
+                tracing::debug!(?token, %amount, "buffer usage exceeds per settlement limit");
+                return false;
+            }
+            let used_today = daily_usage.get(token).copied().unwrap_or_default();
+            match used_today.checked_add(*amount) {
+                Some(total) if total <= limit.per_day => true,
+                _ => {
+                    tracing::debug!(?token, %amount, %used_today, "buffer usage exceeds daily limit");
+                    false
+                }
+            }
+        })
+    }
+
+    fn record_usage_on(&self, today: NaiveDate, usage: &HashMap<H160, U256>) {
+        let mut daily_usage = self.daily_usage.lock().unwrap();
+        let daily_usage = daily_usage.current(today);
+        for (token, amount) in usage {
+            let used = daily_usage.entry(*token).or_default();
+            *used = used.saturating_add(*amount);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashmap;
+
+    #[test]
+    fn parse_buffer_usage_limit() {
+        let limit: BufferUsageLimit = "0x0101010101010101010101010101010101010101|100|1000"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            limit,
+            BufferUsageLimit {
+                token: H160([1; 20]),
+                per_settlement: 100.into(),
+                per_day: 1000.into(),
+            }
+        );
+
+        assert!("0x0101010101010101010101010101010101010101|100"
+            .parse::<BufferUsageLimit>()
+            .is_err());
+        assert!("0x0101010101010101010101010101010101010101|100|1000|1"
+            .parse::<BufferUsageLimit>()
+            .is_err());
+    }
+
+    #[test]
+    fn enforces_per_settlement_and_daily_limits() {
+        let token = H160([1; 20]);
+        let unlimited_token = H160([2; 20]);
+        let limits = BufferUsageLimits::new([BufferUsageLimit {
+            token,
+            per_settlement: 100.into(),
+            per_day: 150.into(),
+        }]);
+        let day = NaiveDate::from_ymd(2022, 8, 1);
+
+        assert!(limits.is_within_limits_on(day, &hashmap! { unlimited_token => U256::MAX }));
+        assert!(limits.is_within_limits_on(day, &hashmap! { token => 100.into() }));
+        assert!(!limits.is_within_limits_on(day, &hashmap! { token => 101.into() }));
+
+        limits.record_usage_on(day, &hashmap! { token => 100.into() });
+        assert!(limits.is_within_limits_on(day, &hashmap! { token => 50.into() }));
+        assert!(!limits.is_within_limits_on(day, &hashmap! { token => 51.into() }));
+
+        // Usage resets on the next day.
+        let next_day = day.succ();
+        assert!(limits.is_within_limits_on(next_day, &hashmap! { token => 100.into() }));
+    }
+}
@@ -1,40 +1,54 @@
 pub mod solver_settlements;
 
 use crate::{
+    auction_amendment::{self, Amendment},
     auction_preprocessing,
+    balance_diffs::BalanceDiffSimulator,
+    buffer_usage_limits::BufferUsageLimits,
+    driver::solver_settlements::RatedSettlement,
     driver_logger::DriverLogger,
+    execution_guard,
     in_flight_orders::InFlightOrders,
     liquidity::order_converter::OrderConverter,
     liquidity_collector::{LiquidityCollecting, LiquidityCollector},
     metrics::SolverMetrics,
     orderbook::OrderBookApi,
-    settlement::{external_prices::ExternalPrices, PriceCheckTokens, Settlement},
+    settlement::{calldata, external_prices::ExternalPrices, PriceCheckTokens, Settlement},
     settlement_post_processing::PostProcessingPipeline,
     settlement_ranker::SettlementRanker,
-    settlement_rater::SettlementRater,
-    settlement_simulation::{self, TenderlyApi},
+    settlement_rater::{RatedSolverSettlement, SettlementRater},
+    settlement_simulation::{self, TransactionSimulating},
     settlement_submission::{SolutionSubmitter, SubmissionError},
-    solver::{Auction, Solver, SolverRunError, Solvers},
+    solver::{Auction, SettlementWithError, Solver, SolverRunError, Solvers},
+    solver_balances::SolverBalances,
+    solver_demotions::SolverDemotions,
 };
 use anyhow::{Context, Result};
+use chrono::Utc;
 use contracts::GPv2Settlement;
 use futures::future::join_all;
-use gas_estimation::GasPriceEstimating;
+use gas_estimation::{GasPrice1559, GasPriceEstimating};
 use model::{
-    auction::AuctionWithId,
+    auction::{AuctionId, AuctionWithId},
+    order::OrderUid,
     solver_competition::{
-        self, CompetitionAuction, Objective, SolverCompetition, SolverSettlement,
+        self, AuctionAmendment, CompetitionAuction, CompetitionPerformance, ExcludedSolver,
+        ExclusionReason, Objective, SimulationFailure, SolverCompetition, SolverSettlement,
+        SubAuctionSolution,
     },
 };
 use num::{rational::Ratio, BigInt, BigRational, ToPrimitive};
 use primitive_types::{H160, U256};
 use shared::{
     current_block::{self, CurrentBlockStream},
+    realized_gas::RealizedGas,
     recent_block_cache::Block,
-    token_list::TokenList,
+    revm_simulation::RevmSimulator,
+    token_list::TokenLists,
     Web3,
 };
 use std::{
+    collections::{BTreeMap, HashMap},
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -42,7 +56,7 @@ use tracing::Instrument as _;
 use web3::types::TransactionReceipt;
 
 pub struct Driver {
-    liquidity_collector: LiquidityCollector,
+    liquidity_collector: Arc<LiquidityCollector>,
     solvers: Solvers,
     gas_price_estimator: Arc<dyn GasPriceEstimating>,
     settle_interval: Duration,
@@ -59,12 +73,36 @@ pub struct Driver {
     fee_objective_scaling_factor: BigRational,
     settlement_ranker: SettlementRanker,
     logger: DriverLogger,
+    buffer_usage_limits: Arc<BufferUsageLimits>,
+    solver_balances: Option<Arc<SolverBalances>>,
+    realized_gas: Option<Arc<RealizedGas>>,
+    auction_amendment_window: Option<Duration>,
+    balance_diff_simulator: Option<BalanceDiffSimulator>,
+    solver_demotions: SolverDemotions,
 }
+
+/// The version of an auction's content after the orders that arrived late were added.
+const AMENDED_AUCTION_VERSION: u32 = 1;
+
+/// The ranked settlements, the settlements that failed to simulate and the performance of solving
+/// an auction.
+type SolvedAuction = (
+    Vec<RatedSolverSettlement>,
+    Vec<SettlementWithError>,
+    CompetitionPerformance,
+);
+
+/// A settlement merged from the winning settlements of the sub-auctions of a clustered auction.
+struct MergedSettlement {
+    call_data: Vec<u8>,
+    sub_auctions: Vec<SubAuctionSolution>,
+}
+
 impl Driver {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         settlement_contract: GPv2Settlement,
-        liquidity_collector: LiquidityCollector,
+        liquidity_collector: Arc<LiquidityCollector>,
         solvers: Solvers,
         gas_price_estimator: Arc<dyn GasPriceEstimating>,
         settle_interval: Duration,
@@ -74,7 +112,7 @@ impl Driver {
         web3: Web3,
         network_id: String,
         solver_time_limit: Duration,
-        market_makable_token_list: Option<TokenList>,
+        market_makable_token_list: Option<Arc<TokenLists>>,
         block_stream: CurrentBlockStream,
         solution_submitter: SolutionSubmitter,
         api: OrderBookApi,
@@ -84,20 +122,28 @@ impl Driver {
         fee_objective_scaling_factor: f64,
         max_settlement_price_deviation: Option<Ratio<BigInt>>,
         token_list_restriction_for_price_checks: PriceCheckTokens,
-        tenderly: Option<TenderlyApi>,
+        simulator: Option<Arc<dyn TransactionSimulating>>,
+        revm_simulator: Option<Arc<RevmSimulator>>,
+        buffer_usage_limits: Arc<BufferUsageLimits>,
+        solver_balances: Option<Arc<SolverBalances>>,
     ) -> Self {
         let post_processing_pipeline = PostProcessingPipeline::new(
             native_token,
             web3.clone(),
             weth_unwrap_factor,
             settlement_contract.clone(),
+            solution_submitter.contract_version,
             market_makable_token_list,
+            buffer_usage_limits.clone(),
+            revm_simulator.clone(),
         );
 
         let settlement_rater = Arc::new(SettlementRater {
             access_list_estimator: solution_submitter.access_list_estimator.clone(),
             settlement_contract: settlement_contract.clone(),
+            settlement_contract_version: solution_submitter.contract_version,
             web3: web3.clone(),
+            revm_simulator,
         });
 
         let settlement_ranker = SettlementRanker {
@@ -106,14 +152,17 @@ impl Driver {
             metrics: metrics.clone(),
             min_order_age,
             settlement_rater,
+            buffer_usage_limits: buffer_usage_limits.clone(),
+            internal_balance_vault: None,
         };
 
         let logger = DriverLogger {
             metrics: metrics.clone(),
             web3,
-            tenderly,
+            simulator,
             network_id,
             settlement_contract,
+            settlement_contract_version: solution_submitter.contract_version,
             simulation_gas_limit,
         };
 
@@ -123,7 +172,7 @@ impl Driver {
             gas_price_estimator,
             settle_interval,
             native_token,
-            metrics,
+            metrics: metrics.clone(),
             solver_time_limit,
             block_stream,
             solution_submitter,
@@ -136,9 +185,49 @@ impl Driver {
                 .unwrap(),
             settlement_ranker,
             logger,
+            buffer_usage_limits,
+            solver_balances,
+            realized_gas: None,
+            auction_amendment_window: None,
+            balance_diff_simulator: None,
+            solver_demotions: SolverDemotions::new(0, Duration::ZERO, metrics),
         }
     }
 
+    /// Records the gas of mined settlements so that solvers sharing the table can estimate the gas
+    /// of interactions more accurately.
+    pub fn with_realized_gas(mut self, realized_gas: Arc<RealizedGas>) -> Self {
+        self.realized_gas = Some(realized_gas);
+        self
+    }
+
+    /// Lets orders that arrive within `window` after an auction was cut join it as an amendment
+    /// that solvers solve in addition to the original auction.
+    pub fn with_auction_amendment_window(mut self, window: Duration) -> Self {
+        self.auction_amendment_window = Some(window);
+        self
+    }
+
+    /// Lets settlements pay out orders to internal balances of the Vault with this address.
+    pub fn with_internal_balance_vault(mut self, vault: H160) -> Self {
+        self.settlement_ranker.internal_balance_vault = Some(vault);
+        self
+    }
+
+    /// Reports the simulated balance changes of the settlement contract for every solution of the
+    /// solver competition.
+    pub fn with_balance_diff_simulator(mut self, simulator: BalanceDiffSimulator) -> Self {
+        self.balance_diff_simulator = Some(simulator);
+        self
+    }
+
+    /// Demotes solvers for `period` after `threshold` consecutive responses that violate the
+    /// solution schema.
+    pub fn with_solver_demotions(mut self, threshold: usize, period: Duration) -> Self {
+        self.solver_demotions = SolverDemotions::new(threshold, period, self.metrics.clone());
+        self
+    }
+
     pub async fn run_forever(&mut self) -> ! {
         loop {
             match self.single_run().await {
@@ -150,12 +239,53 @@ impl Driver {
         }
     }
 
+    /// Splits the solvers into the ones that participate in an auction with the gas price and the
+    /// ones that don't because their account can't pay for a settlement or they are demoted.
+    fn participating_solvers(&self, gas_price: f64) -> (Solvers, Vec<ExcludedSolver>) {
+        let mut participating = Vec::new();
+        let mut excluded = Vec::new();
+        let now = Utc::now();
+        for solver in &self.solvers {
+            let reason = self
+                .solver_balances
+                .as_ref()
+                .and_then(|balances| {
+                    balances.exclusion_reason(solver.account().address(), gas_price)
+                })
+                .or_else(|| self.solver_demotions.exclusion_reason(solver.name(), now));
+            match reason {
+                Some(reason) => {
+                    tracing::warn!(
+                        solver = solver.name(),
+                        ?reason,
+                        "solver does not participate in the auction"
+                    );
+                    match reason {
+                        ExclusionReason::InsufficientBalance { .. } => self
+                            .metrics
+                            .solver_excluded_for_insufficient_balance(solver.name()),
+                        ExclusionReason::SchemaViolations { .. } => self
+                            .metrics
+                            .solver_excluded_for_schema_violations(solver.name()),
+                    }
+                    excluded.push(ExcludedSolver {
+                        solver: solver.name().to_string(),
+                        reason,
+                    });
+                }
+                None => participating.push(solver.clone()),
+            }
+        }
+        (participating, excluded)
+    }
+
     // Returns solver name and result.
     async fn run_solvers(
         &self,
+        solvers: &[Arc<dyn Solver>],
         auction: Auction,
     ) -> Vec<(Arc<dyn Solver>, Result<Vec<Settlement>, SolverRunError>)> {
-        join_all(self.solvers.iter().map(|solver| {
+        join_all(solvers.iter().map(|solver| {
             let auction = auction.clone();
             let metrics = &self.metrics;
             async move {
@@ -174,7 +304,212 @@ impl Driver {
         .await
     }
 
+    /// Runs the solvers on the auction and ranks their settlements. Also reports how many solvers
+    /// timed out and how long simulating the settlements took.
+    async fn solve_and_rank(
+        &self,
+        solvers: &[Arc<dyn Solver>],
+        auction: Auction,
+        external_prices: &ExternalPrices,
+        gas_price: GasPrice1559,
+    ) -> Result<(
+        Vec<RatedSolverSettlement>,
+        Vec<SettlementWithError>,
+        CompetitionPerformance,
+    )> {
+        let auction_id = auction.id;
+        let run_solver_results = self.run_solvers(solvers, auction).await;
+        let now = Utc::now();
+        for (solver, result) in &run_solver_results {
+            match result {
+                Ok(_) => self.solver_demotions.record(solver.name(), Ok(()), now),
+                Err(SolverRunError::Solving(err)) => {
+                    self.solver_demotions.record(solver.name(), Err(err), now)
+                }
+                Err(SolverRunError::Timeout) => (),
+            }
+        }
+        let solver_runs = run_solver_results.len() as u64;
+        let timed_out_runs = run_solver_results
+            .iter()
+            .filter(|(_, result)| matches!(result, Err(SolverRunError::Timeout)))
+            .count() as u64;
+        let simulation_start = Instant::now();
+        let (rated_settlements, errors) = self
+            .settlement_ranker
+            .rank_legal_settlements(auction_id, run_solver_results, external_prices, gas_price)
+            .await?;
+        let performance = CompetitionPerformance {
+            solver_runs,
+            timed_out_runs,
+            simulation_seconds: simulation_start.elapsed().as_secs_f64(),
+        };
+        Ok((rated_settlements, errors, performance))
+    }
+
+    /// Solves an auction that autopilot split into independent clusters of orders. The clusters
+    /// get solved in parallel as sub-auctions that share the auction's liquidity. The best
+    /// settlements of all sub-auctions get merged into a single settlement that competes with the
+    /// individual ones.
+    async fn solve_clustered_auction(
+        &self,
+        solvers: &[Arc<dyn Solver>],
+        auction: Auction,
+        clusters: &[Vec<OrderUid>],
+        external_prices: &ExternalPrices,
+        gas_price: GasPrice1559,
+    ) -> Result<(SolvedAuction, Option<MergedSettlement>)> {
+        let auction_id = auction.id;
+        let sub_auctions = split_auction(auction, clusters);
+        tracing::info!("solving {} independent sub-auctions", sub_auctions.len());
+        let results = join_all(
+            sub_auctions
+                .into_iter()
+                .map(|auction| self.solve_and_rank(solvers, auction, external_prices, gas_price)),
+        )
+        .await;
+
+        let mut rated_settlements = Vec::new();
+        let mut errors = Vec::new();
+        let mut winners = Vec::new();
+        let mut performance = CompetitionPerformance::default();
+        for result in results {
+            let (mut sub_auction_settlements, sub_auction_errors, sub_auction_performance) =
+                result?;
+            // Sub-auctions get simulated in parallel so the slowest one determines how long
+            // simulation took.
+            performance.solver_runs += sub_auction_performance.solver_runs;
+            performance.timed_out_runs += sub_auction_performance.timed_out_runs;
+            performance.simulation_seconds = performance
+                .simulation_seconds
+                .max(sub_auction_performance.simulation_seconds);
+            errors.extend(sub_auction_errors);
+            winners.extend(sub_auction_settlements.pop());
+            rated_settlements.extend(sub_auction_settlements);
+        }
+
+        // Merge starting with the most valuable settlement so that the solver that contributed
+        // the most submits it. The merged settlement gets simulated again because the
+        // sub-auctions share liquidity and might interfere with each other.
+        winners.sort_by(|a, b| b.1.objective_value().cmp(&a.1.objective_value()));
+        let mut merged_settlement = None;
+        if winners.len() > 1 {
+            let (solver, best, _) = &winners[0];
+            let mut merged = best.settlement.clone();
+            let mut contributors = vec![&winners[0]];
+            for winner in &winners[1..] {
+                match merged.clone().merge(winner.1.settlement.clone()) {
+                    Ok(settlement) => {
+                        merged = settlement;
+                        contributors.push(winner);
+                    }
+                    Err(err) => tracing::debug!(?err, "failed to merge sub-auction settlements"),
+                }
+            }
+            let sub_auctions = contributors
+                .into_iter()
+                .map(|(solver, rated_settlement, _)| SubAuctionSolution {
+                    solver: solver.name().to_string(),
+                    objective: rated_settlement
+                        .objective_value()
+                        .to_f64()
+                        .unwrap_or(f64::NAN),
+                    orders: rated_settlement
+                        .settlement
+                        .executed_trades()
+                        .map(|(trade, _)| trade.order.metadata.uid)
+                        .collect(),
+                })
+                .collect();
+            let simulation_start = Instant::now();
+            let (merged, merge_errors) = self
+                .settlement_ranker
+                .rank_legal_settlements(
+                    auction_id,
+                    vec![(solver.clone(), Ok(vec![merged]))],
+                    external_prices,
+                    gas_price,
+                )
+                .await?;
+            performance.simulation_seconds += simulation_start.elapsed().as_secs_f64();
+            merged_settlement = merged
+                .first()
+                .map(|(_, rated_settlement, _)| MergedSettlement {
+                    call_data: settlement_simulation::call_data(
+                        self.solution_submitter.contract_version,
+                        rated_settlement.settlement.clone().into(),
+                    ),
+                    sub_auctions,
+                });
+            rated_settlements.extend(merged);
+            errors.extend(merge_errors);
+        }
+
+        rated_settlements.extend(winners);
+        rated_settlements.sort_by(|a, b| a.1.objective_value().cmp(&b.1.objective_value()));
+        Ok(((rated_settlements, errors, performance), merged_settlement))
+    }
+
+    /// Solves the amended version of an auction with the deadline of the original version.
+    async fn solve_amendment(
+        &self,
+        solvers: &[Arc<dyn Solver>],
+        amendment: &Amendment,
+        auction_id: AuctionId,
+        run_id: u64,
+        deadline: Instant,
+        gas_price: GasPrice1559,
+    ) -> Result<(
+        Vec<RatedSolverSettlement>,
+        Vec<SettlementWithError>,
+        CompetitionPerformance,
+    )> {
+        tracing::info!(orders = ?amendment.added_orders, "solving amended auction");
+        let orders = amendment
+            .auction
+            .orders
+            .iter()
+            .cloned()
+            .filter_map(
+                |order| match self.order_converter.normalize_limit_order(order) {
+                    Ok(order) => Some(order),
+                    Err(err) => {
+                        tracing::error!(?err, "error normalizing limit order");
+                        None
+                    }
+                },
+            )
+            .collect::<Vec<_>>();
+        let external_prices = ExternalPrices::try_from_auction_prices(
+            self.native_token,
+            amendment.auction.prices.clone(),
+        )
+        .context("malformed amended auction prices")?;
+        let block = current_block::block_number(&self.block_stream.borrow())?;
+        let liquidity = self
+            .liquidity_collector
+            .get_liquidity_for_orders(&orders, Block::Number(block))
+            .await?;
+        let auction = Auction {
+            id: auction_id,
+            run: run_id,
+            orders,
+            liquidity,
+            liquidity_fetch_block: block,
+            gas_price: gas_price.effective_gas_price(),
+            deadline,
+            external_prices: external_prices.clone(),
+        };
+        self.solve_and_rank(solvers, auction, &external_prices, gas_price)
+            .await
+    }
+
     pub async fn single_run(&mut self) -> Result<()> {
+        // Other solver instances might have used buffers since the last run.
+        if let Err(err) = self.buffer_usage_limits.update().await {
+            tracing::warn!(?err, "failed to update buffer usage");
+        }
+
         let auction = self
             .api
             .get_auction()
@@ -215,14 +550,20 @@ impl Driver {
         }
 
         let auction_start_block = auction.block;
-        let competition_auction = CompetitionAuction {
+        let mut competition_auction = CompetitionAuction {
             orders: auction
                 .orders
                 .iter()
                 .map(|order| order.metadata.uid)
                 .collect(),
             prices: auction.prices.clone(),
+            amendments: Vec::new(),
         };
+        let amendment_base = self
+            .auction_amendment_window
+            .map(|window| (auction.clone(), inflight_order_uids.clone(), window));
+        let clusters = std::mem::take(&mut auction.clusters);
+        let order_selection_seed = auction.order_selection_seed;
 
         let orders = auction
             .orders
@@ -265,6 +606,9 @@ impl Driver {
             .context("failed to estimate gas price")?;
         tracing::debug!("solving with gas price of {:?}", gas_price);
 
+        let (solvers, excluded_solvers) =
+            self.participating_solvers(gas_price.effective_gas_price());
+
         let auction = Auction {
             id: auction_id,
             run: run_id,
@@ -277,11 +621,78 @@ impl Driver {
         };
 
         tracing::debug!(deadline =? auction.deadline, "solving auction");
-        let run_solver_results = self.run_solvers(auction).await;
-        let (mut rated_settlements, errors) = self
-            .settlement_ranker
-            .rank_legal_settlements(run_solver_results, &external_prices, gas_price)
+        let deadline = auction.deadline;
+        let solve = async {
+            if clusters.is_empty() {
+                let solved = self
+                    .solve_and_rank(&solvers, auction, &external_prices, gas_price)
+                    .await?;
+                Ok((solved, None))
+            } else {
+                self.solve_clustered_auction(
+                    &solvers,
+                    auction,
+                    &clusters,
+                    &external_prices,
+                    gas_price,
+                )
+                .await
+            }
+        };
+        let amend = async {
+            // Amending auctions that had to be split would grow them further beyond the maximum
+            // size.
+            if !clusters.is_empty() {
+                return None;
+            }
+            let (base, in_flight, window) = amendment_base?;
+            let amendment = auction_amendment::wait_for_amendment(
+                &self.api, auction_id, &base, &in_flight, window,
+            )
             .await?;
+            let result = self
+                .solve_amendment(
+                    &solvers, &amendment, auction_id, run_id, deadline, gas_price,
+                )
+                .await;
+            Some((amendment, result))
+        };
+        let (result, amendment) = futures::join!(solve, amend);
+        let ((rated_settlements, mut errors, mut performance), merged_settlement) = result?;
+
+        let mut rated_settlements = rated_settlements
+            .into_iter()
+            .map(|settlement| (settlement, 0))
+            .collect::<Vec<_>>();
+        if let Some((amendment, result)) = amendment {
+            match result {
+                Ok((amended_settlements, amended_errors, amended_performance)) => {
+                    rated_settlements.extend(
+                        amended_settlements
+                            .into_iter()
+                            .map(|settlement| (settlement, AMENDED_AUCTION_VERSION)),
+                    );
+                    rated_settlements
+                        .sort_by(|a, b| a.0 .1.objective_value().cmp(&b.0 .1.objective_value()));
+                    errors.extend(amended_errors);
+                    performance.solver_runs += amended_performance.solver_runs;
+                    performance.timed_out_runs += amended_performance.timed_out_runs;
+                    performance.simulation_seconds = performance
+                        .simulation_seconds
+                        .max(amended_performance.simulation_seconds);
+                    for (token, price) in amendment.auction.prices {
+                        competition_auction.prices.entry(token).or_insert(price);
+                    }
+                    competition_auction.amendments.push(AuctionAmendment {
+                        version: AMENDED_AUCTION_VERSION,
+                        orders: amendment.added_orders,
+                    });
+                }
+                Err(err) => tracing::warn!(?err, "failed to solve amended auction"),
+            }
+        }
+        let (mut rated_settlements, auction_versions): (Vec<_>, Vec<_>) =
+            rated_settlements.into_iter().unzip();
 
         // We don't know the exact block because simulation can happen over multiple blocks but
         // this is a good approximation.
@@ -303,46 +714,81 @@ impl Driver {
             competition_simulation_block: block_during_simulation,
             transaction_hash: None,
             auction: competition_auction,
+            order_selection_seed,
             solutions: rated_settlements
                 .iter()
-                .map(|(solver, rated_settlement, _)| SolverSettlement {
-                    solver: solver.name().to_string(),
-                    objective: Objective {
-                        total: rated_settlement
-                            .objective_value()
-                            .to_f64()
-                            .unwrap_or(f64::NAN),
-                        surplus: rated_settlement.surplus.to_f64().unwrap_or(f64::NAN),
-                        fees: rated_settlement
-                            .unscaled_subsidized_fee
-                            .to_f64()
-                            .unwrap_or(f64::NAN),
-                        cost: rated_settlement.gas_estimate.to_f64_lossy()
-                            * rated_settlement.gas_price.to_f64().unwrap_or(f64::NAN),
-                        gas: rated_settlement.gas_estimate.low_u64(),
+                .zip(auction_versions)
+                .map(
+                    |((solver, rated_settlement, _), auction_version)| SolverSettlement {
+                        solver: solver.name().to_string(),
+                        objective: Objective {
+                            total: rated_settlement
+                                .objective_value()
+                                .to_f64()
+                                .unwrap_or(f64::NAN),
+                            surplus: rated_settlement.surplus.to_f64().unwrap_or(f64::NAN),
+                            fees: rated_settlement
+                                .unscaled_subsidized_fee
+                                .to_f64()
+                                .unwrap_or(f64::NAN),
+                            cost: rated_settlement.effective_gas().to_f64_lossy()
+                                * rated_settlement.gas_price.to_f64().unwrap_or(f64::NAN),
+                            gas: rated_settlement.effective_gas().low_u64(),
+                        },
+                        clearing_prices: rated_settlement
+                            .settlement
+                            .clearing_prices()
+                            .iter()
+                            .map(|(address, price)| (*address, *price))
+                            .collect(),
+                        orders: rated_settlement
+                            .settlement
+                            .executed_trades()
+                            .map(|(trade, _)| solver_competition::Order {
+                                id: trade.order.metadata.uid,
+                                executed_amount: trade.executed_amount,
+                            })
+                            .collect(),
+                        call_data: settlement_simulation::call_data(
+                            self.solution_submitter.contract_version,
+                            rated_settlement.settlement.clone().into(),
+                        ),
+                        auction_version,
+                        balance_diffs: Default::default(),
+                        sub_auctions: Default::default(),
                     },
-                    clearing_prices: rated_settlement
-                        .settlement
-                        .clearing_prices()
-                        .iter()
-                        .map(|(address, price)| (*address, *price))
-                        .collect(),
-                    orders: rated_settlement
-                        .settlement
-                        .executed_trades()
-                        .map(|(trade, _)| solver_competition::Order {
-                            id: trade.order.metadata.uid,
-                            executed_amount: trade.executed_amount,
-                        })
-                        .collect(),
-                    call_data: settlement_simulation::call_data(
-                        rated_settlement.settlement.clone().into(),
-                    ),
+                )
+                .collect(),
+            mev_rebate: None,
+            excluded_solvers,
+            performance: Some(performance),
+            simulation_failures: errors
+                .iter()
+                .map(|(solver, _, _, error)| SimulationFailure {
+                    solver: solver.name().to_string(),
+                    error: error.to_string(),
                 })
                 .collect(),
+            revert: None,
         };
+        let balance_diffs = self.simulate_balance_diffs(&rated_settlements).await;
+        for (solution, balance_diffs) in solver_competition.solutions.iter_mut().zip(balance_diffs)
+        {
+            solution.balance_diffs = balance_diffs;
+        }
+        if let Some(merged) = merged_settlement {
+            if let Some(solution) = solver_competition
+                .solutions
+                .iter_mut()
+                .find(|solution| solution.call_data == merged.call_data)
+            {
+                solution.sub_auctions = merged.sub_auctions;
+            }
+        }
 
-        if let Some((winning_solver, mut winning_settlement, _)) = rated_settlements.pop() {
+        if let Some((winning_solver, mut winning_settlement, access_list)) = rated_settlements.pop()
+        {
+            let ranked_settlement = winning_settlement.clone();
             winning_settlement.settlement = self
                 .post_processing_pipeline
                 .optimize_settlement(
@@ -359,26 +805,46 @@ impl Driver {
                 winning_settlement
             );
 
+            // Compares against the settlement as it was ranked so that the drift only reflects
+            // changes of the chain state and not the post processing.
+            self.logger.report_simulation_drift(
+                &winning_solver,
+                &ranked_settlement,
+                access_list,
+                block_during_simulation,
+                gas_price,
+            );
             self.metrics
                 .complete_runloop_until_transaction(start.elapsed());
-            match submit_settlement(
-                &self.solution_submitter,
-                &self.logger,
-                winning_solver.clone(),
-                winning_settlement.settlement.clone(),
-                winning_settlement.gas_estimate,
-                Some(winning_settlement.id as u64),
+            let executed_orders = match execution_guard::already_executed_orders(
+                &self.solution_submitter.contract,
+                &winning_settlement.settlement,
             )
             .await
             {
-                Ok(receipt) => {
-                    self.update_in_flight_orders(&receipt, &winning_settlement.settlement);
-                    solver_competition.transaction_hash = Some(receipt.transaction_hash);
+                Ok(executed_orders) => executed_orders,
+                Err(err) => {
+                    tracing::warn!(?err, "failed to check for already executed orders");
+                    Vec::new()
                 }
-                Err(SubmissionError::Revert(hash)) => {
-                    solver_competition.transaction_hash = Some(hash);
-                }
-                _ => (),
+            };
+            if !executed_orders.is_empty() {
+                // The orders get excluded from the next auctions until the orderbook has seen
+                // their executions, so the next run solves without them.
+                tracing::warn!(
+                    ?executed_orders,
+                    "not submitting settlement with already executed orders"
+                );
+                let block = current_block::block_number(&self.block_stream.borrow())?;
+                self.in_flight_orders
+                    .mark_executed_orders(block, executed_orders);
+            } else {
+                self.submit_winning_settlement(
+                    &winning_solver,
+                    &winning_settlement,
+                    &mut solver_competition,
+                )
+                .await;
             }
 
             self.logger.report_on_batch(
@@ -388,10 +854,13 @@ impl Driver {
                     .map(|(solver, settlement, _)| (solver, settlement))
                     .collect(),
             );
-            self.send_solver_competition(solver_competition).await;
         }
+        // Competitions without a winner get stored too so that auctions in which every solver
+        // timed out count towards the auction size.
+        self.send_solver_competition(solver_competition).await;
         // Happens after settlement submission so that we do not delay it.
         self.logger.report_simulation_errors(
+            auction_id,
             errors,
             current_block_during_liquidity_fetch,
             gas_price,
@@ -399,6 +868,45 @@ impl Driver {
         Ok(())
     }
 
+    /// Submits the winning settlement and records its outcome in the solver competition.
+    async fn submit_winning_settlement(
+        &mut self,
+        winning_solver: &Arc<dyn Solver>,
+        winning_settlement: &RatedSettlement,
+        solver_competition: &mut SolverCompetition,
+    ) {
+        match submit_settlement(
+            &self.solution_submitter,
+            &self.logger,
+            winning_solver.clone(),
+            winning_settlement.settlement.clone(),
+            winning_settlement.gas_estimate,
+            Some(winning_settlement.id as u64),
+        )
+        .await
+        {
+            Ok(receipt) => {
+                self.buffer_usage_limits
+                    .record_usage(
+                        receipt.transaction_hash,
+                        winning_settlement
+                            .settlement
+                            .encoder
+                            .internal_buffer_usage(),
+                    )
+                    .await;
+                self.update_in_flight_orders(&receipt, &winning_settlement.settlement);
+                self.record_realized_gas(&receipt, &winning_settlement.settlement);
+                solver_competition.transaction_hash = Some(receipt.transaction_hash);
+            }
+            Err(SubmissionError::Revert(hash)) => {
+                solver_competition.transaction_hash = Some(hash);
+                solver_competition.revert = Some(self.logger.settlement_revert(hash).await);
+            }
+            _ => (),
+        }
+    }
+
     /// Marks all orders in the winning settlement as "in flight".
     fn update_in_flight_orders(&mut self, receipt: &TransactionReceipt, settlement: &Settlement) {
         let block = match receipt.block_number {
@@ -411,6 +919,50 @@ impl Driver {
         self.in_flight_orders.mark_settled_orders(block, settlement);
     }
 
+    fn record_realized_gas(&self, receipt: &TransactionReceipt, settlement: &Settlement) {
+        let (realized_gas, gas_used) = match (&self.realized_gas, receipt.gas_used) {
+            (Some(realized_gas), Some(gas_used)) => (realized_gas, gas_used),
+            _ => return,
+        };
+        match settlement.encoder.settlement_gas(gas_used.low_u64()) {
+            Some(settlement_gas) => realized_gas.record(&settlement_gas),
+            None => tracing::debug!("settlement contains interactions whose gas isn't tracked"),
+        }
+    }
+
+    /// The simulated balance changes of the settlement contract for every settlement. Nothing if
+    /// no simulator is configured and an empty map for settlements whose simulation failed.
+    async fn simulate_balance_diffs(
+        &self,
+        settlements: &[RatedSolverSettlement],
+    ) -> Vec<BTreeMap<H160, BigInt>> {
+        let simulator = match &self.balance_diff_simulator {
+            Some(simulator) => simulator,
+            None => return Vec::new(),
+        };
+        join_all(
+            settlements
+                .iter()
+                .map(|(solver, rated_settlement, _)| async move {
+                    simulator
+                        .simulate(
+                            rated_settlement.settlement.clone(),
+                            solver.account().address(),
+                        )
+                        .await
+                        .unwrap_or_else(|err| {
+                            tracing::debug!(
+                                solver = %solver.name(),
+                                ?err,
+                                "failed to simulate balance diffs"
+                            );
+                            Default::default()
+                        })
+                }),
+        )
+        .await
+    }
+
     fn next_run_id(&mut self) -> u64 {
         let id = self.run_id;
         self.run_id += 1;
@@ -434,6 +986,11 @@ pub async fn submit_settlement(
     gas_estimate: U256,
     settlement_id: Option<u64>,
 ) -> Result<TransactionReceipt, SubmissionError> {
+    let calldata_savings = calldata::savings(&settlement);
+    tracing::debug!(calldata_savings, "optimized settlement calldata");
+    logger
+        .metrics
+        .settlement_calldata_saved_bytes(calldata_savings);
     let start = Instant::now();
     let result = solution_submitter
         .settle(settlement.clone(), gas_estimate, solver.account().clone())
@@ -444,3 +1001,64 @@ pub async fn submit_settlement(
         .await;
     result
 }
+
+/// Splits the auction into one sub-auction per cluster of orders. All sub-auctions share the
+/// auction's liquidity. Orders that are not part of a cluster don't get solved.
+fn split_auction(mut auction: Auction, clusters: &[Vec<OrderUid>]) -> Vec<Auction> {
+    let mut orders = std::mem::take(&mut auction.orders)
+        .into_iter()
+        .map(|order| (order.id.clone(), order))
+        .collect::<HashMap<_, _>>();
+    clusters
+        .iter()
+        .map(|cluster| Auction {
+            orders: cluster
+                .iter()
+                .filter_map(|uid| orders.remove(&uid.to_string()))
+                .collect(),
+            ..auction.clone()
+        })
+        .filter(|sub_auction| !sub_auction.orders.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::liquidity::LimitOrder;
+
+    #[test]
+    fn splits_auction_into_clusters() {
+        let uid = |byte| OrderUid([byte; 56]);
+        let order = |byte| LimitOrder {
+            id: uid(byte).to_string(),
+            ..Default::default()
+        };
+        let auction = Auction {
+            orders: vec![order(1), order(2), order(3), order(4)],
+            ..Default::default()
+        };
+        // Order 4 is not part of any cluster because it was in flight when autopilot clustered
+        // the auction and order 5 is no longer part of the auction.
+        let clusters = [vec![uid(1), uid(3)], vec![uid(2)], vec![uid(5)]];
+
+        let sub_auctions = split_auction(auction, &clusters);
+        let ids = sub_auctions
+            .iter()
+            .map(|auction| {
+                auction
+                    .orders
+                    .iter()
+                    .map(|order| order.id.clone())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            [
+                vec![uid(1).to_string(), uid(3).to_string()],
+                vec![uid(2).to_string()]
+            ]
+        );
+    }
+}
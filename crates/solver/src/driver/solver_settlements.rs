@@ -1,5 +1,5 @@
 use crate::{
-    settlement::{external_prices::ExternalPrices, Settlement},
+    settlement::{external_prices::ExternalPrices, gas_refunds, Settlement},
     solver::Solver,
 };
 use ethcontract::U256;
@@ -20,7 +20,9 @@ pub struct RatedSettlement {
     pub surplus: BigRational,                 // In wei.
     pub unscaled_subsidized_fee: BigRational, // In wei.
     pub scaled_unsubsidized_fee: BigRational, // In wei.
+    pub flash_loan_fee: BigRational,          // In wei.
     pub gas_estimate: U256,                   // In gas units.
+    pub gas_refund: U256,                     // In gas units.
     pub gas_price: BigRational,               // In wei per gas unit.
 }
 
@@ -29,20 +31,28 @@ pub struct RatedSettlement {
 fn compute_objective_value(
     surplus: &BigRational,
     solver_fees: &BigRational,
+    flash_loan_fees: &BigRational,
     gas_estimate: &BigRational,
     gas_price: &BigRational,
 ) -> BigRational {
-    let cost = gas_estimate * gas_price;
+    let cost = gas_estimate * gas_price + flash_loan_fees;
     surplus + solver_fees - cost
 }
 
 impl RatedSettlement {
+    /// The gas the settlement pays for. Unlike the gas estimate, which is used as the gas limit,
+    /// this excludes the gas that gets refunded at the end of the execution.
+    pub fn effective_gas(&self) -> U256 {
+        gas_refunds::effective_gas(self.gas_estimate, self.gas_refund)
+    }
+
     pub fn objective_value(&self) -> BigRational {
-        let gas_estimate = self.gas_estimate.to_big_rational();
+        let gas = self.effective_gas().to_big_rational();
         compute_objective_value(
             &self.surplus,
             &self.scaled_unsubsidized_fee,
-            &gas_estimate,
+            &self.flash_loan_fee,
+            &gas,
             &self.gas_price,
         )
     }
@@ -426,6 +436,8 @@ mod tests {
         // Fees is 0.001 ETH
         let solver_fees = BigRational::from_integer(1_000_000_000_000_000_u128.into());
 
+        let flash_loan_fees = BigRational::from_integer(0.into());
+
         let gas_estimate1 = BigRational::from_integer(300_000.into());
         let gas_estimate2 = BigRational::from_integer(500_000.into());
 
@@ -437,8 +449,13 @@ mod tests {
         let gas_price = BigRational::from_integer(10_000_000_000_u128.into());
 
         // Objective value 1 is 1.004 - 3e5 * 10e-9 = 1.001 ETH
-        let obj_value1 =
-            super::compute_objective_value(&surplus1, &solver_fees, &gas_estimate1, &gas_price);
+        let obj_value1 = super::compute_objective_value(
+            &surplus1,
+            &solver_fees,
+            &flash_loan_fees,
+            &gas_estimate1,
+            &gas_price,
+        );
 
         assert_eq!(
             obj_value1,
@@ -446,8 +463,13 @@ mod tests {
         );
 
         // Objective value 2 is 1.01 - 5e5 * 10e-9 = 1.005 ETH
-        let obj_value2 =
-            super::compute_objective_value(&surplus2, &solver_fees, &gas_estimate2, &gas_price);
+        let obj_value2 = super::compute_objective_value(
+            &surplus2,
+            &solver_fees,
+            &flash_loan_fees,
+            &gas_estimate2,
+            &gas_price,
+        );
 
         assert_eq!(
             obj_value2,
@@ -462,8 +484,13 @@ mod tests {
         let gas_price = BigRational::from_integer(30_000_000_000_u128.into());
 
         // Objective value 1 is 1.004 - 3e5 * 30e-9 = 0.995 ETH
-        let obj_value1 =
-            super::compute_objective_value(&surplus1, &solver_fees, &gas_estimate1, &gas_price);
+        let obj_value1 = super::compute_objective_value(
+            &surplus1,
+            &solver_fees,
+            &flash_loan_fees,
+            &gas_estimate1,
+            &gas_price,
+        );
 
         assert_eq!(
             obj_value1,
@@ -471,8 +498,13 @@ mod tests {
         );
 
         // Objective value 2 is 1.01 - 5e5 * 30e-9 = 0.995 ETH
-        let obj_value2 =
-            super::compute_objective_value(&surplus2, &solver_fees, &gas_estimate2, &gas_price);
+        let obj_value2 = super::compute_objective_value(
+            &surplus2,
+            &solver_fees,
+            &flash_loan_fees,
+            &gas_estimate2,
+            &gas_price,
+        );
 
         assert_eq!(
             obj_value2,
@@ -487,8 +519,13 @@ mod tests {
         let gas_price = BigRational::from_integer(50_000_000_000_u128.into());
 
         // Objective value 1 is 1.004 - 3e5 * 50e-9 = 0.989 ETH
-        let obj_value1 =
-            super::compute_objective_value(&surplus1, &solver_fees, &gas_estimate1, &gas_price);
+        let obj_value1 = super::compute_objective_value(
+            &surplus1,
+            &solver_fees,
+            &flash_loan_fees,
+            &gas_estimate1,
+            &gas_price,
+        );
 
         assert_eq!(
             obj_value1,
@@ -496,8 +533,13 @@ mod tests {
         );
 
         // Objective value 2 is 1.01 - 5e5 * 50e-9 = 0.985 ETH
-        let obj_value2 =
-            super::compute_objective_value(&surplus2, &solver_fees, &gas_estimate2, &gas_price);
+        let obj_value2 = super::compute_objective_value(
+            &surplus2,
+            &solver_fees,
+            &flash_loan_fees,
+            &gas_estimate2,
+            &gas_price,
+        );
 
         assert_eq!(
             obj_value2,
@@ -505,6 +547,25 @@ mod tests {
         );
 
         assert!(obj_value1 > obj_value2);
+
+        // Case 4: flash loan fees reduce the objective value
+
+        // Flash loan fees are 0.002 ETH
+        let flash_loan_fees = BigRational::from_integer(2_000_000_000_000_000_u128.into());
+
+        // Objective value 2 is 1.01 - 0.002 - 5e5 * 50e-9 = 0.983 ETH
+        let obj_value2 = super::compute_objective_value(
+            &surplus2,
+            &solver_fees,
+            &flash_loan_fees,
+            &gas_estimate2,
+            &gas_price,
+        );
+
+        assert_eq!(
+            obj_value2,
+            BigRational::from_integer(983_000_000_000_000_000_u128.into())
+        );
     }
 
     #[test]
@@ -1,10 +1,13 @@
 use crate::{
     analytics,
     driver::solver_settlements::RatedSettlement,
+    encoding::SettlementContractVersion,
     metrics::SolverMetrics,
     settlement::Settlement,
     settlement_simulation::{
-        simulate_and_error_with_tenderly_link, simulate_before_after_access_list, TenderlyApi,
+        call_data, simulate_and_error_with_tenderly_link,
+        simulate_and_estimate_gas_at_current_block, simulate_before_after_access_list,
+        TransactionSimulating,
     },
     settlement_submission::SubmissionError,
     solver::{SettlementWithError, Solver},
@@ -13,20 +16,25 @@ use anyhow::{Context, Result};
 use contracts::GPv2Settlement;
 use gas_estimation::GasPrice1559;
 use itertools::Itertools;
-use model::order::{Order, OrderKind};
+use model::{
+    auction::AuctionId,
+    order::{Order, OrderKind},
+    solver_competition::SettlementRevert,
+};
 use num::{BigRational, ToPrimitive};
 use primitive_types::H256;
-use shared::Web3;
+use shared::{http_solver::model::SettlementRejection, Web3};
 use std::sync::Arc;
 use tracing::{Instrument as _, Span};
-use web3::types::{AccessList, TransactionReceipt};
+use web3::types::{AccessList, TraceType, TransactionReceipt};
 
 pub struct DriverLogger {
     pub metrics: Arc<dyn SolverMetrics>,
     pub web3: Web3,
-    pub tenderly: Option<TenderlyApi>,
+    pub simulator: Option<Arc<dyn TransactionSimulating>>,
     pub network_id: String,
     pub settlement_contract: GPv2Settlement,
+    pub settlement_contract_version: SettlementContractVersion,
     pub simulation_gas_limit: u128,
 }
 
@@ -34,7 +42,9 @@ impl DriverLogger {
     pub async fn metric_access_list_gas_saved(&self, transaction_hash: H256) -> Result<()> {
         let gas_saved = simulate_before_after_access_list(
             &self.web3,
-            self.tenderly.as_ref().context("tenderly disabled")?,
+            self.simulator
+                .as_deref()
+                .context("transaction simulation disabled")?,
             self.network_id.clone(),
             transaction_hash,
         )
@@ -51,6 +61,33 @@ impl DriverLogger {
         Ok(())
     }
 
+    /// Replays the reverted settlement transaction to find out why it reverted. Requires a node
+    /// that supports `trace_replayTransaction`.
+    pub async fn settlement_revert(&self, transaction_hash: H256) -> SettlementRevert {
+        match self
+            .web3
+            .trace()
+            .replay_transaction(transaction_hash, vec![TraceType::Trace])
+            .await
+        {
+            Ok(trace) => {
+                let error = trace
+                    .trace
+                    .and_then(|traces| traces.into_iter().next())
+                    .and_then(|trace| trace.error);
+                SettlementRevert::new(error, trace.output.0)
+            }
+            Err(err) => {
+                tracing::warn!(
+                    ?err,
+                    ?transaction_hash,
+                    "failed to replay reverted settlement"
+                );
+                SettlementRevert::default()
+            }
+        }
+    }
+
     /// Collects all orders which got traded in the settlement. Tapping into partially fillable
     /// orders multiple times will not result in duplicates. Partially fillable orders get
     /// considered as traded only the first time we tap into their liquidity.
@@ -131,16 +168,19 @@ impl DriverLogger {
 
     // Log simulation errors only if the simulation also fails in the block at which on chain
     // liquidity was queried. If the simulation succeeds at the previous block then the solver
-    // worked correctly and the error doesn't have to be reported.
+    // worked correctly and the error doesn't have to be reported. Otherwise the solver gets
+    // notified about the rejection of its settlement.
     // Note that we could still report a false positive because the earlier block might be off by if
     // the block has changed just as were were querying the node.
     pub fn report_simulation_errors(
         &self,
+        auction_id: AuctionId,
         errors: Vec<SettlementWithError>,
         current_block_during_liquidity_fetch: u64,
         gas_price: GasPrice1559,
     ) {
         let contract = self.settlement_contract.clone();
+        let version = self.settlement_contract_version;
         let web3 = self.web3.clone();
         let network_id = self.network_id.clone();
         let metrics = self.metrics.clone();
@@ -155,6 +195,7 @@ impl DriverLogger {
                     )
                 }),
                 &contract,
+                version,
                 &web3,
                 gas_price,
                 &network_id,
@@ -180,6 +221,78 @@ impl DriverLogger {
                     );
 
                     metrics.settlement_simulation_failed(solver.name());
+                    solver.notify_rejection(
+                        auction_id,
+                        SettlementRejection::SimulationFailed {
+                            block_number: current_block_during_liquidity_fetch,
+                            error: format!("{:?}", error_at_earlier_block),
+                            call_data: call_data(version, settlement.clone().into()),
+                        },
+                    );
+                }
+            }
+        };
+        tokio::task::spawn(task.instrument(Span::current()));
+    }
+
+    /// Simulates the winning settlement again right before it gets submitted and reports how
+    /// much the simulation drifted from the one the settlement was ranked with. This quantifies
+    /// how much state changes between solving and submitting contribute to reverts.
+    ///
+    /// The simulation happens in the background so that it doesn't delay the submission.
+    pub fn report_simulation_drift(
+        &self,
+        solver: &Arc<dyn Solver>,
+        rated_settlement: &RatedSettlement,
+        access_list: Option<AccessList>,
+        ranking_block: u64,
+        gas_price: GasPrice1559,
+    ) {
+        let contract = self.settlement_contract.clone();
+        let version = self.settlement_contract_version;
+        let web3 = self.web3.clone();
+        let metrics = self.metrics.clone();
+        let account = solver.account().clone();
+        let settlement = rated_settlement.settlement.clone();
+        let ranking_gas = rated_settlement.gas_estimate;
+        let task = async move {
+            let (block, simulation) = futures::join!(
+                web3.eth().block_number(),
+                simulate_and_estimate_gas_at_current_block(
+                    std::iter::once((account, settlement, access_list)),
+                    &contract,
+                    version,
+                    &web3,
+                    gas_price,
+                ),
+            );
+            let (block, simulation) = match (block, simulation) {
+                (Ok(block), Ok(mut simulation)) => (block.as_u64(), simulation.remove(0)),
+                (block, simulation) => {
+                    tracing::debug!(?block, ?simulation, "failed to simulate settlement drift");
+                    return;
+                }
+            };
+            let blocks = block.saturating_sub(ranking_block);
+            match simulation {
+                Ok(submission_gas) => {
+                    let gas_delta = submission_gas.to_f64_lossy() - ranking_gas.to_f64_lossy();
+                    tracing::debug!(
+                        blocks,
+                        %ranking_gas,
+                        %submission_gas,
+                        gas_delta,
+                        "settlement simulation drift"
+                    );
+                    metrics.settlement_simulation_drift(blocks, Some(gas_delta));
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        blocks,
+                        ?err,
+                        "settlement reverts at submission but not when it was ranked"
+                    );
+                    metrics.settlement_simulation_drift(blocks, None);
                 }
             }
         };
@@ -197,14 +310,15 @@ impl DriverLogger {
                 text,
                 "\nid={} solver={} \
              objective={:.2e} surplus={:.2e} \
-             gas_estimate={:.2e} gas_price={:.2e} \
+             gas_estimate={:.2e} gas_refund={:.2e} gas_price={:.2e} \
              unscaled_unsubsidized_fee={:.2e} unscaled_subsidized_fee={:.2e} \
-             access_list_addreses={}",
+             flash_loan_fee={:.2e} access_list_addreses={}",
                 settlement.id,
                 solver.name(),
                 settlement.objective_value().to_f64().unwrap_or(f64::NAN),
                 settlement.surplus.to_f64().unwrap_or(f64::NAN),
                 settlement.gas_estimate.to_f64_lossy(),
+                settlement.gas_refund.to_f64_lossy(),
                 settlement.gas_price.to_f64().unwrap_or(f64::NAN),
                 (&settlement.scaled_unsubsidized_fee / fee_objective_scaling_factor)
                     .to_f64()
@@ -213,6 +327,7 @@ impl DriverLogger {
                     .unscaled_subsidized_fee
                     .to_f64()
                     .unwrap_or(f64::NAN),
+                settlement.flash_loan_fee.to_f64().unwrap_or(f64::NAN),
                 access_list.clone().unwrap_or_default().len()
             )
             .unwrap();
@@ -258,7 +373,9 @@ mod tests {
                     surplus: BigRational::new(1u8.into(), 1u8.into()),
                     unscaled_subsidized_fee: BigRational::new(2u8.into(), 1u8.into()),
                     scaled_unsubsidized_fee: BigRational::new(3u8.into(), 1u8.into()),
+                    flash_loan_fee: Default::default(),
                     gas_estimate: 4.into(),
+                    gas_refund: 0.into(),
                     gas_price: BigRational::new(5u8.into(), 1u8.into()),
                 },
                 None,
@@ -271,7 +388,9 @@ mod tests {
                     surplus: BigRational::new(7u8.into(), 1u8.into()),
                     unscaled_subsidized_fee: BigRational::new(8u8.into(), 1u8.into()),
                     scaled_unsubsidized_fee: BigRational::new(9u8.into(), 1u8.into()),
+                    flash_loan_fee: Default::default(),
                     gas_estimate: 10.into(),
+                    gas_refund: 0.into(),
                     gas_price: BigRational::new(11u8.into(), 1u8.into()),
                 },
                 None,
@@ -1,4 +1,5 @@
-use ethcontract::Bytes;
+use contracts::GPv2Settlement;
+use ethcontract::{dyns::DynMethodBuilder, Bytes};
 use model::{
     order::{BuyTokenDestination, OrderData, OrderKind, SellTokenSource},
     signature::{Signature, SigningScheme},
@@ -14,7 +15,7 @@ pub type EncodedTrade = (
     u32,             // validTo
     Bytes<[u8; 32]>, // appData
     U256,            // feeAmount
-    U256,            // flags
+    TradeFlags,      // flags, encoded by the settlement contract version
     U256,            // executedAmount
     Bytes<Vec<u8>>,  // signature
 );
@@ -37,40 +38,33 @@ pub fn encode_trade(
         order.valid_to,
         Bytes(order.app_data.0),
         order.fee_amount,
-        order_flags(order, signature),
+        TradeFlags::new(order, signature),
         *executed_amount,
         Bytes(signature.encode_for_settlement(owner).to_vec()),
     )
 }
 
-fn order_flags(order: &OrderData, signature: &Signature) -> U256 {
-    let mut result = 0u8;
-    // The kind is encoded as 1 bit in position 0.
-    result |= match order.kind {
-        OrderKind::Sell => 0b0,
-        OrderKind::Buy => 0b1,
-    };
-    // The order fill kind is encoded as 1 bit in position 1.
-    result |= (order.partially_fillable as u8) << 1;
-    // The order sell token balance is encoded as 2 bits in position 2.
-    result |= match order.sell_token_balance {
-        SellTokenSource::Erc20 => 0b00,
-        SellTokenSource::External => 0b10,
-        SellTokenSource::Internal => 0b11,
-    } << 2;
-    // The order buy token balance is encoded as 1 bit in position 4.
-    result |= match order.buy_token_balance {
-        BuyTokenDestination::Erc20 => 0b0,
-        BuyTokenDestination::Internal => 0b1,
-    } << 4;
-    // The signing scheme is encoded as a 2 bits in position 5.
-    result |= match signature.scheme() {
-        SigningScheme::Eip712 => 0b00,
-        SigningScheme::EthSign => 0b01,
-        SigningScheme::Eip1271 => 0b10,
-        SigningScheme::PreSign => 0b11,
-    } << 5;
-    result.into()
+/// The properties of a trade the settlement contract reads from its flags. How they are packed
+/// into the flags depends on the version of the settlement contract.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TradeFlags {
+    pub kind: OrderKind,
+    pub partially_fillable: bool,
+    pub sell_token_balance: SellTokenSource,
+    pub buy_token_balance: BuyTokenDestination,
+    pub signing_scheme: SigningScheme,
+}
+
+impl TradeFlags {
+    pub fn new(order: &OrderData, signature: &Signature) -> Self {
+        Self {
+            kind: order.kind,
+            partially_fillable: order.partially_fillable,
+            sell_token_balance: order.sell_token_balance,
+            buy_token_balance: order.buy_token_balance,
+            signing_scheme: signature.scheme(),
+        }
+    }
 }
 
 pub type EncodedInteraction = (
@@ -87,6 +81,117 @@ pub struct EncodedSettlement {
     pub interactions: [Vec<EncodedInteraction>; 3],
 }
 
+/// The revision of the settlement contract settlements get submitted to. Solvers build
+/// settlements independently of the revision. Only the `settle` call gets encoded for it, so
+/// supporting a new revision of the contract doesn't require changes to the solvers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ArgEnum)]
+pub enum SettlementContractVersion {
+    /// The original GPv2 settlement contract.
+    V1,
+}
+
+impl SettlementContractVersion {
+    fn encoder(self) -> &'static dyn SettlementContractEncoding {
+        match self {
+            Self::V1 => &V1Encoder,
+        }
+    }
+
+    /// Creates the `settle` call of the settlement to the contract at the address of `contract`.
+    pub fn settle(
+        self,
+        contract: &GPv2Settlement,
+        settlement: EncodedSettlement,
+    ) -> DynMethodBuilder<()> {
+        self.encoder().settle(contract, settlement)
+    }
+}
+
+/// Encodes settlements for one revision of the settlement contract.
+pub trait SettlementContractEncoding: Send + Sync {
+    /// Packs the flags of a trade the way the contract's `decodeTrade` expects.
+    fn trade_flags(&self, flags: &TradeFlags) -> U256;
+
+    /// Creates the `settle` call of the settlement. Revisions with a different `settle` signature
+    /// override this with a call through their own contract bindings at the address of
+    /// `contract`.
+    fn settle(
+        &self,
+        contract: &GPv2Settlement,
+        settlement: EncodedSettlement,
+    ) -> DynMethodBuilder<()> {
+        let trades = settlement
+            .trades
+            .into_iter()
+            .map(
+                |(
+                    sell,
+                    buy,
+                    receiver,
+                    sell_amount,
+                    buy_amount,
+                    valid_to,
+                    app_data,
+                    fee,
+                    flags,
+                    executed,
+                    signature,
+                )| {
+                    (
+                        sell,
+                        buy,
+                        receiver,
+                        sell_amount,
+                        buy_amount,
+                        valid_to,
+                        app_data,
+                        fee,
+                        self.trade_flags(&flags),
+                        executed,
+                        signature,
+                    )
+                },
+            )
+            .collect();
+        contract.settle(
+            settlement.tokens,
+            settlement.clearing_prices,
+            trades,
+            settlement.interactions,
+        )
+    }
+}
+
+/// The encoding of the original GPv2 settlement contract.
+pub struct V1Encoder;
+
+impl SettlementContractEncoding for V1Encoder {
+    fn trade_flags(&self, flags: &TradeFlags) -> U256 {
+        let mut result = 0u8;
+        // The kind is encoded as 1 bit in position 0.
+        result |= match flags.kind {
+            OrderKind::Sell => 0b0,
+            OrderKind::Buy => 0b1,
+        };
+        // The order fill kind is encoded as 1 bit in position 1.
+        result |= (flags.partially_fillable as u8) << 1;
+        // The order sell token balance is encoded as 2 bits in position 2.
+        result |= match flags.sell_token_balance {
+            SellTokenSource::Erc20 => 0b00,
+            SellTokenSource::External => 0b10,
+            SellTokenSource::Internal => 0b11,
+        } << 2;
+        // The order buy token balance is encoded as 1 bit in position 4.
+        result |= match flags.buy_token_balance {
+            BuyTokenDestination::Erc20 => 0b0,
+            BuyTokenDestination::Internal => 0b1,
+        } << 4;
+        // The signing scheme is encoded as a 2 bits in position 5.
+        result |= flags.signing_scheme.implementation().settlement_flag() << 5;
+        result.into()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -179,7 +284,7 @@ mod tests {
             ),
         ] {
             assert_eq!(
-                order_flags(&order, &Signature::default_with(scheme)),
+                V1Encoder.trade_flags(&TradeFlags::new(&order, &Signature::default_with(scheme))),
                 U256::from(flags)
             );
         }
@@ -188,15 +293,19 @@ mod tests {
     #[test]
     fn trade_signature_encoding() {
         let owner = H160([1; 20]);
+        // ECDSA recovery ids get normalized to 27 or 28.
         for (signature, bytes) in [
-            (Signature::Eip712(Default::default()), vec![0; 65]),
+            (
+                Signature::Eip712(Default::default()),
+                [&[0; 64][..], &[27]].concat(),
+            ),
             (
                 Signature::EthSign(EcdsaSignature {
                     r: H256([1; 32]),
                     s: H256([1; 32]),
                     v: 1,
                 }),
-                vec![1; 65],
+                [&[1; 64][..], &[28]].concat(),
             ),
             (
                 Signature::Eip1271(vec![1, 2, 3, 4]),
@@ -219,4 +328,60 @@ mod tests {
             assert_eq!(encoded_signature.0, bytes);
         }
     }
+
+    #[test]
+    fn v1_settle_call_packs_trade_flags() {
+        let contract = GPv2Settlement::at(&shared::transport::dummy::web3(), H160([1; 20]));
+        let order = OrderData {
+            kind: OrderKind::Buy,
+            partially_fillable: true,
+            ..Default::default()
+        };
+        let trade = encode_trade(&order, &Signature::PreSign, H160([2; 20]), 0, 1, &1.into());
+        let settlement = EncodedSettlement {
+            tokens: vec![H160([3; 20]), H160([4; 20])],
+            clearing_prices: vec![1.into(), 2.into()],
+            trades: vec![trade.clone()],
+            interactions: Default::default(),
+        };
+
+        let (
+            sell,
+            buy,
+            receiver,
+            sell_amount,
+            buy_amount,
+            valid_to,
+            app_data,
+            fee,
+            _,
+            executed,
+            signature,
+        ) = trade;
+        let expected = contract.settle(
+            settlement.tokens.clone(),
+            settlement.clearing_prices.clone(),
+            vec![(
+                sell,
+                buy,
+                receiver,
+                sell_amount,
+                buy_amount,
+                valid_to,
+                app_data,
+                fee,
+                U256::from(0b1100011),
+                executed,
+                signature,
+            )],
+            Default::default(),
+        );
+        assert_eq!(
+            SettlementContractVersion::V1
+                .settle(&contract, settlement)
+                .tx
+                .data,
+            expected.tx.data
+        );
+    }
 }
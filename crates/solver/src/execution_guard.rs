@@ -0,0 +1,163 @@
+//! Guards against submitting settlements with orders that already got executed.
+//!
+//! Other solvers' settlements or our own settlements of earlier auctions can execute an order
+//! after the auction containing it was cut. Settling such an order again reverts in the settlement
+//! contract, which wastes the gas of the whole settlement. So before submitting, the filled
+//! amounts of the traded orders get checked in the pending block, which includes the transactions
+//! of the node's mempool.
+
+use crate::settlement::Settlement;
+use anyhow::{Context, Result};
+use contracts::GPv2Settlement;
+use ethcontract::{BlockId, BlockNumber, Bytes};
+use futures::future::try_join_all;
+use model::order::{Order, OrderKind, OrderUid};
+use primitive_types::U256;
+use std::collections::HashMap;
+
+/// Returns the orders of the settlement whose remaining amount on chain doesn't cover what the
+/// settlement executes of them.
+pub async fn already_executed_orders(
+    contract: &GPv2Settlement,
+    settlement: &Settlement,
+) -> Result<Vec<OrderUid>> {
+    let uids = executed_amounts(settlement).into_keys().collect::<Vec<_>>();
+    let filled_amounts = try_join_all(uids.iter().map(|uid| async move {
+        contract
+            .filled_amount(Bytes(uid.0.to_vec()))
+            .block(BlockId::Number(BlockNumber::Pending))
+            .call()
+            .await
+            .with_context(|| format!("failed to get filled amount of order {uid}"))
+    }))
+    .await?;
+    let filled_amounts = uids.into_iter().zip(filled_amounts).collect();
+    Ok(orders_exceeding_remaining(settlement, &filled_amounts))
+}
+
+fn orders_exceeding_remaining(
+    settlement: &Settlement,
+    filled_amounts: &HashMap<OrderUid, U256>,
+) -> Vec<OrderUid> {
+    executed_amounts(settlement)
+        .into_iter()
+        .filter(|(uid, (order, executed))| {
+            let filled = filled_amounts.get(uid).copied().unwrap_or_default();
+            exceeds_remaining(order, filled, *executed)
+        })
+        .map(|(uid, _)| uid)
+        .collect()
+}
+
+/// The amount of every order the settlement executes in the unit the settlement contract tracks
+/// filled amounts in.
+fn executed_amounts(settlement: &Settlement) -> HashMap<OrderUid, (&Order, U256)> {
+    let mut executed = HashMap::new();
+    for (trade, execution) in settlement.executed_trades() {
+        let order = &trade.order;
+        let amount = match order.data.kind {
+            OrderKind::Sell => execution.sell_amount,
+            OrderKind::Buy => execution.buy_amount,
+        };
+        let (_, total) = executed
+            .entry(order.metadata.uid)
+            .or_insert((order, U256::zero()));
+        *total = total.saturating_add(amount);
+    }
+    executed
+}
+
+fn exceeds_remaining(order: &Order, filled: U256, executed: U256) -> bool {
+    let total = match order.data.kind {
+        OrderKind::Sell => order.data.sell_amount,
+        OrderKind::Buy => order.data.buy_amount,
+    };
+    filled.saturating_add(executed) > total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settlement::{OrderTrade, Trade};
+    use maplit::hashmap;
+    use model::order::{OrderData, OrderMetadata};
+    use primitive_types::H160;
+
+    #[test]
+    fn detects_executions_beyond_remaining_amount() {
+        let order = |kind, partially_fillable| Order {
+            data: OrderData {
+                sell_amount: 100.into(),
+                buy_amount: 50.into(),
+                kind,
+                partially_fillable,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let fill_or_kill = order(OrderKind::Sell, false);
+        assert!(!exceeds_remaining(&fill_or_kill, 0.into(), 100.into()));
+        assert!(exceeds_remaining(&fill_or_kill, 100.into(), 100.into()));
+
+        let partially_fillable = order(OrderKind::Buy, true);
+        assert!(!exceeds_remaining(
+            &partially_fillable,
+            20.into(),
+            30.into()
+        ));
+        assert!(exceeds_remaining(&partially_fillable, 21.into(), 30.into()));
+    }
+
+    #[test]
+    fn partially_fillable_order_traded_elsewhere() {
+        let (sell_token, buy_token) = (H160([1; 20]), H160([2; 20]));
+        let trade = |uid: u8, partially_fillable: bool, executed_amount: u64| OrderTrade {
+            trade: Trade {
+                order: Order {
+                    data: OrderData {
+                        sell_token,
+                        buy_token,
+                        sell_amount: 100.into(),
+                        buy_amount: 100.into(),
+                        kind: OrderKind::Sell,
+                        partially_fillable,
+                        ..Default::default()
+                    },
+                    metadata: OrderMetadata {
+                        uid: OrderUid([uid; 56]),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                sell_token_index: 0,
+                executed_amount: executed_amount.into(),
+                ..Default::default()
+            },
+            buy_token_index: 1,
+        };
+        let settlement = Settlement::with_trades(
+            hashmap! { sell_token => 1.into(), buy_token => 1.into() },
+            vec![trade(1, true, 40), trade(2, false, 100)],
+            vec![],
+        );
+
+        // Another settlement executed some of the partially fillable order but what is left
+        // still covers our execution.
+        let filled = hashmap! { OrderUid([1; 56]) => 60.into() };
+        assert!(orders_exceeding_remaining(&settlement, &filled).is_empty());
+
+        let filled = hashmap! { OrderUid([1; 56]) => 61.into() };
+        assert_eq!(
+            orders_exceeding_remaining(&settlement, &filled),
+            vec![OrderUid([1; 56])]
+        );
+
+        // Any execution of a fill-or-kill order makes the settlement fail.
+        let filled = hashmap! { OrderUid([2; 56]) => 100.into() };
+        assert_eq!(
+            orders_exceeding_remaining(&settlement, &filled),
+            vec![OrderUid([2; 56])]
+        );
+    }
+}
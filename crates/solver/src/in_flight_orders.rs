@@ -64,13 +64,15 @@ impl InFlightOrders {
         auction.orders.iter_mut().for_each(|order| {
             let uid = &order.metadata.uid;
 
-            if order.data.partially_fillable {
-                if let Some(trades) = self.in_flight_trades.get(uid) {
-                    *order = trades.order_with_remaining_amounts();
-                }
+            if let (true, Some(trades)) = (
+                order.data.partially_fillable,
+                self.in_flight_trades.get(uid),
+            ) {
+                *order = trades.order_with_remaining_amounts();
             } else if in_flight.contains(uid) {
                 // fill-or-kill orders can only be used once and there is already a trade in flight
-                // for this one => Modify it such that it gets filtered out in the next step.
+                // for this one => Modify it such that it gets filtered out in the next step. The
+                // same goes for orders that got executed by someone else.
                 order.metadata.executed_buy_amount = u256_to_big_uint(&order.data.buy_amount);
                 order.metadata.executed_sell_amount_before_fees = order.data.sell_amount;
             }
@@ -87,6 +89,13 @@ impl InFlightOrders {
         in_flight
     }
 
+    /// Tracks orders that got executed at `block` by settlements other than ours. They are
+    /// excluded from auctions until the api has seen the block because it is unknown how much of
+    /// them is left.
+    pub fn mark_executed_orders(&mut self, block: u64, uids: impl IntoIterator<Item = OrderUid>) {
+        self.in_flight.entry(block).or_default().extend(uids);
+    }
+
     /// Tracks all in_flight orders and how much of the executable amount of partially fillable
     /// orders is currently used in in-flight trades.
     pub fn mark_settled_orders(&mut self, block: u64, settlement: &Settlement) {
@@ -252,6 +261,33 @@ mod tests {
         assert_eq!(filtered.len(), 4);
     }
 
+    #[test]
+    fn excludes_orders_executed_by_others() {
+        let order = |uid, partially_fillable| Order {
+            data: OrderData {
+                sell_amount: 100u8.into(),
+                buy_amount: 100u8.into(),
+                partially_fillable,
+                ..Default::default()
+            },
+            metadata: OrderMetadata {
+                uid: OrderUid::from_integer(uid),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut auction = Auction {
+            orders: vec![order(1, false), order(2, true), order(3, true)],
+            ..Default::default()
+        };
+        let mut inflight = InFlightOrders::default();
+        inflight.mark_executed_orders(1, [OrderUid::from_integer(1), OrderUid::from_integer(2)]);
+
+        let in_flight = inflight.update_and_filter(&mut auction);
+        assert_eq!(in_flight.len(), 2);
+        assert_eq!(auction.orders, vec![order(3, true)]);
+    }
+
     #[test]
     fn test_order_is_not_excluded_when_min_buy_amount_is_reached() {
         let order = Order {
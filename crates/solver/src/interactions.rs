@@ -8,7 +8,7 @@ mod weth;
 pub mod zeroex;
 
 pub use balancer_v2::BalancerSwapGivenOutInteraction;
-pub use erc20::Erc20ApproveInteraction;
+pub use erc20::{Erc20ApproveInteraction, Erc20TransferInteraction};
 pub use uniswap_v2::UniswapInteraction;
 pub use uniswap_v3::ExactOutputSingleParams;
 pub use uniswap_v3::UniswapV3Interaction;
@@ -74,13 +74,18 @@ impl Allowances {
             .copied()
             .ok_or_else(|| anyhow!("missing allowance for token {:?}", token))?;
 
-        Ok(if allowance < amount {
+        Ok(if allowance >= amount {
+            Approval::AllowanceSufficient
+        } else if allowance.is_zero() {
             Approval::Approve {
                 token,
                 spender: self.spender,
             }
         } else {
-            Approval::AllowanceSufficient
+            Approval::ResetAndApprove {
+                token,
+                spender: self.spender,
+            }
         })
     }
 
@@ -116,6 +121,25 @@ pub enum Approval {
     /// in order to save gas by allowing approvals to be used over multiple
     /// settlements.
     Approve { token: H160, spender: H160 },
+
+    /// The existing allowance is non-zero but insufficient. Some tokens (most
+    /// notably USDT) revert when changing a non-zero allowance to another
+    /// non-zero value, so the allowance gets reset to 0 before approving
+    /// U256::MAX.
+    ResetAndApprove { token: H160, spender: H160 },
+}
+
+impl Approval {
+    /// Returns the token and spender of the approval or `None` if no approval
+    /// is needed.
+    pub fn token_and_spender(&self) -> Option<(H160, H160)> {
+        match self {
+            Approval::AllowanceSufficient => None,
+            Approval::Approve { token, spender } | Approval::ResetAndApprove { token, spender } => {
+                Some((*token, *spender))
+            }
+        }
+    }
 }
 
 impl Interaction for Approval {
@@ -123,23 +147,31 @@ impl Interaction for Approval {
         match self {
             Approval::AllowanceSufficient => vec![],
             Approval::Approve { token, spender } => {
-                // Use a "dummy" contract - unfortunately `ethcontract` doesn't
-                // allow you use the generated contract intances to encode
-                // transaction data without a `Web3` instance. Hopefully, this
-                // limitation will be lifted soon to clean up stuff like this.
-                let token = dummy_contract!(ERC20, *token);
-                let approve = Erc20ApproveInteraction {
-                    token,
-                    spender: *spender,
-                    amount: U256::max_value(),
-                };
-
-                approve.encode()
+                vec![approve(*token, *spender, U256::max_value())]
             }
+            Approval::ResetAndApprove { token, spender } => vec![
+                approve(*token, *spender, U256::zero()),
+                approve(*token, *spender, U256::max_value()),
+            ],
         }
     }
 }
 
+fn approve(token: H160, spender: H160, amount: U256) -> EncodedInteraction {
+    // Use a "dummy" contract - unfortunately `ethcontract` doesn't allow you
+    // use the generated contract intances to encode transaction data without a
+    // `Web3` instance. Hopefully, this limitation will be lifted soon to clean
+    // up stuff like this.
+    let token = dummy_contract!(ERC20, token);
+    let approve = Erc20ApproveInteraction {
+        token,
+        spender,
+        amount,
+    };
+
+    approve.as_encoded()
+}
+
 /// An allowance manager that retrive approval interactions for a given owner
 /// address.
 pub struct AllowanceManager {
@@ -292,6 +324,23 @@ mod tests {
             },
         );
 
+        assert_eq!(
+            allowances.approve_token(token, 1337.into()).unwrap(),
+            Approval::ResetAndApprove { token, spender }
+        );
+    }
+
+    #[test]
+    fn approval_when_allowance_is_zero() {
+        let spender = H160([0x01; 20]);
+        let token = H160([0x02; 20]);
+        let allowances = Allowances::new(
+            spender,
+            hashmap! {
+                token => U256::zero(),
+            },
+        );
+
         assert_eq!(
             allowances.approve_token(token, 1337.into()).unwrap(),
             Approval::Approve { token, spender }
@@ -382,6 +431,35 @@ mod tests {
                 )
             )]
         );
+        assert_eq!(
+            Approval::ResetAndApprove { token, spender }.encode(),
+            vec![
+                (
+                    token,
+                    0.into(),
+                    Bytes(
+                        hex::decode(
+                            "095ea7b3\
+                             0000000000000000000000000202020202020202020202020202020202020202\
+                             0000000000000000000000000000000000000000000000000000000000000000"
+                        )
+                        .unwrap()
+                    )
+                ),
+                (
+                    token,
+                    0.into(),
+                    Bytes(
+                        hex::decode(
+                            "095ea7b3\
+                             0000000000000000000000000202020202020202020202020202020202020202\
+                             ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"
+                        )
+                        .unwrap()
+                    )
+                ),
+            ]
+        );
     }
 
     fn allowance_call_data(owner: H160, spender: H160) -> web3::types::Bytes {
@@ -2,6 +2,7 @@ use crate::{encoding::EncodedInteraction, settlement::Interaction};
 use contracts::{BalancerV2Vault, GPv2Settlement};
 use ethcontract::{Bytes, H160, H256};
 use primitive_types::U256;
+use shared::realized_gas::InteractionClass;
 
 #[derive(Clone, Debug)]
 pub struct BalancerSwapGivenOutInteraction {
@@ -51,6 +52,10 @@ impl Interaction for BalancerSwapGivenOutInteraction {
         let calldata = method.tx.data.expect("no calldata").0;
         vec![(self.vault.address(), 0.into(), Bytes(calldata))]
     }
+
+    fn gas_class(&self) -> Option<InteractionClass> {
+        Some(InteractionClass::BalancerSwap)
+    }
 }
 
 #[cfg(test)]
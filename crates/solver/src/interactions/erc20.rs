@@ -26,6 +26,22 @@ impl Interaction for Erc20ApproveInteraction {
     }
 }
 
+/// Transfers tokens held by the settlement contract.
+#[derive(Debug)]
+pub struct Erc20TransferInteraction {
+    pub token: ERC20,
+    pub receiver: H160,
+    pub amount: U256,
+}
+
+impl Interaction for Erc20TransferInteraction {
+    fn encode(&self) -> Vec<EncodedInteraction> {
+        let method = self.token.transfer(self.receiver, self.amount);
+        let calldata = method.tx.data.expect("no calldata").0;
+        vec![(self.token.address(), 0.into(), Bytes(calldata))]
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -52,4 +68,27 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn encode_erc20_transfer() {
+        let transfer = Erc20TransferInteraction {
+            token: dummy_contract!(ERC20, [0x01; 20]),
+            receiver: H160([0x02; 20]),
+            amount: U256::from_big_endian(&[0x03; 32]),
+        };
+
+        let encoded = transfer.encode();
+        assert_eq!(encoded.len(), 1);
+        let (target, value, calldata) = &encoded[0];
+        assert_eq!(*target, transfer.token.address());
+        assert_eq!(*value, 0.into());
+        assert_eq!(
+            calldata.0,
+            hex!(
+                "a9059cbb
+                 0000000000000000000000000202020202020202020202020202020202020202
+                 0303030303030303030303030303030303030303030303030303030303030303"
+            )
+        );
+    }
 }
@@ -2,6 +2,7 @@ use crate::{encoding::EncodedInteraction, settlement::Interaction};
 use contracts::{GPv2Settlement, IUniswapLikeRouter};
 use ethcontract::Bytes;
 use primitive_types::{H160, U256};
+use shared::realized_gas::InteractionClass;
 
 #[derive(Debug)]
 pub struct UniswapInteraction {
@@ -17,6 +18,10 @@ impl Interaction for UniswapInteraction {
     fn encode(&self) -> Vec<EncodedInteraction> {
         vec![self.encode_swap()]
     }
+
+    fn gas_class(&self) -> Option<InteractionClass> {
+        Some(InteractionClass::UniswapV2Swap)
+    }
 }
 
 impl UniswapInteraction {
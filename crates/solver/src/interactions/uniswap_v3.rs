@@ -2,6 +2,7 @@ use crate::{encoding::EncodedInteraction, settlement::Interaction};
 use contracts::UniswapV3SwapRouter;
 use ethcontract::Bytes;
 use primitive_types::{H160, U256};
+use shared::realized_gas::InteractionClass;
 
 #[derive(Debug)]
 pub struct UniswapV3Interaction {
@@ -35,6 +36,10 @@ impl Interaction for UniswapV3Interaction {
         let calldata = method.tx.data.expect("no calldata").0;
         vec![(self.router.address(), 0.into(), Bytes(calldata))]
     }
+
+    fn gas_class(&self) -> Option<InteractionClass> {
+        Some(InteractionClass::UniswapV3Swap)
+    }
 }
 
 #[cfg(test)]
@@ -1,8 +1,8 @@
 use crate::{encoding::EncodedInteraction, settlement::Interaction};
 use anyhow::{ensure, Result};
 use contracts::WETH9;
-use ethcontract::Bytes;
 use primitive_types::U256;
+use shared::wrapped_native;
 
 #[derive(Clone, Debug)]
 pub struct UnwrapWethInteraction {
@@ -31,9 +31,7 @@ impl UnwrapWethInteraction {
 
 impl Interaction for UnwrapWethInteraction {
     fn encode(&self) -> Vec<EncodedInteraction> {
-        let method = self.weth.withdraw(self.amount);
-        let calldata = method.tx.data.expect("no calldata").0;
-        vec![(self.weth.address(), 0.into(), Bytes(calldata))]
+        vec![wrapped_native::unwrap(&self.weth, self.amount)]
     }
 }
 
@@ -1,9 +1,13 @@
 mod analytics;
 pub mod arguments;
+pub mod auction_amendment;
 mod auction_preprocessing;
+pub mod balance_diffs;
+pub mod buffer_usage_limits;
 pub mod driver;
 pub mod driver_logger;
 pub mod encoding;
+pub mod execution_guard;
 pub mod in_flight_orders;
 pub mod interactions;
 pub mod liquidity;
@@ -18,6 +22,9 @@ pub mod settlement_rater;
 pub mod settlement_simulation;
 pub mod settlement_submission;
 pub mod solver;
+pub mod solver_balances;
+pub mod solver_demotions;
+pub mod solver_teams;
 #[cfg(test)]
 mod test;
 
@@ -11,7 +11,10 @@ use anyhow::Result;
 use derivative::Derivative;
 #[cfg(test)]
 use model::order::Order;
-use model::{order::OrderKind, TokenPair};
+use model::{
+    order::{OrderKind, QuoteFirmness},
+    TokenPair,
+};
 use num::{rational::Ratio, BigRational};
 use primitive_types::{H160, U256};
 #[cfg(test)]
@@ -98,6 +101,11 @@ pub struct LimitOrder {
     /// perspective.
     pub scaled_unsubsidized_fee: U256,
     pub is_liquidity_order: bool,
+    /// Firmness of the market maker quote the order is based on.
+    pub quote_firmness: Option<QuoteFirmness>,
+    /// The smallest amount a partially fillable order may get executed by, see
+    /// `OrderMetadata::min_partial_fill_amount`.
+    pub min_partial_fill_amount: Option<U256>,
     #[cfg_attr(test, derivative(PartialEq = "ignore"))]
     pub settlement_handling: Arc<dyn SettlementHandling<Self>>,
     pub exchange: Exchange,
@@ -150,6 +158,8 @@ impl Default for LimitOrder {
             scaled_unsubsidized_fee: Default::default(),
             settlement_handling: tests::CapturingSettlementHandler::arc(),
             is_liquidity_order: false,
+            quote_firmness: None,
+            min_partial_fill_amount: None,
             id: Default::default(),
             exchange: Exchange::GnosisProtocol,
         }
@@ -19,7 +19,7 @@ use shared::{
     baseline_solver::BaseTokens, recent_block_cache::Block,
     sources::balancer_v2::pool_fetching::BalancerPoolFetching, Web3,
 };
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 
 /// A liquidity provider for Balancer V2 weighted pools.
 pub struct BalancerV2Liquidity {
@@ -60,6 +60,15 @@ impl BalancerV2Liquidity {
                 .iter()
                 .flat_map(|order| TokenPair::new(order.buy_token, order.sell_token)),
         );
+        self.get_liquidity_for_pairs(pairs, block).await
+    }
+
+    /// Returns the Balancer V2 pools of exactly the specified token pairs.
+    pub async fn get_liquidity_for_pairs(
+        &self,
+        pairs: HashSet<TokenPair>,
+        block: Block,
+    ) -> Result<(Vec<StablePoolOrder>, Vec<WeightedProductOrder>)> {
         let pools = self.pool_fetcher.fetch(pairs, block).await?;
 
         let tokens = pools.relevant_tokens();
@@ -148,7 +157,7 @@ impl SettlementHandler {
         let (asset_in, amount_in) = execution.input;
         let (asset_out, amount_out) = execution.output;
 
-        encoder.append_to_execution_plan(self.allowances.approve_token(asset_in, amount_in)?);
+        encoder.add_approval(self.allowances.approve_token(asset_in, amount_in)?);
         encoder.append_to_execution_plan(BalancerSwapGivenOutInteraction {
             settlement: self.settlement.clone(),
             vault: self.vault.clone(),
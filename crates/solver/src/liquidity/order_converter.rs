@@ -41,7 +41,7 @@ impl OrderConverter {
                 .to_f64_lossy()
                 * self.fee_objective_scaling_factor,
         );
-        let is_liquidity_order = order.metadata.is_liquidity_order;
+        let is_liquidity_order = order.metadata.class.is_liquidity_order();
         Ok(LimitOrder {
             id: order.metadata.uid.to_string(),
             sell_token: order.data.sell_token,
@@ -53,6 +53,8 @@ impl OrderConverter {
             unscaled_subsidized_fee: remaining.remaining(order.data.fee_amount)?,
             scaled_unsubsidized_fee: scaled_fee_amount,
             is_liquidity_order,
+            quote_firmness: order.metadata.quote_firmness,
+            min_partial_fill_amount: order.metadata.min_partial_fill_amount,
             settlement_handling: Arc::new(OrderSettlementHandler {
                 order,
                 native_token,
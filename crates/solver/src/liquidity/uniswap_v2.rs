@@ -80,7 +80,15 @@ impl UniswapLikeLiquidity {
                 .iter()
                 .flat_map(|order| TokenPair::new(order.buy_token, order.sell_token)),
         );
+        self.get_liquidity_for_pairs(pairs, at_block).await
+    }
 
+    /// Returns the AMM liquidity of exactly the specified token pairs.
+    pub async fn get_liquidity_for_pairs(
+        &self,
+        pairs: HashSet<TokenPair>,
+        at_block: Block,
+    ) -> Result<Vec<ConstantProductOrder>> {
         let mut tokens = HashSet::new();
         let mut result = Vec::new();
         for pool in self.pool_fetcher.fetch(pairs, at_block).await? {
@@ -147,7 +155,7 @@ impl SettlementHandling<ConstantProductOrder> for Inner {
     // Creates the required interaction to convert the given input into output. Applies 0.1% slippage tolerance to the output.
     fn encode(&self, execution: AmmOrderExecution, encoder: &mut SettlementEncoder) -> Result<()> {
         let (approval, swap) = self.settle(execution.input, execution.output);
-        encoder.append_to_execution_plan(approval);
+        encoder.add_approval(approval);
         encoder.append_to_execution_plan(swap);
         Ok(())
     }
@@ -85,10 +85,17 @@ impl UniswapV3Liquidity {
                 .iter()
                 .flat_map(|order| TokenPair::new(order.buy_token, order.sell_token)),
         );
+        self.get_liquidity_for_pairs(&pairs).await
+    }
 
+    /// Returns the AMM liquidity of exactly the specified token pairs.
+    pub async fn get_liquidity_for_pairs(
+        &self,
+        pairs: &HashSet<TokenPair>,
+    ) -> Result<Vec<ConcentratedLiquidity>> {
         let mut tokens = HashSet::new();
         let mut result = Vec::new();
-        for pool in self.pool_fetcher.fetch(&pairs).await? {
+        for pool in self.pool_fetcher.fetch(pairs).await? {
             ensure!(
                 pool.tokens.len() == 2,
                 "two tokens required for uniswap v3 pools"
@@ -178,7 +185,7 @@ impl SettlementHandling<ConcentratedLiquidity> for UniswapV3SettlementHandler {
             execution.output,
             self.fee.context("missing fee")?,
         );
-        encoder.append_to_execution_plan(approval);
+        encoder.add_approval(approval);
         encoder.append_to_execution_plan(swap);
         Ok(())
     }
@@ -116,6 +116,8 @@ impl ZeroExLiquidity {
             unscaled_subsidized_fee: U256::zero(),
             scaled_unsubsidized_fee: U256::zero(),
             is_liquidity_order: true,
+            quote_firmness: None,
+            min_partial_fill_amount: None,
             settlement_handling: Arc::new(OrderSettlementHandler {
                 order: record.order,
                 zeroex: self.zeroex.clone(),
@@ -183,7 +185,7 @@ impl SettlementHandling<LimitOrder> for OrderSettlementHandler {
         if executed_amount > u128::MAX.into() {
             anyhow::bail!("0x only supports executed amounts of size u128");
         }
-        encoder.append_to_execution_plan(
+        encoder.add_approval(
             self.allowances
                 .approve_token(self.order.taker_token, executed_amount)?,
         );
@@ -350,7 +352,7 @@ pub mod tests {
         assert_eq!(
             interactions,
             [
-                Approval::Approve {
+                Approval::ResetAndApprove {
                     token: sell_token,
                     spender: zeroex.address(),
                 }
@@ -6,7 +6,9 @@ use crate::{
     },
 };
 use anyhow::{Context, Result};
+use model::TokenPair;
 use shared::recent_block_cache::Block;
+use std::collections::HashSet;
 
 #[mockall::automock]
 #[async_trait::async_trait]
@@ -16,6 +18,14 @@ pub trait LiquidityCollecting: Send + Sync {
         limit_orders: &[LimitOrder],
         at_block: Block,
     ) -> Result<Vec<Liquidity>>;
+
+    /// The AMM liquidity of exactly the specified token pairs. Unlike the liquidity for orders this
+    /// doesn't include pools connecting the tokens through base tokens or market maker orders.
+    async fn get_liquidity_for_pairs(
+        &self,
+        pairs: HashSet<TokenPair>,
+        at_block: Block,
+    ) -> Result<Vec<Liquidity>>;
 }
 
 pub struct LiquidityCollector {
@@ -86,4 +96,43 @@ impl LiquidityCollecting for LiquidityCollector {
 
         Ok(amms)
     }
+
+    async fn get_liquidity_for_pairs(
+        &self,
+        pairs: HashSet<TokenPair>,
+        at_block: Block,
+    ) -> Result<Vec<Liquidity>> {
+        let mut amms = vec![];
+        for liquidity in &self.uniswap_like_liquidity {
+            amms.extend(
+                liquidity
+                    .get_liquidity_for_pairs(pairs.clone(), at_block)
+                    .await
+                    .context("failed to get UniswapLike liquidity")?
+                    .into_iter()
+                    .map(Liquidity::ConstantProduct),
+            );
+        }
+        if let Some(balancer_v2_liquidity) = self.balancer_v2_liquidity.as_ref() {
+            let (stable_orders, weighted_orders) = balancer_v2_liquidity
+                .get_liquidity_for_pairs(pairs.clone(), at_block)
+                .await
+                .context("failed to get Balancer liquidity")?;
+
+            amms.extend(weighted_orders.into_iter().map(Liquidity::BalancerWeighted));
+            amms.extend(stable_orders.into_iter().map(Liquidity::BalancerStable));
+        }
+        if let Some(uniswap_v3_liquidity) = self.uniswap_v3_liquidity.as_ref() {
+            amms.extend(
+                uniswap_v3_liquidity
+                    .get_liquidity_for_pairs(&pairs)
+                    .await
+                    .context("failed to get UniswapV3 liquidity")?
+                    .into_iter()
+                    .map(Liquidity::Concentrated),
+            );
+        }
+
+        Ok(amms)
+    }
 }
@@ -1,14 +1,17 @@
 use anyhow::Context;
 use clap::Parser;
-use contracts::{BalancerV2Vault, IUniswapLikeRouter, UniswapV3SwapRouter, WETH9};
+use contracts::{BalancerV2Vault, IUniswapLikeRouter, UniswapV3SwapRouter, ERC20, WETH9};
 use num::rational::Ratio;
 use primitive_types::U256;
 use shared::{
     baseline_solver::BaseTokens,
     current_block::current_block_stream,
+    hot_reload::{reload_on_sighup, Overrides, Reloadable},
     maintenance::{Maintaining, ServiceMaintenance},
     metrics::serve_metrics,
     network::network_name,
+    preflight::{self, Preflight},
+    realized_gas::RealizedGas,
     recent_block_cache::CacheConfig,
     sources::{
         self,
@@ -18,11 +21,13 @@ use shared::{
         BaselineSource,
     },
     token_info::{CachedTokenInfoFetcher, TokenInfoFetcher},
-    token_list::TokenList,
+    token_list::TokenLists,
     zeroex_api::DefaultZeroExApi,
 };
 use solver::{
-    arguments::TransactionStrategyArg,
+    arguments::{Arguments, TransactionStrategyArg},
+    balance_diffs::BalanceDiffSimulator,
+    buffer_usage_limits::BufferUsageLimits,
     driver::Driver,
     liquidity::{
         balancer_v2::BalancerV2Liquidity, order_converter::OrderConverter,
@@ -31,25 +36,74 @@ use solver::{
     liquidity_collector::LiquidityCollector,
     metrics::Metrics,
     orderbook::OrderBookApi,
-    settlement_simulation::TenderlyApi,
+    settlement_simulation::{create_revm_simulator, create_simulator},
     settlement_submission::{
+        relayer::RelayerApi,
         submitter::{
             custom_nodes_api::CustomNodesApi, eden_api::EdenApi, flashbots_api::FlashbotsApi,
             Strategy,
         },
         GlobalTxPool, SolutionSubmitter, StrategyArgs, TransactionStrategy,
     },
+    solver::{
+        http_solver::liquidity_requests::{self, LiquidityRequests},
+        SolverType,
+    },
+    solver_balances::SolverBalances,
+    solver_teams::SolverTeams,
 };
 use std::{collections::HashMap, sync::Arc};
 
+/// Checks the node, the contracts and the APIs of the orderbook and the configured solvers instead
+/// of starting the solver.
+async fn run_preflight(args: &Arguments) -> ! {
+    let client = shared::http_client(args.shared.http_timeout);
+    let web3 = shared::web3(&client, &args.shared.node_url, "base");
+    let mut checks = Preflight::default();
+    checks.check_chain(&web3).await;
+    checks
+        .check(
+            "orderbook",
+            preflight::url_reachable(&client, &args.orderbook_url),
+        )
+        .await;
+    for solver in &args.solvers {
+        let url = match solver {
+            SolverType::Mip => &args.mip_solver_url,
+            SolverType::CowDexAg => &args.cow_dex_ag_solver_url,
+            SolverType::Quasimodo => &args.quasimodo_solver_url,
+            SolverType::BalancerSor => &args.balancer_sor_url,
+            _ => continue,
+        };
+        checks
+            .check(
+                &format!("solver {:?}", solver),
+                preflight::url_reachable(&client, url),
+            )
+            .await;
+    }
+    for solver in args.external_solvers.iter().flatten() {
+        checks
+            .check(
+                &format!("solver {}", solver.name),
+                preflight::url_reachable(&client, &solver.url),
+            )
+            .await;
+    }
+    checks.exit()
+}
+
 #[tokio::main]
 async fn main() {
-    let args = solver::arguments::Arguments::parse();
+    let args = Arguments::parse();
     shared::tracing::initialize(
         args.shared.log_filter.as_str(),
         args.shared.log_stderr_threshold,
     );
     tracing::info!("running solver with validated arguments:\n{}", args);
+    if args.shared.preflight {
+        run_preflight(&args).await;
+    }
 
     global_metrics::setup_metrics_registry(Some("gp_v2_solver".into()), None);
     let metrics = Arc::new(Metrics::new().expect("Couldn't register metrics"));
@@ -208,6 +262,21 @@ async fn main() {
         fee_objective_scaling_factor: args.fee_objective_scaling_factor,
     });
 
+    let realized_gas = args
+        .realized_gas_samples
+        .map(|samples| Arc::new(RealizedGas::new(samples)));
+    let liquidity_requests = args
+        .liquidity_request_url
+        .as_ref()
+        .map(|url| LiquidityRequests::new(url, args.liquidity_request_max_pairs));
+    let database = args
+        .db_url
+        .as_ref()
+        .map(|url| sqlx::PgPool::connect_lazy(url.as_str()).expect("invalid database url"));
+    let solver_teams = Arc::new(SolverTeams::new(database.clone()));
+    let paraswap_slippage_bps = Reloadable::new(args.paraswap_slippage_bps);
+    let zeroex_slippage_bps = Reloadable::new(args.zeroex_slippage_bps);
+    let oneinch_slippage_bps = Reloadable::new(args.oneinch_slippage_bps);
     let solver = solver::solver::create(
         web3.clone(),
         solvers,
@@ -223,15 +292,15 @@ async fn main() {
         network_name.to_string(),
         chain_id,
         args.shared.disabled_one_inch_protocols,
-        args.paraswap_slippage_bps,
+        paraswap_slippage_bps.clone(),
         args.shared.disabled_paraswap_dexs,
         args.shared.paraswap_partner,
         client.clone(),
         metrics.clone(),
         zeroex_api.clone(),
-        args.zeroex_slippage_bps,
+        zeroex_slippage_bps.clone(),
         args.shared.disabled_zeroex_sources,
-        args.oneinch_slippage_bps,
+        oneinch_slippage_bps.clone(),
         args.shared.quasimodo_uses_internal_buffers,
         args.shared.mip_uses_internal_buffers,
         args.shared.one_inch_url,
@@ -242,6 +311,10 @@ async fn main() {
         order_converter.clone(),
         args.max_settlements_per_solver,
         args.max_merged_settlements,
+        args.flash_loan_lenders,
+        realized_gas.clone(),
+        liquidity_requests.clone(),
+        &solver_teams,
     )
     .expect("failure creating solvers");
 
@@ -283,17 +356,34 @@ async fn main() {
             (None, None)
         };
 
-    let liquidity_collector = LiquidityCollector {
+    let liquidity_collector = Arc::new(LiquidityCollector {
         uniswap_like_liquidity,
         balancer_v2_liquidity,
         zeroex_liquidity,
         uniswap_v3_liquidity,
-    };
-    let market_makable_token_list =
-        TokenList::from_url(&args.market_makable_token_list, chain_id, client.clone())
-            .await
-            .map_err(|err| tracing::error!("Couldn't fetch market makable token list: {}", err))
-            .ok();
+    });
+    if let Some(liquidity_requests) = liquidity_requests {
+        liquidity_requests::serve(
+            liquidity_requests,
+            liquidity_collector.clone(),
+            args.liquidity_request_address,
+        );
+    }
+    let market_makable_token_list = (!args.market_makable_token_list.is_empty()).then(|| {
+        Arc::new(TokenLists::new(
+            client.clone(),
+            chain_id,
+            args.market_makable_token_list.clone(),
+            Vec::new(),
+        ))
+    });
+    if let Some(token_lists) = &market_makable_token_list {
+        tokio::task::spawn(
+            token_lists
+                .clone()
+                .update_forever(args.market_makable_token_list_update_interval),
+        );
+    }
     let submission_nodes_with_url = args
         .transaction_submission_nodes
         .into_iter()
@@ -377,6 +467,17 @@ async fn main() {
                     sub_tx_pool: submitted_transactions.add_sub_pool(Strategy::CustomNodes),
                 }))
             }
+            TransactionStrategyArg::Relayer => {
+                transaction_strategies.push(TransactionStrategy::Relayer(RelayerApi::new(
+                    client.clone(),
+                    args.relayer_url.clone().expect("missing relayer url"),
+                    args.relayer_api_key.clone(),
+                    ERC20::at(&web3, native_token_contract.address()),
+                    args.relayer_fee_recipient
+                        .expect("missing relayer fee recipient"),
+                    args.relayer_fee_factor,
+                )))
+            }
             TransactionStrategyArg::DryRun => {
                 transaction_strategies.push(TransactionStrategy::DryRun)
             }
@@ -397,6 +498,7 @@ async fn main() {
     let solution_submitter = SolutionSubmitter {
         web3: web3.clone(),
         contract: settlement_contract.clone(),
+        contract_version: args.settlement_contract_version,
         gas_price_estimator: gas_price_estimator.clone(),
         target_confirm_time: args.target_confirm_time,
         max_confirm_time: args.max_submission_seconds,
@@ -411,11 +513,37 @@ async fn main() {
         client.clone(),
         args.shared.solver_competition_auth,
     );
-    let tenderly = args
-        .tenderly_url
-        .zip(args.tenderly_api_key)
-        .and_then(|(url, api_key)| TenderlyApi::new(url, client.clone(), &api_key).ok());
+    let simulator = create_simulator(
+        args.simulation_backend,
+        &web3,
+        &client,
+        args.tenderly_url,
+        args.tenderly_api_key.as_deref(),
+    );
+    let revm_simulator = create_revm_simulator(args.simulation_backend, &web3);
+
+    let solver_balances = Arc::new(SolverBalances::new(
+        web3.clone(),
+        &solver,
+        args.solver_balance_alert_threshold,
+        args.solver_balance_required_settlement_gas,
+        metrics.clone(),
+    ));
+
+    let balance_diff_simulator = args
+        .tracing_node_url
+        .as_ref()
+        .map(|url| BalanceDiffSimulator {
+            web3: shared::web3(&client, url, "trace"),
+            settlement_contract: settlement_contract.address(),
+            contract_version: args.settlement_contract_version,
+            native_token: native_token_contract.address(),
+        });
 
+    let mut buffer_usage_limits = BufferUsageLimits::new(args.buffer_usage_limits);
+    if let Some(database) = database {
+        buffer_usage_limits = buffer_usage_limits.with_database(database);
+    }
     let mut driver = Driver::new(
         settlement_contract,
         liquidity_collector,
@@ -439,8 +567,25 @@ async fn main() {
         args.max_settlement_price_deviation
             .map(|max_price_deviation| Ratio::from_float(max_price_deviation).unwrap()),
         args.token_list_restriction_for_price_checks.into(),
-        tenderly,
+        simulator,
+        revm_simulator,
+        Arc::new(buffer_usage_limits),
+        Some(solver_balances.clone()),
     );
+    if let Some(realized_gas) = realized_gas {
+        driver = driver.with_realized_gas(realized_gas);
+    }
+    if let Some(window) = args.auction_amendment_window {
+        driver = driver.with_auction_amendment_window(window);
+    }
+    if let Some(vault) = &vault_contract {
+        driver = driver.with_internal_balance_vault(vault.address());
+    }
+    if let Some(simulator) = balance_diff_simulator {
+        driver = driver.with_balance_diff_simulator(simulator);
+    }
+    driver =
+        driver.with_solver_demotions(args.solver_demotion_threshold, args.solver_demotion_period);
 
     let maintainer = ServiceMaintenance {
         maintainers: pool_caches
@@ -448,10 +593,28 @@ async fn main() {
             .map(|(_, cache)| cache as Arc<dyn Maintaining>)
             .chain(balancer_pool_maintainer)
             .chain(uniswap_v3_maintainer)
+            .chain([
+                solver_balances as Arc<dyn Maintaining>,
+                solver_teams as Arc<dyn Maintaining>,
+            ])
             .collect(),
     };
     tokio::task::spawn(maintainer.run_maintenance_on_new_block(current_block_stream));
 
+    if let Some(path) = args.reload_config_file.clone() {
+        let defaults = (
+            args.paraswap_slippage_bps,
+            args.zeroex_slippage_bps,
+            args.oneinch_slippage_bps,
+        );
+        let apply = move |overrides: Overrides| {
+            paraswap_slippage_bps.set(overrides.paraswap_slippage_bps.unwrap_or(defaults.0));
+            zeroex_slippage_bps.set(overrides.zeroex_slippage_bps.unwrap_or(defaults.1));
+            oneinch_slippage_bps.set(overrides.oneinch_slippage_bps.unwrap_or(defaults.2));
+        };
+        tokio::task::spawn(reload_on_sighup(path, apply));
+    }
+
     serve_metrics(metrics, ([0, 0, 0, 0], args.metrics_port).into());
     driver.run_forever().await;
 }
@@ -6,9 +6,10 @@ use anyhow::Result;
 use ethcontract::U256;
 use model::order::Order;
 use prometheus::{
-    Gauge, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, Opts,
+    Gauge, GaugeVec, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec,
+    IntGaugeVec, Opts,
 };
-use shared::metrics::LivenessChecking;
+use shared::{http_solver::validation::SchemaViolation, metrics::LivenessChecking};
 use std::{
     convert::TryInto,
     sync::Mutex,
@@ -46,6 +47,8 @@ pub enum SettlementSubmissionOutcome {
     Cancel,
     /// Submission disabled
     Disabled,
+    /// Submission aborted because the orders were settled by another transaction
+    AlreadySettled,
     /// General message for failures (for example, failing to connect to client node)
     Failed,
 }
@@ -70,6 +73,16 @@ pub trait SolverMetrics: Send + Sync {
     fn complete_runloop_until_transaction(&self, duration: Duration);
     fn transaction_submission(&self, duration: Duration);
     fn transaction_gas_price(&self, gas_price: U256);
+    fn settlement_calldata_saved_bytes(&self, bytes: usize);
+    /// Reports how the simulation of the winning settlement changed between ranking and
+    /// submission. `gas_delta` is `None` if the settlement reverts at submission time.
+    fn settlement_simulation_drift(&self, blocks: u64, gas_delta: Option<f64>);
+    /// Reports the ETH balance of a solver's account and whether it is below the alert threshold.
+    fn solver_account_balance(&self, solver: &str, balance: f64, below_threshold: bool);
+    fn solver_excluded_for_insufficient_balance(&self, solver: &str);
+    /// Reports the ways in which a response of a solver violated the solution schema.
+    fn solver_schema_violations(&self, solver: &str, violations: &[SchemaViolation]);
+    fn solver_excluded_for_schema_violations(&self, solver: &str);
 }
 
 // TODO add labeled interaction counter once we support more than one interaction
@@ -90,6 +103,15 @@ pub struct Metrics {
     complete_runloop_until_transaction: Histogram,
     transaction_submission: Histogram,
     transaction_gas_price_gwei: Gauge,
+    settlement_calldata_saved_bytes: IntCounter,
+    settlement_simulation_drift: IntCounterVec,
+    settlement_simulation_drift_blocks: Histogram,
+    settlement_simulation_drift_gas: HistogramVec,
+    solver_account_balance: GaugeVec,
+    solver_account_balance_below_threshold: IntGaugeVec,
+    solver_insufficient_balance_exclusions: IntCounterVec,
+    solver_schema_violations: IntCounterVec,
+    solver_schema_violation_exclusions: IntCounterVec,
 }
 
 impl Metrics {
@@ -213,6 +235,85 @@ impl Metrics {
         let transaction_gas_price_gwei = Gauge::with_opts(opts).unwrap();
         registry.register(Box::new(transaction_gas_price_gwei.clone()))?;
 
+        let settlement_calldata_saved_bytes = IntCounter::new(
+            "settlement_calldata_saved_bytes",
+            "Calldata bytes saved by optimizing the encoding of submitted settlements",
+        )?;
+        registry.register(Box::new(settlement_calldata_saved_bytes.clone()))?;
+
+        let settlement_simulation_drift = IntCounterVec::new(
+            Opts::new(
+                "settlement_simulation_drift",
+                "Outcome of simulating the winning settlement again right before submission",
+            ),
+            &["outcome"],
+        )?;
+        registry.register(Box::new(settlement_simulation_drift.clone()))?;
+
+        let settlement_simulation_drift_blocks = Histogram::with_opts(
+            HistogramOpts::new(
+                "settlement_simulation_drift_blocks",
+                "Blocks between the ranking and the submission simulation of the winning settlement",
+            )
+            .buckets(prometheus::linear_buckets(0., 1., 10)?),
+        )?;
+        registry.register(Box::new(settlement_simulation_drift_blocks.clone()))?;
+
+        let settlement_simulation_drift_gas = HistogramVec::new(
+            HistogramOpts::new(
+                "settlement_simulation_drift_gas",
+                "Change of the gas used by the winning settlement between ranking and submission",
+            )
+            .buckets(prometheus::exponential_buckets(100., 2., 12)?),
+            &["sign"],
+        )?;
+        registry.register(Box::new(settlement_simulation_drift_gas.clone()))?;
+
+        let solver_account_balance = GaugeVec::new(
+            Opts::new(
+                "solver_account_balance_eth",
+                "ETH balance of the account a solver submits settlements with",
+            ),
+            &["solver"],
+        )?;
+        registry.register(Box::new(solver_account_balance.clone()))?;
+
+        let solver_account_balance_below_threshold = IntGaugeVec::new(
+            Opts::new(
+                "solver_account_balance_below_threshold",
+                "Whether the ETH balance of a solver's account is below the alert threshold",
+            ),
+            &["solver"],
+        )?;
+        registry.register(Box::new(solver_account_balance_below_threshold.clone()))?;
+
+        let solver_insufficient_balance_exclusions = IntCounterVec::new(
+            Opts::new(
+                "solver_insufficient_balance_exclusions",
+                "Auctions a solver did not participate in because its account could not pay for a settlement",
+            ),
+            &["solver"],
+        )?;
+        registry.register(Box::new(solver_insufficient_balance_exclusions.clone()))?;
+
+        let solver_schema_violations = IntCounterVec::new(
+            Opts::new(
+                "solver_schema_violations",
+                "Violations of the solution schema in solver responses by kind",
+            ),
+            &["solver", "kind"],
+        )?;
+        registry.register(Box::new(solver_schema_violations.clone()))?;
+
+        let solver_schema_violation_exclusions = IntCounterVec::new(
+            Opts::new(
+                "solver_schema_violation_exclusions",
+                "Auctions a solver did not participate in because it was demoted for returning malformed solutions",
+            ),
+            &["solver"],
+        )?;
+        registry.register(Box::new(solver_schema_violation_exclusions.clone()))?;
+
         Ok(Self {
             trade_counter,
             order_settlement_time,
@@ -230,6 +331,15 @@ impl Metrics {
             transaction_submission,
             transaction_gas_price_gwei,
             settlement_access_list_saved_gas,
+            settlement_calldata_saved_bytes,
+            settlement_simulation_drift,
+            settlement_simulation_drift_blocks,
+            settlement_simulation_drift_gas,
+            solver_account_balance,
+            solver_account_balance_below_threshold,
+            solver_insufficient_balance_exclusions,
+            solver_schema_violations,
+            solver_schema_violation_exclusions,
         })
     }
 }
@@ -276,7 +386,7 @@ impl SolverMetrics for Metrics {
     fn order_settled(&self, order: &Order, solver: &str) {
         let time_to_settlement =
             chrono::offset::Utc::now().signed_duration_since(order.metadata.creation_date);
-        let order_type = match order.metadata.is_liquidity_order {
+        let order_type = match order.metadata.class.is_liquidity_order() {
             true => "liquidity_order",
             false => "user_order",
         };
@@ -341,6 +451,7 @@ impl SolverMetrics for Metrics {
             SettlementSubmissionOutcome::Cancel => "cancel",
             SettlementSubmissionOutcome::SimulationRevert => "simulationrevert",
             SettlementSubmissionOutcome::Disabled => "disabled",
+            SettlementSubmissionOutcome::AlreadySettled => "alreadysettled",
             SettlementSubmissionOutcome::Failed => "failed",
         };
         self.settlement_submissions
@@ -383,6 +494,64 @@ impl SolverMetrics for Metrics {
             .set(gas_price.to_f64_lossy() / 1e9)
     }
 
+    fn settlement_calldata_saved_bytes(&self, bytes: usize) {
+        self.settlement_calldata_saved_bytes.inc_by(bytes as u64);
+    }
+
+    fn settlement_simulation_drift(&self, blocks: u64, gas_delta: Option<f64>) {
+        self.settlement_simulation_drift_blocks
+            .observe(blocks as f64);
+        let gas_delta = match gas_delta {
+            Some(gas_delta) => gas_delta,
+            None => {
+                self.settlement_simulation_drift
+                    .with_label_values(&["revert"])
+                    .inc();
+                return;
+            }
+        };
+        self.settlement_simulation_drift
+            .with_label_values(&["success"])
+            .inc();
+        let sign = if gas_delta.is_sign_negative() {
+            "negative"
+        } else {
+            "positive"
+        };
+        self.settlement_simulation_drift_gas
+            .with_label_values(&[sign])
+            .observe(gas_delta.abs());
+    }
+
+    fn solver_account_balance(&self, solver: &str, balance: f64, below_threshold: bool) {
+        self.solver_account_balance
+            .with_label_values(&[solver])
+            .set(balance);
+        self.solver_account_balance_below_threshold
+            .with_label_values(&[solver])
+            .set(below_threshold as _);
+    }
+
+    fn solver_excluded_for_insufficient_balance(&self, solver: &str) {
+        self.solver_insufficient_balance_exclusions
+            .with_label_values(&[solver])
+            .inc();
+    }
+
+    fn solver_schema_violations(&self, solver: &str, violations: &[SchemaViolation]) {
+        for violation in violations {
+            self.solver_schema_violations
+                .with_label_values(&[solver, violation.kind()])
+                .inc();
+        }
+    }
+
+    fn solver_excluded_for_schema_violations(&self, solver: &str) {
+        self.solver_schema_violation_exclusions
+            .with_label_values(&[solver])
+            .inc();
+    }
+
     fn settlement_revertable_status(&self, status: Revertable, solver: &str) {
         let result = match status {
             Revertable::NoRisk => "no_risk",
@@ -429,6 +598,12 @@ impl SolverMetrics for NoopMetrics {
     fn complete_runloop_until_transaction(&self, _: Duration) {}
     fn transaction_submission(&self, _: Duration) {}
     fn transaction_gas_price(&self, _: U256) {}
+    fn settlement_calldata_saved_bytes(&self, _: usize) {}
+    fn settlement_simulation_drift(&self, _: u64, _: Option<f64>) {}
+    fn solver_account_balance(&self, _: &str, _: f64, _: bool) {}
+    fn solver_excluded_for_insufficient_balance(&self, _: &str) {}
+    fn solver_schema_violations(&self, _: &str, _: &[SchemaViolation]) {}
+    fn solver_excluded_for_schema_violations(&self, _: &str) {}
 }
 
 #[cfg(test)]
@@ -444,5 +619,9 @@ mod tests {
         metrics.settlement_simulation_failed("test");
         metrics.settlement_submitted(SettlementSubmissionOutcome::Success, "test");
         metrics.orders_matched_but_not_settled(20);
+        metrics.settlement_simulation_drift(2, Some(-1_000.));
+        metrics.settlement_simulation_drift(1, None);
+        metrics.solver_account_balance("test", 0.5, true);
+        metrics.solver_excluded_for_insufficient_balance("test");
     }
 }
@@ -1,4 +1,6 @@
+pub mod calldata;
 pub mod external_prices;
+pub mod gas_refunds;
 mod settlement_encoder;
 
 use self::external_prices::ExternalPrices;
@@ -12,7 +14,9 @@ use itertools::Itertools;
 use model::order::{Order, OrderKind};
 use num::{rational::Ratio, BigInt, BigRational, One, Signed, Zero};
 use primitive_types::{H160, U256};
-use shared::conversions::U256Ext as _;
+use shared::{
+    conversions::U256Ext as _, realized_gas::InteractionClass, token_amount::TokenAmount,
+};
 use std::{
     collections::{HashMap, HashSet},
     ops::{Mul, Sub},
@@ -151,8 +155,15 @@ impl Trade {
         self.compute_fee_execution(self.scaled_unsubsidized_fee)
     }
 
+    /// Returns the executed part of the signed fee that goes to the solver, which excludes the
+    /// protocol fee.
     pub fn executed_unscaled_subsidized_fee(&self) -> Option<U256> {
-        self.compute_fee_execution(self.order.data.fee_amount)
+        self.compute_fee_execution(
+            self.order
+                .data
+                .fee_amount
+                .saturating_sub(self.order.metadata.protocol_fee_amount),
+        )
     }
 
     fn compute_fee_execution(&self, fee_amount: U256) -> Option<U256> {
@@ -232,6 +243,12 @@ pub trait Interaction: std::fmt::Debug + Send + Sync {
     // never fail. Then the question becomes whether interactions should be allowed to fail encoding
     // for other reasons.
     fn encode(&self) -> Vec<EncodedInteraction>;
+
+    /// The class whose realized gas the interaction gets counted towards. `None` for interactions
+    /// whose gas we don't keep track of.
+    fn gas_class(&self) -> Option<InteractionClass> {
+        None
+    }
 }
 
 impl Interaction for EncodedInteraction {
@@ -295,11 +312,34 @@ impl Settlement {
             .encode(execution, &mut self.encoder)
     }
 
+    /// Returns a copy of the settlement without any interactions. All token amounts that are no
+    /// longer provided by interactions are paid out of the internal buffers.
     pub fn without_onchain_liquidity(&self) -> Self {
-        let encoder = self.encoder.without_onchain_liquidity();
+        let mut encoder = self.encoder.without_onchain_liquidity();
+        encoder.set_internal_buffer_usage(self.trade_buffer_deficits());
         Self { encoder }
     }
 
+    /// Computes for every token how much more the trades pay out than they put in. Without
+    /// interactions these amounts have to come from the internal buffers.
+    fn trade_buffer_deficits(&self) -> HashMap<H160, U256> {
+        let mut flows = HashMap::<H160, (U256, U256)>::new();
+        for (_, execution) in self.executed_trades() {
+            let sell_flow = flows.entry(execution.sell_token).or_default();
+            sell_flow.0 = sell_flow
+                .0
+                .saturating_add(execution.sell_amount)
+                .saturating_add(execution.fee_amount);
+            let buy_flow = flows.entry(execution.buy_token).or_default();
+            buy_flow.1 = buy_flow.1.saturating_add(execution.buy_amount);
+        }
+        flows
+            .into_iter()
+            .filter(|(_, (inflow, outflow))| outflow > inflow)
+            .map(|(token, (inflow, outflow))| (token, outflow - inflow))
+            .collect()
+    }
+
     #[cfg(test)]
     pub fn with_trades(
         clearing_prices: HashMap<H160, U256>,
@@ -389,9 +429,26 @@ impl Settlement {
         max_settlement_price_deviation: &Ratio<BigInt>,
         tokens_to_satisfy_price_test: &PriceCheckTokens,
     ) -> bool {
+        self.price_violation(
+            solver_name,
+            external_prices,
+            max_settlement_price_deviation,
+            tokens_to_satisfy_price_test,
+        )
+        .is_none()
+    }
+
+    // Returns the first token pair whose clearing prices deviate more than max_settlement_price_deviation from the auction prices.
+    pub fn price_violation(
+        &self,
+        solver_name: &str,
+        external_prices: &ExternalPrices,
+        max_settlement_price_deviation: &Ratio<BigInt>,
+        tokens_to_satisfy_price_test: &PriceCheckTokens,
+    ) -> Option<(H160, H160)> {
         if matches!(tokens_to_satisfy_price_test, PriceCheckTokens::Tokens(token_list) if token_list.is_empty())
         {
-            return true;
+            return None;
         }
         // The following check is quadratic in run-time, although a similar check with linear run-time would also be possible.
         // For the linear implementation, one would have to find a unique scaling factor that scales the external prices into
@@ -403,7 +460,7 @@ impl Settlement {
             .clearing_prices()
             .iter()
             .combinations(2)
-            .all(|clearing_price_vector_combination| {
+            .find_map(|clearing_price_vector_combination| {
                 let (sell_token, sell_price) = clearing_price_vector_combination[0];
                 let clearing_price_sell_token = sell_price.to_big_rational();
                 let (buy_token, buy_price) = clearing_price_vector_combination[1];
@@ -411,16 +468,10 @@ impl Settlement {
 
                 if matches!(tokens_to_satisfy_price_test, PriceCheckTokens::Tokens(token_list) if (!token_list.contains(sell_token)) || !token_list.contains(buy_token))
                 {
-                    return true;
+                    return None;
                 }
-                let external_price_sell_token = match external_prices.price(sell_token) {
-                    Some(price) => price,
-                    None => return true,
-                };
-                let external_price_buy_token = match external_prices.price(buy_token) {
-                    Some(price) => price,
-                    None => return true,
-                };
+                let external_price_sell_token = external_prices.price(sell_token)?;
+                let external_price_buy_token = external_prices.price(buy_token)?;
                 // Condition to check: Deviation of clearing prices is bigger than max_settlement_price deviation
                 //
                 // |clearing_price_sell_token / clearing_price_buy_token - external_price_sell_token / external_price_buy_token)|
@@ -438,14 +489,15 @@ impl Settlement {
                     .sub(&external_price_sell_token.mul(&clearing_price_buy_token)).abs()
                     .lt(&max_settlement_price_deviation
                     .mul(&external_price_buy_token.mul(&clearing_price_sell_token)));
-                if !price_check_result {
-                    tracing::debug!(
-                        token_pair =% format!("{:?}-{:?}", sell_token, buy_token),
-                        %solver_name, settlement =? self,
-                        "price violation",
-                    );
+                if price_check_result {
+                    return None;
                 }
-                price_check_result
+                tracing::debug!(
+                    token_pair =% format!("{:?}-{:?}", sell_token, buy_token),
+                    %solver_name, settlement =? self,
+                    "price violation",
+                );
+                Some((*sell_token, *buy_token))
             })
     }
 
@@ -455,13 +507,11 @@ impl Settlement {
             .order_trades()
             .iter()
             .filter_map(|order_trade| {
-                external_prices.try_get_native_amount(
+                let fee = TokenAmount::new(
                     order_trade.trade.order.data.sell_token,
-                    order_trade
-                        .trade
-                        .executed_scaled_unsubsidized_fee()?
-                        .to_big_rational(),
-                )
+                    order_trade.trade.executed_scaled_unsubsidized_fee()?,
+                );
+                external_prices.try_get_native_value(&fee)
             })
             .sum()
     }
@@ -472,13 +522,24 @@ impl Settlement {
             .order_trades()
             .iter()
             .filter_map(|order_trade| {
-                external_prices.try_get_native_amount(
+                let fee = TokenAmount::new(
                     order_trade.trade.order.data.sell_token,
-                    order_trade
-                        .trade
-                        .executed_unscaled_subsidized_fee()?
-                        .to_big_rational(),
-                )
+                    order_trade.trade.executed_unscaled_subsidized_fee()?,
+                );
+                external_prices.try_get_native_value(&fee)
+            })
+            .sum()
+    }
+
+    // Computes the total fees of the flash loans the settlement takes (in wei ETH). Returns `None`
+    // if a fee is in a token without a price because leaving it out would make the settlement
+    // look cheaper than it is.
+    pub fn total_flash_loan_fees(&self, external_prices: &ExternalPrices) -> Option<BigRational> {
+        self.encoder
+            .flash_loan_fees()
+            .iter()
+            .map(|(token, amount)| {
+                external_prices.try_get_native_value(&TokenAmount::new(*token, *amount))
             })
             .sum()
     }
@@ -500,7 +561,9 @@ impl Settlement {
 
 impl From<Settlement> for EncodedSettlement {
     fn from(settlement: Settlement) -> Self {
-        settlement.encoder.finish()
+        let mut encoded = settlement.encoder.finish();
+        calldata::optimize(&mut encoded);
+        encoded
     }
 }
 
@@ -575,7 +638,7 @@ pub mod tests {
     use super::*;
     use crate::{liquidity::SettlementHandling, settlement::external_prices::externalprices};
     use maplit::hashmap;
-    use model::order::{OrderData, OrderKind};
+    use model::order::{OrderData, OrderKind, OrderMetadata};
     use num::FromPrimitive;
     use shared::addr;
 
@@ -1284,6 +1347,10 @@ pub mod tests {
 
         // Fee in sell tokens
         assert_eq!(trade0.trade.executed_fee().unwrap(), 1.into());
+        assert_eq!(
+            trade0.trade.executed_unscaled_subsidized_fee().unwrap(),
+            1.into()
+        );
         assert_eq!(
             trade0.trade.executed_scaled_unsubsidized_fee().unwrap(),
             5.into()
@@ -1302,6 +1369,30 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn unscaled_subsidized_fee_excludes_protocol_fee() {
+        let trade = Trade {
+            order: Order {
+                data: OrderData {
+                    sell_amount: 10.into(),
+                    fee_amount: 4.into(),
+                    kind: OrderKind::Sell,
+                    ..Default::default()
+                },
+                metadata: OrderMetadata {
+                    protocol_fee_amount: 2.into(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            executed_amount: 5.into(),
+            ..Default::default()
+        };
+
+        assert_eq!(trade.executed_fee().unwrap(), 2.into());
+        assert_eq!(trade.executed_unscaled_subsidized_fee().unwrap(), 1.into());
+    }
+
     #[test]
     fn fees_excluded_for_pmm_orders() {
         let token0 = H160([0; 20]);
@@ -1455,4 +1546,74 @@ pub mod tests {
         // to the user!
         assert!(objective_value(&amm, 657196) > objective_value(&pmm, 405053));
     }
+
+    #[test]
+    fn settlement_without_onchain_liquidity_uses_buffers_for_trade_deficits() {
+        let token0 = H160::from_low_u64_be(0);
+        let token1 = H160::from_low_u64_be(1);
+
+        let trade = |sell_token, buy_token, executed_amount: u64| OrderTrade {
+            trade: Trade {
+                order: Order {
+                    data: OrderData {
+                        sell_token,
+                        buy_token,
+                        sell_amount: 10.into(),
+                        buy_amount: 9.into(),
+                        kind: OrderKind::Sell,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                executed_amount: executed_amount.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let settlement = test_settlement(
+            hashmap! {token0 => 1.into(), token1 => 1.into()},
+            vec![trade(token0, token1, 10), trade(token1, token0, 4)],
+            vec![],
+        );
+        assert_eq!(
+            settlement
+                .without_onchain_liquidity()
+                .encoder
+                .internal_buffer_usage(),
+            &hashmap! {token1 => 6.into()}
+        );
+    }
+
+    #[test]
+    fn flash_loan_fees_require_prices() {
+        let token0 = H160::from_low_u64_be(0);
+        let token1 = H160::from_low_u64_be(1);
+        let token2 = H160::from_low_u64_be(2);
+        let mut settlement = test_settlement(Default::default(), vec![], vec![]);
+        let external_prices = externalprices! { native_token: token0, token1 => r(2) };
+        assert_eq!(
+            settlement.total_flash_loan_fees(&external_prices),
+            Some(r(0))
+        );
+
+        settlement
+            .encoder
+            .add_flash_loan_fee(token0, 3.into())
+            .unwrap();
+        settlement
+            .encoder
+            .add_flash_loan_fee(token1, 5.into())
+            .unwrap();
+        assert_eq!(
+            settlement.total_flash_loan_fees(&external_prices),
+            Some(r(13))
+        );
+
+        settlement
+            .encoder
+            .add_flash_loan_fee(token2, 1.into())
+            .unwrap();
+        assert_eq!(settlement.total_flash_loan_fees(&external_prices), None);
+    }
 }
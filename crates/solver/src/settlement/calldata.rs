@@ -0,0 +1,127 @@
+//! Minimizes the calldata of encoded settlements. On L2s the calldata dominates the cost of a
+//! settlement transaction so every word saved matters.
+//!
+//! Liquidity orders get their own clearing price entry for their buy token even if an entry with
+//! the same token and price already exists, and entries can end up without any trade referencing
+//! them. Such entries are removed and the trade indices updated accordingly. Each removed entry
+//! saves one word in the `tokens` and one in the `clearingPrices` array.
+//!
+//! Other changes to the encoding don't make it smaller: all ABI encoded fields of the settlement
+//! are fixed size words, dynamic fields like signatures and interaction calldata can't be
+//! shortened without changing their meaning, and the order of trades and interactions doesn't
+//! affect the size.
+
+use super::Settlement;
+use crate::encoding::EncodedSettlement;
+use primitive_types::{H160, U256};
+use std::collections::{HashMap, HashSet};
+
+/// The ABI encoded size of one entry of the `tokens` and `clearingPrices` arrays.
+const BYTES_PER_CLEARING_PRICE: usize = 2 * 32;
+
+/// Deduplicates identical clearing price entries and removes entries no trade refers to. Returns
+/// the number of calldata bytes saved.
+pub fn optimize(settlement: &mut EncodedSettlement) -> usize {
+    let referenced = settlement
+        .trades
+        .iter()
+        .flat_map(|trade| [trade.0.as_usize(), trade.1.as_usize()])
+        .collect::<HashSet<_>>();
+
+    // Keeping the first occurrence of every entry preserves the layout of uniform clearing prices
+    // followed by liquidity order prices.
+    let mut entries: Vec<(H160, U256)> = Vec::new();
+    let mut new_indices = HashMap::new();
+    let mut remapped = vec![None; settlement.tokens.len()];
+    for (index, entry) in settlement
+        .tokens
+        .iter()
+        .copied()
+        .zip(settlement.clearing_prices.iter().copied())
+        .enumerate()
+    {
+        if !referenced.contains(&index) {
+            continue;
+        }
+        let new_index = *new_indices.entry(entry).or_insert_with(|| {
+            entries.push(entry);
+            entries.len() - 1
+        });
+        remapped[index] = Some(new_index);
+    }
+
+    for trade in &mut settlement.trades {
+        trade.0 = remapped[trade.0.as_usize()]
+            .expect("trade refers to missing token")
+            .into();
+        trade.1 = remapped[trade.1.as_usize()]
+            .expect("trade refers to missing token")
+            .into();
+    }
+    let removed = settlement.tokens.len() - entries.len();
+    (settlement.tokens, settlement.clearing_prices) = entries.into_iter().unzip();
+    removed * BYTES_PER_CLEARING_PRICE
+}
+
+/// The number of calldata bytes `optimize` saves for the settlement.
+pub fn savings(settlement: &Settlement) -> usize {
+    optimize(&mut settlement.encoder.clone().finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{encoding::SettlementContractVersion, settlement_simulation::call_data};
+    use ethcontract::Bytes;
+
+    fn trade(sell_token_index: u64, buy_token_index: u64) -> crate::encoding::EncodedTrade {
+        (
+            sell_token_index.into(),
+            buy_token_index.into(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            Bytes(Default::default()),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            Bytes(Default::default()),
+        )
+    }
+
+    #[test]
+    fn deduplicates_clearing_prices() {
+        let token = |byte| H160([byte; 20]);
+        let mut settlement = EncodedSettlement {
+            tokens: vec![token(1), token(2), token(3), token(2), token(2)],
+            clearing_prices: vec![1.into(), 2.into(), 3.into(), 2.into(), 4.into()],
+            trades: vec![trade(0, 1), trade(0, 3), trade(0, 4)],
+            ..Default::default()
+        };
+        let before = call_data(SettlementContractVersion::V1, settlement.clone()).len();
+
+        let saved = optimize(&mut settlement);
+        assert_eq!(settlement.tokens, [token(1), token(2), token(2)]);
+        assert_eq!(settlement.clearing_prices, [1.into(), 2.into(), 4.into()]);
+        assert_eq!(settlement.trades, [trade(0, 1), trade(0, 1), trade(0, 2)]);
+        assert_eq!(saved, 2 * BYTES_PER_CLEARING_PRICE);
+        assert_eq!(
+            before - call_data(SettlementContractVersion::V1, settlement).len(),
+            saved
+        );
+    }
+
+    #[test]
+    fn keeps_minimal_settlements() {
+        let mut settlement = EncodedSettlement {
+            tokens: vec![H160([1; 20]), H160([2; 20])],
+            clearing_prices: vec![1.into(), 2.into()],
+            trades: vec![trade(1, 0)],
+            ..Default::default()
+        };
+        let original = settlement.clone();
+        assert_eq!(optimize(&mut settlement), 0);
+        assert_eq!(settlement, original);
+    }
+}
@@ -10,7 +10,7 @@ use ethcontract::{H160, U256};
 use lazy_static::lazy_static;
 use model::order::BUY_ETH_ADDRESS;
 use num::{BigInt, BigRational, One as _, ToPrimitive as _};
-use shared::conversions::U256Ext as _;
+use shared::{conversions::U256Ext as _, token_amount::TokenAmount};
 use std::collections::{BTreeMap, HashMap};
 
 /// A collection of external prices used for converting token amounts to native
@@ -82,6 +82,22 @@ impl ExternalPrices {
         Some(self.0.get(&token)? * amount)
     }
 
+    /// Converts a token amount into its native asset equivalent.
+    ///
+    /// # Panic
+    ///
+    /// This method panics if the token does not have a price.
+    pub fn get_native_value(&self, amount: &TokenAmount) -> BigRational {
+        self.try_get_native_value(amount)
+            .unwrap_or_else(|| panic!("missing price for {}", amount.token))
+    }
+
+    /// Converts a token amount into its native asset equivalent or returns `None` if the token
+    /// does not have a price.
+    pub fn try_get_native_value(&self, amount: &TokenAmount) -> Option<BigRational> {
+        Some(amount.native_value(self.0.get(&amount.token)?))
+    }
+
     /// Converts a set of external prices into prices for the HTTP solver.
     ///
     /// Specifically the HTTP solver expects prices to be in `f64` and there not
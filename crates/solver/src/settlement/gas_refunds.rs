@@ -0,0 +1,128 @@
+//! Estimates the gas that gets refunded to a settlement transaction for clearing storage.
+//!
+//! Gas estimates are the gas limit a transaction needs to execute. Refunds only get credited at
+//! the end of the execution so settlements that clear storage, for example by resetting
+//! allowances or freeing the storage of filled orders, pay less than their gas estimate. Without
+//! accounting for this, such settlements look more expensive than they are compared to settlements
+//! that don't clear storage.
+//!
+//! Access list savings don't need to be modeled here because settlements get simulated with their
+//! access list so the gas estimate already includes them.
+
+use crate::encoding::{EncodedInteraction, EncodedSettlement};
+use contracts::GPv2Settlement;
+use ethcontract::common::abi::Token;
+use hex_literal::hex;
+use primitive_types::{H160, U256};
+
+/// The refund for setting a non zero storage slot to zero since EIP-3529.
+pub const STORAGE_CLEAR_REFUND: u64 = 4_800;
+
+/// Refunds are capped to this fraction of the gas used since EIP-3529.
+const MAX_REFUND_QUOTIENT: u64 = 5;
+
+const ERC20_APPROVE: [u8; 4] = hex!("095ea7b3");
+
+/// Returns the gas the settlement's interactions likely get refunded. Only storage clears whose
+/// slots are known to be set are counted: allowance resets, which solvers do after interacting
+/// with a contract, and freeing the storage of orders through the settlement contract.
+pub fn estimate(settlement: &EncodedSettlement, settlement_contract: H160) -> U256 {
+    let storage_clears: u64 = settlement
+        .interactions
+        .iter()
+        .flatten()
+        .map(|interaction| storage_clears(interaction, settlement_contract))
+        .sum();
+    U256::from(storage_clears * STORAGE_CLEAR_REFUND)
+}
+
+/// The gas the transaction pays for after the refund has been credited.
+pub fn effective_gas(gas_estimate: U256, refund: U256) -> U256 {
+    gas_estimate - refund.min(gas_estimate / MAX_REFUND_QUOTIENT)
+}
+
+fn storage_clears((target, _, calldata): &EncodedInteraction, settlement_contract: H160) -> u64 {
+    let calldata = &calldata.0;
+    let selector = match calldata.get(..4) {
+        Some(selector) => selector,
+        None => return 0,
+    };
+    if selector == ERC20_APPROVE {
+        // approve(address spender, uint256 amount) with a zero amount.
+        let is_reset = calldata.len() == 68 && calldata[36..].iter().all(|byte| *byte == 0);
+        return is_reset as u64;
+    }
+    if *target == settlement_contract {
+        return freed_orders(calldata);
+    }
+    0
+}
+
+/// Counts the orders whose storage a call to the settlement contract frees.
+fn freed_orders(calldata: &[u8]) -> u64 {
+    let abi = &GPv2Settlement::raw_contract().abi;
+    ["freeFilledAmountStorage", "freePreSignatureStorage"]
+        .into_iter()
+        .filter_map(|name| abi.function(name).ok())
+        .find(|function| function.short_signature() == calldata[..4])
+        .and_then(|function| function.decode_input(&calldata[4..]).ok())
+        .and_then(|tokens| match tokens.as_slice() {
+            [Token::Array(order_uids)] => Some(order_uids.len() as u64),
+            _ => None,
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interactions::Erc20ApproveInteraction;
+    use contracts::ERC20;
+    use ethcontract::Bytes;
+    use shared::dummy_contract;
+
+    #[test]
+    fn counts_storage_clears() {
+        let settlement_contract = H160([0x01; 20]);
+        let approve = |amount: u64| {
+            Erc20ApproveInteraction {
+                token: dummy_contract!(ERC20, [0x02; 20]),
+                spender: H160([0x03; 20]),
+                amount: amount.into(),
+            }
+            .as_encoded()
+        };
+        let free_filled_amounts = |target, order_uids: usize| {
+            let function = GPv2Settlement::raw_contract()
+                .abi
+                .function("freeFilledAmountStorage")
+                .unwrap();
+            let calldata = function
+                .encode_input(&[Token::Array(vec![Token::Bytes(vec![0; 56]); order_uids])])
+                .unwrap();
+            (target, U256::zero(), Bytes(calldata))
+        };
+        let settlement = EncodedSettlement {
+            interactions: [
+                vec![approve(1), free_filled_amounts(settlement_contract, 2)],
+                vec![approve(0)],
+                vec![
+                    free_filled_amounts(H160([0x04; 20]), 3),
+                    (H160([0x05; 20]), U256::zero(), Bytes(vec![0x06])),
+                ],
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            estimate(&settlement, settlement_contract),
+            U256::from(3 * STORAGE_CLEAR_REFUND)
+        );
+    }
+
+    #[test]
+    fn caps_refunds() {
+        assert_eq!(effective_gas(100_000.into(), 4_800.into()), 95_200.into());
+        assert_eq!(effective_gas(100_000.into(), 48_000.into()), 80_000.into());
+        assert_eq!(effective_gas(0.into(), 4_800.into()), 0.into());
+    }
+}
@@ -1,15 +1,15 @@
 use super::{ExternalPrices, Interaction, LiquidityOrderTrade, OrderTrade, Trade, TradeExecution};
 use crate::{
     encoding::{EncodedSettlement, EncodedTrade},
-    interactions::UnwrapWethInteraction,
+    interactions::{allowances::Approval, UnwrapWethInteraction},
     settlement::trade_surplus_in_native_token,
 };
 use anyhow::{bail, ensure, Context as _, Result};
-use model::order::{Order, OrderKind};
+use model::order::{BuyTokenDestination, Order, OrderKind};
 use num::{BigRational, One};
 use number_conversions::big_rational_to_u256;
 use primitive_types::{H160, U256};
-use shared::conversions::U256Ext;
+use shared::{conversions::U256Ext, realized_gas::SettlementGas};
 use std::{
     collections::{hash_map::Entry, HashMap, HashSet},
     iter,
@@ -44,7 +44,15 @@ pub struct SettlementEncoder {
     // would make the trait not be object safe which prevents using it through `dyn`.
     // TODO: Can we fix this in a better way?
     execution_plan: Vec<Arc<dyn Interaction>>,
+    // Approvals needed by the execution plan. They are encoded as a single batch before the
+    // execution plan and contain at most one approval per token and spender.
+    approvals: Vec<Approval>,
     unwraps: Vec<UnwrapWethInteraction>,
+    // Amounts paid out of the settlement contract's internal buffers instead of being provided by
+    // an interaction.
+    internal_buffer_usage: HashMap<H160, U256>,
+    // Fees of the flash loans taken by the execution plan's interactions.
+    flash_loan_fees: HashMap<H160, U256>,
 }
 
 impl Default for SettlementEncoder {
@@ -76,7 +84,10 @@ impl SettlementEncoder {
             order_trades: Vec::new(),
             liquidity_order_trades: Vec::new(),
             execution_plan: Vec::new(),
+            approvals: Vec::new(),
             unwraps: Vec::new(),
+            internal_buffer_usage: HashMap::new(),
+            flash_loan_fees: HashMap::new(),
         }
     }
 
@@ -100,7 +111,10 @@ impl SettlementEncoder {
             order_trades: self.order_trades.clone(),
             liquidity_order_trades: self.liquidity_order_trades.clone(),
             execution_plan: Vec::new(),
+            approvals: Vec::new(),
             unwraps: self.unwraps.clone(),
+            internal_buffer_usage: self.internal_buffer_usage.clone(),
+            flash_loan_fees: HashMap::new(),
         }
     }
 
@@ -120,6 +134,56 @@ impl SettlementEncoder {
         &self.execution_plan
     }
 
+    /// What the settlement consists of for attributing its realized gas to interactions. `None`
+    /// if it contains approvals or interactions whose gas isn't tracked.
+    pub fn settlement_gas(&self, gas_used: u64) -> Option<SettlementGas> {
+        if !self.approvals.is_empty() {
+            return None;
+        }
+        Some(SettlementGas {
+            gas_used,
+            trades: self.order_trades.len() + self.liquidity_order_trades.len(),
+            unwraps: self.unwraps.len(),
+            interactions: self
+                .execution_plan
+                .iter()
+                .map(|interaction| interaction.gas_class())
+                .collect::<Option<_>>()?,
+        })
+    }
+
+    pub fn approvals(&self) -> &[Approval] {
+        &self.approvals
+    }
+
+    pub fn internal_buffer_usage(&self) -> &HashMap<H160, U256> {
+        &self.internal_buffer_usage
+    }
+
+    pub fn set_internal_buffer_usage(&mut self, usage: HashMap<H160, U256>) {
+        self.internal_buffer_usage = usage;
+    }
+
+    /// Records that `amount` of `token` is paid out of the settlement contract's internal buffers.
+    pub fn add_internal_buffer_usage(&mut self, token: H160, amount: U256) -> Result<()> {
+        let usage = self.internal_buffer_usage.entry(token).or_default();
+        *usage = usage
+            .checked_add(amount)
+            .context("internal buffer usage overflow")?;
+        Ok(())
+    }
+
+    pub fn flash_loan_fees(&self) -> &HashMap<H160, U256> {
+        &self.flash_loan_fees
+    }
+
+    /// Records that the execution plan pays `amount` of `token` in flash loan fees.
+    pub fn add_flash_loan_fee(&mut self, token: H160, amount: U256) -> Result<()> {
+        let fee = self.flash_loan_fees.entry(token).or_default();
+        *fee = fee.checked_add(amount).context("flash loan fee overflow")?;
+        Ok(())
+    }
+
     // Fails if any used token doesn't have a price or if executed amount is impossible.
     pub fn add_trade(
         &mut self,
@@ -235,6 +299,56 @@ impl SettlementEncoder {
         self.execution_plan.push(Arc::new(interaction));
     }
 
+    /// Adds an approval required by the execution plan. Approvals of the same
+    /// token and spender are only encoded once, preferring the one that resets
+    /// the allowance first.
+    pub fn add_approval(&mut self, approval: Approval) {
+        let key = match approval.token_and_spender() {
+            Some(key) => key,
+            None => return,
+        };
+        match self
+            .approvals
+            .iter_mut()
+            .find(|existing| existing.token_and_spender() == Some(key))
+        {
+            Some(existing) => {
+                if matches!(approval, Approval::ResetAndApprove { .. }) {
+                    *existing = approval;
+                }
+            }
+            None => self.approvals.push(approval),
+        }
+    }
+
+    /// The settlement contract deposits the buy tokens of orders that pay out to Balancer Vault
+    /// internal balances into the Vault, which requires the Vault to be approved to transfer them.
+    /// The approvals get dropped again during post-processing if the allowances already suffice.
+    pub fn approve_internal_balance_deposits(&mut self, vault: H160) {
+        let tokens = self
+            .order_trades
+            .iter()
+            .map(|trade| &trade.trade.order)
+            .chain(
+                self.liquidity_order_trades
+                    .iter()
+                    .map(|trade| &trade.trade.order),
+            )
+            .filter(|order| order.data.buy_token_balance == BuyTokenDestination::Internal)
+            .map(|order| order.data.buy_token)
+            .collect::<Vec<_>>();
+        for token in tokens {
+            self.add_approval(Approval::Approve {
+                token,
+                spender: vault,
+            });
+        }
+    }
+
+    pub fn drop_approvals(&mut self) {
+        self.approvals.clear();
+    }
+
     pub fn add_unwrap(&mut self, unwrap: UnwrapWethInteraction) {
         for existing_unwrap in self.unwraps.iter_mut() {
             if existing_unwrap.merge(&unwrap).is_ok() {
@@ -392,6 +506,7 @@ impl SettlementEncoder {
             interactions: [
                 Vec::new(),
                 iter::empty()
+                    .chain(self.approvals.iter().flat_map(|approval| approval.encode()))
                     .chain(
                         self.execution_plan
                             .iter()
@@ -477,10 +592,22 @@ impl SettlementEncoder {
 
         self.execution_plan.append(&mut other.execution_plan);
 
+        for approval in other.approvals {
+            self.add_approval(approval);
+        }
+
         for unwrap in other.unwraps {
             self.add_unwrap(unwrap);
         }
 
+        for (token, amount) in other.internal_buffer_usage {
+            self.add_internal_buffer_usage(token, amount)?;
+        }
+
+        for (token, amount) in other.flash_loan_fees {
+            self.add_flash_loan_fee(token, amount)?;
+        }
+
         Ok(self)
     }
 
@@ -527,13 +654,23 @@ impl SettlementEncoder {
 
 pub fn verify_executed_amount(order: &Order, executed: U256) -> Result<()> {
     let remaining = shared::remaining_amounts::Remaining::from_order(order)?;
-    let valid_executed_amount = match (order.data.partially_fillable, order.data.kind) {
-        (true, OrderKind::Sell) => executed <= remaining.remaining(order.data.sell_amount)?,
-        (true, OrderKind::Buy) => executed <= remaining.remaining(order.data.buy_amount)?,
-        (false, OrderKind::Sell) => executed == remaining.remaining(order.data.sell_amount)?,
-        (false, OrderKind::Buy) => executed == remaining.remaining(order.data.buy_amount)?,
+    let remaining_amount = match order.data.kind {
+        OrderKind::Sell => remaining.remaining(order.data.sell_amount)?,
+        OrderKind::Buy => remaining.remaining(order.data.buy_amount)?,
+    };
+    let valid_executed_amount = match order.data.partially_fillable {
+        true => executed <= remaining_amount,
+        false => executed == remaining_amount,
     };
     ensure!(valid_executed_amount, "invalid executed amount");
+    if let Some(min_partial_fill_amount) = order.metadata.min_partial_fill_amount {
+        ensure!(
+            executed >= min_partial_fill_amount || executed == remaining_amount,
+            "executed amount {} is below the minimum partial fill amount {}",
+            executed,
+            min_partial_fill_amount,
+        );
+    }
     Ok(())
 }
 
@@ -729,6 +866,76 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn settlement_batches_approvals_before_execution_plan() {
+        let interaction: EncodedInteraction = (H160([0x01; 20]), 0.into(), Bytes(Vec::new()));
+        let approve = |token, spender| Approval::Approve {
+            token: H160([token; 20]),
+            spender: H160([spender; 20]),
+        };
+        let reset = Approval::ResetAndApprove {
+            token: H160([0x02; 20]),
+            spender: H160([0x03; 20]),
+        };
+
+        let mut encoder = SettlementEncoder::new(HashMap::new());
+        encoder.append_to_execution_plan(interaction.clone());
+        encoder.add_approval(approve(0x02, 0x03));
+        encoder.add_approval(Approval::AllowanceSufficient);
+        encoder.add_approval(approve(0x02, 0x04));
+        encoder.add_approval(reset);
+        encoder.add_approval(approve(0x02, 0x03));
+
+        assert_eq!(encoder.approvals(), [reset, approve(0x02, 0x04)]);
+        assert_eq!(
+            encoder.finish().interactions[1],
+            [
+                reset.encode(),
+                approve(0x02, 0x04).encode(),
+                interaction.encode()
+            ]
+            .concat(),
+        );
+    }
+
+    #[test]
+    fn approves_vault_for_internal_balance_deposits() {
+        let order = |buy_token, buy_token_balance| Order {
+            data: OrderData {
+                sell_token: H160([0x01; 20]),
+                sell_amount: 1.into(),
+                buy_token,
+                buy_amount: 1.into(),
+                buy_token_balance,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let vault = H160([0xba; 20]);
+
+        let mut encoder = SettlementEncoder::new(hashmap! {
+            H160([0x01; 20]) => 1.into(),
+            H160([0x02; 20]) => 1.into(),
+            H160([0x03; 20]) => 1.into(),
+        });
+        for order in [
+            order(H160([0x02; 20]), BuyTokenDestination::Internal),
+            order(H160([0x02; 20]), BuyTokenDestination::Internal),
+            order(H160([0x03; 20]), BuyTokenDestination::Erc20),
+        ] {
+            encoder.add_trade(order, 1.into(), 0.into()).unwrap();
+        }
+        encoder.approve_internal_balance_deposits(vault);
+
+        assert_eq!(
+            encoder.approvals(),
+            [Approval::Approve {
+                token: H160([0x02; 20]),
+                spender: vault,
+            }]
+        );
+    }
+
     #[test]
     fn settlement_encoder_add_token_equivalency() {
         let token_a = H160([0x00; 20]);
@@ -1015,4 +1222,23 @@ pub mod tests {
         let updated_buy_token_index = encoded_trade.1;
         assert_eq!(updated_buy_token_index, 1.into());
     }
+
+    #[test]
+    fn executed_amount_respects_min_partial_fill_amount() {
+        let order = OrderBuilder::default()
+            .with_kind(OrderKind::Sell)
+            .with_sell_amount(100.into())
+            .with_partially_fillable(true)
+            .with_min_partial_fill_amount(30.into())
+            .build();
+        assert!(verify_executed_amount(&order, 29.into()).is_err());
+        assert!(verify_executed_amount(&order, 30.into()).is_ok());
+        assert!(verify_executed_amount(&order, 100.into()).is_ok());
+
+        // The remainder of an order can be filled even if it is below the minimum.
+        let mut order = order;
+        order.metadata.executed_sell_amount_before_fees = 80.into();
+        assert!(verify_executed_amount(&order, 10.into()).is_err());
+        assert!(verify_executed_amount(&order, 20.into()).is_ok());
+    }
 }
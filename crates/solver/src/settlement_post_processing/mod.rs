@@ -1,17 +1,26 @@
+pub mod optimize_approvals;
 pub mod optimize_buffer_usage;
 pub mod optimize_unwrapping;
 
 use crate::{
-    settlement::Settlement, settlement_simulation::simulate_and_estimate_gas_at_current_block,
-    solver::http_solver::buffers::BufferRetriever,
+    buffer_usage_limits::BufferUsageLimits,
+    encoding::SettlementContractVersion,
+    settlement::Settlement,
+    settlement_simulation::{
+        simulate_and_estimate_gas_at_current_block, simulate_with_revm_at_current_block,
+    },
 };
 use contracts::{GPv2Settlement, WETH9};
 use ethcontract::Account;
 use gas_estimation::GasPrice1559;
+use optimize_approvals::optimize_approvals;
 use optimize_buffer_usage::optimize_buffer_usage;
 use optimize_unwrapping::optimize_unwrapping;
 use primitive_types::H160;
-use shared::{token_list::TokenList, Web3};
+use shared::{
+    buffers::BufferRetriever, revm_simulation::RevmSimulator, token_list::TokenLists, Web3,
+};
+use std::sync::Arc;
 
 /// Determines whether a settlement would be executed successfully.
 #[cfg_attr(test, mockall::automock)]
@@ -23,6 +32,7 @@ pub trait SettlementSimulating: Send + Sync {
 pub struct SettlementSimulator {
     web3: Web3,
     settlement_contract: GPv2Settlement,
+    settlement_contract_version: SettlementContractVersion,
     gas_price: GasPrice1559,
     solver_account: Account,
 }
@@ -33,6 +43,7 @@ impl SettlementSimulating for SettlementSimulator {
         let result = simulate_and_estimate_gas_at_current_block(
             std::iter::once((self.solver_account.clone(), settlement, None)),
             &self.settlement_contract,
+            self.settlement_contract_version,
             &self.web3,
             self.gas_price,
         )
@@ -41,22 +52,51 @@ impl SettlementSimulating for SettlementSimulator {
     }
 }
 
+/// Simulates settlements with the embedded EVM instead of the node.
+pub struct RevmSettlementSimulator {
+    simulator: Arc<RevmSimulator>,
+    settlement_contract: GPv2Settlement,
+    settlement_contract_version: SettlementContractVersion,
+    solver_account: Account,
+}
+
+#[async_trait::async_trait]
+impl SettlementSimulating for RevmSettlementSimulator {
+    async fn settlement_would_succeed(&self, settlement: Settlement) -> bool {
+        let result = simulate_with_revm_at_current_block(
+            std::iter::once((self.solver_account.clone(), settlement, None)),
+            &self.settlement_contract,
+            self.settlement_contract_version,
+            &self.simulator,
+        )
+        .await;
+        matches!(result, Ok(results) if results[0].is_ok())
+    }
+}
+
 pub struct PostProcessingPipeline {
     web3: Web3,
     settlement_contract: GPv2Settlement,
+    settlement_contract_version: SettlementContractVersion,
     unwrap_factor: f64,
     weth: WETH9,
     buffer_retriever: BufferRetriever,
-    market_makable_token_list: Option<TokenList>,
+    market_makable_token_list: Option<Arc<TokenLists>>,
+    buffer_usage_limits: Arc<BufferUsageLimits>,
+    revm_simulator: Option<Arc<RevmSimulator>>,
 }
 
 impl PostProcessingPipeline {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         native_token: H160,
         web3: Web3,
         unwrap_factor: f64,
         settlement_contract: GPv2Settlement,
-        market_makable_token_list: Option<TokenList>,
+        settlement_contract_version: SettlementContractVersion,
+        market_makable_token_list: Option<Arc<TokenLists>>,
+        buffer_usage_limits: Arc<BufferUsageLimits>,
+        revm_simulator: Option<Arc<RevmSimulator>>,
     ) -> Self {
         let weth = WETH9::at(&web3, native_token);
         let buffer_retriever = BufferRetriever::new(web3.clone(), settlement_contract.address());
@@ -64,10 +104,13 @@ impl PostProcessingPipeline {
         Self {
             web3,
             settlement_contract,
+            settlement_contract_version,
             unwrap_factor,
             weth,
             buffer_retriever,
             market_makable_token_list,
+            buffer_usage_limits,
+            revm_simulator,
         }
     }
 
@@ -77,24 +120,52 @@ impl PostProcessingPipeline {
         solver_account: Account,
         gas_price: GasPrice1559,
     ) -> Settlement {
-        let simulator = SettlementSimulator {
-            web3: self.web3.clone(),
-            settlement_contract: self.settlement_contract.clone(),
-            gas_price,
-            solver_account,
-        };
+        match &self.revm_simulator {
+            Some(simulator) => {
+                let simulator = RevmSettlementSimulator {
+                    simulator: simulator.clone(),
+                    settlement_contract: self.settlement_contract.clone(),
+                    settlement_contract_version: self.settlement_contract_version,
+                    solver_account,
+                };
+                self.optimize_with(settlement, &simulator).await
+            }
+            None => {
+                let simulator = SettlementSimulator {
+                    web3: self.web3.clone(),
+                    settlement_contract: self.settlement_contract.clone(),
+                    settlement_contract_version: self.settlement_contract_version,
+                    gas_price,
+                    solver_account,
+                };
+                self.optimize_with(settlement, &simulator).await
+            }
+        }
+    }
 
-        let optimized_solution =
-            optimize_buffer_usage(settlement, &self.market_makable_token_list, &simulator).await;
+    async fn optimize_with(
+        &self,
+        settlement: Settlement,
+        simulator: &impl SettlementSimulating,
+    ) -> Settlement {
+        let optimized_solution = optimize_buffer_usage(
+            settlement,
+            &self.market_makable_token_list,
+            &self.buffer_usage_limits,
+            simulator,
+        )
+        .await;
 
         // an error will leave the settlement unmodified
-        optimize_unwrapping(
+        let optimized_solution = optimize_unwrapping(
             optimized_solution,
-            &simulator,
+            simulator,
             &self.buffer_retriever,
             &self.weth,
             self.unwrap_factor,
         )
-        .await
+        .await;
+
+        optimize_approvals(optimized_solution, simulator).await
     }
 }
@@ -0,0 +1,80 @@
+use super::SettlementSimulating;
+use crate::settlement::Settlement;
+
+/// Approvals are planned from allowances that were cached when the liquidity was fetched (or
+/// added unconditionally when the allowance couldn't be fetched at all), so they might already be
+/// in place by the time the settlement gets submitted. Drop them if the simulation shows that the
+/// settlement succeeds with the current allowances.
+pub async fn optimize_approvals(
+    settlement: Settlement,
+    settlement_simulator: &impl SettlementSimulating,
+) -> Settlement {
+    if settlement.encoder.approvals().is_empty() {
+        return settlement;
+    }
+
+    let mut optimized_settlement = settlement.clone();
+    optimized_settlement.encoder.drop_approvals();
+
+    if settlement_simulator
+        .settlement_would_succeed(optimized_settlement.clone())
+        .await
+    {
+        tracing::debug!("allowances are sufficient, dropping approvals");
+        return optimized_settlement;
+    }
+
+    settlement
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        interactions::allowances::Approval, settlement_post_processing::MockSettlementSimulating,
+    };
+    use primitive_types::H160;
+
+    fn settlement_with_approval() -> Settlement {
+        let mut settlement = Settlement::new(Default::default());
+        settlement.encoder.add_approval(Approval::Approve {
+            token: H160([1; 20]),
+            spender: H160([2; 20]),
+        });
+        settlement
+    }
+
+    #[tokio::test]
+    async fn drops_approvals_if_settlement_succeeds_without_them() {
+        let mut settlement_simulator = MockSettlementSimulating::new();
+        settlement_simulator
+            .expect_settlement_would_succeed()
+            .times(1)
+            .returning(|settlement| settlement.encoder.approvals().is_empty());
+
+        let settlement =
+            optimize_approvals(settlement_with_approval(), &settlement_simulator).await;
+        assert!(settlement.encoder.approvals().is_empty());
+    }
+
+    #[tokio::test]
+    async fn keeps_approvals_if_settlement_needs_them() {
+        let mut settlement_simulator = MockSettlementSimulating::new();
+        settlement_simulator
+            .expect_settlement_would_succeed()
+            .times(1)
+            .returning(|_| false);
+
+        let settlement =
+            optimize_approvals(settlement_with_approval(), &settlement_simulator).await;
+        assert_eq!(settlement.encoder.approvals().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn does_not_simulate_settlements_without_approvals() {
+        let settlement_simulator = MockSettlementSimulating::new();
+        let settlement =
+            optimize_approvals(Settlement::new(Default::default()), &settlement_simulator).await;
+        assert!(settlement.encoder.approvals().is_empty());
+    }
+}
@@ -1,23 +1,29 @@
 use super::SettlementSimulating;
-use crate::settlement::Settlement;
-use shared::token_list::TokenList;
+use crate::{buffer_usage_limits::BufferUsageLimits, settlement::Settlement};
+use shared::token_list::{TokenList, TokenLists};
+use std::sync::Arc;
 
 /// If a settlement only trades trusted tokens try to optimize it by trading with internal buffers.
 pub async fn optimize_buffer_usage(
     settlement: Settlement,
-    market_makable_token_list: &Option<TokenList>,
+    market_makable_token_list: &Option<Arc<TokenLists>>,
+    buffer_usage_limits: &BufferUsageLimits,
     settlement_simulator: &impl SettlementSimulating,
 ) -> Settlement {
     // We don't want to buy tokens that we don't trust. If no list is set, we settle with external liquidity.
     if !market_makable_token_list
         .as_ref()
-        .map(|list| is_only_selling_trusted_tokens(&settlement, list))
+        .map(|lists| is_only_selling_trusted_tokens(&settlement, &lists.current()))
         .unwrap_or(false)
     {
         return settlement;
     }
 
     let optimized_settlement = settlement.clone().without_onchain_liquidity();
+    if !buffer_usage_limits.is_within_limits(optimized_settlement.encoder.internal_buffer_usage()) {
+        tracing::debug!("settlement without onchain liquidity exceeds buffer usage limits");
+        return settlement;
+    }
 
     if settlement_simulator
         .settlement_would_succeed(optimized_settlement.clone())
@@ -1,8 +1,8 @@
 use super::SettlementSimulating;
 use crate::settlement::Settlement;
-use crate::solver::http_solver::buffers::BufferRetrieving;
 use contracts::WETH9;
 use primitive_types::U256;
+use shared::buffers::BufferRetrieving;
 
 /// Tries to do one of 2 optimizations.
 /// 1) Drop WETH unwraps and instead pay ETH with the settlment contract's buffer.
@@ -76,9 +76,8 @@ mod tests {
     use super::*;
     use crate::interactions::UnwrapWethInteraction;
     use crate::settlement_post_processing::MockSettlementSimulating;
-    use crate::solver::http_solver::buffers::MockBufferRetrieving;
     use maplit::hashmap;
-    use shared::dummy_contract;
+    use shared::{buffers::MockBufferRetrieving, dummy_contract};
     use std::collections::HashMap;
 
     fn to_wei(base: u128) -> U256 {
@@ -1,5 +1,6 @@
 use crate::{
-    driver::solver_settlements::{self, retain_mature_settlements},
+    buffer_usage_limits::BufferUsageLimits,
+    driver::solver_settlements::{self, retain_mature_settlements, RatedSettlement},
     metrics::{SolverMetrics, SolverRunOutcome},
     settlement::{external_prices::ExternalPrices, PriceCheckTokens, Settlement},
     settlement_rater::{RatedSolverSettlement, SettlementRating},
@@ -7,8 +8,11 @@ use crate::{
 };
 use anyhow::Result;
 use gas_estimation::GasPrice1559;
-use num::{rational::Ratio, BigInt};
+use model::auction::AuctionId;
+use num::{rational::Ratio, BigInt, Signed, ToPrimitive};
+use primitive_types::H160;
 use rand::prelude::SliceRandom;
+use shared::http_solver::model::SettlementRejection;
 use std::{sync::Arc, time::Duration};
 
 type SolverResult = (Arc<dyn Solver>, Result<Vec<Settlement>, SolverRunError>);
@@ -21,13 +25,20 @@ pub struct SettlementRanker {
     pub min_order_age: Duration,
     pub max_settlement_price_deviation: Option<Ratio<BigInt>>,
     pub token_list_restriction_for_price_checks: PriceCheckTokens,
+    pub buffer_usage_limits: Arc<BufferUsageLimits>,
+    /// The Balancer Vault that orders paying out to internal balances get deposited into, if it
+    /// is deployed on the network.
+    pub internal_balance_vault: Option<H160>,
 }
 
 impl SettlementRanker {
-    /// Discards settlements without user orders and settlements which violate price checks.
+    /// Discards settlements without user orders, settlements which violate price checks and
+    /// settlements which exceed the internal buffer usage limits.
     /// Logs info and updates metrics about the out come of this run loop for each solver.
+    /// Solvers get notified about settlements that were discarded for violating a check.
     fn discard_illegal_settlements(
         &self,
+        auction_id: AuctionId,
         solver: &Arc<dyn Solver>,
         settlements: Result<Vec<Settlement>, SolverRunError>,
         external_prices: &ExternalPrices,
@@ -52,12 +63,19 @@ impl SettlementRanker {
                 if let Some(max_settlement_price_deviation) = &self.max_settlement_price_deviation {
                     let settlement_count = settlement.len();
                     settlement.retain(|settlement| {
-                        settlement.satisfies_price_checks(
+                        let violation = settlement.price_violation(
                             solver.name(),
                             external_prices,
                             max_settlement_price_deviation,
                             &self.token_list_restriction_for_price_checks,
-                        )
+                        );
+                        if let Some(token_pair) = violation {
+                            solver.notify_rejection(
+                                auction_id,
+                                price_violation(settlement, external_prices, token_pair),
+                            );
+                        }
+                        violation.is_none()
                     });
                     if settlement_count != settlement.len() {
                         tracing::debug!(
@@ -67,6 +85,30 @@ impl SettlementRanker {
                     }
                 }
 
+                let settlement_count = settlement.len();
+                settlement.retain(|settlement| {
+                    let usage = settlement.encoder.internal_buffer_usage();
+                    let within_limits = self.buffer_usage_limits.is_within_limits(usage);
+                    if !within_limits {
+                        solver.notify_rejection(
+                            auction_id,
+                            SettlementRejection::BufferUsageExceeded {
+                                internal_buffer_usage: usage
+                                    .iter()
+                                    .map(|(token, amount)| (*token, *amount))
+                                    .collect(),
+                            },
+                        );
+                    }
+                    within_limits
+                });
+                if settlement_count != settlement.len() {
+                    tracing::debug!(
+                        solver_name = %name,
+                        "settlement(s) filtered for exceeding internal buffer usage limits",
+                    );
+                }
+
                 let outcome = match settlement.is_empty() {
                     true => SolverRunOutcome::Empty,
                     false => SolverRunOutcome::Success,
@@ -90,13 +132,18 @@ impl SettlementRanker {
     /// Computes a list of settlements which pass all pre-simulation sanity checks.
     fn get_legal_settlements(
         &self,
+        auction_id: AuctionId,
         settlements: Vec<SolverResult>,
         prices: &ExternalPrices,
     ) -> Vec<(Arc<dyn Solver>, Settlement)> {
         let mut solver_settlements = vec![];
         for (solver, settlements) in settlements {
-            let settlements = self.discard_illegal_settlements(&solver, settlements, prices);
-            for settlement in settlements {
+            let settlements =
+                self.discard_illegal_settlements(auction_id, &solver, settlements, prices);
+            for mut settlement in settlements {
+                if let Some(vault) = self.internal_balance_vault {
+                    settlement.encoder.approve_internal_balance_deposits(vault);
+                }
                 solver_settlements.push((solver.clone(), settlement));
             }
         }
@@ -109,13 +156,17 @@ impl SettlementRanker {
     /// Determines legal settlements and ranks them by simulating them.
     /// Settlements get partitioned into simulation errors and a list
     /// of `RatedSettlement`s sorted by ascending order of objective value.
+    /// Settlements whose objective value is not positive get discarded and reported to their
+    /// solver.
     pub async fn rank_legal_settlements(
         &self,
+        auction_id: AuctionId,
         settlements: Vec<SolverResult>,
         external_prices: &ExternalPrices,
         gas_price: GasPrice1559,
     ) -> Result<(Vec<RatedSolverSettlement>, Vec<SettlementWithError>)> {
-        let solver_settlements = self.get_legal_settlements(settlements, external_prices);
+        let solver_settlements =
+            self.get_legal_settlements(auction_id, settlements, external_prices);
 
         // log considered settlements. While we already log all found settlements, this additonal
         // statement allows us to figure out which settlements were filtered out and which ones are
@@ -132,6 +183,19 @@ impl SettlementRanker {
             .rate_settlements(solver_settlements, external_prices, gas_price)
             .await?;
 
+        rated_settlements.retain(|(solver, settlement, _)| {
+            let objective_value = settlement.objective_value();
+            let positive = objective_value.is_positive();
+            if !positive {
+                tracing::debug!(
+                    solver_name = %solver.name(), %objective_value,
+                    "settlement filtered for non-positive objective value",
+                );
+                solver.notify_rejection(auction_id, objective_mismatch(settlement));
+            }
+            positive
+        });
+
         // Before sorting, make sure to shuffle the settlements. This is to make sure we don't give
         // preference to any specific solver when there is an objective value tie.
         rated_settlements.shuffle(&mut rand::thread_rng());
@@ -150,3 +214,107 @@ impl SettlementRanker {
         Ok((rated_settlements, errors))
     }
 }
+
+fn objective_mismatch(settlement: &RatedSettlement) -> SettlementRejection {
+    SettlementRejection::ObjectiveMismatch {
+        objective_value: settlement.objective_value().to_f64().unwrap_or(f64::NAN),
+        surplus: settlement.surplus.to_f64().unwrap_or(f64::NAN),
+        fees: settlement
+            .scaled_unsubsidized_fee
+            .to_f64()
+            .unwrap_or(f64::NAN),
+        gas: settlement.effective_gas(),
+        gas_price: settlement.gas_price.to_f64().unwrap_or(f64::NAN),
+    }
+}
+
+fn price_violation(
+    settlement: &Settlement,
+    external_prices: &ExternalPrices,
+    (sell_token, buy_token): (H160, H160),
+) -> SettlementRejection {
+    let external_price = |token| {
+        external_prices
+            .price(&token)
+            .and_then(ToPrimitive::to_f64)
+            .unwrap_or(f64::NAN)
+    };
+    SettlementRejection::PriceViolation {
+        sell_token,
+        buy_token,
+        sell_token_clearing_price: settlement.clearing_price(sell_token).unwrap_or_default(),
+        buy_token_clearing_price: settlement.clearing_price(buy_token).unwrap_or_default(),
+        sell_token_external_price: external_price(sell_token),
+        buy_token_external_price: external_price(buy_token),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{metrics::NoopMetrics, settlement_rater::MockSettlementRating, solver::MockSolver};
+    use mockall::predicate::eq;
+    use num::BigRational;
+
+    #[tokio::test]
+    async fn reports_settlements_with_non_positive_objective() {
+        let mut solver = MockSolver::new();
+        solver.expect_name().return_const("solver");
+        solver
+            .expect_notify_rejection()
+            .with(
+                eq(1),
+                eq(SettlementRejection::ObjectiveMismatch {
+                    objective_value: -2.,
+                    surplus: 1.,
+                    fees: 0.,
+                    gas: 3.into(),
+                    gas_price: 1.,
+                }),
+            )
+            .times(1)
+            .return_const(());
+        let solver: Arc<dyn Solver> = Arc::new(solver);
+
+        let rated = |id, surplus| RatedSettlement {
+            id,
+            settlement: Default::default(),
+            surplus: BigRational::from_integer(surplus.into()),
+            unscaled_subsidized_fee: Default::default(),
+            scaled_unsubsidized_fee: Default::default(),
+            flash_loan_fee: Default::default(),
+            gas_estimate: 3.into(),
+            gas_refund: 0.into(),
+            gas_price: BigRational::from_integer(1.into()),
+        };
+        let mut settlement_rater = MockSettlementRating::new();
+        settlement_rater.expect_rate_settlements().returning({
+            let solver = solver.clone();
+            move |_, _, _| {
+                Ok((
+                    vec![
+                        (solver.clone(), rated(0, 5), None),
+                        (solver.clone(), rated(1, 1), None),
+                    ],
+                    vec![],
+                ))
+            }
+        });
+
+        let ranker = SettlementRanker {
+            metrics: Arc::new(NoopMetrics {}),
+            settlement_rater: Arc::new(settlement_rater),
+            min_order_age: Duration::default(),
+            max_settlement_price_deviation: None,
+            token_list_restriction_for_price_checks: PriceCheckTokens::All,
+            buffer_usage_limits: Default::default(),
+            internal_balance_vault: None,
+        };
+        let (rated_settlements, _) = ranker
+            .rank_legal_settlements(1, vec![], &Default::default(), Default::default())
+            .await
+            .unwrap();
+        assert_eq!(rated_settlements.len(), 1);
+        assert_eq!(rated_settlements[0].1.id, 0);
+    }
+}
@@ -1,8 +1,12 @@
 use crate::{
     driver::solver_settlements::RatedSettlement,
-    settlement::{external_prices::ExternalPrices, Settlement},
+    encoding::SettlementContractVersion,
+    settlement::{external_prices::ExternalPrices, gas_refunds, Settlement},
     settlement_access_list::AccessListEstimating,
-    settlement_simulation::{settle_method, simulate_and_estimate_gas_at_current_block},
+    settlement_simulation::{
+        settle_method, simulate_and_estimate_gas_at_current_block,
+        simulate_with_revm_at_current_block,
+    },
     solver::{SettlementWithError, SettlementWithSolver, Solver},
 };
 use anyhow::{Context, Result};
@@ -12,7 +16,7 @@ use gas_estimation::GasPrice1559;
 use itertools::{Either, Itertools};
 use num::BigRational;
 use primitive_types::U256;
-use shared::Web3;
+use shared::{revm_simulation::RevmSimulator, Web3};
 use std::sync::Arc;
 use web3::types::AccessList;
 
@@ -53,7 +57,10 @@ pub trait SettlementRating: Send + Sync {
 pub struct SettlementRater {
     pub access_list_estimator: Arc<dyn AccessListEstimating>,
     pub settlement_contract: GPv2Settlement,
+    pub settlement_contract_version: SettlementContractVersion,
     pub web3: Web3,
+    /// Simulates settlements with the embedded EVM instead of the node if set.
+    pub revm_simulator: Option<Arc<RevmSimulator>>,
 }
 
 impl SettlementRater {
@@ -68,6 +75,7 @@ impl SettlementRater {
                 settle_method(
                     gas_price,
                     &self.settlement_contract,
+                    self.settlement_contract_version,
                     settlement.clone(),
                     solver.account().clone(),
                 )
@@ -100,19 +108,34 @@ impl SettlementRating for SettlementRater {
         gas_price: GasPrice1559,
     ) -> Result<Vec<SimulationDetails>> {
         let settlements = self.append_access_lists(settlements, gas_price).await;
-        let simulations = simulate_and_estimate_gas_at_current_block(
-            settlements.iter().map(|settlement| {
-                (
-                    settlement.0.account().clone(),
-                    settlement.1.clone(),
-                    settlement.2.clone(),
+        let to_simulate = settlements.iter().map(|settlement| {
+            (
+                settlement.0.account().clone(),
+                settlement.1.clone(),
+                settlement.2.clone(),
+            )
+        });
+        let simulations = match &self.revm_simulator {
+            Some(simulator) => {
+                simulate_with_revm_at_current_block(
+                    to_simulate,
+                    &self.settlement_contract,
+                    self.settlement_contract_version,
+                    simulator,
                 )
-            }),
-            &self.settlement_contract,
-            &self.web3,
-            gas_price,
-        )
-        .await
+                .await
+            }
+            None => {
+                simulate_and_estimate_gas_at_current_block(
+                    to_simulate,
+                    &self.settlement_contract,
+                    self.settlement_contract_version,
+                    &self.web3,
+                    gas_price,
+                )
+                .await
+            }
+        }
         .context("failed to simulate settlements")?;
 
         let details: Vec<_> = settlements
@@ -136,6 +159,19 @@ impl SettlementRating for SettlementRater {
         prices: &ExternalPrices,
         gas_price: GasPrice1559,
     ) -> Result<(Vec<RatedSolverSettlement>, Vec<SettlementWithError>)> {
+        let settlements = settlements
+            .into_iter()
+            .filter(|(solver, settlement)| {
+                let priced = settlement.total_flash_loan_fees(prices).is_some();
+                if !priced {
+                    tracing::warn!(
+                        solver = %solver.name(),
+                        "discarding settlement with flash loan fees in tokens without a price"
+                    );
+                }
+                priced
+            })
+            .collect();
         let simulations = self.simulate_settlements(settlements, gas_price).await?;
 
         let gas_price =
@@ -145,13 +181,22 @@ impl SettlementRating for SettlementRater {
             let surplus = settlement.total_surplus(prices);
             let scaled_solver_fees = settlement.total_scaled_unsubsidized_fees(prices);
             let unscaled_subsidized_fee = settlement.total_unscaled_subsidized_fees(prices);
+            let flash_loan_fee = settlement
+                .total_flash_loan_fees(prices)
+                .expect("settlements with unpriced flash loan fees were discarded");
+            let gas_refund = gas_refunds::estimate(
+                &settlement.clone().into(),
+                self.settlement_contract.address(),
+            );
             RatedSettlement {
                 id,
                 settlement,
                 surplus,
                 unscaled_subsidized_fee,
                 scaled_unsubsidized_fee: scaled_solver_fees,
+                flash_loan_fee,
                 gas_estimate,
+                gas_refund,
                 gas_price: gas_price.clone(),
             }
         };
@@ -1,4 +1,7 @@
-use crate::{encoding::EncodedSettlement, settlement::Settlement};
+use crate::{
+    encoding::{EncodedSettlement, SettlementContractVersion},
+    settlement::Settlement,
+};
 use anyhow::{anyhow, Context, Error, Result};
 use contracts::GPv2Settlement;
 use ethcontract::{
@@ -17,7 +20,11 @@ use reqwest::{
     Client, IntoUrl, Url,
 };
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use shared::Web3;
+use shared::{
+    revm_simulation::{self, RevmSimulator},
+    Web3,
+};
+use std::sync::Arc;
 use web3::types::{AccessList, BlockId};
 
 const SIMULATE_BATCH_SIZE: usize = 10;
@@ -41,6 +48,7 @@ const MAX_BASE_GAS_FEE_INCREASE: f64 = 1.125;
 pub async fn simulate_and_estimate_gas_at_current_block(
     settlements: impl Iterator<Item = (Account, Settlement, Option<AccessList>)>,
     contract: &GPv2Settlement,
+    version: SettlementContractVersion,
     web3: &Web3,
     gas_price: GasPrice1559,
 ) -> Result<Vec<Result<U256, ExecutionError>>> {
@@ -65,6 +73,7 @@ pub async fn simulate_and_estimate_gas_at_current_block(
                 let tx = settle_method(
                     gas_price,
                     &contract_with_buffered_transport,
+                    version,
                     settlement.clone(),
                     account.clone(),
                 )
@@ -82,10 +91,42 @@ pub async fn simulate_and_estimate_gas_at_current_block(
     Ok(results)
 }
 
+/// Like [`simulate_and_estimate_gas_at_current_block`] but simulates every settlement with the
+/// embedded EVM instead of `eth_estimateGas` on the node. Reports the gas used by the settlement.
+pub async fn simulate_with_revm_at_current_block(
+    settlements: impl Iterator<Item = (Account, Settlement, Option<AccessList>)>,
+    contract: &GPv2Settlement,
+    version: SettlementContractVersion,
+    simulator: &RevmSimulator,
+) -> Result<Vec<Result<U256, ExecutionError>>> {
+    let simulations = settlements.map(|(account, settlement, access_list)| {
+        let call = revm_simulation::Call {
+            from: account.address(),
+            to: contract.address(),
+            data: call_data(version, settlement.into()),
+            access_list,
+            ..Default::default()
+        };
+        async move {
+            let outcome = simulator
+                .simulate_at_latest_block(vec![call])
+                .await?
+                .pop()
+                .context("missing call outcome")?;
+            Ok(outcome
+                .output
+                .map(|_| U256::from(outcome.gas_used))
+                .map_err(|reason| ExecutionError::Revert(Some(reason))))
+        }
+    });
+    futures::future::try_join_all(simulations).await
+}
+
 #[allow(clippy::needless_collect)]
 pub async fn simulate_and_error_with_tenderly_link(
     settlements: impl Iterator<Item = (Account, Settlement, Option<AccessList>)>,
     contract: &GPv2Settlement,
+    version: SettlementContractVersion,
     web3: &Web3,
     gas_price: GasPrice1559,
     network_id: &str,
@@ -95,7 +136,7 @@ pub async fn simulate_and_error_with_tenderly_link(
     let mut batch = CallBatch::new(web3.transport());
     let futures = settlements
         .map(|(account, settlement, access_list)| {
-            let method = settle_method(gas_price, contract, settlement, account);
+            let method = settle_method(gas_price, contract, version, settlement, account);
             let method = match access_list {
                 Some(access_list) => method.access_list(access_list),
                 None => method,
@@ -124,6 +165,47 @@ pub async fn simulate_and_error_with_tenderly_link(
         .collect()
 }
 
+/// Simulates transactions on top of historic chain state.
+#[async_trait::async_trait]
+pub trait TransactionSimulating: Send + Sync {
+    /// Returns the amount of gas the transaction uses.
+    async fn gas_used(&self, request: TenderlyRequest) -> Result<u64>;
+}
+
+#[derive(Copy, Clone, Debug, clap::ArgEnum)]
+#[clap(rename_all = "verbatim")]
+pub enum SimulationBackend {
+    Tenderly,
+    Revm,
+}
+
+/// Creates the configured simulator. Returns `None` if Tenderly is configured but no Tenderly
+/// credentials are provided.
+pub fn create_simulator(
+    backend: SimulationBackend,
+    web3: &Web3,
+    client: &Client,
+    tenderly_url: Option<Url>,
+    tenderly_api_key: Option<&str>,
+) -> Option<Arc<dyn TransactionSimulating>> {
+    match backend {
+        SimulationBackend::Tenderly => tenderly_url
+            .zip(tenderly_api_key)
+            .and_then(|(url, api_key)| TenderlyApi::new(url, client.clone(), api_key).ok())
+            .map(|tenderly| Arc::new(tenderly) as Arc<dyn TransactionSimulating>),
+        SimulationBackend::Revm => Some(Arc::new(RevmSimulator::new(web3.clone()))),
+    }
+}
+
+/// Creates the embedded EVM simulator if it is the configured backend. Settlements get simulated
+/// with it instead of the node.
+pub fn create_revm_simulator(
+    backend: SimulationBackend,
+    web3: &Web3,
+) -> Option<Arc<RevmSimulator>> {
+    matches!(backend, SimulationBackend::Revm).then(|| Arc::new(RevmSimulator::new(web3.clone())))
+}
+
 #[derive(Debug, Clone, Deserialize)]
 struct TenderlyResponse {
     transaction: TenderlyTransaction,
@@ -136,7 +218,7 @@ struct TenderlyTransaction {
 
 pub async fn simulate_before_after_access_list(
     web3: &Web3,
-    tenderly: &TenderlyApi,
+    simulator: &dyn TransactionSimulating,
     network_id: String,
     transaction_hash: H256,
 ) -> Result<f64> {
@@ -176,11 +258,7 @@ pub async fn simulate_before_after_access_list(
         transaction_index: Some(transaction_index),
     };
 
-    let gas_used_without_access_list = tenderly
-        .send::<TenderlyResponse>(request)
-        .await?
-        .transaction
-        .gas_used;
+    let gas_used_without_access_list = simulator.gas_used(request).await?;
     let gas_used_with_access_list = web3
         .eth()
         .transaction_receipt(transaction_hash)
@@ -195,6 +273,7 @@ pub async fn simulate_before_after_access_list(
 pub fn settle_method(
     gas_price: GasPrice1559,
     contract: &GPv2Settlement,
+    version: SettlementContractVersion,
     settlement: Settlement,
     account: Account,
 ) -> MethodBuilder<DynTransport, ()> {
@@ -203,34 +282,23 @@ pub fn settle_method(
     // a block may have been mined that increases the base gas fee and causes the
     // `eth_call` simulation to fail with `max fee per gas less than block base fee`.
     let gas_price = gas_price.bump(MAX_BASE_GAS_FEE_INCREASE);
-    settle_method_builder(contract, settlement.into(), account)
+    settle_method_builder(contract, version, settlement.into(), account)
         .gas_price(crate::into_gas_price(&gas_price))
 }
 
 pub fn settle_method_builder(
     contract: &GPv2Settlement,
+    version: SettlementContractVersion,
     settlement: EncodedSettlement,
     from: Account,
 ) -> DynMethodBuilder<()> {
-    contract
-        .settle(
-            settlement.tokens,
-            settlement.clearing_prices,
-            settlement.trades,
-            settlement.interactions,
-        )
-        .from(from)
+    version.settle(contract, settlement).from(from)
 }
 
 /// The call data of a settle call with this settlement.
-pub fn call_data(settlement: EncodedSettlement) -> Vec<u8> {
+pub fn call_data(version: SettlementContractVersion, settlement: EncodedSettlement) -> Vec<u8> {
     let contract = GPv2Settlement::at(&shared::transport::dummy::web3(), H160::default());
-    let method = contract.settle(
-        settlement.tokens,
-        settlement.clearing_prices,
-        settlement.trades,
-        settlement.interactions,
-    );
+    let method = version.settle(&contract, settlement);
     // Unwrap because there should always be calldata.
     method.tx.data.unwrap().0
 }
@@ -326,6 +394,41 @@ impl TenderlyApi {
     }
 }
 
+#[async_trait::async_trait]
+impl TransactionSimulating for RevmSimulator {
+    async fn gas_used(&self, request: TenderlyRequest) -> Result<u64> {
+        let call = revm_simulation::Call {
+            from: request.from,
+            to: request.to,
+            data: request.input,
+            gas: request.gas,
+            ..Default::default()
+        };
+        let outcome = self
+            .simulate_in_block(
+                request.block_number,
+                request.transaction_index.unwrap_or_default(),
+                call,
+            )
+            .await?;
+        outcome
+            .output
+            .map_err(|reason| anyhow!("simulated transaction failed: {}", reason))?;
+        Ok(outcome.gas_used)
+    }
+}
+
+#[async_trait::async_trait]
+impl TransactionSimulating for TenderlyApi {
+    async fn gas_used(&self, request: TenderlyRequest) -> Result<u64> {
+        Ok(self
+            .send::<TenderlyResponse>(request)
+            .await?
+            .transaction
+            .gas_used)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -371,6 +474,7 @@ mod tests {
         let result = simulate_and_error_with_tenderly_link(
             settlements.iter().cloned(),
             &contract,
+            SettlementContractVersion::V1,
             &web3,
             Default::default(),
             network_id.as_str(),
@@ -383,6 +487,7 @@ mod tests {
         let result = simulate_and_estimate_gas_at_current_block(
             settlements.iter().cloned(),
             &contract,
+            SettlementContractVersion::V1,
             &web3,
             Default::default(),
         )
@@ -393,6 +498,7 @@ mod tests {
         let result = simulate_and_estimate_gas_at_current_block(
             std::iter::empty(),
             &contract,
+            SettlementContractVersion::V1,
             &web3,
             Default::default(),
         )
@@ -458,7 +564,6 @@ mod tests {
             "settlementContract": "0x9008d19f58aabd9ed0d60971565aa8510560ab41",
             "sellTokenBalance": "erc20",
             "buyTokenBalance": "erc20",
-            "isLiquidityOrder": false,
         });
         let order0: Order = serde_json::from_value(value).unwrap();
         let value = json!(
@@ -489,7 +594,7 @@ mod tests {
             "settlementContract": "0x9008d19f58aabd9ed0d60971565aa8510560ab41",
             "sellTokenBalance": "erc20",
             "buyTokenBalance": "erc20",
-            "isLiquidityOrder": true,
+            "class": "limit",
         });
         let order1: Order = serde_json::from_value(value).unwrap();
         let value = json!(
@@ -520,7 +625,6 @@ mod tests {
             "settlementContract": "0x9008d19f58aabd9ed0d60971565aa8510560ab41",
             "sellTokenBalance": "erc20",
             "buyTokenBalance": "erc20",
-            "isLiquidityOrder": false,
         });
         let order2: Order = serde_json::from_value(value).unwrap();
 
@@ -745,7 +849,13 @@ mod tests {
         let settlement = settlements.get(0).unwrap();
         let settlement_encoded = settlement.encoder.clone().finish();
         println!("Settlement_encoded: {:?}", settlement_encoded);
-        let settlement = settle_method_builder(&contract, settlement_encoded, account).tx;
+        let settlement = settle_method_builder(
+            &contract,
+            SettlementContractVersion::V1,
+            settlement_encoded,
+            account,
+        )
+        .tx;
         println!(
             "Tenderly simulation for generated tx: {:?}",
             tenderly_link(13830346u64, &network_id, settlement)
@@ -770,6 +880,7 @@ mod tests {
         let result = simulate_and_estimate_gas_at_current_block(
             settlements.iter().cloned(),
             &contract,
+            SettlementContractVersion::V1,
             &web3,
             GasPrice1559::default(),
         )
@@ -808,7 +919,7 @@ mod tests {
     #[test]
     fn calldata_works() {
         let settlement = EncodedSettlement::default();
-        let data = call_data(settlement);
+        let data = call_data(SettlementContractVersion::V1, settlement);
         assert!(!data.is_empty());
     }
 }
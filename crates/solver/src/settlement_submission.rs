@@ -1,9 +1,10 @@
 mod dry_run;
+pub mod relayer;
 pub mod submitter;
 
 use crate::{
-    metrics::SettlementSubmissionOutcome, settlement::Settlement,
-    settlement_access_list::AccessListEstimating,
+    encoding::SettlementContractVersion, metrics::SettlementSubmissionOutcome,
+    settlement::Settlement, settlement_access_list::AccessListEstimating,
 };
 use anyhow::{anyhow, Result};
 use contracts::GPv2Settlement;
@@ -14,6 +15,7 @@ use ethcontract::{
 use futures::FutureExt;
 use gas_estimation::{GasPrice1559, GasPriceEstimating};
 use primitive_types::{H256, U256};
+use relayer::RelayerApi;
 use shared::Web3;
 use std::{
     collections::HashMap,
@@ -100,6 +102,7 @@ impl SubTxPoolRef {
 pub struct SolutionSubmitter {
     pub web3: Web3,
     pub contract: GPv2Settlement,
+    pub contract_version: SettlementContractVersion,
     pub gas_price_estimator: Arc<dyn GasPriceEstimating>,
     pub access_list_estimator: Arc<dyn AccessListEstimating>,
     // for gas price estimation
@@ -121,6 +124,8 @@ pub enum TransactionStrategy {
     Eden(StrategyArgs),
     Flashbots(StrategyArgs),
     CustomNodes(StrategyArgs),
+    /// Settlements get executed by a relayer that is paid from the settlement.
+    Relayer(RelayerApi),
     DryRun,
 }
 
@@ -130,7 +135,42 @@ impl TransactionStrategy {
             TransactionStrategy::Eden(args) => Some(args),
             TransactionStrategy::Flashbots(args) => Some(args),
             TransactionStrategy::CustomNodes(args) => Some(args),
-            TransactionStrategy::DryRun => None,
+            TransactionStrategy::Relayer(_) | TransactionStrategy::DryRun => None,
+        }
+    }
+
+    /// Whether the strategy may submit a settlement with the policy. Custom nodes broadcast
+    /// transactions to the public mempool and relayers might.
+    pub fn allows(&self, policy: SubmissionPolicy) -> bool {
+        match policy {
+            SubmissionPolicy::Any => true,
+            SubmissionPolicy::PrivateOnly => matches!(
+                self,
+                TransactionStrategy::Eden(_)
+                    | TransactionStrategy::Flashbots(_)
+                    | TransactionStrategy::DryRun
+            ),
+        }
+    }
+}
+
+/// Where a settlement may be submitted to, resolved from the orders it contains.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SubmissionPolicy {
+    Any,
+    /// At least one order asked for its settlement to never be sent to the public mempool.
+    PrivateOnly,
+}
+
+impl SubmissionPolicy {
+    pub fn for_settlement(settlement: &Settlement) -> Self {
+        if settlement
+            .traded_orders()
+            .any(|order| order.metadata.private_submission)
+        {
+            Self::PrivateOnly
+        } else {
+            Self::Any
         }
     }
 }
@@ -152,15 +192,31 @@ impl SolutionSubmitter {
             .iter()
             .any(|strategy| matches!(strategy, TransactionStrategy::DryRun));
 
+        let policy = SubmissionPolicy::for_settlement(&settlement);
+        if !self
+            .transaction_strategies
+            .iter()
+            .any(|strategy| strategy.allows(policy))
+        {
+            return Err(SubmissionError::from(anyhow!(
+                "settlement contains orders requiring private submission but no private \
+                 transaction strategy is configured"
+            )));
+        }
+
         let network_id = self.web3.net().version().await?;
 
         if is_dry_run {
-            Ok(dry_run::log_settlement(account, &self.contract, settlement).await?)
+            Ok(
+                dry_run::log_settlement(account, &self.contract, self.contract_version, settlement)
+                    .await?,
+            )
         } else {
             let mut futures = self
                 .transaction_strategies
                 .iter()
                 .enumerate()
+                .filter(|(_, strategy)| strategy.allows(policy))
                 .map(|(i, strategy)| {
                     self.settle_with_strategy(
                         strategy,
@@ -206,11 +262,10 @@ impl SolutionSubmitter {
                     )));
                 }
             }
-            TransactionStrategy::CustomNodes(_) => {}
+            TransactionStrategy::CustomNodes(_) | TransactionStrategy::Relayer(_) => {}
             TransactionStrategy::DryRun => unreachable!(),
         };
 
-        let strategy_args = strategy.strategy_args().expect("unreachable code executed");
         let params = SubmitterParams {
             target_confirm_time: self.target_confirm_time,
             gas_estimate,
@@ -218,6 +273,32 @@ impl SolutionSubmitter {
             retry_interval: self.retry_interval,
             network_id,
         };
+        if let TransactionStrategy::Relayer(relayer) = strategy {
+            let gas_price_estimator = SubmitterGasPriceEstimator {
+                inner: self.gas_price_estimator.as_ref(),
+                gas_price_cap: self.gas_price_cap,
+                additional_tip_percentage_of_max_fee: None,
+                max_additional_tip: None,
+                pending_gas_price: None,
+            };
+            return relayer
+                .relay(
+                    &self.web3,
+                    &self.contract,
+                    self.contract_version,
+                    &gas_price_estimator,
+                    settlement,
+                    params,
+                )
+                .instrument(tracing::info_span!(
+                    "submission",
+                    name = "relayer",
+                    i = index
+                ))
+                .await;
+        }
+
+        let strategy_args = strategy.strategy_args().expect("unreachable code executed");
         let gas_price_estimator = SubmitterGasPriceEstimator {
             inner: self.gas_price_estimator.as_ref(),
             gas_price_cap: self.gas_price_cap,
@@ -229,6 +310,7 @@ impl SolutionSubmitter {
         };
         let submitter = Submitter::new(
             &self.contract,
+            self.contract_version,
             account,
             strategy_args.submit_api.as_ref(),
             &gas_price_estimator,
@@ -260,6 +342,8 @@ pub enum SubmissionError {
     Canceled(TransactionHash),
     /// The submission is disabled
     Disabled(DisabledReason),
+    /// Orders of the settlement were already settled by another transaction
+    AlreadySettled(TransactionHash),
     /// An error occured.
     Other(anyhow::Error),
 }
@@ -273,6 +357,7 @@ impl SubmissionError {
             Self::Revert(_) => SettlementSubmissionOutcome::Revert,
             Self::Canceled(_) => SettlementSubmissionOutcome::Cancel,
             Self::Disabled(_) => SettlementSubmissionOutcome::Disabled,
+            Self::AlreadySettled(_) => SettlementSubmissionOutcome::AlreadySettled,
             Self::Other(_) => SettlementSubmissionOutcome::Failed,
         }
     }
@@ -284,6 +369,7 @@ impl SubmissionError {
             Self::Revert(hash) => Some(*hash),
             Self::Canceled(hash) => Some(*hash),
             Self::Disabled(_) => None,
+            Self::AlreadySettled(_) => None,
             Self::Other(_) => None,
         }
     }
@@ -310,6 +396,9 @@ impl SubmissionError {
             SubmissionError::Disabled(reason) => {
                 anyhow!("transaction disabled, reason: {:?}", reason)
             }
+            SubmissionError::AlreadySettled(hash) => {
+                anyhow!("orders already settled by transaction {:?}", hash)
+            }
             SubmissionError::Other(err) => err,
         }
     }
@@ -322,6 +411,9 @@ impl SubmissionError {
             SubmissionError::Canceled(_) => true,
             SubmissionError::Other(_) => false,
             SubmissionError::Disabled(_) => false,
+            // Our transaction can no longer be mined successfully so there is no point in
+            // continuing with other strategies.
+            SubmissionError::AlreadySettled(_) => true,
         }
     }
 }
@@ -356,7 +448,9 @@ impl From<MethodError> for SubmissionError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::settlement::{OrderTrade, Trade};
     use ethcontract::H256;
+    use model::order::{Order, OrderBuilder};
     use submitter::MockTransactionSubmitting;
 
     impl PartialEq for SubmissionError {
@@ -427,6 +521,47 @@ mod tests {
         assert!(strategy.strategy_args().is_none());
     }
 
+    #[test]
+    fn private_orders_restrict_strategies() {
+        let order = |private_submission| {
+            OrderBuilder::default()
+                .with_private_submission(private_submission)
+                .build()
+        };
+        let settlement = |orders: Vec<Order>| {
+            let trades = orders
+                .into_iter()
+                .map(|order| OrderTrade {
+                    trade: Trade {
+                        order,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .collect();
+            Settlement::with_trades(Default::default(), trades, Default::default())
+        };
+
+        let public = settlement(vec![order(false), order(false)]);
+        assert_eq!(
+            SubmissionPolicy::for_settlement(&public),
+            SubmissionPolicy::Any
+        );
+        let private = settlement(vec![order(false), order(true)]);
+        assert_eq!(
+            SubmissionPolicy::for_settlement(&private),
+            SubmissionPolicy::PrivateOnly
+        );
+
+        let custom_nodes = TransactionStrategy::CustomNodes(StrategyArgs::default());
+        let flashbots = TransactionStrategy::Flashbots(StrategyArgs::default());
+        let eden = TransactionStrategy::Eden(StrategyArgs::default());
+        assert!(custom_nodes.allows(SubmissionPolicy::Any));
+        assert!(!custom_nodes.allows(SubmissionPolicy::PrivateOnly));
+        assert!(flashbots.allows(SubmissionPolicy::PrivateOnly));
+        assert!(eden.allows(SubmissionPolicy::PrivateOnly));
+    }
+
     #[test]
     fn global_tx_pool() {
         let sender = Address::default();
@@ -1,8 +1,8 @@
 //! Dry run settlement submission strategy. I.e. just log!
 
 use crate::{
-    settlement::Settlement, settlement_simulation::settle_method_builder,
-    settlement_simulation::tenderly_link,
+    encoding::SettlementContractVersion, settlement::Settlement,
+    settlement_simulation::settle_method_builder, settlement_simulation::tenderly_link,
 };
 use anyhow::Result;
 use contracts::GPv2Settlement;
@@ -12,12 +12,13 @@ use web3::types::TransactionReceipt;
 pub async fn log_settlement(
     account: Account,
     contract: &GPv2Settlement,
+    version: SettlementContractVersion,
     settlement: Settlement,
 ) -> Result<TransactionReceipt> {
     let web3 = contract.raw_instance().web3();
     let current_block = web3.eth().block_number().await?;
     let network = web3.net().version().await?;
-    let settlement = settle_method_builder(contract, settlement.into(), account).tx;
+    let settlement = settle_method_builder(contract, version, settlement.into(), account).tx;
     let simulation_link = tenderly_link(current_block.as_u64(), &network, settlement);
 
     tracing::info!("not submitting transaction in dry-run mode");
@@ -47,6 +48,7 @@ mod tests {
         assert!(log_settlement(
             Account::Local(H160([2; 20]), None),
             &GPv2Settlement::at(&web3, H160([1; 20])),
+            SettlementContractVersion::V1,
             Settlement::new(Default::default()),
         )
         .await
@@ -0,0 +1,268 @@
+//! Submission through a third-party relayer that executes settlements on behalf of the solver.
+//!
+//! Solvers submitting through a relayer don't need to hold ETH for gas. Before a settlement gets
+//! handed to the relayer, an interaction is appended that pays the relayer's fee in the native
+//! token from the settlement contract's buffer, so the relayer gets compensated by the same
+//! transaction it executes. Since only allow-listed solvers can call `settle`, the account the
+//! relayer executes transactions from has to be allow-listed.
+//!
+//! Settlements are relayed with `POST relays` and the resulting task is polled with
+//! `GET tasks/{task_id}` relative to the relayer's URL.
+
+use super::{submitter::SubmitterParams, SubmissionError, ESTIMATE_GAS_LIMIT_FACTOR};
+use crate::{
+    encoding::SettlementContractVersion, interactions::Erc20TransferInteraction,
+    settlement::Settlement, settlement_simulation::call_data,
+};
+use anyhow::{anyhow, Context, Result};
+use contracts::{GPv2Settlement, ERC20};
+use gas_estimation::{GasPrice1559, GasPriceEstimating};
+use primitive_types::{H160, H256, U256};
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use shared::Web3;
+use std::time::Instant;
+use web3::types::TransactionReceipt;
+
+/// Gas used by the interaction paying the relayer.
+const PAYMENT_GAS: u64 = 30_000;
+
+pub struct RelayerApi {
+    client: Client,
+    url: Url,
+    api_key: Option<String>,
+    /// The token the relayer gets paid in. The settlement contract's buffer of it has to cover
+    /// the fees.
+    native_token: ERC20,
+    fee_recipient: H160,
+    /// Factor by which the fee exceeds the estimated cost of executing the settlement.
+    fee_factor: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RelayRequest {
+    chain_id: u64,
+    target: H160,
+    #[serde(with = "model::bytes_hex")]
+    data: Vec<u8>,
+    gas_limit: U256,
+    fee_token: H160,
+    fee: U256,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RelayResponse {
+    task_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum TaskState {
+    Pending,
+    Executed,
+    Reverted,
+    Cancelled,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TaskStatus {
+    state: TaskState,
+    transaction_hash: Option<H256>,
+}
+
+impl RelayerApi {
+    pub fn new(
+        client: Client,
+        url: Url,
+        api_key: Option<String>,
+        native_token: ERC20,
+        fee_recipient: H160,
+        fee_factor: f64,
+    ) -> Self {
+        Self {
+            client,
+            url,
+            api_key,
+            native_token,
+            fee_recipient,
+            fee_factor,
+        }
+    }
+
+    /// Relays the settlement with the relayer's payment and waits until the relayer executed it.
+    pub async fn relay(
+        &self,
+        web3: &Web3,
+        contract: &GPv2Settlement,
+        version: SettlementContractVersion,
+        gas_price_estimator: &dyn GasPriceEstimating,
+        settlement: Settlement,
+        params: SubmitterParams,
+    ) -> Result<TransactionReceipt, SubmissionError> {
+        let gas_limit = gas_limit(params.gas_estimate);
+        let gas_price = gas_price_estimator
+            .estimate_with_limits(gas_limit.to_f64_lossy(), params.target_confirm_time)
+            .await
+            .context("failed to estimate gas price")?;
+        let fee = fee(gas_limit, &gas_price, self.fee_factor);
+        let settlement = self.with_payment(settlement, fee);
+
+        let request = RelayRequest {
+            chain_id: params
+                .network_id
+                .parse()
+                .with_context(|| format!("bad network id {}", params.network_id))?,
+            target: contract.address(),
+            data: call_data(version, settlement.into()),
+            gas_limit,
+            fee_token: self.native_token.address(),
+            fee,
+        };
+        let task_id = self.submit(&request).await?;
+        tracing::info!(%task_id, %fee, "relayed settlement");
+
+        loop {
+            if matches!(params.deadline, Some(deadline) if Instant::now() >= deadline) {
+                return Err(SubmissionError::Timeout);
+            }
+            tokio::time::sleep(params.retry_interval).await;
+            let status = match self.status(&task_id).await {
+                Ok(status) => status,
+                Err(err) => {
+                    tracing::warn!(?err, %task_id, "failed to query relayed settlement");
+                    continue;
+                }
+            };
+            match (status.state, status.transaction_hash) {
+                (TaskState::Pending, _) => continue,
+                (TaskState::Executed, Some(hash)) => {
+                    let receipt =
+                        web3.eth().transaction_receipt(hash).await?.ok_or_else(|| {
+                            anyhow!("no receipt for relayed transaction {:?}", hash)
+                        })?;
+                    return Ok(receipt);
+                }
+                (TaskState::Executed, None) => {
+                    return Err(
+                        anyhow!("relayer executed task {} without a transaction", task_id).into(),
+                    )
+                }
+                (TaskState::Reverted, Some(hash)) => return Err(SubmissionError::Revert(hash)),
+                (TaskState::Reverted, None) => return Err(SubmissionError::SimulationRevert(None)),
+                (TaskState::Cancelled, _) => {
+                    return Err(anyhow!("relayer cancelled task {}", task_id).into())
+                }
+            }
+        }
+    }
+
+    /// Appends the interaction paying the relayer to the end of the settlement.
+    fn with_payment(&self, mut settlement: Settlement, fee: U256) -> Settlement {
+        settlement
+            .encoder
+            .append_to_execution_plan(Erc20TransferInteraction {
+                token: self.native_token.clone(),
+                receiver: self.fee_recipient,
+                amount: fee,
+            });
+        settlement
+    }
+
+    async fn submit(&self, request: &RelayRequest) -> Result<String> {
+        let mut builder = self.client.post(self.url.join("relays")?).json(request);
+        if let Some(api_key) = &self.api_key {
+            builder = builder.bearer_auth(api_key);
+        }
+        let response: RelayResponse = builder
+            .send()
+            .await
+            .context("failed sending relay request")?
+            .error_for_status()?
+            .json()
+            .await
+            .context("bad relay response")?;
+        Ok(response.task_id)
+    }
+
+    async fn status(&self, task_id: &str) -> Result<TaskStatus> {
+        let mut builder = self
+            .client
+            .get(self.url.join(&format!("tasks/{}", task_id))?);
+        if let Some(api_key) = &self.api_key {
+            builder = builder.bearer_auth(api_key);
+        }
+        Ok(builder.send().await?.error_for_status()?.json().await?)
+    }
+}
+
+fn gas_limit(gas_estimate: U256) -> U256 {
+    U256::from_f64_lossy(gas_estimate.to_f64_lossy() * ESTIMATE_GAS_LIMIT_FACTOR) + PAYMENT_GAS
+}
+
+/// The fee in native token paying for the gas of the settlement.
+fn fee(gas_limit: U256, gas_price: &GasPrice1559, fee_factor: f64) -> U256 {
+    U256::from_f64_lossy(gas_limit.to_f64_lossy() * gas_price.effective_gas_price() * fee_factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settlement::Interaction;
+    use shared::dummy_contract;
+
+    #[test]
+    fn fee_covers_gas_limit() {
+        let gas_limit = gas_limit(100_000.into());
+        assert_eq!(gas_limit, 150_000.into());
+
+        let gas_price = GasPrice1559 {
+            base_fee_per_gas: 10e9,
+            max_fee_per_gas: 20e9,
+            max_priority_fee_per_gas: 2e9,
+        };
+        assert_eq!(
+            fee(gas_limit, &gas_price, 1.5),
+            U256::from(150_000u64 * 12_000_000_000 * 3 / 2)
+        );
+    }
+
+    #[test]
+    fn appends_payment() {
+        let relayer = RelayerApi::new(
+            Client::new(),
+            "https://relayer.example/".parse().unwrap(),
+            None,
+            dummy_contract!(ERC20, [0x01; 20]),
+            H160([0x02; 20]),
+            1.,
+        );
+        let settlement = relayer.with_payment(Settlement::new(Default::default()), 42.into());
+        let interactions = settlement.encoder.finish().interactions[1].clone();
+        let transfer = Erc20TransferInteraction {
+            token: dummy_contract!(ERC20, [0x01; 20]),
+            receiver: H160([0x02; 20]),
+            amount: 42.into(),
+        };
+        assert_eq!(interactions, transfer.encode());
+    }
+
+    #[test]
+    fn deserializes_task_status() {
+        let status: TaskStatus = serde_json::from_str(
+            r#"{
+                "state": "executed",
+                "transactionHash": "0x0101010101010101010101010101010101010101010101010101010101010101"
+            }"#,
+        )
+        .unwrap();
+        assert!(matches!(status.state, TaskState::Executed));
+        assert_eq!(status.transaction_hash, Some(H256([1; 32])));
+
+        let status: TaskStatus = serde_json::from_str(r#"{"state": "pending"}"#).unwrap();
+        assert!(matches!(status.state, TaskState::Pending));
+        assert_eq!(status.transaction_hash, None);
+    }
+}
@@ -20,22 +20,32 @@ pub mod flashbots_api;
 
 use super::{SubTxPoolRef, SubmissionError, ESTIMATE_GAS_LIMIT_FACTOR};
 use crate::{
-    settlement::Settlement, settlement_access_list::AccessListEstimating,
-    settlement_simulation::settle_method_builder,
+    encoding::SettlementContractVersion,
+    execution_guard,
+    settlement::Settlement,
+    settlement_access_list::AccessListEstimating,
+    settlement_simulation::{call_data, settle_method_builder},
 };
 use anyhow::{anyhow, ensure, Context, Result};
 use contracts::GPv2Settlement;
-use ethcontract::{contract::MethodBuilder, transaction::TransactionBuilder, Account};
+use ethcontract::{
+    common::abi::{self, Token},
+    contract::MethodBuilder,
+    transaction::TransactionBuilder,
+    Account, BlockId, BlockNumber,
+};
 use futures::FutureExt;
 use gas_estimation::{GasPrice1559, GasPriceEstimating};
+use model::order::OrderUid;
 use primitive_types::{H256, U256};
 use shared::{Web3, Web3Transport};
 use std::{
+    collections::HashSet,
     fmt,
     num::NonZeroU8,
     time::{Duration, Instant},
 };
-use web3::types::{AccessList, TransactionReceipt, U64};
+use web3::types::{AccessList, TransactionId, TransactionReceipt, U64};
 
 /// Minimal gas price replacement factor
 const GAS_PRICE_BUMP: f64 = 1.125;
@@ -188,6 +198,7 @@ impl GasPriceEstimating for SubmitterGasPriceEstimator<'_> {
 
 pub struct Submitter<'a> {
     contract: &'a GPv2Settlement,
+    contract_version: SettlementContractVersion,
     account: &'a Account,
     submit_api: &'a dyn TransactionSubmitting,
     gas_price_estimator: &'a SubmitterGasPriceEstimator<'a>,
@@ -199,6 +210,7 @@ pub struct Submitter<'a> {
 impl<'a> Submitter<'a> {
     pub fn new(
         contract: &'a GPv2Settlement,
+        contract_version: SettlementContractVersion,
         account: &'a Account,
         submit_api: &'a dyn TransactionSubmitting,
         gas_price_estimator: &'a SubmitterGasPriceEstimator<'a>,
@@ -208,6 +220,7 @@ impl<'a> Submitter<'a> {
     ) -> Result<Self> {
         Ok(Self {
             contract,
+            contract_version,
             account,
             submit_api,
             gas_price_estimator,
@@ -229,6 +242,17 @@ impl<'a> Submitter<'a> {
     ) -> Result<TransactionReceipt, SubmissionError> {
         let nonce = self.nonce().await?;
         let name = self.submit_api.name();
+        let start_block = self.block_number().await?;
+        let order_uids = settlement
+            .encoder
+            .order_trades()
+            .iter()
+            .map(|order_trade| order_trade.trade.order.metadata.uid)
+            .collect::<HashSet<_>>();
+        let traded_orders =
+            traded_orders(&call_data(self.contract_version, settlement.clone().into()))
+                .unwrap_or_default();
+        let guarded_settlement = settlement.clone();
 
         tracing::debug!("starting solution submission at nonce {}", nonce);
 
@@ -254,6 +278,15 @@ impl<'a> Submitter<'a> {
         // Nonce future is used to detect if tx is mined
         let nonce_future = self.wait_for_nonce_to_change(nonce);
 
+        // Detects if another transaction already settled some of our orders in which case our
+        // transaction can no longer succeed and we would only be wasting gas.
+        let settled_elsewhere_future = self.wait_for_orders_to_be_settled_elsewhere(
+            &guarded_settlement,
+            &order_uids,
+            &traded_orders,
+            start_block,
+        );
+
         // If specified, deadline future stops submitting when deadline is reached
         let deadline_future = tokio::time::sleep(match params.deadline {
             Some(deadline) => deadline.saturating_duration_since(Instant::now()),
@@ -269,19 +302,15 @@ impl<'a> Submitter<'a> {
                 tracing::debug!("stopping submission because account nonce changed to {}", new_nonce);
                 Ok(None)
             },
+            settling_transaction = settled_elsewhere_future.fuse() => {
+                tracing::debug!(?settling_transaction, "stopping submission because orders were settled by another transaction. cancelling last submitted transaction...");
+                track_settled_elsewhere(&format!("{name}"));
+                self.cancel_last_transaction(&mut transactions, nonce).await;
+                Err(SubmissionError::AlreadySettled(settling_transaction))
+            },
             _ = deadline_future.fuse() => {
                 tracing::debug!("stopping submission because deadline has been reached. cancelling last submitted transaction...");
-
-                if let Some((_, gas_price)) = transactions.last() {
-                    let gas_price = gas_price.bump(GAS_PRICE_BUMP).ceil();
-                    match self
-                        .cancel_transaction(&gas_price, nonce)
-                        .await
-                    {
-                        Ok(handle) => transactions.push((handle, gas_price)),
-                        Err(err) => tracing::warn!("cancellation failed: {:?}", err),
-                    }
-                }
+                self.cancel_last_transaction(&mut transactions, nonce).await;
                 Ok(None)
             },
         };
@@ -350,6 +379,137 @@ impl<'a> Submitter<'a> {
             .context("transaction_count")
     }
 
+    async fn block_number(&self) -> Result<u64> {
+        Ok(self
+            .contract
+            .raw_instance()
+            .web3()
+            .eth()
+            .block_number()
+            .await
+            .context("block_number")?
+            .as_u64())
+    }
+
+    /// Keep polling the pending block and the settlement contract's trade events starting at
+    /// `from_block` until one of the orders gets traded by a transaction that wasn't sent from our
+    /// account and the settlement can no longer be executed because of it. Returns the hash of
+    /// that transaction.
+    ///
+    /// Competing settlements are detected by the orders they trade and not by their auction
+    /// because the calldata of a settlement doesn't identify the auction it solves. Trading a
+    /// partially fillable order doesn't make the settlement fail as long as the remaining amount
+    /// still covers what it executes, so the filled amounts get checked before giving up.
+    async fn wait_for_orders_to_be_settled_elsewhere(
+        &self,
+        settlement: &Settlement,
+        order_uids: &HashSet<OrderUid>,
+        traded_orders: &HashSet<Vec<u8>>,
+        from_block: u64,
+    ) -> H256 {
+        const POLL_INTERVAL: Duration = Duration::from_secs(1);
+        if order_uids.is_empty() && traded_orders.is_empty() {
+            return futures::future::pending().await;
+        }
+        loop {
+            let pending = match self.find_pending_settling_transaction(traded_orders).await {
+                Ok(hash) => hash,
+                Err(err) => {
+                    tracing::error!("error while checking pending settlements: {:?}", err);
+                    None
+                }
+            };
+            let mined = match self.find_settling_transaction(order_uids, from_block).await {
+                Ok(hash) => hash,
+                Err(err) => {
+                    tracing::error!("error while checking for settled orders: {:?}", err);
+                    None
+                }
+            };
+            if let Some(hash) = mined.or(pending) {
+                // The pending block includes both the mined and the pending transactions.
+                match execution_guard::already_executed_orders(&self.contract, settlement).await {
+                    Ok(executed_orders) if !executed_orders.is_empty() => {
+                        tracing::debug!(?hash, ?executed_orders, "orders settled elsewhere");
+                        return hash;
+                    }
+                    Ok(_) => tracing::debug!(?hash, "remaining amounts still cover the settlement"),
+                    Err(err) => tracing::error!("error while checking filled amounts: {:?}", err),
+                }
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Looks for a transaction of another account in the pending block that trades one of the
+    /// orders.
+    async fn find_pending_settling_transaction(
+        &self,
+        traded_orders: &HashSet<Vec<u8>>,
+    ) -> Result<Option<H256>> {
+        if traded_orders.is_empty() {
+            return Ok(None);
+        }
+        let block = self
+            .contract
+            .raw_instance()
+            .web3()
+            .eth()
+            .block_with_txs(BlockId::Number(BlockNumber::Pending))
+            .await
+            .context("pending block")?;
+        Ok(block
+            .into_iter()
+            .flat_map(|block| block.transactions)
+            .find(|transaction| {
+                transaction.to == Some(self.contract.address())
+                    && transaction.from != Some(self.account.address())
+                    && traded_orders_overlap(&transaction.input.0, traded_orders)
+            })
+            .map(|transaction| transaction.hash))
+    }
+
+    async fn find_settling_transaction(
+        &self,
+        order_uids: &HashSet<OrderUid>,
+        from_block: u64,
+    ) -> Result<Option<H256>> {
+        let events = self
+            .contract
+            .events()
+            .trade()
+            .from_block(BlockNumber::Number(from_block.into()))
+            .to_block(BlockNumber::Latest)
+            .query()
+            .await
+            .context("trade events")?;
+        let candidates = events
+            .into_iter()
+            .filter(|event| {
+                <[u8; 56]>::try_from(event.data.order_uid.0.as_slice())
+                    .map(|uid| order_uids.contains(&OrderUid(uid)))
+                    .unwrap_or(false)
+            })
+            .filter_map(|event| Some(event.meta?.transaction_hash))
+            .collect::<HashSet<_>>();
+        for hash in candidates {
+            let transaction = self
+                .contract
+                .raw_instance()
+                .web3()
+                .eth()
+                .transaction(TransactionId::Hash(hash))
+                .await
+                .context("transaction")?;
+            // Our own transaction getting mined is detected by the nonce changing.
+            let sender = transaction.and_then(|transaction| transaction.from);
+            if sender.is_some() && sender != Some(self.account.address()) {
+                return Ok(Some(hash));
+            }
+        }
+        Ok(None)
+    }
+
     /// Keep polling the account's nonce until it is different from initial_nonce returning the new
     /// nonce.
     async fn wait_for_nonce_to_change(&self, initial_nonce: U256) -> U256 {
@@ -526,10 +686,15 @@ impl<'a> Submitter<'a> {
         nonce: U256,
         gas_limit: f64,
     ) -> MethodBuilder<Web3Transport, ()> {
-        settle_method_builder(self.contract, settlement.into(), self.account.clone())
-            .nonce(nonce)
-            .gas(U256::from_f64_lossy(gas_limit))
-            .gas_price(crate::into_gas_price(gas_price))
+        settle_method_builder(
+            self.contract,
+            self.contract_version,
+            settlement.into(),
+            self.account.clone(),
+        )
+        .nonce(nonce)
+        .gas(U256::from_f64_lossy(gas_limit))
+        .gas_price(crate::into_gas_price(gas_price))
     }
 
     /// Estimate access list and validate
@@ -575,6 +740,22 @@ impl<'a> Submitter<'a> {
             .gas(21000.into())
     }
 
+    /// Cancels the most recently submitted transaction, if any, by replacing it with a noop
+    /// transaction at a higher gas price.
+    async fn cancel_last_transaction(
+        &self,
+        transactions: &mut Vec<(TransactionHandle, GasPrice1559)>,
+        nonce: U256,
+    ) {
+        if let Some((_, gas_price)) = transactions.last() {
+            let gas_price = gas_price.bump(GAS_PRICE_BUMP).ceil();
+            match self.cancel_transaction(&gas_price, nonce).await {
+                Ok(handle) => transactions.push((handle, gas_price)),
+                Err(err) => tracing::warn!("cancellation failed: {:?}", err),
+            }
+        }
+    }
+
     /// Prepare all data needed for cancellation of previously submitted transaction and execute cancellation
     async fn cancel_transaction(
         &self,
@@ -586,6 +767,49 @@ impl<'a> Submitter<'a> {
     }
 }
 
+/// Decodes the orders a `settle` call trades. Each order is identified by the ABI encoding of its
+/// trade without the executed amount. This contains all order parameters and the signature, which
+/// commits to the owner, so two trades have the same identifier exactly if they trade the same
+/// order.
+fn traded_orders(calldata: &[u8]) -> Option<HashSet<Vec<u8>>> {
+    let function = GPv2Settlement::raw_contract().abi.function("settle").ok()?;
+    if calldata.get(..4)? != function.short_signature() {
+        return None;
+    }
+    let params = function.decode_input(&calldata[4..]).ok()?;
+    let (tokens, trades) = match params.as_slice() {
+        [Token::Array(tokens), _, Token::Array(trades), _] => (tokens, trades),
+        _ => return None,
+    };
+    trades
+        .iter()
+        .map(|trade| {
+            let mut fields = match trade {
+                Token::Tuple(fields) if fields.len() == 11 => fields.clone(),
+                _ => return None,
+            };
+            // Replace the token indices with the token addresses.
+            for field in &mut fields[..2] {
+                let index = field.clone().into_uint()?;
+                if index >= tokens.len().into() {
+                    return None;
+                }
+                *field = tokens[index.as_usize()].clone();
+            }
+            // Different settlements can execute different amounts of the same order.
+            fields.remove(9);
+            Some(abi::encode(&fields))
+        })
+        .collect()
+}
+
+/// Whether the calldata is a `settle` call that trades any of the orders.
+fn traded_orders_overlap(calldata: &[u8], orders: &HashSet<Vec<u8>>) -> bool {
+    traded_orders(calldata)
+        .map(|traded| !traded.is_disjoint(orders))
+        .unwrap_or(false)
+}
+
 fn status(receipt: TransactionReceipt) -> Result<TransactionReceipt, SubmissionError> {
     if let Some(status) = receipt.status {
         if status == U64::zero() {
@@ -634,6 +858,9 @@ struct Metrics {
     /// Tracks how many transactions get successfully mined by the different submission strategies.
     #[metric(labels("submitter"))]
     mined_transactions: prometheus::IntCounterVec,
+    /// Tracks how often submission was aborted because another transaction settled our orders.
+    #[metric(labels("submitter"))]
+    settled_elsewhere: prometheus::IntCounterVec,
 }
 
 pub(crate) fn track_submission_success(submitter: &str, was_successful: bool) {
@@ -653,6 +880,14 @@ fn track_mined_transactions(submitter: &str) {
         .inc();
 }
 
+fn track_settled_elsewhere(submitter: &str) {
+    Metrics::instance(global_metrics::get_metric_storage_registry())
+        .expect("unexpected error getting metrics instance")
+        .settled_elsewhere
+        .with_label_values(&[submitter])
+        .inc();
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -662,7 +897,8 @@ mod tests {
 
     use super::super::submitter::flashbots_api::FlashbotsApi;
     use super::*;
-    use ethcontract::PrivateKey;
+    use crate::encoding::{EncodedSettlement, EncodedTrade};
+    use ethcontract::{Bytes, PrivateKey};
     use gas_estimation::blocknative::BlockNative;
     use reqwest::Client;
     use shared::gas_price_estimation::FakeGasPriceEstimator;
@@ -721,6 +957,7 @@ mod tests {
             crate::settlement_simulation::simulate_and_estimate_gas_at_current_block(
                 std::iter::once((account.clone(), settlement.clone(), None)),
                 &contract,
+                SettlementContractVersion::V1,
                 &web3,
                 Default::default(),
             )
@@ -735,6 +972,7 @@ mod tests {
 
         let submitter = Submitter::new(
             &contract,
+            SettlementContractVersion::V1,
             &account,
             &flashbots_api,
             &gas_price_estimator,
@@ -768,4 +1006,54 @@ mod tests {
         let gas_price_estimator = gas_price_estimator.with_additional_tip(None);
         assert_eq!(gas_price_estimator.max_additional_tip, None);
     }
+
+    #[test]
+    fn detects_settlements_trading_the_same_orders() {
+        let trade = |sell_token: u64, buy_token: u64, sell_amount: u64, executed: u64| {
+            (
+                sell_token.into(),
+                buy_token.into(),
+                H160::zero(),
+                sell_amount.into(),
+                1.into(),
+                0,
+                Bytes([0; 32]),
+                0.into(),
+                Default::default(),
+                executed.into(),
+                Bytes(vec![1; 65]),
+            )
+        };
+        let settle = |tokens: [H160; 2], trades: Vec<EncodedTrade>| {
+            call_data(
+                SettlementContractVersion::V1,
+                EncodedSettlement {
+                    tokens: tokens.to_vec(),
+                    clearing_prices: vec![1.into(), 1.into()],
+                    trades,
+                    interactions: Default::default(),
+                },
+            )
+        };
+        let (a, b) = (H160([1; 20]), H160([2; 20]));
+
+        let ours = traded_orders(&settle([a, b], vec![trade(0, 1, 10, 10)])).unwrap();
+        assert_eq!(ours.len(), 1);
+
+        // The same order executed partially in a settlement with different token indices.
+        let competing = settle([b, a], vec![trade(0, 1, 20, 20), trade(1, 0, 10, 5)]);
+        assert!(traded_orders_overlap(&competing, &ours));
+
+        // Other orders.
+        assert!(!traded_orders_overlap(
+            &settle([a, b], vec![trade(0, 1, 20, 10)]),
+            &ours
+        ));
+        assert!(!traded_orders_overlap(
+            &settle([a, b], vec![trade(1, 0, 10, 10)]),
+            &ours
+        ));
+        // Not a settlement.
+        assert!(!traded_orders_overlap(&[0; 4], &ours));
+    }
 }
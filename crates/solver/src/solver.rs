@@ -3,6 +3,7 @@ use crate::liquidity::order_converter::OrderConverter;
 use crate::metrics::SolverMetrics;
 use crate::settlement::external_prices::ExternalPrices;
 use crate::solver::balancer_sor_solver::BalancerSorSolver;
+use crate::solver_teams::SolverTeams;
 use crate::{
     liquidity::{LimitOrder, Liquidity},
     settlement::Settlement,
@@ -12,7 +13,11 @@ use baseline_solver::BaselineSolver;
 use contracts::{BalancerV2Vault, GPv2Settlement};
 use ethcontract::errors::ExecutionError;
 use ethcontract::{Account, PrivateKey, H160, U256};
-use http_solver::{buffers::BufferRetriever, HttpSolver};
+use http_solver::{
+    flash_loans::{FlashLoanLender, FlashLoanRetriever},
+    liquidity_requests::LiquidityRequests,
+    HttpSolver,
+};
 use model::auction::AuctionId;
 use naive_solver::NaiveSolver;
 use num::BigRational;
@@ -20,10 +25,12 @@ use oneinch_solver::OneInchSolver;
 use paraswap_solver::ParaswapSolver;
 use reqwest::{Client, Url};
 use shared::balancer_sor_api::DefaultBalancerSorApi;
-use shared::http_solver::{DefaultHttpSolverApi, SolverConfig};
+use shared::http_solver::{model::SettlementRejection, DefaultHttpSolverApi, SolverConfig};
 use shared::zeroex_api::ZeroExApi;
 use shared::{
-    baseline_solver::BaseTokens, conversions::U256Ext, token_info::TokenInfoFetching, Web3,
+    baseline_solver::BaseTokens, buffers::BufferRetriever, conversions::U256Ext,
+    hot_reload::Reloadable, realized_gas::RealizedGas, token_amount::TokenAmount,
+    token_info::TokenInfoFetching, Web3,
 };
 use single_order_solver::{SingleOrderSolver, SingleOrderSolving};
 use std::{
@@ -74,6 +81,10 @@ pub trait Solver: Send + Sync + 'static {
     ///
     /// This method is used for logging and metrics collection.
     fn name(&self) -> &str;
+
+    /// Notifies the solver that the driver rejected one of the settlements it computed for the
+    /// auction. Only solvers that can forward the rejection somewhere need to implement this.
+    fn notify_rejection(&self, _auction_id: AuctionId, _rejection: SettlementRejection) {}
 }
 
 /// A batch auction for a solver to produce a settlement for.
@@ -248,15 +259,15 @@ pub fn create(
     network_id: String,
     chain_id: u64,
     disabled_one_inch_protocols: Vec<String>,
-    paraswap_slippage_bps: u32,
+    paraswap_slippage_bps: Reloadable<u32>,
     disabled_paraswap_dexs: Vec<String>,
     paraswap_partner: Option<String>,
     client: Client,
     solver_metrics: Arc<dyn SolverMetrics>,
     zeroex_api: Arc<dyn ZeroExApi>,
-    zeroex_slippage_bps: u32,
+    zeroex_slippage_bps: Reloadable<u32>,
     disabled_zeroex_sources: Vec<String>,
-    oneinch_slippage_bps: u32,
+    oneinch_slippage_bps: Reloadable<u32>,
     quasimodo_uses_internal_buffers: bool,
     mip_uses_internal_buffers: bool,
     one_inch_url: Url,
@@ -266,6 +277,10 @@ pub fn create(
     order_converter: Arc<OrderConverter>,
     max_settlements_per_solver: usize,
     max_merged_settlements: usize,
+    flash_loan_lenders: Vec<FlashLoanLender>,
+    realized_gas: Option<Arc<RealizedGas>>,
+    liquidity_requests: Option<LiquidityRequests>,
+    solver_teams: &SolverTeams,
 ) -> Result<Solvers> {
     // Tiny helper function to help out with type inference. Otherwise, all
     // `Box::new(...)` expressions would have to be cast `as Box<dyn Solver>`.
@@ -277,6 +292,7 @@ pub fn create(
         web3.clone(),
         settlement_contract.address(),
     ));
+    let flash_loan_retriever = Arc::new(FlashLoanRetriever::new(web3.clone(), flash_loan_lenders));
     let allowance_mananger = Arc::new(AllowanceManager::new(
         web3.clone(),
         settlement_contract.address(),
@@ -286,8 +302,14 @@ pub fn create(
     // solvers (which **does** filter out orders with non-fee-connected-tokens),
     // and one for external solvers (which **does not** filter out orders with
     // non-fee-connected-tokens)
-    let http_instance_with_filtered_orders = http_solver::InstanceCache::default();
-    let http_instance_with_all_orders = http_solver::InstanceCache::default();
+    let http_instance_with_filtered_orders = http_solver::InstanceCache::new(
+        "filtered_orders".to_string(),
+        http_solver::DEFAULT_INSTANCE_CACHE_SIZE,
+    );
+    let http_instance_with_all_orders = http_solver::InstanceCache::new(
+        "all_orders".to_string(),
+        http_solver::DEFAULT_INSTANCE_CACHE_SIZE,
+    );
 
     // Helper function to create http solver instances.
     let create_http_solver = |account: Account,
@@ -296,7 +318,7 @@ pub fn create(
                               config: SolverConfig,
                               filter_non_fee_connected_orders: bool|
      -> HttpSolver {
-        HttpSolver::new(
+        let solver = HttpSolver::new(
             DefaultHttpSolverApi {
                 name,
                 network_name: network_id.clone(),
@@ -309,6 +331,7 @@ pub fn create(
             native_token,
             token_info_fetcher.clone(),
             buffer_retriever.clone(),
+            flash_loan_retriever.clone(),
             allowance_mananger.clone(),
             order_converter.clone(),
             if filter_non_fee_connected_orders {
@@ -317,7 +340,15 @@ pub fn create(
                 http_instance_with_all_orders.clone()
             },
             filter_non_fee_connected_orders,
-        )
+        );
+        let solver = match &realized_gas {
+            Some(realized_gas) => solver.with_realized_gas(realized_gas.clone()),
+            None => solver,
+        };
+        match &liquidity_requests {
+            Some(liquidity_requests) => solver.with_liquidity_requests(liquidity_requests.clone()),
+            None => solver,
+        }
     };
 
     let mut solvers: Vec<Arc<dyn Solver>> = solvers
@@ -372,7 +403,7 @@ pub fn create(
                         disabled_one_inch_protocols.clone(),
                         client.clone(),
                         one_inch_url.clone(),
-                        oneinch_slippage_bps,
+                        oneinch_slippage_bps.clone(),
                         oneinch_max_slippage_in_wei,
                         one_inch_referrer_address,
                     )?,
@@ -384,7 +415,7 @@ pub fn create(
                         settlement_contract.clone(),
                         chain_id,
                         zeroex_api.clone(),
-                        zeroex_slippage_bps,
+                        zeroex_slippage_bps.clone(),
                         disabled_zeroex_sources.clone(),
                     )
                     .unwrap();
@@ -395,7 +426,7 @@ pub fn create(
                     web3.clone(),
                     settlement_contract.clone(),
                     token_info_fetcher.clone(),
-                    paraswap_slippage_bps,
+                    paraswap_slippage_bps.clone(),
                     disabled_paraswap_dexs.clone(),
                     client.clone(),
                     paraswap_partner.clone(),
@@ -432,12 +463,16 @@ pub fn create(
         .collect::<Result<_>>()?;
 
     let external_solvers = external_solvers.into_iter().map(|solver| {
+        let account = solver.account.into_account(chain_id);
+        let callback_url = solver_teams.callback_url(account.address());
         shared(create_http_solver(
-            solver.account.into_account(chain_id),
+            account,
             solver.url,
             solver.name,
             SolverConfig {
                 use_internal_buffers: Some(mip_uses_internal_buffers),
+                notify_rejections: true,
+                callback_url,
                 ..Default::default()
             },
             false,
@@ -474,13 +509,11 @@ impl SellVolumeFilteringSolver {
         mut orders: Vec<LimitOrder>,
         external_prices: &ExternalPrices,
     ) -> Vec<LimitOrder> {
-        let is_minimum_volume = |token: &H160, amount: &U256| {
-            let native_amount = external_prices.get_native_amount(*token, amount.to_big_rational());
-            native_amount >= self.min_value
-        };
+        let is_minimum_volume =
+            |amount: TokenAmount| external_prices.get_native_value(&amount) >= self.min_value;
         orders.retain(|order| {
-            is_minimum_volume(&order.buy_token, &order.buy_amount)
-                || is_minimum_volume(&order.sell_token, &order.sell_amount)
+            is_minimum_volume(TokenAmount::new(order.buy_token, order.buy_amount))
+                || is_minimum_volume(TokenAmount::new(order.sell_token, order.sell_amount))
         });
         orders
     }
@@ -507,6 +540,10 @@ impl Solver for SellVolumeFilteringSolver {
     fn name(&self) -> &str {
         self.inner.name()
     }
+
+    fn notify_rejection(&self, auction_id: AuctionId, rejection: SettlementRejection) {
+        self.inner.notify_rejection(auction_id, rejection)
+    }
 }
 
 #[cfg(test)]
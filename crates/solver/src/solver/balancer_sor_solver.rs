@@ -19,7 +19,10 @@ use contracts::{BalancerV2Vault, GPv2Settlement};
 use ethcontract::{Account, Bytes, I256, U256};
 use maplit::hashmap;
 use model::order::OrderKind;
-use shared::balancer_sor_api::{BalancerSorApi, Query, Quote};
+use shared::{
+    balancer_sor_api::{BalancerSorApi, Query, Quote},
+    realized_gas::InteractionClass,
+};
 use std::sync::Arc;
 
 /// A GPv2 solver that matches GP orders to direct 0x swaps.
@@ -124,7 +127,7 @@ impl SingleOrderSolving for BalancerSorSolver {
 
         let mut settlement = Settlement::new(prices);
         settlement.with_liquidity(&order, order.full_execution_amount())?;
-        settlement.encoder.append_to_execution_plan(approval);
+        settlement.encoder.add_approval(approval);
         settlement.encoder.append_to_execution_plan(batch_swap);
 
         Ok(Some(settlement))
@@ -217,6 +220,11 @@ impl Interaction for BatchSwap {
 
         vec![(self.vault.address(), 0.into(), Bytes(calldata))]
     }
+
+    fn gas_class(&self) -> Option<InteractionClass> {
+        // Batch swaps over several pools cost more than a single swap.
+        (self.quote.swaps.len() == 1).then_some(InteractionClass::BalancerSwap)
+    }
 }
 
 #[cfg(test)]
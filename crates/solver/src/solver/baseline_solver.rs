@@ -1,7 +1,7 @@
 use crate::{
     liquidity::{
-        token_pairs, AmmOrderExecution, ConstantProductOrder, LimitOrder, Liquidity,
-        WeightedProductOrder,
+        token_pairs, AmmOrderExecution, ConcentratedLiquidity, ConstantProductOrder, LimitOrder,
+        Liquidity, WeightedProductOrder,
     },
     settlement::Settlement,
     solver::{Auction, Solver},
@@ -53,6 +53,7 @@ struct Amm {
 enum AmmOrder {
     ConstantProduct(ConstantProductOrder),
     WeightedProduct(WeightedProductOrder),
+    Concentrated(ConcentratedLiquidity),
 }
 
 impl BaselineSolvable for ConstantProductOrder {
@@ -83,11 +84,26 @@ impl BaselineSolvable for WeightedProductOrder {
     }
 }
 
+impl BaselineSolvable for ConcentratedLiquidity {
+    fn get_amount_out(&self, out_token: H160, input: (U256, H160)) -> Option<U256> {
+        self.pool.get_amount_out(out_token, input)
+    }
+
+    fn get_amount_in(&self, in_token: H160, output: (U256, H160)) -> Option<U256> {
+        self.pool.get_amount_in(in_token, output)
+    }
+
+    fn gas_cost(&self) -> usize {
+        self.pool.gas_cost()
+    }
+}
+
 impl BaselineSolvable for Amm {
     fn get_amount_out(&self, out_token: H160, input: (U256, H160)) -> Option<U256> {
         match &self.order {
             AmmOrder::ConstantProduct(order) => order.get_amount_out(out_token, input),
             AmmOrder::WeightedProduct(order) => order.get_amount_out(out_token, input),
+            AmmOrder::Concentrated(order) => order.get_amount_out(out_token, input),
         }
     }
 
@@ -95,6 +111,7 @@ impl BaselineSolvable for Amm {
         match &self.order {
             AmmOrder::ConstantProduct(order) => order.get_amount_in(in_token, output),
             AmmOrder::WeightedProduct(order) => order.get_amount_in(in_token, output),
+            AmmOrder::Concentrated(order) => order.get_amount_in(in_token, output),
         }
     }
 
@@ -102,6 +119,7 @@ impl BaselineSolvable for Amm {
         match &self.order {
             AmmOrder::ConstantProduct(order) => order.gas_cost(),
             AmmOrder::WeightedProduct(order) => order.gas_cost(),
+            AmmOrder::Concentrated(order) => order.gas_cost(),
         }
     }
 }
@@ -145,8 +163,12 @@ impl BaselineSolver {
                             tracing::debug!("Excluded stable pool from baseline solving.")
                         }
                         Liquidity::LimitOrder(_) => {}
-                        Liquidity::Concentrated(_) => {} // not being implemented right now since baseline solver
-                                                         // is not winning anyway
+                        Liquidity::Concentrated(order) => {
+                            amm_map.entry(order.tokens).or_default().push(Amm {
+                                tokens: order.tokens,
+                                order: AmmOrder::Concentrated(order),
+                            });
+                        }
                     }
                     amm_map
                 });
@@ -269,6 +291,7 @@ impl Solution {
             match &amm.order {
                 AmmOrder::ConstantProduct(order) => settlement.with_liquidity(order, execution),
                 AmmOrder::WeightedProduct(order) => settlement.with_liquidity(order, execution),
+                AmmOrder::Concentrated(order) => settlement.with_liquidity(order, execution),
             }?;
             sell_amount = buy_amount;
             sell_token = buy_token;
@@ -305,7 +328,13 @@ mod tests {
     use shared::sources::balancer_v2::swap::fixed_point::Bfp;
     use shared::{
         addr,
-        sources::balancer_v2::pool_fetching::{TokenState, WeightedTokenState},
+        sources::{
+            balancer_v2::pool_fetching::{TokenState, WeightedTokenState},
+            uniswap_v3::{
+                graph_api::Token,
+                pool_fetching::{PoolInfo, PoolState},
+            },
+        },
     };
 
     #[test]
@@ -679,4 +708,63 @@ mod tests {
         let settlements = solver.solve_(vec![order], liquidity);
         assert!(settlements.is_empty());
     }
+
+    #[test]
+    fn routes_through_concentrated_liquidity() {
+        let sell_token = H160::from_low_u64_be(1);
+        let buy_token = H160::from_low_u64_be(2);
+        let order_handler = CapturingSettlementHandler::arc();
+        let orders = vec![LimitOrder {
+            sell_token,
+            buy_token,
+            sell_amount: U256::exp10(18),
+            buy_amount: U256::exp10(17) * 9,
+            kind: OrderKind::Sell,
+            settlement_handling: order_handler.clone(),
+            id: "0".into(),
+            ..Default::default()
+        }];
+
+        let amm_handler = CapturingSettlementHandler::arc();
+        let liquidity = vec![Liquidity::Concentrated(ConcentratedLiquidity {
+            tokens: TokenPair::new(sell_token, buy_token).unwrap(),
+            pool: PoolInfo {
+                tokens: vec![
+                    Token {
+                        id: sell_token,
+                        symbol: "A".to_string(),
+                        decimals: 18,
+                    },
+                    Token {
+                        id: buy_token,
+                        symbol: "B".to_string(),
+                        decimals: 18,
+                    },
+                ],
+                state: PoolState {
+                    sqrt_price: U256::one() << 96,
+                    liquidity: U256::exp10(21),
+                    tick: 0.into(),
+                    liquidity_net: vec![
+                        ((-600).into(), 10i128.pow(21).into()),
+                        (600.into(), (-(10i128.pow(21))).into()),
+                    ],
+                    fee: Ratio::new(3, 1000),
+                },
+                ..Default::default()
+            },
+            settlement_handling: amm_handler.clone(),
+        })];
+
+        let base_tokens = Arc::new(BaseTokens::new(H160::zero(), &[]));
+        let solver = BaselineSolver::new(account(), base_tokens);
+        solver.must_solve(orders, liquidity);
+
+        assert_eq!(order_handler.calls(), vec![U256::exp10(18)]);
+        let executions = amm_handler.calls();
+        assert_eq!(executions.len(), 1);
+        assert_eq!(executions[0].input, (sell_token, U256::exp10(18)));
+        assert_eq!(executions[0].output.0, buy_token);
+        assert!(executions[0].output.1 >= U256::exp10(17) * 9);
+    }
 }
@@ -1,7 +1,8 @@
-pub mod buffers;
+pub mod flash_loans;
+pub mod liquidity_requests;
 pub mod settlement;
 
-use self::settlement::SettlementContext;
+use self::{liquidity_requests::LiquidityRequests, settlement::SettlementContext};
 use crate::{
     interactions::allowances::AllowanceManaging,
     liquidity::{order_converter::OrderConverter, Exchange, LimitOrder, Liquidity},
@@ -9,22 +10,28 @@ use crate::{
     solver::{Auction, Solver},
 };
 use anyhow::{anyhow, Context, Result};
-use buffers::{BufferRetrievalError, BufferRetrieving};
 use ethcontract::{errors::ExecutionError, Account, U256};
-use futures::{join, lock::Mutex};
+use flash_loans::FlashLoanRetrieving;
+use futures::{join, lock::Mutex, Future};
+use lru::LruCache;
 use maplit::{btreemap, hashset};
 use model::{auction::AuctionId, order::OrderKind};
 use num::{BigInt, BigRational};
 use primitive_types::H160;
-use shared::http_solver::{DefaultHttpSolverApi, HttpSolverApi};
-use shared::{
-    http_solver::{gas_model::GasModel, model::*},
-    sources::balancer_v2::pools::common::compute_scaling_rate,
+use shared::http_solver::{
+    validation::{SchemaViolation, SchemaViolations},
+    DefaultHttpSolverApi, HttpSolverApi,
 };
 use shared::{
+    buffers::{BufferRetrievalError, BufferRetrieving},
     measure_time,
+    realized_gas::RealizedGas,
     token_info::{TokenInfo, TokenInfoFetching},
 };
+use shared::{
+    http_solver::{gas_model::GasModel, graph::AuctionGraph, model::*},
+    sources::balancer_v2::pools::common::compute_scaling_rate,
+};
 use std::time::Instant;
 use std::{
     collections::{BTreeMap, HashMap, HashSet},
@@ -42,15 +49,99 @@ pub fn is_transaction_failure(error: &ExecutionError) -> bool {
 // TODO: special rounding for the prices we get from the solver?
 
 /// Data shared between multiple instances of the http solver for the same driver run.
-pub struct InstanceData {
-    run_id: u64,
+#[derive(Clone)]
+struct InstanceData {
     model: BatchAuctionModel,
     context: SettlementContext,
 }
 
+/// How many instances an `InstanceCache` keeps by default. All http solvers of a run share its
+/// instance so only the instances of the most recent runs are worth keeping.
+pub const DEFAULT_INSTANCE_CACHE_SIZE: usize = 2;
+
 /// We keep a cache of per solve instance data because it is the same for all http solver
 /// invocations. Without the cache we would duplicate most of the requests to the node.
-pub type InstanceCache = Arc<Mutex<Option<InstanceData>>>;
+///
+/// Instances are keyed by auction and run. The cache is bounded and evicts the least recently
+/// used instance so that instances don't pile up when auctions keep failing to settle.
+#[derive(Clone)]
+pub struct InstanceCache {
+    /// Distinguishes the metrics of caches.
+    label: String,
+    instances: Arc<Mutex<LruCache<(AuctionId, u64), InstanceData>>>,
+}
+
+impl Default for InstanceCache {
+    fn default() -> Self {
+        Self::new("default".to_string(), DEFAULT_INSTANCE_CACHE_SIZE)
+    }
+}
+
+impl InstanceCache {
+    pub fn new(label: String, capacity: usize) -> Self {
+        Self {
+            label,
+            instances: Arc::new(Mutex::new(LruCache::new(capacity.max(1)))),
+        }
+    }
+
+    /// Returns the cached instance of the run or prepares and caches it. Concurrent calls wait for
+    /// the instance being prepared instead of preparing it again.
+    async fn get_or_try_insert_with<Fut>(
+        &self,
+        key: (AuctionId, u64),
+        prepare: impl FnOnce() -> Fut,
+    ) -> Result<InstanceData>
+    where
+        Fut: Future<Output = Result<InstanceData>>,
+    {
+        let metrics = Metrics::get();
+        let mut instances = self.instances.lock().await;
+        if let Some(instance) = instances.get(&key) {
+            metrics
+                .lookups
+                .with_label_values(&[&self.label, "hit"])
+                .inc();
+            return Ok(instance.clone());
+        }
+        metrics
+            .lookups
+            .with_label_values(&[&self.label, "miss"])
+            .inc();
+
+        let instance = prepare().await?;
+        if instances.len() == instances.cap() {
+            instances.pop_lru();
+            metrics.evictions.with_label_values(&[&self.label]).inc();
+        }
+        instances.put(key, instance.clone());
+        metrics
+            .entries
+            .with_label_values(&[&self.label])
+            .set(instances.len() as i64);
+        Ok(instance)
+    }
+}
+
+#[derive(prometheus_metric_storage::MetricStorage)]
+#[metric(subsystem = "http_solver_instance_cache")]
+struct Metrics {
+    /// Number of cached instances.
+    #[metric(labels("cache"))]
+    entries: prometheus::IntGaugeVec,
+    /// Instance lookups by whether the instance was cached.
+    #[metric(labels("cache", "result"))]
+    lookups: prometheus::IntCounterVec,
+    /// Instances evicted to make room for the instance of a newer run.
+    #[metric(labels("cache"))]
+    evictions: prometheus::IntCounterVec,
+}
+
+impl Metrics {
+    fn get() -> &'static Self {
+        Metrics::instance(global_metrics::get_metric_storage_registry()).unwrap()
+    }
+}
 
 pub struct HttpSolver {
     solver: DefaultHttpSolverApi,
@@ -58,10 +149,13 @@ pub struct HttpSolver {
     native_token: H160,
     token_info_fetcher: Arc<dyn TokenInfoFetching>,
     buffer_retriever: Arc<dyn BufferRetrieving>,
+    flash_loan_retriever: Arc<dyn FlashLoanRetrieving>,
     allowance_manager: Arc<dyn AllowanceManaging>,
     order_converter: Arc<OrderConverter>,
     instance_cache: InstanceCache,
     filter_non_fee_connected_orders: bool,
+    realized_gas: Option<Arc<RealizedGas>>,
+    liquidity_requests: Option<LiquidityRequests>,
 }
 
 impl HttpSolver {
@@ -72,6 +166,7 @@ impl HttpSolver {
         native_token: H160,
         token_info_fetcher: Arc<dyn TokenInfoFetching>,
         buffer_retriever: Arc<dyn BufferRetrieving>,
+        flash_loan_retriever: Arc<dyn FlashLoanRetrieving>,
         allowance_manager: Arc<dyn AllowanceManaging>,
         order_converter: Arc<OrderConverter>,
         instance_cache: InstanceCache,
@@ -83,10 +178,33 @@ impl HttpSolver {
             native_token,
             token_info_fetcher,
             buffer_retriever,
+            flash_loan_retriever,
             allowance_manager,
             order_converter,
             instance_cache,
             filter_non_fee_connected_orders,
+            realized_gas: None,
+            liquidity_requests: None,
+        }
+    }
+
+    /// Prices interactions for the solver with the gas mined settlements actually used.
+    pub fn with_realized_gas(mut self, realized_gas: Arc<RealizedGas>) -> Self {
+        self.realized_gas = Some(realized_gas);
+        self
+    }
+
+    /// Lets the solver request liquidity missing from its instance while it solves.
+    pub fn with_liquidity_requests(mut self, liquidity_requests: LiquidityRequests) -> Self {
+        self.liquidity_requests = Some(liquidity_requests);
+        self
+    }
+
+    fn gas_model(&self, gas_price: f64) -> GasModel {
+        GasModel {
+            native_token: self.native_token,
+            gas_price,
+            realized_gas: self.realized_gas.clone(),
         }
     }
 
@@ -100,7 +218,7 @@ impl HttpSolver {
         external_prices: ExternalPrices,
     ) -> Result<(BatchAuctionModel, SettlementContext)> {
         let tokens = map_tokens_for_solver(&orders, &liquidity);
-        let (token_infos, buffers_result) = join!(
+        let (token_infos, buffers_result, flash_loans) = join!(
             measure_time(
                 self.token_info_fetcher.get_token_infos(tokens.as_slice()),
                 |duration| tracing::debug!("get_token_infos took {} s", duration.as_secs_f32()),
@@ -109,6 +227,10 @@ impl HttpSolver {
                 self.buffer_retriever.get_buffers(tokens.as_slice()),
                 |duration| tracing::debug!("get_buffers took {} s", duration.as_secs_f32()),
             ),
+            measure_time(
+                self.flash_loan_retriever.get_flash_loans(tokens.as_slice()),
+                |duration| tracing::debug!("get_flash_loans took {} s", duration.as_secs_f32()),
+            ),
         );
 
         let buffers: HashMap<_, _> = buffers_result
@@ -153,12 +275,15 @@ impl HttpSolver {
             // know about.
             tokens.iter().copied().collect()
         };
-        let gas_model = GasModel {
-            native_token: self.native_token,
-            gas_price,
-        };
+        let gas_model = self.gas_model(gas_price);
 
-        let token_models = token_models(&token_infos, &price_estimates, &buffers, &gas_model);
+        let token_models = token_models(
+            &token_infos,
+            &price_estimates,
+            &buffers,
+            &flash_loans,
+            &gas_model,
+        );
         let order_models = order_models(&orders, &fee_connected_tokens, &gas_model);
         let amm_models = amm_models(&liquidity, &gas_model);
         let model = BatchAuctionModel {
@@ -171,12 +296,55 @@ impl HttpSolver {
                 run_id: Some(run_id),
                 gas_price: Some(gas_price),
                 native_token: Some(self.native_token),
+                liquidity_url: self
+                    .liquidity_requests
+                    .as_ref()
+                    .map(|requests| requests.url().clone()),
             }),
         };
         Ok((model, SettlementContext { orders, liquidity }))
     }
 }
 
+/// The limit orders in the liquidity which get sent to solvers together with the auction's orders.
+fn liquidity_orders(liquidity: &[Liquidity]) -> impl Iterator<Item = LimitOrder> + '_ {
+    liquidity.iter().filter_map(|liquidity| match liquidity {
+        Liquidity::LimitOrder(order) => Some(order.clone()),
+        _ => None,
+    })
+}
+
+/// Builds the graph of the instance HTTP solvers get for the auction. Token infos, buffers and
+/// flash loans are left out because they require node queries and don't change how the orders
+/// and the liquidity are connected.
+pub fn auction_graph(auction: &Auction, native_token: H160) -> AuctionGraph {
+    let mut orders = auction.orders.clone();
+    orders.extend(liquidity_orders(&auction.liquidity));
+    let tokens = map_tokens_for_solver(&orders, &auction.liquidity);
+    let token_infos = tokens
+        .iter()
+        .map(|token| (*token, TokenInfo::default()))
+        .collect();
+    let gas_model = GasModel {
+        native_token,
+        gas_price: auction.gas_price,
+        realized_gas: None,
+    };
+    let model = BatchAuctionModel {
+        tokens: token_models(
+            &token_infos,
+            &auction.external_prices.clone().into_http_solver_prices(),
+            &Default::default(),
+            &Default::default(),
+            &gas_model,
+        ),
+        orders: order_models(&orders, &tokens.into_iter().collect(), &gas_model),
+        amms: amm_models(&auction.liquidity, &gas_model),
+        metadata: None,
+    };
+    AuctionGraph::from(&model)
+}
+
 fn map_tokens_for_solver(orders: &[LimitOrder], liquidity: &[Liquidity]) -> Vec<H160> {
     let mut token_set = HashSet::new();
     token_set.extend(
@@ -212,6 +380,7 @@ fn token_models(
     token_infos: &HashMap<H160, TokenInfo>,
     price_estimates: &HashMap<H160, f64>,
     buffers: &HashMap<H160, U256>,
+    flash_loans: &HashMap<H160, Vec<FlashLoanModel>>,
     gas_model: &GasModel,
 ) -> BTreeMap<H160, TokenInfoModel> {
     token_infos
@@ -233,6 +402,7 @@ fn token_models(
                         0
                     }),
                     internal_buffer: buffers.get(address).copied(),
+                    flash_loans: flash_loans.get(address).cloned().unwrap_or_default(),
                 },
             )
         })
@@ -272,6 +442,12 @@ fn order_models(
                     fee: order_fee(order),
                     cost,
                     is_liquidity_order: order.is_liquidity_order,
+                    quote_expiry_block: order.quote_firmness.and_then(|f| f.expiry_block),
+                    quote_last_look: order
+                        .quote_firmness
+                        .map(|f| f.last_look)
+                        .unwrap_or_default(),
+                    min_partial_fill_amount: order.min_partial_fill_amount,
                     mandatory: false,
                     has_atomic_execution: !matches!(order.exchange, Exchange::GnosisProtocol),
                 },
@@ -352,7 +528,7 @@ fn amm_models(liquidity: &[Liquidity], gas_model: &GasModel) -> BTreeMap<usize,
                         BigInt::from(*amm.pool.state.fee.numer()),
                         BigInt::from(*amm.pool.state.fee.denom()),
                     ),
-                    cost: gas_model.cost_for_gas(amm.pool.gas_stats.mean_gas),
+                    cost: gas_model.uniswap_v3_cost(amm.pool.gas_stats.mean_gas),
                     mandatory: false,
                 },
             })
@@ -409,6 +585,7 @@ impl Solver for HttpSolver {
             run,
             mut orders,
             liquidity,
+            liquidity_fetch_block,
             gas_price,
             deadline,
             external_prices,
@@ -418,32 +595,30 @@ impl Solver for HttpSolver {
         if orders.is_empty() {
             return Ok(Vec::new());
         };
-        orders.extend(liquidity.iter().filter_map(|liquidity| match liquidity {
-            Liquidity::LimitOrder(order) => Some(order.clone()),
-            _ => None,
-        }));
-
-        let (model, context) = {
-            let mut guard = self.instance_cache.lock().await;
-            match guard.as_mut() {
-                Some(data) if data.run_id == run => (data.model.clone(), data.context.clone()),
-                _ => {
-                    let (model, context) = self
-                        .prepare_model(id, run, orders, liquidity, gas_price, external_prices)
-                        .await?;
-                    tracing::debug!(
-                        "Problem sent to http solvers (json):\n{}",
-                        serde_json::to_string_pretty(&model).unwrap()
-                    );
-                    *guard = Some(InstanceData {
-                        run_id: run,
-                        model: model.clone(),
-                        context: context.clone(),
-                    });
-                    (model, context)
-                }
-            }
-        };
+        orders.extend(liquidity_orders(&liquidity));
+
+        let InstanceData { model, mut context } = self
+            .instance_cache
+            .get_or_try_insert_with((id, run), || async move {
+                let (model, context) = self
+                    .prepare_model(id, run, orders, liquidity, gas_price, external_prices)
+                    .await?;
+                tracing::debug!(
+                    "Problem sent to http solvers (json):\n{}",
+                    serde_json::to_string_pretty(&model).unwrap()
+                );
+                Ok(InstanceData { model, context })
+            })
+            .await?;
+        if let Some(requests) = &self.liquidity_requests {
+            requests.register(
+                (id, run),
+                liquidity_fetch_block,
+                deadline,
+                self.gas_model(gas_price),
+                context.liquidity.len(),
+            );
+        }
 
         let timeout = deadline
             .checked_duration_since(Instant::now())
@@ -464,6 +639,34 @@ impl Solver for HttpSolver {
             serde_json::to_string_pretty(&settled).unwrap()
         );
 
+        let flash_loan_fees =
+            flash_loans::check_flash_loans(&model.tokens, &settled.interaction_data)?;
+
+        if let Some(requests) = &self.liquidity_requests {
+            let instance_liquidity = context.liquidity.len();
+            context.liquidity.extend(requests.requested((id, run)));
+            // Validating the solution skipped the AMMs that aren't part of the instance.
+            let mut unknown_amms = settled
+                .amms
+                .keys()
+                .copied()
+                .filter(|index| {
+                    !model.amms.contains_key(index)
+                        && !(instance_liquidity..context.liquidity.len()).contains(index)
+                })
+                .collect::<Vec<_>>();
+            unknown_amms.sort_unstable();
+            if !unknown_amms.is_empty() {
+                return Err(SchemaViolations(
+                    unknown_amms
+                        .into_iter()
+                        .map(|index| SchemaViolation::UnknownAmm { index })
+                        .collect(),
+                )
+                .into());
+            }
+        }
+
         match settlement::convert_settlement(
             settled.clone(),
             context,
@@ -471,7 +674,12 @@ impl Solver for HttpSolver {
             self.order_converter.clone(),
         )
         .await
-        {
+        .and_then(|mut settlement| {
+            for (token, amount) in flash_loan_fees {
+                settlement.encoder.add_flash_loan_fee(token, amount)?;
+            }
+            Ok(settlement)
+        }) {
             Ok(settlement) => Ok(vec![settlement]),
             Err(err) => {
                 tracing::debug!(
@@ -490,6 +698,13 @@ impl Solver for HttpSolver {
     fn name(&self) -> &str {
         &self.solver.name
     }
+
+    fn notify_rejection(&self, auction_id: AuctionId, rejection: SettlementRejection) {
+        self.solver.notify_rejection(&RejectionModel {
+            auction_id,
+            rejection,
+        });
+    }
 }
 
 #[cfg(test)]
@@ -497,18 +712,61 @@ mod tests {
     use super::*;
     use crate::interactions::allowances::MockAllowanceManaging;
     use crate::liquidity::{tests::CapturingSettlementHandler, ConstantProductOrder, LimitOrder};
-    use crate::solver::http_solver::buffers::MockBufferRetrieving;
+    use crate::solver::http_solver::flash_loans::MockFlashLoanRetrieving;
     use ::model::TokenPair;
     use ethcontract::Address;
     use maplit::hashmap;
     use num::rational::Ratio;
     use reqwest::Client;
-    use shared::http_solver::SolverConfig;
+    use shared::buffers::MockBufferRetrieving;
+    use shared::http_solver::{graph::EdgeKind, SolverConfig};
     use shared::token_info::MockTokenInfoFetching;
     use shared::token_info::TokenInfo;
     use std::sync::Arc;
     use std::time::Duration;
 
+    #[tokio::test]
+    async fn instance_cache_evicts_least_recently_used_run() {
+        // Returns whether the instance had to be prepared.
+        async fn get(cache: &InstanceCache, key: (AuctionId, u64)) -> bool {
+            let mut prepared = false;
+            cache
+                .get_or_try_insert_with(key, || {
+                    prepared = true;
+                    async {
+                        Ok(InstanceData {
+                            model: Default::default(),
+                            context: SettlementContext {
+                                orders: Vec::new(),
+                                liquidity: Vec::new(),
+                            },
+                        })
+                    }
+                })
+                .await
+                .unwrap();
+            prepared
+        }
+
+        let cache = InstanceCache::new("test".to_string(), 2);
+        assert!(get(&cache, (1, 1)).await);
+        assert!(!get(&cache, (1, 1)).await);
+        assert!(get(&cache, (2, 2)).await);
+        assert!(get(&cache, (3, 3)).await);
+        assert_eq!(cache.instances.lock().await.len(), 2);
+        // The first run got evicted while the others are still cached.
+        assert!(!get(&cache, (2, 2)).await);
+        assert!(!get(&cache, (3, 3)).await);
+        assert!(get(&cache, (1, 1)).await);
+
+        // Failing to prepare an instance doesn't cache anything.
+        let result = cache
+            .get_or_try_insert_with((4, 4), || async { Err(anyhow!("failed")) })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(cache.instances.lock().await.len(), 2);
+    }
+
     // cargo test real_solver -- --ignored --nocapture
     // set the env variable GP_V2_OPTIMIZER_URL to use a non localhost optimizer
     #[tokio::test]
@@ -543,6 +801,11 @@ mod tests {
                 }
             });
 
+        let mut mock_flash_loan_retriever = MockFlashLoanRetrieving::new();
+        mock_flash_loan_retriever
+            .expect_get_flash_loans()
+            .return_once(|_| HashMap::new());
+
         let gas_price = 100.;
 
         let solver = HttpSolver::new(
@@ -558,6 +821,7 @@ mod tests {
             H160::zero(),
             Arc::new(mock_token_info_fetcher),
             Arc::new(mock_buffer_retriever),
+            Arc::new(mock_flash_loan_retriever),
             Arc::new(MockAllowanceManaging::new()),
             Arc::new(OrderConverter::test(H160([0x42; 20]))),
             Default::default(),
@@ -626,6 +890,7 @@ mod tests {
         let gas_model = GasModel {
             gas_price: 1e9,
             native_token,
+            realized_gas: None,
         };
 
         let amms = [(native_token, tokens[0]), (tokens[0], tokens[1])]
@@ -670,6 +935,73 @@ mod tests {
         assert_eq!(order_models.len(), 6);
     }
 
+    #[test]
+    fn auction_graph_includes_orders_and_liquidity() {
+        let tokens = [H160([1; 20]), H160([2; 20]), H160([3; 20])];
+        let auction = Auction {
+            orders: vec![LimitOrder {
+                sell_token: tokens[0],
+                buy_token: tokens[1],
+                sell_amount: 1.into(),
+                buy_amount: 1.into(),
+                ..Default::default()
+            }],
+            liquidity: vec![
+                Liquidity::ConstantProduct(ConstantProductOrder {
+                    tokens: TokenPair::new(tokens[1], tokens[2]).unwrap(),
+                    reserves: (1, 1),
+                    fee: 0.into(),
+                    settlement_handling: CapturingSettlementHandler::arc(),
+                }),
+                Liquidity::LimitOrder(LimitOrder {
+                    sell_token: tokens[2],
+                    buy_token: tokens[0],
+                    sell_amount: 1.into(),
+                    buy_amount: 1.into(),
+                    is_liquidity_order: true,
+                    ..Default::default()
+                }),
+            ],
+            ..Default::default()
+        };
+
+        let graph = auction_graph(&auction, tokens[0]);
+        assert_eq!(
+            graph
+                .nodes
+                .iter()
+                .map(|node| node.token)
+                .collect::<Vec<_>>(),
+            tokens
+        );
+        assert_eq!(
+            graph
+                .edges
+                .iter()
+                .map(|edge| (edge.source, edge.target, edge.kind))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    tokens[0],
+                    tokens[1],
+                    EdgeKind::Order {
+                        id: 0,
+                        is_liquidity_order: false
+                    }
+                ),
+                (
+                    tokens[2],
+                    tokens[0],
+                    EdgeKind::Order {
+                        id: 1,
+                        is_liquidity_order: true
+                    }
+                ),
+                (tokens[1], tokens[2], EdgeKind::Amm { id: 0 }),
+            ]
+        );
+    }
+
     #[test]
     fn decode_response() {
         let example_response = r#"
@@ -0,0 +1,323 @@
+//! Flash loans allow solvers to settle large orders without holding the traded tokens in the
+//! settlement contract's buffers. The http solver instance annotates every token with the flash
+//! loans that are available for it and solutions can take them through wrapper interactions.
+
+use anyhow::{anyhow, ensure, Context, Result};
+use contracts::ERC20;
+use ethcontract::{batch::CallBatch, H160, U256};
+use futures::future::join_all;
+use num::BigRational;
+use number_conversions::big_rational_to_u256;
+use shared::{
+    conversions::U256Ext as _,
+    http_solver::model::{FlashLoanModel, FlashLoanProtocol, InteractionData, TokenInfoModel},
+    Web3,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
+use web3::{signing::keccak256, types::CallRequest};
+
+const MAX_BATCH_SIZE: usize = 100;
+
+/// A contract tokens can be flash borrowed from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlashLoanLender {
+    pub protocol: FlashLoanProtocol,
+    /// The Balancer vault or the Aave lending pool.
+    pub address: H160,
+    /// The fee as a fraction of the borrowed amount.
+    pub fee: f64,
+}
+
+impl FromStr for FlashLoanLender {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('|');
+        let protocol = parts.next().ok_or_else(|| anyhow!("missing protocol"))?;
+        let address = parts.next().ok_or_else(|| anyhow!("missing address"))?;
+        let fee = parts.next().ok_or_else(|| anyhow!("missing fee"))?;
+        let protocol = match protocol {
+            "BalancerV2" => FlashLoanProtocol::BalancerV2,
+            "AaveV2" => FlashLoanProtocol::AaveV2,
+            _ => return Err(anyhow!("unknown protocol {}", protocol)),
+        };
+        let fee = fee.parse::<f64>().context("parse fee")?;
+        ensure!((0.0..1.0).contains(&fee), "fee must be a fraction");
+        Ok(Self {
+            protocol,
+            address: address.parse().context("parse address")?,
+            fee,
+        })
+    }
+}
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait::async_trait]
+pub trait FlashLoanRetrieving: Send + Sync {
+    /// Returns the flash loans available for the tokens. Tokens without any are omitted.
+    async fn get_flash_loans(&self, tokens: &[H160]) -> HashMap<H160, Vec<FlashLoanModel>>;
+}
+
+pub struct FlashLoanRetriever {
+    web3: Web3,
+    lenders: Vec<FlashLoanLender>,
+}
+
+impl FlashLoanRetriever {
+    pub fn new(web3: Web3, lenders: Vec<FlashLoanLender>) -> Self {
+        Self { web3, lenders }
+    }
+
+    /// Returns the contracts holding the lender's liquidity of the tokens.
+    async fn liquidity_holders(
+        &self,
+        lender: &FlashLoanLender,
+        tokens: &[H160],
+    ) -> Vec<(H160, H160)> {
+        match lender.protocol {
+            FlashLoanProtocol::BalancerV2 => tokens
+                .iter()
+                .map(|&token| (token, lender.address))
+                .collect(),
+            FlashLoanProtocol::AaveV2 => {
+                let a_tokens = join_all(
+                    tokens
+                        .iter()
+                        .map(|&token| aave_a_token(&self.web3, lender.address, token)),
+                )
+                .await;
+                tokens
+                    .iter()
+                    .zip(a_tokens)
+                    .filter_map(|(&token, a_token)| match a_token {
+                        Ok(Some(a_token)) => Some((token, a_token)),
+                        Ok(None) => None,
+                        Err(err) => {
+                            tracing::debug!(?token, ?err, "failed to fetch aave reserve");
+                            None
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl FlashLoanRetrieving for FlashLoanRetriever {
+    async fn get_flash_loans(&self, tokens: &[H160]) -> HashMap<H160, Vec<FlashLoanModel>> {
+        let mut flash_loans = HashMap::<H160, Vec<FlashLoanModel>>::new();
+        for lender in &self.lenders {
+            let holders = self.liquidity_holders(lender, tokens).await;
+            let mut batch = CallBatch::new(self.web3.transport());
+            let futures = holders
+                .iter()
+                .map(|&(token, holder)| {
+                    ERC20::at(&self.web3, token)
+                        .methods()
+                        .balance_of(holder)
+                        .batch_call(&mut batch)
+                })
+                .collect::<Vec<_>>();
+            batch.execute_all(MAX_BATCH_SIZE).await;
+            for ((token, _), balance) in holders.into_iter().zip(join_all(futures).await) {
+                match balance {
+                    Ok(available) if !available.is_zero() => {
+                        flash_loans.entry(token).or_default().push(FlashLoanModel {
+                            protocol: lender.protocol,
+                            lender: lender.address,
+                            available,
+                            fee: lender.fee,
+                        })
+                    }
+                    Ok(_) => (),
+                    Err(err) => {
+                        tracing::debug!(?token, ?err, "failed to fetch flash loan liquidity")
+                    }
+                }
+            }
+        }
+        flash_loans
+    }
+}
+
+/// Returns the aToken holding the lending pool's liquidity of the token or `None` if the token is
+/// not a reserve of the pool.
+async fn aave_a_token(web3: &Web3, lending_pool: H160, token: H160) -> Result<Option<H160>> {
+    // `getReserveData(address)` returns a struct of static fields of which the aToken address is
+    // the eighth.
+    const A_TOKEN_WORD: usize = 7;
+    let mut data = keccak256(b"getReserveData(address)")[..4].to_vec();
+    data.extend_from_slice(&[0; 12]);
+    data.extend_from_slice(token.as_bytes());
+    let result = web3
+        .eth()
+        .call(
+            CallRequest {
+                to: Some(lending_pool),
+                data: Some(data.into()),
+                ..Default::default()
+            },
+            None,
+        )
+        .await?;
+    let a_token = result
+        .0
+        .get(A_TOKEN_WORD * 32 + 12..(A_TOKEN_WORD + 1) * 32)
+        .map(H160::from_slice)
+        .context("short reserve data")?;
+    Ok(Some(a_token).filter(|a_token| !a_token.is_zero()))
+}
+
+/// Verifies that the flash loans taken by a solution's interactions were offered in the instance
+/// and don't exceed the available amounts. Returns the fees the loans cost per token, rounded up.
+pub fn check_flash_loans(
+    tokens: &BTreeMap<H160, TokenInfoModel>,
+    interactions: &[InteractionData],
+) -> Result<HashMap<H160, U256>> {
+    let mut borrowed = HashMap::<(H160, H160), U256>::new();
+    let mut fees = HashMap::<H160, U256>::new();
+    for flash_loan in interactions
+        .iter()
+        .filter_map(|interaction| interaction.flash_loan.as_ref())
+    {
+        for loan in &flash_loan.loans {
+            let total = borrowed.entry((flash_loan.lender, loan.token)).or_default();
+            *total = total
+                .checked_add(loan.amount)
+                .context("flash loan amount overflow")?;
+            let offer = tokens
+                .get(&loan.token)
+                .into_iter()
+                .flat_map(|info| &info.flash_loans)
+                .find(|offer| offer.lender == flash_loan.lender)
+                .with_context(|| {
+                    format!(
+                        "no flash loan of token {:?} from {:?} available",
+                        loan.token, flash_loan.lender
+                    )
+                })?;
+            ensure!(
+                *total <= offer.available,
+                "flash loan of {} of token {:?} from {:?} exceeds the available {}",
+                total,
+                loan.token,
+                flash_loan.lender,
+                offer.available
+            );
+            let rate = BigRational::from_float(offer.fee).context("invalid flash loan fee")?;
+            let fee = big_rational_to_u256(&(loan.amount.to_big_rational() * rate).ceil())?;
+            let total_fee = fees.entry(loan.token).or_default();
+            *total_fee = total_fee
+                .checked_add(fee)
+                .context("flash loan fee overflow")?;
+        }
+    }
+    Ok(fees)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+    use maplit::btreemap;
+    use shared::{
+        http_solver::model::{FlashLoanDataModel, TokenAmount},
+        transport::create_env_test_transport,
+    };
+
+    #[test]
+    fn parse_flash_loan_lender() {
+        assert_eq!(
+            "BalancerV2|0xBA12222222228d8Ba445958a75a0704d566BF2C8|0"
+                .parse::<FlashLoanLender>()
+                .unwrap(),
+            FlashLoanLender {
+                protocol: FlashLoanProtocol::BalancerV2,
+                address: H160(hex!("BA12222222228d8Ba445958a75a0704d566BF2C8")),
+                fee: 0.,
+            }
+        );
+        assert!("AaveV2|0x7d2768dE32b0b80b7a3454c06BdAc94A69DDc7A9|0.0009"
+            .parse::<FlashLoanLender>()
+            .is_ok());
+        assert!("Unknown|0x7d2768dE32b0b80b7a3454c06BdAc94A69DDc7A9|0"
+            .parse::<FlashLoanLender>()
+            .is_err());
+        assert!("AaveV2|0x7d2768dE32b0b80b7a3454c06BdAc94A69DDc7A9|2"
+            .parse::<FlashLoanLender>()
+            .is_err());
+    }
+
+    #[test]
+    fn checks_flash_loans() {
+        let token = H160([1; 20]);
+        let lender = H160([2; 20]);
+        let tokens = btreemap! {
+            token => TokenInfoModel {
+                flash_loans: vec![FlashLoanModel {
+                    protocol: FlashLoanProtocol::AaveV2,
+                    lender,
+                    available: 100.into(),
+                    fee: 0.0009,
+                }],
+                ..Default::default()
+            },
+        };
+        let interaction = |lender, amount: u64| InteractionData {
+            target: H160([3; 20]),
+            value: 0.into(),
+            call_data: vec![],
+            inputs: vec![],
+            outputs: vec![],
+            exec_plan: None,
+            flash_loan: Some(FlashLoanDataModel {
+                lender,
+                loans: vec![TokenAmount {
+                    amount: amount.into(),
+                    token,
+                }],
+            }),
+        };
+
+        assert!(check_flash_loans(&tokens, &[]).unwrap().is_empty());
+        // Fees get rounded up.
+        assert_eq!(
+            check_flash_loans(&tokens, &[interaction(lender, 100)]).unwrap(),
+            HashMap::from([(token, 1.into())])
+        );
+        // Loans from the same lender add up.
+        assert!(
+            check_flash_loans(&tokens, &[interaction(lender, 50), interaction(lender, 51)])
+                .is_err()
+        );
+        assert!(check_flash_loans(&tokens, &[interaction(H160([4; 20]), 1)]).is_err());
+    }
+
+    // cargo test -p solver flash_loans -- --ignored --nocapture
+    #[tokio::test]
+    #[ignore]
+    async fn mainnet_flash_loans() {
+        let web3 = Web3::new(create_env_test_transport());
+        let retriever = FlashLoanRetriever::new(
+            web3,
+            vec![
+                "BalancerV2|0xBA12222222228d8Ba445958a75a0704d566BF2C8|0"
+                    .parse()
+                    .unwrap(),
+                "AaveV2|0x7d2768dE32b0b80b7a3454c06BdAc94A69DDc7A9|0.0009"
+                    .parse()
+                    .unwrap(),
+            ],
+        );
+        let weth = H160(hex!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"));
+        let not_a_token = H160(hex!("badbadbadbadbadbadbadbadbadbadbadbadbadb"));
+        let flash_loans = retriever.get_flash_loans(&[weth, not_a_token]).await;
+        dbg!(&flash_loans);
+        assert_eq!(flash_loans[&weth].len(), 2);
+        assert!(!flash_loans.contains_key(&not_a_token));
+    }
+}
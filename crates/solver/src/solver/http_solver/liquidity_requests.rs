@@ -0,0 +1,358 @@
+//! Lets HTTP solvers request liquidity that isn't part of their instance while they solve.
+//!
+//! Instances only include the liquidity relevant to the auction's orders. Solvers that want to
+//! route through other tokens can request the pools of additional token pairs from the URL in the
+//! instance metadata until the deadline of the run. The pools get fetched from the configured
+//! liquidity sources at the block of the instance's liquidity and are returned as AMMs whose
+//! indices follow those of the instance. Solutions can use requested AMMs like the instance's own.
+
+use super::amm_models;
+use crate::{liquidity::Liquidity, liquidity_collector::LiquidityCollecting};
+use lru::LruCache;
+use model::{auction::AuctionId, TokenPair};
+use prometheus::IntCounterVec;
+use reqwest::Url;
+use shared::{
+    api::{error, extract_payload, internal_error, ApiReply, IntoWarpReply},
+    http_solver::{
+        gas_model::GasModel,
+        model::{LiquidityRequestModel, LiquidityResponseModel},
+    },
+    recent_block_cache::Block,
+};
+use std::{
+    collections::HashSet,
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use tokio::task::{self, JoinHandle};
+use warp::{
+    hyper::StatusCode,
+    reply::{self, with_status},
+    Filter, Rejection,
+};
+
+/// How many runs liquidity can be requested for. Only the most recent runs are still being solved.
+const MAX_RUNS: usize = 4;
+
+#[derive(prometheus_metric_storage::MetricStorage)]
+#[metric(subsystem = "liquidity_requests")]
+struct Metrics {
+    /// Liquidity requests of solvers by result.
+    #[metric(labels("result"))]
+    requests: IntCounterVec,
+}
+
+impl Metrics {
+    fn get() -> &'static Self {
+        Metrics::instance(global_metrics::get_metric_storage_registry()).unwrap()
+    }
+}
+
+struct Run {
+    block: u64,
+    deadline: Instant,
+    gas_model: GasModel,
+    /// The index of the first requested AMM. The instance's liquidity comes before it.
+    first_index: usize,
+    liquidity: Vec<Liquidity>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RequestError {
+    #[error("liquidity can't be requested for this run")]
+    UnknownRun,
+    #[error("the deadline of the run has passed")]
+    DeadlinePassed,
+    #[error("at most {0} token pairs can be requested at once")]
+    TooManyPairs(usize),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl RequestError {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::UnknownRun => "unknown_run",
+            Self::DeadlinePassed => "deadline_passed",
+            Self::TooManyPairs(_) => "too_many_pairs",
+            Self::Other(_) => "error",
+        }
+    }
+}
+
+impl IntoWarpReply for RequestError {
+    fn into_warp_reply(self) -> ApiReply {
+        match self {
+            Self::UnknownRun => {
+                with_status(error("UnknownRun", self.to_string()), StatusCode::NOT_FOUND)
+            }
+            Self::DeadlinePassed => with_status(
+                error("DeadlinePassed", self.to_string()),
+                StatusCode::BAD_REQUEST,
+            ),
+            Self::TooManyPairs(_) => with_status(
+                error("TooManyPairs", self.to_string()),
+                StatusCode::BAD_REQUEST,
+            ),
+            Self::Other(err) => with_status(
+                internal_error(err.context("liquidity_request")),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        }
+    }
+}
+
+/// The liquidity HTTP solvers requested for the runs they are solving.
+#[derive(Clone)]
+pub struct LiquidityRequests {
+    url: Url,
+    max_pairs: usize,
+    runs: Arc<Mutex<LruCache<(AuctionId, u64), Run>>>,
+}
+
+impl LiquidityRequests {
+    /// `base_url` is where solvers reach the server started with `serve`.
+    pub fn new(base_url: &Url, max_pairs: usize) -> Self {
+        Self {
+            url: base_url
+                .join("liquidity")
+                .expect("invalid liquidity request url"),
+            max_pairs,
+            runs: Arc::new(Mutex::new(LruCache::new(MAX_RUNS))),
+        }
+    }
+
+    /// The URL solvers request liquidity from.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Accepts requests for the run until its deadline. Registering a run again keeps what has
+    /// been requested for it already.
+    pub fn register(
+        &self,
+        key: (AuctionId, u64),
+        block: u64,
+        deadline: Instant,
+        gas_model: GasModel,
+        first_index: usize,
+    ) {
+        let mut runs = self.runs.lock().unwrap();
+        if runs.contains(&key) {
+            return;
+        }
+        runs.put(
+            key,
+            Run {
+                block,
+                deadline,
+                gas_model,
+                first_index,
+                liquidity: Vec::new(),
+            },
+        );
+    }
+
+    /// All liquidity requested for the run so far in the order of the AMM indices, starting with
+    /// the run's first requested index.
+    pub fn requested(&self, key: (AuctionId, u64)) -> Vec<Liquidity> {
+        self.runs
+            .lock()
+            .unwrap()
+            .peek(&key)
+            .map(|run| run.liquidity.clone())
+            .unwrap_or_default()
+    }
+
+    async fn request(
+        &self,
+        collector: &dyn LiquidityCollecting,
+        request: LiquidityRequestModel,
+    ) -> Result<LiquidityResponseModel, RequestError> {
+        if request.token_pairs.len() > self.max_pairs {
+            return Err(RequestError::TooManyPairs(self.max_pairs));
+        }
+        let key = (request.auction_id, request.run_id);
+        let (block, deadline) = {
+            let runs = self.runs.lock().unwrap();
+            let run = runs.peek(&key).ok_or(RequestError::UnknownRun)?;
+            (run.block, run.deadline)
+        };
+        if Instant::now() >= deadline {
+            return Err(RequestError::DeadlinePassed);
+        }
+
+        // Pairs of a token with itself have no liquidity.
+        let pairs = request
+            .token_pairs
+            .into_iter()
+            .filter_map(|(token_a, token_b)| TokenPair::new(token_a, token_b))
+            .collect::<HashSet<_>>();
+        let liquidity = tokio::time::timeout_at(
+            deadline.into(),
+            collector.get_liquidity_for_pairs(pairs, Block::Number(block)),
+        )
+        .await
+        .map_err(|_| RequestError::DeadlinePassed)??;
+
+        let mut runs = self.runs.lock().unwrap();
+        // The run got evicted while fetching if many newer runs started in the meantime.
+        let run = runs.peek_mut(&key).ok_or(RequestError::UnknownRun)?;
+        // Liquidity for pairs doesn't contain limit orders so every item becomes an AMM.
+        let first_index = run.first_index + run.liquidity.len();
+        let amms = amm_models(&liquidity, &run.gas_model)
+            .into_iter()
+            .map(|(index, amm)| (first_index + index, amm))
+            .collect();
+        run.liquidity.extend(liquidity);
+        Ok(LiquidityResponseModel { amms })
+    }
+}
+
+fn request() -> impl Filter<Extract = (LiquidityRequestModel,), Error = Rejection> + Clone {
+    warp::path!("liquidity")
+        .and(warp::post())
+        .and(extract_payload())
+}
+
+fn response(result: Result<LiquidityResponseModel, RequestError>) -> ApiReply {
+    match result {
+        Ok(response) => with_status(reply::json(&response), StatusCode::OK),
+        Err(err) => err.into_warp_reply(),
+    }
+}
+
+/// Serves the liquidity requests of solvers on `address`.
+pub fn serve(
+    requests: LiquidityRequests,
+    collector: Arc<dyn LiquidityCollecting>,
+    address: SocketAddr,
+) -> JoinHandle<()> {
+    let filter = request().and_then(move |request| {
+        let requests = requests.clone();
+        let collector = collector.clone();
+        async move {
+            let result = requests.request(collector.as_ref(), request).await;
+            let label = match &result {
+                Ok(_) => "ok",
+                Err(err) => {
+                    tracing::debug!(?err, "failed liquidity request");
+                    err.label()
+                }
+            };
+            Metrics::get().requests.with_label_values(&[label]).inc();
+            Result::<_, Infallible>::Ok(response(result))
+        }
+    });
+    tracing::info!(%address, "serving liquidity requests");
+    task::spawn(warp::serve(filter).bind(address))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        liquidity::{tests::CapturingSettlementHandler, ConstantProductOrder},
+        liquidity_collector::MockLiquidityCollecting,
+    };
+    use num::rational::Ratio;
+    use primitive_types::H160;
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn pool(tokens: (u64, u64)) -> Liquidity {
+        Liquidity::ConstantProduct(ConstantProductOrder {
+            tokens: TokenPair::new(
+                H160::from_low_u64_be(tokens.0),
+                H160::from_low_u64_be(tokens.1),
+            )
+            .unwrap(),
+            reserves: (100, 100),
+            fee: Ratio::new(3, 1000),
+            settlement_handling: CapturingSettlementHandler::arc(),
+        })
+    }
+
+    fn gas_model() -> GasModel {
+        GasModel {
+            native_token: H160::zero(),
+            gas_price: 1.,
+            realized_gas: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn request_filter() {
+        let result = warp::test::request()
+            .path("/liquidity")
+            .method("POST")
+            .json(&json!({
+                "auction_id": 1,
+                "run_id": 2,
+                "token_pairs": [[H160::from_low_u64_be(1), H160::from_low_u64_be(2)]],
+            }))
+            .filter(&request())
+            .await
+            .unwrap();
+        assert_eq!(
+            result,
+            LiquidityRequestModel {
+                auction_id: 1,
+                run_id: 2,
+                token_pairs: vec![(H160::from_low_u64_be(1), H160::from_low_u64_be(2))],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn requested_amms_follow_the_instance() {
+        let mut collector = MockLiquidityCollecting::new();
+        collector
+            .expect_get_liquidity_for_pairs()
+            .withf(|pairs, block| pairs.len() == 1 && *block == Block::Number(10))
+            .returning(|_, _| Ok(vec![pool((1, 2))]));
+        let requests = LiquidityRequests::new(&"http://localhost:9590".parse().unwrap(), 2);
+        assert_eq!(requests.url().as_str(), "http://localhost:9590/liquidity");
+        let deadline = Instant::now() + Duration::from_secs(60);
+        requests.register((1, 1), 10, deadline, gas_model(), 3);
+
+        let request = |run_id, token_pairs| LiquidityRequestModel {
+            auction_id: 1,
+            run_id,
+            token_pairs,
+        };
+        let pair = (H160::from_low_u64_be(1), H160::from_low_u64_be(2));
+        for index in [3, 4] {
+            let response = requests
+                .request(&collector, request(1, vec![pair]))
+                .await
+                .unwrap();
+            assert_eq!(response.amms.keys().copied().collect::<Vec<_>>(), [index]);
+        }
+        assert_eq!(requests.requested((1, 1)), vec![pool((1, 2)); 2]);
+
+        // Registering the run again keeps the requested liquidity.
+        requests.register((1, 1), 11, deadline, gas_model(), 0);
+        assert_eq!(requests.requested((1, 1)).len(), 2);
+
+        assert!(matches!(
+            requests.request(&collector, request(2, vec![pair])).await,
+            Err(RequestError::UnknownRun)
+        ));
+        assert!(matches!(
+            requests
+                .request(&collector, request(1, vec![pair; 3]))
+                .await,
+            Err(RequestError::TooManyPairs(2))
+        ));
+
+        requests.register((1, 2), 10, Instant::now(), gas_model(), 0);
+        assert!(matches!(
+            requests.request(&collector, request(2, vec![pair])).await,
+            Err(RequestError::DeadlinePassed)
+        ));
+    }
+}
@@ -58,6 +58,22 @@ impl Execution {
         }
     }
 
+    /// The token amounts this execution would have sent to the settlement contract. When the
+    /// execution gets internalized these amounts are paid out of the internal buffers instead.
+    fn outputs(&self) -> Vec<(H160, U256)> {
+        match self {
+            Execution::Amm(executed_amm) => vec![executed_amm.output],
+            Execution::CustomInteraction(interaction) => interaction
+                .outputs
+                .iter()
+                .map(|output| (output.token, output.amount))
+                .collect(),
+            Execution::LimitOrder(order) => {
+                vec![(order.order.sell_token, order.executed_sell_amount)]
+            }
+        }
+    }
+
     fn add_to_settlement(&self, settlement: &mut Settlement) -> Result<()> {
         use Execution::*;
 
@@ -167,11 +183,11 @@ impl IntermediateSettlement {
     fn into_settlement(self) -> Result<Settlement> {
         let mut settlement = Settlement::new(self.prices);
 
-        // Make sure to always add approval interactions **before** any
-        // interactions from the execution plan - the execution plan typically
-        // consists of AMM swaps that require these approvals to be in place.
+        // Approval interactions are always encoded **before** any interactions
+        // from the execution plan - the execution plan typically consists of
+        // AMM swaps that require these approvals to be in place.
         for approval in self.approvals {
-            settlement.encoder.append_to_execution_plan(approval);
+            settlement.encoder.add_approval(approval);
         }
 
         for execution in &self.executions {
@@ -184,6 +200,11 @@ impl IntermediateSettlement {
                 // itself which is enough to manually recontruct what the actual
                 // calldata would have been.
                 tracing::debug!(?execution, "internalized AMM execution");
+                for (token, amount) in execution.outputs() {
+                    settlement
+                        .encoder
+                        .add_internal_buffer_usage(token, amount)?;
+                }
 
                 continue;
             }
@@ -591,6 +612,10 @@ mod tests {
             }]
         );
         assert_eq!(internal_amm_handler.calls(), vec![]);
+        assert_eq!(
+            settlement.encoder.internal_buffer_usage(),
+            &hashmap! { t1 => 1.into() }
+        );
         assert_eq!(
             wp_amm_handler.calls(),
             vec![AmmOrderExecution {
@@ -908,6 +933,7 @@ mod tests {
                 sequence: 1u32,
                 position: 1u32,
             })),
+            flash_loan: None,
         }];
         let orders = vec![ExecutedLimitOrder {
             order: Default::default(),
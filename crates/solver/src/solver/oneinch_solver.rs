@@ -24,6 +24,8 @@ use primitive_types::{H160, U256};
 use reqwest::Client;
 use reqwest::Url;
 use shared::conversions::U256Ext;
+use shared::error_class::ClassifyError;
+use shared::hot_reload::Reloadable;
 use shared::oneinch_api::{
     OneInchClient, OneInchClientImpl, ProtocolCache, RestError, RestResponse, Swap, SwapQuery,
 };
@@ -43,7 +45,7 @@ pub struct OneInchSolver {
     #[derivative(Debug = "ignore")]
     allowance_fetcher: Box<dyn AllowanceManaging>,
     protocol_cache: ProtocolCache,
-    oneinch_slippage_bps: u32,
+    oneinch_slippage_bps: Reloadable<u32>,
     /// how much slippage in wei we allow per trade
     max_slippage_in_wei: Option<U256>,
     referrer_address: Option<H160>,
@@ -52,8 +54,8 @@ pub struct OneInchSolver {
 impl From<RestError> for SettlementError {
     fn from(error: RestError) -> Self {
         SettlementError {
-            inner: anyhow!(error.description),
-            retryable: matches!(error.status_code, 500),
+            class: error.class(),
+            inner: anyhow!(error),
         }
     }
 }
@@ -69,7 +71,7 @@ impl OneInchSolver {
         disabled_protocols: impl IntoIterator<Item = String>,
         client: Client,
         one_inch_url: Url,
-        oneinch_slippage_bps: u32,
+        oneinch_slippage_bps: impl Into<Reloadable<u32>>,
         max_slippage_in_wei: Option<U256>,
         referrer_address: Option<H160>,
     ) -> Result<Self> {
@@ -81,7 +83,7 @@ impl OneInchSolver {
             client: Box::new(OneInchClientImpl::new(one_inch_url, client, chain_id)?),
             allowance_fetcher: Box::new(AllowanceManager::new(web3, settlement_address)),
             protocol_cache: ProtocolCache::default(),
-            oneinch_slippage_bps,
+            oneinch_slippage_bps: oneinch_slippage_bps.into(),
             max_slippage_in_wei,
             referrer_address,
         })
@@ -186,7 +188,7 @@ impl OneInchSolver {
 
         settlement.with_liquidity(&order, order.sell_amount)?;
 
-        settlement.encoder.append_to_execution_plan(approval);
+        settlement.encoder.add_approval(approval);
         settlement.encoder.append_to_execution_plan(swap);
 
         Ok(Some(settlement))
@@ -214,6 +216,7 @@ impl SingleOrderSolving for OneInchSolver {
             .protocol_cache
             .get_allowed_protocols(&self.disabled_protocols, self.client.as_ref())
             .await?;
+        let slippage_bps = *self.oneinch_slippage_bps.get();
         let slippage = match self.max_slippage_in_wei {
             Some(wei) => Self::compute_max_slippage(
                 auction.external_prices.price(&order.buy_token).expect(
@@ -221,10 +224,10 @@ impl SingleOrderSolving for OneInchSolver {
                     for buy_token and sell_token are known",
                 ),
                 &order.buy_amount,
-                self.oneinch_slippage_bps,
+                slippage_bps,
                 &wei,
             )?,
-            None => Slippage::percentage_from_basis_points(self.oneinch_slippage_bps).unwrap(),
+            None => Slippage::percentage_from_basis_points(slippage_bps).unwrap(),
         };
         self.settle_order_with_protocols_and_slippage(order, protocols, slippage)
             .await
@@ -271,7 +274,7 @@ mod tests {
             client: Box::new(client),
             allowance_fetcher: Box::new(allowance_fetcher),
             protocol_cache: ProtocolCache::default(),
-            oneinch_slippage_bps: 10u32,
+            oneinch_slippage_bps: 10u32.into(),
             max_slippage_in_wei: Some(U256::MAX),
             referrer_address: None,
         }
@@ -584,7 +587,8 @@ mod tests {
             None,
         )
         .unwrap();
-        let slippage = Slippage::percentage_from_basis_points(solver.oneinch_slippage_bps).unwrap();
+        let slippage =
+            Slippage::percentage_from_basis_points(*solver.oneinch_slippage_bps.get()).unwrap();
         let settlement = solver
             .settle_order_with_protocols_and_slippage(
                 Order {
@@ -15,6 +15,8 @@ use ethcontract::{Account, Bytes, H160, U256};
 use maplit::hashmap;
 use model::order::OrderKind;
 use reqwest::Client;
+use shared::error_class::ClassifyError;
+use shared::hot_reload::Reloadable;
 use shared::paraswap_api::{
     DefaultParaswapApi, ParaswapApi, ParaswapResponseError, PriceQuery, PriceResponse, Side,
     TradeAmount, TransactionBuilderQuery, TransactionBuilderResponse,
@@ -39,7 +41,7 @@ pub struct ParaswapSolver {
     allowance_fetcher: Box<dyn AllowanceManaging>,
     #[derivative(Debug = "ignore")]
     client: Box<dyn ParaswapApi + Send + Sync>,
-    slippage_bps: u32,
+    slippage_bps: Reloadable<u32>,
     disabled_paraswap_dexs: Vec<String>,
 }
 
@@ -50,7 +52,7 @@ impl ParaswapSolver {
         web3: Web3,
         settlement_contract: GPv2Settlement,
         token_info: Arc<dyn TokenInfoFetching>,
-        slippage_bps: u32,
+        slippage_bps: impl Into<Reloadable<u32>>,
         disabled_paraswap_dexs: Vec<String>,
         client: Client,
         partner: Option<String>,
@@ -68,7 +70,7 @@ impl ParaswapSolver {
                 partner: partner.unwrap_or_else(|| REFERRER.into()),
                 rate_limiter,
             }),
-            slippage_bps,
+            slippage_bps: slippage_bps.into(),
             disabled_paraswap_dexs,
         }
     }
@@ -76,10 +78,9 @@ impl ParaswapSolver {
 
 impl From<ParaswapResponseError> for SettlementError {
     fn from(err: ParaswapResponseError) -> Self {
-        let retryable = err.is_retryable();
         SettlementError {
+            class: err.class(),
             inner: anyhow!(err),
-            retryable,
         }
     }
 }
@@ -113,7 +114,7 @@ impl SingleOrderSolving for ParaswapSolver {
         });
         settlement.with_liquidity(&order, amount)?;
 
-        settlement.encoder.append_to_execution_plan(
+        settlement.encoder.add_approval(
             self.allowance_fetcher
                 .get_approval(&ApprovalRequest {
                     token: order.sell_token,
@@ -177,7 +178,7 @@ impl ParaswapSolver {
             src_token: order.sell_token,
             dest_token: order.buy_token,
             trade_amount,
-            slippage: self.slippage_bps,
+            slippage: *self.slippage_bps.get(),
             src_decimals: decimals(token_info, &order.sell_token)?,
             dest_decimals: decimals(token_info, &order.buy_token)?,
             price_route: price_response.clone().price_route_raw,
@@ -236,7 +237,7 @@ mod tests {
             token_info: Arc::new(token_info),
             allowance_fetcher,
             settlement_contract: dummy_contract!(GPv2Settlement, H160::zero()),
-            slippage_bps: 10,
+            slippage_bps: 10u32.into(),
             disabled_paraswap_dexs: vec![],
         };
 
@@ -286,7 +287,7 @@ mod tests {
             token_info: Arc::new(token_info),
             allowance_fetcher,
             settlement_contract: dummy_contract!(GPv2Settlement, H160::zero()),
-            slippage_bps: 10,
+            slippage_bps: 10u32.into(),
             disabled_paraswap_dexs: vec![],
         };
 
@@ -391,7 +392,7 @@ mod tests {
             token_info: Arc::new(token_info),
             allowance_fetcher,
             settlement_contract: dummy_contract!(GPv2Settlement, H160::zero()),
-            slippage_bps: 10,
+            slippage_bps: 10u32.into(),
             disabled_paraswap_dexs: vec![],
         };
 
@@ -487,7 +488,7 @@ mod tests {
             token_info: Arc::new(token_info),
             allowance_fetcher,
             settlement_contract: dummy_contract!(GPv2Settlement, H160::zero()),
-            slippage_bps: 1000, // 10%
+            slippage_bps: 1000u32.into(), // 10%
             disabled_paraswap_dexs: vec![],
         };
 
@@ -538,7 +539,7 @@ mod tests {
             web3,
             settlement,
             token_info_fetcher,
-            1,
+            1u32,
             vec![],
             Client::new(),
             None,
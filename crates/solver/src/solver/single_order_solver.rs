@@ -9,8 +9,47 @@ use anyhow::{Error, Result};
 use ethcontract::Account;
 use primitive_types::U256;
 use rand::prelude::SliceRandom;
+use shared::error_class::{ClassifyError, ErrorClass};
 use std::{collections::VecDeque, sync::Arc, time::Duration};
 
+/// How long to wait before retrying after the first rate limited response. Every successive rate
+/// limited response doubles the back off up to `MAX_RATE_LIMIT_BACK_OFF`.
+const MIN_RATE_LIMIT_BACK_OFF: Duration = Duration::from_millis(250);
+const MAX_RATE_LIMIT_BACK_OFF: Duration = Duration::from_secs(4);
+
+/// How long an order waits before it gets retried after its first transient error. Every
+/// successive transient error of the same order doubles the back off up to
+/// `MAX_TRANSIENT_BACK_OFF`.
+const MIN_TRANSIENT_BACK_OFF: Duration = Duration::from_millis(100);
+const MAX_TRANSIENT_BACK_OFF: Duration = Duration::from_secs(2);
+/// How often an order gets retried after transient errors before the solver gives up on it.
+const MAX_TRANSIENT_RETRIES: u32 = 3;
+
+/// An order waiting to be solved.
+struct QueuedOrder {
+    order: LimitOrder,
+    /// How often solving the order failed with a transient error.
+    transient_failures: u32,
+    /// The order must not be retried before this point in time.
+    not_before: tokio::time::Instant,
+}
+
+impl QueuedOrder {
+    fn new(order: LimitOrder) -> Self {
+        Self {
+            order,
+            transient_failures: 0,
+            not_before: tokio::time::Instant::now(),
+        }
+    }
+
+    fn transient_back_off(&self) -> Duration {
+        MIN_TRANSIENT_BACK_OFF
+            .saturating_mul(1 << self.transient_failures.saturating_sub(1).min(16))
+            .min(MAX_TRANSIENT_BACK_OFF)
+    }
+}
+
 #[cfg_attr(test, mockall::automock)]
 #[async_trait::async_trait]
 /// Implementations of this trait know how to settle a single limit order (not taking advantage of batching multiple orders together)
@@ -64,11 +103,23 @@ impl Solver for SingleOrderSolver {
         let mut orders = orders
             .into_iter()
             .filter(|order| !order.is_liquidity_order)
+            .map(QueuedOrder::new)
             .collect::<VecDeque<_>>();
         let mut settlements = Vec::new();
         let settle = async {
-            while let Some(order) = orders.pop_front() {
-                match self.inner.try_settle_order(order.clone(), &auction).await {
+            let mut back_off = MIN_RATE_LIMIT_BACK_OFF;
+            while let Some(mut queued) = orders.pop_front() {
+                // Retried orders are queued behind all other orders so the back off usually
+                // elapses while the other orders get solved.
+                tokio::time::sleep_until(queued.not_before).await;
+                let result = self
+                    .inner
+                    .try_settle_order(queued.order.clone(), &auction)
+                    .await;
+                if !matches!(&result, Err(err) if err.class == ErrorClass::RateLimited) {
+                    back_off = MIN_RATE_LIMIT_BACK_OFF;
+                }
+                match result {
                     Ok(settlement) => {
                         self.metrics
                             .single_order_solver_succeeded(self.inner.name());
@@ -77,11 +128,43 @@ impl Solver for SingleOrderSolver {
                     Err(err) => {
                         let name = self.inner.name();
                         self.metrics.single_order_solver_failed(name);
-                        if err.retryable {
-                            tracing::warn!("Solver {} retryable error: {:?}", name, &err.inner);
-                            orders.push_back(order);
-                        } else {
-                            tracing::warn!("Solver {} error: {:?}", name, &err.inner);
+                        match err.class {
+                            ErrorClass::Transient => {
+                                queued.transient_failures += 1;
+                                if queued.transient_failures > MAX_TRANSIENT_RETRIES {
+                                    tracing::warn!(
+                                        "Solver {} giving up on order {} after {} retryable errors: {:?}",
+                                        name,
+                                        queued.order.id,
+                                        queued.transient_failures,
+                                        &err.inner
+                                    );
+                                    continue;
+                                }
+                                let back_off = queued.transient_back_off();
+                                tracing::warn!(
+                                    "Solver {} retryable error, retrying in {:?}: {:?}",
+                                    name,
+                                    back_off,
+                                    &err.inner
+                                );
+                                queued.not_before = tokio::time::Instant::now() + back_off;
+                                orders.push_back(queued);
+                            }
+                            ErrorClass::RateLimited => {
+                                tracing::warn!(
+                                    "Solver {} rate limited, backing off for {:?}: {:?}",
+                                    name,
+                                    back_off,
+                                    &err.inner
+                                );
+                                tokio::time::sleep(back_off).await;
+                                back_off = std::cmp::min(2 * back_off, MAX_RATE_LIMIT_BACK_OFF);
+                                orders.push_back(queued);
+                            }
+                            ErrorClass::Fatal | ErrorClass::Invalid => {
+                                tracing::warn!("Solver {} error: {:?}", name, &err.inner);
+                            }
                         }
                     }
                 }
@@ -121,14 +204,15 @@ impl Solver for SingleOrderSolver {
 #[derive(Debug)]
 pub struct SettlementError {
     pub inner: anyhow::Error,
-    pub retryable: bool,
+    /// Decides whether and when the order gets retried.
+    pub class: ErrorClass,
 }
 
 impl From<anyhow::Error> for SettlementError {
     fn from(err: Error) -> Self {
         SettlementError {
+            class: err.class(),
             inner: err,
-            retryable: false,
         }
     }
 }
@@ -214,7 +298,7 @@ mod tests {
                 let result = match call_count {
                     0 => Err(SettlementError {
                         inner: anyhow!(""),
-                        retryable: true,
+                        class: ErrorClass::Transient,
                     }),
                     1 => Ok(None),
                     _ => unreachable!(),
@@ -238,6 +322,136 @@ mod tests {
             .unwrap();
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn backs_off_when_rate_limited() {
+        let mut inner = MockSingleOrderSolving::new();
+        inner.expect_name().return_const("");
+        let mut call_count = 0u32;
+        inner
+            .expect_try_settle_order()
+            .times(3)
+            .returning(move |_, _| {
+                let result = match call_count {
+                    0 | 1 => Err(SettlementError {
+                        inner: anyhow!(""),
+                        class: ErrorClass::RateLimited,
+                    }),
+                    2 => Ok(None),
+                    _ => unreachable!(),
+                };
+                call_count += 1;
+                result
+            });
+
+        let solver = test_solver(inner);
+        let handler = Arc::new(CapturingSettlementHandler::default());
+        let order = LimitOrder {
+            settlement_handling: handler.clone(),
+            ..Default::default()
+        };
+        let start = tokio::time::Instant::now();
+        solver
+            .solve(Auction {
+                orders: vec![order],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(start.elapsed() >= 3 * MIN_RATE_LIMIT_BACK_OFF);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn backs_off_exponentially_on_transient_errors() {
+        let mut inner = MockSingleOrderSolving::new();
+        inner.expect_name().return_const("");
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let calls_ = calls.clone();
+        let mut call_count = 0u32;
+        inner
+            .expect_try_settle_order()
+            .times(4)
+            .returning(move |_, _| {
+                calls_.lock().unwrap().push(tokio::time::Instant::now());
+                let result = match call_count {
+                    0..=2 => Err(SettlementError {
+                        inner: anyhow!(""),
+                        class: ErrorClass::Transient,
+                    }),
+                    3 => Ok(None),
+                    _ => unreachable!(),
+                };
+                call_count += 1;
+                result
+            });
+
+        let solver = test_solver(inner);
+        let handler = Arc::new(CapturingSettlementHandler::default());
+        let order = LimitOrder {
+            settlement_handling: handler.clone(),
+            ..Default::default()
+        };
+        solver
+            .solve(Auction {
+                orders: vec![order],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let calls = calls.lock().unwrap();
+        let delays = calls
+            .windows(2)
+            .map(|window| window[1] - window[0])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            [
+                MIN_TRANSIENT_BACK_OFF,
+                2 * MIN_TRANSIENT_BACK_OFF,
+                4 * MIN_TRANSIENT_BACK_OFF
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_on_orders_after_too_many_transient_errors() {
+        let mut inner = MockSingleOrderSolving::new();
+        inner.expect_name().return_const("");
+        inner
+            .expect_try_settle_order()
+            .times(MAX_TRANSIENT_RETRIES as usize + 1)
+            .returning(|_, _| {
+                Err(SettlementError {
+                    inner: anyhow!(""),
+                    class: ErrorClass::Transient,
+                })
+            });
+
+        let solver = test_solver(inner);
+        let handler = Arc::new(CapturingSettlementHandler::default());
+        let order = LimitOrder {
+            settlement_handling: handler.clone(),
+            ..Default::default()
+        };
+        let settlements = solver
+            .solve(Auction {
+                orders: vec![order],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(settlements.is_empty());
+    }
+
+    #[test]
+    fn transient_back_off_is_capped() {
+        let mut queued = QueuedOrder::new(Default::default());
+        queued.transient_failures = 1;
+        assert_eq!(queued.transient_back_off(), MIN_TRANSIENT_BACK_OFF);
+        queued.transient_failures = 100;
+        assert_eq!(queued.transient_back_off(), MAX_TRANSIENT_BACK_OFF);
+    }
+
     #[tokio::test]
     async fn does_not_retry_unretryable() {
         let mut inner = MockSingleOrderSolving::new();
@@ -245,7 +459,7 @@ mod tests {
         inner.expect_try_settle_order().times(1).returning(|_, _| {
             Err(SettlementError {
                 inner: anyhow!(""),
-                retryable: false,
+                class: ErrorClass::Fatal,
             })
         });
 
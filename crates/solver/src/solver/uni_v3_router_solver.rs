@@ -84,7 +84,7 @@ impl SingleOrderSolving for UniV3RouterSolver {
                 amount: order.sell_amount,
             })
             .await?;
-        settlement.encoder.append_to_execution_plan(approval);
+        settlement.encoder.add_approval(approval);
         let interaction: EncodedInteraction = (
             self.swap_router_02,
             U256::from(0u32),
@@ -33,6 +33,8 @@ use ethcontract::{Account, Bytes};
 use maplit::hashmap;
 use model::order::OrderKind;
 use shared::{
+    error_class::ClassifyError,
+    hot_reload::Reloadable,
     solver_utils::Slippage,
     zeroex_api::{SwapQuery, SwapResponse, ZeroExApi, ZeroExResponseError},
     Web3,
@@ -47,7 +49,7 @@ pub struct ZeroExSolver {
     account: Account,
     api: Arc<dyn ZeroExApi>,
     allowance_fetcher: Box<dyn AllowanceManaging>,
-    zeroex_slippage_bps: u32,
+    zeroex_slippage_bps: Reloadable<u32>,
     excluded_sources: Vec<String>,
 }
 
@@ -61,7 +63,7 @@ impl ZeroExSolver {
         settlement_contract: GPv2Settlement,
         chain_id: u64,
         api: Arc<dyn ZeroExApi>,
-        zeroex_slippage_bps: u32,
+        zeroex_slippage_bps: impl Into<Reloadable<u32>>,
         excluded_sources: Vec<String>,
     ) -> Result<Self> {
         ensure!(
@@ -73,7 +75,7 @@ impl ZeroExSolver {
             account,
             allowance_fetcher: Box::new(allowance_fetcher),
             api,
-            zeroex_slippage_bps,
+            zeroex_slippage_bps: zeroex_slippage_bps.into(),
             excluded_sources,
         })
     }
@@ -95,8 +97,10 @@ impl SingleOrderSolving for ZeroExSolver {
             buy_token: order.buy_token,
             sell_amount,
             buy_amount,
-            slippage_percentage: Slippage::number_from_basis_points(self.zeroex_slippage_bps)
-                .unwrap(),
+            slippage_percentage: Slippage::number_from_basis_points(
+                *self.zeroex_slippage_bps.get(),
+            )
+            .unwrap(),
             excluded_sources: self.excluded_sources.clone(),
             enable_slippage_protection: false,
         };
@@ -115,7 +119,7 @@ impl SingleOrderSolving for ZeroExSolver {
 
         settlement.with_liquidity(&order, order.full_execution_amount())?;
 
-        settlement.encoder.append_to_execution_plan(
+        settlement.encoder.add_approval(
             self.allowance_fetcher
                 .get_approval(&ApprovalRequest {
                     token: order.sell_token,
@@ -141,7 +145,7 @@ impl From<ZeroExResponseError> for SettlementError {
     fn from(err: ZeroExResponseError) -> Self {
         SettlementError {
             inner: anyhow!("0x Response Error {:?}", err),
-            retryable: matches!(err, ZeroExResponseError::ServerError(_)),
+            class: err.class(),
         }
     }
 }
@@ -300,7 +304,7 @@ mod tests {
             account: account(),
             api: Arc::new(client),
             allowance_fetcher,
-            zeroex_slippage_bps: 10u32,
+            zeroex_slippage_bps: 10u32.into(),
             excluded_sources: Default::default(),
         };
 
@@ -448,7 +452,7 @@ mod tests {
             account: account(),
             api: Arc::new(client),
             allowance_fetcher,
-            zeroex_slippage_bps: 10u32,
+            zeroex_slippage_bps: 10u32.into(),
             excluded_sources: Default::default(),
         };
 
@@ -507,7 +511,7 @@ mod tests {
             account: account(),
             api: Arc::new(client),
             allowance_fetcher,
-            zeroex_slippage_bps: 10u32,
+            zeroex_slippage_bps: 10u32.into(),
             excluded_sources: Default::default(),
         };
 
@@ -551,4 +555,43 @@ mod tests {
             .unwrap();
         assert_eq!(handler.calls(), vec![4321.into()]);
     }
+
+    #[tokio::test]
+    async fn uses_reloaded_slippage() {
+        let mut client = MockZeroExApi::new();
+        let mut sequence = Sequence::new();
+        for bps in [10, 50] {
+            let slippage = Slippage::number_from_basis_points(bps).unwrap().0;
+            client
+                .expect_get_swap()
+                .times(1)
+                .in_sequence(&mut sequence)
+                .withf(move |query| query.slippage_percentage.0 == slippage)
+                .returning(|_| Err(ZeroExResponseError::RateLimited));
+        }
+
+        let slippage_bps = Reloadable::new(10);
+        let solver = ZeroExSolver {
+            account: account(),
+            api: Arc::new(client),
+            allowance_fetcher: Box::new(MockAllowanceManaging::new()),
+            zeroex_slippage_bps: slippage_bps.clone(),
+            excluded_sources: Default::default(),
+        };
+
+        let order = LimitOrder {
+            sell_amount: 1.into(),
+            buy_amount: 1.into(),
+            ..Default::default()
+        };
+        assert!(solver
+            .try_settle_order(order.clone(), &Auction::default())
+            .await
+            .is_err());
+        slippage_bps.set(50);
+        assert!(solver
+            .try_settle_order(order, &Auction::default())
+            .await
+            .is_err());
+    }
 }
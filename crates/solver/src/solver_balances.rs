@@ -0,0 +1,128 @@
+//! Monitoring of the ETH balances of the solver accounts. Solvers pay for the gas of their
+//! settlements so an account that runs out of funds can no longer submit winning solutions.
+
+use crate::{metrics::SolverMetrics, solver::Solver};
+use anyhow::{anyhow, Result};
+use futures::future::join_all;
+use model::solver_competition::ExclusionReason;
+use primitive_types::{H160, U256};
+use shared::{maintenance::Maintaining, Web3};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+pub struct SolverBalances {
+    web3: Web3,
+    /// Names and addresses of the solver accounts.
+    accounts: Vec<(String, H160)>,
+    /// Balance in ETH below which an account is reported as running low on funds.
+    alert_threshold: f64,
+    /// The gas of a settlement a solver's account has to be able to pay for in order to
+    /// participate in auctions. `None` if solvers are never excluded.
+    required_settlement_gas: Option<u64>,
+    metrics: Arc<dyn SolverMetrics>,
+    balances: Mutex<HashMap<H160, U256>>,
+}
+
+impl SolverBalances {
+    pub fn new(
+        web3: Web3,
+        solvers: &[Arc<dyn Solver>],
+        alert_threshold: f64,
+        required_settlement_gas: Option<u64>,
+        metrics: Arc<dyn SolverMetrics>,
+    ) -> Self {
+        Self {
+            web3,
+            accounts: solvers
+                .iter()
+                .map(|solver| (solver.name().to_string(), solver.account().address()))
+                .collect(),
+            alert_threshold,
+            required_settlement_gas,
+            metrics,
+            balances: Default::default(),
+        }
+    }
+
+    /// Returns why the solver with the specified account may not participate in an auction with
+    /// the gas price or `None` if it may. Solvers whose balance hasn't been fetched yet are
+    /// allowed to participate.
+    pub fn exclusion_reason(&self, account: H160, gas_price: f64) -> Option<ExclusionReason> {
+        let gas = self.required_settlement_gas?;
+        let balance = *self.balances.lock().unwrap().get(&account)?;
+        insufficient_balance(balance, gas, gas_price)
+    }
+}
+
+fn insufficient_balance(balance: U256, gas: u64, gas_price: f64) -> Option<ExclusionReason> {
+    let required = U256::from_f64_lossy(gas as f64 * gas_price);
+    (balance < required).then(|| ExclusionReason::InsufficientBalance { balance, required })
+}
+
+#[async_trait::async_trait]
+impl Maintaining for SolverBalances {
+    async fn run_maintenance(&self) -> Result<()> {
+        let balances = join_all(
+            self.accounts
+                .iter()
+                .map(|(_, address)| self.web3.eth().balance(*address, None)),
+        )
+        .await;
+
+        let mut failed = Vec::new();
+        for ((name, address), balance) in self.accounts.iter().zip(balances) {
+            let balance = match balance {
+                Ok(balance) => balance,
+                Err(err) => {
+                    tracing::warn!(solver = %name, ?err, "failed to fetch solver account balance");
+                    failed.push(name.as_str());
+                    continue;
+                }
+            };
+            let balance_in_eth = balance.to_f64_lossy() / 1e18;
+            let below_threshold = balance_in_eth < self.alert_threshold;
+            if below_threshold {
+                tracing::warn!(
+                    solver = %name,
+                    account = ?address,
+                    balance = balance_in_eth,
+                    "solver account balance is below the alert threshold, top up the account"
+                );
+            }
+            self.metrics
+                .solver_account_balance(name, balance_in_eth, below_threshold);
+            self.balances.lock().unwrap().insert(*address, balance);
+        }
+
+        if !failed.is_empty() {
+            return Err(anyhow!(
+                "failed to fetch the account balances of solvers {:?}",
+                failed
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn balance_has_to_cover_settlement_gas() {
+        let gas_price = 50e9;
+        assert_eq!(
+            insufficient_balance(U256::exp10(16), 1_000_000, gas_price),
+            Some(ExclusionReason::InsufficientBalance {
+                balance: U256::exp10(16),
+                required: U256::from(50_000_000_000_000_000u64),
+            })
+        );
+        assert_eq!(
+            insufficient_balance(U256::exp10(17), 1_000_000, gas_price),
+            None
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Temporary demotion of solvers that return malformed solutions. A solver whose responses keep
+//! violating the schema is most likely running an incompatible version, so instead of paying for
+//! its requests every auction it sits out for a while.
+
+use crate::metrics::SolverMetrics;
+use chrono::{DateTime, Duration, Utc};
+use model::solver_competition::ExclusionReason;
+use shared::http_solver::validation::SchemaViolations;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time,
+};
+
+pub struct SolverDemotions {
+    /// Number of consecutive malformed responses after which a solver gets demoted. 0 if solvers
+    /// are never demoted.
+    threshold: usize,
+    /// How long a solver stays demoted.
+    period: Duration,
+    metrics: Arc<dyn SolverMetrics>,
+    solvers: Mutex<HashMap<String, Record>>,
+}
+
+#[derive(Default)]
+struct Record {
+    consecutive_violations: usize,
+    demoted_until: Option<DateTime<Utc>>,
+}
+
+impl SolverDemotions {
+    pub fn new(threshold: usize, period: time::Duration, metrics: Arc<dyn SolverMetrics>) -> Self {
+        Self {
+            threshold,
+            period: Duration::from_std(period).expect("demotion period out of range"),
+            metrics,
+            solvers: Default::default(),
+        }
+    }
+
+    /// Records the outcome of a solver run. Only schema violations count against a solver, other
+    /// errors are ignored and valid responses reset the count.
+    pub fn record(&self, solver: &str, result: Result<(), &anyhow::Error>, now: DateTime<Utc>) {
+        let violations = match result {
+            Ok(()) => {
+                self.solvers.lock().unwrap().remove(solver);
+                return;
+            }
+            Err(err) => match err
+                .chain()
+                .find_map(|err| err.downcast_ref::<SchemaViolations>())
+            {
+                Some(violations) => violations,
+                None => return,
+            },
+        };
+        self.metrics.solver_schema_violations(solver, &violations.0);
+        if self.threshold == 0 {
+            return;
+        }
+
+        let mut solvers = self.solvers.lock().unwrap();
+        let record = solvers.entry(solver.to_string()).or_default();
+        record.consecutive_violations += 1;
+        if record.consecutive_violations >= self.threshold {
+            let until = now + self.period;
+            tracing::warn!(
+                %solver, %until, violations = record.consecutive_violations,
+                "demoting solver for returning malformed solutions"
+            );
+            record.consecutive_violations = 0;
+            record.demoted_until = Some(until);
+        }
+    }
+
+    /// Returns why the solver may not participate in an auction or `None` if it may.
+    pub fn exclusion_reason(&self, solver: &str, now: DateTime<Utc>) -> Option<ExclusionReason> {
+        let until = self.solvers.lock().unwrap().get(solver)?.demoted_until?;
+        (now < until).then(|| ExclusionReason::SchemaViolations { until })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::NoopMetrics;
+    use shared::http_solver::validation::SchemaViolation;
+
+    #[test]
+    fn demotes_solvers_after_consecutive_violations() {
+        let demotions =
+            SolverDemotions::new(2, time::Duration::from_secs(600), Arc::new(NoopMetrics {}));
+        let violations =
+            anyhow::Error::new(SchemaViolations(vec![SchemaViolation::UnknownOrder {
+                index: 0,
+            }]))
+            .context("invalid response json");
+        let other_error = anyhow::anyhow!("timeout");
+        let now = Utc::now();
+
+        // A valid response in between resets the count and other errors don't count.
+        demotions.record("solver", Err(&violations), now);
+        demotions.record("solver", Ok(()), now);
+        demotions.record("solver", Err(&violations), now);
+        demotions.record("solver", Err(&other_error), now);
+        assert_eq!(demotions.exclusion_reason("solver", now), None);
+
+        demotions.record("solver", Err(&violations), now);
+        let until = now + Duration::minutes(10);
+        assert_eq!(
+            demotions.exclusion_reason("solver", now),
+            Some(ExclusionReason::SchemaViolations { until })
+        );
+        assert_eq!(demotions.exclusion_reason("other", now), None);
+        assert_eq!(demotions.exclusion_reason("solver", until), None);
+    }
+}
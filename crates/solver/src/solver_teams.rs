@@ -0,0 +1,99 @@
+//! Applies the configuration solver teams control through the orderbook API to their solvers.
+
+use anyhow::{Context, Result};
+use primitive_types::H160;
+use reqwest::Url;
+use shared::{http_solver::CallbackUrl, maintenance::Maintaining};
+use sqlx::PgPool;
+use std::{collections::HashMap, sync::Mutex};
+
+/// Keeps the callback urls of the http solvers in sync with the urls their teams configured.
+/// Solvers are identified by the account they submit settlements with.
+#[derive(Default)]
+pub struct SolverTeams {
+    callback_urls: Mutex<HashMap<H160, CallbackUrl>>,
+    database: Option<PgPool>,
+}
+
+impl SolverTeams {
+    pub fn new(database: Option<PgPool>) -> Self {
+        Self {
+            callback_urls: Default::default(),
+            database,
+        }
+    }
+
+    /// Returns the callback url of the solver with this account.
+    pub fn callback_url(&self, solver: H160) -> CallbackUrl {
+        self.callback_urls
+            .lock()
+            .unwrap()
+            .entry(solver)
+            .or_default()
+            .clone()
+    }
+
+    fn update(&self, configured: Vec<(H160, String)>) {
+        let configured = configured.into_iter().collect::<HashMap<_, _>>();
+        for (solver, callback_url) in self.callback_urls.lock().unwrap().iter() {
+            let url = configured
+                .get(solver)
+                .and_then(|url| match Url::parse(url) {
+                    Ok(url) => Some(url),
+                    Err(err) => {
+                        tracing::warn!(?solver, ?err, "invalid solver team callback url");
+                        None
+                    }
+                });
+            if callback_url.get() != url {
+                tracing::info!(?solver, ?url, "solver team changed callback url");
+                callback_url.set(url);
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Maintaining for SolverTeams {
+    async fn run_maintenance(&self) -> Result<()> {
+        let database = match &self.database {
+            Some(database) => database,
+            None => return Ok(()),
+        };
+        let mut ex = database.acquire().await?;
+        let configured = database::solver_teams::callback_urls(&mut ex)
+            .await
+            .context("solver team callback urls")?;
+        self.update(
+            configured
+                .into_iter()
+                .map(|(solver, url)| (H160(solver.0), url))
+                .collect(),
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn updates_callback_urls_of_registered_solvers() {
+        let teams = SolverTeams::default();
+        let solver = teams.callback_url(H160([1; 20]));
+        let other = teams.callback_url(H160([2; 20]));
+
+        teams.update(vec![
+            (H160([1; 20]), "http://solver.test".to_string()),
+            (H160([2; 20]), "not a url".to_string()),
+            (H160([3; 20]), "http://unknown.test".to_string()),
+        ]);
+        assert_eq!(solver.get(), Some("http://solver.test".parse().unwrap()));
+        assert_eq!(other.get(), None);
+
+        // Solvers fall back to the configured url once the team removes its callback url.
+        teams.update(vec![]);
+        assert_eq!(solver.get(), None);
+    }
+}
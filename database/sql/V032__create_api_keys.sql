@@ -0,0 +1,13 @@
+-- API keys allow an owner to delegate order placement to a service (for example a market maker's
+-- trading infrastructure) without having to sign every order with their wallet.
+-- Only the keccak256 hash of a key is stored. The owner authorizes a key by signing its hash.
+
+CREATE TABLE api_keys (
+    key_hash bytea PRIMARY KEY,
+    owner bytea NOT NULL,
+    valid_to bigint NOT NULL,
+    creation_timestamp timestamptz NOT NULL,
+    revocation_timestamp timestamptz
+);
+
+CREATE INDEX api_keys_owner ON api_keys USING HASH (owner);
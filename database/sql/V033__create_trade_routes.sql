@@ -0,0 +1,22 @@
+-- The venues (AMM pools or aggregators) that an indexed settlement interacted with in order to fill
+-- a trade. Venues are identified by decoding the interactions of the settlement transaction, so
+-- pure CoWs have no venues.
+-- Trades are referenced by the block number and log index of their trade event.
+
+CREATE TYPE VenueKind AS ENUM ('uniswapv2', 'uniswapv3', 'balancerv2', 'zeroex', 'oneinch', 'paraswap');
+
+CREATE TABLE trade_venues (
+    block_number bigint NOT NULL,
+    log_index bigint NOT NULL,
+    -- The position of the venue in the route.
+    venue_index bigint NOT NULL,
+    kind VenueKind NOT NULL,
+    address bytea NOT NULL,
+
+    PRIMARY KEY (block_number, log_index, venue_index)
+);
+
+-- Settlements that got indexed before this migration get processed by the route indexer.
+ALTER TABLE settlements ADD COLUMN routes_indexed boolean NOT NULL DEFAULT false;
+
+CREATE INDEX settlements_routes_not_indexed ON settlements (block_number, log_index) WHERE NOT routes_indexed;
@@ -0,0 +1,15 @@
+-- Results of sending auctions to a "shadow" set of drivers. Their solutions are simulated and
+-- scored like regular solutions but never executed. This allows evaluating new solvers on
+-- production auctions before they are allowed to settle.
+
+CREATE TABLE shadow_competitions (
+    auction_id bigint NOT NULL,
+    driver text NOT NULL,
+    creation_timestamp timestamptz NOT NULL,
+    -- NULL if the driver did not return a valid solution.
+    score double precision,
+    -- The solution as reported by the driver or the reason it was invalid.
+    json jsonb NOT NULL,
+
+    PRIMARY KEY (auction_id, driver)
+);
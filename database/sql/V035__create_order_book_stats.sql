@@ -0,0 +1,30 @@
+-- Rolling order book statistics over the last 24 hours.
+--
+-- Trades don't store when they happened so the timestamp of the block of every settlement gets
+-- filled in by a maintenance job. All trades of a block share its timestamp.
+
+ALTER TABLE settlements ADD COLUMN block_timestamp timestamptz;
+
+CREATE INDEX settlements_without_block_timestamp ON settlements (block_number) WHERE block_timestamp IS NULL;
+CREATE INDEX settlements_block_timestamp ON settlements (block_timestamp);
+
+-- The view always has exactly one row. It is refreshed periodically by the same maintenance job
+-- so that reading the stats is cheap.
+-- The volume is denominated in the native token and uses the sell token price the order was
+-- quoted with. Trades of orders without a quote don't count towards the volume.
+CREATE MATERIALIZED VIEW order_book_stats AS
+WITH recent_settlements AS (
+    SELECT block_number, log_index
+    FROM settlements
+    WHERE block_timestamp > now() - interval '24 hours'
+)
+SELECT
+    (SELECT COUNT(*) FROM recent_settlements) AS settlement_count,
+    COUNT(t.order_uid) AS trade_count,
+    COUNT(DISTINCT o.owner) AS unique_traders,
+    COALESCE(SUM(t.sell_amount::double precision * oq.sell_token_price), 0) AS volume,
+    now() AS refreshed_at
+FROM trades t
+JOIN (SELECT DISTINCT block_number FROM recent_settlements) b ON b.block_number = t.block_number
+LEFT JOIN orders o ON o.uid = t.order_uid
+LEFT JOIN order_quotes oq ON oq.order_uid = t.order_uid;
@@ -0,0 +1,22 @@
+-- Rewards solvers earn for their settlements on top of the fee reimbursement.
+--
+-- Rewards get computed by a maintenance job once the block timestamp of the settlement is known
+-- according to the reward scheme the job is configured with. Changing the scheme only affects
+-- settlements that have not been rewarded yet.
+
+CREATE TABLE settlement_rewards (
+    -- The event index of the settlement.
+    block_number bigint NOT NULL,
+    log_index bigint NOT NULL,
+    solver bytea NOT NULL,
+    tx_hash bytea NOT NULL,
+    -- Settled orders that are not liquidity orders.
+    user_orders bigint NOT NULL,
+    -- The objective value of the winning solution of the solver competition. NULL if the
+    -- competition is not known.
+    quality_score double precision,
+    -- The reward denominated in the reward token.
+    reward double precision NOT NULL,
+
+    PRIMARY KEY (block_number, log_index)
+);
@@ -0,0 +1,14 @@
+-- Records the batch cancellations partners trigger for all open orders with their app data, for
+-- example when their frontend quoted bad prices. The cancelled orders themselves are marked as
+-- cancelled in the orders table.
+
+CREATE TABLE app_data_cancellations (
+    app_data bytea NOT NULL,
+    -- The owner of the API key that requested the cancellation.
+    owner bytea NOT NULL,
+    timestamp timestamptz NOT NULL,
+    -- Number of orders that were cancelled.
+    orders bigint NOT NULL
+);
+
+CREATE INDEX app_data_cancellations_app_data ON app_data_cancellations USING BTREE (app_data, timestamp);
@@ -0,0 +1,8 @@
+-- Market makers can attach firmness metadata to the quotes behind their liquidity orders so that
+-- stale quotes don't get included in auctions.
+
+-- The last block in which the quote can be filled. NULL if the quote doesn't expire before the
+-- order's valid_to.
+ALTER TABLE orders ADD quote_expiry_block bigint;
+-- Whether the market maker can still reject the fill after the settlement has been submitted.
+ALTER TABLE orders ADD quote_last_look boolean NOT NULL DEFAULT false;
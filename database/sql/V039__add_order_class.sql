@@ -0,0 +1,12 @@
+-- Orders are validated and included in auctions according to their class.
+CREATE TYPE OrderClass AS ENUM ('market', 'limit', 'liquidity', 'twap');
+
+ALTER TABLE orders ADD COLUMN class OrderClass NOT NULL DEFAULT 'market';
+
+-- Liquidity orders of market makers never paid fees and were the only orders that could be
+-- partially fillable. The other flagged orders are user orders that were placed outside of the
+-- market price.
+UPDATE orders SET class = 'liquidity'
+WHERE is_liquidity_order AND (partially_fillable OR full_fee_amount = 0);
+UPDATE orders SET class = 'limit'
+WHERE is_liquidity_order AND NOT partially_fillable AND full_fee_amount <> 0;
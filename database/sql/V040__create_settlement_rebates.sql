@@ -0,0 +1,35 @@
+-- Rebates block builders pay for the order flow of settlements, for example through MEV-share style
+-- private relays.
+--
+-- Builders pay rebates with transactions from the fee recipient of the block that includes the
+-- settlement. Rebates get indexed by a maintenance job once the solver competition of the
+-- settlement is stored so that they can be attributed to its auction. Settlements without a rebate
+-- are stored with a rebate of 0 so that they only get processed once.
+
+CREATE TABLE settlement_rebates (
+    -- The event index of the settlement.
+    block_number bigint NOT NULL,
+    log_index bigint NOT NULL,
+    tx_hash bytea NOT NULL,
+    -- The auction of the solver competition that produced the settlement. NULL if the competition
+    -- is not known.
+    auction_id bigint,
+    -- The address the rebate was paid to.
+    recipient bytea NOT NULL,
+    -- The rebate in wei.
+    rebate numeric(78,0) NOT NULL,
+
+    PRIMARY KEY (block_number, log_index)
+);
+
+CREATE INDEX settlement_rebates_auction_id ON settlement_rebates (auction_id);
+
+-- The share of a settlement's rebate that is attributed to each user order it settled.
+CREATE TABLE order_rebates (
+    block_number bigint NOT NULL,
+    log_index bigint NOT NULL,
+    order_uid bytea NOT NULL,
+    rebate numeric(78,0) NOT NULL,
+
+    PRIMARY KEY (block_number, log_index, order_uid)
+);
@@ -0,0 +1,21 @@
+-- The part of an order's signed fee that is owed to the protocol instead of the solver that settles
+-- it. Orders created before protocol fees existed did not pay any.
+ALTER TABLE orders
+    ADD COLUMN protocol_fee_amount numeric(78,0) NOT NULL DEFAULT 0;
+
+-- The protocol fees accrued by trades. Rows get inserted together with the trade events of orders
+-- with a protocol fee and the fee of partially filled orders accrues proportionally to the executed
+-- fee.
+CREATE TABLE protocol_fee_accruals (
+    -- The event index of the trade.
+    block_number bigint NOT NULL,
+    log_index bigint NOT NULL,
+    order_uid bytea NOT NULL,
+    -- The token the fee was paid in, which is the sell token of the order.
+    token bytea NOT NULL,
+    amount numeric(78,0) NOT NULL,
+
+    PRIMARY KEY (block_number, log_index)
+);
+
+CREATE INDEX protocol_fee_accruals_token ON protocol_fee_accruals (token);
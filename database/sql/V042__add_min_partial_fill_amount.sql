@@ -0,0 +1,5 @@
+-- The smallest amount a partially fillable order may be executed with in a single trade, in the
+-- token its amounts are fixed in: the sell token for sell orders and the buy token for buy orders.
+-- A trade of the order's whole remaining amount is allowed even if it is smaller. NULL if the order
+-- has no minimum.
+ALTER TABLE orders ADD COLUMN min_partial_fill_amount numeric(78,0);
@@ -0,0 +1,8 @@
+-- The fee subsidy that was applied to the quote of an order. The subsidized fee is
+-- `(gas_amount * gas_price - subsidy_discount) * subsidy_factor / sell_token_price` where the
+-- discounted fee in native token is lower bounded by `subsidy_min_discounted`. NULL for orders
+-- created before subsidies were recorded.
+ALTER TABLE order_quotes
+    ADD COLUMN subsidy_discount double precision,
+    ADD COLUMN subsidy_min_discounted double precision,
+    ADD COLUMN subsidy_factor double precision;
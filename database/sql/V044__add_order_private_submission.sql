@@ -0,0 +1,3 @@
+-- Whether settlements containing the order may only be submitted through private relays and never
+-- to the public mempool, which protects the order from being front-run.
+ALTER TABLE orders ADD COLUMN private_submission boolean NOT NULL DEFAULT false;
@@ -0,0 +1,15 @@
+-- Solver teams that can inspect their solutions and rewards and configure their driver through the
+-- API instead of going through the operators.
+--
+-- Teams get registered by the operators. A team authenticates with an API key of its owner.
+
+CREATE TABLE solver_teams (
+    owner bytea PRIMARY KEY,
+    -- The name of the team's solver in solver competitions.
+    name text NOT NULL UNIQUE,
+    -- The account the solver submits settlements with.
+    solver bytea NOT NULL,
+    -- The configuration the team controls. NULL keeps the operators' defaults.
+    callback_url text,
+    instance_schema_version bigint
+);
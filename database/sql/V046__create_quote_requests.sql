@@ -0,0 +1,52 @@
+-- Attributes quote requests to integrators so that the conversion from quotes to orders to fills
+-- can be tracked per integrator.
+--
+-- Integrators are identified by the app data of their quotes and orders. Quotes themselves get
+-- deleted once they expire, so every request gets logged separately.
+
+CREATE TABLE quote_requests (
+    id bigserial PRIMARY KEY,
+    time timestamptz NOT NULL,
+    app_data bytea NOT NULL,
+    user_agent text,
+    -- The stored quote the request got. NULL if quoting failed or the quote wasn't stored.
+    quote_id bigint
+);
+
+CREATE INDEX quote_requests_time ON quote_requests (time);
+
+-- The quote funnel per day and app data over the last 90 days. Refreshed periodically by the
+-- autopilot. Orders count on the day they were created, independently of whether they were
+-- created from a logged quote.
+CREATE MATERIALIZED VIEW integrator_quote_funnel AS
+WITH requests AS (
+    SELECT
+        date_trunc('day', time) AS day,
+        app_data,
+        COUNT(*) AS quote_requests,
+        COUNT(quote_id) AS quotes
+    FROM quote_requests
+    WHERE time > now() - interval '90 days'
+    GROUP BY 1, 2
+), placed AS (
+    SELECT
+        date_trunc('day', o.creation_timestamp) AS day,
+        o.app_data,
+        COUNT(*) AS orders,
+        COUNT(*) FILTER (WHERE EXISTS (SELECT 1 FROM trades t WHERE t.order_uid = o.uid)) AS filled_orders
+    FROM orders o
+    WHERE o.creation_timestamp > now() - interval '90 days'
+    GROUP BY 1, 2
+)
+SELECT
+    day,
+    app_data,
+    COALESCE(r.quote_requests, 0) AS quote_requests,
+    COALESCE(r.quotes, 0) AS quotes,
+    COALESCE(p.orders, 0) AS orders,
+    COALESCE(p.filled_orders, 0) AS filled_orders
+FROM requests r
+FULL OUTER JOIN placed p USING (day, app_data);
+
+-- Allows refreshing the view concurrently so that reads don't block during the refresh.
+CREATE UNIQUE INDEX integrator_quote_funnel_day_app_data ON integrator_quote_funnel (day, app_data);
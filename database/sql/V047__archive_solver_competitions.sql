@@ -0,0 +1,12 @@
+-- Solver competitions older than a configurable age get moved to object storage to keep the
+-- database from growing without bound. Archived competitions keep their row so that they can
+-- still be found by id and transaction hash but their json is NULL.
+--
+-- Competitions that existed before this migration count as created when it ran.
+
+ALTER TABLE solver_competitions
+ADD COLUMN created timestamptz NOT NULL DEFAULT now();
+
+-- Only competitions that still need to be archived are indexed.
+CREATE INDEX solver_competitions_unarchived_created ON solver_competitions (created)
+WHERE json IS NOT NULL;
@@ -0,0 +1,2 @@
+-- Lets the api estimate when the next auction gets created.
+ALTER TABLE auctions ADD COLUMN created timestamptz NOT NULL DEFAULT now();
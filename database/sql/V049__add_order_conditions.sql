@@ -0,0 +1,4 @@
+-- The on-chain condition that has to hold for the order to be included in an auction, for example
+-- a price oracle threshold of a stop-loss order. Stored as the JSON of the API. NULL for orders
+-- without a condition.
+ALTER TABLE orders ADD COLUMN condition jsonb;
@@ -0,0 +1,64 @@
+-- Totals of the fees, protocol fees and surplus of the trades every solver settled per token.
+--
+-- Sums of numeric(78,0) amounts can exceed 78 digits and surplus can be negative, so the totals are
+-- unconstrained numerics which neither overflow nor lose precision. The view is refreshed
+-- periodically by the same maintenance job that reconciles indexed trades with the chain.
+--
+-- Fees and protocol fees are denominated in the sell token of the order. Surplus is the executed
+-- amount minus the limit amount scaled to the fill, denominated in the buy token of sell orders and
+-- in the sell token of buy orders.
+CREATE MATERIALIZED VIEW solver_accounting AS
+WITH settled_trades AS (
+    SELECT
+        s.solver,
+        t.block_number,
+        t.log_index,
+        t.sell_amount - t.fee_amount AS executed_sell_amount,
+        t.buy_amount AS executed_buy_amount,
+        t.fee_amount,
+        o.kind,
+        o.sell_token,
+        o.buy_token,
+        o.sell_amount,
+        o.buy_amount
+    FROM trades t
+    JOIN LATERAL (
+        SELECT solver FROM settlements s
+        WHERE s.block_number = t.block_number
+        AND   s.log_index > t.log_index
+        ORDER BY s.log_index ASC
+        LIMIT 1
+    ) AS s ON true
+    JOIN orders o ON o.uid = t.order_uid
+),
+contributions AS (
+    SELECT solver, sell_token AS token, 1 AS trades, fee_amount::numeric AS fees,
+        0::numeric AS protocol_fees, 0::numeric AS surplus
+    FROM settled_trades
+UNION ALL
+    SELECT st.solver, st.sell_token, 0, 0, p.amount, 0
+    FROM settled_trades st
+    JOIN protocol_fee_accruals p ON p.block_number = st.block_number AND p.log_index = st.log_index
+UNION ALL
+    SELECT solver, buy_token, 0, 0, 0,
+        executed_buy_amount - TRUNC(buy_amount * executed_sell_amount / sell_amount)
+    FROM settled_trades
+    WHERE kind = 'sell' AND sell_amount > 0
+UNION ALL
+    SELECT solver, sell_token, 0, 0, 0,
+        TRUNC(sell_amount * executed_buy_amount / buy_amount) - executed_sell_amount
+    FROM settled_trades
+    WHERE kind = 'buy' AND buy_amount > 0
+)
+SELECT
+    solver,
+    token,
+    SUM(trades)::bigint AS trades,
+    SUM(fees) AS fees,
+    SUM(protocol_fees) AS protocol_fees,
+    SUM(surplus) AS surplus
+FROM contributions
+GROUP BY solver, token;
+
+-- Allows refreshing the view concurrently.
+CREATE UNIQUE INDEX solver_accounting_solver_token ON solver_accounting (solver, token);
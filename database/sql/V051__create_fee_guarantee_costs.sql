@@ -0,0 +1,9 @@
+-- What honoring the fee guarantee of expired quotes cost, denominated in the network's native token.
+-- Quotes get deleted after they expired so the quote id is not a foreign key.
+CREATE TABLE fee_guarantee_costs (
+    quote_id bigint PRIMARY KEY,
+    cost double precision NOT NULL,
+    time timestamptz NOT NULL
+);
+
+CREATE INDEX fee_guarantee_costs_time ON fee_guarantee_costs USING BTREE (time);
@@ -0,0 +1,6 @@
+-- Allows searching solver competitions by the tokens of their auction. The prices of an auction
+-- include every token of its orders.
+
+CREATE INDEX solver_competitions_auction_tokens ON solver_competitions
+USING gin ((json->'auction'->'prices'))
+WHERE json IS NOT NULL;
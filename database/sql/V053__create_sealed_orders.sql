@@ -0,0 +1,13 @@
+-- Orders that were submitted encrypted and get revealed when the autopilot cuts an auction. A sealed
+-- order is pending until it is either revealed, in which case the uid of the placed order is set, or
+-- rejected, in which case the reason is set.
+CREATE TABLE sealed_orders (
+    id bytea PRIMARY KEY,
+    sealed bytea NOT NULL,
+    creation_timestamp timestamptz NOT NULL,
+    order_uid bytea,
+    rejection_reason text
+);
+
+CREATE INDEX sealed_orders_pending ON sealed_orders USING BTREE (creation_timestamp)
+WHERE order_uid IS NULL AND rejection_reason IS NULL;
@@ -0,0 +1,17 @@
+-- The latest diagnosis of why open orders that have been waiting to be matched for a while don't
+-- get matched. Orders that aren't stuck anymore have no diagnosis.
+CREATE TYPE StuckOrderReason AS ENUM (
+    'token_denied',
+    'missing_allowance',
+    'insufficient_balance',
+    'no_liquidity_path',
+    'too_small',
+    'price_out_of_range',
+    'unknown'
+);
+
+CREATE TABLE order_diagnoses (
+    order_uid bytea PRIMARY KEY,
+    reason StuckOrderReason NOT NULL,
+    diagnosed_at timestamptz NOT NULL
+);
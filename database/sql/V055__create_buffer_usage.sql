@@ -0,0 +1,12 @@
+-- The amounts executed settlements took from the settlement contract's internal buffers so that
+-- daily buffer usage limits hold across solver restarts and instances.
+CREATE TABLE buffer_usage (
+    tx_hash bytea NOT NULL,
+    token bytea NOT NULL,
+    amount numeric(78,0) NOT NULL,
+    time timestamptz NOT NULL,
+
+    PRIMARY KEY (tx_hash, token)
+);
+
+CREATE INDEX buffer_usage_time ON buffer_usage USING BTREE (time);
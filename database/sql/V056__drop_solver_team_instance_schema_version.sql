@@ -0,0 +1,3 @@
+-- Drivers only speak a single version of the auction instance format so there is nothing for
+-- solver teams to choose.
+ALTER TABLE solver_teams DROP COLUMN instance_schema_version;
@@ -0,0 +1,2 @@
+-- Whether an order is a liquidity order follows from its class.
+ALTER TABLE orders DROP COLUMN is_liquidity_order;
@@ -0,0 +1,4 @@
+-- Rewards add up exactly when they are summed per accounting period.
+ALTER TABLE settlement_rewards
+    ALTER COLUMN quality_score TYPE numeric USING quality_score::numeric,
+    ALTER COLUMN reward TYPE numeric USING reward::numeric;
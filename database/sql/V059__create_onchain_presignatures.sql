@@ -0,0 +1,7 @@
+-- Presign orders that the autopilot found pre-signed in the settlement contract before their
+-- `PreSignature` events got indexed. The block is the one the auction was cut at, so events of
+-- later blocks take precedence.
+CREATE TABLE onchain_presignatures (
+    order_uid bytea PRIMARY KEY,
+    block_number bigint NOT NULL
+);